@@ -0,0 +1,323 @@
+//! Amazon Bedrock Guardrails for any model.
+//!
+//! [`BedrockGuardrail`] checks text with the `ApplyGuardrail` API, so the content
+//! filters, denied topics, word filters and PII policies of a Bedrock guardrail apply
+//! to every model the agent uses, not only to models hosted on Bedrock. Attach it with
+//! `ConfigurableAgentBuilder::with_guardrail`.
+//!
+//! When the guardrail intervenes, policies that block produce
+//! [`GuardrailVerdict::Block`] with the guardrail's configured blocked message;
+//! policies that only anonymize (PII masking) produce [`GuardrailVerdict::Mask`] with
+//! the masked text.
+//!
+//! The IAM policy needs `bedrock:ApplyGuardrail` on the guardrail.
+
+use agents_core::guardrail::{Guardrail, GuardrailSource, GuardrailVerdict};
+use anyhow::Context;
+use async_trait::async_trait;
+use aws_sdk_bedrockruntime::operation::apply_guardrail::ApplyGuardrailOutput;
+use aws_sdk_bedrockruntime::types::{
+    GuardrailAction, GuardrailAssessment, GuardrailContentBlock, GuardrailContentSource,
+    GuardrailTextBlock,
+};
+use aws_sdk_bedrockruntime::Client;
+
+/// Answer used when the guardrail blocks without a configured message.
+const DEFAULT_BLOCKED_MESSAGE: &str = "Sorry, I can't help with that request.";
+
+/// Guardrail backed by the Bedrock `ApplyGuardrail` API.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use agents_aws::BedrockGuardrail;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let guardrail = BedrockGuardrail::builder()
+///         .guardrail_id("gr-abc123")
+///         .version("2")
+///         .build()
+///         .await?;
+///     Ok(())
+/// }
+/// ```
+pub struct BedrockGuardrail {
+    client: Client,
+    guardrail_id: String,
+    version: String,
+    check_input: bool,
+    check_output: bool,
+}
+
+impl BedrockGuardrail {
+    /// Create a guardrail checking input and output with default AWS configuration.
+    pub async fn new(
+        guardrail_id: impl Into<String>,
+        version: impl Into<String>,
+    ) -> anyhow::Result<Self> {
+        Self::builder()
+            .guardrail_id(guardrail_id)
+            .version(version)
+            .build()
+            .await
+    }
+
+    /// Create a builder for configuring the Bedrock guardrail.
+    pub fn builder() -> BedrockGuardrailBuilder {
+        BedrockGuardrailBuilder::default()
+    }
+}
+
+/// Policies of an assessment that took an action, with that action
+/// (`BLOCKED`, `ANONYMIZED`).
+fn findings(assessment: &GuardrailAssessment) -> Vec<(String, String)> {
+    let mut findings = Vec::new();
+    let mut push = |reason: String, action: &str| {
+        if action != "NONE" {
+            findings.push((reason, action.to_string()));
+        }
+    };
+    if let Some(policy) = assessment.topic_policy() {
+        for topic in policy.topics() {
+            push(format!("topic:{}", topic.name()), topic.action().as_str());
+        }
+    }
+    if let Some(policy) = assessment.content_policy() {
+        for filter in policy.filters() {
+            push(
+                format!("content:{}", filter.r#type().as_str()),
+                filter.action().as_str(),
+            );
+        }
+    }
+    if let Some(policy) = assessment.word_policy() {
+        for word in policy.custom_words() {
+            push(format!("word:{}", word.r#match()), word.action().as_str());
+        }
+        for word in policy.managed_word_lists() {
+            push(
+                format!("word:{}", word.r#type().as_str()),
+                word.action().as_str(),
+            );
+        }
+    }
+    if let Some(policy) = assessment.sensitive_information_policy() {
+        for entity in policy.pii_entities() {
+            push(
+                format!("pii:{}", entity.r#type().as_str()),
+                entity.action().as_str(),
+            );
+        }
+        for regex in policy.regexes() {
+            push(
+                format!("regex:{}", regex.name().unwrap_or_default()),
+                regex.action().as_str(),
+            );
+        }
+    }
+    if let Some(policy) = assessment.contextual_grounding_policy() {
+        for filter in policy.filters() {
+            push(
+                format!("grounding:{}", filter.r#type().as_str()),
+                filter.action().as_str(),
+            );
+        }
+    }
+    findings
+}
+
+fn verdict(output: &ApplyGuardrailOutput) -> GuardrailVerdict {
+    if *output.action() != GuardrailAction::GuardrailIntervened {
+        return GuardrailVerdict::Allow;
+    }
+    let findings: Vec<(String, String)> = output.assessments().iter().flat_map(findings).collect();
+    let text: String = output.outputs().iter().filter_map(|o| o.text()).collect();
+    let blocked = findings.is_empty() || findings.iter().any(|(_, action)| action == "BLOCKED");
+    let reasons = findings.into_iter().map(|(reason, _)| reason).collect();
+    if blocked {
+        let message = if text.is_empty() {
+            DEFAULT_BLOCKED_MESSAGE.to_string()
+        } else {
+            text
+        };
+        GuardrailVerdict::Block { message, reasons }
+    } else {
+        GuardrailVerdict::Mask { text, reasons }
+    }
+}
+
+#[async_trait]
+impl Guardrail for BedrockGuardrail {
+    fn id(&self) -> &str {
+        &self.guardrail_id
+    }
+
+    async fn check(&self, text: &str, source: GuardrailSource) -> anyhow::Result<GuardrailVerdict> {
+        let source = match source {
+            GuardrailSource::Input if self.check_input => GuardrailContentSource::Input,
+            GuardrailSource::Output if self.check_output => GuardrailContentSource::Output,
+            _ => return Ok(GuardrailVerdict::Allow),
+        };
+        let content = GuardrailTextBlock::builder()
+            .text(text)
+            .build()
+            .context("Invalid guardrail text block")?;
+        let output = self
+            .client
+            .apply_guardrail()
+            .guardrail_identifier(&self.guardrail_id)
+            .guardrail_version(&self.version)
+            .source(source)
+            .content(GuardrailContentBlock::Text(content))
+            .send()
+            .await
+            .with_context(|| format!("Failed to apply Bedrock guardrail {}", self.guardrail_id))?;
+        Ok(verdict(&output))
+    }
+}
+
+/// Builder for configuring a Bedrock guardrail.
+pub struct BedrockGuardrailBuilder {
+    guardrail_id: Option<String>,
+    version: Option<String>,
+    check_input: bool,
+    check_output: bool,
+    client: Option<Client>,
+}
+
+impl Default for BedrockGuardrailBuilder {
+    fn default() -> Self {
+        Self {
+            guardrail_id: None,
+            version: None,
+            check_input: true,
+            check_output: true,
+            client: None,
+        }
+    }
+}
+
+impl BedrockGuardrailBuilder {
+    /// Set the guardrail id or ARN (required).
+    pub fn guardrail_id(mut self, id: impl Into<String>) -> Self {
+        self.guardrail_id = Some(id.into());
+        self
+    }
+
+    /// Set the guardrail version (default: "DRAFT").
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// Check user messages (default: true).
+    pub fn check_input(mut self, enabled: bool) -> Self {
+        self.check_input = enabled;
+        self
+    }
+
+    /// Check model responses (default: true).
+    pub fn check_output(mut self, enabled: bool) -> Self {
+        self.check_output = enabled;
+        self
+    }
+
+    /// Use a custom Bedrock Runtime client.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Build the Bedrock guardrail.
+    pub async fn build(self) -> anyhow::Result<BedrockGuardrail> {
+        let guardrail_id = self
+            .guardrail_id
+            .context("Guardrail id is required for BedrockGuardrail")?;
+        let client = match self.client {
+            Some(client) => client,
+            None => {
+                let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+                Client::new(&config)
+            }
+        };
+
+        Ok(BedrockGuardrail {
+            client,
+            guardrail_id,
+            version: self.version.unwrap_or_else(|| "DRAFT".to_string()),
+            check_input: self.check_input,
+            check_output: self.check_output,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_bedrockruntime::types::{
+        GuardrailOutputContent, GuardrailPiiEntityFilter, GuardrailPiiEntityType,
+        GuardrailSensitiveInformationPolicyAction, GuardrailSensitiveInformationPolicyAssessment,
+        GuardrailTopic, GuardrailTopicPolicyAction, GuardrailTopicPolicyAssessment,
+        GuardrailTopicType,
+    };
+
+    fn output(text: &str, assessment: GuardrailAssessment) -> ApplyGuardrailOutput {
+        ApplyGuardrailOutput::builder()
+            .action(GuardrailAction::GuardrailIntervened)
+            .outputs(GuardrailOutputContent::builder().text(text).build())
+            .assessments(assessment)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn anonymized_pii_masks_and_denied_topics_block() {
+        let pii = GuardrailAssessment::builder()
+            .sensitive_information_policy(
+                GuardrailSensitiveInformationPolicyAssessment::builder()
+                    .pii_entities(
+                        GuardrailPiiEntityFilter::builder()
+                            .r#match("ana@example.com")
+                            .r#type(GuardrailPiiEntityType::Email)
+                            .action(GuardrailSensitiveInformationPolicyAction::Anonymized)
+                            .build()
+                            .unwrap(),
+                    )
+                    .set_regexes(Some(Vec::new()))
+                    .build()
+                    .unwrap(),
+            )
+            .build();
+        assert_eq!(
+            verdict(&output("Write to {EMAIL}", pii)),
+            GuardrailVerdict::Mask {
+                text: "Write to {EMAIL}".into(),
+                reasons: vec!["pii:EMAIL".into()],
+            }
+        );
+
+        let topic = GuardrailAssessment::builder()
+            .topic_policy(
+                GuardrailTopicPolicyAssessment::builder()
+                    .topics(
+                        GuardrailTopic::builder()
+                            .name("Investment advice")
+                            .r#type(GuardrailTopicType::Deny)
+                            .action(GuardrailTopicPolicyAction::Blocked)
+                            .build()
+                            .unwrap(),
+                    )
+                    .build()
+                    .unwrap(),
+            )
+            .build();
+        assert_eq!(
+            verdict(&output("I can't give investment advice.", topic)),
+            GuardrailVerdict::Block {
+                message: "I can't give investment advice.".into(),
+                reasons: vec!["topic:Investment advice".into()],
+            }
+        );
+    }
+}
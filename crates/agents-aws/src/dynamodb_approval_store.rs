@@ -0,0 +1,420 @@
+//! DynamoDB-backed store of pending HITL approvals.
+//!
+//! Lets an agent deployed on Lambda or containers record the tool calls waiting for
+//! a human, while a separate admin service lists and decides them.
+//!
+//! ## Table Schema
+//!
+//! - **Primary Key**: `thread_id` (String) + `call_id` (String, sort key)
+//! - **Attributes**:
+//!   - `approval` (String) - The [`PendingApproval`] as JSON
+//!   - `status` (String) - `pending` or `decided`
+//!   - `approver` (String) - Assigned reviewer; only set while the approval is pending
+//!   - `created_at` (String) - RFC 3339 time of the interrupt
+//!   - `expires_at` (Number) - Unix epoch seconds; enable TTL on this attribute
+//! - **Global Secondary Index** `approver-index`: `approver` + `created_at` (sort key)
+//!
+//! ```bash
+//! aws dynamodb create-table \
+//!   --table-name agent-approvals \
+//!   --attribute-definitions AttributeName=thread_id,AttributeType=S \
+//!     AttributeName=call_id,AttributeType=S \
+//!     AttributeName=approver,AttributeType=S \
+//!     AttributeName=created_at,AttributeType=S \
+//!   --key-schema AttributeName=thread_id,KeyType=HASH AttributeName=call_id,KeyType=RANGE \
+//!   --global-secondary-indexes '[{"IndexName":"approver-index",
+//!     "KeySchema":[{"AttributeName":"approver","KeyType":"HASH"},
+//!                  {"AttributeName":"created_at","KeyType":"RANGE"}],
+//!     "Projection":{"ProjectionType":"ALL"}}]' \
+//!   --billing-mode PAY_PER_REQUEST
+//!
+//! aws dynamodb update-time-to-live \
+//!   --table-name agent-approvals \
+//!   --time-to-live-specification "Enabled=true, AttributeName=expires_at"
+//! ```
+//!
+//! Decided approvals drop out of the index, so it only holds the work of each
+//! reviewer. DynamoDB deletes expired items within a few days of expiry; until then
+//! they are filtered out of listings and can no longer be decided.
+
+use agents_core::approval_store::{
+    ApprovalDecision, ApprovalError, ApprovalStore, PendingApproval,
+};
+use agents_core::persistence::ThreadId;
+use anyhow::Context;
+use async_trait::async_trait;
+use aws_sdk_dynamodb::error::SdkError;
+use aws_sdk_dynamodb::{types::AttributeValue, Client};
+use chrono::SecondsFormat;
+use std::collections::HashMap;
+
+/// DynamoDB implementation of [`ApprovalStore`].
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use agents_aws::DynamoDbApprovalStore;
+/// use agents_core::approval_store::{ApprovalDecision, ApprovalStore};
+/// use agents_core::hitl::HitlAction;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Admin service: approve everything waiting for the finance team
+///     let store = DynamoDbApprovalStore::new("agent-approvals").await?;
+///     for approval in store.list_for_approver("finance").await? {
+///         let decision = ApprovalDecision::new(HitlAction::Accept, "ana@example.com");
+///         store.decide(&approval.thread_id, approval.call_id(), decision).await?;
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct DynamoDbApprovalStore {
+    client: Client,
+    table_name: String,
+    approver_index: String,
+}
+
+impl DynamoDbApprovalStore {
+    /// Create a new DynamoDB approval store with default AWS configuration.
+    pub async fn new(table_name: impl Into<String>) -> anyhow::Result<Self> {
+        Self::builder().table_name(table_name).build().await
+    }
+
+    /// Create a builder for configuring the DynamoDB approval store.
+    pub fn builder() -> DynamoDbApprovalStoreBuilder {
+        DynamoDbApprovalStoreBuilder::default()
+    }
+
+    fn key(thread_id: &ThreadId, call_id: &str) -> HashMap<String, AttributeValue> {
+        HashMap::from([
+            (
+                "thread_id".to_string(),
+                AttributeValue::S(thread_id.clone()),
+            ),
+            (
+                "call_id".to_string(),
+                AttributeValue::S(call_id.to_string()),
+            ),
+        ])
+    }
+
+    fn now() -> AttributeValue {
+        AttributeValue::N(chrono::Utc::now().timestamp().to_string())
+    }
+
+    /// The approval if it can still be decided, or why it can't.
+    fn decidable(
+        approval: Option<PendingApproval>,
+        thread_id: &ThreadId,
+        call_id: &str,
+    ) -> Result<PendingApproval, ApprovalError> {
+        let thread_id = thread_id.clone();
+        let call_id = call_id.to_string();
+        match approval {
+            None => Err(ApprovalError::NotFound { thread_id, call_id }),
+            Some(a) if a.decision.is_some() => {
+                Err(ApprovalError::AlreadyDecided { thread_id, call_id })
+            }
+            Some(a) if a.is_expired() => Err(ApprovalError::Expired { thread_id, call_id }),
+            Some(a) => Ok(a),
+        }
+    }
+}
+
+fn to_item(approval: &PendingApproval) -> anyhow::Result<HashMap<String, AttributeValue>> {
+    let json = serde_json::to_string(approval).context("Failed to serialize approval")?;
+    let mut item = DynamoDbApprovalStore::key(&approval.thread_id, approval.call_id());
+    item.insert("approval".to_string(), AttributeValue::S(json));
+    item.insert(
+        "created_at".to_string(),
+        AttributeValue::S(
+            approval
+                .interrupt
+                .created_at
+                .to_rfc3339_opts(SecondsFormat::Millis, true),
+        ),
+    );
+    item.insert(
+        "expires_at".to_string(),
+        AttributeValue::N(approval.expires_at.timestamp().to_string()),
+    );
+    let status = match (&approval.decision, &approval.approver) {
+        (Some(_), _) => "decided",
+        (None, approver) => {
+            if let Some(approver) = approver {
+                item.insert("approver".to_string(), AttributeValue::S(approver.clone()));
+            }
+            "pending"
+        }
+    };
+    item.insert("status".to_string(), AttributeValue::S(status.to_string()));
+    Ok(item)
+}
+
+fn from_item(item: &HashMap<String, AttributeValue>) -> anyhow::Result<PendingApproval> {
+    let json = item
+        .get("approval")
+        .and_then(|v| v.as_s().ok())
+        .context("Approval item has no approval attribute")?;
+    serde_json::from_str(json).context("Failed to deserialize approval")
+}
+
+/// Whether a failed request was a conditional check failure.
+fn is_conditional_failure<E, R>(error: &SdkError<E, R>) -> bool
+where
+    E: aws_sdk_dynamodb::error::ProvideErrorMetadata,
+{
+    error
+        .as_service_error()
+        .and_then(|e| e.code())
+        .is_some_and(|code| code == "ConditionalCheckFailedException")
+}
+
+#[async_trait]
+impl ApprovalStore for DynamoDbApprovalStore {
+    async fn put(&self, approval: &PendingApproval) -> anyhow::Result<()> {
+        self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(to_item(approval)?))
+            .send()
+            .await
+            .context("Failed to save approval to DynamoDB")?;
+        tracing::debug!(
+            thread_id = %approval.thread_id,
+            call_id = %approval.call_id(),
+            table = %self.table_name,
+            "Saved approval to DynamoDB"
+        );
+        Ok(())
+    }
+
+    async fn get(
+        &self,
+        thread_id: &ThreadId,
+        call_id: &str,
+    ) -> anyhow::Result<Option<PendingApproval>> {
+        let output = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .set_key(Some(Self::key(thread_id, call_id)))
+            .consistent_read(true)
+            .send()
+            .await
+            .context("Failed to load approval from DynamoDB")?;
+        output.item().map(from_item).transpose()
+    }
+
+    async fn list_for_approver(&self, approver: &str) -> anyhow::Result<Vec<PendingApproval>> {
+        let mut pages = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .index_name(&self.approver_index)
+            .key_condition_expression("#approver = :approver")
+            .filter_expression("#expires_at > :now")
+            .expression_attribute_names("#approver", "approver")
+            .expression_attribute_names("#expires_at", "expires_at")
+            .expression_attribute_values(":approver", AttributeValue::S(approver.to_string()))
+            .expression_attribute_values(":now", Self::now())
+            .into_paginator()
+            .items()
+            .send();
+
+        let mut approvals = Vec::new();
+        while let Some(item) = pages.next().await {
+            let item = item.context("Failed to query approvals from DynamoDB")?;
+            let approval = from_item(&item)?;
+            // The index is eventually consistent; skip decisions not reflected yet
+            if approval.is_pending() {
+                approvals.push(approval);
+            }
+        }
+        Ok(approvals)
+    }
+
+    async fn decide(
+        &self,
+        thread_id: &ThreadId,
+        call_id: &str,
+        decision: ApprovalDecision,
+    ) -> anyhow::Result<PendingApproval> {
+        let current = self.get(thread_id, call_id).await?;
+        let mut approval = Self::decidable(current, thread_id, call_id)?;
+        approval.decision = Some(decision);
+        let json = serde_json::to_string(&approval).context("Failed to serialize approval")?;
+
+        let result = self
+            .client
+            .update_item()
+            .table_name(&self.table_name)
+            .set_key(Some(Self::key(thread_id, call_id)))
+            .update_expression("SET #approval = :approval, #status = :decided REMOVE #approver")
+            .condition_expression("#status = :pending AND #expires_at > :now")
+            .expression_attribute_names("#approval", "approval")
+            .expression_attribute_names("#status", "status")
+            .expression_attribute_names("#approver", "approver")
+            .expression_attribute_names("#expires_at", "expires_at")
+            .expression_attribute_values(":approval", AttributeValue::S(json))
+            .expression_attribute_values(":decided", AttributeValue::S("decided".to_string()))
+            .expression_attribute_values(":pending", AttributeValue::S("pending".to_string()))
+            .expression_attribute_values(":now", Self::now())
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(approval),
+            Err(e) if is_conditional_failure(&e) => {
+                // Decided by someone else or expired since it was read
+                let current = self.get(thread_id, call_id).await?;
+                let error = match Self::decidable(current, thread_id, call_id) {
+                    Err(error) => error,
+                    Ok(_) => ApprovalError::AlreadyDecided {
+                        thread_id: thread_id.clone(),
+                        call_id: call_id.to_string(),
+                    },
+                };
+                Err(error.into())
+            }
+            Err(e) => Err(e).context("Failed to record decision in DynamoDB"),
+        }
+    }
+
+    async fn remove(&self, thread_id: &ThreadId, call_id: &str) -> anyhow::Result<()> {
+        self.client
+            .delete_item()
+            .table_name(&self.table_name)
+            .set_key(Some(Self::key(thread_id, call_id)))
+            .send()
+            .await
+            .context("Failed to delete approval from DynamoDB")?;
+        Ok(())
+    }
+}
+
+/// Builder for configuring a DynamoDB approval store.
+#[derive(Default)]
+pub struct DynamoDbApprovalStoreBuilder {
+    table_name: Option<String>,
+    approver_index: Option<String>,
+    client: Option<Client>,
+}
+
+impl DynamoDbApprovalStoreBuilder {
+    /// Set the DynamoDB table name.
+    pub fn table_name(mut self, table_name: impl Into<String>) -> Self {
+        self.table_name = Some(table_name.into());
+        self
+    }
+
+    /// Set the name of the index by approver (default: "approver-index").
+    pub fn approver_index(mut self, index_name: impl Into<String>) -> Self {
+        self.approver_index = Some(index_name.into());
+        self
+    }
+
+    /// Use a custom DynamoDB client.
+    ///
+    /// This is useful for testing with LocalStack or using custom endpoints.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Build the DynamoDB approval store.
+    pub async fn build(self) -> anyhow::Result<DynamoDbApprovalStore> {
+        let table_name = self
+            .table_name
+            .ok_or_else(|| anyhow::anyhow!("Table name is required"))?;
+
+        let client = match self.client {
+            Some(client) => client,
+            None => {
+                let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+                Client::new(&config)
+            }
+        };
+
+        Ok(DynamoDbApprovalStore {
+            client,
+            table_name,
+            approver_index: self
+                .approver_index
+                .unwrap_or_else(|| "approver-index".to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::hitl::{HitlAction, HitlInterrupt};
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn approval() -> PendingApproval {
+        let interrupt = HitlInterrupt::new("issue_refund", json!({"amount": 250}), "call-1", None);
+        PendingApproval::new("thread-1", interrupt, Duration::from_secs(3600))
+            .with_approver("finance")
+    }
+
+    #[test]
+    fn only_pending_approvals_are_indexed_by_approver() {
+        let pending = approval();
+        let item = to_item(&pending).unwrap();
+        assert_eq!(item["approver"].as_s().unwrap(), "finance");
+        assert_eq!(item["status"].as_s().unwrap(), "pending");
+        assert_eq!(
+            item["expires_at"].as_n().unwrap(),
+            &pending.expires_at.timestamp().to_string()
+        );
+        assert_eq!(from_item(&item).unwrap(), pending);
+
+        let mut decided = pending;
+        decided.decision = Some(ApprovalDecision::new(HitlAction::Accept, "ana"));
+        let item = to_item(&decided).unwrap();
+        assert!(!item.contains_key("approver"));
+        assert_eq!(item["status"].as_s().unwrap(), "decided");
+    }
+
+    #[tokio::test]
+    #[ignore] // Requires DynamoDB or LocalStack
+    async fn test_dynamodb_decisions_are_recorded_once() {
+        let store = DynamoDbApprovalStore::new("agent-approvals-test")
+            .await
+            .expect("Failed to create DynamoDB client");
+        let pending = approval();
+        store.put(&pending).await.unwrap();
+        assert!(store
+            .list_for_approver("finance")
+            .await
+            .unwrap()
+            .iter()
+            .any(|a| a.call_id() == "call-1"));
+
+        let thread = pending.thread_id.clone();
+        store
+            .decide(
+                &thread,
+                "call-1",
+                ApprovalDecision::new(HitlAction::Accept, "ana"),
+            )
+            .await
+            .unwrap();
+        let again = store
+            .decide(
+                &thread,
+                "call-1",
+                ApprovalDecision::new(HitlAction::Accept, "bo"),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            again.downcast_ref(),
+            Some(ApprovalError::AlreadyDecided { .. })
+        ));
+        store.remove(&thread, "call-1").await.unwrap();
+    }
+}
@@ -31,6 +31,7 @@
 //! Or use Terraform (see `deploy/modules/dynamodb/`).
 
 use agents_core::persistence::{Checkpointer, ThreadId};
+use agents_core::profiling::{self, ProfilePhase};
 use agents_core::state::AgentStateSnapshot;
 use anyhow::Context;
 use async_trait::async_trait;
@@ -106,7 +107,8 @@ impl Checkpointer for DynamoDbCheckpointer {
         state: &AgentStateSnapshot,
     ) -> anyhow::Result<()> {
         let state_json =
-            serde_json::to_string(state).context("Failed to serialize agent state to JSON")?;
+            profiling::time(ProfilePhase::Serialization, || serde_json::to_string(state))
+                .context("Failed to serialize agent state to JSON")?;
 
         let mut item = HashMap::new();
         item.insert(
@@ -164,7 +166,10 @@ impl Checkpointer for DynamoDbCheckpointer {
                     .and_then(|v| v.as_s().ok())
                     .ok_or_else(|| anyhow::anyhow!("State attribute not found or invalid"))?;
 
-                let state: AgentStateSnapshot = serde_json::from_str(state_value)
+                let state: AgentStateSnapshot =
+                    profiling::time(ProfilePhase::Serialization, || {
+                        serde_json::from_str(state_value)
+                    })
                     .context("Failed to deserialize agent state from JSON")?;
 
                 tracing::debug!(
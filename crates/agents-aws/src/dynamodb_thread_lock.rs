@@ -0,0 +1,259 @@
+//! DynamoDB-backed per-thread leases using conditional writes.
+//!
+//! ## Table Schema
+//!
+//! The lock expects a dedicated table (or one shared with nothing but locks):
+//!
+//! - **Primary Key**: `thread_id` (String)
+//! - **Attributes**:
+//!   - `owner` (String) - Replica holding the lease
+//!   - `token` (String) - Unique per acquisition
+//!   - `expires_at` (Number) - Unix epoch milliseconds when the lease expires
+//!
+//! ```bash
+//! aws dynamodb create-table \
+//!   --table-name agent-thread-locks \
+//!   --attribute-definitions AttributeName=thread_id,AttributeType=S \
+//!   --key-schema AttributeName=thread_id,KeyType=HASH \
+//!   --billing-mode PAY_PER_REQUEST
+//! ```
+//!
+//! Expired leases are overwritten by the next acquirer, so no cleanup is needed.
+//! Expiry is compared against each replica's clock; keep leases well above the
+//! expected clock skew.
+
+use agents_core::persistence::ThreadId;
+use agents_core::thread_lock::{LeaseRequest, ThreadLease, ThreadLock};
+use anyhow::Context;
+use async_trait::async_trait;
+use aws_sdk_dynamodb::error::SdkError;
+use aws_sdk_dynamodb::{types::AttributeValue, Client};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// DynamoDB implementation of [`ThreadLock`].
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use agents_aws::DynamoDbThreadLock;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let lock = DynamoDbThreadLock::new("agent-thread-locks").await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct DynamoDbThreadLock {
+    client: Client,
+    table_name: String,
+}
+
+impl DynamoDbThreadLock {
+    /// Create a new DynamoDB thread lock with default AWS configuration.
+    pub async fn new(table_name: impl Into<String>) -> anyhow::Result<Self> {
+        Self::builder().table_name(table_name).build().await
+    }
+
+    /// Create a builder for configuring the DynamoDB thread lock.
+    pub fn builder() -> DynamoDbThreadLockBuilder {
+        DynamoDbThreadLockBuilder::default()
+    }
+
+    fn key(thread_id: &ThreadId) -> HashMap<String, AttributeValue> {
+        HashMap::from([(
+            "thread_id".to_string(),
+            AttributeValue::S(thread_id.clone()),
+        )])
+    }
+
+    fn millis(lease: &ThreadLease) -> String {
+        lease.expires_at.timestamp_millis().to_string()
+    }
+}
+
+/// Whether a failed request was a conditional check failure (lease held or lost).
+fn is_conditional_failure<E, R>(error: &SdkError<E, R>) -> bool
+where
+    E: aws_sdk_dynamodb::error::ProvideErrorMetadata,
+{
+    error
+        .as_service_error()
+        .and_then(|e| e.code())
+        .is_some_and(|code| code == "ConditionalCheckFailedException")
+}
+
+#[async_trait]
+impl ThreadLock for DynamoDbThreadLock {
+    async fn try_acquire(&self, request: &LeaseRequest) -> anyhow::Result<Option<ThreadLease>> {
+        let lease = ThreadLease::new(request, uuid::Uuid::new_v4().to_string());
+        let mut condition = "attribute_not_exists(thread_id) OR #expires_at < :now".to_string();
+        if request.reclaim_own {
+            condition.push_str(" OR #owner = :owner");
+        }
+
+        let mut item = Self::key(&request.thread_id);
+        item.insert("owner".to_string(), AttributeValue::S(lease.owner.clone()));
+        item.insert("token".to_string(), AttributeValue::S(lease.token.clone()));
+        item.insert(
+            "expires_at".to_string(),
+            AttributeValue::N(Self::millis(&lease)),
+        );
+
+        let mut put = self
+            .client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(item))
+            .condition_expression(condition)
+            .expression_attribute_names("#expires_at", "expires_at")
+            .expression_attribute_values(
+                ":now",
+                AttributeValue::N(chrono::Utc::now().timestamp_millis().to_string()),
+            );
+        if request.reclaim_own {
+            put = put
+                .expression_attribute_names("#owner", "owner")
+                .expression_attribute_values(":owner", AttributeValue::S(request.owner.clone()));
+        }
+
+        match put.send().await {
+            Ok(_) => {
+                tracing::debug!(
+                    thread_id = %request.thread_id,
+                    owner = %request.owner,
+                    table = %self.table_name,
+                    "Acquired thread lease in DynamoDB"
+                );
+                Ok(Some(lease))
+            }
+            Err(e) if is_conditional_failure(&e) => Ok(None),
+            Err(e) => Err(e).context("Failed to acquire thread lease in DynamoDB"),
+        }
+    }
+
+    async fn renew(
+        &self,
+        lease: &ThreadLease,
+        duration: Duration,
+    ) -> anyhow::Result<Option<ThreadLease>> {
+        let renewed = ThreadLease {
+            expires_at: agents_core::thread_lock::expires_after(duration),
+            ..lease.clone()
+        };
+
+        let result = self
+            .client
+            .update_item()
+            .table_name(&self.table_name)
+            .set_key(Some(Self::key(&lease.thread_id)))
+            .update_expression("SET #expires_at = :expires_at")
+            .condition_expression("#token = :token")
+            .expression_attribute_names("#expires_at", "expires_at")
+            .expression_attribute_names("#token", "token")
+            .expression_attribute_values(":expires_at", AttributeValue::N(Self::millis(&renewed)))
+            .expression_attribute_values(":token", AttributeValue::S(lease.token.clone()))
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(Some(renewed)),
+            Err(e) if is_conditional_failure(&e) => Ok(None),
+            Err(e) => Err(e).context("Failed to renew thread lease in DynamoDB"),
+        }
+    }
+
+    async fn release(&self, lease: &ThreadLease) -> anyhow::Result<()> {
+        let result = self
+            .client
+            .delete_item()
+            .table_name(&self.table_name)
+            .set_key(Some(Self::key(&lease.thread_id)))
+            .condition_expression("#token = :token")
+            .expression_attribute_names("#token", "token")
+            .expression_attribute_values(":token", AttributeValue::S(lease.token.clone()))
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) if is_conditional_failure(&e) => Ok(()),
+            Err(e) => Err(e).context("Failed to release thread lease in DynamoDB"),
+        }
+    }
+}
+
+/// Builder for configuring a DynamoDB thread lock.
+#[derive(Default)]
+pub struct DynamoDbThreadLockBuilder {
+    table_name: Option<String>,
+    client: Option<Client>,
+}
+
+impl DynamoDbThreadLockBuilder {
+    /// Set the DynamoDB table name.
+    pub fn table_name(mut self, table_name: impl Into<String>) -> Self {
+        self.table_name = Some(table_name.into());
+        self
+    }
+
+    /// Use a custom DynamoDB client.
+    ///
+    /// This is useful for testing with LocalStack or using custom endpoints.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Build the DynamoDB thread lock.
+    pub async fn build(self) -> anyhow::Result<DynamoDbThreadLock> {
+        let table_name = self
+            .table_name
+            .ok_or_else(|| anyhow::anyhow!("Table name is required"))?;
+
+        let client = match self.client {
+            Some(client) => client,
+            None => {
+                let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+                Client::new(&config)
+            }
+        };
+
+        Ok(DynamoDbThreadLock { client, table_name })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(owner: &str) -> LeaseRequest {
+        LeaseRequest {
+            thread_id: "lock-test-thread".to_string(),
+            owner: owner.to_string(),
+            duration: Duration::from_secs(10),
+            reclaim_own: false,
+        }
+    }
+
+    #[tokio::test]
+    #[ignore] // Requires DynamoDB or LocalStack
+    async fn test_dynamodb_lease_is_exclusive() {
+        let lock = DynamoDbThreadLock::new("agent-thread-locks-test")
+            .await
+            .expect("Failed to create DynamoDB client");
+
+        let lease = lock.try_acquire(&request("a")).await.unwrap().unwrap();
+        assert!(lock.try_acquire(&request("b")).await.unwrap().is_none());
+
+        lock.release(&lease).await.unwrap();
+        let next = lock.try_acquire(&request("b")).await.unwrap().unwrap();
+        assert!(lock
+            .renew(&lease, Duration::from_secs(10))
+            .await
+            .unwrap()
+            .is_none());
+        lock.release(&next).await.unwrap();
+    }
+}
@@ -0,0 +1,390 @@
+//! AWS Lambda adapter for serving an agent behind API Gateway or a Function URL.
+//!
+//! Build the agent once in `main`, during the Lambda init phase, and hand it to a
+//! [`LambdaAgentHandler`]. The agent's model clients, checkpointer and other
+//! connections are then reused by every invocation the warm instance serves.
+//!
+//! Requests are `POST`s with a JSON body:
+//!
+//! ```json
+//! { "thread_id": "customer-42", "message": "Where is my order?" }
+//! ```
+//!
+//! `thread_id` is optional; without it a new thread is started. Each message runs
+//! through [`DeepAgent::handle_message_for_thread`], so the thread's state is loaded
+//! from and saved to the agent's checkpointer. The response is
+//!
+//! ```json
+//! { "thread_id": "customer-42", "response": "Your order shipped yesterday." }
+//! ```
+//!
+//! With [`LambdaAgentHandler::run_streaming`] (Function URL with the
+//! `RESPONSE_STREAM` invoke mode) the response is a `text/event-stream` of the run's
+//! events, ending with a `done` (or `error`) event carrying the same JSON.
+//!
+//! The function can also be invoked directly (Step Functions, other Lambdas) with
+//! the request JSON as the event. The response JSON is then returned as is, and a
+//! failed run fails the invocation so the caller's retry policy applies.
+//!
+//! The correlation id of a run is the `x-correlation-id` header, or the Lambda
+//! request id.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use agents_core::correlation::with_correlation_id;
+use agents_core::events::{AgentEvent, EventBroadcaster};
+use agents_core::messaging::{AgentMessage, MessageContent};
+use agents_runtime::{AgentShutdownError, DeepAgent, ThreadLockError};
+use async_trait::async_trait;
+use bytes::Bytes;
+use lambda_http::http::{header, StatusCode};
+use lambda_http::lambda_runtime::streaming::{self, Sender};
+use lambda_http::request::RequestContext;
+use lambda_http::{service_fn, Body, Error, Request, RequestExt, Response};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+/// Header carrying the caller's correlation id.
+const CORRELATION_HEADER: &str = "x-correlation-id";
+
+#[derive(Debug, Deserialize)]
+struct ChatRequest {
+    thread_id: Option<String>,
+    message: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ChatResponse {
+    thread_id: String,
+    response: serde_json::Value,
+}
+
+impl ChatResponse {
+    fn new(thread_id: String, message: AgentMessage) -> Self {
+        let response = match message.content {
+            MessageContent::Text(text) => serde_json::Value::String(text),
+            MessageContent::Json(value) => value,
+            content @ MessageContent::Image { .. } => serde_json::Value::String(content.to_text()),
+        };
+        Self {
+            thread_id,
+            response,
+        }
+    }
+}
+
+/// Serves a [`DeepAgent`] from Lambda HTTP events (API Gateway REST and HTTP APIs,
+/// Function URLs).
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// #[tokio::main]
+/// async fn main() -> Result<(), lambda_http::Error> {
+///     // Created once per instance and reused across invocations
+///     let agent = ConfigurableAgentBuilder::new("You are a support agent")
+///         .with_model(model)
+///         .with_checkpointer(Arc::new(DynamoDbCheckpointer::new("agent-threads").await?))
+///         .build()?;
+///
+///     LambdaAgentHandler::new(Arc::new(agent)).run().await
+/// }
+/// ```
+#[derive(Clone)]
+pub struct LambdaAgentHandler {
+    agent: Arc<DeepAgent>,
+    /// Event channels of streamed runs, by correlation id
+    streams: Arc<Mutex<HashMap<String, mpsc::UnboundedSender<AgentEvent>>>>,
+}
+
+impl LambdaAgentHandler {
+    /// Streamed responses only carry events when the agent has an event dispatcher
+    /// (any broadcaster configured on the builder).
+    pub fn new(agent: Arc<DeepAgent>) -> Self {
+        let streams = Arc::new(Mutex::new(HashMap::new()));
+        agent.add_broadcaster(Arc::new(StreamForwarder {
+            streams: streams.clone(),
+        }));
+        Self { agent, streams }
+    }
+
+    /// Run the Lambda runtime, answering each request with the agent's full response.
+    pub async fn run(self) -> Result<(), Error> {
+        lambda_http::run(service_fn(move |request| {
+            let handler = self.clone();
+            async move { handler.handle(request).await }
+        }))
+        .await
+    }
+
+    /// Run the Lambda runtime, streaming each run's events as server-sent events.
+    /// Requires a Function URL with the `RESPONSE_STREAM` invoke mode.
+    pub async fn run_streaming(self) -> Result<(), Error> {
+        lambda_http::run_with_streaming_response(service_fn(move |request| {
+            let handler = self.clone();
+            async move { handler.handle_streaming(request).await }
+        }))
+        .await
+    }
+
+    /// Handle one request and return the agent's full response.
+    pub async fn handle(&self, request: Request) -> Result<Response<Body>, Error> {
+        let (chat, correlation_id) = match parse_request(&request) {
+            Ok(parsed) => parsed,
+            Err(error) => return Ok(bad_request(&error).map(Body::from)),
+        };
+        let thread_id = chat.thread_id.unwrap_or_else(new_thread_id);
+        let run = self
+            .agent
+            .handle_message_for_thread(&thread_id, &chat.message);
+
+        let response = match with_correlation_id(correlation_id, run).await {
+            Ok(message) => json_response(StatusCode::OK, &ChatResponse::new(thread_id, message)),
+            Err(e) if is_direct_invocation(&request) => {
+                tracing::error!(error = %format!("{e:#}"), "Agent run failed");
+                return Err(error_status(&e).1.into());
+            }
+            Err(e) => error_response(&e),
+        };
+        Ok(response.map(Body::from))
+    }
+
+    /// Handle one request, streaming the run's events and then its response.
+    pub async fn handle_streaming(
+        &self,
+        request: Request,
+    ) -> Result<Response<streaming::Body>, Error> {
+        let (chat, correlation_id) = match parse_request(&request) {
+            Ok(parsed) => parsed,
+            Err(error) => return Ok(bad_request(&error).map(streaming::Body::from)),
+        };
+        let thread_id = chat.thread_id.unwrap_or_else(new_thread_id);
+
+        let (events_tx, events_rx) = mpsc::unbounded_channel();
+        self.streams_guard()
+            .insert(correlation_id.clone(), events_tx);
+        let (sender, body) = streaming::Body::channel();
+
+        let handler = self.clone();
+        tokio::spawn(async move {
+            handler
+                .stream_run(sender, events_rx, thread_id, chat.message, correlation_id)
+                .await;
+        });
+
+        Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+            .body(body)?)
+    }
+
+    async fn stream_run(
+        &self,
+        mut sender: Sender,
+        mut events: mpsc::UnboundedReceiver<AgentEvent>,
+        thread_id: String,
+        message: String,
+        correlation_id: String,
+    ) {
+        let run = with_correlation_id(
+            correlation_id.clone(),
+            self.agent.handle_message_for_thread(&thread_id, &message),
+        );
+        tokio::pin!(run);
+
+        // A client that disconnected stops receiving events; the run still completes
+        let mut connected = true;
+        let result = loop {
+            tokio::select! {
+                biased;
+                Some(event) = events.recv() => {
+                    connected = connected && send_event(&mut sender, &event).await;
+                }
+                result = &mut run => break result,
+            }
+        };
+        self.agent.flush_broadcasts().await;
+        self.streams_guard().remove(&correlation_id);
+        while let Ok(event) = events.try_recv() {
+            connected = connected && send_event(&mut sender, &event).await;
+        }
+        if !connected {
+            return;
+        }
+
+        let (name, data) = match result {
+            Ok(message) => (
+                "done",
+                serde_json::to_string(&ChatResponse::new(thread_id.clone(), message)),
+            ),
+            Err(e) => {
+                tracing::warn!(error = %format!("{e:#}"), "Streamed agent run failed");
+                (
+                    "error",
+                    serde_json::to_string(&serde_json::json!({ "error": error_status(&e).1 })),
+                )
+            }
+        };
+        if let Ok(data) = data {
+            let _ = sender.send_data(sse_frame(name, &data)).await;
+        }
+    }
+
+    fn streams_guard(
+        &self,
+    ) -> std::sync::MutexGuard<'_, HashMap<String, mpsc::UnboundedSender<AgentEvent>>> {
+        self.streams.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Whether the function was invoked directly rather than through HTTP.
+fn is_direct_invocation(request: &Request) -> bool {
+    matches!(
+        request.request_context_ref(),
+        Some(RequestContext::PassThrough)
+    )
+}
+
+fn new_thread_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// Decode the request body and pick the correlation id, or why the request is invalid.
+fn parse_request(request: &Request) -> Result<(ChatRequest, String), String> {
+    let chat: ChatRequest = serde_json::from_slice(request.body().as_ref())
+        .map_err(|e| format!("invalid request body: {e}"))?;
+    if chat.message.trim().is_empty() {
+        return Err("message must not be empty".to_string());
+    }
+    let correlation_id = request
+        .headers()
+        .get(CORRELATION_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .or_else(|| request.lambda_context_ref().map(|c| c.request_id.clone()))
+        .unwrap_or_else(new_thread_id);
+    Ok((chat, correlation_id))
+}
+
+fn bad_request(error: &str) -> Response<String> {
+    json_response(
+        StatusCode::BAD_REQUEST,
+        &serde_json::json!({ "error": error }),
+    )
+}
+
+fn json_response(status: StatusCode, body: &impl Serialize) -> Response<String> {
+    let mut response = Response::new(serde_json::to_string(body).unwrap_or_default());
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        header::HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+/// Status and message for a failed run; internal errors are not exposed to callers.
+fn error_status(error: &anyhow::Error) -> (StatusCode, &'static str) {
+    if let Some(ThreadLockError::Busy { .. }) = error.downcast_ref::<ThreadLockError>() {
+        return (StatusCode::CONFLICT, "thread is busy, retry later");
+    }
+    if error.is::<AgentShutdownError>() {
+        return (StatusCode::SERVICE_UNAVAILABLE, "agent is shutting down");
+    }
+    (StatusCode::INTERNAL_SERVER_ERROR, "internal error")
+}
+
+fn error_response(error: &anyhow::Error) -> Response<String> {
+    let (status, message) = error_status(error);
+    if status == StatusCode::INTERNAL_SERVER_ERROR {
+        tracing::error!(error = %format!("{error:#}"), "Agent run failed");
+    }
+    json_response(status, &serde_json::json!({ "error": message }))
+}
+
+fn sse_frame(event: &str, data: &str) -> Bytes {
+    Bytes::from(format!("event: {event}\ndata: {data}\n\n"))
+}
+
+/// Send an event to the client; `false` once the client is gone.
+async fn send_event(sender: &mut Sender, event: &AgentEvent) -> bool {
+    let Ok(data) = event.to_wire_json() else {
+        return true;
+    };
+    sender
+        .send_data(sse_frame(event.event_type_name(), &data))
+        .await
+        .is_ok()
+}
+
+/// Routes the events of streamed runs to their response, keyed by correlation id.
+struct StreamForwarder {
+    streams: Arc<Mutex<HashMap<String, mpsc::UnboundedSender<AgentEvent>>>>,
+}
+
+#[async_trait]
+impl EventBroadcaster for StreamForwarder {
+    fn id(&self) -> &str {
+        "lambda-stream"
+    }
+
+    fn should_broadcast(&self, event: &AgentEvent) -> bool {
+        self.streams
+            .lock()
+            .map(|streams| streams.contains_key(&event.metadata().correlation_id))
+            .unwrap_or(false)
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+
+    async fn broadcast(&self, event: &AgentEvent) -> anyhow::Result<()> {
+        if let Ok(streams) = self.streams.lock() {
+            if let Some(stream) = streams.get(&event.metadata().correlation_id) {
+                let _ = stream.send(event.clone());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(body: &str) -> Request {
+        let mut request = Request::new(Body::from(body));
+        request
+            .headers_mut()
+            .insert(CORRELATION_HEADER, "corr-1".parse().unwrap());
+        request
+    }
+
+    #[test]
+    fn requests_are_validated() {
+        let (chat, correlation_id) =
+            parse_request(&request(r#"{"thread_id": "t-1", "message": "hi"}"#)).unwrap();
+        assert_eq!(chat.thread_id.as_deref(), Some("t-1"));
+        assert_eq!(correlation_id, "corr-1");
+
+        assert!(parse_request(&request(r#"{"message": "  "}"#)).is_err());
+        assert!(parse_request(&request("not json")).is_err());
+    }
+
+    #[test]
+    fn run_errors_map_to_status_codes() {
+        let busy = anyhow::Error::new(ThreadLockError::Busy {
+            thread_id: "t-1".into(),
+            waited_ms: 500,
+        });
+        assert_eq!(error_status(&busy).0, StatusCode::CONFLICT);
+
+        let internal = anyhow::anyhow!("database password rejected");
+        let (status, message) = error_status(&internal);
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(!message.contains("password"));
+    }
+}
@@ -4,8 +4,14 @@
 //!
 //! ## Features
 //!
-//! - `dynamodb`: Enable DynamoDB checkpointer for state persistence
+//! - `dynamodb`: Enable DynamoDB checkpointer, thread lock and HITL approval store
 //! - `secrets`: Enable AWS Secrets Manager integration
+//! - `s3`: Enable the S3 output sink for long agent responses
+//! - `sqs`: Enable the SQS job queue for agent workers
+//! - `sns`: Enable the SNS event broadcaster
+//! - `bedrock`: Enable Amazon Bedrock Guardrails for any model
+//! - `lambda`: Enable the Lambda runtime adapter for API Gateway and Function URLs,
+//!   and the Step Functions workflow exporter
 //! - `aws-sdk`: Enable all AWS integrations
 //!
 //! ## Examples
@@ -33,25 +39,72 @@
 //! # }
 //! ```
 
+#[cfg(feature = "bedrock")]
+pub mod bedrock_guardrail;
+
+#[cfg(feature = "bedrock")]
+pub use bedrock_guardrail::{BedrockGuardrail, BedrockGuardrailBuilder};
+
+#[cfg(feature = "dynamodb")]
+pub mod dynamodb_approval_store;
+
+#[cfg(feature = "dynamodb")]
+pub use dynamodb_approval_store::{DynamoDbApprovalStore, DynamoDbApprovalStoreBuilder};
+
 #[cfg(feature = "dynamodb")]
 pub mod dynamodb_checkpointer;
 
 #[cfg(feature = "dynamodb")]
 pub use dynamodb_checkpointer::{DynamoDbCheckpointer, DynamoDbCheckpointerBuilder};
 
+#[cfg(feature = "dynamodb")]
+pub mod dynamodb_thread_lock;
+
+#[cfg(feature = "dynamodb")]
+pub use dynamodb_thread_lock::{DynamoDbThreadLock, DynamoDbThreadLockBuilder};
+
+#[cfg(feature = "s3")]
+pub mod s3_output_sink;
+
+#[cfg(feature = "s3")]
+pub use s3_output_sink::{S3OutputSink, S3OutputSinkBuilder};
+
+#[cfg(feature = "sqs")]
+pub mod sqs_job_queue;
+
+#[cfg(feature = "sqs")]
+pub use sqs_job_queue::{SqsJobQueue, SqsJobQueueBuilder};
+
+#[cfg(feature = "sns")]
+pub mod sns_event_broadcaster;
+
+#[cfg(feature = "lambda")]
+pub mod lambda;
+
+#[cfg(feature = "lambda")]
+pub use lambda::LambdaAgentHandler;
+
+#[cfg(feature = "lambda")]
+pub mod step_functions;
+
+#[cfg(feature = "lambda")]
+pub use step_functions::{AgentWorkflow, WorkflowStep};
+
+#[cfg(feature = "sns")]
+pub use sns_event_broadcaster::{SnsEventBroadcaster, SnsEventBroadcasterBuilder};
+
 // Re-export core types for convenience
+pub use agents_core::job_queue::JobQueue;
 pub use agents_core::persistence::{Checkpointer, ThreadId};
-
-/// Placeholder trait for loading configuration secrets.
-pub trait SecretsProvider {
-    fn fetch(&self, key: &str) -> anyhow::Result<String>;
-}
+pub use agents_core::secrets::SecretsProvider;
+pub use agents_core::thread_lock::ThreadLock;
 
 /// Stub Secrets Manager provider; real implementation will sit behind the `secrets` feature.
 pub struct UnimplementedSecretsProvider;
 
+#[async_trait::async_trait]
 impl SecretsProvider for UnimplementedSecretsProvider {
-    fn fetch(&self, key: &str) -> anyhow::Result<String> {
+    async fn fetch(&self, key: &str) -> anyhow::Result<String> {
         Err(anyhow::anyhow!("Secrets provider not implemented: {key}"))
     }
 }
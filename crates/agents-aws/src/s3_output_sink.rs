@@ -0,0 +1,261 @@
+//! S3-backed output sink for long agent responses.
+//!
+//! Outputs are buffered and uploaded in 5 MiB parts with S3 multipart uploads, so
+//! arbitrarily long reports never have to be held in memory at once. Outputs smaller
+//! than one part are stored with a single `PutObject`.
+//!
+//! The sink only needs `s3:PutObject` and `s3:AbortMultipartUpload` on the target
+//! prefix.
+
+use agents_core::sink::{OutputSink, OutputWriter};
+use anyhow::Context;
+use async_trait::async_trait;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
+use aws_sdk_s3::Client;
+
+/// Smallest part size S3 accepts for every part but the last.
+const PART_SIZE: usize = 5 * 1024 * 1024;
+
+/// Output sink that stores each output as an S3 object.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use agents_aws::S3OutputSink;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let sink = S3OutputSink::builder()
+///         .bucket("agent-reports")
+///         .prefix("research/")
+///         .build()
+///         .await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct S3OutputSink {
+    client: Client,
+    bucket: String,
+    prefix: String,
+    content_type: String,
+}
+
+impl S3OutputSink {
+    /// Create a sink writing to `bucket` with the default AWS configuration.
+    pub async fn new(bucket: impl Into<String>) -> anyhow::Result<Self> {
+        Self::builder().bucket(bucket).build().await
+    }
+
+    /// Create a builder for configuring the S3 sink.
+    pub fn builder() -> S3OutputSinkBuilder {
+        S3OutputSinkBuilder::default()
+    }
+
+    fn key(&self, name: &str) -> String {
+        format!("{}{}", self.prefix, name.trim_start_matches('/'))
+    }
+}
+
+#[async_trait]
+impl OutputSink for S3OutputSink {
+    async fn create(&self, name: &str) -> anyhow::Result<Box<dyn OutputWriter>> {
+        Ok(Box::new(S3OutputWriter {
+            client: self.client.clone(),
+            bucket: self.bucket.clone(),
+            key: self.key(name),
+            content_type: self.content_type.clone(),
+            buffer: Vec::new(),
+            upload_id: None,
+            parts: Vec::new(),
+        }))
+    }
+}
+
+struct S3OutputWriter {
+    client: Client,
+    bucket: String,
+    key: String,
+    content_type: String,
+    buffer: Vec<u8>,
+    upload_id: Option<String>,
+    parts: Vec<CompletedPart>,
+}
+
+impl S3OutputWriter {
+    async fn upload_part(&mut self) -> anyhow::Result<()> {
+        let upload_id = match &self.upload_id {
+            Some(id) => id.clone(),
+            None => {
+                let upload = self
+                    .client
+                    .create_multipart_upload()
+                    .bucket(&self.bucket)
+                    .key(&self.key)
+                    .content_type(&self.content_type)
+                    .send()
+                    .await
+                    .context("Failed to start S3 multipart upload")?;
+                let id = upload
+                    .upload_id()
+                    .context("S3 did not return a multipart upload id")?
+                    .to_string();
+                self.upload_id = Some(id.clone());
+                id
+            }
+        };
+
+        let part_number = self.parts.len() as i32 + 1;
+        let body = std::mem::take(&mut self.buffer);
+        let part = self
+            .client
+            .upload_part()
+            .bucket(&self.bucket)
+            .key(&self.key)
+            .upload_id(&upload_id)
+            .part_number(part_number)
+            .body(ByteStream::from(body))
+            .send()
+            .await
+            .with_context(|| format!("Failed to upload part {part_number} to S3"))?;
+
+        self.parts.push(
+            CompletedPart::builder()
+                .set_e_tag(part.e_tag().map(str::to_string))
+                .part_number(part_number)
+                .build(),
+        );
+        Ok(())
+    }
+
+    async fn complete(&mut self) -> anyhow::Result<()> {
+        let Some(upload_id) = self.upload_id.clone() else {
+            self.client
+                .put_object()
+                .bucket(&self.bucket)
+                .key(&self.key)
+                .content_type(&self.content_type)
+                .body(ByteStream::from(std::mem::take(&mut self.buffer)))
+                .send()
+                .await
+                .context("Failed to write output to S3")?;
+            return Ok(());
+        };
+
+        if !self.buffer.is_empty() {
+            self.upload_part().await?;
+        }
+        self.client
+            .complete_multipart_upload()
+            .bucket(&self.bucket)
+            .key(&self.key)
+            .upload_id(&upload_id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(std::mem::take(&mut self.parts)))
+                    .build(),
+            )
+            .send()
+            .await
+            .context("Failed to complete S3 multipart upload")?;
+        Ok(())
+    }
+
+    async fn abort(&self) {
+        let Some(upload_id) = &self.upload_id else {
+            return;
+        };
+        if let Err(e) = self
+            .client
+            .abort_multipart_upload()
+            .bucket(&self.bucket)
+            .key(&self.key)
+            .upload_id(upload_id)
+            .send()
+            .await
+        {
+            tracing::warn!(key = %self.key, error = %e, "Failed to abort S3 multipart upload");
+        }
+    }
+}
+
+#[async_trait]
+impl OutputWriter for S3OutputWriter {
+    async fn write(&mut self, chunk: &str) -> anyhow::Result<()> {
+        self.buffer.extend_from_slice(chunk.as_bytes());
+        if self.buffer.len() >= PART_SIZE {
+            if let Err(e) = self.upload_part().await {
+                self.abort().await;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn finish(mut self: Box<Self>) -> anyhow::Result<String> {
+        if let Err(e) = self.complete().await {
+            self.abort().await;
+            return Err(e);
+        }
+
+        tracing::debug!(bucket = %self.bucket, key = %self.key, "Wrote output to S3");
+        Ok(format!("s3://{}/{}", self.bucket, self.key))
+    }
+}
+
+/// Builder for configuring an S3 output sink.
+#[derive(Default)]
+pub struct S3OutputSinkBuilder {
+    bucket: Option<String>,
+    prefix: String,
+    content_type: Option<String>,
+    client: Option<Client>,
+}
+
+impl S3OutputSinkBuilder {
+    /// Set the target bucket (required).
+    pub fn bucket(mut self, bucket: impl Into<String>) -> Self {
+        self.bucket = Some(bucket.into());
+        self
+    }
+
+    /// Prefix prepended to every object key, e.g. `"reports/"`.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Content type of stored objects (default: `text/markdown; charset=utf-8`).
+    pub fn content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    /// Use a custom S3 client.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Build the S3 output sink.
+    pub async fn build(self) -> anyhow::Result<S3OutputSink> {
+        let bucket = self.bucket.context("Bucket is required for S3OutputSink")?;
+        let client = match self.client {
+            Some(client) => client,
+            None => {
+                let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+                Client::new(&config)
+            }
+        };
+
+        Ok(S3OutputSink {
+            client,
+            bucket,
+            prefix: self.prefix,
+            content_type: self
+                .content_type
+                .unwrap_or_else(|| "text/markdown; charset=utf-8".to_string()),
+        })
+    }
+}
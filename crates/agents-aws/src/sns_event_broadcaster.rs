@@ -0,0 +1,149 @@
+//! SNS event broadcaster for publishing agent events to a topic.
+//!
+//! Each [`AgentEvent`] is published as JSON with the `event_type`, `thread_id` and
+//! `correlation_id` message attributes, so subscribers (SQS queues, Lambda
+//! functions, HTTP endpoints) can narrow what they receive with filter policies.
+//! FIFO topics receive the events of a thread in order, grouped by thread id.
+//!
+//! The IAM policy needs `sns:Publish` on the topic.
+
+use agents_core::events::{AgentEvent, EventBroadcaster};
+use anyhow::Context;
+use async_trait::async_trait;
+use aws_sdk_sns::types::MessageAttributeValue;
+use aws_sdk_sns::Client;
+use std::collections::HashSet;
+
+/// Event broadcaster publishing to an SNS topic.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use agents_aws::SnsEventBroadcaster;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let broadcaster = SnsEventBroadcaster::builder()
+///         .topic_arn("arn:aws:sns:us-east-1:123456789012:agent-events")
+///         .event_types(["agent_completed", "tool_failed"])
+///         .build()
+///         .await?;
+///     Ok(())
+/// }
+/// ```
+pub struct SnsEventBroadcaster {
+    client: Client,
+    topic_arn: String,
+    event_types: Option<HashSet<String>>,
+}
+
+impl SnsEventBroadcaster {
+    /// Create a broadcaster publishing every event to `topic_arn`.
+    pub async fn new(topic_arn: impl Into<String>) -> anyhow::Result<Self> {
+        Self::builder().topic_arn(topic_arn).build().await
+    }
+
+    /// Create a builder for configuring the SNS broadcaster.
+    pub fn builder() -> SnsEventBroadcasterBuilder {
+        SnsEventBroadcasterBuilder::default()
+    }
+}
+
+fn string_attribute(value: &str) -> anyhow::Result<MessageAttributeValue> {
+    MessageAttributeValue::builder()
+        .data_type("String")
+        .string_value(value)
+        .build()
+        .context("Invalid SNS message attribute")
+}
+
+#[async_trait]
+impl EventBroadcaster for SnsEventBroadcaster {
+    fn id(&self) -> &str {
+        "sns"
+    }
+
+    fn should_broadcast(&self, event: &AgentEvent) -> bool {
+        self.event_types
+            .as_ref()
+            .is_none_or(|types| types.contains(event.event_type_name()))
+    }
+
+    async fn broadcast(&self, event: &AgentEvent) -> anyhow::Result<()> {
+        let metadata = event.metadata();
+        let message = event.to_wire_json().context("Failed to serialize event")?;
+        let mut request = self
+            .client
+            .publish()
+            .topic_arn(&self.topic_arn)
+            .message(message)
+            .message_attributes("event_type", string_attribute(event.event_type_name())?)
+            .message_attributes("thread_id", string_attribute(&metadata.thread_id)?)
+            .message_attributes(
+                "correlation_id",
+                string_attribute(&metadata.correlation_id)?,
+            );
+        if self.topic_arn.ends_with(".fifo") {
+            request = request
+                .message_group_id(&metadata.thread_id)
+                .message_deduplication_id(uuid::Uuid::new_v4().simple().to_string());
+        }
+        request
+            .send()
+            .await
+            .with_context(|| format!("Failed to publish event to {}", self.topic_arn))?;
+        Ok(())
+    }
+}
+
+/// Builder for configuring an SNS event broadcaster.
+#[derive(Default)]
+pub struct SnsEventBroadcasterBuilder {
+    topic_arn: Option<String>,
+    event_types: Option<HashSet<String>>,
+    client: Option<Client>,
+}
+
+impl SnsEventBroadcasterBuilder {
+    /// Set the topic to publish to (required).
+    pub fn topic_arn(mut self, arn: impl Into<String>) -> Self {
+        self.topic_arn = Some(arn.into());
+        self
+    }
+
+    /// Only publish these event types (`agent_completed`, `tool_failed`, ...).
+    pub fn event_types<I, S>(mut self, types: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.event_types = Some(types.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Use a custom SNS client.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Build the SNS event broadcaster.
+    pub async fn build(self) -> anyhow::Result<SnsEventBroadcaster> {
+        let topic_arn = self
+            .topic_arn
+            .context("Topic ARN is required for SnsEventBroadcaster")?;
+        let client = match self.client {
+            Some(client) => client,
+            None => {
+                let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+                Client::new(&config)
+            }
+        };
+
+        Ok(SnsEventBroadcaster {
+            client,
+            topic_arn,
+            event_types: self.event_types,
+        })
+    }
+}
@@ -0,0 +1,468 @@
+//! SQS-backed job queue for agent workers.
+//!
+//! Workers long-poll the jobs queue; a received message stays invisible to other
+//! workers while it is processed, and its visibility timeout is extended in the
+//! background until the job completes. A worker that dies stops extending it, so
+//! the message reappears and another worker picks it up. Once a message has been
+//! received more often than the queue's redrive policy allows, SQS moves it to the
+//! queue's dead-letter queue.
+//!
+//! Message bodies are [`AgentJob`]s as JSON. `job_id` may be omitted (the SQS
+//! message id is used), and SNS notifications delivered without raw message
+//! delivery are unwrapped, so a queue subscribed to an existing SNS topic works as is.
+//!
+//! The IAM policy needs `sqs:ReceiveMessage`, `sqs:DeleteMessage`,
+//! `sqs:ChangeMessageVisibility` and `sqs:SendMessage` on the queues involved.
+
+use agents_core::events::AgentEvent;
+use agents_core::job_queue::{AgentJob, AgentJobResult, ClaimedJob, JobQueue};
+use anyhow::Context;
+use async_trait::async_trait;
+use aws_sdk_sqs::types::{MessageAttributeValue, MessageSystemAttributeName};
+use aws_sdk_sqs::Client;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Most messages one `ReceiveMessage` call returns.
+const MAX_BATCH: usize = 10;
+/// Longest long-polling wait SQS supports.
+const MAX_WAIT_SECONDS: u64 = 20;
+
+/// SQS implementation of [`JobQueue`].
+///
+/// Results are sent to the results queue and events to the events queue, when
+/// configured. SQS cannot look messages up by id, so [`JobQueue::result`] always
+/// returns `None`; consumers read the results queue instead.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use agents_aws::SqsJobQueue;
+/// use std::time::Duration;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let queue = SqsJobQueue::builder()
+///         .queue_url("https://sqs.us-east-1.amazonaws.com/123456789012/agent-jobs")
+///         .results_queue_url("https://sqs.us-east-1.amazonaws.com/123456789012/agent-results")
+///         .dead_letter_queue_url("https://sqs.us-east-1.amazonaws.com/123456789012/agent-jobs-dlq")
+///         .visibility_timeout(Duration::from_secs(120))
+///         .build()
+///         .await?;
+///     Ok(())
+/// }
+/// ```
+pub struct SqsJobQueue {
+    client: Client,
+    queue_url: String,
+    results_queue_url: Option<String>,
+    events_queue_url: Option<String>,
+    dead_letter_queue_url: Option<String>,
+    visibility_timeout: Duration,
+    retry_delay: Duration,
+    /// Visibility extensions of jobs being processed, by receipt handle
+    heartbeats: Arc<Mutex<HashMap<String, JoinHandle<()>>>>,
+}
+
+/// Message body; `job_id` falls back to the SQS message id.
+#[derive(Deserialize)]
+struct JobBody {
+    job_id: Option<String>,
+    thread_id: String,
+    message: String,
+}
+
+/// SNS notification as delivered to SQS without raw message delivery.
+#[derive(Deserialize)]
+struct SnsEnvelope {
+    #[serde(rename = "Type")]
+    kind: String,
+    #[serde(rename = "Message")]
+    message: String,
+}
+
+/// Decode a message body into a job, unwrapping SNS notifications.
+fn parse_job(body: &str, message_id: &str) -> Option<AgentJob> {
+    let job = match serde_json::from_str::<JobBody>(body) {
+        Ok(job) => job,
+        Err(_) => {
+            let envelope: SnsEnvelope = serde_json::from_str(body).ok()?;
+            if envelope.kind != "Notification" {
+                return None;
+            }
+            serde_json::from_str(&envelope.message).ok()?
+        }
+    };
+    Some(AgentJob {
+        job_id: job.job_id.unwrap_or_else(|| message_id.to_string()),
+        thread_id: job.thread_id,
+        message: job.message,
+    })
+}
+
+fn string_attribute(value: &str) -> anyhow::Result<MessageAttributeValue> {
+    MessageAttributeValue::builder()
+        .data_type("String")
+        .string_value(value)
+        .build()
+        .context("Invalid SQS message attribute")
+}
+
+fn is_fifo(queue_url: &str) -> bool {
+    queue_url.ends_with(".fifo")
+}
+
+impl SqsJobQueue {
+    /// Create a queue consuming `queue_url` with the default AWS configuration.
+    pub async fn new(queue_url: impl Into<String>) -> anyhow::Result<Self> {
+        Self::builder().queue_url(queue_url).build().await
+    }
+
+    /// Create a builder for configuring the SQS job queue.
+    pub fn builder() -> SqsJobQueueBuilder {
+        SqsJobQueueBuilder::default()
+    }
+
+    /// Send `body` to `queue_url`; FIFO queues group messages by thread.
+    async fn send(
+        &self,
+        queue_url: &str,
+        body: String,
+        job_id: &str,
+        thread_id: &str,
+        attributes: &[(&str, &str)],
+    ) -> anyhow::Result<()> {
+        let mut request = self
+            .client
+            .send_message()
+            .queue_url(queue_url)
+            .message_body(body)
+            .message_attributes("job_id", string_attribute(job_id)?);
+        for (name, value) in attributes {
+            request = request.message_attributes(*name, string_attribute(value)?);
+        }
+        if is_fifo(queue_url) {
+            request = request
+                .message_group_id(thread_id)
+                .message_deduplication_id(uuid::Uuid::new_v4().simple().to_string());
+        }
+        request
+            .send()
+            .await
+            .with_context(|| format!("Failed to send message to {queue_url}"))?;
+        Ok(())
+    }
+
+    /// Keep the message invisible while its job is processed.
+    fn start_heartbeat(&self, receipt: &str) {
+        let client = self.client.clone();
+        let queue_url = self.queue_url.clone();
+        let handle = receipt.to_string();
+        let timeout = self.visibility_timeout;
+        let task = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(timeout / 2).await;
+                if let Err(e) = client
+                    .change_message_visibility()
+                    .queue_url(&queue_url)
+                    .receipt_handle(&handle)
+                    .visibility_timeout(timeout.as_secs() as i32)
+                    .send()
+                    .await
+                {
+                    tracing::warn!(error = %e, "Failed to extend SQS message visibility");
+                }
+            }
+        });
+        self.heartbeats_guard().insert(receipt.to_string(), task);
+    }
+
+    fn stop_heartbeat(&self, receipt: &str) {
+        if let Some(task) = self.heartbeats_guard().remove(receipt) {
+            task.abort();
+        }
+    }
+
+    fn heartbeats_guard(&self) -> std::sync::MutexGuard<'_, HashMap<String, JoinHandle<()>>> {
+        self.heartbeats.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    async fn delete(&self, receipt: &str) -> anyhow::Result<()> {
+        self.client
+            .delete_message()
+            .queue_url(&self.queue_url)
+            .receipt_handle(receipt)
+            .send()
+            .await
+            .context("Failed to delete SQS message")?;
+        Ok(())
+    }
+
+    /// Move a message that is not a job to the dead-letter queue, if there is one.
+    async fn reject(&self, body: &str, message_id: &str, receipt: &str) -> anyhow::Result<()> {
+        let Some(dlq) = &self.dead_letter_queue_url else {
+            tracing::error!(
+                message_id,
+                "Malformed job message left for the redrive policy"
+            );
+            return Ok(());
+        };
+        tracing::error!(
+            message_id,
+            "Moving malformed job message to the dead-letter queue"
+        );
+        self.send(
+            dlq,
+            body.to_string(),
+            message_id,
+            message_id,
+            &[("failure_reason", "malformed job")],
+        )
+        .await?;
+        self.delete(receipt).await
+    }
+}
+
+impl Drop for SqsJobQueue {
+    fn drop(&mut self) {
+        for (_, task) in self.heartbeats_guard().drain() {
+            task.abort();
+        }
+    }
+}
+
+#[async_trait]
+impl JobQueue for SqsJobQueue {
+    async fn enqueue(&self, job: &AgentJob) -> anyhow::Result<()> {
+        let body = serde_json::to_string(job).context("Failed to serialize job")?;
+        self.send(&self.queue_url, body, &job.job_id, &job.thread_id, &[])
+            .await?;
+        tracing::debug!(job_id = %job.job_id, thread_id = %job.thread_id, "Enqueued job in SQS");
+        Ok(())
+    }
+
+    async fn claim(
+        &self,
+        _consumer: &str,
+        limit: usize,
+        wait: Duration,
+    ) -> anyhow::Result<Vec<ClaimedJob>> {
+        let received = self
+            .client
+            .receive_message()
+            .queue_url(&self.queue_url)
+            .max_number_of_messages(limit.clamp(1, MAX_BATCH) as i32)
+            .wait_time_seconds(wait.as_secs().min(MAX_WAIT_SECONDS) as i32)
+            .visibility_timeout(self.visibility_timeout.as_secs() as i32)
+            .message_system_attribute_names(MessageSystemAttributeName::ApproximateReceiveCount)
+            .send()
+            .await
+            .context("Failed to receive SQS messages")?;
+
+        let mut jobs = Vec::new();
+        for message in received.messages.unwrap_or_default() {
+            let (Some(receipt), Some(body)) = (message.receipt_handle(), message.body()) else {
+                continue;
+            };
+            let message_id = message.message_id().unwrap_or(receipt);
+            let Some(job) = parse_job(body, message_id) else {
+                self.reject(body, message_id, receipt).await?;
+                continue;
+            };
+            let deliveries = message
+                .attributes()
+                .and_then(|a| a.get(&MessageSystemAttributeName::ApproximateReceiveCount))
+                .and_then(|count| count.parse().ok())
+                .unwrap_or(1);
+            self.start_heartbeat(receipt);
+            jobs.push(ClaimedJob {
+                job,
+                receipt: receipt.to_string(),
+                deliveries,
+            });
+        }
+        Ok(jobs)
+    }
+
+    async fn complete(&self, job: &ClaimedJob, result: &AgentJobResult) -> anyhow::Result<()> {
+        self.stop_heartbeat(&job.receipt);
+        let (job_id, thread_id) = (job.job.job_id.as_str(), job.job.thread_id.as_str());
+
+        if let (Err(reason), Some(dlq)) = (&result.outcome, &self.dead_letter_queue_url) {
+            let body = serde_json::to_string(&job.job).context("Failed to serialize job")?;
+            self.send(dlq, body, job_id, thread_id, &[("failure_reason", reason)])
+                .await?;
+        }
+        if let Some(results) = &self.results_queue_url {
+            let body = serde_json::to_string(result).context("Failed to serialize job result")?;
+            let status = if result.outcome.is_ok() {
+                "completed"
+            } else {
+                "failed"
+            };
+            self.send(results, body, job_id, thread_id, &[("status", status)])
+                .await?;
+        }
+        self.delete(&job.receipt).await?;
+        tracing::debug!(job_id, "Completed job in SQS");
+        Ok(())
+    }
+
+    async fn release(&self, job: &ClaimedJob) -> anyhow::Result<()> {
+        self.stop_heartbeat(&job.receipt);
+        self.client
+            .change_message_visibility()
+            .queue_url(&self.queue_url)
+            .receipt_handle(&job.receipt)
+            .visibility_timeout(self.retry_delay.as_secs() as i32)
+            .send()
+            .await
+            .context("Failed to release SQS message")?;
+        Ok(())
+    }
+
+    async fn publish_event(&self, job_id: &str, event: &AgentEvent) -> anyhow::Result<()> {
+        let Some(events) = &self.events_queue_url else {
+            return Ok(());
+        };
+        let body = event.to_wire_json().context("Failed to serialize event")?;
+        self.send(
+            events,
+            body,
+            job_id,
+            &event.metadata().thread_id,
+            &[("event_type", event.event_type_name())],
+        )
+        .await
+    }
+
+    async fn result(&self, _job_id: &str) -> anyhow::Result<Option<AgentJobResult>> {
+        Ok(None)
+    }
+}
+
+/// Builder for configuring an SQS job queue.
+#[derive(Default)]
+pub struct SqsJobQueueBuilder {
+    queue_url: Option<String>,
+    results_queue_url: Option<String>,
+    events_queue_url: Option<String>,
+    dead_letter_queue_url: Option<String>,
+    visibility_timeout: Option<Duration>,
+    retry_delay: Option<Duration>,
+    client: Option<Client>,
+}
+
+impl SqsJobQueueBuilder {
+    /// Set the URL of the jobs queue (required).
+    pub fn queue_url(mut self, url: impl Into<String>) -> Self {
+        self.queue_url = Some(url.into());
+        self
+    }
+
+    /// Send every [`AgentJobResult`] to this queue.
+    pub fn results_queue_url(mut self, url: impl Into<String>) -> Self {
+        self.results_queue_url = Some(url.into());
+        self
+    }
+
+    /// Send the events of every job to this queue.
+    pub fn events_queue_url(mut self, url: impl Into<String>) -> Self {
+        self.events_queue_url = Some(url.into());
+        self
+    }
+
+    /// Send failed jobs and malformed messages to this queue. Jobs that are never
+    /// completed reach the DLQ through the jobs queue's redrive policy instead.
+    pub fn dead_letter_queue_url(mut self, url: impl Into<String>) -> Self {
+        self.dead_letter_queue_url = Some(url.into());
+        self
+    }
+
+    /// How long a received job stays hidden from other workers (default: 5 minutes).
+    /// Extended in the background while the job runs; this bounds how long a job of
+    /// a crashed worker waits before it is redelivered.
+    pub fn visibility_timeout(mut self, timeout: Duration) -> Self {
+        self.visibility_timeout = Some(timeout);
+        self
+    }
+
+    /// Delay before a released job (busy thread, shutdown) is received again
+    /// (default: 10 seconds).
+    pub fn retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = Some(delay);
+        self
+    }
+
+    /// Use a custom SQS client.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Build the SQS job queue.
+    pub async fn build(self) -> anyhow::Result<SqsJobQueue> {
+        let queue_url = self
+            .queue_url
+            .context("Queue URL is required for SqsJobQueue")?;
+        let client = match self.client {
+            Some(client) => client,
+            None => {
+                let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+                Client::new(&config)
+            }
+        };
+
+        Ok(SqsJobQueue {
+            client,
+            queue_url,
+            results_queue_url: self.results_queue_url,
+            events_queue_url: self.events_queue_url,
+            dead_letter_queue_url: self.dead_letter_queue_url,
+            // SQS accepts 0 to 12 hours; heartbeats need at least a couple of seconds
+            visibility_timeout: self
+                .visibility_timeout
+                .unwrap_or(Duration::from_secs(300))
+                .clamp(Duration::from_secs(2), Duration::from_secs(43_200)),
+            retry_delay: self
+                .retry_delay
+                .unwrap_or(Duration::from_secs(10))
+                .min(Duration::from_secs(43_200)),
+            heartbeats: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jobs_are_parsed_from_plain_and_sns_bodies() {
+        let job = parse_job(
+            r#"{"job_id": "job-1", "thread_id": "t-1", "message": "hi"}"#,
+            "msg-1",
+        )
+        .unwrap();
+        assert_eq!(job.job_id, "job-1");
+
+        let job = parse_job(r#"{"thread_id": "t-1", "message": "hi"}"#, "msg-2").unwrap();
+        assert_eq!(job.job_id, "msg-2");
+
+        let envelope = serde_json::json!({
+            "Type": "Notification",
+            "TopicArn": "arn:aws:sns:us-east-1:123456789012:orders",
+            "Message": r#"{"thread_id": "t-2", "message": "order shipped"}"#,
+        });
+        let job = parse_job(&envelope.to_string(), "msg-3").unwrap();
+        assert_eq!(
+            (job.thread_id.as_str(), job.message.as_str()),
+            ("t-2", "order shipped")
+        );
+
+        assert!(parse_job("not json", "msg-4").is_none());
+    }
+}
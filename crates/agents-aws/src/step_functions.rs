@@ -0,0 +1,357 @@
+//! Export declarative agent workflows as AWS Step Functions state machines.
+//!
+//! An [`AgentWorkflow`] is a fixed sequence of agent steps with optional branches.
+//! Exported to Amazon States Language, every step becomes a `Task` state invoking
+//! the function that serves the agent with [`LambdaAgentHandler`](crate::LambdaAgentHandler):
+//! Step Functions owns the process (ordering, branching, retries, execution
+//! history for audits) while the agent does the work of each step.
+//!
+//! All steps of an execution run on one thread, named after the execution, so each
+//! step sees the conversation of the steps before it. The execution input is
+//! `{"input": "..."}`; prompts reference it as `{input}`. The response of a step is
+//! stored at `$.<step>.response`.
+
+use anyhow::bail;
+use serde_json::{json, Map, Value};
+use std::collections::HashSet;
+
+/// Errors retried on task states: failed runs (e.g. a busy thread) and Lambda
+/// throttling.
+const RETRY_ERRORS: [&str; 3] = [
+    "States.TaskFailed",
+    "Lambda.ServiceException",
+    "Lambda.TooManyRequestsException",
+];
+
+/// Where a step continues.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Transition {
+    /// The next step in the workflow, or the end after the last one
+    Sequential,
+    Next(String),
+    End,
+}
+
+/// One agent step of a workflow.
+#[derive(Debug, Clone)]
+pub struct WorkflowStep {
+    name: String,
+    prompt: String,
+    transition: Transition,
+    /// `(text, step)`: continue with `step` when the response contains `text`
+    branches: Vec<(String, String)>,
+    retries: u32,
+    timeout_seconds: Option<u32>,
+}
+
+impl WorkflowStep {
+    /// A step sending `prompt` to the agent. Names may contain ASCII letters, digits
+    /// and `_`, since they are also used in JSON paths.
+    pub fn new(name: impl Into<String>, prompt: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            prompt: prompt.into(),
+            transition: Transition::Sequential,
+            branches: Vec::new(),
+            retries: 2,
+            timeout_seconds: None,
+        }
+    }
+
+    /// Continue with `step` instead of the next step in order.
+    pub fn then(mut self, step: impl Into<String>) -> Self {
+        self.transition = Transition::Next(step.into());
+        self
+    }
+
+    /// End the workflow after this step.
+    pub fn end(mut self) -> Self {
+        self.transition = Transition::End;
+        self
+    }
+
+    /// Continue with `step` when the response contains `text`. Branches are checked
+    /// in order; without a match the step's regular transition applies.
+    pub fn branch(mut self, text: impl Into<String>, step: impl Into<String>) -> Self {
+        self.branches.push((text.into(), step.into()));
+        self
+    }
+
+    /// Retry a failed run up to `retries` times with exponential backoff (default: 2).
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    pub fn with_timeout_seconds(mut self, seconds: u32) -> Self {
+        self.timeout_seconds = Some(seconds);
+        self
+    }
+}
+
+/// A declarative multi-step agent workflow.
+///
+/// # Examples
+///
+/// ```rust
+/// use agents_aws::{AgentWorkflow, WorkflowStep};
+///
+/// let workflow = AgentWorkflow::new("Claim intake")
+///     .step(
+///         WorkflowStep::new("Assess", "Assess this insurance claim: {input}. Answer APPROVE or REVIEW.")
+///             .branch("REVIEW", "Escalate"),
+///     )
+///     .step(WorkflowStep::new("Approve", "Draft the approval letter.").end())
+///     .step(WorkflowStep::new("Escalate", "Summarize the claim for a human adjuster."));
+///
+/// let definition = workflow
+///     .to_step_functions("arn:aws:lambda:us-east-1:123456789012:function:claims-agent")
+///     .unwrap();
+/// assert_eq!(definition["StartAt"], "Assess");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AgentWorkflow {
+    comment: String,
+    steps: Vec<WorkflowStep>,
+}
+
+impl AgentWorkflow {
+    pub fn new(comment: impl Into<String>) -> Self {
+        Self {
+            comment: comment.into(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Append a step; the first step starts the workflow.
+    pub fn step(mut self, step: WorkflowStep) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Check names and transitions.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.steps.is_empty() {
+            bail!("workflow has no steps");
+        }
+        let mut names = HashSet::new();
+        for step in &self.steps {
+            let valid = !step.name.is_empty()
+                && step
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid {
+                bail!("invalid step name '{}'", step.name);
+            }
+            if step.name == "input" {
+                bail!("step name 'input' is reserved for the execution input");
+            }
+            if !names.insert(step.name.as_str()) {
+                bail!("duplicate step '{}'", step.name);
+            }
+        }
+        for step in &self.steps {
+            let targets = step.branches.iter().map(|(_, target)| target);
+            let next = match &step.transition {
+                Transition::Next(target) => Some(target),
+                _ => None,
+            };
+            for target in targets.chain(next) {
+                if !names.contains(target.as_str()) {
+                    bail!(
+                        "step '{}' continues with unknown step '{target}'",
+                        step.name
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Amazon States Language definition invoking `function_arn` for every step.
+    pub fn to_step_functions(&self, function_arn: &str) -> anyhow::Result<Value> {
+        self.validate()?;
+        let mut states = Map::new();
+        for (index, step) in self.steps.iter().enumerate() {
+            let next = match &step.transition {
+                Transition::Sequential => self.steps.get(index + 1).map(|s| s.name.clone()),
+                Transition::Next(target) => Some(target.clone()),
+                Transition::End => None,
+            };
+
+            let mut task = json!({
+                "Type": "Task",
+                "Resource": "arn:aws:states:::lambda:invoke",
+                "Parameters": {
+                    "FunctionName": function_arn,
+                    "Payload": payload(&step.prompt),
+                },
+                "ResultSelector": { "response.$": "$.Payload.response" },
+                "ResultPath": format!("$.{}", step.name),
+            });
+            if step.retries > 0 {
+                task["Retry"] = json!([{
+                    "ErrorEquals": RETRY_ERRORS,
+                    "IntervalSeconds": 5,
+                    "MaxAttempts": step.retries,
+                    "BackoffRate": 2.0,
+                }]);
+            }
+            if let Some(seconds) = step.timeout_seconds {
+                task["TimeoutSeconds"] = json!(seconds);
+            }
+
+            if step.branches.is_empty() {
+                set_next(&mut task, next);
+                states.insert(step.name.clone(), task);
+                continue;
+            }
+
+            let route = format!("{}-route", step.name);
+            task["Next"] = json!(route);
+            states.insert(step.name.clone(), task);
+
+            let choices: Vec<Value> = step
+                .branches
+                .iter()
+                .map(|(text, target)| {
+                    json!({
+                        "Variable": format!("$.{}.response", step.name),
+                        "StringMatches": format!("*{}*", escape_wildcards(text)),
+                        "Next": target,
+                    })
+                })
+                .collect();
+            let mut choice = json!({ "Type": "Choice", "Choices": choices });
+            match next {
+                Some(next) => choice["Default"] = json!(next),
+                None => {
+                    let done = format!("{}-done", step.name);
+                    choice["Default"] = json!(done);
+                    states.insert(done, json!({ "Type": "Succeed" }));
+                }
+            }
+            // Generated names contain `-`, which step names cannot
+            states.insert(route, choice);
+        }
+
+        Ok(json!({
+            "Comment": self.comment,
+            "StartAt": self.steps[0].name,
+            "States": states,
+        }))
+    }
+}
+
+fn set_next(state: &mut Value, next: Option<String>) {
+    match next {
+        Some(next) => state["Next"] = json!(next),
+        None => state["End"] = json!(true),
+    }
+}
+
+/// Lambda payload of a step: the execution name as thread and the prompt, with
+/// `{input}` filled in from the execution input.
+fn payload(prompt: &str) -> Value {
+    let mut payload = json!({ "thread_id.$": "$$.Execution.Name" });
+    let parts: Vec<&str> = prompt.split("{input}").collect();
+    if parts.len() == 1 {
+        payload["message"] = json!(prompt);
+        return payload;
+    }
+    let template = parts
+        .iter()
+        .map(|part| escape_intrinsic(part))
+        .collect::<Vec<_>>()
+        .join("{}");
+    let inputs = vec!["$.input"; parts.len() - 1].join(", ");
+    payload["message.$"] = json!(format!("States.Format('{template}', {inputs})"));
+    payload
+}
+
+/// Escape the characters intrinsic function string literals reserve.
+fn escape_intrinsic(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\'' | '{' | '}' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn escape_wildcards(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('*', "\\*")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARN: &str = "arn:aws:lambda:us-east-1:123456789012:function:agent";
+
+    #[test]
+    fn steps_become_tasks_and_branches_become_choices() {
+        let definition = AgentWorkflow::new("Refunds")
+            .step(
+                WorkflowStep::new("Check", "Can order {input} be refunded? Say YES or NO.")
+                    .branch("NO", "Decline"),
+            )
+            .step(WorkflowStep::new("Refund", "Issue the refund.").end())
+            .step(WorkflowStep::new(
+                "Decline",
+                "Explain why it can't be refunded.",
+            ))
+            .to_step_functions(ARN)
+            .unwrap();
+
+        let states = &definition["States"];
+        let check = &states["Check"];
+        assert_eq!(check["Next"], "Check-route");
+        assert_eq!(
+            check["Parameters"]["Payload"]["message.$"],
+            "States.Format('Can order {} be refunded? Say YES or NO.', $.input)"
+        );
+        assert_eq!(
+            check["Parameters"]["Payload"]["thread_id.$"],
+            "$$.Execution.Name"
+        );
+
+        let route = &states["Check-route"];
+        assert_eq!(route["Choices"][0]["StringMatches"], "*NO*");
+        assert_eq!(route["Choices"][0]["Next"], "Decline");
+        assert_eq!(route["Default"], "Refund");
+        assert_eq!(states["Refund"]["End"], true);
+        assert_eq!(states["Decline"]["End"], true);
+        assert_eq!(
+            states["Decline"]["Parameters"]["Payload"]["message"],
+            "Explain why it can't be refunded."
+        );
+    }
+
+    #[test]
+    fn invalid_workflows_are_rejected() {
+        assert!(AgentWorkflow::new("empty").validate().is_err());
+        let unknown = AgentWorkflow::new("x").step(WorkflowStep::new("A", "a").then("B"));
+        assert!(unknown.validate().is_err());
+        let duplicate = AgentWorkflow::new("x")
+            .step(WorkflowStep::new("A", "a"))
+            .step(WorkflowStep::new("A", "b"));
+        assert!(duplicate.validate().is_err());
+        assert!(AgentWorkflow::new("x")
+            .step(WorkflowStep::new("two words", "a"))
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn intrinsic_literals_are_escaped() {
+        let payload = payload("Quote 'it' as {json}: {input}");
+        assert_eq!(
+            payload["message.$"],
+            r"States.Format('Quote \'it\' as \{json\}: {}', $.input)"
+        );
+    }
+}
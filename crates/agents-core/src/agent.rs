@@ -21,12 +21,119 @@ pub trait PlannerHandle: Send + Sync + std::any::Any {
     fn as_any(&self) -> &dyn std::any::Any;
 }
 
-/// Minimal metadata about an agent instance.
+/// Metadata about an agent instance, including the features it supports.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentDescriptor {
     pub name: String,
     pub version: String,
     pub description: Option<String>,
+    /// Features orchestrators can rely on when talking to this agent
+    #[serde(default)]
+    pub capabilities: AgentCapabilities,
+}
+
+impl AgentDescriptor {
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+            description: None,
+            capabilities: AgentCapabilities::default(),
+        }
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn with_capabilities(mut self, capabilities: AgentCapabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+}
+
+/// Features declared by an agent for capability negotiation.
+///
+/// Capabilities can be checked by name with [`supports`](Self::supports):
+/// `"streaming"`, `"vision"`, `"tools"`, `"hitl"`, `"tool:<name>"`,
+/// `"language:<tag>"` and `"subagent:<name>"`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentCapabilities {
+    /// Responses can be streamed token by token
+    #[serde(default)]
+    pub streaming: bool,
+    /// Image inputs are understood by the underlying model
+    #[serde(default)]
+    pub vision: bool,
+    /// Names of the tools the agent can call
+    #[serde(default)]
+    pub tools: Vec<String>,
+    /// Some tool calls pause for human approval
+    #[serde(default)]
+    pub human_in_the_loop: bool,
+    /// BCP 47 language tags the agent is configured to answer in
+    #[serde(default)]
+    pub languages: Vec<String>,
+    /// Names of the sub-agents available for delegation
+    #[serde(default)]
+    pub sub_agents: Vec<String>,
+}
+
+impl AgentCapabilities {
+    pub fn with_streaming(mut self, enabled: bool) -> Self {
+        self.streaming = enabled;
+        self
+    }
+
+    pub fn with_vision(mut self, enabled: bool) -> Self {
+        self.vision = enabled;
+        self
+    }
+
+    pub fn with_language(mut self, language: impl Into<String>) -> Self {
+        let language = language.into();
+        if !self.languages.contains(&language) {
+            self.languages.push(language);
+        }
+        self
+    }
+
+    /// Whether the named capability is available.
+    ///
+    /// Language tags match case-insensitively and a bare language matches any
+    /// region (`"language:ar"` is satisfied by `ar-AE`).
+    pub fn supports(&self, capability: &str) -> bool {
+        if let Some(tool) = capability.strip_prefix("tool:") {
+            return self.tools.iter().any(|t| t == tool);
+        }
+        if let Some(agent) = capability.strip_prefix("subagent:") {
+            return self.sub_agents.iter().any(|a| a == agent);
+        }
+        if let Some(language) = capability.strip_prefix("language:") {
+            let language = language.to_ascii_lowercase();
+            return self.languages.iter().any(|l| {
+                let l = l.to_ascii_lowercase();
+                l == language || l.split('-').next() == Some(language.as_str())
+            });
+        }
+        match capability {
+            "streaming" => self.streaming,
+            "vision" => self.vision,
+            "tools" => !self.tools.is_empty(),
+            "hitl" | "human_in_the_loop" => self.human_in_the_loop,
+            _ => false,
+        }
+    }
+
+    /// The requested capabilities this agent does not support.
+    pub fn missing<'a>(&self, required: &[&'a str]) -> Vec<&'a str> {
+        required
+            .iter()
+            .copied()
+            .filter(|capability| !self.supports(capability))
+            .collect()
+    }
 }
 
 /// Message that returns the planner's decision for the next step.
@@ -109,3 +216,58 @@ pub trait AgentHandle: Send + Sync {
 }
 
 // ToolResponse has been removed - use ToolResult from crate::tools instead
+
+tokio::task_local! {
+    static CURRENT_AGENT: String;
+}
+
+/// Name of the agent whose run is executing on this task, if any.
+///
+/// A sub-agent's run shadows its parent's, so components shared between them (such
+/// as a token-tracking model wrapper) can attribute their work to the right agent.
+pub fn current_agent_name() -> Option<String> {
+    CURRENT_AGENT.try_with(|name| name.clone()).ok()
+}
+
+/// Run `future` as the run of the agent called `name`.
+pub async fn with_current_agent<F: std::future::Future>(
+    name: impl Into<String>,
+    future: F,
+) -> F::Output {
+    CURRENT_AGENT.scope(name.into(), future).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capabilities_negotiation() {
+        let capabilities = AgentCapabilities {
+            tools: vec!["search".into()],
+            sub_agents: vec!["critic".into()],
+            ..Default::default()
+        }
+        .with_streaming(true)
+        .with_language("ar-AE");
+
+        assert!(capabilities.supports("streaming"));
+        assert!(capabilities.supports("tools"));
+        assert!(capabilities.supports("tool:search"));
+        assert!(capabilities.supports("subagent:critic"));
+        assert!(capabilities.supports("language:ar"));
+        assert!(capabilities.supports("language:AR-ae"));
+        assert!(!capabilities.supports("language:en"));
+        assert_eq!(
+            capabilities.missing(&["streaming", "vision", "hitl"]),
+            vec!["vision", "hitl"]
+        );
+    }
+
+    #[test]
+    fn legacy_descriptor_json_deserializes() {
+        let descriptor: AgentDescriptor =
+            serde_json::from_str(r#"{"name":"a","version":"1","description":null}"#).unwrap();
+        assert_eq!(descriptor.capabilities, AgentCapabilities::default());
+    }
+}
@@ -0,0 +1,302 @@
+//! Pending human approvals, shared between the agent and an approval service.
+//!
+//! When a run on a thread pauses for a [`HitlInterrupt`], the agent records a
+//! [`PendingApproval`] in the configured [`ApprovalStore`]. Reviewers list the
+//! approvals assigned to them and record their decision in the store, possibly from a
+//! separate admin service; the agent later resumes the thread with the decision.
+//! Approvals expire: an expired approval can no longer be decided and the tool call
+//! is rejected when the thread resumes.
+
+use crate::hitl::{HitlAction, HitlInterrupt};
+use crate::persistence::ThreadId;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// A tool call waiting for a human decision.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingApproval {
+    pub thread_id: ThreadId,
+    pub interrupt: HitlInterrupt,
+    /// Reviewer the approval is assigned to; `None` for anyone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approver: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decision: Option<ApprovalDecision>,
+}
+
+impl PendingApproval {
+    /// An undecided approval expiring after `ttl`.
+    pub fn new(thread_id: impl Into<ThreadId>, interrupt: HitlInterrupt, ttl: Duration) -> Self {
+        let ttl = chrono::Duration::from_std(ttl).unwrap_or(chrono::Duration::MAX);
+        Self {
+            thread_id: thread_id.into(),
+            expires_at: Utc::now()
+                .checked_add_signed(ttl)
+                .unwrap_or(DateTime::<Utc>::MAX_UTC),
+            interrupt,
+            approver: None,
+            decision: None,
+        }
+    }
+
+    pub fn with_approver(mut self, approver: impl Into<String>) -> Self {
+        self.approver = Some(approver.into());
+        self
+    }
+
+    pub fn call_id(&self) -> &str {
+        &self.interrupt.call_id
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+
+    /// Undecided and not expired.
+    pub fn is_pending(&self) -> bool {
+        self.decision.is_none() && !self.is_expired()
+    }
+}
+
+/// A reviewer's decision on a pending approval.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApprovalDecision {
+    pub action: HitlAction,
+    pub decided_by: String,
+    pub decided_at: DateTime<Utc>,
+    /// Reviewer's note, recorded with the decision in the thread state
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl ApprovalDecision {
+    pub fn new(action: HitlAction, decided_by: impl Into<String>) -> Self {
+        Self {
+            action,
+            decided_by: decided_by.into(),
+            decided_at: Utc::now(),
+            reason: None,
+        }
+    }
+
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+}
+
+/// Why a decision could not be recorded.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ApprovalError {
+    #[error("no pending approval for call '{call_id}' on thread '{thread_id}'")]
+    NotFound {
+        thread_id: ThreadId,
+        call_id: String,
+    },
+    #[error("call '{call_id}' on thread '{thread_id}' was already decided")]
+    AlreadyDecided {
+        thread_id: ThreadId,
+        call_id: String,
+    },
+    #[error("approval for call '{call_id}' on thread '{thread_id}' expired")]
+    Expired {
+        thread_id: ThreadId,
+        call_id: String,
+    },
+}
+
+/// Storage for pending approvals (in-memory, DynamoDB, ...).
+#[async_trait]
+pub trait ApprovalStore: Send + Sync {
+    /// Insert or replace an approval.
+    async fn put(&self, approval: &PendingApproval) -> anyhow::Result<()>;
+
+    /// The approval for a tool call, decided or not. Expired approvals may be
+    /// returned until the backend purges them.
+    async fn get(
+        &self,
+        thread_id: &ThreadId,
+        call_id: &str,
+    ) -> anyhow::Result<Option<PendingApproval>>;
+
+    /// Pending approvals assigned to `approver`, oldest first.
+    async fn list_for_approver(&self, approver: &str) -> anyhow::Result<Vec<PendingApproval>>;
+
+    /// Record a decision on a pending approval. Fails with [`ApprovalError`] when the
+    /// approval does not exist, was already decided or expired.
+    async fn decide(
+        &self,
+        thread_id: &ThreadId,
+        call_id: &str,
+        decision: ApprovalDecision,
+    ) -> anyhow::Result<PendingApproval>;
+
+    /// Delete an approval once the thread resumed.
+    async fn remove(&self, thread_id: &ThreadId, call_id: &str) -> anyhow::Result<()>;
+}
+
+/// Process-local approval store for tests and single-process deployments.
+#[derive(Debug, Default)]
+pub struct InMemoryApprovalStore {
+    approvals: Mutex<HashMap<(ThreadId, String), PendingApproval>>,
+}
+
+impl InMemoryApprovalStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(
+        &self,
+    ) -> anyhow::Result<std::sync::MutexGuard<'_, HashMap<(ThreadId, String), PendingApproval>>>
+    {
+        self.approvals
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Approval store poisoned"))
+    }
+}
+
+#[async_trait]
+impl ApprovalStore for InMemoryApprovalStore {
+    async fn put(&self, approval: &PendingApproval) -> anyhow::Result<()> {
+        self.lock()?.insert(
+            (approval.thread_id.clone(), approval.call_id().to_string()),
+            approval.clone(),
+        );
+        Ok(())
+    }
+
+    async fn get(
+        &self,
+        thread_id: &ThreadId,
+        call_id: &str,
+    ) -> anyhow::Result<Option<PendingApproval>> {
+        Ok(self
+            .lock()?
+            .get(&(thread_id.clone(), call_id.to_string()))
+            .cloned())
+    }
+
+    async fn list_for_approver(&self, approver: &str) -> anyhow::Result<Vec<PendingApproval>> {
+        let mut approvals: Vec<PendingApproval> = self
+            .lock()?
+            .values()
+            .filter(|a| a.approver.as_deref() == Some(approver) && a.is_pending())
+            .cloned()
+            .collect();
+        approvals.sort_by_key(|a| a.interrupt.created_at);
+        Ok(approvals)
+    }
+
+    async fn decide(
+        &self,
+        thread_id: &ThreadId,
+        call_id: &str,
+        decision: ApprovalDecision,
+    ) -> anyhow::Result<PendingApproval> {
+        let mut approvals = self.lock()?;
+        let key = (thread_id.clone(), call_id.to_string());
+        let Some(approval) = approvals.get_mut(&key) else {
+            return Err(ApprovalError::NotFound {
+                thread_id: key.0,
+                call_id: key.1,
+            }
+            .into());
+        };
+        if approval.decision.is_some() {
+            return Err(ApprovalError::AlreadyDecided {
+                thread_id: key.0,
+                call_id: key.1,
+            }
+            .into());
+        }
+        if approval.is_expired() {
+            return Err(ApprovalError::Expired {
+                thread_id: key.0,
+                call_id: key.1,
+            }
+            .into());
+        }
+        approval.decision = Some(decision);
+        Ok(approval.clone())
+    }
+
+    async fn remove(&self, thread_id: &ThreadId, call_id: &str) -> anyhow::Result<()> {
+        self.lock()?
+            .remove(&(thread_id.clone(), call_id.to_string()));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn approval(call_id: &str, ttl: Duration) -> PendingApproval {
+        let interrupt = HitlInterrupt::new("refund", json!({"amount": 250}), call_id, None);
+        PendingApproval::new("thread-1", interrupt, ttl).with_approver("finance")
+    }
+
+    #[tokio::test]
+    async fn decisions_are_recorded_once() {
+        let store = InMemoryApprovalStore::new();
+        store
+            .put(&approval("call-1", Duration::from_secs(60)))
+            .await
+            .unwrap();
+        store
+            .put(&approval("call-2", Duration::ZERO))
+            .await
+            .unwrap();
+
+        let listed = store.list_for_approver("finance").await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert!(store.list_for_approver("legal").await.unwrap().is_empty());
+
+        let thread = "thread-1".to_string();
+        let decided = store
+            .decide(
+                &thread,
+                "call-1",
+                ApprovalDecision::new(HitlAction::Accept, "ana"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(decided.decision.unwrap().decided_by, "ana");
+        assert!(store.list_for_approver("finance").await.unwrap().is_empty());
+
+        let again = store
+            .decide(
+                &thread,
+                "call-1",
+                ApprovalDecision::new(HitlAction::Accept, "bo"),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            again.downcast_ref(),
+            Some(ApprovalError::AlreadyDecided { .. })
+        ));
+        let expired = store
+            .decide(
+                &thread,
+                "call-2",
+                ApprovalDecision::new(HitlAction::Accept, "ana"),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            expired.downcast_ref(),
+            Some(ApprovalError::Expired { .. })
+        ));
+
+        store.remove(&thread, "call-1").await.unwrap();
+        assert!(store.get(&thread, "call-1").await.unwrap().is_none());
+    }
+}
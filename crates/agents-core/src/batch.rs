@@ -0,0 +1,71 @@
+//! Provider batch APIs for offline workloads.
+//!
+//! OpenAI and Anthropic accept a set of requests, process them asynchronously
+//! (usually within hours) and bill them at roughly half the interactive price.
+//! [`BatchModel`] exposes the submit / poll / retrieve cycle; the runtime's
+//! `BatchExecutor` drives it for a whole job.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::llm::{LlmRequest, LlmResponse};
+
+/// One request of a batch, identified by an id unique within the batch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchRequest {
+    pub custom_id: String,
+    pub request: LlmRequest,
+}
+
+impl BatchRequest {
+    pub fn new(custom_id: impl Into<String>, request: LlmRequest) -> Self {
+        Self {
+            custom_id: custom_id.into(),
+            request,
+        }
+    }
+}
+
+/// Outcome of one request of a finished batch.
+#[derive(Debug, Clone)]
+pub struct BatchResult {
+    pub custom_id: String,
+    /// The response, or the provider's error for this request
+    pub outcome: Result<LlmResponse, String>,
+}
+
+/// Processing state of a submitted batch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum BatchStatus {
+    /// Still being validated or processed
+    InProgress { completed: u32, total: u32 },
+    /// Results can be retrieved; individual requests may still have failed
+    Completed,
+    /// The provider rejected or abandoned the batch as a whole
+    Failed { reason: String },
+}
+
+impl BatchStatus {
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, Self::InProgress { .. })
+    }
+}
+
+/// A provider that can process requests through its batch API.
+#[async_trait]
+pub trait BatchModel: Send + Sync {
+    /// Largest number of requests accepted in a single batch.
+    fn max_batch_size(&self) -> usize {
+        10_000
+    }
+
+    /// Submit `requests` and return the provider's batch id.
+    async fn submit_batch(&self, requests: Vec<BatchRequest>) -> anyhow::Result<String>;
+
+    /// Current state of a submitted batch.
+    async fn batch_status(&self, batch_id: &str) -> anyhow::Result<BatchStatus>;
+
+    /// Results of a [completed](BatchStatus::Completed) batch, in no particular order.
+    async fn batch_results(&self, batch_id: &str) -> anyhow::Result<Vec<BatchResult>>;
+}
@@ -0,0 +1,197 @@
+//! Blob stores for binary artifacts produced by tools.
+//!
+//! Tools that create files a user should see rather than read (charts, exports,
+//! generated documents) store the bytes in a [`BlobStore`] and hand the model back
+//! the returned location, which the reply can then link or embed.
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+/// A stored artifact.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Blob {
+    pub bytes: Vec<u8>,
+    pub content_type: String,
+}
+
+/// Storage for binary artifacts (local files, object storage, ...).
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    /// Store `bytes` under `key` (e.g. `charts/3f2a.png`) and return its location
+    /// (path, URL, URI, ...). Storing an existing key replaces it.
+    async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> anyhow::Result<String>;
+
+    /// Fetch a stored artifact.
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Blob>>;
+}
+
+fn relative_key(key: &str) -> anyhow::Result<&str> {
+    let relative = key.trim_start_matches('/');
+    if relative.is_empty() || relative.split('/').any(|part| part == "..") {
+        anyhow::bail!("Blob key '{key}' must stay inside the store");
+    }
+    Ok(relative)
+}
+
+/// Stores each artifact as a file under a directory.
+///
+/// Set a [public URL](Self::with_public_url) when the directory is served over
+/// HTTP, so locations are links a web UI can display.
+#[derive(Debug, Clone)]
+pub struct FileBlobStore {
+    dir: PathBuf,
+    public_url: Option<String>,
+}
+
+impl FileBlobStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            public_url: None,
+        }
+    }
+
+    /// Report locations as `{url}/{key}` instead of file paths.
+    pub fn with_public_url(mut self, url: impl Into<String>) -> Self {
+        self.public_url = Some(url.into().trim_end_matches('/').to_string());
+        self
+    }
+}
+
+fn content_type_for(path: &std::path::Path) -> &'static str {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("png") => "image/png",
+        Some("svg") => "image/svg+xml",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("json") => "application/json",
+        Some("csv") => "text/csv",
+        Some("txt" | "md") => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+#[async_trait]
+impl BlobStore for FileBlobStore {
+    async fn put(&self, key: &str, bytes: Vec<u8>, _content_type: &str) -> anyhow::Result<String> {
+        let relative = relative_key(key)?;
+        let path = self.dir.join(relative);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, bytes).await?;
+        Ok(match &self.public_url {
+            Some(url) => format!("{url}/{relative}"),
+            None => path.display().to_string(),
+        })
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Blob>> {
+        let path = self.dir.join(relative_key(key)?);
+        match tokio::fs::read(&path).await {
+            Ok(bytes) => Ok(Some(Blob {
+                bytes,
+                content_type: content_type_for(&path).to_string(),
+            })),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// In-memory blob store for testing and development.
+#[derive(Debug, Default, Clone)]
+pub struct InMemoryBlobStore {
+    blobs: Arc<RwLock<HashMap<String, Blob>>>,
+}
+
+impl InMemoryBlobStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn keys(&self) -> Vec<String> {
+        self.blobs
+            .read()
+            .map(|blobs| blobs.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl BlobStore for InMemoryBlobStore {
+    async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> anyhow::Result<String> {
+        let relative = relative_key(key)?.to_string();
+        let location = format!("memory://{relative}");
+        self.blobs
+            .write()
+            .map_err(|_| anyhow::anyhow!("Failed to acquire write lock on in-memory blob store"))?
+            .insert(
+                relative,
+                Blob {
+                    bytes,
+                    content_type: content_type.to_string(),
+                },
+            );
+        Ok(location)
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Blob>> {
+        let relative = relative_key(key)?;
+        Ok(self
+            .blobs
+            .read()
+            .map_err(|_| anyhow::anyhow!("Failed to acquire read lock on in-memory blob store"))?
+            .get(relative)
+            .cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn file_store_round_trips_and_reports_public_urls() {
+        let dir = std::env::temp_dir().join(format!("agents-blob-{}", std::process::id()));
+        let store = FileBlobStore::new(&dir);
+        let location = store
+            .put("charts/a.png", vec![1, 2, 3], "image/png")
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&location).unwrap(), vec![1, 2, 3]);
+        let blob = store.get("charts/a.png").await.unwrap().unwrap();
+        assert_eq!(blob.content_type, "image/png");
+        assert!(store.get("charts/missing.png").await.unwrap().is_none());
+        assert!(store
+            .put("../escape.png", vec![], "image/png")
+            .await
+            .is_err());
+
+        let store = FileBlobStore::new(&dir).with_public_url("https://cdn.example.com/");
+        assert_eq!(
+            store
+                .put("b.svg", b"<svg/>".to_vec(), "image/svg+xml")
+                .await
+                .unwrap(),
+            "https://cdn.example.com/b.svg"
+        );
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn memory_store_keeps_content_type() {
+        let store = InMemoryBlobStore::new();
+        assert_eq!(
+            store
+                .put("/x.svg", b"<svg/>".to_vec(), "image/svg+xml")
+                .await
+                .unwrap(),
+            "memory://x.svg"
+        );
+        let blob = store.get("x.svg").await.unwrap().unwrap();
+        assert_eq!(blob.content_type, "image/svg+xml");
+        assert_eq!(store.keys(), vec!["x.svg".to_string()]);
+    }
+}
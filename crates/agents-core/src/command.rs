@@ -43,7 +43,7 @@ impl Command {
         }
         if let Some(files) = self.state.files {
             for (path, content) in files {
-                snapshot.files.insert(path, content);
+                snapshot.insert_file(path, content);
             }
         }
         if let Some(scratch) = self.state.scratchpad {
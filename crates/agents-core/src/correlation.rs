@@ -0,0 +1,40 @@
+//! Correlation ids tying one request's events, logs, provider calls and checkpoints
+//! together.
+//!
+//! A run executes inside a correlation scope: the id passed in
+//! [`MessageMetadata::correlation_id`](crate::messaging::MessageMetadata::correlation_id),
+//! the id of the run that invoked it (for sub-agents), or the run id. Components
+//! working on behalf of the run read it with [`current_correlation_id`].
+
+use std::future::Future;
+
+tokio::task_local! {
+    static CURRENT: String;
+}
+
+/// The correlation id of the run executing on this task, if any.
+pub fn current_correlation_id() -> Option<String> {
+    CURRENT.try_with(|id| id.clone()).ok()
+}
+
+/// Run `future` with `id` as the [current](current_correlation_id) correlation id.
+pub async fn with_correlation_id<F: Future>(id: impl Into<String>, future: F) -> F::Output {
+    CURRENT.scope(id.into(), future).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn scope_is_visible_to_nested_futures_only() {
+        assert!(current_correlation_id().is_none());
+        let seen = with_correlation_id("req-1", async {
+            tokio::task::yield_now().await;
+            current_correlation_id()
+        })
+        .await;
+        assert_eq!(seen.as_deref(), Some("req-1"));
+        assert!(current_correlation_id().is_none());
+    }
+}
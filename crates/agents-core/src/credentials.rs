@@ -0,0 +1,108 @@
+//! Provider credentials scoped to a single run.
+//!
+//! Multi-tenant services where customers bring their own provider keys run each
+//! request inside a credentials scope: model providers called on behalf of the run
+//! use the key from [`current_api_key`] instead of the one they were built with.
+//! Keys live only in the scope of the run's task. They are never written to agent
+//! state, messages or events, and [`ApiKey`] redacts itself when formatted.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+
+tokio::task_local! {
+    static CURRENT: ProviderCredentials;
+}
+
+/// An API key that doesn't show up in logs. Use [`ApiKey::expose`] to read it.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ApiKey(String);
+
+impl ApiKey {
+    pub fn new(key: impl Into<String>) -> Self {
+        Self(key.into())
+    }
+
+    /// The key itself, for authenticating a request.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ApiKey(<redacted>)")
+    }
+}
+
+/// API keys per model provider ("openai", "anthropic", "gemini", ...).
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct ProviderCredentials {
+    keys: HashMap<String, ApiKey>,
+}
+
+impl ProviderCredentials {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_api_key(mut self, provider: impl Into<String>, key: impl Into<String>) -> Self {
+        self.keys.insert(provider.into(), ApiKey::new(key));
+        self
+    }
+
+    pub fn api_key(&self, provider: &str) -> Option<&ApiKey> {
+        self.keys.get(provider)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+impl fmt::Debug for ProviderCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut providers: Vec<_> = self.keys.keys().collect();
+        providers.sort();
+        f.debug_struct("ProviderCredentials")
+            .field("providers", &providers)
+            .finish()
+    }
+}
+
+/// The key for `provider` in the credentials scope of the current run, if any.
+pub fn current_api_key(provider: &str) -> Option<ApiKey> {
+    CURRENT
+        .try_with(|credentials| credentials.api_key(provider).cloned())
+        .ok()
+        .flatten()
+}
+
+/// Run `future` with `credentials` as the [current](current_api_key) credentials.
+pub async fn with_provider_credentials<F: Future>(
+    credentials: ProviderCredentials,
+    future: F,
+) -> F::Output {
+    CURRENT.scope(credentials, future).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn keys_are_scoped_to_the_run_and_redacted() {
+        let credentials = ProviderCredentials::new().with_api_key("openai", "sk-customer");
+        assert!(!format!("{credentials:?}").contains("sk-customer"));
+
+        let seen = with_provider_credentials(credentials, async {
+            tokio::task::yield_now().await;
+            (current_api_key("openai"), current_api_key("anthropic"))
+        })
+        .await;
+        assert_eq!(seen.0.as_ref().map(ApiKey::expose), Some("sk-customer"));
+        assert!(seen.1.is_none());
+        assert_eq!(format!("{:?}", seen.0.unwrap()), "ApiKey(<redacted>)");
+        assert!(current_api_key("openai").is_none());
+    }
+}
@@ -0,0 +1,138 @@
+//! Dead letters: runs that failed, kept for inspection and replay.
+//!
+//! When a run errors out, the agent records a [`DeadLetter`] holding the input, the
+//! state the run started from, the events emitted before the failure and the error
+//! in the configured [`DeadLetterStore`]. Operators list the dead letters to find
+//! out what went wrong and replay them once the cause is fixed.
+
+use crate::events::AgentEvent;
+use crate::messaging::AgentMessage;
+use crate::persistence::ThreadId;
+use crate::state::AgentStateSnapshot;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// A failed run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub id: String,
+    /// Name of the agent the run failed on
+    pub agent: String,
+    /// Run id, as reported by `DeepAgent::last_run_id`
+    pub run_id: String,
+    /// Thread the run was for; `None` for runs given their state directly
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread_id: Option<ThreadId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    pub input: AgentMessage,
+    /// State the run started from
+    pub state: AgentStateSnapshot,
+    /// Events emitted before the run failed
+    #[serde(default)]
+    pub events: Vec<AgentEvent>,
+    pub error: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+impl DeadLetter {
+    pub fn new(
+        agent: impl Into<String>,
+        run_id: impl Into<String>,
+        input: AgentMessage,
+        state: AgentStateSnapshot,
+        error: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            agent: agent.into(),
+            run_id: run_id.into(),
+            thread_id: None,
+            correlation_id: None,
+            input,
+            state,
+            events: Vec::new(),
+            error: error.into(),
+            failed_at: Utc::now(),
+        }
+    }
+
+    pub fn with_thread_id(mut self, thread_id: impl Into<ThreadId>) -> Self {
+        self.thread_id = Some(thread_id.into());
+        self
+    }
+
+    pub fn with_correlation_id(mut self, correlation_id: impl Into<String>) -> Self {
+        self.correlation_id = Some(correlation_id.into());
+        self
+    }
+
+    pub fn with_events(mut self, events: Vec<AgentEvent>) -> Self {
+        self.events = events;
+        self
+    }
+}
+
+/// Storage for dead letters (in-memory, Postgres, S3, ...).
+#[async_trait]
+pub trait DeadLetterStore: Send + Sync {
+    /// Insert or replace a dead letter.
+    async fn put(&self, letter: &DeadLetter) -> anyhow::Result<()>;
+
+    async fn get(&self, id: &str) -> anyhow::Result<Option<DeadLetter>>;
+
+    /// Dead letters of `agent`, most recent first.
+    async fn list(&self, agent: &str) -> anyhow::Result<Vec<DeadLetter>>;
+
+    /// Delete a dead letter once it was handled or replayed.
+    async fn remove(&self, id: &str) -> anyhow::Result<()>;
+}
+
+/// Process-local dead-letter store for tests and single-process deployments.
+#[derive(Debug, Default)]
+pub struct InMemoryDeadLetterStore {
+    letters: Mutex<HashMap<String, DeadLetter>>,
+}
+
+impl InMemoryDeadLetterStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> anyhow::Result<std::sync::MutexGuard<'_, HashMap<String, DeadLetter>>> {
+        self.letters
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Dead-letter store poisoned"))
+    }
+}
+
+#[async_trait]
+impl DeadLetterStore for InMemoryDeadLetterStore {
+    async fn put(&self, letter: &DeadLetter) -> anyhow::Result<()> {
+        self.lock()?.insert(letter.id.clone(), letter.clone());
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> anyhow::Result<Option<DeadLetter>> {
+        Ok(self.lock()?.get(id).cloned())
+    }
+
+    async fn list(&self, agent: &str) -> anyhow::Result<Vec<DeadLetter>> {
+        let mut letters: Vec<DeadLetter> = self
+            .lock()?
+            .values()
+            .filter(|letter| letter.agent == agent)
+            .cloned()
+            .collect();
+        letters.sort_by_key(|letter| std::cmp::Reverse(letter.failed_at));
+        Ok(letters)
+    }
+
+    async fn remove(&self, id: &str) -> anyhow::Result<()> {
+        self.lock()?.remove(id);
+        Ok(())
+    }
+}
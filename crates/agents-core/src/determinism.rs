@@ -0,0 +1,101 @@
+//! Deterministic runs for debugging.
+//!
+//! Inside [`with_seed`], model requests carry a fixed sampling seed and
+//! [`next_uuid`] draws from a generator seeded with it; inside [`with_frozen_time`],
+//! [`now`] reports a fixed instant. Two runs of the same input then render the same
+//! prompts and ids, so their traces can be compared line by line.
+
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+tokio::task_local! {
+    static SEEDED: Arc<SeededRng>;
+    static FROZEN_NOW: DateTime<Utc>;
+}
+
+/// SplitMix64: small, fast and good enough for ids; not for cryptography.
+struct SeededRng {
+    seed: u64,
+    state: Mutex<u64>,
+}
+
+impl SeededRng {
+    fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            state: Mutex::new(seed),
+        }
+    }
+
+    fn next_u64(&self) -> u64 {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+/// Run `future` with a generator seeded with `seed`, restarted on each call.
+pub async fn with_seed<F: Future>(seed: u64, future: F) -> F::Output {
+    SEEDED.scope(Arc::new(SeededRng::new(seed)), future).await
+}
+
+/// Seed of the deterministic run in progress, if any.
+pub fn current_seed() -> Option<u64> {
+    SEEDED.try_with(|rng| rng.seed).ok()
+}
+
+/// A random (v4) UUID, drawn from the seeded generator in a deterministic run.
+pub fn next_uuid() -> Uuid {
+    match SEEDED.try_with(|rng| (rng.next_u64(), rng.next_u64())) {
+        Ok((high, low)) => {
+            let mut bytes = [0u8; 16];
+            bytes[..8].copy_from_slice(&high.to_be_bytes());
+            bytes[8..].copy_from_slice(&low.to_be_bytes());
+            uuid::Builder::from_random_bytes(bytes).into_uuid()
+        }
+        Err(_) => Uuid::new_v4(),
+    }
+}
+
+/// Run `future` with [`now`] reporting `now` instead of the current time.
+pub async fn with_frozen_time<F: Future>(now: DateTime<Utc>, future: F) -> F::Output {
+    FROZEN_NOW.scope(now, future).await
+}
+
+/// The current time, or the frozen time inside [`with_frozen_time`].
+pub fn now() -> DateTime<Utc> {
+    FROZEN_NOW
+        .try_with(|now| *now)
+        .unwrap_or_else(|_| Utc::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[tokio::test]
+    async fn seeded_runs_repeat_their_ids() {
+        let ids = || async { (next_uuid(), next_uuid(), current_seed()) };
+        let first = with_seed(7, ids()).await;
+        let second = with_seed(7, ids()).await;
+        assert_eq!(first, second);
+        assert_ne!(first.0, first.1);
+        assert_eq!(first.2, Some(7));
+        assert_eq!(first.0.get_version_num(), 4);
+        assert_ne!(with_seed(8, ids()).await.0, first.0);
+        assert_eq!(current_seed(), None);
+    }
+
+    #[tokio::test]
+    async fn frozen_time_is_reported_by_now() {
+        let frozen = Utc.with_ymd_and_hms(2026, 1, 1, 9, 0, 0).unwrap();
+        assert_eq!(with_frozen_time(frozen, async { now() }).await, frozen);
+        assert_ne!(now(), frozen);
+    }
+}
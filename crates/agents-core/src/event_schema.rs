@@ -0,0 +1,229 @@
+//! Versioned JSON wire format of [`AgentEvent`]
+//!
+//! Events leave the process through broadcasters, job queues and SSE streams, and
+//! are decoded by consumers that upgrade on their own schedule. On the wire an event
+//! is its JSON object with two fixed fields: `event_type`, naming the variant, and
+//! `schema_version`, the [`EVENT_SCHEMA_VERSION`] it was written with.
+//!
+//! ```json
+//! {"schema_version":1,"event_type":"tool_started","metadata":{...},"tool_name":"search","input_summary":"..."}
+//! ```
+//!
+//! # Compatibility
+//!
+//! Within a schema version, changes are additive only:
+//!
+//! - new optional fields may be added to any event; consumers must ignore fields
+//!   they do not know;
+//! - new event types may be added; [`EventEnvelope::from_json`] reports them as
+//!   [`EventDecodeError::UnknownEventType`] so consumers can skip them;
+//! - fields are never removed, renamed or retyped, and `event_type` names never change.
+//!
+//! Anything else bumps the schema version. Decoding rejects versions newer than the
+//! one this build writes, and reads events without a version as version 1.
+//!
+//! With the `schema` feature, [`event_json_schema`] exports a JSON Schema of the
+//! envelope for generating consumer types.
+
+use crate::events::AgentEvent;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Version of the event wire format written by this build.
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// `event_type` of every event in [`EVENT_SCHEMA_VERSION`].
+pub const EVENT_TYPES: &[&str] = &[
+    "agent_started",
+    "agent_completed",
+    "tool_call_started",
+    "tool_started",
+    "tool_completed",
+    "tool_failed",
+    "sub_agent_started",
+    "sub_agent_completed",
+    "todos_updated",
+    "state_checkpointed",
+    "planning_complete",
+    "token_usage",
+    "streaming_token",
+    "deprecated_tool_called",
+    "guardrail_intervened",
+    "credential_rotated",
+    "hitl_decided",
+    "memory_blocked",
+    "memory_purged",
+    "state_size_exceeded",
+];
+
+/// An event as it appears on the wire.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EventEnvelope {
+    /// Wire format version the event was written with
+    pub schema_version: u32,
+    #[serde(flatten)]
+    pub event: AgentEvent,
+}
+
+/// Borrowing counterpart of [`EventEnvelope`] used for encoding.
+#[derive(Serialize)]
+struct EventEnvelopeRef<'a> {
+    schema_version: u32,
+    #[serde(flatten)]
+    event: &'a AgentEvent,
+}
+
+/// Why an event could not be decoded.
+#[derive(Debug, Error)]
+pub enum EventDecodeError {
+    #[error("event schema version {found} is newer than the supported version {supported}")]
+    UnsupportedVersion { found: u64, supported: u32 },
+    /// The event type was added after this build; safe to skip.
+    #[error("unknown event type `{0}`")]
+    UnknownEventType(String),
+    #[error("malformed event: {0}")]
+    Malformed(#[from] serde_json::Error),
+}
+
+impl EventEnvelope {
+    /// Wrap `event` with the current schema version.
+    pub fn new(event: AgentEvent) -> Self {
+        Self {
+            schema_version: EVENT_SCHEMA_VERSION,
+            event,
+        }
+    }
+
+    /// Decode an event written by any build up to the current schema version.
+    pub fn from_json(json: &str) -> Result<Self, EventDecodeError> {
+        let mut value: serde_json::Value = serde_json::from_str(json)?;
+        let object = value
+            .as_object_mut()
+            .ok_or_else(|| malformed("event is not a JSON object"))?;
+
+        let version = match object.get("schema_version") {
+            None => 1,
+            Some(version) => version
+                .as_u64()
+                .ok_or_else(|| malformed("schema_version is not a number"))?,
+        };
+        if version > u64::from(EVENT_SCHEMA_VERSION) {
+            return Err(EventDecodeError::UnsupportedVersion {
+                found: version,
+                supported: EVENT_SCHEMA_VERSION,
+            });
+        }
+        if let Some(event_type) = object.get("event_type").and_then(|t| t.as_str()) {
+            if !EVENT_TYPES.contains(&event_type) {
+                return Err(EventDecodeError::UnknownEventType(event_type.to_string()));
+            }
+        }
+
+        object.remove("schema_version");
+        let event = serde_json::from_value(value)?;
+        Ok(Self {
+            schema_version: version as u32,
+            event,
+        })
+    }
+}
+
+fn malformed(message: &str) -> EventDecodeError {
+    EventDecodeError::Malformed(serde::de::Error::custom(message))
+}
+
+impl AgentEvent {
+    /// The event in the versioned wire format, see the [module docs](crate::event_schema).
+    pub fn to_wire_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(&EventEnvelopeRef {
+            schema_version: EVENT_SCHEMA_VERSION,
+            event: self,
+        })
+    }
+}
+
+/// JSON Schema of [`EventEnvelope`], for generating consumer types.
+#[cfg(feature = "schema")]
+pub fn event_json_schema() -> serde_json::Value {
+    schemars::schema_for!(EventEnvelope).to_value()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{EventMetadata, ToolStartedEvent};
+
+    fn tool_started() -> AgentEvent {
+        AgentEvent::ToolStarted(ToolStartedEvent {
+            metadata: EventMetadata::new("thread-1".into(), "run-1".into(), None),
+            tool_name: "search".into(),
+            input_summary: "rust".into(),
+        })
+    }
+
+    #[test]
+    fn events_round_trip_with_their_version() {
+        let event = tool_started();
+        let json = event.to_wire_json().unwrap();
+        assert!(json.starts_with(r#"{"schema_version":1,"event_type":"tool_started","#));
+
+        let envelope = EventEnvelope::from_json(&json).unwrap();
+        assert_eq!(envelope.schema_version, EVENT_SCHEMA_VERSION);
+        assert_eq!(
+            serde_json::to_value(&envelope.event).unwrap(),
+            serde_json::to_value(&event).unwrap()
+        );
+        assert_eq!(serde_json::to_string(&envelope).unwrap(), json);
+    }
+
+    #[test]
+    fn unversioned_unknown_and_future_events() {
+        // Events written before the envelope existed read as version 1
+        let legacy = serde_json::to_string(&tool_started()).unwrap();
+        assert_eq!(EventEnvelope::from_json(&legacy).unwrap().schema_version, 1);
+
+        // Unknown fields are ignored
+        let extended = legacy.replacen('{', r#"{"added_later":true,"#, 1);
+        assert!(EventEnvelope::from_json(&extended).is_ok());
+
+        let unknown = r#"{"schema_version":1,"event_type":"run_paused","metadata":{}}"#;
+        assert!(matches!(
+            EventEnvelope::from_json(unknown),
+            Err(EventDecodeError::UnknownEventType(t)) if t == "run_paused"
+        ));
+
+        let future = legacy.replacen('{', r#"{"schema_version":2,"#, 1);
+        assert!(matches!(
+            EventEnvelope::from_json(&future),
+            Err(EventDecodeError::UnsupportedVersion { found: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn every_event_type_is_listed() {
+        // The names `AgentEvent::event_type_name` returns
+        let source = include_str!("events.rs");
+        let names: Vec<&str> = source
+            .lines()
+            .filter_map(|line| line.trim().split_once("(_) => \""))
+            .filter(|(variant, _)| variant.starts_with("AgentEvent::"))
+            .map(|(_, name)| name.trim_end_matches(['"', ',']))
+            .collect();
+        assert_eq!(names, EVENT_TYPES);
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn schema_describes_every_event_type() {
+        let schema = event_json_schema().to_string();
+        for event_type in EVENT_TYPES {
+            assert!(
+                schema.contains(&format!("\"{}\"", event_type)),
+                "{}",
+                event_type
+            );
+        }
+        assert!(schema.contains("schema_version"));
+    }
+}
@@ -1,15 +1,22 @@
 //! Event system for agent lifecycle tracking and progress broadcasting
 
+use crate::experiment::{current_experiment, ExperimentAssignment};
+use crate::guardrail::GuardrailSource;
+use crate::hitl::HitlDecisionKind;
 use crate::state::TodoItem;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use tokio::sync::Notify;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(tag = "event_type", rename_all = "snake_case")]
 pub enum AgentEvent {
     AgentStarted(AgentStartedEvent),
     AgentCompleted(AgentCompletedEvent),
+    ToolCallStarted(ToolCallStartedEvent),
     ToolStarted(ToolStartedEvent),
     ToolCompleted(ToolCompletedEvent),
     ToolFailed(ToolFailedEvent),
@@ -20,6 +27,13 @@ pub enum AgentEvent {
     PlanningComplete(PlanningCompleteEvent),
     TokenUsage(TokenUsageEvent),
     StreamingToken(StreamingTokenEvent),
+    DeprecatedToolCalled(DeprecatedToolCalledEvent),
+    GuardrailIntervened(GuardrailIntervenedEvent),
+    CredentialRotated(CredentialRotatedEvent),
+    HitlDecided(HitlDecidedEvent),
+    MemoryBlocked(MemoryBlockedEvent),
+    MemoryPurged(MemoryPurgedEvent),
+    StateSizeExceeded(StateSizeExceededEvent),
 }
 
 impl AgentEvent {
@@ -27,6 +41,7 @@ impl AgentEvent {
         match self {
             AgentEvent::AgentStarted(_) => "agent_started",
             AgentEvent::AgentCompleted(_) => "agent_completed",
+            AgentEvent::ToolCallStarted(_) => "tool_call_started",
             AgentEvent::ToolStarted(_) => "tool_started",
             AgentEvent::ToolCompleted(_) => "tool_completed",
             AgentEvent::ToolFailed(_) => "tool_failed",
@@ -37,6 +52,13 @@ impl AgentEvent {
             AgentEvent::PlanningComplete(_) => "planning_complete",
             AgentEvent::TokenUsage(_) => "token_usage",
             AgentEvent::StreamingToken(_) => "streaming_token",
+            AgentEvent::DeprecatedToolCalled(_) => "deprecated_tool_called",
+            AgentEvent::GuardrailIntervened(_) => "guardrail_intervened",
+            AgentEvent::CredentialRotated(_) => "credential_rotated",
+            AgentEvent::HitlDecided(_) => "hitl_decided",
+            AgentEvent::MemoryBlocked(_) => "memory_blocked",
+            AgentEvent::MemoryPurged(_) => "memory_purged",
+            AgentEvent::StateSizeExceeded(_) => "state_size_exceeded",
         }
     }
 
@@ -44,6 +66,7 @@ impl AgentEvent {
         match self {
             AgentEvent::AgentStarted(e) => &e.metadata,
             AgentEvent::AgentCompleted(e) => &e.metadata,
+            AgentEvent::ToolCallStarted(e) => &e.metadata,
             AgentEvent::ToolStarted(e) => &e.metadata,
             AgentEvent::ToolCompleted(e) => &e.metadata,
             AgentEvent::ToolFailed(e) => &e.metadata,
@@ -54,17 +77,28 @@ impl AgentEvent {
             AgentEvent::PlanningComplete(e) => &e.metadata,
             AgentEvent::TokenUsage(e) => &e.metadata,
             AgentEvent::StreamingToken(e) => &e.metadata,
+            AgentEvent::DeprecatedToolCalled(e) => &e.metadata,
+            AgentEvent::GuardrailIntervened(e) => &e.metadata,
+            AgentEvent::CredentialRotated(e) => &e.metadata,
+            AgentEvent::HitlDecided(e) => &e.metadata,
+            AgentEvent::MemoryBlocked(e) => &e.metadata,
+            AgentEvent::MemoryPurged(e) => &e.metadata,
+            AgentEvent::StateSizeExceeded(e) => &e.metadata,
         }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct EventMetadata {
     pub thread_id: String,
     pub correlation_id: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub customer_id: Option<String>,
     pub timestamp: String,
+    /// Experiment variant of the run that emitted the event
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub experiment: Option<ExperimentAssignment>,
 }
 
 impl EventMetadata {
@@ -74,11 +108,20 @@ impl EventMetadata {
             correlation_id,
             customer_id,
             timestamp: chrono::Utc::now().to_rfc3339(),
+            experiment: current_experiment(),
         }
     }
+
+    /// Metadata carrying the current run's correlation id, or a fresh one outside a run.
+    pub fn for_current_run(thread_id: impl Into<String>) -> Self {
+        let correlation_id = crate::correlation::current_correlation_id()
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        Self::new(thread_id.into(), correlation_id, None)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AgentStartedEvent {
     pub metadata: EventMetadata,
     pub agent_name: String,
@@ -86,6 +129,7 @@ pub struct AgentStartedEvent {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AgentCompletedEvent {
     pub metadata: EventMetadata,
     pub agent_name: String,
@@ -94,7 +138,20 @@ pub struct AgentCompletedEvent {
     pub response: String,         // Full response text
 }
 
+/// Emitted while a response streams, as soon as the model starts a tool call and
+/// before its arguments are complete
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ToolCallStartedEvent {
+    pub metadata: EventMetadata,
+    pub agent_name: String,
+    pub tool_name: String,
+    /// Arguments received so far
+    pub partial_args: serde_json::Value,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ToolStartedEvent {
     pub metadata: EventMetadata,
     pub tool_name: String,
@@ -102,6 +159,7 @@ pub struct ToolStartedEvent {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ToolCompletedEvent {
     pub metadata: EventMetadata,
     pub tool_name: String,
@@ -111,6 +169,7 @@ pub struct ToolCompletedEvent {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ToolFailedEvent {
     pub metadata: EventMetadata,
     pub tool_name: String,
@@ -121,6 +180,7 @@ pub struct ToolFailedEvent {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct SubAgentStartedEvent {
     pub metadata: EventMetadata,
     pub agent_name: String,
@@ -129,6 +189,7 @@ pub struct SubAgentStartedEvent {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct SubAgentCompletedEvent {
     pub metadata: EventMetadata,
     pub agent_name: String,
@@ -137,6 +198,7 @@ pub struct SubAgentCompletedEvent {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct TodosUpdatedEvent {
     pub metadata: EventMetadata,
     pub todos: Vec<TodoItem>,
@@ -147,6 +209,7 @@ pub struct TodosUpdatedEvent {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct StateCheckpointedEvent {
     pub metadata: EventMetadata,
     pub checkpoint_id: String,
@@ -154,6 +217,7 @@ pub struct StateCheckpointedEvent {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct PlanningCompleteEvent {
     pub metadata: EventMetadata,
     pub action_type: String,
@@ -161,19 +225,172 @@ pub struct PlanningCompleteEvent {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct TokenUsageEvent {
     pub metadata: EventMetadata,
     pub usage: TokenUsage,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct StreamingTokenEvent {
     pub metadata: EventMetadata,
     pub agent_name: String,
     pub token: String,
+    /// Position of the chunk in the streamed run; broadcasts may arrive out of order
+    #[serde(default)]
+    pub sequence: u64,
+}
+
+/// Emitted when the model invokes a tool whose schema is marked deprecated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DeprecatedToolCalledEvent {
+    pub metadata: EventMetadata,
+    pub tool_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+    pub notice: String,
+}
+
+/// Emitted when a guardrail masks or blocks model input or output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GuardrailIntervenedEvent {
+    pub metadata: EventMetadata,
+    pub guardrail: String,
+    pub source: GuardrailSource,
+    /// Whether the text was blocked rather than masked
+    pub blocked: bool,
+    /// Policies that matched (topics, filters, PII types, ...)
+    pub reasons: Vec<String>,
+}
+
+/// Why a model picked up a new credential
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialRotationReason {
+    /// The cached credential reached its TTL and the store had a new one
+    Expired,
+    /// The provider rejected the credential (HTTP 401)
+    Rejected,
+}
+
+/// Emitted when a model switches to a rotated credential. Never carries the secret.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CredentialRotatedEvent {
+    pub metadata: EventMetadata,
+    /// Model provider, e.g. "openai"
+    pub provider: String,
+    /// Key of the secret in the secrets provider
+    pub secret_key: String,
+    pub reason: CredentialRotationReason,
+}
+
+/// Emitted when a reviewer decides on a tool call awaiting approval.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HitlDecidedEvent {
+    pub metadata: EventMetadata,
+    pub tool_name: String,
+    pub call_id: String,
+    pub action: HitlDecisionKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decided_by: Option<String>,
+    pub original_args: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edited_tool_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edited_args: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Why a memory policy refused to store a fact
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryBlockReason {
+    /// The fact's category must never be stored
+    Prohibited,
+    /// The category needs consent the user has not given
+    NoConsent,
+    /// The fact could not be classified
+    Unclassified,
+}
+
+/// Emitted when a memory policy refuses to store a fact about a user. Never
+/// carries the fact's value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MemoryBlockedEvent {
+    pub metadata: EventMetadata,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    pub key: String,
+    pub category: String,
+    pub reason: MemoryBlockReason,
+}
+
+/// Why stored memories were deleted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryPurgeReason {
+    /// The retention period of their category ended
+    Expired,
+    /// The user withdrew consent to their category
+    ConsentWithdrawn,
+    /// Their category was prohibited after they were stored
+    Prohibited,
+    /// All data of the user was erased
+    Erased,
+}
+
+/// Emitted when memories about a user are deleted by a memory policy or erasure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MemoryPurgedEvent {
+    pub metadata: EventMetadata,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    /// Keys of the deleted memories
+    pub keys: Vec<String>,
+    pub reason: MemoryPurgeReason,
+}
+
+/// Measure of a thread's state that a size threshold applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum StateSizeMetric {
+    /// Bytes of the thread's conversation messages
+    MessageBytes,
+    /// Bytes of the files in the thread's virtual filesystem
+    FileBytes,
+    /// Number of tool output artifacts kept as files
+    Artifacts,
+    /// Bytes of the serialized state
+    TotalBytes,
+}
+
+/// Emitted when a checkpointed thread's state grows past a configured size threshold
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StateSizeExceededEvent {
+    pub metadata: EventMetadata,
+    pub thread_id: String,
+    pub metric: StateSizeMetric,
+    pub value: usize,
+    pub threshold: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct TokenUsage {
     /// Number of input tokens
     pub input_tokens: u32,
@@ -191,6 +408,23 @@ pub struct TokenUsage {
     pub duration_ms: u64,
     /// Timestamp of the request
     pub timestamp: String,
+    /// Input tokens served from the provider's prompt cache (part of `input_tokens`)
+    #[serde(default)]
+    pub cache_read_tokens: u32,
+    /// Input tokens written to the provider's prompt cache (part of `input_tokens`)
+    #[serde(default)]
+    pub cache_write_tokens: u32,
+    /// Agent or sub-agent that made the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_name: Option<String>,
+    #[serde(default)]
+    pub purpose: crate::llm::UsagePurpose,
+    /// Experiment variant of the run that made the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub experiment: Option<ExperimentAssignment>,
+    /// Tenant the run that made the request was served for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
 }
 
 impl TokenUsage {
@@ -215,6 +449,47 @@ impl TokenUsage {
             model,
             duration_ms,
             timestamp: chrono::Utc::now().to_rfc3339(),
+            cache_read_tokens: 0,
+            cache_write_tokens: 0,
+            agent_name: None,
+            purpose: crate::llm::UsagePurpose::default(),
+            experiment: None,
+            tenant: None,
+        }
+    }
+
+    pub fn with_cache_tokens(mut self, read: u32, write: u32) -> Self {
+        self.cache_read_tokens = read;
+        self.cache_write_tokens = write;
+        self
+    }
+
+    pub fn with_agent_name(mut self, agent_name: impl Into<String>) -> Self {
+        self.agent_name = Some(agent_name.into());
+        self
+    }
+
+    pub fn with_purpose(mut self, purpose: crate::llm::UsagePurpose) -> Self {
+        self.purpose = purpose;
+        self
+    }
+
+    pub fn with_experiment(mut self, experiment: ExperimentAssignment) -> Self {
+        self.experiment = Some(experiment);
+        self
+    }
+
+    pub fn with_tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tenant = Some(tenant.into());
+        self
+    }
+
+    /// Cost-attribution bucket: `"summarization"` for summarization calls, otherwise
+    /// the agent name (`""` outside an agent run).
+    pub fn component(&self) -> &str {
+        match self.purpose {
+            crate::llm::UsagePurpose::Summarization => "summarization",
+            crate::llm::UsagePurpose::Reasoning => self.agent_name.as_deref().unwrap_or_default(),
         }
     }
 }
@@ -236,12 +511,32 @@ pub trait EventBroadcaster: Send + Sync {
 
 pub struct EventDispatcher {
     broadcasters: std::sync::RwLock<Vec<Arc<dyn EventBroadcaster>>>,
+    in_flight: Arc<InFlightBroadcasts>,
+    /// Events held back for an outbox instead of being broadcast, when enabled
+    staged: std::sync::Mutex<Option<Vec<AgentEvent>>>,
+}
+
+/// Counts broadcasts that were spawned but have not completed yet.
+#[derive(Default)]
+struct InFlightBroadcasts {
+    count: AtomicUsize,
+    idle: Notify,
+}
+
+impl InFlightBroadcasts {
+    fn finish(&self) {
+        if self.count.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.idle.notify_waiters();
+        }
+    }
 }
 
 impl EventDispatcher {
     pub fn new() -> Self {
         Self {
             broadcasters: std::sync::RwLock::new(Vec::new()),
+            in_flight: Arc::new(InFlightBroadcasts::default()),
+            staged: std::sync::Mutex::new(None),
         }
     }
 
@@ -255,6 +550,23 @@ impl EventDispatcher {
     }
 
     pub async fn dispatch(&self, event: AgentEvent) {
+        self.emit(event);
+    }
+
+    /// Spawn the broadcasts for `event` without waiting for them.
+    ///
+    /// Broadcasts started here are tracked, so [`flush`](Self::flush) can wait for
+    /// them before the process exits.
+    pub fn emit(&self, event: AgentEvent) {
+        if !matches!(event, AgentEvent::StreamingToken(_)) {
+            if let Ok(mut staged) = self.staged.lock() {
+                if let Some(staged) = staged.as_mut() {
+                    staged.push(event);
+                    return;
+                }
+            }
+        }
+
         let broadcasters = {
             if let Ok(guard) = self.broadcasters.read() {
                 guard.clone()
@@ -266,11 +578,14 @@ impl EventDispatcher {
 
         for broadcaster in broadcasters {
             let event_clone = event.clone();
+            let in_flight = self.in_flight.clone();
+            in_flight.count.fetch_add(1, Ordering::AcqRel);
             tokio::spawn(async move {
                 // Skip streaming tokens for broadcasters that don't support them
                 if matches!(event_clone, AgentEvent::StreamingToken(_))
                     && !broadcaster.supports_streaming()
                 {
+                    in_flight.finish();
                     return;
                 }
 
@@ -283,9 +598,102 @@ impl EventDispatcher {
                         );
                     }
                 }
+                in_flight.finish();
             });
         }
     }
+
+    /// Hold emitted events back instead of broadcasting them.
+    ///
+    /// Used with an event outbox: staged events are persisted together with the
+    /// state and delivered later by the outbox relay via [`deliver`](Self::deliver).
+    /// Streaming tokens are never staged.
+    pub fn enable_staging(&self) {
+        if let Ok(mut staged) = self.staged.lock() {
+            staged.get_or_insert_with(Vec::new);
+        }
+    }
+
+    pub fn is_staging(&self) -> bool {
+        self.staged.lock().map(|s| s.is_some()).unwrap_or(false)
+    }
+
+    /// Drain the events staged so far.
+    pub fn take_staged(&self) -> Vec<AgentEvent> {
+        self.staged
+            .lock()
+            .ok()
+            .and_then(|mut staged| staged.as_mut().map(std::mem::take))
+            .unwrap_or_default()
+    }
+
+    /// Drain the staged events of one run, identified by its correlation id, and
+    /// keep those of other runs sharing this dispatcher.
+    pub fn take_staged_for(&self, correlation_id: &str) -> Vec<AgentEvent> {
+        let Ok(mut staged) = self.staged.lock() else {
+            return Vec::new();
+        };
+        let Some(staged) = staged.as_mut() else {
+            return Vec::new();
+        };
+        let (taken, kept) = std::mem::take(staged)
+            .into_iter()
+            .partition(|event| event.metadata().correlation_id == correlation_id);
+        *staged = kept;
+        taken
+    }
+
+    /// Broadcast `event` to every broadcaster and wait for the results.
+    ///
+    /// Unlike [`emit`](Self::emit) this ignores staging and fails when any
+    /// broadcaster failed, so callers can retry the event.
+    pub async fn deliver(&self, event: &AgentEvent) -> anyhow::Result<()> {
+        let broadcasters = self
+            .broadcasters
+            .read()
+            .map_err(|_| anyhow::anyhow!("Failed to acquire read lock on broadcasters"))?
+            .clone();
+
+        let mut failed = Vec::new();
+        for broadcaster in broadcasters {
+            if matches!(event, AgentEvent::StreamingToken(_)) && !broadcaster.supports_streaming() {
+                continue;
+            }
+            if !broadcaster.should_broadcast(event) {
+                continue;
+            }
+            if let Err(e) = broadcaster.broadcast(event).await {
+                failed.push(format!("{}: {}", broadcaster.id(), e));
+            }
+        }
+
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(
+                "Failed to deliver event: {}",
+                failed.join("; ")
+            ))
+        }
+    }
+
+    /// Number of broadcasts that have been started but not completed.
+    pub fn pending_broadcasts(&self) -> usize {
+        self.in_flight.count.load(Ordering::Acquire)
+    }
+
+    /// Wait until every broadcast started so far has completed.
+    pub async fn flush(&self) {
+        loop {
+            let idle = self.in_flight.idle.notified();
+            tokio::pin!(idle);
+            idle.as_mut().enable();
+            if self.pending_broadcasts() == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
 }
 
 impl Default for EventDispatcher {
@@ -293,3 +701,90 @@ impl Default for EventDispatcher {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct SlowBroadcaster {
+        delivered: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl EventBroadcaster for SlowBroadcaster {
+        fn id(&self) -> &str {
+            "slow"
+        }
+
+        async fn broadcast(&self, _event: &AgentEvent) -> anyhow::Result<()> {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            self.delivered.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn flush_waits_for_spawned_broadcasts() {
+        let delivered = Arc::new(AtomicUsize::new(0));
+        let dispatcher = EventDispatcher::new();
+        dispatcher.add_broadcaster(Arc::new(SlowBroadcaster {
+            delivered: delivered.clone(),
+        }));
+
+        for _ in 0..3 {
+            dispatcher.emit(AgentEvent::StateCheckpointed(StateCheckpointedEvent {
+                metadata: EventMetadata::new("t".into(), "c".into(), None),
+                checkpoint_id: "default".into(),
+                state_size_bytes: 0,
+            }));
+        }
+        assert_eq!(dispatcher.pending_broadcasts(), 3);
+
+        dispatcher.flush().await;
+        assert_eq!(delivered.load(Ordering::SeqCst), 3);
+        assert_eq!(dispatcher.pending_broadcasts(), 0);
+    }
+
+    #[tokio::test]
+    async fn staged_events_wait_for_delivery() {
+        let delivered = Arc::new(AtomicUsize::new(0));
+        let dispatcher = EventDispatcher::new();
+        dispatcher.add_broadcaster(Arc::new(SlowBroadcaster {
+            delivered: delivered.clone(),
+        }));
+        dispatcher.enable_staging();
+
+        dispatcher.emit(AgentEvent::StateCheckpointed(StateCheckpointedEvent {
+            metadata: EventMetadata::new("t".into(), "c".into(), None),
+            checkpoint_id: "default".into(),
+            state_size_bytes: 0,
+        }));
+        assert_eq!(dispatcher.pending_broadcasts(), 0);
+
+        let staged = dispatcher.take_staged();
+        assert_eq!(staged.len(), 1);
+        assert!(dispatcher.take_staged().is_empty());
+
+        dispatcher.deliver(&staged[0]).await.unwrap();
+        assert_eq!(delivered.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn staged_events_are_taken_per_run() {
+        let dispatcher = EventDispatcher::new();
+        dispatcher.enable_staging();
+        for correlation_id in ["run-a", "run-b", "run-a"] {
+            dispatcher.emit(AgentEvent::StateCheckpointed(StateCheckpointedEvent {
+                metadata: EventMetadata::new("t".into(), correlation_id.into(), None),
+                checkpoint_id: "default".into(),
+                state_size_bytes: 0,
+            }));
+        }
+
+        assert_eq!(dispatcher.take_staged_for("run-a").len(), 2);
+        assert!(dispatcher.take_staged_for("run-a").is_empty());
+        let rest = dispatcher.take_staged();
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].metadata().correlation_id, "run-b");
+    }
+}
@@ -0,0 +1,39 @@
+//! The experiment variant a run was assigned to.
+//!
+//! A run assigned to a variant of an A/B experiment executes inside an experiment
+//! scope. Event metadata and token usage records created while it runs carry the
+//! assignment, so outcomes and costs can be compared between variants.
+
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+
+tokio::task_local! {
+    static CURRENT: ExperimentAssignment;
+}
+
+/// An experiment and the variant a thread was assigned to
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ExperimentAssignment {
+    pub experiment: String,
+    pub variant: String,
+}
+
+impl ExperimentAssignment {
+    pub fn new(experiment: impl Into<String>, variant: impl Into<String>) -> Self {
+        Self {
+            experiment: experiment.into(),
+            variant: variant.into(),
+        }
+    }
+}
+
+/// The experiment assignment of the run executing on this task, if any.
+pub fn current_experiment() -> Option<ExperimentAssignment> {
+    CURRENT.try_with(|assignment| assignment.clone()).ok()
+}
+
+/// Run `future` with `assignment` as the [current](current_experiment) assignment.
+pub async fn with_experiment<F: Future>(assignment: ExperimentAssignment, future: F) -> F::Output {
+    CURRENT.scope(assignment, future).await
+}
@@ -0,0 +1,373 @@
+//! Knowledge graph memory.
+//!
+//! Facts the agent learns are kept as a graph: entities (people, companies,
+//! products, ...) joined by relations such as `Ana -works_at-> Acme`. A
+//! [`GraphStore`] holds them across threads, so an agent can answer questions that
+//! chain several facts ("which of Ana's colleagues ordered the X200?") by walking
+//! the graph instead of searching conversations.
+
+use crate::persistence::ThreadId;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use petgraph::stable_graph::{EdgeIndex, NodeIndex, StableGraph};
+use petgraph::visit::EdgeRef;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Mutex;
+
+/// A node of the graph. Entities are identified by name, ignoring case.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphEntity {
+    pub name: String,
+    /// Kind of entity, e.g. `person`, `company` or `product`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+}
+
+impl GraphEntity {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            kind: None,
+        }
+    }
+
+    pub fn with_kind(mut self, kind: impl Into<String>) -> Self {
+        self.kind = Some(kind.into());
+        self
+    }
+}
+
+/// A relation between two entities, e.g. `Ana -works_at-> Acme`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphFact {
+    pub subject: String,
+    /// Relation in snake_case, e.g. `works_at` or `ordered`
+    pub predicate: String,
+    pub object: String,
+    /// Thread the fact was learned in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread_id: Option<ThreadId>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+impl GraphFact {
+    pub fn new(
+        subject: impl Into<String>,
+        predicate: impl Into<String>,
+        object: impl Into<String>,
+    ) -> Self {
+        Self {
+            subject: subject.into(),
+            predicate: predicate.into(),
+            object: object.into(),
+            thread_id: None,
+            recorded_at: Utc::now(),
+        }
+    }
+
+    pub fn with_thread(mut self, thread_id: impl Into<ThreadId>) -> Self {
+        self.thread_id = Some(thread_id.into());
+        self
+    }
+
+    /// `subject -predicate-> object`, the form facts are shown to the model in.
+    pub fn summary(&self) -> String {
+        format!("{} -{}-> {}", self.subject, self.predicate, self.object)
+    }
+}
+
+/// Store of entities and the facts joining them (in-memory, Neo4j, ...).
+#[async_trait]
+pub trait GraphStore: Send + Sync {
+    /// Add entities and facts. Entities named in facts are created when missing; an
+    /// existing entity keeps its kind unless a new one is given. Adding a fact that
+    /// already exists only refreshes when it was recorded.
+    async fn add(&self, entities: &[GraphEntity], facts: &[GraphFact]) -> anyhow::Result<()>;
+
+    /// Entities whose name contains `query`, ignoring case.
+    async fn find_entities(&self, query: &str, limit: usize) -> anyhow::Result<Vec<GraphEntity>>;
+
+    /// Facts within `hops` relations of `entity`, in either direction, nearest first.
+    async fn neighborhood(
+        &self,
+        entity: &str,
+        hops: usize,
+        limit: usize,
+    ) -> anyhow::Result<Vec<GraphFact>>;
+
+    /// Shortest chain of facts connecting two entities, following relations in
+    /// either direction, or `None` when they are more than `max_hops` apart.
+    async fn path(
+        &self,
+        from: &str,
+        to: &str,
+        max_hops: usize,
+    ) -> anyhow::Result<Option<Vec<GraphFact>>>;
+
+    /// Drop the facts learned in a thread, and entities left without facts.
+    async fn delete_thread(&self, thread_id: &ThreadId) -> anyhow::Result<()>;
+}
+
+fn key(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+#[derive(Debug, Default)]
+struct Graph {
+    graph: StableGraph<GraphEntity, GraphFact>,
+    nodes: HashMap<String, NodeIndex>,
+}
+
+impl Graph {
+    fn node(&mut self, entity: GraphEntity) -> NodeIndex {
+        let key = key(&entity.name);
+        match self.nodes.get(&key) {
+            Some(&index) => {
+                if entity.kind.is_some() {
+                    self.graph[index].kind = entity.kind;
+                }
+                index
+            }
+            None => {
+                let index = self.graph.add_node(entity);
+                self.nodes.insert(key, index);
+                index
+            }
+        }
+    }
+
+    fn add_fact(&mut self, fact: GraphFact) {
+        let source = self.node(GraphEntity::new(fact.subject.trim()));
+        let target = self.node(GraphEntity::new(fact.object.trim()));
+        let existing = self
+            .graph
+            .edges_connecting(source, target)
+            .find(|edge| edge.weight().predicate == fact.predicate)
+            .map(|edge| edge.id());
+        match existing {
+            Some(edge) => self.graph[edge] = fact,
+            None => {
+                self.graph.add_edge(source, target, fact);
+            }
+        }
+    }
+
+    /// Edges touching `node`, in either direction.
+    fn incident(&self, node: NodeIndex) -> impl Iterator<Item = (EdgeIndex, NodeIndex)> + '_ {
+        self.graph
+            .edges_directed(node, petgraph::Direction::Outgoing)
+            .map(|edge| (edge.id(), edge.target()))
+            .chain(
+                self.graph
+                    .edges_directed(node, petgraph::Direction::Incoming)
+                    .map(|edge| (edge.id(), edge.source())),
+            )
+    }
+}
+
+/// Process-local graph store for tests and single-process deployments.
+#[derive(Debug, Default)]
+pub struct InMemoryGraphStore {
+    graph: Mutex<Graph>,
+}
+
+impl InMemoryGraphStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> anyhow::Result<std::sync::MutexGuard<'_, Graph>> {
+        self.graph
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Graph store poisoned"))
+    }
+}
+
+#[async_trait]
+impl GraphStore for InMemoryGraphStore {
+    async fn add(&self, entities: &[GraphEntity], facts: &[GraphFact]) -> anyhow::Result<()> {
+        let mut graph = self.lock()?;
+        for entity in entities {
+            graph.node(entity.clone());
+        }
+        for fact in facts {
+            graph.add_fact(fact.clone());
+        }
+        Ok(())
+    }
+
+    async fn find_entities(&self, query: &str, limit: usize) -> anyhow::Result<Vec<GraphEntity>> {
+        let query = key(query);
+        let graph = self.lock()?;
+        let mut entities: Vec<GraphEntity> = graph
+            .graph
+            .node_weights()
+            .filter(|entity| key(&entity.name).contains(&query))
+            .cloned()
+            .collect();
+        // Exact and shorter names first
+        entities.sort_by_key(|entity| (key(&entity.name) != query, entity.name.len()));
+        entities.truncate(limit);
+        Ok(entities)
+    }
+
+    async fn neighborhood(
+        &self,
+        entity: &str,
+        hops: usize,
+        limit: usize,
+    ) -> anyhow::Result<Vec<GraphFact>> {
+        let graph = self.lock()?;
+        let Some(&start) = graph.nodes.get(&key(entity)) else {
+            return Ok(Vec::new());
+        };
+        let mut facts = Vec::new();
+        let mut seen_edges = HashSet::new();
+        let mut seen_nodes = HashSet::from([start]);
+        let mut queue = VecDeque::from([(start, 0)]);
+        while let Some((node, depth)) = queue.pop_front() {
+            if depth == hops {
+                continue;
+            }
+            for (edge, next) in graph.incident(node) {
+                if seen_edges.insert(edge) {
+                    facts.push(graph.graph[edge].clone());
+                    if facts.len() == limit {
+                        return Ok(facts);
+                    }
+                }
+                if seen_nodes.insert(next) {
+                    queue.push_back((next, depth + 1));
+                }
+            }
+        }
+        Ok(facts)
+    }
+
+    async fn path(
+        &self,
+        from: &str,
+        to: &str,
+        max_hops: usize,
+    ) -> anyhow::Result<Option<Vec<GraphFact>>> {
+        let graph = self.lock()?;
+        let (Some(&start), Some(&goal)) = (graph.nodes.get(&key(from)), graph.nodes.get(&key(to)))
+        else {
+            return Ok(None);
+        };
+        if start == goal {
+            return Ok(Some(Vec::new()));
+        }
+        // Breadth-first search, remembering the edge each node was reached by
+        let mut reached_by: HashMap<NodeIndex, (NodeIndex, EdgeIndex)> = HashMap::new();
+        let mut queue = VecDeque::from([(start, 0)]);
+        while let Some((node, depth)) = queue.pop_front() {
+            if depth == max_hops {
+                continue;
+            }
+            for (edge, next) in graph.incident(node) {
+                if next == start || reached_by.contains_key(&next) {
+                    continue;
+                }
+                reached_by.insert(next, (node, edge));
+                if next == goal {
+                    let mut path = Vec::new();
+                    let mut current = goal;
+                    while let Some(&(previous, edge)) = reached_by.get(&current) {
+                        path.push(graph.graph[edge].clone());
+                        current = previous;
+                    }
+                    path.reverse();
+                    return Ok(Some(path));
+                }
+                queue.push_back((next, depth + 1));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn delete_thread(&self, thread_id: &ThreadId) -> anyhow::Result<()> {
+        let mut graph = self.lock()?;
+        let edges: Vec<EdgeIndex> = graph
+            .graph
+            .edge_indices()
+            .filter(|&edge| graph.graph[edge].thread_id.as_ref() == Some(thread_id))
+            .collect();
+        for edge in edges {
+            graph.graph.remove_edge(edge);
+        }
+        let orphans: Vec<NodeIndex> = graph
+            .graph
+            .node_indices()
+            .filter(|&node| graph.incident(node).next().is_none())
+            .collect();
+        for node in orphans {
+            if let Some(entity) = graph.graph.remove_node(node) {
+                graph.nodes.remove(&key(&entity.name));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn store() -> InMemoryGraphStore {
+        let store = InMemoryGraphStore::new();
+        store
+            .add(
+                &[GraphEntity::new("Ana").with_kind("person")],
+                &[
+                    GraphFact::new("Ana", "works_at", "Acme").with_thread("t1"),
+                    GraphFact::new("Bo", "works_at", "Acme").with_thread("t1"),
+                    GraphFact::new("Bo", "ordered", "X200").with_thread("t2"),
+                    GraphFact::new("X200", "made_by", "Zeta").with_thread("t2"),
+                ],
+            )
+            .await
+            .unwrap();
+        store
+    }
+
+    #[tokio::test]
+    async fn walks_relations_in_both_directions() {
+        let store = store().await;
+
+        let facts = store.neighborhood("ana", 1, 10).await.unwrap();
+        assert_eq!(facts.len(), 1);
+        let facts = store.neighborhood("ANA", 2, 10).await.unwrap();
+        let summaries: Vec<String> = facts.iter().map(GraphFact::summary).collect();
+        assert_eq!(
+            summaries,
+            vec!["Ana -works_at-> Acme", "Bo -works_at-> Acme"]
+        );
+
+        let path = store.path("Ana", "Zeta", 4).await.unwrap().unwrap();
+        assert_eq!(path.len(), 4);
+        assert_eq!(path[2].summary(), "Bo -ordered-> X200");
+        assert!(store.path("Ana", "Zeta", 3).await.unwrap().is_none());
+
+        let found = store.find_entities("an", 10).await.unwrap();
+        assert_eq!(found, vec![GraphEntity::new("Ana").with_kind("person")]);
+    }
+
+    #[tokio::test]
+    async fn repeated_facts_are_merged_and_threads_can_be_forgotten() {
+        let store = store().await;
+        store
+            .add(
+                &[],
+                &[GraphFact::new("ana", "works_at", "ACME").with_thread("t3")],
+            )
+            .await
+            .unwrap();
+        assert_eq!(store.neighborhood("Ana", 1, 10).await.unwrap().len(), 1);
+
+        store.delete_thread(&"t2".to_string()).await.unwrap();
+        assert!(store.find_entities("X200", 10).await.unwrap().is_empty());
+        assert!(store.path("Ana", "Bo", 2).await.unwrap().is_some());
+    }
+}
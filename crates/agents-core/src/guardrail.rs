@@ -0,0 +1,44 @@
+//! Content guardrails checking what goes into and comes out of the model.
+//!
+//! A [`Guardrail`] inspects the user's message before a model call and the model's
+//! response after it. It can let the text through, mask parts of it (e.g. PII) or
+//! block it, in which case the agent answers with the guardrail's message instead.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+/// Which side of a model call is checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum GuardrailSource {
+    /// The user's message, before it reaches the model
+    Input,
+    /// The model's response, before it reaches the user
+    Output,
+}
+
+/// Outcome of a guardrail check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GuardrailVerdict {
+    Allow,
+    /// Continue with `text`, e.g. with PII replaced by placeholders
+    Mask {
+        text: String,
+        reasons: Vec<String>,
+    },
+    /// Stop and answer with `message`
+    Block {
+        message: String,
+        reasons: Vec<String>,
+    },
+}
+
+/// A content policy applied to model calls (Bedrock Guardrails, a moderation API, ...).
+#[async_trait]
+pub trait Guardrail: Send + Sync {
+    /// Name reported in events and logs.
+    fn id(&self) -> &str;
+
+    async fn check(&self, text: &str, source: GuardrailSource) -> anyhow::Result<GuardrailVerdict>;
+}
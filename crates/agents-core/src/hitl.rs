@@ -84,6 +84,132 @@ pub enum HitlAction {
     },
 }
 
+impl HitlAction {
+    /// Kind of this action, without its payload.
+    pub fn kind(&self) -> HitlDecisionKind {
+        match self {
+            HitlAction::Accept => HitlDecisionKind::Accept,
+            HitlAction::Edit { .. } => HitlDecisionKind::Edit,
+            HitlAction::Reject { .. } => HitlDecisionKind::Reject,
+            HitlAction::Respond { .. } => HitlDecisionKind::Respond,
+        }
+    }
+}
+
+/// Kind of a human decision on an interrupt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum HitlDecisionKind {
+    Accept,
+    Edit,
+    Reject,
+    Respond,
+}
+
+impl HitlDecisionKind {
+    fn verb(self) -> &'static str {
+        match self {
+            HitlDecisionKind::Accept => "approved",
+            HitlDecisionKind::Edit => "edited",
+            HitlDecisionKind::Reject => "rejected",
+            HitlDecisionKind::Respond => "answered instead of running",
+        }
+    }
+}
+
+/// Audit record of a human decision on a tool call, kept in the thread state.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HitlDecisionRecord {
+    /// Tool call ID of the interrupt
+    pub call_id: String,
+
+    /// Tool the model called
+    pub tool_name: String,
+
+    pub action: HitlDecisionKind,
+
+    /// Reviewer who decided; `None` when the caller did not say
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decided_by: Option<String>,
+
+    pub decided_at: DateTime<Utc>,
+
+    /// Arguments the model called the tool with
+    pub original_args: serde_json::Value,
+
+    /// Tool run instead of the original, for edits that changed it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edited_tool_name: Option<String>,
+
+    /// Arguments the tool ran with, for edits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edited_args: Option<serde_json::Value>,
+
+    /// Rejection reason or reviewer's note
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl HitlDecisionRecord {
+    /// Record `action` taken on `interrupt`.
+    ///
+    /// The reason is the rejection reason, or `note` for other actions.
+    pub fn new(
+        interrupt: &HitlInterrupt,
+        action: &HitlAction,
+        decided_by: Option<String>,
+        note: Option<String>,
+    ) -> Self {
+        let (edited_tool_name, edited_args, reason) = match action {
+            HitlAction::Edit {
+                tool_name,
+                tool_args,
+            } => (
+                (tool_name != &interrupt.tool_name).then(|| tool_name.clone()),
+                Some(tool_args.clone()),
+                note,
+            ),
+            HitlAction::Reject { reason } => (None, None, reason.clone().or(note)),
+            HitlAction::Accept | HitlAction::Respond { .. } => (None, None, note),
+        };
+        Self {
+            call_id: interrupt.call_id.clone(),
+            tool_name: interrupt.tool_name.clone(),
+            action: action.kind(),
+            decided_by,
+            decided_at: Utc::now(),
+            original_args: interrupt.tool_args.clone(),
+            edited_tool_name,
+            edited_args,
+            reason,
+        }
+    }
+
+    /// One-line description for the conversation history, e.g.
+    /// `Human review: ana edited the call to transfer (call_1). Original arguments: {...}. Edited arguments: {...}.`
+    pub fn summary(&self) -> String {
+        let reviewer = self.decided_by.as_deref().unwrap_or("a reviewer");
+        let mut text = format!(
+            "Human review: {reviewer} {} the call to {} ({}).",
+            self.action.verb(),
+            self.tool_name,
+            self.call_id
+        );
+        if let Some(args) = &self.edited_args {
+            text.push_str(&format!(" Original arguments: {}.", self.original_args));
+            if let Some(tool) = &self.edited_tool_name {
+                text.push_str(&format!(" Ran {tool} instead."));
+            }
+            text.push_str(&format!(" Edited arguments: {args}."));
+        }
+        if let Some(reason) = &self.reason {
+            text.push_str(&format!(" Reason: {reason}"));
+        }
+        text
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -105,6 +231,35 @@ mod tests {
         assert_eq!(interrupt.policy_note, Some("Test note".to_string()));
     }
 
+    #[test]
+    fn decision_record_keeps_original_and_edited_args() {
+        let interrupt = HitlInterrupt::new("transfer", json!({"amount": 900}), "call_1", None);
+        let edit = HitlAction::Edit {
+            tool_name: "transfer".to_string(),
+            tool_args: json!({"amount": 90}),
+        };
+
+        let record =
+            HitlDecisionRecord::new(&interrupt, &edit, Some("ana".into()), Some("typo".into()));
+
+        assert_eq!(record.action, HitlDecisionKind::Edit);
+        assert_eq!(record.original_args, json!({"amount": 900}));
+        assert_eq!(record.edited_args, Some(json!({"amount": 90})));
+        assert_eq!(record.edited_tool_name, None);
+        assert_eq!(
+            record.summary(),
+            "Human review: ana edited the call to transfer (call_1). \
+             Original arguments: {\"amount\":900}. Edited arguments: {\"amount\":90}. Reason: typo"
+        );
+
+        let reject = HitlAction::Reject {
+            reason: Some("not today".into()),
+        };
+        let record = HitlDecisionRecord::new(&interrupt, &reject, None, Some("ignored".into()));
+        assert_eq!(record.reason.as_deref(), Some("not today"));
+        assert_eq!(record.edited_args, None);
+    }
+
     #[test]
     fn test_hitl_interrupt_serialization() {
         let interrupt = HitlInterrupt::new(
@@ -0,0 +1,293 @@
+//! Work queue for running agents as an asynchronous, horizontally scaled backend.
+//!
+//! Producers push [`AgentJob`]s (a message for a thread) to a [`JobQueue`]; a pool of
+//! workers claims them, runs the agent and writes the [`AgentJobResult`] and the
+//! run's events back to the queue. A job that was claimed but never completed (the
+//! worker crashed) is handed to another worker once its claim times out, so delivery
+//! is at-least-once.
+
+use crate::events::AgentEvent;
+use crate::messaging::AgentMessage;
+use crate::persistence::ThreadId;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// A message to run on a thread.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentJob {
+    /// Unique id; results and events are published under it
+    pub job_id: String,
+    pub thread_id: ThreadId,
+    pub message: String,
+}
+
+impl AgentJob {
+    /// A job with a fresh id.
+    pub fn new(thread_id: impl Into<ThreadId>, message: impl Into<String>) -> Self {
+        Self {
+            job_id: uuid::Uuid::new_v4().to_string(),
+            thread_id: thread_id.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn with_job_id(mut self, job_id: impl Into<String>) -> Self {
+        self.job_id = job_id.into();
+        self
+    }
+}
+
+/// A job claimed by a worker.
+#[derive(Debug, Clone)]
+pub struct ClaimedJob {
+    pub job: AgentJob,
+    /// Backend handle used to acknowledge the job (stream entry id, receipt handle)
+    pub receipt: String,
+    /// How many times the job has been handed out, including this one
+    pub deliveries: u32,
+}
+
+/// Outcome of a processed job.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentJobResult {
+    pub job_id: String,
+    pub thread_id: ThreadId,
+    /// The agent's response, or why the job failed
+    pub outcome: Result<AgentMessage, String>,
+    /// Worker that processed the job
+    pub worker: String,
+}
+
+/// Queue backend shared by producers and workers (Redis Streams, in-memory, ...).
+#[async_trait]
+pub trait JobQueue: Send + Sync {
+    /// Add a job to the queue.
+    async fn enqueue(&self, job: &AgentJob) -> anyhow::Result<()>;
+
+    /// Claim up to `limit` jobs for `consumer`, waiting up to `wait` when none are
+    /// available. Jobs whose claim timed out are handed out again.
+    async fn claim(
+        &self,
+        consumer: &str,
+        limit: usize,
+        wait: Duration,
+    ) -> anyhow::Result<Vec<ClaimedJob>>;
+
+    /// Publish the result of a claimed job and remove it from the queue.
+    async fn complete(&self, job: &ClaimedJob, result: &AgentJobResult) -> anyhow::Result<()>;
+
+    /// Give a claimed job back without completing it, e.g. because its thread is busy.
+    ///
+    /// The default leaves the job to be redelivered once its claim times out.
+    async fn release(&self, _job: &ClaimedJob) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Publish an event emitted while processing a job.
+    async fn publish_event(&self, job_id: &str, event: &AgentEvent) -> anyhow::Result<()>;
+
+    /// Result of a completed job, if it is available.
+    async fn result(&self, job_id: &str) -> anyhow::Result<Option<AgentJobResult>>;
+}
+
+#[derive(Debug)]
+struct InFlight {
+    claimed: ClaimedJob,
+    claimed_at: Instant,
+}
+
+#[derive(Debug, Default)]
+struct Queues {
+    pending: VecDeque<(AgentJob, u32)>,
+    in_flight: HashMap<String, InFlight>,
+    results: HashMap<String, AgentJobResult>,
+    events: HashMap<String, Vec<AgentEvent>>,
+    next_receipt: u64,
+}
+
+/// Process-local queue for tests and single-process deployments.
+#[derive(Debug)]
+pub struct InMemoryJobQueue {
+    queues: Mutex<Queues>,
+    claim_timeout: Duration,
+    notify: Notify,
+}
+
+impl Default for InMemoryJobQueue {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(300))
+    }
+}
+
+impl InMemoryJobQueue {
+    /// Claimed jobs not completed within `claim_timeout` are handed out again.
+    pub fn new(claim_timeout: Duration) -> Self {
+        Self {
+            queues: Mutex::new(Queues::default()),
+            claim_timeout,
+            notify: Notify::new(),
+        }
+    }
+
+    /// Events published for a job so far.
+    pub fn events(&self, job_id: &str) -> Vec<AgentEvent> {
+        self.queues
+            .lock()
+            .map(|q| q.events.get(job_id).cloned().unwrap_or_default())
+            .unwrap_or_default()
+    }
+
+    /// Jobs waiting to be claimed or being processed.
+    pub fn len(&self) -> usize {
+        self.queues
+            .lock()
+            .map(|q| q.pending.len() + q.in_flight.len())
+            .unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn take(&self, consumer: &str, limit: usize) -> anyhow::Result<Vec<ClaimedJob>> {
+        let mut queues = self
+            .queues
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Job queue poisoned"))?;
+        let expired: Vec<String> = queues
+            .in_flight
+            .iter()
+            .filter(|(_, f)| f.claimed_at.elapsed() >= self.claim_timeout)
+            .map(|(receipt, _)| receipt.clone())
+            .collect();
+        for receipt in expired {
+            if let Some(flight) = queues.in_flight.remove(&receipt) {
+                tracing::warn!(job_id = %flight.claimed.job.job_id, "Job claim timed out, requeueing");
+                let claimed = flight.claimed;
+                queues.pending.push_front((claimed.job, claimed.deliveries));
+            }
+        }
+
+        let mut claimed = Vec::new();
+        while claimed.len() < limit {
+            let Some((job, deliveries)) = queues.pending.pop_front() else {
+                break;
+            };
+            queues.next_receipt += 1;
+            let receipt = format!("{consumer}-{}", queues.next_receipt);
+            let job = ClaimedJob {
+                job,
+                receipt: receipt.clone(),
+                deliveries: deliveries + 1,
+            };
+            queues.in_flight.insert(
+                receipt,
+                InFlight {
+                    claimed: job.clone(),
+                    claimed_at: Instant::now(),
+                },
+            );
+            claimed.push(job);
+        }
+        Ok(claimed)
+    }
+}
+
+#[async_trait]
+impl JobQueue for InMemoryJobQueue {
+    async fn enqueue(&self, job: &AgentJob) -> anyhow::Result<()> {
+        self.queues
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Job queue poisoned"))?
+            .pending
+            .push_back((job.clone(), 0));
+        self.notify.notify_one();
+        Ok(())
+    }
+
+    async fn claim(
+        &self,
+        consumer: &str,
+        limit: usize,
+        wait: Duration,
+    ) -> anyhow::Result<Vec<ClaimedJob>> {
+        let jobs = self.take(consumer, limit)?;
+        if !jobs.is_empty() {
+            return Ok(jobs);
+        }
+        let _ = tokio::time::timeout(wait, self.notify.notified()).await;
+        self.take(consumer, limit)
+    }
+
+    async fn complete(&self, job: &ClaimedJob, result: &AgentJobResult) -> anyhow::Result<()> {
+        let mut queues = self
+            .queues
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Job queue poisoned"))?;
+        queues.in_flight.remove(&job.receipt);
+        queues.results.insert(result.job_id.clone(), result.clone());
+        Ok(())
+    }
+
+    async fn publish_event(&self, job_id: &str, event: &AgentEvent) -> anyhow::Result<()> {
+        self.queues
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Job queue poisoned"))?
+            .events
+            .entry(job_id.to_string())
+            .or_default()
+            .push(event.clone());
+        Ok(())
+    }
+
+    async fn result(&self, job_id: &str) -> anyhow::Result<Option<AgentJobResult>> {
+        Ok(self
+            .queues
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Job queue poisoned"))?
+            .results
+            .get(job_id)
+            .cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn timed_out_claims_are_redelivered() {
+        let queue = InMemoryJobQueue::new(Duration::from_millis(10));
+        queue
+            .enqueue(&AgentJob::new("thread-1", "hello").with_job_id("job-1"))
+            .await
+            .unwrap();
+
+        let first = queue.claim("worker-a", 10, Duration::ZERO).await.unwrap();
+        assert_eq!(first.len(), 1);
+        assert!(queue
+            .claim("worker-b", 10, Duration::ZERO)
+            .await
+            .unwrap()
+            .is_empty());
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let second = queue.claim("worker-b", 10, Duration::ZERO).await.unwrap();
+        assert_eq!(second[0].job.job_id, "job-1");
+        assert_eq!(second[0].deliveries, 2);
+
+        let result = AgentJobResult {
+            job_id: "job-1".into(),
+            thread_id: "thread-1".into(),
+            outcome: Err("failed".into()),
+            worker: "worker-b".into(),
+        };
+        queue.complete(&second[0], &result).await.unwrap();
+        assert!(queue.is_empty());
+        assert!(queue.result("job-1").await.unwrap().is_some());
+    }
+}
@@ -0,0 +1,511 @@
+//! Incremental parsing of JSON documents streamed in fragments
+//!
+//! Providers stream tool-call arguments as arbitrary slices of a JSON document.
+//! [`JsonStreamParser`] consumes the fragments as they arrive and builds the value
+//! directly, so the raw text is never buffered and the arguments received so far are
+//! available from [`snapshot`](JsonStreamParser::snapshot) before the document is
+//! complete.
+//!
+//! ```rust
+//! use agents_core::json_stream::JsonStreamParser;
+//! use serde_json::json;
+//!
+//! let mut parser = JsonStreamParser::new();
+//! parser.push(r#"{"path": "notes.md", "content": "Dra"#).unwrap();
+//! assert_eq!(
+//!     parser.snapshot(),
+//!     Some(json!({ "path": "notes.md", "content": "Dra" }))
+//! );
+//!
+//! parser.push(r#"ft"}"#).unwrap();
+//! assert_eq!(
+//!     parser.finish().unwrap(),
+//!     json!({ "path": "notes.md", "content": "Draft" })
+//! );
+//! ```
+
+use serde_json::{Map, Value};
+
+/// Builds a JSON value from fragments of its text.
+#[derive(Debug, Default)]
+pub struct JsonStreamParser {
+    stack: Vec<Frame>,
+    token: Token,
+    root: Option<Value>,
+    /// Characters consumed, for error messages
+    offset: usize,
+}
+
+#[derive(Debug)]
+enum Frame {
+    Object {
+        map: Map<String, Value>,
+        key: Option<String>,
+        expect: ObjectExpect,
+    },
+    Array {
+        items: Vec<Value>,
+        expect: ArrayExpect,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ObjectExpect {
+    KeyOrEnd,
+    Key,
+    Colon,
+    Value,
+    CommaOrEnd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ArrayExpect {
+    ValueOrEnd,
+    Value,
+    CommaOrEnd,
+}
+
+#[derive(Debug, Default)]
+enum Token {
+    #[default]
+    None,
+    String {
+        text: String,
+        escape: Escape,
+        /// High half of a UTF-16 surrogate pair awaiting its low half
+        high_surrogate: Option<u16>,
+        is_key: bool,
+    },
+    Number(String),
+    Literal(String),
+}
+
+#[derive(Debug, Default, PartialEq)]
+enum Escape {
+    #[default]
+    None,
+    Backslash,
+    Unicode(String),
+}
+
+impl JsonStreamParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Consume the next fragment of the document.
+    pub fn push(&mut self, fragment: &str) -> anyhow::Result<()> {
+        for c in fragment.chars() {
+            self.feed(c)?;
+            self.offset += 1;
+        }
+        Ok(())
+    }
+
+    /// Whether a whole document has been parsed.
+    pub fn is_complete(&self) -> bool {
+        self.root.is_some()
+    }
+
+    /// The document parsed so far, with open strings, arrays and objects closed.
+    /// Keys without a value yet and incomplete literals are left out. `None` until
+    /// the document has started.
+    pub fn snapshot(&self) -> Option<Value> {
+        if let Some(root) = &self.root {
+            return Some(root.clone());
+        }
+        let mut child = match &self.token {
+            Token::String {
+                text,
+                is_key: false,
+                ..
+            } => Some(Value::String(text.clone())),
+            Token::Number(text) => serde_json::from_str::<serde_json::Number>(text)
+                .ok()
+                .map(Value::Number),
+            _ => None,
+        };
+        for frame in self.stack.iter().rev() {
+            child = Some(match frame {
+                Frame::Object { map, key, .. } => {
+                    let mut map = map.clone();
+                    if let (Some(key), Some(value)) = (key, child) {
+                        map.insert(key.clone(), value);
+                    }
+                    Value::Object(map)
+                }
+                Frame::Array { items, .. } => {
+                    let mut items = items.clone();
+                    items.extend(child);
+                    Value::Array(items)
+                }
+            });
+        }
+        child
+    }
+
+    /// The complete document. Fails when the fragments did not form one.
+    pub fn finish(mut self) -> anyhow::Result<Value> {
+        // A top-level number or literal ends with the input
+        match std::mem::take(&mut self.token) {
+            Token::Number(text) => self.finish_number(text)?,
+            Token::Literal(text) => self.finish_literal(text)?,
+            Token::None => {}
+            Token::String { .. } => anyhow::bail!("Unterminated string at end of JSON input"),
+        }
+        self.root
+            .ok_or_else(|| anyhow::anyhow!("Incomplete JSON document"))
+    }
+
+    fn feed(&mut self, c: char) -> anyhow::Result<()> {
+        match &mut self.token {
+            Token::None => self.structural(c),
+            Token::String { .. } => self.string_char(c),
+            Token::Number(text) => {
+                if c.is_ascii_digit() || matches!(c, '+' | '-' | '.' | 'e' | 'E') {
+                    text.push(c);
+                    Ok(())
+                } else {
+                    let Token::Number(text) = std::mem::take(&mut self.token) else {
+                        unreachable!()
+                    };
+                    self.finish_number(text)?;
+                    self.structural(c)
+                }
+            }
+            Token::Literal(text) => {
+                if c.is_ascii_alphabetic() {
+                    text.push(c);
+                    Ok(())
+                } else {
+                    let Token::Literal(text) = std::mem::take(&mut self.token) else {
+                        unreachable!()
+                    };
+                    self.finish_literal(text)?;
+                    self.structural(c)
+                }
+            }
+        }
+    }
+
+    fn structural(&mut self, c: char) -> anyhow::Result<()> {
+        match c {
+            c if c.is_whitespace() => {}
+            '{' => {
+                self.expect_value(c)?;
+                self.stack.push(Frame::Object {
+                    map: Map::new(),
+                    key: None,
+                    expect: ObjectExpect::KeyOrEnd,
+                });
+            }
+            '[' => {
+                self.expect_value(c)?;
+                self.stack.push(Frame::Array {
+                    items: Vec::new(),
+                    expect: ArrayExpect::ValueOrEnd,
+                });
+            }
+            '}' => match self.stack.pop() {
+                Some(Frame::Object {
+                    map,
+                    expect: ObjectExpect::KeyOrEnd | ObjectExpect::CommaOrEnd,
+                    ..
+                }) => {
+                    self.complete_value(Value::Object(map))?;
+                }
+                _ => return Err(self.unexpected(c)),
+            },
+            ']' => match self.stack.pop() {
+                Some(Frame::Array {
+                    items,
+                    expect: ArrayExpect::ValueOrEnd | ArrayExpect::CommaOrEnd,
+                }) => {
+                    self.complete_value(Value::Array(items))?;
+                }
+                _ => return Err(self.unexpected(c)),
+            },
+            ',' => match self.stack.last_mut() {
+                Some(Frame::Object { expect, .. }) if *expect == ObjectExpect::CommaOrEnd => {
+                    *expect = ObjectExpect::Key;
+                }
+                Some(Frame::Array { expect, .. }) if *expect == ArrayExpect::CommaOrEnd => {
+                    *expect = ArrayExpect::Value;
+                }
+                _ => return Err(self.unexpected(c)),
+            },
+            ':' => match self.stack.last_mut() {
+                Some(Frame::Object { expect, .. }) if *expect == ObjectExpect::Colon => {
+                    *expect = ObjectExpect::Value;
+                }
+                _ => return Err(self.unexpected(c)),
+            },
+            '"' => {
+                let is_key = matches!(
+                    self.stack.last(),
+                    Some(Frame::Object {
+                        expect: ObjectExpect::KeyOrEnd | ObjectExpect::Key,
+                        ..
+                    })
+                );
+                if !is_key {
+                    self.expect_value(c)?;
+                }
+                self.token = Token::String {
+                    text: String::new(),
+                    escape: Escape::None,
+                    high_surrogate: None,
+                    is_key,
+                };
+            }
+            '-' | '0'..='9' => {
+                self.expect_value(c)?;
+                self.token = Token::Number(c.to_string());
+            }
+            't' | 'f' | 'n' => {
+                self.expect_value(c)?;
+                self.token = Token::Literal(c.to_string());
+            }
+            _ => return Err(self.unexpected(c)),
+        }
+        Ok(())
+    }
+
+    fn string_char(&mut self, c: char) -> anyhow::Result<()> {
+        let offset = self.offset;
+        let Token::String {
+            text,
+            escape,
+            high_surrogate,
+            ..
+        } = &mut self.token
+        else {
+            unreachable!()
+        };
+        match escape {
+            Escape::None => match c {
+                '"' => {
+                    if high_surrogate.take().is_some() {
+                        text.push(char::REPLACEMENT_CHARACTER);
+                    }
+                    let Token::String { text, is_key, .. } = std::mem::take(&mut self.token) else {
+                        unreachable!()
+                    };
+                    return self.finish_string(text, is_key);
+                }
+                '\\' => *escape = Escape::Backslash,
+                c => {
+                    if high_surrogate.take().is_some() {
+                        text.push(char::REPLACEMENT_CHARACTER);
+                    }
+                    text.push(c);
+                }
+            },
+            Escape::Backslash => {
+                let unescaped = match c {
+                    '"' => '"',
+                    '\\' => '\\',
+                    '/' => '/',
+                    'b' => '\u{8}',
+                    'f' => '\u{c}',
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    'u' => {
+                        *escape = Escape::Unicode(String::with_capacity(4));
+                        return Ok(());
+                    }
+                    _ => anyhow::bail!("Invalid escape '\\{}' at JSON offset {}", c, offset),
+                };
+                if high_surrogate.take().is_some() {
+                    text.push(char::REPLACEMENT_CHARACTER);
+                }
+                text.push(unescaped);
+                *escape = Escape::None;
+            }
+            Escape::Unicode(hex) => {
+                if !c.is_ascii_hexdigit() {
+                    anyhow::bail!("Invalid unicode escape at JSON offset {}", offset);
+                }
+                hex.push(c);
+                if hex.len() < 4 {
+                    return Ok(());
+                }
+                let unit = u16::from_str_radix(hex, 16)?;
+                *escape = Escape::None;
+                match (high_surrogate.take(), unit) {
+                    (None, 0xD800..=0xDBFF) => *high_surrogate = Some(unit),
+                    (Some(high), 0xDC00..=0xDFFF) => {
+                        let code =
+                            0x10000 + ((high as u32 - 0xD800) << 10) + (unit as u32 - 0xDC00);
+                        text.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                    }
+                    (high, unit) => {
+                        if high.is_some() {
+                            text.push(char::REPLACEMENT_CHARACTER);
+                        }
+                        if (0xD800..=0xDBFF).contains(&unit) {
+                            *high_surrogate = Some(unit);
+                        } else {
+                            text.push(
+                                char::from_u32(unit as u32).unwrap_or(char::REPLACEMENT_CHARACTER),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish_string(&mut self, text: String, is_key: bool) -> anyhow::Result<()> {
+        if !is_key {
+            return self.complete_value(Value::String(text));
+        }
+        if let Some(Frame::Object { key, expect, .. }) = self.stack.last_mut() {
+            *key = Some(text);
+            *expect = ObjectExpect::Colon;
+        }
+        Ok(())
+    }
+
+    fn finish_number(&mut self, text: String) -> anyhow::Result<()> {
+        let number = serde_json::from_str::<serde_json::Number>(&text).map_err(|_| {
+            anyhow::anyhow!("Invalid number '{}' at JSON offset {}", text, self.offset)
+        })?;
+        self.complete_value(Value::Number(number))
+    }
+
+    fn finish_literal(&mut self, text: String) -> anyhow::Result<()> {
+        let value = match text.as_str() {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            "null" => Value::Null,
+            _ => anyhow::bail!("Invalid literal '{}' at JSON offset {}", text, self.offset),
+        };
+        self.complete_value(value)
+    }
+
+    /// Check that a value may start here.
+    fn expect_value(&self, c: char) -> anyhow::Result<()> {
+        let allowed = match self.stack.last() {
+            None => self.root.is_none(),
+            Some(Frame::Object { expect, .. }) => *expect == ObjectExpect::Value,
+            Some(Frame::Array { expect, .. }) => {
+                matches!(expect, ArrayExpect::ValueOrEnd | ArrayExpect::Value)
+            }
+        };
+        if allowed {
+            Ok(())
+        } else {
+            Err(self.unexpected(c))
+        }
+    }
+
+    fn complete_value(&mut self, value: Value) -> anyhow::Result<()> {
+        match self.stack.last_mut() {
+            None => self.root = Some(value),
+            Some(Frame::Object {
+                map, key, expect, ..
+            }) => {
+                if let Some(key) = key.take() {
+                    map.insert(key, value);
+                }
+                *expect = ObjectExpect::CommaOrEnd;
+            }
+            Some(Frame::Array { items, expect }) => {
+                items.push(value);
+                *expect = ArrayExpect::CommaOrEnd;
+            }
+        }
+        Ok(())
+    }
+
+    fn unexpected(&self, c: char) -> anyhow::Error {
+        anyhow::anyhow!("Unexpected '{}' at JSON offset {}", c, self.offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse_in_pieces(text: &str, size: usize) -> Value {
+        let chars: Vec<char> = text.chars().collect();
+        let mut parser = JsonStreamParser::new();
+        for piece in chars.chunks(size) {
+            parser.push(&piece.iter().collect::<String>()).unwrap();
+        }
+        parser.finish().unwrap()
+    }
+
+    #[test]
+    fn fragments_of_any_size_parse_like_the_whole_document() {
+        let document = json!({
+            "query": "größe \"quoted\" \\ back\nslash 😀",
+            "limit": -12.5e2,
+            "flags": [true, false, null, {}, []],
+            "nested": { "ids": [1, 2, 3], "empty": "" }
+        });
+        let text = serde_json::to_string(&document).unwrap();
+        let escaped = r#"{"emoji": "😀", "tab": "a\tbé"}"#;
+        for size in [1, 2, 3, 7, text.len()] {
+            assert_eq!(parse_in_pieces(&text, size), document);
+            assert_eq!(
+                parse_in_pieces(escaped, size),
+                json!({ "emoji": "😀", "tab": "a\tbé" })
+            );
+        }
+    }
+
+    #[test]
+    fn snapshot_closes_open_containers() {
+        let mut parser = JsonStreamParser::new();
+        assert_eq!(parser.snapshot(), None);
+
+        parser.push(r#"{"path": "re"#).unwrap();
+        assert_eq!(parser.snapshot(), Some(json!({ "path": "re" })));
+
+        parser.push(r#"port.md", "tags": [1, 2"#).unwrap();
+        assert_eq!(
+            parser.snapshot(),
+            Some(json!({ "path": "report.md", "tags": [1, 2] }))
+        );
+
+        // A key without its value yet is left out
+        parser.push(r#"], "dra"#).unwrap();
+        assert_eq!(
+            parser.snapshot(),
+            Some(json!({ "path": "report.md", "tags": [1, 2] }))
+        );
+        assert!(!parser.is_complete());
+
+        parser.push(r#"ft": tru"#).unwrap();
+        parser.push("e}").unwrap();
+        assert!(parser.is_complete());
+        assert_eq!(
+            parser.finish().unwrap(),
+            json!({ "path": "report.md", "tags": [1, 2], "draft": true })
+        );
+    }
+
+    #[test]
+    fn malformed_documents_are_rejected() {
+        for text in [
+            r#"{"a" 1}"#,
+            r#"{"a": tru}"#,
+            r#"[1,,2]"#,
+            r#"{"a": 1}}"#,
+            r#""\x""#,
+        ] {
+            let mut parser = JsonStreamParser::new();
+            let result = parser.push(text).and_then(|_| parser.finish().map(|_| ()));
+            assert!(result.is_err(), "{} should be rejected", text);
+        }
+
+        let mut parser = JsonStreamParser::new();
+        parser.push(r#"{"a": "unterminated"#).unwrap();
+        assert!(parser.finish().is_err());
+    }
+}
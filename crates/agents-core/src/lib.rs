@@ -3,31 +3,103 @@
 //! so runtimes and integrations can compose them without pulling in heavy deps.
 
 pub mod agent;
+pub mod approval_store;
+pub mod batch;
+pub mod blob;
 pub mod command;
+pub mod correlation;
+pub mod credentials;
+pub mod dead_letter;
+pub mod determinism;
+pub mod event_schema;
 pub mod events;
+pub mod experiment;
+pub mod graph;
+pub mod guardrail;
 pub mod hitl;
+pub mod job_queue;
+pub mod json_stream;
 pub mod llm;
+pub mod memory;
 pub mod messaging;
+pub mod metering;
+pub mod outbox;
 pub mod persistence;
+pub mod policy;
+pub mod profiling;
 pub mod prompts;
+pub mod quota;
+pub mod response_cache;
+pub mod search;
+pub mod secrets;
 pub mod security;
+pub mod sink;
 pub mod state;
+pub mod thread_lock;
+pub mod thread_template;
+pub mod tool_cache;
 pub mod tools;
 pub mod toon;
+pub mod trace_context;
+pub mod user_profile;
 
-pub use agent::{AgentDescriptor, AgentHandle, PlannerHandle};
+pub use agent::{AgentCapabilities, AgentDescriptor, AgentHandle, PlannerHandle};
+pub use approval_store::{
+    ApprovalDecision, ApprovalError, ApprovalStore, InMemoryApprovalStore, PendingApproval,
+};
+pub use batch::{BatchModel, BatchRequest, BatchResult, BatchStatus};
+pub use blob::{Blob, BlobStore, FileBlobStore, InMemoryBlobStore};
 pub use command::{Command, StateDiff};
+pub use credentials::{ApiKey, ProviderCredentials};
+pub use dead_letter::{DeadLetter, DeadLetterStore, InMemoryDeadLetterStore};
+pub use event_schema::{EventDecodeError, EventEnvelope, EVENT_SCHEMA_VERSION};
 pub use events::{
-    AgentCompletedEvent, AgentEvent, AgentStartedEvent, EventBroadcaster, EventDispatcher,
-    EventMetadata, PlanningCompleteEvent, StateCheckpointedEvent, SubAgentCompletedEvent,
-    SubAgentStartedEvent, TodosUpdatedEvent, ToolCompletedEvent, ToolFailedEvent, ToolStartedEvent,
+    AgentCompletedEvent, AgentEvent, AgentStartedEvent, CredentialRotatedEvent,
+    CredentialRotationReason, DeprecatedToolCalledEvent, EventBroadcaster, EventDispatcher,
+    EventMetadata, GuardrailIntervenedEvent, HitlDecidedEvent, MemoryBlockReason,
+    MemoryBlockedEvent, MemoryPurgeReason, MemoryPurgedEvent, PlanningCompleteEvent,
+    StateCheckpointedEvent, StateSizeExceededEvent, StateSizeMetric, SubAgentCompletedEvent,
+    SubAgentStartedEvent, TodosUpdatedEvent, ToolCallStartedEvent, ToolCompletedEvent,
+    ToolFailedEvent, ToolStartedEvent,
 };
-pub use hitl::{AgentInterrupt, HitlAction, HitlInterrupt};
+pub use experiment::ExperimentAssignment;
+pub use graph::{GraphEntity, GraphFact, GraphStore, InMemoryGraphStore};
+pub use guardrail::{Guardrail, GuardrailSource, GuardrailVerdict};
+pub use hitl::{AgentInterrupt, HitlAction, HitlDecisionKind, HitlDecisionRecord, HitlInterrupt};
+pub use job_queue::{AgentJob, AgentJobResult, ClaimedJob, InMemoryJobQueue, JobQueue};
+pub use json_stream::JsonStreamParser;
+pub use memory::{InMemoryMemoryStore, MemoryEntry, MemoryStore, UserId};
 pub use messaging::{
-    AgentMessage, CacheControl, MessageContent, MessageMetadata, MessageRole, ToolInvocation,
+    AgentMessage, CacheControl, ImageData, MessageContent, MessageMetadata, MessageRole,
+    ToolInvocation,
+};
+pub use metering::{InMemoryMeterSink, MeterEvent, MeterSink};
+pub use outbox::{
+    EventOutbox, InMemoryEventOutbox, OutboxRecord, OutboxRelay, OutboxRelayHandle, RelayStats,
+};
+pub use persistence::{
+    Checkpointer, CheckpointerConfig, InMemoryCheckpointer, ThreadId, ThreadMetadata,
+};
+pub use policy::{PolicyDecision, PolicyProvider, PolicyRequest, PolicyThread};
+pub use quota::{
+    InMemoryQuotaStore, QuotaLimit, QuotaPeriod, QuotaRemaining, QuotaStore, QuotaUsage,
+};
+pub use response_cache::{
+    cosine_similarity, CachedResponse, Embedder, InMemoryResponseCache, ResponseCache,
+};
+pub use search::{ConversationIndex, InMemoryConversationIndex, IndexedMessage, SearchHit};
+pub use secrets::{
+    CachedSecretsProvider, InMemorySecretsProvider, SecretsProvider, SECRET_URI_SCHEME,
 };
-pub use persistence::{Checkpointer, CheckpointerConfig, InMemoryCheckpointer, ThreadId};
+pub use sink::{FileOutputSink, InMemoryOutputSink, OutputSink, OutputWriter, WriterOutputSink};
+pub use thread_lock::{InMemoryThreadLock, LeaseRequest, ThreadLease, ThreadLock};
+pub use thread_template::ThreadTemplate;
+pub use tool_cache::{InMemoryToolResultCache, ToolResultCache};
 pub use tools::{
-    Tool, ToolBox, ToolContext, ToolParameterSchema, ToolRegistry, ToolResult, ToolSchema,
+    ArgumentError, Tool, ToolArgs, ToolBox, ToolContext, ToolDeprecation, ToolExample,
+    ToolExtensions, ToolParameterSchema, ToolParameterType, ToolProvider, ToolRegistry, ToolResult,
+    ToolSchema,
 };
 pub use toon::{ToonEncodeError, ToonEncoder};
+pub use trace_context::TraceContext;
+pub use user_profile::{ProfileFact, UserProfile};
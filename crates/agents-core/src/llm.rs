@@ -14,6 +14,13 @@ pub struct LlmRequest {
     /// Available tools that the LLM can invoke
     #[serde(default)]
     pub tools: Vec<ToolSchema>,
+    /// Sampling temperature; the provider's default when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Sampling seed, for providers that support one; requests created in a
+    /// deterministic run default to its seed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
 }
 
 impl LlmRequest {
@@ -23,6 +30,8 @@ impl LlmRequest {
             system_prompt: system_prompt.into(),
             messages,
             tools: Vec::new(),
+            temperature: None,
+            seed: crate::determinism::current_seed(),
         }
     }
 
@@ -31,11 +40,85 @@ impl LlmRequest {
         self.tools = tools;
         self
     }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LlmResponse {
     pub message: AgentMessage,
+    /// Token counts reported by the provider, when it reports them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<LlmUsage>,
+}
+
+impl LlmResponse {
+    pub fn new(message: AgentMessage) -> Self {
+        Self {
+            message,
+            usage: None,
+        }
+    }
+
+    pub fn with_usage(mut self, usage: LlmUsage) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+}
+
+/// Token counts reported by a provider for a single request.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LlmUsage {
+    /// All prompt tokens, including those read from or written to the prompt cache
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    /// Prompt tokens served from the provider's cache
+    #[serde(default)]
+    pub cache_read_tokens: u32,
+    /// Prompt tokens written to the provider's cache
+    #[serde(default)]
+    pub cache_write_tokens: u32,
+    pub provider: String,
+    pub model: String,
+    /// Cost in USD, when the provider or gateway reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<f64>,
+}
+
+impl LlmUsage {
+    pub fn new(
+        provider: impl Into<String>,
+        model: impl Into<String>,
+        input_tokens: u32,
+        output_tokens: u32,
+    ) -> Self {
+        Self {
+            input_tokens,
+            output_tokens,
+            provider: provider.into(),
+            model: model.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_cache_tokens(mut self, read: u32, write: u32) -> Self {
+        self.cache_read_tokens = read;
+        self.cache_write_tokens = write;
+        self
+    }
+
+    pub fn with_cost(mut self, cost: f64) -> Self {
+        self.cost = Some(cost);
+        self
+    }
 }
 
 /// A chunk of streaming response from the LLM
@@ -43,6 +126,15 @@ pub struct LlmResponse {
 pub enum StreamChunk {
     /// A text delta to append to the response
     TextDelta(String),
+    /// The model started a tool call. Sent once per call as soon as its name is
+    /// known, with the arguments streamed so far; the complete call is part of the
+    /// final message.
+    ToolCallStarted {
+        /// Position of the call among the tool calls of the response
+        index: usize,
+        name: String,
+        partial_args: serde_json::Value,
+    },
     /// The stream has finished
     Done {
         /// The complete final message
@@ -52,6 +144,35 @@ pub enum StreamChunk {
     Error(String),
 }
 
+/// What a model request was made for, so usage can be attributed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum UsagePurpose {
+    /// Planning and answering in the agent loop
+    #[default]
+    Reasoning,
+    /// Condensing tool outputs or conversation history
+    Summarization,
+}
+
+tokio::task_local! {
+    static PURPOSE: UsagePurpose;
+}
+
+/// Purpose of the model requests made on this task (`Reasoning` by default).
+pub fn current_usage_purpose() -> UsagePurpose {
+    PURPOSE.try_with(|purpose| *purpose).unwrap_or_default()
+}
+
+/// Run `future` with its model requests attributed to `purpose`.
+pub async fn with_usage_purpose<F: std::future::Future>(
+    purpose: UsagePurpose,
+    future: F,
+) -> F::Output {
+    PURPOSE.scope(purpose, future).await
+}
+
 /// Type alias for a pinned boxed stream of chunks
 pub type ChunkStream = Pin<Box<dyn Stream<Item = anyhow::Result<StreamChunk>> + Send>>;
 
@@ -65,10 +186,17 @@ pub trait LanguageModel: Send + Sync {
     async fn generate_stream(&self, request: LlmRequest) -> anyhow::Result<ChunkStream> {
         // Default implementation: call generate() and return complete response as a single chunk
         let response = self.generate(request).await?;
+        // Usage reported by generate() is dropped: the chunk protocol has no slot for it
         Ok(Box::pin(futures::stream::once(async move {
             Ok(StreamChunk::Done {
                 message: response.message,
             })
         })))
     }
+
+    /// Name of the model requests go to, e.g. `gpt-4o-mini`, when known.
+    /// Wrappers report the name of the model they wrap.
+    fn model_name(&self) -> Option<String> {
+        None
+    }
 }
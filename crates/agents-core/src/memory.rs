@@ -0,0 +1,203 @@
+//! User-scoped memory.
+//!
+//! Thread state ends with the thread. What an agent learns about a person (their
+//! name, vehicle, account ids, preferences) is kept per user instead, in a
+//! [`MemoryStore`] namespace keyed by a [`UserId`] that is separate from the
+//! thread id, so a returning customer's context carries into new conversations
+//! and can be erased in one call.
+
+use crate::persistence::ThreadId;
+use crate::user_profile::ProfileFact;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+
+/// Identifier of the person an agent talks to, across all of their threads.
+pub type UserId = String;
+
+/// One remembered fact about a user, e.g. `vehicle = 2019 Honda Civic`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemoryEntry {
+    /// Snake_case key, unique within the user's namespace
+    pub key: String,
+    pub value: String,
+    /// How sure the extraction was, from 0.0 to 1.0
+    pub confidence: f32,
+    /// Excerpt of the message the fact was taken from
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub source: String,
+    /// Thread the fact was learned in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread_id: Option<ThreadId>,
+    pub updated_at: DateTime<Utc>,
+    /// Category assigned by a memory policy, e.g. `contact`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// When the entry must be forgotten
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl MemoryEntry {
+    /// A fact stated outright, with confidence 1.0.
+    pub fn new(key: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            value: value.into(),
+            confidence: 1.0,
+            source: String::new(),
+            thread_id: None,
+            updated_at: Utc::now(),
+            category: None,
+            expires_at: None,
+        }
+    }
+
+    pub fn with_thread(mut self, thread_id: impl Into<ThreadId>) -> Self {
+        self.thread_id = Some(thread_id.into());
+        self
+    }
+
+    /// Entry for a fact of a thread's [`UserProfile`](crate::user_profile::UserProfile).
+    pub fn from_profile_fact(key: impl Into<String>, fact: &ProfileFact) -> Self {
+        Self {
+            key: key.into(),
+            value: fact.value.clone(),
+            confidence: fact.confidence,
+            source: fact.source.clone(),
+            thread_id: None,
+            updated_at: fact.updated_at,
+            category: fact.category.clone(),
+            expires_at: fact.expires_at,
+        }
+    }
+
+    pub fn to_profile_fact(&self) -> ProfileFact {
+        ProfileFact {
+            value: self.value.clone(),
+            confidence: self.confidence,
+            source: self.source.clone(),
+            updated_at: self.updated_at,
+            category: self.category.clone(),
+            expires_at: self.expires_at,
+        }
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// Store of user-scoped memories (in-memory, Redis, ...).
+#[async_trait]
+pub trait MemoryStore: Send + Sync {
+    /// Save entries for a user, replacing entries with the same key.
+    async fn put(&self, user_id: &UserId, entries: &[MemoryEntry]) -> anyhow::Result<()>;
+
+    /// All entries of a user, ordered by key.
+    async fn list(&self, user_id: &UserId) -> anyhow::Result<Vec<MemoryEntry>>;
+
+    /// Delete one entry, returning whether it existed.
+    async fn delete(&self, user_id: &UserId, key: &str) -> anyhow::Result<bool>;
+
+    /// Delete everything stored for a user, returning how many entries were erased.
+    async fn erase_user(&self, user_id: &UserId) -> anyhow::Result<usize>;
+}
+
+/// Process-local memory store for tests and single-process deployments.
+#[derive(Debug, Default)]
+pub struct InMemoryMemoryStore {
+    users: RwLock<HashMap<UserId, BTreeMap<String, MemoryEntry>>>,
+}
+
+impl InMemoryMemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl MemoryStore for InMemoryMemoryStore {
+    async fn put(&self, user_id: &UserId, entries: &[MemoryEntry]) -> anyhow::Result<()> {
+        let mut users = self
+            .users
+            .write()
+            .map_err(|_| anyhow::anyhow!("Memory store poisoned"))?;
+        let memories = users.entry(user_id.clone()).or_default();
+        for entry in entries {
+            memories.insert(entry.key.clone(), entry.clone());
+        }
+        Ok(())
+    }
+
+    async fn list(&self, user_id: &UserId) -> anyhow::Result<Vec<MemoryEntry>> {
+        let users = self
+            .users
+            .read()
+            .map_err(|_| anyhow::anyhow!("Memory store poisoned"))?;
+        Ok(users
+            .get(user_id)
+            .map(|memories| memories.values().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn delete(&self, user_id: &UserId, key: &str) -> anyhow::Result<bool> {
+        let mut users = self
+            .users
+            .write()
+            .map_err(|_| anyhow::anyhow!("Memory store poisoned"))?;
+        Ok(users
+            .get_mut(user_id)
+            .is_some_and(|memories| memories.remove(key).is_some()))
+    }
+
+    async fn erase_user(&self, user_id: &UserId) -> anyhow::Result<usize> {
+        let mut users = self
+            .users
+            .write()
+            .map_err(|_| anyhow::anyhow!("Memory store poisoned"))?;
+        Ok(users.remove(user_id).map_or(0, |memories| memories.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn users_have_separate_namespaces() {
+        let store = InMemoryMemoryStore::new();
+        let ana = "ana".to_string();
+        let bo = "bo".to_string();
+        store
+            .put(
+                &ana,
+                &[
+                    MemoryEntry::new("vehicle", "Civic").with_thread("t1"),
+                    MemoryEntry::new("name", "Ana"),
+                ],
+            )
+            .await
+            .unwrap();
+        store
+            .put(&ana, &[MemoryEntry::new("vehicle", "Corolla")])
+            .await
+            .unwrap();
+        store
+            .put(&bo, &[MemoryEntry::new("name", "Bo")])
+            .await
+            .unwrap();
+
+        let memories = store.list(&ana).await.unwrap();
+        assert_eq!(memories.len(), 2);
+        assert_eq!(memories[1].value, "Corolla");
+
+        assert!(store.delete(&ana, "name").await.unwrap());
+        assert!(!store.delete(&ana, "name").await.unwrap());
+        assert_eq!(store.erase_user(&ana).await.unwrap(), 1);
+        assert!(store.list(&ana).await.unwrap().is_empty());
+        assert_eq!(store.list(&bo).await.unwrap().len(), 1);
+    }
+}
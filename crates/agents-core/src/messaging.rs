@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 
 /// Core message structure exchanged between runtimes, planners, and tools.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -22,6 +23,12 @@ pub enum MessageRole {
 pub enum MessageContent {
     Text(String),
     Json(serde_json::Value),
+    /// Images for vision models, with optional accompanying text
+    Image {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        text: Option<String>,
+        images: Vec<ImageData>,
+    },
 }
 
 impl MessageContent {
@@ -38,6 +45,70 @@ impl MessageContent {
             _ => None,
         }
     }
+
+    pub fn images(&self) -> &[ImageData] {
+        match self {
+            MessageContent::Image { images, .. } => images,
+            _ => &[],
+        }
+    }
+
+    /// Text form of the content, for providers, logs and token estimates. Images
+    /// are represented by a placeholder naming their type and size.
+    pub fn to_text(&self) -> String {
+        self.text().into_owned()
+    }
+
+    /// Like [`to_text`](Self::to_text), but borrows plain text instead of copying it.
+    pub fn text(&self) -> Cow<'_, str> {
+        match self {
+            MessageContent::Text(text) => Cow::Borrowed(text),
+            MessageContent::Json(value) => Cow::Owned(value.to_string()),
+            MessageContent::Image { text, images } => {
+                let placeholders = images.iter().map(ImageData::placeholder);
+                Cow::Owned(
+                    text.iter()
+                        .cloned()
+                        .chain(placeholders)
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                )
+            }
+        }
+    }
+}
+
+/// A base64-encoded image.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ImageData {
+    /// MIME type, e.g. `image/png`
+    pub mime_type: String,
+    /// Base64-encoded image bytes
+    pub data: String,
+}
+
+impl ImageData {
+    pub fn new(mime_type: impl Into<String>, data: impl Into<String>) -> Self {
+        Self {
+            mime_type: mime_type.into(),
+            data: data.into(),
+        }
+    }
+
+    /// `data:` URL of the image, as accepted by OpenAI-compatible APIs.
+    pub fn data_url(&self) -> String {
+        format!("data:{};base64,{}", self.mime_type, self.data)
+    }
+
+    /// Size of the decoded image in bytes.
+    pub fn byte_len(&self) -> usize {
+        let padding = self.data.bytes().rev().take_while(|b| *b == b'=').count();
+        (self.data.len() * 3 / 4).saturating_sub(padding)
+    }
+
+    fn placeholder(&self) -> String {
+        format!("[Image: {} ({} bytes)]", self.mime_type, self.byte_len())
+    }
 }
 
 /// Payload passed into tools when invoked by the runtime.
@@ -55,6 +126,12 @@ pub struct MessageMetadata {
     pub tool_call_id: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub cache_control: Option<CacheControl>,
+    /// W3C `traceparent` of the caller; the run joins that trace instead of starting one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub traceparent: Option<String>,
+    /// Id tying this request's events, logs and checkpoints together; generated when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
 }
 
 /// Cache control metadata for Anthropic prompt caching
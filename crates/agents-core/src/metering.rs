@@ -0,0 +1,81 @@
+//! Usage metering for billing.
+//!
+//! Runs served for a tenant execute inside a tenant scope, so token usage records
+//! created while they run carry the tenant. A meter aggregates them into
+//! [`MeterEvent`]s that a [`MeterSink`] delivers to a billing system. Every event
+//! has a stable identifier, so a delivery retried after a failure is recognized as a
+//! duplicate by billing systems that deduplicate on it, like Stripe.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::Mutex;
+
+tokio::task_local! {
+    static CURRENT: String;
+}
+
+/// The tenant of the run executing on this task, if any.
+pub fn current_tenant() -> Option<String> {
+    CURRENT.try_with(String::clone).ok()
+}
+
+/// Run `future` with `tenant` as the [current](current_tenant) tenant.
+pub async fn with_tenant<F: Future>(tenant: String, future: F) -> F::Output {
+    CURRENT.scope(tenant, future).await
+}
+
+/// Usage of one tenant to be billed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MeterEvent {
+    /// Idempotency key; unchanged when the delivery of the event is retried
+    pub identifier: String,
+    /// Name of the meter in the billing system
+    pub event_name: String,
+    pub tenant: String,
+    pub value: u64,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Destination of meter events (Stripe, an internal billing API, ...).
+#[async_trait]
+pub trait MeterSink: Send + Sync {
+    /// Deliver `events`. On failure the same events, with the same identifiers, are
+    /// delivered again later, so events delivered before the failure must be
+    /// accepted or ignored a second time.
+    async fn send(&self, events: &[MeterEvent]) -> anyhow::Result<()>;
+}
+
+/// Sink keeping delivered events in memory, for tests.
+#[derive(Debug, Default)]
+pub struct InMemoryMeterSink {
+    events: Mutex<Vec<MeterEvent>>,
+}
+
+impl InMemoryMeterSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events delivered so far, duplicates dropped as a billing system would.
+    pub fn events(&self) -> Vec<MeterEvent> {
+        self.events.lock().map(|e| e.clone()).unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl MeterSink for InMemoryMeterSink {
+    async fn send(&self, events: &[MeterEvent]) -> anyhow::Result<()> {
+        let mut delivered = self
+            .events
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Meter sink poisoned"))?;
+        for event in events {
+            if !delivered.iter().any(|e| e.identifier == event.identifier) {
+                delivered.push(event.clone());
+            }
+        }
+        Ok(())
+    }
+}
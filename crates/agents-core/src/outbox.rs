@@ -0,0 +1,512 @@
+//! Transactional outbox for agent events.
+//!
+//! Broadcasting events straight from the run means a crash or a failing
+//! broadcaster can leave subscribers out of sync with the saved state. With an
+//! [`EventOutbox`], events are written in the same transaction as the state
+//! snapshot, and an [`OutboxRelay`] delivers them to the broadcasters afterwards,
+//! retrying until every broadcaster accepted them (at-least-once delivery).
+//! Broadcasters should therefore tolerate duplicates, e.g. by deduplicating on
+//! the event's `correlation_id` and type.
+
+use crate::events::{AgentEvent, EventDispatcher};
+use crate::persistence::{Checkpointer, ThreadId};
+use crate::state::AgentStateSnapshot;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+/// An event waiting in the outbox.
+#[derive(Debug, Clone)]
+pub struct OutboxRecord {
+    pub id: i64,
+    pub thread_id: ThreadId,
+    pub event: AgentEvent,
+    /// Failed delivery attempts so far
+    pub attempts: u32,
+}
+
+/// Checkpointer that also stores events for later delivery.
+#[async_trait]
+pub trait EventOutbox: Checkpointer {
+    /// Save the state and append `events` to the outbox atomically.
+    async fn save_state_with_events(
+        &self,
+        thread_id: &ThreadId,
+        state: &AgentStateSnapshot,
+        events: &[AgentEvent],
+    ) -> anyhow::Result<()>;
+
+    /// Append events that are not tied to a state save.
+    async fn enqueue_events(
+        &self,
+        thread_id: &ThreadId,
+        events: &[AgentEvent],
+    ) -> anyhow::Result<()>;
+
+    /// Claim up to `limit` undelivered events, oldest first. Claimed events are
+    /// hidden from other relays for `claim_for`, so several replicas can relay from
+    /// the same outbox.
+    async fn claim_pending(
+        &self,
+        limit: usize,
+        claim_for: Duration,
+    ) -> anyhow::Result<Vec<OutboxRecord>>;
+
+    /// Remove delivered events from the pending set.
+    async fn mark_delivered(&self, ids: &[i64]) -> anyhow::Result<()>;
+
+    /// Record a failed attempt. The event becomes pending again after `retry_after`,
+    /// or is parked for good when `retry_after` is `None`.
+    async fn mark_failed(
+        &self,
+        id: i64,
+        error: &str,
+        retry_after: Option<Duration>,
+    ) -> anyhow::Result<()>;
+}
+
+#[derive(Debug, Clone)]
+struct StoredEvent {
+    record: OutboxRecord,
+    available_at: Instant,
+    delivered: bool,
+    dead: bool,
+    last_error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct OutboxTables {
+    states: HashMap<ThreadId, AgentStateSnapshot>,
+    events: Vec<StoredEvent>,
+    next_id: i64,
+}
+
+impl OutboxTables {
+    fn push(&mut self, thread_id: &ThreadId, events: &[AgentEvent]) {
+        let now = Instant::now();
+        for event in events {
+            self.next_id += 1;
+            self.events.push(StoredEvent {
+                record: OutboxRecord {
+                    id: self.next_id,
+                    thread_id: thread_id.clone(),
+                    event: event.clone(),
+                    attempts: 0,
+                },
+                available_at: now,
+                delivered: false,
+                dead: false,
+                last_error: None,
+            });
+        }
+    }
+}
+
+/// In-memory outbox for tests and development.
+#[derive(Debug, Default)]
+pub struct InMemoryEventOutbox {
+    tables: Mutex<OutboxTables>,
+}
+
+impl InMemoryEventOutbox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn tables(&self) -> anyhow::Result<std::sync::MutexGuard<'_, OutboxTables>> {
+        self.tables
+            .lock()
+            .map_err(|_| anyhow::anyhow!("In-memory outbox poisoned"))
+    }
+
+    /// Events not delivered yet, including parked ones.
+    pub fn undelivered(&self) -> Vec<OutboxRecord> {
+        self.tables()
+            .map(|t| {
+                t.events
+                    .iter()
+                    .filter(|e| !e.delivered)
+                    .map(|e| e.record.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Events that exhausted their retries, with the last error.
+    pub fn dead_letters(&self) -> Vec<(OutboxRecord, Option<String>)> {
+        self.tables()
+            .map(|t| {
+                t.events
+                    .iter()
+                    .filter(|e| e.dead)
+                    .map(|e| (e.record.clone(), e.last_error.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl Checkpointer for InMemoryEventOutbox {
+    async fn save_state(
+        &self,
+        thread_id: &ThreadId,
+        state: &AgentStateSnapshot,
+    ) -> anyhow::Result<()> {
+        self.save_state_with_events(thread_id, state, &[]).await
+    }
+
+    async fn load_state(&self, thread_id: &ThreadId) -> anyhow::Result<Option<AgentStateSnapshot>> {
+        Ok(self.tables()?.states.get(thread_id).cloned())
+    }
+
+    async fn delete_thread(&self, thread_id: &ThreadId) -> anyhow::Result<()> {
+        self.tables()?.states.remove(thread_id);
+        Ok(())
+    }
+
+    async fn list_threads(&self) -> anyhow::Result<Vec<ThreadId>> {
+        Ok(self.tables()?.states.keys().cloned().collect())
+    }
+}
+
+#[async_trait]
+impl EventOutbox for InMemoryEventOutbox {
+    async fn save_state_with_events(
+        &self,
+        thread_id: &ThreadId,
+        state: &AgentStateSnapshot,
+        events: &[AgentEvent],
+    ) -> anyhow::Result<()> {
+        let mut tables = self.tables()?;
+        tables.states.insert(thread_id.clone(), state.clone());
+        tables.push(thread_id, events);
+        Ok(())
+    }
+
+    async fn enqueue_events(
+        &self,
+        thread_id: &ThreadId,
+        events: &[AgentEvent],
+    ) -> anyhow::Result<()> {
+        self.tables()?.push(thread_id, events);
+        Ok(())
+    }
+
+    async fn claim_pending(
+        &self,
+        limit: usize,
+        claim_for: Duration,
+    ) -> anyhow::Result<Vec<OutboxRecord>> {
+        let now = Instant::now();
+        let mut tables = self.tables()?;
+        Ok(tables
+            .events
+            .iter_mut()
+            .filter(|e| !e.delivered && !e.dead && e.available_at <= now)
+            .take(limit)
+            .map(|e| {
+                e.available_at = now + claim_for;
+                e.record.clone()
+            })
+            .collect())
+    }
+
+    async fn mark_delivered(&self, ids: &[i64]) -> anyhow::Result<()> {
+        let mut tables = self.tables()?;
+        for event in tables.events.iter_mut() {
+            if ids.contains(&event.record.id) {
+                event.delivered = true;
+            }
+        }
+        Ok(())
+    }
+
+    async fn mark_failed(
+        &self,
+        id: i64,
+        error: &str,
+        retry_after: Option<Duration>,
+    ) -> anyhow::Result<()> {
+        let mut tables = self.tables()?;
+        if let Some(event) = tables.events.iter_mut().find(|e| e.record.id == id) {
+            event.record.attempts += 1;
+            event.last_error = Some(error.to_string());
+            match retry_after {
+                Some(delay) => event.available_at = Instant::now() + delay,
+                None => event.dead = true,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of one relay pass.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RelayStats {
+    pub delivered: usize,
+    /// Deliveries that failed and will be retried
+    pub retried: usize,
+    /// Deliveries that failed for the last time
+    pub dead: usize,
+}
+
+/// Background task delivering outbox events to a dispatcher's broadcasters.
+///
+/// # Example
+///
+/// ```ignore
+/// let outbox = Arc::new(PostgresCheckpointer::new(database_url).await?);
+/// let relay = OutboxRelay::new(outbox.clone(), dispatcher.clone())
+///     .with_poll_interval(Duration::from_millis(500))
+///     .spawn();
+/// // ...
+/// relay.stop().await;
+/// ```
+pub struct OutboxRelay {
+    outbox: Arc<dyn EventOutbox>,
+    dispatcher: Arc<EventDispatcher>,
+    batch_size: usize,
+    poll_interval: Duration,
+    claim_for: Duration,
+    max_attempts: u32,
+    retry_backoff: Duration,
+}
+
+impl OutboxRelay {
+    pub fn new(outbox: Arc<dyn EventOutbox>, dispatcher: Arc<EventDispatcher>) -> Self {
+        Self {
+            outbox,
+            dispatcher,
+            batch_size: 100,
+            poll_interval: Duration::from_secs(1),
+            claim_for: Duration::from_secs(30),
+            max_attempts: 10,
+            retry_backoff: Duration::from_secs(1),
+        }
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// How long to wait between passes when the outbox is empty.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Failed attempts after which an event is parked instead of retried.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Base retry delay, doubled after each failed attempt (capped at 5 minutes).
+    pub fn with_retry_backoff(mut self, retry_backoff: Duration) -> Self {
+        self.retry_backoff = retry_backoff;
+        self
+    }
+
+    fn retry_delay(&self, attempts: u32) -> Duration {
+        self.retry_backoff
+            .saturating_mul(1 << attempts.min(16))
+            .min(Duration::from_secs(300))
+    }
+
+    /// Deliver one batch of pending events.
+    pub async fn relay_once(&self) -> anyhow::Result<RelayStats> {
+        let records = self
+            .outbox
+            .claim_pending(self.batch_size, self.claim_for)
+            .await?;
+
+        let mut stats = RelayStats::default();
+        let mut delivered = Vec::new();
+        for record in records {
+            match self.dispatcher.deliver(&record.event).await {
+                Ok(()) => delivered.push(record.id),
+                Err(e) => {
+                    let attempts = record.attempts + 1;
+                    let retry_after =
+                        (attempts < self.max_attempts).then(|| self.retry_delay(record.attempts));
+                    tracing::warn!(
+                        event_id = record.id,
+                        thread_id = %record.thread_id,
+                        attempts,
+                        error = %e,
+                        "Outbox event delivery failed"
+                    );
+                    if retry_after.is_some() {
+                        stats.retried += 1;
+                    } else {
+                        stats.dead += 1;
+                    }
+                    self.outbox
+                        .mark_failed(record.id, &e.to_string(), retry_after)
+                        .await?;
+                }
+            }
+        }
+
+        if !delivered.is_empty() {
+            stats.delivered = delivered.len();
+            self.outbox.mark_delivered(&delivered).await?;
+        }
+        Ok(stats)
+    }
+
+    /// Run the relay in the background until [`OutboxRelayHandle::stop`] is called.
+    pub fn spawn(self) -> OutboxRelayHandle {
+        let stop = Arc::new(Notify::new());
+        let task = tokio::spawn({
+            let stop = stop.clone();
+            async move {
+                loop {
+                    let idle = match self.relay_once().await {
+                        Ok(stats) => stats.delivered + stats.retried + stats.dead == 0,
+                        Err(e) => {
+                            tracing::warn!(error = %e, "Outbox relay pass failed");
+                            true
+                        }
+                    };
+                    if idle {
+                        tokio::select! {
+                            _ = stop.notified() => return,
+                            _ = tokio::time::sleep(self.poll_interval) => {}
+                        }
+                    }
+                }
+            }
+        });
+        OutboxRelayHandle { stop, task }
+    }
+}
+
+/// Handle to a running [`OutboxRelay`].
+pub struct OutboxRelayHandle {
+    stop: Arc<Notify>,
+    task: JoinHandle<()>,
+}
+
+impl OutboxRelayHandle {
+    /// Stop the relay once its current batch is done.
+    pub async fn stop(self) {
+        self.stop.notify_one();
+        let _ = self.task.await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{EventBroadcaster, EventMetadata, StateCheckpointedEvent};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Fails the first `failures` broadcasts.
+    struct FlakyBroadcaster {
+        failures: AtomicUsize,
+        received: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl EventBroadcaster for FlakyBroadcaster {
+        fn id(&self) -> &str {
+            "flaky"
+        }
+
+        async fn broadcast(&self, _event: &AgentEvent) -> anyhow::Result<()> {
+            if self
+                .failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok()
+            {
+                anyhow::bail!("webhook unavailable");
+            }
+            self.received.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    fn event(n: usize) -> AgentEvent {
+        AgentEvent::StateCheckpointed(StateCheckpointedEvent {
+            metadata: EventMetadata::new("t".into(), format!("c{n}"), None),
+            checkpoint_id: "thread".into(),
+            state_size_bytes: n,
+        })
+    }
+
+    fn relay_with(
+        failures: usize,
+    ) -> (Arc<InMemoryEventOutbox>, Arc<FlakyBroadcaster>, OutboxRelay) {
+        let outbox = Arc::new(InMemoryEventOutbox::new());
+        let broadcaster = Arc::new(FlakyBroadcaster {
+            failures: AtomicUsize::new(failures),
+            received: AtomicUsize::new(0),
+        });
+        let dispatcher = Arc::new(EventDispatcher::new());
+        dispatcher.add_broadcaster(broadcaster.clone());
+        let relay = OutboxRelay::new(outbox.clone(), dispatcher).with_retry_backoff(Duration::ZERO);
+        (outbox, broadcaster, relay)
+    }
+
+    #[tokio::test]
+    async fn failed_deliveries_are_retried() {
+        let (outbox, broadcaster, relay) = relay_with(1);
+        outbox
+            .save_state_with_events(
+                &"thread".to_string(),
+                &AgentStateSnapshot::default(),
+                &[event(1), event(2)],
+            )
+            .await
+            .unwrap();
+
+        let first = relay.relay_once().await.unwrap();
+        assert_eq!(
+            first,
+            RelayStats {
+                delivered: 1,
+                retried: 1,
+                dead: 0
+            }
+        );
+        let second = relay.relay_once().await.unwrap();
+        assert_eq!(second.delivered, 1);
+
+        assert_eq!(broadcaster.received.load(Ordering::SeqCst), 2);
+        assert!(outbox.undelivered().is_empty());
+        assert!(outbox
+            .load_state(&"thread".to_string())
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn events_are_parked_after_max_attempts() {
+        let (outbox, _broadcaster, relay) = relay_with(usize::MAX);
+        let relay = relay.with_max_attempts(2);
+        outbox
+            .enqueue_events(&"thread".to_string(), &[event(1)])
+            .await
+            .unwrap();
+
+        assert_eq!(relay.relay_once().await.unwrap().retried, 1);
+        assert_eq!(relay.relay_once().await.unwrap().dead, 1);
+        assert_eq!(relay.relay_once().await.unwrap(), RelayStats::default());
+
+        let dead = outbox.dead_letters();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].0.attempts, 2);
+        assert!(dead[0]
+            .1
+            .as_deref()
+            .unwrap()
+            .contains("webhook unavailable"));
+    }
+}
@@ -1,9 +1,23 @@
 //! Persistence traits for checkpointing agent state between runs.
+//!
+//! Threads with large files can be loaded lazily: [`Checkpointer::load_state_lazy`]
+//! leaves file contents out of the state, and tools read them on demand through the
+//! [`FileLoader`] of the run.
+//!
+//! Threads can carry [`ThreadMetadata`] (a customer id, the channel, labels) saved
+//! apart from their state, and be listed by tag with
+//! [`Checkpointer::list_threads_by_tag`].
 
 use crate::state::AgentStateSnapshot;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::future::Future;
+use std::sync::Arc;
+
+tokio::task_local! {
+    static FILE_LOADER: FileLoader;
+}
 
 /// Unique identifier for a conversation thread/session.
 pub type ThreadId = String;
@@ -15,6 +29,46 @@ pub struct CheckpointerConfig {
     pub params: HashMap<String, serde_json::Value>,
 }
 
+/// Metadata attached to a thread, saved apart from its state so it survives
+/// checkpoints and can be set before the first run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ThreadMetadata {
+    /// Arbitrary values such as `customer_id` or `channel`
+    #[serde(default)]
+    pub values: BTreeMap<String, serde_json::Value>,
+    /// Labels thread listings can be filtered by
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+}
+
+impl ThreadMetadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_value(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.values.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.insert(tag.into());
+        self
+    }
+
+    pub fn get(&self, key: &str) -> Option<&serde_json::Value> {
+        self.values.get(key)
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(tag)
+    }
+}
+
 /// Trait for persisting and retrieving agent state between conversation runs.
 /// This mirrors the LangGraph Checkpointer interface used in the Python implementation.
 #[async_trait]
@@ -30,11 +84,96 @@ pub trait Checkpointer: Send + Sync {
     /// Returns None if no state exists for this thread.
     async fn load_state(&self, thread_id: &ThreadId) -> anyhow::Result<Option<AgentStateSnapshot>>;
 
+    /// Load the last saved state without file contents: the paths of the saved files
+    /// are listed in [`AgentStateSnapshot::deferred_files`] and read with
+    /// [`load_file`](Self::load_file) when needed.
+    ///
+    /// The default loads the full state. Implementations that override this must keep
+    /// the saved contents of a snapshot's deferred files when saving it.
+    async fn load_state_lazy(
+        &self,
+        thread_id: &ThreadId,
+    ) -> anyhow::Result<Option<AgentStateSnapshot>> {
+        self.load_state(thread_id).await
+    }
+
+    /// Load the saved contents of one file of a thread.
+    async fn load_file(&self, thread_id: &ThreadId, path: &str) -> anyhow::Result<Option<String>> {
+        Ok(self
+            .load_state(thread_id)
+            .await?
+            .and_then(|mut state| state.files.remove(path)))
+    }
+
     /// Delete all saved state for a given thread.
     async fn delete_thread(&self, thread_id: &ThreadId) -> anyhow::Result<()>;
 
     /// List all thread IDs that have saved state.
     async fn list_threads(&self) -> anyhow::Result<Vec<ThreadId>>;
+
+    /// Load the metadata of a thread. The default stores none.
+    async fn load_metadata(&self, thread_id: &ThreadId) -> anyhow::Result<Option<ThreadMetadata>> {
+        let _ = thread_id;
+        Ok(None)
+    }
+
+    /// Replace the metadata of a thread. Deleting the thread deletes its metadata.
+    ///
+    /// The default fails: the checkpointer has nowhere to keep metadata.
+    async fn save_metadata(
+        &self,
+        thread_id: &ThreadId,
+        metadata: &ThreadMetadata,
+    ) -> anyhow::Result<()> {
+        let _ = metadata;
+        anyhow::bail!("this checkpointer cannot store metadata of thread '{thread_id}'")
+    }
+
+    /// List the threads tagged with `tag`.
+    ///
+    /// The default loads the metadata of every thread with saved state; backends that
+    /// index tags override it with a query.
+    async fn list_threads_by_tag(&self, tag: &str) -> anyhow::Result<Vec<ThreadId>> {
+        let mut tagged = Vec::new();
+        for thread_id in self.list_threads().await? {
+            let metadata = self.load_metadata(&thread_id).await?;
+            if metadata.is_some_and(|metadata| metadata.has_tag(tag)) {
+                tagged.push(thread_id);
+            }
+        }
+        Ok(tagged)
+    }
+}
+
+/// Reads the deferred files of the thread a run works on.
+#[derive(Clone)]
+pub struct FileLoader {
+    checkpointer: Arc<dyn Checkpointer>,
+    thread_id: ThreadId,
+}
+
+impl FileLoader {
+    pub fn new(checkpointer: Arc<dyn Checkpointer>, thread_id: impl Into<ThreadId>) -> Self {
+        Self {
+            checkpointer,
+            thread_id: thread_id.into(),
+        }
+    }
+
+    /// Saved contents of the file at `path`.
+    pub async fn load(&self, path: &str) -> anyhow::Result<Option<String>> {
+        self.checkpointer.load_file(&self.thread_id, path).await
+    }
+}
+
+/// The file loader of the run executing on this task, if its state was loaded lazily.
+pub fn current_file_loader() -> Option<FileLoader> {
+    FILE_LOADER.try_with(|loader| loader.clone()).ok()
+}
+
+/// Run `future` with `loader` as the [current](current_file_loader) file loader.
+pub async fn with_file_loader<F: Future>(loader: FileLoader, future: F) -> F::Output {
+    FILE_LOADER.scope(loader, future).await
 }
 
 /// In-memory checkpointer for testing and development.
@@ -42,6 +181,7 @@ pub trait Checkpointer: Send + Sync {
 #[derive(Debug, Default)]
 pub struct InMemoryCheckpointer {
     states: std::sync::RwLock<HashMap<ThreadId, AgentStateSnapshot>>,
+    metadata: std::sync::RwLock<HashMap<ThreadId, ThreadMetadata>>,
 }
 
 impl InMemoryCheckpointer {
@@ -60,7 +200,15 @@ impl Checkpointer for InMemoryCheckpointer {
         let mut states = self.states.write().map_err(|_| {
             anyhow::anyhow!("Failed to acquire write lock on in-memory checkpointer")
         })?;
-        states.insert(thread_id.clone(), state.clone());
+        let mut state = state.clone();
+        if let Some(saved) = states.get(thread_id) {
+            for path in std::mem::take(&mut state.deferred_files) {
+                if let Some(content) = saved.files.get(&path) {
+                    state.files.entry(path).or_insert_with(|| content.clone());
+                }
+            }
+        }
+        states.insert(thread_id.clone(), state);
         tracing::debug!(thread_id = %thread_id, "Saved agent state to memory");
         Ok(())
     }
@@ -76,11 +224,34 @@ impl Checkpointer for InMemoryCheckpointer {
         Ok(state)
     }
 
+    async fn load_state_lazy(
+        &self,
+        thread_id: &ThreadId,
+    ) -> anyhow::Result<Option<AgentStateSnapshot>> {
+        let states = self.states.read().map_err(|_| {
+            anyhow::anyhow!("Failed to acquire read lock on in-memory checkpointer")
+        })?;
+        Ok(states.get(thread_id).map(AgentStateSnapshot::without_files))
+    }
+
+    async fn load_file(&self, thread_id: &ThreadId, path: &str) -> anyhow::Result<Option<String>> {
+        let states = self.states.read().map_err(|_| {
+            anyhow::anyhow!("Failed to acquire read lock on in-memory checkpointer")
+        })?;
+        Ok(states
+            .get(thread_id)
+            .and_then(|state| state.files.get(path).cloned()))
+    }
+
     async fn delete_thread(&self, thread_id: &ThreadId) -> anyhow::Result<()> {
         let mut states = self.states.write().map_err(|_| {
             anyhow::anyhow!("Failed to acquire write lock on in-memory checkpointer")
         })?;
         states.remove(thread_id);
+        self.metadata
+            .write()
+            .map_err(|_| anyhow::anyhow!("Failed to acquire write lock on thread metadata"))?
+            .remove(thread_id);
         tracing::debug!(thread_id = %thread_id, "Deleted thread from memory");
         Ok(())
     }
@@ -91,6 +262,38 @@ impl Checkpointer for InMemoryCheckpointer {
         })?;
         Ok(states.keys().cloned().collect())
     }
+
+    async fn load_metadata(&self, thread_id: &ThreadId) -> anyhow::Result<Option<ThreadMetadata>> {
+        let metadata = self
+            .metadata
+            .read()
+            .map_err(|_| anyhow::anyhow!("Failed to acquire read lock on thread metadata"))?;
+        Ok(metadata.get(thread_id).cloned())
+    }
+
+    async fn save_metadata(
+        &self,
+        thread_id: &ThreadId,
+        metadata: &ThreadMetadata,
+    ) -> anyhow::Result<()> {
+        self.metadata
+            .write()
+            .map_err(|_| anyhow::anyhow!("Failed to acquire write lock on thread metadata"))?
+            .insert(thread_id.clone(), metadata.clone());
+        Ok(())
+    }
+
+    async fn list_threads_by_tag(&self, tag: &str) -> anyhow::Result<Vec<ThreadId>> {
+        let metadata = self
+            .metadata
+            .read()
+            .map_err(|_| anyhow::anyhow!("Failed to acquire read lock on thread metadata"))?;
+        Ok(metadata
+            .iter()
+            .filter(|(_, metadata)| metadata.has_tag(tag))
+            .map(|(thread_id, _)| thread_id.clone())
+            .collect())
+    }
 }
 
 #[cfg(test)]
@@ -181,4 +384,80 @@ mod tests {
         assert!(threads.contains(&"thread1".to_string()));
         assert!(threads.contains(&"thread2".to_string()));
     }
+
+    #[tokio::test]
+    async fn thread_metadata_is_kept_apart_from_state_and_listed_by_tag() {
+        let checkpointer = InMemoryCheckpointer::new();
+        let thread_id = "support-1".to_string();
+        let metadata = ThreadMetadata::new()
+            .with_value("customer_id", "cus_42")
+            .with_value("channel", "whatsapp")
+            .with_tag("vip");
+        checkpointer
+            .save_metadata(&thread_id, &metadata)
+            .await
+            .unwrap();
+        checkpointer
+            .save_state(&thread_id, &sample_state())
+            .await
+            .unwrap();
+        checkpointer
+            .save_state(&"support-2".to_string(), &sample_state())
+            .await
+            .unwrap();
+
+        let loaded = checkpointer.load_metadata(&thread_id).await.unwrap();
+        assert_eq!(loaded.as_ref(), Some(&metadata));
+        assert_eq!(
+            checkpointer.list_threads_by_tag("vip").await.unwrap(),
+            vec![thread_id.clone()]
+        );
+
+        checkpointer.delete_thread(&thread_id).await.unwrap();
+        assert!(checkpointer
+            .load_metadata(&thread_id)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(checkpointer
+            .list_threads_by_tag("vip")
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn lazily_loaded_state_keeps_unread_files_when_saved() {
+        let checkpointer = InMemoryCheckpointer::new();
+        let thread_id = "big-thread".to_string();
+        let mut state = sample_state();
+        state
+            .files
+            .insert("notes.md".to_string(), "draft".to_string());
+        checkpointer.save_state(&thread_id, &state).await.unwrap();
+
+        let mut lazy = checkpointer
+            .load_state_lazy(&thread_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(lazy.files.is_empty());
+        assert_eq!(lazy.file_paths(), vec!["notes.md", "test.txt"]);
+        assert_eq!(lazy.todos.len(), 1);
+        assert_eq!(
+            checkpointer
+                .load_file(&thread_id, "test.txt")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("content")
+        );
+
+        // Only the rewritten file is in memory; the other keeps its saved contents
+        lazy.insert_file("notes.md", "final");
+        checkpointer.save_state(&thread_id, &lazy).await.unwrap();
+        let saved = checkpointer.load_state(&thread_id).await.unwrap().unwrap();
+        assert_eq!(saved.files.get("notes.md").unwrap(), "final");
+        assert_eq!(saved.files.get("test.txt").unwrap(), "content");
+    }
 }
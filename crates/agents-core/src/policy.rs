@@ -0,0 +1,85 @@
+//! Policy engines deciding whether a tool call may run.
+//!
+//! A [`PolicyProvider`] receives every tool call the model makes, together with the
+//! caller's claims and the thread it runs on, and answers with a [`PolicyDecision`]:
+//! run it, refuse it, or pause for human approval. This moves authorization rules out
+//! of the agent's code into a policy engine such as Cedar or OPA, where they can be
+//! reviewed and changed on their own.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+/// A tool call submitted for a decision.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyRequest {
+    /// Name of the agent making the call
+    pub agent: String,
+    pub tool_name: String,
+    pub tool_args: serde_json::Value,
+    /// Claims of the caller the run is served for, e.g. a verified JWT's claims;
+    /// an empty object when the run has none
+    pub claims: serde_json::Value,
+    pub thread: PolicyThread,
+}
+
+/// The thread a tool call runs on.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PolicyThread {
+    /// Thread id; `None` for runs outside a thread
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    /// Locale of the thread (BCP 47 tag)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+}
+
+/// Outcome of a policy evaluation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum PolicyDecision {
+    Allow,
+    /// Refuse the call; the model is told `reason`
+    Deny {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+    /// Pause the run until a reviewer decides, like a `HitlPolicy`
+    RequireApproval {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        note: Option<String>,
+    },
+}
+
+/// A policy engine deciding on tool calls (Cedar, OPA, ...).
+#[async_trait]
+pub trait PolicyProvider: Send + Sync {
+    /// Name reported in logs.
+    fn id(&self) -> &str;
+
+    async fn evaluate(&self, request: &PolicyRequest) -> anyhow::Result<PolicyDecision>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn decisions_use_a_decision_tag() {
+        let decision: PolicyDecision =
+            serde_json::from_value(json!({"decision": "require_approval", "note": "over limit"}))
+                .unwrap();
+        assert_eq!(
+            decision,
+            PolicyDecision::RequireApproval {
+                note: Some("over limit".into())
+            }
+        );
+        assert_eq!(
+            serde_json::to_value(PolicyDecision::Deny { reason: None }).unwrap(),
+            json!({"decision": "deny"})
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! Per-run timing of the phases of a turn.
+//!
+//! When a run is profiled, it executes inside a profiling scope holding a
+//! [`RunProfiler`]. Components time their hot paths with [`time`] and
+//! [`time_async`]: prompt assembly, request and state serialization, provider calls,
+//! tool execution and checkpoint writes. Each timed section is also a `profile`
+//! tracing span. Outside a profiling scope both helpers only run the closure.
+//!
+//! Phases are timed exclusively: a provider call that serializes its request records
+//! the serialization as [`ProfilePhase::Serialization`] and only the remaining time as
+//! [`ProfilePhase::Provider`], so the phases of a run add up to at most its duration.
+
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::Instrument;
+
+tokio::task_local! {
+    static CURRENT: Arc<RunProfiler>;
+}
+
+/// A phase of a turn whose time is accounted separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfilePhase {
+    /// Building the model request: history, middleware, tool selection and examples
+    PromptBuild,
+    /// Encoding requests and state to JSON and decoding responses
+    Serialization,
+    /// Waiting for the model provider
+    Provider,
+    /// Executing tools
+    Tool,
+    /// Loading and writing checkpoints
+    Persistence,
+}
+
+impl ProfilePhase {
+    /// Every phase, in the order of a turn
+    pub const ALL: [ProfilePhase; 5] = [
+        ProfilePhase::PromptBuild,
+        ProfilePhase::Serialization,
+        ProfilePhase::Provider,
+        ProfilePhase::Tool,
+        ProfilePhase::Persistence,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProfilePhase::PromptBuild => "prompt_build",
+            ProfilePhase::Serialization => "serialization",
+            ProfilePhase::Provider => "provider",
+            ProfilePhase::Tool => "tool",
+            ProfilePhase::Persistence => "persistence",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Time spent in each phase of a run, in milliseconds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunProfile {
+    pub prompt_build_ms: f64,
+    pub serialization_ms: f64,
+    pub provider_ms: f64,
+    pub tool_ms: f64,
+    pub persistence_ms: f64,
+    /// Number of provider calls made during the run
+    pub provider_calls: u32,
+}
+
+impl RunProfile {
+    /// Milliseconds spent in `phase`.
+    pub fn phase_ms(&self, phase: ProfilePhase) -> f64 {
+        match phase {
+            ProfilePhase::PromptBuild => self.prompt_build_ms,
+            ProfilePhase::Serialization => self.serialization_ms,
+            ProfilePhase::Provider => self.provider_ms,
+            ProfilePhase::Tool => self.tool_ms,
+            ProfilePhase::Persistence => self.persistence_ms,
+        }
+    }
+
+    /// Milliseconds accounted to any phase.
+    pub fn total_ms(&self) -> f64 {
+        ProfilePhase::ALL
+            .iter()
+            .map(|phase| self.phase_ms(*phase))
+            .sum()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Totals {
+    phases: [Duration; 5],
+    provider_calls: u32,
+}
+
+/// Accumulates the phase timings of one run.
+#[derive(Debug, Default)]
+pub struct RunProfiler {
+    totals: Mutex<Totals>,
+}
+
+impl RunProfiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `elapsed` to `phase`.
+    pub fn record(&self, phase: ProfilePhase, elapsed: Duration) {
+        if let Ok(mut totals) = self.totals.lock() {
+            totals.phases[phase.index()] += elapsed;
+            if phase == ProfilePhase::Provider {
+                totals.provider_calls += 1;
+            }
+        }
+    }
+
+    fn total(&self) -> Duration {
+        self.totals
+            .lock()
+            .map(|totals| totals.phases.iter().sum())
+            .unwrap_or_default()
+    }
+
+    /// The timings recorded so far.
+    pub fn profile(&self) -> RunProfile {
+        let Ok(totals) = self.totals.lock() else {
+            return RunProfile::default();
+        };
+        let ms = |phase: ProfilePhase| totals.phases[phase.index()].as_secs_f64() * 1_000.0;
+        RunProfile {
+            prompt_build_ms: ms(ProfilePhase::PromptBuild),
+            serialization_ms: ms(ProfilePhase::Serialization),
+            provider_ms: ms(ProfilePhase::Provider),
+            tool_ms: ms(ProfilePhase::Tool),
+            persistence_ms: ms(ProfilePhase::Persistence),
+            provider_calls: totals.provider_calls,
+        }
+    }
+}
+
+/// The profiler of the run executing on this task, if it is profiled.
+pub fn current_profiler() -> Option<Arc<RunProfiler>> {
+    CURRENT.try_with(|profiler| profiler.clone()).ok()
+}
+
+/// Run `future` with `profiler` as the [current](current_profiler) profiler.
+pub async fn with_profiler<F: Future>(profiler: Arc<RunProfiler>, future: F) -> F::Output {
+    CURRENT.scope(profiler, future).await
+}
+
+/// Run `f`, accounting its time to `phase` in the current profile.
+pub fn time<T>(phase: ProfilePhase, f: impl FnOnce() -> T) -> T {
+    let Some(profiler) = current_profiler() else {
+        return f();
+    };
+    let _span = tracing::debug_span!("profile", phase = phase.as_str()).entered();
+    let nested_before = profiler.total();
+    let started = Instant::now();
+    let output = f();
+    let nested = profiler.total().saturating_sub(nested_before);
+    profiler.record(phase, started.elapsed().saturating_sub(nested));
+    output
+}
+
+/// Await `future`, accounting its time to `phase` in the current profile. Time the
+/// future spends in other timed sections is accounted to those instead.
+pub async fn time_async<F: Future>(phase: ProfilePhase, future: F) -> F::Output {
+    let Some(profiler) = current_profiler() else {
+        return future.await;
+    };
+    let span = tracing::debug_span!("profile", phase = phase.as_str());
+    let nested_before = profiler.total();
+    let started = Instant::now();
+    let output = future.instrument(span).await;
+    let nested = profiler.total().saturating_sub(nested_before);
+    profiler.record(phase, started.elapsed().saturating_sub(nested));
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn nested_sections_are_timed_exclusively() {
+        let profiler = Arc::new(RunProfiler::new());
+        let started = Instant::now();
+        with_profiler(profiler.clone(), async {
+            time_async(ProfilePhase::Provider, async {
+                time(ProfilePhase::Serialization, || {
+                    std::thread::sleep(Duration::from_millis(20))
+                });
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            })
+            .await;
+        })
+        .await;
+        let wall_ms = started.elapsed().as_secs_f64() * 1_000.0;
+
+        let profile = profiler.profile();
+        assert!(profile.serialization_ms >= 20.0);
+        assert!(profile.provider_ms >= 10.0);
+        // Serialization is not counted again as provider time
+        assert!(profile.total_ms() <= wall_ms);
+        assert_eq!(profile.provider_calls, 1);
+        assert_eq!(profile.tool_ms, 0.0);
+    }
+
+    #[test]
+    fn timing_outside_a_scope_only_runs_the_closure() {
+        assert!(current_profiler().is_none());
+        assert_eq!(time(ProfilePhase::PromptBuild, || 42), 42);
+    }
+}
@@ -0,0 +1,252 @@
+//! Usage quotas per tenant.
+//!
+//! Per-run limits such as `max_iterations` bound a single request; quotas bound what
+//! a tenant consumes over a day or a month. A [`QuotaStore`] keeps one counter of
+//! requests and tokens per tenant and period window, and expires it when the window
+//! ends, so stores shared by all replicas (Redis) enforce one quota across them.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Window a quota is counted over. Windows follow the UTC calendar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaPeriod {
+    Daily,
+    Monthly,
+}
+
+impl QuotaPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuotaPeriod::Daily => "daily",
+            QuotaPeriod::Monthly => "monthly",
+        }
+    }
+
+    /// Identifier of the window containing `now`, e.g. `2026-10-17` or `2026-10`.
+    pub fn window(&self, now: DateTime<Utc>) -> String {
+        match self {
+            QuotaPeriod::Daily => now.format("%Y-%m-%d").to_string(),
+            QuotaPeriod::Monthly => now.format("%Y-%m").to_string(),
+        }
+    }
+
+    /// Start of the window following the one containing `now`.
+    pub fn resets_at(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let date = now.date_naive();
+        let next = match self {
+            QuotaPeriod::Daily => date + Duration::days(1),
+            QuotaPeriod::Monthly if date.month() == 12 => {
+                NaiveDate::from_ymd_opt(date.year() + 1, 1, 1).unwrap_or(date)
+            }
+            QuotaPeriod::Monthly => {
+                NaiveDate::from_ymd_opt(date.year(), date.month() + 1, 1).unwrap_or(date)
+            }
+        };
+        Utc.from_utc_datetime(&next.and_hms_opt(0, 0, 0).unwrap_or_default())
+    }
+
+    /// Store key of `tenant`'s counter for the window containing `now`.
+    pub fn key(&self, tenant: &str, now: DateTime<Utc>) -> String {
+        format!("{}:{}:{}", tenant, self.as_str(), self.window(now))
+    }
+}
+
+/// Requests and tokens a tenant may use per period. Unset maximums are unlimited.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotaLimit {
+    pub period: QuotaPeriod,
+    pub max_requests: Option<u64>,
+    pub max_tokens: Option<u64>,
+}
+
+impl QuotaLimit {
+    pub fn new(period: QuotaPeriod) -> Self {
+        Self {
+            period,
+            max_requests: None,
+            max_tokens: None,
+        }
+    }
+
+    pub fn daily() -> Self {
+        Self::new(QuotaPeriod::Daily)
+    }
+
+    pub fn monthly() -> Self {
+        Self::new(QuotaPeriod::Monthly)
+    }
+
+    pub fn with_max_requests(mut self, requests: u64) -> Self {
+        self.max_requests = Some(requests);
+        self
+    }
+
+    pub fn with_max_tokens(mut self, tokens: u64) -> Self {
+        self.max_tokens = Some(tokens);
+        self
+    }
+
+    /// Whether `usage` leaves nothing of this limit.
+    pub fn is_exhausted(&self, usage: &QuotaUsage) -> bool {
+        self.max_requests.is_some_and(|max| usage.requests >= max)
+            || self.max_tokens.is_some_and(|max| usage.tokens >= max)
+    }
+}
+
+/// Requests and tokens counted in one window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotaUsage {
+    pub requests: u64,
+    pub tokens: u64,
+}
+
+/// What is left of a limit in the current window.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotaRemaining {
+    pub period: QuotaPeriod,
+    pub used: QuotaUsage,
+    /// Requests left; `None` when requests are unlimited
+    pub requests: Option<u64>,
+    /// Tokens left; `None` when tokens are unlimited
+    pub tokens: Option<u64>,
+    pub resets_at: DateTime<Utc>,
+}
+
+impl QuotaRemaining {
+    pub fn new(limit: &QuotaLimit, used: QuotaUsage, now: DateTime<Utc>) -> Self {
+        Self {
+            period: limit.period,
+            used,
+            requests: limit
+                .max_requests
+                .map(|max| max.saturating_sub(used.requests)),
+            tokens: limit.max_tokens.map(|max| max.saturating_sub(used.tokens)),
+            resets_at: limit.period.resets_at(now),
+        }
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.requests == Some(0) || self.tokens == Some(0)
+    }
+}
+
+/// Counters of quota usage (in-memory, Redis, ...).
+#[async_trait]
+pub trait QuotaStore: Send + Sync {
+    /// Usage counted under `key`; zero when nothing was counted or it expired.
+    async fn usage(&self, key: &str) -> anyhow::Result<QuotaUsage>;
+
+    /// Add `usage` to the counter under `key`, expiring it at `expires_at`, and
+    /// return the new total.
+    async fn record(
+        &self,
+        key: &str,
+        usage: QuotaUsage,
+        expires_at: DateTime<Utc>,
+    ) -> anyhow::Result<QuotaUsage>;
+}
+
+/// Counters by key, with their expiry.
+type Counters = HashMap<String, (QuotaUsage, DateTime<Utc>)>;
+
+/// Process-local quota counters for tests and single-process deployments.
+#[derive(Debug, Default)]
+pub struct InMemoryQuotaStore {
+    counters: Mutex<Counters>,
+}
+
+impl InMemoryQuotaStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> anyhow::Result<std::sync::MutexGuard<'_, Counters>> {
+        self.counters
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Quota store poisoned"))
+    }
+}
+
+#[async_trait]
+impl QuotaStore for InMemoryQuotaStore {
+    async fn usage(&self, key: &str) -> anyhow::Result<QuotaUsage> {
+        let now = Utc::now();
+        Ok(self
+            .lock()?
+            .get(key)
+            .filter(|(_, expires_at)| *expires_at > now)
+            .map(|(usage, _)| *usage)
+            .unwrap_or_default())
+    }
+
+    async fn record(
+        &self,
+        key: &str,
+        usage: QuotaUsage,
+        expires_at: DateTime<Utc>,
+    ) -> anyhow::Result<QuotaUsage> {
+        let now = Utc::now();
+        let mut counters = self.lock()?;
+        counters.retain(|_, (_, expires_at)| *expires_at > now);
+        let (total, expiry) = counters
+            .entry(key.to_string())
+            .or_insert((QuotaUsage::default(), expires_at));
+        total.requests += usage.requests;
+        total.tokens += usage.tokens;
+        *expiry = expires_at;
+        Ok(*total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_follow_the_utc_calendar() {
+        let now = Utc.with_ymd_and_hms(2026, 12, 31, 23, 30, 0).unwrap();
+        assert_eq!(QuotaPeriod::Daily.key("acme", now), "acme:daily:2026-12-31");
+        assert_eq!(QuotaPeriod::Monthly.window(now), "2026-12");
+        assert_eq!(
+            QuotaPeriod::Monthly.resets_at(now),
+            Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            QuotaPeriod::Daily.resets_at(now),
+            Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn counters_add_up_until_they_expire() {
+        let store = InMemoryQuotaStore::new();
+        let limit = QuotaLimit::daily()
+            .with_max_requests(2)
+            .with_max_tokens(1_000);
+        let later = Utc::now() + Duration::hours(1);
+        let run = QuotaUsage {
+            requests: 1,
+            tokens: 300,
+        };
+
+        store.record("acme", run, later).await.unwrap();
+        let used = store.record("acme", run, later).await.unwrap();
+        assert_eq!(used, store.usage("acme").await.unwrap());
+        assert!(limit.is_exhausted(&used));
+
+        let remaining = QuotaRemaining::new(&limit, used, Utc::now());
+        assert_eq!(remaining.requests, Some(0));
+        assert_eq!(remaining.tokens, Some(400));
+
+        store
+            .record("expired", run, Utc::now() - Duration::seconds(1))
+            .await
+            .unwrap();
+        assert_eq!(store.usage("expired").await.unwrap(), QuotaUsage::default());
+    }
+}
@@ -0,0 +1,258 @@
+//! Model responses cached for repeated prompts.
+//!
+//! FAQ-style traffic asks the same questions over and over. A [`ResponseCache`]
+//! keeps model responses under a key derived from the request, so an identical
+//! request is answered without calling the provider. Entries may also carry an
+//! embedding of the prompt, so a differently worded question with the same meaning
+//! can be answered from the cache: [`ResponseCache::find_similar`] returns the
+//! closest entry of the same context above a similarity threshold.
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::llm::LlmResponse;
+
+/// Turns text into a vector whose cosine similarity reflects similarity of meaning
+/// (OpenAI embeddings, Bedrock Titan, a local model, ...).
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    async fn embed(&self, text: &str) -> anyhow::Result<Vec<f32>>;
+}
+
+/// A cached model response.
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    /// Exact-match key of the request
+    pub key: String,
+    /// Key of everything in the request except its prompt; semantic matches are
+    /// only made between entries of the same context
+    pub context_key: String,
+    /// The normalized prompt the response answers
+    pub prompt: String,
+    /// Embedding of `prompt`; entries without one only match exactly
+    pub embedding: Option<Vec<f32>>,
+    pub response: LlmResponse,
+}
+
+/// Storage for cached model responses (in-memory, Redis, ...).
+#[async_trait]
+pub trait ResponseCache: Send + Sync {
+    /// The response stored under `key`, unless it is missing or expired.
+    async fn get(&self, key: &str) -> anyhow::Result<Option<LlmResponse>>;
+
+    /// The response of the entry of `context_key` whose embedding is most similar to
+    /// `embedding`, with its cosine similarity, when it reaches `min_similarity`.
+    async fn find_similar(
+        &self,
+        context_key: &str,
+        embedding: &[f32],
+        min_similarity: f32,
+    ) -> anyhow::Result<Option<(LlmResponse, f32)>>;
+
+    /// Store `entry`, expiring after `ttl` when given.
+    async fn put(&self, entry: CachedResponse, ttl: Option<Duration>) -> anyhow::Result<()>;
+
+    /// Delete every entry.
+    async fn clear(&self) -> anyhow::Result<()>;
+}
+
+/// Cached entries with their expiry, by key.
+type Entries = HashMap<String, (CachedResponse, Option<Instant>)>;
+
+/// Process-local response cache for tests and single-process deployments.
+///
+/// Similar prompts are found by comparing against every entry of the context, which
+/// is fast enough for the few thousand entries a FAQ cache holds.
+#[derive(Debug)]
+pub struct InMemoryResponseCache {
+    entries: Mutex<Entries>,
+    max_entries: usize,
+}
+
+impl Default for InMemoryResponseCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InMemoryResponseCache {
+    /// Cache holding up to 10 000 entries.
+    pub fn new() -> Self {
+        Self::with_max_entries(10_000)
+    }
+
+    /// Cache holding up to `max_entries` entries; when full, expired entries are
+    /// dropped and, if that is not enough, the entries expiring first.
+    pub fn with_max_entries(max_entries: usize) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            max_entries: max_entries.max(1),
+        }
+    }
+
+    fn lock(&self) -> anyhow::Result<std::sync::MutexGuard<'_, Entries>> {
+        self.entries
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Response cache poisoned"))
+    }
+}
+
+fn is_live(expires_at: &Option<Instant>, now: Instant) -> bool {
+    expires_at.is_none_or(|expires_at| expires_at > now)
+}
+
+#[async_trait]
+impl ResponseCache for InMemoryResponseCache {
+    async fn get(&self, key: &str) -> anyhow::Result<Option<LlmResponse>> {
+        let mut entries = self.lock()?;
+        match entries.get(key) {
+            Some((_, expires_at)) if !is_live(expires_at, Instant::now()) => {
+                entries.remove(key);
+                Ok(None)
+            }
+            Some((entry, _)) => Ok(Some(entry.response.clone())),
+            None => Ok(None),
+        }
+    }
+
+    async fn find_similar(
+        &self,
+        context_key: &str,
+        embedding: &[f32],
+        min_similarity: f32,
+    ) -> anyhow::Result<Option<(LlmResponse, f32)>> {
+        let now = Instant::now();
+        let entries = self.lock()?;
+        let best = entries
+            .values()
+            .filter(|(entry, expires_at)| {
+                entry.context_key == context_key && is_live(expires_at, now)
+            })
+            .filter_map(|(entry, _)| {
+                let candidate = entry.embedding.as_deref()?;
+                Some((entry, cosine_similarity(embedding, candidate)))
+            })
+            .filter(|(_, similarity)| *similarity >= min_similarity)
+            .max_by(|(_, a), (_, b)| a.total_cmp(b));
+        Ok(best.map(|(entry, similarity)| (entry.response.clone(), similarity)))
+    }
+
+    async fn put(&self, entry: CachedResponse, ttl: Option<Duration>) -> anyhow::Result<()> {
+        let now = Instant::now();
+        let expires_at = ttl.map(|ttl| now + ttl);
+        let mut entries = self.lock()?;
+        if entries.len() >= self.max_entries && !entries.contains_key(&entry.key) {
+            entries.retain(|_, (_, expires_at)| is_live(expires_at, now));
+            while entries.len() >= self.max_entries {
+                let first = entries
+                    .iter()
+                    .min_by_key(|(_, (_, expires_at))| (expires_at.is_none(), *expires_at))
+                    .map(|(key, _)| key.clone());
+                match first {
+                    Some(key) => entries.remove(&key),
+                    None => break,
+                };
+            }
+        }
+        entries.insert(entry.key.clone(), (entry, expires_at));
+        Ok(())
+    }
+
+    async fn clear(&self) -> anyhow::Result<()> {
+        self.lock()?.clear();
+        Ok(())
+    }
+}
+
+/// Cosine similarity of two vectors; 0.0 when their lengths differ or one is zero.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messaging::{AgentMessage, MessageContent, MessageRole};
+
+    fn entry(key: &str, context_key: &str, embedding: Option<Vec<f32>>) -> CachedResponse {
+        CachedResponse {
+            key: key.to_string(),
+            context_key: context_key.to_string(),
+            prompt: key.to_string(),
+            embedding,
+            response: LlmResponse::new(AgentMessage {
+                role: MessageRole::Agent,
+                content: MessageContent::Text(format!("answer to {key}")),
+                metadata: None,
+            }),
+        }
+    }
+
+    fn text(response: &LlmResponse) -> String {
+        response.message.content.to_text()
+    }
+
+    #[tokio::test]
+    async fn test_exact_and_similar_lookups() {
+        let cache = InMemoryResponseCache::with_max_entries(2);
+        cache
+            .put(entry("hours", "faq", Some(vec![1.0, 0.0])), None)
+            .await
+            .unwrap();
+        cache
+            .put(entry("refunds", "faq", Some(vec![0.0, 1.0])), None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            text(&cache.get("hours").await.unwrap().unwrap()),
+            "answer to hours"
+        );
+        let (response, similarity) = cache
+            .find_similar("faq", &[0.9, 0.1], 0.9)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(text(&response), "answer to hours");
+        assert!(similarity > 0.99);
+        assert!(cache
+            .find_similar("other", &[0.9, 0.1], 0.9)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(cache
+            .find_similar("faq", &[0.7, 0.7], 0.9)
+            .await
+            .unwrap()
+            .is_none());
+
+        // Full: the expired entry goes first
+        cache
+            .put(entry("expired", "faq", None), Some(Duration::ZERO))
+            .await
+            .unwrap();
+        assert!(cache.get("expired").await.unwrap().is_none());
+        assert_eq!(cache.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 2.0], &[2.0, 4.0]) - 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]), 0.0);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 0.0]), 0.0);
+    }
+}
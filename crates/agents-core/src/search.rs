@@ -0,0 +1,214 @@
+//! Full-text search over conversation history.
+//!
+//! A [`ConversationIndex`] is fed the user input and the reply of every thread turn
+//! once the turn is checkpointed, so support teams can find past conversations by
+//! content with `DeepAgent::search_threads`.
+
+use crate::messaging::{AgentMessage, MessageRole};
+use crate::persistence::ThreadId;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+/// Characters of context kept on each side of the first match in a snippet.
+const SNIPPET_CONTEXT_CHARS: usize = 60;
+
+/// A message of a thread, as written to the index.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexedMessage {
+    pub thread_id: ThreadId,
+    pub role: MessageRole,
+    pub text: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl IndexedMessage {
+    /// Index entry for `message`, or `None` when it has no text to search.
+    pub fn from_message(thread_id: &ThreadId, message: &AgentMessage) -> Option<Self> {
+        let text = message.content.to_text();
+        if text.trim().is_empty() {
+            return None;
+        }
+        Some(Self {
+            thread_id: thread_id.clone(),
+            role: message.role.clone(),
+            text,
+            created_at: Utc::now(),
+        })
+    }
+}
+
+/// A message matching a search query.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub thread_id: ThreadId,
+    pub role: MessageRole,
+    /// Full text of the message
+    pub text: String,
+    /// Excerpt around the matched terms
+    pub snippet: String,
+    /// Relevance, only comparable between hits of the same search
+    pub score: f32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Search index over the messages of every thread (in-memory, Postgres, ...).
+#[async_trait]
+pub trait ConversationIndex: Send + Sync {
+    async fn index_messages(&self, messages: &[IndexedMessage]) -> anyhow::Result<()>;
+
+    /// Messages matching `query`, best matches first.
+    async fn search(&self, query: &str, limit: usize) -> anyhow::Result<Vec<SearchHit>>;
+
+    /// Drop the messages of a deleted thread.
+    async fn delete_thread(&self, thread_id: &ThreadId) -> anyhow::Result<()>;
+}
+
+/// Process-local index for tests and single-process deployments. A message matches
+/// when it contains every word of the query, ignoring case.
+#[derive(Debug, Default)]
+pub struct InMemoryConversationIndex {
+    messages: Mutex<Vec<IndexedMessage>>,
+}
+
+impl InMemoryConversationIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> anyhow::Result<std::sync::MutexGuard<'_, Vec<IndexedMessage>>> {
+        self.messages
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Conversation index poisoned"))
+    }
+}
+
+#[async_trait]
+impl ConversationIndex for InMemoryConversationIndex {
+    async fn index_messages(&self, messages: &[IndexedMessage]) -> anyhow::Result<()> {
+        self.lock()?.extend_from_slice(messages);
+        Ok(())
+    }
+
+    async fn search(&self, query: &str, limit: usize) -> anyhow::Result<Vec<SearchHit>> {
+        let terms = words(query);
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut hits: Vec<SearchHit> = self
+            .lock()?
+            .iter()
+            .filter_map(|message| {
+                let words = words(&message.text);
+                let counts: Vec<usize> = terms
+                    .iter()
+                    .map(|term| words.iter().filter(|word| *word == term).count())
+                    .collect();
+                if counts.contains(&0) {
+                    return None;
+                }
+                Some(SearchHit {
+                    thread_id: message.thread_id.clone(),
+                    role: message.role.clone(),
+                    text: message.text.clone(),
+                    snippet: snippet(&message.text, &terms[0]),
+                    score: counts.iter().sum::<usize>() as f32 / words.len() as f32,
+                    created_at: message.created_at,
+                })
+            })
+            .collect();
+        hits.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then(b.created_at.cmp(&a.created_at))
+        });
+        hits.truncate(limit);
+        Ok(hits)
+    }
+
+    async fn delete_thread(&self, thread_id: &ThreadId) -> anyhow::Result<()> {
+        self.lock()?
+            .retain(|message| &message.thread_id != thread_id);
+        Ok(())
+    }
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Up to [`SNIPPET_CONTEXT_CHARS`] characters on each side of the first `term`.
+fn snippet(text: &str, term: &str) -> String {
+    let lower = text.to_lowercase();
+    // Lowercasing can change byte offsets, so fall back to the start of the text
+    let start = lower
+        .find(term)
+        .filter(|_| lower.len() == text.len())
+        .and_then(|index| text.get(..index))
+        .map_or(0, |before| before.chars().count());
+    let chars: Vec<char> = text.chars().collect();
+    let from = start.saturating_sub(SNIPPET_CONTEXT_CHARS);
+    let to = (start + term.chars().count() + SNIPPET_CONTEXT_CHARS).min(chars.len());
+    let mut snippet: String = chars[from..to].iter().collect();
+    if from > 0 {
+        snippet.insert(0, '…');
+    }
+    if to < chars.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messaging::MessageContent;
+
+    fn message(thread_id: &str, text: &str) -> IndexedMessage {
+        let message = AgentMessage {
+            role: MessageRole::User,
+            content: MessageContent::Text(text.to_string()),
+            metadata: None,
+        };
+        IndexedMessage::from_message(&thread_id.to_string(), &message).unwrap()
+    }
+
+    #[tokio::test]
+    async fn finds_messages_containing_every_query_word() {
+        let index = InMemoryConversationIndex::new();
+        index
+            .index_messages(&[
+                message("t1", "My refund for order 1042 never arrived"),
+                message("t2", "How do I reset my password?"),
+                message("t3", "Refund please"),
+            ])
+            .await
+            .unwrap();
+
+        let hits = index.search("REFUND order", 10).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].thread_id, "t1");
+        assert_eq!(hits[0].role, MessageRole::User);
+
+        let hits = index.search("refund", 10).await.unwrap();
+        let threads: Vec<&str> = hits.iter().map(|hit| hit.thread_id.as_str()).collect();
+        assert_eq!(threads, vec!["t3", "t1"]);
+
+        index.delete_thread(&"t3".to_string()).await.unwrap();
+        assert_eq!(index.search("refund", 1).await.unwrap()[0].thread_id, "t1");
+        assert!(index.search("  ", 10).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn snippets_are_cut_around_the_first_match() {
+        let text = format!("{} invoice {}", "a".repeat(100), "b".repeat(100));
+        let snippet = snippet(&text, "invoice");
+        assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+        assert!(snippet.contains("invoice"));
+        assert_eq!(snippet.chars().count(), 2 * SNIPPET_CONTEXT_CHARS + 7 + 2);
+    }
+}
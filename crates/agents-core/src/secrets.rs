@@ -0,0 +1,177 @@
+//! Secrets for provider credentials and other configuration.
+//!
+//! Configuration values can reference a secret with a `secret://<key>` URI instead of
+//! holding it in plain text; [`resolve_secret`] replaces such a URI with the value from
+//! a [`SecretsProvider`] when the agent is built. Providers live next to the store they
+//! read from, e.g. HashiCorp Vault and env files in `agents-secrets`.
+//!
+//! # Caching
+//!
+//! `fetch` may go to the backing store on every call. Wrap remote providers in a
+//! [`CachedSecretsProvider`] to keep values for a TTL. `invalidate` drops a cached
+//! value so the next `fetch` reads the current one, e.g. after the store rotated a
+//! credential the caller still had cached.
+
+use anyhow::Context;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Prefix of configuration values that reference a secret.
+pub const SECRET_URI_SCHEME: &str = "secret://";
+
+/// Store of named secrets (Vault, env files, AWS Secrets Manager, in-memory, ...).
+#[async_trait]
+pub trait SecretsProvider: Send + Sync {
+    /// Current value of the secret named `key`.
+    async fn fetch(&self, key: &str) -> anyhow::Result<String>;
+
+    /// Forget any cached value of `key`. Providers without a cache ignore it.
+    fn invalidate(&self, _key: &str) {}
+
+    /// Save `value` under `key`, for secrets obtained at runtime such as OAuth
+    /// tokens. Read-only providers return an error.
+    async fn store(&self, key: &str, _value: &str) -> anyhow::Result<()> {
+        anyhow::bail!("Secrets provider is read-only, cannot store {key}")
+    }
+}
+
+/// Key of a `secret://<key>` URI, or `None` for plain values.
+pub fn secret_key(value: &str) -> Option<&str> {
+    value.strip_prefix(SECRET_URI_SCHEME)
+}
+
+/// Resolve `value` when it is a `secret://` URI; plain values are returned as is.
+pub async fn resolve_secret(provider: &dyn SecretsProvider, value: &str) -> anyhow::Result<String> {
+    match secret_key(value) {
+        Some(key) => provider
+            .fetch(key)
+            .await
+            .with_context(|| format!("Failed to resolve {value}")),
+        None => Ok(value.to_string()),
+    }
+}
+
+/// Keeps values of another provider for a TTL.
+pub struct CachedSecretsProvider {
+    inner: Arc<dyn SecretsProvider>,
+    ttl: Duration,
+    cache: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl CachedSecretsProvider {
+    pub fn new(inner: Arc<dyn SecretsProvider>, ttl: Duration) -> Self {
+        Self {
+            inner,
+            ttl,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl SecretsProvider for CachedSecretsProvider {
+    async fn fetch(&self, key: &str) -> anyhow::Result<String> {
+        let cached = self.cache.lock().ok().and_then(|cache| {
+            cache
+                .get(key)
+                .filter(|(_, fetched_at)| fetched_at.elapsed() < self.ttl)
+                .map(|(value, _)| value.clone())
+        });
+        if let Some(value) = cached {
+            return Ok(value);
+        }
+
+        let value = self.inner.fetch(key).await?;
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(key.to_string(), (value.clone(), Instant::now()));
+        }
+        Ok(value)
+    }
+
+    fn invalidate(&self, key: &str) {
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.remove(key);
+        }
+        self.inner.invalidate(key);
+    }
+
+    async fn store(&self, key: &str, value: &str) -> anyhow::Result<()> {
+        self.inner.store(key, value).await?;
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(key.to_string(), (value.to_string(), Instant::now()));
+        }
+        Ok(())
+    }
+}
+
+/// Secrets held in memory, for tests and local development.
+#[derive(Default)]
+pub struct InMemorySecretsProvider {
+    secrets: RwLock<HashMap<String, String>>,
+}
+
+impl InMemorySecretsProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_secret(self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.set(key, value);
+        self
+    }
+
+    /// Add or replace a secret.
+    pub fn set(&self, key: impl Into<String>, value: impl Into<String>) {
+        if let Ok(mut secrets) = self.secrets.write() {
+            secrets.insert(key.into(), value.into());
+        }
+    }
+}
+
+#[async_trait]
+impl SecretsProvider for InMemorySecretsProvider {
+    async fn fetch(&self, key: &str) -> anyhow::Result<String> {
+        self.secrets
+            .read()
+            .map_err(|_| anyhow::anyhow!("Secrets lock poisoned"))?
+            .get(key)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Secret not found: {key}"))
+    }
+
+    async fn store(&self, key: &str, value: &str) -> anyhow::Result<()> {
+        self.set(key, value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn cached_values_are_kept_until_invalidated() {
+        let store = Arc::new(InMemorySecretsProvider::new().with_secret("openai/api-key", "sk-1"));
+        let cached = CachedSecretsProvider::new(store.clone(), Duration::from_secs(60));
+
+        assert_eq!(
+            resolve_secret(&cached, "secret://openai/api-key")
+                .await
+                .unwrap(),
+            "sk-1"
+        );
+        assert_eq!(
+            resolve_secret(&cached, "sk-plain").await.unwrap(),
+            "sk-plain"
+        );
+
+        store.set("openai/api-key", "sk-2");
+        assert_eq!(cached.fetch("openai/api-key").await.unwrap(), "sk-1");
+        cached.invalidate("openai/api-key");
+        assert_eq!(cached.fetch("openai/api-key").await.unwrap(), "sk-2");
+
+        assert!(resolve_secret(&cached, "secret://missing").await.is_err());
+    }
+}
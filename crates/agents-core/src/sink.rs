@@ -0,0 +1,230 @@
+//! Output sinks for long final responses.
+//!
+//! A sink receives the full text of a final response in chunks and returns where
+//! it was stored, so the conversation (and the checkpointed history) only has to
+//! carry a short confirmation.
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::io::AsyncWriteExt;
+
+/// Destination for diverted outputs (files, object storage, ...).
+#[async_trait]
+pub trait OutputSink: Send + Sync {
+    /// Start a new output named `name` (e.g. `research/3f2a.md`).
+    async fn create(&self, name: &str) -> anyhow::Result<Box<dyn OutputWriter>>;
+}
+
+/// A single output being written to a sink.
+#[async_trait]
+pub trait OutputWriter: Send {
+    /// Append a chunk of text.
+    async fn write(&mut self, chunk: &str) -> anyhow::Result<()>;
+
+    /// Complete the output and return its location (path, URI, ...).
+    async fn finish(self: Box<Self>) -> anyhow::Result<String>;
+}
+
+/// Writes each output to a file under a directory.
+#[derive(Debug, Clone)]
+pub struct FileOutputSink {
+    dir: PathBuf,
+}
+
+impl FileOutputSink {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+struct FileOutputWriter {
+    path: PathBuf,
+    file: tokio::fs::File,
+}
+
+#[async_trait]
+impl OutputSink for FileOutputSink {
+    async fn create(&self, name: &str) -> anyhow::Result<Box<dyn OutputWriter>> {
+        let relative = name.trim_start_matches('/');
+        if relative.split('/').any(|part| part == "..") {
+            anyhow::bail!("Output name '{name}' must stay inside the sink directory");
+        }
+        let path = self.dir.join(relative);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let file = tokio::fs::File::create(&path).await?;
+        Ok(Box::new(FileOutputWriter { path, file }))
+    }
+}
+
+#[async_trait]
+impl OutputWriter for FileOutputWriter {
+    async fn write(&mut self, chunk: &str) -> anyhow::Result<()> {
+        self.file.write_all(chunk.as_bytes()).await?;
+        Ok(())
+    }
+
+    async fn finish(mut self: Box<Self>) -> anyhow::Result<String> {
+        self.file.flush().await?;
+        Ok(self.path.display().to_string())
+    }
+}
+
+/// Streams every output into a shared [`std::io::Write`] (stdout, a log, a buffer).
+pub struct WriterOutputSink<W: Write + Send + 'static> {
+    writer: Arc<Mutex<W>>,
+    label: String,
+}
+
+impl<W: Write + Send + 'static> WriterOutputSink<W> {
+    /// `label` is reported as the location of every output, e.g. `"stdout"`.
+    pub fn new(writer: W, label: impl Into<String>) -> Self {
+        Self::shared(Arc::new(Mutex::new(writer)), label)
+    }
+
+    pub fn shared(writer: Arc<Mutex<W>>, label: impl Into<String>) -> Self {
+        Self {
+            writer,
+            label: label.into(),
+        }
+    }
+}
+
+struct SharedWriter<W: Write + Send + 'static> {
+    writer: Arc<Mutex<W>>,
+    location: String,
+}
+
+#[async_trait]
+impl<W: Write + Send + 'static> OutputSink for WriterOutputSink<W> {
+    async fn create(&self, name: &str) -> anyhow::Result<Box<dyn OutputWriter>> {
+        Ok(Box::new(SharedWriter {
+            writer: self.writer.clone(),
+            location: format!("{}:{}", self.label, name),
+        }))
+    }
+}
+
+#[async_trait]
+impl<W: Write + Send + 'static> OutputWriter for SharedWriter<W> {
+    async fn write(&mut self, chunk: &str) -> anyhow::Result<()> {
+        let mut writer = self
+            .writer
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Output writer lock poisoned"))?;
+        writer.write_all(chunk.as_bytes())?;
+        Ok(())
+    }
+
+    async fn finish(self: Box<Self>) -> anyhow::Result<String> {
+        let mut writer = self
+            .writer
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Output writer lock poisoned"))?;
+        writer.flush()?;
+        Ok(self.location)
+    }
+}
+
+/// In-memory sink for testing and development.
+#[derive(Debug, Default, Clone)]
+pub struct InMemoryOutputSink {
+    outputs: Arc<RwLock<HashMap<String, String>>>,
+}
+
+impl InMemoryOutputSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Contents of a finished output.
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.outputs.read().ok()?.get(name).cloned()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.outputs
+            .read()
+            .map(|outputs| outputs.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+struct InMemoryOutputWriter {
+    name: String,
+    buffer: String,
+    outputs: Arc<RwLock<HashMap<String, String>>>,
+}
+
+#[async_trait]
+impl OutputSink for InMemoryOutputSink {
+    async fn create(&self, name: &str) -> anyhow::Result<Box<dyn OutputWriter>> {
+        Ok(Box::new(InMemoryOutputWriter {
+            name: name.to_string(),
+            buffer: String::new(),
+            outputs: self.outputs.clone(),
+        }))
+    }
+}
+
+#[async_trait]
+impl OutputWriter for InMemoryOutputWriter {
+    async fn write(&mut self, chunk: &str) -> anyhow::Result<()> {
+        self.buffer.push_str(chunk);
+        Ok(())
+    }
+
+    async fn finish(self: Box<Self>) -> anyhow::Result<String> {
+        let location = format!("memory://{}", self.name);
+        self.outputs
+            .write()
+            .map_err(|_| anyhow::anyhow!("Failed to acquire write lock on in-memory sink"))?
+            .insert(self.name, self.buffer);
+        Ok(location)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn file_sink_writes_chunks_under_directory() {
+        let dir = std::env::temp_dir().join(format!("agents-sink-{}", std::process::id()));
+        let sink = FileOutputSink::new(&dir);
+
+        let mut writer = sink.create("research/report.md").await.unwrap();
+        writer.write("# Report\n").await.unwrap();
+        writer.write("Body").await.unwrap();
+        let location = writer.finish().await.unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&location).unwrap(),
+            "# Report\nBody"
+        );
+        assert!(sink.create("../escape.md").await.is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn writer_and_memory_sinks_collect_output() {
+        let buffer = Arc::new(Mutex::new(Vec::<u8>::new()));
+        let sink = WriterOutputSink::shared(buffer.clone(), "buffer");
+        let mut writer = sink.create("out.md").await.unwrap();
+        writer.write("hello ").await.unwrap();
+        writer.write("world").await.unwrap();
+        assert_eq!(writer.finish().await.unwrap(), "buffer:out.md");
+        assert_eq!(buffer.lock().unwrap().as_slice(), b"hello world");
+
+        let sink = InMemoryOutputSink::new();
+        let mut writer = sink.create("a.md").await.unwrap();
+        writer.write("text").await.unwrap();
+        assert!(sink.get("a.md").is_none());
+        assert_eq!(writer.finish().await.unwrap(), "memory://a.md");
+        assert_eq!(sink.get("a.md").as_deref(), Some("text"));
+    }
+}
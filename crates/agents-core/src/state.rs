@@ -1,6 +1,8 @@
-use crate::hitl::AgentInterrupt;
+use crate::hitl::{AgentInterrupt, HitlDecisionRecord};
+use crate::messaging::AgentMessage;
+use crate::user_profile::UserProfile;
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 /// Snapshot of agent state shared between runtime, planners, and tools.
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
@@ -12,15 +14,53 @@ pub struct AgentStateSnapshot {
     /// Pending interrupts awaiting human response
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub pending_interrupts: Vec<AgentInterrupt>,
+
+    /// Human decisions on this thread's interrupts, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hitl_decisions: Vec<HitlDecisionRecord>,
+
+    /// Locale for this thread (BCP 47 tag such as `ar-AE`); overrides the agent default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+
+    /// Conversation the thread was seeded with, sent to the model ahead of the
+    /// messages of its runs, see [`ThreadTemplate`](crate::thread_template::ThreadTemplate)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub preloaded_messages: Vec<AgentMessage>,
+
+    /// Messages of the thread's earlier runs, sent to the model after the preloaded
+    /// conversation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub messages: Vec<AgentMessage>,
+
+    /// Correlation id of the run that last wrote this state
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+
+    /// User the thread is served for, see [`UserId`](crate::memory::UserId)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+
+    /// Facts the user stated about themselves, see
+    /// [`UserProfile`](crate::user_profile::UserProfile)
+    #[serde(default, skip_serializing_if = "UserProfile::is_empty")]
+    pub user_profile: UserProfile,
+
+    /// Saved files whose contents were not loaded with the state, see
+    /// [`Checkpointer::load_state_lazy`](crate::persistence::Checkpointer::load_state_lazy)
+    #[serde(skip)]
+    pub deferred_files: BTreeSet<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct TodoItem {
     pub content: String,
     pub status: TodoStatus,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum TodoStatus {
     Pending,
@@ -48,6 +88,56 @@ impl AgentStateSnapshot {
         self.pending_interrupts.clear();
     }
 
+    /// Set the locale for this thread, overriding the agent's default locale.
+    pub fn with_locale(mut self, locale: impl Into<String>) -> Self {
+        self.locale = Some(locale.into());
+        self
+    }
+
+    /// Write a file, replacing any deferred contents it had.
+    pub fn insert_file(&mut self, path: impl Into<String>, content: impl Into<String>) {
+        let path = path.into();
+        self.deferred_files.remove(&path);
+        self.files.insert(path, content.into());
+    }
+
+    /// Paths of all files, including deferred ones, in order.
+    pub fn file_paths(&self) -> Vec<&str> {
+        let mut paths: Vec<&str> = self
+            .files
+            .keys()
+            .chain(&self.deferred_files)
+            .map(String::as_str)
+            .collect();
+        paths.sort_unstable();
+        paths.dedup();
+        paths
+    }
+
+    /// This state with file contents left out and their paths listed in
+    /// [`deferred_files`](Self::deferred_files), without copying the contents.
+    pub fn without_files(&self) -> Self {
+        Self {
+            todos: self.todos.clone(),
+            files: BTreeMap::new(),
+            scratchpad: self.scratchpad.clone(),
+            pending_interrupts: self.pending_interrupts.clone(),
+            hitl_decisions: self.hitl_decisions.clone(),
+            locale: self.locale.clone(),
+            preloaded_messages: self.preloaded_messages.clone(),
+            messages: self.messages.clone(),
+            correlation_id: self.correlation_id.clone(),
+            user_id: self.user_id.clone(),
+            user_profile: self.user_profile.clone(),
+            deferred_files: self
+                .files
+                .keys()
+                .chain(&self.deferred_files)
+                .cloned()
+                .collect(),
+        }
+    }
+
     /// Check if there are any pending interrupts.
     pub fn has_pending_interrupts(&self) -> bool {
         !self.pending_interrupts.is_empty()
@@ -70,6 +160,22 @@ impl AgentStateSnapshot {
         if !other.pending_interrupts.is_empty() {
             self.pending_interrupts = other.pending_interrupts;
         }
+
+        // Decisions reducer: append, the log only grows
+        self.hitl_decisions.extend(other.hitl_decisions);
+
+        // Locale reducer: take other's locale when set
+        if other.locale.is_some() {
+            self.locale = other.locale;
+        }
+
+        // User reducer: take other's user when set
+        if other.user_id.is_some() {
+            self.user_id = other.user_id;
+        }
+
+        // Profile reducer: other's facts win per key
+        self.user_profile.merge(other.user_profile);
     }
 
     /// File reducer function matching Python's file_reducer behavior.
@@ -357,6 +463,16 @@ mod tests {
         assert_eq!(state1.pending_interrupts.len(), 1);
     }
 
+    #[test]
+    fn test_merge_locale_keeps_existing_when_unset() {
+        let mut state = AgentStateSnapshot::default().with_locale("ar-AE");
+        state.merge(AgentStateSnapshot::default());
+        assert_eq!(state.locale.as_deref(), Some("ar-AE"));
+
+        state.merge(AgentStateSnapshot::default().with_locale("en"));
+        assert_eq!(state.locale.as_deref(), Some("en"));
+    }
+
     #[test]
     fn test_state_serialization_with_interrupts() {
         use crate::hitl::{AgentInterrupt, HitlInterrupt};
@@ -0,0 +1,233 @@
+//! Distributed per-thread locks for horizontally scaled deployments.
+//!
+//! When several replicas serve the same conversation, two of them can run the same
+//! thread at once and the last checkpoint wins. A [`ThreadLock`] hands out
+//! time-limited leases per thread so only one replica works on a thread at a time.
+//! Leases expire on their own, which lets another replica take over a thread whose
+//! owner crashed; the holder renews its lease while it is still working.
+
+use crate::persistence::ThreadId;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Request for a lease on one thread.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeaseRequest {
+    pub thread_id: ThreadId,
+    /// Stable identifier of the replica asking for the lease (e.g. the pod name)
+    pub owner: String,
+    /// How long the lease lasts unless renewed
+    pub duration: Duration,
+    /// Take over a live lease held by the same owner, e.g. after a restart with a
+    /// stable pod name, instead of waiting for it to expire
+    pub reclaim_own: bool,
+}
+
+/// A lease on a thread held by one owner until `expires_at`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadLease {
+    pub thread_id: ThreadId,
+    pub owner: String,
+    /// Unique per acquisition; renewals and releases only apply to the same token
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl ThreadLease {
+    pub fn new(request: &LeaseRequest, token: impl Into<String>) -> Self {
+        Self {
+            thread_id: request.thread_id.clone(),
+            owner: request.owner.clone(),
+            token: token.into(),
+            expires_at: expires_after(request.duration),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        Utc::now() >= self.expires_at
+    }
+}
+
+/// Point in time `duration` from now.
+pub fn expires_after(duration: Duration) -> DateTime<Utc> {
+    Utc::now() + chrono::Duration::from_std(duration).unwrap_or(chrono::Duration::MAX)
+}
+
+/// Backend handing out per-thread leases (Redis, DynamoDB, in-memory, ...).
+#[async_trait]
+pub trait ThreadLock: Send + Sync {
+    /// Acquire the lease for a thread. Returns `None` when another owner holds a
+    /// lease that has not expired yet.
+    async fn try_acquire(&self, request: &LeaseRequest) -> anyhow::Result<Option<ThreadLease>>;
+
+    /// Extend a lease. Returns `None` when the lease was lost (it expired and
+    /// someone else took the thread over).
+    async fn renew(
+        &self,
+        lease: &ThreadLease,
+        duration: Duration,
+    ) -> anyhow::Result<Option<ThreadLease>>;
+
+    /// Release a lease. Releasing a lease that was already lost is not an error.
+    async fn release(&self, lease: &ThreadLease) -> anyhow::Result<()>;
+}
+
+#[derive(Debug, Clone)]
+struct HeldLease {
+    owner: String,
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+/// Process-local lock for tests and single-replica deployments.
+#[derive(Debug, Default)]
+pub struct InMemoryThreadLock {
+    leases: Mutex<HashMap<ThreadId, HeldLease>>,
+}
+
+impl InMemoryThreadLock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Current holder of a thread's lease, if it has not expired.
+    pub fn holder(&self, thread_id: &ThreadId) -> Option<String> {
+        let leases = self.leases.lock().ok()?;
+        leases
+            .get(thread_id)
+            .filter(|held| held.expires_at > Utc::now())
+            .map(|held| held.owner.clone())
+    }
+}
+
+#[async_trait]
+impl ThreadLock for InMemoryThreadLock {
+    async fn try_acquire(&self, request: &LeaseRequest) -> anyhow::Result<Option<ThreadLease>> {
+        let mut leases = self
+            .leases
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Thread lock table poisoned"))?;
+        if let Some(held) = leases.get(&request.thread_id) {
+            let reclaimable = request.reclaim_own && held.owner == request.owner;
+            if held.expires_at > Utc::now() && !reclaimable {
+                return Ok(None);
+            }
+        }
+
+        let lease = ThreadLease::new(request, uuid::Uuid::new_v4().to_string());
+        leases.insert(
+            request.thread_id.clone(),
+            HeldLease {
+                owner: lease.owner.clone(),
+                token: lease.token.clone(),
+                expires_at: lease.expires_at,
+            },
+        );
+        Ok(Some(lease))
+    }
+
+    async fn renew(
+        &self,
+        lease: &ThreadLease,
+        duration: Duration,
+    ) -> anyhow::Result<Option<ThreadLease>> {
+        let mut leases = self
+            .leases
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Thread lock table poisoned"))?;
+        match leases.get_mut(&lease.thread_id) {
+            Some(held) if held.token == lease.token => {
+                held.expires_at = expires_after(duration);
+                Ok(Some(ThreadLease {
+                    expires_at: held.expires_at,
+                    ..lease.clone()
+                }))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn release(&self, lease: &ThreadLease) -> anyhow::Result<()> {
+        let mut leases = self
+            .leases
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Thread lock table poisoned"))?;
+        if leases
+            .get(&lease.thread_id)
+            .is_some_and(|held| held.token == lease.token)
+        {
+            leases.remove(&lease.thread_id);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(owner: &str, millis: u64) -> LeaseRequest {
+        LeaseRequest {
+            thread_id: "thread-1".into(),
+            owner: owner.into(),
+            duration: Duration::from_millis(millis),
+            reclaim_own: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn leases_are_exclusive_until_released_or_expired() {
+        let lock = InMemoryThreadLock::new();
+        let lease = lock
+            .try_acquire(&request("a", 60_000))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(lock
+            .try_acquire(&request("b", 60_000))
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(lock.holder(&"thread-1".to_string()).as_deref(), Some("a"));
+
+        lock.release(&lease).await.unwrap();
+        let short = lock.try_acquire(&request("b", 10)).await.unwrap().unwrap();
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let taken_over = lock.try_acquire(&request("c", 60_000)).await.unwrap();
+        assert!(taken_over.is_some());
+        // The previous holder notices on renewal and its release is a no-op
+        assert!(lock
+            .renew(&short, Duration::from_secs(1))
+            .await
+            .unwrap()
+            .is_none());
+        lock.release(&short).await.unwrap();
+        assert_eq!(lock.holder(&"thread-1".to_string()).as_deref(), Some("c"));
+    }
+
+    #[tokio::test]
+    async fn owners_can_reclaim_their_own_live_lease() {
+        let lock = InMemoryThreadLock::new();
+        let first = lock
+            .try_acquire(&request("pod-0", 60_000))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let mut again = request("pod-0", 60_000);
+        assert!(lock.try_acquire(&again).await.unwrap().is_none());
+        again.reclaim_own = true;
+        let second = lock.try_acquire(&again).await.unwrap().unwrap();
+
+        assert_ne!(first.token, second.token);
+        assert!(lock
+            .renew(&first, Duration::from_secs(1))
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
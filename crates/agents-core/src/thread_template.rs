@@ -0,0 +1,134 @@
+//! Templates for seeding threads before their first run.
+//!
+//! Scripted flows such as onboarding start every conversation the same way. A
+//! [`ThreadTemplate`] describes that start — earlier messages, files, todos,
+//! scratchpad values and thread metadata — so a thread can be created with the
+//! context already in place and the first user message is answered with it,
+//! without model calls to build it up. Templates are plain data and can be loaded
+//! from JSON.
+
+use serde::{Deserialize, Serialize};
+
+use crate::messaging::{AgentMessage, MessageContent, MessageRole};
+use crate::persistence::ThreadMetadata;
+use crate::state::{AgentStateSnapshot, TodoItem};
+
+/// The starting point of a seeded thread.
+///
+/// ```
+/// use agents_core::state::TodoItem;
+/// use agents_core::thread_template::ThreadTemplate;
+///
+/// let onboarding = ThreadTemplate::new()
+///     .with_user_message("I just signed up. Where do I start?")
+///     .with_agent_message("Welcome! Let's connect your first data source.")
+///     .with_file("/onboarding/checklist.md", "1. Connect a source\n2. Invite your team")
+///     .with_todo(TodoItem::pending("Connect a data source"))
+///     .with_tag("onboarding");
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ThreadTemplate {
+    /// Synthetic history, oldest first
+    #[serde(default)]
+    pub messages: Vec<AgentMessage>,
+    /// Files, todos, scratchpad values and locale of the thread
+    #[serde(default)]
+    pub state: AgentStateSnapshot,
+    #[serde(default)]
+    pub metadata: ThreadMetadata,
+}
+
+impl ThreadTemplate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_message(mut self, message: AgentMessage) -> Self {
+        self.messages.push(message);
+        self
+    }
+
+    pub fn with_user_message(self, text: impl Into<String>) -> Self {
+        self.with_text(MessageRole::User, text.into())
+    }
+
+    pub fn with_agent_message(self, text: impl Into<String>) -> Self {
+        self.with_text(MessageRole::Agent, text.into())
+    }
+
+    fn with_text(self, role: MessageRole, text: String) -> Self {
+        self.with_message(AgentMessage {
+            role,
+            content: MessageContent::Text(text),
+            metadata: None,
+        })
+    }
+
+    /// Start from `state`; its own preloaded messages come before the template's.
+    pub fn with_state(mut self, state: AgentStateSnapshot) -> Self {
+        self.state = state;
+        self
+    }
+
+    pub fn with_file(mut self, path: impl Into<String>, content: impl Into<String>) -> Self {
+        self.state.insert_file(path, content);
+        self
+    }
+
+    pub fn with_todo(mut self, todo: TodoItem) -> Self {
+        self.state.todos.push(todo);
+        self
+    }
+
+    pub fn with_scratchpad(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.state.scratchpad.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn with_metadata(mut self, metadata: ThreadMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.metadata.tags.insert(tag.into());
+        self
+    }
+
+    /// The state a thread seeded from this template starts with.
+    pub fn to_state(&self) -> AgentStateSnapshot {
+        let mut state = self.state.clone();
+        state
+            .preloaded_messages
+            .extend(self.messages.iter().cloned());
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_templates_round_trip_through_json() {
+        let template = ThreadTemplate::new()
+            .with_user_message("Where do I start?")
+            .with_agent_message("Connect a data source first.")
+            .with_file("/checklist.md", "1. Connect a source")
+            .with_scratchpad("plan", "trial")
+            .with_tag("onboarding");
+
+        let json = serde_json::to_value(&template).unwrap();
+        let parsed: ThreadTemplate = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), json);
+
+        let state = template.to_state();
+        assert_eq!(state.preloaded_messages.len(), 2);
+        assert_eq!(state.files["/checklist.md"], "1. Connect a source");
+        assert!(template.metadata.has_tag("onboarding"));
+    }
+}
@@ -0,0 +1,99 @@
+//! Tool results cached across runs and sessions.
+//!
+//! Tools that look up slowly changing data (library documentation, reference
+//! material) are often called with the same arguments again and again. A
+//! [`ToolResultCache`] keeps their serialized results under a key derived from the
+//! tool and its arguments, so later identical calls skip the round trip. Callers
+//! build the keys; folding a version into them invalidates outdated entries.
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Storage for cached tool results (in-memory, Redis, SQLite, ...).
+#[async_trait]
+pub trait ToolResultCache: Send + Sync {
+    /// The value stored under `key`, unless it is missing or expired.
+    async fn get(&self, key: &str) -> anyhow::Result<Option<String>>;
+
+    /// Store `value` under `key`, expiring after `ttl` when given.
+    async fn put(&self, key: &str, value: &str, ttl: Option<Duration>) -> anyhow::Result<()>;
+
+    /// Delete every entry whose key starts with `prefix`.
+    async fn invalidate(&self, prefix: &str) -> anyhow::Result<()>;
+}
+
+/// Cached values with their expiry, by key.
+type Entries = HashMap<String, (String, Option<Instant>)>;
+
+/// Process-local tool result cache for tests and single-process deployments.
+#[derive(Debug, Default)]
+pub struct InMemoryToolResultCache {
+    entries: Mutex<Entries>,
+}
+
+impl InMemoryToolResultCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> anyhow::Result<std::sync::MutexGuard<'_, Entries>> {
+        self.entries
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Tool result cache poisoned"))
+    }
+}
+
+#[async_trait]
+impl ToolResultCache for InMemoryToolResultCache {
+    async fn get(&self, key: &str) -> anyhow::Result<Option<String>> {
+        let mut entries = self.lock()?;
+        match entries.get(key) {
+            Some((_, Some(expires_at))) if *expires_at <= Instant::now() => {
+                entries.remove(key);
+                Ok(None)
+            }
+            Some((value, _)) => Ok(Some(value.clone())),
+            None => Ok(None),
+        }
+    }
+
+    async fn put(&self, key: &str, value: &str, ttl: Option<Duration>) -> anyhow::Result<()> {
+        let expires_at = ttl.map(|ttl| Instant::now() + ttl);
+        self.lock()?
+            .insert(key.to_string(), (value.to_string(), expires_at));
+        Ok(())
+    }
+
+    async fn invalidate(&self, prefix: &str) -> anyhow::Result<()> {
+        self.lock()?.retain(|key, _| !key.starts_with(prefix));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_entries_expire_and_invalidate_by_prefix() {
+        let cache = InMemoryToolResultCache::new();
+        cache.put("docs:react:1", "hooks", None).await.unwrap();
+        cache.put("docs:vue:1", "refs", None).await.unwrap();
+        cache
+            .put("docs:svelte:1", "runes", Some(Duration::ZERO))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            cache.get("docs:react:1").await.unwrap().as_deref(),
+            Some("hooks")
+        );
+        assert_eq!(cache.get("docs:svelte:1").await.unwrap(), None);
+
+        cache.invalidate("docs:react:").await.unwrap();
+        assert_eq!(cache.get("docs:react:1").await.unwrap(), None);
+        assert!(cache.get("docs:vue:1").await.unwrap().is_some());
+    }
+}
@@ -9,11 +9,13 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::any::{Any, TypeId};
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use crate::messaging::{AgentMessage, MessageContent, MessageMetadata, MessageRole};
+use crate::messaging::{AgentMessage, ImageData, MessageContent, MessageMetadata, MessageRole};
 use crate::state::AgentStateSnapshot;
+use crate::trace_context::TraceContext;
 
 /// JSON Schema definition for tool parameters
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -139,6 +141,415 @@ impl ToolParameterSchema {
             additional: HashMap::new(),
         }
     }
+
+    /// Create a string parameter restricted to a fixed set of values
+    pub fn string_enum<I, S>(description: impl Into<String>, values: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::string(description).with_enum_values(
+            values
+                .into_iter()
+                .map(|v| Value::String(v.into()))
+                .collect(),
+        )
+    }
+
+    /// Restrict this parameter to the given values
+    pub fn with_enum_values(mut self, values: Vec<Value>) -> Self {
+        self.enum_values = Some(values);
+        self
+    }
+
+    /// Set the default value
+    pub fn with_default(mut self, value: impl Into<Value>) -> Self {
+        self.default = Some(value.into());
+        self
+    }
+
+    /// Inclusive lower bound for number/integer parameters
+    pub fn with_minimum(self, minimum: impl Into<Value>) -> Self {
+        self.with_constraint("minimum", minimum)
+    }
+
+    /// Inclusive upper bound for number/integer parameters
+    pub fn with_maximum(self, maximum: impl Into<Value>) -> Self {
+        self.with_constraint("maximum", maximum)
+    }
+
+    /// Minimum length for string parameters
+    pub fn with_min_length(self, min_length: usize) -> Self {
+        self.with_constraint("minLength", min_length)
+    }
+
+    /// Maximum length for string parameters
+    pub fn with_max_length(self, max_length: usize) -> Self {
+        self.with_constraint("maxLength", max_length)
+    }
+
+    /// Regular expression that string parameters must match
+    pub fn with_pattern(self, pattern: impl Into<String>) -> Self {
+        self.with_constraint("pattern", pattern.into())
+    }
+
+    /// Well-known string format (e.g. `date-time`, `email`, `uri`, `uuid`)
+    pub fn with_format(self, format: impl Into<String>) -> Self {
+        self.with_constraint("format", format.into())
+    }
+
+    /// Minimum number of items for array parameters
+    pub fn with_min_items(self, min_items: usize) -> Self {
+        self.with_constraint("minItems", min_items)
+    }
+
+    /// Maximum number of items for array parameters
+    pub fn with_max_items(self, max_items: usize) -> Self {
+        self.with_constraint("maxItems", max_items)
+    }
+
+    /// Set an arbitrary JSON Schema keyword
+    pub fn with_constraint(mut self, keyword: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.additional.insert(keyword.into(), value.into());
+        self
+    }
+
+    /// Check `value` against this schema, returning every mismatch found.
+    ///
+    /// Covers `type`, `required`, `properties`, `additionalProperties: false`,
+    /// `items`, `enum` and the numeric, length, pattern and item-count constraints.
+    /// Optional properties may be `null`.
+    pub fn validate(&self, value: &Value) -> Result<(), Vec<ArgumentError>> {
+        let mut errors = Vec::new();
+        self.collect_errors("", value, &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn collect_errors(&self, path: &str, value: &Value, errors: &mut Vec<ArgumentError>) {
+        let mut error = |message: String| {
+            errors.push(ArgumentError {
+                path: path.to_string(),
+                message,
+            })
+        };
+
+        let type_matches = match self.schema_type.as_str() {
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            "string" => value.is_string(),
+            "boolean" => value.is_boolean(),
+            "null" => value.is_null(),
+            "number" => value.is_number(),
+            "integer" => {
+                value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+            }
+            _ => true,
+        };
+        if !type_matches {
+            error(format!(
+                "expected {}, got {}",
+                self.schema_type,
+                json_type_name(value)
+            ));
+            return;
+        }
+
+        if let Some(allowed) = &self.enum_values {
+            if !allowed.contains(value) {
+                let allowed: Vec<_> = allowed.iter().map(Value::to_string).collect();
+                error(format!("must be one of {}", allowed.join(", ")));
+            }
+        }
+
+        let constraint = |keyword: &str| self.additional.get(keyword);
+        if let Some(n) = value.as_f64() {
+            if let Some(min) = constraint("minimum").and_then(Value::as_f64) {
+                if n < min {
+                    error(format!("must be at least {}", min));
+                }
+            }
+            if let Some(max) = constraint("maximum").and_then(Value::as_f64) {
+                if n > max {
+                    error(format!("must be at most {}", max));
+                }
+            }
+        }
+        if let Some(text) = value.as_str() {
+            let len = text.chars().count() as u64;
+            if let Some(min) = constraint("minLength").and_then(Value::as_u64) {
+                if len < min {
+                    error(format!("must be at least {} characters", min));
+                }
+            }
+            if let Some(max) = constraint("maxLength").and_then(Value::as_u64) {
+                if len > max {
+                    error(format!("must be at most {} characters", max));
+                }
+            }
+            if let Some(pattern) = constraint("pattern").and_then(Value::as_str) {
+                if regex::Regex::new(pattern).is_ok_and(|re| !re.is_match(text)) {
+                    error(format!("must match the pattern `{}`", pattern));
+                }
+            }
+        }
+        if let Some(items) = value.as_array() {
+            let len = items.len() as u64;
+            if let Some(min) = constraint("minItems").and_then(Value::as_u64) {
+                if len < min {
+                    error(format!("must have at least {} items", min));
+                }
+            }
+            if let Some(max) = constraint("maxItems").and_then(Value::as_u64) {
+                if len > max {
+                    error(format!("must have at most {} items", max));
+                }
+            }
+            if let Some(item_schema) = &self.items {
+                for (i, item) in items.iter().enumerate() {
+                    item_schema.collect_errors(&format!("{}[{}]", path, i), item, errors);
+                }
+            }
+        }
+
+        if let Some(fields) = value.as_object() {
+            let required = self.required.as_deref().unwrap_or_default();
+            let field_path = |name: &str| {
+                if path.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{}.{}", path, name)
+                }
+            };
+            for name in required {
+                if fields.get(name).is_none_or(Value::is_null) {
+                    errors.push(ArgumentError {
+                        path: field_path(name),
+                        message: "required parameter is missing".to_string(),
+                    });
+                }
+            }
+            let closed = constraint("additionalProperties") == Some(&Value::Bool(false));
+            let mut names: Vec<_> = fields.keys().collect();
+            names.sort();
+            for name in names {
+                let field = &fields[name];
+                match self.properties.as_ref().and_then(|p| p.get(name)) {
+                    Some(_) if field.is_null() && !required.contains(name) => {}
+                    Some(schema) => schema.collect_errors(&field_path(name), field, errors),
+                    None if closed => errors.push(ArgumentError {
+                        path: field_path(name),
+                        message: "unknown parameter".to_string(),
+                    }),
+                    None => {}
+                }
+            }
+        }
+    }
+}
+
+fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// A tool argument that does not match the tool's parameter schema.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArgumentError {
+    /// Location of the argument, e.g. `filters.limit` or `tags[2]`; empty for the root
+    pub path: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for ArgumentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "arguments: {}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// Rust types that know their JSON Schema representation as a tool parameter.
+///
+/// Implemented for primitives, `String`, `Vec<T>` and `Option<T>`. Derive it for
+/// unit-only enums with `#[derive(ToolEnum)]` so the `#[tool]` macro exposes the
+/// variants as a JSON Schema `enum` instead of a free-form string.
+pub trait ToolParameterType {
+    /// Schema for a parameter of this type with the given description
+    fn parameter_schema(description: &str) -> ToolParameterSchema;
+}
+
+impl ToolParameterType for String {
+    fn parameter_schema(description: &str) -> ToolParameterSchema {
+        ToolParameterSchema::string(description)
+    }
+}
+
+impl ToolParameterType for bool {
+    fn parameter_schema(description: &str) -> ToolParameterSchema {
+        ToolParameterSchema::boolean(description)
+    }
+}
+
+macro_rules! impl_parameter_type {
+    ($ctor:ident: $($ty:ty),*) => {
+        $(
+            impl ToolParameterType for $ty {
+                fn parameter_schema(description: &str) -> ToolParameterSchema {
+                    ToolParameterSchema::$ctor(description)
+                }
+            }
+        )*
+    };
+}
+
+impl_parameter_type!(integer: i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+impl_parameter_type!(number: f32, f64);
+
+impl<T: ToolParameterType> ToolParameterType for Vec<T> {
+    fn parameter_schema(description: &str) -> ToolParameterSchema {
+        ToolParameterSchema::array(description, T::parameter_schema(description))
+    }
+}
+
+impl<T: ToolParameterType> ToolParameterType for Option<T> {
+    fn parameter_schema(description: &str) -> ToolParameterSchema {
+        T::parameter_schema(description)
+    }
+}
+
+/// A struct describing all arguments of a tool.
+///
+/// Derive it with `#[derive(ToolArgs)]` (alongside `serde::Deserialize`) and pass
+/// the struct to a `#[tool(args = MyArgs)]` function instead of listing many
+/// positional parameters. Field doc comments become parameter descriptions.
+pub trait ToolArgs: serde::de::DeserializeOwned {
+    /// Object schema describing the fields of this struct
+    fn parameters_schema() -> ToolParameterSchema;
+
+    /// Deserialize the arguments the model sent for a tool call
+    fn from_args(args: Value) -> anyhow::Result<Self> {
+        serde_json::from_value(args).map_err(|e| anyhow::anyhow!("Invalid tool arguments: {}", e))
+    }
+}
+
+/// Support code for the `#[tool]` macro; not part of the public API.
+#[doc(hidden)]
+pub mod __private {
+    use super::{ToolParameterSchema, ToolParameterType};
+    use std::marker::PhantomData;
+
+    /// Resolves to the `ToolParameterType` schema when the type implements it and
+    /// to a plain string schema otherwise. Call it as
+    /// `(&&SchemaProbe::<T>(PhantomData)).parameter_schema(..)`: method resolution
+    /// tries the `&&` receiver (`ViaParameterType`) before auto-deref reaches the
+    /// `&` receiver (`ViaStringFallback`).
+    pub struct SchemaProbe<T>(pub PhantomData<T>);
+
+    pub trait ViaParameterType {
+        fn parameter_schema(&self, description: &str) -> ToolParameterSchema;
+    }
+
+    impl<T: ToolParameterType> ViaParameterType for &SchemaProbe<T> {
+        fn parameter_schema(&self, description: &str) -> ToolParameterSchema {
+            T::parameter_schema(description)
+        }
+    }
+
+    pub trait ViaStringFallback {
+        fn parameter_schema(&self, description: &str) -> ToolParameterSchema;
+    }
+
+    impl<T> ViaStringFallback for SchemaProbe<T> {
+        fn parameter_schema(&self, description: &str) -> ToolParameterSchema {
+            ToolParameterSchema::string(description)
+        }
+    }
+}
+
+/// Deprecation details for a tool that is being phased out
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolDeprecation {
+    /// Migration guidance shown to the model (e.g. "Use search_v2 with a `query` argument")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+
+    /// Name of the tool that replaces this one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+
+    /// Version in which the tool was deprecated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+}
+
+impl ToolDeprecation {
+    /// Create a deprecation with migration guidance
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: Some(message.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Name the tool that replaces the deprecated one
+    pub fn with_replacement(mut self, replacement: impl Into<String>) -> Self {
+        self.replacement = Some(replacement.into());
+        self
+    }
+
+    /// Record the version in which the tool was deprecated
+    pub fn since(mut self, version: impl Into<String>) -> Self {
+        self.since = Some(version.into());
+        self
+    }
+}
+
+/// A sample call of a tool, shown to the model as a few-shot example
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolExample {
+    /// When this call is appropriate (e.g. "Find open invoices for a customer")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Arguments of the call
+    pub args: Value,
+
+    /// What the tool returns for this call, abbreviated if long
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+}
+
+impl ToolExample {
+    pub fn new(args: Value) -> Self {
+        Self {
+            description: None,
+            args,
+            result: None,
+        }
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn with_result(mut self, result: impl Into<String>) -> Self {
+        self.result = Some(result.into());
+        self
+    }
 }
 
 /// Complete schema definition for a tool
@@ -152,6 +563,23 @@ pub struct ToolSchema {
 
     /// Input parameter schema (typically an object with properties)
     pub parameters: ToolParameterSchema,
+
+    /// Schema version, bumped when the parameters change incompatibly
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+
+    /// Set when the tool is deprecated; guidance is injected into the model-facing description
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecation: Option<ToolDeprecation>,
+
+    /// Few-shot usage examples rendered into the system prompt
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<ToolExample>,
+
+    /// Arguments the tool is called with by agent self-tests; set only on tools that
+    /// are safe to call outside a conversation (read-only, no side effects)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub self_test_args: Option<Value>,
 }
 
 impl ToolSchema {
@@ -164,9 +592,69 @@ impl ToolSchema {
             name: name.into(),
             description: description.into(),
             parameters,
+            version: None,
+            deprecation: None,
+            examples: Vec::new(),
+            self_test_args: None,
         }
     }
 
+    /// Set the schema version
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// Mark the tool as deprecated
+    pub fn deprecated(mut self, deprecation: ToolDeprecation) -> Self {
+        self.deprecation = Some(deprecation);
+        self
+    }
+
+    /// Attach a few-shot usage example
+    pub fn with_example(mut self, example: ToolExample) -> Self {
+        self.examples.push(example);
+        self
+    }
+
+    /// Mark the tool safe to call from agent self-tests, with `args`
+    pub fn with_self_test_args(mut self, args: Value) -> Self {
+        self.self_test_args = Some(args);
+        self
+    }
+
+    /// Whether the tool is deprecated
+    pub fn is_deprecated(&self) -> bool {
+        self.deprecation.is_some()
+    }
+
+    /// Guidance text describing the deprecation, if any
+    pub fn deprecation_notice(&self) -> Option<String> {
+        let deprecation = self.deprecation.as_ref()?;
+        let mut notice = String::from("DEPRECATED");
+        if let Some(since) = &deprecation.since {
+            notice.push_str(&format!(" since {}", since));
+        }
+        notice.push('.');
+        if let Some(replacement) = &deprecation.replacement {
+            notice.push_str(&format!(" Use `{}` instead.", replacement));
+        }
+        if let Some(message) = &deprecation.message {
+            notice.push(' ');
+            notice.push_str(message);
+        }
+        Some(notice)
+    }
+
+    /// Copy of this schema whose description carries the deprecation guidance,
+    /// so the model prefers the replacement. Non-deprecated schemas are returned as-is.
+    pub fn with_deprecation_guidance(mut self) -> Self {
+        if let Some(notice) = self.deprecation_notice() {
+            self.description = format!("[{}] {}", notice, self.description);
+        }
+        self
+    }
+
     /// Create a tool schema with no parameters
     pub fn no_params(name: impl Into<String>, description: impl Into<String>) -> Self {
         Self {
@@ -182,10 +670,75 @@ impl ToolSchema {
                 default: None,
                 additional: HashMap::new(),
             },
+            version: None,
+            deprecation: None,
+            examples: Vec::new(),
+            self_test_args: None,
         }
     }
 }
 
+/// Typed map of shared dependencies (database pools, HTTP clients, configs)
+/// available to every tool call, keyed by type.
+///
+/// Populated at agent build time with `with_tool_state()` and read from
+/// [`ToolContext::extension`]. Cloning is cheap: values are stored behind `Arc`.
+#[derive(Clone, Default)]
+pub struct ToolExtensions {
+    map: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl ToolExtensions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert a value, replacing any previous value of the same type
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) {
+        self.map.insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
+    /// Insert an already shared value; it is retrieved as `T`, not `Arc<T>`
+    pub fn insert_arc<T: Send + Sync + 'static>(&mut self, value: Arc<T>) {
+        self.map.insert(TypeId::of::<T>(), value);
+    }
+
+    /// Borrow the value of type `T`, if present
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.map
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref::<T>())
+    }
+
+    /// Shared handle to the value of type `T`, if present
+    pub fn get_arc<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        self.map
+            .get(&TypeId::of::<T>())
+            .cloned()
+            .and_then(|value| value.downcast::<T>().ok())
+    }
+
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl std::fmt::Debug for ToolExtensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolExtensions")
+            .field("len", &self.map.len())
+            .finish()
+    }
+}
+
 /// Context provided to tool implementations for accessing agent state and utilities
 #[derive(Clone)]
 pub struct ToolContext {
@@ -197,6 +750,12 @@ pub struct ToolContext {
 
     /// Tool invocation metadata (call ID for responses)
     pub tool_call_id: Option<String>,
+
+    /// Shared dependencies registered on the agent
+    pub extensions: ToolExtensions,
+
+    /// Trace context of this tool call, for propagating to outgoing requests
+    pub trace: Option<TraceContext>,
 }
 
 impl ToolContext {
@@ -206,6 +765,8 @@ impl ToolContext {
             state,
             state_handle: None,
             tool_call_id: None,
+            extensions: ToolExtensions::default(),
+            trace: None,
         }
     }
 
@@ -218,6 +779,8 @@ impl ToolContext {
             state,
             state_handle: Some(state_handle),
             tool_call_id: None,
+            extensions: ToolExtensions::default(),
+            trace: None,
         }
     }
 
@@ -227,6 +790,82 @@ impl ToolContext {
         self
     }
 
+    /// Attach the shared dependencies available to the tool
+    pub fn with_extensions(mut self, extensions: ToolExtensions) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    /// Attach the trace context of this tool call
+    pub fn with_trace(mut self, trace: TraceContext) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
+    /// A fresh UUID for records the tool creates. In a deterministic run it comes
+    /// from the run's seeded generator, so repeated runs create the same ids.
+    pub fn new_uuid(&self) -> uuid::Uuid {
+        crate::determinism::next_uuid()
+    }
+
+    /// The run's own key for `provider` when the caller passed provider credentials,
+    /// for tools that call the provider themselves (embeddings, images, ...).
+    pub fn api_key(&self, provider: &str) -> Option<crate::credentials::ApiKey> {
+        crate::credentials::current_api_key(provider)
+    }
+
+    /// Borrow a shared dependency registered with `with_tool_state()`
+    pub fn extension<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.extensions.get::<T>()
+    }
+
+    /// Like [`extension`](Self::extension), but fails with a descriptive error when
+    /// the dependency was not registered
+    pub fn require_extension<T: Send + Sync + 'static>(&self) -> anyhow::Result<&T> {
+        self.extension::<T>().ok_or_else(|| {
+            anyhow::anyhow!(
+                "Tool state `{}` is not registered; add it with with_tool_state()",
+                std::any::type_name::<T>()
+            )
+        })
+    }
+
+    /// Contents of the file at `path` in the agent's filesystem. Files deferred by a
+    /// lazily loaded thread are fetched from its checkpointer and kept in the state.
+    pub async fn read_file(&self, path: &str) -> anyhow::Result<Option<String>> {
+        if let Some(contents) = self.state.files.get(path) {
+            return Ok(Some(contents.clone()));
+        }
+        if !self.state.deferred_files.contains(path) {
+            return Ok(None);
+        }
+        // Fetched earlier in this call
+        if let Some(handle) = &self.state_handle {
+            let state = handle
+                .read()
+                .map_err(|_| anyhow::anyhow!("Failed to read agent state"))?;
+            if let Some(contents) = state.files.get(path) {
+                return Ok(Some(contents.clone()));
+            }
+        }
+        let Some(loader) = crate::persistence::current_file_loader() else {
+            anyhow::bail!(
+                "File '{}' was not loaded and no file loader is available",
+                path
+            );
+        };
+        let contents = loader.load(path).await?;
+        if let (Some(contents), Some(handle)) = (&contents, &self.state_handle) {
+            let mut state = handle
+                .write()
+                .map_err(|_| anyhow::anyhow!("Failed to write agent state"))?;
+            if state.deferred_files.contains(path) {
+                state.insert_file(path, contents.clone());
+            }
+        }
+        Ok(contents)
+    }
+
     /// Create a tool response message with proper metadata
     pub fn text_response(&self, content: impl Into<String>) -> AgentMessage {
         AgentMessage {
@@ -235,6 +874,8 @@ impl ToolContext {
             metadata: self.tool_call_id.as_ref().map(|id| MessageMetadata {
                 tool_call_id: Some(id.clone()),
                 cache_control: None,
+                traceparent: None,
+                correlation_id: None,
             }),
         }
     }
@@ -247,9 +888,19 @@ impl ToolContext {
             metadata: self.tool_call_id.as_ref().map(|id| MessageMetadata {
                 tool_call_id: Some(id.clone()),
                 cache_control: None,
+                traceparent: None,
+                correlation_id: None,
             }),
         }
     }
+
+    /// Create a tool response carrying images, for vision models
+    pub fn image_response(&self, text: Option<String>, images: Vec<ImageData>) -> AgentMessage {
+        AgentMessage {
+            content: MessageContent::Image { text, images },
+            ..self.text_response(String::new())
+        }
+    }
 }
 
 /// Result of a tool invocation
@@ -298,6 +949,31 @@ pub trait Tool: Send + Sync {
 /// Type alias for boxed tool instances
 pub type ToolBox = Arc<dyn Tool>;
 
+/// Source of tools backed by external processes or services, such as MCP servers.
+///
+/// Agents call [`tools`](ToolProvider::tools) at the start of every run, so a provider
+/// can start its services on first use, and [`shutdown`](ToolProvider::shutdown) when
+/// the agent shuts down.
+#[async_trait]
+pub trait ToolProvider: Send + Sync {
+    /// Tools currently available, starting the backing services if needed.
+    async fn tools(&self) -> anyhow::Result<Vec<ToolBox>>;
+
+    /// Stop the backing services.
+    async fn shutdown(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Connect to each backing service, reporting per service its name and the
+    /// number of tools it offers, or why it is unavailable. Used by agent self-tests.
+    async fn check(&self) -> Vec<(String, anyhow::Result<usize>)> {
+        vec![(
+            "tool provider".to_string(),
+            self.tools().await.map(|tools| tools.len()),
+        )]
+    }
+}
+
 /// Tool registry for managing and discovering available tools
 #[derive(Clone, Default)]
 pub struct ToolRegistry {
@@ -365,3 +1041,204 @@ impl ToolRegistry {
         self.tools.is_empty()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_defaults_to_unversioned_and_active() {
+        let schema = ToolSchema::no_params("ping", "Ping the server");
+        assert!(schema.version.is_none());
+        assert!(!schema.is_deprecated());
+        assert!(schema.deprecation_notice().is_none());
+
+        let json = serde_json::to_value(&schema).unwrap();
+        assert!(json.get("version").is_none());
+        assert!(json.get("deprecation").is_none());
+    }
+
+    #[test]
+    fn deprecation_guidance_is_injected_into_description() {
+        let schema = ToolSchema::no_params("search", "Search the web")
+            .with_version("1.2.0")
+            .deprecated(
+                ToolDeprecation::new("Pass `query` instead of `q`.")
+                    .with_replacement("search_v2")
+                    .since("2.0.0"),
+            );
+
+        assert_eq!(
+            schema.deprecation_notice().unwrap(),
+            "DEPRECATED since 2.0.0. Use `search_v2` instead. Pass `query` instead of `q`."
+        );
+        let guided = schema.with_deprecation_guidance();
+        assert!(guided.description.starts_with("[DEPRECATED since 2.0.0."));
+        assert!(guided.description.ends_with("Search the web"));
+    }
+
+    #[test]
+    fn validate_reports_every_argument_mismatch() {
+        let schema = ToolParameterSchema::object(
+            "search parameters",
+            HashMap::from([
+                (
+                    "query".to_string(),
+                    ToolParameterSchema::string("Query").with_min_length(3),
+                ),
+                (
+                    "limit".to_string(),
+                    ToolParameterSchema::integer("Page size").with_maximum(100),
+                ),
+                (
+                    "tags".to_string(),
+                    ToolParameterSchema::array("Tags", ToolParameterSchema::string("Tag")),
+                ),
+                (
+                    "sort".to_string(),
+                    ToolParameterSchema::string_enum("Sort order", ["asc", "desc"]),
+                ),
+            ]),
+            vec!["query".to_string(), "limit".to_string()],
+        );
+
+        assert!(schema
+            .validate(&serde_json::json!({"query": "rust", "limit": 10, "sort": null}))
+            .is_ok());
+
+        let errors = schema
+            .validate(&serde_json::json!({
+                "limit": 500,
+                "tags": ["ok", 7],
+                "sort": "random",
+            }))
+            .unwrap_err();
+        let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "query: required parameter is missing",
+                "limit: must be at most 100",
+                "sort: must be one of \"asc\", \"desc\"",
+                "tags[1]: expected string, got integer",
+            ]
+        );
+
+        let errors = schema.validate(&serde_json::json!("rust")).unwrap_err();
+        assert_eq!(
+            errors[0].to_string(),
+            "arguments: expected object, got string"
+        );
+    }
+
+    #[test]
+    fn constraints_serialize_as_json_schema_keywords() {
+        let schema = ToolParameterSchema::integer("Page size")
+            .with_minimum(1)
+            .with_maximum(100)
+            .with_default(20);
+        let json = serde_json::to_value(&schema).unwrap();
+        assert_eq!(json["minimum"], 1);
+        assert_eq!(json["maximum"], 100);
+        assert_eq!(json["default"], 20);
+
+        let schema = ToolParameterSchema::string("Email")
+            .with_format("email")
+            .with_pattern("^.+@.+$")
+            .with_max_length(254);
+        let json = serde_json::to_value(&schema).unwrap();
+        assert_eq!(json["format"], "email");
+        assert_eq!(json["pattern"], "^.+@.+$");
+        assert_eq!(json["maxLength"], 254);
+    }
+
+    #[test]
+    fn string_enum_lists_allowed_values() {
+        let schema = ToolParameterSchema::string_enum("Unit", ["celsius", "fahrenheit"]);
+        let json = serde_json::to_value(&schema).unwrap();
+        assert_eq!(json["type"], "string");
+        assert_eq!(json["enum"], serde_json::json!(["celsius", "fahrenheit"]));
+    }
+
+    #[test]
+    fn parameter_type_maps_vec_to_array_items() {
+        let schema = <Vec<u32> as ToolParameterType>::parameter_schema("Ids");
+        assert_eq!(schema.schema_type, "array");
+        assert_eq!(schema.items.unwrap().schema_type, "integer");
+    }
+
+    struct DbPool {
+        url: String,
+    }
+
+    #[test]
+    fn tool_context_exposes_registered_extensions() {
+        let mut extensions = ToolExtensions::new();
+        extensions.insert(DbPool {
+            url: "postgres://localhost".into(),
+        });
+        extensions.insert_arc(Arc::new(42u32));
+
+        let ctx = ToolContext::new(Arc::new(AgentStateSnapshot::default()))
+            .with_extensions(extensions.clone());
+        assert_eq!(
+            ctx.extension::<DbPool>().unwrap().url,
+            "postgres://localhost"
+        );
+        assert_eq!(*extensions.get_arc::<u32>().unwrap(), 42);
+        assert!(ctx.extension::<String>().is_none());
+
+        let err = ctx.require_extension::<String>().unwrap_err();
+        assert!(err.to_string().contains("alloc::string::String"));
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct SearchArgs {
+        query: String,
+        limit: Option<u32>,
+    }
+
+    impl ToolArgs for SearchArgs {
+        fn parameters_schema() -> ToolParameterSchema {
+            let mut properties = HashMap::new();
+            properties.insert("query".to_string(), ToolParameterSchema::string("Query"));
+            properties.insert("limit".to_string(), ToolParameterSchema::integer("Limit"));
+            ToolParameterSchema::object("Search", properties, vec!["query".to_string()])
+        }
+    }
+
+    #[test]
+    fn tool_args_reports_invalid_arguments() {
+        let args = SearchArgs::from_args(serde_json::json!({"query": "rust"})).unwrap();
+        assert_eq!(args.query, "rust");
+        assert!(args.limit.is_none());
+
+        let err = SearchArgs::from_args(serde_json::json!({"limit": 3})).unwrap_err();
+        assert!(err.to_string().contains("Invalid tool arguments"));
+    }
+
+    #[test]
+    #[allow(clippy::needless_borrow)]
+    fn schema_probe_falls_back_to_string() {
+        use __private::{SchemaProbe, ViaParameterType as _, ViaStringFallback as _};
+        use std::marker::PhantomData;
+
+        struct Opaque;
+        let schema = (&&SchemaProbe::<Opaque>(PhantomData)).parameter_schema("x");
+        assert_eq!(schema.schema_type, "string");
+        let schema = (&&SchemaProbe::<f64>(PhantomData)).parameter_schema("x");
+        assert_eq!(schema.schema_type, "number");
+    }
+
+    #[test]
+    fn legacy_schema_json_deserializes_without_version() {
+        let json = serde_json::json!({
+            "name": "legacy",
+            "description": "Old tool",
+            "parameters": {"type": "object"}
+        });
+        let schema: ToolSchema = serde_json::from_value(json).unwrap();
+        assert!(schema.version.is_none());
+        assert!(schema.deprecation.is_none());
+    }
+}
@@ -227,6 +227,50 @@ pub fn format_tool_call_toon(
     serde_json::to_string_pretty(&value).map_err(ToonEncodeError::from)
 }
 
+/// Decode TOON text into a JSON value, the inverse of [`ToonEncoder::encode_json`]
+/// with default options
+#[cfg(feature = "toon")]
+pub fn decode_json(input: &str) -> Result<serde_json::Value, ToonEncodeError> {
+    toon_format::decode_default(input).map_err(ToonEncodeError::from)
+}
+
+/// Decode JSON text (fallback when the toon feature is disabled)
+#[cfg(not(feature = "toon"))]
+pub fn decode_json(input: &str) -> Result<serde_json::Value, ToonEncodeError> {
+    serde_json::from_str(input).map_err(ToonEncodeError::from)
+}
+
+/// Re-encode structured message content as TOON before it enters a prompt
+///
+/// Applies to JSON content and to text holding a JSON object or array, such as
+/// tool results and sub-agent responses. Returns `None` for other text, and
+/// always when the `toon` feature is disabled (the JSON fallback would not be
+/// any shorter).
+pub fn content_to_toon(content: &crate::messaging::MessageContent) -> Option<String> {
+    use crate::messaging::MessageContent;
+
+    if !cfg!(feature = "toon") {
+        return None;
+    }
+    let parsed;
+    let value = match content {
+        MessageContent::Json(value) => value,
+        MessageContent::Text(text) => {
+            let trimmed = text.trim_start();
+            if !(trimmed.starts_with('{') || trimmed.starts_with('[')) {
+                return None;
+            }
+            parsed = serde_json::from_str::<serde_json::Value>(text).ok()?;
+            &parsed
+        }
+        MessageContent::Image { .. } => return None,
+    };
+    if !(value.is_object() || value.is_array()) {
+        return None;
+    }
+    ToonEncoder::new().encode_json(value).ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -272,4 +316,38 @@ mod tests {
         assert!(result.contains("search"));
         assert!(result.contains("rust"));
     }
+
+    #[test]
+    fn plain_text_content_is_left_alone() {
+        use crate::messaging::MessageContent;
+
+        assert!(content_to_toon(&MessageContent::Text("All done.".into())).is_none());
+        assert!(content_to_toon(&MessageContent::Text("{not json".into())).is_none());
+        assert!(content_to_toon(&MessageContent::Json(json!("scalar"))).is_none());
+    }
+
+    #[cfg(feature = "toon")]
+    #[test]
+    fn structured_content_round_trips_through_toon() {
+        use crate::messaging::MessageContent;
+
+        let payloads = [
+            json!({"orders": [
+                {"id": "o-1", "total": 12.5, "note": "rush, gift wrap"},
+                {"id": "o-2", "total": 7.25, "note": ""}
+            ]}),
+            json!({"user": {"name": "Alice", "tags": ["admin", "ops"]}, "active": true}),
+            json!([{"path": "/src/main.rs", "lines": 120}, {"path": "/README.md", "lines": 40}]),
+        ];
+        for payload in payloads {
+            let json_text = payload.to_string();
+            let toon = content_to_toon(&MessageContent::Json(payload.clone())).unwrap();
+            assert!(toon.len() < json_text.len(), "{toon}");
+            assert_eq!(decode_json(&toon).unwrap(), payload);
+            assert_eq!(
+                content_to_toon(&MessageContent::Text(json_text)).as_deref(),
+                Some(toon.as_str())
+            );
+        }
+    }
 }
@@ -0,0 +1,164 @@
+//! W3C trace context carried through agent runs, tool calls and sub-agents.
+//!
+//! Every run executes inside a [`TraceContext`] scope: a child of the `traceparent`
+//! found on the incoming message metadata, of the context of the enclosing run
+//! (for sub-agents), or a fresh root. Tools receive the context of their own span
+//! in [`ToolContext::trace`](crate::tools::ToolContext::trace) and can forward it on
+//! outgoing requests with [`TraceContext::traceparent`].
+
+use std::future::Future;
+
+tokio::task_local! {
+    static CURRENT: TraceContext;
+}
+
+/// A position in a distributed trace, as carried by the W3C `traceparent` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    /// 32 lowercase hex characters shared by every span of the trace
+    pub trace_id: String,
+    /// 16 lowercase hex characters identifying this span
+    pub span_id: String,
+    /// Span this one was started from, if any
+    pub parent_span_id: Option<String>,
+    pub sampled: bool,
+    /// Vendor-specific `tracestate` header, forwarded unchanged
+    pub trace_state: Option<String>,
+}
+
+impl TraceContext {
+    /// Start a new sampled trace.
+    pub fn new_root() -> Self {
+        Self {
+            trace_id: uuid::Uuid::new_v4().simple().to_string(),
+            span_id: new_span_id(),
+            parent_span_id: None,
+            sampled: true,
+            trace_state: None,
+        }
+    }
+
+    /// Parse a `traceparent` header (`00-<trace-id>-<parent-id>-<flags>`).
+    ///
+    /// Returns `None` for malformed headers, all-zero ids and the reserved `ff`
+    /// version, in which case the caller should start a new trace.
+    pub fn from_traceparent(header: &str) -> Option<Self> {
+        let mut parts = header.trim().split('-');
+        let version = parts.next()?;
+        let trace_id = parts.next()?;
+        let span_id = parts.next()?;
+        let flags = parts.next()?;
+        // Future versions may append fields; version 00 may not
+        if version == "00" && parts.next().is_some() {
+            return None;
+        }
+
+        let valid = is_hex(version, 2)
+            && version != "ff"
+            && is_hex(trace_id, 32)
+            && is_hex(span_id, 16)
+            && is_hex(flags, 2)
+            && trace_id.bytes().any(|b| b != b'0')
+            && span_id.bytes().any(|b| b != b'0');
+        if !valid {
+            return None;
+        }
+
+        Some(Self {
+            trace_id: trace_id.to_string(),
+            span_id: span_id.to_string(),
+            parent_span_id: None,
+            sampled: u8::from_str_radix(flags, 16).ok()? & 1 == 1,
+            trace_state: None,
+        })
+    }
+
+    /// Attach the `tracestate` header that arrived with the `traceparent`.
+    pub fn with_trace_state(mut self, trace_state: impl Into<String>) -> Self {
+        self.trace_state = Some(trace_state.into());
+        self
+    }
+
+    /// A new span in the same trace, started from this one.
+    pub fn child(&self) -> Self {
+        Self {
+            trace_id: self.trace_id.clone(),
+            span_id: new_span_id(),
+            parent_span_id: Some(self.span_id.clone()),
+            sampled: self.sampled,
+            trace_state: self.trace_state.clone(),
+        }
+    }
+
+    /// Format as a `traceparent` header naming this span as the parent.
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{}-{}-{}",
+            self.trace_id,
+            self.span_id,
+            if self.sampled { "01" } else { "00" }
+        )
+    }
+
+    /// The context of the run or tool call currently executing on this task, if any.
+    pub fn current() -> Option<Self> {
+        CURRENT.try_with(|trace| trace.clone()).ok()
+    }
+
+    /// Run `future` with this context as [`current`](Self::current).
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT.scope(self, future).await
+    }
+}
+
+fn new_span_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()[..16].to_string()
+}
+
+fn is_hex(value: &str, len: usize) -> bool {
+    value.len() == len
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn traceparent_round_trips_and_rejects_invalid_headers() {
+        let header = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let trace = TraceContext::from_traceparent(header).unwrap();
+        assert_eq!(trace.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert!(trace.sampled);
+        assert_eq!(trace.traceparent(), header);
+
+        let child = trace.child();
+        assert_eq!(child.trace_id, trace.trace_id);
+        assert_eq!(child.parent_span_id.as_deref(), Some("00f067aa0ba902b7"));
+        assert_ne!(child.span_id, trace.span_id);
+
+        for invalid in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+        ] {
+            assert!(
+                TraceContext::from_traceparent(invalid).is_none(),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn scope_sets_the_current_context() {
+        assert!(TraceContext::current().is_none());
+        let root = TraceContext::new_root();
+        let seen = root.clone().scope(async { TraceContext::current() }).await;
+        assert_eq!(seen, Some(root));
+    }
+}
@@ -0,0 +1,152 @@
+//! Structured profile of the user an agent is talking to.
+//!
+//! Stable facts the user states about themselves (name, preferences, vehicle,
+//! account ids, ...) are kept in the thread state as a [`UserProfile`], one entry
+//! per key, each with the model's confidence and the message it came from.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// One fact about the user, e.g. `vehicle = 2019 Honda Civic`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfileFact {
+    pub value: String,
+    /// How sure the extraction was, from 0.0 to 1.0
+    pub confidence: f32,
+    /// Excerpt of the user message the fact was taken from
+    pub source: String,
+    pub updated_at: DateTime<Utc>,
+    /// Category assigned by a memory policy, e.g. `contact`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// When the fact must be forgotten
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl ProfileFact {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// Facts about the user keyed by snake_case name, such as `name`, `vehicle` or
+/// `account_id`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UserProfile {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub facts: BTreeMap<String, ProfileFact>,
+    /// Hash of the last user message facts were extracted from, so each message is
+    /// read once even though the model is called several times per turn
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_message_hash: Option<u64>,
+}
+
+impl UserProfile {
+    pub fn is_empty(&self) -> bool {
+        self.facts.is_empty() && self.last_message_hash.is_none()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&ProfileFact> {
+        self.facts.get(key)
+    }
+
+    /// Record a fact. A new value for a known key replaces the old one unless its
+    /// confidence is lower; repeating the same value keeps the higher confidence.
+    /// Returns whether the profile changed.
+    pub fn set(&mut self, key: impl Into<String>, fact: ProfileFact) -> bool {
+        let key = key.into();
+        match self.facts.get_mut(&key) {
+            Some(existing) if existing.value.eq_ignore_ascii_case(&fact.value) => {
+                existing.confidence = existing.confidence.max(fact.confidence);
+                existing.updated_at = fact.updated_at;
+                existing.category = fact.category;
+                existing.expires_at = fact.expires_at;
+                false
+            }
+            Some(existing) if existing.confidence > fact.confidence => false,
+            _ => {
+                self.facts.insert(key, fact);
+                true
+            }
+        }
+    }
+
+    /// Drop the least confident (then oldest) facts beyond `max`.
+    pub fn truncate(&mut self, max: usize) {
+        while self.facts.len() > max {
+            let weakest = self
+                .facts
+                .iter()
+                .min_by(|(_, a), (_, b)| {
+                    a.confidence
+                        .total_cmp(&b.confidence)
+                        .then(a.updated_at.cmp(&b.updated_at))
+                })
+                .map(|(key, _)| key.clone());
+            match weakest {
+                Some(key) => self.facts.remove(&key),
+                None => break,
+            };
+        }
+    }
+
+    /// Remove the facts expired at `now`, returning their keys.
+    pub fn purge_expired(&mut self, now: DateTime<Utc>) -> Vec<String> {
+        let expired: Vec<String> = self
+            .facts
+            .iter()
+            .filter(|(_, fact)| fact.is_expired(now))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &expired {
+            self.facts.remove(key);
+        }
+        expired
+    }
+
+    /// Take the facts and extraction marker of `other`, keeping facts it lacks.
+    pub fn merge(&mut self, other: UserProfile) {
+        self.facts.extend(other.facts);
+        if other.last_message_hash.is_some() {
+            self.last_message_hash = other.last_message_hash;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fact(value: &str, confidence: f32) -> ProfileFact {
+        ProfileFact {
+            value: value.to_string(),
+            confidence,
+            source: "I drive a Civic".to_string(),
+            updated_at: Utc::now(),
+            category: None,
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn confident_values_win_and_weak_facts_are_dropped_first() {
+        let mut profile = UserProfile::default();
+        assert!(profile.set("vehicle", fact("Honda Civic", 0.9)));
+        assert!(!profile.set("vehicle", fact("Toyota Corolla", 0.4)));
+        assert!(!profile.set("vehicle", fact("honda civic", 0.95)));
+        assert_eq!(profile.get("vehicle").unwrap().confidence, 0.95);
+        assert!(profile.set("vehicle", fact("Toyota Corolla", 0.95)));
+
+        profile.set("name", fact("Ana", 0.99));
+        profile.set("favorite_color", fact("blue", 0.5));
+        profile.truncate(2);
+        assert!(profile.get("favorite_color").is_none());
+        assert_eq!(profile.facts.len(), 2);
+
+        profile.facts.get_mut("name").unwrap().expires_at = Some(Utc::now());
+        assert_eq!(profile.purge_expired(Utc::now()), vec!["name".to_string()]);
+        assert_eq!(profile.facts.len(), 1);
+    }
+}
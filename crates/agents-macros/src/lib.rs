@@ -1,11 +1,17 @@
 //! Procedural macros for Rust Deep Agents SDK
 //!
 //! This crate provides the `#[tool]` macro that converts regular Rust functions
-//! into AI agent tools with automatic JSON Schema generation.
+//! into AI agent tools with automatic JSON Schema generation, the `#[tools]`
+//! macro for tools implemented as methods of a stateful type, the
+//! `#[derive(ToolArgs)]` macro for argument structs, and the `#[derive(ToolEnum)]`
+//! macro that exposes unit enums as JSON Schema enums.
 
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, FnArg, ItemFn, LitStr, Pat, Type};
+use syn::{
+    parse_macro_input, Data, DeriveInput, Fields, FnArg, ImplItem, ItemFn, ItemImpl, LitStr, Pat,
+    Type,
+};
 
 /// Converts a Rust function into an AI agent tool with automatic schema generation.
 ///
@@ -25,119 +31,686 @@ use syn::{parse_macro_input, FnArg, ItemFn, LitStr, Pat, Type};
 ///     vec![]
 /// }
 /// ```
+///
+/// Parameter types map to JSON Schema through `agents_core::tools::ToolParameterType`:
+/// primitives map to their JSON types, `Vec<T>` to an array of `T`, and enums deriving
+/// [`ToolEnum`] to a string `enum`. Any other type is described as a string and
+/// deserialized with serde.
+///
+/// Tools with many parameters can take a single struct deriving [`ToolArgs`]
+/// instead. The description may then be omitted, in which case the function's doc
+/// comment is used:
+///
+/// ```rust,ignore
+/// /// Books a flight
+/// #[tool(args = BookingArgs)]
+/// fn book_flight(args: BookingArgs) -> String {
+///     format!("Booked {} to {}", args.passenger, args.destination)
+/// }
+/// ```
+///
+/// Parameters of type `ToolContext` or `&ToolContext` receive the context of the
+/// current call and are not exposed in the schema. To share state such as
+/// connection pools, put the tools on a type with [`macro@tools`].
 #[proc_macro_attribute]
 pub fn tool(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let description = parse_macro_input!(attr as LitStr);
+    let tool_attr = parse_macro_input!(attr as ToolAttr);
     let input_fn = parse_macro_input!(item as ItemFn);
 
     let fn_name = &input_fn.sig.ident;
-    let fn_name_str = fn_name.to_string();
-    let description_str = description.value();
-    let is_async = input_fn.sig.asyncness.is_some();
+    let tool_struct_name = tool_struct_ident(fn_name);
 
-    // Extract parameters
-    let mut param_schemas = Vec::new();
-    let mut param_idents = Vec::new();
-    let mut required_params = Vec::new();
-    let mut param_extractions = Vec::new();
+    let tool_impl = tool_fn_parts(&input_fn.sig, &input_fn.attrs, &tool_attr).map(|parts| {
+        let call = parts.call(quote! { #fn_name });
+        parts.tool_impl(&tool_struct_name, call)
+    });
 
-    for input in &input_fn.sig.inputs {
-        if let FnArg::Typed(pat_type) = input {
-            if let Pat::Ident(pat_ident) = &*pat_type.pat {
-                let param_name = pat_ident.ident.to_string();
-                let param_ident = &pat_ident.ident;
-                let param_type = &*pat_type.ty;
+    match tool_impl {
+        Ok(tool_impl) => quote! {
+            #input_fn
 
-                // Check if it's Option<T> (optional parameter)
-                let is_optional = is_option_type(param_type);
+            pub struct #tool_struct_name;
 
-                if !is_optional {
-                    required_params.push(param_name.clone());
+            impl #tool_struct_name {
+                pub fn as_tool() -> ::std::sync::Arc<dyn ::agents_core::tools::Tool> {
+                    ::std::sync::Arc::new(#tool_struct_name)
                 }
+            }
+
+            #tool_impl
+        }
+        .into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Turns `#[tool]`-annotated methods of an impl block into tools that share the
+/// receiver, so stateful services (database pools, HTTP clients) need no globals.
+///
+/// Each annotated method must take `&self`. For a method `query` the macro generates
+/// a `QueryTool` struct holding an `Arc` of the receiver type, and an
+/// `as_tools(self: Arc<Self>)` method on the type returning every tool of the block.
+/// Parameters of type `ToolContext` (or `&ToolContext`) are injected with the
+/// current tool context and are not exposed in the schema.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use agents_macros::{tool, tools};
+///
+/// struct Inventory {
+///     pool: PgPool,
+/// }
+///
+/// #[tools]
+/// impl Inventory {
+///     #[tool("Looks up the stock level of a product")]
+///     async fn stock_level(&self, sku: String) -> i64 {
+///         // query self.pool
+///         0
+///     }
+/// }
+///
+/// let tools = Arc::new(Inventory { pool }).as_tools();
+/// ```
+#[proc_macro_attribute]
+pub fn tools(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut item_impl = parse_macro_input!(item as ItemImpl);
+    match tools_impl(&mut item_impl) {
+        Ok(generated) => quote! {
+            #item_impl
+            #generated
+        }
+        .into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn tools_impl(item_impl: &mut ItemImpl) -> syn::Result<proc_macro2::TokenStream> {
+    if item_impl.trait_.is_some() || !item_impl.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &item_impl.self_ty,
+            "#[tools] supports inherent impl blocks without generics",
+        ));
+    }
+    let self_ty = &item_impl.self_ty;
+
+    let mut generated = Vec::new();
+    let mut tool_structs = Vec::new();
+    for item in &mut item_impl.items {
+        let ImplItem::Fn(method) = item else {
+            continue;
+        };
+        let Some(index) = method.attrs.iter().position(|a| a.path().is_ident("tool")) else {
+            continue;
+        };
+        let attr = method.attrs.remove(index);
+        let tool_attr: ToolAttr = attr.parse_args()?;
+
+        if !matches!(method.sig.receiver(), Some(r) if r.reference.is_some() && r.mutability.is_none())
+        {
+            return Err(syn::Error::new_spanned(
+                &method.sig,
+                "#[tool] methods must take `&self`",
+            ));
+        }
+
+        let fn_name = &method.sig.ident;
+        let tool_struct_name = tool_struct_ident(fn_name);
+        let parts = tool_fn_parts(&method.sig, &method.attrs, &tool_attr)?;
+        let call = parts.call(quote! { self.inner.#fn_name });
+        let tool_impl = parts.tool_impl(&tool_struct_name, call);
+
+        generated.push(quote! {
+            pub struct #tool_struct_name {
+                inner: ::std::sync::Arc<#self_ty>,
+            }
+
+            impl #tool_struct_name {
+                pub fn new(inner: ::std::sync::Arc<#self_ty>) -> Self {
+                    Self { inner }
+                }
+
+                pub fn as_tool(
+                    inner: ::std::sync::Arc<#self_ty>,
+                ) -> ::std::sync::Arc<dyn ::agents_core::tools::Tool> {
+                    ::std::sync::Arc::new(Self::new(inner))
+                }
+            }
+
+            #tool_impl
+        });
+        tool_structs.push(tool_struct_name);
+    }
 
-                param_idents.push(param_ident.clone());
+    Ok(quote! {
+        #(#generated)*
 
-                // Generate schema for this parameter
-                let schema_gen = generate_param_schema(&param_name, param_type, is_optional);
-                param_schemas.push(quote! {
-                    properties.insert(
-                        #param_name.to_string(),
-                        #schema_gen
-                    );
-                });
+        impl #self_ty {
+            /// All `#[tool]` methods of this type as tools sharing `self`.
+            pub fn as_tools(
+                self: ::std::sync::Arc<Self>,
+            ) -> ::std::vec::Vec<::std::sync::Arc<dyn ::agents_core::tools::Tool>> {
+                vec![#(#tool_structs::as_tool(self.clone())),*]
+            }
+        }
+    })
+}
+
+/// Everything needed to implement `Tool` for one `#[tool]` function or method.
+struct ToolFnParts {
+    name: String,
+    description: String,
+    is_async: bool,
+    /// Expression building the parameters `ToolParameterSchema`.
+    parameters: proc_macro2::TokenStream,
+    /// Statements binding call arguments from `args` and `ctx`.
+    extractions: Vec<proc_macro2::TokenStream>,
+    /// Arguments passed to the function, in declaration order.
+    call_args: Vec<proc_macro2::TokenStream>,
+}
+
+impl ToolFnParts {
+    fn call(&self, callee: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        let call_args = &self.call_args;
+        if self.is_async {
+            quote! { #callee(#(#call_args),*).await }
+        } else {
+            quote! { #callee(#(#call_args),*) }
+        }
+    }
+
+    fn tool_impl(
+        &self,
+        tool_struct_name: &syn::Ident,
+        call: proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        let Self {
+            name,
+            description,
+            parameters,
+            extractions,
+            ..
+        } = self;
+        quote! {
+            #[::async_trait::async_trait]
+            impl ::agents_core::tools::Tool for #tool_struct_name {
+                fn schema(&self) -> ::agents_core::tools::ToolSchema {
+                    ::agents_core::tools::ToolSchema::new(#name, #description, #parameters)
+                }
 
-                // Generate extraction code
-                let extraction = generate_param_extraction(&param_name, param_type, is_optional);
-                param_extractions.push(extraction);
+                async fn execute(
+                    &self,
+                    args: ::serde_json::Value,
+                    ctx: ::agents_core::tools::ToolContext,
+                ) -> ::anyhow::Result<::agents_core::tools::ToolResult> {
+                    #(#extractions)*
+                    let result = #call;
+                    let output = serde_json::to_string(&result)
+                        .unwrap_or_else(|_| format!("{:?}", result));
+                    Ok(::agents_core::tools::ToolResult::text(&ctx, output))
+                }
             }
         }
     }
+}
 
-    // Generate the tool wrapper
-    let tool_struct_name = syn::Ident::new(
-        &format!("{}Tool", to_pascal_case(&fn_name_str)),
+fn tool_struct_ident(fn_name: &syn::Ident) -> syn::Ident {
+    syn::Ident::new(
+        &format!("{}Tool", to_pascal_case(&fn_name.to_string())),
         fn_name.span(),
-    );
+    )
+}
 
-    let execute_body = if is_async {
-        quote! {
-            let result = #fn_name(#(#param_idents),*).await;
-            let output = serde_json::to_string(&result)
-                .unwrap_or_else(|_| format!("{:?}", result));
-            Ok(::agents_core::tools::ToolResult::text(&ctx, output))
+/// Collect schema, extraction and call arguments from a function signature.
+///
+/// `ToolContext` parameters are injected; with `args = T` the single remaining
+/// parameter receives the deserialized `T`, otherwise each parameter is exposed
+/// individually in the schema.
+fn tool_fn_parts(
+    sig: &syn::Signature,
+    attrs: &[syn::Attribute],
+    tool_attr: &ToolAttr,
+) -> syn::Result<ToolFnParts> {
+    let fn_name_str = sig.ident.to_string();
+    let description = match &tool_attr.description {
+        Some(description) => description.value(),
+        None => doc_comment(attrs).unwrap_or_default(),
+    };
+
+    let mut param_schemas = Vec::new();
+    let mut required_params = Vec::new();
+    let mut extractions = Vec::new();
+    let mut call_args = Vec::new();
+    let mut exposed = 0;
+
+    for input in &sig.inputs {
+        let FnArg::Typed(pat_type) = input else {
+            continue;
+        };
+        let param_type = &*pat_type.ty;
+
+        if let Some(by_ref) = tool_context_param(param_type) {
+            call_args.push(if by_ref {
+                quote! { &ctx }
+            } else {
+                quote! { ctx.clone() }
+            });
+            continue;
         }
-    } else {
-        quote! {
-            let result = #fn_name(#(#param_idents),*);
-            let output = serde_json::to_string(&result)
-                .unwrap_or_else(|_| format!("{:?}", result));
-            Ok(::agents_core::tools::ToolResult::text(&ctx, output))
+
+        exposed += 1;
+        if let Some(args_type) = &tool_attr.args {
+            call_args.push(quote! { tool_args });
+            extractions.push(quote! {
+                let tool_args = <#args_type as ::agents_core::tools::ToolArgs>::from_args(args)?;
+            });
+            continue;
+        }
+
+        let Pat::Ident(pat_ident) = &*pat_type.pat else {
+            return Err(syn::Error::new_spanned(
+                &pat_type.pat,
+                "#[tool] parameters must be plain identifiers",
+            ));
+        };
+        let param_name = pat_ident.ident.to_string();
+        let param_ident = &pat_ident.ident;
+
+        // Check if it's Option<T> (optional parameter)
+        let is_optional = is_option_type(param_type);
+        if !is_optional {
+            required_params.push(param_name.clone());
         }
+
+        // Generate schema for this parameter
+        let schema_gen = generate_param_schema(&param_name, param_type, is_optional);
+        param_schemas.push(quote! {
+            properties.insert(
+                #param_name.to_string(),
+                #schema_gen
+            );
+        });
+
+        // Generate extraction code
+        extractions.push(generate_param_extraction(
+            &param_name,
+            param_type,
+            is_optional,
+        ));
+        call_args.push(quote! { #param_ident });
+    }
+
+    let parameters = match &tool_attr.args {
+        Some(args_type) => {
+            if exposed != 1 {
+                return Err(syn::Error::new_spanned(
+                    &sig.inputs,
+                    "a #[tool(args = ...)] function must take exactly one argument of the args type",
+                ));
+            }
+            quote! { <#args_type as ::agents_core::tools::ToolArgs>::parameters_schema() }
+        }
+        None => quote! {
+            {
+                let mut properties = ::std::collections::HashMap::new();
+                #(#param_schemas)*
+                ::agents_core::tools::ToolParameterSchema::object(
+                    concat!(#fn_name_str, " parameters"),
+                    properties,
+                    vec![#(#required_params.to_string()),*],
+                )
+            }
+        },
     };
 
-    let expanded = quote! {
-        #input_fn
+    Ok(ToolFnParts {
+        name: fn_name_str,
+        description,
+        is_async: sig.asyncness.is_some(),
+        parameters,
+        extractions,
+        call_args,
+    })
+}
 
-        pub struct #tool_struct_name;
+/// `Some(by_ref)` if the parameter is an injected `ToolContext` / `&ToolContext`.
+fn tool_context_param(ty: &Type) -> Option<bool> {
+    match ty {
+        Type::Reference(reference) => {
+            (type_to_string(&reference.elem) == "ToolContext").then_some(true)
+        }
+        _ => (type_to_string(ty) == "ToolContext").then_some(false),
+    }
+}
 
-        impl #tool_struct_name {
-            pub fn as_tool() -> ::std::sync::Arc<dyn ::agents_core::tools::Tool> {
-                ::std::sync::Arc::new(#tool_struct_name)
+/// Implements `ToolParameterType` for a unit-only enum so `#[tool]` parameters of
+/// that type are advertised as a JSON Schema `enum` of the variant names.
+///
+/// Variant names follow serde's `#[serde(rename_all = "...")]` and
+/// `#[serde(rename = "...")]` attributes, so the schema matches what
+/// deserialization accepts.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use agents_macros::{tool, ToolEnum};
+/// use serde::Deserialize;
+///
+/// #[derive(Debug, Deserialize, ToolEnum)]
+/// #[serde(rename_all = "lowercase")]
+/// enum Unit {
+///     Celsius,
+///     Fahrenheit,
+/// }
+///
+/// #[tool("Converts a temperature")]
+/// fn convert(value: f64, to: Unit) -> f64 {
+///     // schema: "to": { "type": "string", "enum": ["celsius", "fahrenheit"] }
+///     value
+/// }
+/// ```
+#[proc_macro_derive(ToolEnum, attributes(serde))]
+pub fn derive_tool_enum(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    match tool_enum_impl(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn tool_enum_impl(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "ToolEnum can only be derived for enums",
+        ));
+    };
+
+    let rename_all = serde_attr(&input.attrs, "rename_all")?;
+    let mut values = Vec::new();
+    for variant in &data.variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "ToolEnum variants cannot carry data",
+            ));
+        }
+        let value = match serde_attr(&variant.attrs, "rename")? {
+            Some(renamed) => renamed,
+            None => apply_rename_all(&variant.ident.to_string(), rename_all.as_deref()),
+        };
+        values.push(value);
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::agents_core::tools::ToolParameterType for #ident #ty_generics #where_clause {
+            fn parameter_schema(description: &str) -> ::agents_core::tools::ToolParameterSchema {
+                ::agents_core::tools::ToolParameterSchema::string_enum(description, [#(#values),*])
             }
         }
+    })
+}
 
-        #[::async_trait::async_trait]
-        impl ::agents_core::tools::Tool for #tool_struct_name {
-            fn schema(&self) -> ::agents_core::tools::ToolSchema {
-                use ::std::collections::HashMap;
-                use ::agents_core::tools::{ToolSchema, ToolParameterSchema};
+/// Read `#[serde(key = "value")]` from `attrs`, ignoring other serde options.
+fn serde_attr(attrs: &[syn::Attribute], key: &str) -> syn::Result<Option<String>> {
+    let mut found = None;
+    for attr in attrs.iter().filter(|a| a.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident(key) && meta.input.peek(syn::Token![=]) {
+                let value: LitStr = meta.value()?.parse()?;
+                found = Some(value.value());
+                Ok(())
+            } else {
+                skip_meta_value(&meta)
+            }
+        })?;
+    }
+    Ok(found)
+}
 
-                let mut properties = HashMap::new();
-                #(#param_schemas)*
+/// Consume the value of a serde option we do not interpret.
+fn skip_meta_value(meta: &syn::meta::ParseNestedMeta) -> syn::Result<()> {
+    if meta.input.peek(syn::Token![=]) {
+        let _: syn::Expr = meta.value()?.parse()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        let content;
+        syn::parenthesized!(content in meta.input);
+        let _: proc_macro2::TokenStream = content.parse()?;
+    }
+    Ok(())
+}
 
-                ToolSchema::new(
-                    #fn_name_str,
-                    #description_str,
-                    ToolParameterSchema::object(
-                        concat!(#fn_name_str, " parameters"),
-                        properties,
-                        vec![#(#required_params.to_string()),*],
-                    ),
-                )
+/// Apply a serde `rename_all` rule to a PascalCase variant name.
+fn apply_rename_all(variant: &str, rule: Option<&str>) -> String {
+    let snake = || {
+        let mut out = String::new();
+        for (i, ch) in variant.char_indices() {
+            if i > 0 && ch.is_uppercase() {
+                out.push('_');
             }
+            out.push(ch.to_ascii_lowercase());
+        }
+        out
+    };
+    match rule {
+        Some("lowercase") => variant.to_ascii_lowercase(),
+        Some("UPPERCASE") => variant.to_ascii_uppercase(),
+        Some("camelCase") => {
+            let mut chars = variant.chars();
+            match chars.next() {
+                Some(first) => first.to_lowercase().collect::<String>() + chars.as_str(),
+                None => String::new(),
+            }
+        }
+        Some("snake_case") => snake(),
+        Some("SCREAMING_SNAKE_CASE") => snake().to_ascii_uppercase(),
+        Some("kebab-case") => snake().replace('_', "-"),
+        Some("SCREAMING-KEBAB-CASE") => snake().replace('_', "-").to_ascii_uppercase(),
+        _ => variant.to_string(),
+    }
+}
 
-            async fn execute(
-                &self,
-                args: ::serde_json::Value,
-                ctx: ::agents_core::tools::ToolContext,
-            ) -> ::anyhow::Result<::agents_core::tools::ToolResult> {
-                #(#param_extractions)*
-                #execute_body
+/// Arguments of the `#[tool]` attribute: `"description"`, `args = Type`, or both.
+struct ToolAttr {
+    description: Option<LitStr>,
+    args: Option<Type>,
+}
+
+impl syn::parse::Parse for ToolAttr {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let mut attr = ToolAttr {
+            description: None,
+            args: None,
+        };
+        if input.peek(LitStr) {
+            attr.description = Some(input.parse()?);
+            if !input.is_empty() {
+                input.parse::<syn::Token![,]>()?;
+            }
+        }
+        while !input.is_empty() {
+            let key: syn::Ident = input.parse()?;
+            input.parse::<syn::Token![=]>()?;
+            if key == "args" {
+                attr.args = Some(input.parse()?);
+            } else {
+                return Err(syn::Error::new_spanned(
+                    key,
+                    "unknown #[tool] option, expected `args`",
+                ));
             }
+            if !input.is_empty() {
+                input.parse::<syn::Token![,]>()?;
+            }
+        }
+        if attr.description.is_none() && attr.args.is_none() {
+            return Err(input.error("expected a tool description"));
+        }
+        Ok(attr)
+    }
+}
+
+/// Derives `ToolArgs` for a struct with named fields, building an object schema
+/// from the field types and doc comments.
+///
+/// Fields of type `Option<T>` or marked `#[serde(default)]` are optional; all
+/// others are required. Field names follow `#[serde(rename = "...")]` and the
+/// struct's `#[serde(rename_all = "...")]`. The struct must also derive
+/// `serde::Deserialize`, which performs the actual extraction.
+///
+/// The derive also implements `ToolParameterType`, so argument structs can be
+/// nested inside each other (e.g. `Vec<LineItem>`).
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use agents_macros::ToolArgs;
+/// use serde::Deserialize;
+///
+/// /// Flight booking request
+/// #[derive(Deserialize, ToolArgs)]
+/// struct BookingArgs {
+///     /// Full name of the passenger
+///     passenger: String,
+///     /// IATA code of the destination airport
+///     destination: String,
+///     /// Number of checked bags
+///     #[serde(default)]
+///     bags: u32,
+/// }
+/// ```
+#[proc_macro_derive(ToolArgs, attributes(serde))]
+pub fn derive_tool_args(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    match tool_args_impl(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn tool_args_impl(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "ToolArgs requires a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "ToolArgs can only be derived for structs",
+            ))
         }
     };
 
-    TokenStream::from(expanded)
+    let ident = &input.ident;
+    let rename_all = serde_attr(&input.attrs, "rename_all")?;
+    let object_description =
+        doc_comment(&input.attrs).unwrap_or_else(|| format!("{} parameters", ident));
+
+    let mut properties = Vec::new();
+    let mut required = Vec::new();
+    for field in fields {
+        let field_ident = field.ident.as_ref().expect("named field");
+        let name = match serde_attr(&field.attrs, "rename")? {
+            Some(renamed) => renamed,
+            None => rename_field(&field_ident.to_string(), rename_all.as_deref()),
+        };
+        let description =
+            doc_comment(&field.attrs).unwrap_or_else(|| format!("Parameter: {}", name));
+        let is_optional = is_option_type(&field.ty);
+        if !is_optional && !serde_flag(&field.attrs, "default")? {
+            required.push(name.clone());
+        }
+        let schema = generate_param_schema_with_description(&description, &field.ty, is_optional);
+        properties.push(quote! {
+            properties.insert(#name.to_string(), #schema);
+        });
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::agents_core::tools::ToolArgs for #ident #ty_generics #where_clause {
+            fn parameters_schema() -> ::agents_core::tools::ToolParameterSchema {
+                let mut properties = ::std::collections::HashMap::new();
+                #(#properties)*
+                ::agents_core::tools::ToolParameterSchema::object(
+                    #object_description,
+                    properties,
+                    vec![#(#required.to_string()),*],
+                )
+            }
+        }
+
+        impl #impl_generics ::agents_core::tools::ToolParameterType for #ident #ty_generics #where_clause {
+            fn parameter_schema(description: &str) -> ::agents_core::tools::ToolParameterSchema {
+                let mut schema =
+                    <Self as ::agents_core::tools::ToolArgs>::parameters_schema();
+                schema.description = Some(description.to_string());
+                schema
+            }
+        }
+    })
+}
+
+/// Join `///` doc comment lines, or `None` if there are none.
+fn doc_comment(attrs: &[syn::Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|a| a.path().is_ident("doc"))
+        .filter_map(|a| match &a.meta {
+            syn::Meta::NameValue(nv) => match &nv.value {
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(s),
+                    ..
+                }) => Some(s.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    let joined = lines.join(" ").trim().to_string();
+    (!joined.is_empty()).then_some(joined)
+}
+
+/// Whether `#[serde(flag)]` is present, e.g. `#[serde(default)]`.
+fn serde_flag(attrs: &[syn::Attribute], flag: &str) -> syn::Result<bool> {
+    let mut found = false;
+    for attr in attrs.iter().filter(|a| a.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident(flag) {
+                found = true;
+            }
+            skip_meta_value(&meta)
+        })?;
+    }
+    Ok(found)
+}
+
+/// Apply a serde `rename_all` rule to a snake_case field name.
+fn rename_field(field: &str, rule: Option<&str>) -> String {
+    match rule {
+        Some("UPPERCASE") | Some("SCREAMING_SNAKE_CASE") => field.to_ascii_uppercase(),
+        Some("PascalCase") => to_pascal_case(field),
+        Some("camelCase") => {
+            let pascal = to_pascal_case(field);
+            let mut chars = pascal.chars();
+            match chars.next() {
+                Some(first) => first.to_lowercase().collect::<String>() + chars.as_str(),
+                None => String::new(),
+            }
+        }
+        Some("kebab-case") => field.replace('_', "-"),
+        Some("SCREAMING-KEBAB-CASE") => field.replace('_', "-").to_ascii_uppercase(),
+        _ => field.to_string(),
+    }
 }
 
 fn is_option_type(ty: &Type) -> bool {
@@ -155,7 +728,14 @@ fn generate_param_schema(
     is_optional: bool,
 ) -> proc_macro2::TokenStream {
     let description = format!("Parameter: {}", param_name);
+    generate_param_schema_with_description(&description, param_type, is_optional)
+}
 
+fn generate_param_schema_with_description(
+    description: &str,
+    param_type: &Type,
+    is_optional: bool,
+) -> proc_macro2::TokenStream {
     // Extract the inner type if it's Option<T>
     let inner_type = if is_optional {
         extract_option_inner_type(param_type)
@@ -178,9 +758,19 @@ fn generate_param_schema(
             ::agents_core::tools::ToolParameterSchema::boolean(#description)
         },
         _ => {
-            // For complex types (Vec, custom structs), default to string
+            // Use the type's ToolParameterType impl (Vec<T>, ToolEnum enums) when it
+            // has one, otherwise fall back to a string
             quote! {
-                ::agents_core::tools::ToolParameterSchema::string(#description)
+                {
+                    #[allow(unused_imports)]
+                    use ::agents_core::tools::__private::{ViaParameterType as _, ViaStringFallback as _};
+                    #[allow(clippy::needless_borrow)]
+                    let schema = (&&::agents_core::tools::__private::SchemaProbe::<#inner_type>(
+                        ::std::marker::PhantomData,
+                    ))
+                    .parameter_schema(#description);
+                    schema
+                }
             }
         }
     }
@@ -12,3 +12,245 @@ async fn test_string_tool() {
     let schema = tool_instance.schema();
     assert_eq!(schema.name, "test_string");
 }
+
+#[derive(Debug, serde::Deserialize, agents_macros::ToolEnum)]
+#[serde(rename_all = "snake_case")]
+enum Priority {
+    Low,
+    VeryHigh,
+    #[serde(rename = "urgent!")]
+    Urgent,
+}
+
+#[tool("Creates a ticket")]
+fn create_ticket(title: String, priority: Priority, labels: Option<Vec<String>>) -> String {
+    format!(
+        "{}:{:?}:{}",
+        title,
+        priority,
+        labels.unwrap_or_default().len()
+    )
+}
+
+#[tokio::test]
+async fn test_enum_and_array_params() {
+    let tool_instance = CreateTicketTool::as_tool();
+    let schema = tool_instance.schema();
+    let priority = &schema.parameters.properties.as_ref().unwrap()["priority"];
+    assert_eq!(priority.schema_type, "string");
+    assert_eq!(
+        priority.enum_values,
+        Some(vec![
+            serde_json::json!("low"),
+            serde_json::json!("very_high"),
+            serde_json::json!("urgent!"),
+        ])
+    );
+    let labels = &schema.parameters.properties.as_ref().unwrap()["labels"];
+    assert_eq!(labels.schema_type, "array");
+    assert_eq!(labels.items.as_ref().unwrap().schema_type, "string");
+    assert_eq!(
+        schema.parameters.required,
+        Some(vec!["title".to_string(), "priority".to_string()])
+    );
+
+    let ctx = agents_core::tools::ToolContext::new(std::sync::Arc::new(
+        agents_core::state::AgentStateSnapshot::default(),
+    ));
+    let result = tool_instance
+        .execute(
+            serde_json::json!({"title": "Outage", "priority": "very_high", "labels": ["ops"]}),
+            ctx,
+        )
+        .await
+        .unwrap();
+    match result {
+        agents_core::tools::ToolResult::Message(m) => {
+            assert!(m.content.as_text().unwrap().contains("Outage:VeryHigh:1"))
+        }
+        _ => panic!("expected message"),
+    }
+}
+
+/// A passenger on the booking
+#[derive(Debug, serde::Deserialize, agents_macros::ToolArgs)]
+struct Passenger {
+    /// Full name
+    name: String,
+}
+
+#[derive(Debug, serde::Deserialize, agents_macros::ToolArgs)]
+#[serde(rename_all = "camelCase")]
+struct BookingArgs {
+    /// IATA code of the destination airport
+    destination_code: String,
+    /// People travelling
+    passengers: Vec<Passenger>,
+    /// Number of checked bags
+    #[serde(default)]
+    checked_bags: u32,
+    seat_class: Option<Priority>,
+}
+
+/// Books a flight for one or more passengers
+#[tool(args = BookingArgs)]
+async fn book_flight(booking: BookingArgs) -> String {
+    format!(
+        "{} x{} ({}) bags={} class={:?}",
+        booking.destination_code,
+        booking.passengers.len(),
+        booking
+            .passengers
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+        booking.checked_bags,
+        booking.seat_class
+    )
+}
+
+#[tokio::test]
+async fn test_tool_args_struct() {
+    let tool_instance = BookFlightTool::as_tool();
+    let schema = tool_instance.schema();
+    assert_eq!(schema.name, "book_flight");
+    assert_eq!(
+        schema.description,
+        "Books a flight for one or more passengers"
+    );
+
+    let properties = schema.parameters.properties.as_ref().unwrap();
+    assert_eq!(
+        properties["destinationCode"].description.as_deref(),
+        Some("IATA code of the destination airport")
+    );
+    let passengers = &properties["passengers"];
+    assert_eq!(passengers.schema_type, "array");
+    assert_eq!(passengers.items.as_ref().unwrap().schema_type, "object");
+    assert!(properties["seatClass"].enum_values.is_some());
+    assert_eq!(
+        schema.parameters.required,
+        Some(vec![
+            "destinationCode".to_string(),
+            "passengers".to_string()
+        ])
+    );
+
+    let ctx = agents_core::tools::ToolContext::new(std::sync::Arc::new(
+        agents_core::state::AgentStateSnapshot::default(),
+    ));
+    let result = tool_instance
+        .execute(
+            serde_json::json!({"destinationCode": "LIS", "passengers": [{"name": "Ana"}]}),
+            ctx.clone(),
+        )
+        .await
+        .unwrap();
+    match result {
+        agents_core::tools::ToolResult::Message(m) => {
+            assert!(m
+                .content
+                .as_text()
+                .unwrap()
+                .contains("LIS x1 (Ana) bags=0 class=None"))
+        }
+        _ => panic!("expected message"),
+    }
+
+    let err = tool_instance
+        .execute(serde_json::json!({"passengers": []}), ctx)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("destinationCode"));
+}
+
+#[tool("Reports how many files are in the agent state")]
+fn count_files(prefix: String, ctx: &agents_core::tools::ToolContext) -> usize {
+    ctx.state
+        .files
+        .keys()
+        .filter(|path| path.starts_with(&prefix))
+        .count()
+}
+
+struct Counter {
+    step: i64,
+}
+
+#[agents_macros::tools]
+impl Counter {
+    #[tool("Adds the configured step to a number")]
+    fn add_step(&self, value: i64) -> i64 {
+        value + self.step
+    }
+
+    /// Multiplies a number by the configured step
+    #[tool(args = MultiplyArgs)]
+    async fn multiply(&self, args: MultiplyArgs, ctx: agents_core::tools::ToolContext) -> String {
+        format!("{}:{}", args.value * self.step, ctx.state.files.len())
+    }
+
+    fn helper(&self) -> i64 {
+        self.step
+    }
+}
+
+#[derive(Debug, serde::Deserialize, agents_macros::ToolArgs)]
+struct MultiplyArgs {
+    /// Number to multiply
+    value: i64,
+}
+
+fn text_of(result: agents_core::tools::ToolResult) -> String {
+    match result {
+        agents_core::tools::ToolResult::Message(m) => m.content.as_text().unwrap().to_string(),
+        _ => panic!("expected message"),
+    }
+}
+
+#[tokio::test]
+async fn test_injected_context_and_methods() {
+    let mut state = agents_core::state::AgentStateSnapshot::default();
+    state.files.insert("notes/a.md".into(), "a".into());
+    state.files.insert("other.txt".into(), "b".into());
+    let ctx = agents_core::tools::ToolContext::new(std::sync::Arc::new(state));
+
+    let count = CountFilesTool::as_tool();
+    let properties = count.schema().parameters.properties.unwrap();
+    assert_eq!(properties.len(), 1);
+    assert!(properties.contains_key("prefix"));
+    let result = count
+        .execute(serde_json::json!({"prefix": "notes/"}), ctx.clone())
+        .await
+        .unwrap();
+    assert_eq!(text_of(result), "1");
+
+    let counter = std::sync::Arc::new(Counter { step: 3 });
+    assert_eq!(counter.helper(), 3);
+    let tools = counter.as_tools();
+    let names: Vec<_> = tools.iter().map(|t| t.schema().name).collect();
+    assert_eq!(names, vec!["add_step", "multiply"]);
+
+    let result = tools[0]
+        .execute(serde_json::json!({"value": 4}), ctx.clone())
+        .await
+        .unwrap();
+    assert_eq!(text_of(result), "7");
+
+    let multiply_schema = tools[1].schema();
+    assert_eq!(
+        multiply_schema.description,
+        "Multiplies a number by the configured step"
+    );
+    assert!(!multiply_schema
+        .parameters
+        .properties
+        .unwrap()
+        .contains_key("ctx"));
+    let result = tools[1]
+        .execute(serde_json::json!({"value": 5}), ctx)
+        .await
+        .unwrap();
+    assert_eq!(text_of(result), "\"15:2\"");
+}
@@ -0,0 +1,223 @@
+//! Caching of MCP tool results
+//!
+//! Documentation servers such as Context7 answer the same lookups in every session.
+//! With an [`McpToolCache`] on the tool adapters, a successful tool result is stored
+//! in a [`ToolResultCache`] (in-memory, Redis or SQLite) and identical calls are
+//! answered from it until the entry expires.
+//!
+//! Keys have the form `{prefix}:{server}:{tool}:{hash}`. The hash covers the
+//! arguments, the server's reported version, the tool's description and input schema
+//! and the cache's own version, so upgrading the server, changing the tool or bumping
+//! [`McpToolCache::with_version`] stops outdated entries from being served.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let cache = McpToolCache::new(Arc::new(RedisToolResultCache::new("redis://127.0.0.1:6379").await?))
+//!     .with_ttl(Duration::from_secs(7 * 24 * 3600))
+//!     .with_tools(["query-docs"]);
+//!
+//! let servers = McpServers::new(config).with_cache(cache);
+//! ```
+
+use crate::{McpClient, McpContent, McpTool};
+use agents_core::tool_cache::ToolResultCache;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Which MCP tool results are cached, where and for how long.
+#[derive(Clone)]
+pub struct McpToolCache {
+    store: Arc<dyn ToolResultCache>,
+    prefix: String,
+    ttl: Option<Duration>,
+    version: Option<String>,
+    tools: Option<HashSet<String>>,
+}
+
+impl McpToolCache {
+    /// Cache the results of every tool in `store` for a day.
+    pub fn new(store: Arc<dyn ToolResultCache>) -> Self {
+        Self {
+            store,
+            prefix: "mcp-cache".to_string(),
+            ttl: Some(Duration::from_secs(24 * 3600)),
+            version: None,
+            tools: None,
+        }
+    }
+
+    /// How long results stay cached; `None` keeps them until invalidated.
+    pub fn with_ttl(mut self, ttl: impl Into<Option<Duration>>) -> Self {
+        self.ttl = ttl.into();
+        self
+    }
+
+    /// Version folded into every key, e.g. the documented library's version. Changing
+    /// it invalidates all entries cached under the previous one.
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// Only cache the tools with these MCP names; all tools when not set.
+    pub fn with_tools<I, S>(mut self, tools: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tools = Some(tools.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Key prefix separating these entries from others in a shared store (default `mcp-cache`).
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Whether results of the tool named `tool` (its MCP name) are cached.
+    pub fn caches(&self, tool: &str) -> bool {
+        self.tools.as_ref().is_none_or(|tools| tools.contains(tool))
+    }
+
+    /// Drop all cached results of `server`, or of every server when `None`.
+    pub async fn invalidate(&self, server: Option<&str>) -> anyhow::Result<()> {
+        let prefix = match server {
+            Some(server) => format!("{}:{}:", self.prefix, server),
+            None => format!("{}:", self.prefix),
+        };
+        self.store.invalidate(&prefix).await
+    }
+
+    /// Key of a call to `tool` on the server `client` is connected to.
+    pub(crate) fn key(&self, client: &McpClient, tool: &McpTool, args: &Value) -> String {
+        let (server, server_version) = client
+            .server_info()
+            .map(|info| {
+                (
+                    info.server_info.name.as_str(),
+                    info.server_info.version.as_deref(),
+                )
+            })
+            .unwrap_or(("unknown", None));
+
+        let mut hasher = Sha256::new();
+        for part in [
+            server_version.unwrap_or_default(),
+            self.version.as_deref().unwrap_or_default(),
+            tool.description.as_deref().unwrap_or_default(),
+            &canonical_json(&tool.input_schema),
+            &canonical_json(args),
+        ] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+        let hash: String = hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        format!("{}:{}:{}:{}", self.prefix, server, tool.name, hash)
+    }
+
+    /// Cached content for `key`. Cache failures are logged and treated as misses.
+    pub(crate) async fn get(&self, key: &str) -> Option<Vec<McpContent>> {
+        match self.store.get(key).await {
+            Ok(Some(value)) => match serde_json::from_str(&value) {
+                Ok(content) => Some(content),
+                Err(e) => {
+                    tracing::warn!(key, error = %e, "Discarding unreadable cached MCP result");
+                    None
+                }
+            },
+            Ok(None) => None,
+            Err(e) => {
+                tracing::warn!(key, error = %e, "MCP tool cache lookup failed");
+                None
+            }
+        }
+    }
+
+    pub(crate) async fn put(&self, key: &str, content: &[McpContent]) {
+        let stored = match serde_json::to_string(content) {
+            Ok(value) => self.store.put(key, &value, self.ttl).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = stored {
+            tracing::warn!(key, error = %e, "Failed to cache MCP tool result");
+        }
+    }
+}
+
+impl std::fmt::Debug for McpToolCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("McpToolCache")
+            .field("prefix", &self.prefix)
+            .field("ttl", &self.ttl)
+            .field("version", &self.version)
+            .field("tools", &self.tools)
+            .finish()
+    }
+}
+
+/// JSON with object keys sorted, so equal arguments give equal keys.
+fn canonical_json(value: &Value) -> String {
+    fn sorted(value: &Value) -> Value {
+        match value {
+            Value::Object(map) => {
+                let mut entries: Vec<_> = map.iter().collect();
+                entries.sort_by_key(|(key, _)| *key);
+                Value::Object(
+                    entries
+                        .into_iter()
+                        .map(|(key, value)| (key.clone(), sorted(value)))
+                        .collect(),
+                )
+            }
+            Value::Array(items) => Value::Array(items.iter().map(sorted).collect()),
+            other => other.clone(),
+        }
+    }
+    sorted(value).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::tool_cache::InMemoryToolResultCache;
+    use serde_json::json;
+
+    #[test]
+    fn test_canonical_json_ignores_key_order() {
+        assert_eq!(
+            canonical_json(&json!({"b": 1, "a": {"d": [{"f": 1, "e": 2}], "c": null}})),
+            canonical_json(&json!({"a": {"c": null, "d": [{"e": 2, "f": 1}]}, "b": 1}))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_and_invalidate() {
+        let cache =
+            McpToolCache::new(Arc::new(InMemoryToolResultCache::new())).with_tools(["query-docs"]);
+        assert!(cache.caches("query-docs"));
+        assert!(!cache.caches("resolve-library-id"));
+
+        let key = "mcp-cache:context7:query-docs:abc";
+        cache
+            .put(key, &[McpContent::text("useEffect runs after render")])
+            .await;
+        assert_eq!(
+            cache.get(key).await.unwrap()[0].as_text(),
+            Some("useEffect runs after render")
+        );
+
+        cache.invalidate(Some("other")).await.unwrap();
+        assert!(cache.get(key).await.is_some());
+        cache.invalidate(Some("context7")).await.unwrap();
+        assert!(cache.get(key).await.is_none());
+    }
+}
@@ -278,6 +278,13 @@ impl McpClient {
         Ok(())
     }
 
+    /// Close the connection of a client shared with tool adapters, stopping a
+    /// subprocess server. Calls made afterwards fail with a transport error.
+    pub async fn disconnect(&self) -> Result<(), McpError> {
+        debug!("Disconnecting MCP client");
+        self.transport.lock().await.close().await
+    }
+
     // ========================================
     // Internal Helper Methods
     // ========================================
@@ -9,8 +9,12 @@
 //!
 //! - **JSON-RPC 2.0**: Full protocol implementation
 //! - **Stdio Transport**: Spawn MCP servers as subprocesses
-//! - **HTTP Transport**: Connect to HTTP-based MCP servers (like Context7)
+//! - **HTTP Transport**: Streamable HTTP with sessions, or plain JSON-RPC for older servers
+//! - **OAuth 2.1**: Authorization with PKCE, dynamic client registration and token refresh
 //! - **Tool Adapter**: Seamless conversion of MCP tools to SDK tools
+//! - **Server Management**: Several servers from declarative config, started lazily
+//! - **Result Cache**: Repeated identical tool calls answered from a persistent cache
+//! - **Agent Server**: Expose an agent to MCP hosts as a tool over stdio
 //! - **Zero External MCP Deps**: Only uses serde, tokio, reqwest, and workspace dependencies
 //!
 //! ## Example (Stdio Transport)
@@ -49,19 +53,28 @@
 pub mod protocol;
 pub mod transport;
 
+mod cache;
 mod client;
+mod server;
+mod servers;
 mod tool_adapter;
 
 // Re-exports
+pub use cache::McpToolCache;
 pub use client::{McpClient, McpClientConfig};
 pub use protocol::{
     error::McpError,
     types::{McpContent, McpTool, McpToolResult},
 };
-pub use tool_adapter::{create_mcp_tools, McpToolAdapter};
+pub use server::McpAgentServer;
+pub use servers::{McpServerConfig, McpServerTransport, McpServers, McpServersConfig};
+pub use tool_adapter::{create_mcp_tools, McpToolAdapter, MCP_RESOURCES_DIR};
 
 #[cfg(feature = "stdio")]
 pub use transport::stdio::StdioTransport;
 
 #[cfg(feature = "http")]
 pub use transport::http::{HttpTransport, HttpTransportBuilder};
+
+#[cfg(feature = "http")]
+pub use transport::oauth::{AuthorizationHandler, McpOAuth, OAuthCredentials};
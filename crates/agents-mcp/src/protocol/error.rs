@@ -26,6 +26,10 @@ pub enum McpError {
     #[error("Protocol error: {0}")]
     Protocol(String),
 
+    /// OAuth authorization with the server failed
+    #[error("Authorization error: {0}")]
+    Auth(String),
+
     /// Timeout waiting for response
     #[error("Request timed out after {0:?}")]
     Timeout(std::time::Duration),
@@ -0,0 +1,329 @@
+//! Serving an agent as an MCP server
+//!
+//! [`McpAgentServer`] lets MCP-compatible hosts call one of our agents as a tool.
+//! The server answers `initialize`, `tools/list` and `tools/call`, exposing a single
+//! tool named after the agent that takes a `task` string and returns the agent's
+//! answer. Each call runs on a fresh state, like a sub-agent invoked through `task`.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let agent = ConfigurableAgentBuilder::new("You coordinate research")
+//!     .with_model(model)
+//!     .with_subagent_config(SubAgentConfig::new(
+//!         "research-agent",
+//!         "Researches a topic and reports the findings",
+//!         "You are a thorough researcher",
+//!     ))
+//!     .build()?;
+//!
+//! // Serve the sub-agent to an MCP host that spawned this process
+//! agent.subagent_mcp_server("research-agent").unwrap().serve_stdio().await?;
+//! ```
+
+use crate::protocol::messages::error_codes;
+use crate::protocol::types::MCP_PROTOCOL_VERSION;
+use crate::protocol::{McpError, McpTool};
+use agents_core::agent::AgentHandle;
+use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+use agents_core::state::AgentStateSnapshot;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+
+/// An agent exposed to MCP hosts as a single tool.
+#[derive(Clone)]
+pub struct McpAgentServer {
+    agent: Arc<dyn AgentHandle>,
+    tool: McpTool,
+    version: String,
+}
+
+impl McpAgentServer {
+    /// Expose `agent` as the tool `name`, described to the host with `description`.
+    pub fn new(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        agent: Arc<dyn AgentHandle>,
+    ) -> Self {
+        Self {
+            agent,
+            tool: McpTool {
+                name: name.into(),
+                description: Some(description.into()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "task": {
+                            "type": "string",
+                            "description": "The task to hand to the agent, with all the context it needs"
+                        }
+                    },
+                    "required": ["task"]
+                }),
+            },
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// Version reported to hosts in `serverInfo` (defaults to the SDK version).
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// The tool definition hosts receive from `tools/list`.
+    pub fn tool(&self) -> &McpTool {
+        &self.tool
+    }
+
+    /// Serve newline-delimited JSON-RPC on this process's stdin and stdout.
+    pub async fn serve_stdio(&self) -> Result<(), McpError> {
+        self.serve(BufReader::new(tokio::io::stdin()), tokio::io::stdout())
+            .await
+    }
+
+    /// Serve newline-delimited JSON-RPC until `reader` is closed.
+    pub async fn serve<R, W>(&self, mut reader: R, mut writer: W) -> Result<(), McpError>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).await? == 0 {
+                return Ok(());
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle_message(line.trim()).await {
+                let mut encoded = serde_json::to_string(&response)?;
+                encoded.push('\n');
+                writer.write_all(encoded.as_bytes()).await?;
+                writer.flush().await?;
+            }
+        }
+    }
+
+    /// Answer one JSON-RPC message; notifications get no response.
+    pub async fn handle_message(&self, message: &str) -> Option<Value> {
+        let request: Value = match serde_json::from_str(message) {
+            Ok(request) => request,
+            Err(e) => {
+                return Some(error_response(
+                    Value::Null,
+                    error_codes::PARSE_ERROR,
+                    e.to_string(),
+                ))
+            }
+        };
+        let id = request.get("id").cloned()?;
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            return Some(error_response(
+                id,
+                error_codes::INVALID_REQUEST,
+                "Missing method",
+            ));
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        let result = match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": MCP_PROTOCOL_VERSION,
+                "capabilities": { "tools": { "listChanged": false } },
+                "serverInfo": { "name": self.tool.name, "version": self.version }
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": [self.tool] })),
+            "tools/call" => self.call_tool(&params).await,
+            other => Err((
+                error_codes::METHOD_NOT_FOUND,
+                format!("Method not found: {}", other),
+            )),
+        };
+
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_response(id, code, message),
+        })
+    }
+
+    async fn call_tool(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let name = params.get("name").and_then(Value::as_str).unwrap_or("");
+        if name != self.tool.name {
+            return Err((
+                error_codes::INVALID_PARAMS,
+                format!("Unknown tool: {}", name),
+            ));
+        }
+        let Some(task) = params
+            .get("arguments")
+            .and_then(|arguments| arguments.get("task"))
+            .and_then(Value::as_str)
+        else {
+            return Err((
+                error_codes::INVALID_PARAMS,
+                "Missing string argument: task".to_string(),
+            ));
+        };
+
+        let input = AgentMessage {
+            role: MessageRole::User,
+            content: MessageContent::Text(task.to_string()),
+            metadata: None,
+        };
+        // Agent failures are tool errors the host's model can react to
+        Ok(
+            match self
+                .agent
+                .handle_message(input, Arc::new(AgentStateSnapshot::default()))
+                .await
+            {
+                Ok(response) => json!({
+                    "content": [{ "type": "text", "text": response.content.to_text() }],
+                    "isError": false
+                }),
+                Err(e) => json!({
+                    "content": [{ "type": "text", "text": format!("Agent failed: {}", e) }],
+                    "isError": true
+                }),
+            },
+        )
+    }
+}
+
+fn error_response(id: Value, code: i64, message: impl Into<String>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message.into() }
+    })
+}
+
+impl std::fmt::Debug for McpAgentServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("McpAgentServer")
+            .field("tool", &self.tool.name)
+            .field("version", &self.version)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::agent::AgentDescriptor;
+    use async_trait::async_trait;
+
+    struct ShoutAgent;
+
+    #[async_trait]
+    impl AgentHandle for ShoutAgent {
+        async fn describe(&self) -> AgentDescriptor {
+            AgentDescriptor::new("shout", "1.0")
+        }
+
+        async fn handle_message(
+            &self,
+            input: AgentMessage,
+            _state: Arc<AgentStateSnapshot>,
+        ) -> anyhow::Result<AgentMessage> {
+            let text = input.content.to_text();
+            if text.is_empty() {
+                anyhow::bail!("nothing to shout");
+            }
+            Ok(AgentMessage {
+                role: MessageRole::Agent,
+                content: MessageContent::Text(text.to_uppercase()),
+                metadata: None,
+            })
+        }
+    }
+
+    fn server() -> McpAgentServer {
+        McpAgentServer::new("shout", "Repeats the task loudly", Arc::new(ShoutAgent))
+    }
+
+    #[tokio::test]
+    async fn test_lists_and_calls_the_agent_tool() {
+        let server = server();
+
+        let listed = server
+            .handle_message(r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#)
+            .await
+            .unwrap();
+        assert_eq!(listed["result"]["tools"][0]["name"], "shout");
+        assert_eq!(
+            listed["result"]["tools"][0]["inputSchema"]["required"],
+            json!(["task"])
+        );
+
+        let called = server
+            .handle_message(
+                r#"{"jsonrpc":"2.0","id":2,"method":"tools/call","params":{"name":"shout","arguments":{"task":"hello"}}}"#,
+            )
+            .await
+            .unwrap();
+        assert_eq!(called["id"], 2);
+        assert_eq!(called["result"]["content"][0]["text"], "HELLO");
+        assert_eq!(called["result"]["isError"], false);
+
+        let failed = server
+            .handle_message(
+                r#"{"jsonrpc":"2.0","id":3,"method":"tools/call","params":{"name":"shout","arguments":{"task":""}}}"#,
+            )
+            .await
+            .unwrap();
+        assert_eq!(failed["result"]["isError"], true);
+    }
+
+    #[tokio::test]
+    async fn test_protocol_errors_and_notifications() {
+        let server = server();
+
+        assert!(server
+            .handle_message(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#)
+            .await
+            .is_none());
+        let unknown = server
+            .handle_message(r#"{"jsonrpc":"2.0","id":"a","method":"resources/list"}"#)
+            .await
+            .unwrap();
+        assert_eq!(unknown["error"]["code"], error_codes::METHOD_NOT_FOUND);
+        let missing = server
+            .handle_message(
+                r#"{"jsonrpc":"2.0","id":4,"method":"tools/call","params":{"name":"shout","arguments":{}}}"#,
+            )
+            .await
+            .unwrap();
+        assert_eq!(missing["error"]["code"], error_codes::INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn test_serves_newline_delimited_requests() {
+        let input = concat!(
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","id":2,"method":"ping"}"#,
+            "\n"
+        );
+        let mut output = Vec::new();
+        server()
+            .serve(BufReader::new(input.as_bytes()), &mut output)
+            .await
+            .unwrap();
+
+        let responses: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["result"]["serverInfo"]["name"], "shout");
+        assert_eq!(responses[1]["id"], 2);
+    }
+}
@@ -0,0 +1,375 @@
+//! Several MCP servers from declarative configuration
+//!
+//! [`McpServers`] owns the connections to a set of servers described by an
+//! [`McpServersConfig`]. A server is started the first time the agent asks for
+//! tools, restarted when its connection was lost, and stopped on shutdown. A server
+//! that fails to start only loses its own tools; the agent keeps running with the
+//! others and the server is tried again on the next run.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let config = McpServersConfig::from_json(r#"{
+//!     "servers": [
+//!         {
+//!             "name": "filesystem",
+//!             "command": "npx",
+//!             "args": ["-y", "@modelcontextprotocol/server-filesystem", "/tmp"],
+//!             "namespace": "fs",
+//!             "allowed_tools": ["read_file", "list_directory"]
+//!         },
+//!         { "name": "docs", "url": "https://mcp.context7.com/mcp" }
+//!     ]
+//! }"#)?;
+//!
+//! let agent = ConfigurableAgentBuilder::new("You are a helpful assistant")
+//!     .with_model(model)
+//!     .with_mcp_servers(config)
+//!     .build()?;
+//! ```
+
+use crate::protocol::McpError;
+use crate::{McpClient, McpToolAdapter, McpToolCache};
+use agents_core::tools::{ToolBox, ToolProvider};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// MCP servers an agent connects to.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct McpServersConfig {
+    #[serde(default)]
+    pub servers: Vec<McpServerConfig>,
+}
+
+impl McpServersConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a configuration like `{"servers": [{"name": "fs", "command": "npx", ...}]}`.
+    pub fn from_json(json: &str) -> Result<Self, McpError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn with_server(mut self, server: McpServerConfig) -> Self {
+        self.servers.push(server);
+        self
+    }
+}
+
+/// One MCP server: how to reach it and which of its tools to expose.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpServerConfig {
+    /// Name used in logs
+    pub name: String,
+    #[serde(flatten)]
+    pub transport: McpServerTransport,
+    /// Prefix for the tool names (`{namespace}_{tool}`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    /// MCP names of the tools to expose; all tools when `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_tools: Option<Vec<String>>,
+}
+
+/// How to reach an MCP server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum McpServerTransport {
+    /// Server spawned as a subprocess (requires the `stdio` feature)
+    Stdio {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        env: HashMap<String, String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        working_dir: Option<String>,
+    },
+    /// Server reached over HTTP (requires the `http` feature)
+    Http {
+        url: String,
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        headers: HashMap<String, String>,
+    },
+}
+
+impl McpServerConfig {
+    /// Server spawned as `command args...`.
+    pub fn stdio<I, S>(name: impl Into<String>, command: impl Into<String>, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            transport: McpServerTransport::Stdio {
+                command: command.into(),
+                args: args.into_iter().map(Into::into).collect(),
+                env: HashMap::new(),
+                working_dir: None,
+            },
+            namespace: None,
+            allowed_tools: None,
+        }
+    }
+
+    /// Server reached at `url`.
+    pub fn http(name: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            transport: McpServerTransport::Http {
+                url: url.into(),
+                headers: HashMap::new(),
+            },
+            namespace: None,
+            allowed_tools: None,
+        }
+    }
+
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Only expose the tools with these MCP names.
+    pub fn with_allowed_tools<I, S>(mut self, tools: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_tools = Some(tools.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Whether the tool named `tool` (its MCP name) is exposed.
+    pub fn allows(&self, tool: &str) -> bool {
+        self.allowed_tools
+            .as_ref()
+            .is_none_or(|allowed| allowed.iter().any(|name| name == tool))
+    }
+
+    async fn connect(&self) -> Result<McpClient, McpError> {
+        match &self.transport {
+            #[cfg(feature = "stdio")]
+            McpServerTransport::Stdio {
+                command,
+                args,
+                env,
+                working_dir,
+            } => {
+                let mut config = crate::transport::stdio::StdioConfig::new(command).args(args);
+                for (key, value) in env {
+                    config = config.env(key, value);
+                }
+                if let Some(dir) = working_dir {
+                    config = config.working_dir(dir);
+                }
+                let transport = crate::StdioTransport::spawn_with_config(config).await?;
+                McpClient::connect(transport).await
+            }
+            #[cfg(feature = "http")]
+            McpServerTransport::Http { url, headers } => {
+                let transport = crate::HttpTransport::new(url)
+                    .with_headers(headers.clone())
+                    .build()?;
+                McpClient::connect(transport).await
+            }
+            #[allow(unreachable_patterns)]
+            _ => Err(McpError::Transport(format!(
+                "MCP server {} uses a transport whose feature is not enabled",
+                self.name
+            ))),
+        }
+    }
+}
+
+/// Connections to the configured MCP servers, providing their tools to an agent.
+pub struct McpServers {
+    servers: Vec<ServerSlot>,
+    cache: Option<McpToolCache>,
+}
+
+struct ServerSlot {
+    config: McpServerConfig,
+    client: Mutex<Option<Arc<McpClient>>>,
+}
+
+impl McpServers {
+    /// Servers are not started until their tools are first requested.
+    pub fn new(config: McpServersConfig) -> Self {
+        Self {
+            servers: config
+                .servers
+                .into_iter()
+                .map(|config| ServerSlot {
+                    config,
+                    client: Mutex::new(None),
+                })
+                .collect(),
+            cache: None,
+        }
+    }
+
+    /// Cache the results of the servers' tools (see [`McpToolCache`]).
+    pub fn with_cache(mut self, cache: McpToolCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+}
+
+impl ServerSlot {
+    /// The connected client, starting the server if it is not running.
+    async fn client(&self) -> Result<Arc<McpClient>, McpError> {
+        let mut client = self.client.lock().await;
+        if let Some(existing) = client.as_ref() {
+            if existing.is_connected().await {
+                return Ok(existing.clone());
+            }
+            tracing::info!(server = %self.config.name, "MCP server connection lost, restarting");
+        }
+        let connected = Arc::new(self.config.connect().await?);
+        tracing::info!(
+            server = %self.config.name,
+            tool_count = connected.tools().len(),
+            "Started MCP server"
+        );
+        *client = Some(connected.clone());
+        Ok(connected)
+    }
+
+    fn tools(&self, client: Arc<McpClient>, cache: Option<&McpToolCache>) -> Vec<ToolBox> {
+        client
+            .tools()
+            .iter()
+            .filter(|tool| self.config.allows(&tool.name))
+            .cloned()
+            .map(|tool| {
+                let mut adapter = McpToolAdapter::new(client.clone(), tool);
+                if let Some(namespace) = &self.config.namespace {
+                    adapter = adapter.with_namespace(namespace);
+                }
+                if let Some(cache) = cache {
+                    adapter = adapter.with_cache(cache.clone());
+                }
+                adapter.into_toolbox()
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl ToolProvider for McpServers {
+    async fn tools(&self) -> anyhow::Result<Vec<ToolBox>> {
+        let mut tools = Vec::new();
+        for server in &self.servers {
+            match server.client().await {
+                Ok(client) => tools.extend(server.tools(client, self.cache.as_ref())),
+                Err(e) => tracing::warn!(
+                    server = %server.config.name,
+                    error = %e,
+                    "MCP server unavailable, its tools are left out of this run"
+                ),
+            }
+        }
+        Ok(tools)
+    }
+
+    async fn check(&self) -> Vec<(String, anyhow::Result<usize>)> {
+        let mut results = Vec::new();
+        for server in &self.servers {
+            let tool_count = match server.client().await {
+                Ok(client) => Ok(server.tools(client, self.cache.as_ref()).len()),
+                Err(e) => Err(anyhow::Error::from(e)),
+            };
+            results.push((format!("mcp:{}", server.config.name), tool_count));
+        }
+        results
+    }
+
+    async fn shutdown(&self) -> anyhow::Result<()> {
+        for server in &self.servers {
+            if let Some(client) = server.client.lock().await.take() {
+                if let Err(e) = client.disconnect().await {
+                    tracing::warn!(server = %server.config.name, error = %e, "Failed to stop MCP server");
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for McpServers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("McpServers")
+            .field(
+                "servers",
+                &self
+                    .servers
+                    .iter()
+                    .map(|server| &server.config.name)
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_from_json() {
+        let config = McpServersConfig::from_json(
+            r#"{
+                "servers": [
+                    {
+                        "name": "filesystem",
+                        "command": "npx",
+                        "args": ["-y", "@modelcontextprotocol/server-filesystem", "/tmp"],
+                        "namespace": "fs",
+                        "allowed_tools": ["read_file"]
+                    },
+                    { "name": "docs", "url": "https://mcp.context7.com/mcp" }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config,
+            McpServersConfig::new()
+                .with_server(
+                    McpServerConfig::stdio(
+                        "filesystem",
+                        "npx",
+                        ["-y", "@modelcontextprotocol/server-filesystem", "/tmp"]
+                    )
+                    .with_namespace("fs")
+                    .with_allowed_tools(["read_file"])
+                )
+                .with_server(McpServerConfig::http(
+                    "docs",
+                    "https://mcp.context7.com/mcp"
+                ))
+        );
+        assert!(config.servers[0].allows("read_file"));
+        assert!(!config.servers[0].allows("write_file"));
+        assert!(config.servers[1].allows("write_file"));
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_servers_are_skipped() {
+        let servers = McpServers::new(McpServersConfig::new().with_server(McpServerConfig::stdio(
+            "missing",
+            "definitely-not-an-mcp-server",
+            Vec::<String>::new(),
+        )));
+
+        assert!(servers.tools().await.unwrap().is_empty());
+        servers.shutdown().await.unwrap();
+    }
+}
@@ -3,11 +3,13 @@
 //! Adapts MCP tools to work with the SDK's tool system.
 //! This allows MCP server tools to be used seamlessly alongside native SDK tools.
 
-use crate::{McpClient, McpContent, McpTool};
+use crate::{McpClient, McpContent, McpTool, McpToolCache};
+use agents_core::command::StateDiff;
+use agents_core::messaging::ImageData;
 use agents_core::tools::{Tool, ToolBox, ToolContext, ToolParameterSchema, ToolResult, ToolSchema};
 use async_trait::async_trait;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use tracing::{debug, instrument};
 
@@ -25,6 +27,9 @@ pub struct McpToolAdapter {
 
     /// Optional namespace prefix for the tool name
     namespace: Option<String>,
+
+    /// Optional cache for the tool's results
+    cache: Option<McpToolCache>,
 }
 
 impl McpToolAdapter {
@@ -39,6 +44,7 @@ impl McpToolAdapter {
             client,
             tool,
             namespace: None,
+            cache: None,
         }
     }
 
@@ -60,6 +66,14 @@ impl McpToolAdapter {
         self
     }
 
+    /// Answer repeated identical calls from `cache`
+    ///
+    /// Only successful results of tools the cache covers are stored.
+    pub fn with_cache(mut self, cache: McpToolCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
     /// Convert this adapter into a boxed Tool (ToolBox)
     pub fn into_toolbox(self) -> ToolBox {
         Arc::new(self)
@@ -144,6 +158,10 @@ impl Tool for McpToolAdapter {
             name: self.effective_name(),
             description: self.tool.description.clone().unwrap_or_default(),
             parameters: Self::convert_schema(&self.tool.input_schema),
+            version: None,
+            deprecation: None,
+            examples: Vec::new(),
+            self_test_args: None,
         }
     }
 
@@ -155,34 +173,135 @@ impl Tool for McpToolAdapter {
             "Executing MCP tool via adapter"
         );
 
+        let cache = self
+            .cache
+            .as_ref()
+            .filter(|cache| cache.caches(&self.tool.name))
+            .map(|cache| (cache, cache.key(&self.client, &self.tool, &args)));
+        if let Some((cache, key)) = &cache {
+            if let Some(content) = cache.get(key).await {
+                debug!(tool = %self.tool.name, "Answered MCP tool call from cache");
+                return Ok(to_tool_result(&ctx, content, false));
+            }
+        }
+
         // Call the MCP server (use original name, not namespaced name)
         let mcp_result = self.client.call_tool(&self.tool.name, args).await?;
 
-        // Convert MCP content to text
-        let content = mcp_result
-            .content
-            .into_iter()
-            .filter_map(|c| match c {
-                McpContent::Text { text } => Some(text),
-                McpContent::Resource { text, .. } => text,
-                McpContent::Image { data, mime_type } => {
-                    // For images, return a placeholder message
-                    Some(format!("[Image: {} ({} bytes)]", mime_type, data.len()))
-                }
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
-
-        // Convert to SDK ToolResult
-        if mcp_result.is_error {
-            // Return error as text (SDK doesn't have explicit error result)
-            Ok(ToolResult::text(&ctx, format!("Error: {}", content)))
-        } else {
-            Ok(ToolResult::text(&ctx, content))
+        if let Some((cache, key)) = &cache {
+            if !mcp_result.is_error {
+                cache.put(key, &mcp_result.content).await;
+            }
         }
+
+        Ok(to_tool_result(
+            &ctx,
+            mcp_result.content,
+            mcp_result.is_error,
+        ))
     }
 }
 
+/// Directory of the agent's files that embedded MCP resources are stored under.
+pub const MCP_RESOURCES_DIR: &str = "mcp_resources";
+
+/// Convert MCP tool output into a tool result.
+///
+/// Text is joined into the message. Images, including image blobs of embedded
+/// resources, are passed on as image content for vision models. Embedded text
+/// resources are stored as files under [`MCP_RESOURCES_DIR`] and referenced in
+/// the message, so the model reads them with `read_file` when it needs them.
+fn to_tool_result(ctx: &ToolContext, content: Vec<McpContent>, is_error: bool) -> ToolResult {
+    let mut lines = Vec::new();
+    let mut images = Vec::new();
+    let mut files = BTreeMap::new();
+
+    for item in content {
+        match item {
+            McpContent::Text { text } => lines.push(text),
+            McpContent::Image { data, mime_type } => images.push(ImageData::new(mime_type, data)),
+            McpContent::Resource {
+                uri,
+                text: Some(text),
+                ..
+            } => {
+                let path = resource_path(&uri);
+                lines.push(format!(
+                    "[Resource {} saved to {} ({} chars), use read_file to view it]",
+                    uri,
+                    path,
+                    text.chars().count()
+                ));
+                files.insert(path, text);
+            }
+            McpContent::Resource {
+                mime_type: Some(mime_type),
+                blob: Some(blob),
+                ..
+            } if mime_type.starts_with("image/") => images.push(ImageData::new(mime_type, blob)),
+            McpContent::Resource {
+                uri,
+                mime_type,
+                blob,
+                ..
+            } => lines.push(format!(
+                "[Resource {} ({}, {} bytes) cannot be shown]",
+                uri,
+                mime_type.as_deref().unwrap_or("unknown type"),
+                blob.map_or(0, |blob| blob.len() * 3 / 4)
+            )),
+        }
+    }
+
+    let mut text = lines.join("\n");
+    if is_error {
+        // Return error as text (SDK doesn't have explicit error result)
+        text = format!("Error: {}", text);
+    }
+
+    let message = if images.is_empty() {
+        ctx.text_response(text)
+    } else {
+        ctx.image_response((!text.is_empty()).then_some(text), images)
+    };
+
+    if files.is_empty() {
+        return ToolResult::Message(message);
+    }
+    if let Some(state_handle) = &ctx.state_handle {
+        let mut state = state_handle
+            .write()
+            .expect("filesystem write lock poisoned");
+        state.files.extend(files.clone());
+    }
+    ToolResult::with_state(
+        message,
+        StateDiff {
+            files: Some(files),
+            ..StateDiff::default()
+        },
+    )
+}
+
+/// File path for an embedded resource: `file:///docs/a b.md` is stored as
+/// `mcp_resources/docs/a_b.md`.
+fn resource_path(uri: &str) -> String {
+    let location = uri.split_once("://").map_or(uri, |(_, rest)| rest);
+    let sanitized: String = location
+        .trim_matches('/')
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '/' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let sanitized = sanitized.replace("..", "_");
+    format!("{}/{}", MCP_RESOURCES_DIR, sanitized)
+}
+
 /// Create ToolBox instances for all tools from an MCP client
 ///
 /// # Arguments
@@ -215,6 +334,8 @@ pub fn create_mcp_tools(client: Arc<McpClient>, namespace: Option<&str>) -> Vec<
 #[cfg(test)]
 mod tests {
     use super::*;
+    use agents_core::messaging::MessageContent;
+    use agents_core::state::AgentStateSnapshot;
 
     #[test]
     fn test_schema_conversion() {
@@ -334,6 +455,143 @@ mod tests {
         assert!(sdk_schema.properties.is_none());
     }
 
+    #[test]
+    fn test_images_become_image_content() {
+        let ctx = ToolContext::new(Arc::new(AgentStateSnapshot::default()));
+        let result = to_tool_result(
+            &ctx,
+            vec![
+                McpContent::Text {
+                    text: "Rendered chart".to_string(),
+                },
+                McpContent::Image {
+                    data: "iVBORw0KGgo=".to_string(),
+                    mime_type: "image/png".to_string(),
+                },
+            ],
+            false,
+        );
+
+        let ToolResult::Message(message) = result else {
+            panic!("expected a plain message");
+        };
+        match message.content {
+            MessageContent::Image { text, images } => {
+                assert_eq!(text.as_deref(), Some("Rendered chart"));
+                assert_eq!(images, vec![ImageData::new("image/png", "iVBORw0KGgo=")]);
+            }
+            other => panic!("expected image content, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_text_resources_are_stored_as_files() {
+        let ctx = ToolContext::new(Arc::new(AgentStateSnapshot::default()));
+        let result = to_tool_result(
+            &ctx,
+            vec![McpContent::Resource {
+                uri: "file:///docs/release notes.md".to_string(),
+                text: Some("# 1.0".to_string()),
+                mime_type: Some("text/markdown".to_string()),
+                blob: None,
+            }],
+            false,
+        );
+
+        let ToolResult::WithStateUpdate {
+            message,
+            state_diff,
+        } = result
+        else {
+            panic!("expected a state update");
+        };
+        let files = state_diff.files.unwrap();
+        assert_eq!(files["mcp_resources/docs/release_notes.md"], "# 1.0");
+        assert!(message
+            .content
+            .as_text()
+            .unwrap()
+            .contains("saved to mcp_resources/docs/release_notes.md"));
+    }
+
+    #[test]
+    fn test_resource_path_stays_inside_resources_dir() {
+        assert_eq!(
+            resource_path("repo://../../etc/passwd"),
+            "mcp_resources/_/_/etc/passwd"
+        );
+        assert_eq!(resource_path("notes.txt"), "mcp_resources/notes.txt");
+    }
+
+    /// Transport replaying canned server messages, failing once they run out.
+    struct ScriptedTransport(std::collections::VecDeque<&'static str>);
+
+    #[async_trait]
+    impl crate::transport::Transport for ScriptedTransport {
+        async fn send(&mut self, _message: &str) -> Result<(), crate::McpError> {
+            Ok(())
+        }
+
+        async fn receive(&mut self) -> Result<String, crate::McpError> {
+            self.0
+                .pop_front()
+                .map(String::from)
+                .ok_or(crate::McpError::ProcessExited)
+        }
+
+        async fn close(&mut self) -> Result<(), crate::McpError> {
+            Ok(())
+        }
+
+        fn is_connected(&self) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cached_results_skip_the_server() {
+        let client = McpClient::connect(ScriptedTransport(
+            [
+                r#"{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2024-11-05","capabilities":{"tools":{}},"serverInfo":{"name":"context7","version":"1.0"}}}"#,
+                r#"{"jsonrpc":"2.0","id":2,"result":{"tools":[{"name":"query-docs","inputSchema":{"type":"object"}}]}}"#,
+                r#"{"jsonrpc":"2.0","id":3,"result":{"content":[{"type":"text","text":"useEffect runs after render"}]}}"#,
+            ]
+            .into(),
+        ))
+        .await
+        .unwrap();
+        let client = Arc::new(client);
+        let cache = McpToolCache::new(Arc::new(
+            agents_core::tool_cache::InMemoryToolResultCache::new(),
+        ));
+        let tool = McpToolAdapter::new(client.clone(), client.tools()[0].clone())
+            .with_cache(cache.clone());
+        let ctx = ToolContext::new(Arc::new(AgentStateSnapshot::default()));
+
+        for args in [
+            serde_json::json!({"library": "react", "topic": "hooks"}),
+            serde_json::json!({"topic": "hooks", "library": "react"}),
+        ] {
+            let ToolResult::Message(message) = tool.execute(args, ctx.clone()).await.unwrap()
+            else {
+                panic!("expected a message");
+            };
+            assert_eq!(
+                message.content.as_text(),
+                Some("useEffect runs after render")
+            );
+        }
+
+        let args = serde_json::json!({"library": "react", "topic": "hooks"});
+        let key = cache.key(&client, &client.tools()[0], &args);
+        assert!(key.starts_with("mcp-cache:context7:query-docs:"));
+        assert!(cache.get(&key).await.is_some());
+        // Bumping the cache version leaves the old entries unused
+        let bumped = cache.with_version("react-19");
+        let key = bumped.key(&client, &client.tools()[0], &args);
+        assert!(bumped.get(&key).await.is_none());
+    }
+
     fn format_name(namespace: Option<&str>, name: &str) -> String {
         let safe_name = name.replace('-', "_");
         match namespace {
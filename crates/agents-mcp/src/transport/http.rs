@@ -1,7 +1,10 @@
 //! HTTP Transport for MCP
 //!
 //! This module provides HTTP transport for MCP servers that expose HTTP endpoints.
-//! This is a generic implementation that works with any HTTP-based MCP server.
+//! It speaks the streamable HTTP transport of the 2025 MCP spec: every message is
+//! POSTed to a single endpoint, the server answers with JSON or an SSE stream, and
+//! the `Mcp-Session-Id` it assigns is sent back on later requests. Older servers that
+//! reject streamable requests are talked to with plain HTTP JSON-RPC instead.
 //!
 //! ## Example
 //!
@@ -15,10 +18,14 @@
 //!
 //! let client = McpClient::connect(transport).await?;
 //! ```
+//!
+//! Servers requiring the MCP authorization spec are accessed with
+//! [`HttpTransportBuilder::with_oauth`] (see [`super::oauth`]).
 
+use super::oauth::McpOAuth;
 use crate::protocol::McpError;
 use async_trait::async_trait;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -31,7 +38,9 @@ use tokio::sync::Mutex;
 /// ## Features
 ///
 /// - Standard HTTP POST for JSON-RPC requests
+/// - Streamable HTTP: SSE responses and session IDs, with fallback to plain JSON-RPC
 /// - Configurable headers for authentication
+/// - OAuth 2.1 authorization with automatic token refresh
 /// - Configurable timeouts
 /// - Connection state tracking
 pub struct HttpTransport {
@@ -41,10 +50,18 @@ pub struct HttpTransport {
     client: reqwest::Client,
     /// Custom headers to include in requests
     headers: HashMap<String, String>,
+    /// OAuth client providing bearer tokens
+    oauth: Option<Arc<McpOAuth>>,
     /// Connection state
     connected: AtomicBool,
-    /// Response buffer for receive() calls
-    response_buffer: Arc<Mutex<Vec<String>>>,
+    /// Messages received but not yet returned by receive()
+    response_buffer: Arc<Mutex<VecDeque<String>>>,
+    /// Session assigned by a streamable HTTP server
+    session_id: Option<String>,
+    /// Whether requests use streamable HTTP; false after falling back to plain JSON-RPC
+    streamable: bool,
+    /// Whether the server accepted a request, settling the transport flavour
+    negotiated: bool,
 }
 
 impl HttpTransport {
@@ -75,6 +92,16 @@ impl HttpTransport {
     pub fn url(&self) -> &str {
         &self.url
     }
+
+    /// Session ID assigned by the server, if it uses sessions
+    pub fn session_id(&self) -> Option<&str> {
+        self.session_id.as_deref()
+    }
+
+    /// Whether the server is spoken to with streamable HTTP rather than plain JSON-RPC
+    pub fn is_streamable(&self) -> bool {
+        self.streamable
+    }
 }
 
 /// Builder for HttpTransport
@@ -84,6 +111,7 @@ pub struct HttpTransportBuilder {
     url: String,
     headers: HashMap<String, String>,
     timeout_secs: u64,
+    oauth: Option<McpOAuth>,
 }
 
 impl HttpTransportBuilder {
@@ -93,6 +121,7 @@ impl HttpTransportBuilder {
             url: url.into(),
             headers: HashMap::new(),
             timeout_secs: 30,
+            oauth: None,
         }
     }
 
@@ -113,6 +142,15 @@ impl HttpTransportBuilder {
         self
     }
 
+    /// Authorize requests with OAuth 2.1 as the MCP authorization spec requires.
+    ///
+    /// The first request runs the authorization flow unless `oauth` finds saved
+    /// tokens; expired or rejected tokens are refreshed automatically.
+    pub fn with_oauth(mut self, oauth: McpOAuth) -> Self {
+        self.oauth = Some(oauth);
+        self
+    }
+
     /// Set the request timeout in seconds (default: 30)
     pub fn with_timeout_secs(mut self, secs: u64) -> Self {
         self.timeout_secs = secs;
@@ -134,8 +172,12 @@ impl HttpTransportBuilder {
             url: self.url,
             client,
             headers: self.headers,
+            oauth: self.oauth.map(Arc::new),
             connected: AtomicBool::new(true),
-            response_buffer: Arc::new(Mutex::new(Vec::new())),
+            response_buffer: Arc::new(Mutex::new(VecDeque::new())),
+            session_id: None,
+            streamable: true,
+            negotiated: false,
         })
     }
 }
@@ -145,24 +187,29 @@ impl super::Transport for HttpTransport {
     async fn send(&mut self, message: &str) -> Result<(), McpError> {
         tracing::debug!(url = %self.url, "MCP HTTP send: {}", message);
 
-        // Build request with required MCP headers
-        let mut request = self
-            .client
-            .post(&self.url)
-            .header("Content-Type", "application/json")
-            .header("Accept", "application/json, text/event-stream");
-
-        // Add custom headers
-        for (key, value) in &self.headers {
-            request = request.header(key, value);
+        let mut response = self.post(message).await?;
+        // A rejected token is refreshed (or re-authorized) once
+        if response.status() == reqwest::StatusCode::UNAUTHORIZED && self.oauth.is_some() {
+            response = self.post(message).await?;
+        }
+        // Servers predating streamable HTTP may reject its headers on the first request
+        if self.streamable && !self.negotiated && rejects_streamable(response.status()) {
+            tracing::info!(
+                url = %self.url,
+                status = %response.status(),
+                "MCP server rejected streamable HTTP, falling back to plain JSON-RPC"
+            );
+            self.streamable = false;
+            response = self.post(message).await?;
+        }
+        // The server forgot our session; a new one starts with the next initialize
+        if response.status() == reqwest::StatusCode::NOT_FOUND && self.session_id.is_some() {
+            self.session_id = None;
+            self.connected.store(false, Ordering::SeqCst);
+            return Err(McpError::Transport(
+                "MCP session expired, reconnect to start a new session".to_string(),
+            ));
         }
-
-        // Send request
-        let response = request
-            .body(message.to_string())
-            .send()
-            .await
-            .map_err(|e| McpError::Transport(format!("HTTP request failed: {}", e)))?;
 
         // Check status
         if !response.status().is_success() {
@@ -174,6 +221,23 @@ impl super::Transport for HttpTransport {
             )));
         }
 
+        self.negotiated = true;
+
+        if self.streamable {
+            if let Some(session_id) = response
+                .headers()
+                .get(MCP_SESSION_ID)
+                .and_then(|value| value.to_str().ok())
+            {
+                self.session_id = Some(session_id.to_string());
+            }
+        }
+        let is_event_stream = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|content_type| content_type.starts_with("text/event-stream"));
+
         // Buffer the response for receive()
         let body = response
             .text()
@@ -182,9 +246,19 @@ impl super::Transport for HttpTransport {
 
         tracing::debug!(url = %self.url, "MCP HTTP response: {}", body);
 
+        // Notifications are acknowledged with 202 Accepted and no body
+        if body.trim().is_empty() {
+            return Ok(());
+        }
+        let messages = if is_event_stream {
+            sse_messages(&body)
+        } else {
+            vec![body]
+        };
+
         // Store response in buffer
         let mut buffer = self.response_buffer.lock().await;
-        buffer.push(body);
+        buffer.extend(messages);
 
         Ok(())
     }
@@ -192,7 +266,7 @@ impl super::Transport for HttpTransport {
     async fn receive(&mut self) -> Result<String, McpError> {
         // Check for buffered response
         let mut buffer = self.response_buffer.lock().await;
-        if let Some(response) = buffer.pop() {
+        if let Some(response) = buffer.pop_front() {
             return Ok(response);
         }
         drop(buffer);
@@ -205,6 +279,19 @@ impl super::Transport for HttpTransport {
     }
 
     async fn close(&mut self) -> Result<(), McpError> {
+        // Let the server free the session; servers may refuse with 405
+        if let Some(session_id) = self.session_id.take() {
+            let mut request = self
+                .client
+                .delete(&self.url)
+                .header(MCP_SESSION_ID, session_id);
+            for (key, value) in &self.headers {
+                request = request.header(key, value);
+            }
+            if let Err(e) = request.send().await {
+                tracing::debug!(url = %self.url, error = %e, "Failed to end MCP session");
+            }
+        }
         self.connected.store(false, Ordering::SeqCst);
         Ok(())
     }
@@ -214,11 +301,100 @@ impl super::Transport for HttpTransport {
     }
 }
 
+impl HttpTransport {
+    async fn post(&self, message: &str) -> Result<reqwest::Response, McpError> {
+        // Build request with required MCP headers
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header("Content-Type", "application/json");
+        if self.streamable {
+            request = request.header("Accept", "application/json, text/event-stream");
+            if let Some(session_id) = &self.session_id {
+                request = request.header(MCP_SESSION_ID, session_id);
+            }
+        } else {
+            request = request.header("Accept", "application/json");
+        }
+
+        // Add custom headers
+        for (key, value) in &self.headers {
+            request = request.header(key, value);
+        }
+
+        let token = match &self.oauth {
+            Some(oauth) => Some(oauth.access_token(&self.client, &self.url).await?),
+            None => None,
+        };
+        if let Some(token) = &token {
+            request = request.bearer_auth(token);
+        }
+
+        // Send request
+        let response = request
+            .body(message.to_string())
+            .send()
+            .await
+            .map_err(|e| McpError::Transport(format!("HTTP request failed: {}", e)))?;
+
+        if let (Some(oauth), Some(token)) = (&self.oauth, &token) {
+            if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+                let challenge = response
+                    .headers()
+                    .get(reqwest::header::WWW_AUTHENTICATE)
+                    .and_then(|value| value.to_str().ok());
+                oauth.unauthorized(token, challenge).await;
+            }
+        }
+        Ok(response)
+    }
+}
+
+/// Header carrying the session ID of a streamable HTTP server
+const MCP_SESSION_ID: &str = "Mcp-Session-Id";
+
+/// Statuses with which servers without streamable HTTP refuse its requests
+fn rejects_streamable(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 400 | 404 | 405 | 406 | 415)
+}
+
+/// JSON-RPC messages in an SSE response body.
+///
+/// Each event's `data` lines form one message. Requests the server sends to the
+/// client (e.g. sampling) are not supported and left out.
+fn sse_messages(body: &str) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut data: Vec<&str> = Vec::new();
+    for line in body.lines().chain(std::iter::once("")) {
+        if line.is_empty() {
+            if !data.is_empty() {
+                messages.push(data.join("\n"));
+                data.clear();
+            }
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data.push(value.strip_prefix(' ').unwrap_or(value));
+        }
+    }
+    messages.retain(|message| {
+        let is_server_request = serde_json::from_str::<serde_json::Value>(message)
+            .map(|value| value.get("method").is_some() && value.get("id").is_some())
+            .unwrap_or(false);
+        if is_server_request {
+            tracing::debug!("Ignoring request from MCP server: {}", message);
+        }
+        !is_server_request
+    });
+    messages
+}
+
 impl std::fmt::Debug for HttpTransport {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("HttpTransport")
             .field("url", &self.url)
             .field("headers", &self.headers.keys().collect::<Vec<_>>())
+            .field("oauth", &self.oauth.is_some())
+            .field("streamable", &self.streamable)
+            .field("session", &self.session_id.is_some())
             .field("connected", &self.connected.load(Ordering::SeqCst))
             .finish()
     }
@@ -275,4 +451,133 @@ mod tests {
         assert!(debug_str.contains("Authorization"));
         assert!(!debug_str.contains("secret"));
     }
+
+    #[test]
+    fn test_sse_messages() {
+        let body = concat!(
+            ": keep-alive\n\n",
+            "event: message\n",
+            "data: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\"}\n\n",
+            "id: 7\n",
+            "data: {\"jsonrpc\":\"2.0\",\"id\":9,\"method\":\"sampling/createMessage\"}\n\n",
+            "data: {\"jsonrpc\":\"2.0\",\n",
+            "data: \"id\":1,\"result\":{}}\n"
+        );
+
+        assert_eq!(
+            sse_messages(body),
+            vec![
+                r#"{"jsonrpc":"2.0","method":"notifications/progress"}"#,
+                "{\"jsonrpc\":\"2.0\",\n\"id\":1,\"result\":{}}",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_starts_streamable_without_session() {
+        let transport = HttpTransport::new("https://example.com/mcp")
+            .build()
+            .unwrap();
+
+        assert!(transport.is_streamable());
+        assert_eq!(transport.session_id(), None);
+        assert!(rejects_streamable(reqwest::StatusCode::NOT_ACCEPTABLE));
+        assert!(!rejects_streamable(reqwest::StatusCode::UNAUTHORIZED));
+    }
+
+    /// Answer each HTTP request on a local port with the next canned response,
+    /// returning the received requests' head lines.
+    async fn serve(responses: Vec<&'static str>) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut received = Vec::new();
+                let mut chunk = [0u8; 4096];
+                loop {
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    received.extend_from_slice(&chunk[..n]);
+                    let text = String::from_utf8_lossy(&received).to_string();
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        let length = text[..end]
+                            .lines()
+                            .find_map(|line| {
+                                line.to_lowercase()
+                                    .strip_prefix("content-length: ")
+                                    .map(|v| v.trim().parse::<usize>().unwrap())
+                            })
+                            .unwrap_or(0);
+                        if received.len() >= end + 4 + length {
+                            requests.push(text[..end].to_lowercase());
+                            break;
+                        }
+                    }
+                }
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.shutdown().await.unwrap();
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    #[tokio::test]
+    async fn test_streamable_session_round_trip() {
+        let (url, server) = serve(vec![
+            "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nmcp-session-id: s-42\r\nconnection: close\r\ncontent-length: 44\r\n\r\ndata: {\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{}}\n\n",
+            "HTTP/1.1 202 Accepted\r\nconnection: close\r\ncontent-length: 0\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nconnection: close\r\ncontent-length: 0\r\n\r\n",
+        ])
+        .await;
+        let mut transport = HttpTransport::new(url).build().unwrap();
+
+        transport
+            .send(r#"{"jsonrpc":"2.0","id":1,"method":"initialize"}"#)
+            .await
+            .unwrap();
+        assert_eq!(
+            transport.receive().await.unwrap(),
+            r#"{"jsonrpc":"2.0","id":1,"result":{}}"#
+        );
+        assert_eq!(transport.session_id(), Some("s-42"));
+        transport
+            .send(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#)
+            .await
+            .unwrap();
+        assert!(transport.receive().await.is_err());
+        transport.close().await.unwrap();
+
+        let requests = server.await.unwrap();
+        assert!(!requests[0].contains("mcp-session-id"));
+        assert!(requests[1].contains("mcp-session-id: s-42"));
+        assert!(requests[2].starts_with("delete "));
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_plain_json_rpc() {
+        let (url, server) = serve(vec![
+            "HTTP/1.1 406 Not Acceptable\r\nconnection: close\r\ncontent-length: 0\r\n\r\n",
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\nconnection: close\r\ncontent-length: 36\r\n\r\n{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{}}",
+        ])
+        .await;
+        let mut transport = HttpTransport::new(url).build().unwrap();
+
+        transport
+            .send(r#"{"jsonrpc":"2.0","id":1,"method":"initialize"}"#)
+            .await
+            .unwrap();
+        assert_eq!(
+            transport.receive().await.unwrap(),
+            r#"{"jsonrpc":"2.0","id":1,"result":{}}"#
+        );
+        assert!(!transport.is_streamable());
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].contains("accept: application/json, text/event-stream"));
+        assert!(!requests[1].contains("text/event-stream"));
+    }
 }
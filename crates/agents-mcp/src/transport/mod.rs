@@ -6,7 +6,8 @@
 //! ## Available Transports
 //!
 //! - **stdio** (default): For subprocess-based MCP servers
-//! - **http**: For HTTP-based MCP servers (like Context7)
+//! - **http**: For HTTP-based MCP servers (like Context7) using streamable HTTP or
+//!   plain JSON-RPC, with optional OAuth 2.1 authorization (see [`oauth`])
 
 #[cfg(feature = "stdio")]
 pub mod stdio;
@@ -14,6 +15,9 @@ pub mod stdio;
 #[cfg(feature = "http")]
 pub mod http;
 
+#[cfg(feature = "http")]
+pub mod oauth;
+
 use crate::protocol::McpError;
 use async_trait::async_trait;
 
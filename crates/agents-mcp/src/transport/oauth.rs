@@ -0,0 +1,660 @@
+//! OAuth 2.1 authorization for remote MCP servers
+//!
+//! Implements the client side of the MCP authorization spec:
+//!
+//! - Discovery of the authorization server through protected resource metadata
+//!   (RFC 9728) and authorization server metadata (RFC 8414)
+//! - Dynamic client registration (RFC 7591) when no client id is configured
+//! - Authorization code flow with PKCE (`S256`) and resource indicators (RFC 8707)
+//! - Token refresh, and persistence of the tokens through a [`SecretsProvider`]
+//!
+//! The user-facing step, opening the authorization URL in a browser and capturing
+//! the redirect, is left to an [`AuthorizationHandler`].
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use agents_mcp::{AuthorizationHandler, HttpTransport, McpOAuth};
+//!
+//! struct PasteCallback;
+//!
+//! #[async_trait::async_trait]
+//! impl AuthorizationHandler for PasteCallback {
+//!     async fn authorize(&self, authorization_url: &str) -> anyhow::Result<String> {
+//!         println!("Open {authorization_url} and paste the URL you are redirected to:");
+//!         let mut callback = String::new();
+//!         std::io::stdin().read_line(&mut callback)?;
+//!         Ok(callback)
+//!     }
+//! }
+//!
+//! let oauth = McpOAuth::new("http://127.0.0.1:8765/callback", Arc::new(PasteCallback), secrets)
+//!     .with_scopes(["mcp:tools"]);
+//!
+//! let transport = HttpTransport::new("https://mcp.example.com/mcp")
+//!     .with_oauth(oauth)
+//!     .build()?;
+//! ```
+
+use crate::protocol::McpError;
+use agents_core::secrets::SecretsProvider;
+use async_trait::async_trait;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+
+/// Access tokens expiring within this margin are refreshed before use.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+/// Performs the interactive part of the authorization code flow.
+#[async_trait]
+pub trait AuthorizationHandler: Send + Sync {
+    /// Send the user to `authorization_url` and return the URL the authorization
+    /// server redirected the browser to, i.e. the redirect URI with `code` and
+    /// `state` query parameters.
+    async fn authorize(&self, authorization_url: &str) -> anyhow::Result<String>;
+}
+
+/// OAuth client for one MCP server, attached with
+/// [`HttpTransportBuilder::with_oauth`](super::http::HttpTransportBuilder::with_oauth).
+pub struct McpOAuth {
+    redirect_uri: String,
+    handler: Arc<dyn AuthorizationHandler>,
+    secrets: Arc<dyn SecretsProvider>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    client_name: String,
+    scopes: Vec<String>,
+    token_key: Option<String>,
+    session: Mutex<Session>,
+}
+
+/// Credentials kept in memory and saved under the token key.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct OAuthCredentials {
+    pub client_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    pub access_token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    /// Unix timestamp (seconds) the access token expires at, if the server said
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+}
+
+impl OAuthCredentials {
+    fn is_fresh(&self) -> bool {
+        match self.expires_at {
+            Some(expires_at) => unix_now() + EXPIRY_MARGIN.as_secs() < expires_at,
+            None => true,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Session {
+    loaded: bool,
+    credentials: Option<OAuthCredentials>,
+    /// Access token the server rejected; not used again
+    rejected_token: Option<String>,
+    /// `resource_metadata` URL from the server's `WWW-Authenticate` challenge
+    resource_metadata: Option<String>,
+    metadata: Option<AuthServerMetadata>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct AuthServerMetadata {
+    authorization_endpoint: String,
+    token_endpoint: String,
+    #[serde(default)]
+    registration_endpoint: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ProtectedResourceMetadata {
+    #[serde(default)]
+    authorization_servers: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct RegistrationResponse {
+    client_id: String,
+    #[serde(default)]
+    client_secret: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+impl McpOAuth {
+    /// Create an OAuth client redirecting to `redirect_uri` after authorization and
+    /// saving its tokens to `secrets`.
+    pub fn new(
+        redirect_uri: impl Into<String>,
+        handler: Arc<dyn AuthorizationHandler>,
+        secrets: Arc<dyn SecretsProvider>,
+    ) -> Self {
+        Self {
+            redirect_uri: redirect_uri.into(),
+            handler,
+            secrets,
+            client_id: None,
+            client_secret: None,
+            client_name: "rust-deep-agents-sdk".to_string(),
+            scopes: Vec::new(),
+            token_key: None,
+            session: Mutex::new(Session::default()),
+        }
+    }
+
+    /// Use a pre-registered client instead of dynamic client registration.
+    pub fn with_client_id(mut self, client_id: impl Into<String>) -> Self {
+        self.client_id = Some(client_id.into());
+        self
+    }
+
+    /// Secret of a confidential pre-registered client.
+    pub fn with_client_secret(mut self, client_secret: impl Into<String>) -> Self {
+        self.client_secret = Some(client_secret.into());
+        self
+    }
+
+    /// Client name sent with dynamic client registration.
+    pub fn with_client_name(mut self, name: impl Into<String>) -> Self {
+        self.client_name = name.into();
+        self
+    }
+
+    /// Scopes to request.
+    pub fn with_scopes<I, S>(mut self, scopes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.scopes = scopes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Secret key the credentials are saved under (default: `mcp-oauth/<host>`).
+    pub fn with_token_key(mut self, key: impl Into<String>) -> Self {
+        self.token_key = Some(key.into());
+        self
+    }
+
+    /// Access token for requests to `resource`, the MCP server URL. Loads saved
+    /// credentials, refreshes them, or runs the authorization flow as needed.
+    pub(crate) async fn access_token(
+        &self,
+        http: &reqwest::Client,
+        resource: &str,
+    ) -> Result<String, McpError> {
+        let mut session = self.session.lock().await;
+        let token_key = self.token_key(resource)?;
+
+        if !session.loaded {
+            session.loaded = true;
+            // A missing secret just means the server was not authorized yet
+            if let Ok(saved) = self.secrets.fetch(&token_key).await {
+                session.credentials = serde_json::from_str(&saved).ok();
+            }
+        }
+
+        if let Some(credentials) = &session.credentials {
+            let rejected = session.rejected_token.as_ref() == Some(&credentials.access_token);
+            if credentials.is_fresh() && !rejected {
+                return Ok(credentials.access_token.clone());
+            }
+        }
+
+        let metadata = match &session.metadata {
+            Some(metadata) => metadata.clone(),
+            None => {
+                let metadata =
+                    discover(http, resource, session.resource_metadata.as_deref()).await?;
+                session.metadata = Some(metadata.clone());
+                metadata
+            }
+        };
+
+        let refreshed = match &session.credentials {
+            Some(credentials) if credentials.refresh_token.is_some() => {
+                match self.refresh(http, &metadata, resource, credentials).await {
+                    Ok(refreshed) => Some(refreshed),
+                    Err(e) => {
+                        tracing::warn!(error = %e, "MCP OAuth token refresh failed, re-authorizing");
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+        let credentials = match refreshed {
+            Some(credentials) => credentials,
+            None => self.authorize(http, &metadata, resource).await?,
+        };
+
+        if let Err(e) = self
+            .secrets
+            .store(&token_key, &serde_json::to_string(&credentials)?)
+            .await
+        {
+            tracing::warn!(error = %e, key = %token_key, "Could not save MCP OAuth tokens");
+        }
+        let access_token = credentials.access_token.clone();
+        session.credentials = Some(credentials);
+        session.rejected_token = None;
+        Ok(access_token)
+    }
+
+    /// Record that the server rejected `access_token` with 401 and the given
+    /// `WWW-Authenticate` header, so the next [`access_token`](Self::access_token)
+    /// call refreshes or re-authorizes.
+    pub(crate) async fn unauthorized(&self, access_token: &str, challenge: Option<&str>) {
+        let mut session = self.session.lock().await;
+        session.rejected_token = Some(access_token.to_string());
+        if let Some(url) = challenge.and_then(resource_metadata_url) {
+            if session.resource_metadata.as_deref() != Some(url.as_str()) {
+                session.resource_metadata = Some(url);
+                session.metadata = None;
+            }
+        }
+    }
+
+    fn token_key(&self, resource: &str) -> Result<String, McpError> {
+        if let Some(key) = &self.token_key {
+            return Ok(key.clone());
+        }
+        let url = parse_url(resource)?;
+        Ok(format!("mcp-oauth/{}", url.host_str().unwrap_or("default")))
+    }
+
+    async fn authorize(
+        &self,
+        http: &reqwest::Client,
+        metadata: &AuthServerMetadata,
+        resource: &str,
+    ) -> Result<OAuthCredentials, McpError> {
+        let (client_id, client_secret) = match &self.client_id {
+            Some(client_id) => (client_id.clone(), self.client_secret.clone()),
+            None => self.register(http, metadata).await?,
+        };
+
+        let verifier = code_verifier();
+        let state = uuid::Uuid::new_v4().simple().to_string();
+        let mut url = parse_url(&metadata.authorization_endpoint)?;
+        {
+            let mut query = url.query_pairs_mut();
+            query
+                .append_pair("response_type", "code")
+                .append_pair("client_id", &client_id)
+                .append_pair("redirect_uri", &self.redirect_uri)
+                .append_pair("code_challenge", &code_challenge(&verifier))
+                .append_pair("code_challenge_method", "S256")
+                .append_pair("state", &state)
+                .append_pair("resource", resource);
+            if !self.scopes.is_empty() {
+                query.append_pair("scope", &self.scopes.join(" "));
+            }
+        }
+
+        let callback = self
+            .handler
+            .authorize(url.as_str())
+            .await
+            .map_err(|e| McpError::Auth(format!("Authorization was not completed: {}", e)))?;
+        let code = authorization_code(&callback, &state)?;
+
+        let mut form = vec![
+            ("grant_type", "authorization_code".to_string()),
+            ("code", code),
+            ("redirect_uri", self.redirect_uri.clone()),
+            ("code_verifier", verifier),
+            ("resource", resource.to_string()),
+        ];
+        client_auth(&mut form, &client_id, client_secret.as_deref());
+        let tokens = request_tokens(http, &metadata.token_endpoint, &form).await?;
+        Ok(credentials(client_id, client_secret, tokens, None))
+    }
+
+    async fn refresh(
+        &self,
+        http: &reqwest::Client,
+        metadata: &AuthServerMetadata,
+        resource: &str,
+        current: &OAuthCredentials,
+    ) -> Result<OAuthCredentials, McpError> {
+        let refresh_token = current.refresh_token.clone().unwrap_or_default();
+        let mut form = vec![
+            ("grant_type", "refresh_token".to_string()),
+            ("refresh_token", refresh_token.clone()),
+            ("resource", resource.to_string()),
+        ];
+        client_auth(
+            &mut form,
+            &current.client_id,
+            current.client_secret.as_deref(),
+        );
+        let tokens = request_tokens(http, &metadata.token_endpoint, &form).await?;
+        // Servers that do not rotate refresh tokens omit them from the response
+        Ok(credentials(
+            current.client_id.clone(),
+            current.client_secret.clone(),
+            tokens,
+            Some(refresh_token),
+        ))
+    }
+
+    async fn register(
+        &self,
+        http: &reqwest::Client,
+        metadata: &AuthServerMetadata,
+    ) -> Result<(String, Option<String>), McpError> {
+        let endpoint = metadata.registration_endpoint.as_deref().ok_or_else(|| {
+            McpError::Auth(
+                "Authorization server does not support dynamic client registration; \
+                 configure a client id with with_client_id()"
+                    .to_string(),
+            )
+        })?;
+        let response = http
+            .post(endpoint)
+            .json(&serde_json::json!({
+                "client_name": self.client_name,
+                "redirect_uris": [self.redirect_uri],
+                "grant_types": ["authorization_code", "refresh_token"],
+                "response_types": ["code"],
+                "token_endpoint_auth_method": "none",
+            }))
+            .send()
+            .await
+            .map_err(|e| McpError::Auth(format!("Client registration failed: {}", e)))?;
+        let registration: RegistrationResponse = read_json(response, "Client registration").await?;
+        Ok((registration.client_id, registration.client_secret))
+    }
+}
+
+impl std::fmt::Debug for McpOAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("McpOAuth")
+            .field("redirect_uri", &self.redirect_uri)
+            .field("client_id", &self.client_id)
+            .field("scopes", &self.scopes)
+            .finish()
+    }
+}
+
+/// Find the authorization server of `resource`: protected resource metadata first,
+/// then the server's own origin as older MCP servers expect.
+async fn discover(
+    http: &reqwest::Client,
+    resource: &str,
+    resource_metadata: Option<&str>,
+) -> Result<AuthServerMetadata, McpError> {
+    let resource_url = parse_url(resource)?;
+    let mut candidates: Vec<String> = resource_metadata.map(str::to_string).into_iter().collect();
+    candidates.extend(well_known_urls(&resource_url, "oauth-protected-resource"));
+
+    let mut issuer = None;
+    for candidate in candidates {
+        if let Some(metadata) = fetch_json::<ProtectedResourceMetadata>(http, &candidate).await {
+            issuer = metadata.authorization_servers.into_iter().next();
+            if issuer.is_some() {
+                break;
+            }
+        }
+    }
+    let issuer = match issuer {
+        Some(issuer) => parse_url(&issuer)?,
+        None => resource_url.clone(),
+    };
+
+    for name in ["oauth-authorization-server", "openid-configuration"] {
+        for candidate in well_known_urls(&issuer, name) {
+            if let Some(metadata) = fetch_json::<AuthServerMetadata>(http, &candidate).await {
+                return Ok(metadata);
+            }
+        }
+    }
+
+    // Default endpoints of the 2025-03-26 MCP authorization spec
+    let origin = issuer.origin().ascii_serialization();
+    Ok(AuthServerMetadata {
+        authorization_endpoint: format!("{}/authorize", origin),
+        token_endpoint: format!("{}/token", origin),
+        registration_endpoint: Some(format!("{}/register", origin)),
+    })
+}
+
+/// Well-known metadata URLs of `url`, path-specific first (RFC 8414 section 3.1).
+fn well_known_urls(url: &Url, name: &str) -> Vec<String> {
+    let origin = url.origin().ascii_serialization();
+    let path = url.path().trim_end_matches('/');
+    let mut urls = Vec::new();
+    if !path.is_empty() {
+        urls.push(format!("{}/.well-known/{}{}", origin, name, path));
+    }
+    urls.push(format!("{}/.well-known/{}", origin, name));
+    urls
+}
+
+async fn fetch_json<T: for<'de> Deserialize<'de>>(http: &reqwest::Client, url: &str) -> Option<T> {
+    let response = http.get(url).send().await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    response.json().await.ok()
+}
+
+async fn request_tokens(
+    http: &reqwest::Client,
+    token_endpoint: &str,
+    form: &[(&str, String)],
+) -> Result<TokenResponse, McpError> {
+    let response = http
+        .post(token_endpoint)
+        .form(form)
+        .send()
+        .await
+        .map_err(|e| McpError::Auth(format!("Token request failed: {}", e)))?;
+    read_json(response, "Token request").await
+}
+
+async fn read_json<T: for<'de> Deserialize<'de>>(
+    response: reqwest::Response,
+    what: &str,
+) -> Result<T, McpError> {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(McpError::Auth(format!(
+            "{} failed with HTTP {}: {}",
+            what, status, body
+        )));
+    }
+    serde_json::from_str(&body)
+        .map_err(|e| McpError::Auth(format!("{} returned an invalid response: {}", what, e)))
+}
+
+fn client_auth(form: &mut Vec<(&str, String)>, client_id: &str, client_secret: Option<&str>) {
+    form.push(("client_id", client_id.to_string()));
+    if let Some(secret) = client_secret {
+        form.push(("client_secret", secret.to_string()));
+    }
+}
+
+fn credentials(
+    client_id: String,
+    client_secret: Option<String>,
+    tokens: TokenResponse,
+    previous_refresh_token: Option<String>,
+) -> OAuthCredentials {
+    OAuthCredentials {
+        client_id,
+        client_secret,
+        access_token: tokens.access_token,
+        refresh_token: tokens.refresh_token.or(previous_refresh_token),
+        expires_at: tokens.expires_in.map(|secs| unix_now() + secs),
+    }
+}
+
+/// `resource_metadata` parameter of a `WWW-Authenticate: Bearer` challenge.
+fn resource_metadata_url(challenge: &str) -> Option<String> {
+    let start = challenge.find("resource_metadata=")? + "resource_metadata=".len();
+    let value = &challenge[start..];
+    let value = match value.strip_prefix('"') {
+        Some(quoted) => &quoted[..quoted.find('"')?],
+        None => value.split([',', ' ']).next()?,
+    };
+    Some(value.to_string())
+}
+
+/// Authorization code from the redirect URL, checking `state` against the request.
+fn authorization_code(callback: &str, expected_state: &str) -> Result<String, McpError> {
+    let url = parse_url(callback.trim())?;
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+    if let Some(error) = param("error") {
+        let description = param("error_description").unwrap_or_default();
+        return Err(McpError::Auth(format!(
+            "Authorization denied: {} {}",
+            error, description
+        )));
+    }
+    if param("state").as_deref() != Some(expected_state) {
+        return Err(McpError::Auth(
+            "Authorization callback state does not match the request".to_string(),
+        ));
+    }
+    param("code").ok_or_else(|| McpError::Auth("Authorization callback has no code".to_string()))
+}
+
+/// Random PKCE code verifier: 64 characters from two v4 UUIDs.
+fn code_verifier() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+/// `S256` code challenge of `verifier` (RFC 7636 section 4.2).
+fn code_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+fn parse_url(url: &str) -> Result<Url, McpError> {
+    Url::parse(url).map_err(|e| McpError::Auth(format!("Invalid URL {}: {}", url, e)))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::secrets::InMemorySecretsProvider;
+
+    struct NoBrowser;
+
+    #[async_trait]
+    impl AuthorizationHandler for NoBrowser {
+        async fn authorize(&self, _authorization_url: &str) -> anyhow::Result<String> {
+            anyhow::bail!("no browser in tests")
+        }
+    }
+
+    #[test]
+    fn test_code_challenge_is_s256() {
+        assert_eq!(
+            code_challenge("M25iVXpKU3puUjFaYWg3T1NDTDQtcW1ROUY5YXlwalNoc0hhakxifmZHag"),
+            "qjrzSW9gMiUgpUvqgEPE4_-8swvyCtfOVvg55o5S_es"
+        );
+        assert_eq!(code_verifier().len(), 64);
+    }
+
+    #[test]
+    fn test_well_known_urls_insert_path_after_well_known() {
+        let url = Url::parse("https://mcp.example.com/v1/mcp").unwrap();
+        assert_eq!(
+            well_known_urls(&url, "oauth-protected-resource"),
+            vec![
+                "https://mcp.example.com/.well-known/oauth-protected-resource/v1/mcp",
+                "https://mcp.example.com/.well-known/oauth-protected-resource",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resource_metadata_from_challenge() {
+        assert_eq!(
+            resource_metadata_url(
+                r#"Bearer error="invalid_token", resource_metadata="https://mcp.example.com/.well-known/oauth-protected-resource""#
+            )
+            .as_deref(),
+            Some("https://mcp.example.com/.well-known/oauth-protected-resource")
+        );
+        assert_eq!(resource_metadata_url("Bearer realm=\"mcp\""), None);
+    }
+
+    #[test]
+    fn test_authorization_code_checks_state() {
+        let callback = "http://127.0.0.1:8765/callback?code=abc&state=s1";
+        assert_eq!(authorization_code(callback, "s1").unwrap(), "abc");
+        assert!(authorization_code(callback, "s2").is_err());
+        assert!(
+            authorization_code("http://127.0.0.1/cb?error=access_denied&state=s1", "s1").is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_saved_credentials_are_loaded() {
+        let saved = OAuthCredentials {
+            client_id: "client".to_string(),
+            access_token: "token-1".to_string(),
+            expires_at: Some(unix_now() + 3600),
+            ..OAuthCredentials::default()
+        };
+        let secrets = Arc::new(InMemorySecretsProvider::new().with_secret(
+            "mcp-oauth/mcp.example.com",
+            serde_json::to_string(&saved).unwrap(),
+        ));
+        let oauth = McpOAuth::new(
+            "http://127.0.0.1:8765/callback",
+            Arc::new(NoBrowser),
+            secrets,
+        );
+        let http = reqwest::Client::new();
+
+        assert_eq!(
+            oauth
+                .access_token(&http, "https://mcp.example.com/mcp")
+                .await
+                .unwrap(),
+            "token-1"
+        );
+        // Tokens about to expire are refreshed rather than used
+        assert!(!OAuthCredentials {
+            expires_at: Some(unix_now() + 5),
+            ..saved
+        }
+        .is_fresh());
+    }
+}
@@ -7,13 +7,20 @@
 //! ## Available Backends
 //!
 //! - **Redis**: High-performance in-memory data store with optional persistence
-//! - **PostgreSQL**: Robust relational database with ACID guarantees
+//! - **PostgreSQL**: Robust relational database with ACID guarantees, plus a
+//!   transactional event outbox and a full-text conversation index
+//! - **SQLite**: Local file for tool result caching
+//! - **Neo4j**: Knowledge graph store for graph memory
 //! - **DynamoDB**: AWS-managed NoSQL database (available in `agents-aws` crate)
 //!
 //! ## Feature Flags
 //!
-//! - `redis`: Enable Redis checkpointer
-//! - `postgres`: Enable PostgreSQL checkpointer
+//! - `redis`: Enable Redis checkpointer, Redis (Redlock) thread lock, Redis
+//!   Streams job queue, Redis tool result cache, Redis quota store and Redis user
+//!   memory store
+//! - `postgres`: Enable PostgreSQL checkpointer and conversation index
+//! - `sqlite`: Enable SQLite tool result cache
+//! - `neo4j`: Enable Neo4j knowledge graph store
 //! - `all`: Enable all backends
 //!
 //! ## Examples
@@ -49,15 +56,70 @@
 #[cfg(feature = "redis")]
 pub mod redis_checkpointer;
 
+#[cfg(feature = "redis")]
+pub mod redis_thread_lock;
+
+#[cfg(feature = "redis")]
+pub mod redis_job_queue;
+#[cfg(feature = "redis")]
+pub mod redis_memory_store;
+
+#[cfg(feature = "redis")]
+pub mod redis_tool_cache;
+
+#[cfg(feature = "redis")]
+pub mod redis_quota_store;
+
 #[cfg(feature = "postgres")]
 pub mod postgres_checkpointer;
 
+#[cfg(feature = "postgres")]
+pub mod postgres_search;
+
+#[cfg(feature = "sqlite")]
+pub mod sqlite_tool_cache;
+
+#[cfg(feature = "neo4j")]
+pub mod neo4j_graph;
+
 #[cfg(feature = "redis")]
 pub use redis_checkpointer::RedisCheckpointer;
 
+#[cfg(feature = "redis")]
+pub use redis_thread_lock::{RedisThreadLock, RedisThreadLockBuilder};
+
+#[cfg(feature = "redis")]
+pub use redis_job_queue::{RedisJobQueue, RedisJobQueueBuilder};
+
+#[cfg(feature = "redis")]
+pub use redis_quota_store::{RedisQuotaStore, RedisQuotaStoreBuilder};
+
+#[cfg(feature = "redis")]
+pub use redis_tool_cache::{RedisToolResultCache, RedisToolResultCacheBuilder};
+
+#[cfg(feature = "redis")]
+pub use redis_memory_store::{RedisMemoryStore, RedisMemoryStoreBuilder};
+
 #[cfg(feature = "postgres")]
 pub use postgres_checkpointer::PostgresCheckpointer;
 
+#[cfg(feature = "postgres")]
+pub use postgres_search::{PostgresConversationIndex, PostgresConversationIndexBuilder};
+
+#[cfg(feature = "sqlite")]
+pub use sqlite_tool_cache::{SqliteToolResultCache, SqliteToolResultCacheBuilder};
+
+#[cfg(feature = "neo4j")]
+pub use neo4j_graph::Neo4jGraphStore;
+
 // Re-export core types for convenience
+pub use agents_core::graph::GraphStore;
+pub use agents_core::job_queue::JobQueue;
+pub use agents_core::memory::MemoryStore;
+pub use agents_core::outbox::EventOutbox;
 pub use agents_core::persistence::{Checkpointer, ThreadId};
+pub use agents_core::quota::QuotaStore;
+pub use agents_core::search::ConversationIndex;
 pub use agents_core::state::AgentStateSnapshot;
+pub use agents_core::thread_lock::ThreadLock;
+pub use agents_core::tool_cache::ToolResultCache;
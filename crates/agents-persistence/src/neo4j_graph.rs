@@ -0,0 +1,213 @@
+//! Neo4j knowledge graph store.
+//!
+//! Entities are `:Entity` nodes keyed by their lowercased name, and facts are
+//! `:RELATES` relationships carrying the predicate, so any predicate the model
+//! extracts can be stored without creating relationship types. The store creates a
+//! uniqueness constraint on `Entity.key` when it connects.
+
+use agents_core::graph::{GraphEntity, GraphFact, GraphStore};
+use agents_core::persistence::ThreadId;
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use neo4rs::{query, Graph, Query, Row};
+
+/// Neo4j implementation of [`GraphStore`].
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use agents_persistence::Neo4jGraphStore;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let store = Neo4jGraphStore::new("127.0.0.1:7687", "neo4j", "password").await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Neo4jGraphStore {
+    graph: Graph,
+}
+
+impl Neo4jGraphStore {
+    /// Connect to the database, creating the entity key constraint if it doesn't exist.
+    pub async fn new(uri: &str, user: &str, password: &str) -> anyhow::Result<Self> {
+        let graph = Graph::new(uri, user, password)
+            .await
+            .context("Failed to connect to Neo4j")?;
+        Self::from_graph(graph).await
+    }
+
+    /// Use an existing connection pool.
+    pub async fn from_graph(graph: Graph) -> anyhow::Result<Self> {
+        graph
+            .run(query(
+                "CREATE CONSTRAINT agent_entity_key IF NOT EXISTS \
+                 FOR (e:Entity) REQUIRE e.key IS UNIQUE",
+            ))
+            .await
+            .context("Failed to create Neo4j entity constraint")?;
+        Ok(Self { graph })
+    }
+
+    async fn facts(&self, query: Query) -> anyhow::Result<Vec<GraphFact>> {
+        let mut rows = self.graph.execute(query).await?;
+        let mut facts = Vec::new();
+        while let Some(row) = rows.next().await? {
+            facts.push(fact_from_row(&row)?);
+        }
+        Ok(facts)
+    }
+}
+
+fn key(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+/// Columns every fact query returns.
+const FACT_COLUMNS: &str = "startNode(r).name AS subject, r.predicate AS predicate, \
+    endNode(r).name AS object, r.thread_id AS thread_id, r.recorded_at AS recorded_at";
+
+fn fact_from_row(row: &Row) -> anyhow::Result<GraphFact> {
+    let recorded_at: String = row.get("recorded_at")?;
+    Ok(GraphFact {
+        subject: row.get("subject")?,
+        predicate: row.get("predicate")?,
+        object: row.get("object")?,
+        thread_id: row.get("thread_id")?,
+        recorded_at: DateTime::parse_from_rfc3339(&recorded_at)
+            .map(|at| at.with_timezone(&Utc))
+            .context("Invalid recorded_at on a Neo4j fact")?,
+    })
+}
+
+#[async_trait]
+impl GraphStore for Neo4jGraphStore {
+    async fn add(&self, entities: &[GraphEntity], facts: &[GraphFact]) -> anyhow::Result<()> {
+        let mut queries = Vec::with_capacity(entities.len() + facts.len());
+        for entity in entities {
+            queries.push(
+                query(
+                    "MERGE (e:Entity {key: $key}) ON CREATE SET e.name = $name \
+                     SET e.kind = coalesce($kind, e.kind)",
+                )
+                .param("key", key(&entity.name))
+                .param("name", entity.name.trim())
+                .param("kind", entity.kind.clone()),
+            );
+        }
+        for fact in facts {
+            queries.push(
+                query(
+                    "MERGE (a:Entity {key: $subject_key}) ON CREATE SET a.name = $subject \
+                     MERGE (b:Entity {key: $object_key}) ON CREATE SET b.name = $object \
+                     MERGE (a)-[r:RELATES {predicate: $predicate}]->(b) \
+                     SET r.thread_id = $thread_id, r.recorded_at = $recorded_at",
+                )
+                .param("subject_key", key(&fact.subject))
+                .param("subject", fact.subject.trim())
+                .param("object_key", key(&fact.object))
+                .param("object", fact.object.trim())
+                .param("predicate", fact.predicate.as_str())
+                .param("thread_id", fact.thread_id.clone())
+                .param("recorded_at", fact.recorded_at.to_rfc3339()),
+            );
+        }
+        let mut txn = self.graph.start_txn().await?;
+        txn.run_queries(queries).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    async fn find_entities(
+        &self,
+        query_text: &str,
+        limit: usize,
+    ) -> anyhow::Result<Vec<GraphEntity>> {
+        let mut rows = self
+            .graph
+            .execute(
+                query(
+                    "MATCH (e:Entity) WHERE e.key CONTAINS $query \
+                     RETURN e.name AS name, e.kind AS kind \
+                     ORDER BY e.key <> $query, size(e.name) LIMIT $limit",
+                )
+                .param("query", key(query_text))
+                .param("limit", limit as i64),
+            )
+            .await?;
+        let mut entities = Vec::new();
+        while let Some(row) = rows.next().await? {
+            entities.push(GraphEntity {
+                name: row.get("name")?,
+                kind: row.get("kind")?,
+            });
+        }
+        Ok(entities)
+    }
+
+    async fn neighborhood(
+        &self,
+        entity: &str,
+        hops: usize,
+        limit: usize,
+    ) -> anyhow::Result<Vec<GraphFact>> {
+        if hops == 0 {
+            return Ok(Vec::new());
+        }
+        // Variable-length bounds cannot be parameters
+        self.facts(
+            query(&format!(
+                "MATCH path = (:Entity {{key: $key}})-[:RELATES*1..{hops}]-() \
+                 UNWIND range(0, length(path) - 1) AS depth \
+                 WITH relationships(path)[depth] AS r, depth \
+                 WITH r, min(depth) AS depth ORDER BY depth, r.recorded_at LIMIT $limit \
+                 RETURN {FACT_COLUMNS}"
+            ))
+            .param("key", key(entity))
+            .param("limit", limit as i64),
+        )
+        .await
+    }
+
+    async fn path(
+        &self,
+        from: &str,
+        to: &str,
+        max_hops: usize,
+    ) -> anyhow::Result<Option<Vec<GraphFact>>> {
+        if key(from) == key(to) {
+            let exists = !self.find_entities(from, 1).await?.is_empty();
+            return Ok(exists.then(Vec::new));
+        }
+        if max_hops == 0 {
+            return Ok(None);
+        }
+        let facts = self
+            .facts(
+                query(&format!(
+                    "MATCH (a:Entity {{key: $from}}), (b:Entity {{key: $to}}) \
+                     MATCH path = shortestPath((a)-[:RELATES*..{max_hops}]-(b)) \
+                     UNWIND relationships(path) AS r \
+                     RETURN {FACT_COLUMNS}"
+                ))
+                .param("from", key(from))
+                .param("to", key(to)),
+            )
+            .await?;
+        Ok((!facts.is_empty()).then_some(facts))
+    }
+
+    async fn delete_thread(&self, thread_id: &ThreadId) -> anyhow::Result<()> {
+        let mut txn = self.graph.start_txn().await?;
+        txn.run_queries([
+            query("MATCH ()-[r:RELATES {thread_id: $thread_id}]->() DELETE r")
+                .param("thread_id", thread_id.as_str()),
+            query("MATCH (e:Entity) WHERE NOT (e)--() DELETE e"),
+        ])
+        .await?;
+        txn.commit().await?;
+        Ok(())
+    }
+}
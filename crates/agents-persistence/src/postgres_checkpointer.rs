@@ -18,12 +18,49 @@
 //!     updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
 //! );
 //! ```
+//!
+//! Lazily loaded states are read with `state - 'files'` and single files with
+//! `state->'files'->>path`, so large files stay in the database until a tool reads
+//! them.
+//!
+//! It also implements [`EventOutbox`]: events are stored in `<table>_outbox` in the
+//! same transaction as the state and handed to an `OutboxRelay` for delivery.
+//!
+//! ```sql
+//! CREATE TABLE IF NOT EXISTS agent_checkpoints_outbox (
+//!     id BIGSERIAL PRIMARY KEY,
+//!     thread_id TEXT NOT NULL,
+//!     event JSONB NOT NULL,
+//!     attempts INT NOT NULL DEFAULT 0,
+//!     last_error TEXT,
+//!     available_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+//!     delivered_at TIMESTAMPTZ,
+//!     dead BOOLEAN NOT NULL DEFAULT FALSE,
+//!     created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+//! );
+//! ```
+//!
+//! Thread metadata lives in `<table>_metadata`, with a GIN index on the tags so
+//! threads can be listed by tag:
+//!
+//! ```sql
+//! CREATE TABLE IF NOT EXISTS agent_checkpoints_metadata (
+//!     thread_id TEXT PRIMARY KEY,
+//!     metadata JSONB NOT NULL,
+//!     tags TEXT[] NOT NULL DEFAULT '{}',
+//!     updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+//! );
+//! ```
 
-use agents_core::persistence::{Checkpointer, ThreadId};
+use agents_core::events::AgentEvent;
+use agents_core::outbox::{EventOutbox, OutboxRecord};
+use agents_core::persistence::{Checkpointer, ThreadId, ThreadMetadata};
+use agents_core::profiling::{self, ProfilePhase};
 use agents_core::state::AgentStateSnapshot;
 use anyhow::Context;
 use async_trait::async_trait;
 use sqlx::{postgres::PgPoolOptions, PgPool, Row};
+use std::time::Duration;
 
 /// PostgreSQL-backed checkpointer with connection pooling.
 ///
@@ -107,6 +144,126 @@ impl PostgresCheckpointer {
             .await
             .context("Failed to create index")?;
 
+        let create_outbox_sql = format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {} (
+                id BIGSERIAL PRIMARY KEY,
+                thread_id TEXT NOT NULL,
+                event JSONB NOT NULL,
+                attempts INT NOT NULL DEFAULT 0,
+                last_error TEXT,
+                available_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                delivered_at TIMESTAMPTZ,
+                dead BOOLEAN NOT NULL DEFAULT FALSE,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            self.outbox_table()
+        );
+
+        sqlx::query(&create_outbox_sql)
+            .execute(&self.pool)
+            .await
+            .context("Failed to create outbox table")?;
+
+        let create_outbox_index_sql = format!(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_{}_pending
+            ON {} (available_at) WHERE delivered_at IS NULL AND NOT dead
+            "#,
+            self.outbox_table(),
+            self.outbox_table()
+        );
+
+        sqlx::query(&create_outbox_index_sql)
+            .execute(&self.pool)
+            .await
+            .context("Failed to create outbox index")?;
+
+        let create_metadata_sql = format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {} (
+                thread_id TEXT PRIMARY KEY,
+                metadata JSONB NOT NULL,
+                tags TEXT[] NOT NULL DEFAULT '{{}}',
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            self.metadata_table()
+        );
+
+        sqlx::query(&create_metadata_sql)
+            .execute(&self.pool)
+            .await
+            .context("Failed to create thread metadata table")?;
+
+        let create_tags_index_sql = format!(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_{}_tags
+            ON {} USING GIN (tags)
+            "#,
+            self.metadata_table(),
+            self.metadata_table()
+        );
+
+        sqlx::query(&create_tags_index_sql)
+            .execute(&self.pool)
+            .await
+            .context("Failed to create thread tags index")?;
+
+        Ok(())
+    }
+
+    fn outbox_table(&self) -> String {
+        format!("{}_outbox", self.table_name)
+    }
+
+    fn metadata_table(&self) -> String {
+        format!("{}_metadata", self.table_name)
+    }
+
+    /// Upsert of `$2` for thread `$1`. Files deferred by a lazy load (`$3`) keep
+    /// their saved contents.
+    fn upsert_state_sql(&self) -> String {
+        format!(
+            r#"
+            INSERT INTO {table} (thread_id, state, created_at, updated_at)
+            VALUES ($1, $2, NOW(), NOW())
+            ON CONFLICT (thread_id)
+            DO UPDATE SET state = jsonb_set(
+                $2,
+                '{{files}}',
+                COALESCE(
+                    (SELECT jsonb_object_agg(key, value)
+                     FROM jsonb_each({table}.state->'files')
+                     WHERE key = ANY($3)),
+                    '{{}}'::jsonb
+                ) || ($2->'files')
+            ), updated_at = NOW()
+            "#,
+            table = self.table_name
+        )
+    }
+
+    async fn insert_events(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        thread_id: &ThreadId,
+        events: &[AgentEvent],
+    ) -> anyhow::Result<()> {
+        let query = format!(
+            "INSERT INTO {} (thread_id, event) VALUES ($1, $2)",
+            self.outbox_table()
+        );
+        for event in events {
+            let json = serde_json::to_value(event).context("Failed to serialize event")?;
+            sqlx::query(&query)
+                .bind(thread_id)
+                .bind(&json)
+                .execute(&mut **tx)
+                .await
+                .context("Failed to write event to the outbox")?;
+        }
         Ok(())
     }
 }
@@ -118,22 +275,13 @@ impl Checkpointer for PostgresCheckpointer {
         thread_id: &ThreadId,
         state: &AgentStateSnapshot,
     ) -> anyhow::Result<()> {
-        let json =
-            serde_json::to_value(state).context("Failed to serialize agent state to JSON")?;
-
-        let query = format!(
-            r#"
-            INSERT INTO {} (thread_id, state, created_at, updated_at)
-            VALUES ($1, $2, NOW(), NOW())
-            ON CONFLICT (thread_id) 
-            DO UPDATE SET state = $2, updated_at = NOW()
-            "#,
-            self.table_name
-        );
+        let json = profiling::time(ProfilePhase::Serialization, || serde_json::to_value(state))
+            .context("Failed to serialize agent state to JSON")?;
 
-        sqlx::query(&query)
+        sqlx::query(&self.upsert_state_sql())
             .bind(thread_id)
             .bind(&json)
+            .bind(deferred_files(state))
             .execute(&self.pool)
             .await
             .context("Failed to save state to PostgreSQL")?;
@@ -163,8 +311,9 @@ impl Checkpointer for PostgresCheckpointer {
 
         match row {
             Some((json,)) => {
-                let state: AgentStateSnapshot = serde_json::from_value(json)
-                    .context("Failed to deserialize agent state from JSON")?;
+                let state: AgentStateSnapshot =
+                    profiling::time(ProfilePhase::Serialization, || serde_json::from_value(json))
+                        .context("Failed to deserialize agent state from JSON")?;
 
                 tracing::debug!(
                     thread_id = %thread_id,
@@ -185,6 +334,61 @@ impl Checkpointer for PostgresCheckpointer {
         }
     }
 
+    async fn load_state_lazy(
+        &self,
+        thread_id: &ThreadId,
+    ) -> anyhow::Result<Option<AgentStateSnapshot>> {
+        let query = format!(
+            r#"
+            SELECT jsonb_set(state, '{{files}}', '{{}}'::jsonb),
+                   ARRAY(SELECT jsonb_object_keys(COALESCE(state->'files', '{{}}'::jsonb)))
+            FROM {} WHERE thread_id = $1
+            "#,
+            self.table_name
+        );
+
+        let row: Option<(serde_json::Value, Vec<String>)> = sqlx::query_as(&query)
+            .bind(thread_id)
+            .fetch_optional(&self.pool)
+            .await
+            .context("Failed to load state from PostgreSQL")?;
+
+        let Some((json, files)) = row else {
+            return Ok(None);
+        };
+        let mut state: AgentStateSnapshot =
+            profiling::time(ProfilePhase::Serialization, || serde_json::from_value(json))
+                .context("Failed to deserialize agent state from JSON")?;
+        state.deferred_files = files.into_iter().collect();
+
+        tracing::debug!(
+            thread_id = %thread_id,
+            table = %self.table_name,
+            deferred_files = state.deferred_files.len(),
+            "Loaded agent state without files from PostgreSQL"
+        );
+
+        Ok(Some(state))
+    }
+
+    async fn load_file(&self, thread_id: &ThreadId, path: &str) -> anyhow::Result<Option<String>> {
+        let query = format!(
+            r#"
+            SELECT state->'files'->>$2 FROM {} WHERE thread_id = $1
+            "#,
+            self.table_name
+        );
+
+        let row: Option<(Option<String>,)> = sqlx::query_as(&query)
+            .bind(thread_id)
+            .bind(path)
+            .fetch_optional(&self.pool)
+            .await
+            .context("Failed to load file from PostgreSQL")?;
+
+        Ok(row.and_then(|(content,)| content))
+    }
+
     async fn delete_thread(&self, thread_id: &ThreadId) -> anyhow::Result<()> {
         let query = format!(
             r#"
@@ -199,6 +403,13 @@ impl Checkpointer for PostgresCheckpointer {
             .await
             .context("Failed to delete thread from PostgreSQL")?;
 
+        let delete_metadata = format!("DELETE FROM {} WHERE thread_id = $1", self.metadata_table());
+        sqlx::query(&delete_metadata)
+            .bind(thread_id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to delete thread metadata from PostgreSQL")?;
+
         tracing::debug!(
             thread_id = %thread_id,
             table = %self.table_name,
@@ -228,6 +439,219 @@ impl Checkpointer for PostgresCheckpointer {
 
         Ok(threads)
     }
+
+    async fn load_metadata(&self, thread_id: &ThreadId) -> anyhow::Result<Option<ThreadMetadata>> {
+        let query = format!(
+            "SELECT metadata FROM {} WHERE thread_id = $1",
+            self.metadata_table()
+        );
+
+        let row: Option<(serde_json::Value,)> = sqlx::query_as(&query)
+            .bind(thread_id)
+            .fetch_optional(&self.pool)
+            .await
+            .context("Failed to load thread metadata from PostgreSQL")?;
+
+        row.map(|(json,)| {
+            serde_json::from_value(json).context("Failed to deserialize thread metadata")
+        })
+        .transpose()
+    }
+
+    async fn save_metadata(
+        &self,
+        thread_id: &ThreadId,
+        metadata: &ThreadMetadata,
+    ) -> anyhow::Result<()> {
+        let json = serde_json::to_value(metadata).context("Failed to serialize thread metadata")?;
+        let tags: Vec<String> = metadata.tags.iter().cloned().collect();
+        let query = format!(
+            r#"
+            INSERT INTO {} (thread_id, metadata, tags, updated_at)
+            VALUES ($1, $2, $3, NOW())
+            ON CONFLICT (thread_id)
+            DO UPDATE SET metadata = $2, tags = $3, updated_at = NOW()
+            "#,
+            self.metadata_table()
+        );
+
+        sqlx::query(&query)
+            .bind(thread_id)
+            .bind(&json)
+            .bind(&tags)
+            .execute(&self.pool)
+            .await
+            .context("Failed to save thread metadata to PostgreSQL")?;
+
+        tracing::debug!(
+            thread_id = %thread_id,
+            table = %self.metadata_table(),
+            "Saved thread metadata to PostgreSQL"
+        );
+
+        Ok(())
+    }
+
+    async fn list_threads_by_tag(&self, tag: &str) -> anyhow::Result<Vec<ThreadId>> {
+        let query = format!(
+            "SELECT thread_id FROM {} WHERE tags @> ARRAY[$1] ORDER BY updated_at DESC",
+            self.metadata_table()
+        );
+
+        let rows: Vec<(String,)> = sqlx::query_as(&query)
+            .bind(tag)
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to list tagged threads from PostgreSQL")?;
+
+        Ok(rows.into_iter().map(|(thread_id,)| thread_id).collect())
+    }
+}
+
+fn deferred_files(state: &AgentStateSnapshot) -> Vec<String> {
+    state.deferred_files.iter().cloned().collect()
+}
+
+#[async_trait]
+impl EventOutbox for PostgresCheckpointer {
+    async fn save_state_with_events(
+        &self,
+        thread_id: &ThreadId,
+        state: &AgentStateSnapshot,
+        events: &[AgentEvent],
+    ) -> anyhow::Result<()> {
+        let json = profiling::time(ProfilePhase::Serialization, || serde_json::to_value(state))
+            .context("Failed to serialize agent state to JSON")?;
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to start transaction")?;
+        sqlx::query(&self.upsert_state_sql())
+            .bind(thread_id)
+            .bind(&json)
+            .bind(deferred_files(state))
+            .execute(&mut *tx)
+            .await
+            .context("Failed to save state to PostgreSQL")?;
+        self.insert_events(&mut tx, thread_id, events).await?;
+        tx.commit()
+            .await
+            .context("Failed to commit state and events")?;
+
+        tracing::debug!(
+            thread_id = %thread_id,
+            table = %self.table_name,
+            events = events.len(),
+            "Saved agent state and events to PostgreSQL"
+        );
+
+        Ok(())
+    }
+
+    async fn enqueue_events(
+        &self,
+        thread_id: &ThreadId,
+        events: &[AgentEvent],
+    ) -> anyhow::Result<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to start transaction")?;
+        self.insert_events(&mut tx, thread_id, events).await?;
+        tx.commit().await.context("Failed to commit events")?;
+        Ok(())
+    }
+
+    async fn claim_pending(
+        &self,
+        limit: usize,
+        claim_for: Duration,
+    ) -> anyhow::Result<Vec<OutboxRecord>> {
+        let query = format!(
+            r#"
+            UPDATE {table} SET available_at = NOW() + make_interval(secs => $2)
+            WHERE id IN (
+                SELECT id FROM {table}
+                WHERE delivered_at IS NULL AND NOT dead AND available_at <= NOW()
+                ORDER BY id
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, thread_id, event, attempts
+            "#,
+            table = self.outbox_table()
+        );
+
+        let rows: Vec<(i64, String, serde_json::Value, i32)> = sqlx::query_as(&query)
+            .bind(limit as i64)
+            .bind(claim_for.as_secs_f64())
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to claim outbox events")?;
+
+        let mut records = Vec::with_capacity(rows.len());
+        for (id, thread_id, event, attempts) in rows {
+            match serde_json::from_value(event) {
+                Ok(event) => records.push(OutboxRecord {
+                    id,
+                    thread_id,
+                    event,
+                    attempts: attempts.max(0) as u32,
+                }),
+                Err(e) => {
+                    // Park events this version cannot read instead of retrying them forever
+                    tracing::warn!(event_id = id, error = %e, "Unreadable outbox event");
+                    self.mark_failed(id, &e.to_string(), None).await?;
+                }
+            }
+        }
+        records.sort_by_key(|record| record.id);
+        Ok(records)
+    }
+
+    async fn mark_delivered(&self, ids: &[i64]) -> anyhow::Result<()> {
+        let query = format!(
+            "UPDATE {} SET delivered_at = NOW() WHERE id = ANY($1)",
+            self.outbox_table()
+        );
+        sqlx::query(&query)
+            .bind(ids)
+            .execute(&self.pool)
+            .await
+            .context("Failed to mark outbox events as delivered")?;
+        Ok(())
+    }
+
+    async fn mark_failed(
+        &self,
+        id: i64,
+        error: &str,
+        retry_after: Option<Duration>,
+    ) -> anyhow::Result<()> {
+        let query = format!(
+            r#"
+            UPDATE {} SET
+                attempts = attempts + 1,
+                last_error = $2,
+                available_at = NOW() + make_interval(secs => $3),
+                dead = $4
+            WHERE id = $1
+            "#,
+            self.outbox_table()
+        );
+        sqlx::query(&query)
+            .bind(id)
+            .bind(error)
+            .bind(retry_after.unwrap_or_default().as_secs_f64())
+            .bind(retry_after.is_none())
+            .execute(&self.pool)
+            .await
+            .context("Failed to record outbox delivery failure")?;
+        Ok(())
+    }
 }
 
 /// Builder for configuring a PostgreSQL checkpointer.
@@ -356,6 +780,45 @@ mod tests {
             .expect("Failed to delete thread");
     }
 
+    #[tokio::test]
+    #[ignore] // Requires PostgreSQL instance running
+    async fn test_postgres_lazy_load_keeps_unread_files() {
+        let checkpointer = PostgresCheckpointer::new("postgresql://localhost/agents_test")
+            .await
+            .expect("Failed to connect to PostgreSQL");
+
+        let thread_id = "test-lazy-thread".to_string();
+        let mut state = sample_state();
+        state
+            .files
+            .insert("notes.md".to_string(), "draft".to_string());
+        checkpointer.save_state(&thread_id, &state).await.unwrap();
+
+        let mut lazy = checkpointer
+            .load_state_lazy(&thread_id)
+            .await
+            .unwrap()
+            .expect("State should exist");
+        assert!(lazy.files.is_empty());
+        assert_eq!(lazy.file_paths(), vec!["notes.md", "test.txt"]);
+        assert_eq!(
+            checkpointer
+                .load_file(&thread_id, "test.txt")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("content")
+        );
+
+        lazy.insert_file("notes.md", "final");
+        checkpointer.save_state(&thread_id, &lazy).await.unwrap();
+        let saved = checkpointer.load_state(&thread_id).await.unwrap().unwrap();
+        assert_eq!(saved.files.get("notes.md").unwrap(), "final");
+        assert_eq!(saved.files.get("test.txt").unwrap(), "content");
+
+        checkpointer.delete_thread(&thread_id).await.unwrap();
+    }
+
     #[tokio::test]
     #[ignore] // Requires PostgreSQL instance running
     async fn test_postgres_list_threads() {
@@ -393,4 +856,38 @@ mod tests {
             .await
             .unwrap();
     }
+
+    #[tokio::test]
+    #[ignore] // Requires PostgreSQL instance running
+    async fn test_postgres_thread_metadata_and_tags() {
+        let checkpointer = PostgresCheckpointer::new("postgresql://localhost/agents_test")
+            .await
+            .expect("Failed to connect to PostgreSQL");
+
+        let thread_id = "test-tagged-thread".to_string();
+        let metadata = ThreadMetadata::new()
+            .with_value("channel", "email")
+            .with_tag("vip");
+        checkpointer
+            .save_metadata(&thread_id, &metadata)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            checkpointer.load_metadata(&thread_id).await.unwrap(),
+            Some(metadata)
+        );
+        assert!(checkpointer
+            .list_threads_by_tag("vip")
+            .await
+            .unwrap()
+            .contains(&thread_id));
+
+        checkpointer.delete_thread(&thread_id).await.unwrap();
+        assert!(checkpointer
+            .load_metadata(&thread_id)
+            .await
+            .unwrap()
+            .is_none());
+    }
 }
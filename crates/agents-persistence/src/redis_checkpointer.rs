@@ -12,8 +12,10 @@
 //! - Connection pooling for efficient resource usage
 //! - TTL support for automatic state expiration
 //! - Namespace support for multi-tenant applications
+//! - Thread metadata, with a set per tag for listing threads by tag
 
-use agents_core::persistence::{Checkpointer, ThreadId};
+use agents_core::persistence::{Checkpointer, ThreadId, ThreadMetadata};
+use agents_core::profiling::{self, ProfilePhase};
 use agents_core::state::AgentStateSnapshot;
 use anyhow::Context;
 use async_trait::async_trait;
@@ -75,6 +77,16 @@ impl RedisCheckpointer {
     fn threads_index_key(&self) -> String {
         format!("{}:threads", self.namespace)
     }
+
+    /// Generate the Redis key for the metadata of a thread.
+    fn metadata_key(&self, thread_id: &ThreadId) -> String {
+        format!("{}:thread-metadata:{}", self.namespace, thread_id)
+    }
+
+    /// Generate the Redis key for the set of threads tagged with `tag`.
+    fn tag_index_key(&self, tag: &str) -> String {
+        format!("{}:tag:{}", self.namespace, tag)
+    }
 }
 
 #[async_trait]
@@ -87,8 +99,8 @@ impl Checkpointer for RedisCheckpointer {
         let key = self.key_for_thread(thread_id);
         let index_key = self.threads_index_key();
 
-        let json =
-            serde_json::to_string(state).context("Failed to serialize agent state to JSON")?;
+        let json = profiling::time(ProfilePhase::Serialization, || serde_json::to_string(state))
+            .context("Failed to serialize agent state to JSON")?;
 
         let mut conn = self.connection.clone();
 
@@ -128,8 +140,9 @@ impl Checkpointer for RedisCheckpointer {
 
         match json {
             Some(data) => {
-                let state: AgentStateSnapshot = serde_json::from_str(&data)
-                    .context("Failed to deserialize agent state from JSON")?;
+                let state: AgentStateSnapshot =
+                    profiling::time(ProfilePhase::Serialization, || serde_json::from_str(&data))
+                        .context("Failed to deserialize agent state from JSON")?;
 
                 tracing::debug!(
                     thread_id = %thread_id,
@@ -165,6 +178,18 @@ impl Checkpointer for RedisCheckpointer {
             .await
             .context("Failed to update thread index")?;
 
+        // Delete the metadata and its tag entries
+        if let Some(metadata) = self.load_metadata(thread_id).await? {
+            for tag in &metadata.tags {
+                conn.srem::<_, _, ()>(self.tag_index_key(tag), thread_id)
+                    .await
+                    .context("Failed to update tag index")?;
+            }
+            conn.del::<_, ()>(self.metadata_key(thread_id))
+                .await
+                .context("Failed to delete thread metadata from Redis")?;
+        }
+
         tracing::debug!(
             thread_id = %thread_id,
             namespace = %self.namespace,
@@ -185,6 +210,66 @@ impl Checkpointer for RedisCheckpointer {
 
         Ok(threads)
     }
+
+    async fn load_metadata(&self, thread_id: &ThreadId) -> anyhow::Result<Option<ThreadMetadata>> {
+        let mut conn = self.connection.clone();
+
+        let json: Option<String> = conn
+            .get(self.metadata_key(thread_id))
+            .await
+            .context("Failed to load thread metadata from Redis")?;
+
+        json.map(|data| {
+            serde_json::from_str(&data).context("Failed to deserialize thread metadata from JSON")
+        })
+        .transpose()
+    }
+
+    async fn save_metadata(
+        &self,
+        thread_id: &ThreadId,
+        metadata: &ThreadMetadata,
+    ) -> anyhow::Result<()> {
+        let json =
+            serde_json::to_string(metadata).context("Failed to serialize thread metadata")?;
+        let previous = self.load_metadata(thread_id).await?.unwrap_or_default();
+        let mut conn = self.connection.clone();
+
+        conn.set::<_, _, ()>(self.metadata_key(thread_id), json)
+            .await
+            .context("Failed to save thread metadata to Redis")?;
+
+        for tag in previous.tags.difference(&metadata.tags) {
+            conn.srem::<_, _, ()>(self.tag_index_key(tag), thread_id)
+                .await
+                .context("Failed to update tag index")?;
+        }
+        for tag in &metadata.tags {
+            conn.sadd::<_, _, ()>(self.tag_index_key(tag), thread_id)
+                .await
+                .context("Failed to update tag index")?;
+        }
+
+        tracing::debug!(
+            thread_id = %thread_id,
+            namespace = %self.namespace,
+            tags = metadata.tags.len(),
+            "Saved thread metadata to Redis"
+        );
+
+        Ok(())
+    }
+
+    async fn list_threads_by_tag(&self, tag: &str) -> anyhow::Result<Vec<ThreadId>> {
+        let mut conn = self.connection.clone();
+
+        let threads: Vec<String> = conn
+            .smembers(self.tag_index_key(tag))
+            .await
+            .context("Failed to list tagged threads from Redis")?;
+
+        Ok(threads)
+    }
 }
 
 /// Builder for configuring a Redis checkpointer.
@@ -329,4 +414,51 @@ mod tests {
             .await
             .unwrap();
     }
+
+    #[tokio::test]
+    #[ignore] // Requires Redis instance running
+    async fn test_redis_thread_metadata_and_tags() {
+        let checkpointer = RedisCheckpointer::builder()
+            .url("redis://127.0.0.1:6379")
+            .namespace("test-metadata")
+            .build()
+            .await
+            .expect("Failed to connect to Redis");
+
+        let thread_id = "tagged-thread".to_string();
+        let metadata = ThreadMetadata::new()
+            .with_value("customer_id", "cus_42")
+            .with_tag("vip");
+        checkpointer
+            .save_metadata(&thread_id, &metadata)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            checkpointer.load_metadata(&thread_id).await.unwrap(),
+            Some(metadata)
+        );
+        assert_eq!(
+            checkpointer.list_threads_by_tag("vip").await.unwrap(),
+            vec![thread_id.clone()]
+        );
+
+        // Retagging drops the thread from its old tags
+        checkpointer
+            .save_metadata(&thread_id, &ThreadMetadata::new().with_tag("churned"))
+            .await
+            .unwrap();
+        assert!(checkpointer
+            .list_threads_by_tag("vip")
+            .await
+            .unwrap()
+            .is_empty());
+
+        checkpointer.delete_thread(&thread_id).await.unwrap();
+        assert!(checkpointer
+            .list_threads_by_tag("churned")
+            .await
+            .unwrap()
+            .is_empty());
+    }
 }
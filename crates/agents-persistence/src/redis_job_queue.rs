@@ -0,0 +1,388 @@
+//! Redis Streams-backed job queue for agent workers.
+//!
+//! Jobs are entries of the `<namespace>:jobs` stream, read by the workers of one
+//! consumer group. Entries stay in the group's pending list until the worker that
+//! read them completes them; entries idle for longer than the claim timeout (the
+//! worker crashed) are taken over by the next worker that claims jobs.
+//!
+//! Keys written per job:
+//! - `<namespace>:job:<job_id>:result` — the [`AgentJobResult`] as JSON
+//! - `<namespace>:job:<job_id>:events` — a stream of the run's events
+//!
+//! Every result is also appended to the `<namespace>:job_results` stream so
+//! producers can follow completions without polling each job.
+
+use agents_core::events::AgentEvent;
+use agents_core::job_queue::{AgentJob, AgentJobResult, ClaimedJob, JobQueue};
+use anyhow::Context;
+use async_trait::async_trait;
+use redis::aio::ConnectionManager;
+use redis::streams::{StreamAutoClaimReply, StreamId, StreamPendingCountReply, StreamReadReply};
+use redis::AsyncCommands;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// Approximate length kept for the results stream and each job's event stream.
+const STREAM_MAXLEN: usize = 10_000;
+
+/// Redis Streams implementation of [`JobQueue`].
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use agents_core::job_queue::{AgentJob, JobQueue};
+/// use agents_persistence::RedisJobQueue;
+/// use std::time::Duration;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let queue = RedisJobQueue::builder()
+///         .url("redis://127.0.0.1:6379")
+///         .namespace("myapp")
+///         .claim_timeout(Duration::from_secs(600))
+///         .build()
+///         .await?;
+///
+///     queue.enqueue(&AgentJob::new("thread-42", "Summarize my open orders")).await?;
+///     Ok(())
+/// }
+/// ```
+pub struct RedisJobQueue {
+    connection: ConnectionManager,
+    /// Separate connection for blocking reads, which would stall other commands
+    blocking: Mutex<ConnectionManager>,
+    namespace: String,
+    group: String,
+    claim_timeout: Duration,
+    result_ttl: Duration,
+}
+
+impl RedisJobQueue {
+    /// Create a queue backed by a single Redis instance.
+    pub async fn new(url: &str) -> anyhow::Result<Self> {
+        Self::builder().url(url).build().await
+    }
+
+    /// Create a builder for configuring the Redis job queue.
+    pub fn builder() -> RedisJobQueueBuilder {
+        RedisJobQueueBuilder::default()
+    }
+
+    fn jobs_key(&self) -> String {
+        format!("{}:jobs", self.namespace)
+    }
+
+    fn results_key(&self) -> String {
+        format!("{}:job_results", self.namespace)
+    }
+
+    fn result_key(&self, job_id: &str) -> String {
+        format!("{}:job:{}:result", self.namespace, job_id)
+    }
+
+    fn events_key(&self, job_id: &str) -> String {
+        format!("{}:job:{}:events", self.namespace, job_id)
+    }
+
+    /// Take over entries other workers left idle for longer than the claim timeout.
+    async fn reclaim(&self, consumer: &str, limit: usize) -> anyhow::Result<Vec<ClaimedJob>> {
+        let mut conn = self.connection.clone();
+        let reply: StreamAutoClaimReply = redis::cmd("XAUTOCLAIM")
+            .arg(self.jobs_key())
+            .arg(&self.group)
+            .arg(consumer)
+            .arg(self.claim_timeout.as_millis() as u64)
+            .arg("0-0")
+            .arg("COUNT")
+            .arg(limit)
+            .query_async(&mut conn)
+            .await
+            .context("Failed to reclaim idle jobs")?;
+
+        let mut jobs = Vec::with_capacity(reply.claimed.len());
+        for entry in reply.claimed {
+            let pending: StreamPendingCountReply = conn
+                .xpending_count(self.jobs_key(), &self.group, &entry.id, &entry.id, 1)
+                .await?;
+            let deliveries = pending.ids.first().map_or(1, |p| p.times_delivered as u32);
+            tracing::warn!(entry_id = %entry.id, deliveries, "Reclaimed idle job");
+            jobs.extend(self.parse_entry(entry, deliveries).await?);
+        }
+        Ok(jobs)
+    }
+
+    /// Decode a stream entry; malformed entries are acknowledged and dropped.
+    async fn parse_entry(
+        &self,
+        entry: StreamId,
+        deliveries: u32,
+    ) -> anyhow::Result<Option<ClaimedJob>> {
+        let job = entry
+            .get::<String>("job")
+            .and_then(|json| serde_json::from_str::<AgentJob>(&json).ok());
+        match job {
+            Some(job) => Ok(Some(ClaimedJob {
+                job,
+                receipt: entry.id,
+                deliveries,
+            })),
+            None => {
+                tracing::error!(entry_id = %entry.id, "Dropping malformed job entry");
+                let mut conn = self.connection.clone();
+                let _: () = redis::pipe()
+                    .xack(self.jobs_key(), &self.group, &[&entry.id])
+                    .ignore()
+                    .xdel(self.jobs_key(), &[&entry.id])
+                    .ignore()
+                    .query_async(&mut conn)
+                    .await?;
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl JobQueue for RedisJobQueue {
+    async fn enqueue(&self, job: &AgentJob) -> anyhow::Result<()> {
+        let json = serde_json::to_string(job).context("Failed to serialize job")?;
+        let mut conn = self.connection.clone();
+        let _: String = conn
+            .xadd(self.jobs_key(), "*", &[("job", json)])
+            .await
+            .context("Failed to enqueue job")?;
+        tracing::debug!(job_id = %job.job_id, thread_id = %job.thread_id, "Enqueued job in Redis");
+        Ok(())
+    }
+
+    async fn claim(
+        &self,
+        consumer: &str,
+        limit: usize,
+        wait: Duration,
+    ) -> anyhow::Result<Vec<ClaimedJob>> {
+        let reclaimed = self.reclaim(consumer, limit).await?;
+        if !reclaimed.is_empty() {
+            return Ok(reclaimed);
+        }
+
+        let mut read = redis::cmd("XREADGROUP");
+        read.arg("GROUP")
+            .arg(&self.group)
+            .arg(consumer)
+            .arg("COUNT")
+            .arg(limit);
+        // BLOCK 0 would wait forever
+        if !wait.is_zero() {
+            read.arg("BLOCK").arg(wait.as_millis().max(1) as u64);
+        }
+        read.arg("STREAMS").arg(self.jobs_key()).arg(">");
+
+        let reply: Option<StreamReadReply> = {
+            let mut conn = self.blocking.lock().await;
+            read.query_async(&mut *conn)
+                .await
+                .context("Failed to read jobs")?
+        };
+        let mut jobs = Vec::new();
+        for entry in reply.into_iter().flat_map(|r| r.keys).flat_map(|k| k.ids) {
+            jobs.extend(self.parse_entry(entry, 1).await?);
+        }
+        Ok(jobs)
+    }
+
+    async fn complete(&self, job: &ClaimedJob, result: &AgentJobResult) -> anyhow::Result<()> {
+        let json = serde_json::to_string(result).context("Failed to serialize job result")?;
+        let mut conn = self.connection.clone();
+        let _: () = redis::pipe()
+            .atomic()
+            .set_ex(
+                self.result_key(&result.job_id),
+                &json,
+                self.result_ttl.as_secs().max(1),
+            )
+            .ignore()
+            .xadd_maxlen(
+                self.results_key(),
+                redis::streams::StreamMaxlen::Approx(STREAM_MAXLEN),
+                "*",
+                &[
+                    ("job_id", result.job_id.as_str()),
+                    ("result", json.as_str()),
+                ],
+            )
+            .ignore()
+            .xack(self.jobs_key(), &self.group, &[&job.receipt])
+            .ignore()
+            .xdel(self.jobs_key(), &[&job.receipt])
+            .ignore()
+            .query_async(&mut conn)
+            .await
+            .context("Failed to complete job")?;
+        tracing::debug!(job_id = %result.job_id, "Completed job in Redis");
+        Ok(())
+    }
+
+    async fn publish_event(&self, job_id: &str, event: &AgentEvent) -> anyhow::Result<()> {
+        let json = event.to_wire_json().context("Failed to serialize event")?;
+        let key = self.events_key(job_id);
+        let mut conn = self.connection.clone();
+        let _: () = redis::pipe()
+            .xadd_maxlen(
+                &key,
+                redis::streams::StreamMaxlen::Approx(STREAM_MAXLEN),
+                "*",
+                &[("event", json)],
+            )
+            .ignore()
+            .expire(&key, self.result_ttl.as_secs().max(1) as i64)
+            .ignore()
+            .query_async(&mut conn)
+            .await
+            .context("Failed to publish job event")?;
+        Ok(())
+    }
+
+    async fn result(&self, job_id: &str) -> anyhow::Result<Option<AgentJobResult>> {
+        let mut conn = self.connection.clone();
+        let json: Option<String> = conn
+            .get(self.result_key(job_id))
+            .await
+            .context("Failed to load job result")?;
+        json.map(|json| serde_json::from_str(&json).context("Failed to deserialize job result"))
+            .transpose()
+    }
+}
+
+/// Builder for configuring a Redis job queue.
+#[derive(Default)]
+pub struct RedisJobQueueBuilder {
+    url: Option<String>,
+    namespace: Option<String>,
+    group: Option<String>,
+    claim_timeout: Option<Duration>,
+    result_ttl: Option<Duration>,
+}
+
+impl RedisJobQueueBuilder {
+    /// Set the Redis connection URL.
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Set the namespace for queue keys (default: "agents").
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Set the consumer group shared by the workers (default: "agent-workers").
+    pub fn group(mut self, group: impl Into<String>) -> Self {
+        self.group = Some(group.into());
+        self
+    }
+
+    /// Time after which a job claimed by an unresponsive worker is handed out again
+    /// (default: 5 minutes). Keep it above the longest expected run.
+    pub fn claim_timeout(mut self, timeout: Duration) -> Self {
+        self.claim_timeout = Some(timeout);
+        self
+    }
+
+    /// How long results and events are kept (default: 24 hours).
+    pub fn result_ttl(mut self, ttl: Duration) -> Self {
+        self.result_ttl = Some(ttl);
+        self
+    }
+
+    /// Connect and create the consumer group if it does not exist yet.
+    pub async fn build(self) -> anyhow::Result<RedisJobQueue> {
+        let url = self
+            .url
+            .ok_or_else(|| anyhow::anyhow!("Redis URL is required"))?;
+        let client = redis::Client::open(url.as_str()).context("Failed to create Redis client")?;
+        let connection = ConnectionManager::new(client.clone())
+            .await
+            .context("Failed to establish Redis connection")?;
+        let blocking = ConnectionManager::new(client)
+            .await
+            .context("Failed to establish Redis connection")?;
+
+        let queue = RedisJobQueue {
+            connection,
+            blocking: Mutex::new(blocking),
+            namespace: self.namespace.unwrap_or_else(|| "agents".to_string()),
+            group: self.group.unwrap_or_else(|| "agent-workers".to_string()),
+            claim_timeout: self.claim_timeout.unwrap_or(Duration::from_secs(300)),
+            result_ttl: self.result_ttl.unwrap_or(Duration::from_secs(86_400)),
+        };
+
+        // Start from the beginning so jobs enqueued before the first worker are processed
+        let mut conn = queue.connection.clone();
+        let created: redis::RedisResult<()> = conn
+            .xgroup_create_mkstream(queue.jobs_key(), &queue.group, "0")
+            .await;
+        match created {
+            Ok(()) => {}
+            Err(e) if e.code() == Some("BUSYGROUP") => {}
+            Err(e) => return Err(e).context("Failed to create consumer group"),
+        }
+        Ok(queue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+
+    #[tokio::test]
+    #[ignore] // Requires Redis instance running
+    async fn test_redis_job_roundtrip() {
+        let queue = RedisJobQueue::builder()
+            .url("redis://127.0.0.1:6379")
+            .namespace(format!("test-{}", uuid::Uuid::new_v4().simple()))
+            .claim_timeout(Duration::from_millis(50))
+            .build()
+            .await
+            .expect("Failed to connect to Redis");
+
+        let job = AgentJob::new("thread-1", "hello");
+        queue.enqueue(&job).await.unwrap();
+
+        let claimed = queue
+            .claim("worker-a", 10, Duration::from_millis(100))
+            .await
+            .unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].job, job);
+
+        // worker-a goes silent; worker-b takes the job over
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let reclaimed = queue
+            .claim("worker-b", 10, Duration::from_millis(100))
+            .await
+            .unwrap();
+        assert_eq!(reclaimed[0].deliveries, 2);
+
+        let result = AgentJobResult {
+            job_id: job.job_id.clone(),
+            thread_id: job.thread_id.clone(),
+            outcome: Ok(AgentMessage {
+                role: MessageRole::Agent,
+                content: MessageContent::Text("hi".into()),
+                metadata: None,
+            }),
+            worker: "worker-b".into(),
+        };
+        queue.complete(&reclaimed[0], &result).await.unwrap();
+        let stored = queue.result(&job.job_id).await.unwrap().unwrap();
+        assert_eq!(stored.worker, "worker-b");
+        assert!(queue
+            .claim("worker-c", 10, Duration::ZERO)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,178 @@
+//! Redis-backed user memory store.
+//!
+//! Each user's memories are one hash, `<namespace>:memory:<user_id>`, mapping the
+//! entry key to the entry as JSON, so erasing a user is a single `DEL`.
+
+use agents_core::memory::{MemoryEntry, MemoryStore, UserId};
+use anyhow::Context;
+use async_trait::async_trait;
+use redis::{aio::ConnectionManager, AsyncCommands};
+
+/// Redis implementation of [`MemoryStore`], shared by all agent replicas.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use agents_persistence::RedisMemoryStore;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let store = RedisMemoryStore::new("redis://127.0.0.1:6379").await?;
+///
+///     // With namespace
+///     let store = RedisMemoryStore::builder()
+///         .url("redis://127.0.0.1:6379")
+///         .namespace("myapp")
+///         .build()
+///         .await?;
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct RedisMemoryStore {
+    connection: ConnectionManager,
+    namespace: String,
+}
+
+impl RedisMemoryStore {
+    /// Create a store with the default namespace.
+    pub async fn new(url: &str) -> anyhow::Result<Self> {
+        Self::builder().url(url).build().await
+    }
+
+    /// Create a builder for configuring the Redis memory store.
+    pub fn builder() -> RedisMemoryStoreBuilder {
+        RedisMemoryStoreBuilder::default()
+    }
+
+    fn user_key(&self, user_id: &UserId) -> String {
+        format!("{}:memory:{}", self.namespace, user_id)
+    }
+}
+
+#[async_trait]
+impl MemoryStore for RedisMemoryStore {
+    async fn put(&self, user_id: &UserId, entries: &[MemoryEntry]) -> anyhow::Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let fields = entries
+            .iter()
+            .map(|entry| Ok((entry.key.clone(), serde_json::to_string(entry)?)))
+            .collect::<anyhow::Result<Vec<(String, String)>>>()?;
+        let mut conn = self.connection.clone();
+        conn.hset_multiple::<_, _, _, ()>(self.user_key(user_id), &fields)
+            .await
+            .context("Failed to save user memories to Redis")
+    }
+
+    async fn list(&self, user_id: &UserId) -> anyhow::Result<Vec<MemoryEntry>> {
+        let mut conn = self.connection.clone();
+        let fields: Vec<(String, String)> = conn
+            .hgetall(self.user_key(user_id))
+            .await
+            .context("Failed to load user memories from Redis")?;
+        let mut entries = fields
+            .into_iter()
+            .map(|(_, json)| serde_json::from_str(&json).context("Invalid user memory in Redis"))
+            .collect::<anyhow::Result<Vec<MemoryEntry>>>()?;
+        entries.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(entries)
+    }
+
+    async fn delete(&self, user_id: &UserId, key: &str) -> anyhow::Result<bool> {
+        let mut conn = self.connection.clone();
+        let deleted: usize = conn
+            .hdel(self.user_key(user_id), key)
+            .await
+            .context("Failed to delete user memory from Redis")?;
+        Ok(deleted > 0)
+    }
+
+    async fn erase_user(&self, user_id: &UserId) -> anyhow::Result<usize> {
+        let mut conn = self.connection.clone();
+        let key = self.user_key(user_id);
+        let (count, _): (usize, ()) = redis::pipe()
+            .atomic()
+            .hlen(&key)
+            .del(&key)
+            .ignore()
+            .query_async(&mut conn)
+            .await
+            .context("Failed to erase user memories from Redis")?;
+        Ok(count)
+    }
+}
+
+/// Builder for configuring a [`RedisMemoryStore`].
+#[derive(Default)]
+pub struct RedisMemoryStoreBuilder {
+    url: Option<String>,
+    namespace: Option<String>,
+}
+
+impl RedisMemoryStoreBuilder {
+    /// Set the Redis connection URL.
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Set the namespace prefix for all keys (default: "agents").
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Build the store and connect to Redis.
+    pub async fn build(self) -> anyhow::Result<RedisMemoryStore> {
+        let url = self
+            .url
+            .ok_or_else(|| anyhow::anyhow!("Redis URL is required"))?;
+        let client = redis::Client::open(url).context("Failed to create Redis client")?;
+        let connection = ConnectionManager::new(client)
+            .await
+            .context("Failed to connect to Redis")?;
+
+        Ok(RedisMemoryStore {
+            connection,
+            namespace: self.namespace.unwrap_or_else(|| "agents".to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[ignore] // Requires Redis instance running
+    async fn test_redis_memory_store() {
+        let store = RedisMemoryStore::builder()
+            .url("redis://127.0.0.1:6379")
+            .namespace("test-memory")
+            .build()
+            .await
+            .expect("Failed to connect to Redis");
+        let user = "ana".to_string();
+
+        store
+            .put(
+                &user,
+                &[
+                    MemoryEntry::new("vehicle", "Civic").with_thread("t1"),
+                    MemoryEntry::new("name", "Ana"),
+                ],
+            )
+            .await
+            .unwrap();
+        let memories = store.list(&user).await.unwrap();
+        assert_eq!(memories.len(), 2);
+        assert_eq!(memories[0].key, "name");
+
+        assert!(store.delete(&user, "name").await.unwrap());
+        assert_eq!(store.erase_user(&user).await.unwrap(), 1);
+        assert!(store.list(&user).await.unwrap().is_empty());
+    }
+}
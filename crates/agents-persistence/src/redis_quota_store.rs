@@ -0,0 +1,161 @@
+//! Redis-backed quota counters.
+//!
+//! Each counter is a hash `<namespace>:quota:<key>` with `requests` and `tokens`
+//! fields, incremented with `HINCRBY` and expired with `EXPIREAT` at the end of its
+//! window in one transaction, so replicas count into the same quota.
+
+use agents_core::quota::{QuotaStore, QuotaUsage};
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use redis::{aio::ConnectionManager, AsyncCommands};
+
+/// Redis implementation of [`QuotaStore`], shared by all agent replicas.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use agents_persistence::RedisQuotaStore;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let store = RedisQuotaStore::new("redis://127.0.0.1:6379").await?;
+///
+///     // With namespace
+///     let store = RedisQuotaStore::builder()
+///         .url("redis://127.0.0.1:6379")
+///         .namespace("myapp")
+///         .build()
+///         .await?;
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct RedisQuotaStore {
+    connection: ConnectionManager,
+    namespace: String,
+}
+
+impl RedisQuotaStore {
+    /// Create a quota store with the default namespace.
+    pub async fn new(url: &str) -> anyhow::Result<Self> {
+        Self::builder().url(url).build().await
+    }
+
+    /// Create a builder for configuring the Redis quota store.
+    pub fn builder() -> RedisQuotaStoreBuilder {
+        RedisQuotaStoreBuilder::default()
+    }
+
+    fn full_key(&self, key: &str) -> String {
+        format!("{}:quota:{}", self.namespace, key)
+    }
+}
+
+#[async_trait]
+impl QuotaStore for RedisQuotaStore {
+    async fn usage(&self, key: &str) -> anyhow::Result<QuotaUsage> {
+        let mut conn = self.connection.clone();
+        let (requests, tokens): (Option<u64>, Option<u64>) = conn
+            .hget(self.full_key(key), &["requests", "tokens"])
+            .await
+            .context("Failed to read quota usage from Redis")?;
+        Ok(QuotaUsage {
+            requests: requests.unwrap_or(0),
+            tokens: tokens.unwrap_or(0),
+        })
+    }
+
+    async fn record(
+        &self,
+        key: &str,
+        usage: QuotaUsage,
+        expires_at: DateTime<Utc>,
+    ) -> anyhow::Result<QuotaUsage> {
+        let mut conn = self.connection.clone();
+        let key = self.full_key(key);
+        let (requests, tokens): (u64, u64) = redis::pipe()
+            .atomic()
+            .hincr(&key, "requests", usage.requests)
+            .hincr(&key, "tokens", usage.tokens)
+            .expire_at(&key, expires_at.timestamp())
+            .ignore()
+            .query_async(&mut conn)
+            .await
+            .context("Failed to record quota usage in Redis")?;
+        Ok(QuotaUsage { requests, tokens })
+    }
+}
+
+/// Builder for configuring a [`RedisQuotaStore`].
+#[derive(Default)]
+pub struct RedisQuotaStoreBuilder {
+    url: Option<String>,
+    namespace: Option<String>,
+}
+
+impl RedisQuotaStoreBuilder {
+    /// Set the Redis connection URL.
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Set the namespace prefix for all keys (default: "agents").
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Build the quota store and connect to Redis.
+    pub async fn build(self) -> anyhow::Result<RedisQuotaStore> {
+        let url = self
+            .url
+            .ok_or_else(|| anyhow::anyhow!("Redis URL is required"))?;
+        let client = redis::Client::open(url).context("Failed to create Redis client")?;
+        let connection = ConnectionManager::new(client)
+            .await
+            .context("Failed to connect to Redis")?;
+
+        Ok(RedisQuotaStore {
+            connection,
+            namespace: self.namespace.unwrap_or_else(|| "agents".to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[tokio::test]
+    #[ignore] // Requires Redis instance running
+    async fn test_redis_quota_store() {
+        let store = RedisQuotaStore::builder()
+            .url("redis://127.0.0.1:6379")
+            .namespace("test-quota")
+            .build()
+            .await
+            .expect("Failed to connect to Redis");
+
+        let key = format!("acme:daily:{}", uuid::Uuid::new_v4());
+        let run = QuotaUsage {
+            requests: 1,
+            tokens: 250,
+        };
+        let expires_at = Utc::now() + Duration::minutes(1);
+        store.record(&key, run, expires_at).await.unwrap();
+        let total = store.record(&key, run, expires_at).await.unwrap();
+
+        assert_eq!(
+            total,
+            QuotaUsage {
+                requests: 2,
+                tokens: 500
+            }
+        );
+        assert_eq!(store.usage(&key).await.unwrap(), total);
+    }
+}
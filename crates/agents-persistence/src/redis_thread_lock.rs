@@ -0,0 +1,317 @@
+//! Redis-backed per-thread leases using the Redlock algorithm.
+//!
+//! With a single URL this is a plain `SET NX PX` lock. With several independent
+//! Redis nodes (not replicas of each other), a lease is only granted when a majority
+//! of nodes accepted it within the lease's validity, so losing one node does not let
+//! two replicas hold the same thread.
+//!
+//! Each key holds `"<owner>|<token>"`; renewals and releases only touch keys that
+//! still hold the caller's token.
+
+use agents_core::persistence::ThreadId;
+use agents_core::thread_lock::{expires_after, LeaseRequest, ThreadLease, ThreadLock};
+use anyhow::Context;
+use async_trait::async_trait;
+use redis::{aio::ConnectionManager, Script};
+use std::time::{Duration, Instant};
+
+const ACQUIRE_SCRIPT: &str = r#"
+local current = redis.call('GET', KEYS[1])
+if current then
+    local holder = string.match(current, '^(.*)|[^|]*$')
+    if ARGV[3] ~= '1' or holder ~= ARGV[4] then
+        return 0
+    end
+end
+redis.call('SET', KEYS[1], ARGV[1], 'PX', ARGV[2])
+return 1
+"#;
+
+const RENEW_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('PEXPIRE', KEYS[1], ARGV[2])
+end
+return 0
+"#;
+
+const RELEASE_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+"#;
+
+/// Redis (Redlock) implementation of [`ThreadLock`].
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use agents_persistence::RedisThreadLock;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Single Redis instance
+///     let lock = RedisThreadLock::new("redis://127.0.0.1:6379").await?;
+///
+///     // Redlock across independent nodes
+///     let lock = RedisThreadLock::builder()
+///         .url("redis://redis-a:6379")
+///         .url("redis://redis-b:6379")
+///         .url("redis://redis-c:6379")
+///         .namespace("myapp")
+///         .build()
+///         .await?;
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct RedisThreadLock {
+    nodes: Vec<ConnectionManager>,
+    namespace: String,
+    node_timeout: Duration,
+}
+
+impl RedisThreadLock {
+    /// Create a lock backed by a single Redis instance.
+    pub async fn new(url: &str) -> anyhow::Result<Self> {
+        Self::builder().url(url).build().await
+    }
+
+    /// Create a builder for configuring the Redis thread lock.
+    pub fn builder() -> RedisThreadLockBuilder {
+        RedisThreadLockBuilder::default()
+    }
+
+    fn key_for_thread(&self, thread_id: &ThreadId) -> String {
+        format!("{}:lock:{}", self.namespace, thread_id)
+    }
+
+    fn quorum(&self) -> usize {
+        self.nodes.len() / 2 + 1
+    }
+
+    /// Run `script` on every node and count the nodes that returned a non-zero reply.
+    async fn run_on_nodes(&self, script: &Script, key: &str, args: &[String]) -> usize {
+        let mut accepted = 0;
+        for node in &self.nodes {
+            let mut conn = node.clone();
+            let mut invocation = script.key(key);
+            for arg in args {
+                invocation.arg(arg);
+            }
+            let reply =
+                tokio::time::timeout(self.node_timeout, invocation.invoke_async::<i64>(&mut conn))
+                    .await;
+            match reply {
+                Ok(Ok(n)) if n > 0 => accepted += 1,
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => tracing::warn!(key, error = %e, "Redis lock node failed"),
+                Err(_) => tracing::warn!(key, "Redis lock node timed out"),
+            }
+        }
+        accepted
+    }
+
+    /// Remaining validity of a lease after `elapsed` spent acquiring it, allowing
+    /// for clock drift between nodes.
+    fn validity(duration: Duration, elapsed: Duration) -> Option<Duration> {
+        let drift = duration / 100 + Duration::from_millis(2);
+        duration
+            .checked_sub(elapsed)
+            .and_then(|left| left.checked_sub(drift))
+            .filter(|left| !left.is_zero())
+    }
+
+    fn value(lease_owner: &str, token: &str) -> String {
+        format!("{lease_owner}|{token}")
+    }
+}
+
+#[async_trait]
+impl ThreadLock for RedisThreadLock {
+    async fn try_acquire(&self, request: &LeaseRequest) -> anyhow::Result<Option<ThreadLease>> {
+        let key = self.key_for_thread(&request.thread_id);
+        let token = uuid::Uuid::new_v4().simple().to_string();
+        let value = Self::value(&request.owner, &token);
+        let started = Instant::now();
+
+        let accepted = self
+            .run_on_nodes(
+                &Script::new(ACQUIRE_SCRIPT),
+                &key,
+                &[
+                    value.clone(),
+                    request.duration.as_millis().to_string(),
+                    if request.reclaim_own { "1" } else { "0" }.to_string(),
+                    request.owner.clone(),
+                ],
+            )
+            .await;
+
+        match Self::validity(request.duration, started.elapsed()) {
+            Some(validity) if accepted >= self.quorum() => {
+                tracing::debug!(
+                    thread_id = %request.thread_id,
+                    owner = %request.owner,
+                    nodes = accepted,
+                    "Acquired thread lease in Redis"
+                );
+                Ok(Some(ThreadLease {
+                    expires_at: expires_after(validity),
+                    ..ThreadLease::new(request, token)
+                }))
+            }
+            _ => {
+                // Undo partial acquisitions so the thread frees up right away
+                if accepted > 0 {
+                    self.run_on_nodes(&Script::new(RELEASE_SCRIPT), &key, &[value])
+                        .await;
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    async fn renew(
+        &self,
+        lease: &ThreadLease,
+        duration: Duration,
+    ) -> anyhow::Result<Option<ThreadLease>> {
+        let key = self.key_for_thread(&lease.thread_id);
+        let started = Instant::now();
+        let accepted = self
+            .run_on_nodes(
+                &Script::new(RENEW_SCRIPT),
+                &key,
+                &[
+                    Self::value(&lease.owner, &lease.token),
+                    duration.as_millis().to_string(),
+                ],
+            )
+            .await;
+
+        match Self::validity(duration, started.elapsed()) {
+            Some(validity) if accepted >= self.quorum() => Ok(Some(ThreadLease {
+                expires_at: expires_after(validity),
+                ..lease.clone()
+            })),
+            _ => Ok(None),
+        }
+    }
+
+    async fn release(&self, lease: &ThreadLease) -> anyhow::Result<()> {
+        let key = self.key_for_thread(&lease.thread_id);
+        self.run_on_nodes(
+            &Script::new(RELEASE_SCRIPT),
+            &key,
+            &[Self::value(&lease.owner, &lease.token)],
+        )
+        .await;
+        Ok(())
+    }
+}
+
+/// Builder for configuring a Redis thread lock.
+#[derive(Default)]
+pub struct RedisThreadLockBuilder {
+    urls: Vec<String>,
+    namespace: Option<String>,
+    node_timeout: Option<Duration>,
+}
+
+impl RedisThreadLockBuilder {
+    /// Add a Redis node. Add three or five independent nodes for Redlock.
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.urls.push(url.into());
+        self
+    }
+
+    /// Set the namespace for lock keys (default: "agents").
+    ///
+    /// Use the same namespace as the checkpointer to keep a deployment's keys together.
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Time allowed for each node to answer (default: 50ms).
+    ///
+    /// Keeps an unreachable node from eating into the lease's validity.
+    pub fn node_timeout(mut self, timeout: Duration) -> Self {
+        self.node_timeout = Some(timeout);
+        self
+    }
+
+    /// Build the Redis thread lock.
+    pub async fn build(self) -> anyhow::Result<RedisThreadLock> {
+        if self.urls.is_empty() {
+            anyhow::bail!("At least one Redis URL is required");
+        }
+
+        let mut nodes = Vec::with_capacity(self.urls.len());
+        for url in &self.urls {
+            let client =
+                redis::Client::open(url.as_str()).context("Failed to create Redis client")?;
+            let connection = ConnectionManager::new(client)
+                .await
+                .with_context(|| format!("Failed to establish Redis connection to {url}"))?;
+            nodes.push(connection);
+        }
+
+        Ok(RedisThreadLock {
+            nodes,
+            namespace: self.namespace.unwrap_or_else(|| "agents".to_string()),
+            node_timeout: self.node_timeout.unwrap_or(Duration::from_millis(50)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(owner: &str) -> LeaseRequest {
+        LeaseRequest {
+            thread_id: "lock-test-thread".to_string(),
+            owner: owner.to_string(),
+            duration: Duration::from_secs(10),
+            reclaim_own: false,
+        }
+    }
+
+    #[test]
+    fn validity_accounts_for_elapsed_time_and_drift() {
+        let lease = Duration::from_secs(10);
+        assert_eq!(
+            RedisThreadLock::validity(lease, Duration::from_secs(1)),
+            Some(Duration::from_millis(8_898))
+        );
+        assert_eq!(RedisThreadLock::validity(lease, lease), None);
+    }
+
+    #[tokio::test]
+    #[ignore] // Requires Redis instance running
+    async fn test_redis_lease_is_exclusive() {
+        let lock = RedisThreadLock::new("redis://127.0.0.1:6379")
+            .await
+            .expect("Failed to connect to Redis");
+
+        let lease = lock.try_acquire(&request("a")).await.unwrap().unwrap();
+        assert!(lock.try_acquire(&request("b")).await.unwrap().is_none());
+        assert!(lock
+            .renew(&lease, Duration::from_secs(10))
+            .await
+            .unwrap()
+            .is_some());
+
+        lock.release(&lease).await.unwrap();
+        let next = lock.try_acquire(&request("b")).await.unwrap().unwrap();
+        assert!(lock
+            .renew(&lease, Duration::from_secs(10))
+            .await
+            .unwrap()
+            .is_none());
+        lock.release(&next).await.unwrap();
+    }
+}
@@ -0,0 +1,188 @@
+//! Redis-backed tool result cache.
+//!
+//! Entries are stored as `SET <namespace>:<key> <value> PX <ttl>`, so Redis expires
+//! them on its own. Invalidation scans for the prefix and deletes the matches.
+
+use agents_core::tool_cache::ToolResultCache;
+use anyhow::Context;
+use async_trait::async_trait;
+use redis::{aio::ConnectionManager, AsyncCommands};
+use std::time::Duration;
+
+/// Redis implementation of [`ToolResultCache`], shared by all agent replicas.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use agents_persistence::RedisToolResultCache;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let cache = RedisToolResultCache::new("redis://127.0.0.1:6379").await?;
+///
+///     // With namespace
+///     let cache = RedisToolResultCache::builder()
+///         .url("redis://127.0.0.1:6379")
+///         .namespace("myapp")
+///         .build()
+///         .await?;
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct RedisToolResultCache {
+    connection: ConnectionManager,
+    namespace: String,
+}
+
+impl RedisToolResultCache {
+    /// Create a cache with the default namespace.
+    pub async fn new(url: &str) -> anyhow::Result<Self> {
+        Self::builder().url(url).build().await
+    }
+
+    /// Create a builder for configuring the Redis tool result cache.
+    pub fn builder() -> RedisToolResultCacheBuilder {
+        RedisToolResultCacheBuilder::default()
+    }
+
+    fn full_key(&self, key: &str) -> String {
+        format!("{}:tool-cache:{}", self.namespace, key)
+    }
+}
+
+#[async_trait]
+impl ToolResultCache for RedisToolResultCache {
+    async fn get(&self, key: &str) -> anyhow::Result<Option<String>> {
+        let mut conn = self.connection.clone();
+        conn.get(self.full_key(key))
+            .await
+            .context("Failed to read cached tool result from Redis")
+    }
+
+    async fn put(&self, key: &str, value: &str, ttl: Option<Duration>) -> anyhow::Result<()> {
+        let mut conn = self.connection.clone();
+        let key = self.full_key(key);
+        match ttl {
+            Some(ttl) => {
+                conn.pset_ex::<_, _, ()>(&key, value, ttl.as_millis().max(1) as u64)
+                    .await
+            }
+            None => conn.set::<_, _, ()>(&key, value).await,
+        }
+        .context("Failed to cache tool result in Redis")
+    }
+
+    async fn invalidate(&self, prefix: &str) -> anyhow::Result<()> {
+        let mut conn = self.connection.clone();
+        let pattern = format!("{}*", escape_glob(&self.full_key(prefix)));
+        let keys: Vec<String> = {
+            let mut scan = conn
+                .scan_match::<_, String>(&pattern)
+                .await
+                .context("Failed to scan cached tool results in Redis")?;
+            let mut keys = Vec::new();
+            while let Some(key) = scan.next_item().await {
+                keys.push(key);
+            }
+            keys
+        };
+        for chunk in keys.chunks(500) {
+            conn.del::<_, ()>(chunk)
+                .await
+                .context("Failed to delete cached tool results from Redis")?;
+        }
+        tracing::debug!(
+            prefix,
+            deleted = keys.len(),
+            "Invalidated cached tool results"
+        );
+        Ok(())
+    }
+}
+
+/// Escape the characters Redis treats specially in `SCAN MATCH` patterns.
+fn escape_glob(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Builder for configuring a [`RedisToolResultCache`].
+#[derive(Default)]
+pub struct RedisToolResultCacheBuilder {
+    url: Option<String>,
+    namespace: Option<String>,
+}
+
+impl RedisToolResultCacheBuilder {
+    /// Set the Redis connection URL.
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Set the namespace prefix for all keys (default: "agents").
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Build the cache and connect to Redis.
+    pub async fn build(self) -> anyhow::Result<RedisToolResultCache> {
+        let url = self
+            .url
+            .ok_or_else(|| anyhow::anyhow!("Redis URL is required"))?;
+        let client = redis::Client::open(url).context("Failed to create Redis client")?;
+        let connection = ConnectionManager::new(client)
+            .await
+            .context("Failed to connect to Redis")?;
+
+        Ok(RedisToolResultCache {
+            connection,
+            namespace: self.namespace.unwrap_or_else(|| "agents".to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_glob() {
+        assert_eq!(escape_glob("mcp-cache:a*b?[c]"), r"mcp-cache:a\*b\?\[c\]");
+    }
+
+    #[tokio::test]
+    #[ignore] // Requires Redis instance running
+    async fn test_redis_tool_cache() {
+        let cache = RedisToolResultCache::builder()
+            .url("redis://127.0.0.1:6379")
+            .namespace("test-tool-cache")
+            .build()
+            .await
+            .expect("Failed to connect to Redis");
+
+        cache.put("docs:react:1", "hooks", None).await.unwrap();
+        cache
+            .put("docs:vue:1", "refs", Some(Duration::from_secs(60)))
+            .await
+            .unwrap();
+        assert_eq!(
+            cache.get("docs:react:1").await.unwrap().as_deref(),
+            Some("hooks")
+        );
+
+        cache.invalidate("docs:react:").await.unwrap();
+        assert_eq!(cache.get("docs:react:1").await.unwrap(), None);
+        assert!(cache.get("docs:vue:1").await.unwrap().is_some());
+        cache.invalidate("docs:").await.unwrap();
+    }
+}
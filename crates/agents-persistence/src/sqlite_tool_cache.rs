@@ -0,0 +1,230 @@
+//! SQLite-backed tool result cache.
+//!
+//! A local file keeps cached tool results across sessions without running a
+//! server. The cache automatically creates the following table:
+//!
+//! ```sql
+//! CREATE TABLE IF NOT EXISTS tool_result_cache (
+//!     key TEXT PRIMARY KEY,
+//!     value TEXT NOT NULL,
+//!     expires_at INTEGER -- Unix milliseconds, NULL for no expiry
+//! );
+//! ```
+
+use agents_core::tool_cache::ToolResultCache;
+use anyhow::Context;
+use async_trait::async_trait;
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// SQLite implementation of [`ToolResultCache`].
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use agents_persistence::SqliteToolResultCache;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Created on first use
+///     let cache = SqliteToolResultCache::new("sqlite://tool-cache.db?mode=rwc").await?;
+///
+///     // With custom table
+///     let cache = SqliteToolResultCache::builder()
+///         .url("sqlite://tool-cache.db?mode=rwc")
+///         .table_name("docs_cache")
+///         .build()
+///         .await?;
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct SqliteToolResultCache {
+    pool: SqlitePool,
+    table_name: String,
+}
+
+impl SqliteToolResultCache {
+    /// Open the cache database, creating the table if it doesn't exist.
+    pub async fn new(database_url: &str) -> anyhow::Result<Self> {
+        Self::builder().url(database_url).build().await
+    }
+
+    /// Create a builder for configuring the SQLite tool result cache.
+    pub fn builder() -> SqliteToolResultCacheBuilder {
+        SqliteToolResultCacheBuilder::default()
+    }
+
+    async fn ensure_table(&self) -> anyhow::Result<()> {
+        let create_table_sql = format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {} (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                expires_at INTEGER
+            )
+            "#,
+            self.table_name
+        );
+        sqlx::query(&create_table_sql)
+            .execute(&self.pool)
+            .await
+            .context("Failed to create tool result cache table")?;
+        Ok(())
+    }
+
+    /// Delete expired entries. Lookups already skip them; this reclaims the space.
+    pub async fn purge_expired(&self) -> anyhow::Result<u64> {
+        let sql = format!(
+            "DELETE FROM {} WHERE expires_at IS NOT NULL AND expires_at <= ?",
+            self.table_name
+        );
+        let result = sqlx::query(&sql)
+            .bind(now_millis())
+            .execute(&self.pool)
+            .await
+            .context("Failed to purge expired tool results")?;
+        Ok(result.rows_affected())
+    }
+}
+
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as i64)
+}
+
+#[async_trait]
+impl ToolResultCache for SqliteToolResultCache {
+    async fn get(&self, key: &str) -> anyhow::Result<Option<String>> {
+        let sql = format!(
+            "SELECT value FROM {} WHERE key = ? AND (expires_at IS NULL OR expires_at > ?)",
+            self.table_name
+        );
+        sqlx::query_scalar(&sql)
+            .bind(key)
+            .bind(now_millis())
+            .fetch_optional(&self.pool)
+            .await
+            .context("Failed to read cached tool result from SQLite")
+    }
+
+    async fn put(&self, key: &str, value: &str, ttl: Option<Duration>) -> anyhow::Result<()> {
+        let sql = format!(
+            "INSERT OR REPLACE INTO {} (key, value, expires_at) VALUES (?, ?, ?)",
+            self.table_name
+        );
+        sqlx::query(&sql)
+            .bind(key)
+            .bind(value)
+            .bind(ttl.map(|ttl| now_millis() + ttl.as_millis() as i64))
+            .execute(&self.pool)
+            .await
+            .context("Failed to cache tool result in SQLite")?;
+        Ok(())
+    }
+
+    async fn invalidate(&self, prefix: &str) -> anyhow::Result<()> {
+        // substr instead of LIKE, so `_` and `%` in keys match literally
+        let sql = format!(
+            "DELETE FROM {} WHERE substr(key, 1, length(?1)) = ?1",
+            self.table_name
+        );
+        let result = sqlx::query(&sql)
+            .bind(prefix)
+            .execute(&self.pool)
+            .await
+            .context("Failed to delete cached tool results from SQLite")?;
+        tracing::debug!(
+            prefix,
+            deleted = result.rows_affected(),
+            "Invalidated cached tool results"
+        );
+        Ok(())
+    }
+}
+
+/// Builder for configuring a [`SqliteToolResultCache`].
+#[derive(Default)]
+pub struct SqliteToolResultCacheBuilder {
+    url: Option<String>,
+    table_name: Option<String>,
+    max_connections: Option<u32>,
+}
+
+impl SqliteToolResultCacheBuilder {
+    /// Set the SQLite connection URL (add `?mode=rwc` to create the file).
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Set the table name (default: "tool_result_cache").
+    pub fn table_name(mut self, table_name: impl Into<String>) -> Self {
+        self.table_name = Some(table_name.into());
+        self
+    }
+
+    /// Set the maximum number of connections in the pool (default: 5). In-memory
+    /// databases need 1, as each connection opens its own.
+    pub fn max_connections(mut self, max: u32) -> Self {
+        self.max_connections = Some(max);
+        self
+    }
+
+    /// Build the cache and initialize the table.
+    pub async fn build(self) -> anyhow::Result<SqliteToolResultCache> {
+        let url = self
+            .url
+            .ok_or_else(|| anyhow::anyhow!("SQLite URL is required"))?;
+        let pool = SqlitePoolOptions::new()
+            .max_connections(self.max_connections.unwrap_or(5))
+            .connect(&url)
+            .await
+            .context("Failed to open SQLite database")?;
+
+        let cache = SqliteToolResultCache {
+            pool,
+            table_name: self
+                .table_name
+                .unwrap_or_else(|| "tool_result_cache".to_string()),
+        };
+        cache.ensure_table().await?;
+        Ok(cache)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sqlite_tool_cache() {
+        let cache = SqliteToolResultCache::builder()
+            .url("sqlite::memory:")
+            .max_connections(1)
+            .build()
+            .await
+            .unwrap();
+
+        cache.put("docs:react_1", "hooks", None).await.unwrap();
+        cache.put("docs:reacts", "other", None).await.unwrap();
+        cache
+            .put("docs:vue:1", "refs", Some(Duration::ZERO))
+            .await
+            .unwrap();
+        cache.put("docs:react_1", "effects", None).await.unwrap();
+
+        assert_eq!(
+            cache.get("docs:react_1").await.unwrap().as_deref(),
+            Some("effects")
+        );
+        assert_eq!(cache.get("docs:vue:1").await.unwrap(), None);
+        assert_eq!(cache.purge_expired().await.unwrap(), 1);
+
+        cache.invalidate("docs:react_").await.unwrap();
+        assert_eq!(cache.get("docs:react_1").await.unwrap(), None);
+        assert!(cache.get("docs:reacts").await.unwrap().is_some());
+    }
+}
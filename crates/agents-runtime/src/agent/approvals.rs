@@ -0,0 +1,134 @@
+//! Recording HITL interrupts as pending approvals for out-of-process review
+//!
+//! With an [`ApprovalStore`] configured, `DeepAgent::handle_message_for_thread`
+//! records every interrupt the run leaves pending as a [`PendingApproval`], assigned
+//! to the approver configured for the tool. Reviewers decide through the store, e.g.
+//! from an admin service; `DeepAgent::resume_thread` then applies the decision to the
+//! thread, or rejects the tool call once the approval expired.
+
+use agents_core::approval_store::{ApprovalStore, PendingApproval};
+use agents_core::hitl::HitlInterrupt;
+use agents_core::persistence::ThreadId;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Default time reviewers have to decide.
+pub const DEFAULT_APPROVAL_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Where and for whom pending approvals are recorded.
+///
+/// # Example
+///
+/// ```ignore
+/// let store = Arc::new(DynamoDbApprovalStore::new("agent-approvals").await?);
+/// let agent = ConfigurableAgentBuilder::new("You are a billing assistant")
+///     .with_model(model)
+///     .with_checkpointer(checkpointer)
+///     .with_tool_interrupt("issue_refund", HitlPolicy { allow_auto: false, note: None })
+///     .with_approval_config(
+///         ApprovalConfig::new(store)
+///             .with_ttl(Duration::from_secs(4 * 60 * 60))
+///             .with_approver("issue_refund", "finance"),
+///     )
+///     .build()?;
+/// ```
+#[derive(Clone)]
+pub struct ApprovalConfig {
+    pub store: Arc<dyn ApprovalStore>,
+    pub ttl: Duration,
+    /// Approver per tool name
+    pub approvers: HashMap<String, String>,
+    /// Approver of tools without their own
+    pub default_approver: Option<String>,
+}
+
+impl std::fmt::Debug for ApprovalConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApprovalConfig")
+            .field("ttl", &self.ttl)
+            .field("approvers", &self.approvers)
+            .field("default_approver", &self.default_approver)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ApprovalConfig {
+    pub fn new(store: Arc<dyn ApprovalStore>) -> Self {
+        Self {
+            store,
+            ttl: DEFAULT_APPROVAL_TTL,
+            approvers: HashMap::new(),
+            default_approver: None,
+        }
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Assign approvals of `tool_name` to `approver`.
+    pub fn with_approver(
+        mut self,
+        tool_name: impl Into<String>,
+        approver: impl Into<String>,
+    ) -> Self {
+        self.approvers.insert(tool_name.into(), approver.into());
+        self
+    }
+
+    pub fn with_default_approver(mut self, approver: impl Into<String>) -> Self {
+        self.default_approver = Some(approver.into());
+        self
+    }
+
+    pub(crate) fn pending(
+        &self,
+        thread_id: &ThreadId,
+        interrupt: &HitlInterrupt,
+    ) -> PendingApproval {
+        let approval = PendingApproval::new(thread_id.clone(), interrupt.clone(), self.ttl);
+        match self
+            .approvers
+            .get(&interrupt.tool_name)
+            .or(self.default_approver.as_ref())
+        {
+            Some(approver) => approval.with_approver(approver.clone()),
+            None => approval,
+        }
+    }
+
+    /// Whether an interrupt's approval would have expired by now.
+    pub(crate) fn has_expired(&self, interrupt: &HitlInterrupt) -> bool {
+        chrono::Duration::from_std(self.ttl)
+            .ok()
+            .and_then(|ttl| interrupt.created_at.checked_add_signed(ttl))
+            .is_some_and(|expires_at| expires_at <= chrono::Utc::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::approval_store::InMemoryApprovalStore;
+    use serde_json::json;
+
+    #[test]
+    fn approvals_are_routed_by_tool() {
+        let config = ApprovalConfig::new(Arc::new(InMemoryApprovalStore::new()))
+            .with_approver("issue_refund", "finance")
+            .with_default_approver("ops");
+        let thread = "thread-1".to_string();
+
+        let refund = HitlInterrupt::new("issue_refund", json!({}), "call-1", None);
+        assert_eq!(
+            config.pending(&thread, &refund).approver.as_deref(),
+            Some("finance")
+        );
+        let restart = HitlInterrupt::new("restart_service", json!({}), "call-2", None);
+        let pending = config.pending(&thread, &restart);
+        assert_eq!(pending.approver.as_deref(), Some("ops"));
+        assert!(pending.is_pending());
+    }
+}
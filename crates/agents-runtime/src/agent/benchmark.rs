@@ -0,0 +1,611 @@
+//! Benchmark suites for comparing agent builds
+//!
+//! A [`BenchmarkSuite`] is a list of tasks, typically loaded from a JSONL file such
+//! as a tool-use QA set: each task has an input, the answers that count as correct
+//! and optionally the tools the agent is expected to call. A [`BenchmarkRunner`]
+//! runs every task on a fresh thread and records whether it succeeded, what it
+//! cost, how long it took and how many planner iterations it needed. The resulting
+//! [`BenchmarkReport`] is JSON for keeping next to a release and Markdown for
+//! reading, and [`BenchmarkReport::compare`] lines up two reports, e.g. before and
+//! after an SDK upgrade.
+
+use super::report::{format_duration, ToolCallStatus};
+use super::run_trace::TraceStep;
+use super::DeepAgent;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// How a response is matched against a task's expected answers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnswerMatch {
+    /// The response contains an expected answer, ignoring case
+    #[default]
+    Contains,
+    /// The trimmed response equals an expected answer, ignoring case
+    Exact,
+}
+
+/// One task of a suite, one line of a JSONL file:
+///
+/// ```json
+/// {"id": "weather-1", "input": "Is it raining in Paris?", "expected": ["yes", "no"], "expected_tools": ["get_weather"]}
+/// ```
+///
+/// `expected` may be a single string. A task without expected answers or tools
+/// succeeds when the run completes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkTask {
+    pub id: String,
+    pub input: String,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub expected: Vec<String>,
+    /// Tools that must each be called successfully at least once
+    #[serde(default)]
+    pub expected_tools: Vec<String>,
+    #[serde(default, rename = "match")]
+    pub answer_match: AnswerMatch,
+}
+
+impl BenchmarkTask {
+    pub fn new(id: impl Into<String>, input: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            input: input.into(),
+            expected: Vec::new(),
+            expected_tools: Vec::new(),
+            answer_match: AnswerMatch::default(),
+        }
+    }
+
+    pub fn with_expected(mut self, answer: impl Into<String>) -> Self {
+        self.expected.push(answer.into());
+        self
+    }
+
+    pub fn with_expected_tool(mut self, tool: impl Into<String>) -> Self {
+        self.expected_tools.push(tool.into());
+        self
+    }
+
+    pub fn with_answer_match(mut self, answer_match: AnswerMatch) -> Self {
+        self.answer_match = answer_match;
+        self
+    }
+
+    /// Whether `response` is one of the expected answers.
+    pub fn accepts(&self, response: &str) -> bool {
+        if self.expected.is_empty() {
+            return true;
+        }
+        let response = response.trim();
+        self.expected
+            .iter()
+            .any(|expected| match self.answer_match {
+                AnswerMatch::Contains => response
+                    .to_lowercase()
+                    .contains(&expected.trim().to_lowercase()),
+                AnswerMatch::Exact => response.eq_ignore_ascii_case(expected.trim()),
+            })
+    }
+}
+
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(answer) => vec![answer],
+        OneOrMany::Many(answers) => answers,
+    })
+}
+
+/// Named list of benchmark tasks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkSuite {
+    pub name: String,
+    pub tasks: Vec<BenchmarkTask>,
+}
+
+impl BenchmarkSuite {
+    pub fn new(name: impl Into<String>, tasks: Vec<BenchmarkTask>) -> Self {
+        Self {
+            name: name.into(),
+            tasks,
+        }
+    }
+
+    /// Parse one task per line; blank lines and lines starting with `//` are skipped.
+    pub fn from_jsonl(name: impl Into<String>, jsonl: &str) -> anyhow::Result<Self> {
+        let tasks = jsonl
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with("//"))
+            .map(|(number, line)| {
+                serde_json::from_str(line).map_err(|e| {
+                    anyhow::anyhow!("Invalid benchmark task on line {}: {}", number + 1, e)
+                })
+            })
+            .collect::<anyhow::Result<Vec<BenchmarkTask>>>()?;
+        Ok(Self::new(name, tasks))
+    }
+
+    /// Load a JSONL file, naming the suite after the file stem.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let jsonl = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "benchmark".to_string());
+        Self::from_jsonl(name, &jsonl)
+    }
+}
+
+/// Outcome of one task.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TaskResult {
+    pub task_id: String,
+    pub success: bool,
+    pub latency_ms: u64,
+    /// Planner iterations of the run
+    pub iterations: usize,
+    pub tool_calls: usize,
+    pub total_tokens: u64,
+    pub cost: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+    /// Why the task failed: the run's error, a timeout or a missing tool call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>,
+}
+
+/// Aggregates over the tasks of a report.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkSummary {
+    pub tasks: usize,
+    pub passed: usize,
+    /// Passed tasks over all tasks, from 0.0 to 1.0
+    pub success_rate: f64,
+    pub total_cost: f64,
+    pub mean_cost: f64,
+    pub total_tokens: u64,
+    pub p50_latency_ms: u64,
+    pub p95_latency_ms: u64,
+    pub mean_iterations: f64,
+}
+
+impl BenchmarkSummary {
+    fn from_results(results: &[TaskResult]) -> Self {
+        let tasks = results.len();
+        if tasks == 0 {
+            return Self::default();
+        }
+        let passed = results.iter().filter(|result| result.success).count();
+        let total_cost: f64 = results.iter().map(|result| result.cost).sum();
+        let mut latencies: Vec<u64> = results.iter().map(|result| result.latency_ms).collect();
+        latencies.sort_unstable();
+        let percentile = |p: f64| latencies[((latencies.len() - 1) as f64 * p).round() as usize];
+        Self {
+            tasks,
+            passed,
+            success_rate: passed as f64 / tasks as f64,
+            total_cost,
+            mean_cost: total_cost / tasks as f64,
+            total_tokens: results.iter().map(|result| result.total_tokens).sum(),
+            p50_latency_ms: percentile(0.5),
+            p95_latency_ms: percentile(0.95),
+            mean_iterations: results
+                .iter()
+                .map(|result| result.iterations as f64)
+                .sum::<f64>()
+                / tasks as f64,
+        }
+    }
+}
+
+/// Results of a suite run, serializable to compare across builds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    pub suite: String,
+    /// Free-form label of the build, e.g. `sdk-0.0.30 gpt-4o-mini`
+    pub label: String,
+    /// Version of the SDK the suite ran on
+    pub sdk_version: String,
+    pub started_at: String,
+    pub summary: BenchmarkSummary,
+    pub results: Vec<TaskResult>,
+}
+
+impl BenchmarkReport {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+
+    /// Read a report saved with [`to_json`](Self::to_json).
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    /// Render the summary and a row per task as Markdown.
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::new();
+        let _ = writeln!(md, "# Benchmark: {}\n", self.suite);
+        if !self.label.is_empty() {
+            let _ = writeln!(md, "- **Label:** {}", self.label);
+        }
+        let _ = writeln!(md, "- **SDK:** {}", self.sdk_version);
+        let _ = writeln!(md, "- **Started:** {}", self.started_at);
+
+        let summary = &self.summary;
+        let _ = writeln!(md, "\n## Summary\n");
+        let _ = writeln!(md, "| Metric | Value |\n|--------|-------|");
+        let _ = writeln!(
+            md,
+            "| Success rate | {:.1}% ({}/{}) |",
+            summary.success_rate * 100.0,
+            summary.passed,
+            summary.tasks
+        );
+        let _ = writeln!(
+            md,
+            "| Cost | ${:.4} (${:.4} per task) |",
+            summary.total_cost, summary.mean_cost
+        );
+        let _ = writeln!(md, "| Tokens | {} |", summary.total_tokens);
+        let _ = writeln!(
+            md,
+            "| Latency | p50 {} / p95 {} |",
+            format_duration(summary.p50_latency_ms),
+            format_duration(summary.p95_latency_ms)
+        );
+        let _ = writeln!(
+            md,
+            "| Iterations | {:.1} per task |",
+            summary.mean_iterations
+        );
+
+        let _ = writeln!(md, "\n## Tasks\n");
+        let _ = writeln!(
+            md,
+            "| Task | Result | Latency | Iterations | Cost |\n|------|--------|---------|------------|------|"
+        );
+        for result in &self.results {
+            let outcome = match (&result.failure, result.success) {
+                (_, true) => "✅ pass".to_string(),
+                (Some(failure), false) => format!("❌ {}", failure.replace('|', "\\|")),
+                (None, false) => "❌ wrong answer".to_string(),
+            };
+            let _ = writeln!(
+                md,
+                "| {} | {} | {} | {} | ${:.4} |",
+                result.task_id,
+                outcome,
+                format_duration(result.latency_ms),
+                result.iterations,
+                result.cost
+            );
+        }
+        md
+    }
+
+    /// Markdown table of this report's summary against `baseline`, with the change
+    /// of each metric, followed by the tasks whose outcome differs.
+    pub fn compare(&self, baseline: &BenchmarkReport) -> String {
+        let (new, old) = (&self.summary, &baseline.summary);
+        let name = |report: &BenchmarkReport| {
+            if report.label.is_empty() {
+                report.sdk_version.clone()
+            } else {
+                report.label.clone()
+            }
+        };
+        let mut md = String::new();
+        let _ = writeln!(md, "# Benchmark: {}\n", self.suite);
+        let _ = writeln!(
+            md,
+            "| Metric | {} | {} | Change |\n|--------|------|------|--------|",
+            name(baseline),
+            name(self)
+        );
+        let _ = writeln!(
+            md,
+            "| Success rate | {:.1}% | {:.1}% | {:+.1} pts |",
+            old.success_rate * 100.0,
+            new.success_rate * 100.0,
+            (new.success_rate - old.success_rate) * 100.0
+        );
+        let _ = writeln!(
+            md,
+            "| Cost per task | ${:.4} | ${:.4} | {} |",
+            old.mean_cost,
+            new.mean_cost,
+            relative_change(old.mean_cost, new.mean_cost)
+        );
+        let _ = writeln!(
+            md,
+            "| p50 latency | {} | {} | {} |",
+            format_duration(old.p50_latency_ms),
+            format_duration(new.p50_latency_ms),
+            relative_change(old.p50_latency_ms as f64, new.p50_latency_ms as f64)
+        );
+        let _ = writeln!(
+            md,
+            "| p95 latency | {} | {} | {} |",
+            format_duration(old.p95_latency_ms),
+            format_duration(new.p95_latency_ms),
+            relative_change(old.p95_latency_ms as f64, new.p95_latency_ms as f64)
+        );
+        let _ = writeln!(
+            md,
+            "| Iterations per task | {:.1} | {:.1} | {} |",
+            old.mean_iterations,
+            new.mean_iterations,
+            relative_change(old.mean_iterations, new.mean_iterations)
+        );
+
+        let changed: Vec<(&TaskResult, &TaskResult)> = self
+            .results
+            .iter()
+            .filter_map(|result| {
+                let before = baseline
+                    .results
+                    .iter()
+                    .find(|before| before.task_id == result.task_id)?;
+                (before.success != result.success).then_some((before, result))
+            })
+            .collect();
+        if !changed.is_empty() {
+            let _ = writeln!(md, "\n## Changed Tasks\n");
+            for (before, after) in changed {
+                let verdict = |result: &TaskResult| if result.success { "pass" } else { "fail" };
+                let _ = writeln!(
+                    md,
+                    "- `{}`: {} → {}",
+                    after.task_id,
+                    verdict(before),
+                    verdict(after)
+                );
+            }
+        }
+        md
+    }
+}
+
+fn relative_change(old: f64, new: f64) -> String {
+    if old == 0.0 {
+        return "-".to_string();
+    }
+    format!("{:+.1}%", (new - old) / old * 100.0)
+}
+
+/// Runs a suite against an agent, one task at a time on a fresh thread.
+///
+/// Cost and token counts come from the run reports, so build the agent with token
+/// tracking for them to be filled in.
+///
+/// # Example
+///
+/// ```ignore
+/// let suite = BenchmarkSuite::load("benchmarks/tool-use-qa.jsonl")?;
+/// let report = BenchmarkRunner::new(suite)
+///     .with_label("sdk-0.0.30 gpt-4o-mini")
+///     .with_timeout(Duration::from_secs(120))
+///     .run(&agent)
+///     .await;
+///
+/// std::fs::write("bench-0.0.30.json", report.to_json().to_string())?;
+/// let baseline = BenchmarkReport::load("bench-0.0.29.json")?;
+/// println!("{}", report.compare(&baseline));
+/// ```
+pub struct BenchmarkRunner {
+    suite: BenchmarkSuite,
+    label: String,
+    timeout: Option<Duration>,
+}
+
+impl BenchmarkRunner {
+    pub fn new(suite: BenchmarkSuite) -> Self {
+        Self {
+            suite,
+            label: String::new(),
+            timeout: None,
+        }
+    }
+
+    /// Label naming the build in reports and comparisons.
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = label.into();
+        self
+    }
+
+    /// Fail tasks that run longer than `timeout` (default: no limit).
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Run every task and report the results. Failed runs count as failed tasks.
+    pub async fn run(&self, agent: &DeepAgent) -> BenchmarkReport {
+        let started_at = chrono::Utc::now().to_rfc3339();
+        let mut results = Vec::with_capacity(self.suite.tasks.len());
+        for task in &self.suite.tasks {
+            let result = self.run_task(agent, task).await;
+            tracing::info!(
+                suite = %self.suite.name,
+                task = %task.id,
+                success = result.success,
+                latency_ms = result.latency_ms,
+                "Benchmark task finished"
+            );
+            results.push(result);
+        }
+        BenchmarkReport {
+            suite: self.suite.name.clone(),
+            label: self.label.clone(),
+            sdk_version: env!("CARGO_PKG_VERSION").to_string(),
+            started_at,
+            summary: BenchmarkSummary::from_results(&results),
+            results,
+        }
+    }
+
+    async fn run_task(&self, agent: &DeepAgent, task: &BenchmarkTask) -> TaskResult {
+        let thread_id = format!("bench-{}-{}", task.id, uuid::Uuid::new_v4());
+        let previous_run = agent.last_run_id();
+        let started = Instant::now();
+        let run = agent.handle_message_for_thread(&thread_id, &task.input);
+        let outcome = match self.timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, run).await {
+                Ok(outcome) => outcome,
+                Err(_) => Err(anyhow::anyhow!(
+                    "timed out after {}",
+                    format_duration(timeout.as_millis() as u64)
+                )),
+            },
+            None => run.await,
+        };
+        let mut result = TaskResult {
+            task_id: task.id.clone(),
+            latency_ms: started.elapsed().as_millis() as u64,
+            ..Default::default()
+        };
+
+        let run_id = agent
+            .last_run_id()
+            .filter(|run_id| Some(run_id) != previous_run.as_ref());
+        let mut called = Vec::new();
+        if let Some(run_id) = run_id {
+            if let Some(report) = agent.generate_report(&run_id) {
+                if let Some(usage) = &report.usage {
+                    result.total_tokens = usage.total_tokens;
+                    result.cost = usage.estimated_cost;
+                }
+                result.tool_calls = report.tool_calls.len();
+                called = report
+                    .tool_calls
+                    .iter()
+                    .filter(|call| call.status == ToolCallStatus::Succeeded)
+                    .map(|call| call.tool_name.clone())
+                    .collect();
+            }
+            if let Some(trace) = agent.run_trace(&run_id) {
+                result.iterations = trace
+                    .steps
+                    .iter()
+                    .filter(|step| matches!(step, TraceStep::Planning { .. }))
+                    .count();
+            }
+        }
+
+        match outcome {
+            Ok(response) => {
+                let response = response.content.to_text();
+                let missing: Vec<&str> = task
+                    .expected_tools
+                    .iter()
+                    .filter(|tool| !called.contains(tool))
+                    .map(String::as_str)
+                    .collect();
+                result.success = missing.is_empty() && task.accepts(&response);
+                if !missing.is_empty() {
+                    result.failure = Some(format!("tools not called: {}", missing.join(", ")));
+                }
+                result.response = Some(response);
+            }
+            Err(e) => result.failure = Some(e.to_string()),
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::ConfigurableAgentBuilder;
+    use agents_core::llm::{LanguageModel, LlmRequest, LlmResponse};
+    use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+    use async_trait::async_trait;
+    use std::sync::Arc;
+
+    struct CapitalModel;
+
+    #[async_trait]
+    impl LanguageModel for CapitalModel {
+        async fn generate(&self, request: LlmRequest) -> anyhow::Result<LlmResponse> {
+            let question = request
+                .messages
+                .last()
+                .map(|message| message.content.to_text())
+                .unwrap_or_default();
+            let answer = if question.contains("France") {
+                "The capital of France is Paris."
+            } else {
+                "I don't know."
+            };
+            Ok(LlmResponse::new(AgentMessage {
+                role: MessageRole::Agent,
+                content: MessageContent::Text(answer.to_string()),
+                metadata: None,
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn runs_suites_and_compares_reports() {
+        let suite = BenchmarkSuite::from_jsonl(
+            "capitals",
+            r#"{"id": "fr", "input": "Capital of France?", "expected": "paris"}
+// comments and blank lines are skipped
+
+{"id": "pe", "input": "Capital of Peru?", "expected": ["Lima"]}
+{"id": "de", "input": "Capital of Germany?", "expected": "Berlin", "expected_tools": ["search"]}"#,
+        )
+        .unwrap();
+        assert_eq!(suite.tasks.len(), 3);
+        assert_eq!(suite.tasks[2].expected_tools, vec!["search".to_string()]);
+
+        let agent = ConfigurableAgentBuilder::new("Answer geography questions")
+            .with_model(Arc::new(CapitalModel))
+            .build()
+            .unwrap();
+        let report = BenchmarkRunner::new(suite)
+            .with_label("candidate")
+            .run(&agent)
+            .await;
+        assert_eq!(report.summary.tasks, 3);
+        assert_eq!(report.summary.passed, 1);
+        assert!(report.results[0].success);
+        assert_eq!(
+            report.results[2].failure.as_deref(),
+            Some("tools not called: search")
+        );
+        assert!(report
+            .to_markdown()
+            .contains("| Success rate | 33.3% (1/3) |"));
+
+        let mut baseline = report.clone();
+        baseline.label = "baseline".to_string();
+        baseline.results[1].success = true;
+        baseline.summary = BenchmarkSummary::from_results(&baseline.results);
+        let comparison = report.compare(&baseline);
+        assert!(comparison.contains("| Success rate | 66.7% | 33.3% | -33.3 pts |"));
+        assert!(comparison.contains("- `pe`: pass → fail"));
+
+        let json = report.to_json().to_string();
+        let parsed: BenchmarkReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, report);
+    }
+}
@@ -6,18 +6,60 @@
 use super::api::{
     create_async_deep_agent_from_config, create_deep_agent_from_config, get_default_model,
 };
+use super::approvals::ApprovalConfig;
+use super::citations::CitationConfig;
+use super::concurrency::ConcurrencyConfig;
 use super::config::{DeepAgentConfig, SubAgentConfig, SummarizationConfig};
-use super::runtime::DeepAgent;
+use super::deterministic::DeterministicConfig;
+use super::experiments::Experiment;
+use super::graph_memory::GraphMemoryConfig;
+use super::memory_policy::MemoryPolicy;
+use super::offload::OffloadConfig;
+use super::output_sink::OutputSinkConfig;
+use super::profile::{Profile, ProfileSettings};
+use super::quotas::QuotaConfig;
+use super::run_options::RunModel;
+use super::runtime::{DeepAgent, BUILTIN_TOOL_NAMES};
+use super::skills::{self, Skill, SkillRequirement};
+use super::state_size::StateSizeLimits;
+use super::stream_coalescing::StreamCoalescing;
+use super::thread_affinity::ThreadLockConfig;
+use super::thread_titles::ThreadTitleConfig;
+use super::tool_arguments::ToolArgumentValidation;
+use super::tool_conflicts::ToolConflictStrategy;
+use super::tool_examples::ToolExamplesConfig;
+use super::tool_output::ToolOutputConfig;
+use super::tool_retry::ToolRetryConfig;
+use super::tool_selection::ToolSelectionConfig;
+use super::translation::TranslationConfig;
+use super::user_memory::UserMemoryConfig;
+use super::validation::{ConfigIssue, ConfigValidationError};
+use crate::locale::{LocaleConfig, PromptPack};
 use crate::middleware::{
+    context_assembly::{ContextAssemblyConfig, ContextAssemblyMiddleware},
+    guardrails::GuardrailMiddleware,
+    prompt_compression::PromptCompressionConfig,
+    response_cache::{ResponseCacheConfig, ResponseCacheMiddleware},
+    time_context::TimeContextConfig,
     token_tracking::{TokenTrackingConfig, TokenTrackingMiddleware},
+    user_profile::UserProfileConfig,
     HitlPolicy,
 };
 use crate::planner::LlmBackedPlanner;
-use crate::prompts::PromptFormat;
-use agents_core::agent::PlannerHandle;
+use crate::prompts::{PromptFormat, PromptSections};
+use agents_core::agent::{AgentCapabilities, AgentDescriptor, PlannerHandle};
+use agents_core::approval_store::ApprovalStore;
+use agents_core::dead_letter::DeadLetterStore;
+use agents_core::guardrail::Guardrail;
 use agents_core::llm::LanguageModel;
+use agents_core::outbox::EventOutbox;
 use agents_core::persistence::Checkpointer;
-use agents_core::tools::ToolBox;
+use agents_core::policy::PolicyProvider;
+use agents_core::search::ConversationIndex;
+use agents_core::sink::OutputSink;
+use agents_core::thread_lock::ThreadLock;
+use agents_core::tools::{ToolBox, ToolExtensions, ToolProvider};
+use agents_toolkit::builtin::time::create_time_tools;
 use std::collections::{HashMap, HashSet};
 use std::num::NonZeroUsize;
 use std::sync::Arc;
@@ -28,9 +70,13 @@ pub struct ConfigurableAgentBuilder {
     instructions: String,
     custom_system_prompt: Option<String>,
     prompt_format: PromptFormat,
+    prompt_sections: Option<PromptSections>,
     planner: Option<Arc<dyn PlannerHandle>>,
     tools: Vec<ToolBox>,
     subagents: Vec<SubAgentConfig>,
+    /// Instructions of the registered skills, by skill name
+    skill_instructions: Vec<(String, String)>,
+    skill_requirements: Vec<(String, SkillRequirement)>,
     summarization: Option<SummarizationConfig>,
     tool_interrupts: HashMap<String, HitlPolicy>,
     builtin_tools: Option<HashSet<String>>,
@@ -41,6 +87,47 @@ pub struct ConfigurableAgentBuilder {
     enable_pii_sanitization: bool,
     token_tracking_config: Option<TokenTrackingConfig>,
     max_iterations: NonZeroUsize,
+    profiling: bool,
+    step_recording: bool,
+    lazy_file_loading: bool,
+    tool_output: Option<ToolOutputConfig>,
+    profile: Profile,
+    profile_settings: HashMap<Profile, ProfileSettings>,
+    tool_retry: Option<ToolRetryConfig>,
+    citations: Option<CitationConfig>,
+    tool_selection: Option<ToolSelectionConfig>,
+    tool_examples: Option<ToolExamplesConfig>,
+    tool_argument_validation: ToolArgumentValidation,
+    tool_conflicts: ToolConflictStrategy,
+    tool_extensions: ToolExtensions,
+    descriptor: AgentDescriptor,
+    locale: Option<LocaleConfig>,
+    time_context: Option<TimeContextConfig>,
+    deterministic: Option<DeterministicConfig>,
+    prompt_compression: Option<PromptCompressionConfig>,
+    user_profile: Option<UserProfileConfig>,
+    user_memory: Option<UserMemoryConfig>,
+    memory_policy: Option<MemoryPolicy>,
+    state_size_limits: Option<StateSizeLimits>,
+    offload: Option<OffloadConfig>,
+    output_sink: Option<OutputSinkConfig>,
+    translation: Option<TranslationConfig>,
+    stream_coalescing: Option<StreamCoalescing>,
+    concurrency: Option<ConcurrencyConfig>,
+    thread_lock: Option<ThreadLockConfig>,
+    experiment: Option<Experiment>,
+    event_outbox: Option<Arc<dyn EventOutbox>>,
+    approvals: Option<ApprovalConfig>,
+    policy_provider: Option<Arc<dyn PolicyProvider>>,
+    dead_letters: Option<Arc<dyn DeadLetterStore>>,
+    conversation_index: Option<Arc<dyn ConversationIndex>>,
+    thread_titles: Option<ThreadTitleConfig>,
+    graph_memory: Option<GraphMemoryConfig>,
+    quotas: Option<QuotaConfig>,
+    response_cache: Option<ResponseCacheConfig>,
+    context_assembly: Option<ContextAssemblyConfig>,
+    guardrails: Vec<Arc<dyn Guardrail>>,
+    tool_providers: Vec<Arc<dyn ToolProvider>>,
 }
 
 impl ConfigurableAgentBuilder {
@@ -49,9 +136,12 @@ impl ConfigurableAgentBuilder {
             instructions: instructions.into(),
             custom_system_prompt: None,
             prompt_format: PromptFormat::default(),
+            prompt_sections: None,
             planner: None,
             tools: Vec::new(),
             subagents: Vec::new(),
+            skill_instructions: Vec::new(),
+            skill_requirements: Vec::new(),
             summarization: None,
             tool_interrupts: HashMap::new(),
             builtin_tools: None,
@@ -62,6 +152,48 @@ impl ConfigurableAgentBuilder {
             enable_pii_sanitization: true, // Enabled by default for security
             token_tracking_config: None,
             max_iterations: NonZeroUsize::new(10).unwrap(),
+            profiling: false,
+            step_recording: false,
+            lazy_file_loading: false,
+            tool_output: None,
+            profile: Profile::default(),
+            profile_settings: HashMap::new(),
+            tool_retry: None,
+            citations: None,
+            tool_selection: None,
+            tool_examples: None,
+            tool_argument_validation: ToolArgumentValidation::default(),
+            tool_conflicts: ToolConflictStrategy::default(),
+            tool_extensions: ToolExtensions::default(),
+            descriptor: AgentDescriptor::new("deep-agent", "0.0.1")
+                .with_description("Rust deep agent"),
+            locale: None,
+            time_context: None,
+            deterministic: None,
+            prompt_compression: None,
+            user_profile: None,
+            user_memory: None,
+            memory_policy: None,
+            state_size_limits: None,
+            offload: None,
+            output_sink: None,
+            translation: None,
+            stream_coalescing: None,
+            concurrency: None,
+            thread_lock: None,
+            experiment: None,
+            event_outbox: None,
+            approvals: None,
+            policy_provider: None,
+            dead_letters: None,
+            conversation_index: None,
+            thread_titles: None,
+            graph_memory: None,
+            quotas: None,
+            response_cache: None,
+            context_assembly: None,
+            guardrails: Vec::new(),
+            tool_providers: Vec::new(),
         }
     }
 
@@ -140,6 +272,35 @@ impl ConfigurableAgentBuilder {
         self
     }
 
+    /// Choose which sections make up the system prompt and in which order.
+    ///
+    /// The built-in prompt consists of the base expectations, your instructions, the
+    /// tool protocol and the planning, filesystem and sub-agent guidance. Sections can
+    /// be reordered, dropped or replaced with your own text; the tools themselves stay
+    /// available.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use agents_runtime::prompts::{PromptSection, PromptSections};
+    ///
+    /// let agent = ConfigurableAgentBuilder::new("You are a support agent")
+    ///     .with_model(model)
+    ///     .with_prompt_sections(
+    ///         PromptSections::new()
+    ///             .without(PromptSection::Filesystem)
+    ///             .replace(PromptSection::Planning, "Plan only for requests with 3+ steps."),
+    ///     )
+    ///     .build()?;
+    /// ```
+    ///
+    /// A prompt set with `with_system_prompt()` or a localized prompt pack takes the
+    /// place of the instructions section and replaces the built-in tool protocol.
+    pub fn with_prompt_sections(mut self, sections: PromptSections) -> Self {
+        self.prompt_sections = Some(sections);
+        self
+    }
+
     /// Add a tool to the agent
     pub fn with_tool(mut self, tool: ToolBox) -> Self {
         self.tools.push(tool);
@@ -163,6 +324,52 @@ impl ConfigurableAgentBuilder {
         self
     }
 
+    /// Register a skill: its tools and sub-agents are added to the agent, its
+    /// instructions appended to the system prompt, and its requirements checked
+    /// when the agent is built.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You are a travel assistant")
+    ///     .with_model(model)
+    ///     .with_skill(WeatherSkill::default())
+    ///     .build()?;
+    /// ```
+    pub fn with_skill(mut self, skill: impl Skill) -> Self {
+        let name = skill.name().to_string();
+        self.tools.extend(skill.tools());
+        self.subagents.extend(skill.subagents());
+        if let Some(instructions) = skill.instructions() {
+            self.skill_instructions.push((name.clone(), instructions));
+        }
+        self.skill_requirements.extend(
+            skill
+                .requirements()
+                .into_iter()
+                .map(|requirement| (name.clone(), requirement)),
+        );
+        self
+    }
+
+    /// Register several skills, such as all the [`BuiltinSkill`]s
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You are a finance assistant")
+    ///     .with_model(model)
+    ///     .with_skills(BuiltinSkill::ALL)
+    ///     .build()?;
+    /// ```
+    ///
+    /// [`BuiltinSkill`]: super::BuiltinSkill
+    pub fn with_skills<I>(self, skills: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Skill,
+    {
+        skills.into_iter().fold(self, Self::with_skill)
+    }
+
     /// Convenience method: automatically create subagents from a list of tools.
     /// Each tool becomes a specialized subagent with that single tool.
     pub fn with_subagent_tools<I>(mut self, tools: I) -> Self
@@ -310,6 +517,63 @@ impl ConfigurableAgentBuilder {
         self
     }
 
+    /// Time the phases of every run: prompt build, serialization, provider calls,
+    /// tool execution and checkpoint writes. The timings are added to the run's
+    /// [`RunReport`](super::RunReport) and emitted as `profile` tracing spans.
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("instructions")
+    ///     .with_model(model)
+    ///     .with_profiling(true)
+    ///     .build()?;
+    ///
+    /// agent.handle_message("Summarize the thread", state).await?;
+    /// let run_id = agent.last_run_id().unwrap();
+    /// println!("{:?}", agent.generate_report(&run_id).unwrap().profile);
+    /// ```
+    pub fn with_profiling(mut self, enabled: bool) -> Self {
+        self.profiling = enabled;
+        self
+    }
+
+    /// Record the prompt, state and decision of every planner iteration, so runs
+    /// can be stepped through with a [`RunDebugger`](super::RunDebugger).
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("instructions")
+    ///     .with_model(model)
+    ///     .with_step_recording(true)
+    ///     .build()?;
+    ///
+    /// agent.handle_message("Find last quarter's revenue", state).await?;
+    /// let recording = agent.run_recording(&agent.last_run_id().unwrap()).unwrap();
+    /// let mut debugger = RunDebugger::new(recording);
+    /// ```
+    pub fn with_step_recording(mut self, enabled: bool) -> Self {
+        self.step_recording = enabled;
+        self
+    }
+
+    /// Load thread state without file contents and read files from the checkpointer
+    /// when a tool needs them, for threads holding large files. Unread files keep
+    /// their saved contents when the state is checkpointed.
+    ///
+    /// Requires a checkpointer that overrides `Checkpointer::load_state_lazy`, such as
+    /// `InMemoryCheckpointer` or `PostgresCheckpointer`; with others the full state is
+    /// loaded as before.
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("instructions")
+    ///     .with_model(model)
+    ///     .with_checkpointer(checkpointer)
+    ///     .with_lazy_file_loading(true)
+    ///     .build()?;
+    /// ```
+    pub fn with_lazy_file_loading(mut self, enabled: bool) -> Self {
+        self.lazy_file_loading = enabled;
+        self
+    }
+
     /// Enable token tracking for monitoring LLM usage and costs.
     ///
     /// This enables tracking of token usage, costs, and performance metrics
@@ -400,123 +664,1343 @@ impl ConfigurableAgentBuilder {
         self
     }
 
-    pub fn build(self) -> anyhow::Result<DeepAgent> {
-        self.finalize(create_deep_agent_from_config)
+    /// Protect the context window from oversized tool outputs.
+    ///
+    /// Tool results longer than their budget are shortened (head/tail truncation or
+    /// LLM summarization) before the planner sees them. The full output is written to
+    /// `tool_outputs/<tool>_<call_id>.txt` in the agent's virtual filesystem so the
+    /// model can read it back with `read_file`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use agents_runtime::agent::ToolOutputConfig;
+    ///
+    /// let agent = ConfigurableAgentBuilder::new("You are a researcher")
+    ///     .with_model(model)
+    ///     .with_tool_output_limits(ToolOutputConfig::new(8_000).with_tool_limit("web_scrape", 4_000))
+    ///     .build()?;
+    /// ```
+    pub fn with_tool_output_limits(mut self, config: ToolOutputConfig) -> Self {
+        self.tool_output = Some(config);
+        self
     }
 
-    /// Build an agent using the async constructor alias. This mirrors the Python
-    /// async_create_deep_agent entry point, while reusing the same runtime internals.
-    pub fn build_async(self) -> anyhow::Result<DeepAgent> {
-        self.finalize(create_async_deep_agent_from_config)
+    /// Build the agent for `profile`, applying its [`ProfileSettings`] on top of the
+    /// rest of the configuration: disabled tools are removed, tools needing approval
+    /// get a [`HitlPolicy`], and `max_iterations` and tool output budgets are capped.
+    /// Without settings from [`with_profile_settings`](Self::with_profile_settings) the
+    /// profile's defaults apply; production turns off payload logging and forces PII
+    /// sanitization. Defaults to [`Profile::Development`], which restricts nothing.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You are a support agent")
+    ///     .with_tools(tools)
+    ///     .with_profile_settings(
+    ///         Profile::Production,
+    ///         ProfileSettings::production().disable_tool("run_sql"),
+    ///     )
+    ///     .with_profile(Profile::from_env()?)
+    ///     .build()?;
+    /// ```
+    pub fn with_profile(mut self, profile: Profile) -> Self {
+        self.profile = profile;
+        self
     }
 
-    fn finalize(self, ctor: fn(DeepAgentConfig) -> DeepAgent) -> anyhow::Result<DeepAgent> {
-        let Self {
-            instructions,
-            custom_system_prompt,
-            prompt_format,
-            planner,
-            tools,
-            subagents,
-            summarization,
-            tool_interrupts,
-            builtin_tools,
-            auto_general_purpose,
-            enable_prompt_caching,
-            checkpointer,
-            event_dispatcher,
-            enable_pii_sanitization,
-            token_tracking_config,
-            max_iterations,
-        } = self;
-
-        let planner = planner.unwrap_or_else(|| {
-            // Use default model if no planner is set
-            let default_model = get_default_model().expect("Failed to get default model");
-            Arc::new(LlmBackedPlanner::new(default_model)) as Arc<dyn PlannerHandle>
-        });
-
-        // Wrap the planner with token tracking if enabled
-        let final_planner = if let Some(token_config) = token_tracking_config {
-            if token_config.enabled {
-                // Extract the underlying model from the planner
-                let planner_any = planner.as_any();
-                if let Some(llm_planner) = planner_any.downcast_ref::<LlmBackedPlanner>() {
-                    let model = llm_planner.model().clone();
-                    let tracked_model = Arc::new(TokenTrackingMiddleware::new(
-                        token_config,
-                        model,
-                        event_dispatcher.clone(),
-                    ));
-                    Arc::new(LlmBackedPlanner::new(tracked_model)) as Arc<dyn PlannerHandle>
-                } else {
-                    planner
-                }
-            } else {
-                planner
-            }
-        } else {
-            planner
-        };
+    /// Settings applied when the agent is built for `profile`, replacing its defaults.
+    pub fn with_profile_settings(mut self, profile: Profile, settings: ProfileSettings) -> Self {
+        self.profile_settings.insert(profile, settings);
+        self
+    }
 
-        let mut cfg = DeepAgentConfig::new(instructions, final_planner)
-            .with_auto_general_purpose(auto_general_purpose)
-            .with_prompt_caching(enable_prompt_caching)
-            .with_pii_sanitization(enable_pii_sanitization)
-            .with_max_iterations(max_iterations.get())
-            .with_prompt_format(prompt_format);
+    /// Retry tool calls that return an error.
+    ///
+    /// Each failed call is attempted again after an exponential backoff, up to the
+    /// tool's `max_attempts`. When all attempts fail, the planner receives the error
+    /// with guidance to try another tool or approach instead of repeating the call.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use agents_runtime::agent::{ToolRetryConfig, ToolRetryPolicy};
+    ///
+    /// let agent = ConfigurableAgentBuilder::new("You are a researcher")
+    ///     .with_model(model)
+    ///     .with_tool_retries(ToolRetryConfig::per_tool_only().with_tool_policy("web_search", ToolRetryPolicy::new(3)))
+    ///     .build()?;
+    /// ```
+    pub fn with_tool_retries(mut self, config: ToolRetryConfig) -> Self {
+        self.tool_retry = Some(config);
+        self
+    }
 
-        // Apply custom system prompt if provided
-        if let Some(prompt) = custom_system_prompt {
-            cfg = cfg.with_system_prompt(prompt);
-        }
+    /// Require final answers to reference the results of the run's tools.
+    ///
+    /// An answer that shares no run of words or URL with a result of the source
+    /// tools is kept in the history and the planner is asked again with corrective
+    /// instructions, up to `max_corrections` times; after that the answer is
+    /// returned. Emits `GuardrailIntervened` events for every answer sent back.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You are a researcher")
+    ///     .with_model(model)
+    ///     .with_tool(web_search)
+    ///     .with_citations(CitationConfig::new().with_source_tool("web_search"))
+    ///     .build()?;
+    /// ```
+    pub fn with_citations(mut self, config: CitationConfig) -> Self {
+        self.citations = Some(config);
+        self
+    }
 
-        if let Some(ckpt) = checkpointer {
-            cfg = cfg.with_checkpointer(ckpt);
-        }
-        if let Some(dispatcher) = event_dispatcher {
-            cfg = cfg.with_event_dispatcher(dispatcher);
-        }
-        if let Some(sum) = summarization {
-            cfg = cfg.with_summarization(sum);
-        }
-        if let Some(selected) = builtin_tools {
-            cfg = cfg.with_builtin_tools(selected);
-        }
-        for (name, policy) in tool_interrupts {
-            cfg = cfg.with_tool_interrupt(name, policy);
-        }
-        for tool in tools {
-            cfg = cfg.with_tool(tool);
-        }
-        for sub_cfg in subagents {
-            cfg = cfg.with_subagent_config(sub_cfg);
-        }
+    /// Shrink the tool section of the prompt for large tool registries.
+    ///
+    /// Each turn, the registered tools are ranked against the recent conversation and
+    /// only the top-K schemas are sent to the model, together with any pinned tools
+    /// (the built-ins are pinned by default). All tools remain executable.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use agents_runtime::agent::ToolSelectionConfig;
+    ///
+    /// let agent = ConfigurableAgentBuilder::new("You are a helpful assistant")
+    ///     .with_model(model)
+    ///     .with_tools(mcp_tools)
+    ///     .with_tool_selection(ToolSelectionConfig::new(10).with_pinned_tool("search_docs"))
+    ///     .build()?;
+    /// ```
+    pub fn with_tool_selection(mut self, config: ToolSelectionConfig) -> Self {
+        self.tool_selection = Some(config);
+        self
+    }
 
-        Ok(ctor(cfg))
+    /// Control the few-shot examples attached to tool schemas.
+    ///
+    /// Examples added with `ToolSchema::with_example` are listed after the system
+    /// prompt for the tools exposed each turn, at most `max_per_tool` per tool and
+    /// in the agent's prompt format unless one is set.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use agents_runtime::agent::ToolExamplesConfig;
+    ///
+    /// let agent = ConfigurableAgentBuilder::new("You are a helpful assistant")
+    ///     .with_model(model)
+    ///     .with_tool_examples(ToolExamplesConfig::default().with_max_per_tool(1))
+    ///     .build()?;
+    /// ```
+    pub fn with_tool_examples(mut self, config: ToolExamplesConfig) -> Self {
+        self.tool_examples = Some(config);
+        self
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Check the arguments of every tool call against the tool's parameter schema.
+    ///
+    /// With [`ToolArgumentValidation::Strict`] a call with missing, mistyped or
+    /// out-of-range arguments is not executed. The model instead receives an
+    /// `invalid_arguments` error listing each problem, so it can retry.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use agents_runtime::agent::ToolArgumentValidation;
+    ///
+    /// let agent = ConfigurableAgentBuilder::new("You are a booking assistant")
+    ///     .with_model(model)
+    ///     .with_tool_argument_validation(ToolArgumentValidation::Strict)
+    ///     .build()?;
+    /// ```
+    pub fn with_tool_argument_validation(mut self, mode: ToolArgumentValidation) -> Self {
+        self.tool_argument_validation = mode;
+        self
+    }
 
-    #[test]
-    fn test_builder_default_max_iterations() {
-        let builder = ConfigurableAgentBuilder::new("test instructions");
-        assert_eq!(builder.max_iterations.get(), 10);
+    /// Decide what happens when a registered tool is named like a built-in tool,
+    /// e.g. an MCP server that also provides `read_file`.
+    ///
+    /// By default `build()` fails and lists each collision. Colliding tools can
+    /// instead be renamed with a prefix, or one side can take priority.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use agents_runtime::agent::ToolConflictStrategy;
+    ///
+    /// let agent = ConfigurableAgentBuilder::new("You are a helpful assistant")
+    ///     .with_model(model)
+    ///     .with_tools(mcp_tools)
+    ///     .with_tool_conflicts(ToolConflictStrategy::prefix("mcp"))
+    ///     .build()?;
+    /// ```
+    pub fn with_tool_conflicts(mut self, strategy: ToolConflictStrategy) -> Self {
+        self.tool_conflicts = strategy;
+        self
     }
 
-    #[test]
-    fn test_builder_custom_max_iterations() {
-        let builder = ConfigurableAgentBuilder::new("test instructions").with_max_iterations(20);
-        assert_eq!(builder.max_iterations.get(), 20);
+    /// Share a dependency (database pool, HTTP client, config) with every tool.
+    ///
+    /// Values are keyed by type; registering a second value of the same type
+    /// replaces the first. Sub-agents inherit the registered state.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You are a support agent")
+    ///     .with_model(model)
+    ///     .with_tool_state(pool.clone())
+    ///     .with_tool(lookup_order)
+    ///     .build()?;
+    ///
+    /// // Inside the tool:
+    /// let pool = ctx.require_extension::<PgPool>()?;
+    /// ```
+    pub fn with_tool_state<T: Send + Sync + 'static>(mut self, value: T) -> Self {
+        self.tool_extensions.insert(value);
+        self
     }
 
-    #[test]
-    #[should_panic(expected = "max_iterations must be greater than 0")]
-    fn test_builder_zero_max_iterations_panics() {
-        let _builder = ConfigurableAgentBuilder::new("test instructions").with_max_iterations(0);
+    /// Name reported by `describe()` (defaults to `deep-agent`).
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.descriptor.name = name.into();
+        self
+    }
+
+    /// Description reported by `describe()`.
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.descriptor.description = Some(description.into());
+        self
+    }
+
+    /// Version reported by `describe()`.
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.descriptor.version = version.into();
+        self
+    }
+
+    /// Declare capabilities the runtime cannot detect, such as vision support or
+    /// the languages the agent answers in.
+    ///
+    /// Tools, sub-agents, streaming and HITL support are filled in automatically, so
+    /// orchestrators can negotiate features from `describe()`:
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You are a support agent")
+    ///     .with_model(model)
+    ///     .with_name("support")
+    ///     .with_capabilities(AgentCapabilities::default().with_vision(true).with_language("en"))
+    ///     .build()?;
+    ///
+    /// let missing = agent.describe().await.capabilities.missing(&["streaming", "vision"]);
+    /// assert!(missing.is_empty());
+    /// ```
+    pub fn with_capabilities(mut self, capabilities: AgentCapabilities) -> Self {
+        self.descriptor.capabilities = capabilities;
+        self
+    }
+
+    /// Answer in the given locale by default (e.g. `"ar-AE"`).
+    ///
+    /// The built-in Deep Agent prompt, the response language and the built-in tool
+    /// descriptions are taken from the matching prompt pack, falling back through
+    /// parent tags (`ar-AE` → `ar`), [`with_locale_fallbacks`](Self::with_locale_fallbacks)
+    /// and finally English. A thread can switch locale by setting
+    /// `AgentStateSnapshot::locale`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("أنت مساعد خدمة عملاء لمركز صيانة السيارات")
+    ///     .with_model(model)
+    ///     .with_locale("ar-AE")
+    ///     .with_locale_fallbacks(["en"])
+    ///     .build()?;
+    /// ```
+    pub fn with_locale(mut self, locale: impl Into<String>) -> Self {
+        let mut config = self.locale.take().unwrap_or_default();
+        config.default_locale = locale.into();
+        self.locale = Some(config);
+        self
+    }
+
+    /// Locales tried after the requested locale and its parent tags.
+    pub fn with_locale_fallbacks<I, S>(mut self, fallbacks: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.locale = Some(
+            self.locale
+                .take()
+                .unwrap_or_default()
+                .with_fallbacks(fallbacks),
+        );
+        self
+    }
+
+    /// Register a prompt pack with localized prompts and tool descriptions.
+    pub fn with_prompt_pack(mut self, pack: PromptPack) -> Self {
+        self.locale = Some(self.locale.take().unwrap_or_default().with_pack(pack));
+        self
+    }
+
+    /// Stamp every model request with the current date and time.
+    ///
+    /// Adds a "Current Date and Time" section to the system prompt on each turn and,
+    /// unless disabled with [`TimeContextConfig::with_tools`], the `get_current_time`
+    /// and `date_math` tools. Sub-agents inherit the setting.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You schedule car service appointments")
+    ///     .with_model(model)
+    ///     .with_time_context(TimeContextConfig::for_timezone("Asia/Dubai")?)
+    ///     .build()?;
+    /// ```
+    pub fn with_time_context(mut self, config: TimeContextConfig) -> Self {
+        self.time_context = Some(config);
+        self
+    }
+
+    /// Make runs reproducible for debugging: a fixed sampling seed where the provider
+    /// supports one, a frozen time in the time context and time tools, and tool ids
+    /// from [`ToolContext::new_uuid`](agents_core::tools::ToolContext::new_uuid)
+    /// drawn from a seeded generator.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You schedule car service appointments")
+    ///     .with_model(model)
+    ///     .with_time_context(TimeContextConfig::for_timezone("Asia/Dubai")?)
+    ///     .with_deterministic_mode(DeterministicConfig::new(42))
+    ///     .build()?;
+    /// ```
+    pub fn with_deterministic_mode(mut self, config: DeterministicConfig) -> Self {
+        self.deterministic = Some(config);
+        self
+    }
+
+    /// Compress older messages before each model call.
+    ///
+    /// Text messages outside the most recent few are shrunk to a target ratio, by
+    /// default with an extractive heuristic that keeps the most informative sentences;
+    /// [`PromptCompressionConfig::with_model`] uses a small model instead. The stored
+    /// history is not modified. Sub-agents inherit the setting.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You are a researcher")
+    ///     .with_model(model)
+    ///     .with_prompt_compression(PromptCompressionConfig::default().with_target_ratio(0.4))
+    ///     .build()?;
+    /// ```
+    pub fn with_prompt_compression(mut self, config: PromptCompressionConfig) -> Self {
+        self.prompt_compression = Some(config);
+        self
+    }
+
+    /// Keep a profile of the user in the thread state.
+    ///
+    /// Before the first model call of each turn, `config.model` reads the new user
+    /// message and extracts the stable facts it states about the user (name,
+    /// preferences, vehicle, account ids, ...), each with a confidence and the
+    /// message it came from. The profile is added to the system prompt and saved
+    /// with the thread, so it survives restarts when a checkpointer is configured.
+    /// Sub-agents don't see it.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You schedule car service appointments")
+    ///     .with_model(model)
+    ///     .with_checkpointer(checkpointer)
+    ///     .with_user_profile(UserProfileConfig::new(small_model))
+    ///     .build()?;
+    /// ```
+    pub fn with_user_profile(mut self, config: UserProfileConfig) -> Self {
+        self.user_profile = Some(config);
+        self
+    }
+
+    /// Carry what is learned about a user into all of their threads.
+    ///
+    /// Runs name their user with [`RunOptions::with_user`](super::RunOptions::with_user).
+    /// At the start of each turn the user's memories are added to the thread's
+    /// [user profile](Self::with_user_profile), newer memories replacing older facts,
+    /// and the facts the turn learned are saved back to `config.store` under the
+    /// user's id. Threads are tagged with the user, so
+    /// [`DeepAgent::erase_user`](super::DeepAgent::erase_user) deletes their
+    /// memories and threads together. Without `with_user_profile`, remembered facts
+    /// are shown to the model but no new ones are extracted.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You schedule car service appointments")
+    ///     .with_model(model)
+    ///     .with_checkpointer(checkpointer)
+    ///     .with_user_profile(UserProfileConfig::new(small_model))
+    ///     .with_user_memory(UserMemoryConfig::new(Arc::new(InMemoryMemoryStore::new())))
+    ///     .build()?;
+    /// ```
+    pub fn with_user_memory(mut self, config: UserMemoryConfig) -> Self {
+        self.user_memory = Some(config);
+        self
+    }
+
+    /// Review every user fact against `policy` before it is remembered.
+    ///
+    /// Facts the [user profile](Self::with_user_profile) extracts and facts added to
+    /// [graph memory](Self::with_graph_memory) are classified first; facts in
+    /// prohibited categories, or in categories the user has not consented to, are
+    /// dropped and reported as `MemoryBlocked` events. Allowed profile facts carry
+    /// their category and an expiry, and at the start of each turn the facts of the
+    /// thread and of [user memory](Self::with_user_memory) that expired or lost
+    /// consent are deleted and reported as `MemoryPurged` events.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let consents = Arc::new(InMemoryConsentStore::new());
+    /// let agent = ConfigurableAgentBuilder::new("You schedule car service appointments")
+    ///     .with_model(model)
+    ///     .with_user_profile(UserProfileConfig::new(small_model))
+    ///     .with_memory_policy(
+    ///         MemoryPolicy::new(Arc::new(KeywordClassifier::default()))
+    ///             .prohibit(["health", "religion", "politics"])
+    ///             .require_consent(["contact"], consents.clone())
+    ///             .retain("contact", Duration::days(90)),
+    ///     )
+    ///     .build()?;
+    /// ```
+    pub fn with_memory_policy(mut self, policy: MemoryPolicy) -> Self {
+        self.memory_policy = Some(policy);
+        self
+    }
+
+    /// Warn when a thread's state grows past `limits`.
+    ///
+    /// Each checkpoint measures the thread's messages, files and tool output
+    /// artifacts. A thread crossing one of the thresholds is logged as a warning and
+    /// reported as a `StateSizeExceeded` event; it is reported again only after
+    /// dropping back under the threshold and crossing it anew. Sizes are available
+    /// from [`DeepAgent::thread_state_size`] and [`DeepAgent::state_metrics`] with
+    /// or without limits.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You are a research assistant")
+    ///     .with_model(model)
+    ///     .with_checkpointer(checkpointer)
+    ///     .with_state_size_limits(
+    ///         StateSizeLimits::new()
+    ///             .with_max_file_bytes(20 * 1024 * 1024)
+    ///             .with_max_artifacts(200),
+    ///     )
+    ///     .build()?;
+    /// ```
+    pub fn with_state_size_limits(mut self, limits: StateSizeLimits) -> Self {
+        self.state_size_limits = Some(limits);
+        self
+    }
+
+    /// Keep tool results and user messages longer than the threshold out of the
+    /// history.
+    ///
+    /// The content goes to the blob store, and the history keeps a summary and an
+    /// `offload://` reference. The `read_offloaded` tool lets the model page
+    /// through it, and a tool argument that is exactly a reference reaches the
+    /// tool as the full content. Offloading happens before
+    /// [tool output budgets](Self::with_tool_output_limits) apply, so offloaded
+    /// results are not also kept in the virtual filesystem.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You are a research assistant")
+    ///     .with_model(model)
+    ///     .with_message_offloading(
+    ///         OffloadConfig::new(Arc::new(FileBlobStore::new("/var/lib/agent/blobs")))
+    ///             .with_threshold_chars(30_000),
+    ///     )
+    ///     .build()?;
+    /// ```
+    pub fn with_message_offloading(mut self, config: OffloadConfig) -> Self {
+        self.tools.extend(config.tools());
+        self.offload = Some(config);
+        self
+    }
+
+    /// Stream final responses longer than 4,000 characters to `sink`.
+    ///
+    /// The chat (and the checkpointed history) receives a short preview and the
+    /// location of the full text instead. Sub-agents are not affected, since their
+    /// answers are consumed by the parent agent.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You write detailed market reports")
+    ///     .with_model(model)
+    ///     .with_output_sink(Arc::new(FileOutputSink::new("./reports")))
+    ///     .build()?;
+    /// ```
+    pub fn with_output_sink(self, sink: Arc<dyn OutputSink>) -> Self {
+        self.with_output_sink_config(OutputSinkConfig::new(sink))
+    }
+
+    /// Configure the output sink with a custom size threshold and preview length.
+    pub fn with_output_sink_config(mut self, config: OutputSinkConfig) -> Self {
+        self.output_sink = Some(config);
+        self
+    }
+
+    /// Run the agent in a working language for users who write in another one.
+    ///
+    /// User messages are translated into the working language (English by default)
+    /// before they enter the history, and the final response is translated back.
+    /// Code blocks, inline code and JSON are kept verbatim. Streamed runs deliver the
+    /// translated response as a single chunk. Sub-agents are not affected.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You are a car service assistant")
+    ///     .with_model(model)
+    ///     .with_translation(TranslationConfig::with_model(small_model))
+    ///     .build()?;
+    /// ```
+    pub fn with_translation(mut self, config: TranslationConfig) -> Self {
+        self.translation = Some(config);
+        self
+    }
+
+    /// Merge the text deltas of `handle_message_stream` before they are returned.
+    ///
+    /// Without coalescing every provider delta becomes its own chunk, which over SSE
+    /// means thousands of tiny events. Deltas are flushed after a delay, once enough
+    /// characters are buffered, or (optionally) at sentence boundaries.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You are a helpful assistant")
+    ///     .with_model(model)
+    ///     .with_stream_coalescing(StreamCoalescing::new().with_sentence_flush(true))
+    ///     .build()?;
+    /// ```
+    pub fn with_stream_coalescing(mut self, coalescing: StreamCoalescing) -> Self {
+        self.stream_coalescing = Some(coalescing);
+        self
+    }
+
+    /// Cap concurrent runs and tool executions to protect downstream APIs.
+    ///
+    /// Runs over the cap wait in a FIFO queue; when the queue is full or the wait
+    /// exceeds the queue timeout, the call fails with `ConcurrencyLimitError`.
+    /// Sub-agents keep their own, unlimited, counters. Current occupancy is
+    /// available from `agent.concurrency_stats()`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You are a support agent")
+    ///     .with_model(model)
+    ///     .with_concurrency_limits(
+    ///         ConcurrencyConfig::new()
+    ///             .with_max_concurrent_runs(8)
+    ///             .with_max_queued_runs(32)
+    ///             .with_queue_timeout(Duration::from_secs(10)),
+    ///     )
+    ///     .build()?;
+    /// ```
+    pub fn with_concurrency_limits(mut self, concurrency: ConcurrencyConfig) -> Self {
+        self.concurrency = Some(concurrency);
+        self
+    }
+
+    /// Hold a per-thread lease while `handle_message_for_thread` runs, so replicas
+    /// sharing a checkpointer never process the same thread concurrently.
+    ///
+    /// Uses the default lease (30s, renewed in the background) and acquire timeout
+    /// (10s); see [`with_thread_lock_config`](Self::with_thread_lock_config) to tune them.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You are a support agent")
+    ///     .with_model(model)
+    ///     .with_checkpointer(checkpointer)
+    ///     .with_thread_lock(Arc::new(RedisThreadLock::new("redis://redis:6379").await?))
+    ///     .build()?;
+    /// ```
+    pub fn with_thread_lock(self, lock: Arc<dyn ThreadLock>) -> Self {
+        self.with_thread_lock_config(ThreadLockConfig::new(lock))
+    }
+
+    /// Per-thread leases with a custom owner, lease length or takeover behaviour.
+    pub fn with_thread_lock_config(mut self, thread_lock: ThreadLockConfig) -> Self {
+        self.thread_lock = Some(thread_lock);
+        self
+    }
+
+    /// Split threads run with `handle_message_for_thread` between the variants of
+    /// `experiment`, recording each run's variant in its events, report and token usage.
+    pub fn with_experiment(mut self, experiment: Experiment) -> Self {
+        self.experiment = Some(experiment);
+        self
+    }
+
+    /// Save events in the same transaction as the state and deliver them through an
+    /// [`OutboxRelay`](agents_core::outbox::OutboxRelay) with at-least-once semantics.
+    ///
+    /// The outbox replaces the checkpointer. While it is configured, the event
+    /// dispatcher holds events back (except streaming tokens) until the relay
+    /// delivers them; run the relay with the same dispatcher.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let store = Arc::new(PostgresCheckpointer::new(database_url).await?);
+    /// let dispatcher = Arc::new(EventDispatcher::new());
+    /// dispatcher.add_broadcaster(Arc::new(webhook));
+    ///
+    /// let agent = ConfigurableAgentBuilder::new("You are a support agent")
+    ///     .with_model(model)
+    ///     .with_event_dispatcher(dispatcher.clone())
+    ///     .with_event_outbox(store.clone())
+    ///     .build()?;
+    /// let relay = OutboxRelay::new(store, dispatcher).spawn();
+    /// ```
+    pub fn with_event_outbox(mut self, outbox: Arc<dyn EventOutbox>) -> Self {
+        self.event_outbox = Some(outbox);
+        self
+    }
+
+    /// Record the interrupts of `handle_message_for_thread` runs in an approval store,
+    /// so reviewers can decide from another process and `DeepAgent::resume_thread`
+    /// continues the thread with their decision.
+    ///
+    /// Approvals expire after 24 hours and are not assigned to an approver; see
+    /// [`with_approval_config`](Self::with_approval_config) to route them.
+    pub fn with_approval_store(self, store: Arc<dyn ApprovalStore>) -> Self {
+        self.with_approval_config(ApprovalConfig::new(store))
+    }
+
+    /// Pending approvals with a custom expiry and approvers per tool.
+    pub fn with_approval_config(mut self, approvals: ApprovalConfig) -> Self {
+        self.approvals = Some(approvals);
+        self
+    }
+
+    /// Decide on tool calls with a policy engine such as Cedar or OPA instead of, or
+    /// on top of, hand-written [`HitlPolicy`]s. Each call is submitted with its
+    /// arguments, the caller's [claims](crate::RunOptions::with_claims) and the
+    /// thread; denied calls are skipped with the reason given to the model, and calls
+    /// needing approval pause the run like a `HitlPolicy`. Calls are denied when the
+    /// evaluation fails.
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You are a billing assistant")
+    ///     .with_policy_provider(Arc::new(CedarPolicyProvider::new(&policies)?))
+    ///     .build()?;
+    /// let options = RunOptions::new().with_claims(verified_jwt.claims);
+    /// agent.handle_message_for_thread_with_options(&thread_id, text, options).await?;
+    /// ```
+    pub fn with_policy_provider(mut self, provider: Arc<dyn PolicyProvider>) -> Self {
+        self.policy_provider = Some(provider);
+        self
+    }
+
+    /// Keep runs that fail as dead letters: the input, the state the run started
+    /// from, the events emitted before the failure and the error. Replay one with
+    /// `DeepAgent::replay_dead_letter` once the cause is fixed.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let dead_letters = Arc::new(InMemoryDeadLetterStore::new());
+    /// let agent = ConfigurableAgentBuilder::new("You are a support agent")
+    ///     .with_model(model)
+    ///     .with_dead_letter_store(dead_letters.clone())
+    ///     .build()?;
+    ///
+    /// for letter in dead_letters.list("deep-agent").await? {
+    ///     println!("{}: {}", letter.id, letter.error);
+    /// }
+    /// ```
+    pub fn with_dead_letter_store(mut self, store: Arc<dyn DeadLetterStore>) -> Self {
+        self.dead_letters = Some(store);
+        self
+    }
+
+    /// Keep a full-text index of conversations. The user input and the reply of
+    /// every thread turn are indexed once the turn is checkpointed, and
+    /// `DeepAgent::search_threads` finds past conversations by content.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You are a support agent")
+    ///     .with_model(model)
+    ///     .with_checkpointer(checkpointer)
+    ///     .with_conversation_index(Arc::new(PostgresConversationIndex::new(url).await?))
+    ///     .build()?;
+    ///
+    /// for hit in agent.search_threads("refund never arrived", 20).await? {
+    ///     println!("{}: {}", hit.thread_id, hit.snippet);
+    /// }
+    /// ```
+    pub fn with_conversation_index(mut self, index: Arc<dyn ConversationIndex>) -> Self {
+        self.conversation_index = Some(index);
+        self
+    }
+
+    /// Have a cheap model write a short title and a one-line summary of each thread
+    /// after its first turns, refreshed periodically. They are stored in the thread
+    /// metadata under `TITLE_KEY` and `SUMMARY_KEY`, so session lists can show them
+    /// instead of thread ids. Requires a checkpointer that stores metadata.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You are a support agent")
+    ///     .with_model(model)
+    ///     .with_checkpointer(checkpointer)
+    ///     .with_thread_titles(ThreadTitleConfig::new(small_model))
+    ///     .build()?;
+    /// ```
+    pub fn with_thread_titles(mut self, config: ThreadTitleConfig) -> Self {
+        self.thread_titles = Some(config);
+        self
+    }
+
+    /// Keep a knowledge graph of the facts stated in conversations. After every
+    /// checkpointed thread turn, a model extracts entities and relations into the
+    /// graph store, which all threads share; the `graph_entities`, `graph_facts` and
+    /// `graph_path` tools let the agent answer multi-hop questions from it. Facts
+    /// learned in a thread are dropped when the thread is deleted.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You are an account manager")
+    ///     .with_model(model)
+    ///     .with_checkpointer(checkpointer)
+    ///     .with_graph_memory(GraphMemoryConfig::new(
+    ///         Arc::new(Neo4jGraphStore::new("127.0.0.1:7687", "neo4j", &password).await?),
+    ///         small_model,
+    ///     ))
+    ///     .build()?;
+    /// ```
+    pub fn with_graph_memory(mut self, config: GraphMemoryConfig) -> Self {
+        self.tools.extend(config.tools());
+        self.graph_memory = Some(config);
+        self
+    }
+
+    /// Enforce daily and monthly quotas per tenant on runs started with
+    /// `RunOptions::with_tenant`. Tenants over quota are rejected with
+    /// `QuotaExceededError` or degraded to a cheaper model; `DeepAgent::remaining_quota`
+    /// reports what is left.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You are a support agent")
+    ///     .with_model(model)
+    ///     .with_quotas(
+    ///         QuotaConfig::new(Arc::new(RedisQuotaStore::new(redis_url).await?))
+    ///             .with_limit(QuotaLimit::monthly().with_max_requests(10_000).with_max_tokens(5_000_000)),
+    ///     )
+    ///     .build()?;
+    /// ```
+    pub fn with_quotas(mut self, config: QuotaConfig) -> Self {
+        self.quotas = Some(config);
+        self
+    }
+
+    /// Add tools from a provider backed by external services. The provider is asked
+    /// for its tools at the start of every run and shut down with the agent.
+    pub fn with_tool_provider(mut self, provider: Arc<dyn ToolProvider>) -> Self {
+        self.tool_providers.push(provider);
+        self
+    }
+
+    /// Expose the tools of the MCP servers in `config`. Each server is started on
+    /// the agent's first run, restarted if its connection is lost, and stopped by
+    /// `DeepAgent::shutdown`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let servers = McpServersConfig::new()
+    ///     .with_server(
+    ///         McpServerConfig::stdio("filesystem", "npx", ["-y", "@modelcontextprotocol/server-filesystem", "/tmp"])
+    ///             .with_namespace("fs")
+    ///             .with_allowed_tools(["read_file", "list_directory"]),
+    ///     )
+    ///     .with_server(McpServerConfig::http("docs", "https://mcp.context7.com/mcp"));
+    ///
+    /// let agent = ConfigurableAgentBuilder::new("You are a helpful assistant")
+    ///     .with_model(model)
+    ///     .with_mcp_servers(servers)
+    ///     .build()?;
+    /// ```
+    #[cfg(feature = "mcp")]
+    pub fn with_mcp_servers(self, config: agents_mcp::McpServersConfig) -> Self {
+        self.with_tool_provider(Arc::new(agents_mcp::McpServers::new(config)))
+    }
+
+    /// Answer repeated prompts from a response cache instead of the model.
+    ///
+    /// Requests are matched exactly on their normalized prompt and everything sent
+    /// with it; with [`ResponseCacheConfig::with_semantic_matching`], differently
+    /// worded prompts with the same meaning match too. Cached answers cost no tokens
+    /// and are not counted by token tracking or quotas; guardrails still check them.
+    ///
+    /// Applies to the main model.
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You answer questions about our product")
+    ///     .with_model(model)
+    ///     .with_response_cache(ResponseCacheConfig::new(Arc::new(InMemoryResponseCache::new())))
+    ///     .build()?;
+    /// ```
+    pub fn with_response_cache(mut self, config: ResponseCacheConfig) -> Self {
+        self.response_cache = Some(config);
+        self
+    }
+
+    /// Fit requests to the context window of the model they go to, using the
+    /// strategy of the model's [`ContextAssemblyConfig`] catalog entry: which older
+    /// messages are dropped first, and whether they are left out or summarized.
+    /// Requests to models without an entry or default are left alone.
+    ///
+    /// Applies to the main model, including models swapped in by `RunOptions`.
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You are a research assistant")
+    ///     .with_model(model)
+    ///     .with_context_assembly(ContextAssemblyConfig::builtin())
+    ///     .build()?;
+    /// ```
+    pub fn with_context_assembly(mut self, config: ContextAssemblyConfig) -> Self {
+        self.context_assembly = Some(config);
+        self
+    }
+
+    /// Check user messages and model responses with a guardrail, e.g. Bedrock
+    /// Guardrails. Guardrails run in the order they were added; masked text reaches
+    /// the model (or user) masked and blocked text is answered with the guardrail's
+    /// message. Emits `GuardrailIntervened` events.
+    ///
+    /// Applies to the main model; streaming responses are delivered in one chunk.
+    ///
+    /// ```ignore
+    /// let agent = ConfigurableAgentBuilder::new("You are a support agent")
+    ///     .with_model(model)
+    ///     .with_guardrail(Arc::new(BedrockGuardrail::new("gr-abc123", "1").await?))
+    ///     .build()?;
+    /// ```
+    pub fn with_guardrail(mut self, guardrail: Arc<dyn Guardrail>) -> Self {
+        self.guardrails.push(guardrail);
+        self
+    }
+
+    /// Check the configuration and report every problem found, e.g. duplicate tool
+    /// names or HITL policies for tools the agent does not have. `build()` runs this
+    /// first and fails with the returned [`ConfigValidationError`].
+    pub fn validate(&self) -> Result<(), ConfigValidationError> {
+        let mut issues = Vec::new();
+        if self.planner.is_none() && std::env::var("OPENAI_API_KEY").is_err() {
+            issues.push(ConfigIssue::MissingModel);
+        }
+
+        let mut tool_names = HashSet::new();
+        for tool in &self.tools {
+            let name = tool.schema().name;
+            if !tool_names.insert(name.clone())
+                && !issues.contains(&ConfigIssue::DuplicateTool { name: name.clone() })
+            {
+                issues.push(ConfigIssue::DuplicateTool { name });
+            }
+        }
+
+        let mut subagent_names = HashSet::new();
+        for subagent in &self.subagents {
+            let name = subagent.name.clone();
+            if tool_names.contains(&name) {
+                issues.push(ConfigIssue::SubAgentNameCollision { name });
+            } else if !subagent_names.insert(name.clone())
+                && !issues.contains(&ConfigIssue::DuplicateSubAgent { name: name.clone() })
+            {
+                issues.push(ConfigIssue::DuplicateSubAgent { name });
+            }
+        }
+
+        let builtin_names = self.builtin_tool_names();
+        if self.tool_conflicts == ToolConflictStrategy::Error {
+            let mut conflicts: Vec<_> = tool_names.intersection(&builtin_names).collect();
+            conflicts.sort();
+            issues.extend(
+                conflicts
+                    .into_iter()
+                    .map(|name| ConfigIssue::BuiltinToolConflict { name: name.clone() }),
+            );
+        }
+
+        tool_names.extend(builtin_names);
+        let mut unknown: Vec<_> = self
+            .tool_interrupts
+            .keys()
+            .filter(|name| !tool_names.contains(*name))
+            .collect();
+        unknown.sort();
+        issues.extend(
+            unknown
+                .into_iter()
+                .map(|name| ConfigIssue::UnknownInterruptTool { name: name.clone() }),
+        );
+
+        let features = skills::enabled_features();
+        for (skill, requirement) in &self.skill_requirements {
+            let met = match requirement {
+                SkillRequirement::Tool(name) => tool_names.contains(name),
+                SkillRequirement::Checkpointer => self.checkpointer.is_some(),
+                SkillRequirement::Feature(feature) => features.contains(&feature.as_str()),
+            };
+            if !met {
+                issues.push(ConfigIssue::UnmetSkillRequirement {
+                    skill: skill.clone(),
+                    requirement: requirement.to_string(),
+                });
+            }
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigValidationError { issues })
+        }
+    }
+
+    /// Names of the tools the agent gets from its middleware.
+    fn builtin_tool_names(&self) -> HashSet<String> {
+        let mut names = HashSet::from(["task".to_string()]);
+        names.extend(
+            BUILTIN_TOOL_NAMES
+                .iter()
+                .filter(|name| {
+                    self.builtin_tools
+                        .as_ref()
+                        .is_none_or(|selected| selected.contains(**name))
+                })
+                .map(|name| name.to_string()),
+        );
+        if let Some(time_context) = self.time_context.as_ref().filter(|c| c.include_tools) {
+            names.extend(
+                create_time_tools(time_context.timezone)
+                    .iter()
+                    .map(|tool| tool.schema().name),
+            );
+        }
+        names
+    }
+
+    pub fn build(self) -> anyhow::Result<DeepAgent> {
+        self.finalize(create_deep_agent_from_config)
+    }
+
+    /// Build an agent using the async constructor alias. This mirrors the Python
+    /// async_create_deep_agent entry point, while reusing the same runtime internals.
+    pub fn build_async(self) -> anyhow::Result<DeepAgent> {
+        self.finalize(create_async_deep_agent_from_config)
+    }
+
+    /// Fold the selected profile's restrictions into the configuration. Disabled tools
+    /// and payload logging are left in the returned settings for the runtime.
+    fn apply_profile(mut self) -> (Self, ProfileSettings) {
+        let mut settings = self
+            .profile_settings
+            .remove(&self.profile)
+            .unwrap_or_else(|| self.profile.default_settings());
+        for (name, policy) in std::mem::take(&mut settings.approval_required) {
+            self.tool_interrupts.entry(name).or_insert(policy);
+        }
+        if settings.force_pii_sanitization {
+            self.enable_pii_sanitization = true;
+        }
+        if !settings.verbose_logging {
+            if let Some(tracking) = self.token_tracking_config.as_mut() {
+                tracking.log_usage = false;
+            }
+        }
+        if let Some(cap) = settings.max_iterations {
+            self.max_iterations = self.max_iterations.min(cap);
+        }
+        if let Some(cap) = settings.max_tool_output_chars {
+            let mut limits = self
+                .tool_output
+                .take()
+                .unwrap_or_else(|| ToolOutputConfig::new(cap));
+            limits.default_max_chars = Some(limits.default_max_chars.map_or(cap, |c| c.min(cap)));
+            for max_chars in limits.tool_max_chars.values_mut() {
+                *max_chars = (*max_chars).min(cap);
+            }
+            self.tool_output = Some(limits);
+        }
+        (self, settings)
+    }
+
+    fn finalize(self, ctor: fn(DeepAgentConfig) -> DeepAgent) -> anyhow::Result<DeepAgent> {
+        let (this, profile_restrictions) = self.apply_profile();
+        this.validate()?;
+        let Self {
+            instructions,
+            custom_system_prompt,
+            prompt_format,
+            prompt_sections,
+            planner,
+            tools,
+            subagents,
+            skill_instructions,
+            skill_requirements: _,
+            summarization,
+            tool_interrupts,
+            builtin_tools,
+            auto_general_purpose,
+            enable_prompt_caching,
+            checkpointer,
+            event_dispatcher,
+            enable_pii_sanitization,
+            token_tracking_config,
+            max_iterations,
+            profiling,
+            step_recording,
+            lazy_file_loading,
+            tool_output,
+            profile,
+            profile_settings: _,
+            tool_retry,
+            citations,
+            tool_selection,
+            tool_examples,
+            tool_argument_validation,
+            tool_conflicts,
+            tool_extensions,
+            descriptor,
+            locale,
+            time_context,
+            deterministic,
+            prompt_compression,
+            user_profile,
+            user_memory,
+            memory_policy,
+            state_size_limits,
+            offload,
+            output_sink,
+            translation,
+            stream_coalescing,
+            concurrency,
+            thread_lock,
+            experiment,
+            event_outbox,
+            approvals,
+            policy_provider,
+            dead_letters,
+            conversation_index,
+            thread_titles,
+            graph_memory,
+            quotas,
+            response_cache,
+            context_assembly,
+            guardrails,
+            tool_providers,
+        } = this;
+
+        let planner = match planner {
+            Some(planner) => planner,
+            // Use default model if no planner is set
+            None => Arc::new(LlmBackedPlanner::new(get_default_model()?)) as Arc<dyn PlannerHandle>,
+        };
+
+        // Runs may swap the model; below tracking and guardrails so both still apply
+        let planner = match planner.as_any().downcast_ref::<LlmBackedPlanner>() {
+            Some(llm_planner) => {
+                let model = Arc::new(RunModel::new(llm_planner.model().clone()));
+                Arc::new(LlmBackedPlanner::new(model)) as Arc<dyn PlannerHandle>
+            }
+            None => planner,
+        };
+
+        // Wrap the planner with token tracking if enabled
+        let mut usage_tracker = None;
+        let final_planner = if let Some(token_config) = token_tracking_config.clone() {
+            if token_config.enabled {
+                // Extract the underlying model from the planner
+                let planner_any = planner.as_any();
+                if let Some(llm_planner) = planner_any.downcast_ref::<LlmBackedPlanner>() {
+                    let model = llm_planner.model().clone();
+                    let tracked_model = Arc::new(TokenTrackingMiddleware::new(
+                        token_config,
+                        model,
+                        event_dispatcher.clone(),
+                    ));
+                    usage_tracker = Some(tracked_model.clone());
+                    Arc::new(LlmBackedPlanner::new(tracked_model)) as Arc<dyn PlannerHandle>
+                } else {
+                    planner
+                }
+            } else {
+                planner
+            }
+        } else {
+            planner
+        };
+
+        // Above tracking, so usage is estimated on the request actually sent
+        let final_planner = match context_assembly {
+            Some(config) => match final_planner.as_any().downcast_ref::<LlmBackedPlanner>() {
+                Some(llm_planner) => {
+                    let config = match &usage_tracker {
+                        Some(tracker) => config.tracked_by(tracker),
+                        None => config,
+                    };
+                    let assembled = Arc::new(ContextAssemblyMiddleware::new(
+                        config,
+                        llm_planner.model().clone(),
+                    ));
+                    Arc::new(LlmBackedPlanner::new(assembled)) as Arc<dyn PlannerHandle>
+                }
+                None => {
+                    tracing::warn!(
+                        "Context assembly needs a model-backed planner and was not applied"
+                    );
+                    final_planner
+                }
+            },
+            None => final_planner,
+        };
+
+        // Cache hits skip token tracking, so they are never billed
+        let final_planner = match response_cache {
+            Some(config) => match final_planner.as_any().downcast_ref::<LlmBackedPlanner>() {
+                Some(llm_planner) => {
+                    let cached = Arc::new(ResponseCacheMiddleware::new(
+                        config,
+                        llm_planner.model().clone(),
+                    ));
+                    Arc::new(LlmBackedPlanner::new(cached)) as Arc<dyn PlannerHandle>
+                }
+                None => {
+                    tracing::warn!(
+                        "Response caching needs a model-backed planner and was not applied"
+                    );
+                    final_planner
+                }
+            },
+            None => final_planner,
+        };
+
+        // Guardrails wrap the tracked model, so blocked input is never billed
+        let final_planner = if guardrails.is_empty() {
+            final_planner
+        } else if let Some(llm_planner) = final_planner.as_any().downcast_ref::<LlmBackedPlanner>()
+        {
+            let guarded = Arc::new(GuardrailMiddleware::new(
+                guardrails,
+                llm_planner.model().clone(),
+                event_dispatcher.clone(),
+            ));
+            Arc::new(LlmBackedPlanner::new(guarded)) as Arc<dyn PlannerHandle>
+        } else {
+            tracing::warn!("Guardrails need a model-backed planner and were not applied");
+            final_planner
+        };
+
+        let instructions = skills::with_skill_instructions(instructions, &skill_instructions);
+        let custom_system_prompt = custom_system_prompt
+            .map(|prompt| skills::with_skill_instructions(prompt, &skill_instructions));
+
+        let mut cfg = DeepAgentConfig::new(instructions, final_planner)
+            .with_auto_general_purpose(auto_general_purpose)
+            .with_prompt_caching(enable_prompt_caching)
+            .with_pii_sanitization(enable_pii_sanitization)
+            .with_max_iterations(max_iterations.get())
+            .with_profiling(profiling)
+            .with_step_recording(step_recording)
+            .with_lazy_file_loading(lazy_file_loading)
+            .with_prompt_format(prompt_format)
+            .with_profile(profile)
+            .with_disabled_tools(profile_restrictions.disabled_tools)
+            .with_tool_mocks(profile_restrictions.tool_mocks)
+            .with_tool_payload_logging(profile_restrictions.verbose_logging);
+
+        // Apply custom system prompt if provided
+        if let Some(prompt) = custom_system_prompt {
+            cfg = cfg.with_system_prompt(prompt);
+        }
+
+        if let Some(sections) = prompt_sections {
+            cfg = cfg.with_prompt_sections(sections);
+        }
+
+        if let Some(limits) = tool_output {
+            cfg = cfg.with_tool_output_limits(limits);
+        }
+        if let Some(retries) = tool_retry {
+            cfg = cfg.with_tool_retries(retries);
+        }
+        if let Some(citations) = citations {
+            cfg = cfg.with_citations(citations);
+        }
+        // Sub-agents with their own model track usage with the same settings
+        if let Some(tracking) = token_tracking_config {
+            cfg = cfg.with_token_tracking_config(tracking);
+        }
+        if let Some(tracker) = usage_tracker {
+            cfg = cfg.with_usage_tracker(tracker);
+        }
+        if let Some(selection) = tool_selection {
+            cfg = cfg.with_tool_selection(selection);
+        }
+        if let Some(examples) = tool_examples {
+            cfg = cfg.with_tool_examples(examples);
+        }
+        cfg = cfg.with_tool_argument_validation(tool_argument_validation);
+        cfg = cfg.with_tool_conflicts(tool_conflicts);
+        cfg = cfg
+            .with_tool_extensions(tool_extensions)
+            .with_descriptor(descriptor);
+        if let Some(locale) = locale {
+            cfg = cfg.with_locale_config(locale);
+        }
+        if let Some(time_context) = time_context {
+            cfg = cfg.with_time_context(time_context);
+        }
+        if let Some(deterministic) = deterministic {
+            cfg = cfg.with_deterministic_mode(deterministic);
+        }
+        if let Some(compression) = prompt_compression {
+            cfg = cfg.with_prompt_compression(compression);
+        }
+        if let Some(profile) = user_profile {
+            cfg = cfg.with_user_profile(profile);
+        }
+        if let Some(memory) = user_memory {
+            cfg = cfg.with_user_memory(memory);
+        }
+        if let Some(policy) = memory_policy {
+            cfg = cfg.with_memory_policy(policy);
+        }
+        if let Some(limits) = state_size_limits {
+            cfg = cfg.with_state_size_limits(limits);
+        }
+        if let Some(offload) = offload {
+            cfg = cfg.with_message_offloading(offload);
+        }
+        if let Some(output_sink) = output_sink {
+            cfg = cfg.with_output_sink(output_sink);
+        }
+        if let Some(translation) = translation {
+            cfg = cfg.with_translation(translation);
+        }
+        if let Some(coalescing) = stream_coalescing {
+            cfg = cfg.with_stream_coalescing(coalescing);
+        }
+        if let Some(concurrency) = concurrency {
+            cfg = cfg.with_concurrency_limits(concurrency);
+        }
+        if let Some(thread_lock) = thread_lock {
+            cfg = cfg.with_thread_lock(thread_lock);
+        }
+        if let Some(experiment) = experiment {
+            cfg = cfg.with_experiment(experiment);
+        }
+        if let Some(ckpt) = checkpointer {
+            cfg = cfg.with_checkpointer(ckpt);
+        }
+        if let Some(outbox) = event_outbox {
+            cfg = cfg.with_event_outbox(outbox);
+        }
+        if let Some(approvals) = approvals {
+            cfg = cfg.with_approvals(approvals);
+        }
+        if let Some(provider) = policy_provider {
+            cfg = cfg.with_policy_provider(provider);
+        }
+        if let Some(store) = dead_letters {
+            cfg = cfg.with_dead_letter_store(store);
+        }
+        if let Some(index) = conversation_index {
+            cfg = cfg.with_conversation_index(index);
+        }
+        if let Some(titles) = thread_titles {
+            cfg = cfg.with_thread_titles(titles);
+        }
+        if let Some(graph) = graph_memory {
+            cfg = cfg.with_graph_memory(graph);
+        }
+        if let Some(quotas) = quotas {
+            cfg = cfg.with_quotas(quotas);
+        }
+        for provider in tool_providers {
+            cfg = cfg.with_tool_provider(provider);
+        }
+        if let Some(dispatcher) = event_dispatcher {
+            cfg = cfg.with_event_dispatcher(dispatcher);
+        }
+        if let Some(sum) = summarization {
+            cfg = cfg.with_summarization(sum);
+        }
+        if let Some(selected) = builtin_tools {
+            cfg = cfg.with_builtin_tools(selected);
+        }
+        for (name, policy) in tool_interrupts {
+            cfg = cfg.with_tool_interrupt(name, policy);
+        }
+        for tool in tools {
+            cfg = cfg.with_tool(tool);
+        }
+        for sub_cfg in subagents {
+            cfg = cfg.with_subagent_config(sub_cfg);
+        }
+
+        Ok(ctor(cfg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_default_max_iterations() {
+        let builder = ConfigurableAgentBuilder::new("test instructions");
+        assert_eq!(builder.max_iterations.get(), 10);
+    }
+
+    #[test]
+    fn test_builder_custom_max_iterations() {
+        let builder = ConfigurableAgentBuilder::new("test instructions").with_max_iterations(20);
+        assert_eq!(builder.max_iterations.get(), 20);
+    }
+
+    #[test]
+    #[should_panic(expected = "max_iterations must be greater than 0")]
+    fn test_builder_zero_max_iterations_panics() {
+        let _builder = ConfigurableAgentBuilder::new("test instructions").with_max_iterations(0);
     }
 
     #[test]
@@ -590,4 +2074,150 @@ mod tests {
         assert_eq!(builder.max_iterations.get(), 15);
         assert!(!builder.enable_pii_sanitization);
     }
+
+    #[test]
+    fn test_builder_with_prompt_sections() {
+        use crate::prompts::PromptSection;
+
+        let builder = ConfigurableAgentBuilder::new("test")
+            .with_prompt_sections(PromptSections::new().without(PromptSection::Filesystem));
+        let sections = builder.prompt_sections.unwrap();
+        assert!(!sections.contains(PromptSection::Filesystem));
+        assert!(sections.contains(PromptSection::Planning));
+    }
+
+    #[test]
+    fn test_builder_with_tool_state() {
+        let builder = ConfigurableAgentBuilder::new("test instructions")
+            .with_tool_state(String::from("shared"))
+            .with_tool_state(7u64);
+        assert_eq!(builder.tool_extensions.len(), 2);
+        assert_eq!(builder.tool_extensions.get::<u64>(), Some(&7));
+    }
+
+    #[test]
+    fn test_builder_validation_reports_every_issue() {
+        use crate::providers::{OpenAiChatModel, OpenAiConfig};
+        use agents_core::tools::{ToolParameterSchema, ToolResult};
+
+        let review = || HitlPolicy {
+            allow_auto: false,
+            note: None,
+        };
+
+        let tool = |name: &str| {
+            agents_toolkit::tool_sync(
+                name,
+                "test tool",
+                ToolParameterSchema::object("No parameters", HashMap::new(), Vec::new()),
+                |_args, ctx| Ok(ToolResult::text(&ctx, "ok")),
+            )
+        };
+        let builder = ConfigurableAgentBuilder::new("test instructions")
+            .with_model(Arc::new(
+                OpenAiChatModel::new(OpenAiConfig::new("sk-test", "gpt-4o-mini")).unwrap(),
+            ))
+            .with_tools([tool("search"), tool("search"), tool("notify")])
+            .with_subagent_config([
+                SubAgentConfig::new("notify", "Sends notifications", "Notify people"),
+                SubAgentConfig::new("researcher", "Researches", "Research topics"),
+                SubAgentConfig::new("researcher", "Researches", "Research topics"),
+            ])
+            .with_tool_interrupt("search", review())
+            .with_tool_interrupt("read_file", review())
+            .with_tool_interrupt("delete_account", review());
+
+        let error = builder.validate().unwrap_err();
+        assert_eq!(
+            error.issues,
+            vec![
+                ConfigIssue::DuplicateTool {
+                    name: "search".into()
+                },
+                ConfigIssue::SubAgentNameCollision {
+                    name: "notify".into()
+                },
+                ConfigIssue::DuplicateSubAgent {
+                    name: "researcher".into()
+                },
+                ConfigIssue::UnknownInterruptTool {
+                    name: "delete_account".into()
+                },
+            ]
+        );
+        assert!(error
+            .to_string()
+            .starts_with("invalid agent configuration (4 problems)"));
+        assert!(builder.build().is_err());
+    }
+
+    #[test]
+    fn test_builder_validation_reports_builtin_tool_conflicts() {
+        use crate::providers::{OpenAiChatModel, OpenAiConfig};
+        use agents_core::tools::{ToolParameterSchema, ToolResult};
+
+        let builder = || {
+            ConfigurableAgentBuilder::new("test instructions")
+                .with_model(Arc::new(
+                    OpenAiChatModel::new(OpenAiConfig::new("sk-test", "gpt-4o-mini")).unwrap(),
+                ))
+                .with_tool(agents_toolkit::tool_sync(
+                    "read_file",
+                    "Reads a remote file",
+                    ToolParameterSchema::object("No parameters", HashMap::new(), Vec::new()),
+                    |_args, ctx| Ok(ToolResult::text(&ctx, "remote")),
+                ))
+        };
+
+        let error = builder().validate().unwrap_err();
+        assert_eq!(
+            error.issues,
+            vec![ConfigIssue::BuiltinToolConflict {
+                name: "read_file".into()
+            }]
+        );
+        assert!(builder()
+            .with_builtin_tools(["write_todos"])
+            .validate()
+            .is_ok());
+        assert!(builder()
+            .with_tool_conflicts(ToolConflictStrategy::prefix("remote"))
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn production_profile_caps_budgets_and_requires_approval() {
+        let (builder, restrictions) = ConfigurableAgentBuilder::new("test instructions")
+            .with_max_iterations(50)
+            .with_pii_sanitization(false)
+            .with_tool_output_limits(ToolOutputConfig::new(50_000).with_tool_limit("web", 4_000))
+            .with_profile_settings(
+                Profile::Production,
+                ProfileSettings::production()
+                    .disable_tool("write_file")
+                    .require_approval("edit_file", "Edits are reviewed"),
+            )
+            .with_profile(Profile::Production)
+            .apply_profile();
+
+        assert_eq!(builder.max_iterations.get(), 10);
+        assert!(builder.enable_pii_sanitization);
+        let limits = builder.tool_output.unwrap();
+        assert_eq!(limits.default_max_chars, Some(20_000));
+        assert_eq!(limits.limit_for("web"), Some(4_000));
+        assert_eq!(
+            builder.tool_interrupts["edit_file"].note.as_deref(),
+            Some("Edits are reviewed")
+        );
+        assert!(restrictions.disabled_tools.contains("write_file"));
+        assert!(!restrictions.verbose_logging);
+
+        let (builder, restrictions) = ConfigurableAgentBuilder::new("test instructions")
+            .with_max_iterations(50)
+            .apply_profile();
+        assert_eq!(builder.max_iterations.get(), 50);
+        assert!(builder.tool_output.is_none());
+        assert!(restrictions.verbose_logging);
+    }
 }
@@ -0,0 +1,182 @@
+//! Built-in skills: deterministic tools for work models get wrong in their head
+//!
+//! Models drop digits in long multiplications, misremember conversion factors,
+//! miscount days between dates and lose rows when summing a spreadsheet by eye. Each [`BuiltinSkill`] registers deterministic
+//! tools from `agents-toolkit` together with instructions telling the model to
+//! use them instead of answering from memory.
+//!
+//! ```ignore
+//! let agent = ConfigurableAgentBuilder::new("You are a finance assistant")
+//!     .with_model(model)
+//!     .with_skill(BuiltinSkill::Math)
+//!     .with_skill(BuiltinSkill::Units)
+//!     .build()?;
+//! ```
+
+use std::fmt;
+
+use super::skills::Skill;
+use agents_core::tools::ToolBox;
+use agents_toolkit::builtin::{
+    create_data_tools, create_math_tools, create_text_tools, create_time_tools, create_unit_tools,
+};
+
+/// The skills that ship with the SDK.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BuiltinSkill {
+    /// `calculate`: exact decimal arithmetic
+    Math,
+    /// `convert_units`: length, mass, volume, area, time, speed, data and temperature
+    Units,
+    /// `get_current_time` and `date_math`, in UTC unless the model names a timezone.
+    /// A [time context](super::ConfigurableAgentBuilder::with_time_context) with
+    /// tools already adds both; don't register this skill alongside one.
+    DateTime,
+    /// `regex_extract`, `regex_replace` and `text_stats`
+    Text,
+    /// `load_table`, `query_table` and `describe_table` for CSV files, and Excel
+    /// workbooks with the `xlsx` feature
+    Data,
+}
+
+impl BuiltinSkill {
+    pub const ALL: [BuiltinSkill; 5] = [
+        BuiltinSkill::Math,
+        BuiltinSkill::Units,
+        BuiltinSkill::DateTime,
+        BuiltinSkill::Text,
+        BuiltinSkill::Data,
+    ];
+
+    /// Look a skill up by its [`name`](Skill::name), e.g. from configuration.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|skill| skill.name().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+impl fmt::Display for BuiltinSkill {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Skill for BuiltinSkill {
+    fn name(&self) -> &str {
+        match self {
+            BuiltinSkill::Math => "math",
+            BuiltinSkill::Units => "units",
+            BuiltinSkill::DateTime => "datetime",
+            BuiltinSkill::Text => "text",
+            BuiltinSkill::Data => "data",
+        }
+    }
+
+    fn tools(&self) -> Vec<ToolBox> {
+        match self {
+            BuiltinSkill::Math => create_math_tools(),
+            BuiltinSkill::Units => create_unit_tools(),
+            BuiltinSkill::DateTime => create_time_tools(chrono_tz::UTC),
+            BuiltinSkill::Text => create_text_tools(),
+            BuiltinSkill::Data => create_data_tools(),
+        }
+    }
+
+    fn instructions(&self) -> Option<String> {
+        let instructions = match self {
+            BuiltinSkill::Math => {
+                "Never do arithmetic in your head. Use `calculate` for every sum, product, \
+                 percentage or rounding, and quote its result exactly."
+            }
+            BuiltinSkill::Units => {
+                "Use `convert_units` for every unit conversion instead of recalling \
+                 conversion factors."
+            }
+            BuiltinSkill::DateTime => {
+                "Use `get_current_time` to learn today's date and `date_math` to add to \
+                 dates or count the days between them. Never assume the date or count \
+                 calendar days yourself."
+            }
+            BuiltinSkill::Text => {
+                "Use `text_stats` to count words, sentences or characters, and \
+                 `regex_extract` or `regex_replace` to find or rewrite every occurrence \
+                 of a pattern in a text, instead of scanning it by eye."
+            }
+            BuiltinSkill::Data => {
+                "To analyze a CSV or spreadsheet file, load it with `load_table`, then \
+                 answer with `query_table` (filters, group_by, aggregates, sort_by) and \
+                 `describe_table` rather than reading and adding up rows yourself. Quote \
+                 the numbers of the result tables exactly."
+            }
+        };
+        Some(instructions.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::ConfigurableAgentBuilder;
+    use agents_core::agent::{PlannerAction, PlannerContext, PlannerDecision, PlannerHandle};
+    use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+    use agents_core::state::AgentStateSnapshot;
+    use async_trait::async_trait;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    /// Calculates 0.1 + 0.2, then answers with the result.
+    struct SumPlanner;
+
+    #[async_trait]
+    impl PlannerHandle for SumPlanner {
+        async fn plan(
+            &self,
+            context: PlannerContext,
+            _state: Arc<AgentStateSnapshot>,
+        ) -> anyhow::Result<PlannerDecision> {
+            assert!(context.system_prompt.contains("## Skill: math"));
+            let next_action = match context.history.last() {
+                Some(last) if last.role == MessageRole::Tool => PlannerAction::Respond {
+                    message: AgentMessage {
+                        role: MessageRole::Agent,
+                        content: MessageContent::Text(last.content.to_text()),
+                        metadata: None,
+                    },
+                },
+                _ => PlannerAction::CallTool {
+                    tool_name: "calculate".into(),
+                    payload: json!({ "expression": "0.1 + 0.2" }),
+                },
+            };
+            Ok(PlannerDecision { next_action })
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    #[tokio::test]
+    async fn test_builtin_skills_register_their_tools() {
+        assert_eq!(
+            BuiltinSkill::from_name("DateTime"),
+            Some(BuiltinSkill::DateTime)
+        );
+        assert_eq!(BuiltinSkill::from_name("chess"), None);
+
+        let agent = ConfigurableAgentBuilder::new("You are a finance assistant")
+            .with_planner(Arc::new(SumPlanner))
+            .with_skills(BuiltinSkill::ALL)
+            .build()
+            .unwrap();
+        let reply = agent
+            .handle_message(
+                "What is 0.1 + 0.2?",
+                Arc::new(AgentStateSnapshot::default()),
+            )
+            .await
+            .unwrap();
+        assert!(reply.content.to_text().contains("\"result\":\"0.3\""));
+    }
+}
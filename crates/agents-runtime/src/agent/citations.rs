@@ -0,0 +1,261 @@
+//! Requiring final answers to cite tool results
+//!
+//! Research agents sometimes answer from the model's memory and ignore what their
+//! tools found. Each run keeps the provenance of the content it gathered: the
+//! results of the tools it called. With a [`CitationConfig`], a final answer that
+//! references none of them is not returned; the answer stays in the history and
+//! the planner is sent back with corrective instructions, up to a number of times.
+//!
+//! An answer references a tool result when it shares a run of words with it
+//! (quoting, or repeating names, figures and titles) or contains one of its URLs.
+//! This is a heuristic: it tells answers built on the results from answers that
+//! ignore them, not whether every claim is supported.
+
+use std::collections::HashSet;
+
+use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+
+const DEFAULT_INSTRUCTIONS: &str = "Your answer does not use the tool results of this \
+conversation. Base the answer on them: quote or name the sources you rely on, and say \
+so when they do not answer the question.";
+
+const NO_SOURCES_INSTRUCTIONS: &str = "Your answer is not based on any tool result. Use \
+your tools to find sources before answering, and quote or name the sources you rely on.";
+
+/// Requirement that final answers reference the results of the run's tools.
+///
+/// # Example
+///
+/// ```ignore
+/// let agent = ConfigurableAgentBuilder::new("You are a researcher")
+///     .with_model(model)
+///     .with_tool(web_search)
+///     .with_citations(CitationConfig::new().with_source_tool("web_search"))
+///     .build()?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CitationConfig {
+    /// Tools whose results count as sources; every tool when empty
+    pub source_tools: Vec<String>,
+    /// Consecutive words an answer must share with a result to reference it (default: 4)
+    pub min_shared_words: usize,
+    /// Answers sent back before an uncited answer is returned anyway (default: 2)
+    pub max_corrections: usize,
+    /// Sent to the planner with an uncited answer when the run has tool results
+    pub instructions: String,
+}
+
+impl Default for CitationConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CitationConfig {
+    pub fn new() -> Self {
+        Self {
+            source_tools: Vec::new(),
+            min_shared_words: 4,
+            max_corrections: 2,
+            instructions: DEFAULT_INSTRUCTIONS.to_string(),
+        }
+    }
+
+    /// Only count the results of `tool_name` (and other tools added this way).
+    pub fn with_source_tool(mut self, tool_name: impl Into<String>) -> Self {
+        self.source_tools.push(tool_name.into());
+        self
+    }
+
+    pub fn with_min_shared_words(mut self, words: usize) -> Self {
+        self.min_shared_words = words.max(1);
+        self
+    }
+
+    pub fn with_max_corrections(mut self, corrections: usize) -> Self {
+        self.max_corrections = corrections;
+        self
+    }
+
+    pub fn with_instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = instructions.into();
+        self
+    }
+
+    fn counts(&self, tool_name: &str) -> bool {
+        self.source_tools.is_empty() || self.source_tools.iter().any(|t| t == tool_name)
+    }
+
+    /// Whether `answer` references one of the source results in `provenance`.
+    pub(crate) fn is_cited(&self, answer: &str, provenance: &Provenance) -> bool {
+        let answer_words = words(answer);
+        let answer_ngrams = ngrams(&answer_words, self.min_shared_words);
+        let answer_urls = urls(answer);
+        provenance
+            .results
+            .iter()
+            .filter(|(tool_name, _)| self.counts(tool_name))
+            .any(|(_, result)| {
+                urls(result).iter().any(|url| answer_urls.contains(url))
+                    || ngrams(&words(result), self.min_shared_words)
+                        .iter()
+                        .any(|ngram| answer_ngrams.contains(ngram))
+            })
+    }
+
+    /// Instructions sent back with an uncited answer.
+    pub(crate) fn correction(&self, provenance: &Provenance) -> AgentMessage {
+        let has_sources = provenance
+            .results
+            .iter()
+            .any(|(tool_name, _)| self.counts(tool_name));
+        let text = if has_sources {
+            self.instructions.clone()
+        } else {
+            NO_SOURCES_INSTRUCTIONS.to_string()
+        };
+        AgentMessage {
+            role: MessageRole::System,
+            content: MessageContent::Text(text),
+            metadata: None,
+        }
+    }
+}
+
+/// Tool results gathered by a run, by tool.
+#[derive(Debug, Default)]
+pub(crate) struct Provenance {
+    results: Vec<(String, String)>,
+}
+
+impl Provenance {
+    pub(crate) fn record(&mut self, tool_name: &str, result: &AgentMessage) {
+        self.results
+            .push((tool_name.to_string(), result.content.to_text()));
+    }
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn ngrams(words: &[String], n: usize) -> HashSet<&[String]> {
+    words.windows(n).collect()
+}
+
+fn urls(text: &str) -> HashSet<&str> {
+    text.split_whitespace()
+        .filter(|token| token.starts_with("http://") || token.starts_with("https://"))
+        .map(|url| url.trim_end_matches(|c: char| !c.is_alphanumeric() && c != '/'))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::ConfigurableAgentBuilder;
+    use agents_core::llm::{LanguageModel, LlmRequest, LlmResponse};
+    use agents_core::state::AgentStateSnapshot;
+    use agents_core::tools::{Tool, ToolContext, ToolParameterSchema, ToolResult, ToolSchema};
+    use async_trait::async_trait;
+    use std::sync::Arc;
+
+    const RESULT: &str =
+        "Rust 1.0 was released on May 15, 2015. See https://blog.rust-lang.org/2015/05/15.";
+
+    fn provenance() -> Provenance {
+        let mut provenance = Provenance::default();
+        provenance.record(
+            "web_search",
+            &AgentMessage {
+                role: MessageRole::Tool,
+                content: MessageContent::Text(RESULT.into()),
+                metadata: None,
+            },
+        );
+        provenance
+    }
+
+    #[test]
+    fn test_answers_cite_by_shared_words_or_urls() {
+        let config = CitationConfig::new();
+        let provenance = provenance();
+        assert!(config.is_cited("It was released on May 15, 2015.", &provenance));
+        assert!(config.is_cited("Source: https://blog.rust-lang.org/2015/05/15", &provenance));
+        assert!(!config.is_cited("Rust came out in 2015.", &provenance));
+
+        let other_tool = CitationConfig::new().with_source_tool("fetch_url");
+        assert!(!other_tool.is_cited("It was released on May 15, 2015.", &provenance));
+        assert_eq!(
+            other_tool.correction(&provenance).content.to_text(),
+            NO_SOURCES_INSTRUCTIONS
+        );
+    }
+
+    struct SearchTool;
+
+    #[async_trait]
+    impl Tool for SearchTool {
+        fn schema(&self) -> ToolSchema {
+            ToolSchema::new(
+                "web_search",
+                "Search the web",
+                ToolParameterSchema::object("Search arguments", Default::default(), vec![]),
+            )
+        }
+
+        async fn execute(
+            &self,
+            _args: serde_json::Value,
+            ctx: ToolContext,
+        ) -> anyhow::Result<ToolResult> {
+            Ok(ToolResult::text(&ctx, RESULT))
+        }
+    }
+
+    /// Searches, answers from memory, then quotes the result once corrected.
+    struct ForgetfulModel;
+
+    #[async_trait]
+    impl LanguageModel for ForgetfulModel {
+        async fn generate(&self, request: LlmRequest) -> anyhow::Result<LlmResponse> {
+            let last = request.messages.last().map(|m| m.role.clone());
+            let text = match last {
+                Some(MessageRole::User) => {
+                    r#"{"tool_calls":[{"name":"web_search","args":{}}]}"#.to_string()
+                }
+                Some(MessageRole::Tool) => "Rust came out in 2015.".to_string(),
+                _ => "Rust 1.0 was released on May 15, 2015.".to_string(),
+            };
+            Ok(LlmResponse::new(AgentMessage {
+                role: MessageRole::Agent,
+                content: MessageContent::Text(text),
+                metadata: None,
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_uncited_answers_are_sent_back() {
+        let agent = ConfigurableAgentBuilder::new("research")
+            .with_model(Arc::new(ForgetfulModel))
+            .with_tool(Arc::new(SearchTool))
+            .with_citations(CitationConfig::new())
+            .build()
+            .unwrap();
+        let reply = agent
+            .handle_message(
+                "When was Rust 1.0 released?",
+                Arc::new(AgentStateSnapshot::default()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            reply.content.to_text(),
+            "Rust 1.0 was released on May 15, 2015."
+        );
+    }
+}
@@ -0,0 +1,603 @@
+//! Concurrency limits per agent instance
+//!
+//! A burst of simultaneous conversations turns into a burst of LLM and tool calls
+//! against downstream APIs. `ConcurrencyConfig` caps how many runs and tool
+//! executions an agent performs at once. Runs over the cap wait in per-priority
+//! queues, optionally bounded in length and waiting time; tool executions over the
+//! cap wait until a slot frees up. Freed slots go to the queues by weighted round
+//! robin, so interactive traffic is served first without starving batch work.
+//!
+//! Every run keeps its state and conversation in a context of its own, so runs of
+//! different threads execute side by side up to the run limit.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// Scheduling class of a run waiting for a slot.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum RunPriority {
+    /// Batch and background traffic
+    Low,
+    #[default]
+    Normal,
+    /// Interactive sessions and premium customers
+    High,
+}
+
+impl RunPriority {
+    /// Queue index, highest priority first.
+    fn index(self) -> usize {
+        match self {
+            RunPriority::High => 0,
+            RunPriority::Normal => 1,
+            RunPriority::Low => 2,
+        }
+    }
+}
+
+/// Relative share of freed slots each priority receives while runs of several
+/// priorities are waiting.
+///
+/// With the default `6:3:1`, six of every ten freed slots go to `High` runs as long
+/// as all three queues are non-empty. A queue that is empty does not take part, so
+/// `Low` runs get every slot when nothing else waits. Weights below 1 are raised to 1
+/// so that no priority starves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriorityWeights {
+    pub high: u32,
+    pub normal: u32,
+    pub low: u32,
+}
+
+impl Default for PriorityWeights {
+    fn default() -> Self {
+        Self {
+            high: 6,
+            normal: 3,
+            low: 1,
+        }
+    }
+}
+
+impl PriorityWeights {
+    pub fn new(high: u32, normal: u32, low: u32) -> Self {
+        Self { high, normal, low }
+    }
+
+    fn by_index(&self) -> [i64; 3] {
+        [self.high, self.normal, self.low].map(|w| i64::from(w.max(1)))
+    }
+}
+
+/// Limits on concurrent runs and tool executions for one agent.
+///
+/// # Example
+///
+/// ```ignore
+/// let agent = ConfigurableAgentBuilder::new("You are a support agent")
+///     .with_model(model)
+///     .with_concurrency_limits(
+///         ConcurrencyConfig::new()
+///             .with_max_concurrent_runs(8)
+///             .with_max_concurrent_tools(4)
+///             .with_queue_timeout(Duration::from_secs(10)),
+///     )
+///     .build()?;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConcurrencyConfig {
+    /// Runs (`handle_message`, streams, HITL resumes) executing at once
+    pub max_concurrent_runs: Option<usize>,
+    /// Tool executions running at once, across all runs
+    pub max_concurrent_tools: Option<usize>,
+    /// Runs allowed to wait for a slot; further runs are rejected immediately
+    pub max_queued_runs: Option<usize>,
+    /// How long a run may wait for a slot before it is rejected
+    pub queue_timeout: Option<Duration>,
+    /// How freed slots are shared between waiting priorities
+    pub priority_weights: PriorityWeights,
+}
+
+impl ConcurrencyConfig {
+    /// No limits; enable the ones you need with the `with_*` methods.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_concurrent_runs(mut self, max: usize) -> Self {
+        self.max_concurrent_runs = Some(max.max(1));
+        self
+    }
+
+    pub fn with_max_concurrent_tools(mut self, max: usize) -> Self {
+        self.max_concurrent_tools = Some(max.max(1));
+        self
+    }
+
+    pub fn with_max_queued_runs(mut self, max: usize) -> Self {
+        self.max_queued_runs = Some(max);
+        self
+    }
+
+    pub fn with_queue_timeout(mut self, timeout: Duration) -> Self {
+        self.queue_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_priority_weights(mut self, weights: PriorityWeights) -> Self {
+        self.priority_weights = weights;
+        self
+    }
+}
+
+/// Returned when a run cannot get a slot.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ConcurrencyLimitError {
+    #[error("agent is at capacity and its run queue is full ({capacity} waiting)")]
+    QueueFull { capacity: usize },
+    #[error("timed out after {waited_ms} ms waiting for a free run slot")]
+    QueueTimeout { waited_ms: u64 },
+}
+
+/// Current occupancy of an agent's concurrency limits.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConcurrencyStats {
+    pub active_runs: usize,
+    pub queued_runs: usize,
+    /// Waiting runs per priority
+    pub queued_high: usize,
+    pub queued_normal: usize,
+    pub queued_low: usize,
+    pub max_concurrent_runs: Option<usize>,
+    pub active_tools: usize,
+    pub queued_tools: usize,
+    pub max_concurrent_tools: Option<usize>,
+    /// Runs rejected because the queue was full or the wait timed out
+    pub rejected_runs: u64,
+}
+
+type Waiters = VecDeque<(u64, oneshot::Sender<()>)>;
+
+#[derive(Default)]
+struct LimiterState {
+    active: usize,
+    next_ticket: u64,
+    /// FIFO queues indexed by [`RunPriority::index`]
+    waiters: [Waiters; 3],
+    /// Smooth weighted round-robin credit per queue
+    credit: [i64; 3],
+    rejected: u64,
+}
+
+impl LimiterState {
+    fn queued(&self) -> usize {
+        self.waiters.iter().map(VecDeque::len).sum()
+    }
+
+    /// Pick the queue that receives the next free slot.
+    fn next_queue(&mut self, weights: [i64; 3]) -> Option<usize> {
+        let waiting: Vec<usize> = (0..3).filter(|&i| !self.waiters[i].is_empty()).collect();
+        let total: i64 = waiting.iter().map(|&i| weights[i]).sum();
+        for &i in &waiting {
+            self.credit[i] += weights[i];
+        }
+        // Ties go to the higher priority, which comes first
+        let pick = waiting.into_iter().rev().max_by_key(|&i| self.credit[i])?;
+        self.credit[pick] -= total;
+        Some(pick)
+    }
+}
+
+/// Counting limiter with weighted priority queues. Slots are handed directly to a
+/// waiter on release, so late arrivals cannot overtake queued runs.
+pub(crate) struct Limiter {
+    max: Option<usize>,
+    weights: [i64; 3],
+    state: Mutex<LimiterState>,
+}
+
+impl Limiter {
+    pub(crate) fn new(max: Option<usize>, weights: PriorityWeights) -> Arc<Self> {
+        Arc::new(Self {
+            max,
+            weights: weights.by_index(),
+            state: Mutex::new(LimiterState::default()),
+        })
+    }
+
+    /// Take a slot, waiting in line when none is free.
+    pub(crate) async fn acquire(
+        self: &Arc<Self>,
+        priority: RunPriority,
+        max_queued: Option<usize>,
+        timeout: Option<Duration>,
+    ) -> Result<Permit, ConcurrencyLimitError> {
+        let (ticket, receiver) = {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            match self.max {
+                Some(max) if state.active >= max => {}
+                _ => {
+                    state.active += 1;
+                    return Ok(Permit {
+                        limiter: self.clone(),
+                    });
+                }
+            }
+            if let Some(capacity) = max_queued {
+                if state.queued() >= capacity {
+                    state.rejected += 1;
+                    return Err(ConcurrencyLimitError::QueueFull { capacity });
+                }
+            }
+            let (sender, receiver) = oneshot::channel();
+            let ticket = state.next_ticket;
+            state.next_ticket += 1;
+            state.waiters[priority.index()].push_back((ticket, sender));
+            (ticket, receiver)
+        };
+
+        let mut waiter = Waiter {
+            limiter: self.clone(),
+            queue: priority.index(),
+            ticket,
+            receiver: Some(receiver),
+        };
+        let started = std::time::Instant::now();
+        let granted = match timeout {
+            Some(timeout) => {
+                let receiver = waiter.receiver.as_mut().expect("receiver present");
+                tokio::time::timeout(timeout, receiver).await.is_ok()
+            }
+            None => {
+                let receiver = waiter.receiver.as_mut().expect("receiver present");
+                receiver.await.is_ok()
+            }
+        };
+
+        if granted {
+            waiter.receiver = None;
+            return Ok(Permit {
+                limiter: self.clone(),
+            });
+        }
+        // The slot may have been handed over just as the timeout fired
+        if waiter.cancel() {
+            return Ok(Permit {
+                limiter: self.clone(),
+            });
+        }
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.rejected += 1;
+        Err(ConcurrencyLimitError::QueueTimeout {
+            waited_ms: started.elapsed().as_millis() as u64,
+        })
+    }
+
+    pub(crate) fn active(&self) -> usize {
+        self.state.lock().map(|s| s.active).unwrap_or_default()
+    }
+
+    pub(crate) fn queued(&self) -> usize {
+        self.state.lock().map(|s| s.queued()).unwrap_or_default()
+    }
+
+    fn queued_by_priority(&self) -> [usize; 3] {
+        self.state
+            .lock()
+            .map(|s| s.waiters.each_ref().map(VecDeque::len))
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn rejected(&self) -> u64 {
+        self.state.lock().map(|s| s.rejected).unwrap_or_default()
+    }
+
+    pub(crate) fn max(&self) -> Option<usize> {
+        self.max
+    }
+
+    fn release(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        while let Some(queue) = state.next_queue(self.weights) {
+            let Some((_, sender)) = state.waiters[queue].pop_front() else {
+                continue;
+            };
+            // Hand the slot over; waiters that gave up have dropped their receiver
+            if sender.send(()).is_ok() {
+                return;
+            }
+        }
+        state.active = state.active.saturating_sub(1);
+    }
+}
+
+/// A slot held until dropped.
+pub(crate) struct Permit {
+    limiter: Arc<Limiter>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.limiter.release();
+    }
+}
+
+/// A queued request; gives its slot back if it is dropped after being granted.
+struct Waiter {
+    limiter: Arc<Limiter>,
+    queue: usize,
+    ticket: u64,
+    receiver: Option<oneshot::Receiver<()>>,
+}
+
+impl Waiter {
+    /// Leave the queue. Returns true when a slot had already been granted.
+    fn cancel(&mut self) -> bool {
+        let Some(mut receiver) = self.receiver.take() else {
+            return false;
+        };
+        let mut state = self.limiter.state.lock().unwrap_or_else(|e| e.into_inner());
+        let waiters = &mut state.waiters[self.queue];
+        if let Some(pos) = waiters.iter().position(|(t, _)| *t == self.ticket) {
+            waiters.remove(pos);
+            return false;
+        }
+        // Not queued anymore, so the slot was sent while we held the receiver
+        receiver.try_recv().is_ok()
+    }
+}
+
+impl Drop for Waiter {
+    fn drop(&mut self) {
+        if self.cancel() {
+            self.limiter.release();
+        }
+    }
+}
+
+/// Run and tool limiters of one agent.
+pub(crate) struct ConcurrencyLimits {
+    config: ConcurrencyConfig,
+    runs: Arc<Limiter>,
+    tools: Arc<Limiter>,
+}
+
+impl ConcurrencyLimits {
+    pub(crate) fn new(config: ConcurrencyConfig) -> Self {
+        Self {
+            runs: Limiter::new(config.max_concurrent_runs, config.priority_weights),
+            tools: Limiter::new(config.max_concurrent_tools, config.priority_weights),
+            config,
+        }
+    }
+
+    pub(crate) async fn acquire_run(
+        &self,
+        priority: RunPriority,
+    ) -> Result<Permit, ConcurrencyLimitError> {
+        self.runs
+            .acquire(
+                priority,
+                self.config.max_queued_runs,
+                self.config.queue_timeout,
+            )
+            .await
+    }
+
+    /// Tool executions wait without a timeout: the run already holds its slot.
+    pub(crate) async fn acquire_tool(&self, priority: RunPriority) -> Permit {
+        self.tools
+            .acquire(priority, None, None)
+            .await
+            .expect("tool slots are acquired without queue limits")
+    }
+
+    pub(crate) fn stats(&self) -> ConcurrencyStats {
+        let [queued_high, queued_normal, queued_low] = self.runs.queued_by_priority();
+        ConcurrencyStats {
+            active_runs: self.runs.active(),
+            queued_runs: self.runs.queued(),
+            queued_high,
+            queued_normal,
+            queued_low,
+            max_concurrent_runs: self.runs.max(),
+            active_tools: self.tools.active(),
+            queued_tools: self.tools.queued(),
+            max_concurrent_tools: self.tools.max(),
+            rejected_runs: self.runs.rejected(),
+        }
+    }
+}
+
+impl Default for ConcurrencyLimits {
+    fn default() -> Self {
+        Self::new(ConcurrencyConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{config::DeepAgentConfig, runtime::create_deep_agent_from_config};
+    use crate::test_support::FileWritingPlanner;
+
+    use agents_core::messaging::MessageRole;
+    use agents_core::persistence::{Checkpointer, InMemoryCheckpointer, ThreadId};
+
+    #[tokio::test]
+    async fn queued_runs_get_slots_in_order() {
+        let limiter = Limiter::new(Some(1), PriorityWeights::default());
+        let first = limiter
+            .acquire(RunPriority::Normal, None, None)
+            .await
+            .unwrap();
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut handles = Vec::new();
+        for i in 0..3 {
+            let limiter = limiter.clone();
+            let order = order.clone();
+            handles.push(tokio::spawn(async move {
+                let _permit = limiter
+                    .acquire(RunPriority::Normal, None, None)
+                    .await
+                    .unwrap();
+                order.lock().unwrap().push(i);
+            }));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(limiter.queued(), 3);
+
+        drop(first);
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), vec![0, 1, 2]);
+        assert_eq!(limiter.active(), 0);
+    }
+
+    #[tokio::test]
+    async fn rejects_when_queue_is_full_or_wait_times_out() {
+        let limits = ConcurrencyLimits::new(
+            ConcurrencyConfig::new()
+                .with_max_concurrent_runs(1)
+                .with_max_queued_runs(1)
+                .with_queue_timeout(Duration::from_millis(50)),
+        );
+        let _running = limits.acquire_run(RunPriority::Normal).await.unwrap();
+
+        let waiting = limits.acquire_run(RunPriority::Normal);
+        let rejected = async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            limits.acquire_run(RunPriority::Normal).await
+        };
+        let (waiting, rejected) = tokio::join!(waiting, rejected);
+
+        assert!(matches!(
+            waiting.err(),
+            Some(ConcurrencyLimitError::QueueTimeout { .. })
+        ));
+        assert_eq!(
+            rejected.err(),
+            Some(ConcurrencyLimitError::QueueFull { capacity: 1 })
+        );
+
+        let stats = limits.stats();
+        assert_eq!(stats.active_runs, 1);
+        assert_eq!(stats.queued_runs, 0);
+        assert_eq!(stats.rejected_runs, 2);
+    }
+
+    #[tokio::test]
+    async fn abandoned_waiters_do_not_leak_slots() {
+        let limiter = Limiter::new(Some(1), PriorityWeights::default());
+        let held = limiter
+            .acquire(RunPriority::Normal, None, None)
+            .await
+            .unwrap();
+
+        let abandoned = tokio::time::timeout(
+            Duration::from_millis(20),
+            limiter.acquire(RunPriority::Normal, None, None),
+        )
+        .await;
+        assert!(abandoned.is_err());
+        assert_eq!(limiter.queued(), 0);
+
+        drop(held);
+        assert_eq!(limiter.active(), 0);
+        let _again = limiter
+            .acquire(RunPriority::Normal, None, None)
+            .await
+            .unwrap();
+        assert_eq!(limiter.active(), 1);
+    }
+
+    #[tokio::test]
+    async fn freed_slots_follow_priority_weights() {
+        let limiter = Limiter::new(Some(1), PriorityWeights::new(2, 1, 1));
+        let held = limiter
+            .acquire(RunPriority::Normal, None, None)
+            .await
+            .unwrap();
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut handles = Vec::new();
+        let arrivals = [
+            ("low-1", RunPriority::Low),
+            ("low-2", RunPriority::Low),
+            ("low-3", RunPriority::Low),
+            ("high-1", RunPriority::High),
+            ("high-2", RunPriority::High),
+            ("high-3", RunPriority::High),
+        ];
+        for (name, priority) in arrivals {
+            let limiter = limiter.clone();
+            let order = order.clone();
+            handles.push(tokio::spawn(async move {
+                let _permit = limiter.acquire(priority, None, None).await.unwrap();
+                order.lock().unwrap().push(name);
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }));
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(limiter.queued_by_priority(), [3, 0, 3]);
+
+        drop(held);
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        // High runs go first, but low runs still get a share while highs wait
+        assert_eq!(
+            *order.lock().unwrap(),
+            vec!["high-1", "low-1", "high-2", "high-3", "low-2", "low-3"]
+        );
+    }
+
+    #[tokio::test]
+    async fn concurrent_thread_runs_keep_their_own_state() {
+        let checkpointer = Arc::new(InMemoryCheckpointer::new());
+        let agent = Arc::new(create_deep_agent_from_config(
+            DeepAgentConfig::new("assist", Arc::new(FileWritingPlanner))
+                .with_checkpointer(checkpointer.clone())
+                .with_concurrency_limits(ConcurrencyConfig::new().with_max_concurrent_runs(8)),
+        ));
+        assert_eq!(agent.concurrency_stats().max_concurrent_runs, Some(8));
+
+        let runs = ["alpha", "beta"].map(|name| {
+            let agent = agent.clone();
+            tokio::spawn(async move {
+                agent
+                    .handle_message_for_thread(&ThreadId::from(name), name)
+                    .await
+            })
+        });
+        for run in runs {
+            let reply = run.await.unwrap().unwrap();
+            assert_eq!(reply.content.as_text(), Some("done"));
+        }
+
+        for name in ["alpha", "beta"] {
+            let state = checkpointer
+                .load_state(&ThreadId::from(name))
+                .await
+                .unwrap()
+                .unwrap();
+            let files: Vec<&String> = state.files.keys().collect();
+            assert_eq!(files, vec![&format!("{name}.txt")]);
+            let users: Vec<_> = state
+                .messages
+                .iter()
+                .filter(|message| message.role == MessageRole::User)
+                .filter_map(|message| message.content.as_text())
+                .collect();
+            assert_eq!(users, vec![name]);
+        }
+    }
+}
@@ -3,11 +3,46 @@
 //! This module contains all the configuration structures used to build Deep Agents,
 //! including parameter structs that mirror the Python SDK API.
 
-use crate::middleware::{token_tracking::TokenTrackingConfig, AgentMiddleware, HitlPolicy};
-use crate::prompts::PromptFormat;
-use agents_core::agent::PlannerHandle;
+use super::approvals::ApprovalConfig;
+use super::citations::CitationConfig;
+use super::concurrency::ConcurrencyConfig;
+use super::deterministic::DeterministicConfig;
+use super::experiments::Experiment;
+use super::graph_memory::GraphMemoryConfig;
+use super::memory_policy::MemoryPolicy;
+use super::offload::OffloadConfig;
+use super::output_sink::OutputSinkConfig;
+use super::profile::Profile;
+use super::quotas::QuotaConfig;
+use super::state_size::StateSizeLimits;
+use super::stream_coalescing::StreamCoalescing;
+use super::thread_affinity::ThreadLockConfig;
+use super::thread_titles::ThreadTitleConfig;
+use super::tool_arguments::ToolArgumentValidation;
+use super::tool_conflicts::ToolConflictStrategy;
+use super::tool_examples::ToolExamplesConfig;
+use super::tool_mocks::ToolMocks;
+use super::tool_output::ToolOutputConfig;
+use super::tool_retry::ToolRetryConfig;
+use super::tool_selection::ToolSelectionConfig;
+use super::translation::TranslationConfig;
+use super::user_memory::UserMemoryConfig;
+use crate::locale::LocaleConfig;
+use crate::middleware::{
+    prompt_compression::PromptCompressionConfig,
+    time_context::TimeContextConfig,
+    token_tracking::{TokenTrackingConfig, TokenTrackingMiddleware},
+    user_profile::UserProfileConfig,
+    AgentMiddleware, HitlPolicy,
+};
+use crate::prompts::{PromptFormat, PromptSections};
+use agents_core::agent::{AgentCapabilities, AgentDescriptor, PlannerHandle};
+use agents_core::dead_letter::DeadLetterStore;
+use agents_core::outbox::EventOutbox;
 use agents_core::persistence::Checkpointer;
-use agents_core::tools::ToolBox;
+use agents_core::policy::PolicyProvider;
+use agents_core::search::ConversationIndex;
+use agents_core::tools::{ToolBox, ToolExtensions, ToolProvider};
 use std::collections::{HashMap, HashSet};
 use std::num::NonZeroUsize;
 use std::sync::Arc;
@@ -48,6 +83,8 @@ pub struct DeepAgentConfig {
     pub custom_system_prompt: Option<String>,
     /// Format for tool call examples in the system prompt (JSON or TOON)
     pub prompt_format: PromptFormat,
+    /// Order and content of the system prompt sections; `None` keeps the default prompt
+    pub prompt_sections: Option<PromptSections>,
     pub planner: Arc<dyn PlannerHandle>,
     pub tools: Vec<ToolBox>,
     pub subagent_configs: Vec<SubAgentConfig>,
@@ -60,7 +97,90 @@ pub struct DeepAgentConfig {
     pub event_dispatcher: Option<Arc<agents_core::events::EventDispatcher>>,
     pub enable_pii_sanitization: bool,
     pub token_tracking_config: Option<TokenTrackingConfig>,
+    /// Tracker wrapping the planner's model; sub-agents, summarizers and tool
+    /// executions are recorded into it for per-component cost attribution
+    pub usage_tracker: Option<Arc<TokenTrackingMiddleware>>,
     pub max_iterations: NonZeroUsize,
+    /// Time the phases of each run into its report
+    pub profiling: bool,
+    /// Record the prompt, state and decision of each planner iteration
+    pub step_recording: bool,
+    /// Load thread state without file contents, reading files on demand
+    pub lazy_file_loading: bool,
+    /// Budgets applied to tool outputs before they enter the conversation history
+    pub tool_output: Option<ToolOutputConfig>,
+    /// Environment profile the agent was built for
+    pub profile: Profile,
+    /// Tools removed by the profile, whatever their source
+    pub disabled_tools: HashSet<String>,
+    /// Tools answered by mocks under the profile
+    pub tool_mocks: ToolMocks,
+    /// Log the arguments of each tool call
+    pub log_tool_payloads: bool,
+    /// Retries of failed tool calls
+    pub tool_retry: Option<ToolRetryConfig>,
+    /// Requirement that final answers reference the run's tool results
+    pub citations: Option<CitationConfig>,
+    /// Per-turn selection of the most relevant tool schemas
+    pub tool_selection: Option<ToolSelectionConfig>,
+    /// Rendering of the few-shot examples attached to tool schemas
+    pub tool_examples: ToolExamplesConfig,
+    /// Whether tool arguments are checked against the tool's schema before execution
+    pub tool_argument_validation: ToolArgumentValidation,
+    /// Handling of registered tools named like a built-in tool
+    pub tool_conflicts: ToolConflictStrategy,
+    /// Shared dependencies exposed to tools through `ToolContext::extension`
+    pub tool_extensions: ToolExtensions,
+    /// Name, description and declared capabilities reported by `describe()`
+    pub descriptor: AgentDescriptor,
+    /// Locale, fallback chain and localized prompt packs
+    pub locale: Option<LocaleConfig>,
+    /// Current date/time injected into every request, plus the time tools
+    pub time_context: Option<TimeContextConfig>,
+    /// Seed and frozen time making runs reproducible
+    pub deterministic: Option<DeterministicConfig>,
+    /// Compression of older messages before each model call
+    pub prompt_compression: Option<PromptCompressionConfig>,
+    /// Profile of the user extracted from their messages and added to the prompt
+    pub user_profile: Option<UserProfileConfig>,
+    /// Store of memories shared by the threads of a user
+    pub user_memory: Option<UserMemoryConfig>,
+    /// Categories of user facts that are blocked, need consent or expire
+    pub memory_policy: Option<MemoryPolicy>,
+    /// Thresholds past which a thread's checkpointed state is reported
+    pub state_size_limits: Option<StateSizeLimits>,
+    /// Blob storage taking messages too large to keep in the history
+    pub offload: Option<OffloadConfig>,
+    /// Sink receiving long final responses in place of the chat history
+    pub output_sink: Option<OutputSinkConfig>,
+    /// Translation of user messages into the working language and of responses back
+    pub translation: Option<TranslationConfig>,
+    /// How text deltas are merged in `handle_message_stream`
+    pub stream_coalescing: Option<StreamCoalescing>,
+    /// Caps on concurrent runs and tool executions
+    pub concurrency: Option<ConcurrencyConfig>,
+    /// Per-thread leases held by `handle_message_for_thread`
+    pub thread_lock: Option<ThreadLockConfig>,
+    /// Variants threads run with `handle_message_for_thread` are split between
+    pub experiment: Option<Experiment>,
+    /// Store for events saved together with the state; also used as the checkpointer
+    pub event_outbox: Option<Arc<dyn EventOutbox>>,
+    /// Store recording interrupts of thread runs as pending approvals
+    pub approvals: Option<ApprovalConfig>,
+    /// Policy engine deciding whether tool calls run, are denied or need approval
+    pub policy_provider: Option<Arc<dyn PolicyProvider>>,
+    /// Store receiving runs that failed, for inspection and replay
+    pub dead_letters: Option<Arc<dyn DeadLetterStore>>,
+    /// Full-text index fed the messages of every checkpointed thread turn
+    pub conversation_index: Option<Arc<dyn ConversationIndex>>,
+    /// Model-written titles and summaries stored in thread metadata
+    pub thread_titles: Option<ThreadTitleConfig>,
+    /// Knowledge graph fed the facts of every checkpointed thread turn
+    pub graph_memory: Option<GraphMemoryConfig>,
+    /// Daily and monthly quotas of the tenants runs are served for
+    pub quotas: Option<QuotaConfig>,
+    /// Sources of tools loaded at the start of each run, e.g. MCP servers
+    pub tool_providers: Vec<Arc<dyn ToolProvider>>,
 }
 
 impl DeepAgentConfig {
@@ -69,6 +189,7 @@ impl DeepAgentConfig {
             instructions: instructions.into(),
             custom_system_prompt: None,
             prompt_format: PromptFormat::default(),
+            prompt_sections: None,
             planner,
             tools: Vec::new(),
             subagent_configs: Vec::new(),
@@ -81,7 +202,49 @@ impl DeepAgentConfig {
             event_dispatcher: None,
             enable_pii_sanitization: true, // Enabled by default for security
             token_tracking_config: None,
+            usage_tracker: None,
             max_iterations: NonZeroUsize::new(10).unwrap(),
+            profiling: false,
+            step_recording: false,
+            lazy_file_loading: false,
+            tool_output: None,
+            profile: Profile::default(),
+            disabled_tools: HashSet::new(),
+            tool_mocks: ToolMocks::new(),
+            log_tool_payloads: true,
+            tool_retry: None,
+            citations: None,
+            tool_selection: None,
+            tool_examples: ToolExamplesConfig::default(),
+            tool_argument_validation: ToolArgumentValidation::default(),
+            tool_conflicts: ToolConflictStrategy::default(),
+            tool_extensions: ToolExtensions::default(),
+            descriptor: AgentDescriptor::new("deep-agent", "0.0.1")
+                .with_description("Rust deep agent"),
+            locale: None,
+            time_context: None,
+            deterministic: None,
+            prompt_compression: None,
+            user_profile: None,
+            user_memory: None,
+            memory_policy: None,
+            state_size_limits: None,
+            offload: None,
+            output_sink: None,
+            translation: None,
+            stream_coalescing: None,
+            concurrency: None,
+            thread_lock: None,
+            experiment: None,
+            event_outbox: None,
+            approvals: None,
+            policy_provider: None,
+            dead_letters: None,
+            conversation_index: None,
+            thread_titles: None,
+            graph_memory: None,
+            quotas: None,
+            tool_providers: Vec::new(),
         }
     }
 
@@ -104,6 +267,13 @@ impl DeepAgentConfig {
         self
     }
 
+    /// Assemble the system prompt from `sections`, reordering, dropping or replacing
+    /// the built-in ones.
+    pub fn with_prompt_sections(mut self, sections: PromptSections) -> Self {
+        self.prompt_sections = Some(sections);
+        self
+    }
+
     pub fn with_tool(mut self, tool: ToolBox) -> Self {
         self.tools.push(tool);
         self
@@ -203,12 +373,41 @@ impl DeepAgentConfig {
         self
     }
 
+    /// Time the phases of each run (prompt build, serialization, provider, tools,
+    /// persistence) into its report.
+    pub fn with_profiling(mut self, enabled: bool) -> Self {
+        self.profiling = enabled;
+        self
+    }
+
+    /// Record the prompt, state and decision of each planner iteration in the run
+    /// journal, for stepping through runs with a `RunDebugger`.
+    pub fn with_step_recording(mut self, enabled: bool) -> Self {
+        self.step_recording = enabled;
+        self
+    }
+
+    /// Load thread state without file contents and read files from the checkpointer
+    /// when tools need them.
+    pub fn with_lazy_file_loading(mut self, enabled: bool) -> Self {
+        self.lazy_file_loading = enabled;
+        self
+    }
+
     /// Configure token tracking for monitoring LLM usage and costs.
     pub fn with_token_tracking_config(mut self, config: TokenTrackingConfig) -> Self {
         self.token_tracking_config = Some(config);
         self
     }
 
+    /// Attribute usage to components through the tracker wrapping the planner's model.
+    ///
+    /// `ConfigurableAgentBuilder` sets this when token tracking is enabled.
+    pub fn with_usage_tracker(mut self, tracker: Arc<TokenTrackingMiddleware>) -> Self {
+        self.usage_tracker = Some(tracker);
+        self
+    }
+
     /// Set the maximum number of ReAct loop iterations before stopping.
     ///
     /// **Note**: `max_iterations` must be greater than 0. Passing 0 will result in a panic.
@@ -225,6 +424,251 @@ impl DeepAgentConfig {
             NonZeroUsize::new(max_iterations).expect("max_iterations must be greater than 0");
         self
     }
+
+    /// Limit how much of each tool's output is inserted into the conversation history.
+    ///
+    /// Oversized outputs are truncated or summarized and the full text is stored in the
+    /// agent's virtual filesystem for later retrieval.
+    pub fn with_tool_output_limits(mut self, config: ToolOutputConfig) -> Self {
+        self.tool_output = Some(config);
+        self
+    }
+
+    /// Retry failed tool calls with backoff and hand exhausted failures back to the planner.
+    pub fn with_tool_retries(mut self, config: ToolRetryConfig) -> Self {
+        self.tool_retry = Some(config);
+        self
+    }
+
+    /// Send final answers that reference none of the run's tool results back to the
+    /// planner with corrective instructions.
+    pub fn with_citations(mut self, config: CitationConfig) -> Self {
+        self.citations = Some(config);
+        self
+    }
+
+    /// Only expose the top-K most relevant tool schemas (plus pinned tools) per turn.
+    pub fn with_tool_selection(mut self, config: ToolSelectionConfig) -> Self {
+        self.tool_selection = Some(config);
+        self
+    }
+
+    /// Control how few-shot tool examples are rendered into the system prompt.
+    pub fn with_tool_examples(mut self, config: ToolExamplesConfig) -> Self {
+        self.tool_examples = config;
+        self
+    }
+
+    /// Check tool arguments against the tool's schema and return mismatches to the model.
+    pub fn with_tool_argument_validation(mut self, mode: ToolArgumentValidation) -> Self {
+        self.tool_argument_validation = mode;
+        self
+    }
+
+    /// Decide which tool is exposed when a registered tool is named like a built-in one.
+    pub fn with_tool_conflicts(mut self, strategy: ToolConflictStrategy) -> Self {
+        self.tool_conflicts = strategy;
+        self
+    }
+
+    /// Register a shared dependency that tools can read via `ToolContext::extension::<T>()`.
+    pub fn with_tool_state<T: Send + Sync + 'static>(mut self, value: T) -> Self {
+        self.tool_extensions.insert(value);
+        self
+    }
+
+    /// Replace all shared tool dependencies at once.
+    pub fn with_tool_extensions(mut self, extensions: ToolExtensions) -> Self {
+        self.tool_extensions = extensions;
+        self
+    }
+
+    /// Set the name, description and declared capabilities reported by `describe()`.
+    ///
+    /// Tools, sub-agents, streaming and HITL support are filled in by the runtime.
+    pub fn with_descriptor(mut self, descriptor: AgentDescriptor) -> Self {
+        self.descriptor = descriptor;
+        self
+    }
+
+    /// Declare capabilities the runtime cannot detect (vision, languages).
+    pub fn with_capabilities(mut self, capabilities: AgentCapabilities) -> Self {
+        self.descriptor.capabilities = capabilities;
+        self
+    }
+
+    /// Localize the system prompt, response language and tool descriptions.
+    pub fn with_locale_config(mut self, locale: LocaleConfig) -> Self {
+        self.locale = Some(locale);
+        self
+    }
+
+    /// Tell the model the current date and time and expose the time tools.
+    pub fn with_time_context(mut self, time_context: TimeContextConfig) -> Self {
+        self.time_context = Some(time_context);
+        self
+    }
+
+    /// Run with a fixed seed and frozen time.
+    pub fn with_deterministic_mode(mut self, deterministic: DeterministicConfig) -> Self {
+        self.deterministic = Some(deterministic);
+        self
+    }
+
+    /// Compress older messages before they are sent to the model.
+    pub fn with_prompt_compression(mut self, compression: PromptCompressionConfig) -> Self {
+        self.prompt_compression = Some(compression);
+        self
+    }
+
+    /// Build a profile of the user from their messages and personalize the prompt with it.
+    pub fn with_user_profile(mut self, config: UserProfileConfig) -> Self {
+        self.user_profile = Some(config);
+        self
+    }
+
+    /// Share what is learned about a user between their threads.
+    pub fn with_user_memory(mut self, config: UserMemoryConfig) -> Self {
+        self.user_memory = Some(config);
+        self
+    }
+
+    /// Review user facts against a policy before they are remembered.
+    pub fn with_memory_policy(mut self, policy: MemoryPolicy) -> Self {
+        self.memory_policy = Some(policy);
+        self
+    }
+
+    /// Report threads whose state grows past the limits.
+    pub fn with_state_size_limits(mut self, limits: StateSizeLimits) -> Self {
+        self.state_size_limits = Some(limits);
+        self
+    }
+
+    /// Offload messages above the threshold to blob storage.
+    pub fn with_message_offloading(mut self, offload: OffloadConfig) -> Self {
+        self.offload = Some(offload);
+        self
+    }
+
+    /// Stream long final responses to a sink and keep a short confirmation in the chat.
+    pub fn with_output_sink(mut self, output_sink: OutputSinkConfig) -> Self {
+        self.output_sink = Some(output_sink);
+        self
+    }
+
+    /// Run the agent in a working language, translating user messages and responses.
+    pub fn with_translation(mut self, translation: TranslationConfig) -> Self {
+        self.translation = Some(translation);
+        self
+    }
+
+    /// Merge streamed text deltas into larger chunks.
+    pub fn with_stream_coalescing(mut self, coalescing: StreamCoalescing) -> Self {
+        self.stream_coalescing = Some(coalescing);
+        self
+    }
+
+    /// Limit concurrent runs and tool executions of this agent.
+    pub fn with_concurrency_limits(mut self, concurrency: ConcurrencyConfig) -> Self {
+        self.concurrency = Some(concurrency);
+        self
+    }
+
+    /// Hold a per-thread lease while `handle_message_for_thread` runs.
+    pub fn with_thread_lock(mut self, thread_lock: ThreadLockConfig) -> Self {
+        self.thread_lock = Some(thread_lock);
+        self
+    }
+
+    /// Assign threads to the variants of an A/B experiment.
+    pub fn with_experiment(mut self, experiment: Experiment) -> Self {
+        self.experiment = Some(experiment);
+        self
+    }
+
+    /// Persist events together with the state and deliver them through an outbox relay.
+    ///
+    /// The outbox also becomes the agent's checkpointer.
+    pub fn with_event_outbox(mut self, outbox: Arc<dyn EventOutbox>) -> Self {
+        self.checkpointer = Some(outbox.clone());
+        self.event_outbox = Some(outbox);
+        self
+    }
+
+    /// Record interrupts of `handle_message_for_thread` runs as pending approvals.
+    pub fn with_approvals(mut self, approvals: ApprovalConfig) -> Self {
+        self.approvals = Some(approvals);
+        self
+    }
+
+    /// Record the environment profile the agent is built for.
+    pub fn with_profile(mut self, profile: Profile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Remove `tools` from the agent, including built-in and provided tools.
+    pub fn with_disabled_tools(mut self, tools: HashSet<String>) -> Self {
+        self.disabled_tools = tools;
+        self
+    }
+
+    /// Answer the calls of the tools in `mocks` with their mocks, including built-in
+    /// and provided tools.
+    pub fn with_tool_mocks(mut self, mocks: ToolMocks) -> Self {
+        self.tool_mocks = mocks;
+        self
+    }
+
+    /// Log the arguments of each tool call (enabled by default).
+    pub fn with_tool_payload_logging(mut self, enabled: bool) -> Self {
+        self.log_tool_payloads = enabled;
+        self
+    }
+
+    /// Submit tool calls to `provider` before they run.
+    pub fn with_policy_provider(mut self, provider: Arc<dyn PolicyProvider>) -> Self {
+        self.policy_provider = Some(provider);
+        self
+    }
+
+    /// Record failed runs as dead letters in `store`.
+    pub fn with_dead_letter_store(mut self, store: Arc<dyn DeadLetterStore>) -> Self {
+        self.dead_letters = Some(store);
+        self
+    }
+
+    /// Index the messages of thread turns in `index` for `DeepAgent::search_threads`.
+    pub fn with_conversation_index(mut self, index: Arc<dyn ConversationIndex>) -> Self {
+        self.conversation_index = Some(index);
+        self
+    }
+
+    /// Title and summarize threads in their metadata as they progress.
+    pub fn with_thread_titles(mut self, config: ThreadTitleConfig) -> Self {
+        self.thread_titles = Some(config);
+        self
+    }
+
+    /// Extract facts from thread turns into a knowledge graph. Register the graph's
+    /// query tools separately, e.g. with `GraphMemoryConfig::tools`.
+    pub fn with_graph_memory(mut self, config: GraphMemoryConfig) -> Self {
+        self.graph_memory = Some(config);
+        self
+    }
+
+    /// Enforce per-tenant quotas on runs started with a tenant.
+    pub fn with_quotas(mut self, config: QuotaConfig) -> Self {
+        self.quotas = Some(config);
+        self
+    }
+
+    /// Add tools from `provider`, loaded at the start of each run.
+    pub fn with_tool_provider(mut self, provider: Arc<dyn ToolProvider>) -> Self {
+        self.tool_providers.push(provider);
+        self
+    }
 }
 
 /// Configuration for creating and registering a subagent using a simple, Python-like shape.
@@ -411,4 +855,45 @@ mod tests {
         assert!(!config3.auto_general_purpose);
         assert!(config3.enable_pii_sanitization);
     }
+
+    #[tokio::test]
+    async fn test_describe_reports_capabilities() {
+        use agents_core::agent::AgentHandle;
+
+        let planner = create_mock_planner();
+        let config = DeepAgentConfig::new("test instructions", planner)
+            .with_descriptor(
+                AgentDescriptor::new("support", "1.2.0").with_description("Support agent"),
+            )
+            .with_capabilities(AgentCapabilities::default().with_language("ar-AE"))
+            .with_builtin_tools(["write_todos"]);
+        let agent = super::super::runtime::create_deep_agent_from_config(config);
+
+        let descriptor = agent.describe().await;
+        assert_eq!(descriptor.name, "support");
+        assert_eq!(descriptor.version, "1.2.0");
+        let capabilities = descriptor.capabilities;
+        assert!(capabilities.supports("streaming"));
+        assert!(capabilities.supports("tool:write_todos"));
+        assert!(capabilities.supports("subagent:general-purpose"));
+        assert!(capabilities.supports("language:ar"));
+        assert_eq!(
+            capabilities.missing(&["vision", "hitl"]),
+            vec!["vision", "hitl"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_time_context_adds_time_tools() {
+        use agents_core::agent::AgentHandle;
+
+        let planner = create_mock_planner();
+        let config = DeepAgentConfig::new("test instructions", planner)
+            .with_time_context(TimeContextConfig::for_timezone("Asia/Dubai").unwrap());
+        let agent = super::super::runtime::create_deep_agent_from_config(config);
+
+        let capabilities = agent.describe().await.capabilities;
+        assert!(capabilities.supports("tool:get_current_time"));
+        assert!(capabilities.supports("tool:date_math"));
+    }
 }
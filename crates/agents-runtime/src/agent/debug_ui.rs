@@ -0,0 +1,418 @@
+//! Local dashboard for watching an agent during development
+//!
+//! With the `debug-ui` feature, [`DebugUi`] serves a single-page dashboard and a small
+//! JSON API from an axum router: the threads saved by the checkpointer with their
+//! state, the HITL approvals still pending, recent runs with their reports and traces,
+//! and a live stream of agent events over a WebSocket.
+//!
+//! The dashboard has no authentication and shows full conversation state, so bind it
+//! to localhost only. It only answers requests addressed to a loopback host, from
+//! its own pages, so other sites can neither read it through DNS rebinding nor open
+//! its WebSocket.
+
+use super::runtime::DeepAgent;
+use agents_core::events::{AgentEvent, EventBroadcaster};
+use agents_core::hitl::AgentInterrupt;
+use async_trait::async_trait;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+const DASHBOARD: &str = include_str!("debug_ui.html");
+
+/// Events buffered for each dashboard before the oldest are dropped
+const EVENT_BUFFER: usize = 256;
+
+/// Local debugging dashboard for a [`DeepAgent`].
+///
+/// Live events need an event dispatcher on the agent, i.e. any
+/// `with_event_broadcaster` or `with_event_dispatcher` call on the builder; threads,
+/// state and approvals need a checkpointer.
+///
+/// ```ignore
+/// let agent = Arc::new(
+///     ConfigurableAgentBuilder::new("You are a helpful assistant")
+///         .with_model(model)
+///         .with_checkpointer(Arc::new(InMemoryCheckpointer::new()))
+///         .with_event_dispatcher(Arc::new(EventDispatcher::new()))
+///         .build()?,
+/// );
+///
+/// // Open http://127.0.0.1:4000
+/// tokio::spawn(DebugUi::new(agent.clone()).serve("127.0.0.1:4000"));
+///
+/// // Or mount it in an existing app
+/// let app = app.nest("/debug", DebugUi::new(agent.clone()).router());
+/// ```
+#[derive(Clone)]
+pub struct DebugUi {
+    agent: Arc<DeepAgent>,
+    events: broadcast::Sender<AgentEvent>,
+}
+
+impl DebugUi {
+    /// Dashboard for `agent`, streaming the events it emits from now on.
+    pub fn new(agent: Arc<DeepAgent>) -> Self {
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        agent.add_broadcaster(Arc::new(DebugUiBroadcaster {
+            events: events.clone(),
+        }));
+        Self { agent, events }
+    }
+
+    /// Routes of the dashboard, to serve on their own or nest in an existing app.
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route("/", get(dashboard))
+            .route("/api/threads", get(threads))
+            .route("/api/threads/:thread_id", get(thread_state))
+            .route("/api/approvals", get(approvals))
+            .route("/api/runs", get(runs))
+            .route("/api/runs/:run_id", get(run))
+            .route("/ws", get(events))
+            .layer(middleware::from_fn(local_only))
+            .with_state(self.clone())
+    }
+
+    /// Serve the dashboard on `addr` until the task is dropped.
+    pub async fn serve(self, addr: impl tokio::net::ToSocketAddrs) -> anyhow::Result<()> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        tracing::info!(address = %listener.local_addr()?, "Debug UI listening");
+        axum::serve(listener, self.router()).await?;
+        Ok(())
+    }
+}
+
+/// Forwards the agent's events to the connected dashboards.
+struct DebugUiBroadcaster {
+    events: broadcast::Sender<AgentEvent>,
+}
+
+#[async_trait]
+impl EventBroadcaster for DebugUiBroadcaster {
+    fn id(&self) -> &str {
+        "debug-ui"
+    }
+
+    async fn broadcast(&self, event: &AgentEvent) -> anyhow::Result<()> {
+        // Fails only while no dashboard is connected
+        let _ = self.events.send(event.clone());
+        Ok(())
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+}
+
+/// Reject requests for other hosts, which reach the dashboard through DNS
+/// rebinding, and requests from other origins, e.g. a page opening the WebSocket.
+async fn local_only(request: Request, next: Next) -> Response {
+    let headers = request.headers();
+    let host = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .unwrap_or_default();
+    let same_origin = headers.get(header::ORIGIN).is_none_or(|origin| {
+        origin
+            .to_str()
+            .ok()
+            .and_then(|origin| origin.split_once("://"))
+            .is_some_and(|(_, authority)| authority.eq_ignore_ascii_case(host))
+    });
+    if !is_loopback_host(host) || !same_origin {
+        tracing::warn!(
+            host,
+            "Rejected debug UI request from another host or origin"
+        );
+        return (StatusCode::FORBIDDEN, "Forbidden").into_response();
+    }
+    next.run(request).await
+}
+
+/// Whether a `Host` header names this machine: `localhost` or a loopback address.
+fn is_loopback_host(host: &str) -> bool {
+    let name = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => host.rsplit_once(':').map_or(host, |(name, _)| name),
+    };
+    name.eq_ignore_ascii_case("localhost")
+        || name
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+async fn dashboard() -> Html<&'static str> {
+    Html(DASHBOARD)
+}
+
+async fn threads(State(ui): State<DebugUi>) -> Response {
+    match ui.agent.list_threads().await {
+        Ok(mut threads) => {
+            threads.sort();
+            Json(threads).into_response()
+        }
+        Err(e) => internal_error(e),
+    }
+}
+
+async fn thread_state(State(ui): State<DebugUi>, Path(thread_id): Path<String>) -> Response {
+    let Some(checkpointer) = ui.agent.checkpointer() else {
+        return not_found("No checkpointer configured");
+    };
+    match checkpointer.load_state(&thread_id).await {
+        Ok(Some(state)) => Json(state).into_response(),
+        Ok(None) => not_found("Unknown thread"),
+        Err(e) => internal_error(e),
+    }
+}
+
+async fn approvals(State(ui): State<DebugUi>) -> Response {
+    match pending_approvals(&ui.agent).await {
+        Ok(approvals) => Json(approvals).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// Interrupts pending in saved threads, with their approval record when an
+/// approval store is configured.
+async fn pending_approvals(agent: &DeepAgent) -> anyhow::Result<Vec<Value>> {
+    let Some(checkpointer) = agent.checkpointer() else {
+        return Ok(Vec::new());
+    };
+    let mut pending = Vec::new();
+    for thread_id in checkpointer.list_threads().await? {
+        let Some(state) = checkpointer.load_state(&thread_id).await? else {
+            continue;
+        };
+        for AgentInterrupt::HumanInLoop(interrupt) in state.pending_interrupts {
+            let approval = match agent.approval_config() {
+                Some(config) => config.store.get(&thread_id, &interrupt.call_id).await?,
+                None => None,
+            };
+            pending.push(json!({
+                "thread_id": thread_id,
+                "interrupt": interrupt,
+                "approval": approval,
+            }));
+        }
+    }
+    Ok(pending)
+}
+
+async fn runs(State(ui): State<DebugUi>) -> Json<Vec<Value>> {
+    let journal = ui.agent.journal();
+    let runs = journal
+        .run_ids()
+        .into_iter()
+        .rev()
+        .filter_map(|run_id| journal.report(&run_id))
+        .map(|report| {
+            json!({
+                "run_id": report.run_id,
+                "agent_name": report.agent_name,
+                "started_at": report.started_at,
+                "duration_ms": report.duration_ms,
+                "request": report.request,
+                "tool_calls": report.tool_calls.len(),
+                "failed_tool_calls": report.failed_tool_calls(),
+            })
+        })
+        .collect();
+    Json(runs)
+}
+
+async fn run(State(ui): State<DebugUi>, Path(run_id): Path<String>) -> Response {
+    let journal = ui.agent.journal();
+    match (journal.report(&run_id), journal.trace(&run_id)) {
+        (Some(report), Some(trace)) => Json(json!({
+            "markdown": report.to_markdown(),
+            "mermaid": trace.to_mermaid(),
+            "report": report,
+            "trace": trace,
+        }))
+        .into_response(),
+        _ => not_found("Unknown run"),
+    }
+}
+
+async fn events(State(ui): State<DebugUi>, ws: WebSocketUpgrade) -> Response {
+    let receiver = ui.events.subscribe();
+    ws.on_upgrade(move |socket| stream_events(socket, receiver))
+}
+
+async fn stream_events(mut socket: WebSocket, mut events: broadcast::Receiver<AgentEvent>) {
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                tracing::debug!(skipped, "Debug UI client fell behind, events dropped");
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        let Ok(text) = event.to_wire_json() else {
+            continue;
+        };
+        if socket.send(Message::Text(text)).await.is_err() {
+            break;
+        }
+    }
+}
+
+fn not_found(message: &str) -> Response {
+    (StatusCode::NOT_FOUND, Json(json!({ "error": message }))).into_response()
+}
+
+fn internal_error(error: anyhow::Error) -> Response {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({ "error": error.to_string() })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{config::DeepAgentConfig, runtime::create_deep_agent_from_config};
+    use crate::test_support::ReplyPlanner;
+
+    use agents_core::events::EventDispatcher;
+    use agents_core::hitl::HitlInterrupt;
+
+    use agents_core::persistence::{Checkpointer, InMemoryCheckpointer};
+    use agents_core::state::AgentStateSnapshot;
+
+    #[tokio::test]
+    async fn dashboard_serves_threads_approvals_runs_and_events() {
+        let checkpointer = Arc::new(InMemoryCheckpointer::new());
+        let paused = AgentStateSnapshot {
+            pending_interrupts: vec![AgentInterrupt::HumanInLoop(HitlInterrupt::new(
+                "issue_refund",
+                json!({ "amount": 40 }),
+                "call-1",
+                None,
+            ))],
+            ..Default::default()
+        };
+        checkpointer
+            .save_state(&"refunds".to_string(), &paused)
+            .await
+            .unwrap();
+        let agent = Arc::new(create_deep_agent_from_config(
+            DeepAgentConfig::new("assist", Arc::new(ReplyPlanner("pong")))
+                .with_checkpointer(checkpointer)
+                .with_event_dispatcher(Arc::new(EventDispatcher::new())),
+        ));
+
+        let ui = DebugUi::new(agent.clone());
+        let mut live = ui.events.subscribe();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let router = ui.router();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        agent
+            .handle_message("ping", Arc::new(AgentStateSnapshot::default()))
+            .await
+            .unwrap();
+        assert!(matches!(
+            live.recv().await.unwrap(),
+            AgentEvent::AgentStarted(_)
+        ));
+
+        let client = reqwest::Client::new();
+        let get = |path: &str| client.get(format!("{}{}", base, path)).send();
+
+        let page = get("/").await.unwrap().text().await.unwrap();
+        assert!(page.contains("<title>Agent Debugger</title>"));
+
+        let threads: Value = get("/api/threads").await.unwrap().json().await.unwrap();
+        assert_eq!(threads, json!(["refunds"]));
+        let state: Value = get("/api/threads/refunds")
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(
+            state["pending_interrupts"][0]["tool_name"],
+            json!("issue_refund")
+        );
+        assert_eq!(
+            get("/api/threads/missing").await.unwrap().status(),
+            StatusCode::NOT_FOUND.as_u16()
+        );
+
+        let approvals: Value = get("/api/approvals").await.unwrap().json().await.unwrap();
+        assert_eq!(approvals[0]["thread_id"], json!("refunds"));
+        assert_eq!(approvals[0]["interrupt"]["call_id"], json!("call-1"));
+        assert!(approvals[0]["approval"].is_null());
+
+        let runs: Value = get("/api/runs").await.unwrap().json().await.unwrap();
+        assert_eq!(runs[0]["request"], json!("ping"));
+        let run_id = runs[0]["run_id"].as_str().unwrap();
+        let run: Value = get(&format!("/api/runs/{}", run_id))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert!(run["mermaid"]
+            .as_str()
+            .unwrap()
+            .starts_with("sequenceDiagram"));
+        assert_eq!(run["report"]["response"], json!("pong"));
+
+        // Rebound hostnames and other sites' pages are turned away
+        let rebound = client
+            .get(format!("{}/api/threads/refunds", base))
+            .header("host", "attacker.example:4000")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(rebound.status(), StatusCode::FORBIDDEN.as_u16());
+        let cross_site = client
+            .get(format!("{}/ws", base))
+            .header("origin", "https://attacker.example")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(cross_site.status(), StatusCode::FORBIDDEN.as_u16());
+        let own_page = client
+            .get(format!("{}/api/threads", base))
+            .header("origin", &base)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(own_page.status(), StatusCode::OK.as_u16());
+    }
+
+    #[test]
+    fn loopback_hosts_are_recognized() {
+        for host in [
+            "localhost:4000",
+            "127.0.0.1",
+            "127.0.0.1:4000",
+            "[::1]:4000",
+        ] {
+            assert!(is_loopback_host(host), "{host}");
+        }
+        for host in [
+            "attacker.example",
+            "10.0.0.5:4000",
+            "localhost.attacker.example",
+            "",
+        ] {
+            assert!(!is_loopback_host(host), "{host}");
+        }
+    }
+}
@@ -0,0 +1,284 @@
+//! Time-travel debugging of recorded runs
+//!
+//! With [step recording](super::ConfigurableAgentBuilder::with_step_recording) on,
+//! the run journal keeps every planner iteration of a run: the exact prompt the
+//! planner received (system prompt, messages and tool schemas), the state it saw,
+//! its decision and what the tool it called returned. A [`RunDebugger`] loads such a
+//! [`RunRecording`], from the agent or from JSON saved earlier, and steps back and
+//! forth through the iterations.
+//!
+//! To see how a run would have gone otherwise, edit the result of one of its tool
+//! calls and diverge: the agent continues from that iteration with the edited
+//! result, calling its model and tools again. Diverge on an agent built with
+//! [`ToolMocks`](super::ToolMocks) for side-effecting tools so a replay doesn't
+//! repeat payments or notifications.
+//!
+//! ```ignore
+//! let recording = agent.run_recording(&run_id).unwrap();
+//! std::fs::write("run.json", serde_json::to_string(&recording)?)?;
+//!
+//! let mut debugger = RunDebugger::new(serde_json::from_str(&std::fs::read_to_string("run.json")?)?);
+//! while let Some(step) = debugger.current() {
+//!     println!("{}", step.render_prompt());
+//!     debugger.step_forward();
+//! }
+//!
+//! debugger.seek(2);
+//! let reply = debugger
+//!     .diverge(&staging_agent, MessageContent::Text("No results found".into()))
+//!     .await?;
+//! ```
+
+use super::prompt_snapshot::PromptSnapshot;
+use super::runtime::DeepAgent;
+use agents_core::agent::{PlannerAction, PlannerContext};
+use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+use agents_core::state::AgentStateSnapshot;
+use serde::{Deserialize, Serialize};
+
+/// One planner iteration of a recorded run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedStep {
+    /// Iteration number, starting at 1
+    pub iteration: usize,
+    /// Prompt the planner received: system prompt, messages and tool schemas
+    pub context: PlannerContext,
+    /// State the planner saw
+    pub state: AgentStateSnapshot,
+    pub action: PlannerAction,
+    /// Message the tool call added to the history: its result or error
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_result: Option<AgentMessage>,
+}
+
+impl RecordedStep {
+    /// The step's system prompt and tool schemas.
+    pub fn prompt_snapshot(&self) -> PromptSnapshot {
+        PromptSnapshot {
+            system_prompt: self.context.system_prompt.clone(),
+            tools: self.context.tools.clone(),
+        }
+    }
+
+    /// The prompt of the step as Markdown: system prompt, tools, then messages.
+    pub fn render_prompt(&self) -> String {
+        let mut text = self.prompt_snapshot().render();
+        text.push_str("\n# Messages\n");
+        for message in &self.context.history {
+            text.push_str(&format!(
+                "\n## {:?}\n\n{}\n",
+                message.role,
+                message.content.to_text().trim()
+            ));
+        }
+        text
+    }
+}
+
+/// The recorded iterations of a run, in order.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunRecording {
+    pub run_id: String,
+    pub steps: Vec<RecordedStep>,
+}
+
+/// Cursor over the iterations of a [`RunRecording`].
+#[derive(Debug, Clone)]
+pub struct RunDebugger {
+    recording: RunRecording,
+    position: usize,
+}
+
+impl RunDebugger {
+    /// Start at the first iteration of `recording`.
+    pub fn new(recording: RunRecording) -> Self {
+        Self {
+            recording,
+            position: 0,
+        }
+    }
+
+    pub fn recording(&self) -> &RunRecording {
+        &self.recording
+    }
+
+    /// The iteration under the cursor; `None` for a recording without steps.
+    pub fn current(&self) -> Option<&RecordedStep> {
+        self.recording.steps.get(self.position)
+    }
+
+    /// Move to the next iteration; `None`, without moving, at the last one.
+    pub fn step_forward(&mut self) -> Option<&RecordedStep> {
+        if self.position + 1 >= self.recording.steps.len() {
+            return None;
+        }
+        self.position += 1;
+        self.current()
+    }
+
+    /// Move to the previous iteration; `None`, without moving, at the first one.
+    pub fn step_back(&mut self) -> Option<&RecordedStep> {
+        if self.position == 0 {
+            return None;
+        }
+        self.position -= 1;
+        self.current()
+    }
+
+    /// Move to iteration `iteration`; `None`, without moving, if it wasn't recorded.
+    pub fn seek(&mut self, iteration: usize) -> Option<&RecordedStep> {
+        let position = self
+            .recording
+            .steps
+            .iter()
+            .position(|step| step.iteration == iteration)?;
+        self.position = position;
+        self.current()
+    }
+
+    /// Continue the run on `agent` from the current iteration as if its tool call
+    /// had returned `tool_result`, and return the new final answer.
+    ///
+    /// The agent starts from the recorded prompt and the state recorded after the
+    /// call, so state changes made by the original call are kept. Later iterations
+    /// call the agent's planner and tools; the diverged run is journaled like any
+    /// other, so it can be recorded and debugged in turn.
+    pub async fn diverge(
+        &self,
+        agent: &DeepAgent,
+        tool_result: MessageContent,
+    ) -> anyhow::Result<AgentMessage> {
+        let step = self
+            .current()
+            .ok_or_else(|| anyhow::anyhow!("The recording has no steps"))?;
+        let state = self
+            .recording
+            .steps
+            .get(self.position + 1)
+            .unwrap_or(step)
+            .state
+            .clone();
+        let tool_result = AgentMessage {
+            role: MessageRole::Tool,
+            content: tool_result,
+            metadata: step
+                .tool_result
+                .as_ref()
+                .and_then(|message| message.metadata.clone()),
+        };
+        agent.continue_from_step(step, state, tool_result).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::ConfigurableAgentBuilder;
+    use agents_core::agent::{PlannerDecision, PlannerHandle};
+    use agents_core::tools::{Tool, ToolContext, ToolParameterSchema, ToolResult, ToolSchema};
+    use async_trait::async_trait;
+    use serde_json::{json, Value};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Counts lookups and always finds 42.
+    struct LookupTool(Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl Tool for LookupTool {
+        fn schema(&self) -> ToolSchema {
+            ToolSchema::new(
+                "lookup_price",
+                "Look up a price",
+                ToolParameterSchema::object("Lookup", Default::default(), vec![]),
+            )
+        }
+
+        async fn execute(&self, _args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(ToolResult::text(&ctx, "42"))
+        }
+    }
+
+    /// Looks the price up, then answers with it.
+    struct PricePlanner;
+
+    #[async_trait]
+    impl PlannerHandle for PricePlanner {
+        async fn plan(
+            &self,
+            context: PlannerContext,
+            _state: Arc<AgentStateSnapshot>,
+        ) -> anyhow::Result<PlannerDecision> {
+            let next_action = match context.history.last() {
+                Some(last) if last.role == MessageRole::Tool => PlannerAction::Respond {
+                    message: AgentMessage {
+                        role: MessageRole::Agent,
+                        content: MessageContent::Text(format!(
+                            "The price is {}",
+                            last.content.to_text()
+                        )),
+                        metadata: None,
+                    },
+                },
+                _ => PlannerAction::CallTool {
+                    tool_name: "lookup_price".into(),
+                    payload: json!({}),
+                },
+            };
+            Ok(PlannerDecision { next_action })
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recorded_runs_can_be_stepped_through_and_diverged() {
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let agent = ConfigurableAgentBuilder::new("You quote prices")
+            .with_planner(Arc::new(PricePlanner))
+            .with_tool(Arc::new(LookupTool(lookups.clone())))
+            .with_step_recording(true)
+            .build()
+            .unwrap();
+        let reply = agent
+            .handle_message(
+                "What does it cost?",
+                Arc::new(AgentStateSnapshot::default()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(reply.content.to_text(), "The price is 42");
+
+        let recording = agent.run_recording(&agent.last_run_id().unwrap()).unwrap();
+        let json = serde_json::to_string(&recording).unwrap();
+        let mut debugger = RunDebugger::new(serde_json::from_str(&json).unwrap());
+
+        let first = debugger.current().unwrap();
+        assert!(matches!(first.action, PlannerAction::CallTool { .. }));
+        assert_eq!(first.tool_result.as_ref().unwrap().content.to_text(), "42");
+        assert!(first.render_prompt().contains("You quote prices"));
+        assert!(first.render_prompt().contains("What does it cost?"));
+
+        let second = debugger.step_forward().unwrap();
+        assert_eq!(second.iteration, 2);
+        assert!(matches!(second.action, PlannerAction::Respond { .. }));
+        assert!(debugger.step_forward().is_none());
+        assert_eq!(debugger.step_back().unwrap().iteration, 1);
+
+        let diverged = debugger
+            .diverge(&agent, MessageContent::Text("17".into()))
+            .await
+            .unwrap();
+        assert_eq!(diverged.content.to_text(), "The price is 17");
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+
+        debugger.seek(2);
+        assert!(debugger
+            .diverge(&agent, MessageContent::Text("17".into()))
+            .await
+            .is_err());
+    }
+}
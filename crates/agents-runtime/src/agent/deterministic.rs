@@ -0,0 +1,60 @@
+//! Deterministic mode for reproducible runs
+//!
+//! Debugging an agent means running the same input again, but sampling, the date in
+//! the prompt and every generated id differ between runs. In deterministic mode each
+//! run:
+//!
+//! - sends a fixed sampling seed to providers that support one (OpenAI and
+//!   OpenAI-compatible servers, Gemini); Anthropic has no seed and samples as usual;
+//! - renders the time context, and answers the time tools, with a frozen timestamp;
+//! - draws [`ToolContext::new_uuid`](agents_core::tools::ToolContext::new_uuid) ids
+//!   from a generator seeded anew at the start of the run.
+//!
+//! Two runs of the same input then produce comparable traces. Providers only make a
+//! best effort to honor a seed, so responses can still differ occasionally.
+
+use super::prompt_snapshot::PromptSnapshot;
+use agents_core::determinism;
+use chrono::{DateTime, Utc};
+use std::future::Future;
+
+/// Seed and frozen time of deterministic runs.
+///
+/// # Example
+///
+/// ```ignore
+/// let agent = ConfigurableAgentBuilder::new("You are a scheduling assistant")
+///     .with_model(model)
+///     .with_time_context(TimeContextConfig::default())
+///     .with_deterministic_mode(DeterministicConfig::new(42))
+///     .build()?;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeterministicConfig {
+    /// Sampling seed, also seeding the id generator
+    pub seed: u64,
+    /// Time reported to the prompt and time tools
+    pub frozen_time: DateTime<Utc>,
+}
+
+impl DeterministicConfig {
+    /// Runs seeded with `seed`, with the time frozen at 2000-01-01 00:00 UTC like
+    /// prompt snapshots.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            frozen_time: PromptSnapshot::rendered_at(),
+        }
+    }
+
+    pub fn with_frozen_time(mut self, frozen_time: DateTime<Utc>) -> Self {
+        self.frozen_time = frozen_time;
+        self
+    }
+
+    /// Run `future` with the seed and frozen time in effect.
+    pub(crate) async fn scope<F: Future>(&self, future: F) -> F::Output {
+        determinism::with_frozen_time(self.frozen_time, determinism::with_seed(self.seed, future))
+            .await
+    }
+}
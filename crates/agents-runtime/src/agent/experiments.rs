@@ -0,0 +1,291 @@
+//! Per-thread A/B experiments on prompts and models
+//!
+//! An [`Experiment`] splits threads between variants that differ in instructions,
+//! model or temperature. `DeepAgent::handle_message_for_thread` assigns each thread
+//! by hashing its id, so a conversation keeps its variant across turns, replicas and
+//! restarts without storing the assignment anywhere. The run executes inside an
+//! experiment scope: its events, run report and token usage records carry the
+//! [`ExperimentAssignment`], so outcomes and costs can be compared per variant.
+//!
+//! Options passed to the call take precedence over the variant's settings.
+
+use super::run_options::RunOptions;
+use agents_core::experiment::ExperimentAssignment;
+use agents_core::llm::LanguageModel;
+use std::sync::Arc;
+
+/// One configuration under test.
+///
+/// Settings left unset keep the agent's own.
+#[derive(Clone)]
+pub struct ExperimentVariant {
+    pub name: String,
+    /// Relative share of threads assigned to the variant
+    pub weight: u32,
+    /// System prompt used instead of the agent's instructions
+    pub instructions: Option<String>,
+    /// Model used instead of the agent's model
+    pub model: Option<Arc<dyn LanguageModel>>,
+    pub temperature: Option<f32>,
+}
+
+impl std::fmt::Debug for ExperimentVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExperimentVariant")
+            .field("name", &self.name)
+            .field("weight", &self.weight)
+            .field("instructions", &self.instructions)
+            .field("model", &self.model.as_ref().map(|_| "<model>"))
+            .field("temperature", &self.temperature)
+            .finish()
+    }
+}
+
+impl ExperimentVariant {
+    /// A variant with weight 1 that keeps the agent's configuration, e.g. the control.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            weight: 1,
+            instructions: None,
+            model: None,
+            temperature: None,
+        }
+    }
+
+    pub fn with_weight(mut self, weight: u32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn with_instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = Some(instructions.into());
+        self
+    }
+
+    pub fn with_model(mut self, model: Arc<dyn LanguageModel>) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+}
+
+/// Variants threads are split between.
+///
+/// # Example
+///
+/// ```ignore
+/// let experiment = Experiment::new("support-prompt-v2")
+///     .with_variant(ExperimentVariant::new("control").with_weight(9))
+///     .with_variant(
+///         ExperimentVariant::new("concise")
+///             .with_instructions(CONCISE_PROMPT)
+///             .with_model(mini_model)
+///             .with_temperature(0.2),
+///     );
+/// let agent = ConfigurableAgentBuilder::new(SUPPORT_PROMPT)
+///     .with_model(model)
+///     .with_checkpointer(checkpointer)
+///     .with_experiment(experiment)
+///     .build()?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Experiment {
+    pub name: String,
+    pub variants: Vec<ExperimentVariant>,
+}
+
+impl Experiment {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            variants: Vec::new(),
+        }
+    }
+
+    pub fn with_variant(mut self, variant: ExperimentVariant) -> Self {
+        self.variants.push(variant);
+        self
+    }
+
+    /// The variant `thread_id` is assigned to; `None` when no variant has a weight.
+    ///
+    /// The assignment only depends on the experiment name, the thread id and the
+    /// variants, so it is stable across processes. Renaming the experiment reshuffles
+    /// every thread.
+    pub fn assign(&self, thread_id: &str) -> Option<&ExperimentVariant> {
+        let total: u64 = self.variants.iter().map(|v| u64::from(v.weight)).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut bucket = fnv1a(&[self.name.as_bytes(), b"\0", thread_id.as_bytes()]) % total;
+        self.variants.iter().find(|variant| {
+            let weight = u64::from(variant.weight);
+            if bucket < weight {
+                return true;
+            }
+            bucket -= weight;
+            false
+        })
+    }
+
+    /// The assignment recorded for `thread_id`, see [`assign`](Self::assign).
+    pub fn assignment(&self, thread_id: &str) -> Option<ExperimentAssignment> {
+        self.assign(thread_id)
+            .map(|variant| ExperimentAssignment::new(&self.name, &variant.name))
+    }
+
+    /// Fill the options of a run on `thread_id` the caller left unset from the
+    /// thread's variant.
+    pub(crate) fn configure(
+        &self,
+        thread_id: &str,
+        mut options: RunOptions,
+    ) -> (RunOptions, Option<ExperimentAssignment>) {
+        let Some(variant) = self.assign(thread_id) else {
+            return (options, None);
+        };
+        if options.instructions.is_none() {
+            options.instructions = variant.instructions.clone();
+        }
+        if options.model.is_none() {
+            options.model = variant.model.clone();
+        }
+        if options.temperature.is_none() {
+            options.temperature = variant.temperature;
+        }
+        let assignment = ExperimentAssignment::new(&self.name, &variant.name);
+        (options, Some(assignment))
+    }
+}
+
+/// 64-bit FNV-1a; unlike `DefaultHasher`, its output is fixed across Rust releases.
+pub(crate) fn fnv1a(parts: &[&[u8]]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in parts.iter().flat_map(|part| part.iter()) {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::ConfigurableAgentBuilder;
+    use agents_core::llm::{LlmRequest, LlmResponse};
+    use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+    use agents_core::persistence::InMemoryCheckpointer;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Answers every request, recording its system prompt and temperature.
+    #[derive(Default)]
+    struct RecordingModel(Mutex<Vec<(String, Option<f32>)>>);
+
+    #[async_trait]
+    impl LanguageModel for RecordingModel {
+        async fn generate(&self, request: LlmRequest) -> anyhow::Result<LlmResponse> {
+            self.0
+                .lock()
+                .unwrap()
+                .push((request.system_prompt.to_string(), request.temperature));
+            Ok(LlmResponse::new(AgentMessage {
+                role: MessageRole::Agent,
+                content: MessageContent::Text("done".to_string()),
+                metadata: None,
+            }))
+        }
+    }
+
+    #[test]
+    fn threads_are_split_by_weight_and_keep_their_variant() {
+        let experiment = Experiment::new("prompt-v2")
+            .with_variant(ExperimentVariant::new("control").with_weight(3))
+            .with_variant(ExperimentVariant::new("treatment").with_instructions("Be brief."));
+
+        let mut treatment = 0;
+        for i in 0..4000 {
+            let thread_id = format!("thread-{}", i);
+            let variant = experiment.assign(&thread_id).unwrap();
+            assert_eq!(experiment.assign(&thread_id).unwrap().name, variant.name);
+            if variant.name == "treatment" {
+                treatment += 1;
+            }
+        }
+        // One in four threads, give or take
+        assert!((850..1150).contains(&treatment), "{}", treatment);
+
+        let unweighted =
+            Experiment::new("off").with_variant(ExperimentVariant::new("a").with_weight(0));
+        assert!(unweighted.assign("thread-1").is_none());
+    }
+
+    #[test]
+    fn explicit_options_take_precedence_over_the_variant() {
+        let experiment = Experiment::new("temperature").with_variant(
+            ExperimentVariant::new("cold")
+                .with_instructions("Be brief.")
+                .with_temperature(0.1),
+        );
+        let (options, assignment) =
+            experiment.configure("thread-1", RunOptions::new().with_temperature(0.9));
+
+        assert_eq!(options.instructions.as_deref(), Some("Be brief."));
+        assert_eq!(options.temperature, Some(0.9));
+        assert_eq!(
+            assignment,
+            Some(ExperimentAssignment::new("temperature", "cold"))
+        );
+    }
+
+    #[tokio::test]
+    async fn thread_runs_use_and_record_their_variant() {
+        let default_model = Arc::new(RecordingModel::default());
+        let variant_model = Arc::new(RecordingModel::default());
+        let agent = ConfigurableAgentBuilder::new("Answer in detail.")
+            .with_model(default_model.clone())
+            .with_checkpointer(Arc::new(InMemoryCheckpointer::new()))
+            .with_token_tracking(true)
+            .with_experiment(
+                Experiment::new("prompt-v2").with_variant(
+                    ExperimentVariant::new("concise")
+                        .with_instructions("Answer in one sentence.")
+                        .with_model(variant_model.clone())
+                        .with_temperature(0.2),
+                ),
+            )
+            .build()
+            .unwrap();
+
+        agent
+            .handle_message_for_thread(&"thread-1".to_string(), "hello")
+            .await
+            .unwrap();
+
+        assert!(default_model.0.lock().unwrap().is_empty());
+        let requests = variant_model.0.lock().unwrap().clone();
+        assert!(requests[0].0.contains("Answer in one sentence."));
+        assert!(!requests[0].0.contains("Answer in detail."));
+        assert_eq!(requests[0].1, Some(0.2));
+
+        let assignment = ExperimentAssignment::new("prompt-v2", "concise");
+        assert_eq!(
+            agent.experiment_assignment("thread-1"),
+            Some(assignment.clone())
+        );
+        let report = agent
+            .generate_report(&agent.last_run_id().unwrap())
+            .unwrap();
+        assert_eq!(report.experiment, Some(assignment.clone()));
+        assert!(report
+            .to_markdown()
+            .contains("- **Experiment:** prompt-v2 (variant `concise`)"));
+        let usage = agent.usage_by_variant().unwrap();
+        assert_eq!(usage[&assignment].request_count, 1);
+    }
+}
@@ -0,0 +1,132 @@
+//! Redacted thread exports for bug reports
+//!
+//! A thread that misbehaves is easiest to debug with its state and what the agent
+//! did, but both hold customer data. [`DeepAgent::export_thread_redacted`](super::DeepAgent::export_thread_redacted)
+//! gathers the thread's checkpointed state and metadata with the traces and step
+//! recordings of its runs still in the journal, then redacts the whole document:
+//! emails, phone numbers and card numbers, API keys and tokens, and the values of
+//! fields named like credentials. The result can be attached to an issue.
+//!
+//! Redaction is pattern based: it catches the formats it knows, not names or
+//! addresses in free text. Review an export before publishing it.
+
+use super::debugger::RunRecording;
+use super::run_trace::RunTrace;
+use agents_core::persistence::ThreadMetadata;
+use agents_core::security::redact_json;
+use agents_core::state::AgentStateSnapshot;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Contents of a thread export, before redaction.
+#[derive(Debug, Clone, Serialize)]
+pub struct ThreadExport {
+    pub thread_id: String,
+    /// Checkpointed state: messages, files, todos and scratchpad
+    pub state: AgentStateSnapshot,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<ThreadMetadata>,
+    /// Traces of the thread's runs still in the journal, oldest first
+    pub traces: Vec<RunTrace>,
+    /// Step recordings of those runs, when step recording is on
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub recordings: Vec<RunRecording>,
+}
+
+impl ThreadExport {
+    /// The export as JSON with PII and credentials redacted.
+    ///
+    /// Redaction works on the JSON document, so the result is not guaranteed to
+    /// deserialize back into the export's types.
+    pub fn redacted(&self) -> anyhow::Result<Value> {
+        Ok(redact_json(&serde_json::to_value(self)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::agent::ConfigurableAgentBuilder;
+    use agents_core::agent::{PlannerAction, PlannerContext, PlannerDecision, PlannerHandle};
+    use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+    use agents_core::persistence::InMemoryCheckpointer;
+    use agents_core::state::AgentStateSnapshot;
+    use async_trait::async_trait;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    /// Writes the request to a file, then confirms.
+    struct NotePlanner;
+
+    #[async_trait]
+    impl PlannerHandle for NotePlanner {
+        async fn plan(
+            &self,
+            context: PlannerContext,
+            _state: Arc<AgentStateSnapshot>,
+        ) -> anyhow::Result<PlannerDecision> {
+            let last = context.history.last().unwrap();
+            let next_action = match last.role {
+                MessageRole::User => PlannerAction::CallTool {
+                    tool_name: "write_file".into(),
+                    payload: json!({
+                        "file_path": "/notes.md",
+                        "content": last.content.to_text(),
+                    }),
+                },
+                _ => PlannerAction::Respond {
+                    message: AgentMessage {
+                        role: MessageRole::Agent,
+                        content: MessageContent::Text("Noted.".into()),
+                        metadata: None,
+                    },
+                },
+            };
+            Ok(PlannerDecision { next_action })
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exports_are_redacted() {
+        let agent = ConfigurableAgentBuilder::new("Take notes")
+            .with_planner(Arc::new(NotePlanner))
+            .with_checkpointer(Arc::new(InMemoryCheckpointer::new()))
+            .with_step_recording(true)
+            .build()
+            .unwrap();
+        let thread_id = "thread-1".to_string();
+        agent
+            .handle_message_for_thread(
+                &thread_id,
+                "Reach jane@example.com, key sk-live-0123456789abcdefghijklmn",
+            )
+            .await
+            .unwrap();
+        agent
+            .handle_message(
+                "Another thread's request",
+                Arc::new(AgentStateSnapshot::default()),
+            )
+            .await
+            .unwrap();
+
+        let export = agent.export_thread_redacted(&thread_id).await.unwrap();
+        assert_eq!(
+            export["state"]["files"]["/notes.md"],
+            "Reach [EMAIL], key [SECRET]"
+        );
+        assert_eq!(export["traces"].as_array().unwrap().len(), 1);
+        assert_eq!(export["recordings"].as_array().unwrap().len(), 1);
+
+        let text = export.to_string();
+        assert!(!text.contains("jane@example.com"));
+        assert!(!text.contains("sk-live"));
+        assert!(agent
+            .export_thread_redacted(&"unknown".to_string())
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,494 @@
+//! Knowledge graph memory
+//!
+//! After each checkpointed turn, a model extracts the entities and relations the
+//! turn states ("Ana works at Acme", "order 1042 contains an X200") and adds them
+//! to a [`GraphStore`] shared by all threads. The agent gets three tools to query
+//! what has accumulated:
+//!
+//! - `graph_entities` finds entities by name
+//! - `graph_facts` lists the facts around an entity, up to three relations away
+//! - `graph_path` finds the chain of facts connecting two entities
+//!
+//! so it can answer questions that combine facts learned in different turns or
+//! threads.
+
+use super::memory_policy::{MemoryDecision, MemoryPolicy};
+use agents_core::graph::{GraphEntity, GraphFact, GraphStore};
+use agents_core::llm::{with_usage_purpose, LanguageModel, LlmRequest, UsagePurpose};
+use agents_core::memory::UserId;
+use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+use agents_core::persistence::ThreadId;
+use agents_core::tools::{Tool, ToolBox, ToolContext, ToolParameterSchema, ToolResult, ToolSchema};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Most hops `graph_facts` walks from an entity
+const MAX_HOPS: usize = 3;
+/// Most hops `graph_path` searches by default
+const DEFAULT_PATH_HOPS: usize = 4;
+/// Most entities or facts a tool returns
+const MAX_RESULTS: usize = 50;
+
+const EXTRACTION_PROMPT: &str = "You extract durable facts from a conversation turn for a \
+    knowledge graph. Reply with JSON only: {\"entities\": [{\"name\": \"...\", \"type\": \
+    \"...\"}], \"facts\": [{\"subject\": \"...\", \"predicate\": \"...\", \"object\": \
+    \"...\"}]}. Entities are specific people, organizations, products, places, accounts, \
+    orders and the like, named the way the conversation names them; types are single \
+    lowercase words. Predicates are short snake_case verbs such as works_at, ordered or \
+    located_in. Only include facts the turn states as true, not questions, guesses or \
+    the assistant's suggestions. Reply {\"entities\": [], \"facts\": []} when there are none.";
+
+/// Configuration for knowledge graph memory.
+///
+/// # Example
+///
+/// ```ignore
+/// let graph = Arc::new(InMemoryGraphStore::new());
+/// let agent = ConfigurableAgentBuilder::new("You are an account manager")
+///     .with_model(model)
+///     .with_checkpointer(checkpointer)
+///     .with_graph_memory(GraphMemoryConfig::new(graph.clone(), small_model))
+///     .build()?;
+/// ```
+#[derive(Clone)]
+pub struct GraphMemoryConfig {
+    pub store: Arc<dyn GraphStore>,
+    /// Model extracting facts from each turn, typically a small and cheap one
+    pub model: Arc<dyn LanguageModel>,
+    /// Facts kept from one turn; the rest are dropped (default: 20)
+    pub max_facts_per_turn: usize,
+    /// Characters of each message given to the extraction model (default: 2000)
+    pub excerpt_chars: usize,
+}
+
+impl std::fmt::Debug for GraphMemoryConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GraphMemoryConfig")
+            .field("max_facts_per_turn", &self.max_facts_per_turn)
+            .field("excerpt_chars", &self.excerpt_chars)
+            .finish_non_exhaustive()
+    }
+}
+
+impl GraphMemoryConfig {
+    pub fn new(store: Arc<dyn GraphStore>, model: Arc<dyn LanguageModel>) -> Self {
+        Self {
+            store,
+            model,
+            max_facts_per_turn: 20,
+            excerpt_chars: 2000,
+        }
+    }
+
+    pub fn with_max_facts_per_turn(mut self, facts: usize) -> Self {
+        self.max_facts_per_turn = facts;
+        self
+    }
+
+    pub fn with_excerpt_chars(mut self, chars: usize) -> Self {
+        self.excerpt_chars = chars;
+        self
+    }
+
+    /// Tools querying the graph.
+    pub fn tools(&self) -> Vec<ToolBox> {
+        vec![
+            Arc::new(GraphEntitiesTool {
+                store: self.store.clone(),
+            }),
+            Arc::new(GraphFactsTool {
+                store: self.store.clone(),
+            }),
+            Arc::new(GraphPathTool {
+                store: self.store.clone(),
+            }),
+        ]
+    }
+
+    /// Extract the facts a turn states and add them to the store, returning how
+    /// many were added. Entities and facts the `policy` blocks for `user_id` are
+    /// dropped.
+    pub async fn record_turn(
+        &self,
+        thread_id: &ThreadId,
+        messages: &[&AgentMessage],
+        policy: Option<&MemoryPolicy>,
+        user_id: Option<&UserId>,
+    ) -> anyhow::Result<usize> {
+        let transcript: Vec<String> = messages
+            .iter()
+            .filter_map(|message| self.excerpt(message))
+            .collect();
+        if transcript.is_empty() {
+            return Ok(0);
+        }
+
+        let request = LlmRequest::new(
+            EXTRACTION_PROMPT,
+            vec![AgentMessage {
+                role: MessageRole::User,
+                content: MessageContent::Text(transcript.join("\n")),
+                metadata: None,
+            }],
+        );
+        let response =
+            with_usage_purpose(UsagePurpose::Summarization, self.model.generate(request)).await?;
+        let (mut entities, mut facts) = parse_extraction(&response.message.content.to_text())
+            .ok_or_else(|| anyhow::anyhow!("Extraction model returned no JSON"))?;
+        facts.truncate(self.max_facts_per_turn);
+        if let Some(policy) = policy {
+            let mut allowed = Vec::with_capacity(entities.len());
+            for entity in entities {
+                let kind = entity.kind.as_deref().unwrap_or("entity");
+                if let MemoryDecision::Allow { .. } =
+                    policy.review(user_id, kind, &entity.name).await
+                {
+                    allowed.push(entity);
+                }
+            }
+            entities = allowed;
+            let mut allowed = Vec::with_capacity(facts.len());
+            for fact in facts {
+                let statement = format!("{} {}", fact.subject, fact.object);
+                if let MemoryDecision::Allow { .. } =
+                    policy.review(user_id, &fact.predicate, &statement).await
+                {
+                    allowed.push(fact);
+                }
+            }
+            facts = allowed;
+        }
+        if entities.is_empty() && facts.is_empty() {
+            return Ok(0);
+        }
+        let facts: Vec<GraphFact> = facts
+            .into_iter()
+            .map(|fact| fact.with_thread(thread_id.clone()))
+            .collect();
+        self.store.add(&entities, &facts).await?;
+        Ok(facts.len())
+    }
+
+    fn excerpt(&self, message: &AgentMessage) -> Option<String> {
+        let speaker = match message.role {
+            MessageRole::User => "User",
+            MessageRole::Agent => "Assistant",
+            MessageRole::Tool | MessageRole::System => return None,
+        };
+        let text = message.content.to_text();
+        let text = text.trim();
+        if text.is_empty() {
+            return None;
+        }
+        let mut excerpt: String = text.chars().take(self.excerpt_chars).collect();
+        if excerpt.len() < text.len() {
+            excerpt.push('…');
+        }
+        Some(format!("{speaker}: {excerpt}"))
+    }
+}
+
+#[derive(Deserialize)]
+struct ExtractedEntity {
+    name: String,
+    #[serde(default, rename = "type")]
+    kind: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ExtractedFact {
+    subject: String,
+    predicate: String,
+    object: String,
+}
+
+#[derive(Deserialize)]
+struct Extraction {
+    #[serde(default)]
+    entities: Vec<ExtractedEntity>,
+    #[serde(default)]
+    facts: Vec<ExtractedFact>,
+}
+
+/// `Works At` and `works-at` become `works_at`.
+fn snake_case(predicate: &str) -> String {
+    predicate
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Entities and facts from the model's reply, possibly in a code fence. Facts
+/// missing a part are dropped.
+fn parse_extraction(reply: &str) -> Option<(Vec<GraphEntity>, Vec<GraphFact>)> {
+    let trimmed = reply.trim();
+    let json = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|body| body.strip_suffix("```"))
+        .unwrap_or(trimmed)
+        .trim();
+    let extraction: Extraction = serde_json::from_str(json).ok()?;
+    let entities = extraction
+        .entities
+        .into_iter()
+        .filter(|entity| !entity.name.trim().is_empty())
+        .map(|entity| {
+            let name = GraphEntity::new(entity.name.trim());
+            match entity.kind.map(|kind| kind.trim().to_lowercase()) {
+                Some(kind) if !kind.is_empty() => name.with_kind(kind),
+                _ => name,
+            }
+        })
+        .collect();
+    let facts = extraction
+        .facts
+        .into_iter()
+        .filter_map(|fact| {
+            let predicate = snake_case(&fact.predicate);
+            let (subject, object) = (fact.subject.trim(), fact.object.trim());
+            (!subject.is_empty() && !object.is_empty() && !predicate.is_empty())
+                .then(|| GraphFact::new(subject, predicate, object))
+        })
+        .collect();
+    Some((entities, facts))
+}
+
+fn fact_lines(facts: &[GraphFact]) -> String {
+    facts
+        .iter()
+        .map(|fact| format!("- {}", fact.summary()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn required_str<'a>(args: &'a Value, name: &str) -> anyhow::Result<&'a str> {
+    args.get(name)
+        .and_then(Value::as_str)
+        .filter(|value| !value.trim().is_empty())
+        .ok_or_else(|| anyhow::anyhow!("Missing required parameter: {name}"))
+}
+
+/// Finds entities in the knowledge graph by name
+struct GraphEntitiesTool {
+    store: Arc<dyn GraphStore>,
+}
+
+#[async_trait]
+impl Tool for GraphEntitiesTool {
+    fn schema(&self) -> ToolSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "query".to_string(),
+            ToolParameterSchema::string("Part of the entity's name, e.g. 'acme'"),
+        );
+        ToolSchema::new(
+            "graph_entities",
+            "Find people, organizations, products and other entities remembered from past conversations by name.",
+            ToolParameterSchema::object("Entity search", properties, vec!["query".to_string()]),
+        )
+    }
+
+    async fn execute(&self, args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+        let query = required_str(&args, "query")?;
+        let entities = self.store.find_entities(query, MAX_RESULTS).await?;
+        if entities.is_empty() {
+            return Ok(ToolResult::text(
+                &ctx,
+                format!("No entities match '{query}'"),
+            ));
+        }
+        let lines: Vec<String> = entities
+            .iter()
+            .map(|entity| match &entity.kind {
+                Some(kind) => format!("- {} ({kind})", entity.name),
+                None => format!("- {}", entity.name),
+            })
+            .collect();
+        Ok(ToolResult::text(&ctx, lines.join("\n")))
+    }
+}
+
+/// Lists the facts around an entity
+struct GraphFactsTool {
+    store: Arc<dyn GraphStore>,
+}
+
+#[async_trait]
+impl Tool for GraphFactsTool {
+    fn schema(&self) -> ToolSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "entity".to_string(),
+            ToolParameterSchema::string("Exact name of the entity"),
+        );
+        properties.insert(
+            "hops".to_string(),
+            ToolParameterSchema::integer(
+                "How many relations away to look: 1 for direct facts, up to 3 (default: 1)",
+            )
+            .with_minimum(1)
+            .with_maximum(MAX_HOPS),
+        );
+        ToolSchema::new(
+            "graph_facts",
+            "List remembered facts about an entity, and with hops > 1 the facts about related entities too.",
+            ToolParameterSchema::object("Fact lookup", properties, vec!["entity".to_string()]),
+        )
+    }
+
+    async fn execute(&self, args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+        let entity = required_str(&args, "entity")?;
+        let hops = args
+            .get("hops")
+            .and_then(Value::as_u64)
+            .map_or(1, |hops| hops as usize)
+            .clamp(1, MAX_HOPS);
+        let facts = self.store.neighborhood(entity, hops, MAX_RESULTS).await?;
+        if facts.is_empty() {
+            return Ok(ToolResult::text(
+                &ctx,
+                format!(
+                    "Nothing is known about '{entity}'; use graph_entities to find its exact name"
+                ),
+            ));
+        }
+        Ok(ToolResult::text(&ctx, fact_lines(&facts)))
+    }
+}
+
+/// Finds how two entities are connected
+struct GraphPathTool {
+    store: Arc<dyn GraphStore>,
+}
+
+#[async_trait]
+impl Tool for GraphPathTool {
+    fn schema(&self) -> ToolSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "from".to_string(),
+            ToolParameterSchema::string("Exact name of the first entity"),
+        );
+        properties.insert(
+            "to".to_string(),
+            ToolParameterSchema::string("Exact name of the second entity"),
+        );
+        properties.insert(
+            "max_hops".to_string(),
+            ToolParameterSchema::integer("Longest chain of facts to consider (default: 4)")
+                .with_minimum(1)
+                .with_maximum(6),
+        );
+        ToolSchema::new(
+            "graph_path",
+            "Find the chain of remembered facts connecting two entities, e.g. how a person relates to a product.",
+            ToolParameterSchema::object(
+                "Path search",
+                properties,
+                vec!["from".to_string(), "to".to_string()],
+            ),
+        )
+    }
+
+    async fn execute(&self, args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+        let from = required_str(&args, "from")?;
+        let to = required_str(&args, "to")?;
+        let max_hops = args
+            .get("max_hops")
+            .and_then(Value::as_u64)
+            .map_or(DEFAULT_PATH_HOPS, |hops| hops as usize)
+            .clamp(1, 6);
+        let text = match self.store.path(from, to, max_hops).await? {
+            Some(path) if path.is_empty() => format!("'{from}' and '{to}' are the same entity"),
+            Some(path) => fact_lines(&path),
+            None => format!("No connection between '{from}' and '{to}' within {max_hops} facts"),
+        };
+        Ok(ToolResult::text(&ctx, text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::graph::InMemoryGraphStore;
+    use agents_core::llm::LlmResponse;
+    use agents_core::state::AgentStateSnapshot;
+    use serde_json::json;
+
+    struct ExtractionModel;
+
+    #[async_trait]
+    impl LanguageModel for ExtractionModel {
+        async fn generate(&self, request: LlmRequest) -> anyhow::Result<LlmResponse> {
+            let input = request.messages[0].content.to_text();
+            assert!(input.starts_with("User: Ana from Acme ordered"));
+            let reply = r#"```json
+{"entities": [{"name": "Ana", "type": "Person"}, {"name": "Acme", "type": "company"}],
+ "facts": [{"subject": "Ana", "predicate": "Works At", "object": "Acme"},
+           {"subject": "Ana", "predicate": "ordered", "object": "X200"},
+           {"subject": "", "predicate": "ordered", "object": "X300"}]}
+```"#;
+            Ok(LlmResponse::new(AgentMessage {
+                role: MessageRole::Agent,
+                content: MessageContent::Text(reply.to_string()),
+                metadata: None,
+            }))
+        }
+    }
+
+    fn text(role: MessageRole, text: &str) -> AgentMessage {
+        AgentMessage {
+            role,
+            content: MessageContent::Text(text.to_string()),
+            metadata: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn turns_become_facts_the_tools_can_query() {
+        let store = Arc::new(InMemoryGraphStore::new());
+        let config = GraphMemoryConfig::new(store.clone(), Arc::new(ExtractionModel));
+        let user = text(MessageRole::User, "Ana from Acme ordered an X200 yesterday");
+        let reply = text(MessageRole::Agent, "Thanks, I found the order.");
+
+        let added = config
+            .record_turn(&"t1".to_string(), &[&user, &reply], None, None)
+            .await
+            .unwrap();
+        assert_eq!(added, 2);
+
+        let tools: HashMap<String, ToolBox> = config
+            .tools()
+            .into_iter()
+            .map(|tool| (tool.schema().name, tool))
+            .collect();
+        let ctx = || ToolContext::new(Arc::new(AgentStateSnapshot::default()));
+        let run = |name: &str, args: Value| {
+            let tool = tools[name].clone();
+            async move {
+                let ToolResult::Message(message) = tool.execute(args, ctx()).await.unwrap() else {
+                    panic!("expected a message");
+                };
+                message.content.to_text().to_string()
+            }
+        };
+
+        assert_eq!(
+            run("graph_entities", json!({"query": "ana"})).await,
+            "- Ana (person)"
+        );
+        assert_eq!(
+            run("graph_path", json!({"from": "Acme", "to": "X200"})).await,
+            "- Ana -works_at-> Acme\n- Ana -ordered-> X200"
+        );
+        assert!(run("graph_facts", json!({"entity": "Zeta"}))
+            .await
+            .starts_with("Nothing is known about 'Zeta'"));
+    }
+}
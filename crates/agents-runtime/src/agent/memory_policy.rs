@@ -0,0 +1,595 @@
+//! Consent and retention controls on memory writes
+//!
+//! Some facts must not be remembered at all (health, religion, ...), some only
+//! with the user's consent, and none forever. A [`MemoryPolicy`] classifies every
+//! fact before it is written to a user profile, user memory or the knowledge
+//! graph, blocks prohibited categories and categories the user has not consented
+//! to, and stamps what it allows with an expiry for its category. At the start of
+//! each turn, facts that expired or whose category lost consent are purged from
+//! the thread and the user's memories. Blocks and purges are reported as
+//! `MemoryBlocked` and `MemoryPurged` events for the audit trail.
+
+use agents_core::events::{
+    AgentEvent, EventDispatcher, EventMetadata, MemoryBlockReason, MemoryBlockedEvent,
+    MemoryPurgeReason, MemoryPurgedEvent,
+};
+use agents_core::memory::UserId;
+use agents_core::user_profile::UserProfile;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+/// Assigns a category such as `health` or `contact` to a fact.
+#[async_trait]
+pub trait MemoryClassifier: Send + Sync {
+    /// Category of the fact `key = value`, or `None` when it has none.
+    async fn classify(&self, key: &str, value: &str) -> anyhow::Result<Option<String>>;
+}
+
+/// Classifies facts by keywords in their key or value, ignoring case.
+///
+/// The default rules cover the special categories of GDPR article 9 (`health`,
+/// `religion`, `politics`, `ethnicity`, `sexuality`, `biometric`) and the common
+/// `financial` and `contact` categories. The first matching category wins.
+#[derive(Debug, Clone)]
+pub struct KeywordClassifier {
+    rules: Vec<(String, Vec<String>)>,
+}
+
+impl Default for KeywordClassifier {
+    fn default() -> Self {
+        Self::new()
+            .with_category(
+                "health",
+                [
+                    "health",
+                    "medical",
+                    "diagnos",
+                    "condition",
+                    "illness",
+                    "disease",
+                    "allerg",
+                    "medication",
+                    "prescription",
+                    "pregnan",
+                    "disabilit",
+                    "therapy",
+                    "diabetes",
+                    "cancer",
+                    "blood_type",
+                ],
+            )
+            .with_category(
+                "religion",
+                [
+                    "religio",
+                    "faith",
+                    "church",
+                    "mosque",
+                    "synagogue",
+                    "temple",
+                ],
+            )
+            .with_category(
+                "politics",
+                ["politic", "party_affiliation", "vote", "union_member"],
+            )
+            .with_category("ethnicity", ["ethnic", "race", "racial"])
+            .with_category("sexuality", ["sexual", "gender_identity"])
+            .with_category("biometric", ["biometric", "fingerprint", "face_id", "dna"])
+            .with_category(
+                "financial",
+                [
+                    "card_number",
+                    "credit_card",
+                    "iban",
+                    "bank_account",
+                    "salary",
+                    "income",
+                ],
+            )
+            .with_category("contact", ["email", "phone", "address", "postcode", "zip"])
+    }
+}
+
+impl KeywordClassifier {
+    /// A classifier without rules.
+    pub fn new() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    /// Put facts whose key or value contains one of `keywords` in `category`.
+    pub fn with_category<I, S>(mut self, category: impl Into<String>, keywords: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.rules.push((
+            category.into(),
+            keywords
+                .into_iter()
+                .map(|keyword| keyword.into().to_lowercase())
+                .collect(),
+        ));
+        self
+    }
+}
+
+#[async_trait]
+impl MemoryClassifier for KeywordClassifier {
+    async fn classify(&self, key: &str, value: &str) -> anyhow::Result<Option<String>> {
+        let text = format!("{} {}", key, value).to_lowercase();
+        Ok(self
+            .rules
+            .iter()
+            .find(|(_, keywords)| keywords.iter().any(|keyword| text.contains(keyword)))
+            .map(|(category, _)| category.clone()))
+    }
+}
+
+/// Categories each user consented to having remembered.
+#[async_trait]
+pub trait ConsentStore: Send + Sync {
+    async fn granted(&self, user_id: &UserId) -> anyhow::Result<BTreeSet<String>>;
+
+    async fn grant(&self, user_id: &UserId, category: &str) -> anyhow::Result<()>;
+
+    async fn revoke(&self, user_id: &UserId, category: &str) -> anyhow::Result<()>;
+}
+
+/// Process-local consent store for tests and single-process deployments.
+#[derive(Debug, Default)]
+pub struct InMemoryConsentStore {
+    consents: RwLock<HashMap<UserId, BTreeSet<String>>>,
+}
+
+impl InMemoryConsentStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl ConsentStore for InMemoryConsentStore {
+    async fn granted(&self, user_id: &UserId) -> anyhow::Result<BTreeSet<String>> {
+        let consents = self
+            .consents
+            .read()
+            .map_err(|_| anyhow::anyhow!("Consent store poisoned"))?;
+        Ok(consents.get(user_id).cloned().unwrap_or_default())
+    }
+
+    async fn grant(&self, user_id: &UserId, category: &str) -> anyhow::Result<()> {
+        self.consents
+            .write()
+            .map_err(|_| anyhow::anyhow!("Consent store poisoned"))?
+            .entry(user_id.clone())
+            .or_default()
+            .insert(category.to_string());
+        Ok(())
+    }
+
+    async fn revoke(&self, user_id: &UserId, category: &str) -> anyhow::Result<()> {
+        if let Some(granted) = self
+            .consents
+            .write()
+            .map_err(|_| anyhow::anyhow!("Consent store poisoned"))?
+            .get_mut(user_id)
+        {
+            granted.remove(category);
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of [`MemoryPolicy::review`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum MemoryDecision {
+    /// Store the fact with this category and expiry
+    Allow {
+        category: Option<String>,
+        expires_at: Option<DateTime<Utc>>,
+    },
+    Block {
+        category: String,
+        reason: MemoryBlockReason,
+    },
+}
+
+/// Rules deciding which facts may be remembered, and for how long.
+///
+/// # Example
+///
+/// ```ignore
+/// let consents = Arc::new(InMemoryConsentStore::new());
+/// let policy = MemoryPolicy::new(Arc::new(KeywordClassifier::default()))
+///     .prohibit(["health", "religion", "politics", "ethnicity", "sexuality", "biometric"])
+///     .require_consent(["contact"], consents.clone())
+///     .retain("contact", Duration::days(90))
+///     .with_default_retention(Duration::days(365));
+///
+/// let agent = ConfigurableAgentBuilder::new("You schedule car service appointments")
+///     .with_model(model)
+///     .with_user_profile(UserProfileConfig::new(small_model))
+///     .with_memory_policy(policy)
+///     .build()?;
+/// ```
+#[derive(Clone)]
+pub struct MemoryPolicy {
+    classifier: Arc<dyn MemoryClassifier>,
+    prohibited: HashSet<String>,
+    consent_required: HashSet<String>,
+    consents: Option<Arc<dyn ConsentStore>>,
+    retention: HashMap<String, Duration>,
+    default_retention: Option<Duration>,
+    event_dispatcher: Option<Arc<EventDispatcher>>,
+}
+
+impl std::fmt::Debug for MemoryPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MemoryPolicy")
+            .field("prohibited", &self.prohibited)
+            .field("consent_required", &self.consent_required)
+            .field("retention", &self.retention)
+            .field("default_retention", &self.default_retention)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MemoryPolicy {
+    /// A policy allowing every category until rules are added.
+    pub fn new(classifier: Arc<dyn MemoryClassifier>) -> Self {
+        Self {
+            classifier,
+            prohibited: HashSet::new(),
+            consent_required: HashSet::new(),
+            consents: None,
+            retention: HashMap::new(),
+            default_retention: None,
+            event_dispatcher: None,
+        }
+    }
+
+    /// Never remember facts in these categories.
+    pub fn prohibit<I, S>(mut self, categories: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.prohibited
+            .extend(categories.into_iter().map(Into::into));
+        self
+    }
+
+    /// Only remember facts in these categories for users who granted consent to
+    /// the category in `consents`. Facts of threads without a user are blocked.
+    pub fn require_consent<I, S>(mut self, categories: I, consents: Arc<dyn ConsentStore>) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.consent_required
+            .extend(categories.into_iter().map(Into::into));
+        self.consents = Some(consents);
+        self
+    }
+
+    /// Forget facts in `category` after `retention`.
+    pub fn retain(mut self, category: impl Into<String>, retention: Duration) -> Self {
+        self.retention.insert(category.into(), retention);
+        self
+    }
+
+    /// Forget facts in categories without their own retention after `retention`.
+    pub fn with_default_retention(mut self, retention: Duration) -> Self {
+        self.default_retention = Some(retention);
+        self
+    }
+
+    pub(crate) fn with_event_dispatcher(
+        mut self,
+        dispatcher: Option<Arc<EventDispatcher>>,
+    ) -> Self {
+        self.event_dispatcher = dispatcher;
+        self
+    }
+
+    /// Store of user consents, when a category requires consent.
+    pub fn consents(&self) -> Option<&Arc<dyn ConsentStore>> {
+        self.consents.as_ref()
+    }
+
+    /// Decide whether the fact `key = value` about `user_id` may be remembered.
+    /// Blocks are logged and reported as `MemoryBlocked` events; a failed
+    /// classification or consent lookup blocks the fact.
+    pub async fn review(&self, user_id: Option<&UserId>, key: &str, value: &str) -> MemoryDecision {
+        let category = match self.classifier.classify(key, value).await {
+            Ok(category) => category,
+            Err(e) => {
+                tracing::warn!(key, "Failed to classify memory, not storing it: {}", e);
+                return self.block(
+                    user_id,
+                    key,
+                    "unclassified",
+                    MemoryBlockReason::Unclassified,
+                );
+            }
+        };
+        let Some(category) = category else {
+            return MemoryDecision::Allow {
+                category: None,
+                expires_at: self
+                    .default_retention
+                    .map(|retention| Utc::now() + retention),
+            };
+        };
+        if self.prohibited.contains(&category) {
+            return self.block(user_id, key, &category, MemoryBlockReason::Prohibited);
+        }
+        if self.consent_required.contains(&category) && !self.consented(user_id, &category).await {
+            return self.block(user_id, key, &category, MemoryBlockReason::NoConsent);
+        }
+        let expires_at = self
+            .retention
+            .get(&category)
+            .or(self.default_retention.as_ref())
+            .map(|retention| Utc::now() + *retention);
+        MemoryDecision::Allow {
+            category: Some(category),
+            expires_at,
+        }
+    }
+
+    async fn consented(&self, user_id: Option<&UserId>, category: &str) -> bool {
+        user_id.is_some()
+            && self
+                .granted(user_id)
+                .await
+                .is_some_and(|granted| granted.contains(category))
+    }
+
+    fn block(
+        &self,
+        user_id: Option<&UserId>,
+        key: &str,
+        category: &str,
+        reason: MemoryBlockReason,
+    ) -> MemoryDecision {
+        tracing::info!(
+            user_id = user_id.map(String::as_str).unwrap_or("-"),
+            key,
+            category,
+            ?reason,
+            "Memory write blocked"
+        );
+        if let Some(dispatcher) = &self.event_dispatcher {
+            dispatcher.emit(AgentEvent::MemoryBlocked(MemoryBlockedEvent {
+                metadata: EventMetadata::for_current_run("default"),
+                user_id: user_id.cloned(),
+                key: key.to_string(),
+                category: category.to_string(),
+                reason,
+            }));
+        }
+        MemoryDecision::Block {
+            category: category.to_string(),
+            reason,
+        }
+    }
+
+    /// Facts that must be forgotten now, with the reason: they expired, their
+    /// category was prohibited since, or the user withdrew consent to it. Consent is
+    /// not enforced when the user's consents cannot be loaded.
+    pub(crate) async fn stale<'a, I>(
+        &self,
+        user_id: Option<&UserId>,
+        facts: I,
+    ) -> Vec<(String, MemoryPurgeReason)>
+    where
+        I: IntoIterator<Item = (&'a String, Option<&'a str>, Option<DateTime<Utc>>)>,
+    {
+        let granted = self.granted(user_id).await;
+        let now = Utc::now();
+        facts
+            .into_iter()
+            .filter_map(|(key, category, expires_at)| {
+                let reason = if expires_at.is_some_and(|expires_at| expires_at <= now) {
+                    MemoryPurgeReason::Expired
+                } else {
+                    let category = category?;
+                    if self.prohibited.contains(category) {
+                        MemoryPurgeReason::Prohibited
+                    } else if self.consent_required.contains(category)
+                        && granted
+                            .as_ref()
+                            .is_some_and(|granted| !granted.contains(category))
+                    {
+                        MemoryPurgeReason::ConsentWithdrawn
+                    } else {
+                        return None;
+                    }
+                };
+                Some((key.clone(), reason))
+            })
+            .collect()
+    }
+
+    /// Remove the stale facts of a thread's profile, reporting them.
+    pub(crate) async fn enforce(&self, user_id: Option<&UserId>, profile: &mut UserProfile) {
+        let stale = self
+            .stale(
+                user_id,
+                profile
+                    .facts
+                    .iter()
+                    .map(|(key, fact)| (key, fact.category.as_deref(), fact.expires_at)),
+            )
+            .await;
+        for (key, _) in &stale {
+            profile.facts.remove(key);
+        }
+        self.report(user_id, stale);
+    }
+
+    /// Categories `user_id` consented to, `None` when unknown.
+    async fn granted(&self, user_id: Option<&UserId>) -> Option<BTreeSet<String>> {
+        let (Some(user_id), Some(consents)) = (user_id, &self.consents) else {
+            return Some(BTreeSet::new());
+        };
+        if self.consent_required.is_empty() {
+            return Some(BTreeSet::new());
+        }
+        match consents.granted(user_id).await {
+            Ok(granted) => Some(granted),
+            Err(e) => {
+                tracing::warn!(user_id = %user_id, "Failed to load user consents: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Report purged facts, one `MemoryPurged` event per reason.
+    pub(crate) fn report(
+        &self,
+        user_id: Option<&UserId>,
+        purged: Vec<(String, MemoryPurgeReason)>,
+    ) {
+        for reason in [
+            MemoryPurgeReason::Expired,
+            MemoryPurgeReason::Prohibited,
+            MemoryPurgeReason::ConsentWithdrawn,
+        ] {
+            let keys = purged
+                .iter()
+                .filter(|(_, purged)| *purged == reason)
+                .map(|(key, _)| key.clone())
+                .collect();
+            self.purged(user_id, keys, reason);
+        }
+    }
+
+    /// Report purged memories as a `MemoryPurged` event.
+    pub(crate) fn purged(
+        &self,
+        user_id: Option<&UserId>,
+        keys: Vec<String>,
+        reason: MemoryPurgeReason,
+    ) {
+        if keys.is_empty() {
+            return;
+        }
+        tracing::info!(
+            user_id = user_id.map(String::as_str).unwrap_or("-"),
+            keys = ?keys,
+            ?reason,
+            "Memories purged"
+        );
+        if let Some(dispatcher) = &self.event_dispatcher {
+            dispatcher.emit(AgentEvent::MemoryPurged(MemoryPurgedEvent {
+                metadata: EventMetadata::for_current_run("default"),
+                user_id: user_id.cloned(),
+                keys,
+                reason,
+            }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::user_profile::ProfileFact;
+
+    #[tokio::test]
+    async fn blocks_prohibited_and_unconsented_categories() {
+        let consents = Arc::new(InMemoryConsentStore::new());
+        let policy = MemoryPolicy::new(Arc::new(KeywordClassifier::default()))
+            .prohibit(["health"])
+            .require_consent(["contact"], consents.clone())
+            .retain("contact", Duration::days(30));
+        let ana = "ana".to_string();
+
+        assert!(matches!(
+            policy.review(Some(&ana), "allergy", "penicillin").await,
+            MemoryDecision::Block {
+                reason: MemoryBlockReason::Prohibited,
+                ..
+            }
+        ));
+        assert!(matches!(
+            policy.review(Some(&ana), "email", "ana@example.com").await,
+            MemoryDecision::Block {
+                reason: MemoryBlockReason::NoConsent,
+                ..
+            }
+        ));
+
+        consents.grant(&ana, "contact").await.unwrap();
+        let MemoryDecision::Allow {
+            category,
+            expires_at,
+        } = policy.review(Some(&ana), "email", "ana@example.com").await
+        else {
+            panic!("expected the email to be allowed");
+        };
+        assert_eq!(category.as_deref(), Some("contact"));
+        assert!(expires_at.unwrap() > Utc::now() + Duration::days(29));
+        assert!(matches!(
+            policy.review(None, "email", "ana@example.com").await,
+            MemoryDecision::Block { .. }
+        ));
+
+        assert_eq!(
+            policy.review(Some(&ana), "vehicle", "2019 Civic").await,
+            MemoryDecision::Allow {
+                category: None,
+                expires_at: None
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn purges_expired_and_withdrawn_facts() {
+        let consents = Arc::new(InMemoryConsentStore::new());
+        let policy = MemoryPolicy::new(Arc::new(KeywordClassifier::default()))
+            .require_consent(["contact"], consents.clone());
+        let ana = "ana".to_string();
+        consents.grant(&ana, "contact").await.unwrap();
+
+        let fact = |value: &str, category: Option<&str>, expires_at| ProfileFact {
+            value: value.to_string(),
+            confidence: 0.9,
+            source: String::new(),
+            updated_at: Utc::now(),
+            category: category.map(str::to_string),
+            expires_at,
+        };
+        let mut profile = UserProfile::default();
+        profile.set("email", fact("ana@example.com", Some("contact"), None));
+        profile.set(
+            "vehicle",
+            fact("2019 Civic", None, Some(Utc::now() - Duration::minutes(1))),
+        );
+        profile.set("name", fact("Ana", None, None));
+
+        policy.enforce(Some(&ana), &mut profile).await;
+        assert_eq!(profile.facts.len(), 2);
+        assert!(profile.get("vehicle").is_none());
+
+        consents.revoke(&ana, "contact").await.unwrap();
+        let stale = policy
+            .stale(
+                Some(&ana),
+                profile
+                    .facts
+                    .iter()
+                    .map(|(key, fact)| (key, fact.category.as_deref(), fact.expires_at)),
+            )
+            .await;
+        assert_eq!(
+            stale,
+            vec![("email".to_string(), MemoryPurgeReason::ConsentWithdrawn)]
+        );
+    }
+}
@@ -2,20 +2,149 @@
 //!
 //! This module contains the core Deep Agent implementation split into logical components:
 //! - `api`: Public API functions that mirror the Python SDK exactly
+//! - `approvals`: Recording HITL interrupts as pending approvals for out-of-process review
+//! - `benchmark`: Task suites loaded from JSONL, run for success rate, cost, latency and iterations
+//! - `builtin_skills`: Math, unit, date and text skills shipped with the SDK
+//! - `citations`: Requiring final answers to reference the run's tool results
+//! - `concurrency`: Limits on concurrent runs and tool executions
 //! - `config`: Configuration structs and builders
+//! - `debug_ui`: Local dashboard of threads, state, approvals and live events (`debug-ui` feature)
+//! - `debugger`: Stepping through recorded runs and diverging from edited tool results
+//! - `deterministic`: Seeded, frozen-time runs for reproducible debugging
+//! - `experiments`: Per-thread A/B assignment of prompt and model variants
+//! - `export`: Redacted thread exports for attaching to bug reports
+//! - `graph_memory`: Facts extracted from each turn into a knowledge graph, with query tools
+//! - `memory_policy`: Category blocking, consent and retention for stored user facts
+//! - `offload`: Moving large messages to blob storage behind references
 //! - `runtime`: Core DeepAgent runtime implementation
 //! - `builder`: Fluent builder pattern for agent construction
+//! - `output_sink`: Diverting long final responses to files or object storage
+//! - `profile`: Development, staging and production restrictions on tools, logging and budgets
+//! - `pool`: Built agents cached per configuration with LRU eviction
+//! - `prompt_snapshot`: Rendering the assembled system prompt for approval tests
+//! - `quotas`: Daily and monthly request and token quotas per tenant
+//! - `report`: Run journal and Markdown/JSON run reports
+//! - `run_trace`: Mermaid and Graphviz diagrams of a run
+//! - `run_options`: Per-call options such as provider keys, model and tool overrides
+//! - `self_test`: Startup checks of tools, models and tool providers
+//! - `shutdown`: Graceful shutdown draining in-flight runs
+//! - `skills`: Reusable capabilities bundling tools, instructions and sub-agents
+//! - `state_size`: Per-thread state size metrics, process totals and size thresholds
+//! - `stream_coalescing`: Merging streamed text deltas into larger chunks
+//! - `stream_tee`: Copying streamed runs to subscribers and broadcasters
+//! - `thread_affinity`: Per-thread leases for horizontally scaled deployments
+//! - `thread_titles`: Model-written titles and summaries kept in thread metadata
+//! - `tool_arguments`: Strict checking of model-provided tool arguments
+//! - `tool_conflicts`: Resolution of registered tools named like built-in tools
+//! - `tool_examples`: Few-shot tool usage examples rendered into the prompt
+//! - `tool_mocks`: Canned responses replacing real tools under a profile
+//! - `tool_output`: Per-tool output budgets for oversized tool results
+//! - `tool_retry`: Retrying failed tool calls with backoff and error feedback
+//! - `tool_selection`: Per-turn top-K tool selection for large registries
+//! - `translation`: Translating conversations to and from a working language
+//! - `user_memory`: User-scoped memories shared by a user's threads, with erasure
+//! - `validation`: Builder configuration checks reporting every problem at once
 
 pub mod api;
+pub mod approvals;
+pub mod benchmark;
 pub mod builder;
+pub mod builtin_skills;
+pub mod citations;
+pub mod concurrency;
 pub mod config;
+#[cfg(feature = "debug-ui")]
+pub mod debug_ui;
+pub mod debugger;
+pub mod deterministic;
+pub mod experiments;
+pub mod export;
+pub mod graph_memory;
+pub mod memory_policy;
+pub mod offload;
+pub mod output_sink;
+pub mod pool;
+pub mod profile;
+pub mod prompt_snapshot;
+pub mod quotas;
+pub mod report;
+pub mod run_options;
+pub mod run_trace;
 pub mod runtime;
+pub mod self_test;
+pub mod shutdown;
+pub mod skills;
+pub mod state_size;
+pub mod stream_coalescing;
+pub mod stream_tee;
+pub mod thread_affinity;
+pub mod thread_titles;
+pub mod tool_arguments;
+pub mod tool_conflicts;
+pub mod tool_examples;
+pub mod tool_mocks;
+pub mod tool_output;
+pub mod tool_retry;
+pub mod tool_selection;
+pub mod translation;
+pub mod user_memory;
+pub mod validation;
 
 // Re-export the main public API
 pub use api::{create_async_deep_agent, create_deep_agent, get_default_model};
+pub use approvals::ApprovalConfig;
+pub use benchmark::{
+    AnswerMatch, BenchmarkReport, BenchmarkRunner, BenchmarkSuite, BenchmarkSummary, BenchmarkTask,
+    TaskResult,
+};
 pub use builder::ConfigurableAgentBuilder;
+pub use builtin_skills::BuiltinSkill;
+pub use citations::CitationConfig;
+pub use concurrency::{
+    ConcurrencyConfig, ConcurrencyLimitError, ConcurrencyStats, PriorityWeights, RunPriority,
+};
 pub use config::{CreateDeepAgentParams, DeepAgentConfig, SubAgentConfig, SummarizationConfig};
+#[cfg(feature = "debug-ui")]
+pub use debug_ui::DebugUi;
+pub use debugger::{RecordedStep, RunDebugger, RunRecording};
+pub use deterministic::DeterministicConfig;
+pub use experiments::{Experiment, ExperimentVariant};
+pub use export::ThreadExport;
+pub use graph_memory::GraphMemoryConfig;
+pub use memory_policy::{
+    ConsentStore, InMemoryConsentStore, KeywordClassifier, MemoryClassifier, MemoryDecision,
+    MemoryPolicy,
+};
+pub use offload::OffloadConfig;
+pub use output_sink::OutputSinkConfig;
+pub use pool::{AgentPool, AgentPoolKey};
+pub use profile::{Profile, ProfileSettings};
+pub use prompt_snapshot::PromptSnapshot;
+pub use quotas::{QuotaAction, QuotaConfig, QuotaExceededError};
+pub use report::{RunJournal, RunReport, RunUsage, ToolCallRecord, ToolCallStatus};
+pub use run_options::RunOptions;
+pub use run_trace::{RunTrace, TraceStep};
 pub use runtime::DeepAgent;
+pub use self_test::{
+    SelfTestCheck, SelfTestCheckKind, SelfTestOptions, SelfTestReport, SelfTestStatus,
+};
+pub use shutdown::{AgentShutdownError, ShutdownReport};
+pub use skills::{Skill, SkillRequirement};
+pub use state_size::{StateMetrics, StateSize, StateSizeLimits};
+pub use stream_coalescing::{coalesce_stream, StreamCoalescing};
+pub use stream_tee::{TeedChunk, STREAM_SUBSCRIBER_BUFFER};
+pub use thread_affinity::{ThreadLockConfig, ThreadLockError};
+pub use thread_titles::ThreadTitleConfig;
+pub use tool_arguments::ToolArgumentValidation;
+pub use tool_conflicts::ToolConflictStrategy;
+pub use tool_examples::ToolExamplesConfig;
+pub use tool_mocks::{CannedResponse, ToolMock, ToolMocks};
+pub use tool_output::{ToolOutputConfig, ToolOutputStrategy};
+pub use tool_retry::{ToolRetryConfig, ToolRetryPolicy};
+pub use tool_selection::{KeywordToolSelector, ToolSelectionConfig, ToolSelector};
+pub use translation::{ModelTranslator, TranslationConfig, Translator};
+pub use user_memory::{UserErasure, UserMemoryConfig};
+pub use validation::{ConfigIssue, ConfigValidationError};
 
 #[cfg(test)]
 mod builtin_tools_parity_tests;
@@ -0,0 +1,360 @@
+//! Offloading of large messages to blob storage
+//!
+//! A tool result or user message longer than the threshold is stored in a
+//! [`BlobStore`] and replaced in the conversation history by a summary and an
+//! `offload://` reference. The agent gets a `read_offloaded` tool to page through
+//! the stored content, and a tool call whose argument is exactly a reference
+//! receives the full content in its place, so the body only re-enters memory
+//! when a tool needs it.
+
+use super::tool_output::{message_text, ToolOutputStrategy};
+use crate::middleware::token_tracking::TokenTrackingMiddleware;
+use agents_core::blob::BlobStore;
+use agents_core::messaging::{AgentMessage, MessageContent};
+use agents_core::tools::{Tool, ToolBox, ToolContext, ToolParameterSchema, ToolResult, ToolSchema};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Scheme of the references left in place of offloaded content
+pub const OFFLOAD_SCHEME: &str = "offload://";
+
+/// Name of the tool reading offloaded content
+pub const READ_OFFLOADED_TOOL: &str = "read_offloaded";
+
+/// Default size, in characters, above which a message is offloaded
+pub const DEFAULT_OFFLOAD_THRESHOLD_CHARS: usize = 50_000;
+
+/// Characters `read_offloaded` returns when no limit is given
+const DEFAULT_READ_CHARS: usize = 10_000;
+
+/// Configuration for offloading large messages.
+///
+/// # Example
+///
+/// ```ignore
+/// let agent = ConfigurableAgentBuilder::new("You are a research assistant")
+///     .with_model(model)
+///     .with_message_offloading(
+///         OffloadConfig::new(Arc::new(FileBlobStore::new("/var/lib/agent/blobs")))
+///             .with_threshold_chars(30_000)
+///             .with_summary(ToolOutputStrategy::Summarize(small_model)),
+///     )
+///     .build()?;
+/// ```
+#[derive(Clone)]
+pub struct OffloadConfig {
+    pub store: Arc<dyn BlobStore>,
+    /// Messages longer than this many characters are offloaded
+    pub threshold_chars: usize,
+    /// Characters of summary or preview kept in the history (default: 2000)
+    pub summary_chars: usize,
+    /// How the kept summary is made (default: head and tail of the content)
+    pub summary: ToolOutputStrategy,
+    /// Blob key prefix of offloaded content (default: `offloaded`)
+    pub key_prefix: String,
+}
+
+impl std::fmt::Debug for OffloadConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OffloadConfig")
+            .field("threshold_chars", &self.threshold_chars)
+            .field("summary_chars", &self.summary_chars)
+            .field("summary", &self.summary)
+            .field("key_prefix", &self.key_prefix)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OffloadConfig {
+    pub fn new(store: Arc<dyn BlobStore>) -> Self {
+        Self {
+            store,
+            threshold_chars: DEFAULT_OFFLOAD_THRESHOLD_CHARS,
+            summary_chars: 2_000,
+            summary: ToolOutputStrategy::HeadTail,
+            key_prefix: "offloaded".to_string(),
+        }
+    }
+
+    pub fn with_threshold_chars(mut self, chars: usize) -> Self {
+        self.threshold_chars = chars;
+        self
+    }
+
+    pub fn with_summary_chars(mut self, chars: usize) -> Self {
+        self.summary_chars = chars;
+        self
+    }
+
+    pub fn with_summary(mut self, summary: ToolOutputStrategy) -> Self {
+        self.summary = summary;
+        self
+    }
+
+    pub fn with_key_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.key_prefix = prefix.into().trim_matches('/').to_string();
+        self
+    }
+
+    /// Route the summarizer's requests through `tracker` so their cost is attributed.
+    pub(crate) fn tracked_by(mut self, tracker: &TokenTrackingMiddleware) -> Self {
+        if let ToolOutputStrategy::Summarize(model) = self.summary {
+            self.summary = ToolOutputStrategy::Summarize(Arc::new(tracker.share(model)));
+        }
+        self
+    }
+
+    /// The `read_offloaded` tool.
+    pub fn tools(&self) -> Vec<ToolBox> {
+        vec![Arc::new(ReadOffloadedTool {
+            config: self.clone(),
+        })]
+    }
+
+    /// Blob key a reference points to, if it is a reference to content offloaded
+    /// under this configuration's prefix.
+    fn key_of<'a>(&self, reference: &'a str) -> Option<&'a str> {
+        let key = reference.strip_prefix(OFFLOAD_SCHEME)?;
+        let name = key.strip_prefix(&self.key_prefix)?.strip_prefix('/')?;
+        (!name.is_empty()).then_some(key)
+    }
+
+    async fn load(&self, reference: &str) -> anyhow::Result<String> {
+        let key = self.key_of(reference).ok_or_else(|| {
+            anyhow::anyhow!("'{reference}' is not a reference to offloaded content")
+        })?;
+        let blob =
+            self.store.get(key).await?.ok_or_else(|| {
+                anyhow::anyhow!("Offloaded content '{reference}' no longer exists")
+            })?;
+        Ok(String::from_utf8(blob.bytes)?)
+    }
+
+    /// Store a message longer than the threshold and return the message keeping its
+    /// summary and reference instead. Images, and messages the store fails to
+    /// take, are returned as they are.
+    pub(crate) async fn offload(&self, source: &str, message: AgentMessage) -> AgentMessage {
+        if matches!(message.content, MessageContent::Image { .. }) {
+            return message;
+        }
+        let text = message_text(&message);
+        let total_chars = text.chars().count();
+        if total_chars <= self.threshold_chars {
+            return message;
+        }
+
+        let id = agents_core::determinism::next_uuid().simple().to_string();
+        let key = format!("{}/{}.txt", self.key_prefix, id);
+        let summary = self.summary.shorten(&text, self.summary_chars).await;
+        if let Err(e) = self
+            .store
+            .put(&key, text.into_bytes(), "text/plain; charset=utf-8")
+            .await
+        {
+            tracing::warn!(source, error = %e, "Failed to offload large message; keeping it in the history");
+            return message;
+        }
+        let reference = format!("{OFFLOAD_SCHEME}{key}");
+        tracing::info!(source, chars = total_chars, reference = %reference, "📦 Large message offloaded to blob storage");
+
+        AgentMessage {
+            role: message.role,
+            content: MessageContent::Text(format!(
+                "{}\n\n[{} characters offloaded to {}. Read them with {}, or pass {} as a tool argument to give the tool the full content.]",
+                summary, total_chars, reference, READ_OFFLOADED_TOOL, reference
+            )),
+            metadata: message.metadata,
+        }
+    }
+
+    /// Replace every string argument of `tool_name` that is a reference to offloaded
+    /// content with the content itself.
+    pub(crate) async fn rehydrate(
+        &self,
+        tool_name: &str,
+        mut args: Value,
+    ) -> anyhow::Result<Value> {
+        if tool_name != READ_OFFLOADED_TOOL {
+            self.rehydrate_value(&mut args).await?;
+        }
+        Ok(args)
+    }
+
+    async fn rehydrate_value(&self, value: &mut Value) -> anyhow::Result<()> {
+        let mut pending = vec![value];
+        while let Some(value) = pending.pop() {
+            match value {
+                Value::String(text) if self.key_of(text).is_some() => {
+                    *text = self.load(text).await?;
+                }
+                Value::Array(items) => pending.extend(items.iter_mut()),
+                Value::Object(fields) => pending.extend(fields.values_mut()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Pages through offloaded content
+struct ReadOffloadedTool {
+    config: OffloadConfig,
+}
+
+#[async_trait]
+impl Tool for ReadOffloadedTool {
+    fn schema(&self) -> ToolSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "reference".to_string(),
+            ToolParameterSchema::string("The offload:// reference of the content"),
+        );
+        properties.insert(
+            "offset".to_string(),
+            ToolParameterSchema::integer("Character to start reading at (default: 0)")
+                .with_minimum(0),
+        );
+        properties.insert(
+            "limit".to_string(),
+            ToolParameterSchema::integer("Most characters to return (default: 10000)")
+                .with_minimum(1),
+        );
+        ToolSchema::new(
+            READ_OFFLOADED_TOOL,
+            "Read part of a large message or tool result that was offloaded from the conversation.",
+            ToolParameterSchema::object(
+                "Offloaded content to read",
+                properties,
+                vec!["reference".to_string()],
+            ),
+        )
+    }
+
+    async fn execute(&self, args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+        let reference = args
+            .get("reference")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow::anyhow!("Missing required parameter: reference"))?;
+        let offset = args.get("offset").and_then(Value::as_u64).unwrap_or(0) as usize;
+        let limit = args
+            .get("limit")
+            .and_then(Value::as_u64)
+            .map_or(DEFAULT_READ_CHARS, |limit| limit as usize);
+
+        let content = self.config.load(reference).await?;
+        let total = content.chars().count();
+        let offset = offset.min(total);
+        let part: String = content.chars().skip(offset).take(limit).collect();
+        let end = offset.saturating_add(limit).min(total);
+        let text = if end < total {
+            format!("{part}\n\n[Characters {offset}-{end} of {total}; continue with offset {end}.]")
+        } else {
+            part
+        };
+        Ok(ToolResult::text(&ctx, text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::blob::InMemoryBlobStore;
+    use agents_core::messaging::MessageRole;
+    use agents_core::state::AgentStateSnapshot;
+
+    fn tool_message(text: String) -> AgentMessage {
+        AgentMessage {
+            role: MessageRole::Tool,
+            content: MessageContent::Text(text),
+            metadata: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn large_messages_are_offloaded_and_rehydrated_for_tools() {
+        let store = Arc::new(InMemoryBlobStore::new());
+        let config = OffloadConfig::new(store.clone())
+            .with_threshold_chars(100)
+            .with_summary_chars(30);
+
+        let small = config
+            .offload("tool:web", tool_message("short".into()))
+            .await;
+        assert_eq!(small.content, MessageContent::Text("short".into()));
+
+        let body = "x".repeat(500);
+        let offloaded = config.offload("tool:web", tool_message(body.clone())).await;
+        let text = offloaded.content.as_text().unwrap();
+        assert!(text.contains("500 characters offloaded to offload://offloaded/"));
+        assert!(!text.contains(&body[..100]));
+        let [key] = store.keys().try_into().unwrap();
+        let reference = format!("{OFFLOAD_SCHEME}{key}");
+
+        let args = serde_json::json!({ "to": "ana", "attachments": [reference.clone()] });
+        let args = config.rehydrate("send_email", args).await.unwrap();
+        assert_eq!(args["attachments"][0], body);
+        assert_eq!(args["to"], "ana");
+        // The reader gets the reference itself
+        let args = serde_json::json!({ "reference": reference.clone() });
+        let args = config.rehydrate(READ_OFFLOADED_TOOL, args).await.unwrap();
+        assert_eq!(args["reference"], reference);
+        // Blobs outside the prefix are not exposed
+        let args = serde_json::json!({ "path": "offload://charts/a.png" });
+        assert_eq!(
+            config.rehydrate("send_email", args.clone()).await.unwrap(),
+            args
+        );
+    }
+
+    #[tokio::test]
+    async fn read_offloaded_pages_through_content() {
+        let store = Arc::new(InMemoryBlobStore::new());
+        store
+            .put("offloaded/a.txt", "0123456789".into(), "text/plain")
+            .await
+            .unwrap();
+        let tool = OffloadConfig::new(store).tools().remove(0);
+        let ctx = ToolContext::new(Arc::new(AgentStateSnapshot::default()));
+
+        let read = |args: Value| {
+            let tool = tool.clone();
+            let ctx = ctx.clone();
+            async move {
+                match tool.execute(args, ctx).await.unwrap() {
+                    ToolResult::Message(message) => message.content.to_text(),
+                    _ => panic!("expected a message"),
+                }
+            }
+        };
+        let first =
+            read(serde_json::json!({ "reference": "offload://offloaded/a.txt", "limit": 4 })).await;
+        assert_eq!(
+            first,
+            "0123\n\n[Characters 0-4 of 10; continue with offset 4.]"
+        );
+        let rest =
+            read(serde_json::json!({ "reference": "offload://offloaded/a.txt", "offset": 4 }))
+                .await;
+        assert_eq!(rest, "456789");
+        let huge = read(serde_json::json!({
+            "reference": "offload://offloaded/a.txt",
+            "offset": 1,
+            "limit": u64::MAX,
+        }))
+        .await;
+        assert_eq!(huge, "123456789");
+        let past_end =
+            read(serde_json::json!({ "reference": "offload://offloaded/a.txt", "offset": 50 }))
+                .await;
+        assert_eq!(past_end, "");
+        assert!(tool
+            .execute(
+                serde_json::json!({ "reference": "offload://offloaded/missing.txt" }),
+                ctx.clone()
+            )
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,178 @@
+//! Diverting long final responses to an output sink
+//!
+//! Long reports bloat the conversation history that is sent back to the model on
+//! every turn and persisted by the checkpointer. When an output sink is configured,
+//! final responses above a size threshold are streamed to the sink (a file, an S3
+//! object, any writer) and the chat receives a short confirmation with a preview and
+//! the location of the full text.
+
+use agents_core::sink::OutputSink;
+use std::sync::Arc;
+
+/// Responses shorter than this many characters stay in the chat by default.
+pub const DEFAULT_SINK_MIN_CHARS: usize = 4_000;
+
+/// Characters of the response kept in the confirmation message by default.
+pub const DEFAULT_SINK_PREVIEW_CHARS: usize = 300;
+
+/// Size of the chunks written to the sink.
+const CHUNK_CHARS: usize = 8_192;
+
+/// Configuration for diverting long final responses to an [`OutputSink`].
+///
+/// # Example
+///
+/// ```ignore
+/// let agent = ConfigurableAgentBuilder::new("You write long research reports")
+///     .with_model(model)
+///     .with_output_sink_config(
+///         OutputSinkConfig::new(Arc::new(FileOutputSink::new("./reports")))
+///             .with_min_chars(10_000),
+///     )
+///     .build()?;
+/// ```
+#[derive(Clone)]
+pub struct OutputSinkConfig {
+    pub sink: Arc<dyn OutputSink>,
+    /// Only responses with at least this many characters are diverted
+    pub min_chars: usize,
+    /// Characters of the response repeated in the confirmation message
+    pub preview_chars: usize,
+}
+
+impl std::fmt::Debug for OutputSinkConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OutputSinkConfig")
+            .field("min_chars", &self.min_chars)
+            .field("preview_chars", &self.preview_chars)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OutputSinkConfig {
+    pub fn new(sink: Arc<dyn OutputSink>) -> Self {
+        Self {
+            sink,
+            min_chars: DEFAULT_SINK_MIN_CHARS,
+            preview_chars: DEFAULT_SINK_PREVIEW_CHARS,
+        }
+    }
+
+    pub fn with_min_chars(mut self, min_chars: usize) -> Self {
+        self.min_chars = min_chars;
+        self
+    }
+
+    pub fn with_preview_chars(mut self, preview_chars: usize) -> Self {
+        self.preview_chars = preview_chars;
+        self
+    }
+
+    /// Stream `text` to the sink under `name` and return the confirmation message that
+    /// replaces it in the chat, or `None` when the text is below the threshold.
+    pub(crate) async fn divert(&self, name: &str, text: &str) -> anyhow::Result<Option<String>> {
+        let total_chars = text.chars().count();
+        if total_chars < self.min_chars {
+            return Ok(None);
+        }
+
+        let mut writer = self.sink.create(name).await?;
+        let mut rest = text;
+        while !rest.is_empty() {
+            let split = rest
+                .char_indices()
+                .nth(CHUNK_CHARS)
+                .map(|(idx, _)| idx)
+                .unwrap_or(rest.len());
+            writer.write(&rest[..split]).await?;
+            rest = &rest[split..];
+        }
+        let location = writer.finish().await?;
+
+        let note = format!(
+            "[Full response ({} characters) saved to {}]",
+            total_chars, location
+        );
+        if self.preview_chars == 0 {
+            return Ok(Some(note));
+        }
+        let preview: String = text.chars().take(self.preview_chars).collect();
+        Ok(Some(format!("{}…\n\n{}", preview.trim_end(), note)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::sink::InMemoryOutputSink;
+
+    #[tokio::test]
+    async fn diverts_only_long_responses() {
+        let sink = InMemoryOutputSink::new();
+        let config = OutputSinkConfig::new(Arc::new(sink.clone()))
+            .with_min_chars(20)
+            .with_preview_chars(5);
+
+        assert!(config.divert("short.md", "tiny").await.unwrap().is_none());
+        assert!(sink.names().is_empty());
+
+        let report = "é".repeat(CHUNK_CHARS + 10);
+        let confirmation = config.divert("long.md", &report).await.unwrap().unwrap();
+        assert_eq!(sink.get("long.md").unwrap(), report);
+        assert!(confirmation.starts_with("ééééé…"));
+        assert!(confirmation.ends_with(&format!(
+            "[Full response ({} characters) saved to memory://long.md]",
+            CHUNK_CHARS + 10
+        )));
+    }
+
+    struct LongAnswerPlanner;
+
+    #[async_trait::async_trait]
+    impl agents_core::agent::PlannerHandle for LongAnswerPlanner {
+        async fn plan(
+            &self,
+            _context: agents_core::agent::PlannerContext,
+            _state: Arc<agents_core::state::AgentStateSnapshot>,
+        ) -> anyhow::Result<agents_core::agent::PlannerDecision> {
+            use agents_core::agent::{PlannerAction, PlannerDecision};
+            use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+
+            Ok(PlannerDecision {
+                next_action: PlannerAction::Respond {
+                    message: AgentMessage {
+                        role: MessageRole::Agent,
+                        content: MessageContent::Text("Quarterly report. ".repeat(50)),
+                        metadata: None,
+                    },
+                },
+            })
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    #[tokio::test]
+    async fn agent_returns_confirmation_for_long_answers() {
+        use crate::agent::{config::DeepAgentConfig, runtime::create_deep_agent_from_config};
+        use agents_core::state::AgentStateSnapshot;
+
+        let sink = InMemoryOutputSink::new();
+        let config = DeepAgentConfig::new("assist", Arc::new(LongAnswerPlanner))
+            .with_output_sink(OutputSinkConfig::new(Arc::new(sink.clone())).with_min_chars(100));
+        let agent = create_deep_agent_from_config(config);
+
+        let reply = agent
+            .handle_message("Write the report", Arc::new(AgentStateSnapshot::default()))
+            .await
+            .unwrap();
+        let reply = reply.content.as_text().unwrap().to_string();
+
+        let name = format!("deep-agent/{}.md", agent.last_run_id().unwrap());
+        assert_eq!(sink.get(&name).unwrap(), "Quarterly report. ".repeat(50));
+        assert!(reply.contains("[Full response (900 characters) saved to memory://deep-agent/"));
+        assert!(reply.chars().count() < 450);
+    }
+}
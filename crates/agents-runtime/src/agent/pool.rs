@@ -0,0 +1,217 @@
+//! Pooling built agents per configuration
+//!
+//! Servers with per-tenant agent variants (model, tool set, prompt) would otherwise
+//! assemble the middleware stack on every request. An [`AgentPool`] builds each
+//! variant once through a factory and keeps the most recently used ones, evicting the
+//! least recently used variant when full. Variants that only differ in the model,
+//! tool subset or limits of a run are better served by one agent with
+//! [`RunOptions`](super::run_options::RunOptions).
+
+use super::runtime::DeepAgent;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::Hash;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+
+/// Default number of agents kept by a pool.
+pub const DEFAULT_POOL_CAPACITY: usize = 64;
+
+/// Configuration an agent is built for: the tenant, model and tool set.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct AgentPoolKey {
+    pub tenant: Option<String>,
+    pub model: Option<String>,
+    pub tools: BTreeSet<String>,
+}
+
+impl AgentPoolKey {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tenant = Some(tenant.into());
+        self
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    pub fn with_tools<I, S>(mut self, tools: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tools = tools.into_iter().map(Into::into).collect();
+        self
+    }
+}
+
+type AgentFactory<K> = dyn Fn(&K) -> anyhow::Result<DeepAgent> + Send + Sync;
+
+struct PooledAgent {
+    agent: Arc<DeepAgent>,
+    last_used: u64,
+}
+
+struct PoolEntries<K> {
+    agents: HashMap<K, PooledAgent>,
+    clock: u64,
+}
+
+/// Built agents keyed by configuration, with least-recently-used eviction.
+///
+/// # Example
+///
+/// ```ignore
+/// let pool = AgentPool::new(move |key: &AgentPoolKey| {
+///     ConfigurableAgentBuilder::new(prompts.for_tenant(key.tenant.as_deref()))
+///         .with_model(models.get(key.model.as_deref())?)
+///         .with_tools(registry.select(&key.tools))
+///         .build()
+/// })
+/// .with_capacity(128);
+///
+/// let key = AgentPoolKey::new().with_tenant(&tenant.id).with_model(&tenant.model);
+/// let agent = pool.get(&key)?;
+/// let reply = agent.handle_message(text, state).await?;
+/// ```
+pub struct AgentPool<K = AgentPoolKey> {
+    factory: Arc<AgentFactory<K>>,
+    capacity: NonZeroUsize,
+    entries: Mutex<PoolEntries<K>>,
+}
+
+impl<K: Clone + Eq + Hash> AgentPool<K> {
+    /// A pool building missing agents with `factory`.
+    pub fn new(factory: impl Fn(&K) -> anyhow::Result<DeepAgent> + Send + Sync + 'static) -> Self {
+        Self {
+            factory: Arc::new(factory),
+            capacity: NonZeroUsize::new(DEFAULT_POOL_CAPACITY).unwrap(),
+            entries: Mutex::new(PoolEntries {
+                agents: HashMap::new(),
+                clock: 0,
+            }),
+        }
+    }
+
+    /// Keep at most `capacity` agents (default: [`DEFAULT_POOL_CAPACITY`]).
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = NonZeroUsize::new(capacity).expect("pool capacity must be greater than 0");
+        self
+    }
+
+    /// The agent for `key`, built on first use.
+    ///
+    /// Evicted agents stay usable by callers still holding them. Concurrent misses
+    /// for the same key may build it more than once; one of the agents is kept.
+    pub fn get(&self, key: &K) -> anyhow::Result<Arc<DeepAgent>> {
+        if let Some(agent) = self.lookup(key) {
+            return Ok(agent);
+        }
+        let built = Arc::new((self.factory)(key)?);
+
+        let mut entries = self.lock();
+        entries.clock += 1;
+        let now = entries.clock;
+        if let Some(pooled) = entries.agents.get_mut(key) {
+            pooled.last_used = now;
+            return Ok(pooled.agent.clone());
+        }
+        if entries.agents.len() >= self.capacity.get() {
+            let oldest = entries
+                .agents
+                .iter()
+                .min_by_key(|(_, pooled)| pooled.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.agents.remove(&oldest);
+                tracing::debug!(capacity = self.capacity.get(), "Evicted pooled agent");
+            }
+        }
+        entries.agents.insert(
+            key.clone(),
+            PooledAgent {
+                agent: built.clone(),
+                last_used: now,
+            },
+        );
+        Ok(built)
+    }
+
+    /// Drop the agent for `key`, e.g. after the tenant's configuration changed.
+    pub fn invalidate(&self, key: &K) {
+        self.lock().agents.remove(key);
+    }
+
+    /// Drop all agents.
+    pub fn clear(&self) {
+        self.lock().agents.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().agents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lookup(&self, key: &K) -> Option<Arc<DeepAgent>> {
+        let mut entries = self.lock();
+        entries.clock += 1;
+        let now = entries.clock;
+        entries.agents.get_mut(key).map(|pooled| {
+            pooled.last_used = now;
+            pooled.agent.clone()
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, PoolEntries<K>> {
+        // The entries stay consistent even if a holder panicked
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::ConfigurableAgentBuilder;
+    use crate::test_support::EchoModel;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn least_recently_used_agent_is_evicted() {
+        let builds = Arc::new(AtomicUsize::new(0));
+        let counter = builds.clone();
+        let pool = AgentPool::new(move |key: &AgentPoolKey| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            ConfigurableAgentBuilder::new(format!("Serve {:?}", key.tenant))
+                .with_model(Arc::new(EchoModel("")))
+                .build()
+        })
+        .with_capacity(2);
+        let tenant = |name: &str| AgentPoolKey::new().with_tenant(name).with_model("gpt-4o");
+
+        let acme = pool.get(&tenant("acme")).unwrap();
+        pool.get(&tenant("globex")).unwrap();
+        assert!(Arc::ptr_eq(&acme, &pool.get(&tenant("acme")).unwrap()));
+        assert_eq!(builds.load(Ordering::SeqCst), 2);
+
+        // globex is the least recently used
+        pool.get(&tenant("initech")).unwrap();
+        assert_eq!(pool.len(), 2);
+        pool.get(&tenant("acme")).unwrap();
+        assert_eq!(builds.load(Ordering::SeqCst), 3);
+        pool.get(&tenant("globex")).unwrap();
+        assert_eq!(builds.load(Ordering::SeqCst), 4);
+
+        pool.invalidate(&tenant("globex"));
+        assert_eq!(pool.len(), 1);
+    }
+}
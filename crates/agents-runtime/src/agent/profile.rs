@@ -0,0 +1,234 @@
+//! Deployment profiles restricting an agent per environment
+//!
+//! One builder describes the agent for every environment; [`Profile`] picks how much
+//! it may do where it runs. Each profile has [`ProfileSettings`] listing tools that are
+//! removed or must go through human approval, whether tool payloads and token usage are
+//! logged, caps on the iteration and tool output budgets, and tools answered by mocks
+//! instead of their real implementations. The caps only lower what the builder
+//! configures, so a profile never widens an agent.
+//!
+//! ```ignore
+//! let agent = ConfigurableAgentBuilder::new("You are a support agent")
+//!     .with_tools(tools)
+//!     .with_profile_settings(
+//!         Profile::Production,
+//!         ProfileSettings::production()
+//!             .disable_tool("run_sql")
+//!             .require_approval("issue_refund", "Refunds are reviewed in production"),
+//!     )
+//!     .with_profile(Profile::from_env()?)
+//!     .with_checkpointer(checkpointer)
+//!     .build()?;
+//! ```
+
+use super::tool_mocks::ToolMocks;
+use crate::middleware::HitlPolicy;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::str::FromStr;
+
+/// Environment variable read by [`Profile::from_env`].
+pub const PROFILE_ENV_VAR: &str = "AGENT_PROFILE";
+
+/// Environment an agent is built for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Profile {
+    /// No restrictions beyond the builder's own configuration
+    #[default]
+    Development,
+    Staging,
+    Production,
+}
+
+impl Profile {
+    /// The profile named by `AGENT_PROFILE`, or development when it is unset.
+    pub fn from_env() -> anyhow::Result<Self> {
+        match std::env::var(PROFILE_ENV_VAR) {
+            Ok(name) => name.parse(),
+            Err(std::env::VarError::NotPresent) => Ok(Self::Development),
+            Err(e) => Err(anyhow::anyhow!("{PROFILE_ENV_VAR}: {e}")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Development => "development",
+            Self::Staging => "staging",
+            Self::Production => "production",
+        }
+    }
+
+    /// Settings used when the builder has none for this profile.
+    pub fn default_settings(&self) -> ProfileSettings {
+        match self {
+            Self::Development => ProfileSettings::development(),
+            Self::Staging => ProfileSettings::staging(),
+            Self::Production => ProfileSettings::production(),
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Profile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "dev" | "development" => Ok(Self::Development),
+            "stage" | "staging" => Ok(Self::Staging),
+            "prod" | "production" => Ok(Self::Production),
+            other => Err(anyhow::anyhow!(
+                "unknown profile '{other}', expected development, staging or production"
+            )),
+        }
+    }
+}
+
+/// What an agent may do under a [`Profile`].
+#[derive(Debug, Clone)]
+pub struct ProfileSettings {
+    /// Tools removed from the agent, whether registered, built in or provided
+    pub disabled_tools: HashSet<String>,
+    /// Tools paused for approval; a `with_tool_interrupt` for the same tool wins
+    pub approval_required: HashMap<String, HitlPolicy>,
+    /// Log tool call payloads and per-request token usage
+    pub verbose_logging: bool,
+    /// Keep PII sanitization of event previews on even if the builder turns it off
+    pub force_pii_sanitization: bool,
+    /// Upper bound on the builder's `max_iterations`
+    pub max_iterations: Option<NonZeroUsize>,
+    /// Upper bound on tool output budgets, in characters
+    pub max_tool_output_chars: Option<usize>,
+    /// Tools whose calls are answered by mocks, such as payment and notification
+    /// APIs in staging
+    pub tool_mocks: ToolMocks,
+}
+
+impl ProfileSettings {
+    /// No restrictions, verbose logging.
+    pub fn development() -> Self {
+        Self {
+            disabled_tools: HashSet::new(),
+            approval_required: HashMap::new(),
+            verbose_logging: true,
+            force_pii_sanitization: false,
+            max_iterations: None,
+            max_tool_output_chars: None,
+            tool_mocks: ToolMocks::new(),
+        }
+    }
+
+    /// Production budgets with verbose logging left on for debugging.
+    pub fn staging() -> Self {
+        Self {
+            verbose_logging: true,
+            ..Self::production()
+        }
+    }
+
+    /// No payload logging, PII sanitization forced, at most 10 iterations and
+    /// 20 000 characters per tool output.
+    pub fn production() -> Self {
+        Self {
+            disabled_tools: HashSet::new(),
+            approval_required: HashMap::new(),
+            verbose_logging: false,
+            force_pii_sanitization: true,
+            max_iterations: NonZeroUsize::new(10),
+            max_tool_output_chars: Some(super::tool_output::DEFAULT_TOOL_OUTPUT_MAX_CHARS),
+            tool_mocks: ToolMocks::new(),
+        }
+    }
+
+    /// Remove `tool_name` from the agent.
+    pub fn disable_tool(mut self, tool_name: impl Into<String>) -> Self {
+        self.disabled_tools.insert(tool_name.into());
+        self
+    }
+
+    /// Pause calls to `tool_name` for a reviewer, showing them `note`.
+    pub fn require_approval(
+        mut self,
+        tool_name: impl Into<String>,
+        note: impl Into<String>,
+    ) -> Self {
+        self.approval_required.insert(
+            tool_name.into(),
+            HitlPolicy {
+                allow_auto: false,
+                note: Some(note.into()),
+            },
+        );
+        self
+    }
+
+    pub fn with_verbose_logging(mut self, enabled: bool) -> Self {
+        self.verbose_logging = enabled;
+        self
+    }
+
+    pub fn with_force_pii_sanitization(mut self, enabled: bool) -> Self {
+        self.force_pii_sanitization = enabled;
+        self
+    }
+
+    /// Cap the builder's `max_iterations` at `max_iterations`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_iterations` is 0.
+    pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations =
+            Some(NonZeroUsize::new(max_iterations).expect("max_iterations must be greater than 0"));
+        self
+    }
+
+    /// Cap every tool output budget at `max_chars`.
+    pub fn with_max_tool_output_chars(mut self, max_chars: usize) -> Self {
+        self.max_tool_output_chars = Some(max_chars);
+        self
+    }
+
+    /// Answer the calls of the tools in `mocks` with their mocks.
+    pub fn with_tool_mocks(mut self, mocks: ToolMocks) -> Self {
+        self.tool_mocks = mocks;
+        self
+    }
+}
+
+impl Default for ProfileSettings {
+    fn default() -> Self {
+        Self::development()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_parse_from_short_and_long_names() {
+        assert_eq!("prod".parse::<Profile>().unwrap(), Profile::Production);
+        assert_eq!(" Staging ".parse::<Profile>().unwrap(), Profile::Staging);
+        assert_eq!("dev".parse::<Profile>().unwrap(), Profile::Development);
+        assert!("qa".parse::<Profile>().is_err());
+        assert_eq!(Profile::Production.to_string(), "production");
+    }
+
+    #[test]
+    fn staging_keeps_production_budgets_with_verbose_logging() {
+        let staging = ProfileSettings::staging();
+        assert!(staging.verbose_logging);
+        assert_eq!(
+            staging.max_iterations,
+            ProfileSettings::production().max_iterations
+        );
+        assert!(!ProfileSettings::production().verbose_logging);
+    }
+}
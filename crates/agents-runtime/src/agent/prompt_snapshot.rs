@@ -0,0 +1,321 @@
+//! Approval tests for the assembled system prompt
+//!
+//! The system prompt an agent sends is assembled from the built-in prompts of the
+//! SDK, the middleware stack and the agent's own instructions. A [`PromptSnapshot`]
+//! captures what the model sees on the first turn of a new thread so it can be
+//! committed next to the tests and compared in CI: an SDK upgrade that changes a
+//! built-in prompt then shows up as a failing test with a line diff instead of a
+//! silent change in agent behavior.
+//!
+//! ```ignore
+//! #[tokio::test]
+//! async fn support_agent_prompt_is_unchanged() {
+//!     let agent = build_support_agent();
+//!     agent
+//!         .prompt_snapshot()
+//!         .await
+//!         .unwrap()
+//!         .assert_matches("tests/snapshots/support_agent.prompt.md");
+//! }
+//! ```
+//!
+//! Missing snapshot files are written on the first run. Set
+//! `UPDATE_PROMPT_SNAPSHOTS=1` to accept changes and rewrite existing ones.
+
+use agents_core::tools::ToolSchema;
+use chrono::{DateTime, TimeZone, Utc};
+use serde_json::Value;
+use std::path::Path;
+
+/// Environment variable that makes [`PromptSnapshot::assert_matches`] rewrite
+/// snapshots that differ instead of failing.
+pub const UPDATE_SNAPSHOTS_ENV: &str = "UPDATE_PROMPT_SNAPSHOTS";
+
+/// Unchanged lines shown around each change in a diff
+const DIFF_CONTEXT: usize = 3;
+
+/// The system prompt and tool schemas an agent sends on the first turn of a new
+/// thread, created with [`DeepAgent::prompt_snapshot`](super::DeepAgent::prompt_snapshot).
+#[derive(Debug, Clone)]
+pub struct PromptSnapshot {
+    pub system_prompt: String,
+    /// Tool schemas exposed to the model, sorted by name
+    pub tools: Vec<ToolSchema>,
+}
+
+impl PromptSnapshot {
+    /// Time reported by the time context in snapshots, so they are reproducible.
+    pub fn rendered_at() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap()
+    }
+
+    /// The snapshot as Markdown: the system prompt followed by every tool with
+    /// its description and parameters.
+    pub fn render(&self) -> String {
+        let mut text = format!(
+            "# System Prompt\n\n{}\n\n# Tools\n",
+            self.system_prompt.trim()
+        );
+        for tool in &self.tools {
+            let parameters = serde_json::to_value(&tool.parameters)
+                .and_then(|value| serde_json::to_string_pretty(&sort_keys(value)))
+                .unwrap_or_else(|_| "{}".into());
+            text.push_str(&format!(
+                "\n## {}\n\n{}\n\n```json\n{}\n```\n",
+                tool.name,
+                tool.description.trim(),
+                parameters
+            ));
+        }
+        text
+    }
+
+    /// Line diff from `expected` to the rendered snapshot, `None` when they match.
+    pub fn diff(&self, expected: &str) -> Option<String> {
+        diff_lines(expected, &self.render())
+    }
+
+    /// Compare with the snapshot stored at `path`.
+    ///
+    /// A missing snapshot is written and the assertion passes. A differing one fails
+    /// with a diff, unless `UPDATE_PROMPT_SNAPSHOTS` is set, in which case it is
+    /// rewritten.
+    ///
+    /// # Panics
+    ///
+    /// When the rendered snapshot differs from the stored one, or the file cannot be
+    /// read or written.
+    pub fn assert_matches(&self, path: impl AsRef<Path>) {
+        let path = path.as_ref();
+        let rendered = self.render();
+        let update = std::env::var_os(UPDATE_SNAPSHOTS_ENV).is_some_and(|value| value != "0");
+        let write = |reason: &str| {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir).unwrap_or_else(|e| {
+                    panic!("Failed to create {}: {}", dir.display(), e);
+                });
+            }
+            std::fs::write(path, &rendered).unwrap_or_else(|e| {
+                panic!("Failed to write {}: {}", path.display(), e);
+            });
+            tracing::info!(path = %path.display(), "{}", reason);
+        };
+
+        let expected = match std::fs::read_to_string(path) {
+            Ok(expected) => expected,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                write("Prompt snapshot written");
+                return;
+            }
+            Err(e) => panic!("Failed to read {}: {}", path.display(), e),
+        };
+        let Some(diff) = diff_lines(&expected, &rendered) else {
+            return;
+        };
+        if update {
+            write("Prompt snapshot updated");
+            return;
+        }
+        panic!(
+            "Prompt snapshot {} does not match (- stored, + current). \
+             Rerun with {}=1 to accept the change.\n\n{}",
+            path.display(),
+            UPDATE_SNAPSHOTS_ENV,
+            diff
+        );
+    }
+}
+
+/// Sort object keys recursively; parameter properties come from a `HashMap` and
+/// `serde_json` may preserve insertion order.
+fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, sort_keys(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(sort_keys).collect()),
+        other => other,
+    }
+}
+
+/// Unified-style line diff from `expected` to `actual`, `None` when they are equal.
+///
+/// Removed lines start with `-`, added lines with `+` and unchanged context lines
+/// with a space; skipped unchanged lines are marked with `@@`.
+pub fn diff_lines(expected: &str, actual: &str) -> Option<String> {
+    if expected == actual {
+        return None;
+    }
+    let old: Vec<&str> = expected.lines().collect();
+    let new: Vec<&str> = actual.lines().collect();
+
+    // Longest common subsequence table, filled from the end
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            ops.push((' ', old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(('-', old[i]));
+            i += 1;
+        } else {
+            ops.push(('+', new[j]));
+            j += 1;
+        }
+    }
+
+    if ops.iter().all(|(op, _)| *op == ' ') {
+        // Only line endings differ
+        return Some("@@ line endings or trailing newline differ @@".to_string());
+    }
+
+    let near_change = |index: usize| {
+        let start = index.saturating_sub(DIFF_CONTEXT);
+        let end = (index + DIFF_CONTEXT + 1).min(ops.len());
+        ops[start..end].iter().any(|(op, _)| *op != ' ')
+    };
+    let mut diff = String::new();
+    let mut skipped = false;
+    for (index, (op, line)) in ops.iter().enumerate() {
+        if *op == ' ' && !near_change(index) {
+            skipped = true;
+            continue;
+        }
+        if skipped {
+            diff.push_str("@@\n");
+            skipped = false;
+        }
+        diff.push_str(&format!("{}{}\n", op, line));
+    }
+    if skipped {
+        diff.push_str("@@\n");
+    }
+    Some(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{config::DeepAgentConfig, runtime::create_deep_agent_from_config};
+    use crate::middleware::time_context::TimeContextConfig;
+    use agents_core::agent::{PlannerContext, PlannerDecision, PlannerHandle};
+    use agents_core::state::AgentStateSnapshot;
+    use agents_core::tools::ToolParameterSchema;
+    use async_trait::async_trait;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    struct UnusedPlanner;
+
+    #[async_trait]
+    impl PlannerHandle for UnusedPlanner {
+        async fn plan(
+            &self,
+            _context: PlannerContext,
+            _state: Arc<AgentStateSnapshot>,
+        ) -> anyhow::Result<PlannerDecision> {
+            anyhow::bail!("snapshots never call the planner")
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    fn snapshot(prompt: &str) -> PromptSnapshot {
+        PromptSnapshot {
+            system_prompt: prompt.to_string(),
+            tools: vec![ToolSchema::new(
+                "lookup_order",
+                "Look up an order",
+                ToolParameterSchema::object(
+                    "Order lookup",
+                    HashMap::from([(
+                        "order_id".to_string(),
+                        ToolParameterSchema::string("Order id"),
+                    )]),
+                    vec!["order_id".to_string()],
+                ),
+            )],
+        }
+    }
+
+    #[tokio::test]
+    async fn agent_snapshots_are_reproducible() {
+        let agent = create_deep_agent_from_config(
+            DeepAgentConfig::new("Answer order questions.", Arc::new(UnusedPlanner))
+                .with_time_context(TimeContextConfig::for_timezone("Asia/Dubai").unwrap()),
+        );
+
+        let first = agent.prompt_snapshot().await.unwrap();
+        let second = agent.prompt_snapshot().await.unwrap();
+        assert_eq!(first.render(), second.render());
+        assert!(first.system_prompt.contains("Answer order questions."));
+        assert!(first
+            .system_prompt
+            .contains("It is Saturday, January 1, 2000 04:00 (Asia/Dubai, UTC+04:00)"));
+
+        let names: Vec<_> = first.tools.iter().map(|tool| tool.name.as_str()).collect();
+        let mut sorted = names.clone();
+        sorted.sort();
+        assert_eq!(names, sorted);
+        assert!(names.contains(&"write_todos"));
+    }
+
+    #[test]
+    fn diff_shows_changed_lines_with_context() {
+        let expected = "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\n";
+        let actual = "one\ntwo\nthree\nfour\nfive\nsix\nSEVEN\neight\nnine\nten\n";
+        assert_eq!(
+            diff_lines(expected, actual).unwrap(),
+            "@@\n four\n five\n six\n-seven\n+SEVEN\n eight\n nine\n+ten\n"
+        );
+        assert_eq!(diff_lines(expected, expected), None);
+    }
+
+    #[test]
+    fn snapshots_are_written_compared_and_updated() {
+        let dir =
+            std::env::temp_dir().join(format!("agents-prompt-snapshot-{}", std::process::id()));
+        let path = dir.join("agent.prompt.md");
+
+        let original = snapshot("Answer order questions.");
+        original.assert_matches(&path);
+        let stored = std::fs::read_to_string(&path).unwrap();
+        assert!(stored.starts_with("# System Prompt\n\nAnswer order questions.\n\n# Tools\n"));
+        assert!(stored.contains("## lookup_order\n\nLook up an order\n\n```json\n"));
+
+        // Unchanged prompts match
+        original.assert_matches(&path);
+
+        let changed = snapshot("Answer order and refund questions.");
+        let diff = changed.diff(&stored).unwrap();
+        assert!(diff.contains("-Answer order questions.\n+Answer order and refund questions.\n"));
+        let failure = std::panic::catch_unwind(|| changed.assert_matches(&path)).unwrap_err();
+        let message = failure.downcast_ref::<String>().unwrap();
+        assert!(message.contains(UPDATE_SNAPSHOTS_ENV));
+        assert!(message.contains("+Answer order and refund questions."));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
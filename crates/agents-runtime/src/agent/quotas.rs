@@ -0,0 +1,302 @@
+//! Daily and monthly usage quotas per tenant
+//!
+//! Runs started with [`RunOptions::with_tenant`](super::RunOptions::with_tenant)
+//! are checked against the tenant's quotas before they start and counted afterwards:
+//! one request plus the tokens of the run's model calls, including those of
+//! sub-agents sharing the agent's model. Token counts are the ones reported by the
+//! provider, or estimated from the text length when it reports none.
+//!
+//! A tenant over quota is rejected with [`QuotaExceededError`], or served by a
+//! cheaper model with [`QuotaAction::Degrade`]. Quota store failures are logged and
+//! the run is let through, so an unavailable store does not take the agent down.
+
+use agents_core::llm::{LanguageModel, LlmRequest, LlmResponse};
+use agents_core::quota::{QuotaLimit, QuotaRemaining, QuotaStore, QuotaUsage};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+tokio::task_local! {
+    static RUN_TOKENS: Arc<AtomicU64>;
+}
+
+/// What happens to runs of a tenant over quota.
+#[derive(Clone)]
+pub enum QuotaAction {
+    /// Fail the run with [`QuotaExceededError`]
+    Reject,
+    /// Answer with this model instead of the agent's, typically a small and cheap one
+    Degrade(Arc<dyn LanguageModel>),
+}
+
+impl std::fmt::Debug for QuotaAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuotaAction::Reject => f.write_str("Reject"),
+            QuotaAction::Degrade(_) => f.write_str("Degrade(<model>)"),
+        }
+    }
+}
+
+/// Quotas enforced on runs started for a tenant.
+///
+/// # Example
+///
+/// ```ignore
+/// let store = Arc::new(RedisQuotaStore::new("redis://redis:6379").await?);
+/// let agent = ConfigurableAgentBuilder::new("You are a support agent")
+///     .with_model(model)
+///     .with_quotas(
+///         QuotaConfig::new(store)
+///             .with_limit(QuotaLimit::daily().with_max_requests(200))
+///             .with_limit(QuotaLimit::monthly().with_max_tokens(2_000_000))
+///             .with_tenant_limits("enterprise", [QuotaLimit::monthly().with_max_tokens(50_000_000)])
+///             .on_exceeded(QuotaAction::Degrade(small_model)),
+///     )
+///     .build()?;
+///
+/// let options = RunOptions::new().with_tenant(&customer.id);
+/// match agent.handle_message_with_options(text, options, state).await {
+///     Err(e) if e.is::<QuotaExceededError>() => StatusCode::TOO_MANY_REQUESTS,
+///     ...
+/// }
+/// ```
+#[derive(Clone)]
+pub struct QuotaConfig {
+    pub store: Arc<dyn QuotaStore>,
+    /// Limits of tenants without limits of their own
+    pub limits: Vec<QuotaLimit>,
+    /// Limits replacing the default ones for specific tenants
+    pub tenant_limits: HashMap<String, Vec<QuotaLimit>>,
+    pub on_exceeded: QuotaAction,
+}
+
+impl std::fmt::Debug for QuotaConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QuotaConfig")
+            .field("limits", &self.limits)
+            .field("tenant_limits", &self.tenant_limits)
+            .field("on_exceeded", &self.on_exceeded)
+            .finish_non_exhaustive()
+    }
+}
+
+impl QuotaConfig {
+    pub fn new(store: Arc<dyn QuotaStore>) -> Self {
+        Self {
+            store,
+            limits: Vec::new(),
+            tenant_limits: HashMap::new(),
+            on_exceeded: QuotaAction::Reject,
+        }
+    }
+
+    pub fn with_limit(mut self, limit: QuotaLimit) -> Self {
+        self.limits.push(limit);
+        self
+    }
+
+    /// Use `limits` for `tenant` instead of the default limits. An empty list makes
+    /// the tenant unlimited; its usage is still counted.
+    pub fn with_tenant_limits(
+        mut self,
+        tenant: impl Into<String>,
+        limits: impl IntoIterator<Item = QuotaLimit>,
+    ) -> Self {
+        self.tenant_limits
+            .insert(tenant.into(), limits.into_iter().collect());
+        self
+    }
+
+    pub fn on_exceeded(mut self, action: QuotaAction) -> Self {
+        self.on_exceeded = action;
+        self
+    }
+
+    pub fn limits_for(&self, tenant: &str) -> &[QuotaLimit] {
+        self.tenant_limits.get(tenant).unwrap_or(&self.limits)
+    }
+
+    /// What is left of each of `tenant`'s limits in the current windows.
+    pub async fn remaining(&self, tenant: &str) -> anyhow::Result<Vec<QuotaRemaining>> {
+        let now = Utc::now();
+        let mut remaining = Vec::new();
+        for limit in self.limits_for(tenant) {
+            let used = self.store.usage(&limit.period.key(tenant, now)).await?;
+            remaining.push(QuotaRemaining::new(limit, used, now));
+        }
+        Ok(remaining)
+    }
+
+    /// Fail when one of `tenant`'s limits is used up.
+    pub(crate) async fn check(&self, tenant: &str) -> Result<(), QuotaExceededError> {
+        let remaining = match self.remaining(tenant).await {
+            Ok(remaining) => remaining,
+            Err(e) => {
+                tracing::warn!(tenant, "Failed to read quota usage, allowing run: {}", e);
+                return Ok(());
+            }
+        };
+        match remaining.into_iter().find(QuotaRemaining::is_exhausted) {
+            Some(remaining) => Err(QuotaExceededError {
+                tenant: tenant.to_string(),
+                remaining,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Count a finished run of `tenant` in every period it has a limit for.
+    pub(crate) async fn record(&self, tenant: &str, tokens: u64) {
+        let now = Utc::now();
+        let usage = QuotaUsage {
+            requests: 1,
+            tokens,
+        };
+        let mut periods = Vec::new();
+        for limit in self.limits_for(tenant) {
+            if !periods.contains(&limit.period) {
+                periods.push(limit.period);
+            }
+        }
+        for period in periods {
+            let key = period.key(tenant, now);
+            if let Err(e) = self.store.record(&key, usage, period.resets_at(now)).await {
+                tracing::warn!(tenant, key = %key, "Failed to record quota usage: {}", e);
+            }
+        }
+    }
+}
+
+/// A run was rejected because its tenant used up a quota.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "tenant {tenant} exceeded its {} quota; it resets at {}",
+    remaining.period.as_str(),
+    remaining.resets_at
+)]
+pub struct QuotaExceededError {
+    pub tenant: String,
+    /// The exhausted limit
+    pub remaining: QuotaRemaining,
+}
+
+/// Run `future`, returning its output and the tokens its model calls used.
+pub(crate) async fn metered<F: Future>(future: F) -> (F::Output, u64) {
+    let tokens = Arc::new(AtomicU64::new(0));
+    let output = RUN_TOKENS.scope(tokens.clone(), future).await;
+    (output, tokens.load(Ordering::Relaxed))
+}
+
+/// Whether the current run's tokens are being counted.
+pub(crate) fn is_metered() -> bool {
+    RUN_TOKENS.try_with(|_| ()).is_ok()
+}
+
+/// Count the tokens of a model call towards the current run's quota.
+/// `estimated_input` is used when the provider reports no usage.
+pub(crate) fn record_response(estimated_input: u64, response: &LlmResponse) {
+    let tokens = match &response.usage {
+        Some(usage) => u64::from(usage.input_tokens) + u64::from(usage.output_tokens),
+        None => estimated_input + estimate_tokens(&response.message.content.to_text()),
+    };
+    let _ = RUN_TOKENS.try_with(|counter| counter.fetch_add(tokens, Ordering::Relaxed));
+}
+
+/// Estimated prompt tokens of `request`.
+pub(crate) fn estimate_request(request: &LlmRequest) -> u64 {
+    estimate_tokens(&request.system_prompt)
+        + request
+            .messages
+            .iter()
+            .map(|message| estimate_tokens(&message.content.to_text()))
+            .sum::<u64>()
+}
+
+/// About four characters per token, as in token tracking.
+fn estimate_tokens(text: &str) -> u64 {
+    text.len().div_ceil(4) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{ConfigurableAgentBuilder, RunOptions};
+    use agents_core::llm::LlmUsage;
+    use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+    use agents_core::quota::{InMemoryQuotaStore, QuotaPeriod};
+    use agents_core::state::AgentStateSnapshot;
+    use async_trait::async_trait;
+
+    /// Answers with its name, reporting 100 tokens per call.
+    struct NamedModel(&'static str);
+
+    #[async_trait]
+    impl LanguageModel for NamedModel {
+        async fn generate(&self, _request: LlmRequest) -> anyhow::Result<LlmResponse> {
+            let message = AgentMessage {
+                role: MessageRole::Agent,
+                content: MessageContent::Text(self.0.to_string()),
+                metadata: None,
+            };
+            Ok(LlmResponse::new(message).with_usage(LlmUsage::new("test", self.0, 80, 20)))
+        }
+    }
+
+    async fn ask(agent: &crate::agent::DeepAgent, tenant: &str) -> anyhow::Result<String> {
+        let options = RunOptions::new().with_tenant(tenant);
+        let reply = agent
+            .handle_message_with_options("hi", options, Arc::new(AgentStateSnapshot::default()))
+            .await?;
+        Ok(reply.content.to_text())
+    }
+
+    #[tokio::test]
+    async fn tenants_over_quota_are_rejected_or_degraded() {
+        let store = Arc::new(InMemoryQuotaStore::new());
+        let quotas = QuotaConfig::new(store.clone())
+            .with_limit(QuotaLimit::daily().with_max_requests(2))
+            .with_limit(QuotaLimit::monthly().with_max_tokens(1_000))
+            .with_tenant_limits("enterprise", []);
+        let agent = ConfigurableAgentBuilder::new("assist")
+            .with_model(Arc::new(NamedModel("large")))
+            .with_quotas(quotas.clone())
+            .build()
+            .unwrap();
+
+        assert_eq!(ask(&agent, "acme").await.unwrap(), "large");
+        let remaining = agent.remaining_quota("acme").await.unwrap();
+        assert_eq!(remaining[0].requests, Some(1));
+        assert_eq!(remaining[1].tokens, Some(900));
+
+        ask(&agent, "acme").await.unwrap();
+        let error = ask(&agent, "acme").await.unwrap_err();
+        let exceeded = error.downcast_ref::<QuotaExceededError>().unwrap();
+        assert_eq!(exceeded.tenant, "acme");
+        assert_eq!(exceeded.remaining.period, QuotaPeriod::Daily);
+        assert_eq!(
+            agent.remaining_quota("acme").await.unwrap()[0]
+                .used
+                .requests,
+            2
+        );
+
+        // Other tenants and tenants without limits are unaffected
+        assert_eq!(ask(&agent, "globex").await.unwrap(), "large");
+        assert!(agent
+            .remaining_quota("enterprise")
+            .await
+            .unwrap()
+            .is_empty());
+
+        let degrading = ConfigurableAgentBuilder::new("assist")
+            .with_model(Arc::new(NamedModel("large")))
+            .with_quotas(quotas.on_exceeded(QuotaAction::Degrade(Arc::new(NamedModel("small")))))
+            .build()
+            .unwrap();
+        assert_eq!(ask(&degrading, "acme").await.unwrap(), "small");
+        assert_eq!(ask(&degrading, "globex").await.unwrap(), "large");
+    }
+}
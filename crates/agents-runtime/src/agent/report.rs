@@ -0,0 +1,765 @@
+//! Run journal and human-readable run reports
+//!
+//! Every call to `handle_message` is a run. The agent keeps the events of its most
+//! recent runs in a [`RunJournal`], from which a [`RunReport`] summarizing the tools
+//! called, sources used, files written and cost can be rendered as Markdown or JSON.
+
+use super::debugger::{RecordedStep, RunRecording};
+use super::run_trace::RunTrace;
+use agents_core::events::{AgentEvent, EventBroadcaster};
+use agents_core::experiment::ExperimentAssignment;
+use agents_core::messaging::AgentMessage;
+use agents_core::profiling::{ProfilePhase, RunProfile, RunProfiler};
+use agents_core::state::{TodoItem, TodoStatus};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+
+/// Number of runs kept by the journal before the oldest is dropped
+pub const DEFAULT_RUN_HISTORY: usize = 20;
+
+struct RunRecord {
+    run_id: String,
+    thread_id: Option<String>,
+    events: Vec<AgentEvent>,
+    files_written: BTreeSet<String>,
+    sources: Vec<String>,
+    profiler: Option<Arc<RunProfiler>>,
+    steps: Vec<RecordedStep>,
+}
+
+/// Bounded, in-memory record of the events of recent runs.
+///
+/// The agent records its own events directly. Token usage and sub-agent events are
+/// emitted by other components, so the journal is also an [`EventBroadcaster`] that
+/// the runtime attaches to the agent's event dispatcher when one is configured.
+pub struct RunJournal {
+    capacity: usize,
+    runs: Mutex<VecDeque<RunRecord>>,
+}
+
+impl Default for RunJournal {
+    fn default() -> Self {
+        Self::new(DEFAULT_RUN_HISTORY)
+    }
+}
+
+impl RunJournal {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            runs: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Open a new run and return its id. Later records are attributed to it.
+    pub fn start_run(&self) -> String {
+        let run_id = uuid::Uuid::new_v4().to_string();
+        if let Ok(mut runs) = self.runs.lock() {
+            if runs.len() >= self.capacity {
+                runs.pop_front();
+            }
+            runs.push_back(RunRecord {
+                run_id: run_id.clone(),
+                thread_id: None,
+                events: Vec::new(),
+                files_written: BTreeSet::new(),
+                sources: Vec::new(),
+                profiler: None,
+                steps: Vec::new(),
+            });
+        }
+        run_id
+    }
+
+    /// Id of the most recent run, if any.
+    pub fn current_run_id(&self) -> Option<String> {
+        self.runs
+            .lock()
+            .ok()
+            .and_then(|runs| runs.back().map(|run| run.run_id.clone()))
+    }
+
+    /// Ids of the runs still held by the journal, oldest first.
+    pub fn run_ids(&self) -> Vec<String> {
+        self.runs
+            .lock()
+            .map(|runs| runs.iter().map(|run| run.run_id.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Ids of the runs of `thread_id` still held by the journal, oldest first.
+    pub fn thread_run_ids(&self, thread_id: &str) -> Vec<String> {
+        self.runs
+            .lock()
+            .map(|runs| {
+                runs.iter()
+                    .filter(|run| run.thread_id.as_deref() == Some(thread_id))
+                    .map(|run| run.run_id.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Attribute a run to the thread it ran on.
+    pub(crate) fn assign_thread(&self, run_id: &str, thread_id: &str) {
+        if let Ok(mut runs) = self.runs.lock() {
+            if let Some(run) = runs.iter_mut().find(|run| run.run_id == run_id) {
+                run.thread_id = Some(thread_id.to_string());
+            }
+        }
+    }
+
+    fn with_current<F: FnOnce(&mut RunRecord)>(&self, f: F) {
+        if let Ok(mut runs) = self.runs.lock() {
+            if let Some(run) = runs.back_mut() {
+                f(run);
+            }
+        }
+    }
+
+    /// Record an event against the current run. Streaming tokens are skipped.
+    pub fn record(&self, event: &AgentEvent) {
+        if matches!(event, AgentEvent::StreamingToken(_)) {
+            return;
+        }
+        self.with_current(|run| run.events.push(event.clone()));
+    }
+
+    /// Record files created or modified during the current run.
+    pub fn record_files<I, S>(&self, paths: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let paths: Vec<String> = paths.into_iter().map(Into::into).collect();
+        self.with_current(|run| run.files_written.extend(paths));
+    }
+
+    /// Record the URLs found in a tool output as sources of the current run.
+    pub fn record_sources(&self, text: &str) {
+        let urls = extract_urls(text);
+        if urls.is_empty() {
+            return;
+        }
+        self.with_current(|run| {
+            for url in urls {
+                if !run.sources.contains(&url) {
+                    run.sources.push(url);
+                }
+            }
+        });
+    }
+
+    /// Record a planner iteration of the current run.
+    pub(crate) fn record_step(&self, step: RecordedStep) {
+        self.with_current(|run| run.steps.push(step));
+    }
+
+    /// Record the message the tool call of the current run's latest step returned.
+    pub(crate) fn record_step_result(&self, message: &AgentMessage) {
+        self.with_current(|run| {
+            if let Some(step) = run.steps.last_mut() {
+                step.tool_result = Some(message.clone());
+            }
+        });
+    }
+
+    /// Attach the profiler timing a run; its timings appear in the run's report.
+    pub fn attach_profiler(&self, run_id: &str, profiler: Arc<RunProfiler>) {
+        if let Ok(mut runs) = self.runs.lock() {
+            if let Some(run) = runs.iter_mut().find(|run| run.run_id == run_id) {
+                run.profiler = Some(profiler);
+            }
+        }
+    }
+
+    /// Events recorded for a run; empty if the run is unknown or was evicted.
+    pub fn events(&self, run_id: &str) -> Vec<AgentEvent> {
+        self.runs
+            .lock()
+            .ok()
+            .and_then(|runs| {
+                runs.iter()
+                    .find(|run| run.run_id == run_id)
+                    .map(|run| run.events.clone())
+            })
+            .unwrap_or_default()
+    }
+
+    /// Build the report of a run, or `None` if the run is unknown or was evicted.
+    pub fn report(&self, run_id: &str) -> Option<RunReport> {
+        let runs = self.runs.lock().ok()?;
+        let run = runs.iter().find(|run| run.run_id == run_id)?;
+
+        let mut report = RunReport::from_events(run_id, &run.events);
+        report.files_written = run.files_written.iter().cloned().collect();
+        let mut sources = run.sources.clone();
+        if let Some(response) = &report.response {
+            for url in extract_urls(response) {
+                if !sources.contains(&url) {
+                    sources.push(url);
+                }
+            }
+        }
+        report.sources = sources;
+        report.profile = run.profiler.as_ref().map(|profiler| profiler.profile());
+        Some(report)
+    }
+
+    /// Build the trace of a run, or `None` if the run is unknown or was evicted.
+    pub fn trace(&self, run_id: &str) -> Option<RunTrace> {
+        let runs = self.runs.lock().ok()?;
+        let run = runs.iter().find(|run| run.run_id == run_id)?;
+        Some(RunTrace::from_events(run_id, &run.events))
+    }
+
+    /// The recorded steps of a run, or `None` if the run is unknown or was evicted.
+    pub fn recording(&self, run_id: &str) -> Option<RunRecording> {
+        let runs = self.runs.lock().ok()?;
+        let run = runs.iter().find(|run| run.run_id == run_id)?;
+        Some(RunRecording {
+            run_id: run_id.to_string(),
+            steps: run.steps.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl EventBroadcaster for RunJournal {
+    fn id(&self) -> &str {
+        "run-journal"
+    }
+
+    async fn broadcast(&self, event: &AgentEvent) -> anyhow::Result<()> {
+        self.record(event);
+        Ok(())
+    }
+
+    fn should_broadcast(&self, event: &AgentEvent) -> bool {
+        // Everything else is recorded by the agent itself
+        matches!(
+            event,
+            AgentEvent::TokenUsage(_)
+                | AgentEvent::SubAgentStarted(_)
+                | AgentEvent::SubAgentCompleted(_)
+        )
+    }
+}
+
+/// Outcome of a tool call within a run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolCallStatus {
+    /// Started but never finished, e.g. paused for human approval
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+/// A single tool call within a run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCallRecord {
+    pub tool_name: String,
+    pub input_summary: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    pub status: ToolCallStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Token usage and estimated cost accumulated over a run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunUsage {
+    pub requests: usize,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub total_tokens: u64,
+    pub estimated_cost: f64,
+}
+
+/// Summary of a single run: what was asked, which tools ran, what was produced and
+/// what it cost.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunReport {
+    pub run_id: String,
+    pub agent_name: String,
+    /// Experiment variant the run was assigned to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub experiment: Option<ExperimentAssignment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+    pub tool_calls: Vec<ToolCallRecord>,
+    pub sub_agents: Vec<String>,
+    pub sources: Vec<String>,
+    pub files_written: Vec<String>,
+    pub todos: Vec<TodoItem>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<RunUsage>,
+    /// Time spent per phase, when the agent was built with profiling
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<RunProfile>,
+}
+
+impl RunReport {
+    /// Fold the events of a run into a report.
+    pub fn from_events(run_id: impl Into<String>, events: &[AgentEvent]) -> Self {
+        let mut report = RunReport {
+            run_id: run_id.into(),
+            ..Default::default()
+        };
+
+        for event in events {
+            match event {
+                AgentEvent::AgentStarted(e) => {
+                    report.agent_name = e.agent_name.clone();
+                    report.experiment = e.metadata.experiment.clone();
+                    report.started_at = Some(e.metadata.timestamp.clone());
+                    report.request = Some(e.message_preview.clone());
+                }
+                AgentEvent::AgentCompleted(e) => {
+                    report.completed_at = Some(e.metadata.timestamp.clone());
+                    report.duration_ms = Some(e.duration_ms);
+                    report.response = Some(e.response.clone());
+                }
+                AgentEvent::ToolStarted(e) => report.tool_calls.push(ToolCallRecord {
+                    tool_name: e.tool_name.clone(),
+                    input_summary: e.input_summary.clone(),
+                    duration_ms: None,
+                    status: ToolCallStatus::Pending,
+                    error: None,
+                }),
+                AgentEvent::ToolCompleted(e) => {
+                    if let Some(call) = report.pending_call(&e.tool_name) {
+                        call.duration_ms = Some(e.duration_ms);
+                        call.status = if e.success {
+                            ToolCallStatus::Succeeded
+                        } else {
+                            ToolCallStatus::Failed
+                        };
+                    }
+                }
+                AgentEvent::ToolFailed(e) => {
+                    if let Some(call) = report.pending_call(&e.tool_name) {
+                        call.duration_ms = Some(e.duration_ms);
+                        call.status = ToolCallStatus::Failed;
+                        call.error = Some(e.error_message.clone());
+                    }
+                }
+                AgentEvent::SubAgentStarted(e) if !report.sub_agents.contains(&e.agent_name) => {
+                    report.sub_agents.push(e.agent_name.clone());
+                }
+                AgentEvent::TodosUpdated(e) => report.todos = e.todos.clone(),
+                AgentEvent::TokenUsage(e) => {
+                    let usage = report.usage.get_or_insert_with(RunUsage::default);
+                    usage.requests += 1;
+                    usage.input_tokens += u64::from(e.usage.input_tokens);
+                    usage.output_tokens += u64::from(e.usage.output_tokens);
+                    usage.total_tokens += u64::from(e.usage.total_tokens);
+                    usage.estimated_cost += e.usage.estimated_cost;
+                }
+                _ => {}
+            }
+        }
+
+        report
+    }
+
+    fn pending_call(&mut self, tool_name: &str) -> Option<&mut ToolCallRecord> {
+        self.tool_calls
+            .iter_mut()
+            .rev()
+            .find(|call| call.tool_name == tool_name && call.status == ToolCallStatus::Pending)
+    }
+
+    /// Number of tool calls that failed.
+    pub fn failed_tool_calls(&self) -> usize {
+        self.tool_calls
+            .iter()
+            .filter(|call| call.status == ToolCallStatus::Failed)
+            .count()
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+
+    /// Render the report as a Markdown document.
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::new();
+        let name = if self.agent_name.is_empty() {
+            "agent"
+        } else {
+            &self.agent_name
+        };
+        let _ = writeln!(md, "# Run Report: {}\n", name);
+        let _ = writeln!(md, "- **Run ID:** `{}`", self.run_id);
+        if let Some(experiment) = &self.experiment {
+            let _ = writeln!(
+                md,
+                "- **Experiment:** {} (variant `{}`)",
+                experiment.experiment, experiment.variant
+            );
+        }
+        if let Some(started_at) = &self.started_at {
+            let _ = writeln!(md, "- **Started:** {}", started_at);
+        }
+        let status = if self.completed_at.is_some() {
+            "completed"
+        } else {
+            "incomplete"
+        };
+        let _ = writeln!(md, "- **Status:** {}", status);
+        if let Some(duration_ms) = self.duration_ms {
+            let _ = writeln!(md, "- **Duration:** {}", format_duration(duration_ms));
+        }
+
+        if let Some(request) = &self.request {
+            let _ = writeln!(md, "\n## Request\n\n> {}", request.replace('\n', "\n> "));
+        }
+
+        let _ = writeln!(md, "\n## Summary\n");
+        let _ = writeln!(md, "| Metric | Value |\n|--------|-------|");
+        let _ = writeln!(
+            md,
+            "| Tool calls | {} ({} failed) |",
+            self.tool_calls.len(),
+            self.failed_tool_calls()
+        );
+        let _ = writeln!(md, "| Sub-agents | {} |", self.sub_agents.len());
+        let _ = writeln!(md, "| Sources | {} |", self.sources.len());
+        let _ = writeln!(md, "| Files written | {} |", self.files_written.len());
+        if let Some(usage) = &self.usage {
+            let _ = writeln!(
+                md,
+                "| Tokens | {} ({} in / {} out, {} requests) |",
+                usage.total_tokens, usage.input_tokens, usage.output_tokens, usage.requests
+            );
+            let _ = writeln!(md, "| Estimated cost | ${:.4} |", usage.estimated_cost);
+        }
+
+        if !self.tool_calls.is_empty() {
+            let _ = writeln!(md, "\n## Tool Calls\n");
+            let _ = writeln!(
+                md,
+                "| # | Tool | Duration | Status |\n|---|------|----------|--------|"
+            );
+            for (i, call) in self.tool_calls.iter().enumerate() {
+                let duration = call
+                    .duration_ms
+                    .map(format_duration)
+                    .unwrap_or_else(|| "-".to_string());
+                let status = match call.status {
+                    ToolCallStatus::Pending => "⏸️ pending".to_string(),
+                    ToolCallStatus::Succeeded => "✅ ok".to_string(),
+                    ToolCallStatus::Failed => match &call.error {
+                        Some(error) => format!("❌ {}", error.replace('|', "\\|")),
+                        None => "❌ failed".to_string(),
+                    },
+                };
+                let _ = writeln!(
+                    md,
+                    "| {} | `{}` | {} | {} |",
+                    i + 1,
+                    call.tool_name,
+                    duration,
+                    status
+                );
+            }
+        }
+
+        if let Some(profile) = &self.profile {
+            let _ = writeln!(md, "\n## Profile\n");
+            let _ = writeln!(md, "| Phase | Time |\n|-------|------|");
+            for phase in ProfilePhase::ALL {
+                let _ = writeln!(
+                    md,
+                    "| {} | {:.1}ms |",
+                    phase.as_str(),
+                    profile.phase_ms(phase)
+                );
+            }
+            let _ = writeln!(md, "| provider calls | {} |", profile.provider_calls);
+        }
+
+        if !self.sub_agents.is_empty() {
+            let _ = writeln!(md, "\n## Sub-Agents\n");
+            for agent in &self.sub_agents {
+                let _ = writeln!(md, "- {}", agent);
+            }
+        }
+
+        if !self.sources.is_empty() {
+            let _ = writeln!(md, "\n## Sources\n");
+            for source in &self.sources {
+                let _ = writeln!(md, "- <{}>", source);
+            }
+        }
+
+        if !self.files_written.is_empty() {
+            let _ = writeln!(md, "\n## Files Written\n");
+            for path in &self.files_written {
+                let _ = writeln!(md, "- `{}`", path);
+            }
+        }
+
+        if !self.todos.is_empty() {
+            let _ = writeln!(md, "\n## Plan\n");
+            for todo in &self.todos {
+                let mark = if matches!(todo.status, TodoStatus::Completed) {
+                    "x"
+                } else {
+                    " "
+                };
+                let _ = writeln!(md, "- [{}] {}", mark, todo.content);
+            }
+        }
+
+        if let Some(response) = &self.response {
+            let _ = writeln!(md, "\n## Response\n\n{}", response);
+        }
+
+        md
+    }
+}
+
+pub(super) fn format_duration(ms: u64) -> String {
+    if ms < 1_000 {
+        format!("{}ms", ms)
+    } else {
+        format!("{:.1}s", ms as f64 / 1_000.0)
+    }
+}
+
+/// URLs mentioned in free text, in order of appearance and without trailing punctuation.
+fn extract_urls(text: &str) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    for token in text.split(|c: char| c.is_whitespace() || "<>\"'()[]{}`|".contains(c)) {
+        if !(token.starts_with("http://") || token.starts_with("https://")) {
+            continue;
+        }
+        let url = token.trim_end_matches(['.', ',', ';', ':', '!', '?', '*']);
+        if url.len() > "https://".len() && !urls.iter().any(|u| u == url) {
+            urls.push(url.to_string());
+        }
+    }
+    urls
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::events::{
+        AgentCompletedEvent, AgentStartedEvent, EventMetadata, TokenUsage, TokenUsageEvent,
+        ToolCompletedEvent, ToolFailedEvent, ToolStartedEvent,
+    };
+
+    fn metadata() -> EventMetadata {
+        EventMetadata::new("default".into(), "run".into(), None)
+    }
+
+    fn tool_started(name: &str) -> AgentEvent {
+        AgentEvent::ToolStarted(ToolStartedEvent {
+            metadata: metadata(),
+            tool_name: name.into(),
+            input_summary: "{}".into(),
+        })
+    }
+
+    #[test]
+    fn report_folds_events_into_markdown() {
+        let journal = RunJournal::new(2);
+        let run_id = journal.start_run();
+        journal.record(&AgentEvent::AgentStarted(AgentStartedEvent {
+            metadata: metadata(),
+            agent_name: "research".into(),
+            message_preview: "Compare Rust web frameworks".into(),
+        }));
+        journal.record(&tool_started("web_search"));
+        journal.record(&AgentEvent::ToolCompleted(ToolCompletedEvent {
+            metadata: metadata(),
+            tool_name: "web_search".into(),
+            duration_ms: 1_250,
+            result_summary: "3 results".into(),
+            success: true,
+        }));
+        journal.record_sources("See https://github.com/tokio-rs/axum. Also (https://actix.rs)");
+        journal.record(&tool_started("fetch"));
+        journal.record(&AgentEvent::ToolFailed(ToolFailedEvent {
+            metadata: metadata(),
+            tool_name: "fetch".into(),
+            duration_ms: 40,
+            error_message: "timeout".into(),
+            is_recoverable: true,
+            retry_count: 0,
+        }));
+        journal.record_files(["report.md"]);
+        journal.record(&AgentEvent::TokenUsage(TokenUsageEvent {
+            metadata: metadata(),
+            usage: TokenUsage::new(1_000, 200, "openai", "gpt-4o-mini", 900, 0.0012),
+        }));
+        journal.record(&AgentEvent::AgentCompleted(AgentCompletedEvent {
+            metadata: metadata(),
+            agent_name: "research".into(),
+            duration_ms: 4_200,
+            response_preview: "Axum".into(),
+            response: "Axum wins, see https://actix.rs and https://rocket.rs.".into(),
+        }));
+
+        let report = journal.report(&run_id).unwrap();
+        assert_eq!(report.tool_calls.len(), 2);
+        assert_eq!(report.tool_calls[0].status, ToolCallStatus::Succeeded);
+        assert_eq!(report.failed_tool_calls(), 1);
+        assert_eq!(
+            report.sources,
+            vec![
+                "https://github.com/tokio-rs/axum",
+                "https://actix.rs",
+                "https://rocket.rs"
+            ]
+        );
+        assert_eq!(report.usage.as_ref().unwrap().total_tokens, 1_200);
+
+        let md = report.to_markdown();
+        assert!(md.starts_with("# Run Report: research"));
+        assert!(md.contains("| Tool calls | 2 (1 failed) |"));
+        assert!(md.contains("| 1 | `web_search` | 1.2s | ✅ ok |"));
+        assert!(md.contains("❌ timeout"));
+        assert!(md.contains("- `report.md`"));
+        assert!(md.contains("| Estimated cost | $0.0012 |"));
+        assert_eq!(report.to_json()["tool_calls"][1]["status"], "failed");
+    }
+
+    #[test]
+    fn journal_evicts_oldest_run() {
+        let journal = RunJournal::new(2);
+        let first = journal.start_run();
+        journal.record(&tool_started("ls"));
+        let second = journal.start_run();
+        let third = journal.start_run();
+
+        assert!(journal.report(&first).is_none());
+        assert_eq!(journal.run_ids(), vec![second.clone(), third.clone()]);
+        assert_eq!(journal.current_run_id(), Some(third));
+        let report = journal.report(&second).unwrap();
+        assert!(report.tool_calls.is_empty());
+        assert!(report.to_markdown().contains("- **Status:** incomplete"));
+    }
+
+    struct ScriptedPlanner {
+        step: Mutex<usize>,
+    }
+
+    #[async_trait]
+    impl agents_core::agent::PlannerHandle for ScriptedPlanner {
+        async fn plan(
+            &self,
+            _context: agents_core::agent::PlannerContext,
+            _state: std::sync::Arc<agents_core::state::AgentStateSnapshot>,
+        ) -> anyhow::Result<agents_core::agent::PlannerDecision> {
+            use agents_core::agent::{PlannerAction, PlannerDecision};
+            use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+
+            let mut step = self.step.lock().unwrap();
+            *step += 1;
+            let next_action = if *step == 1 {
+                PlannerAction::CallTool {
+                    tool_name: "write_file".into(),
+                    payload: serde_json::json!({
+                        "file_path": "notes.md",
+                        "content": "draft"
+                    }),
+                }
+            } else {
+                PlannerAction::Respond {
+                    message: AgentMessage {
+                        role: MessageRole::Agent,
+                        content: MessageContent::Text("Done, based on https://docs.rs".into()),
+                        metadata: None,
+                    },
+                }
+            };
+            Ok(PlannerDecision { next_action })
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    #[tokio::test]
+    async fn agent_generates_report_for_last_run() {
+        use crate::agent::{config::DeepAgentConfig, runtime::create_deep_agent_from_config};
+        use agents_core::state::AgentStateSnapshot;
+        use std::sync::Arc;
+
+        let planner = Arc::new(ScriptedPlanner {
+            step: Mutex::new(0),
+        });
+        let agent = create_deep_agent_from_config(DeepAgentConfig::new("assist", planner));
+        assert!(agent.last_run_id().is_none());
+
+        agent
+            .handle_message("Write notes", Arc::new(AgentStateSnapshot::default()))
+            .await
+            .unwrap();
+
+        let run_id = agent.last_run_id().unwrap();
+        let report = agent.generate_report(&run_id).unwrap();
+        assert_eq!(report.agent_name, "deep-agent");
+        assert_eq!(report.tool_calls.len(), 1);
+        assert_eq!(report.tool_calls[0].tool_name, "write_file");
+        assert_eq!(report.tool_calls[0].status, ToolCallStatus::Succeeded);
+        assert_eq!(report.files_written, vec!["notes.md"]);
+        assert_eq!(report.sources, vec!["https://docs.rs"]);
+        assert!(report.to_markdown().contains("## Response"));
+        assert!(report.profile.is_none());
+        assert!(agent.generate_report("unknown").is_none());
+
+        let trace = agent.run_trace(&run_id).unwrap();
+        assert_eq!(trace.steps.len(), 3);
+        assert!(trace.to_mermaid().contains("participant T1 as write_file"));
+    }
+
+    #[tokio::test]
+    async fn profiled_thread_runs_report_phase_timings() {
+        use crate::agent::{config::DeepAgentConfig, runtime::create_deep_agent_from_config};
+        use agents_core::persistence::InMemoryCheckpointer;
+        use std::sync::Arc;
+
+        let planner = Arc::new(ScriptedPlanner {
+            step: Mutex::new(0),
+        });
+        let agent = create_deep_agent_from_config(
+            DeepAgentConfig::new("assist", planner)
+                .with_checkpointer(Arc::new(InMemoryCheckpointer::new()))
+                .with_profiling(true),
+        );
+        agent
+            .handle_message_for_thread(&"notes".to_string(), "Write notes")
+            .await
+            .unwrap();
+
+        let report = agent
+            .generate_report(&agent.last_run_id().unwrap())
+            .unwrap();
+        let profile = report.profile.as_ref().unwrap();
+        assert_eq!(profile.provider_calls, 2);
+        assert!(profile.tool_ms > 0.0);
+        assert!(profile.persistence_ms > 0.0);
+        let md = report.to_markdown();
+        assert!(md.contains("## Profile"));
+        assert!(md.contains("| provider calls | 2 |"));
+    }
+}
@@ -0,0 +1,428 @@
+//! Per-call options for a single run
+//!
+//! [`RunOptions`] carry what differs between calls of the same agent, such as the
+//! provider keys of the customer the request is served for, a different model tier
+//! or a narrower tool set. They apply to the run only and are never persisted, so one
+//! agent instance serves every combination.
+//!
+//! The user a run is served for is the exception: the thread is assigned to them.
+//!
+//! Provider keys, the model and the temperature also apply to the sub-agents and
+//! tools the run invokes. The tool subset, iteration limit, output format and caller
+//! claims only apply to the agent handling the message; sub-agents keep their own.
+
+use super::quotas;
+use crate::output_format::OutputFormat;
+use agents_core::credentials::{with_provider_credentials, ProviderCredentials};
+use agents_core::llm::{ChunkStream, LanguageModel, LlmRequest, LlmResponse};
+use agents_core::memory::UserId;
+use agents_core::metering::with_tenant;
+use async_trait::async_trait;
+use std::collections::HashSet;
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+tokio::task_local! {
+    static MODEL_OVERRIDE: ModelOverride;
+    static INSTRUCTIONS: String;
+}
+
+/// Instructions of the run whose prompt is being built, when its options replace the
+/// agent's own.
+pub(crate) fn current_instructions() -> Option<String> {
+    INSTRUCTIONS.try_with(String::clone).ok()
+}
+
+/// Build a prompt with `instructions` in place of the agent's. Only scoped around
+/// prompt building, so sub-agents started by the run's tools keep their own.
+pub(crate) async fn with_instructions<F: Future>(
+    instructions: Option<String>,
+    future: F,
+) -> F::Output {
+    match instructions {
+        Some(instructions) => INSTRUCTIONS.scope(instructions, future).await,
+        None => future.await,
+    }
+}
+
+/// Options for one call of `DeepAgent::handle_message_with_options`.
+///
+/// # Example
+///
+/// ```ignore
+/// // Bring-your-own-key: the run calls OpenAI with the customer's key
+/// let options = RunOptions::new().with_api_key("openai", customer.openai_key());
+/// let reply = agent.handle_message_with_options(text, options, state).await?;
+///
+/// // Premium users get the larger model, anonymous users a read-only tool set
+/// let options = if user.is_premium() {
+///     RunOptions::new().with_model(premium_model.clone())
+/// } else if user.is_anonymous() {
+///     RunOptions::new()
+///         .with_allowed_tools(["search_docs", "read_file"])
+///         .with_max_iterations(3)
+/// } else {
+///     RunOptions::new()
+/// };
+/// ```
+#[derive(Clone, Default)]
+pub struct RunOptions {
+    /// Provider keys used instead of the models' own keys
+    pub credentials: Option<ProviderCredentials>,
+    /// System prompt used instead of the agent's instructions
+    pub instructions: Option<String>,
+    /// Model used instead of the agent's model
+    pub model: Option<Arc<dyn LanguageModel>>,
+    /// Sampling temperature of the model requests
+    pub temperature: Option<f32>,
+    /// Names of the tools the agent may use; all of them when unset
+    pub allowed_tools: Option<HashSet<String>>,
+    /// Iteration limit used instead of the agent's `max_iterations`
+    pub max_iterations: Option<NonZeroUsize>,
+    /// Formatting of the reply for the channel it is sent to
+    pub output_format: Option<OutputFormat>,
+    /// Claims of the caller, passed to the agent's policy provider
+    pub claims: Option<serde_json::Value>,
+    /// Tenant whose quotas the run is checked against and counted in
+    pub tenant: Option<String>,
+    /// User the run is served for, whose memories the thread shares
+    pub user_id: Option<UserId>,
+}
+
+impl std::fmt::Debug for RunOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RunOptions")
+            .field("credentials", &self.credentials)
+            .field("instructions", &self.instructions)
+            .field("model", &self.model.as_ref().map(|_| "<model>"))
+            .field("temperature", &self.temperature)
+            .field("allowed_tools", &self.allowed_tools)
+            .field("max_iterations", &self.max_iterations)
+            .field("output_format", &self.output_format)
+            .field("claims", &self.claims)
+            .field("tenant", &self.tenant)
+            .field("user_id", &self.user_id)
+            .finish()
+    }
+}
+
+impl RunOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `key` for calls to `provider` ("openai", "anthropic", "gemini").
+    pub fn with_api_key(mut self, provider: impl Into<String>, key: impl Into<String>) -> Self {
+        self.credentials = Some(
+            self.credentials
+                .take()
+                .unwrap_or_default()
+                .with_api_key(provider, key),
+        );
+        self
+    }
+
+    pub fn with_credentials(mut self, credentials: ProviderCredentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Answer with `instructions` instead of the agent's own. Middleware prompts are
+    /// still added; sub-agents keep their instructions.
+    pub fn with_instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = Some(instructions.into());
+        self
+    }
+
+    /// Answer with `model` instead of the agent's model.
+    ///
+    /// The agent's token tracking and guardrails apply to it as well. Agents built
+    /// with a custom planner instead of a model ignore the override.
+    pub fn with_model(mut self, model: Arc<dyn LanguageModel>) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Only offer and run the named tools. Calls to other tools fail like calls to
+    /// unknown tools.
+    pub fn with_allowed_tools<I, S>(mut self, tools: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_tools = Some(tools.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations =
+            Some(NonZeroUsize::new(max_iterations).expect("max_iterations must be greater than 0"));
+        self
+    }
+
+    /// Rewrite the reply for a channel, e.g. as plain text for SMS. The conversation
+    /// history keeps the reply as generated.
+    pub fn with_output_format(mut self, format: OutputFormat) -> Self {
+        self.output_format = Some(format);
+        self
+    }
+
+    /// Claims of the caller the run is served for, e.g. a verified JWT's claims. The
+    /// agent's [`PolicyProvider`](agents_core::policy::PolicyProvider) decides on tool
+    /// calls with them.
+    pub fn with_claims(mut self, claims: serde_json::Value) -> Self {
+        self.claims = Some(claims);
+        self
+    }
+
+    /// Check the run against `tenant`'s quotas and count it in them, when the agent
+    /// has [`QuotaConfig`](super::quotas::QuotaConfig) configured. Token usage of the
+    /// run is recorded with the tenant for metering.
+    pub fn with_tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tenant = Some(tenant.into());
+        self
+    }
+
+    /// Serve the run for `user_id`. The thread is assigned to the user and tagged
+    /// `user:<user_id>`; with [user memory](super::UserMemoryConfig) configured it
+    /// starts from what is remembered about them and adds what it learns.
+    pub fn with_user(mut self, user_id: impl Into<UserId>) -> Self {
+        self.user_id = Some(user_id.into());
+        self
+    }
+
+    pub(crate) fn allows_tool(&self, name: &str) -> bool {
+        self.allowed_tools
+            .as_ref()
+            .is_none_or(|allowed| allowed.contains(name))
+    }
+
+    /// Run `future` with these options in effect.
+    pub(crate) async fn scope<F: Future>(&self, future: F) -> F::Output {
+        // Runs are large futures; keep them off the stack of the nested scopes
+        let future = Box::pin(future);
+        let future = async {
+            match &self.tenant {
+                Some(tenant) => with_tenant(tenant.clone(), future).await,
+                None => future.await,
+            }
+        };
+        let future = async {
+            match &self.credentials {
+                Some(credentials) if !credentials.is_empty() => {
+                    with_provider_credentials(credentials.clone(), future).await
+                }
+                _ => future.await,
+            }
+        };
+        if self.model.is_none() && self.temperature.is_none() {
+            return future.await;
+        }
+        let model_override = ModelOverride {
+            model: self.model.clone(),
+            temperature: self.temperature,
+        };
+        MODEL_OVERRIDE.scope(model_override, future).await
+    }
+}
+
+#[derive(Clone)]
+struct ModelOverride {
+    model: Option<Arc<dyn LanguageModel>>,
+    temperature: Option<f32>,
+}
+
+/// The agent's model, replaced by the model and temperature of the current run's
+/// options. The builder places it below token tracking and guardrails, so both see
+/// the override.
+pub(crate) struct RunModel {
+    model: Arc<dyn LanguageModel>,
+}
+
+impl RunModel {
+    pub(crate) fn new(model: Arc<dyn LanguageModel>) -> Self {
+        Self { model }
+    }
+
+    fn resolve(&self, mut request: LlmRequest) -> (Arc<dyn LanguageModel>, LlmRequest) {
+        let Ok(model_override) = MODEL_OVERRIDE.try_with(ModelOverride::clone) else {
+            return (self.model.clone(), request);
+        };
+        if request.temperature.is_none() {
+            request.temperature = model_override.temperature;
+        }
+        let model = model_override.model.unwrap_or_else(|| self.model.clone());
+        (model, request)
+    }
+}
+
+#[async_trait]
+impl LanguageModel for RunModel {
+    async fn generate(&self, request: LlmRequest) -> anyhow::Result<LlmResponse> {
+        let (model, request) = self.resolve(request);
+        if !quotas::is_metered() {
+            return model.generate(request).await;
+        }
+        let estimated_input = quotas::estimate_request(&request);
+        let response = model.generate(request).await?;
+        quotas::record_response(estimated_input, &response);
+        Ok(response)
+    }
+
+    async fn generate_stream(&self, request: LlmRequest) -> anyhow::Result<ChunkStream> {
+        let (model, request) = self.resolve(request);
+        model.generate_stream(request).await
+    }
+    fn model_name(&self) -> Option<String> {
+        match MODEL_OVERRIDE.try_with(|o| o.model.clone()) {
+            Ok(Some(model)) => model.model_name(),
+            _ => self.model.model_name(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::ConfigurableAgentBuilder;
+    use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+    use agents_core::state::AgentStateSnapshot;
+    use agents_core::tools::{ToolParameterSchema, ToolResult};
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Mutex;
+
+    /// Always calls `delete`, recording the temperature and tools of each request.
+    #[derive(Default)]
+    struct DeletingModel(Mutex<Vec<(Option<f32>, Vec<String>)>>);
+
+    #[async_trait]
+    impl LanguageModel for DeletingModel {
+        async fn generate(&self, request: LlmRequest) -> anyhow::Result<LlmResponse> {
+            let mut tools: Vec<_> = request.tools.iter().map(|t| t.name.clone()).collect();
+            tools.sort();
+            self.0.lock().unwrap().push((request.temperature, tools));
+            Ok(LlmResponse::new(AgentMessage {
+                role: MessageRole::Agent,
+                content: MessageContent::Text(
+                    r#"{"tool_calls":[{"name":"delete","args":{}}]}"#.to_string(),
+                ),
+                metadata: None,
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn options_override_model_tools_and_iterations_for_one_run() {
+        let deleted = Arc::new(AtomicBool::new(false));
+        let tool = |name: &'static str, deleted: Arc<AtomicBool>| {
+            agents_toolkit::tool_sync(
+                name,
+                name,
+                ToolParameterSchema::object("No parameters", HashMap::new(), Vec::new()),
+                move |_args, ctx| {
+                    if name == "delete" {
+                        deleted.store(true, Ordering::SeqCst);
+                    }
+                    Ok(ToolResult::text(&ctx, "done"))
+                },
+            )
+        };
+        let default_model = Arc::new(DeletingModel::default());
+        let premium_model = Arc::new(DeletingModel::default());
+        let agent = ConfigurableAgentBuilder::new("assist")
+            .with_model(default_model.clone())
+            .with_tool(tool("search", deleted.clone()))
+            .with_tool(tool("delete", deleted.clone()))
+            .with_builtin_tools(Vec::<String>::new())
+            .with_auto_general_purpose(false)
+            .build()
+            .unwrap();
+
+        let options = RunOptions::new()
+            .with_model(premium_model.clone())
+            .with_temperature(0.2)
+            .with_allowed_tools(["search"])
+            .with_max_iterations(2);
+        let reply = agent
+            .handle_message_with_options(
+                "clean up",
+                options,
+                Arc::new(AgentStateSnapshot::default()),
+            )
+            .await
+            .unwrap();
+
+        assert!(reply
+            .content
+            .as_text()
+            .unwrap()
+            .contains("maximum number of steps"));
+        assert!(!deleted.load(Ordering::SeqCst));
+        assert!(default_model.0.lock().unwrap().is_empty());
+        assert_eq!(
+            *premium_model.0.lock().unwrap(),
+            vec![(Some(0.2), vec!["search".to_string()]); 2]
+        );
+
+        // Without options the agent is unchanged
+        agent
+            .handle_message("clean up", Arc::new(AgentStateSnapshot::default()))
+            .await
+            .unwrap();
+        assert!(deleted.load(Ordering::SeqCst));
+        let (temperature, tools) = default_model.0.lock().unwrap()[0].clone();
+        assert_eq!(temperature, None);
+        assert!(tools.contains(&"delete".to_string()));
+    }
+
+    /// Answers in Markdown, recording the history of each request.
+    #[derive(Default)]
+    struct MarkdownModel(Mutex<Vec<Vec<String>>>);
+
+    #[async_trait]
+    impl LanguageModel for MarkdownModel {
+        async fn generate(&self, request: LlmRequest) -> anyhow::Result<LlmResponse> {
+            let history = request
+                .messages
+                .iter()
+                .map(|m| m.content.to_text())
+                .collect();
+            self.0.lock().unwrap().push(history);
+            Ok(LlmResponse::new(AgentMessage {
+                role: MessageRole::Agent,
+                content: MessageContent::Text("**Done** 🎉".to_string()),
+                metadata: None,
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn output_format_rewrites_the_reply_but_not_the_history() {
+        let model = Arc::new(MarkdownModel::default());
+        let agent = ConfigurableAgentBuilder::new("assist")
+            .with_model(model.clone())
+            .build()
+            .unwrap();
+
+        let options = RunOptions::new().with_output_format(OutputFormat::sms());
+        let reply = agent
+            .handle_message_with_options("refund", options, Arc::new(AgentStateSnapshot::default()))
+            .await
+            .unwrap();
+        assert_eq!(reply.content.as_text(), Some("Done"));
+
+        agent
+            .handle_message("thanks", Arc::new(AgentStateSnapshot::default()))
+            .await
+            .unwrap();
+        let requests = model.0.lock().unwrap();
+        assert!(requests[1].contains(&"**Done** 🎉".to_string()));
+    }
+}
@@ -0,0 +1,557 @@
+//! Diagrams of a run for debugging and documentation
+//!
+//! A [`RunTrace`] lays out the events of a run from the [`RunJournal`](super::RunJournal)
+//! as steps: planner iterations, tool calls with their durations and delegations to
+//! sub-agents. It renders as a Mermaid sequence diagram or a Graphviz DOT graph that
+//! can be pasted into Markdown or piped into `dot -Tsvg`.
+
+use super::report::{format_duration, ToolCallRecord, ToolCallStatus};
+use agents_core::events::AgentEvent;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+
+/// Characters of free text (requests, arguments, responses) shown per diagram label
+const LABEL_CHARS: usize = 60;
+
+/// One step of a run, in order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TraceStep {
+    /// The planner decided on the next action.
+    Planning {
+        iteration: usize,
+        /// `respond`, `call_tool` or `terminate`
+        action: String,
+        summary: String,
+    },
+    ToolCall(ToolCallRecord),
+    /// The agent handed a task to a sub-agent through the `task` tool.
+    Delegation {
+        agent: String,
+        instruction: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        duration_ms: Option<u64>,
+        status: ToolCallStatus,
+    },
+}
+
+/// The steps of a single run, renderable as a diagram.
+///
+/// ```ignore
+/// let run_id = agent.last_run_id().unwrap();
+/// let trace = agent.run_trace(&run_id).unwrap();
+/// std::fs::write("run.mmd", trace.to_mermaid())?;
+/// std::fs::write("run.dot", trace.to_dot())?;
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunTrace {
+    pub run_id: String,
+    pub agent_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    pub steps: Vec<TraceStep>,
+}
+
+/// A sub-agent run reported by its start and completion events
+struct SubAgentRun {
+    agent: String,
+    instruction: String,
+    duration_ms: Option<u64>,
+}
+
+impl RunTrace {
+    /// Lay out the events of a run as steps.
+    ///
+    /// Sub-agent events are broadcast asynchronously, so they are matched to the
+    /// run's `task` tool calls in order rather than placed where they were recorded.
+    pub fn from_events(run_id: impl Into<String>, events: &[AgentEvent]) -> Self {
+        let mut trace = RunTrace {
+            run_id: run_id.into(),
+            ..Default::default()
+        };
+        let mut sub_agent_runs: Vec<SubAgentRun> = Vec::new();
+        let mut iteration = 0;
+
+        for event in events {
+            match event {
+                AgentEvent::AgentStarted(e) => {
+                    trace.agent_name = e.agent_name.clone();
+                    trace.request = Some(e.message_preview.clone());
+                }
+                AgentEvent::AgentCompleted(e) => {
+                    trace.duration_ms = Some(e.duration_ms);
+                    trace.response = Some(e.response.clone());
+                }
+                AgentEvent::PlanningComplete(e) => {
+                    iteration += 1;
+                    trace.steps.push(TraceStep::Planning {
+                        iteration,
+                        action: e.action_type.clone(),
+                        summary: e.action_summary.clone(),
+                    });
+                }
+                AgentEvent::ToolStarted(e) => {
+                    trace.steps.push(TraceStep::ToolCall(ToolCallRecord {
+                        tool_name: e.tool_name.clone(),
+                        input_summary: e.input_summary.clone(),
+                        duration_ms: None,
+                        status: ToolCallStatus::Pending,
+                        error: None,
+                    }))
+                }
+                AgentEvent::ToolCompleted(e) => {
+                    if let Some(call) = trace.pending_call(&e.tool_name) {
+                        call.duration_ms = Some(e.duration_ms);
+                        call.status = if e.success {
+                            ToolCallStatus::Succeeded
+                        } else {
+                            ToolCallStatus::Failed
+                        };
+                    }
+                }
+                AgentEvent::ToolFailed(e) => {
+                    if let Some(call) = trace.pending_call(&e.tool_name) {
+                        call.duration_ms = Some(e.duration_ms);
+                        call.status = ToolCallStatus::Failed;
+                        call.error = Some(e.error_message.clone());
+                    }
+                }
+                AgentEvent::SubAgentStarted(e) => sub_agent_runs.push(SubAgentRun {
+                    agent: e.agent_name.clone(),
+                    instruction: e.instruction_summary.clone(),
+                    duration_ms: None,
+                }),
+                AgentEvent::SubAgentCompleted(e) => {
+                    if let Some(run) = sub_agent_runs
+                        .iter_mut()
+                        .find(|run| run.agent == e.agent_name && run.duration_ms.is_none())
+                    {
+                        run.duration_ms = Some(e.duration_ms);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut sub_agent_runs = sub_agent_runs.into_iter();
+        for step in &mut trace.steps {
+            let TraceStep::ToolCall(call) = step else {
+                continue;
+            };
+            if call.tool_name != "task" {
+                continue;
+            }
+            let Some(run) = sub_agent_runs.next() else {
+                break;
+            };
+            *step = TraceStep::Delegation {
+                agent: run.agent,
+                instruction: run.instruction,
+                duration_ms: run.duration_ms.or(call.duration_ms),
+                status: call.status,
+            };
+        }
+
+        trace
+    }
+
+    fn pending_call(&mut self, tool_name: &str) -> Option<&mut ToolCallRecord> {
+        self.steps.iter_mut().rev().find_map(|step| match step {
+            TraceStep::ToolCall(call)
+                if call.tool_name == tool_name && call.status == ToolCallStatus::Pending =>
+            {
+                Some(call)
+            }
+            _ => None,
+        })
+    }
+
+    fn agent_label(&self) -> &str {
+        if self.agent_name.is_empty() {
+            "agent"
+        } else {
+            &self.agent_name
+        }
+    }
+
+    /// Render the run as a Mermaid sequence diagram.
+    pub fn to_mermaid(&self) -> String {
+        let mut participants: Vec<(String, String)> = Vec::new();
+        let mut participant = |kind: &str, name: &str| -> String {
+            if let Some((id, _)) = participants.iter().find(|(_, n)| n == name) {
+                return id.clone();
+            }
+            let id = format!("{}{}", kind, participants.len() + 1);
+            participants.push((id.clone(), name.to_string()));
+            id
+        };
+
+        let mut body = String::new();
+        if let Some(request) = &self.request {
+            let _ = writeln!(body, "    User->>Agent: {}", mermaid_text(request));
+        }
+        for step in &self.steps {
+            match step {
+                TraceStep::Planning {
+                    iteration,
+                    action,
+                    summary,
+                } => {
+                    let _ = writeln!(body, "    Agent->>Planner: iteration {}", iteration);
+                    let _ = writeln!(
+                        body,
+                        "    Planner-->>Agent: {}",
+                        mermaid_text(if action == "call_tool" {
+                            summary
+                        } else {
+                            action
+                        })
+                    );
+                }
+                TraceStep::ToolCall(call) => {
+                    let id = participant("T", &call.tool_name);
+                    let _ = writeln!(
+                        body,
+                        "    Agent->>{}: {}",
+                        id,
+                        mermaid_text(&call.input_summary)
+                    );
+                    let _ = writeln!(
+                        body,
+                        "    {}{}Agent: {}",
+                        id,
+                        mermaid_arrow(call.status),
+                        mermaid_text(&outcome(call.status, call.duration_ms, &call.error))
+                    );
+                }
+                TraceStep::Delegation {
+                    agent,
+                    instruction,
+                    duration_ms,
+                    status,
+                } => {
+                    let id = participant("S", agent);
+                    let _ = writeln!(body, "    Agent->>+{}: {}", id, mermaid_text(instruction));
+                    let _ = writeln!(
+                        body,
+                        "    {}{}-Agent: {}",
+                        id,
+                        mermaid_arrow(*status),
+                        mermaid_text(&outcome(*status, *duration_ms, &None))
+                    );
+                }
+            }
+        }
+        if let Some(response) = &self.response {
+            let mut text = mermaid_text(response);
+            if let Some(duration_ms) = self.duration_ms {
+                text = format!("{} ({})", text, format_duration(duration_ms));
+            }
+            let _ = writeln!(body, "    Agent-->>User: {}", text);
+        }
+
+        let mut diagram = String::from("sequenceDiagram\n");
+        let _ = writeln!(diagram, "    actor User");
+        let _ = writeln!(
+            diagram,
+            "    participant Agent as {}",
+            mermaid_text(self.agent_label())
+        );
+        if self
+            .steps
+            .iter()
+            .any(|step| matches!(step, TraceStep::Planning { .. }))
+        {
+            let _ = writeln!(diagram, "    participant Planner");
+        }
+        for (id, name) in &participants {
+            let _ = writeln!(diagram, "    participant {} as {}", id, mermaid_text(name));
+        }
+        diagram.push_str(&body);
+        diagram
+    }
+
+    /// Render the run as a Graphviz DOT graph, one node per step.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph run {\n");
+        let _ = writeln!(dot, "    rankdir=TB;");
+        let _ = writeln!(dot, "    node [shape=box, fontname=\"Helvetica\"];");
+        let _ = writeln!(
+            dot,
+            "    label={};",
+            dot_string(&format!("{} run {}", self.agent_label(), self.run_id))
+        );
+
+        let mut nodes: Vec<String> = Vec::new();
+        let request = self.request.as_deref().unwrap_or("");
+        let _ = writeln!(
+            dot,
+            "    start [shape=oval, label={}];",
+            dot_string(&format!("Request\n{}", truncate(request)))
+        );
+        nodes.push("start".to_string());
+
+        for (i, step) in self.steps.iter().enumerate() {
+            let id = format!("s{}", i + 1);
+            let (label, attributes) = match step {
+                TraceStep::Planning {
+                    iteration,
+                    action,
+                    summary,
+                } => (
+                    format!("Plan {}\n{}", iteration, truncate(summary)),
+                    format!("shape=diamond, comment={}", dot_string(action)),
+                ),
+                TraceStep::ToolCall(call) => (
+                    format!(
+                        "{}\n{}",
+                        call.tool_name,
+                        outcome(call.status, call.duration_ms, &call.error)
+                    ),
+                    format!("color={}", dot_color(call.status)),
+                ),
+                TraceStep::Delegation {
+                    agent,
+                    instruction,
+                    duration_ms,
+                    status,
+                } => (
+                    format!(
+                        "Sub-agent {}\n{}\n{}",
+                        agent,
+                        truncate(instruction),
+                        outcome(*status, *duration_ms, &None)
+                    ),
+                    format!("shape=box3d, color={}", dot_color(*status)),
+                ),
+            };
+            let _ = writeln!(
+                dot,
+                "    {} [label={}, {}];",
+                id,
+                dot_string(&label),
+                attributes
+            );
+            nodes.push(id);
+        }
+
+        if let Some(response) = &self.response {
+            let mut label = format!("Response\n{}", truncate(response));
+            if let Some(duration_ms) = self.duration_ms {
+                label = format!("{}\n{}", label, format_duration(duration_ms));
+            }
+            let _ = writeln!(dot, "    end [shape=oval, label={}];", dot_string(&label));
+            nodes.push("end".to_string());
+        }
+
+        for pair in nodes.windows(2) {
+            let _ = writeln!(dot, "    {} -> {};", pair[0], pair[1]);
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn outcome(status: ToolCallStatus, duration_ms: Option<u64>, error: &Option<String>) -> String {
+    let status = match (status, error) {
+        (ToolCallStatus::Pending, _) => "pending".to_string(),
+        (ToolCallStatus::Succeeded, _) => "ok".to_string(),
+        (ToolCallStatus::Failed, Some(error)) => format!("failed: {}", truncate(error)),
+        (ToolCallStatus::Failed, None) => "failed".to_string(),
+    };
+    match duration_ms {
+        Some(duration_ms) => format!("{} ({})", status, format_duration(duration_ms)),
+        None => status,
+    }
+}
+
+/// First line of `text`, cut to [`LABEL_CHARS`] characters.
+fn truncate(text: &str) -> String {
+    let line = text.lines().next().unwrap_or("").trim();
+    if line.chars().count() > LABEL_CHARS || text.trim().lines().nth(1).is_some() {
+        let cut: String = line.chars().take(LABEL_CHARS).collect();
+        format!("{}…", cut.trim_end())
+    } else {
+        line.to_string()
+    }
+}
+
+fn mermaid_arrow(status: ToolCallStatus) -> &'static str {
+    match status {
+        ToolCallStatus::Failed => "--x",
+        _ => "-->>",
+    }
+}
+
+/// Text safe for a Mermaid message: `#` and `;` end statements, so they become entities.
+fn mermaid_text(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in truncate(text).chars() {
+        match c {
+            '#' => escaped.push_str("#35;"),
+            ';' => escaped.push_str("#59;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn dot_color(status: ToolCallStatus) -> &'static str {
+    match status {
+        ToolCallStatus::Pending => "gray",
+        ToolCallStatus::Succeeded => "darkgreen",
+        ToolCallStatus::Failed => "red",
+    }
+}
+
+/// A quoted DOT string with line breaks as `\n`.
+fn dot_string(text: &str) -> String {
+    format!(
+        "\"{}\"",
+        text.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::events::{
+        AgentCompletedEvent, AgentStartedEvent, EventMetadata, PlanningCompleteEvent,
+        SubAgentCompletedEvent, SubAgentStartedEvent, ToolCompletedEvent, ToolFailedEvent,
+        ToolStartedEvent,
+    };
+
+    fn metadata() -> EventMetadata {
+        EventMetadata::new("default".into(), "run".into(), None)
+    }
+
+    fn planned(summary: &str) -> AgentEvent {
+        AgentEvent::PlanningComplete(PlanningCompleteEvent {
+            metadata: metadata(),
+            action_type: if summary.starts_with("Call tool") {
+                "call_tool".into()
+            } else {
+                "respond".into()
+            },
+            action_summary: summary.into(),
+        })
+    }
+
+    fn tool(name: &str, input: &str, duration_ms: u64, error: Option<&str>) -> Vec<AgentEvent> {
+        let started = AgentEvent::ToolStarted(ToolStartedEvent {
+            metadata: metadata(),
+            tool_name: name.into(),
+            input_summary: input.into(),
+        });
+        let finished = match error {
+            None => AgentEvent::ToolCompleted(ToolCompletedEvent {
+                metadata: metadata(),
+                tool_name: name.into(),
+                duration_ms,
+                result_summary: "done".into(),
+                success: true,
+            }),
+            Some(error) => AgentEvent::ToolFailed(ToolFailedEvent {
+                metadata: metadata(),
+                tool_name: name.into(),
+                duration_ms,
+                error_message: error.into(),
+                is_recoverable: true,
+                retry_count: 0,
+            }),
+        };
+        vec![started, finished]
+    }
+
+    fn run_events() -> Vec<AgentEvent> {
+        let mut events = vec![
+            AgentEvent::AgentStarted(AgentStartedEvent {
+                metadata: metadata(),
+                agent_name: "research".into(),
+                message_preview: "Compare axum; actix #1".into(),
+            }),
+            planned("Call tool: web_search"),
+        ];
+        events.extend(tool("web_search", r#"{"q":"axum"}"#, 1_250, None));
+        events.push(planned("Call tool: task"));
+        events.extend(tool("task", r#"{"agent":"critic"}"#, 3_100, None));
+        events.push(planned("Call tool: fetch"));
+        events.extend(tool("fetch", r#"{"url":"x"}"#, 40, Some("timeout")));
+        events.push(planned("Respond: Axum"));
+        events.push(AgentEvent::AgentCompleted(AgentCompletedEvent {
+            metadata: metadata(),
+            agent_name: "research".into(),
+            duration_ms: 4_500,
+            response_preview: "Axum".into(),
+            response: "Axum wins".into(),
+        }));
+        // Broadcast sub-agent events arrive after the run
+        events.push(AgentEvent::SubAgentStarted(SubAgentStartedEvent {
+            metadata: metadata(),
+            agent_name: "critic".into(),
+            instruction_summary: "Review the comparison".into(),
+            delegation_depth: 1,
+        }));
+        events.push(AgentEvent::SubAgentCompleted(SubAgentCompletedEvent {
+            metadata: metadata(),
+            agent_name: "critic".into(),
+            duration_ms: 3_000,
+            result_summary: "Looks right".into(),
+        }));
+        events
+    }
+
+    #[test]
+    fn trace_matches_delegations_to_task_calls() {
+        let trace = RunTrace::from_events("run-1", &run_events());
+
+        assert_eq!(trace.steps.len(), 7);
+        assert!(matches!(
+            &trace.steps[3],
+            TraceStep::Delegation { agent, duration_ms: Some(3_000), .. } if agent == "critic"
+        ));
+        assert!(matches!(
+            &trace.steps[5],
+            TraceStep::ToolCall(call) if call.status == ToolCallStatus::Failed
+        ));
+    }
+
+    #[test]
+    fn trace_renders_as_mermaid() {
+        let mermaid = RunTrace::from_events("run-1", &run_events()).to_mermaid();
+
+        assert!(mermaid.starts_with("sequenceDiagram\n    actor User\n"));
+        assert!(mermaid.contains("    participant Agent as research\n"));
+        assert!(mermaid.contains("    participant T1 as web_search\n"));
+        assert!(mermaid.contains("    participant S2 as critic\n"));
+        assert!(mermaid.contains("    User->>Agent: Compare axum#59; actix #35;1\n"));
+        assert!(mermaid.contains("    Agent->>Planner: iteration 1\n"));
+        assert!(mermaid.contains("    Planner-->>Agent: Call tool: web_search\n"));
+        assert!(mermaid.contains("    T1-->>Agent: ok (1.2s)\n"));
+        assert!(mermaid.contains("    Agent->>+S2: Review the comparison\n"));
+        assert!(mermaid.contains("    S2-->>-Agent: ok (3.0s)\n"));
+        assert!(mermaid.contains("    T3--xAgent: failed: timeout (40ms)\n"));
+        assert!(mermaid.ends_with("    Agent-->>User: Axum wins (4.5s)\n"));
+    }
+
+    #[test]
+    fn trace_renders_as_dot() {
+        let dot = RunTrace::from_events("run-1", &run_events()).to_dot();
+
+        assert!(dot.starts_with("digraph run {\n"));
+        assert!(dot.contains(r#"s2 [label="web_search\nok (1.2s)", color=darkgreen];"#));
+        assert!(dot.contains(
+            r#"s4 [label="Sub-agent critic\nReview the comparison\nok (3.0s)", shape=box3d, color=darkgreen];"#
+        ));
+        assert!(dot.contains(r#"s6 [label="fetch\nfailed: timeout (40ms)", color=red];"#));
+        assert!(dot.contains("    start -> s1;\n"));
+        assert!(dot.contains("    s7 -> end;\n"));
+        assert!(dot.ends_with("}\n"));
+    }
+}
@@ -3,30 +3,90 @@
 //! This module contains the core DeepAgent struct and its runtime behavior,
 //! including message handling, tool execution, HITL support, and state management.
 
+use super::approvals::ApprovalConfig;
+use super::citations::{CitationConfig, Provenance};
+use super::concurrency::{ConcurrencyLimits, ConcurrencyStats, Permit, RunPriority};
 use super::config::DeepAgentConfig;
+use super::debugger::{RecordedStep, RunRecording};
+use super::deterministic::DeterministicConfig;
+use super::experiments::Experiment;
+use super::export::ThreadExport;
+use super::graph_memory::GraphMemoryConfig;
+use super::memory_policy::MemoryPolicy;
+use super::offload::OffloadConfig;
+use super::output_sink::OutputSinkConfig;
+use super::profile::Profile;
+use super::prompt_snapshot::PromptSnapshot;
+use super::quotas::{self, QuotaAction, QuotaConfig};
+use super::report::{RunJournal, RunReport};
+use super::run_options::{self, RunOptions};
+use super::run_trace::RunTrace;
+use super::self_test::{self, SelfTestCheck, SelfTestCheckKind, SelfTestOptions, SelfTestReport};
+use super::shutdown::{
+    AgentShutdownError, RunGate, RunGuard, ShutdownReport, SHUTDOWN_FLUSH_TIMEOUT,
+};
+use super::state_size::{StateMetrics, StateSize, StateSizeLimits, StateSizeTracker};
+use super::stream_coalescing::StreamCoalescing;
+use super::stream_tee::{StreamTee, TeedChunk};
+use super::thread_affinity::{HeldLease, ThreadLockConfig};
+use super::thread_titles::ThreadTitleConfig;
+use super::tool_arguments::{self, ToolArgumentValidation};
+use super::tool_conflicts::ToolConflictStrategy;
+use super::tool_examples::ToolExamplesConfig;
+use super::tool_mocks::ToolMocks;
+use super::tool_output::{self, ToolOutputConfig};
+use super::tool_retry::ToolRetryConfig;
+use super::tool_selection::ToolSelectionConfig;
+use super::translation::TranslationConfig;
+use super::user_memory::{self, UserErasure, UserMemoryConfig};
+use crate::locale::LocaleConfig;
+use crate::middleware::token_tracking::{TokenTrackingMiddleware, TokenUsageSummary};
 use crate::middleware::{
+    prompt_compression::PromptCompressionMiddleware,
+    time_context::{self, TimeContextMiddleware},
+    user_profile::UserProfileMiddleware,
     AgentMiddleware, AnthropicPromptCachingMiddleware, BaseSystemPromptMiddleware,
     DeepAgentPromptMiddleware, FilesystemMiddleware, HumanInLoopMiddleware, MiddlewareContext,
     ModelRequest, PlanningMiddleware, SubAgentDescriptor, SubAgentMiddleware, SubAgentRegistration,
     SummarizationMiddleware,
 };
 use crate::planner::LlmBackedPlanner;
+use crate::prompts::PromptFormat;
 use agents_core::agent::{
-    AgentDescriptor, AgentHandle, PlannerAction, PlannerContext, PlannerHandle,
+    with_current_agent, AgentDescriptor, AgentHandle, PlannerAction, PlannerContext, PlannerHandle,
 };
-use agents_core::hitl::{AgentInterrupt, HitlAction};
+use agents_core::approval_store::ApprovalDecision;
+use agents_core::correlation::{current_correlation_id, with_correlation_id};
+use agents_core::dead_letter::{DeadLetter, DeadLetterStore};
+use agents_core::experiment::{self, ExperimentAssignment};
+use agents_core::guardrail::GuardrailSource;
+use agents_core::hitl::{AgentInterrupt, HitlAction, HitlDecisionRecord};
+use agents_core::memory::{MemoryEntry, UserId};
 use agents_core::messaging::{AgentMessage, MessageContent, MessageMetadata, MessageRole};
-use agents_core::persistence::{Checkpointer, ThreadId};
+use agents_core::outbox::EventOutbox;
+use agents_core::persistence::{self, Checkpointer, FileLoader, ThreadId, ThreadMetadata};
+use agents_core::policy::{PolicyDecision, PolicyProvider, PolicyRequest, PolicyThread};
+use agents_core::profiling::{self, ProfilePhase, RunProfiler};
+use agents_core::quota::QuotaRemaining;
+use agents_core::search::{ConversationIndex, IndexedMessage, SearchHit};
 use agents_core::state::AgentStateSnapshot;
-use agents_core::tools::{ToolBox, ToolContext, ToolResult};
+use agents_core::thread_template::ThreadTemplate;
+use agents_core::tools::{
+    ArgumentError, ToolBox, ToolContext, ToolExtensions, ToolProvider, ToolResult,
+};
+use agents_core::trace_context::TraceContext;
 use async_trait::async_trait;
 use serde_json::Value;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
 use std::num::NonZeroUsize;
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::Instrument;
 
 // Built-in tool names exposed by middlewares. The `task` tool for subagents is not gated.
-const BUILTIN_TOOL_NAMES: &[&str] = &["write_todos", "ls", "read_file", "write_file", "edit_file"];
+pub(crate) const BUILTIN_TOOL_NAMES: &[&str] =
+    &["write_todos", "ls", "read_file", "write_file", "edit_file"];
 
 // (no streaming types in baseline)
 
@@ -47,6 +107,35 @@ fn count_todos(todos: &[agents_core::state::TodoItem]) -> (usize, usize, usize)
     (pending, in_progress, completed)
 }
 
+/// History entry recording the planner's decision to call a tool. Kept as a system
+/// message so the model doesn't echo it back as a response.
+fn tool_call_message(tool_name: &str, payload: &Value) -> AgentMessage {
+    AgentMessage {
+        role: MessageRole::System,
+        content: MessageContent::Text(format!(
+            "Calling tool: {} with args: {}",
+            tool_name,
+            serde_json::to_string(payload).unwrap_or_default()
+        )),
+        metadata: None,
+    }
+}
+
+/// State and conversation of the run in progress. Every run executes in a scope of
+/// its own, so concurrent runs of one agent never see each other's state.
+struct RunContext {
+    /// Agent the run belongs to; runs of sub-agents open scopes of their own
+    agent: usize,
+    /// Thread runs keep their conversation in the thread's state
+    thread_id: Option<ThreadId>,
+    state: Arc<RwLock<AgentStateSnapshot>>,
+    history: Arc<RwLock<Vec<AgentMessage>>>,
+}
+
+tokio::task_local! {
+    static RUN_CONTEXT: RunContext;
+}
+
 /// Core Deep Agent runtime implementation
 ///
 /// This struct contains all the runtime state and behavior for a Deep Agent,
@@ -57,7 +146,15 @@ pub struct DeepAgent {
     planner: Arc<dyn PlannerHandle>,
     middlewares: Vec<Arc<dyn AgentMiddleware>>,
     base_tools: Vec<ToolBox>,
+    tool_providers: Vec<Arc<dyn ToolProvider>>,
+    subagents: Vec<SubAgentRegistration>,
+    /// Models of the sub-agents configured with their own model, pinged by self-tests
+    subagent_models: Vec<(String, Arc<dyn agents_core::llm::LanguageModel>)>,
+    /// Tools returned by `tool_providers` at the start of the latest run
+    provider_tools: RwLock<Vec<ToolBox>>,
+    /// State of the latest run, used outside of runs
     state: Arc<RwLock<AgentStateSnapshot>>,
+    /// Conversation of the runs without a thread
     history: Arc<RwLock<Vec<AgentMessage>>>,
     _summarization: Option<Arc<SummarizationMiddleware>>,
     _hitl: Option<Arc<HumanInLoopMiddleware>>,
@@ -66,26 +163,134 @@ pub struct DeepAgent {
     event_dispatcher: Option<Arc<agents_core::events::EventDispatcher>>,
     enable_pii_sanitization: bool,
     max_iterations: NonZeroUsize,
+    profiling: bool,
+    step_recording: bool,
+    lazy_file_loading: bool,
+    tool_output: Option<ToolOutputConfig>,
+    profile: Profile,
+    /// Tools removed by the profile
+    disabled_tools: HashSet<String>,
+    /// Tools answered by mocks under the profile
+    tool_mocks: ToolMocks,
+    log_tool_payloads: bool,
+    tool_retry: Option<ToolRetryConfig>,
+    citations: Option<CitationConfig>,
+    tool_selection: Option<ToolSelectionConfig>,
+    tool_examples: ToolExamplesConfig,
+    tool_argument_validation: ToolArgumentValidation,
+    tool_conflicts: ToolConflictStrategy,
+    prompt_format: PromptFormat,
+    tool_extensions: ToolExtensions,
+    locale: Option<LocaleConfig>,
+    deterministic: Option<DeterministicConfig>,
+    journal: Arc<RunJournal>,
+    offload: Option<OffloadConfig>,
+    output_sink: Option<OutputSinkConfig>,
+    translation: Option<TranslationConfig>,
+    stream_coalescing: Option<StreamCoalescing>,
+    stream_tee: StreamTee,
+    run_gate: Arc<RunGate>,
+    concurrency: ConcurrencyLimits,
+    thread_lock: Option<ThreadLockConfig>,
+    experiment: Option<Experiment>,
+    approvals: Option<ApprovalConfig>,
+    policy_provider: Option<Arc<dyn PolicyProvider>>,
+    dead_letters: Option<Arc<dyn DeadLetterStore>>,
+    conversation_index: Option<Arc<dyn ConversationIndex>>,
+    thread_titles: Option<ThreadTitleConfig>,
+    graph_memory: Option<GraphMemoryConfig>,
+    user_memory: Option<UserMemoryConfig>,
+    memory_policy: Option<Arc<MemoryPolicy>>,
+    state_size_limits: Option<StateSizeLimits>,
+    state_sizes: StateSizeTracker,
+    quotas: Option<QuotaConfig>,
+    event_outbox: Option<Arc<dyn EventOutbox>>,
+    usage_tracker: Option<Arc<TokenTrackingMiddleware>>,
 }
 
 impl DeepAgent {
     fn collect_tools(&self) -> HashMap<String, ToolBox> {
-        let mut tools: HashMap<String, ToolBox> = HashMap::new();
-        for tool in &self.base_tools {
-            tools.insert(tool.schema().name.clone(), tool.clone());
-        }
+        let mut builtin: HashMap<String, ToolBox> = HashMap::new();
         for middleware in &self.middlewares {
             for tool in middleware.tools() {
                 let tool_name = tool.schema().name.clone();
                 if self.should_include(&tool_name) {
-                    tools.insert(tool_name, tool);
+                    builtin.insert(tool_name, tool);
+                }
+            }
+        }
+        let provider_tools = self
+            .provider_tools
+            .read()
+            .map(|tools| tools.clone())
+            .unwrap_or_default();
+        let mut tools: HashMap<String, ToolBox> = HashMap::new();
+        for tool in self.base_tools.iter().chain(&provider_tools) {
+            let tool = if builtin.contains_key(&tool.schema().name) {
+                match self.tool_conflicts.resolve(tool.clone()) {
+                    Some(tool) => tool,
+                    None => continue,
                 }
+            } else {
+                tool.clone()
+            };
+            tools.insert(tool.schema().name.clone(), tool);
+        }
+        for (name, tool) in builtin {
+            if self.tool_conflicts.keeps_builtin() || !tools.contains_key(&name) {
+                tools.insert(name, tool);
             }
         }
+        tools.retain(|name, _| !self.disabled_tools.contains(name));
+        self.tool_mocks.apply(&mut tools);
         tools
     }
+
+    /// Ask the tool providers for their current tools, starting them on first use.
+    /// If a provider fails, the tools of the previous run stay in place.
+    async fn refresh_provider_tools(&self) {
+        if self.tool_providers.is_empty() {
+            return;
+        }
+        let mut loaded = Vec::new();
+        for provider in &self.tool_providers {
+            match provider.tools().await {
+                Ok(tools) => loaded.push(tools),
+                Err(e) => {
+                    tracing::warn!(error = %e, "Tool provider failed, keeping previous tools");
+                    return;
+                }
+            }
+        }
+        if let Ok(mut tools) = self.provider_tools.write() {
+            *tools = loaded.into_iter().flatten().collect();
+        }
+    }
     // no streaming path in baseline
 
+    /// Schemas exposed to the model this turn: localized for the thread's locale and
+    /// narrowed by tool selection when configured.
+    async fn tool_schemas_for_turn(
+        &self,
+        tools: &HashMap<String, ToolBox>,
+        messages: &[AgentMessage],
+    ) -> Vec<agents_core::tools::ToolSchema> {
+        let mut schemas: Vec<_> = tools.values().map(|t| t.schema()).collect();
+        if let Some(locale) = &self.locale {
+            let thread_locale = self.run_state().read().ok().and_then(|s| s.locale.clone());
+            let thread_locale = thread_locale.as_deref().unwrap_or(&locale.default_locale);
+            schemas = locale.localize_tools(thread_locale, schemas);
+        }
+        let schemas: Vec<_> = schemas
+            .into_iter()
+            .map(|schema| schema.with_deprecation_guidance())
+            .collect();
+        match &self.tool_selection {
+            Some(selection) => selection.select(schemas, messages).await,
+            None => schemas,
+        }
+    }
+
     fn should_include(&self, name: &str) -> bool {
         let is_builtin = BUILTIN_TOOL_NAMES.contains(&name);
         if !is_builtin {
@@ -97,38 +302,258 @@ impl DeepAgent {
         }
     }
 
+    /// Read `f` from the context of this agent's run in progress, if any.
+    fn with_run_context<T>(&self, f: impl FnOnce(&RunContext) -> T) -> Option<T> {
+        let agent = Arc::as_ptr(&self.state) as usize;
+        RUN_CONTEXT
+            .try_with(|context| (context.agent == agent).then(|| f(context)))
+            .ok()
+            .flatten()
+    }
+
+    /// State of the run in progress, or of the latest run outside of runs.
+    fn run_state(&self) -> Arc<RwLock<AgentStateSnapshot>> {
+        self.with_run_context(|context| context.state.clone())
+            .unwrap_or_else(|| self.state.clone())
+    }
+
+    fn run_history(&self) -> Arc<RwLock<Vec<AgentMessage>>> {
+        self.with_run_context(|context| context.history.clone())
+            .unwrap_or_else(|| self.history.clone())
+    }
+
+    /// Whether the conversation of the run in progress is saved with its thread.
+    fn is_thread_run(&self) -> bool {
+        self.with_run_context(|context| context.thread_id.is_some())
+            .unwrap_or(false)
+    }
+
+    /// Execute `run` in a run scope of its own. Thread runs start with an empty
+    /// conversation and load theirs with the thread's state; other runs continue
+    /// the agent's conversation. Afterwards the agent keeps the state of the run,
+    /// with the conversation of thread runs, and the conversation of runs without
+    /// a thread.
+    async fn in_run_scope<F: Future>(&self, thread_id: Option<&ThreadId>, run: F) -> F::Output {
+        if self.with_run_context(|_| ()).is_some() {
+            return Box::pin(run).await;
+        }
+        let (state, history) = match thread_id {
+            Some(_) => Default::default(),
+            None => {
+                let mut state = self.state.read().map(|s| s.clone()).unwrap_or_default();
+                state.messages.clear();
+                let history = self.history.read().map(|h| h.clone()).unwrap_or_default();
+                (state, history)
+            }
+        };
+        let state = Arc::new(RwLock::new(state));
+        let history = Arc::new(RwLock::new(history));
+        let context = RunContext {
+            agent: Arc::as_ptr(&self.state) as usize,
+            thread_id: thread_id.cloned(),
+            state: state.clone(),
+            history: history.clone(),
+        };
+        let output = RUN_CONTEXT.scope(context, Box::pin(run)).await;
+
+        let (Ok(state), Ok(history)) = (state.read(), history.read()) else {
+            return output;
+        };
+        let mut state = state.clone();
+        match thread_id {
+            Some(_) => state.messages = history.clone(),
+            None => {
+                if let Ok(mut agent_history) = self.history.write() {
+                    *agent_history = history.clone();
+                }
+            }
+        }
+        if let Ok(mut latest) = self.state.write() {
+            *latest = state;
+        }
+        output
+    }
+
+    /// Make `state` the state of the run in progress; a thread's saved conversation
+    /// becomes the run's history.
+    fn install_state(&self, mut state: AgentStateSnapshot) {
+        if self.is_thread_run() {
+            if let Ok(mut history) = self.run_history().write() {
+                *history = std::mem::take(&mut state.messages);
+            }
+        }
+        if let Ok(mut state_guard) = self.run_state().write() {
+            *state_guard = state;
+        }
+    }
+
+    /// The run's state as it is checkpointed, with the conversation of thread runs.
+    fn checkpoint_state(&self) -> anyhow::Result<AgentStateSnapshot> {
+        let mut state = self
+            .run_state()
+            .read()
+            .map_err(|_| anyhow::anyhow!("Failed to read agent state"))?
+            .clone();
+        if self.is_thread_run() {
+            state.messages = self.current_history();
+        }
+        Ok(state)
+    }
+
     fn append_history(&self, message: AgentMessage) {
-        if let Ok(mut history) = self.history.write() {
+        if let Ok(mut history) = self.run_history().write() {
             history.push(message);
         }
     }
 
     fn current_history(&self) -> Vec<AgentMessage> {
-        self.history.read().map(|h| h.clone()).unwrap_or_default()
+        self.run_history()
+            .read()
+            .map(|h| h.clone())
+            .unwrap_or_default()
+    }
+
+    /// Messages sent to the model: the thread's preloaded conversation, then the
+    /// history of its runs. Copied on every iteration, because middleware receive
+    /// the request's messages by value and may rewrite them; the copy then moves on
+    /// to the planner and the provider.
+    fn request_history(&self) -> Vec<AgentMessage> {
+        let mut messages = self
+            .run_state()
+            .read()
+            .map(|state| state.preloaded_messages.clone())
+            .unwrap_or_default();
+        messages.extend(self.current_history());
+        messages
+    }
+
+    /// Record the message the latest step's tool call added to the history.
+    fn record_step_result(&self, message: &AgentMessage) {
+        if self.step_recording {
+            self.journal.record_step_result(message);
+        }
     }
 
     fn emit_event(&self, event: agents_core::events::AgentEvent) {
+        self.journal.record(&event);
         if let Some(dispatcher) = &self.event_dispatcher {
-            let dispatcher_clone = dispatcher.clone();
-            tokio::spawn(async move {
-                dispatcher_clone.dispatch(event).await;
-            });
+            dispatcher.emit(event);
+        }
+    }
+
+    /// Take the staged events of the run with `correlation_id`; runs of other agents
+    /// sharing the dispatcher keep theirs.
+    fn take_staged_events(
+        &self,
+        correlation_id: Option<&str>,
+    ) -> Vec<agents_core::events::AgentEvent> {
+        match (&self.event_outbox, &self.event_dispatcher, correlation_id) {
+            (Some(_), Some(dispatcher), Some(correlation_id)) => {
+                dispatcher.take_staged_for(correlation_id)
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Correlation id of events emitted outside a correlation scope: that of the run
+    /// that last wrote the agent's state.
+    fn state_correlation_id(&self, state: &AgentStateSnapshot) -> Option<String> {
+        state
+            .correlation_id
+            .clone()
+            .or_else(|| self.journal.current_run_id())
+    }
+
+    fn restage_events(&self, events: Vec<agents_core::events::AgentEvent>) {
+        if let Some(dispatcher) = &self.event_dispatcher {
+            for event in events {
+                dispatcher.emit(event);
+            }
+        }
+    }
+
+    /// Write `state` to the checkpointer; with an event outbox, the events the run
+    /// that wrote `state` staged since the last write are stored in the same
+    /// transaction.
+    async fn persist_state(
+        &self,
+        thread_id: &ThreadId,
+        state: &AgentStateSnapshot,
+    ) -> anyhow::Result<()> {
+        tracing::debug!(
+            thread_id = %thread_id,
+            correlation_id = state.correlation_id.as_deref().unwrap_or_default(),
+            "Saving agent state"
+        );
+        let persisted = profiling::time_async(ProfilePhase::Persistence, async {
+            match (&self.event_outbox, &self.checkpointer) {
+                (Some(outbox), _) => {
+                    let correlation_id = self.state_correlation_id(state);
+                    let events = self.take_staged_events(correlation_id.as_deref());
+                    if let Err(e) = outbox
+                        .save_state_with_events(thread_id, state, &events)
+                        .await
+                    {
+                        self.restage_events(events);
+                        return Err(e);
+                    }
+                    Ok(())
+                }
+                (None, Some(checkpointer)) => checkpointer.save_state(thread_id, state).await,
+                (None, None) => Ok(()),
+            }
+        })
+        .await;
+        let saved = self.event_outbox.is_some() || self.checkpointer.is_some();
+        if saved && persisted.is_ok() {
+            self.record_state_size(thread_id, state);
+        }
+        persisted
+    }
+
+    /// Wait until every event broadcast started so far has completed, e.g. before
+    /// reporting a run as done to consumers of its events.
+    pub async fn flush_broadcasts(&self) {
+        if let Some(dispatcher) = &self.event_dispatcher {
+            dispatcher.flush().await;
+        }
+    }
+
+    /// Move events staged during a run that ended without a checkpoint to the outbox.
+    /// Called while the run is still admitted, so the agent's state is still that of
+    /// the run.
+    async fn flush_staged_events(&self, thread_id: &ThreadId) {
+        let Some(outbox) = &self.event_outbox else {
+            return;
+        };
+        let correlation_id = match self.run_state().read() {
+            Ok(state) => self.state_correlation_id(&state),
+            Err(_) => return,
+        };
+        let events = self.take_staged_events(correlation_id.as_deref());
+        if events.is_empty() {
+            return;
+        }
+        if let Err(e) = outbox.enqueue_events(thread_id, &events).await {
+            tracing::warn!(error = %e, "Failed to write events to the outbox");
+            self.restage_events(events);
         }
     }
 
     fn create_event_metadata(&self) -> agents_core::events::EventMetadata {
-        agents_core::events::EventMetadata::new(
-            "default".to_string(),
-            uuid::Uuid::new_v4().to_string(),
-            None,
-        )
+        // Events of the same run share its correlation id (the run id unless supplied)
+        let correlation_id = current_correlation_id()
+            .or_else(|| {
+                let state = self.run_state();
+                let state = state.read().ok()?;
+                self.state_correlation_id(&state)
+            })
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        agents_core::events::EventMetadata::new("default".to_string(), correlation_id, None)
     }
 
     fn truncate_message(&self, message: &AgentMessage) -> String {
-        let text = match &message.content {
-            MessageContent::Text(t) => t.clone(),
-            MessageContent::Json(v) => v.to_string(),
-        };
+        let text = message.content.to_text();
 
         if self.enable_pii_sanitization {
             agents_core::security::safe_preview(&text, agents_core::security::MAX_PREVIEW_LENGTH)
@@ -139,10 +564,7 @@ impl DeepAgent {
     }
 
     fn get_full_message_text(&self, message: &AgentMessage) -> String {
-        match &message.content {
-            MessageContent::Text(t) => t.clone(),
-            MessageContent::Json(v) => v.to_string(),
-        }
+        message.content.to_text()
     }
 
     fn summarize_payload(&self, payload: &Value) -> String {
@@ -163,19 +585,17 @@ impl DeepAgent {
 
     /// Save the current agent state to the configured checkpointer.
     pub async fn save_state(&self, thread_id: &ThreadId) -> anyhow::Result<()> {
-        if let Some(ref checkpointer) = self.checkpointer {
-            let state = self
-                .state
-                .read()
-                .map_err(|_| anyhow::anyhow!("Failed to read agent state"))?
-                .clone();
+        if self.checkpointer.is_some() {
+            let state = self.checkpoint_state()?;
 
             // Calculate state size before saving
-            let state_json = serde_json::to_string(&state)?;
+            let state_json = profiling::time(ProfilePhase::Serialization, || {
+                serde_json::to_string(&state)
+            })?;
             let state_size = state_json.len();
 
             // Save state to checkpointer
-            checkpointer.save_state(thread_id, &state).await?;
+            self.persist_state(thread_id, &state).await?;
 
             // Emit StateCheckpointed event after successful save
             self.emit_event(agents_core::events::AgentEvent::StateCheckpointed(
@@ -199,14 +619,54 @@ impl DeepAgent {
         }
     }
 
+    /// Keep the size of a checkpointed thread and report thresholds it crossed.
+    fn record_state_size(&self, thread_id: &ThreadId, state: &AgentStateSnapshot) {
+        // Thread runs checkpoint their conversation with the state
+        let history = if self.is_thread_run() {
+            Vec::new()
+        } else {
+            self.current_history()
+        };
+        let size = StateSize::measure(state, &history);
+        let previous = self.state_sizes.record(thread_id, size);
+        let Some(limits) = &self.state_size_limits else {
+            return;
+        };
+        for (metric, value, threshold) in limits.crossed(previous.as_ref(), &size) {
+            tracing::warn!(
+                thread_id = %thread_id,
+                metric = ?metric,
+                value,
+                threshold,
+                "Thread state exceeds size threshold"
+            );
+            self.emit_event(agents_core::events::AgentEvent::StateSizeExceeded(
+                agents_core::events::StateSizeExceededEvent {
+                    metadata: self.create_event_metadata(),
+                    thread_id: thread_id.to_string(),
+                    metric,
+                    value,
+                    threshold,
+                },
+            ));
+        }
+    }
+
+    /// Size of a thread's state at its latest checkpoint by this process.
+    pub fn thread_state_size(&self, thread_id: &ThreadId) -> Option<StateSize> {
+        self.state_sizes.get(thread_id)
+    }
+
+    /// State sizes of the threads checkpointed by this process, added up.
+    pub fn state_metrics(&self) -> StateMetrics {
+        self.state_sizes.metrics()
+    }
+
     /// Load agent state from the configured checkpointer.
     pub async fn load_state(&self, thread_id: &ThreadId) -> anyhow::Result<bool> {
         if let Some(ref checkpointer) = self.checkpointer {
             if let Some(saved_state) = checkpointer.load_state(thread_id).await? {
-                *self
-                    .state
-                    .write()
-                    .map_err(|_| anyhow::anyhow!("Failed to write agent state"))? = saved_state;
+                self.install_state(saved_state);
                 tracing::info!(thread_id = %thread_id, "Loaded agent state from checkpointer");
                 Ok(true)
             } else {
@@ -221,6 +681,13 @@ impl DeepAgent {
 
     /// Delete saved state for the specified thread.
     pub async fn delete_thread(&self, thread_id: &ThreadId) -> anyhow::Result<()> {
+        self.state_sizes.remove(thread_id);
+        if let Some(index) = &self.conversation_index {
+            index.delete_thread(thread_id).await?;
+        }
+        if let Some(graph) = &self.graph_memory {
+            graph.store.delete_thread(thread_id).await?;
+        }
         if let Some(ref checkpointer) = self.checkpointer {
             checkpointer.delete_thread(thread_id).await
         } else {
@@ -229,898 +696,4435 @@ impl DeepAgent {
         }
     }
 
-    /// List all threads with saved state.
-    pub async fn list_threads(&self) -> anyhow::Result<Vec<ThreadId>> {
-        if let Some(ref checkpointer) = self.checkpointer {
-            checkpointer.list_threads().await
-        } else {
-            Ok(Vec::new())
+    /// Memories saved for a user, ordered by key. Empty without user memory.
+    pub async fn user_memories(&self, user_id: &UserId) -> anyhow::Result<Vec<MemoryEntry>> {
+        match &self.user_memory {
+            Some(memory) => memory.store.list(user_id).await,
+            None => Ok(Vec::new()),
         }
     }
 
-    async fn execute_tool(
-        &self,
-        tool: ToolBox,
-        _tool_name: String,
-        payload: Value,
-    ) -> anyhow::Result<AgentMessage> {
-        let state_snapshot = self.state.read().unwrap().clone();
-        let ctx = ToolContext::with_mutable_state(Arc::new(state_snapshot), self.state.clone());
-
-        let result = tool.execute(payload, ctx).await?;
-        Ok(self.apply_tool_result(result))
+    /// Erase everything kept about a user: their memories, and the threads served
+    /// for them with those threads' search entries and graph facts.
+    pub async fn erase_user(&self, user_id: &UserId) -> anyhow::Result<UserErasure> {
+        let memories = match &self.user_memory {
+            Some(memory) => {
+                let keys: Vec<String> = memory
+                    .store
+                    .list(user_id)
+                    .await?
+                    .into_iter()
+                    .map(|entry| entry.key)
+                    .collect();
+                let erased = memory.store.erase_user(user_id).await?;
+                if !keys.is_empty() {
+                    self.emit_event(agents_core::events::AgentEvent::MemoryPurged(
+                        agents_core::events::MemoryPurgedEvent {
+                            metadata: self.create_event_metadata(),
+                            user_id: Some(user_id.clone()),
+                            keys,
+                            reason: agents_core::events::MemoryPurgeReason::Erased,
+                        },
+                    ));
+                }
+                erased
+            }
+            None => 0,
+        };
+        let threads = self
+            .list_threads_by_tag(&user_memory::user_tag(user_id))
+            .await?;
+        for thread_id in &threads {
+            self.delete_thread(thread_id).await?;
+        }
+        tracing::info!(
+            user_id = %user_id,
+            memories,
+            threads = threads.len(),
+            "Erased user"
+        );
+        Ok(UserErasure { memories, threads })
     }
 
-    fn apply_tool_result(&self, result: ToolResult) -> AgentMessage {
-        match result {
-            ToolResult::Message(message) => {
-                // Tool results are not added to conversation history
-                // Only the final LLM response after tool execution is added
-                message
+    /// Revoke a user's consent to a category of the [memory
+    /// policy](super::ConfigurableAgentBuilder::with_memory_policy) and delete their
+    /// memories in it, returning the deleted keys. Facts of the category kept in the
+    /// user's threads are removed at the start of each thread's next turn.
+    pub async fn withdraw_consent(
+        &self,
+        user_id: &UserId,
+        category: &str,
+    ) -> anyhow::Result<Vec<String>> {
+        let consents = self
+            .memory_policy
+            .as_ref()
+            .and_then(|policy| policy.consents())
+            .ok_or_else(|| anyhow::anyhow!("No memory policy requiring consent is configured"))?;
+        consents.revoke(user_id, category).await?;
+        let mut keys = Vec::new();
+        if let Some(memory) = &self.user_memory {
+            for entry in memory.store.list(user_id).await? {
+                if entry.category.as_deref() == Some(category)
+                    && memory.store.delete(user_id, &entry.key).await?
+                {
+                    keys.push(entry.key);
+                }
             }
-            ToolResult::WithStateUpdate {
-                message,
-                state_diff,
-            } => {
-                // Check if todos were updated
-                let todos_updated = state_diff.todos.is_some();
+        }
+        if let Some(policy) = &self.memory_policy {
+            policy.purged(
+                Some(user_id),
+                keys.clone(),
+                agents_core::events::MemoryPurgeReason::ConsentWithdrawn,
+            );
+        }
+        Ok(keys)
+    }
 
-                if let Ok(mut state) = self.state.write() {
-                    let command = agents_core::command::Command::with_state(state_diff);
-                    command.apply_to(&mut state);
+    #[cfg(any(feature = "debug-ui", feature = "email"))]
+    pub(crate) fn checkpointer(&self) -> Option<&Arc<dyn Checkpointer>> {
+        self.checkpointer.as_ref()
+    }
 
-                    // Emit TodosUpdated event if todos were modified
-                    if todos_updated {
-                        let (pending_count, in_progress_count, completed_count) =
-                            count_todos(&state.todos);
+    #[cfg(any(feature = "debug-ui", feature = "telegram"))]
+    pub(crate) fn approval_config(&self) -> Option<&ApprovalConfig> {
+        self.approvals.as_ref()
+    }
 
-                        self.emit_event(agents_core::events::AgentEvent::TodosUpdated(
-                            agents_core::events::TodosUpdatedEvent {
-                                metadata: self.create_event_metadata(),
-                                todos: state.todos.clone(),
-                                pending_count,
-                                in_progress_count,
-                                completed_count,
-                                last_updated: chrono::Utc::now().to_rfc3339(),
-                            },
-                        ));
+    #[cfg(feature = "debug-ui")]
+    pub(crate) fn journal(&self) -> &RunJournal {
+        &self.journal
+    }
 
-                        tracing::debug!(
-                            pending = pending_count,
-                            in_progress = in_progress_count,
-                            completed = completed_count,
-                            total = state.todos.len(),
-                            "📝 Todos updated and event emitted"
-                        );
-                    }
-                }
-                // Tool results are not added to conversation history
-                // Only the final LLM response after tool execution is added
-                message
-            }
+    /// List all threads with saved state.
+    pub async fn list_threads(&self) -> anyhow::Result<Vec<ThreadId>> {
+        if let Some(ref checkpointer) = self.checkpointer {
+            checkpointer.list_threads().await
+        } else {
+            Ok(Vec::new())
         }
     }
 
-    /// Get the current pending interrupt, if any.
-    pub fn current_interrupt(&self) -> Option<AgentInterrupt> {
-        self.state
-            .read()
-            .ok()
-            .and_then(|guard| guard.pending_interrupts.first().cloned())
+    /// Messages of past thread turns matching `query`, best matches first. Requires
+    /// a conversation index, see `with_conversation_index`.
+    pub async fn search_threads(
+        &self,
+        query: &str,
+        limit: usize,
+    ) -> anyhow::Result<Vec<SearchHit>> {
+        let Some(index) = &self.conversation_index else {
+            anyhow::bail!("Cannot search threads: no conversation index is configured");
+        };
+        index.search(query, limit).await
     }
 
-    /// Add a broadcaster dynamically to the agent's event dispatcher.
+    /// What is left of each of `tenant`'s quotas in the current day and month.
+    /// Requires quotas, see `with_quotas`.
     ///
-    /// Add a single broadcaster dynamically after the agent is built.
-    ///
-    /// This is useful for per-conversation or per-customer broadcasters.
+    /// ```ignore
+    /// for quota in agent.remaining_quota(&customer.id).await? {
+    ///     println!("{:?}: {:?} requests left until {}", quota.period, quota.requests, quota.resets_at);
+    /// }
+    /// ```
+    pub async fn remaining_quota(&self, tenant: &str) -> anyhow::Result<Vec<QuotaRemaining>> {
+        let Some(quotas) = &self.quotas else {
+            anyhow::bail!("Cannot report quotas: no quotas are configured");
+        };
+        quotas.remaining(tenant).await
+    }
+
+    /// Create a thread from `template`, so its first run already has the template's
+    /// conversation, files and todos as context. No model calls are made. Fails
+    /// when the thread already has state; delete it first to seed it again.
     ///
-    /// # Example
-    /// ```no_run
-    /// use std::sync::Arc;
-    /// // agent.add_broadcaster(Arc::new(MyBroadcaster::new()));
+    /// ```ignore
+    /// let onboarding = ThreadTemplate::new()
+    ///     .with_user_message("I just signed up. Where do I start?")
+    ///     .with_agent_message("Welcome! Let's connect your first data source.")
+    ///     .with_tag("onboarding");
+    /// agent.seed_thread(&thread_id, &onboarding).await?;
+    /// agent.handle_message_for_thread(&thread_id, "It's a Postgres database").await?;
     /// ```
-    pub fn add_broadcaster(&self, broadcaster: Arc<dyn agents_core::events::EventBroadcaster>) {
-        if let Some(dispatcher) = &self.event_dispatcher {
-            dispatcher.add_broadcaster(broadcaster);
-            tracing::debug!("Broadcaster added to event dispatcher");
-        } else {
-            tracing::warn!("add_broadcaster called but no event dispatcher configured");
+    pub async fn seed_thread(
+        &self,
+        thread_id: &ThreadId,
+        template: &ThreadTemplate,
+    ) -> anyhow::Result<()> {
+        let Some(ref checkpointer) = self.checkpointer else {
+            anyhow::bail!("Cannot seed thread: no checkpointer is configured");
+        };
+        if checkpointer.load_state(thread_id).await?.is_some() {
+            anyhow::bail!("Cannot seed thread {}: it already has state", thread_id);
+        }
+        let state = template.to_state();
+        checkpointer.save_state(thread_id, &state).await?;
+        if template.metadata != ThreadMetadata::default() {
+            checkpointer
+                .save_metadata(thread_id, &template.metadata)
+                .await?;
         }
+        let messages: Vec<&AgentMessage> = state.preloaded_messages.iter().collect();
+        self.index_turn(thread_id, &messages).await;
+        tracing::debug!(
+            thread_id = %thread_id,
+            messages = messages.len(),
+            "Seeded thread from template"
+        );
+        Ok(())
     }
 
-    /// Add multiple broadcasters at once.
-    ///
-    /// This is useful when you need to add several broadcasters for a conversation
-    /// (e.g., WhatsApp, SSE, DynamoDB).
+    /// Export a thread for a bug report: its checkpointed state and metadata, and
+    /// the traces and step recordings of its runs still in the journal, with PII
+    /// and credentials redacted. See [`ThreadExport`].
     ///
-    /// # Example
-    /// ```no_run
-    /// use std::sync::Arc;
-    /// // agent.add_broadcasters(vec![
-    /// //     Arc::new(WhatsAppBroadcaster::new(phone)),
-    /// //     Arc::new(SseBroadcaster::new(channel)),
-    /// //     Arc::new(DynamoDbBroadcaster::new(table)),
-    /// // ]);
+    /// ```ignore
+    /// let export = agent.export_thread_redacted(&thread_id).await?;
+    /// std::fs::write("thread.json", serde_json::to_string_pretty(&export)?)?;
     /// ```
-    pub fn add_broadcasters(
+    pub async fn export_thread_redacted(&self, thread_id: &ThreadId) -> anyhow::Result<Value> {
+        let checkpointer = self
+            .checkpointer
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Exporting a thread requires a checkpointer"))?;
+        let state = checkpointer
+            .load_state(thread_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Thread {} has no saved state", thread_id))?;
+        let run_ids = self.journal.thread_run_ids(thread_id);
+        let export = ThreadExport {
+            thread_id: thread_id.clone(),
+            state,
+            metadata: checkpointer.load_metadata(thread_id).await?,
+            traces: run_ids
+                .iter()
+                .filter_map(|run_id| self.journal.trace(run_id))
+                .collect(),
+            recordings: run_ids
+                .iter()
+                .filter_map(|run_id| self.journal.recording(run_id))
+                .filter(|recording| !recording.steps.is_empty())
+                .collect(),
+        };
+        export.redacted()
+    }
+
+    /// Metadata attached to a thread, if any.
+    pub async fn thread_metadata(
         &self,
-        broadcasters: Vec<Arc<dyn agents_core::events::EventBroadcaster>>,
-    ) {
-        if let Some(dispatcher) = &self.event_dispatcher {
-            for broadcaster in broadcasters {
-                dispatcher.add_broadcaster(broadcaster);
-            }
-            tracing::debug!("Multiple broadcasters added to event dispatcher");
+        thread_id: &ThreadId,
+    ) -> anyhow::Result<Option<ThreadMetadata>> {
+        if let Some(ref checkpointer) = self.checkpointer {
+            checkpointer.load_metadata(thread_id).await
         } else {
-            tracing::warn!("add_broadcasters called but no event dispatcher configured");
+            Ok(None)
         }
     }
 
-    /// Resume execution after human approval of an interrupt.
-    pub async fn resume_with_approval(&self, action: HitlAction) -> anyhow::Result<AgentMessage> {
-        // Get the first pending interrupt
-        let interrupt = {
-            let state_guard = self
-                .state
-                .read()
-                .map_err(|_| anyhow::anyhow!("Failed to acquire read lock on state"))?;
-            state_guard
-                .pending_interrupts
-                .first()
-                .cloned()
-                .ok_or_else(|| anyhow::anyhow!("No pending interrupts"))?
+    /// Replace the metadata of a thread. Works before the thread's first run.
+    pub async fn set_thread_metadata(
+        &self,
+        thread_id: &ThreadId,
+        metadata: &ThreadMetadata,
+    ) -> anyhow::Result<()> {
+        let Some(ref checkpointer) = self.checkpointer else {
+            anyhow::bail!("Cannot save thread metadata: no checkpointer is configured");
         };
+        checkpointer.save_metadata(thread_id, metadata).await
+    }
 
-        let result_message = match action {
-            HitlAction::Accept => {
-                // Execute with original args
-                let AgentInterrupt::HumanInLoop(hitl) = interrupt;
-                tracing::info!(
-                    tool_name = %hitl.tool_name,
-                    call_id = %hitl.call_id,
-                    "✅ HITL: Tool approved, executing with original arguments"
-                );
+    /// Add `tag` to the metadata of a thread, keeping its other metadata.
+    pub async fn tag_thread(&self, thread_id: &ThreadId, tag: &str) -> anyhow::Result<()> {
+        let metadata = self
+            .thread_metadata(thread_id)
+            .await?
+            .unwrap_or_default()
+            .with_tag(tag);
+        self.set_thread_metadata(thread_id, &metadata).await
+    }
 
-                let tools = self.collect_tools();
-                let tool = tools
-                    .get(&hitl.tool_name)
-                    .cloned()
-                    .ok_or_else(|| anyhow::anyhow!("Tool '{}' not found", hitl.tool_name))?;
+    /// List the threads tagged with `tag`.
+    pub async fn list_threads_by_tag(&self, tag: &str) -> anyhow::Result<Vec<ThreadId>> {
+        if let Some(ref checkpointer) = self.checkpointer {
+            checkpointer.list_threads_by_tag(tag).await
+        } else {
+            Ok(Vec::new())
+        }
+    }
 
-                self.execute_tool(tool, hitl.tool_name, hitl.tool_args)
-                    .await?
-            }
+    async fn execute_tool(
+        &self,
+        tool: ToolBox,
+        tool_name: String,
+        payload: Value,
+        priority: RunPriority,
+    ) -> anyhow::Result<AgentMessage> {
+        self.report_deprecated_call(&tool);
 
-            HitlAction::Edit {
-                tool_name,
-                tool_args,
-            } => {
-                // Execute with modified args
-                tracing::info!(
+        let _slot = self.concurrency.acquire_tool(priority).await;
+        if let Some(tracker) = &self.usage_tracker {
+            tracker.record_tool_call(&self.descriptor.name, &tool_name);
+        }
+        let state_snapshot = self.run_state().read().unwrap().clone();
+        let trace = TraceContext::current()
+            .map(|run| run.child())
+            .unwrap_or_else(TraceContext::new_root);
+        let span = tracing::info_span!(
+            "tool_call",
+            tool = %tool_name,
+            trace_id = %trace.trace_id,
+            span_id = %trace.span_id,
+        );
+        let payload = match &self.offload {
+            Some(offload) => offload.rehydrate(&tool_name, payload).await?,
+            None => payload,
+        };
+        let ctx =
+            ToolContext::with_mutable_state(Arc::new(state_snapshot), self.run_state().clone())
+                .with_extensions(self.tool_extensions.clone())
+                .with_trace(trace.clone());
+
+        let result = profiling::time_async(
+            ProfilePhase::Tool,
+            trace.scope(tool.execute(payload, ctx).instrument(span)),
+        )
+        .await?;
+        let message = self.apply_tool_result(result);
+        self.journal
+            .record_sources(&tool_output::message_text(&message));
+        let message = self.encode_structured_output(message);
+        let message = match &self.offload {
+            Some(offload) => offload.offload(&tool_name, message).await,
+            None => message,
+        };
+        Ok(self.enforce_output_budget(&tool_name, message).await)
+    }
+
+    /// Execute a tool call, retrying failures per the tool's retry policy.
+    /// Returns the last result and the number of attempts made.
+    async fn execute_tool_with_retries(
+        &self,
+        tool: &ToolBox,
+        tool_name: &str,
+        payload: &Value,
+        priority: RunPriority,
+    ) -> (anyhow::Result<AgentMessage>, u32) {
+        let policy = self
+            .tool_retry
+            .as_ref()
+            .and_then(|retries| retries.policy_for(tool_name))
+            .copied();
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let result = self
+                .execute_tool(
+                    tool.clone(),
+                    tool_name.to_string(),
+                    payload.clone(),
+                    priority,
+                )
+                .await;
+            let Err(e) = &result else {
+                return (result, attempts);
+            };
+            let Some(policy) = policy.filter(|p| attempts < p.max_attempts) else {
+                return (result, attempts);
+            };
+            let delay = policy.delay(attempts);
+            tracing::warn!(
+                tool_name = %tool_name,
+                attempt = attempts,
+                max_attempts = policy.max_attempts,
+                "🔁 Tool failed, retrying in {:?}: {}",
+                delay,
+                e
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Record a tool call whose arguments failed strict validation and tell the model why.
+    /// Send an answer citing none of the run's tool results back to the planner.
+    fn require_citation(
+        &self,
+        citations: &CitationConfig,
+        answer: AgentMessage,
+        provenance: &Provenance,
+    ) {
+        tracing::warn!("🛡️ Final answer cites no tool result; asking the planner again");
+        self.emit_event(agents_core::events::AgentEvent::GuardrailIntervened(
+            agents_core::events::GuardrailIntervenedEvent {
+                metadata: self.create_event_metadata(),
+                guardrail: "citations".to_string(),
+                source: GuardrailSource::Output,
+                blocked: true,
+                reasons: vec!["answer references no tool result".to_string()],
+            },
+        ));
+        self.append_history(answer);
+        self.append_history(citations.correction(provenance));
+    }
+
+    fn reject_tool_arguments(&self, tool_name: &str, errors: &[ArgumentError]) {
+        let summary = errors
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; ");
+        tracing::warn!(tool_name = %tool_name, "⚠️ Invalid tool arguments: {}", summary);
+
+        self.emit_event(agents_core::events::AgentEvent::ToolFailed(
+            agents_core::events::ToolFailedEvent {
+                metadata: self.create_event_metadata(),
+                tool_name: tool_name.to_string(),
+                duration_ms: 0,
+                error_message: format!("Invalid arguments: {}", summary),
+                is_recoverable: true,
+                retry_count: 0,
+            },
+        ));
+        self.append_history(tool_arguments::rejection_message(tool_name, errors));
+    }
+
+    /// Decision of the policy engine on a tool call; calls are denied when the
+    /// evaluation fails.
+    async fn check_tool_policy(
+        &self,
+        policy: &Arc<dyn PolicyProvider>,
+        tool_name: &str,
+        payload: &serde_json::Value,
+        thread_id: Option<&ThreadId>,
+        options: &RunOptions,
+    ) -> PolicyDecision {
+        let locale = self
+            .run_state()
+            .read()
+            .ok()
+            .and_then(|state| state.locale.clone());
+        let request = PolicyRequest {
+            agent: self.descriptor.name.clone(),
+            tool_name: tool_name.to_string(),
+            tool_args: payload.clone(),
+            claims: options
+                .claims
+                .clone()
+                .unwrap_or_else(|| serde_json::json!({})),
+            thread: PolicyThread {
+                id: thread_id.map(ToString::to_string),
+                correlation_id: current_correlation_id(),
+                locale,
+            },
+        };
+        match policy.evaluate(&request).await {
+            Ok(decision) => {
+                tracing::debug!(
+                    policy = policy.id(),
                     tool_name = %tool_name,
-                    "✏️ HITL: Tool edited, executing with modified arguments"
+                    decision = ?decision,
+                    "Policy decision"
                 );
+                decision
+            }
+            Err(e) => {
+                tracing::error!(
+                    policy = policy.id(),
+                    tool_name = %tool_name,
+                    "Policy evaluation failed, denying tool call: {:#}",
+                    e
+                );
+                PolicyDecision::Deny {
+                    reason: Some("The tool call could not be authorized.".to_string()),
+                }
+            }
+        }
+    }
 
-                let tools = self.collect_tools();
-                let tool = tools
-                    .get(&tool_name)
-                    .cloned()
-                    .ok_or_else(|| anyhow::anyhow!("Tool '{}' not found", tool_name))?;
+    /// Record a tool call denied by the policy engine and tell the model why.
+    fn deny_tool_call(&self, tool_name: &str, reason: Option<String>) {
+        let reason = reason.unwrap_or_else(|| "Denied by policy".to_string());
+        tracing::warn!(tool_name = %tool_name, "🚫 Tool call denied by policy: {}", reason);
+
+        self.emit_event(agents_core::events::AgentEvent::ToolFailed(
+            agents_core::events::ToolFailedEvent {
+                metadata: self.create_event_metadata(),
+                tool_name: tool_name.to_string(),
+                duration_ms: 0,
+                error_message: format!("Denied by policy: {}", reason),
+                is_recoverable: false,
+                retry_count: 0,
+            },
+        ));
+        self.append_history(AgentMessage {
+            role: MessageRole::Tool,
+            content: MessageContent::Json(serde_json::json!({
+                "error": "denied_by_policy",
+                "tool": tool_name,
+                "reason": reason,
+                "hint": "The tool was not executed. Do not retry it with the same arguments.",
+            })),
+            metadata: None,
+        });
+    }
+
+    /// In TOON mode, re-encode JSON tool results (including sub-agent responses) as
+    /// TOON before they enter the conversation history.
+    fn encode_structured_output(&self, message: AgentMessage) -> AgentMessage {
+        if self.prompt_format != PromptFormat::Toon {
+            return message;
+        }
+        match agents_core::toon::content_to_toon(&message.content) {
+            Some(toon) => AgentMessage {
+                content: MessageContent::Text(toon),
+                ..message
+            },
+            None => message,
+        }
+    }
+
+    /// Warn and emit a `DeprecatedToolCalled` event when the model calls a deprecated tool.
+    fn report_deprecated_call(&self, tool: &ToolBox) {
+        let schema = tool.schema();
+        let Some(notice) = schema.deprecation_notice() else {
+            return;
+        };
+
+        tracing::warn!(
+            tool_name = %schema.name,
+            version = ?schema.version,
+            "⚠️ Deprecated tool called: {}",
+            notice
+        );
+
+        self.emit_event(agents_core::events::AgentEvent::DeprecatedToolCalled(
+            agents_core::events::DeprecatedToolCalledEvent {
+                metadata: self.create_event_metadata(),
+                tool_name: schema.name.clone(),
+                tool_version: schema.version.clone(),
+                replacement: schema.deprecation.and_then(|d| d.replacement),
+                notice,
+            },
+        ));
+    }
+
+    /// Shorten an oversized tool result before it enters the conversation history,
+    /// storing the full output in the virtual filesystem when artifacts are enabled.
+    async fn enforce_output_budget(&self, tool_name: &str, message: AgentMessage) -> AgentMessage {
+        let Some(config) = &self.tool_output else {
+            return message;
+        };
+        let Some(max_chars) = config.limit_for(tool_name) else {
+            return message;
+        };
+
+        let text = tool_output::message_text(&message);
+        let total_chars = text.chars().count();
+        if total_chars <= max_chars {
+            return message;
+        }
 
-                self.execute_tool(tool, tool_name, tool_args).await?
+        let shortened = config.shorten(&text, max_chars).await;
+        let note = if config.store_artifacts {
+            let call_id = uuid::Uuid::new_v4().simple().to_string();
+            let path = tool_output::artifact_path(tool_name, &call_id[..8]);
+            if let Ok(mut state) = self.run_state().write() {
+                state.insert_file(path.clone(), text);
             }
+            format!(
+                "[Output of {} shortened from {} characters. Full output saved to '{}'; use read_file to inspect it.]",
+                tool_name, total_chars, path
+            )
+        } else {
+            format!(
+                "[Output of {} shortened from {} characters.]",
+                tool_name, total_chars
+            )
+        };
 
-            HitlAction::Reject { reason } => {
-                // Don't execute - return rejection message
-                tracing::info!("❌ HITL: Tool rejected");
+        tracing::info!(
+            tool_name = %tool_name,
+            original_chars = total_chars,
+            max_chars,
+            "✂️ Tool output exceeded budget and was shortened"
+        );
 
-                let text = reason
-                    .unwrap_or_else(|| "Tool execution rejected by human reviewer.".to_string());
+        AgentMessage {
+            role: message.role,
+            content: MessageContent::Text(format!("{}\n\n{}", shortened, note)),
+            metadata: message.metadata,
+        }
+    }
 
-                let message = AgentMessage {
-                    role: MessageRole::Tool,
-                    content: MessageContent::Text(text),
-                    metadata: None,
-                };
+    /// Stream a long final response to the configured output sink, keeping only a
+    /// confirmation in the conversation history.
+    async fn divert_final_output(&self, message: AgentMessage) -> AgentMessage {
+        let Some(config) = &self.output_sink else {
+            return message;
+        };
 
-                self.append_history(message.clone());
+        let run_id = self
+            .journal
+            .current_run_id()
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        let name = format!("{}/{}.md", self.descriptor.name, run_id);
+        let text = self.get_full_message_text(&message);
+        match config.divert(&name, &text).await {
+            Ok(Some(confirmation)) => {
+                tracing::info!(output = %name, chars = text.chars().count(), "📄 Final response written to output sink");
+                AgentMessage {
+                    role: message.role,
+                    content: MessageContent::Text(confirmation),
+                    metadata: message.metadata,
+                }
+            }
+            Ok(None) => message,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to write final response to output sink; keeping it in the chat");
                 message
             }
+        }
+    }
 
-            HitlAction::Respond { message } => {
-                // Don't execute - return custom message
-                tracing::info!("💬 HITL: Custom response provided");
-
-                self.append_history(message.clone());
+    fn apply_tool_result(&self, result: ToolResult) -> AgentMessage {
+        match result {
+            ToolResult::Message(message) => {
+                // Tool results are not added to conversation history
+                // Only the final LLM response after tool execution is added
                 message
             }
-        };
+            ToolResult::WithStateUpdate {
+                message,
+                state_diff,
+            } => {
+                // Check if todos were updated
+                let todos_updated = state_diff.todos.is_some();
+                if let Some(files) = &state_diff.files {
+                    self.journal.record_files(files.keys().cloned());
+                }
 
-        // Clear the interrupt from state
-        {
-            let mut state_guard = self
-                .state
-                .write()
-                .map_err(|_| anyhow::anyhow!("Failed to acquire write lock on state"))?;
-            state_guard.clear_interrupts();
+                if let Ok(mut state) = self.run_state().write() {
+                    let command = agents_core::command::Command::with_state(state_diff);
+                    command.apply_to(&mut state);
+
+                    // Emit TodosUpdated event if todos were modified
+                    if todos_updated {
+                        let (pending_count, in_progress_count, completed_count) =
+                            count_todos(&state.todos);
+
+                        self.emit_event(agents_core::events::AgentEvent::TodosUpdated(
+                            agents_core::events::TodosUpdatedEvent {
+                                metadata: self.create_event_metadata(),
+                                todos: state.todos.clone(),
+                                pending_count,
+                                in_progress_count,
+                                completed_count,
+                                last_updated: chrono::Utc::now().to_rfc3339(),
+                            },
+                        ));
+
+                        tracing::debug!(
+                            pending = pending_count,
+                            in_progress = in_progress_count,
+                            completed = completed_count,
+                            total = state.todos.len(),
+                            "📝 Todos updated and event emitted"
+                        );
+                    }
+                }
+                // Tool results are not added to conversation history
+                // Only the final LLM response after tool execution is added
+                message
+            }
         }
+    }
 
-        // Persist cleared state
-        if let Some(checkpointer) = &self.checkpointer {
-            let state_clone = self
-                .state
-                .read()
-                .map_err(|_| anyhow::anyhow!("Failed to acquire read lock on state"))?
-                .clone();
-            checkpointer
-                .save_state(&ThreadId::default(), &state_clone)
-                .await?;
+    /// The system prompt and tool schemas the agent sends on the first turn of a new
+    /// thread, for prompt regression tests.
+    ///
+    /// The history is left out and the time context reports
+    /// [`PromptSnapshot::rendered_at`], so the snapshot only changes when the
+    /// configuration or the SDK's built-in prompts do.
+    pub async fn prompt_snapshot(&self) -> anyhow::Result<PromptSnapshot> {
+        self.refresh_provider_tools().await;
+        let mut request = ModelRequest::new(&self.instructions, Vec::new());
+        time_context::with_fixed_time(PromptSnapshot::rendered_at(), async {
+            for middleware in &self.middlewares {
+                let mut ctx =
+                    MiddlewareContext::with_request(&mut request, self.run_state().clone());
+                middleware.modify_model_request(&mut ctx).await?;
+            }
+            anyhow::Ok(())
+        })
+        .await?;
+
+        let tools = self.collect_tools();
+        let mut tools = self.tool_schemas_for_turn(&tools, &request.messages).await;
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+        if let Some(examples) = self.tool_examples.render(&tools) {
+            request.append_prompt(&examples);
         }
+        Ok(PromptSnapshot {
+            system_prompt: request.system_prompt,
+            tools,
+        })
+    }
 
-        Ok(result_message)
+    /// Experiment variant `thread_id` is assigned to, for recording outcomes such as
+    /// customer ratings next to the variant.
+    pub fn experiment_assignment(&self, thread_id: &str) -> Option<ExperimentAssignment> {
+        self.experiment.as_ref()?.assignment(thread_id)
     }
 
-    /// Handle message from string input - converts string to AgentMessage internally
-    pub async fn handle_message(
-        &self,
-        input: impl AsRef<str>,
-        state: Arc<AgentStateSnapshot>,
-    ) -> anyhow::Result<AgentMessage> {
-        self.handle_message_with_metadata(input, None, state).await
+    /// Id of the most recent `handle_message` run, for use with [`generate_report`](Self::generate_report).
+    pub fn last_run_id(&self) -> Option<String> {
+        self.journal.current_run_id()
     }
 
-    /// Handle message from string input with metadata - converts string to AgentMessage internally
-    pub async fn handle_message_with_metadata(
+    /// Summarize a recent run: tools called, sources, files written and cost.
+    ///
+    /// The agent keeps the last [`DEFAULT_RUN_HISTORY`](super::report::DEFAULT_RUN_HISTORY)
+    /// runs; older ids return `None`. Token usage and sub-agent activity are included
+    /// when token tracking and an event dispatcher are configured.
+    pub fn generate_report(&self, run_id: &str) -> Option<RunReport> {
+        self.journal.report(run_id)
+    }
+
+    /// Planner iterations, tool calls and sub-agent delegations of a recent run,
+    /// renderable with [`RunTrace::to_mermaid`] or [`RunTrace::to_dot`].
+    pub fn run_trace(&self, run_id: &str) -> Option<RunTrace> {
+        self.journal.trace(run_id)
+    }
+
+    /// Prompt, state and decision of each planner iteration of a recent run, for
+    /// stepping through with a [`RunDebugger`](super::RunDebugger). `None` when the
+    /// run is unknown or was evicted; without
+    /// [`with_step_recording`](super::ConfigurableAgentBuilder::with_step_recording)
+    /// the recording has no steps.
+    pub fn run_recording(&self, run_id: &str) -> Option<RunRecording> {
+        self.journal.recording(run_id)
+    }
+
+    /// Continue the run recorded in `step` as if its tool call had returned
+    /// `tool_result`, with `state` as the state after the call.
+    pub(crate) async fn continue_from_step(
         &self,
-        input: impl AsRef<str>,
-        metadata: Option<MessageMetadata>,
-        state: Arc<AgentStateSnapshot>,
+        step: &RecordedStep,
+        state: AgentStateSnapshot,
+        tool_result: AgentMessage,
     ) -> anyhow::Result<AgentMessage> {
-        let agent_message = AgentMessage {
-            role: MessageRole::User,
-            content: MessageContent::Text(input.as_ref().to_string()),
-            metadata,
-        };
-        self.handle_message_internal(agent_message, state).await
+        let run = self.continue_recorded_step(step, state, tool_result);
+        Box::pin(self.in_run_scope(None, run)).await
     }
 
-    /// Internal method that contains the actual message handling logic
-    async fn handle_message_internal(
+    async fn continue_recorded_step(
         &self,
-        input: AgentMessage,
-        loaded_state: Arc<AgentStateSnapshot>,
+        step: &RecordedStep,
+        mut state: AgentStateSnapshot,
+        tool_result: AgentMessage,
     ) -> anyhow::Result<AgentMessage> {
-        let start_time = std::time::Instant::now();
+        let PlannerAction::CallTool { tool_name, payload } = &step.action else {
+            anyhow::bail!("Iteration {} did not call a tool", step.iteration);
+        };
+        let _run = self.run_gate.enter()?;
+        let _slot = self.concurrency.acquire_run(RunPriority::default()).await?;
+        self.journal.start_run();
 
-        // Initialize internal state with loaded state from checkpointer
-        // This ensures conversation context is maintained across sessions
-        if let Ok(mut state_guard) = self.state.write() {
-            *state_guard = (*loaded_state).clone();
+        // The recorded messages already include the thread's conversation
+        state.preloaded_messages.clear();
+        state.messages.clear();
+        if let Ok(mut state_guard) = self.run_state().write() {
+            *state_guard = state;
+        }
+        let mut history = step.context.history.clone();
+        history.push(tool_call_message(tool_name, payload));
+        history.push(tool_result);
+        let request = history
+            .iter()
+            .rev()
+            .find(|message| message.role == MessageRole::User)
+            .cloned();
+        if let Ok(mut history_guard) = self.run_history().write() {
+            *history_guard = history;
         }
 
-        self.emit_event(agents_core::events::AgentEvent::AgentStarted(
-            agents_core::events::AgentStartedEvent {
-                metadata: self.create_event_metadata(),
-                agent_name: self.descriptor.name.clone(),
-                message_preview: self.truncate_message(&input),
-            },
-        ));
-
-        self.append_history(input.clone());
+        if let Some(request) = request {
+            self.emit_event(agents_core::events::AgentEvent::AgentStarted(
+                agents_core::events::AgentStartedEvent {
+                    metadata: self.create_event_metadata(),
+                    agent_name: self.descriptor.name.clone(),
+                    message_preview: self.truncate_message(&request),
+                },
+            ));
+        }
+        self.refresh_provider_tools().await;
+        self.react_loop(
+            std::time::Instant::now(),
+            None,
+            RunPriority::default(),
+            &RunOptions::default(),
+            step.iteration,
+        )
+        .await
+    }
 
-        // ReAct loop: continue until LLM responds with text (not tool calls)
-        let max_iterations = self.max_iterations.get();
-        let mut iteration = 0;
+    /// Current number of active and queued runs and tool executions.
+    /// Token usage and tool executions broken down by agent, sub-agent and
+    /// summarization, when token tracking is enabled through the builder.
+    ///
+    /// ```ignore
+    /// if let Some(summary) = agent.usage_summary() {
+    ///     for (component, usage) in summary.components_by_cost() {
+    ///         println!("{component}: ${:.4}, {} tool calls", usage.cost, usage.total_tool_calls());
+    ///     }
+    /// }
+    /// ```
+    pub fn usage_summary(&self) -> Option<TokenUsageSummary> {
+        self.usage_tracker
+            .as_ref()
+            .map(|tracker| tracker.get_total_usage())
+    }
 
-        loop {
-            iteration += 1;
-            if iteration > max_iterations {
-                tracing::warn!(
-                    "⚠️ Max iterations ({}) reached, stopping ReAct loop",
-                    max_iterations
+    /// Token usage per experiment variant, when token tracking is enabled through the
+    /// builder. Runs outside the experiment are left out.
+    pub fn usage_by_variant(&self) -> Option<BTreeMap<ExperimentAssignment, TokenUsageSummary>> {
+        self.usage_tracker
+            .as_ref()
+            .map(|tracker| tracker.get_usage_by_variant())
+    }
+
+    /// The registered sub-agent called `name`, including `general-purpose` when enabled.
+    pub fn subagent(&self, name: &str) -> Option<Arc<dyn AgentHandle>> {
+        self.subagents
+            .iter()
+            .find(|registration| registration.descriptor.name == name)
+            .map(|registration| registration.agent.clone())
+    }
+
+    /// Serve the sub-agent called `name` to MCP hosts.
+    ///
+    /// The MCP tool takes the sub-agent's name and the description from its
+    /// `SubAgentConfig`.
+    ///
+    /// ```ignore
+    /// agent
+    ///     .subagent_mcp_server("research-agent")
+    ///     .context("research-agent is not configured")?
+    ///     .serve_stdio()
+    ///     .await?;
+    /// ```
+    #[cfg(feature = "mcp")]
+    pub fn subagent_mcp_server(&self, name: &str) -> Option<agents_mcp::McpAgentServer> {
+        self.subagents
+            .iter()
+            .find(|registration| registration.descriptor.name == name)
+            .map(|registration| {
+                agents_mcp::McpAgentServer::new(
+                    registration.descriptor.name.clone(),
+                    registration.descriptor.description.clone(),
+                    registration.agent.clone(),
+                )
+                .with_version(self.descriptor.version.clone())
+            })
+    }
+
+    pub fn concurrency_stats(&self) -> ConcurrencyStats {
+        self.concurrency.stats()
+    }
+
+    /// Environment profile the agent was built for.
+    pub fn profile(&self) -> Profile {
+        self.profile
+    }
+
+    /// Check the agent's tools, models and tool providers before it takes traffic,
+    /// with every check of [`SelfTestOptions::default`].
+    pub async fn self_test(&self) -> SelfTestReport {
+        self.self_test_with_options(SelfTestOptions::default())
+            .await
+    }
+
+    /// [`self_test`](Self::self_test) with a choice of checks.
+    pub async fn self_test_with_options(&self, options: SelfTestOptions) -> SelfTestReport {
+        let mut report = SelfTestReport::new(&self.descriptor.name);
+
+        if options.check_tool_providers {
+            for provider in &self.tool_providers {
+                let (checks, elapsed) =
+                    self_test::timed(options.timeout, async { Ok(provider.check().await) }).await;
+                let checks = checks.unwrap_or_else(|e| vec![("tool provider".to_string(), Err(e))]);
+                for (name, tool_count) in checks {
+                    let kind = SelfTestCheckKind::ToolProvider;
+                    let check = match tool_count {
+                        Ok(count) => {
+                            SelfTestCheck::passed(kind, name).with_detail(format!("tools: {count}"))
+                        }
+                        Err(e) => SelfTestCheck::failed(kind, name, e.to_string()),
+                    };
+                    report.checks.push(check.with_duration(elapsed));
+                }
+            }
+            self.refresh_provider_tools().await;
+        }
+
+        let mut tools: Vec<_> = self.collect_tools().into_iter().collect();
+        tools.sort_by(|a, b| a.0.cmp(&b.0));
+        for (name, tool) in tools {
+            let schema = tool.schema();
+            let problems = self_test::schema_problems(&schema);
+            report.checks.push(if problems.is_empty() {
+                SelfTestCheck::passed(SelfTestCheckKind::ToolSchema, &name)
+            } else {
+                SelfTestCheck::failed(SelfTestCheckKind::ToolSchema, &name, problems.join("; "))
+            });
+
+            let Some(args) = schema.self_test_args.filter(|_| options.call_tools) else {
+                continue;
+            };
+            // Tools write to a copy of the state, leaving the agent's untouched
+            let state = self.run_state().read().unwrap().clone();
+            let ctx = ToolContext::with_mutable_state(
+                Arc::new(state.clone()),
+                Arc::new(RwLock::new(state)),
+            )
+            .with_extensions(self.tool_extensions.clone());
+            let (result, elapsed) =
+                self_test::timed(options.timeout, tool.execute(args, ctx)).await;
+            let check = match result {
+                Ok(_) => SelfTestCheck::passed(SelfTestCheckKind::ToolCall, &name),
+                Err(e) => SelfTestCheck::failed(SelfTestCheckKind::ToolCall, &name, e.to_string()),
+            };
+            report.checks.push(check.with_duration(elapsed));
+        }
+
+        if options.ping_models {
+            let mut models = Vec::new();
+            if let Some(planner) = self.planner.as_any().downcast_ref::<LlmBackedPlanner>() {
+                models.push((self.descriptor.name.clone(), planner.model().clone()));
+            }
+            models.extend(self.subagent_models.iter().cloned());
+            for (agent, model) in models {
+                let (result, elapsed) =
+                    self_test::timed(options.timeout, self_test::ping(model.as_ref())).await;
+                let check = match result {
+                    Ok(()) => SelfTestCheck::passed(SelfTestCheckKind::Model, agent),
+                    Err(e) => SelfTestCheck::failed(SelfTestCheckKind::Model, agent, e.to_string()),
+                };
+                report.checks.push(check.with_duration(elapsed));
+            }
+        }
+
+        report
+    }
+
+    /// Whether the agent still accepts new messages (false once `shutdown` was called).
+    pub fn is_accepting(&self) -> bool {
+        !self.run_gate.is_closed()
+    }
+
+    /// Stop accepting messages, drain in-flight runs and flush state.
+    ///
+    /// New messages fail with [`AgentShutdownError::NotAccepting`] right away. Runs
+    /// already in flight get `grace` to finish; after that they are asked to stop at
+    /// their next step, checkpoint their thread and fail with
+    /// [`AgentShutdownError::Interrupted`]. Finally pending event broadcasts
+    /// (including token usage records) are flushed. Stopping runs and flushing events
+    /// may each take up to 5 seconds beyond `grace`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// tokio::signal::ctrl_c().await?;
+    /// let report = agent.shutdown(Duration::from_secs(30)).await?;
+    /// tracing::info!(?report, "agent stopped");
+    /// ```
+    pub async fn shutdown(&self, grace: Duration) -> anyhow::Result<ShutdownReport> {
+        let started = std::time::Instant::now();
+        self.run_gate.close();
+
+        let drained = tokio::time::timeout(grace, self.run_gate.wait_idle())
+            .await
+            .is_ok();
+        if !drained {
+            tracing::warn!(
+                in_flight = self.run_gate.in_flight(),
+                "Grace period expired, stopping in-flight runs"
+            );
+            self.run_gate.request_stop();
+            if tokio::time::timeout(SHUTDOWN_FLUSH_TIMEOUT, self.run_gate.wait_idle())
+                .await
+                .is_err()
+            {
+                tracing::warn!("In-flight runs did not stop, shutting down anyway");
+            }
+        }
+
+        // Interrupted runs checkpoint their own thread as they stop
+        let interrupted_runs = self.run_gate.stops();
+        let checkpointed =
+            self.checkpointer.is_some() && self.run_gate.checkpoints() >= interrupted_runs;
+
+        for provider in &self.tool_providers {
+            if let Err(e) = provider.shutdown().await {
+                tracing::warn!(error = %e, "Failed to shut down tool provider");
+            }
+        }
+
+        let events_flushed = match &self.event_dispatcher {
+            Some(dispatcher) => tokio::time::timeout(SHUTDOWN_FLUSH_TIMEOUT, dispatcher.flush())
+                .await
+                .is_ok(),
+            None => true,
+        };
+        if !events_flushed {
+            tracing::warn!("Timed out flushing pending event broadcasts");
+        }
+
+        Ok(ShutdownReport {
+            drained,
+            interrupted_runs,
+            checkpointed,
+            events_flushed,
+            duration_ms: started.elapsed().as_millis() as u64,
+        })
+    }
+
+    /// Get the current pending interrupt, if any.
+    pub fn current_interrupt(&self) -> Option<AgentInterrupt> {
+        self.run_state()
+            .read()
+            .ok()
+            .and_then(|guard| guard.pending_interrupts.first().cloned())
+    }
+
+    /// Add a broadcaster dynamically to the agent's event dispatcher.
+    ///
+    /// Add a single broadcaster dynamically after the agent is built.
+    ///
+    /// This is useful for per-conversation or per-customer broadcasters.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use std::sync::Arc;
+    /// // agent.add_broadcaster(Arc::new(MyBroadcaster::new()));
+    /// ```
+    pub fn add_broadcaster(&self, broadcaster: Arc<dyn agents_core::events::EventBroadcaster>) {
+        if let Some(dispatcher) = &self.event_dispatcher {
+            dispatcher.add_broadcaster(broadcaster);
+            tracing::debug!("Broadcaster added to event dispatcher");
+        } else {
+            tracing::warn!("add_broadcaster called but no event dispatcher configured");
+        }
+    }
+
+    /// Add multiple broadcasters at once.
+    ///
+    /// This is useful when you need to add several broadcasters for a conversation
+    /// (e.g., WhatsApp, SSE, DynamoDB).
+    ///
+    /// # Example
+    /// ```no_run
+    /// use std::sync::Arc;
+    /// // agent.add_broadcasters(vec![
+    /// //     Arc::new(WhatsAppBroadcaster::new(phone)),
+    /// //     Arc::new(SseBroadcaster::new(channel)),
+    /// //     Arc::new(DynamoDbBroadcaster::new(table)),
+    /// // ]);
+    /// ```
+    pub fn add_broadcasters(
+        &self,
+        broadcasters: Vec<Arc<dyn agents_core::events::EventBroadcaster>>,
+    ) {
+        if let Some(dispatcher) = &self.event_dispatcher {
+            for broadcaster in broadcasters {
+                dispatcher.add_broadcaster(broadcaster);
+            }
+            tracing::debug!("Multiple broadcasters added to event dispatcher");
+        } else {
+            tracing::warn!("add_broadcasters called but no event dispatcher configured");
+        }
+    }
+
+    /// Receive the chunks of every streamed run from now on, as the caller of
+    /// `handle_message_stream` receives them.
+    ///
+    /// Lets observers such as WebSocket clients or live dashboards follow a response
+    /// while it is sent to the HTTP client. A subscriber that falls more than
+    /// [`STREAM_SUBSCRIBER_BUFFER`](super::stream_tee::STREAM_SUBSCRIBER_BUFFER)
+    /// chunks behind skips the oldest ones and receives a `Lagged` error.
+    ///
+    /// ```ignore
+    /// let mut chunks = agent.subscribe_stream();
+    /// tokio::spawn(async move {
+    ///     while let Ok(teed) = chunks.recv().await {
+    ///         if let StreamChunk::TextDelta(text) = teed.chunk {
+    ///             dashboard.append(&teed.correlation_id, &text);
+    ///         }
+    ///     }
+    /// });
+    /// ```
+    pub fn subscribe_stream(&self) -> tokio::sync::broadcast::Receiver<TeedChunk> {
+        self.stream_tee.subscribe()
+    }
+
+    /// Resume execution after human approval of an interrupt.
+    ///
+    /// The decision is recorded without a reviewer; use
+    /// [`resume_with_decision`](Self::resume_with_decision) to say who decided.
+    pub async fn resume_with_approval(&self, action: HitlAction) -> anyhow::Result<AgentMessage> {
+        self.resume_with_reviewer(action, None, None).await
+    }
+
+    /// Resume execution with a reviewer's decision, recording who decided and why
+    /// in the state's [`hitl_decisions`](AgentStateSnapshot::hitl_decisions), the
+    /// conversation history and a `HitlDecided` event.
+    ///
+    /// ```ignore
+    /// let edit = HitlAction::Edit { tool_name: "transfer".into(), tool_args: json!({"amount": 90}) };
+    /// agent
+    ///     .resume_with_decision(ApprovalDecision::new(edit, "ana").with_reason("amount typo"))
+    ///     .await?;
+    /// ```
+    pub async fn resume_with_decision(
+        &self,
+        decision: ApprovalDecision,
+    ) -> anyhow::Result<AgentMessage> {
+        self.resume_with_reviewer(decision.action, Some(decision.decided_by), decision.reason)
+            .await
+    }
+
+    async fn resume_with_reviewer(
+        &self,
+        action: HitlAction,
+        decided_by: Option<String>,
+        note: Option<String>,
+    ) -> anyhow::Result<AgentMessage> {
+        let _admission = self.admit_run(RunPriority::Normal).await?;
+        let run = async {
+            let result_message = self.apply_approval(action, decided_by, note).await?;
+
+            // Persist cleared state
+            if self.checkpointer.is_some() {
+                let state_clone = self.checkpoint_state()?;
+                self.persist_state(&ThreadId::default(), &state_clone)
+                    .await?;
+            }
+
+            Ok(result_message)
+        };
+        Box::pin(self.in_run_scope(None, run)).await
+    }
+
+    /// Apply a decision to the first pending interrupt and clear the interrupts.
+    async fn apply_approval(
+        &self,
+        action: HitlAction,
+        decided_by: Option<String>,
+        note: Option<String>,
+    ) -> anyhow::Result<AgentMessage> {
+        // Get the first pending interrupt
+        let interrupt = {
+            let state = self.run_state();
+            let state_guard = state
+                .read()
+                .map_err(|_| anyhow::anyhow!("Failed to acquire read lock on state"))?;
+            state_guard
+                .pending_interrupts
+                .first()
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("No pending interrupts"))?
+        };
+        let AgentInterrupt::HumanInLoop(pending) = &interrupt;
+        self.record_decision(HitlDecisionRecord::new(pending, &action, decided_by, note))?;
+
+        let result_message = match action {
+            HitlAction::Accept => {
+                // Execute with original args
+                let AgentInterrupt::HumanInLoop(hitl) = interrupt;
+                tracing::info!(
+                    tool_name = %hitl.tool_name,
+                    call_id = %hitl.call_id,
+                    "✅ HITL: Tool approved, executing with original arguments"
                 );
-                let response = AgentMessage {
-                    role: MessageRole::Agent,
-                    content: MessageContent::Text(
-                        "I've reached the maximum number of steps. Let me summarize what I've done so far.".to_string()
-                    ),
+
+                let tools = self.collect_tools();
+                let tool = tools
+                    .get(&hitl.tool_name)
+                    .cloned()
+                    .ok_or_else(|| anyhow::anyhow!("Tool '{}' not found", hitl.tool_name))?;
+
+                self.execute_tool(tool, hitl.tool_name, hitl.tool_args, RunPriority::Normal)
+                    .await?
+            }
+
+            HitlAction::Edit {
+                tool_name,
+                tool_args,
+            } => {
+                // Execute with modified args
+                tracing::info!(
+                    tool_name = %tool_name,
+                    "✏️ HITL: Tool edited, executing with modified arguments"
+                );
+
+                let tools = self.collect_tools();
+                let tool = tools
+                    .get(&tool_name)
+                    .cloned()
+                    .ok_or_else(|| anyhow::anyhow!("Tool '{}' not found", tool_name))?;
+
+                self.execute_tool(tool, tool_name, tool_args, RunPriority::Normal)
+                    .await?
+            }
+
+            HitlAction::Reject { reason } => {
+                // Don't execute - return rejection message
+                tracing::info!("❌ HITL: Tool rejected");
+
+                let text = reason
+                    .unwrap_or_else(|| "Tool execution rejected by human reviewer.".to_string());
+
+                let message = AgentMessage {
+                    role: MessageRole::Tool,
+                    content: MessageContent::Text(text),
                     metadata: None,
                 };
-                self.append_history(response.clone());
-                return Ok(response);
+
+                self.append_history(message.clone());
+                message
             }
 
-            tracing::debug!("🔄 ReAct iteration {}/{}", iteration, max_iterations);
+            HitlAction::Respond { message } => {
+                // Don't execute - return custom message
+                tracing::info!("💬 HITL: Custom response provided");
 
-            // Build request with current history
-            let mut request = ModelRequest::new(&self.instructions, self.current_history());
-            let tools = self.collect_tools();
-            for middleware in &self.middlewares {
-                let mut ctx = MiddlewareContext::with_request(&mut request, self.state.clone());
-                middleware.modify_model_request(&mut ctx).await?;
+                self.append_history(message.clone());
+                message
             }
+        };
 
-            let tool_schemas: Vec<_> = tools.values().map(|t| t.schema()).collect();
-            let context = PlannerContext {
-                history: request.messages.clone(),
-                system_prompt: request.system_prompt.clone(),
-                tools: tool_schemas,
-            };
-            let state_snapshot = Arc::new(self.state.read().map(|s| s.clone()).unwrap_or_default());
+        // Clear the interrupt from state
+        {
+            let state = self.run_state();
+            let mut state_guard = state
+                .write()
+                .map_err(|_| anyhow::anyhow!("Failed to acquire write lock on state"))?;
+            state_guard.clear_interrupts();
+        }
 
-            // Ask LLM what to do
-            let decision = self.planner.plan(context, state_snapshot).await?;
+        Ok(result_message)
+    }
 
-            // Emit PlanningComplete event
-            self.emit_event(agents_core::events::AgentEvent::PlanningComplete(
-                agents_core::events::PlanningCompleteEvent {
-                    metadata: self.create_event_metadata(),
-                    action_type: match &decision.next_action {
-                        PlannerAction::Respond { .. } => "respond".to_string(),
-                        PlannerAction::CallTool { .. } => "call_tool".to_string(),
-                        PlannerAction::Terminate => "terminate".to_string(),
-                    },
-                    action_summary: match &decision.next_action {
-                        PlannerAction::Respond { message } => {
-                            format!("Respond: {}", self.truncate_message(message))
-                        }
-                        PlannerAction::CallTool { tool_name, .. } => {
-                            format!("Call tool: {}", tool_name)
-                        }
-                        PlannerAction::Terminate => "Terminate".to_string(),
+    /// Keep a decision in the state, note it in the history and emit `HitlDecided`.
+    fn record_decision(&self, record: HitlDecisionRecord) -> anyhow::Result<()> {
+        tracing::info!(
+            tool_name = %record.tool_name,
+            call_id = %record.call_id,
+            action = ?record.action,
+            decided_by = record.decided_by.as_deref().unwrap_or("-"),
+            "HITL decision recorded"
+        );
+        self.append_history(AgentMessage {
+            role: MessageRole::System,
+            content: MessageContent::Text(record.summary()),
+            metadata: None,
+        });
+        self.emit_event(agents_core::events::AgentEvent::HitlDecided(
+            agents_core::events::HitlDecidedEvent {
+                metadata: self.create_event_metadata(),
+                tool_name: record.tool_name.clone(),
+                call_id: record.call_id.clone(),
+                action: record.action,
+                decided_by: record.decided_by.clone(),
+                original_args: record.original_args.clone(),
+                edited_tool_name: record.edited_tool_name.clone(),
+                edited_args: record.edited_args.clone(),
+                reason: record.reason.clone(),
+            },
+        ));
+        self.run_state()
+            .write()
+            .map_err(|_| anyhow::anyhow!("Failed to acquire write lock on state"))?
+            .hitl_decisions
+            .push(record);
+        Ok(())
+    }
+
+    /// Resume a thread paused for approval with the decision recorded in the
+    /// configured [`ApprovalStore`](agents_core::approval_store::ApprovalStore).
+    ///
+    /// Returns `None` when the thread has no pending interrupt or its approval is still
+    /// undecided. An approval that expired undecided rejects the tool call. Like
+    /// [`handle_message_for_thread`](Self::handle_message_for_thread), the thread's
+    /// lease is held while the decision is applied and the state saved.
+    ///
+    /// ```ignore
+    /// // Admin service
+    /// store.decide(&thread_id, &call_id, ApprovalDecision::new(HitlAction::Accept, "ana")).await?;
+    /// // Agent service, e.g. triggered by the admin service or a table stream
+    /// if let Some(reply) = agent.resume_thread(&thread_id).await? { notify(reply) }
+    /// ```
+    pub async fn resume_thread(
+        &self,
+        thread_id: &ThreadId,
+    ) -> anyhow::Result<Option<AgentMessage>> {
+        let approvals = self
+            .approvals
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No approval store configured"))?;
+        let lease = match &self.thread_lock {
+            Some(config) => Some(config.acquire(thread_id).await?),
+            None => None,
+        };
+
+        let result = match self.admit_run(RunPriority::Normal).await {
+            Ok(_admission) => {
+                let run = async {
+                    let result = self
+                        .resume_on_thread(approvals, thread_id, lease.as_ref())
+                        .await;
+                    self.flush_staged_events(thread_id).await;
+                    result
+                };
+                Box::pin(self.in_run_scope(Some(thread_id), run)).await
+            }
+            Err(e) => Err(e),
+        };
+        if let Some(lease) = lease {
+            lease.release().await;
+        }
+        result
+    }
+
+    async fn resume_on_thread(
+        &self,
+        approvals: &ApprovalConfig,
+        thread_id: &ThreadId,
+        lease: Option<&HeldLease>,
+    ) -> anyhow::Result<Option<AgentMessage>> {
+        if !self.load_state(thread_id).await? {
+            return Ok(None);
+        }
+        let Some(AgentInterrupt::HumanInLoop(interrupt)) = self.current_interrupt() else {
+            return Ok(None);
+        };
+
+        let approval = approvals.store.get(thread_id, &interrupt.call_id).await?;
+        // The store may already have purged an approval that expired
+        let expired = approval
+            .as_ref()
+            .map_or_else(|| approvals.has_expired(&interrupt), |a| a.is_expired());
+        let (action, decided_by, note) = match approval.and_then(|a| a.decision) {
+            Some(decision) => {
+                tracing::info!(
+                    thread_id = %thread_id,
+                    call_id = %interrupt.call_id,
+                    decided_by = %decision.decided_by,
+                    "Resuming thread with recorded approval"
+                );
+                (decision.action, Some(decision.decided_by), decision.reason)
+            }
+            None if expired => {
+                tracing::info!(
+                    thread_id = %thread_id,
+                    call_id = %interrupt.call_id,
+                    "Approval expired, rejecting tool call"
+                );
+                let action = HitlAction::Reject {
+                    reason: Some("Approval request expired before a reviewer decided.".to_string()),
+                };
+                (action, None, None)
+            }
+            None => return Ok(None),
+        };
+
+        let message = self.apply_approval(action, decided_by, note).await?;
+        if let Some(lease) = lease {
+            lease.ensure_held()?;
+        }
+        self.save_state(thread_id).await?;
+        approvals
+            .store
+            .remove(thread_id, &interrupt.call_id)
+            .await?;
+        self.index_turn(thread_id, &[&message]).await;
+        Ok(Some(message))
+    }
+
+    /// Record the interrupts a thread run left pending in the approval store.
+    async fn record_pending_approvals(&self, thread_id: &ThreadId) -> anyhow::Result<()> {
+        let Some(approvals) = &self.approvals else {
+            return Ok(());
+        };
+        let interrupts = self
+            .run_state()
+            .read()
+            .map_err(|_| anyhow::anyhow!("Failed to acquire read lock on state"))?
+            .pending_interrupts
+            .clone();
+        for AgentInterrupt::HumanInLoop(interrupt) in interrupts {
+            if approvals
+                .store
+                .get(thread_id, &interrupt.call_id)
+                .await?
+                .is_some()
+            {
+                continue;
+            }
+            let approval = approvals.pending(thread_id, &interrupt);
+            approvals.store.put(&approval).await?;
+            tracing::info!(
+                thread_id = %thread_id,
+                tool_name = %interrupt.tool_name,
+                call_id = %interrupt.call_id,
+                approver = approval.approver.as_deref().unwrap_or_default(),
+                "Recorded pending approval"
+            );
+        }
+        Ok(())
+    }
+
+    /// Handle message from string input - converts string to AgentMessage internally
+    pub async fn handle_message(
+        &self,
+        input: impl AsRef<str>,
+        state: Arc<AgentStateSnapshot>,
+    ) -> anyhow::Result<AgentMessage> {
+        self.handle_message_with_metadata(input, None, state).await
+    }
+
+    /// Handle message from string input with metadata - converts string to AgentMessage internally
+    pub async fn handle_message_with_metadata(
+        &self,
+        input: impl AsRef<str>,
+        metadata: Option<MessageMetadata>,
+        state: Arc<AgentStateSnapshot>,
+    ) -> anyhow::Result<AgentMessage> {
+        let agent_message = AgentMessage {
+            role: MessageRole::User,
+            content: MessageContent::Text(input.as_ref().to_string()),
+            metadata,
+        };
+        self.handle_message_internal(
+            agent_message,
+            state,
+            None,
+            RunPriority::Normal,
+            &RunOptions::default(),
+        )
+        .await
+    }
+
+    /// Handle a message with per-call options, e.g. the provider key of the customer
+    /// the request is served for, or a different model or tool set.
+    ///
+    /// ```ignore
+    /// let options = RunOptions::new().with_api_key("openai", customer.openai_key());
+    /// let reply = agent.handle_message_with_options(text, options, state).await?;
+    /// ```
+    pub async fn handle_message_with_options(
+        &self,
+        input: impl AsRef<str>,
+        options: RunOptions,
+        state: Arc<AgentStateSnapshot>,
+    ) -> anyhow::Result<AgentMessage> {
+        let options = self.apply_quota(options).await?;
+        let agent_message = AgentMessage {
+            role: MessageRole::User,
+            content: MessageContent::Text(input.as_ref().to_string()),
+            metadata: None,
+        };
+        let run = options.scope(self.handle_message_internal(
+            agent_message,
+            state,
+            None,
+            RunPriority::Normal,
+            &options,
+        ));
+        self.count_in_quota(&options, run).await
+    }
+
+    /// Handle a message with a scheduling priority.
+    ///
+    /// The priority only matters when concurrency limits are configured and the
+    /// agent is at capacity: queued runs are then picked by the weighted policy of
+    /// [`ConcurrencyConfig`](super::concurrency::ConcurrencyConfig), and tool
+    /// executions of the run queue with the same priority.
+    ///
+    /// ```ignore
+    /// let priority = if customer.is_premium() { RunPriority::High } else { RunPriority::Normal };
+    /// let reply = agent.handle_message_with_priority(text, priority, state).await?;
+    /// ```
+    pub async fn handle_message_with_priority(
+        &self,
+        input: impl AsRef<str>,
+        priority: RunPriority,
+        state: Arc<AgentStateSnapshot>,
+    ) -> anyhow::Result<AgentMessage> {
+        let agent_message = AgentMessage {
+            role: MessageRole::User,
+            content: MessageContent::Text(input.as_ref().to_string()),
+            metadata: None,
+        };
+        self.handle_message_internal(agent_message, state, None, priority, &RunOptions::default())
+            .await
+    }
+
+    /// Handle a message on a checkpointed thread, holding the thread's lease.
+    ///
+    /// Loads the thread's state from the checkpointer, runs the message and saves the
+    /// state back. With [`ThreadLockConfig`](super::thread_affinity::ThreadLockConfig)
+    /// configured, the thread's lease is held for the whole run so replicas sharing a
+    /// checkpointer never process the same thread at once: a busy thread fails with
+    /// [`ThreadLockError::Busy`](super::thread_affinity::ThreadLockError::Busy) after the acquire timeout, and a run that lost its
+    /// lease fails with [`ThreadLockError::LeaseLost`](super::thread_affinity::ThreadLockError::LeaseLost) without saving its state.
+    ///
+    /// ```ignore
+    /// match agent.handle_message_for_thread(&thread_id, text).await {
+    ///     Err(e) if e.is::<ThreadLockError>() => StatusCode::CONFLICT,
+    ///     ...
+    /// }
+    /// ```
+    pub async fn handle_message_for_thread(
+        &self,
+        thread_id: &ThreadId,
+        input: impl AsRef<str>,
+    ) -> anyhow::Result<AgentMessage> {
+        self.handle_message_for_thread_with_options(thread_id, input, RunOptions::default())
+            .await
+    }
+
+    /// [`handle_message_for_thread`](Self::handle_message_for_thread) with per-call
+    /// options. The options apply to this run only and are not saved with the thread.
+    ///
+    /// With an [`Experiment`] configured, options left unset are taken from the
+    /// thread's variant.
+    pub async fn handle_message_for_thread_with_options(
+        &self,
+        thread_id: &ThreadId,
+        input: impl AsRef<str>,
+        options: RunOptions,
+    ) -> anyhow::Result<AgentMessage> {
+        let options = self.apply_quota(options).await?;
+        let lease = match &self.thread_lock {
+            Some(config) => Some(config.acquire(thread_id).await?),
+            None => None,
+        };
+
+        let (options, assignment) = match &self.experiment {
+            Some(experiment) => experiment.configure(thread_id, options),
+            None => (options, None),
+        };
+        // The run holds its slot from load to save
+        let admission = match self.admit_run(RunPriority::Normal).await {
+            Ok(admission) => admission,
+            Err(e) => {
+                if let Some(lease) = lease {
+                    lease.release().await;
+                }
+                return Err(e);
+            }
+        };
+        let run =
+            options.scope(self.run_on_thread(thread_id, input.as_ref(), lease.as_ref(), &options));
+        let run = async {
+            match assignment {
+                Some(assignment) => experiment::with_experiment(assignment, run).await,
+                None => run.await,
+            }
+        };
+        let run = async {
+            let result = self.count_in_quota(&options, run).await;
+            self.flush_staged_events(thread_id).await;
+            result
+        };
+        let result = Box::pin(self.in_run_scope(Some(thread_id), run)).await;
+        drop(admission);
+        if let Some(lease) = lease {
+            lease.release().await;
+        }
+        result
+    }
+
+    /// Check the run against the quotas of its tenant: a tenant over quota is
+    /// rejected, or served by the degraded model in the options returned.
+    async fn apply_quota(&self, options: RunOptions) -> anyhow::Result<RunOptions> {
+        let (Some(quotas), Some(tenant)) = (&self.quotas, &options.tenant) else {
+            return Ok(options);
+        };
+        let Err(exceeded) = quotas.check(tenant).await else {
+            return Ok(options);
+        };
+        match &quotas.on_exceeded {
+            QuotaAction::Reject => Err(exceeded.into()),
+            QuotaAction::Degrade(model) => {
+                tracing::info!(tenant = %tenant, "{}; serving the run with the degraded model", exceeded);
+                Ok(options.with_model(model.clone()))
+            }
+        }
+    }
+
+    /// Run `run` and count it, with the tokens of its model calls, in the quotas of
+    /// the tenant it is served for. Failed runs are counted too.
+    async fn count_in_quota<F>(&self, options: &RunOptions, run: F) -> anyhow::Result<AgentMessage>
+    where
+        F: Future<Output = anyhow::Result<AgentMessage>>,
+    {
+        let (Some(quotas), Some(tenant)) = (&self.quotas, &options.tenant) else {
+            return run.await;
+        };
+        let (result, tokens) = quotas::metered(run).await;
+        quotas.record(tenant, tokens).await;
+        result
+    }
+
+    async fn run_on_thread(
+        &self,
+        thread_id: &ThreadId,
+        input: &str,
+        lease: Option<&HeldLease>,
+        options: &RunOptions,
+    ) -> anyhow::Result<AgentMessage> {
+        let run = Box::pin(self.run_thread_turn(thread_id, input, lease, options));
+        if self.profiling {
+            profiling::with_profiler(Arc::new(RunProfiler::new()), run).await
+        } else {
+            run.await
+        }
+    }
+
+    async fn run_thread_turn(
+        &self,
+        thread_id: &ThreadId,
+        input: &str,
+        lease: Option<&HeldLease>,
+        options: &RunOptions,
+    ) -> anyhow::Result<AgentMessage> {
+        let state = match &self.checkpointer {
+            Some(checkpointer) if self.lazy_file_loading => {
+                profiling::time_async(
+                    ProfilePhase::Persistence,
+                    checkpointer.load_state_lazy(thread_id),
+                )
+                .await?
+            }
+            Some(checkpointer) => {
+                profiling::time_async(
+                    ProfilePhase::Persistence,
+                    checkpointer.load_state(thread_id),
+                )
+                .await?
+            }
+            None => None,
+        };
+        let mut state = state.unwrap_or_default();
+        self.assign_user(thread_id, &mut state, options).await?;
+        let turn_started = chrono::Utc::now();
+        let agent_message = AgentMessage {
+            role: MessageRole::User,
+            content: MessageContent::Text(input.to_string()),
+            metadata: None,
+        };
+        let run = Box::pin(self.run_admitted(
+            agent_message.clone(),
+            Arc::new(state),
+            Some(thread_id),
+            RunPriority::Normal,
+            options,
+        ));
+        let response = match &self.checkpointer {
+            // Tools read deferred files through the thread's checkpointer
+            Some(checkpointer) if self.lazy_file_loading => {
+                let loader = FileLoader::new(checkpointer.clone(), thread_id.clone());
+                persistence::with_file_loader(loader, run).await?
+            }
+            _ => run.await?,
+        };
+
+        // Fence the checkpoint: another replica may own the thread by now
+        if let Some(lease) = lease {
+            lease.ensure_held()?;
+        }
+        self.save_state(thread_id).await?;
+        self.record_pending_approvals(thread_id).await?;
+        self.index_turn(thread_id, &[&agent_message, &response])
+            .await;
+        self.title_turn(thread_id, &[&agent_message, &response])
+            .await;
+        self.remember_turn(thread_id, &[&agent_message, &response])
+            .await;
+        self.remember_user(thread_id, turn_started).await;
+        Ok(response)
+    }
+
+    /// Add the messages of a checkpointed turn to the conversation index. A failure
+    /// is logged rather than failing the run, whose state is already saved.
+    async fn index_turn(&self, thread_id: &ThreadId, messages: &[&AgentMessage]) {
+        let Some(index) = &self.conversation_index else {
+            return;
+        };
+        let entries: Vec<IndexedMessage> = messages
+            .iter()
+            .filter_map(|message| IndexedMessage::from_message(thread_id, message))
+            .collect();
+        if let Err(e) = index.index_messages(&entries).await {
+            tracing::error!(thread_id = %thread_id, "Failed to index conversation turn: {}", e);
+        }
+    }
+
+    /// Count a checkpointed turn towards the thread's title and summary, writing
+    /// them when due. Failures are logged; the turn is still counted when only the
+    /// model call failed.
+    async fn title_turn(&self, thread_id: &ThreadId, messages: &[&AgentMessage]) {
+        let (Some(titles), Some(checkpointer)) = (&self.thread_titles, &self.checkpointer) else {
+            return;
+        };
+        let mut metadata = match checkpointer.load_metadata(thread_id).await {
+            Ok(metadata) => metadata.unwrap_or_default(),
+            Err(e) => {
+                tracing::warn!(thread_id = %thread_id, "Failed to load thread metadata: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = titles.record_turn(&mut metadata, messages).await {
+            tracing::warn!(thread_id = %thread_id, "Failed to title thread: {}", e);
+        }
+        if let Err(e) = checkpointer.save_metadata(thread_id, &metadata).await {
+            tracing::warn!(thread_id = %thread_id, "Failed to save thread title: {}", e);
+        }
+    }
+
+    /// Assign the thread to the user the run is served for, tagging it when the user
+    /// changes, add the user's memories to its profile and drop the facts the memory
+    /// policy no longer allows to keep.
+    async fn assign_user(
+        &self,
+        thread_id: &ThreadId,
+        state: &mut AgentStateSnapshot,
+        options: &RunOptions,
+    ) -> anyhow::Result<()> {
+        if let Some(user_id) = &options.user_id {
+            if state.user_id.as_ref() != Some(user_id) {
+                if self.checkpointer.is_some() {
+                    self.tag_thread(thread_id, &user_memory::user_tag(user_id))
+                        .await?;
+                }
+                state.user_id = Some(user_id.clone());
+            }
+        }
+        if let (Some(memory), Some(user_id)) = (&self.user_memory, &state.user_id) {
+            let policy = self.memory_policy.as_deref();
+            if let Err(e) = memory.load(user_id, &mut state.user_profile, policy).await {
+                tracing::warn!(user_id = %user_id, "Failed to load user memories: {}", e);
+            }
+        }
+        if let Some(policy) = &self.memory_policy {
+            policy
+                .enforce(state.user_id.as_ref(), &mut state.user_profile)
+                .await;
+        }
+        Ok(())
+    }
+
+    /// Save the profile facts a turn learned to the memories of the thread's user.
+    async fn remember_user(&self, thread_id: &ThreadId, since: chrono::DateTime<chrono::Utc>) {
+        let Some(memory) = &self.user_memory else {
+            return;
+        };
+        let (user_id, profile) = {
+            let state = self.run_state();
+            let state = state.read().unwrap();
+            (state.user_id.clone(), state.user_profile.clone())
+        };
+        let Some(user_id) = user_id else {
+            return;
+        };
+        match memory.save(&user_id, thread_id, &profile, since).await {
+            Ok(0) => {}
+            Ok(saved) => tracing::debug!(user_id = %user_id, saved, "Saved user memories"),
+            Err(e) => tracing::warn!(user_id = %user_id, "Failed to save user memories: {}", e),
+        }
+    }
+
+    /// Add the facts a checkpointed turn states to the knowledge graph. Failures are
+    /// logged; the turn is not retried.
+    async fn remember_turn(&self, thread_id: &ThreadId, messages: &[&AgentMessage]) {
+        let Some(graph) = &self.graph_memory else {
+            return;
+        };
+        let user_id = self.run_state().read().unwrap().user_id.clone();
+        let policy = self.memory_policy.as_deref();
+        match graph
+            .record_turn(thread_id, messages, policy, user_id.as_ref())
+            .await
+        {
+            Ok(facts) => {
+                tracing::debug!(thread_id = %thread_id, facts, "Added facts to graph memory")
+            }
+            Err(e) => {
+                tracing::warn!(thread_id = %thread_id, "Failed to extract graph facts: {}", e)
+            }
+        }
+    }
+
+    /// Admit a run: refuse it once shutdown has started, then wait for the agent's
+    /// run slot. Runs share the agent's state, so callers that load or save that
+    /// state around the run keep the admission until they are done with it.
+    async fn admit_run(&self, priority: RunPriority) -> anyhow::Result<(RunGuard, Permit)> {
+        let run = self.run_gate.enter()?;
+        let slot = self.concurrency.acquire_run(priority).await?;
+        Ok((run, slot))
+    }
+
+    async fn handle_message_internal(
+        &self,
+        input: AgentMessage,
+        loaded_state: Arc<AgentStateSnapshot>,
+        thread_id: Option<&ThreadId>,
+        priority: RunPriority,
+        options: &RunOptions,
+    ) -> anyhow::Result<AgentMessage> {
+        let _admission = self.admit_run(priority).await?;
+        let run = async {
+            let result = self
+                .run_admitted(input, loaded_state, thread_id, priority, options)
+                .await;
+            // These runs end without a checkpoint
+            self.flush_staged_events(&thread_id.cloned().unwrap_or_default())
+                .await;
+            result
+        };
+        Box::pin(self.in_run_scope(thread_id, run)).await
+    }
+
+    /// Run a message and persist the resulting state, as `AgentHandle::handle_message`
+    /// does. The state and the run's events are written under the same thread. The
+    /// caller holds the run's admission.
+    async fn run_and_persist(
+        &self,
+        input: AgentMessage,
+        loaded_state: Arc<AgentStateSnapshot>,
+        thread_id: Option<&ThreadId>,
+    ) -> anyhow::Result<AgentMessage> {
+        let thread = thread_id.cloned().unwrap_or_default();
+        let result = Box::pin(self.run_admitted(
+            input,
+            loaded_state,
+            thread_id,
+            RunPriority::Normal,
+            &RunOptions::default(),
+        ))
+        .await;
+
+        // Persist state to checkpointer after successful message handling
+        let persisted = match &result {
+            Ok(_) if self.checkpointer.is_some() => match self.checkpoint_state() {
+                Ok(state) => self.persist_state(&thread, &state).await,
+                Err(e) => Err(e),
+            },
+            _ => Ok(()),
+        };
+        // Events no checkpoint took along, e.g. those of a failed run
+        self.flush_staged_events(&thread).await;
+        persisted.and(result)
+    }
+
+    /// Run a message inside its own trace span and correlation scope, joining the
+    /// caller's trace and correlation id when the message carries them or the run was
+    /// started from another run's tool. A failed run is recorded as a dead letter.
+    /// The caller holds the run's admission.
+    async fn run_admitted(
+        &self,
+        input: AgentMessage,
+        loaded_state: Arc<AgentStateSnapshot>,
+        thread_id: Option<&ThreadId>,
+        priority: RunPriority,
+        options: &RunOptions,
+    ) -> anyhow::Result<AgentMessage> {
+        let run_id = self.journal.start_run();
+        if let Some(thread_id) = thread_id {
+            self.journal.assign_thread(&run_id, thread_id);
+        }
+        let replay_input = self
+            .dead_letters
+            .as_ref()
+            .map(|_| (input.clone(), loaded_state.clone()));
+
+        let metadata = input.metadata.as_ref();
+        // Without a caller-supplied id, events of the run share the run id
+        let correlation_id = metadata
+            .and_then(|m| m.correlation_id.clone())
+            .or_else(current_correlation_id)
+            .unwrap_or_else(|| run_id.clone());
+        let parent = metadata
+            .and_then(|m| m.traceparent.as_deref())
+            .and_then(TraceContext::from_traceparent)
+            .or_else(TraceContext::current);
+        let trace = parent
+            .map(|parent| parent.child())
+            .unwrap_or_else(TraceContext::new_root);
+        let span = tracing::info_span!(
+            "agent_run",
+            agent = %self.descriptor.name,
+            trace_id = %trace.trace_id,
+            span_id = %trace.span_id,
+            parent_span_id = trace.parent_span_id.as_deref().unwrap_or_default(),
+            correlation_id = %correlation_id,
+        );
+        let run = trace.scope(with_current_agent(
+            self.descriptor.name.clone(),
+            self.run_translated(input, loaded_state, thread_id, priority, options)
+                .instrument(span),
+        ));
+        let run = async {
+            match &self.deterministic {
+                Some(deterministic) => deterministic.scope(run).await,
+                None => run.await,
+            }
+        };
+        // Thread runs reuse the profiler timing the checkpoint load and save; nested
+        // runs without profiling record into the profile of the run that started them
+        let profiler = self.profiling.then(|| {
+            thread_id
+                .and_then(|_| profiling::current_profiler())
+                .unwrap_or_default()
+        });
+        // Boxed, so the large run future isn't moved across the stack into the scope
+        let run = Box::pin(with_correlation_id(correlation_id.clone(), run));
+        let result = match profiler {
+            Some(profiler) => {
+                self.journal.attach_profiler(&run_id, profiler.clone());
+                profiling::with_profiler(profiler, run).await
+            }
+            None => run.await,
+        };
+        let result = match &options.output_format {
+            Some(format) => result.map(|response| format.format_message(response)),
+            None => result,
+        };
+
+        if let (Err(e), Some((input, state))) = (&result, replay_input) {
+            let mut letter = DeadLetter::new(
+                self.descriptor.name.clone(),
+                run_id.clone(),
+                input,
+                (*state).clone(),
+                format!("{:#}", e),
+            )
+            .with_correlation_id(correlation_id)
+            .with_events(self.journal.events(&run_id));
+            if let Some(thread_id) = thread_id {
+                letter = letter.with_thread_id(thread_id.clone());
+            }
+            self.record_dead_letter(&letter).await;
+        }
+        result
+    }
+
+    async fn record_dead_letter(&self, letter: &DeadLetter) {
+        let Some(store) = &self.dead_letters else {
+            return;
+        };
+        match store.put(letter).await {
+            Ok(()) => tracing::warn!(
+                dead_letter_id = %letter.id,
+                run_id = %letter.run_id,
+                "☠️ Run failed and was recorded as a dead letter: {}",
+                letter.error
+            ),
+            Err(e) => tracing::error!(
+                run_id = %letter.run_id,
+                "Failed to record dead letter: {}",
+                e
+            ),
+        }
+    }
+
+    /// Run a dead letter's input again, e.g. after fixing the cause of the failure.
+    ///
+    /// Thread runs are replayed on their thread, reloading its current state; other
+    /// runs start from the state recorded with the dead letter. The dead letter is
+    /// removed when the replay succeeds. A failed replay is recorded as a new dead
+    /// letter and the original is kept.
+    ///
+    /// ```ignore
+    /// for letter in store.list("support-agent").await? {
+    ///     let reply = agent.replay_dead_letter(&letter.id).await?;
+    /// }
+    /// ```
+    pub async fn replay_dead_letter(&self, id: &str) -> anyhow::Result<AgentMessage> {
+        let store = self
+            .dead_letters
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No dead-letter store is configured"))?;
+        let letter = store
+            .get(id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Dead letter '{}' not found", id))?;
+
+        let response = match &letter.thread_id {
+            Some(thread_id) => {
+                let input = letter.input.content.as_text().unwrap_or_default();
+                self.handle_message_for_thread(thread_id, input).await?
+            }
+            None => {
+                self.handle_message_internal(
+                    letter.input.clone(),
+                    Arc::new(letter.state.clone()),
+                    None,
+                    RunPriority::Normal,
+                    &RunOptions::default(),
+                )
+                .await?
+            }
+        };
+        store.remove(id).await?;
+        Ok(response)
+    }
+
+    /// Translate the input into the working language when a translation layer is
+    /// configured, run it and translate the response back into the user's language.
+    async fn run_translated(
+        &self,
+        input: AgentMessage,
+        loaded_state: Arc<AgentStateSnapshot>,
+        thread_id: Option<&ThreadId>,
+        priority: RunPriority,
+        options: &RunOptions,
+    ) -> anyhow::Result<AgentMessage> {
+        let Some(translation) = &self.translation else {
+            return self
+                .run_message(input, loaded_state, thread_id, priority, options)
+                .await;
+        };
+        let Some(user_language) = translation.user_language(&input, &loaded_state).await else {
+            return self
+                .run_message(input, loaded_state, thread_id, priority, options)
+                .await;
+        };
+
+        let working_language = translation.working_language.as_str();
+        tracing::debug!(from = %user_language, to = %working_language, "🌐 Translating run");
+        let input = translation
+            .translate_message(input, &user_language, working_language)
+            .await;
+        let response = self
+            .run_message(input, loaded_state, thread_id, priority, options)
+            .await?;
+        Ok(translation
+            .translate_message(response, working_language, &user_language)
+            .await)
+    }
+
+    /// Internal method that contains the actual message handling logic
+    async fn run_message(
+        &self,
+        input: AgentMessage,
+        loaded_state: Arc<AgentStateSnapshot>,
+        thread_id: Option<&ThreadId>,
+        priority: RunPriority,
+        options: &RunOptions,
+    ) -> anyhow::Result<AgentMessage> {
+        let start_time = std::time::Instant::now();
+
+        // Initialize internal state with loaded state from checkpointer
+        // This ensures conversation context is maintained across sessions
+        let mut state = (*loaded_state).clone();
+        state.correlation_id = current_correlation_id();
+        self.install_state(state);
+
+        self.emit_event(agents_core::events::AgentEvent::AgentStarted(
+            agents_core::events::AgentStartedEvent {
+                metadata: self.create_event_metadata(),
+                agent_name: self.descriptor.name.clone(),
+                message_preview: self.truncate_message(&input),
+            },
+        ));
+
+        let input = match &self.offload {
+            Some(offload) => offload.offload("user", input).await,
+            None => input,
+        };
+        self.append_history(input.clone());
+        self.refresh_provider_tools().await;
+        Box::pin(self.react_loop(start_time, thread_id, priority, options, 0)).await
+    }
+
+    /// Plan and execute tool calls until the planner answers, starting after
+    /// `iteration` iterations.
+    async fn react_loop(
+        &self,
+        start_time: std::time::Instant,
+        thread_id: Option<&ThreadId>,
+        priority: RunPriority,
+        options: &RunOptions,
+        mut iteration: usize,
+    ) -> anyhow::Result<AgentMessage> {
+        // ReAct loop: continue until LLM responds with text (not tool calls)
+        let max_iterations = options.max_iterations.unwrap_or(self.max_iterations).get();
+        let mut provenance = Provenance::default();
+        let mut corrections = 0;
+
+        loop {
+            if self.run_gate.stop_requested() {
+                self.run_gate.record_stop();
+                if self.checkpointer.is_some() {
+                    let state_clone = self.checkpoint_state()?;
+                    self.persist_state(&thread_id.cloned().unwrap_or_default(), &state_clone)
+                        .await?;
+                    self.run_gate.record_checkpoint();
+                }
+                return Err(AgentShutdownError::Interrupted { steps: iteration }.into());
+            }
+
+            iteration += 1;
+            if iteration > max_iterations {
+                tracing::warn!(
+                    "⚠️ Max iterations ({}) reached, stopping ReAct loop",
+                    max_iterations
+                );
+                let response = AgentMessage {
+                    role: MessageRole::Agent,
+                    content: MessageContent::Text(
+                        "I've reached the maximum number of steps. Let me summarize what I've done so far.".to_string()
+                    ),
+                    metadata: None,
+                };
+                self.append_history(response.clone());
+                return Ok(response);
+            }
+
+            tracing::debug!("🔄 ReAct iteration {}/{}", iteration, max_iterations);
+
+            // Build request with current history
+            let build = async {
+                let instructions = options
+                    .instructions
+                    .as_deref()
+                    .unwrap_or(&self.instructions);
+                let mut request = ModelRequest::new(instructions, self.request_history());
+                let mut tools = self.collect_tools();
+                tools.retain(|name, _| options.allows_tool(name));
+                for middleware in &self.middlewares {
+                    let mut ctx =
+                        MiddlewareContext::with_request(&mut request, self.run_state().clone());
+                    middleware.modify_model_request(&mut ctx).await?;
+                }
+
+                let tool_schemas = self.tool_schemas_for_turn(&tools, &request.messages).await;
+                if let Some(examples) = self.tool_examples.render(&tool_schemas) {
+                    request.append_prompt(&examples);
+                }
+                let context = PlannerContext {
+                    history: request.messages,
+                    system_prompt: request.system_prompt,
+                    tools: tool_schemas,
+                };
+                let state_snapshot = Arc::new(
+                    self.run_state()
+                        .read()
+                        .map(|s| s.clone())
+                        .unwrap_or_default(),
+                );
+                anyhow::Ok((tools, context, state_snapshot))
+            };
+            let (tools, context, state_snapshot) = profiling::time_async(
+                ProfilePhase::PromptBuild,
+                run_options::with_instructions(options.instructions.clone(), build),
+            )
+            .await?;
+
+            let recorded = self
+                .step_recording
+                .then(|| (context.clone(), (*state_snapshot).clone()));
+
+            // Ask LLM what to do
+            let decision = profiling::time_async(
+                ProfilePhase::Provider,
+                self.planner.plan(context, state_snapshot),
+            )
+            .await?;
+
+            if let Some((context, state)) = recorded {
+                self.journal.record_step(RecordedStep {
+                    iteration,
+                    context,
+                    state,
+                    action: decision.next_action.clone(),
+                    tool_result: None,
+                });
+            }
+
+            // Emit PlanningComplete event
+            self.emit_event(agents_core::events::AgentEvent::PlanningComplete(
+                agents_core::events::PlanningCompleteEvent {
+                    metadata: self.create_event_metadata(),
+                    action_type: match &decision.next_action {
+                        PlannerAction::Respond { .. } => "respond".to_string(),
+                        PlannerAction::CallTool { .. } => "call_tool".to_string(),
+                        PlannerAction::Terminate => "terminate".to_string(),
+                    },
+                    action_summary: match &decision.next_action {
+                        PlannerAction::Respond { message } => {
+                            format!("Respond: {}", self.truncate_message(message))
+                        }
+                        PlannerAction::CallTool { tool_name, .. } => {
+                            format!("Call tool: {}", tool_name)
+                        }
+                        PlannerAction::Terminate => "Terminate".to_string(),
+                    },
+                },
+            ));
+
+            match decision.next_action {
+                PlannerAction::Respond { message } => {
+                    if let Some(citations) = &self.citations {
+                        if corrections < citations.max_corrections
+                            && !citations.is_cited(&message.content.text(), &provenance)
+                        {
+                            corrections += 1;
+                            self.require_citation(citations, message, &provenance);
+                            // Loop continues - LLM answers again from the tool results
+                            continue;
+                        }
+                    }
+
+                    // LLM decided to respond with text - exit loop
+                    let message = self.divert_final_output(message).await;
+                    self.emit_event(agents_core::events::AgentEvent::AgentCompleted(
+                        agents_core::events::AgentCompletedEvent {
+                            metadata: self.create_event_metadata(),
+                            agent_name: self.descriptor.name.clone(),
+                            duration_ms: start_time.elapsed().as_millis() as u64,
+                            response_preview: self.truncate_message(&message),
+                            response: self.get_full_message_text(&message),
+                        },
+                    ));
+
+                    self.append_history(message.clone());
+                    return Ok(message);
+                }
+                PlannerAction::CallTool { tool_name, payload } => {
+                    // Add AI's decision to call tool to history
+                    // This is needed for OpenAI's API which expects:
+                    // 1. Assistant message with tool call
+                    // 2. Tool message with result
+                    // Using System role to avoid LLM echoing this back as a response
+                    self.append_history(tool_call_message(&tool_name, &payload));
+
+                    if let Some(tool) = tools.get(&tool_name).cloned() {
+                        if self.tool_argument_validation == ToolArgumentValidation::Strict {
+                            if let Err(errors) = tool.schema().parameters.validate(&payload) {
+                                self.reject_tool_arguments(&tool_name, &errors);
+                                // Loop continues - LLM will retry with corrected arguments
+                                continue;
+                            }
+                        }
+
+                        // Ask the policy engine, then all middleware, for interrupts
+                        // before executing tool
+                        let call_id = format!("call_{}", uuid::Uuid::new_v4());
+                        let mut interrupt = None;
+                        if let Some(policy) = &self.policy_provider {
+                            match self
+                                .check_tool_policy(policy, &tool_name, &payload, thread_id, options)
+                                .await
+                            {
+                                PolicyDecision::Allow => {}
+                                PolicyDecision::Deny { reason } => {
+                                    self.deny_tool_call(&tool_name, reason);
+                                    // Loop continues - LLM sees the denial
+                                    continue;
+                                }
+                                PolicyDecision::RequireApproval { note } => {
+                                    interrupt = Some(AgentInterrupt::HumanInLoop(
+                                        agents_core::hitl::HitlInterrupt::new(
+                                            &tool_name,
+                                            payload.clone(),
+                                            &call_id,
+                                            note,
+                                        ),
+                                    ));
+                                }
+                            }
+                        }
+                        for middleware in &self.middlewares {
+                            if interrupt.is_some() {
+                                break;
+                            }
+                            interrupt = middleware
+                                .before_tool_execution(&tool_name, &payload, &call_id)
+                                .await?;
+                        }
+                        if let Some(interrupt) = interrupt {
+                            // Save interrupt to state
+                            {
+                                let state = self.run_state();
+                                let mut state_guard = state.write().map_err(|_| {
+                                    anyhow::anyhow!("Failed to acquire write lock on state")
+                                })?;
+                                state_guard.add_interrupt(interrupt.clone());
+                            }
+
+                            // Persist state with checkpointer
+                            if self.checkpointer.is_some() {
+                                let state_clone = self.checkpoint_state()?;
+                                self.persist_state(
+                                    &thread_id.cloned().unwrap_or_default(),
+                                    &state_clone,
+                                )
+                                .await?;
+                            }
+
+                            // Return interrupt message - execution pauses here
+                            let interrupt_message = AgentMessage {
+                                role: MessageRole::System,
+                                content: MessageContent::Text(format!(
+                                    "⏸️ Execution paused: Tool '{}' requires human approval",
+                                    tool_name
+                                )),
+                                metadata: None,
+                            };
+                            self.append_history(interrupt_message.clone());
+                            return Ok(interrupt_message);
+                        }
+
+                        // No interrupt - execute tool
+                        let tool_start_time = std::time::Instant::now();
+
+                        self.emit_event(agents_core::events::AgentEvent::ToolStarted(
+                            agents_core::events::ToolStartedEvent {
+                                metadata: self.create_event_metadata(),
+                                tool_name: tool_name.clone(),
+                                input_summary: self.summarize_payload(&payload),
+                            },
+                        ));
+
+                        if self.log_tool_payloads {
+                            tracing::warn!(
+                                "⚙️ EXECUTING TOOL: {} with payload: {}",
+                                tool_name,
+                                serde_json::to_string(&payload)
+                                    .unwrap_or_else(|_| "invalid json".to_string())
+                            );
+                        } else {
+                            tracing::warn!("⚙️ EXECUTING TOOL: {}", tool_name);
+                        }
+
+                        let (result, attempts) = Box::pin(
+                            self.execute_tool_with_retries(&tool, &tool_name, &payload, priority),
+                        )
+                        .await;
+
+                        let duration = tool_start_time.elapsed();
+                        match result {
+                            Ok(tool_result_message) => {
+                                let content_preview = match &tool_result_message.content {
+                                    MessageContent::Text(t) => {
+                                        if t.chars().count() > 100 {
+                                            format!("{:.100}... ({} chars)", t, t.chars().count())
+                                        } else {
+                                            t.clone()
+                                        }
+                                    }
+                                    MessageContent::Json(v) => {
+                                        format!("JSON: {} bytes", v.to_string().len())
+                                    }
+                                    MessageContent::Image { .. } => {
+                                        tool_result_message.content.to_text()
+                                    }
+                                };
+
+                                self.emit_event(agents_core::events::AgentEvent::ToolCompleted(
+                                    agents_core::events::ToolCompletedEvent {
+                                        metadata: self.create_event_metadata(),
+                                        tool_name: tool_name.clone(),
+                                        duration_ms: duration.as_millis() as u64,
+                                        result_summary: content_preview.clone(),
+                                        success: true,
+                                    },
+                                ));
+
+                                tracing::warn!(
+                                    "✅ TOOL COMPLETED: {} in {:?} - Result: {}",
+                                    tool_name,
+                                    duration,
+                                    content_preview
+                                );
+
+                                // Add tool result to history and continue ReAct loop
+                                provenance.record(&tool_name, &tool_result_message);
+                                self.record_step_result(&tool_result_message);
+                                self.append_history(tool_result_message);
+                                // Loop continues - LLM will see tool result and decide next action
+                            }
+                            Err(e) => {
+                                self.emit_event(agents_core::events::AgentEvent::ToolFailed(
+                                    agents_core::events::ToolFailedEvent {
+                                        metadata: self.create_event_metadata(),
+                                        tool_name: tool_name.clone(),
+                                        duration_ms: duration.as_millis() as u64,
+                                        error_message: e.to_string(),
+                                        is_recoverable: true,
+                                        retry_count: attempts - 1,
+                                    },
+                                ));
+
+                                tracing::error!(
+                                    "❌ TOOL FAILED: {} in {:?} - Error: {}",
+                                    tool_name,
+                                    duration,
+                                    e
+                                );
+
+                                // Add error to history and continue - let LLM handle the error
+                                let text = match &self.tool_retry {
+                                    Some(retries) => {
+                                        retries.failure_text(&tool_name, attempts, &e.to_string())
+                                    }
+                                    None => format!("Error executing {}: {}", tool_name, e),
+                                };
+                                let error_message = AgentMessage {
+                                    role: MessageRole::Tool,
+                                    content: MessageContent::Text(text),
+                                    metadata: None,
+                                };
+                                self.record_step_result(&error_message);
+                                self.append_history(error_message);
+                                // Loop continues - LLM will see error and decide how to handle it
+                            }
+                        }
+                    } else {
+                        // Tool not found - add error to history and continue
+                        tracing::warn!("⚠️ Tool '{}' not found", tool_name);
+                        let error_message = AgentMessage {
+                            role: MessageRole::Tool,
+                            content: MessageContent::Text(format!(
+                                "Tool '{}' not found. Available tools: {}",
+                                tool_name,
+                                tools
+                                    .keys()
+                                    .map(|k| k.as_str())
+                                    .collect::<Vec<_>>()
+                                    .join(", ")
+                            )),
+                            metadata: None,
+                        };
+                        self.record_step_result(&error_message);
+                        self.append_history(error_message);
+                        // Loop continues - LLM will see error and try something else
+                    }
+                }
+                PlannerAction::Terminate => {
+                    // LLM decided to terminate - exit loop
+                    tracing::debug!("🛑 Agent terminated");
+                    let message = AgentMessage {
+                        role: MessageRole::Agent,
+                        content: MessageContent::Text("Task completed.".into()),
+                        metadata: None,
+                    };
+                    self.append_history(message.clone());
+                    return Ok(message);
+                }
+            }
+        }
+    }
+
+    /// Prompt the model and return its response stream; `handle_message_stream`
+    /// runs this in the deterministic scope when one is configured.
+    async fn start_message_stream(
+        &self,
+        input: AgentMessage,
+        _state: Arc<AgentStateSnapshot>,
+        thread_id: Option<&ThreadId>,
+    ) -> anyhow::Result<agents_core::agent::AgentStream> {
+        use crate::planner::LlmBackedPlanner;
+        use agents_core::llm::{LlmRequest, StreamChunk};
+        use futures::StreamExt;
+
+        // Translated responses only exist once the run is complete
+        if self.translation.is_some() {
+            let response = self
+                .handle_message_internal(
+                    input,
+                    _state,
+                    thread_id,
+                    RunPriority::Normal,
+                    &RunOptions::default(),
+                )
+                .await?;
+            return Ok(Box::pin(futures::stream::once(async move {
+                Ok(StreamChunk::Done { message: response })
+            })));
+        }
+
+        let run = self.run_gate.enter()?;
+        let slot = self.concurrency.acquire_run(RunPriority::Normal).await?;
+
+        // Add input to history
+        let input = match &self.offload {
+            Some(offload) => offload.offload("user", input).await,
+            None => input,
+        };
+        self.append_history(input.clone());
+        self.refresh_provider_tools().await;
+
+        // Build the request similar to handle_message_internal
+        let mut request = ModelRequest::new(&self.instructions, self.request_history());
+        let tools = self.collect_tools();
+
+        // Apply middleware modifications
+        for middleware in &self.middlewares {
+            let mut ctx = MiddlewareContext::with_request(&mut request, self.run_state().clone());
+            middleware.modify_model_request(&mut ctx).await?;
+        }
+
+        // Convert ModelRequest to LlmRequest and add tools
+        let tool_schemas = self.tool_schemas_for_turn(&tools, &request.messages).await;
+        if let Some(examples) = self.tool_examples.render(&tool_schemas) {
+            request.append_prompt(&examples);
+        }
+        let llm_request = LlmRequest {
+            system_prompt: request.system_prompt,
+            messages: request.messages,
+            tools: tool_schemas,
+            temperature: None,
+            seed: agents_core::determinism::current_seed(),
+        };
+
+        // Try to get the underlying LLM model for streaming
+        let planner_any = self.planner.as_any();
+
+        if let Some(llm_planner) = planner_any.downcast_ref::<LlmBackedPlanner>() {
+            // We have an LlmBackedPlanner, use its model for streaming
+            let model = llm_planner.model().clone();
+            let stream = model.generate_stream(llm_request).await?;
+
+            // Wrap stream to emit events to broadcasters
+            let agent_name = self.descriptor.name.clone();
+            let event_dispatcher = self.event_dispatcher.clone();
+            let event_outbox = self.event_outbox.clone();
+            // Shared by all events of the streamed run
+            let run_correlation_id = agents_core::correlation::current_correlation_id()
+                .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+            let correlation = run_correlation_id.clone();
+            let run_thread = thread_id.cloned().unwrap_or_default();
+            let wrapped_stream = stream.then(move |chunk_result| {
+                // The run counts as in flight until the stream is dropped
+                let _run = (&run, &slot);
+                let dispatcher = event_dispatcher.clone();
+                let outbox = event_outbox.clone();
+                let name = agent_name.clone();
+                let correlation_id = correlation.clone();
+                let thread_id = run_thread.clone();
+
+                async move {
+                    match &chunk_result {
+                        Ok(StreamChunk::ToolCallStarted {
+                            name: tool_name,
+                            partial_args,
+                            ..
+                        }) => {
+                            if let Some(ref dispatcher) = dispatcher {
+                                let event = agents_core::events::AgentEvent::ToolCallStarted(
+                                    agents_core::events::ToolCallStartedEvent {
+                                        metadata: agents_core::events::EventMetadata::new(
+                                            "default".to_string(),
+                                            correlation_id.clone(),
+                                            None,
+                                        ),
+                                        agent_name: name.clone(),
+                                        tool_name: tool_name.clone(),
+                                        partial_args: partial_args.clone(),
+                                    },
+                                );
+                                dispatcher.dispatch(event).await;
+                            }
+                        }
+                        Ok(StreamChunk::Done { message }) => {
+                            // Emit agent completed event
+                            if let Some(ref dispatcher) = dispatcher {
+                                let full_text = message.content.to_text();
+
+                                let preview = if full_text.len() > 100 {
+                                    format!("{}...", &full_text[..100])
+                                } else {
+                                    full_text.clone()
+                                };
+
+                                let event = agents_core::events::AgentEvent::AgentCompleted(
+                                    agents_core::events::AgentCompletedEvent {
+                                        metadata: agents_core::events::EventMetadata::new(
+                                            "default".to_string(),
+                                            correlation_id.clone(),
+                                            None,
+                                        ),
+                                        agent_name: name.clone(),
+                                        duration_ms: 0, // Duration not tracked in streaming mode
+                                        response_preview: preview,
+                                        response: full_text,
+                                    },
+                                );
+                                dispatcher.dispatch(event).await;
+
+                                // Streamed runs never checkpoint; hand staged events over
+                                if let Some(outbox) = outbox {
+                                    let events = dispatcher.take_staged_for(&correlation_id);
+                                    if let Err(e) =
+                                        outbox.enqueue_events(&thread_id, &events).await
+                                    {
+                                        tracing::warn!(error = %e, "Failed to write events to the outbox");
+                                    }
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                    chunk_result
+                }
+            });
+
+            let stream: agents_core::agent::AgentStream = Box::pin(wrapped_stream);
+            let stream = match &self.stream_coalescing {
+                Some(coalescing) => coalescing.apply(stream),
+                None => stream,
+            };
+            Ok(self.stream_tee.tee(
+                stream,
+                run_correlation_id,
+                self.descriptor.name.clone(),
+                self.event_dispatcher.clone(),
+            ))
+        } else {
+            // Fallback to non-streaming
+            drop(slot);
+            drop(run);
+            let response = self
+                .handle_message_internal(
+                    input,
+                    _state,
+                    thread_id,
+                    RunPriority::Normal,
+                    &RunOptions::default(),
+                )
+                .await?;
+            Ok(Box::pin(futures::stream::once(async move {
+                Ok(StreamChunk::Done { message: response })
+            })))
+        }
+    }
+}
+
+#[async_trait]
+impl AgentHandle for DeepAgent {
+    async fn describe(&self) -> AgentDescriptor {
+        let mut descriptor = self.descriptor.clone();
+        let mut tools: Vec<String> = self.collect_tools().into_keys().collect();
+        tools.sort();
+        descriptor.capabilities.tools = tools;
+        descriptor
+    }
+
+    async fn handle_message(
+        &self,
+        input: AgentMessage,
+        _state: Arc<AgentStateSnapshot>,
+    ) -> anyhow::Result<AgentMessage> {
+        let _admission = self.admit_run(RunPriority::Normal).await?;
+        let run = self.run_and_persist(input, _state, None);
+        Box::pin(self.in_run_scope(None, run)).await
+    }
+
+    async fn handle_message_stream(
+        &self,
+        input: AgentMessage,
+        state: Arc<AgentStateSnapshot>,
+    ) -> anyhow::Result<agents_core::agent::AgentStream> {
+        let start = self.in_run_scope(None, self.start_message_stream(input, state, None));
+        match &self.deterministic {
+            Some(deterministic) => Box::pin(deterministic.scope(start)).await,
+            None => Box::pin(start).await,
+        }
+    }
+
+    async fn current_interrupt(&self) -> anyhow::Result<Option<AgentInterrupt>> {
+        let state = self.run_state();
+        let state_guard = state
+            .read()
+            .map_err(|_| anyhow::anyhow!("Failed to acquire read lock on state"))?;
+        Ok(state_guard.pending_interrupts.first().cloned())
+    }
+
+    async fn resume_with_approval(
+        &self,
+        action: agents_core::hitl::HitlAction,
+    ) -> anyhow::Result<AgentMessage> {
+        self.resume_with_approval(action).await
+    }
+}
+
+/// Create a deep agent from configuration - matches Python middleware assembly exactly
+///
+/// This function assembles the middleware stack in the same order as the Python SDK:
+/// planning → filesystem → subagents → summarization → prompt caching → optional HITL
+pub fn create_deep_agent_from_config(config: DeepAgentConfig) -> DeepAgent {
+    let state = Arc::new(RwLock::new(AgentStateSnapshot::default()));
+    let history = Arc::new(RwLock::new(Vec::<AgentMessage>::new()));
+
+    // Configured sections are all rendered by the Deep Agent prompt middleware
+    let sectioned = config.prompt_sections.is_some();
+    let mut planning = PlanningMiddleware::new(state.clone());
+    let mut filesystem = FilesystemMiddleware::new(state.clone());
+    if sectioned {
+        planning = planning.without_prompt();
+        filesystem = filesystem.without_prompt();
+    }
+    let planning = Arc::new(planning);
+    let filesystem = Arc::new(filesystem);
+
+    // Build sub-agents from configurations
+    let mut registrations: Vec<SubAgentRegistration> = Vec::new();
+    let mut subagent_models = Vec::new();
+
+    for subagent_config in &config.subagent_configs {
+        // Determine the planner for this sub-agent
+        let sub_planner = if let Some(ref model) = subagent_config.model {
+            // Sub-agent has its own model - wrap it in a planner, tracking its usage
+            // like the parent's
+            let model = match (&config.usage_tracker, &config.token_tracking_config) {
+                (Some(tracker), _) => Arc::new(tracker.share(model.clone()))
+                    as Arc<dyn agents_core::llm::LanguageModel>,
+                (None, Some(tracking)) if tracking.enabled => {
+                    Arc::new(TokenTrackingMiddleware::new(
+                        tracking.clone(),
+                        model.clone(),
+                        config.event_dispatcher.clone(),
+                    ))
+                }
+                _ => model.clone(),
+            };
+            subagent_models.push((subagent_config.name.clone(), model.clone()));
+            Arc::new(LlmBackedPlanner::new(model)) as Arc<dyn PlannerHandle>
+        } else {
+            // Inherit parent's planner
+            config.planner.clone()
+        };
+
+        // Create a DeepAgentConfig for this sub-agent
+        let mut sub_cfg = DeepAgentConfig::new(subagent_config.instructions.clone(), sub_planner)
+            .with_descriptor(
+                AgentDescriptor::new(
+                    subagent_config.name.clone(),
+                    config.descriptor.version.clone(),
+                )
+                .with_description(subagent_config.description.clone())
+                .with_capabilities(config.descriptor.capabilities.clone()),
+            );
+
+        // Inherit max_iterations from parent
+        sub_cfg = sub_cfg.with_max_iterations(config.max_iterations.get());
+
+        // Configure tools
+        if let Some(ref tools) = subagent_config.tools {
+            tracing::debug!(
+                "  - Configuring {} tools for {}",
+                tools.len(),
+                subagent_config.name
+            );
+            for tool in tools {
+                sub_cfg = sub_cfg.with_tool(tool.clone());
+            }
+        }
+
+        // Configure built-in tools
+        if let Some(ref builtin) = subagent_config.builtin_tools {
+            sub_cfg = sub_cfg.with_builtin_tools(builtin.iter().cloned());
+        }
+
+        // Sub-agents should not have their own sub-agents
+        sub_cfg = sub_cfg.with_auto_general_purpose(false);
+
+        // Configure prompt caching
+        sub_cfg = sub_cfg.with_prompt_caching(subagent_config.enable_prompt_caching);
+
+        // Inherit PII sanitization setting from parent
+        sub_cfg = sub_cfg.with_pii_sanitization(config.enable_pii_sanitization);
+
+        // Inherit tool output budgets, retries and tool selection from parent
+        if let Some(ref limits) = config.tool_output {
+            sub_cfg = sub_cfg.with_tool_output_limits(limits.clone());
+        }
+        if let Some(ref retries) = config.tool_retry {
+            sub_cfg = sub_cfg.with_tool_retries(retries.clone());
+        }
+        if let Some(ref selection) = config.tool_selection {
+            sub_cfg = sub_cfg.with_tool_selection(selection.clone());
+        }
+        sub_cfg = sub_cfg.with_tool_examples(config.tool_examples.clone());
+        sub_cfg = sub_cfg.with_tool_argument_validation(config.tool_argument_validation);
+        sub_cfg = sub_cfg.with_tool_conflicts(config.tool_conflicts.clone());
+
+        // Mocked tools stay mocked in sub-agents
+        sub_cfg = sub_cfg.with_tool_mocks(config.tool_mocks.clone());
+
+        // Sub-agent tools see the same shared dependencies
+        sub_cfg = sub_cfg.with_tool_extensions(config.tool_extensions.clone());
+
+        // Tool executions and summaries count towards the parent's usage
+        if let Some(ref tracker) = config.usage_tracker {
+            sub_cfg = sub_cfg.with_usage_tracker(tracker.clone());
+        }
+
+        // Sub-agents answer in the parent's locale and share its clock
+        if let Some(ref locale) = config.locale {
+            sub_cfg = sub_cfg.with_locale_config(locale.clone());
+        }
+        if let Some(ref time_context) = config.time_context {
+            sub_cfg = sub_cfg.with_time_context(time_context.clone());
+        }
+        if let Some(ref compression) = config.prompt_compression {
+            sub_cfg = sub_cfg.with_prompt_compression(compression.clone());
+        }
+
+        // Build the sub-agent recursively
+        let sub_agent = create_deep_agent_from_config(sub_cfg);
+
+        // Register the sub-agent
+        registrations.push(SubAgentRegistration {
+            descriptor: SubAgentDescriptor {
+                name: subagent_config.name.clone(),
+                description: subagent_config.description.clone(),
+            },
+            agent: Arc::new(sub_agent),
+        });
+
+        tracing::info!("=> Registered sub-agent: {}", subagent_config.name);
+    }
+
+    tracing::info!("=> Total sub-agents registered: {}", registrations.len());
+
+    // Optionally inject a general-purpose subagent
+    if config.auto_general_purpose {
+        let has_gp = registrations
+            .iter()
+            .any(|r| r.descriptor.name == "general-purpose");
+        if !has_gp {
+            // Create a subagent with inherited planner/tools and same instructions
+            let mut sub_cfg =
+                DeepAgentConfig::new(config.instructions.clone(), config.planner.clone())
+                    .with_auto_general_purpose(false)
+                    .with_prompt_caching(config.enable_prompt_caching)
+                    .with_pii_sanitization(config.enable_pii_sanitization)
+                    .with_max_iterations(config.max_iterations.get())
+                    .with_descriptor(
+                        AgentDescriptor::new("general-purpose", config.descriptor.version.clone())
+                            .with_description("Default reasoning agent")
+                            .with_capabilities(config.descriptor.capabilities.clone()),
+                    );
+            if let Some(ref selected) = config.builtin_tools {
+                sub_cfg = sub_cfg.with_builtin_tools(selected.iter().cloned());
+            }
+            if let Some(ref sum) = config.summarization {
+                sub_cfg = sub_cfg.with_summarization(sum.clone());
+            }
+            if let Some(ref limits) = config.tool_output {
+                sub_cfg = sub_cfg.with_tool_output_limits(limits.clone());
+            }
+            if let Some(ref retries) = config.tool_retry {
+                sub_cfg = sub_cfg.with_tool_retries(retries.clone());
+            }
+            if let Some(ref selection) = config.tool_selection {
+                sub_cfg = sub_cfg.with_tool_selection(selection.clone());
+            }
+            sub_cfg = sub_cfg.with_tool_examples(config.tool_examples.clone());
+            sub_cfg = sub_cfg.with_tool_argument_validation(config.tool_argument_validation);
+            sub_cfg = sub_cfg.with_tool_conflicts(config.tool_conflicts.clone());
+            sub_cfg = sub_cfg.with_tool_mocks(config.tool_mocks.clone());
+            sub_cfg = sub_cfg.with_tool_extensions(config.tool_extensions.clone());
+            if let Some(ref tracker) = config.usage_tracker {
+                sub_cfg = sub_cfg.with_usage_tracker(tracker.clone());
+            }
+            if let Some(ref locale) = config.locale {
+                sub_cfg = sub_cfg.with_locale_config(locale.clone());
+            }
+            if let Some(ref time_context) = config.time_context {
+                sub_cfg = sub_cfg.with_time_context(time_context.clone());
+            }
+            if let Some(ref compression) = config.prompt_compression {
+                sub_cfg = sub_cfg.with_prompt_compression(compression.clone());
+            }
+            for t in &config.tools {
+                sub_cfg = sub_cfg.with_tool(t.clone());
+            }
+            for provider in &config.tool_providers {
+                sub_cfg = sub_cfg.with_tool_provider(provider.clone());
+            }
+
+            let gp = create_deep_agent_from_config(sub_cfg);
+            registrations.push(SubAgentRegistration {
+                descriptor: SubAgentDescriptor {
+                    name: "general-purpose".into(),
+                    description: "Default reasoning agent".into(),
+                },
+                agent: Arc::new(gp),
+            });
+        }
+    }
+
+    let sub_agent_names: Vec<String> = registrations
+        .iter()
+        .map(|r| r.descriptor.name.clone())
+        .collect();
+    let subagents = registrations.clone();
+    let mut subagent =
+        SubAgentMiddleware::new_with_events(registrations, config.event_dispatcher.clone())
+            .with_prompt_format(config.prompt_format);
+    let sub_agents_prompt = subagent.system_prompt();
+    if sectioned {
+        subagent = subagent.without_prompt();
+    }
+    let subagent = Arc::new(subagent);
+
+    // Create Deep Agent prompt middleware - use override if custom system prompt is set
+    // Otherwise use the configured prompt format (JSON or TOON)
+    let mut deep_agent_prompt = if let Some(ref custom_prompt) = config.custom_system_prompt {
+        DeepAgentPromptMiddleware::with_override(custom_prompt.clone())
+    } else {
+        DeepAgentPromptMiddleware::with_format(config.instructions.clone(), config.prompt_format)
+    };
+    if let Some(ref locale) = config.locale {
+        deep_agent_prompt = deep_agent_prompt.with_locale(locale.clone());
+    }
+    if let Some(ref sections) = config.prompt_sections {
+        deep_agent_prompt = deep_agent_prompt.with_sections(sections.clone(), sub_agents_prompt);
+    }
+    let deep_agent_prompt: Arc<dyn AgentMiddleware> = Arc::new(deep_agent_prompt);
+    let summarization = config.summarization.as_ref().map(|cfg| {
+        Arc::new(SummarizationMiddleware::new(
+            cfg.messages_to_keep,
+            cfg.summary_note.clone(),
+        ))
+    });
+    let hitl = if config.tool_interrupts.is_empty() {
+        None
+    } else {
+        // Validate that checkpointer is configured when HITL is enabled
+        if config.checkpointer.is_none() {
+            tracing::error!(
+                "⚠️ HITL middleware requires a checkpointer to persist interrupt state. \
+                 HITL will be disabled. Please configure a checkpointer to enable HITL."
+            );
+            None
+        } else {
+            tracing::info!("🔒 HITL enabled for {} tools", config.tool_interrupts.len());
+            Some(Arc::new(HumanInLoopMiddleware::new(
+                config.tool_interrupts.clone(),
+            )))
+        }
+    };
+
+    // Assemble middleware stack with Deep Agent prompt for automatic tool usage
+    // Order: base → deep agent prompt → planning → filesystem → subagents → time → user profile
+    //        → summarization → compression → caching → HITL
+    let mut middlewares: Vec<Arc<dyn AgentMiddleware>> = Vec::new();
+    if !sectioned {
+        middlewares.push(Arc::new(BaseSystemPromptMiddleware));
+    }
+    middlewares.extend([deep_agent_prompt, planning, filesystem, subagent]);
+    if let Some(ref time_context) = config.time_context {
+        middlewares.push(Arc::new(TimeContextMiddleware::new(time_context.clone())));
+    }
+    let memory_policy = config
+        .memory_policy
+        .clone()
+        .map(|policy| Arc::new(policy.with_event_dispatcher(config.event_dispatcher.clone())));
+    match (&config.user_profile, &config.user_memory) {
+        (Some(profile), _) => {
+            let mut user_profile = UserProfileMiddleware::new(profile.clone());
+            if let Some(policy) = &memory_policy {
+                user_profile = user_profile.with_policy(policy.clone());
+            }
+            middlewares.push(Arc::new(user_profile));
+        }
+        (None, Some(_)) => middlewares.push(Arc::new(UserProfileMiddleware::prompt_only())),
+        (None, None) => {}
+    }
+    if let Some(ref summary) = summarization {
+        middlewares.push(summary.clone());
+    }
+    if let Some(ref compression) = config.prompt_compression {
+        middlewares.push(Arc::new(PromptCompressionMiddleware::new(
+            compression.clone(),
+        )));
+    }
+    if config.enable_prompt_caching {
+        middlewares.push(Arc::new(AnthropicPromptCachingMiddleware::with_defaults()));
+    }
+    if let Some(ref hitl_mw) = hitl {
+        middlewares.push(hitl_mw.clone());
+    }
+
+    let journal = Arc::new(RunJournal::default());
+    if let Some(ref dispatcher) = config.event_dispatcher {
+        dispatcher.add_broadcaster(journal.clone());
+    }
+    match (&config.event_outbox, &config.event_dispatcher) {
+        // Events now reach broadcasters through the outbox relay
+        (Some(_), Some(dispatcher)) => dispatcher.enable_staging(),
+        (Some(_), None) => tracing::warn!(
+            "An event outbox is configured without an event dispatcher; no events will be recorded"
+        ),
+        _ => {}
+    }
+
+    let mut descriptor = config.descriptor;
+    descriptor.capabilities.streaming = true;
+    descriptor.capabilities.human_in_the_loop = hitl.is_some();
+    descriptor.capabilities.sub_agents = sub_agent_names;
+    if let Some(ref locale) = config.locale {
+        descriptor.capabilities = descriptor
+            .capabilities
+            .with_language(locale.default_locale.clone());
+    }
+
+    DeepAgent {
+        descriptor,
+        instructions: config.instructions,
+        planner: config.planner,
+        middlewares,
+        base_tools: config.tools,
+        tool_providers: config.tool_providers,
+        subagents,
+        subagent_models,
+        provider_tools: RwLock::new(Vec::new()),
+        state,
+        history,
+        _summarization: summarization,
+        _hitl: hitl,
+        builtin_tools: config.builtin_tools,
+        checkpointer: config.checkpointer,
+        event_dispatcher: config.event_dispatcher,
+        enable_pii_sanitization: config.enable_pii_sanitization,
+        max_iterations: config.max_iterations,
+        profiling: config.profiling,
+        step_recording: config.step_recording,
+        lazy_file_loading: config.lazy_file_loading,
+        tool_output: match (config.tool_output, &config.usage_tracker) {
+            (Some(limits), Some(tracker)) => Some(limits.tracked_by(tracker)),
+            (limits, _) => limits,
+        },
+        profile: config.profile,
+        disabled_tools: config.disabled_tools,
+        tool_mocks: config.tool_mocks,
+        log_tool_payloads: config.log_tool_payloads,
+        tool_retry: config.tool_retry,
+        citations: config.citations,
+        tool_selection: config.tool_selection,
+        tool_examples: ToolExamplesConfig {
+            format: config.tool_examples.format.or(Some(config.prompt_format)),
+            ..config.tool_examples
+        },
+        tool_argument_validation: config.tool_argument_validation,
+        tool_conflicts: config.tool_conflicts,
+        prompt_format: config.prompt_format,
+        tool_extensions: config.tool_extensions,
+        locale: config.locale,
+        deterministic: config.deterministic,
+        journal,
+        offload: match (config.offload, &config.usage_tracker) {
+            (Some(offload), Some(tracker)) => Some(offload.tracked_by(tracker)),
+            (offload, _) => offload,
+        },
+        output_sink: config.output_sink,
+        translation: config.translation,
+        stream_coalescing: config.stream_coalescing,
+        stream_tee: StreamTee::default(),
+        run_gate: Arc::new(RunGate::default()),
+        concurrency: config
+            .concurrency
+            .map(ConcurrencyLimits::new)
+            .unwrap_or_default(),
+        thread_lock: config.thread_lock,
+        experiment: config.experiment,
+        approvals: config.approvals,
+        policy_provider: config.policy_provider,
+        dead_letters: config.dead_letters,
+        conversation_index: config.conversation_index,
+        thread_titles: config.thread_titles,
+        graph_memory: config.graph_memory,
+        user_memory: config.user_memory,
+        memory_policy,
+        state_size_limits: config.state_size_limits,
+        state_sizes: StateSizeTracker::default(),
+        quotas: config.quotas,
+        event_outbox: config.event_outbox,
+        usage_tracker: config.usage_tracker,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::tool_retry::ToolRetryPolicy;
+    use crate::test_support::{ReplyPlanner, SlowPlanner};
+    use agents_core::agent::PlannerDecision;
+    use agents_core::events::{AgentEvent, EventBroadcaster, EventDispatcher};
+    use agents_core::outbox::{InMemoryEventOutbox, OutboxRelay};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<&'static str>>);
+
+    #[async_trait]
+    impl EventBroadcaster for Recorder {
+        fn id(&self) -> &str {
+            "recorder"
+        }
+
+        async fn broadcast(&self, event: &AgentEvent) -> anyhow::Result<()> {
+            self.0.lock().unwrap().push(event.event_type_name());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn outbox_events_are_saved_with_the_state_and_relayed() {
+        let outbox = Arc::new(InMemoryEventOutbox::new());
+        let recorder = Arc::new(Recorder::default());
+        let dispatcher = Arc::new(EventDispatcher::new());
+        dispatcher.add_broadcaster(recorder.clone());
+
+        let agent = create_deep_agent_from_config(
+            DeepAgentConfig::new("assist", Arc::new(ReplyPlanner("ok")))
+                .with_event_dispatcher(dispatcher.clone())
+                .with_event_outbox(outbox.clone()),
+        );
+        let thread: ThreadId = "thread-1".into();
+        agent
+            .handle_message_for_thread(&thread, "hi")
+            .await
+            .unwrap();
+        dispatcher.flush().await;
+
+        assert!(recorder.0.lock().unwrap().is_empty());
+        assert!(outbox.load_state(&thread).await.unwrap().is_some());
+        let pending = outbox.undelivered();
+        assert!(pending.iter().all(|record| record.thread_id == thread));
+        assert!(pending
+            .iter()
+            .any(|record| matches!(record.event, AgentEvent::AgentStarted(_))));
+
+        let stats = OutboxRelay::new(outbox.clone(), dispatcher)
+            .relay_once()
+            .await
+            .unwrap();
+        assert_eq!(stats.delivered, pending.len());
+        assert!(recorder.0.lock().unwrap().contains(&"agent_started"));
+        assert!(outbox.undelivered().is_empty());
+    }
+
+    #[tokio::test]
+    async fn outbox_events_stay_with_the_run_and_thread_that_staged_them() {
+        let outbox = Arc::new(InMemoryEventOutbox::new());
+        let dispatcher = Arc::new(EventDispatcher::new());
+        let agent = || {
+            Arc::new(create_deep_agent_from_config(
+                DeepAgentConfig::new(
+                    "assist",
+                    Arc::new(SlowPlanner::answering(std::time::Duration::from_millis(30))),
+                )
+                .with_event_dispatcher(dispatcher.clone())
+                .with_event_outbox(outbox.clone()),
+            ))
+        };
+
+        // Two agents sharing the dispatcher stage events at the same time
+        let runs = ["thread-a", "thread-b"].map(|thread| {
+            let agent = agent();
+            tokio::spawn(async move {
+                agent
+                    .handle_message_for_thread(&thread.to_string(), thread)
+                    .await
+            })
+        });
+        for run in runs {
+            run.await.unwrap().unwrap();
+        }
+
+        let pending = outbox.undelivered();
+        for thread in ["thread-a", "thread-b"] {
+            let events: Vec<&AgentEvent> = pending
+                .iter()
+                .filter(|record| record.thread_id == thread)
+                .map(|record| &record.event)
+                .collect();
+            let started: Vec<&str> = events
+                .iter()
+                .filter_map(|event| match event {
+                    AgentEvent::AgentStarted(started) => Some(started.message_preview.as_str()),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(started, vec![thread]);
+            let correlation_ids: HashSet<String> = events
+                .iter()
+                .map(|event| event.metadata().correlation_id.clone())
+                .collect();
+            assert_eq!(correlation_ids.len(), 1);
+        }
+        assert_eq!(
+            pending
+                .iter()
+                .filter(|record| record.thread_id == ThreadId::default())
+                .count(),
+            0
+        );
+    }
+
+    /// Answers every prompt with "streamed".
+    struct StreamedModel;
+
+    #[async_trait]
+    impl agents_core::llm::LanguageModel for StreamedModel {
+        async fn generate(
+            &self,
+            _request: agents_core::llm::LlmRequest,
+        ) -> anyhow::Result<agents_core::llm::LlmResponse> {
+            Ok(agents_core::llm::LlmResponse::new(AgentMessage {
+                role: MessageRole::Agent,
+                content: MessageContent::Text("streamed".into()),
+                metadata: None,
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn runs_without_a_thread_commit_state_and_events_to_the_same_thread() {
+        use futures::StreamExt;
+
+        let outbox = Arc::new(InMemoryEventOutbox::new());
+        let agent = create_deep_agent_from_config(
+            DeepAgentConfig::new(
+                "assist",
+                Arc::new(crate::planner::LlmBackedPlanner::new(Arc::new(
+                    StreamedModel,
+                ))),
+            )
+            .with_event_dispatcher(Arc::new(EventDispatcher::new()))
+            .with_event_outbox(outbox.clone()),
+        );
+
+        let user = |text: &str| AgentMessage {
+            role: MessageRole::User,
+            content: MessageContent::Text(text.into()),
+            metadata: None,
+        };
+        let state = Arc::new(AgentStateSnapshot::default());
+        AgentHandle::handle_message(&agent, user("hi"), state.clone())
+            .await
+            .unwrap();
+        let mut stream = agent
+            .handle_message_stream(user("stream it"), state)
+            .await
+            .unwrap();
+        while stream.next().await.is_some() {}
+
+        assert!(outbox
+            .load_state(&ThreadId::default())
+            .await
+            .unwrap()
+            .is_some());
+        let pending = outbox.undelivered();
+        assert!(pending
+            .iter()
+            .all(|record| record.thread_id == ThreadId::default()));
+        let completed = pending
+            .iter()
+            .filter(|record| matches!(record.event, AgentEvent::AgentCompleted(_)))
+            .count();
+        assert_eq!(completed, 2);
+    }
+
+    /// Reads `report.md`, then answers with what it read.
+    struct ReadReportPlanner;
+
+    #[async_trait]
+    impl PlannerHandle for ReadReportPlanner {
+        async fn plan(
+            &self,
+            context: PlannerContext,
+            _state: Arc<AgentStateSnapshot>,
+        ) -> anyhow::Result<PlannerDecision> {
+            let next_action = match context.history.last() {
+                Some(last) if last.role == MessageRole::Tool => PlannerAction::Respond {
+                    message: AgentMessage {
+                        role: MessageRole::Agent,
+                        content: last.content.clone(),
+                        metadata: None,
+                    },
+                },
+                _ => PlannerAction::CallTool {
+                    tool_name: "read_file".into(),
+                    payload: serde_json::json!({ "file_path": "report.md" }),
+                },
+            };
+            Ok(PlannerDecision { next_action })
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    #[tokio::test]
+    async fn lazily_loaded_threads_read_files_on_demand() {
+        use agents_core::persistence::InMemoryCheckpointer;
+
+        let checkpointer = Arc::new(InMemoryCheckpointer::new());
+        let thread: ThreadId = "thread-1".into();
+        let mut saved = AgentStateSnapshot::default();
+        saved
+            .files
+            .insert("report.md".into(), "Q3 revenue grew".into());
+        saved
+            .files
+            .insert("dataset.csv".into(), "x,y\n".repeat(10_000));
+        checkpointer.save_state(&thread, &saved).await.unwrap();
+
+        let agent = create_deep_agent_from_config(
+            DeepAgentConfig::new("assist", Arc::new(ReadReportPlanner))
+                .with_checkpointer(checkpointer.clone())
+                .with_lazy_file_loading(true),
+        );
+        let response = agent
+            .handle_message_for_thread(&thread, "What does the report say?")
+            .await
+            .unwrap();
+        assert!(response.content.to_text().contains("Q3 revenue grew"));
+
+        // Only the file the tool read was loaded into the run's state
+        let state = agent.state.read().unwrap().clone();
+        assert_eq!(state.files.keys().collect::<Vec<_>>(), vec!["report.md"]);
+        assert_eq!(state.file_paths(), vec!["dataset.csv", "report.md"]);
+
+        let saved = checkpointer.load_state(&thread).await.unwrap().unwrap();
+        assert_eq!(saved.files.len(), 2);
+        assert_eq!(saved.files["dataset.csv"].len(), 40_000);
+    }
+
+    #[tokio::test]
+    async fn threads_are_tagged_before_their_first_run() {
+        use agents_core::persistence::InMemoryCheckpointer;
+
+        let agent = create_deep_agent_from_config(
+            DeepAgentConfig::new("assist", Arc::new(ReadReportPlanner))
+                .with_checkpointer(Arc::new(InMemoryCheckpointer::new())),
+        );
+        let thread: ThreadId = "support-7".into();
+        agent
+            .set_thread_metadata(
+                &thread,
+                &ThreadMetadata::new().with_value("channel", "telegram"),
+            )
+            .await
+            .unwrap();
+        agent.tag_thread(&thread, "escalated").await.unwrap();
+
+        let metadata = agent.thread_metadata(&thread).await.unwrap().unwrap();
+        assert_eq!(
+            metadata.get("channel"),
+            Some(&serde_json::json!("telegram"))
+        );
+        assert!(metadata.has_tag("escalated"));
+        assert_eq!(
+            agent.list_threads_by_tag("escalated").await.unwrap(),
+            vec![thread]
+        );
+        assert!(agent.list_threads().await.unwrap().is_empty());
+    }
+
+    /// Answers with the texts of the conversation it was given.
+    struct HistoryPlanner;
+
+    #[async_trait]
+    impl PlannerHandle for HistoryPlanner {
+        async fn plan(
+            &self,
+            context: PlannerContext,
+            _state: Arc<AgentStateSnapshot>,
+        ) -> anyhow::Result<PlannerDecision> {
+            let texts: Vec<String> = context
+                .history
+                .iter()
+                .map(|message| message.content.to_text())
+                .collect();
+            Ok(PlannerDecision {
+                next_action: PlannerAction::Respond {
+                    message: AgentMessage {
+                        role: MessageRole::Agent,
+                        content: MessageContent::Text(texts.join(" | ")),
+                        metadata: None,
                     },
                 },
-            ));
+            })
+        }
 
-            match decision.next_action {
-                PlannerAction::Respond { message } => {
-                    // LLM decided to respond with text - exit loop
-                    self.emit_event(agents_core::events::AgentEvent::AgentCompleted(
-                        agents_core::events::AgentCompletedEvent {
-                            metadata: self.create_event_metadata(),
-                            agent_name: self.descriptor.name.clone(),
-                            duration_ms: start_time.elapsed().as_millis() as u64,
-                            response_preview: self.truncate_message(&message),
-                            response: self.get_full_message_text(&message),
-                        },
-                    ));
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
 
-                    self.append_history(message.clone());
-                    return Ok(message);
-                }
-                PlannerAction::CallTool { tool_name, payload } => {
-                    // Add AI's decision to call tool to history
-                    // This is needed for OpenAI's API which expects:
-                    // 1. Assistant message with tool call
-                    // 2. Tool message with result
-                    // Using System role to avoid LLM echoing this back as a response
-                    let tool_call_message = AgentMessage {
-                        role: MessageRole::System,
-                        content: MessageContent::Text(format!(
-                            "Calling tool: {} with args: {}",
-                            tool_name,
-                            serde_json::to_string(&payload).unwrap_or_default()
-                        )),
-                        metadata: None,
-                    };
-                    self.append_history(tool_call_message);
+    #[tokio::test]
+    async fn seeded_threads_start_with_the_template_conversation() {
+        use agents_core::persistence::InMemoryCheckpointer;
+        use agents_core::state::TodoItem;
 
-                    if let Some(tool) = tools.get(&tool_name).cloned() {
-                        // Check all middleware for interrupts before executing tool
-                        let call_id = format!("call_{}", uuid::Uuid::new_v4());
-                        for middleware in &self.middlewares {
-                            if let Some(interrupt) = middleware
-                                .before_tool_execution(&tool_name, &payload, &call_id)
-                                .await?
-                            {
-                                // Save interrupt to state
-                                {
-                                    let mut state_guard = self.state.write().map_err(|_| {
-                                        anyhow::anyhow!("Failed to acquire write lock on state")
-                                    })?;
-                                    state_guard.add_interrupt(interrupt.clone());
-                                }
+        let checkpointer = Arc::new(InMemoryCheckpointer::new());
+        let agent = create_deep_agent_from_config(
+            DeepAgentConfig::new("assist", Arc::new(HistoryPlanner))
+                .with_checkpointer(checkpointer.clone()),
+        );
+        let thread: ThreadId = "onboarding-1".into();
+        let template = ThreadTemplate::new()
+            .with_user_message("Where do I start?")
+            .with_agent_message("Connect a data source.")
+            .with_todo(TodoItem::pending("Connect a data source"))
+            .with_tag("onboarding");
+        agent.seed_thread(&thread, &template).await.unwrap();
 
-                                // Persist state with checkpointer
-                                if let Some(checkpointer) = &self.checkpointer {
-                                    let state_clone = self
-                                        .state
-                                        .read()
-                                        .map_err(|_| {
-                                            anyhow::anyhow!("Failed to acquire read lock on state")
-                                        })?
-                                        .clone();
-                                    checkpointer
-                                        .save_state(&ThreadId::default(), &state_clone)
-                                        .await?;
-                                }
+        let response = agent
+            .handle_message_for_thread(&thread, "It's Postgres")
+            .await
+            .unwrap();
+        assert_eq!(
+            response.content.to_text(),
+            "Where do I start? | Connect a data source. | It's Postgres"
+        );
+        let saved = checkpointer.load_state(&thread).await.unwrap().unwrap();
+        assert_eq!(saved.todos.len(), 1);
+        assert_eq!(saved.preloaded_messages.len(), 2);
+        assert_eq!(
+            agent.list_threads_by_tag("onboarding").await.unwrap(),
+            vec![thread.clone()]
+        );
 
-                                // Return interrupt message - execution pauses here
-                                let interrupt_message = AgentMessage {
-                                    role: MessageRole::System,
-                                    content: MessageContent::Text(format!(
-                                        "⏸️ Execution paused: Tool '{}' requires human approval",
-                                        tool_name
-                                    )),
-                                    metadata: None,
-                                };
-                                self.append_history(interrupt_message.clone());
-                                return Ok(interrupt_message);
-                            }
-                        }
+        let error = agent.seed_thread(&thread, &template).await.unwrap_err();
+        assert!(error.to_string().contains("already has state"));
+    }
 
-                        // No interrupt - execute tool
-                        let tool_start_time = std::time::Instant::now();
+    #[tokio::test]
+    async fn checkpointed_turns_are_searchable_until_the_thread_is_deleted() {
+        use agents_core::persistence::InMemoryCheckpointer;
+        use agents_core::search::InMemoryConversationIndex;
 
-                        self.emit_event(agents_core::events::AgentEvent::ToolStarted(
-                            agents_core::events::ToolStartedEvent {
-                                metadata: self.create_event_metadata(),
-                                tool_name: tool_name.clone(),
-                                input_summary: self.summarize_payload(&payload),
-                            },
-                        ));
+        let agent = create_deep_agent_from_config(
+            DeepAgentConfig::new("assist", Arc::new(ReplyPlanner("ok")))
+                .with_checkpointer(Arc::new(InMemoryCheckpointer::new()))
+                .with_conversation_index(Arc::new(InMemoryConversationIndex::new())),
+        );
+        let thread: ThreadId = "support-9".into();
+        agent
+            .handle_message_for_thread(&thread, "My parcel to Dubai is late")
+            .await
+            .unwrap();
+        agent
+            .handle_message_for_thread(&"support-10".into(), "Change my password")
+            .await
+            .unwrap();
 
-                        tracing::warn!(
-                            "⚙️ EXECUTING TOOL: {} with payload: {}",
-                            tool_name,
-                            serde_json::to_string(&payload)
-                                .unwrap_or_else(|_| "invalid json".to_string())
-                        );
+        let hits = agent.search_threads("dubai parcel", 10).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].thread_id, thread);
+        assert_eq!(hits[0].role, MessageRole::User);
+        // The replies are indexed too
+        assert_eq!(agent.search_threads("ok", 10).await.unwrap().len(), 2);
 
-                        let result = self
-                            .execute_tool(tool.clone(), tool_name.clone(), payload.clone())
-                            .await;
+        agent.delete_thread(&thread).await.unwrap();
+        assert!(agent.search_threads("dubai", 10).await.unwrap().is_empty());
+    }
 
-                        let duration = tool_start_time.elapsed();
-                        match result {
-                            Ok(tool_result_message) => {
-                                let content_preview = match &tool_result_message.content {
-                                    MessageContent::Text(t) => {
-                                        if t.chars().count() > 100 {
-                                            format!("{:.100}... ({} chars)", t, t.chars().count())
-                                        } else {
-                                            t.clone()
-                                        }
-                                    }
-                                    MessageContent::Json(v) => {
-                                        format!("JSON: {} bytes", v.to_string().len())
-                                    }
-                                };
+    /// Names every conversation "Late parcel".
+    struct TitleModel;
 
-                                self.emit_event(agents_core::events::AgentEvent::ToolCompleted(
-                                    agents_core::events::ToolCompletedEvent {
-                                        metadata: self.create_event_metadata(),
-                                        tool_name: tool_name.clone(),
-                                        duration_ms: duration.as_millis() as u64,
-                                        result_summary: content_preview.clone(),
-                                        success: true,
-                                    },
-                                ));
+    #[async_trait]
+    impl agents_core::llm::LanguageModel for TitleModel {
+        async fn generate(
+            &self,
+            _request: agents_core::llm::LlmRequest,
+        ) -> anyhow::Result<agents_core::llm::LlmResponse> {
+            Ok(agents_core::llm::LlmResponse::new(AgentMessage {
+                role: MessageRole::Agent,
+                content: MessageContent::Text(
+                    r#"{"title": "Late parcel", "summary": "Parcel to Dubai is late."}"#.into(),
+                ),
+                metadata: None,
+            }))
+        }
+    }
 
-                                tracing::warn!(
-                                    "✅ TOOL COMPLETED: {} in {:?} - Result: {}",
-                                    tool_name,
-                                    duration,
-                                    content_preview
-                                );
+    #[tokio::test]
+    async fn threads_are_titled_in_their_metadata_after_the_first_turns() {
+        use super::super::thread_titles::{SUMMARY_KEY, TITLE_KEY};
+        use agents_core::persistence::InMemoryCheckpointer;
 
-                                // Add tool result to history and continue ReAct loop
-                                self.append_history(tool_result_message);
-                                // Loop continues - LLM will see tool result and decide next action
-                            }
-                            Err(e) => {
-                                self.emit_event(agents_core::events::AgentEvent::ToolFailed(
-                                    agents_core::events::ToolFailedEvent {
-                                        metadata: self.create_event_metadata(),
-                                        tool_name: tool_name.clone(),
-                                        duration_ms: duration.as_millis() as u64,
-                                        error_message: e.to_string(),
-                                        is_recoverable: true,
-                                        retry_count: 0,
-                                    },
-                                ));
+        let agent = create_deep_agent_from_config(
+            DeepAgentConfig::new("assist", Arc::new(ReplyPlanner("ok")))
+                .with_checkpointer(Arc::new(InMemoryCheckpointer::new()))
+                .with_thread_titles(ThreadTitleConfig::new(Arc::new(TitleModel))),
+        );
+        let thread: ThreadId = "support-11".into();
+        agent
+            .set_thread_metadata(&thread, &ThreadMetadata::new().with_tag("vip"))
+            .await
+            .unwrap();
 
-                                tracing::error!(
-                                    "❌ TOOL FAILED: {} in {:?} - Error: {}",
-                                    tool_name,
-                                    duration,
-                                    e
-                                );
+        agent
+            .handle_message_for_thread(&thread, "Where is my parcel?")
+            .await
+            .unwrap();
+        let metadata = agent.thread_metadata(&thread).await.unwrap().unwrap();
+        assert!(metadata.get(TITLE_KEY).is_none());
 
-                                // Add error to history and continue - let LLM handle the error
-                                let error_message = AgentMessage {
-                                    role: MessageRole::Tool,
-                                    content: MessageContent::Text(format!(
-                                        "Error executing {}: {}",
-                                        tool_name, e
-                                    )),
-                                    metadata: None,
-                                };
-                                self.append_history(error_message);
-                                // Loop continues - LLM will see error and decide how to handle it
-                            }
-                        }
-                    } else {
-                        // Tool not found - add error to history and continue
-                        tracing::warn!("⚠️ Tool '{}' not found", tool_name);
-                        let error_message = AgentMessage {
-                            role: MessageRole::Tool,
-                            content: MessageContent::Text(format!(
-                                "Tool '{}' not found. Available tools: {}",
-                                tool_name,
-                                tools
-                                    .keys()
-                                    .map(|k| k.as_str())
-                                    .collect::<Vec<_>>()
-                                    .join(", ")
-                            )),
-                            metadata: None,
-                        };
-                        self.append_history(error_message);
-                        // Loop continues - LLM will see error and try something else
-                    }
-                }
-                PlannerAction::Terminate => {
-                    // LLM decided to terminate - exit loop
-                    tracing::debug!("🛑 Agent terminated");
-                    let message = AgentMessage {
+        agent
+            .handle_message_for_thread(&thread, "It was due in Dubai on Monday")
+            .await
+            .unwrap();
+        let metadata = agent.thread_metadata(&thread).await.unwrap().unwrap();
+        assert_eq!(metadata.get(TITLE_KEY), Some(&"Late parcel".into()));
+        assert_eq!(
+            metadata.get(SUMMARY_KEY),
+            Some(&"Parcel to Dubai is late.".into())
+        );
+        assert!(metadata.has_tag("vip"));
+    }
+
+    /// Calls `probe` once, then answers.
+    struct ProbePlanner;
+
+    #[async_trait]
+    impl PlannerHandle for ProbePlanner {
+        async fn plan(
+            &self,
+            context: PlannerContext,
+            _state: Arc<AgentStateSnapshot>,
+        ) -> anyhow::Result<PlannerDecision> {
+            let next_action = match context.history.last() {
+                Some(message) if message.role == MessageRole::User => PlannerAction::CallTool {
+                    tool_name: "probe".into(),
+                    payload: serde_json::json!({}),
+                },
+                _ => PlannerAction::Respond {
+                    message: AgentMessage {
                         role: MessageRole::Agent,
-                        content: MessageContent::Text("Task completed.".into()),
+                        content: MessageContent::Text("ok".into()),
                         metadata: None,
-                    };
-                    self.append_history(message.clone());
-                    return Ok(message);
-                }
-            }
+                    },
+                },
+            };
+            Ok(PlannerDecision { next_action })
         }
-    }
-}
 
-#[async_trait]
-impl AgentHandle for DeepAgent {
-    async fn describe(&self) -> AgentDescriptor {
-        self.descriptor.clone()
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
     }
 
-    async fn handle_message(
-        &self,
-        input: AgentMessage,
-        _state: Arc<AgentStateSnapshot>,
-    ) -> anyhow::Result<AgentMessage> {
-        let response = self.handle_message_internal(input, _state).await?;
+    #[derive(Default)]
+    struct TraceProbe(Mutex<Option<(Option<TraceContext>, Option<TraceContext>)>>);
 
-        // Persist state to checkpointer after successful message handling
-        if let Some(checkpointer) = &self.checkpointer {
-            let state_clone = self
-                .state
-                .read()
-                .map_err(|_| anyhow::anyhow!("Failed to acquire read lock on state"))?
-                .clone();
-            checkpointer
-                .save_state(&ThreadId::default(), &state_clone)
-                .await?;
+    #[async_trait]
+    impl agents_core::tools::Tool for TraceProbe {
+        fn schema(&self) -> agents_core::tools::ToolSchema {
+            agents_core::tools::ToolSchema::no_params("probe", "Records the trace context")
         }
 
-        Ok(response)
+        async fn execute(&self, _args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+            *self.0.lock().unwrap() = Some((ctx.trace.clone(), TraceContext::current()));
+            Ok(ToolResult::text(&ctx, "done"))
+        }
     }
 
-    async fn handle_message_stream(
-        &self,
-        input: AgentMessage,
-        _state: Arc<AgentStateSnapshot>,
-    ) -> anyhow::Result<agents_core::agent::AgentStream> {
-        use crate::planner::LlmBackedPlanner;
-        use agents_core::llm::{LlmRequest, StreamChunk};
-        use futures::StreamExt;
+    #[tokio::test]
+    async fn tool_calls_join_the_trace_of_the_incoming_message() {
+        let probe = Arc::new(TraceProbe::default());
+        let agent = create_deep_agent_from_config(
+            DeepAgentConfig::new("assist", Arc::new(ProbePlanner)).with_tool(probe.clone()),
+        );
+        let incoming = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        agent
+            .handle_message_with_metadata(
+                "hi",
+                Some(MessageMetadata {
+                    traceparent: Some(incoming.into()),
+                    ..Default::default()
+                }),
+                Arc::new(AgentStateSnapshot::default()),
+            )
+            .await
+            .unwrap();
 
-        // Add input to history
-        self.append_history(input.clone());
+        let (tool_trace, current) = probe.0.lock().unwrap().clone().unwrap();
+        let tool_trace = tool_trace.unwrap();
+        assert_eq!(tool_trace.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(current.as_ref(), Some(&tool_trace));
+        // The tool span is a child of the run span, which is a child of the caller
+        assert_ne!(
+            tool_trace.parent_span_id.as_deref(),
+            Some("00f067aa0ba902b7")
+        );
+        assert!(tool_trace.parent_span_id.is_some());
+        assert!(TraceContext::current().is_none());
+    }
 
-        // Build the request similar to handle_message_internal
-        let mut request = ModelRequest::new(&self.instructions, self.current_history());
-        let tools = self.collect_tools();
+    struct JsonProbe;
 
-        // Apply middleware modifications
-        for middleware in &self.middlewares {
-            let mut ctx = MiddlewareContext::with_request(&mut request, self.state.clone());
-            middleware.modify_model_request(&mut ctx).await?;
+    #[async_trait]
+    impl agents_core::tools::Tool for JsonProbe {
+        fn schema(&self) -> agents_core::tools::ToolSchema {
+            agents_core::tools::ToolSchema::no_params("probe", "Returns a list of orders")
         }
 
-        // Convert ModelRequest to LlmRequest and add tools
-        let tool_schemas: Vec<_> = tools.values().map(|t| t.schema()).collect();
-        let llm_request = LlmRequest {
-            system_prompt: request.system_prompt.clone(),
-            messages: request.messages.clone(),
-            tools: tool_schemas,
+        async fn execute(&self, _args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+            Ok(ToolResult::json(&ctx, orders_payload()))
+        }
+    }
+
+    fn orders_payload() -> Value {
+        serde_json::json!({ "orders": [
+            { "id": "o-1", "status": "shipped" },
+            { "id": "o-2", "status": "pending" }
+        ]})
+    }
+
+    #[tokio::test]
+    async fn toon_format_reencodes_json_tool_results() {
+        let agent = create_deep_agent_from_config(
+            DeepAgentConfig::new("assist", Arc::new(ProbePlanner))
+                .with_tool(Arc::new(JsonProbe))
+                .with_prompt_format(PromptFormat::Toon),
+        );
+        agent
+            .handle_message("hi", Arc::new(AgentStateSnapshot::default()))
+            .await
+            .unwrap();
+
+        let history = agent.current_history();
+        let tool_message = history
+            .iter()
+            .find(|message| message.role == MessageRole::Tool)
+            .expect("tool result in history");
+        let value = match &tool_message.content {
+            MessageContent::Text(text) => agents_core::toon::decode_json(text).unwrap(),
+            MessageContent::Json(value) => value.clone(),
+            MessageContent::Image { .. } => panic!("unexpected image content"),
         };
+        assert_eq!(value, orders_payload());
+        if cfg!(feature = "toon") {
+            assert!(matches!(tool_message.content, MessageContent::Text(_)));
+        }
+    }
 
-        // Try to get the underlying LLM model for streaming
-        let planner_any = self.planner.as_any();
+    struct TaggingTranslator;
 
-        if let Some(llm_planner) = planner_any.downcast_ref::<LlmBackedPlanner>() {
-            // We have an LlmBackedPlanner, use its model for streaming
-            let model = llm_planner.model().clone();
-            let stream = model.generate_stream(llm_request).await?;
+    #[async_trait]
+    impl crate::agent::translation::Translator for TaggingTranslator {
+        async fn translate(&self, text: &str, _from: &str, to: &str) -> anyhow::Result<String> {
+            Ok(format!("[{to}] {text}"))
+        }
+    }
 
-            // Wrap stream to emit events to broadcasters
-            let agent_name = self.descriptor.name.clone();
-            let event_dispatcher = self.event_dispatcher.clone();
+    #[tokio::test]
+    async fn translation_runs_agent_in_working_language() {
+        let agent = create_deep_agent_from_config(
+            DeepAgentConfig::new("assist", Arc::new(ReplyPlanner("ok")))
+                .with_translation(TranslationConfig::new(Arc::new(TaggingTranslator))),
+        );
+        let response = agent
+            .handle_message("مرحبا", Arc::new(AgentStateSnapshot::default()))
+            .await
+            .unwrap();
 
-            let wrapped_stream = stream.then(move |chunk_result| {
-                let dispatcher = event_dispatcher.clone();
-                let name = agent_name.clone();
+        assert_eq!(agent.get_full_message_text(&response), "[ar] ok");
+        let history: Vec<String> = agent
+            .current_history()
+            .iter()
+            .map(|m| agent.get_full_message_text(m))
+            .collect();
+        assert_eq!(history, vec!["[en] مرحبا", "ok"]);
+    }
 
-                async move {
-                    match &chunk_result {
-                        Ok(StreamChunk::TextDelta(token)) => {
-                            // Emit streaming token event
-                            if let Some(ref dispatcher) = dispatcher {
-                                let event = agents_core::events::AgentEvent::StreamingToken(
-                                    agents_core::events::StreamingTokenEvent {
-                                        metadata: agents_core::events::EventMetadata::new(
-                                            "default".to_string(),
-                                            uuid::Uuid::new_v4().to_string(),
-                                            None,
-                                        ),
-                                        agent_name: name.clone(),
-                                        token: token.clone(),
-                                    },
-                                );
-                                dispatcher.dispatch(event).await;
-                            }
-                        }
-                        Ok(StreamChunk::Done { message }) => {
-                            // Emit agent completed event
-                            if let Some(ref dispatcher) = dispatcher {
-                                let full_text = match &message.content {
-                                    agents_core::messaging::MessageContent::Text(t) => t.clone(),
-                                    agents_core::messaging::MessageContent::Json(v) => {
-                                        v.to_string()
-                                    }
-                                };
+    #[derive(Default)]
+    struct CorrelationRecorder(Mutex<Vec<String>>);
 
-                                let preview = if full_text.len() > 100 {
-                                    format!("{}...", &full_text[..100])
-                                } else {
-                                    full_text.clone()
-                                };
+    #[async_trait]
+    impl EventBroadcaster for CorrelationRecorder {
+        fn id(&self) -> &str {
+            "correlation-recorder"
+        }
+
+        async fn broadcast(&self, event: &AgentEvent) -> anyhow::Result<()> {
+            self.0
+                .lock()
+                .unwrap()
+                .push(event.metadata().correlation_id.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn correlation_id_reaches_events_and_checkpoints() {
+        let recorder = Arc::new(CorrelationRecorder::default());
+        let dispatcher = Arc::new(EventDispatcher::new());
+        dispatcher.add_broadcaster(recorder.clone());
+        let checkpointer = Arc::new(agents_core::persistence::InMemoryCheckpointer::new());
+        let agent = create_deep_agent_from_config(
+            DeepAgentConfig::new("assist", Arc::new(ReplyPlanner("ok")))
+                .with_event_dispatcher(dispatcher.clone())
+                .with_checkpointer(checkpointer.clone()),
+        );
+
+        let metadata = MessageMetadata {
+            correlation_id: Some("req-42".into()),
+            ..Default::default()
+        };
+        AgentHandle::handle_message(
+            &agent,
+            AgentMessage {
+                role: MessageRole::User,
+                content: MessageContent::Text("hi".into()),
+                metadata: Some(metadata),
+            },
+            Arc::new(AgentStateSnapshot::default()),
+        )
+        .await
+        .unwrap();
+        dispatcher.flush().await;
+
+        let seen = recorder.0.lock().unwrap().clone();
+        assert!(!seen.is_empty());
+        assert!(seen.iter().all(|id| id == "req-42"));
+        let saved = checkpointer
+            .load_state(&ThreadId::default())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(saved.correlation_id.as_deref(), Some("req-42"));
+
+        // Without one, the run id is used
+        agent
+            .handle_message("again", Arc::new(AgentStateSnapshot::default()))
+            .await
+            .unwrap();
+        dispatcher.flush().await;
+        let run_id = agent.last_run_id().unwrap();
+        assert_eq!(recorder.0.lock().unwrap().last(), Some(&run_id));
+    }
 
-                                let event = agents_core::events::AgentEvent::AgentCompleted(
-                                    agents_core::events::AgentCompletedEvent {
-                                        metadata: agents_core::events::EventMetadata::new(
-                                            "default".to_string(),
-                                            uuid::Uuid::new_v4().to_string(),
-                                            None,
-                                        ),
-                                        agent_name: name.clone(),
-                                        duration_ms: 0, // Duration not tracked in streaming mode
-                                        response_preview: preview,
-                                        response: full_text,
-                                    },
-                                );
-                                dispatcher.dispatch(event).await;
-                            }
-                        }
-                        _ => {}
-                    }
-                    chunk_result
+    /// Calls `probe` on its first turn, then answers.
+    #[derive(Default)]
+    struct ProbeOncePlanner(std::sync::atomic::AtomicBool);
+
+    #[async_trait]
+    impl PlannerHandle for ProbeOncePlanner {
+        async fn plan(
+            &self,
+            _context: PlannerContext,
+            _state: Arc<AgentStateSnapshot>,
+        ) -> anyhow::Result<PlannerDecision> {
+            let next_action = if self.0.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                PlannerAction::Respond {
+                    message: AgentMessage {
+                        role: MessageRole::Agent,
+                        content: MessageContent::Text("ok".into()),
+                        metadata: None,
+                    },
                 }
-            });
+            } else {
+                PlannerAction::CallTool {
+                    tool_name: "probe".into(),
+                    payload: serde_json::json!({}),
+                }
+            };
+            Ok(PlannerDecision { next_action })
+        }
 
-            Ok(Box::pin(wrapped_stream))
-        } else {
-            // Fallback to non-streaming
-            let response = self.handle_message_internal(input, _state).await?;
-            Ok(Box::pin(futures::stream::once(async move {
-                Ok(StreamChunk::Done { message: response })
-            })))
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
         }
     }
 
-    async fn current_interrupt(&self) -> anyhow::Result<Option<AgentInterrupt>> {
-        let state_guard = self
-            .state
-            .read()
-            .map_err(|_| anyhow::anyhow!("Failed to acquire read lock on state"))?;
-        Ok(state_guard.pending_interrupts.first().cloned())
+    #[tokio::test]
+    async fn thread_resumes_with_decision_from_the_approval_store() {
+        use agents_core::approval_store::{ApprovalDecision, ApprovalStore, InMemoryApprovalStore};
+        use agents_core::persistence::InMemoryCheckpointer;
+
+        let probe = Arc::new(TraceProbe::default());
+        let store = Arc::new(InMemoryApprovalStore::new());
+        let checkpointer = Arc::new(InMemoryCheckpointer::new());
+        let agent = create_deep_agent_from_config(
+            DeepAgentConfig::new("assist", Arc::new(ProbeOncePlanner::default()))
+                .with_tool(probe.clone())
+                .with_tool_interrupt(
+                    "probe",
+                    crate::middleware::HitlPolicy {
+                        allow_auto: false,
+                        note: None,
+                    },
+                )
+                .with_checkpointer(checkpointer.clone())
+                .with_approvals(ApprovalConfig::new(store.clone()).with_approver("probe", "ops")),
+        );
+        let thread: ThreadId = "thread-1".into();
+        agent
+            .handle_message_for_thread(&thread, "hi")
+            .await
+            .unwrap();
+        // The paused run is saved to its own thread only
+        assert!(checkpointer
+            .load_state(&ThreadId::default())
+            .await
+            .unwrap()
+            .is_none());
+        let saved = checkpointer.load_state(&thread).await.unwrap().unwrap();
+        assert_eq!(saved.pending_interrupts.len(), 1);
+
+        let pending = store.list_for_approver("ops").await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].thread_id, thread);
+        assert!(agent.resume_thread(&thread).await.unwrap().is_none());
+        assert!(probe.0.lock().unwrap().is_none());
+
+        store
+            .decide(
+                &thread,
+                pending[0].call_id(),
+                ApprovalDecision::new(HitlAction::Accept, "ana"),
+            )
+            .await
+            .unwrap();
+        assert!(agent.resume_thread(&thread).await.unwrap().is_some());
+        assert!(probe.0.lock().unwrap().is_some());
+        assert!(agent.current_interrupt().is_none());
+        assert!(store
+            .get(&thread, pending[0].call_id())
+            .await
+            .unwrap()
+            .is_none());
     }
 
-    async fn resume_with_approval(
-        &self,
-        action: agents_core::hitl::HitlAction,
-    ) -> anyhow::Result<AgentMessage> {
-        self.resume_with_approval(action).await
+    #[tokio::test]
+    async fn edited_approval_is_recorded_in_state_history_and_events() {
+        use agents_core::approval_store::ApprovalDecision;
+        use agents_core::hitl::HitlDecisionKind;
+        use agents_core::persistence::InMemoryCheckpointer;
+
+        let checkpointer = Arc::new(InMemoryCheckpointer::new());
+        let recorder = Arc::new(Recorder::default());
+        let dispatcher = Arc::new(EventDispatcher::new());
+        dispatcher.add_broadcaster(recorder.clone());
+        let agent = create_deep_agent_from_config(
+            DeepAgentConfig::new("assist", Arc::new(ProbeOncePlanner::default()))
+                .with_tool(Arc::new(TraceProbe::default()))
+                .with_tool_interrupt(
+                    "probe",
+                    crate::middleware::HitlPolicy {
+                        allow_auto: false,
+                        note: None,
+                    },
+                )
+                .with_checkpointer(checkpointer.clone())
+                .with_event_dispatcher(dispatcher.clone()),
+        );
+        let thread: ThreadId = "thread-1".into();
+        agent
+            .handle_message_for_thread(&thread, "hi")
+            .await
+            .unwrap();
+
+        let edit = HitlAction::Edit {
+            tool_name: "probe".into(),
+            tool_args: serde_json::json!({ "limit": 5 }),
+        };
+        agent
+            .resume_with_decision(ApprovalDecision::new(edit, "ana").with_reason("smaller batch"))
+            .await
+            .unwrap();
+        agent.save_state(&thread).await.unwrap();
+        dispatcher.flush().await;
+
+        let state = checkpointer.load_state(&thread).await.unwrap().unwrap();
+        let [decision] = state.hitl_decisions.as_slice() else {
+            panic!("expected one decision, got {:?}", state.hitl_decisions);
+        };
+        assert_eq!(decision.action, HitlDecisionKind::Edit);
+        assert_eq!(decision.decided_by.as_deref(), Some("ana"));
+        assert_eq!(decision.original_args, serde_json::json!({}));
+        assert_eq!(
+            decision.edited_args,
+            Some(serde_json::json!({ "limit": 5 }))
+        );
+        assert_eq!(decision.reason.as_deref(), Some("smaller batch"));
+        assert!(agent.current_history().iter().any(|message| {
+            message.role == MessageRole::System
+                && message
+                    .content
+                    .as_text()
+                    .is_some_and(|text| text.starts_with("Human review: ana edited"))
+        }));
+        assert!(recorder.0.lock().unwrap().contains(&"hitl_decided"));
     }
-}
 
-/// Create a deep agent from configuration - matches Python middleware assembly exactly
-///
-/// This function assembles the middleware stack in the same order as the Python SDK:
-/// planning → filesystem → subagents → summarization → prompt caching → optional HITL
-pub fn create_deep_agent_from_config(config: DeepAgentConfig) -> DeepAgent {
-    let state = Arc::new(RwLock::new(AgentStateSnapshot::default()));
-    let history = Arc::new(RwLock::new(Vec::<AgentMessage>::new()));
+    /// Answers every tool call with the same decision, remembering the requests.
+    struct FixedPolicy(PolicyDecision, Mutex<Vec<PolicyRequest>>);
 
-    let planning = Arc::new(PlanningMiddleware::new(state.clone()));
-    let filesystem = Arc::new(FilesystemMiddleware::new(state.clone()));
+    #[async_trait]
+    impl PolicyProvider for FixedPolicy {
+        fn id(&self) -> &str {
+            "fixed"
+        }
 
-    // Build sub-agents from configurations
-    let mut registrations: Vec<SubAgentRegistration> = Vec::new();
+        async fn evaluate(&self, request: &PolicyRequest) -> anyhow::Result<PolicyDecision> {
+            self.1.lock().unwrap().push(request.clone());
+            Ok(self.0.clone())
+        }
+    }
 
-    for subagent_config in &config.subagent_configs {
-        // Determine the planner for this sub-agent
-        let sub_planner = if let Some(ref model) = subagent_config.model {
-            // Sub-agent has its own model - wrap it in a planner
-            Arc::new(LlmBackedPlanner::new(model.clone())) as Arc<dyn PlannerHandle>
-        } else {
-            // Inherit parent's planner
-            config.planner.clone()
+    #[tokio::test]
+    async fn policy_provider_denies_or_pauses_tool_calls() {
+        let run = |decision: PolicyDecision| async move {
+            let probe = Arc::new(TraceProbe::default());
+            let policy = Arc::new(FixedPolicy(decision, Mutex::default()));
+            let agent = create_deep_agent_from_config(
+                DeepAgentConfig::new("assist", Arc::new(ProbeOncePlanner::default()))
+                    .with_tool(probe.clone())
+                    .with_policy_provider(policy.clone()),
+            );
+            let options = RunOptions::new().with_claims(serde_json::json!({ "sub": "ana" }));
+            let reply = agent
+                .handle_message_for_thread_with_options(&"thread-1".into(), "hi", options)
+                .await
+                .unwrap();
+            let ran = probe.0.lock().unwrap().is_some();
+            (agent, policy, reply, ran)
         };
 
-        // Create a DeepAgentConfig for this sub-agent
-        let mut sub_cfg = DeepAgentConfig::new(subagent_config.instructions.clone(), sub_planner);
+        let (agent, policy, reply, ran) = run(PolicyDecision::Deny {
+            reason: Some("weekend".into()),
+        })
+        .await;
+        assert!(!ran);
+        assert_eq!(reply.content.as_text(), Some("ok"));
+        assert!(agent.state.read().unwrap().messages.iter().any(|message| matches!(
+            &message.content,
+            MessageContent::Json(json) if json["error"] == "denied_by_policy" && json["reason"] == "weekend"
+        )));
+        let request = policy.1.lock().unwrap()[0].clone();
+        assert_eq!(request.tool_name, "probe");
+        assert_eq!(request.claims["sub"], "ana");
+        assert_eq!(request.thread.id.as_deref(), Some("thread-1"));
 
-        // Inherit max_iterations from parent
-        sub_cfg = sub_cfg.with_max_iterations(config.max_iterations.get());
+        let (agent, _, _, ran) = run(PolicyDecision::RequireApproval {
+            note: Some("needs review".into()),
+        })
+        .await;
+        assert!(!ran);
+        let Some(AgentInterrupt::HumanInLoop(interrupt)) = agent.current_interrupt() else {
+            panic!("expected the run to pause for approval");
+        };
+        assert_eq!(interrupt.policy_note.as_deref(), Some("needs review"));
 
-        // Configure tools
-        if let Some(ref tools) = subagent_config.tools {
-            tracing::debug!(
-                "  - Configuring {} tools for {}",
-                tools.len(),
-                subagent_config.name
+        let (_, _, _, ran) = run(PolicyDecision::Allow).await;
+        assert!(ran);
+    }
+
+    #[tokio::test]
+    async fn deterministic_runs_repeat_ids_time_and_seed() {
+        let run = || async {
+            let seen = Arc::new(Mutex::new(None));
+            let recorder = seen.clone();
+            let probe = agents_toolkit::tool_sync(
+                "probe",
+                "Creates a record",
+                agents_core::tools::ToolParameterSchema::object(
+                    "No parameters",
+                    HashMap::new(),
+                    Vec::new(),
+                ),
+                move |_args, ctx| {
+                    *recorder.lock().unwrap() = Some((
+                        ctx.new_uuid(),
+                        agents_core::determinism::now(),
+                        agents_core::determinism::current_seed(),
+                    ));
+                    Ok(ToolResult::text(&ctx, "created"))
+                },
             );
-            for tool in tools {
-                sub_cfg = sub_cfg.with_tool(tool.clone());
-            }
+            let agent = create_deep_agent_from_config(
+                DeepAgentConfig::new("assist", Arc::new(ProbeOncePlanner::default()))
+                    .with_tool(probe)
+                    .with_deterministic_mode(DeterministicConfig::new(42)),
+            );
+            agent
+                .handle_message("create it", Arc::new(AgentStateSnapshot::default()))
+                .await
+                .unwrap();
+            let seen = seen.lock().unwrap().take();
+            seen.unwrap()
+        };
+
+        let (first_id, now, seed) = run().await;
+        assert_eq!(run().await.0, first_id);
+        assert_eq!(now, PromptSnapshot::rendered_at());
+        assert_eq!(seed, Some(42));
+    }
+
+    #[test]
+    fn tools_disabled_by_the_profile_are_not_offered() {
+        let agent = create_deep_agent_from_config(
+            DeepAgentConfig::new("assist", Arc::new(ReplyPlanner("ok")))
+                .with_tool(Arc::new(TraceProbe::default()))
+                .with_disabled_tools(HashSet::from(["probe".into(), "write_file".into()])),
+        );
+        let tools = agent.collect_tools();
+        assert!(!tools.contains_key("probe"));
+        assert!(!tools.contains_key("write_file"));
+        assert!(tools.contains_key("read_file"));
+    }
+
+    #[test]
+    fn registered_tools_named_like_builtins_follow_the_conflict_strategy() {
+        let agent = |strategy| {
+            create_deep_agent_from_config(
+                DeepAgentConfig::new("assist", Arc::new(ReplyPlanner("ok")))
+                    .with_tool(Arc::new(TraceProbe::default()))
+                    .with_tool(agents_toolkit::tool_sync(
+                        "read_file",
+                        "Reads a remote file",
+                        agents_core::tools::ToolParameterSchema::object(
+                            "No parameters",
+                            HashMap::new(),
+                            Vec::new(),
+                        ),
+                        |_args, ctx| Ok(ToolResult::text(&ctx, "remote")),
+                    ))
+                    .with_tool_conflicts(strategy),
+            )
+        };
+        let description = |tools: &HashMap<String, ToolBox>, name: &str| {
+            tools.get(name).map(|tool| tool.schema().description)
+        };
+
+        let tools = agent(ToolConflictStrategy::prefix("mcp")).collect_tools();
+        assert_eq!(
+            description(&tools, "mcp_read_file").as_deref(),
+            Some("Reads a remote file")
+        );
+        assert_ne!(
+            description(&tools, "read_file").as_deref(),
+            Some("Reads a remote file")
+        );
+        assert!(tools.contains_key("probe"));
+
+        let tools = agent(ToolConflictStrategy::PreferRegistered).collect_tools();
+        assert_eq!(
+            description(&tools, "read_file").as_deref(),
+            Some("Reads a remote file")
+        );
+
+        let tools = agent(ToolConflictStrategy::PreferBuiltin).collect_tools();
+        assert_ne!(
+            description(&tools, "read_file").as_deref(),
+            Some("Reads a remote file")
+        );
+        assert!(!tools.contains_key("mcp_read_file"));
+    }
+
+    /// Calls `lookup` with a string id once, then answers.
+    struct BadArgsPlanner;
+
+    #[async_trait]
+    impl PlannerHandle for BadArgsPlanner {
+        async fn plan(
+            &self,
+            context: PlannerContext,
+            _state: Arc<AgentStateSnapshot>,
+        ) -> anyhow::Result<PlannerDecision> {
+            let next_action = match context.history.last() {
+                Some(message) if message.role == MessageRole::User => PlannerAction::CallTool {
+                    tool_name: "lookup".into(),
+                    payload: serde_json::json!({"id": "seven"}),
+                },
+                _ => PlannerAction::Respond {
+                    message: AgentMessage {
+                        role: MessageRole::Agent,
+                        content: MessageContent::Text("ok".into()),
+                        metadata: None,
+                    },
+                },
+            };
+            Ok(PlannerDecision { next_action })
         }
 
-        // Configure built-in tools
-        if let Some(ref builtin) = subagent_config.builtin_tools {
-            sub_cfg = sub_cfg.with_builtin_tools(builtin.iter().cloned());
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
         }
+    }
 
-        // Sub-agents should not have their own sub-agents
-        sub_cfg = sub_cfg.with_auto_general_purpose(false);
+    #[tokio::test]
+    async fn strict_validation_returns_argument_errors_to_the_model() {
+        let calls = Arc::new(Mutex::new(0));
+        let agent = |mode| {
+            let calls = calls.clone();
+            create_deep_agent_from_config(
+                DeepAgentConfig::new("assist", Arc::new(BadArgsPlanner))
+                    .with_tool(agents_toolkit::tool_sync(
+                        "lookup",
+                        "Looks up a record",
+                        agents_core::tools::ToolParameterSchema::object(
+                            "lookup parameters",
+                            HashMap::from([(
+                                "id".to_string(),
+                                agents_core::tools::ToolParameterSchema::integer("Record id"),
+                            )]),
+                            vec!["id".to_string()],
+                        ),
+                        move |_args, ctx| {
+                            *calls.lock().unwrap() += 1;
+                            Ok(ToolResult::text(&ctx, "record"))
+                        },
+                    ))
+                    .with_tool_argument_validation(mode),
+            )
+        };
 
-        // Configure prompt caching
-        sub_cfg = sub_cfg.with_prompt_caching(subagent_config.enable_prompt_caching);
+        let strict = agent(ToolArgumentValidation::Strict);
+        strict
+            .handle_message("find it", Arc::new(AgentStateSnapshot::default()))
+            .await
+            .unwrap();
+        assert_eq!(*calls.lock().unwrap(), 0);
+        let rejection = strict
+            .current_history()
+            .into_iter()
+            .find(|m| m.role == MessageRole::Tool)
+            .unwrap();
+        let MessageContent::Json(rejection) = rejection.content else {
+            panic!("expected a structured rejection");
+        };
+        assert_eq!(rejection["error"], "invalid_arguments");
+        assert_eq!(rejection["problems"][0]["path"], "id");
+        assert_eq!(
+            rejection["problems"][0]["message"],
+            "expected integer, got string"
+        );
 
-        // Inherit PII sanitization setting from parent
-        sub_cfg = sub_cfg.with_pii_sanitization(config.enable_pii_sanitization);
+        agent(ToolArgumentValidation::Lenient)
+            .handle_message("find it", Arc::new(AgentStateSnapshot::default()))
+            .await
+            .unwrap();
+        assert_eq!(*calls.lock().unwrap(), 1);
+    }
 
-        // Build the sub-agent recursively
-        let sub_agent = create_deep_agent_from_config(sub_cfg);
+    #[tokio::test]
+    async fn failed_tool_calls_are_retried_then_reported_with_guidance() {
+        let agent = |failures: usize, calls: Arc<Mutex<usize>>| {
+            let policy = ToolRetryPolicy::new(3).with_backoff(Duration::ZERO, Duration::ZERO);
+            create_deep_agent_from_config(
+                DeepAgentConfig::new("assist", Arc::new(BadArgsPlanner))
+                    .with_tool(agents_toolkit::tool_sync(
+                        "lookup",
+                        "Looks up a record",
+                        agents_core::tools::ToolParameterSchema::object(
+                            "lookup parameters",
+                            HashMap::new(),
+                            Vec::new(),
+                        ),
+                        move |_args, ctx| {
+                            let mut calls = calls.lock().unwrap();
+                            *calls += 1;
+                            if *calls <= failures {
+                                anyhow::bail!("connection reset");
+                            }
+                            Ok(ToolResult::text(&ctx, "record"))
+                        },
+                    ))
+                    .with_tool_retries(ToolRetryConfig::new(policy)),
+            )
+        };
+        let tool_message = |agent: &DeepAgent| {
+            agent
+                .current_history()
+                .into_iter()
+                .find(|m| m.role == MessageRole::Tool)
+                .and_then(|m| m.content.as_text().map(str::to_string))
+                .unwrap()
+        };
 
-        // Register the sub-agent
-        registrations.push(SubAgentRegistration {
-            descriptor: SubAgentDescriptor {
-                name: subagent_config.name.clone(),
-                description: subagent_config.description.clone(),
-            },
-            agent: Arc::new(sub_agent),
-        });
+        let calls = Arc::new(Mutex::new(0));
+        let flaky = agent(2, calls.clone());
+        flaky
+            .handle_message("find it", Arc::new(AgentStateSnapshot::default()))
+            .await
+            .unwrap();
+        assert_eq!(*calls.lock().unwrap(), 3);
+        assert_eq!(tool_message(&flaky), "record");
 
-        tracing::info!("=> Registered sub-agent: {}", subagent_config.name);
+        let calls = Arc::new(Mutex::new(0));
+        let broken = agent(usize::MAX, calls.clone());
+        broken
+            .handle_message("find it", Arc::new(AgentStateSnapshot::default()))
+            .await
+            .unwrap();
+        assert_eq!(*calls.lock().unwrap(), 3);
+        let error = tool_message(&broken);
+        assert!(error.starts_with("Error executing lookup: connection reset"));
+        assert!(error.contains("Choose an alternative"));
     }
 
-    tracing::info!("=> Total sub-agents registered: {}", registrations.len());
+    /// Fails until `healthy` is set, then answers.
+    #[derive(Default)]
+    struct OutagePlanner {
+        healthy: std::sync::atomic::AtomicBool,
+    }
 
-    // Optionally inject a general-purpose subagent
-    if config.auto_general_purpose {
-        let has_gp = registrations
-            .iter()
-            .any(|r| r.descriptor.name == "general-purpose");
-        if !has_gp {
-            // Create a subagent with inherited planner/tools and same instructions
-            let mut sub_cfg =
-                DeepAgentConfig::new(config.instructions.clone(), config.planner.clone())
-                    .with_auto_general_purpose(false)
-                    .with_prompt_caching(config.enable_prompt_caching)
-                    .with_pii_sanitization(config.enable_pii_sanitization)
-                    .with_max_iterations(config.max_iterations.get());
-            if let Some(ref selected) = config.builtin_tools {
-                sub_cfg = sub_cfg.with_builtin_tools(selected.iter().cloned());
-            }
-            if let Some(ref sum) = config.summarization {
-                sub_cfg = sub_cfg.with_summarization(sum.clone());
-            }
-            for t in &config.tools {
-                sub_cfg = sub_cfg.with_tool(t.clone());
+    #[async_trait]
+    impl PlannerHandle for OutagePlanner {
+        async fn plan(
+            &self,
+            context: PlannerContext,
+            state: Arc<AgentStateSnapshot>,
+        ) -> anyhow::Result<PlannerDecision> {
+            if !self.healthy.load(std::sync::atomic::Ordering::SeqCst) {
+                anyhow::bail!("model provider unavailable");
             }
+            ReplyPlanner("ok").plan(context, state).await
+        }
 
-            let gp = create_deep_agent_from_config(sub_cfg);
-            registrations.push(SubAgentRegistration {
-                descriptor: SubAgentDescriptor {
-                    name: "general-purpose".into(),
-                    description: "Default reasoning agent".into(),
-                },
-                agent: Arc::new(gp),
-            });
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
         }
     }
 
-    let subagent = Arc::new(SubAgentMiddleware::new_with_events(
-        registrations,
-        config.event_dispatcher.clone(),
-    ));
-    let base_prompt = Arc::new(BaseSystemPromptMiddleware);
+    #[tokio::test]
+    async fn failed_runs_become_dead_letters_that_can_be_replayed() {
+        use agents_core::dead_letter::InMemoryDeadLetterStore;
 
-    // Create Deep Agent prompt middleware - use override if custom system prompt is set
-    // Otherwise use the configured prompt format (JSON or TOON)
-    let deep_agent_prompt: Arc<dyn AgentMiddleware> =
-        if let Some(ref custom_prompt) = config.custom_system_prompt {
-            Arc::new(DeepAgentPromptMiddleware::with_override(
-                custom_prompt.clone(),
-            ))
-        } else {
-            Arc::new(DeepAgentPromptMiddleware::with_format(
-                config.instructions.clone(),
-                config.prompt_format,
-            ))
-        };
-    let summarization = config.summarization.as_ref().map(|cfg| {
-        Arc::new(SummarizationMiddleware::new(
-            cfg.messages_to_keep,
-            cfg.summary_note.clone(),
-        ))
-    });
-    let hitl = if config.tool_interrupts.is_empty() {
-        None
-    } else {
-        // Validate that checkpointer is configured when HITL is enabled
-        if config.checkpointer.is_none() {
-            tracing::error!(
-                "⚠️ HITL middleware requires a checkpointer to persist interrupt state. \
-                 HITL will be disabled. Please configure a checkpointer to enable HITL."
-            );
-            None
-        } else {
-            tracing::info!("🔒 HITL enabled for {} tools", config.tool_interrupts.len());
-            Some(Arc::new(HumanInLoopMiddleware::new(
-                config.tool_interrupts.clone(),
-            )))
+        let planner = Arc::new(OutagePlanner::default());
+        let store = Arc::new(InMemoryDeadLetterStore::new());
+        let agent = create_deep_agent_from_config(
+            DeepAgentConfig::new("assist", planner.clone()).with_dead_letter_store(store.clone()),
+        );
+
+        let error = agent
+            .handle_message("refund order 42", Arc::new(AgentStateSnapshot::default()))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("model provider unavailable"));
+
+        let letters = store.list("deep-agent").await.unwrap();
+        assert_eq!(letters.len(), 1);
+        let letter = &letters[0];
+        assert_eq!(letter.input.content.as_text(), Some("refund order 42"));
+        assert_eq!(letter.thread_id, None);
+        assert!(letter.error.contains("model provider unavailable"));
+        assert_eq!(Some(&letter.run_id), letter.correlation_id.as_ref());
+        assert!(letter
+            .events
+            .iter()
+            .any(|event| matches!(event, AgentEvent::AgentStarted(_))));
+
+        planner
+            .healthy
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        let reply = agent.replay_dead_letter(&letter.id).await.unwrap();
+        assert_eq!(reply.content.as_text(), Some("ok"));
+        assert!(store.list("deep-agent").await.unwrap().is_empty());
+        assert!(agent.replay_dead_letter(&letter.id).await.is_err());
+    }
+
+    /// Provides a `lookup` tool once started; counts starts and shutdowns.
+    #[derive(Default)]
+    struct LazyProvider {
+        started: Mutex<usize>,
+        stopped: Mutex<bool>,
+    }
+
+    #[async_trait]
+    impl ToolProvider for LazyProvider {
+        async fn tools(&self) -> anyhow::Result<Vec<ToolBox>> {
+            *self.started.lock().unwrap() += 1;
+            Ok(vec![agents_toolkit::tool_sync(
+                "lookup",
+                "Looks up a record",
+                agents_core::tools::ToolParameterSchema::object(
+                    "lookup parameters",
+                    HashMap::new(),
+                    Vec::new(),
+                ),
+                |_args, ctx| Ok(ToolResult::text(&ctx, "record")),
+            )])
         }
-    };
 
-    // Assemble middleware stack with Deep Agent prompt for automatic tool usage
-    // Order: base → deep agent prompt → planning → filesystem → subagents → summarization → caching → HITL
-    let mut middlewares: Vec<Arc<dyn AgentMiddleware>> = vec![
-        base_prompt,
-        deep_agent_prompt,
-        planning,
-        filesystem,
-        subagent,
-    ];
-    if let Some(ref summary) = summarization {
-        middlewares.push(summary.clone());
+        async fn shutdown(&self) -> anyhow::Result<()> {
+            *self.stopped.lock().unwrap() = true;
+            Ok(())
+        }
     }
-    if config.enable_prompt_caching {
-        middlewares.push(Arc::new(AnthropicPromptCachingMiddleware::with_defaults()));
+
+    #[tokio::test]
+    async fn self_test_checks_schemas_safe_tools_and_providers() {
+        let flaky = agents_toolkit::ToolBuilder::new("flaky_lookup", "Looks up a record")
+            .with_self_test_args(serde_json::json!({}))
+            .build_sync(|_args, _ctx| anyhow::bail!("database unreachable"));
+        let agent = create_deep_agent_from_config(
+            DeepAgentConfig::new("assist", Arc::new(ReplyPlanner("ok")))
+                .with_tool(Arc::new(TraceProbe::default()))
+                .with_tool(flaky)
+                .with_tool_provider(Arc::new(LazyProvider::default())),
+        );
+
+        let report = agent.self_test().await;
+        let check = |kind, target: &str| {
+            report
+                .checks
+                .iter()
+                .find(|check| check.kind == kind && check.target == target)
+                .unwrap_or_else(|| panic!("no {kind:?} check of {target}"))
+                .clone()
+        };
+        assert_eq!(
+            check(SelfTestCheckKind::ToolProvider, "tool provider")
+                .detail
+                .as_deref(),
+            Some("tools: 1")
+        );
+        // Built-in, registered and provided tools all have valid schemas
+        assert!(!check(SelfTestCheckKind::ToolSchema, "lookup").is_failed());
+        assert!(!check(SelfTestCheckKind::ToolSchema, "write_file").is_failed());
+        assert!(report
+            .checks
+            .iter()
+            .filter(|check| check.kind == SelfTestCheckKind::ToolSchema)
+            .all(|check| !check.is_failed()));
+        // Only tools marked with self-test arguments are called
+        assert!(check(SelfTestCheckKind::ToolCall, "flaky_lookup").is_failed());
+        assert!(report.checks.iter().all(
+            |check| check.kind != SelfTestCheckKind::ToolCall || check.target == "flaky_lookup"
+        ));
+        // The planner is not model-backed, so there is no model to ping
+        assert!(report
+            .checks
+            .iter()
+            .all(|check| check.kind != SelfTestCheckKind::Model));
+        assert!(report
+            .ensure_passed()
+            .unwrap_err()
+            .to_string()
+            .contains("tool call flaky_lookup: database unreachable"));
     }
-    if let Some(ref hitl_mw) = hitl {
-        middlewares.push(hitl_mw.clone());
+
+    #[tokio::test]
+    async fn tool_providers_load_on_first_run_and_stop_on_shutdown() {
+        let provider = Arc::new(LazyProvider::default());
+        let agent = create_deep_agent_from_config(
+            DeepAgentConfig::new("assist", Arc::new(BadArgsPlanner))
+                .with_tool_provider(provider.clone()),
+        );
+        assert_eq!(*provider.started.lock().unwrap(), 0);
+        assert!(!agent.collect_tools().contains_key("lookup"));
+
+        agent
+            .handle_message("find it", Arc::new(AgentStateSnapshot::default()))
+            .await
+            .unwrap();
+        assert_eq!(*provider.started.lock().unwrap(), 1);
+        let tool_message = agent
+            .current_history()
+            .into_iter()
+            .find(|m| m.role == MessageRole::Tool)
+            .unwrap();
+        assert_eq!(tool_message.content.as_text(), Some("record"));
+
+        agent.shutdown(Duration::from_secs(1)).await.unwrap();
+        assert!(*provider.stopped.lock().unwrap());
     }
 
-    DeepAgent {
-        descriptor: AgentDescriptor {
-            name: "deep-agent".into(),
-            version: "0.0.1".into(),
-            description: Some("Rust deep agent".into()),
-        },
-        instructions: config.instructions,
-        planner: config.planner,
-        middlewares,
-        base_tools: config.tools,
-        state,
-        history,
-        _summarization: summarization,
-        _hitl: hitl,
-        builtin_tools: config.builtin_tools,
-        checkpointer: config.checkpointer,
-        event_dispatcher: config.event_dispatcher,
-        enable_pii_sanitization: config.enable_pii_sanitization,
-        max_iterations: config.max_iterations,
+    #[tokio::test]
+    async fn subagents_can_be_looked_up_and_served() {
+        let agent = create_deep_agent_from_config(
+            DeepAgentConfig::new("assist", Arc::new(ReplyPlanner("ok"))).with_subagent_config(
+                crate::agent::SubAgentConfig::new(
+                    "research-agent",
+                    "Researches a topic",
+                    "You research",
+                ),
+            ),
+        );
+        assert!(agent.subagent("general-purpose").is_some());
+        assert!(agent.subagent("missing").is_none());
+        let response = agent
+            .subagent("research-agent")
+            .unwrap()
+            .handle_message(
+                AgentMessage {
+                    role: MessageRole::User,
+                    content: MessageContent::Text("look into it".into()),
+                    metadata: None,
+                },
+                Arc::new(AgentStateSnapshot::default()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.content.as_text(), Some("ok"));
+
+        #[cfg(feature = "mcp")]
+        {
+            let server = agent.subagent_mcp_server("research-agent").unwrap();
+            assert_eq!(server.tool().name, "research-agent");
+            assert_eq!(
+                server.tool().description.as_deref(),
+                Some("Researches a topic")
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn state_size_threshold_is_reported_once_per_crossing() {
+        use agents_core::persistence::InMemoryCheckpointer;
+
+        let recorder = Arc::new(Recorder::default());
+        let dispatcher = Arc::new(EventDispatcher::new());
+        dispatcher.add_broadcaster(recorder.clone());
+        let agent = create_deep_agent_from_config(
+            DeepAgentConfig::new("assist", Arc::new(ReplyPlanner("ok")))
+                .with_checkpointer(Arc::new(InMemoryCheckpointer::new()))
+                .with_event_dispatcher(dispatcher.clone())
+                .with_state_size_limits(StateSizeLimits::new().with_max_file_bytes(100)),
+        );
+        let thread: ThreadId = "thread-1".into();
+        agent
+            .state
+            .write()
+            .unwrap()
+            .files
+            .insert("big.txt".into(), "x".repeat(500));
+        agent.save_state(&thread).await.unwrap();
+        agent.save_state(&thread).await.unwrap();
+        dispatcher.flush().await;
+
+        let events = recorder.0.lock().unwrap().clone();
+        assert_eq!(
+            events
+                .iter()
+                .filter(|e| **e == "state_size_exceeded")
+                .count(),
+            1
+        );
+        assert_eq!(agent.thread_state_size(&thread).unwrap().file_bytes, 500);
+        let metrics = agent.state_metrics();
+        assert_eq!(metrics.threads, 1);
+        assert_eq!(metrics.largest_thread, Some(thread.clone()));
+
+        agent.delete_thread(&thread).await.unwrap();
+        assert_eq!(agent.state_metrics().threads, 0);
     }
 }
@@ -0,0 +1,386 @@
+//! Startup self-tests catching misconfiguration before traffic reaches the agent
+//!
+//! [`DeepAgent::self_test`](super::DeepAgent::self_test) checks what can be checked
+//! without a conversation and returns a [`SelfTestReport`]:
+//!
+//! - every tool schema is well formed: a name providers accept, a description, an
+//!   object parameter schema whose required parameters exist, and examples that match it;
+//! - tools marked with [`ToolSchema::with_self_test_args`] are called with those
+//!   arguments, against a copy of the agent's state;
+//! - the agent's model and the models of sub-agents with their own model answer a
+//!   one-line request;
+//! - tool providers such as MCP servers connect and list their tools.
+//!
+//! ```ignore
+//! let agent = builder.build()?;
+//! agent.self_test().await.ensure_passed()?;
+//! ```
+
+use agents_core::llm::{LanguageModel, LlmRequest};
+use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+use agents_core::tools::ToolSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// Time each tool call, model ping or provider check may take
+pub const DEFAULT_SELF_TEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Longest tool name accepted by the OpenAI, Anthropic and Gemini APIs
+const MAX_TOOL_NAME_LEN: usize = 64;
+
+/// Which checks a self-test runs. Schema checks always run.
+#[derive(Debug, Clone)]
+pub struct SelfTestOptions {
+    /// Call the tools marked with self-test arguments
+    pub call_tools: bool,
+    /// Send a one-line request to each model (billed like any other request)
+    pub ping_models: bool,
+    /// Connect to the tool providers
+    pub check_tool_providers: bool,
+    pub timeout: Duration,
+}
+
+impl Default for SelfTestOptions {
+    fn default() -> Self {
+        Self {
+            call_tools: true,
+            ping_models: true,
+            check_tool_providers: true,
+            timeout: DEFAULT_SELF_TEST_TIMEOUT,
+        }
+    }
+}
+
+impl SelfTestOptions {
+    /// Only the checks that need no network or tool side effects: tool schemas.
+    pub fn offline() -> Self {
+        Self {
+            call_tools: false,
+            ping_models: false,
+            check_tool_providers: false,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_tool_calls(mut self, enabled: bool) -> Self {
+        self.call_tools = enabled;
+        self
+    }
+
+    pub fn with_model_pings(mut self, enabled: bool) -> Self {
+        self.ping_models = enabled;
+        self
+    }
+
+    pub fn with_tool_provider_checks(mut self, enabled: bool) -> Self {
+        self.check_tool_providers = enabled;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+/// What a [`SelfTestCheck`] verified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelfTestCheckKind {
+    ToolSchema,
+    ToolCall,
+    Model,
+    ToolProvider,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelfTestStatus {
+    Passed,
+    Failed,
+}
+
+/// Outcome of one check.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfTestCheck {
+    pub kind: SelfTestCheckKind,
+    /// Tool, model (by agent name) or provider checked
+    pub target: String,
+    pub status: SelfTestStatus,
+    /// Why the check failed, or what it found
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    pub duration_ms: u64,
+}
+
+impl SelfTestCheck {
+    pub fn passed(kind: SelfTestCheckKind, target: impl Into<String>) -> Self {
+        Self {
+            kind,
+            target: target.into(),
+            status: SelfTestStatus::Passed,
+            detail: None,
+            duration_ms: 0,
+        }
+    }
+
+    pub fn failed(
+        kind: SelfTestCheckKind,
+        target: impl Into<String>,
+        detail: impl Into<String>,
+    ) -> Self {
+        Self {
+            status: SelfTestStatus::Failed,
+            detail: Some(detail.into()),
+            ..Self::passed(kind, target)
+        }
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration_ms = duration.as_millis() as u64;
+        self
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.status == SelfTestStatus::Failed
+    }
+}
+
+/// Result of [`DeepAgent::self_test`](super::DeepAgent::self_test).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfTestReport {
+    pub agent: String,
+    pub checks: Vec<SelfTestCheck>,
+}
+
+impl SelfTestReport {
+    pub fn new(agent: impl Into<String>) -> Self {
+        Self {
+            agent: agent.into(),
+            checks: Vec::new(),
+        }
+    }
+
+    /// Whether no check failed.
+    pub fn passed(&self) -> bool {
+        !self.checks.iter().any(SelfTestCheck::is_failed)
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &SelfTestCheck> {
+        self.checks.iter().filter(|check| check.is_failed())
+    }
+
+    /// An error listing the failed checks, if any; for refusing to start.
+    pub fn ensure_passed(&self) -> anyhow::Result<()> {
+        if self.passed() {
+            return Ok(());
+        }
+        let failures: Vec<String> = self
+            .failures()
+            .map(|check| {
+                format!(
+                    "{} {}: {}",
+                    kind_label(check.kind),
+                    check.target,
+                    check.detail.as_deref().unwrap_or("failed")
+                )
+            })
+            .collect();
+        anyhow::bail!(
+            "self-test of agent '{}' failed:\n- {}",
+            self.agent,
+            failures.join("\n- ")
+        )
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# Self-test: {}\n", self.agent);
+        let failed = self.failures().count();
+        let _ = writeln!(out, "{} checks, {} failed\n", self.checks.len(), failed);
+        out.push_str("| Check | Target | Status | Detail |\n");
+        out.push_str("|-------|--------|--------|--------|\n");
+        for check in &self.checks {
+            let _ = writeln!(
+                out,
+                "| {} | `{}` | {} | {} |",
+                kind_label(check.kind),
+                check.target,
+                match check.status {
+                    SelfTestStatus::Passed => "passed",
+                    SelfTestStatus::Failed => "**failed**",
+                },
+                check.detail.as_deref().unwrap_or("").replace('|', "\\|")
+            );
+        }
+        out
+    }
+}
+
+fn kind_label(kind: SelfTestCheckKind) -> &'static str {
+    match kind {
+        SelfTestCheckKind::ToolSchema => "tool schema",
+        SelfTestCheckKind::ToolCall => "tool call",
+        SelfTestCheckKind::Model => "model",
+        SelfTestCheckKind::ToolProvider => "tool provider",
+    }
+}
+
+/// Run one check, failing it when it takes longer than `timeout`.
+pub(crate) async fn timed<T>(
+    timeout: Duration,
+    check: impl Future<Output = anyhow::Result<T>>,
+) -> (anyhow::Result<T>, Duration) {
+    let started = Instant::now();
+    let result = match tokio::time::timeout(timeout, check).await {
+        Ok(result) => result,
+        Err(_) => Err(anyhow::anyhow!(
+            "timed out after {}s",
+            timeout.as_secs_f32()
+        )),
+    };
+    (result, started.elapsed())
+}
+
+/// Send `model` a one-line request.
+pub(crate) async fn ping(model: &dyn LanguageModel) -> anyhow::Result<()> {
+    let request = LlmRequest::new(
+        "This is a connectivity check. Reply with OK.",
+        vec![AgentMessage {
+            role: MessageRole::User,
+            content: MessageContent::Text("ping".to_string()),
+            metadata: None,
+        }],
+    );
+    model.generate(request).await.map(|_| ())
+}
+
+/// Problems of a tool schema that would make providers reject it or the model
+/// misuse it.
+pub(crate) fn schema_problems(schema: &ToolSchema) -> Vec<String> {
+    let mut problems = Vec::new();
+    let name = &schema.name;
+    if name.is_empty()
+        || name.len() > MAX_TOOL_NAME_LEN
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        problems.push(format!(
+            "name must be 1-{MAX_TOOL_NAME_LEN} letters, digits, '_' or '-'"
+        ));
+    }
+    if schema.description.trim().is_empty() {
+        problems.push("description is empty".to_string());
+    }
+    let parameters = &schema.parameters;
+    if parameters.schema_type != "object" {
+        problems.push(format!(
+            "parameters must be an object schema, not '{}'",
+            parameters.schema_type
+        ));
+    }
+    for required in parameters.required.iter().flatten() {
+        let declared = parameters
+            .properties
+            .as_ref()
+            .is_some_and(|properties| properties.contains_key(required));
+        if !declared {
+            problems.push(format!("required parameter '{required}' is not declared"));
+        }
+    }
+    let sample_args = schema
+        .examples
+        .iter()
+        .enumerate()
+        .map(|(i, example)| (format!("example {}", i + 1), &example.args))
+        .chain(
+            schema
+                .self_test_args
+                .iter()
+                .map(|args| ("self-test arguments".to_string(), args)),
+        );
+    for (label, args) in sample_args {
+        if let Err(errors) = parameters.validate(args) {
+            let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+            problems.push(format!(
+                "{label} does not match the parameters: {}",
+                errors.join("; ")
+            ));
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::tools::{ToolExample, ToolParameterSchema};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn schema(name: &str) -> ToolSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "query".to_string(),
+            ToolParameterSchema::string("Search query"),
+        );
+        ToolSchema::new(
+            name,
+            "Search the docs",
+            ToolParameterSchema::object("Search", properties, vec!["query".to_string()]),
+        )
+    }
+
+    #[test]
+    fn well_formed_schemas_have_no_problems() {
+        let schema = schema("search_docs")
+            .with_example(ToolExample::new(json!({ "query": "refunds" })))
+            .with_self_test_args(json!({ "query": "health" }));
+        assert!(schema_problems(&schema).is_empty());
+    }
+
+    #[test]
+    fn schema_problems_are_reported() {
+        let mut schema = schema("search docs").with_example(ToolExample::new(json!({})));
+        schema.description = " ".into();
+        schema.parameters.required = Some(vec!["query".into(), "limit".into()]);
+
+        let problems = schema_problems(&schema);
+        assert_eq!(problems.len(), 4, "{problems:?}");
+        assert!(problems[0].starts_with("name must be"));
+        assert_eq!(problems[1], "description is empty");
+        assert_eq!(problems[2], "required parameter 'limit' is not declared");
+        assert!(problems[3].starts_with("example 1 does not match"));
+    }
+
+    #[test]
+    fn failed_reports_list_their_failures() {
+        let mut report = SelfTestReport::new("support");
+        report.checks.push(SelfTestCheck::passed(
+            SelfTestCheckKind::ToolSchema,
+            "search",
+        ));
+        assert!(report.ensure_passed().is_ok());
+
+        report.checks.push(SelfTestCheck::failed(
+            SelfTestCheckKind::ToolProvider,
+            "mcp:github",
+            "connection refused",
+        ));
+        let error = report.ensure_passed().unwrap_err().to_string();
+        assert!(error.contains("tool provider mcp:github: connection refused"));
+        assert!(report.to_markdown().contains("**failed**"));
+    }
+}
@@ -0,0 +1,358 @@
+//! Graceful shutdown for rolling deploys
+//!
+//! `DeepAgent::shutdown` stops the agent from accepting new messages, waits for
+//! in-flight runs to finish, asks the remaining ones to stop at their next step once
+//! the grace period is over, checkpoints the state and flushes pending event
+//! broadcasts (including token usage records).
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// Extra time given to interrupted runs to stop, and to event broadcasts to flush.
+pub(crate) const SHUTDOWN_FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Errors returned to callers while the agent is shutting down.
+///
+/// Servers can match on it to answer with `503 Service Unavailable` and let the
+/// load balancer retry on another instance:
+///
+/// ```ignore
+/// match agent.handle_message(input, state).await {
+///     Err(e) if e.is::<AgentShutdownError>() => StatusCode::SERVICE_UNAVAILABLE,
+///     ...
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AgentShutdownError {
+    /// The message arrived after `shutdown()` was called
+    #[error("agent is shutting down and no longer accepts messages")]
+    NotAccepting,
+    /// The run did not finish within the grace period; its state was checkpointed
+    #[error("run interrupted by shutdown after {steps} steps; state was checkpointed")]
+    Interrupted { steps: usize },
+}
+
+/// Outcome of `DeepAgent::shutdown`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// Whether every in-flight run finished within the grace period
+    pub drained: bool,
+    /// Runs stopped at their next step once the grace period ran out; streams and
+    /// runs still waiting for a slot are not stopped
+    pub interrupted_runs: usize,
+    /// Whether every interrupted run saved its state to its thread in the configured
+    /// checkpointer
+    pub checkpointed: bool,
+    /// Whether all pending event broadcasts completed
+    pub events_flushed: bool,
+    pub duration_ms: u64,
+}
+
+/// Tracks in-flight runs and whether new ones may start.
+#[derive(Default)]
+pub(crate) struct RunGate {
+    closed: AtomicBool,
+    stop_requested: AtomicBool,
+    in_flight: AtomicUsize,
+    /// Runs that stopped at a step on request
+    stops: AtomicUsize,
+    /// Stopped runs that checkpointed their state
+    checkpoints: AtomicUsize,
+    idle: Notify,
+}
+
+impl RunGate {
+    /// Register a new run, or fail once the gate has been closed.
+    pub(crate) fn enter(self: &Arc<Self>) -> Result<RunGuard, AgentShutdownError> {
+        self.in_flight.fetch_add(1, Ordering::AcqRel);
+        if self.closed.load(Ordering::Acquire) {
+            self.leave();
+            return Err(AgentShutdownError::NotAccepting);
+        }
+        Ok(RunGuard { gate: self.clone() })
+    }
+
+    pub(crate) fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+
+    /// Ask in-flight runs to stop at their next step.
+    pub(crate) fn request_stop(&self) {
+        self.stop_requested.store(true, Ordering::Release);
+    }
+
+    pub(crate) fn stop_requested(&self) -> bool {
+        self.stop_requested.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn record_stop(&self) {
+        self.stops.fetch_add(1, Ordering::AcqRel);
+    }
+
+    pub(crate) fn stops(&self) -> usize {
+        self.stops.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn record_checkpoint(&self) {
+        self.checkpoints.fetch_add(1, Ordering::AcqRel);
+    }
+
+    pub(crate) fn checkpoints(&self) -> usize {
+        self.checkpoints.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Acquire)
+    }
+
+    /// Resolve once no run is in flight.
+    pub(crate) async fn wait_idle(&self) {
+        loop {
+            let idle = self.idle.notified();
+            tokio::pin!(idle);
+            idle.as_mut().enable();
+            if self.in_flight() == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+
+    fn leave(&self) {
+        if self.in_flight.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.idle.notify_waiters();
+        }
+    }
+}
+
+/// Keeps a run registered with its [`RunGate`] until dropped.
+pub(crate) struct RunGuard {
+    gate: Arc<RunGate>,
+}
+
+impl Drop for RunGuard {
+    fn drop(&mut self) {
+        self.gate.leave();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{config::DeepAgentConfig, runtime::create_deep_agent_from_config};
+    use crate::test_support::SlowPlanner;
+
+    use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+    use agents_core::persistence::{Checkpointer, InMemoryCheckpointer, ThreadId};
+    use agents_core::state::AgentStateSnapshot;
+
+    #[tokio::test]
+    async fn gate_rejects_new_runs_and_reports_idle() {
+        let gate = Arc::new(RunGate::default());
+        let guard = gate.enter().unwrap();
+        gate.close();
+        assert_eq!(gate.enter().err(), Some(AgentShutdownError::NotAccepting));
+        assert_eq!(gate.in_flight(), 1);
+
+        drop(guard);
+        tokio::time::timeout(Duration::from_secs(1), gate.wait_idle())
+            .await
+            .expect("gate should be idle");
+    }
+
+    #[tokio::test]
+    async fn shutdown_drains_in_flight_runs() {
+        let agent = Arc::new(create_deep_agent_from_config(DeepAgentConfig::new(
+            "assist",
+            Arc::new(SlowPlanner {
+                delay: Duration::from_millis(100),
+                respond: true,
+            }),
+        )));
+
+        let running = agent.clone();
+        let run = tokio::spawn(async move {
+            running
+                .handle_message("hello", Arc::new(AgentStateSnapshot::default()))
+                .await
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let report = agent.shutdown(Duration::from_secs(5)).await.unwrap();
+        assert!(report.drained);
+        assert_eq!(report.interrupted_runs, 0);
+        assert!(report.events_flushed);
+        assert_eq!(run.await.unwrap().unwrap().content.as_text(), Some("done"));
+
+        let err = agent
+            .handle_message("too late", Arc::new(AgentStateSnapshot::default()))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<AgentShutdownError>(),
+            Some(&AgentShutdownError::NotAccepting)
+        );
+        assert!(!agent.is_accepting());
+    }
+
+    #[tokio::test]
+    async fn shutdown_interrupts_and_checkpoints_runs_past_grace() {
+        let checkpointer = Arc::new(InMemoryCheckpointer::new());
+        let agent = Arc::new(create_deep_agent_from_config(
+            DeepAgentConfig::new(
+                "assist",
+                Arc::new(SlowPlanner {
+                    delay: Duration::from_millis(30),
+                    respond: false,
+                }),
+            )
+            .with_max_iterations(1_000)
+            .with_checkpointer(checkpointer.clone()),
+        ));
+
+        let running = agent.clone();
+        let run = tokio::spawn(async move {
+            running
+                .handle_message("work forever", Arc::new(AgentStateSnapshot::default()))
+                .await
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let report = agent.shutdown(Duration::from_millis(50)).await.unwrap();
+        assert!(!report.drained);
+        assert_eq!(report.interrupted_runs, 1);
+        assert!(report.checkpointed);
+
+        let err = run.await.unwrap().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AgentShutdownError>(),
+            Some(AgentShutdownError::Interrupted { .. })
+        ));
+        assert!(checkpointer
+            .load_state(&ThreadId::default())
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    /// Streams its answer after a pause.
+    struct SlowStreamModel;
+
+    #[async_trait::async_trait]
+    impl agents_core::llm::LanguageModel for SlowStreamModel {
+        async fn generate(
+            &self,
+            _request: agents_core::llm::LlmRequest,
+        ) -> anyhow::Result<agents_core::llm::LlmResponse> {
+            unreachable!("only streamed")
+        }
+
+        async fn generate_stream(
+            &self,
+            _request: agents_core::llm::LlmRequest,
+        ) -> anyhow::Result<agents_core::llm::ChunkStream> {
+            Ok(Box::pin(futures::stream::once(async {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                Ok(agents_core::llm::StreamChunk::Done {
+                    message: AgentMessage {
+                        role: MessageRole::Agent,
+                        content: MessageContent::Text("done".into()),
+                        metadata: None,
+                    },
+                })
+            })))
+        }
+    }
+
+    #[tokio::test]
+    async fn streams_past_grace_are_not_reported_as_interrupted() {
+        use agents_core::agent::AgentHandle;
+        use futures::StreamExt;
+
+        let agent = Arc::new(create_deep_agent_from_config(
+            DeepAgentConfig::new(
+                "assist",
+                Arc::new(crate::planner::LlmBackedPlanner::new(Arc::new(
+                    SlowStreamModel,
+                ))),
+            )
+            .with_checkpointer(Arc::new(InMemoryCheckpointer::new())),
+        ));
+        let mut stream = agent
+            .handle_message_stream(
+                AgentMessage {
+                    role: MessageRole::User,
+                    content: MessageContent::Text("stream it".into()),
+                    metadata: None,
+                },
+                Arc::new(AgentStateSnapshot::default()),
+            )
+            .await
+            .unwrap();
+        let streamed = tokio::spawn(async move { while stream.next().await.is_some() {} });
+
+        // The stream never reaches a step, so it neither stops nor checkpoints
+        let report = agent.shutdown(Duration::from_millis(50)).await.unwrap();
+        assert!(!report.drained);
+        assert_eq!(report.interrupted_runs, 0);
+        assert!(report.checkpointed);
+        streamed.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn interrupted_thread_runs_checkpoint_their_own_thread() {
+        let checkpointer = Arc::new(InMemoryCheckpointer::new());
+        let agent = Arc::new(create_deep_agent_from_config(
+            DeepAgentConfig::new(
+                "assist",
+                Arc::new(SlowPlanner {
+                    delay: Duration::from_millis(30),
+                    respond: false,
+                }),
+            )
+            .with_max_iterations(1_000)
+            .with_checkpointer(checkpointer.clone()),
+        ));
+        let thread_id = ThreadId::from("customer-7");
+        let mut saved = AgentStateSnapshot::default();
+        saved.insert_file("notes.md", "ongoing");
+        checkpointer.save_state(&thread_id, &saved).await.unwrap();
+
+        let running = agent.clone();
+        let thread = thread_id.clone();
+        let run = tokio::spawn(async move {
+            running
+                .handle_message_for_thread(&thread, "work forever")
+                .await
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let report = agent.shutdown(Duration::from_millis(50)).await.unwrap();
+        assert_eq!(report.interrupted_runs, 1);
+        assert!(report.checkpointed);
+        let err = run.await.unwrap().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AgentShutdownError>(),
+            Some(AgentShutdownError::Interrupted { .. })
+        ));
+
+        // The interrupted run wrote the thread, keeping what it had loaded
+        let reloaded = checkpointer.load_state(&thread_id).await.unwrap().unwrap();
+        assert!(reloaded.correlation_id.is_some());
+        assert_eq!(
+            reloaded.files.get("notes.md").map(String::as_str),
+            Some("ongoing")
+        );
+        assert!(checkpointer
+            .load_state(&ThreadId::default())
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,254 @@
+//! Skills: reusable agent capabilities packaged as crates
+//!
+//! A [`Skill`] bundles what an agent needs for one capability: tools, instructions
+//! for the system prompt, sub-agents, and the [`SkillRequirement`]s the host agent
+//! must meet. Skills are plain Rust types, so they are published and versioned as
+//! crates and registered with one builder call:
+//!
+//! ```ignore
+//! let agent = ConfigurableAgentBuilder::new("You are a travel assistant")
+//!     .with_model(model)
+//!     .with_skill(WeatherSkill::default())
+//!     .build()?;
+//! ```
+//!
+//! The skill's tools and sub-agents join the agent's own, its instructions are
+//! appended to the system prompt under a heading naming the skill, and its
+//! requirements are checked with the rest of the configuration when the agent is
+//! built. Duplicate tool or sub-agent names across skills are reported like any
+//! other duplicate.
+
+use std::fmt;
+
+use super::config::SubAgentConfig;
+use agents_core::tools::ToolBox;
+
+/// Something a skill needs from the agent it is registered with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkillRequirement {
+    /// A tool the skill's instructions rely on, registered or built in
+    Tool(String),
+    /// A checkpointer, for skills keeping data across turns
+    Checkpointer,
+    /// A cargo feature of `agents-runtime`, such as `mcp` or `toon`
+    Feature(String),
+}
+
+impl fmt::Display for SkillRequirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkillRequirement::Tool(name) => write!(f, "tool `{name}`"),
+            SkillRequirement::Checkpointer => write!(f, "a checkpointer"),
+            SkillRequirement::Feature(feature) => {
+                write!(f, "the `{feature}` feature of agents-runtime")
+            }
+        }
+    }
+}
+
+/// A reusable agent capability: tools, instructions and sub-agents.
+///
+/// Only [`name`](Skill::name) is required; the other parts default to empty.
+///
+/// ```ignore
+/// #[derive(Default)]
+/// pub struct WeatherSkill {
+///     api_key: String,
+/// }
+///
+/// impl Skill for WeatherSkill {
+///     fn name(&self) -> &str {
+///         "weather"
+///     }
+///
+///     fn tools(&self) -> Vec<ToolBox> {
+///         vec![Arc::new(ForecastTool::new(&self.api_key))]
+///     }
+///
+///     fn instructions(&self) -> Option<String> {
+///         Some("Use `forecast` before answering questions about the weather.".into())
+///     }
+///
+///     fn requirements(&self) -> Vec<SkillRequirement> {
+///         vec![SkillRequirement::Tool("write_file".into())]
+///     }
+/// }
+/// ```
+pub trait Skill: Send + Sync {
+    /// Short unique name, used in the system prompt and in configuration errors
+    fn name(&self) -> &str;
+
+    fn tools(&self) -> Vec<ToolBox> {
+        Vec::new()
+    }
+
+    /// Instructions appended to the agent's system prompt
+    fn instructions(&self) -> Option<String> {
+        None
+    }
+
+    fn subagents(&self) -> Vec<SubAgentConfig> {
+        Vec::new()
+    }
+
+    fn requirements(&self) -> Vec<SkillRequirement> {
+        Vec::new()
+    }
+}
+
+/// Cargo features `agents-runtime` was built with.
+pub(crate) fn enabled_features() -> Vec<&'static str> {
+    [
+        ("toon", cfg!(feature = "toon")),
+        ("mcp", cfg!(feature = "mcp")),
+        ("cedar", cfg!(feature = "cedar")),
+        ("debug-ui", cfg!(feature = "debug-ui")),
+        ("email", cfg!(feature = "email")),
+        ("telegram", cfg!(feature = "telegram")),
+        ("twilio", cfg!(feature = "twilio")),
+        ("xlsx", cfg!(feature = "xlsx")),
+        ("charts", cfg!(feature = "charts")),
+        ("sql-postgres", cfg!(feature = "sql-postgres")),
+        ("sql-mysql", cfg!(feature = "sql-mysql")),
+        ("browser", cfg!(feature = "browser")),
+    ]
+    .into_iter()
+    .filter_map(|(feature, enabled)| enabled.then_some(feature))
+    .collect()
+}
+
+/// Instructions a skill adds to the system prompt: `prompt` followed by a section
+/// per skill with instructions.
+pub(crate) fn with_skill_instructions(
+    mut prompt: String,
+    instructions: &[(String, String)],
+) -> String {
+    for (skill, text) in instructions {
+        prompt.push_str(&format!("\n\n## Skill: {skill}\n\n{}", text.trim()));
+    }
+    prompt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{ConfigIssue, ConfigurableAgentBuilder};
+    use agents_core::agent::{PlannerAction, PlannerContext, PlannerDecision, PlannerHandle};
+    use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+    use agents_core::state::AgentStateSnapshot;
+    use agents_core::tools::{ToolParameterSchema, ToolResult};
+    use async_trait::async_trait;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    struct WeatherSkill;
+
+    impl Skill for WeatherSkill {
+        fn name(&self) -> &str {
+            "weather"
+        }
+
+        fn tools(&self) -> Vec<ToolBox> {
+            vec![agents_toolkit::tool_sync(
+                "forecast",
+                "Weather forecast",
+                ToolParameterSchema::object("Forecast", HashMap::new(), Vec::new()),
+                |_args, ctx| Ok(ToolResult::text(&ctx, "Sunny")),
+            )]
+        }
+
+        fn instructions(&self) -> Option<String> {
+            Some("Use `forecast` for weather questions.".into())
+        }
+
+        fn subagents(&self) -> Vec<SubAgentConfig> {
+            vec![SubAgentConfig::new(
+                "meteorologist",
+                "Explains weather patterns",
+                "You explain weather patterns.",
+            )]
+        }
+
+        fn requirements(&self) -> Vec<SkillRequirement> {
+            vec![
+                SkillRequirement::Tool("write_file".into()),
+                SkillRequirement::Checkpointer,
+            ]
+        }
+    }
+
+    /// Keeps the prompt and tools of the first request, then answers.
+    #[derive(Default)]
+    struct PromptPlanner(Mutex<Option<PlannerContext>>);
+
+    #[async_trait]
+    impl PlannerHandle for PromptPlanner {
+        async fn plan(
+            &self,
+            context: PlannerContext,
+            _state: Arc<AgentStateSnapshot>,
+        ) -> anyhow::Result<PlannerDecision> {
+            self.0.lock().unwrap().get_or_insert(context);
+            Ok(PlannerDecision {
+                next_action: PlannerAction::Respond {
+                    message: AgentMessage {
+                        role: MessageRole::Agent,
+                        content: MessageContent::Text("Done".into()),
+                        metadata: None,
+                    },
+                },
+            })
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    #[tokio::test]
+    async fn test_skills_add_tools_instructions_and_subagents() {
+        let planner = Arc::new(PromptPlanner::default());
+        let agent = ConfigurableAgentBuilder::new("You are a travel assistant")
+            .with_planner(planner.clone())
+            .with_checkpointer(Arc::new(
+                agents_core::persistence::InMemoryCheckpointer::new(),
+            ))
+            .with_skill(WeatherSkill)
+            .build()
+            .unwrap();
+        agent
+            .handle_message("Weather?", Arc::new(AgentStateSnapshot::default()))
+            .await
+            .unwrap();
+
+        let context = planner.0.lock().unwrap().take().unwrap();
+        assert!(context
+            .system_prompt
+            .contains("## Skill: weather\n\nUse `forecast` for weather questions."));
+        assert!(context.tools.iter().any(|tool| tool.name == "forecast"));
+        assert!(agent.subagent("meteorologist").is_some());
+    }
+
+    #[test]
+    fn test_unmet_requirements_fail_the_build() {
+        let error = ConfigurableAgentBuilder::new("You are a travel assistant")
+            .with_planner(Arc::new(PromptPlanner::default()))
+            .with_builtin_tools(["ls"])
+            .with_skill(WeatherSkill)
+            .validate()
+            .unwrap_err();
+        assert_eq!(
+            error.issues,
+            vec![
+                ConfigIssue::UnmetSkillRequirement {
+                    skill: "weather".into(),
+                    requirement: "tool `write_file`".into(),
+                },
+                ConfigIssue::UnmetSkillRequirement {
+                    skill: "weather".into(),
+                    requirement: "a checkpointer".into(),
+                },
+            ]
+        );
+    }
+}
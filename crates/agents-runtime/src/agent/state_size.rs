@@ -0,0 +1,265 @@
+//! State size metrics and thresholds
+//!
+//! Every checkpoint measures the thread's state: bytes of its messages and files
+//! and the number of tool output artifacts it keeps. The latest size of each
+//! thread is kept in memory, so [`DeepAgent::state_metrics`](super::DeepAgent::state_metrics)
+//! can report totals for the process and point at the largest thread.
+//!
+//! With [`StateSizeLimits`], a thread growing past a threshold is logged as a
+//! warning and reported as a `StateSizeExceeded` event, once per crossing, before
+//! runaway files or histories exhaust memory.
+
+use super::tool_output::TOOL_OUTPUT_ARTIFACT_DIR;
+use agents_core::events::StateSizeMetric;
+use agents_core::messaging::AgentMessage;
+use agents_core::persistence::ThreadId;
+use agents_core::state::AgentStateSnapshot;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Size of a thread's state at its latest checkpoint.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateSize {
+    /// Serialized bytes of the preloaded messages and the history of the runs
+    pub message_bytes: usize,
+    /// Bytes of file contents in the virtual filesystem
+    pub file_bytes: usize,
+    /// Files in the virtual filesystem, including ones not loaded
+    pub file_count: usize,
+    /// Files holding full tool outputs that were shortened
+    pub artifact_count: usize,
+    /// Bytes of the serialized state plus the history of the runs
+    pub total_bytes: usize,
+}
+
+impl StateSize {
+    /// Measure `state` together with the conversation `history` kept in memory.
+    pub fn measure(state: &AgentStateSnapshot, history: &[AgentMessage]) -> Self {
+        let history_bytes: usize = history.iter().map(message_bytes).sum();
+        let preloaded_bytes: usize = state.preloaded_messages.iter().map(message_bytes).sum();
+        let state_bytes = serde_json::to_vec(state).map(|v| v.len()).unwrap_or(0);
+        let artifact_prefix = format!("{}/", TOOL_OUTPUT_ARTIFACT_DIR);
+        Self {
+            message_bytes: preloaded_bytes + history_bytes,
+            file_bytes: state.files.values().map(String::len).sum(),
+            file_count: state.files.len() + state.deferred_files.len(),
+            artifact_count: state
+                .files
+                .keys()
+                .chain(&state.deferred_files)
+                .filter(|path| path.starts_with(&artifact_prefix))
+                .count(),
+            total_bytes: state_bytes + history_bytes,
+        }
+    }
+
+    /// Value of one of the measures.
+    pub fn get(&self, metric: StateSizeMetric) -> usize {
+        match metric {
+            StateSizeMetric::MessageBytes => self.message_bytes,
+            StateSizeMetric::FileBytes => self.file_bytes,
+            StateSizeMetric::Artifacts => self.artifact_count,
+            StateSizeMetric::TotalBytes => self.total_bytes,
+        }
+    }
+}
+
+fn message_bytes(message: &AgentMessage) -> usize {
+    serde_json::to_vec(message).map(|v| v.len()).unwrap_or(0)
+}
+
+/// Thresholds past which a thread's state is reported.
+///
+/// # Example
+///
+/// ```ignore
+/// let agent = ConfigurableAgentBuilder::new("You are a research assistant")
+///     .with_model(model)
+///     .with_checkpointer(checkpointer)
+///     .with_state_size_limits(
+///         StateSizeLimits::new()
+///             .with_max_file_bytes(20 * 1024 * 1024)
+///             .with_max_artifacts(200),
+///     )
+///     .build()?;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateSizeLimits {
+    pub max_message_bytes: Option<usize>,
+    pub max_file_bytes: Option<usize>,
+    pub max_artifacts: Option<usize>,
+    pub max_total_bytes: Option<usize>,
+}
+
+impl StateSizeLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_message_bytes(mut self, bytes: usize) -> Self {
+        self.max_message_bytes = Some(bytes);
+        self
+    }
+
+    pub fn with_max_file_bytes(mut self, bytes: usize) -> Self {
+        self.max_file_bytes = Some(bytes);
+        self
+    }
+
+    pub fn with_max_artifacts(mut self, artifacts: usize) -> Self {
+        self.max_artifacts = Some(artifacts);
+        self
+    }
+
+    pub fn with_max_total_bytes(mut self, bytes: usize) -> Self {
+        self.max_total_bytes = Some(bytes);
+        self
+    }
+
+    fn threshold(&self, metric: StateSizeMetric) -> Option<usize> {
+        match metric {
+            StateSizeMetric::MessageBytes => self.max_message_bytes,
+            StateSizeMetric::FileBytes => self.max_file_bytes,
+            StateSizeMetric::Artifacts => self.max_artifacts,
+            StateSizeMetric::TotalBytes => self.max_total_bytes,
+        }
+    }
+
+    /// Thresholds `current` is over and `previous` was not, with the value and
+    /// the threshold.
+    pub fn crossed(
+        &self,
+        previous: Option<&StateSize>,
+        current: &StateSize,
+    ) -> Vec<(StateSizeMetric, usize, usize)> {
+        [
+            StateSizeMetric::MessageBytes,
+            StateSizeMetric::FileBytes,
+            StateSizeMetric::Artifacts,
+            StateSizeMetric::TotalBytes,
+        ]
+        .into_iter()
+        .filter_map(|metric| {
+            let threshold = self.threshold(metric)?;
+            let value = current.get(metric);
+            let was_over = previous.is_some_and(|p| p.get(metric) > threshold);
+            (value > threshold && !was_over).then_some((metric, value, threshold))
+        })
+        .collect()
+    }
+}
+
+/// State sizes of the threads checkpointed by this process, added up.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateMetrics {
+    /// Threads measured since the process started
+    pub threads: usize,
+    pub message_bytes: usize,
+    pub file_bytes: usize,
+    pub file_count: usize,
+    pub artifact_count: usize,
+    pub total_bytes: usize,
+    /// Thread with the largest state, if any was measured
+    pub largest_thread: Option<ThreadId>,
+    pub largest_thread_bytes: usize,
+}
+
+/// Latest state size of each thread.
+#[derive(Debug, Default)]
+pub(crate) struct StateSizeTracker {
+    sizes: Mutex<HashMap<ThreadId, StateSize>>,
+}
+
+impl StateSizeTracker {
+    /// Keep the size of a thread, returning its previous size.
+    pub(crate) fn record(&self, thread_id: &ThreadId, size: StateSize) -> Option<StateSize> {
+        self.sizes
+            .lock()
+            .ok()
+            .and_then(|mut sizes| sizes.insert(thread_id.clone(), size))
+    }
+
+    pub(crate) fn get(&self, thread_id: &ThreadId) -> Option<StateSize> {
+        self.sizes
+            .lock()
+            .ok()
+            .and_then(|sizes| sizes.get(thread_id).copied())
+    }
+
+    pub(crate) fn remove(&self, thread_id: &ThreadId) {
+        if let Ok(mut sizes) = self.sizes.lock() {
+            sizes.remove(thread_id);
+        }
+    }
+
+    pub(crate) fn metrics(&self) -> StateMetrics {
+        let Ok(sizes) = self.sizes.lock() else {
+            return StateMetrics::default();
+        };
+        let mut metrics = StateMetrics {
+            threads: sizes.len(),
+            ..StateMetrics::default()
+        };
+        for (thread_id, size) in sizes.iter() {
+            metrics.message_bytes += size.message_bytes;
+            metrics.file_bytes += size.file_bytes;
+            metrics.file_count += size.file_count;
+            metrics.artifact_count += size.artifact_count;
+            metrics.total_bytes += size.total_bytes;
+            if metrics.largest_thread.is_none() || size.total_bytes > metrics.largest_thread_bytes {
+                metrics.largest_thread = Some(thread_id.clone());
+                metrics.largest_thread_bytes = size.total_bytes;
+            }
+        }
+        metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::messaging::{MessageContent, MessageRole};
+
+    #[test]
+    fn measures_threads_and_reports_each_crossing_once() {
+        let mut state = AgentStateSnapshot::default();
+        state
+            .files
+            .insert("notes.md".to_string(), "x".repeat(1_000));
+        state.files.insert(
+            format!("{}/search_1.txt", TOOL_OUTPUT_ARTIFACT_DIR),
+            "y".repeat(500),
+        );
+        state.deferred_files.insert("report.pdf".to_string());
+        let history = vec![AgentMessage {
+            role: MessageRole::User,
+            content: MessageContent::Text("hello".to_string()),
+            metadata: None,
+        }];
+
+        let size = StateSize::measure(&state, &history);
+        assert_eq!(size.file_bytes, 1_500);
+        assert_eq!(size.file_count, 3);
+        assert_eq!(size.artifact_count, 1);
+        assert!(size.message_bytes > 0);
+        assert!(size.total_bytes > size.file_bytes + size.message_bytes);
+
+        let limits = StateSizeLimits::new().with_max_file_bytes(1_200);
+        let tracker = StateSizeTracker::default();
+        let thread = "t1".to_string();
+        let previous = tracker.record(&thread, size);
+        assert_eq!(
+            limits.crossed(previous.as_ref(), &size),
+            vec![(StateSizeMetric::FileBytes, 1_500, 1_200)]
+        );
+        let previous = tracker.record(&thread, size);
+        assert!(limits.crossed(previous.as_ref(), &size).is_empty());
+
+        tracker.record(&"t2".to_string(), StateSize::default());
+        let metrics = tracker.metrics();
+        assert_eq!(metrics.threads, 2);
+        assert_eq!(metrics.file_bytes, 1_500);
+        assert_eq!(metrics.largest_thread.as_deref(), Some("t1"));
+    }
+}
@@ -0,0 +1,274 @@
+//! Coalescing streamed text deltas into larger chunks
+//!
+//! Providers emit a delta per token, which over SSE or WebSockets means thousands of
+//! tiny frames per response. The adapter here merges consecutive deltas and flushes
+//! them after a delay, once enough characters are buffered, or at the end of a
+//! sentence. It is pull-based: nothing is read from the model stream while the
+//! consumer is busy, so a slow client applies backpressure to the provider instead
+//! of growing a buffer.
+
+use agents_core::agent::AgentStream;
+use agents_core::llm::StreamChunk;
+use futures::StreamExt;
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Default time a delta may wait in the buffer before it is flushed.
+pub const DEFAULT_COALESCE_DELAY: Duration = Duration::from_millis(50);
+
+/// Default number of buffered characters that forces a flush.
+pub const DEFAULT_COALESCE_MAX_CHARS: usize = 256;
+
+/// Options controlling how text deltas are merged before they reach the consumer.
+///
+/// Flushes happen when any enabled condition is met. `Done` and `Error` chunks are
+/// never delayed; buffered text is always flushed right before them.
+///
+/// # Example
+///
+/// ```ignore
+/// let agent = ConfigurableAgentBuilder::new("You are a helpful assistant")
+///     .with_model(model)
+///     .with_stream_coalescing(
+///         StreamCoalescing::new()
+///             .with_max_delay(Duration::from_millis(100))
+///             .with_sentence_flush(true),
+///     )
+///     .build()?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamCoalescing {
+    /// Flush when the oldest buffered delta has waited this long
+    pub max_delay: Option<Duration>,
+    /// Flush once the buffer holds at least this many characters
+    pub max_chars: Option<usize>,
+    /// Flush whenever the buffer ends a sentence or a line
+    pub flush_on_sentence: bool,
+}
+
+impl Default for StreamCoalescing {
+    fn default() -> Self {
+        Self {
+            max_delay: Some(DEFAULT_COALESCE_DELAY),
+            max_chars: Some(DEFAULT_COALESCE_MAX_CHARS),
+            flush_on_sentence: false,
+        }
+    }
+}
+
+impl StreamCoalescing {
+    /// Flush every 50 ms or 256 characters, whichever comes first.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = Some(max_delay);
+        self
+    }
+
+    pub fn without_max_delay(mut self) -> Self {
+        self.max_delay = None;
+        self
+    }
+
+    pub fn with_max_chars(mut self, max_chars: usize) -> Self {
+        self.max_chars = Some(max_chars);
+        self
+    }
+
+    pub fn without_max_chars(mut self) -> Self {
+        self.max_chars = None;
+        self
+    }
+
+    pub fn with_sentence_flush(mut self, flush_on_sentence: bool) -> Self {
+        self.flush_on_sentence = flush_on_sentence;
+        self
+    }
+
+    /// Wrap `stream` so that text deltas are coalesced according to these options.
+    pub fn apply(&self, stream: AgentStream) -> AgentStream {
+        coalesce_stream(stream, self.clone())
+    }
+
+    fn should_flush(&self, buffer: &str, buffered_chars: usize) -> bool {
+        if self.max_chars.is_some_and(|max| buffered_chars >= max) {
+            return true;
+        }
+        self.flush_on_sentence && ends_sentence(buffer)
+    }
+}
+
+fn ends_sentence(text: &str) -> bool {
+    if text.ends_with('\n') {
+        return true;
+    }
+    text.trim_end_matches([' ', '"', '\'', ')', ']', '”', '’'])
+        .ends_with(['.', '!', '?', '…', '。', '！', '？'])
+}
+
+struct Coalescer {
+    inner: AgentStream,
+    config: StreamCoalescing,
+    buffer: String,
+    buffered_chars: usize,
+    deadline: Option<Instant>,
+    ready: VecDeque<anyhow::Result<StreamChunk>>,
+    finished: bool,
+}
+
+impl Coalescer {
+    fn flush(&mut self) {
+        if !self.buffer.is_empty() {
+            let text = std::mem::take(&mut self.buffer);
+            self.ready.push_back(Ok(StreamChunk::TextDelta(text)));
+        }
+        self.buffered_chars = 0;
+        self.deadline = None;
+    }
+
+    fn push_delta(&mut self, delta: String) {
+        if self.buffer.is_empty() {
+            self.deadline = self.config.max_delay.map(|delay| Instant::now() + delay);
+        }
+        self.buffered_chars += delta.chars().count();
+        self.buffer.push_str(&delta);
+        if self.config.should_flush(&self.buffer, self.buffered_chars) {
+            self.flush();
+        }
+    }
+
+    async fn next_chunk(&mut self) -> Option<anyhow::Result<StreamChunk>> {
+        loop {
+            if let Some(chunk) = self.ready.pop_front() {
+                return Some(chunk);
+            }
+            if self.finished {
+                return None;
+            }
+
+            let next = match self.deadline {
+                Some(deadline) => {
+                    match tokio::time::timeout_at(deadline, self.inner.next()).await {
+                        Ok(next) => next,
+                        Err(_) => {
+                            self.flush();
+                            continue;
+                        }
+                    }
+                }
+                None => self.inner.next().await,
+            };
+
+            match next {
+                Some(Ok(StreamChunk::TextDelta(delta))) => self.push_delta(delta),
+                Some(other) => {
+                    self.flush();
+                    self.ready.push_back(other);
+                }
+                None => {
+                    self.flush();
+                    self.finished = true;
+                }
+            }
+        }
+    }
+}
+
+/// Coalesce the text deltas of `stream` according to `config`.
+pub fn coalesce_stream(stream: AgentStream, config: StreamCoalescing) -> AgentStream {
+    let coalescer = Coalescer {
+        inner: stream,
+        config,
+        buffer: String::new(),
+        buffered_chars: 0,
+        deadline: None,
+        ready: VecDeque::new(),
+        finished: false,
+    };
+
+    Box::pin(futures::stream::unfold(
+        coalescer,
+        |mut coalescer| async move {
+            let chunk = coalescer.next_chunk().await?;
+            Some((chunk, coalescer))
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+
+    fn deltas(parts: &[&str]) -> AgentStream {
+        let mut chunks: Vec<anyhow::Result<StreamChunk>> = parts
+            .iter()
+            .map(|part| Ok(StreamChunk::TextDelta(part.to_string())))
+            .collect();
+        chunks.push(Ok(StreamChunk::Done {
+            message: AgentMessage {
+                role: MessageRole::Agent,
+                content: MessageContent::Text(parts.concat()),
+                metadata: None,
+            },
+        }));
+        Box::pin(futures::stream::iter(chunks))
+    }
+
+    async fn collect(stream: AgentStream) -> Vec<String> {
+        stream
+            .map(|chunk| match chunk.unwrap() {
+                StreamChunk::TextDelta(text) => text,
+                StreamChunk::ToolCallStarted { name, .. } => format!("<tool {name}>"),
+                StreamChunk::Done { .. } => "<done>".to_string(),
+                StreamChunk::Error(e) => format!("<error {e}>"),
+            })
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn merges_deltas_up_to_max_chars() {
+        let config = StreamCoalescing::new()
+            .without_max_delay()
+            .with_max_chars(6);
+        let chunks = collect(config.apply(deltas(&["Hel", "lo", " wo", "rld", "!"]))).await;
+        assert_eq!(chunks, vec!["Hello wo", "rld!", "<done>"]);
+    }
+
+    #[tokio::test]
+    async fn flushes_at_sentence_boundaries() {
+        let config = StreamCoalescing::new()
+            .without_max_delay()
+            .without_max_chars()
+            .with_sentence_flush(true);
+        let chunks = collect(config.apply(deltas(&[
+            "First", " one.", " Second", " one?\"", " Third", "\n", "tail",
+        ])))
+        .await;
+        assert_eq!(
+            chunks,
+            vec!["First one.", " Second one?\"", " Third\n", "tail", "<done>"]
+        );
+    }
+
+    #[tokio::test]
+    async fn flushes_buffered_text_after_max_delay() {
+        let slow: AgentStream = Box::pin(futures::stream::iter(["a", "b", "c"]).then(
+            |part| async move {
+                if part == "c" {
+                    tokio::time::sleep(Duration::from_millis(300)).await;
+                }
+                Ok(StreamChunk::TextDelta(part.to_string()))
+            },
+        ));
+
+        let delayed = StreamCoalescing::new()
+            .without_max_chars()
+            .with_max_delay(Duration::from_millis(20));
+        assert_eq!(collect(delayed.apply(slow)).await, vec!["ab", "c"]);
+    }
+}
@@ -0,0 +1,174 @@
+//! Teeing a streamed run to observers
+//!
+//! `handle_message_stream` hands its chunks to the caller, typically an HTTP
+//! handler forwarding them to the client. The tee here copies every chunk as the
+//! caller receives it, after coalescing, to two kinds of observers:
+//!
+//! - subscribers from [`DeepAgent::subscribe_stream`](super::DeepAgent::subscribe_stream),
+//!   e.g. WebSocket observers of a conversation, which receive [`TeedChunk`]s in order;
+//! - event broadcasters that support streaming, which receive each text delta as a
+//!   `StreamingToken` event.
+//!
+//! Observers never slow the caller down: subscribers that fall behind lose the oldest
+//! chunks, and broadcasts run in the background. Broadcasts may complete out of
+//! order, so streaming token events carry their position in the run.
+
+use agents_core::agent::AgentStream;
+use agents_core::events::{AgentEvent, EventDispatcher, EventMetadata, StreamingTokenEvent};
+use agents_core::llm::StreamChunk;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+/// Chunks buffered for each subscriber before the oldest are dropped
+pub const STREAM_SUBSCRIBER_BUFFER: usize = 1024;
+
+/// A chunk of a streamed run, as seen by subscribers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TeedChunk {
+    /// Correlation id of the run, shared by all its chunks and events
+    pub correlation_id: String,
+    pub agent_name: String,
+    /// Position of the chunk in the run, starting at 0
+    pub sequence: u64,
+    pub chunk: StreamChunk,
+}
+
+/// Fan-out of streamed runs to subscribers and broadcasters.
+#[derive(Clone)]
+pub(crate) struct StreamTee {
+    sender: broadcast::Sender<TeedChunk>,
+}
+
+impl Default for StreamTee {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(STREAM_SUBSCRIBER_BUFFER);
+        Self { sender }
+    }
+}
+
+impl StreamTee {
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<TeedChunk> {
+        self.sender.subscribe()
+    }
+
+    /// Wrap `stream` so that each chunk is copied to the observers as it is pulled.
+    pub(crate) fn tee(
+        &self,
+        stream: AgentStream,
+        correlation_id: String,
+        agent_name: String,
+        dispatcher: Option<Arc<EventDispatcher>>,
+    ) -> AgentStream {
+        let sender = self.sender.clone();
+        let mut sequence = 0;
+        Box::pin(stream.map(move |chunk| {
+            let teed = match &chunk {
+                Ok(chunk) => chunk.clone(),
+                Err(e) => StreamChunk::Error(e.to_string()),
+            };
+            if let (Some(dispatcher), StreamChunk::TextDelta(token)) = (&dispatcher, &teed) {
+                dispatcher.emit(AgentEvent::StreamingToken(StreamingTokenEvent {
+                    metadata: EventMetadata::new(
+                        "default".to_string(),
+                        correlation_id.clone(),
+                        None,
+                    ),
+                    agent_name: agent_name.clone(),
+                    token: token.clone(),
+                    sequence,
+                }));
+            }
+            if sender.receiver_count() > 0 {
+                // Fails only when the last subscriber left in the meantime
+                let _ = sender.send(TeedChunk {
+                    correlation_id: correlation_id.clone(),
+                    agent_name: agent_name.clone(),
+                    sequence,
+                    chunk: teed,
+                });
+            }
+            sequence += 1;
+            chunk
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::events::EventBroadcaster;
+    use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct TokenRecorder(Mutex<Vec<(u64, String)>>);
+
+    #[async_trait]
+    impl EventBroadcaster for TokenRecorder {
+        fn id(&self) -> &str {
+            "tokens"
+        }
+
+        async fn broadcast(&self, event: &AgentEvent) -> anyhow::Result<()> {
+            if let AgentEvent::StreamingToken(event) = event {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push((event.sequence, event.token.clone()));
+            }
+            Ok(())
+        }
+
+        fn supports_streaming(&self) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test]
+    async fn every_observer_sees_the_chunks_the_caller_sees() {
+        let recorder = Arc::new(TokenRecorder::default());
+        let dispatcher = Arc::new(EventDispatcher::new());
+        dispatcher.add_broadcaster(recorder.clone());
+        let tee = StreamTee::default();
+        let mut first = tee.subscribe();
+        let mut second = tee.subscribe();
+
+        let chunks: Vec<anyhow::Result<StreamChunk>> = vec![
+            Ok(StreamChunk::TextDelta("Hel".into())),
+            Ok(StreamChunk::TextDelta("lo".into())),
+            Ok(StreamChunk::Done {
+                message: AgentMessage {
+                    role: MessageRole::Agent,
+                    content: MessageContent::Text("Hello".into()),
+                    metadata: None,
+                },
+            }),
+        ];
+        let stream = tee.tee(
+            Box::pin(futures::stream::iter(chunks)),
+            "run-1".into(),
+            "support".into(),
+            Some(dispatcher.clone()),
+        );
+        assert_eq!(stream.collect::<Vec<_>>().await.len(), 3);
+        dispatcher.flush().await;
+
+        for subscriber in [&mut first, &mut second] {
+            let mut received = Vec::new();
+            while let Ok(teed) = subscriber.try_recv() {
+                assert_eq!(teed.correlation_id, "run-1");
+                received.push((teed.sequence, teed.chunk));
+            }
+            assert_eq!(received.len(), 3);
+            assert!(matches!(&received[1], (1, StreamChunk::TextDelta(text)) if text == "lo"));
+            assert!(matches!(received[2], (2, StreamChunk::Done { .. })));
+        }
+
+        let mut tokens = recorder.0.lock().unwrap().clone();
+        tokens.sort();
+        assert_eq!(tokens, vec![(0, "Hel".to_string()), (1, "lo".to_string())]);
+    }
+}
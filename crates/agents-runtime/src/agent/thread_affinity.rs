@@ -0,0 +1,337 @@
+//! Per-thread leases for running one agent on several replicas
+//!
+//! `DeepAgent::handle_message_for_thread` acquires the thread's lease from the
+//! configured [`ThreadLock`], loads the thread's checkpoint, runs the message and
+//! saves the state before releasing the lease. While the run is in progress the lease
+//! is renewed in the background; if it is lost anyway (the replica stalled past the
+//! lease and another one took over), the state is not saved so the new owner's
+//! progress is not clobbered.
+
+use agents_core::persistence::ThreadId;
+use agents_core::thread_lock::{LeaseRequest, ThreadLease, ThreadLock};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Default lease length; renewed every third of it while a run is in progress.
+pub const DEFAULT_LEASE: Duration = Duration::from_secs(30);
+
+/// Default time to wait for another replica to release a thread.
+pub const DEFAULT_LOCK_WAIT: Duration = Duration::from_secs(10);
+
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How an agent acquires per-thread leases.
+///
+/// # Example
+///
+/// ```ignore
+/// let lock = Arc::new(RedisThreadLock::new("redis://redis:6379").await?);
+/// let agent = ConfigurableAgentBuilder::new("You are a support agent")
+///     .with_model(model)
+///     .with_checkpointer(checkpointer)
+///     .with_thread_lock_config(
+///         ThreadLockConfig::new(lock)
+///             .with_lease(Duration::from_secs(60))
+///             .with_acquire_timeout(Duration::from_secs(5)),
+///     )
+///     .build()?;
+/// ```
+#[derive(Clone)]
+pub struct ThreadLockConfig {
+    pub lock: Arc<dyn ThreadLock>,
+    /// Identifies this replica; defaults to `$HOSTNAME` plus a random suffix
+    pub owner: String,
+    pub lease: Duration,
+    /// How long to wait for a busy thread before failing with `ThreadLockError::Busy`
+    pub acquire_timeout: Duration,
+    /// Take over live leases held by the same `owner` (for stable replica names)
+    pub reclaim_own_leases: bool,
+}
+
+impl std::fmt::Debug for ThreadLockConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ThreadLockConfig")
+            .field("owner", &self.owner)
+            .field("lease", &self.lease)
+            .field("acquire_timeout", &self.acquire_timeout)
+            .field("reclaim_own_leases", &self.reclaim_own_leases)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ThreadLockConfig {
+    pub fn new(lock: Arc<dyn ThreadLock>) -> Self {
+        let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "agent".to_string());
+        let suffix = uuid::Uuid::new_v4().simple().to_string();
+        Self {
+            lock,
+            owner: format!("{}-{}", host, &suffix[..8]),
+            lease: DEFAULT_LEASE,
+            acquire_timeout: DEFAULT_LOCK_WAIT,
+            reclaim_own_leases: false,
+        }
+    }
+
+    pub fn with_owner(mut self, owner: impl Into<String>) -> Self {
+        self.owner = owner.into();
+        self
+    }
+
+    pub fn with_lease(mut self, lease: Duration) -> Self {
+        self.lease = lease;
+        self
+    }
+
+    pub fn with_acquire_timeout(mut self, timeout: Duration) -> Self {
+        self.acquire_timeout = timeout;
+        self
+    }
+
+    pub fn with_reclaim_own_leases(mut self, reclaim: bool) -> Self {
+        self.reclaim_own_leases = reclaim;
+        self
+    }
+
+    /// Wait for the thread's lease and keep it renewed until released.
+    pub(crate) async fn acquire(&self, thread_id: &ThreadId) -> anyhow::Result<HeldLease> {
+        let request = LeaseRequest {
+            thread_id: thread_id.clone(),
+            owner: self.owner.clone(),
+            duration: self.lease,
+            reclaim_own: self.reclaim_own_leases,
+        };
+        let started = std::time::Instant::now();
+        loop {
+            if let Some(lease) = self.lock.try_acquire(&request).await? {
+                tracing::debug!(thread_id = %thread_id, owner = %self.owner, "Acquired thread lease");
+                return Ok(HeldLease::start(self.lock.clone(), lease, self.lease));
+            }
+            if started.elapsed() >= self.acquire_timeout {
+                return Err(ThreadLockError::Busy {
+                    thread_id: thread_id.clone(),
+                    waited_ms: started.elapsed().as_millis() as u64,
+                }
+                .into());
+            }
+            tokio::time::sleep(RETRY_INTERVAL).await;
+        }
+    }
+}
+
+/// Errors from `handle_message_for_thread`.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ThreadLockError {
+    #[error("thread {thread_id} is being handled by another replica (waited {waited_ms} ms)")]
+    Busy { thread_id: ThreadId, waited_ms: u64 },
+    #[error("lease on thread {thread_id} was lost during the run; state was not saved")]
+    LeaseLost { thread_id: ThreadId },
+}
+
+/// A lease kept alive by a background renewal task.
+pub(crate) struct HeldLease {
+    lock: Arc<dyn ThreadLock>,
+    current: Arc<Mutex<Option<ThreadLease>>>,
+    thread_id: ThreadId,
+    heartbeat: JoinHandle<()>,
+}
+
+impl HeldLease {
+    fn start(lock: Arc<dyn ThreadLock>, lease: ThreadLease, duration: Duration) -> Self {
+        let thread_id = lease.thread_id.clone();
+        let current = Arc::new(Mutex::new(Some(lease)));
+        let heartbeat = tokio::spawn({
+            let lock = lock.clone();
+            let current = current.clone();
+            async move {
+                loop {
+                    tokio::time::sleep(duration / 3).await;
+                    let Some(lease) = current.lock().ok().and_then(|l| l.clone()) else {
+                        return;
+                    };
+                    match lock.renew(&lease, duration).await {
+                        Ok(renewed) => {
+                            let lost = renewed.is_none();
+                            if let Ok(mut slot) = current.lock() {
+                                *slot = renewed;
+                            }
+                            if lost {
+                                tracing::warn!(thread_id = %lease.thread_id, "Thread lease was taken over");
+                                return;
+                            }
+                        }
+                        // Keep the old expiry; `ensure_held` fails once it passes
+                        Err(e) => tracing::warn!(
+                            thread_id = %lease.thread_id,
+                            error = %e,
+                            "Failed to renew thread lease"
+                        ),
+                    }
+                }
+            }
+        });
+        Self {
+            lock,
+            current,
+            thread_id,
+            heartbeat,
+        }
+    }
+
+    /// Fail unless the lease is still held and unexpired.
+    pub(crate) fn ensure_held(&self) -> Result<(), ThreadLockError> {
+        let held = self
+            .current
+            .lock()
+            .ok()
+            .and_then(|lease| lease.clone())
+            .is_some_and(|lease| !lease.is_expired());
+        if held {
+            Ok(())
+        } else {
+            Err(ThreadLockError::LeaseLost {
+                thread_id: self.thread_id.clone(),
+            })
+        }
+    }
+
+    pub(crate) async fn release(self) {
+        self.heartbeat.abort();
+        let lease = self.current.lock().ok().and_then(|mut l| l.take());
+        if let Some(lease) = lease {
+            if let Err(e) = self.lock.release(&lease).await {
+                tracing::warn!(thread_id = %self.thread_id, error = %e, "Failed to release thread lease");
+            }
+        }
+    }
+}
+
+impl Drop for HeldLease {
+    fn drop(&mut self) {
+        // Dropped without `release` (e.g. the run was cancelled): stop renewing and
+        // let the lease expire
+        self.heartbeat.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{config::DeepAgentConfig, runtime::create_deep_agent_from_config};
+    use crate::test_support::SlowPlanner;
+
+    use agents_core::persistence::{Checkpointer, InMemoryCheckpointer};
+
+    use agents_core::thread_lock::InMemoryThreadLock;
+    use async_trait::async_trait;
+
+    fn replica(
+        name: &str,
+        lock: Arc<dyn ThreadLock>,
+        checkpointer: Arc<InMemoryCheckpointer>,
+        planner_delay: Duration,
+        lease: Duration,
+    ) -> crate::agent::DeepAgent {
+        create_deep_agent_from_config(
+            DeepAgentConfig::new("assist", Arc::new(SlowPlanner::answering(planner_delay)))
+                .with_checkpointer(checkpointer)
+                .with_thread_lock(
+                    ThreadLockConfig::new(lock)
+                        .with_owner(name)
+                        .with_lease(lease)
+                        .with_acquire_timeout(Duration::from_millis(50)),
+                ),
+        )
+    }
+
+    #[tokio::test]
+    async fn second_replica_waits_for_the_thread() {
+        let lock = Arc::new(InMemoryThreadLock::new());
+        let checkpointer = Arc::new(InMemoryCheckpointer::new());
+        let a = Arc::new(replica(
+            "a",
+            lock.clone(),
+            checkpointer.clone(),
+            Duration::from_millis(300),
+            DEFAULT_LEASE,
+        ));
+        let b = replica(
+            "b",
+            lock.clone(),
+            checkpointer.clone(),
+            Duration::ZERO,
+            DEFAULT_LEASE,
+        );
+        let thread: ThreadId = "customer-42".into();
+
+        let running = {
+            let (a, thread) = (a.clone(), thread.clone());
+            tokio::spawn(async move { a.handle_message_for_thread(&thread, "first").await })
+        };
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(lock.holder(&thread).as_deref(), Some("a"));
+
+        let err = b
+            .handle_message_for_thread(&thread, "second")
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ThreadLockError>(),
+            Some(ThreadLockError::Busy { .. })
+        ));
+
+        running.await.unwrap().unwrap();
+        assert!(lock.holder(&thread).is_none());
+        assert!(checkpointer.load_state(&thread).await.unwrap().is_some());
+        b.handle_message_for_thread(&thread, "second")
+            .await
+            .unwrap();
+    }
+
+    /// Grants leases but reports every renewal as lost.
+    struct StolenLock(InMemoryThreadLock);
+
+    #[async_trait]
+    impl ThreadLock for StolenLock {
+        async fn try_acquire(&self, request: &LeaseRequest) -> anyhow::Result<Option<ThreadLease>> {
+            self.0.try_acquire(request).await
+        }
+
+        async fn renew(
+            &self,
+            _lease: &ThreadLease,
+            _duration: Duration,
+        ) -> anyhow::Result<Option<ThreadLease>> {
+            Ok(None)
+        }
+
+        async fn release(&self, lease: &ThreadLease) -> anyhow::Result<()> {
+            self.0.release(lease).await
+        }
+    }
+
+    #[tokio::test]
+    async fn lost_lease_skips_the_checkpoint() {
+        let checkpointer = Arc::new(InMemoryCheckpointer::new());
+        let agent = replica(
+            "a",
+            Arc::new(StolenLock(InMemoryThreadLock::new())),
+            checkpointer.clone(),
+            Duration::from_millis(100),
+            Duration::from_millis(30),
+        );
+        let thread: ThreadId = "customer-7".into();
+
+        let err = agent
+            .handle_message_for_thread(&thread, "hello")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ThreadLockError>(),
+            Some(&ThreadLockError::LeaseLost {
+                thread_id: thread.clone()
+            })
+        );
+        assert!(checkpointer.load_state(&thread).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,346 @@
+//! Automatic thread titles and summaries
+//!
+//! Session lists show a thread id unless something better is stored. With thread
+//! titling, a cheap model writes a short title and a one-line summary of a thread
+//! after its first turns and refreshes them periodically. Both are kept in the
+//! thread metadata under [`TITLE_KEY`] and [`SUMMARY_KEY`].
+//!
+//! The runtime does not keep per-thread transcripts, so excerpts of the turns since
+//! the last titling are carried in the metadata until the next model call, which
+//! also sees the previous title and summary.
+
+use agents_core::llm::{with_usage_purpose, LanguageModel, LlmRequest, UsagePurpose};
+use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+use agents_core::persistence::ThreadMetadata;
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Metadata key of the generated title.
+pub const TITLE_KEY: &str = "title";
+/// Metadata key of the generated one-line summary.
+pub const SUMMARY_KEY: &str = "summary";
+/// Metadata key of the number of turns the thread has had.
+pub const TURNS_KEY: &str = "title_turns";
+/// Metadata key of the turn count at the last titling.
+const TITLED_AT_KEY: &str = "titled_at_turn";
+/// Metadata key of the excerpts not summarized yet.
+const PENDING_KEY: &str = "title_pending";
+
+/// Excerpts kept for the next titling call; older ones are dropped.
+const MAX_PENDING_EXCERPTS: usize = 20;
+/// Titles longer than this many characters are cut.
+const MAX_TITLE_CHARS: usize = 80;
+
+const TITLING_PROMPT: &str = "You name conversations for a list of chat sessions. Given the \
+    previous title and summary, if any, and excerpts of the latest messages, reply with JSON \
+    only: {\"title\": \"...\", \"summary\": \"...\"}. The title has at most six words and no \
+    trailing punctuation; the summary is one sentence describing what the user wants and \
+    where the conversation stands.";
+
+/// Configuration for titling threads.
+///
+/// # Example
+///
+/// ```ignore
+/// let agent = ConfigurableAgentBuilder::new("You are a support agent")
+///     .with_model(model)
+///     .with_checkpointer(checkpointer)
+///     .with_thread_titles(ThreadTitleConfig::new(small_model).with_refresh_every(5))
+///     .build()?;
+///
+/// let metadata = agent.thread_metadata(&thread_id).await?.unwrap_or_default();
+/// println!("{}", metadata.get(TITLE_KEY).and_then(|v| v.as_str()).unwrap_or(&thread_id));
+/// ```
+#[derive(Clone)]
+pub struct ThreadTitleConfig {
+    /// Model writing titles and summaries, typically a small and cheap one
+    pub model: Arc<dyn LanguageModel>,
+    /// Number of turns after which the first title is written (default: 2)
+    pub first_after_turns: usize,
+    /// Turns between refreshes of the title and summary; `None` keeps the first
+    /// ones (default: 10)
+    pub refresh_every: Option<usize>,
+    /// Characters kept from each message for the next titling call (default: 500)
+    pub excerpt_chars: usize,
+}
+
+impl std::fmt::Debug for ThreadTitleConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ThreadTitleConfig")
+            .field("first_after_turns", &self.first_after_turns)
+            .field("refresh_every", &self.refresh_every)
+            .field("excerpt_chars", &self.excerpt_chars)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ThreadTitleConfig {
+    pub fn new(model: Arc<dyn LanguageModel>) -> Self {
+        Self {
+            model,
+            first_after_turns: 2,
+            refresh_every: Some(10),
+            excerpt_chars: 500,
+        }
+    }
+
+    pub fn with_first_after_turns(mut self, turns: usize) -> Self {
+        self.first_after_turns = turns.max(1);
+        self
+    }
+
+    pub fn with_refresh_every(mut self, turns: usize) -> Self {
+        self.refresh_every = Some(turns.max(1));
+        self
+    }
+
+    /// Keep the first title and summary for the life of the thread.
+    pub fn without_refresh(mut self) -> Self {
+        self.refresh_every = None;
+        self
+    }
+
+    pub fn with_excerpt_chars(mut self, chars: usize) -> Self {
+        self.excerpt_chars = chars;
+        self
+    }
+
+    /// Count a checkpointed turn in `metadata` and, when due, write a new title and
+    /// summary. Excerpts are kept for a later attempt when the model call fails.
+    pub async fn record_turn(
+        &self,
+        metadata: &mut ThreadMetadata,
+        messages: &[&AgentMessage],
+    ) -> anyhow::Result<()> {
+        let turns = count(metadata, TURNS_KEY) + 1;
+        metadata.values.insert(TURNS_KEY.to_string(), turns.into());
+
+        let mut pending: Vec<Value> = match metadata.values.remove(PENDING_KEY) {
+            Some(Value::Array(excerpts)) => excerpts,
+            _ => Vec::new(),
+        };
+        pending.extend(messages.iter().filter_map(|message| self.excerpt(message)));
+        let overflow = pending.len().saturating_sub(MAX_PENDING_EXCERPTS);
+        pending.drain(..overflow);
+
+        if !self.is_due(metadata, turns) {
+            metadata
+                .values
+                .insert(PENDING_KEY.to_string(), Value::Array(pending));
+            return Ok(());
+        }
+
+        match self.generate(metadata, &pending).await {
+            Ok((title, summary)) => {
+                metadata.values.insert(TITLE_KEY.to_string(), title.into());
+                metadata
+                    .values
+                    .insert(SUMMARY_KEY.to_string(), summary.into());
+                metadata
+                    .values
+                    .insert(TITLED_AT_KEY.to_string(), turns.into());
+                Ok(())
+            }
+            Err(e) => {
+                metadata
+                    .values
+                    .insert(PENDING_KEY.to_string(), Value::Array(pending));
+                Err(e)
+            }
+        }
+    }
+
+    fn is_due(&self, metadata: &ThreadMetadata, turns: u64) -> bool {
+        if metadata.get(TITLE_KEY).is_none() {
+            return turns >= self.first_after_turns as u64;
+        }
+        self.refresh_every
+            .is_some_and(|every| turns >= count(metadata, TITLED_AT_KEY) + every as u64)
+    }
+
+    fn excerpt(&self, message: &AgentMessage) -> Option<Value> {
+        let speaker = match message.role {
+            MessageRole::User => "User",
+            MessageRole::Agent => "Assistant",
+            MessageRole::Tool | MessageRole::System => return None,
+        };
+        let text = message.content.to_text();
+        let text = text.trim();
+        if text.is_empty() {
+            return None;
+        }
+        let mut excerpt: String = text.chars().take(self.excerpt_chars).collect();
+        if excerpt.len() < text.len() {
+            excerpt.push('…');
+        }
+        Some(format!("{speaker}: {excerpt}").into())
+    }
+
+    async fn generate(
+        &self,
+        metadata: &ThreadMetadata,
+        excerpts: &[Value],
+    ) -> anyhow::Result<(String, String)> {
+        let mut input = String::new();
+        for (key, label) in [
+            (TITLE_KEY, "Previous title"),
+            (SUMMARY_KEY, "Previous summary"),
+        ] {
+            if let Some(previous) = metadata.get(key).and_then(Value::as_str) {
+                input.push_str(&format!("{label}: {previous}\n"));
+            }
+        }
+        input.push_str("Latest messages:\n");
+        for excerpt in excerpts.iter().filter_map(Value::as_str) {
+            input.push_str(excerpt);
+            input.push('\n');
+        }
+
+        let request = LlmRequest::new(
+            TITLING_PROMPT,
+            vec![AgentMessage {
+                role: MessageRole::User,
+                content: MessageContent::Text(input),
+                metadata: None,
+            }],
+        );
+        let response =
+            with_usage_purpose(UsagePurpose::Summarization, self.model.generate(request)).await?;
+        parse_title(&response.message.content.to_text())
+            .ok_or_else(|| anyhow::anyhow!("Titling model returned no title"))
+    }
+}
+
+fn count(metadata: &ThreadMetadata, key: &str) -> u64 {
+    metadata.get(key).and_then(Value::as_u64).unwrap_or(0)
+}
+
+#[derive(Deserialize)]
+struct TitleReply {
+    title: String,
+    #[serde(default)]
+    summary: String,
+}
+
+/// Title and summary from the model's reply: the requested JSON, possibly in a code
+/// fence, or else the first line as title and the rest as summary.
+fn parse_title(reply: &str) -> Option<(String, String)> {
+    let trimmed = reply.trim();
+    let json = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|body| body.strip_suffix("```"))
+        .unwrap_or(trimmed)
+        .trim();
+    let (title, summary) = match serde_json::from_str::<TitleReply>(json) {
+        Ok(reply) => (reply.title, reply.summary),
+        Err(_) => {
+            let mut lines = trimmed.lines().map(str::trim).filter(|l| !l.is_empty());
+            let title = lines.next()?.to_string();
+            (title, lines.collect::<Vec<_>>().join(" "))
+        }
+    };
+    let title = title
+        .trim()
+        .trim_matches(|c| c == '"' || c == '\'' || c == '#' || c == '*')
+        .trim();
+    if title.is_empty() {
+        return None;
+    }
+    Some((
+        title.chars().take(MAX_TITLE_CHARS).collect(),
+        summary.trim().to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::llm::LlmResponse;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct TitleModel {
+        inputs: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl LanguageModel for TitleModel {
+        async fn generate(&self, request: LlmRequest) -> anyhow::Result<LlmResponse> {
+            let input = request.messages[0].content.to_text();
+            let mut inputs = self.inputs.lock().unwrap();
+            inputs.push(input);
+            let reply = format!(
+                "```json\n{{\"title\": \"Refund request #{}\", \"summary\": \"User wants a refund.\"}}\n```",
+                inputs.len()
+            );
+            Ok(LlmResponse::new(text(MessageRole::Agent, &reply)))
+        }
+    }
+
+    fn text(role: MessageRole, text: &str) -> AgentMessage {
+        AgentMessage {
+            role,
+            content: MessageContent::Text(text.to_string()),
+            metadata: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn titles_after_the_first_turns_and_refreshes_periodically() {
+        let model = Arc::new(TitleModel::default());
+        let config = ThreadTitleConfig::new(model.clone()).with_refresh_every(2);
+        let mut metadata = ThreadMetadata::new().with_tag("vip");
+        let user = text(MessageRole::User, "I want my money back for order 1042");
+        let reply = text(MessageRole::Agent, "Sorry to hear that, let me check.");
+
+        config
+            .record_turn(&mut metadata, &[&user, &reply])
+            .await
+            .unwrap();
+        assert!(metadata.get(TITLE_KEY).is_none());
+
+        config
+            .record_turn(&mut metadata, &[&user, &reply])
+            .await
+            .unwrap();
+        assert_eq!(metadata.get(TITLE_KEY), Some(&"Refund request #1".into()));
+        assert_eq!(
+            metadata.get(SUMMARY_KEY),
+            Some(&"User wants a refund.".into())
+        );
+        let inputs = model.inputs.lock().unwrap().clone();
+        assert_eq!(inputs[0].matches("User: I want my money back").count(), 2);
+        assert!(metadata.has_tag("vip"));
+
+        config
+            .record_turn(&mut metadata, &[&user, &reply])
+            .await
+            .unwrap();
+        assert_eq!(model.inputs.lock().unwrap().len(), 1);
+
+        config
+            .record_turn(&mut metadata, &[&user, &reply])
+            .await
+            .unwrap();
+        assert_eq!(metadata.get(TITLE_KEY), Some(&"Refund request #2".into()));
+        assert_eq!(metadata.get(TURNS_KEY), Some(&4.into()));
+        let inputs = model.inputs.lock().unwrap().clone();
+        assert!(inputs[1].starts_with("Previous title: Refund request #1\n"));
+        assert_eq!(inputs[1].matches("User: ").count(), 2);
+    }
+
+    #[test]
+    fn plain_text_replies_are_split_into_title_and_summary() {
+        assert_eq!(
+            parse_title("\"Billing question\"\nUser asks about a double charge."),
+            Some((
+                "Billing question".to_string(),
+                "User asks about a double charge.".to_string()
+            ))
+        );
+        assert_eq!(parse_title("  "), None);
+    }
+}
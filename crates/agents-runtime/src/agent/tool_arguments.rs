@@ -0,0 +1,45 @@
+//! Validation of model-provided tool arguments against the tool's schema
+//!
+//! In lenient mode arguments go to the tool as-is, so values the tool cannot read
+//! are coerced or dropped. In strict mode a call whose arguments do not match the
+//! parameter schema is not executed; the model receives a structured error listing
+//! each mismatch and can retry with corrected arguments.
+
+use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+use agents_core::tools::ArgumentError;
+use serde_json::json;
+
+/// How tool arguments proposed by the model are checked before execution.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use agents_runtime::agent::ToolArgumentValidation;
+///
+/// let agent = ConfigurableAgentBuilder::new("You are a booking assistant")
+///     .with_tools(tools)
+///     .with_tool_argument_validation(ToolArgumentValidation::Strict)
+///     .build()?;
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ToolArgumentValidation {
+    /// Pass arguments to the tool unchecked (default).
+    #[default]
+    Lenient,
+    /// Reject calls whose arguments do not match the tool's parameter schema.
+    Strict,
+}
+
+/// Tool message telling the model why its arguments for `tool_name` were rejected.
+pub(crate) fn rejection_message(tool_name: &str, errors: &[ArgumentError]) -> AgentMessage {
+    AgentMessage {
+        role: MessageRole::Tool,
+        content: MessageContent::Json(json!({
+            "error": "invalid_arguments",
+            "tool": tool_name,
+            "problems": errors,
+            "hint": "The tool was not executed. Call it again with arguments matching its parameter schema.",
+        })),
+        metadata: None,
+    }
+}
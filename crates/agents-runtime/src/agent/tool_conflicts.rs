@@ -0,0 +1,118 @@
+//! Resolution of name collisions between registered and built-in tools
+//!
+//! Tools passed to `with_tools` (e.g. from an MCP server) may share a name with a
+//! tool the agent's middleware provides, such as `read_file` or `task`. The
+//! [`ToolConflictStrategy`] decides which of them the model sees.
+
+use agents_core::tools::{Tool, ToolBox, ToolContext, ToolResult, ToolSchema};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// How a registered tool named like a built-in tool is handled.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use agents_runtime::agent::ToolConflictStrategy;
+///
+/// // The MCP server's `read_file` is exposed as `mcp_read_file`
+/// let agent = ConfigurableAgentBuilder::new("You are a helpful assistant")
+///     .with_tools(mcp_tools)
+///     .with_tool_conflicts(ToolConflictStrategy::prefix("mcp"))
+///     .build()?;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ToolConflictStrategy {
+    /// `build()` fails with a `ConfigIssue::BuiltinToolConflict` per collision.
+    /// Agents configured without the builder keep the built-in tool.
+    #[default]
+    Error,
+    /// The registered tool is exposed as `{prefix}_{name}`; the built-in keeps its name.
+    AutoPrefix(String),
+    /// The built-in tool wins and the registered tool is hidden.
+    PreferBuiltin,
+    /// The registered tool wins and the built-in tool is hidden.
+    PreferRegistered,
+}
+
+impl ToolConflictStrategy {
+    /// Expose colliding registered tools as `{prefix}_{name}`.
+    pub fn prefix(prefix: impl Into<String>) -> Self {
+        Self::AutoPrefix(prefix.into())
+    }
+
+    /// Whether a built-in tool named like a registered tool is exposed.
+    pub(crate) fn keeps_builtin(&self) -> bool {
+        !matches!(self, Self::PreferRegistered)
+    }
+
+    /// The registered tool to expose in place of `tool`, which collides with a
+    /// built-in tool, or `None` when it is hidden.
+    pub(crate) fn resolve(&self, tool: ToolBox) -> Option<ToolBox> {
+        match self {
+            Self::AutoPrefix(prefix) => {
+                let name = format!("{}_{}", prefix, tool.schema().name);
+                Some(Arc::new(RenamedTool { name, inner: tool }))
+            }
+            Self::PreferRegistered => Some(tool),
+            Self::Error | Self::PreferBuiltin => None,
+        }
+    }
+}
+
+/// A tool exposed to the model under another name.
+struct RenamedTool {
+    name: String,
+    inner: ToolBox,
+}
+
+#[async_trait]
+impl Tool for RenamedTool {
+    fn schema(&self) -> ToolSchema {
+        ToolSchema {
+            name: self.name.clone(),
+            ..self.inner.schema()
+        }
+    }
+
+    async fn execute(&self, args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+        self.inner.execute(args, ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::tools::ToolParameterSchema;
+    use std::collections::HashMap;
+
+    fn read_file() -> ToolBox {
+        agents_toolkit::tool_sync(
+            "read_file",
+            "Reads a remote file",
+            ToolParameterSchema::object("No parameters", HashMap::new(), Vec::new()),
+            |_args, ctx| Ok(ToolResult::text(&ctx, "remote")),
+        )
+    }
+
+    #[test]
+    fn test_strategies_resolve_registered_tool() {
+        let prefixed = ToolConflictStrategy::prefix("mcp")
+            .resolve(read_file())
+            .unwrap();
+        assert_eq!(prefixed.schema().name, "mcp_read_file");
+        assert_eq!(prefixed.schema().description, "Reads a remote file");
+
+        let registered = ToolConflictStrategy::PreferRegistered
+            .resolve(read_file())
+            .unwrap();
+        assert_eq!(registered.schema().name, "read_file");
+        assert!(!ToolConflictStrategy::PreferRegistered.keeps_builtin());
+
+        assert!(ToolConflictStrategy::PreferBuiltin
+            .resolve(read_file())
+            .is_none());
+        assert!(ToolConflictStrategy::Error.keeps_builtin());
+    }
+}
@@ -0,0 +1,156 @@
+//! Few-shot tool examples rendered into the system prompt
+//!
+//! Examples attached to a [`ToolSchema`] with [`ToolSchema::with_example`] are
+//! listed after the system prompt for the tools exposed on each turn, so tool
+//! selection also limits which examples the model sees.
+
+use agents_core::tools::{ToolExample, ToolSchema};
+use agents_core::toon::ToonEncoder;
+
+use crate::prompts::PromptFormat;
+
+/// Controls how tool examples are included in the prompt.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use agents_runtime::agent::ToolExamplesConfig;
+///
+/// let agent = ConfigurableAgentBuilder::new("You are an order assistant")
+///     .with_tool_examples(ToolExamplesConfig::default().with_max_per_tool(1))
+///     .build()?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolExamplesConfig {
+    /// Whether examples are rendered at all (default: true).
+    pub enabled: bool,
+    /// Maximum examples rendered per tool (default: 2).
+    pub max_per_tool: usize,
+    /// Rendering format; `None` follows the agent's prompt format.
+    pub format: Option<PromptFormat>,
+}
+
+impl Default for ToolExamplesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_per_tool: 2,
+            format: None,
+        }
+    }
+}
+
+impl ToolExamplesConfig {
+    /// Never render tool examples.
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_max_per_tool(mut self, max: usize) -> Self {
+        self.max_per_tool = max;
+        self
+    }
+
+    pub fn with_format(mut self, format: PromptFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Prompt section listing the examples of `schemas`, or `None` when there are none.
+    pub fn render(&self, schemas: &[ToolSchema]) -> Option<String> {
+        if !self.enabled || self.max_per_tool == 0 {
+            return None;
+        }
+        let format = self.format.unwrap_or_default();
+
+        let mut with_examples: Vec<_> = schemas.iter().filter(|s| !s.examples.is_empty()).collect();
+        if with_examples.is_empty() {
+            return None;
+        }
+        with_examples.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut section = String::from("## Tool Examples");
+        for schema in with_examples {
+            section.push_str(&format!("\n\n{}:", schema.name));
+            for example in schema.examples.iter().take(self.max_per_tool) {
+                section.push_str(&render_example(example, format));
+            }
+        }
+        Some(section)
+    }
+}
+
+fn render_example(example: &ToolExample, format: PromptFormat) -> String {
+    let mut rendered = String::from("\n-");
+    if let Some(description) = &example.description {
+        rendered.push(' ');
+        rendered.push_str(description);
+        rendered.push_str("\n ");
+    }
+    let args = match format {
+        PromptFormat::Json => Some(example.args.to_string()),
+        PromptFormat::Toon => ToonEncoder::compact()
+            .encode_json(&example.args)
+            .ok()
+            .map(|toon| indent(&toon)),
+    };
+    rendered.push_str(" args: ");
+    rendered.push_str(&args.unwrap_or_else(|| example.args.to_string()));
+    if let Some(result) = &example.result {
+        rendered.push_str("\n  result: ");
+        rendered.push_str(&indent(result));
+    }
+    rendered
+}
+
+/// Indent continuation lines so multi-line values stay under their example.
+fn indent(text: &str) -> String {
+    text.trim_end().replace('\n', "\n    ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn lookup_schema() -> ToolSchema {
+        ToolSchema::no_params("lookup_order", "Look up an order")
+            .with_example(
+                ToolExample::new(json!({ "order_id": "o-1" }))
+                    .with_description("Check a single order")
+                    .with_result(r#"{"status":"shipped"}"#),
+            )
+            .with_example(ToolExample::new(json!({ "order_id": "o-2" })))
+            .with_example(ToolExample::new(json!({ "order_id": "o-3" })))
+    }
+
+    #[test]
+    fn renders_capped_examples_for_tools_that_have_them() {
+        let schemas = vec![ToolSchema::no_params("ls", "List files"), lookup_schema()];
+        let section = ToolExamplesConfig::default().render(&schemas).unwrap();
+
+        assert_eq!(
+            section,
+            "## Tool Examples\n\n\
+             lookup_order:\n\
+             - Check a single order\n  \
+             args: {\"order_id\":\"o-1\"}\n  \
+             result: {\"status\":\"shipped\"}\n\
+             - args: {\"order_id\":\"o-2\"}"
+        );
+        assert!(!section.contains("ls:"));
+    }
+
+    #[test]
+    fn disabled_or_empty_renders_nothing() {
+        assert!(ToolExamplesConfig::disabled()
+            .render(&[lookup_schema()])
+            .is_none());
+        assert!(ToolExamplesConfig::default()
+            .render(&[ToolSchema::no_params("ls", "List files")])
+            .is_none());
+    }
+}
@@ -0,0 +1,244 @@
+//! Mocked tools for non-production environments
+//!
+//! Staging runs the same agent build as production, but must not charge cards or
+//! message customers. [`ToolMocks`] maps tool names to [`ToolMock`]s; enabled through
+//! a profile's [`ProfileSettings`](super::ProfileSettings), the mocked tools keep their
+//! schemas, so the model sees the same agent, while their calls are answered by the
+//! mocks instead of the real implementations. Sub-agents inherit the mocks.
+//!
+//! ```ignore
+//! let agent = ConfigurableAgentBuilder::new("You are a billing agent")
+//!     .with_tools(tools)
+//!     .with_profile_settings(
+//!         Profile::Staging,
+//!         ProfileSettings::staging().with_tool_mocks(
+//!             ToolMocks::new()
+//!                 .with_response("charge_card", json!({ "status": "succeeded", "id": "ch_test" }))
+//!                 .with_mock("send_sms", Arc::new(|args: &Value| Ok(json!({ "sent_to": args["to"] })))),
+//!         ),
+//!     )
+//!     .with_profile(Profile::from_env()?)
+//!     .build()?;
+//! ```
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use agents_core::tools::{Tool, ToolBox, ToolContext, ToolResult, ToolSchema};
+use async_trait::async_trait;
+use serde_json::Value;
+
+/// Answers the calls of a mocked tool.
+///
+/// Closures taking the call's arguments and returning a JSON result are mocks.
+#[async_trait]
+pub trait ToolMock: Send + Sync {
+    async fn respond(&self, args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult>;
+}
+
+#[async_trait]
+impl<F> ToolMock for F
+where
+    F: Fn(&Value) -> anyhow::Result<Value> + Send + Sync,
+{
+    async fn respond(&self, args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+        Ok(canned_result(&ctx, self(&args)?))
+    }
+}
+
+/// Mock answering every call with the same result.
+#[derive(Debug, Clone)]
+pub struct CannedResponse(pub Value);
+
+#[async_trait]
+impl ToolMock for CannedResponse {
+    async fn respond(&self, _args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+        Ok(canned_result(&ctx, self.0.clone()))
+    }
+}
+
+/// Strings are returned as text, other values as JSON.
+fn canned_result(ctx: &ToolContext, value: Value) -> ToolResult {
+    match value {
+        Value::String(text) => ToolResult::text(ctx, text),
+        value => ToolResult::json(ctx, value),
+    }
+}
+
+/// Mocks by tool name.
+#[derive(Clone, Default)]
+pub struct ToolMocks {
+    mocks: HashMap<String, Arc<dyn ToolMock>>,
+}
+
+impl std::fmt::Debug for ToolMocks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.names()).finish()
+    }
+}
+
+impl ToolMocks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer calls of `tool_name` with `mock`.
+    pub fn with_mock(mut self, tool_name: impl Into<String>, mock: Arc<dyn ToolMock>) -> Self {
+        self.mocks.insert(tool_name.into(), mock);
+        self
+    }
+
+    /// Answer every call of `tool_name` with `response`; strings are returned as
+    /// text, other values as JSON.
+    pub fn with_response(self, tool_name: impl Into<String>, response: impl Into<Value>) -> Self {
+        self.with_mock(tool_name, Arc::new(CannedResponse(response.into())))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mocks.is_empty()
+    }
+
+    pub fn contains(&self, tool_name: &str) -> bool {
+        self.mocks.contains_key(tool_name)
+    }
+
+    /// Names of the mocked tools, sorted.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.mocks.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Replace the mocked tools among `tools` with their mocks.
+    pub(crate) fn apply(&self, tools: &mut HashMap<String, ToolBox>) {
+        for (name, tool) in tools.iter_mut() {
+            if let Some(mock) = self.mocks.get(name) {
+                *tool = Arc::new(MockedTool {
+                    schema: tool.schema(),
+                    mock: mock.clone(),
+                });
+            }
+        }
+    }
+}
+
+/// A tool whose calls are answered by a mock.
+struct MockedTool {
+    schema: ToolSchema,
+    mock: Arc<dyn ToolMock>,
+}
+
+#[async_trait]
+impl Tool for MockedTool {
+    fn schema(&self) -> ToolSchema {
+        self.schema.clone()
+    }
+
+    async fn execute(&self, args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+        tracing::info!(tool_name = %self.schema.name, "🧪 Tool call answered by mock");
+        self.mock.respond(args, ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{ConfigurableAgentBuilder, Profile, ProfileSettings};
+    use agents_core::agent::{PlannerAction, PlannerContext, PlannerDecision, PlannerHandle};
+    use agents_core::messaging::{AgentMessage, MessageRole};
+    use agents_core::state::AgentStateSnapshot;
+    use agents_core::tools::ToolParameterSchema;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Counts real charges.
+    struct ChargeCard(Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl Tool for ChargeCard {
+        fn schema(&self) -> ToolSchema {
+            ToolSchema::new(
+                "charge_card",
+                "Charge the customer's card",
+                ToolParameterSchema::object("Charge", HashMap::new(), Vec::new()),
+            )
+        }
+
+        async fn execute(&self, _args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(ToolResult::text(&ctx, "charged for real"))
+        }
+    }
+
+    /// Charges once, then answers with the tool result.
+    struct ChargePlanner;
+
+    #[async_trait]
+    impl PlannerHandle for ChargePlanner {
+        async fn plan(
+            &self,
+            context: PlannerContext,
+            _state: Arc<AgentStateSnapshot>,
+        ) -> anyhow::Result<PlannerDecision> {
+            let next_action = match context.history.last() {
+                Some(last) if last.role == MessageRole::Tool => PlannerAction::Respond {
+                    message: AgentMessage {
+                        role: MessageRole::Agent,
+                        content: last.content.clone(),
+                        metadata: None,
+                    },
+                },
+                _ => PlannerAction::CallTool {
+                    tool_name: "charge_card".into(),
+                    payload: json!({ "amount": 42 }),
+                },
+            };
+            Ok(PlannerDecision { next_action })
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    async fn charge(profile: Profile, charges: &Arc<AtomicUsize>) -> String {
+        let mocks = ToolMocks::new().with_mock(
+            "charge_card",
+            Arc::new(|args: &Value| Ok(json!({ "status": "succeeded", "amount": args["amount"] }))),
+        );
+        let agent = ConfigurableAgentBuilder::new("bill")
+            .with_planner(Arc::new(ChargePlanner))
+            .with_tool(Arc::new(ChargeCard(charges.clone())))
+            .with_profile_settings(
+                Profile::Staging,
+                ProfileSettings::staging().with_tool_mocks(mocks),
+            )
+            .with_profile(profile)
+            .build()
+            .unwrap();
+        agent
+            .handle_message("Charge me", Arc::new(AgentStateSnapshot::default()))
+            .await
+            .unwrap()
+            .content
+            .to_text()
+    }
+
+    #[tokio::test]
+    async fn test_mocks_answer_tool_calls_only_under_their_profile() {
+        let charges = Arc::new(AtomicUsize::new(0));
+
+        let staged = charge(Profile::Staging, &charges).await;
+        assert_eq!(
+            serde_json::from_str::<Value>(&staged).unwrap(),
+            json!({ "status": "succeeded", "amount": 42 })
+        );
+        assert_eq!(charges.load(Ordering::SeqCst), 0);
+
+        assert_eq!(
+            charge(Profile::Development, &charges).await,
+            "charged for real"
+        );
+        assert_eq!(charges.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,340 @@
+//! Per-tool output budgets that keep oversized tool results out of the planner context
+//!
+//! A single tool call (a scraped web page, a large query result) can return more text
+//! than the model's context window can hold. When a budget is configured, results that
+//! exceed it are shortened before they are appended to the conversation history, either
+//! by keeping the head and tail of the output or by asking a language model for a summary.
+//! The untouched output is stored in the agent's virtual filesystem so the model can
+//! retrieve it later with `read_file`.
+
+use crate::middleware::token_tracking::TokenTrackingMiddleware;
+use agents_core::llm::{with_usage_purpose, LanguageModel, LlmRequest, UsagePurpose};
+use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Directory (in the agent's virtual filesystem) where full tool outputs are stored.
+pub const TOOL_OUTPUT_ARTIFACT_DIR: &str = "tool_outputs";
+
+/// Default character budget applied to every tool without an explicit limit.
+pub const DEFAULT_TOOL_OUTPUT_MAX_CHARS: usize = 20_000;
+
+const SUMMARY_SYSTEM_PROMPT: &str = "You condense tool outputs for an AI agent. \
+Summarize the output below, preserving concrete facts, numbers, identifiers, URLs and \
+error messages the agent may need. Reply with the summary only.";
+
+/// How an oversized tool output is shortened.
+#[derive(Clone, Default)]
+pub enum ToolOutputStrategy {
+    /// Keep the beginning and the end of the output and drop the middle.
+    #[default]
+    HeadTail,
+    /// Summarize the output with the given model.
+    ///
+    /// Falls back to head/tail truncation if the model call fails or the summary
+    /// itself exceeds the budget.
+    Summarize(Arc<dyn LanguageModel>),
+}
+
+impl std::fmt::Debug for ToolOutputStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::HeadTail => write!(f, "HeadTail"),
+            Self::Summarize(_) => write!(f, "Summarize(..)"),
+        }
+    }
+}
+
+/// Configuration for tool output budgets.
+///
+/// # Example
+///
+/// ```ignore
+/// let limits = ToolOutputConfig::new(8_000)
+///     .with_tool_limit("web_scrape", 4_000)
+///     .with_strategy(ToolOutputStrategy::Summarize(summary_model));
+///
+/// let agent = ConfigurableAgentBuilder::new("You are a researcher")
+///     .with_model(model)
+///     .with_tool_output_limits(limits)
+///     .build()?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct ToolOutputConfig {
+    /// Budget (in characters) for tools without an explicit limit. `None` leaves them untouched.
+    pub default_max_chars: Option<usize>,
+    /// Per-tool budgets that override `default_max_chars`.
+    pub tool_max_chars: HashMap<String, usize>,
+    /// How oversized outputs are shortened.
+    pub strategy: ToolOutputStrategy,
+    /// Store the full output under [`TOOL_OUTPUT_ARTIFACT_DIR`] when it is shortened.
+    pub store_artifacts: bool,
+}
+
+impl Default for ToolOutputConfig {
+    fn default() -> Self {
+        Self::new(DEFAULT_TOOL_OUTPUT_MAX_CHARS)
+    }
+}
+
+impl ToolOutputConfig {
+    /// Create a configuration with the same budget for every tool.
+    pub fn new(default_max_chars: usize) -> Self {
+        Self {
+            default_max_chars: Some(default_max_chars),
+            tool_max_chars: HashMap::new(),
+            strategy: ToolOutputStrategy::HeadTail,
+            store_artifacts: true,
+        }
+    }
+
+    /// Only budget the tools given via [`with_tool_limit`](Self::with_tool_limit).
+    pub fn per_tool_only() -> Self {
+        Self {
+            default_max_chars: None,
+            ..Self::new(DEFAULT_TOOL_OUTPUT_MAX_CHARS)
+        }
+    }
+
+    /// Route the summarizer's requests through `tracker` so their cost is attributed.
+    pub(crate) fn tracked_by(mut self, tracker: &TokenTrackingMiddleware) -> Self {
+        if let ToolOutputStrategy::Summarize(model) = self.strategy {
+            self.strategy = ToolOutputStrategy::Summarize(Arc::new(tracker.share(model)));
+        }
+        self
+    }
+
+    /// Set the budget for a specific tool.
+    pub fn with_tool_limit(mut self, tool_name: impl Into<String>, max_chars: usize) -> Self {
+        self.tool_max_chars.insert(tool_name.into(), max_chars);
+        self
+    }
+
+    /// Set the strategy used to shorten oversized outputs.
+    pub fn with_strategy(mut self, strategy: ToolOutputStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Enable or disable storing the full output as an artifact (enabled by default).
+    pub fn with_artifacts(mut self, enabled: bool) -> Self {
+        self.store_artifacts = enabled;
+        self
+    }
+
+    /// Budget that applies to `tool_name`, if any.
+    pub fn limit_for(&self, tool_name: &str) -> Option<usize> {
+        self.tool_max_chars
+            .get(tool_name)
+            .copied()
+            .or(self.default_max_chars)
+    }
+
+    /// Shorten `text` to fit `max_chars` using the configured strategy.
+    pub async fn shorten(&self, text: &str, max_chars: usize) -> String {
+        self.strategy.shorten(text, max_chars).await
+    }
+}
+
+impl ToolOutputStrategy {
+    /// Shorten `text` to fit `max_chars`.
+    pub async fn shorten(&self, text: &str, max_chars: usize) -> String {
+        match self {
+            ToolOutputStrategy::HeadTail => head_tail_truncate(text, max_chars),
+            ToolOutputStrategy::Summarize(model) => {
+                match summarize_output(model.as_ref(), text).await {
+                    Ok(summary) if summary.chars().count() <= max_chars => summary,
+                    Ok(_) => {
+                        tracing::warn!("✂️ Tool output summary exceeded budget, truncating");
+                        head_tail_truncate(text, max_chars)
+                    }
+                    Err(e) => {
+                        tracing::warn!("✂️ Tool output summarization failed: {}", e);
+                        head_tail_truncate(text, max_chars)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Keep the head and tail of `text` within `max_chars`, marking the omitted middle.
+///
+/// Two thirds of the budget go to the head, where most tools put the relevant part.
+pub fn head_tail_truncate(text: &str, max_chars: usize) -> String {
+    let total = text.chars().count();
+    if total <= max_chars {
+        return text.to_string();
+    }
+
+    let head_len = max_chars * 2 / 3;
+    let tail_len = max_chars - head_len;
+    let head: String = text.chars().take(head_len).collect();
+    let tail: String = text.chars().skip(total - tail_len).collect();
+    let omitted = total - head_len - tail_len;
+
+    format!(
+        "{}\n\n[... {} characters omitted ...]\n\n{}",
+        head, omitted, tail
+    )
+}
+
+/// Path of the artifact holding the full output of a tool call.
+pub fn artifact_path(tool_name: &str, call_id: &str) -> String {
+    format!("{}/{}_{}.txt", TOOL_OUTPUT_ARTIFACT_DIR, tool_name, call_id)
+}
+
+/// Text content of a tool result message, regardless of its representation.
+pub(crate) fn message_text(message: &AgentMessage) -> String {
+    message.content.to_text()
+}
+
+async fn summarize_output(model: &dyn LanguageModel, text: &str) -> anyhow::Result<String> {
+    // Attributed to "summarization" rather than the agent by token tracking
+    let request = LlmRequest::new(
+        SUMMARY_SYSTEM_PROMPT,
+        vec![AgentMessage {
+            role: MessageRole::User,
+            content: MessageContent::Text(text.to_string()),
+            metadata: None,
+        }],
+    );
+    let response = with_usage_purpose(UsagePurpose::Summarization, model.generate(request)).await?;
+    Ok(message_text(&response.message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::llm::LlmResponse;
+    use async_trait::async_trait;
+
+    struct FixedSummaryModel(&'static str);
+
+    #[async_trait]
+    impl LanguageModel for FixedSummaryModel {
+        async fn generate(&self, _request: LlmRequest) -> anyhow::Result<LlmResponse> {
+            Ok(LlmResponse {
+                message: AgentMessage {
+                    role: MessageRole::Agent,
+                    content: MessageContent::Text(self.0.to_string()),
+                    metadata: None,
+                },
+                usage: None,
+            })
+        }
+    }
+
+    struct FailingModel;
+
+    #[async_trait]
+    impl LanguageModel for FailingModel {
+        async fn generate(&self, _request: LlmRequest) -> anyhow::Result<LlmResponse> {
+            anyhow::bail!("model unavailable")
+        }
+    }
+
+    #[test]
+    fn head_tail_keeps_short_text() {
+        assert_eq!(head_tail_truncate("short", 10), "short");
+    }
+
+    #[test]
+    fn head_tail_drops_middle() {
+        let text = format!("{}{}{}", "a".repeat(60), "b".repeat(100), "c".repeat(30));
+        let out = head_tail_truncate(&text, 90);
+        assert!(out.starts_with(&"a".repeat(60)));
+        assert!(out.ends_with(&"c".repeat(30)));
+        assert!(out.contains("[... 100 characters omitted ...]"));
+    }
+
+    #[test]
+    fn head_tail_is_char_boundary_safe() {
+        let text = "é".repeat(50);
+        let out = head_tail_truncate(&text, 10);
+        assert!(out.contains("40 characters omitted"));
+    }
+
+    #[test]
+    fn per_tool_limit_overrides_default() {
+        let config = ToolOutputConfig::new(100).with_tool_limit("web", 10);
+        assert_eq!(config.limit_for("web"), Some(10));
+        assert_eq!(config.limit_for("other"), Some(100));
+
+        let config = ToolOutputConfig::per_tool_only().with_tool_limit("web", 10);
+        assert_eq!(config.limit_for("other"), None);
+    }
+
+    #[tokio::test]
+    async fn summarize_strategy_uses_model() {
+        let config = ToolOutputConfig::new(50).with_strategy(ToolOutputStrategy::Summarize(
+            Arc::new(FixedSummaryModel("the gist")),
+        ));
+        let out = config.shorten(&"x".repeat(200), 50).await;
+        assert_eq!(out, "the gist");
+    }
+
+    #[tokio::test]
+    async fn summarize_strategy_falls_back_on_error() {
+        let config = ToolOutputConfig::new(50)
+            .with_strategy(ToolOutputStrategy::Summarize(Arc::new(FailingModel)));
+        let out = config.shorten(&"x".repeat(200), 50).await;
+        assert!(out.contains("characters omitted"));
+    }
+
+    struct CallThenEchoPlanner;
+
+    #[async_trait]
+    impl agents_core::agent::PlannerHandle for CallThenEchoPlanner {
+        async fn plan(
+            &self,
+            context: agents_core::agent::PlannerContext,
+            _state: Arc<agents_core::state::AgentStateSnapshot>,
+        ) -> anyhow::Result<agents_core::agent::PlannerDecision> {
+            use agents_core::agent::{PlannerAction, PlannerDecision};
+            let last = context.history.last().cloned().unwrap();
+            let next_action = if last.role == MessageRole::User {
+                PlannerAction::CallTool {
+                    tool_name: "big".into(),
+                    payload: serde_json::json!({}),
+                }
+            } else {
+                PlannerAction::Respond { message: last }
+            };
+            Ok(PlannerDecision { next_action })
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    #[tokio::test]
+    async fn agent_truncates_tool_output_and_stores_artifact() {
+        use crate::agent::config::DeepAgentConfig;
+        use crate::agent::runtime::create_deep_agent_from_config;
+        use agents_core::state::AgentStateSnapshot;
+        use agents_core::tools::{ToolParameterSchema, ToolResult};
+
+        let big = agents_toolkit::tool_sync(
+            "big",
+            "Returns a large payload",
+            ToolParameterSchema::object("No parameters", HashMap::new(), Vec::new()),
+            |_args, ctx| Ok(ToolResult::text(&ctx, "z".repeat(5_000))),
+        );
+        let config = DeepAgentConfig::new("assist", Arc::new(CallThenEchoPlanner))
+            .with_tool(big)
+            .with_auto_general_purpose(false)
+            .with_tool_output_limits(ToolOutputConfig::new(10_000).with_tool_limit("big", 300));
+        let agent = create_deep_agent_from_config(config);
+
+        let response = agent
+            .handle_message("go", Arc::new(AgentStateSnapshot::default()))
+            .await
+            .unwrap();
+        let text = response.content.as_text().unwrap();
+        assert!(text.contains("shortened from 5000 characters"));
+        assert!(text.contains("tool_outputs/big_"));
+        assert!(text.len() < 1_000);
+    }
+}
@@ -0,0 +1,173 @@
+//! Automatic retries of failed tool calls
+//!
+//! Tools backed by networks and external services fail transiently. With a retry
+//! policy, a tool call that returns an error is attempted again after an exponential
+//! backoff. When the attempts are exhausted, the error is handed back to the planner
+//! together with guidance to take another path instead of repeating the call.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Attempts and backoff for one tool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ToolRetryPolicy {
+    /// Total attempts including the first call (at least 1).
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for each further retry.
+    pub initial_backoff: Duration,
+    /// Upper bound for the delay between attempts.
+    pub max_backoff: Duration,
+}
+
+impl Default for ToolRetryPolicy {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+impl ToolRetryPolicy {
+    /// Attempt a call up to `max_attempts` times, waiting 500ms, 1s, 2s... in between.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Delay before the attempt following `failed_attempts` failures.
+    pub fn delay(&self, failed_attempts: u32) -> Duration {
+        let exponent = failed_attempts.saturating_sub(1).min(16);
+        self.initial_backoff
+            .saturating_mul(1 << exponent)
+            .min(self.max_backoff)
+    }
+}
+
+/// Retry policies for tool calls.
+///
+/// # Example
+///
+/// ```ignore
+/// let retries = ToolRetryConfig::new(ToolRetryPolicy::new(2))
+///     .with_tool_policy(
+///         "web_search",
+///         ToolRetryPolicy::new(4).with_backoff(Duration::from_secs(1), Duration::from_secs(10)),
+///     );
+///
+/// let agent = ConfigurableAgentBuilder::new("You are a researcher")
+///     .with_model(model)
+///     .with_tool_retries(retries)
+///     .build()?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolRetryConfig {
+    /// Policy for tools without their own. `None` calls them once.
+    pub default_policy: Option<ToolRetryPolicy>,
+    /// Per-tool policies that override `default_policy`.
+    pub tool_policies: HashMap<String, ToolRetryPolicy>,
+    /// Add guidance to choose an alternative path to the final error (enabled by default).
+    pub error_feedback: bool,
+}
+
+impl Default for ToolRetryConfig {
+    fn default() -> Self {
+        Self::new(ToolRetryPolicy::default())
+    }
+}
+
+impl ToolRetryConfig {
+    /// Apply `policy` to every tool.
+    pub fn new(policy: ToolRetryPolicy) -> Self {
+        Self {
+            default_policy: Some(policy),
+            tool_policies: HashMap::new(),
+            error_feedback: true,
+        }
+    }
+
+    /// Only retry the tools given via [`with_tool_policy`](Self::with_tool_policy).
+    pub fn per_tool_only() -> Self {
+        Self {
+            default_policy: None,
+            ..Self::default()
+        }
+    }
+
+    /// Set the policy for a specific tool.
+    pub fn with_tool_policy(
+        mut self,
+        tool_name: impl Into<String>,
+        policy: ToolRetryPolicy,
+    ) -> Self {
+        self.tool_policies.insert(tool_name.into(), policy);
+        self
+    }
+
+    /// Enable or disable the guidance appended to errors once retries are exhausted.
+    pub fn with_error_feedback(mut self, enabled: bool) -> Self {
+        self.error_feedback = enabled;
+        self
+    }
+
+    /// Policy applying to `tool_name`, if any.
+    pub fn policy_for(&self, tool_name: &str) -> Option<&ToolRetryPolicy> {
+        self.tool_policies
+            .get(tool_name)
+            .or(self.default_policy.as_ref())
+    }
+
+    /// Text returned to the planner after the last attempt of `tool_name` failed.
+    pub(crate) fn failure_text(&self, tool_name: &str, attempts: u32, error: &str) -> String {
+        let mut text = format!("Error executing {}: {}", tool_name, error);
+        if self.error_feedback {
+            text.push_str(&format!(
+                "\n\nThe tool failed {} time{} and will not succeed if called again the same way. \
+                 Choose an alternative: use another tool, change the arguments, or continue \
+                 without this result and tell the user what could not be done.",
+                attempts,
+                if attempts == 1 { "" } else { "s" }
+            ));
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_cap() {
+        let policy = ToolRetryPolicy::new(5)
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(350));
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(200));
+        assert_eq!(policy.delay(3), Duration::from_millis(350));
+        assert_eq!(ToolRetryPolicy::new(0).max_attempts, 1);
+    }
+
+    #[test]
+    fn test_tool_policies_override_the_default() {
+        let config =
+            ToolRetryConfig::per_tool_only().with_tool_policy("search", ToolRetryPolicy::new(4));
+        assert_eq!(config.policy_for("search").unwrap().max_attempts, 4);
+        assert!(config.policy_for("calculator").is_none());
+
+        let text = config.failure_text("search", 4, "timed out");
+        assert!(text.starts_with("Error executing search: timed out"));
+        assert!(text.contains("failed 4 times"));
+        assert_eq!(
+            config
+                .with_error_feedback(false)
+                .failure_text("search", 4, "timed out"),
+            "Error executing search: timed out"
+        );
+    }
+}
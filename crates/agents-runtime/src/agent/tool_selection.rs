@@ -0,0 +1,293 @@
+//! Per-turn tool selection for agents with large tool registries
+//!
+//! With dozens of tools (MCP servers plus built-ins) the tool schemas dominate the
+//! prompt. A tool selection stage ranks the registered tools against the current
+//! conversation and only exposes the top-K most relevant schemas to the planner.
+//! Pinned tools are always exposed regardless of their score.
+//!
+//! Selection only affects which schemas the model sees; every registered tool can
+//! still be executed if the model calls it by name.
+
+use agents_core::messaging::{AgentMessage, MessageRole};
+use agents_core::tools::ToolSchema;
+use async_trait::async_trait;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Tools pinned by default: the built-in planning, filesystem and delegation tools.
+pub const DEFAULT_PINNED_TOOLS: &[&str] = &[
+    "write_todos",
+    "ls",
+    "read_file",
+    "write_file",
+    "edit_file",
+    "task",
+];
+
+/// Number of recent messages used to build the selection query.
+const QUERY_HISTORY_WINDOW: usize = 4;
+
+const STOPWORDS: &[&str] = &[
+    "the", "and", "for", "with", "that", "this", "from", "you", "your", "are", "was", "can",
+    "please", "what", "how", "into", "about", "use", "using", "tool", "tools", "should", "would",
+    "could", "have", "has", "will", "then", "them", "they", "their", "there", "when", "which",
+];
+
+/// Ranks tools by relevance to a query.
+///
+/// Implement this to plug in embedding-based selection; [`KeywordToolSelector`] is
+/// the default.
+#[async_trait]
+pub trait ToolSelector: Send + Sync {
+    /// Return the names of at most `top_k` tools from `candidates`, most relevant first.
+    async fn select(
+        &self,
+        query: &str,
+        candidates: &[ToolSchema],
+        top_k: usize,
+    ) -> anyhow::Result<Vec<String>>;
+}
+
+/// Keyword overlap selector: scores tools by how many query terms appear in their
+/// name (weighted double) and description.
+#[derive(Debug, Default, Clone)]
+pub struct KeywordToolSelector;
+
+#[async_trait]
+impl ToolSelector for KeywordToolSelector {
+    async fn select(
+        &self,
+        query: &str,
+        candidates: &[ToolSchema],
+        top_k: usize,
+    ) -> anyhow::Result<Vec<String>> {
+        let query_terms = tokenize(query);
+
+        let mut scored: Vec<(usize, &str)> = candidates
+            .iter()
+            .map(|schema| {
+                let name_terms = tokenize(&schema.name);
+                let description_terms = tokenize(&schema.description);
+                let score = query_terms
+                    .iter()
+                    .map(|term| {
+                        let mut s = 0;
+                        if name_terms.contains(term) {
+                            s += 2;
+                        }
+                        if description_terms.contains(term) {
+                            s += 1;
+                        }
+                        s
+                    })
+                    .sum();
+                (score, schema.name.as_str())
+            })
+            .collect();
+
+        // Highest score first; ties broken by name so selection is deterministic.
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+
+        Ok(scored
+            .into_iter()
+            .take(top_k)
+            .map(|(_, name)| name.to_string())
+            .collect())
+    }
+}
+
+/// Configuration for per-turn tool selection.
+///
+/// # Example
+///
+/// ```ignore
+/// let selection = ToolSelectionConfig::new(8).with_pinned_tool("search_docs");
+///
+/// let agent = ConfigurableAgentBuilder::new("You are a helpful assistant")
+///     .with_model(model)
+///     .with_tools(mcp_tools)
+///     .with_tool_selection(selection)
+///     .build()?;
+/// ```
+#[derive(Clone)]
+pub struct ToolSelectionConfig {
+    /// Maximum number of non-pinned tools exposed per turn.
+    pub top_k: usize,
+    /// Tools that are always exposed.
+    pub pinned: HashSet<String>,
+    /// Strategy used to rank non-pinned tools.
+    pub selector: Arc<dyn ToolSelector>,
+}
+
+impl ToolSelectionConfig {
+    /// Expose at most `top_k` tools per turn in addition to the pinned ones.
+    ///
+    /// The built-in tools in [`DEFAULT_PINNED_TOOLS`] are pinned by default.
+    pub fn new(top_k: usize) -> Self {
+        Self {
+            top_k,
+            pinned: DEFAULT_PINNED_TOOLS.iter().map(|s| s.to_string()).collect(),
+            selector: Arc::new(KeywordToolSelector),
+        }
+    }
+
+    /// Always expose the given tool.
+    pub fn with_pinned_tool(mut self, tool_name: impl Into<String>) -> Self {
+        self.pinned.insert(tool_name.into());
+        self
+    }
+
+    /// Replace the pinned set (including the default built-in pins).
+    pub fn with_pinned_tools<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.pinned = names.into_iter().map(|s| s.into()).collect();
+        self
+    }
+
+    /// Use a custom selector (e.g. embedding similarity).
+    pub fn with_selector(mut self, selector: Arc<dyn ToolSelector>) -> Self {
+        self.selector = selector;
+        self
+    }
+
+    /// Narrow `schemas` down to the pinned tools plus the top-K tools for `history`.
+    ///
+    /// Returns the input unchanged when it already fits, and falls back to the full
+    /// list if the selector fails.
+    pub async fn select(
+        &self,
+        schemas: Vec<ToolSchema>,
+        history: &[AgentMessage],
+    ) -> Vec<ToolSchema> {
+        let (pinned, candidates): (Vec<_>, Vec<_>) = schemas
+            .into_iter()
+            .partition(|schema| self.pinned.contains(&schema.name));
+
+        if candidates.len() <= self.top_k {
+            return pinned.into_iter().chain(candidates).collect();
+        }
+
+        let query = selection_query(history);
+        let selected = match self.selector.select(&query, &candidates, self.top_k).await {
+            Ok(names) => names.into_iter().collect::<HashSet<_>>(),
+            Err(e) => {
+                tracing::warn!("🧰 Tool selection failed, exposing all tools: {}", e);
+                return pinned.into_iter().chain(candidates).collect();
+            }
+        };
+
+        let total = candidates.len() + pinned.len();
+        let result: Vec<ToolSchema> = pinned
+            .into_iter()
+            .chain(
+                candidates
+                    .into_iter()
+                    .filter(|schema| selected.contains(&schema.name)),
+            )
+            .collect();
+
+        tracing::debug!(
+            selected = result.len(),
+            total,
+            "🧰 Narrowed tool schemas for this turn"
+        );
+
+        result
+    }
+}
+
+/// Build the selection query from the most recent user and tool messages.
+fn selection_query(history: &[AgentMessage]) -> String {
+    history
+        .iter()
+        .rev()
+        .filter(|m| matches!(m.role, MessageRole::User | MessageRole::Tool))
+        .take(QUERY_HISTORY_WINDOW)
+        .map(|m| m.content.text())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn tokenize(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.len() >= 3)
+        .map(|w| w.to_lowercase())
+        .filter(|w| !STOPWORDS.contains(&w.as_str()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::messaging::MessageContent;
+    use agents_core::tools::ToolParameterSchema;
+    use std::collections::HashMap;
+
+    fn schema(name: &str, description: &str) -> ToolSchema {
+        ToolSchema::new(
+            name,
+            description,
+            ToolParameterSchema::object("params", HashMap::new(), Vec::new()),
+        )
+    }
+
+    fn user(text: &str) -> AgentMessage {
+        AgentMessage {
+            role: MessageRole::User,
+            content: MessageContent::Text(text.to_string()),
+            metadata: None,
+        }
+    }
+
+    fn registry() -> Vec<ToolSchema> {
+        vec![
+            schema("write_todos", "Update the todo list"),
+            schema(
+                "weather_lookup",
+                "Get the current weather forecast for a city",
+            ),
+            schema("stock_quote", "Fetch the latest stock price for a ticker"),
+            schema("send_email", "Send an email message to a recipient"),
+            schema("translate_text", "Translate text between languages"),
+        ]
+    }
+
+    #[tokio::test]
+    async fn keyword_selector_ranks_by_overlap() {
+        let names = KeywordToolSelector
+            .select("what is the weather in Paris", &registry(), 1)
+            .await
+            .unwrap();
+        assert_eq!(names, vec!["weather_lookup".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn selection_keeps_pinned_tools() {
+        let config = ToolSelectionConfig::new(1);
+        let selected = config
+            .select(registry(), &[user("send an email to Bob")])
+            .await;
+        let names: Vec<_> = selected.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["write_todos", "send_email"]);
+    }
+
+    #[tokio::test]
+    async fn selection_is_noop_when_registry_fits() {
+        let config = ToolSelectionConfig::new(10);
+        let selected = config.select(registry(), &[user("hello")]).await;
+        assert_eq!(selected.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn custom_pins_replace_defaults() {
+        let config = ToolSelectionConfig::new(1).with_pinned_tools(["stock_quote"]);
+        let selected = config
+            .select(registry(), &[user("translate this text to French")])
+            .await;
+        let names: Vec<_> = selected.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["stock_quote", "translate_text"]);
+    }
+}
@@ -0,0 +1,358 @@
+//! Translating conversations to and from a working language
+//!
+//! Tools, sub-agents and prompts are usually written for one language. With a
+//! translation layer the agent runs entirely in that working language: user messages
+//! in another language are translated before they enter the history, and the final
+//! response is translated back into the user's language. Code blocks, inline code and
+//! JSON are masked before translation and restored verbatim afterwards.
+//!
+//! The user's language is, in order: the language fixed with
+//! [`TranslationConfig::with_user_language`], the language detected by the
+//! [`Translator`], or the thread locale ([`AgentStateSnapshot::locale`]).
+
+use agents_core::llm::{LanguageModel, LlmRequest};
+use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+use agents_core::state::AgentStateSnapshot;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// Translates text between languages identified by BCP 47 tags (`en`, `ar-AE`).
+#[async_trait]
+pub trait Translator: Send + Sync {
+    /// Translate `text` from `from` into `to`. Placeholders such as `⟦0⟧` must be
+    /// kept exactly as they are.
+    async fn translate(&self, text: &str, from: &str, to: &str) -> anyhow::Result<String>;
+
+    /// Language of `text`, or `None` when it cannot be determined.
+    ///
+    /// The default recognizes non-Latin scripts with [`detect_script_language`].
+    async fn detect_language(&self, text: &str) -> anyhow::Result<Option<String>> {
+        Ok(detect_script_language(text).map(str::to_string))
+    }
+}
+
+/// Translates with a language model, typically a small and cheap one.
+pub struct ModelTranslator {
+    model: Arc<dyn LanguageModel>,
+}
+
+impl ModelTranslator {
+    pub fn new(model: Arc<dyn LanguageModel>) -> Self {
+        Self { model }
+    }
+}
+
+#[async_trait]
+impl Translator for ModelTranslator {
+    async fn translate(&self, text: &str, from: &str, to: &str) -> anyhow::Result<String> {
+        let system_prompt = format!(
+            "Translate the user's text from {from} into {to}. Keep placeholders such as ⟦0⟧, \
+             names, identifiers, numbers and URLs exactly as they are. Reply with the \
+             translation only."
+        );
+        let request = LlmRequest::new(
+            system_prompt,
+            vec![AgentMessage {
+                role: MessageRole::User,
+                content: MessageContent::Text(text.to_string()),
+                metadata: None,
+            }],
+        );
+        let response = self.model.generate(request).await?;
+        Ok(response.message.content.to_text())
+    }
+}
+
+/// Configuration for translating conversations.
+///
+/// Sub-agents are not translated: they receive tasks from the agent in the working
+/// language already.
+///
+/// # Example
+///
+/// ```ignore
+/// let agent = ConfigurableAgentBuilder::new("You are a car service assistant")
+///     .with_model(model)
+///     .with_translation(TranslationConfig::with_model(small_model))
+///     .build()?;
+/// ```
+#[derive(Clone)]
+pub struct TranslationConfig {
+    pub translator: Arc<dyn Translator>,
+    /// Language the agent, its tools and sub-agents work in (default: `en`)
+    pub working_language: String,
+    /// Skip detection and always translate from and to this language
+    pub user_language: Option<String>,
+}
+
+impl std::fmt::Debug for TranslationConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TranslationConfig")
+            .field("working_language", &self.working_language)
+            .field("user_language", &self.user_language)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TranslationConfig {
+    pub fn new(translator: Arc<dyn Translator>) -> Self {
+        Self {
+            translator,
+            working_language: "en".to_string(),
+            user_language: None,
+        }
+    }
+
+    /// Translate with `model` through [`ModelTranslator`].
+    pub fn with_model(model: Arc<dyn LanguageModel>) -> Self {
+        Self::new(Arc::new(ModelTranslator::new(model)))
+    }
+
+    pub fn with_working_language(mut self, language: impl Into<String>) -> Self {
+        self.working_language = language.into();
+        self
+    }
+
+    pub fn with_user_language(mut self, language: impl Into<String>) -> Self {
+        self.user_language = Some(language.into());
+        self
+    }
+
+    /// Language `message` should be translated from, or `None` when it is already
+    /// in the working language.
+    pub async fn user_language(
+        &self,
+        message: &AgentMessage,
+        state: &AgentStateSnapshot,
+    ) -> Option<String> {
+        let MessageContent::Text(text) = &message.content else {
+            return None;
+        };
+        let language = match &self.user_language {
+            Some(language) => language.clone(),
+            None => {
+                let detected = self
+                    .translator
+                    .detect_language(text)
+                    .await
+                    .unwrap_or_else(|e| {
+                        tracing::warn!("🌐 Language detection failed: {}", e);
+                        None
+                    });
+                detected.or_else(|| state.locale.clone())?
+            }
+        };
+        (!same_language(&language, &self.working_language)).then_some(language)
+    }
+
+    /// Translate a text message; JSON content is returned unchanged.
+    pub async fn translate_message(
+        &self,
+        message: AgentMessage,
+        from: &str,
+        to: &str,
+    ) -> AgentMessage {
+        let MessageContent::Text(text) = &message.content else {
+            return message;
+        };
+        let translated = self.translate_text(text, from, to).await;
+        AgentMessage {
+            content: MessageContent::Text(translated),
+            ..message
+        }
+    }
+
+    /// Translate `text`, keeping code and JSON verbatim. Falls back to the original
+    /// text when the translator fails or drops a masked segment.
+    pub async fn translate_text(&self, text: &str, from: &str, to: &str) -> String {
+        if text.trim().is_empty() || is_json(text) {
+            return text.to_string();
+        }
+        let (masked, segments) = mask_code(text);
+        match self.translator.translate(&masked, from, to).await {
+            Ok(translated) => unmask_code(&translated, &segments).unwrap_or_else(|| {
+                tracing::warn!("🌐 Translation dropped code segments, keeping original text");
+                text.to_string()
+            }),
+            Err(e) => {
+                tracing::warn!("🌐 Translation from {} to {} failed: {}", from, to, e);
+                text.to_string()
+            }
+        }
+    }
+}
+
+/// Language of a text written in a non-Latin script, by majority of letters.
+///
+/// Returns `None` for Latin-script (or letterless) text, whose language cannot be
+/// told from the script alone.
+pub fn detect_script_language(text: &str) -> Option<&'static str> {
+    let mut latin = 0;
+    let mut scripts: Vec<(&'static str, usize)> = Vec::new();
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        let language = match c as u32 {
+            0x0600..=0x06FF
+            | 0x0750..=0x077F
+            | 0x08A0..=0x08FF
+            | 0xFB50..=0xFDFF
+            | 0xFE70..=0xFEFF => "ar",
+            0x0590..=0x05FF => "he",
+            0x0370..=0x03FF => "el",
+            0x0400..=0x04FF => "ru",
+            0x0900..=0x097F => "hi",
+            0x0E00..=0x0E7F => "th",
+            0x3040..=0x30FF => "ja",
+            0xAC00..=0xD7AF => "ko",
+            0x4E00..=0x9FFF => "zh",
+            _ => {
+                latin += 1;
+                continue;
+            }
+        };
+        match scripts.iter_mut().find(|(l, _)| *l == language) {
+            Some((_, count)) => *count += 1,
+            None => scripts.push((language, 1)),
+        }
+    }
+    // Japanese mixes kana with Chinese characters
+    if scripts.iter().any(|(l, _)| *l == "ja") {
+        return Some("ja");
+    }
+    scripts
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .filter(|(_, count)| *count > latin)
+        .map(|(language, _)| language)
+}
+
+/// Whether two BCP 47 tags share their primary language (`ar-AE` and `ar`).
+fn same_language(a: &str, b: &str) -> bool {
+    let primary = |tag: &str| tag.split(['-', '_']).next().unwrap_or("").to_lowercase();
+    primary(a) == primary(b)
+}
+
+fn is_json(text: &str) -> bool {
+    let trimmed = text.trim();
+    (trimmed.starts_with('{') || trimmed.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(trimmed).is_ok()
+}
+
+fn placeholder(index: usize) -> String {
+    format!("⟦{index}⟧")
+}
+
+/// Replace fenced code blocks and inline code with placeholders.
+fn mask_code(text: &str) -> (String, Vec<String>) {
+    let mut masked = String::with_capacity(text.len());
+    let mut segments = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('`') {
+        let fence = if rest[start..].starts_with("```") {
+            "```"
+        } else {
+            "`"
+        };
+        let body_start = start + fence.len();
+        let end = match rest[body_start..].find(fence) {
+            Some(offset) => body_start + offset + fence.len(),
+            // An unclosed fence runs to the end; a stray backtick is plain text
+            None if fence == "```" => rest.len(),
+            None => {
+                masked.push_str(&rest[..body_start]);
+                rest = &rest[body_start..];
+                continue;
+            }
+        };
+        masked.push_str(&rest[..start]);
+        masked.push_str(&placeholder(segments.len()));
+        segments.push(rest[start..end].to_string());
+        rest = &rest[end..];
+    }
+    masked.push_str(rest);
+    (masked, segments)
+}
+
+/// Put masked segments back, or `None` if a placeholder went missing.
+fn unmask_code(text: &str, segments: &[String]) -> Option<String> {
+    let mut restored = text.to_string();
+    for (index, segment) in segments.iter().enumerate() {
+        let placeholder = placeholder(index);
+        if !restored.contains(&placeholder) {
+            return None;
+        }
+        restored = restored.replacen(&placeholder, segment, 1);
+    }
+    Some(restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tags the text with the target language, or fails for `fail`.
+    struct TaggingTranslator;
+
+    #[async_trait]
+    impl Translator for TaggingTranslator {
+        async fn translate(&self, text: &str, _from: &str, to: &str) -> anyhow::Result<String> {
+            if text.contains("fail") {
+                anyhow::bail!("translator unavailable");
+            }
+            Ok(format!("[{to}] {text}"))
+        }
+    }
+
+    #[tokio::test]
+    async fn code_and_json_survive_translation() {
+        let config = TranslationConfig::new(Arc::new(TaggingTranslator));
+        let text = "Run `cargo test` then:\n```sh\necho `date`\n```\ndone";
+
+        let translated = config.translate_text(text, "en", "ar").await;
+        assert_eq!(translated, format!("[ar] {text}"));
+
+        let json = r#"{"status": "shipped"}"#;
+        assert_eq!(config.translate_text(json, "en", "ar").await, json);
+        assert_eq!(config.translate_text("fail", "en", "ar").await, "fail");
+    }
+
+    #[test]
+    fn masked_code_is_replaced_by_placeholders() {
+        let (masked, segments) = mask_code("use `ls` or ```\nls -la\n``` and a stray ` tick");
+        assert_eq!(masked, "use ⟦0⟧ or ⟦1⟧ and a stray ` tick");
+        assert_eq!(segments, vec!["`ls`", "```\nls -la\n```"]);
+        assert!(unmask_code("⟦0⟧ only", &segments).is_none());
+    }
+
+    #[tokio::test]
+    async fn user_language_is_detected_from_script() {
+        let config = TranslationConfig::new(Arc::new(TaggingTranslator));
+        let state = AgentStateSnapshot::default();
+        let message = |text: &str| AgentMessage {
+            role: MessageRole::User,
+            content: MessageContent::Text(text.to_string()),
+            metadata: None,
+        };
+
+        assert_eq!(
+            config
+                .user_language(&message("متى موعد صيانة سيارتي؟"), &state)
+                .await
+                .as_deref(),
+            Some("ar")
+        );
+        assert_eq!(
+            config
+                .user_language(&message("When is my car service?"), &state)
+                .await,
+            None
+        );
+        let french = AgentStateSnapshot::default().with_locale("fr-FR");
+        assert_eq!(
+            config
+                .user_language(&message("Quand est mon rendez-vous ?"), &french)
+                .await
+                .as_deref(),
+            Some("fr-FR")
+        );
+    }
+}
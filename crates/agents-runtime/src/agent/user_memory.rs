@@ -0,0 +1,177 @@
+//! Cross-thread user memory
+//!
+//! The [user profile](crate::middleware::user_profile) of a thread is lost to the
+//! user's next conversation. With user memory, runs name the user they are served
+//! for ([`RunOptions::with_user`](super::RunOptions::with_user)); the profile facts
+//! learned in a turn are saved to the user's namespace of a [`MemoryStore`], and
+//! every thread of that user starts from them. The user's threads are tagged so
+//! [`DeepAgent::erase_user`](super::DeepAgent::erase_user) can delete everything
+//! kept about them.
+
+use super::memory_policy::MemoryPolicy;
+use agents_core::memory::{MemoryEntry, MemoryStore, UserId};
+use agents_core::persistence::ThreadId;
+use agents_core::user_profile::UserProfile;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+
+/// Tag of the threads served for `user_id`.
+pub fn user_tag(user_id: &str) -> String {
+    format!("user:{user_id}")
+}
+
+/// Configuration for cross-thread user memory.
+///
+/// # Example
+///
+/// ```ignore
+/// let agent = ConfigurableAgentBuilder::new("You schedule car service appointments")
+///     .with_model(model)
+///     .with_checkpointer(checkpointer)
+///     .with_user_profile(UserProfileConfig::new(small_model))
+///     .with_user_memory(UserMemoryConfig::new(Arc::new(
+///         RedisMemoryStore::new("redis://127.0.0.1:6379").await?,
+///     )))
+///     .build()?;
+///
+/// let options = RunOptions::new().with_user(customer.id());
+/// agent.handle_message_for_thread_with_options(&thread_id, text, options).await?;
+/// ```
+#[derive(Clone)]
+pub struct UserMemoryConfig {
+    pub store: Arc<dyn MemoryStore>,
+}
+
+impl std::fmt::Debug for UserMemoryConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UserMemoryConfig").finish_non_exhaustive()
+    }
+}
+
+impl UserMemoryConfig {
+    pub fn new(store: Arc<dyn MemoryStore>) -> Self {
+        Self { store }
+    }
+
+    /// Add the user's memories to a thread's profile. A memory replaces a fact of
+    /// the thread when it is newer, e.g. learned in another thread since. Memories
+    /// the policy no longer allows to keep are deleted instead.
+    pub(crate) async fn load(
+        &self,
+        user_id: &UserId,
+        profile: &mut UserProfile,
+        policy: Option<&MemoryPolicy>,
+    ) -> anyhow::Result<()> {
+        let mut entries = self.store.list(user_id).await?;
+        if let Some(policy) = policy {
+            let stale = policy
+                .stale(
+                    Some(user_id),
+                    entries
+                        .iter()
+                        .map(|entry| (&entry.key, entry.category.as_deref(), entry.expires_at)),
+                )
+                .await;
+            for (key, _) in &stale {
+                self.store.delete(user_id, key).await?;
+            }
+            entries.retain(|entry| !stale.iter().any(|(key, _)| *key == entry.key));
+            policy.report(Some(user_id), stale);
+        }
+        for entry in entries {
+            let newer = profile
+                .get(&entry.key)
+                .is_none_or(|fact| fact.updated_at < entry.updated_at);
+            if newer {
+                profile
+                    .facts
+                    .insert(entry.key.clone(), entry.to_profile_fact());
+            }
+        }
+        Ok(())
+    }
+
+    /// Save the profile facts updated since `since` to the user's memories,
+    /// returning how many were saved.
+    pub(crate) async fn save(
+        &self,
+        user_id: &UserId,
+        thread_id: &ThreadId,
+        profile: &UserProfile,
+        since: DateTime<Utc>,
+    ) -> anyhow::Result<usize> {
+        let entries: Vec<MemoryEntry> = profile
+            .facts
+            .iter()
+            .filter(|(_, fact)| fact.updated_at >= since)
+            .map(|(key, fact)| MemoryEntry::from_profile_fact(key, fact).with_thread(thread_id))
+            .collect();
+        self.store.put(user_id, &entries).await?;
+        Ok(entries.len())
+    }
+}
+
+/// What [`DeepAgent::erase_user`](super::DeepAgent::erase_user) deleted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserErasure {
+    /// Memories erased from the user's namespace
+    pub memories: usize,
+    /// Threads of the user that were deleted
+    pub threads: Vec<ThreadId>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::memory::InMemoryMemoryStore;
+    use agents_core::user_profile::ProfileFact;
+    use chrono::Duration;
+
+    fn fact(value: &str, updated_at: DateTime<Utc>) -> ProfileFact {
+        ProfileFact {
+            value: value.to_string(),
+            confidence: 0.9,
+            source: value.to_string(),
+            updated_at,
+            category: None,
+            expires_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn newer_memories_reach_the_thread_and_new_facts_are_saved() {
+        let store = Arc::new(InMemoryMemoryStore::new());
+        let memory = UserMemoryConfig::new(store.clone());
+        let ana = "ana".to_string();
+        let earlier = Utc::now() - Duration::hours(1);
+        store
+            .put(
+                &ana,
+                &[
+                    MemoryEntry::new("vehicle", "2021 Corolla"),
+                    MemoryEntry::from_profile_fact("name", &fact("Ana", earlier)),
+                ],
+            )
+            .await
+            .unwrap();
+
+        let mut profile = UserProfile::default();
+        profile.set("vehicle", fact("2019 Civic", earlier));
+        profile.set("name", fact("Ana Silva", Utc::now()));
+        memory.load(&ana, &mut profile, None).await.unwrap();
+        assert_eq!(profile.get("vehicle").unwrap().value, "2021 Corolla");
+        assert_eq!(profile.get("name").unwrap().value, "Ana Silva");
+
+        let turn_started = Utc::now();
+        profile.set("language", fact("Portuguese", Utc::now()));
+        let saved = memory
+            .save(&ana, &"t2".to_string(), &profile, turn_started)
+            .await
+            .unwrap();
+        assert_eq!(saved, 1);
+        let memories = store.list(&ana).await.unwrap();
+        assert_eq!(memories.len(), 3);
+        assert_eq!(memories[0].key, "language");
+        assert_eq!(memories[0].thread_id.as_deref(), Some("t2"));
+    }
+}
@@ -0,0 +1,70 @@
+//! Validation of a builder configuration before the agent is assembled
+//!
+//! `ConfigurableAgentBuilder::build` checks the whole configuration first and fails
+//! with a [`ConfigValidationError`] listing every problem, so a misconfigured agent
+//! is fixed in one pass instead of one error at a time.
+
+use std::fmt;
+
+/// A problem in an agent configuration, with the way to fix it.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ConfigIssue {
+    /// Neither a model nor a planner was set and OPENAI_API_KEY is missing
+    #[error("no model configured: call with_model() or with_planner(), or set OPENAI_API_KEY for the default model")]
+    MissingModel,
+    /// Two registered tools share a name; only one of them would be reachable
+    #[error(
+        "tool `{name}` is registered more than once: rename one of the tools or register it once"
+    )]
+    DuplicateTool { name: String },
+    /// Two sub-agents share a name; the task tool could only reach one of them
+    #[error("sub-agent `{name}` is configured more than once: give each sub-agent a unique name")]
+    DuplicateSubAgent { name: String },
+    /// A sub-agent is named like a tool, which the model easily confuses
+    #[error(
+        "sub-agent `{name}` has the name of a tool: rename the sub-agent, e.g. `{name}-agent`"
+    )]
+    SubAgentNameCollision { name: String },
+    /// A registered tool is named like a built-in tool and no resolution was chosen
+    #[error("tool `{name}` collides with the built-in tool of the same name: rename it or choose a ToolConflictStrategy with with_tool_conflicts()")]
+    BuiltinToolConflict { name: String },
+    /// A HITL policy was set for a tool the agent does not have
+    #[error("HITL policy references unknown tool `{name}`: register the tool or fix the name in with_tool_interrupt()")]
+    UnknownInterruptTool { name: String },
+    /// A registered skill needs something the agent is not configured with
+    #[error(
+        "skill `{skill}` requires {requirement}: configure it on the builder or remove the skill"
+    )]
+    UnmetSkillRequirement { skill: String, requirement: String },
+}
+
+/// All problems found in an agent configuration.
+///
+/// ```ignore
+/// if let Err(e) = builder.validate() {
+///     for issue in &e.issues {
+///         eprintln!("- {issue}");
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigValidationError {
+    pub issues: Vec<ConfigIssue>,
+}
+
+impl fmt::Display for ConfigValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid agent configuration ({} problem{})",
+            self.issues.len(),
+            if self.issues.len() == 1 { "" } else { "s" }
+        )?;
+        for issue in &self.issues {
+            write!(f, "\n  - {issue}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigValidationError {}
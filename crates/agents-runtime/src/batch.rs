@@ -0,0 +1,281 @@
+//! Run a non-interactive prompt over many inputs through a provider batch API.
+//!
+//! Batch requests are single-turn: the model sees the system prompt and one input,
+//! and tool calls in the response are returned as-is rather than executed.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use agents_core::batch::{BatchModel, BatchRequest, BatchStatus};
+use agents_core::llm::{LlmRequest, LlmResponse};
+use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+use serde::{Deserialize, Serialize};
+
+/// Submits inputs in provider-sized batches and collects the results in input order.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use agents_runtime::batch::BatchExecutor;
+/// use agents_runtime::{OpenAiChatModel, OpenAiConfig};
+/// use std::sync::Arc;
+///
+/// # async fn example(products: Vec<String>) -> anyhow::Result<()> {
+/// let model = OpenAiChatModel::new(OpenAiConfig::new("sk-...", "gpt-4o-mini"))?;
+/// let executor = BatchExecutor::new(Arc::new(model), "Write a one-line product summary.");
+///
+/// for output in executor.run(products).await? {
+///     println!("{}: {:?}", output.index, output.text());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct BatchExecutor {
+    model: Arc<dyn BatchModel>,
+    system_prompt: String,
+    poll_interval: Duration,
+    max_batch_size: Option<usize>,
+}
+
+/// Batches submitted for one set of inputs; serializable so a job can be
+/// resumed with [`BatchExecutor::wait`] from another process.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchJob {
+    /// Provider batch ids; batch `i` holds inputs `i * batch_size..(i + 1) * batch_size`
+    pub batch_ids: Vec<String>,
+    pub batch_size: usize,
+    pub input_count: usize,
+}
+
+/// Result for the input at `index`.
+#[derive(Debug, Clone)]
+pub struct BatchOutput {
+    pub index: usize,
+    pub outcome: Result<LlmResponse, String>,
+}
+
+impl BatchOutput {
+    /// Text of a successful response.
+    pub fn text(&self) -> Option<String> {
+        Some(self.outcome.as_ref().ok()?.message.content.to_text())
+    }
+}
+
+impl BatchExecutor {
+    pub fn new(model: Arc<dyn BatchModel>, system_prompt: impl Into<String>) -> Self {
+        Self {
+            model,
+            system_prompt: system_prompt.into(),
+            poll_interval: Duration::from_secs(60),
+            max_batch_size: None,
+        }
+    }
+
+    /// How long to wait between status checks (default: 60 seconds).
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Split inputs into batches of at most `size` requests (default: the provider limit).
+    pub fn with_max_batch_size(mut self, size: usize) -> Self {
+        self.max_batch_size = Some(size.max(1));
+        self
+    }
+
+    /// Submit, wait for and collect every input.
+    pub async fn run<I, S>(&self, inputs: I) -> anyhow::Result<Vec<BatchOutput>>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let job = self.submit(inputs).await?;
+        self.wait(&job).await
+    }
+
+    /// Submit the inputs without waiting for them to be processed.
+    pub async fn submit<I, S>(&self, inputs: I) -> anyhow::Result<BatchJob>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let requests: Vec<BatchRequest> = inputs
+            .into_iter()
+            .enumerate()
+            .map(|(index, input)| BatchRequest::new(custom_id(index), self.request_for(input)))
+            .collect();
+        let input_count = requests.len();
+        let size = self
+            .max_batch_size
+            .unwrap_or_else(|| self.model.max_batch_size())
+            .max(1);
+
+        let mut batch_ids = Vec::new();
+        let mut requests = requests.into_iter().peekable();
+        while requests.peek().is_some() {
+            let chunk: Vec<_> = requests.by_ref().take(size).collect();
+            batch_ids.push(self.model.submit_batch(chunk).await?);
+        }
+        tracing::info!(
+            inputs = input_count,
+            batches = batch_ids.len(),
+            "Submitted batch job"
+        );
+        Ok(BatchJob {
+            batch_ids,
+            batch_size: size,
+            input_count,
+        })
+    }
+
+    /// Poll until every batch of `job` has finished and return one output per input.
+    ///
+    /// Inputs of a batch that failed as a whole, and inputs the provider returned
+    /// no result for, get an error outcome.
+    pub async fn wait(&self, job: &BatchJob) -> anyhow::Result<Vec<BatchOutput>> {
+        let mut outcomes: HashMap<String, Result<LlmResponse, String>> = HashMap::new();
+        let mut failures: HashMap<usize, String> = HashMap::new();
+        let mut pending: Vec<usize> = (0..job.batch_ids.len()).collect();
+
+        loop {
+            let mut still_pending = Vec::new();
+            for batch in pending {
+                let batch_id = &job.batch_ids[batch];
+                match self.model.batch_status(batch_id).await? {
+                    BatchStatus::InProgress { completed, total } => {
+                        tracing::debug!(%batch_id, completed, total, "Batch in progress");
+                        still_pending.push(batch);
+                    }
+                    BatchStatus::Completed => {
+                        for result in self.model.batch_results(batch_id).await? {
+                            outcomes.insert(result.custom_id, result.outcome);
+                        }
+                    }
+                    BatchStatus::Failed { reason } => {
+                        tracing::warn!(%batch_id, %reason, "Batch failed");
+                        failures.insert(batch, format!("batch {batch_id} failed: {reason}"));
+                    }
+                }
+            }
+            if still_pending.is_empty() {
+                break;
+            }
+            pending = still_pending;
+            tokio::time::sleep(self.poll_interval).await;
+        }
+
+        Ok((0..job.input_count)
+            .map(|index| {
+                let outcome = outcomes.remove(&custom_id(index)).unwrap_or_else(|| {
+                    Err(failures
+                        .get(&(index / job.batch_size.max(1)))
+                        .cloned()
+                        .unwrap_or_else(|| "no result returned".to_string()))
+                });
+                BatchOutput { index, outcome }
+            })
+            .collect())
+    }
+
+    fn request_for(&self, input: impl Into<String>) -> LlmRequest {
+        LlmRequest::new(
+            self.system_prompt.clone(),
+            vec![AgentMessage {
+                role: MessageRole::User,
+                content: MessageContent::Text(input.into()),
+                metadata: None,
+            }],
+        )
+    }
+}
+
+fn custom_id(index: usize) -> String {
+    format!("input-{index}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::batch::BatchResult;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Echoes each input back after one in-progress poll; batches containing
+    /// "reject" fail as a whole.
+    #[derive(Default)]
+    struct EchoBatches {
+        batches: Mutex<Vec<(Vec<BatchRequest>, bool)>>,
+    }
+
+    #[async_trait]
+    impl BatchModel for EchoBatches {
+        fn max_batch_size(&self) -> usize {
+            2
+        }
+
+        async fn submit_batch(&self, requests: Vec<BatchRequest>) -> anyhow::Result<String> {
+            let mut batches = self.batches.lock().unwrap();
+            batches.push((requests, false));
+            Ok((batches.len() - 1).to_string())
+        }
+
+        async fn batch_status(&self, batch_id: &str) -> anyhow::Result<BatchStatus> {
+            let mut batches = self.batches.lock().unwrap();
+            let (requests, polled) = &mut batches[batch_id.parse::<usize>()?];
+            if !*polled {
+                *polled = true;
+                return Ok(BatchStatus::InProgress {
+                    completed: 0,
+                    total: requests.len() as u32,
+                });
+            }
+            let rejected = requests.iter().any(|r| {
+                matches!(&r.request.messages[0].content, MessageContent::Text(t) if t == "reject")
+            });
+            Ok(if rejected {
+                BatchStatus::Failed {
+                    reason: "invalid input".into(),
+                }
+            } else {
+                BatchStatus::Completed
+            })
+        }
+
+        async fn batch_results(&self, batch_id: &str) -> anyhow::Result<Vec<BatchResult>> {
+            let batches = self.batches.lock().unwrap();
+            let (requests, _) = &batches[batch_id.parse::<usize>()?];
+            Ok(requests
+                .iter()
+                .rev()
+                .map(|r| BatchResult {
+                    custom_id: r.custom_id.clone(),
+                    outcome: Ok(LlmResponse::new(r.request.messages[0].clone())),
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn run_splits_inputs_and_returns_outputs_in_order() {
+        let model = Arc::new(EchoBatches::default());
+        let executor = BatchExecutor::new(model.clone(), "Summarize")
+            .with_poll_interval(Duration::from_millis(1));
+
+        let outputs = executor.run(["a", "b", "c", "reject", "e"]).await.unwrap();
+
+        assert_eq!(model.batches.lock().unwrap().len(), 3);
+        assert_eq!(outputs.len(), 5);
+        assert_eq!(outputs[0].text().as_deref(), Some("a"));
+        assert_eq!(outputs[1].text().as_deref(), Some("b"));
+        // "c" shares the rejected batch
+        for output in &outputs[2..4] {
+            assert!(output
+                .outcome
+                .as_ref()
+                .unwrap_err()
+                .contains("invalid input"));
+        }
+        assert_eq!(outputs[4].text().as_deref(), Some("e"));
+    }
+}
@@ -0,0 +1,234 @@
+//! Discord broadcaster posting agent progress through a channel webhook
+//!
+//! Messages are rendered with [`EventTemplates`], [`EventTemplates::text`] by
+//! default, and split into several messages when longer than Discord accepts.
+//! Discord's per-webhook rate limits are honoured: deliveries wait when the
+//! bucket is exhausted and retry after `429` responses.
+
+use crate::output_format::OutputFormat;
+use crate::webhook::{send_rate_limited, split_message, EventTemplates};
+use agents_core::events::{AgentEvent, EventBroadcaster};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::json;
+use std::collections::HashSet;
+
+/// Characters per Discord message; longer texts are split into several messages
+pub const DISCORD_MESSAGE_LIMIT: usize = 2000;
+
+/// Event broadcaster posting messages to a Discord webhook.
+///
+/// Mentions in agent output (`@everyone`, users, roles) are not resolved, so a
+/// run cannot ping the channel.
+///
+/// # Example
+///
+/// ```ignore
+/// let discord = DiscordBroadcaster::new(std::env::var("DISCORD_WEBHOOK_URL")?)?
+///     .with_username("Support Agent")
+///     .with_event_types(["agent_completed", "tool_failed"]);
+/// dispatcher.add_broadcaster(Arc::new(discord));
+/// ```
+pub struct DiscordBroadcaster {
+    id: String,
+    client: Client,
+    url: String,
+    thread_id: Option<String>,
+    username: Option<String>,
+    event_types: Option<HashSet<String>>,
+    templates: EventTemplates,
+    output_format: Option<OutputFormat>,
+    /// Posts one event at a time, keeping messages in order
+    send_lock: tokio::sync::Mutex<()>,
+}
+
+impl DiscordBroadcaster {
+    /// Post through a webhook URL, `https://discord.com/api/webhooks/{id}/{token}`.
+    pub fn new(url: impl Into<String>) -> anyhow::Result<Self> {
+        Ok(Self {
+            id: "discord".to_string(),
+            client: Client::builder()
+                .user_agent("rust-deep-agents-sdk/0.1")
+                .build()?,
+            url: url.into(),
+            thread_id: None,
+            username: None,
+            event_types: None,
+            templates: EventTemplates::text(),
+            output_format: None,
+            send_lock: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    /// Broadcaster id used in delivery logs.
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = id.into();
+        self
+    }
+
+    /// Post into a thread or forum post of the webhook's channel.
+    pub fn with_thread_id(mut self, thread_id: impl Into<String>) -> Self {
+        self.thread_id = Some(thread_id.into());
+        self
+    }
+
+    /// Name shown as the author, instead of the webhook's.
+    pub fn with_username(mut self, username: impl Into<String>) -> Self {
+        self.username = Some(username.into());
+        self
+    }
+
+    /// Only post events of these types.
+    pub fn with_event_types<I, S>(mut self, event_types: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.event_types = Some(event_types.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Templates rendering the message text of each event type.
+    pub fn with_templates(mut self, templates: EventTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// Rewrite messages, e.g. to strip emoji or cut long agent replies.
+    pub fn with_output_format(mut self, format: OutputFormat) -> Self {
+        self.output_format = Some(format);
+        self
+    }
+}
+
+#[async_trait]
+impl EventBroadcaster for DiscordBroadcaster {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn should_broadcast(&self, event: &AgentEvent) -> bool {
+        let event_type = event.event_type_name();
+        self.event_types
+            .as_ref()
+            .is_none_or(|types| types.contains(event_type))
+            && self.templates.covers(event_type)
+    }
+
+    async fn broadcast(&self, event: &AgentEvent) -> anyhow::Result<()> {
+        let text = match self.templates.render(event)? {
+            Some(text) if !text.trim().is_empty() => text,
+            _ => return Ok(()),
+        };
+        let text = match &self.output_format {
+            Some(format) => format.apply(&text),
+            None => text,
+        };
+        let target = format!("Discord {}", self.id);
+
+        let _send = self.send_lock.lock().await;
+        for chunk in split_message(&text, DISCORD_MESSAGE_LIMIT) {
+            let mut body = json!({
+                "content": chunk,
+                "allowed_mentions": { "parse": [] },
+            });
+            if let Some(username) = &self.username {
+                body["username"] = json!(username);
+            }
+            let mut request = self
+                .client
+                .post(&self.url)
+                .header(
+                    crate::providers::CORRELATION_ID_HEADER,
+                    &event.metadata().correlation_id,
+                )
+                .json(&body);
+            if let Some(thread_id) = &self.thread_id {
+                request = request.query(&[("thread_id", thread_id)]);
+            }
+            send_rate_limited(request, &target).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::events::{AgentCompletedEvent, EventMetadata};
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Accepts every request, recording its raw text. The first response
+    /// exhausts the rate-limit bucket.
+    async fn serve_discord(seen: Arc<Mutex<Vec<String>>>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!(
+            "http://{}/api/webhooks/1/token",
+            listener.local_addr().unwrap()
+        );
+        tokio::spawn(async move {
+            let mut remaining = 0;
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buf = vec![0; 16 * 1024];
+                while !String::from_utf8_lossy(&request).ends_with('}') {
+                    let read = socket.read(&mut buf).await.unwrap_or(0);
+                    if read == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..read]);
+                }
+                seen.lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&request).to_string());
+                let response = format!(
+                    "HTTP/1.1 204 No Content\r\nx-ratelimit-remaining: {remaining}\r\nx-ratelimit-reset-after: 0.05\r\nconnection: close\r\n\r\n"
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+                remaining += 1;
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn long_messages_are_split_without_mentions() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let url = serve_discord(seen.clone()).await;
+        let discord = DiscordBroadcaster::new(&url)
+            .unwrap()
+            .with_thread_id("42")
+            .with_username("Support Agent")
+            .with_templates(
+                EventTemplates::new()
+                    .with_template("agent_completed", "{{ agent_name }}: {{ response }}")
+                    .unwrap(),
+            );
+
+        let event = AgentEvent::AgentCompleted(AgentCompletedEvent {
+            metadata: EventMetadata::new("thread-1".into(), "run-1".into(), None),
+            agent_name: "support".into(),
+            duration_ms: 900,
+            response_preview: String::new(),
+            response: format!("@everyone {}", "all good ".repeat(250)),
+        });
+        assert!(discord.should_broadcast(&event));
+        discord.broadcast(&event).await.unwrap();
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        let mut text = String::new();
+        for request in seen.iter() {
+            assert!(request.starts_with("POST /api/webhooks/1/token?thread_id=42 "));
+            let body: serde_json::Value =
+                serde_json::from_str(&request[request.find("\r\n\r\n").unwrap() + 4..]).unwrap();
+            assert_eq!(body["username"], "Support Agent");
+            assert_eq!(body["allowed_mentions"]["parse"], json!([]));
+            let content = body["content"].as_str().unwrap();
+            assert!(content.chars().count() <= DISCORD_MESSAGE_LIMIT);
+            text.push_str(content);
+        }
+        assert!(text.starts_with("support: @everyone all good"));
+    }
+}
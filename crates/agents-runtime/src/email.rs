@@ -0,0 +1,1141 @@
+//! Email channel: agent inbox workflows over IMAP and SMTP
+//!
+//! With the `email` feature, an [`EmailChannel`] polls a [`Mailbox`] for unseen
+//! emails, runs each on the thread of its conversation with
+//! [`DeepAgent::handle_message_for_thread`] and sends the agent's reply with a
+//! [`MailSender`]. [`ImapMailbox`] and [`SmtpSender`] connect to any IMAP and SMTP
+//! server; implement the traits for other providers.
+//!
+//! A conversation is identified by its root message: the first `Message-ID` in the
+//! `References` header, else `In-Reply-To`, else the email's own id. Replies to
+//! replies therefore land on the same thread. Attachments are saved as artifacts in
+//! the thread's files under [`EMAIL_ATTACHMENT_DIR`], where the agent reads them
+//! with its file tools; binary attachments are stored base64-encoded.
+
+use crate::agent::{DeepAgent, RunOptions};
+use crate::output_format::OutputFormat;
+use agents_core::correlation::with_correlation_id;
+use agents_core::persistence::ThreadId;
+use agents_core::secrets::{resolve_secret, SecretsProvider};
+use anyhow::Context;
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use mail_parser::{MessageParser, MimeHeaders};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+
+/// Directory of the thread files holding email attachments
+pub const EMAIL_ATTACHMENT_DIR: &str = "email_attachments";
+
+/// Emails fetched by one IMAP poll; the rest wait for the next poll
+const IMAP_FETCH_LIMIT: usize = 50;
+
+/// An email as fetched from a [`Mailbox`].
+#[derive(Debug, Clone)]
+pub struct RawEmail {
+    /// Id of the email in its mailbox, passed back to [`Mailbox::mark_seen`]
+    pub uid: u32,
+    /// RFC 5322 message
+    pub data: Vec<u8>,
+}
+
+/// Source of incoming emails.
+#[async_trait]
+pub trait Mailbox: Send + Sync {
+    /// Emails not marked as seen yet, oldest first.
+    async fn fetch_unseen(&self) -> anyhow::Result<Vec<RawEmail>>;
+
+    /// Mark an email as seen so it is not fetched again.
+    async fn mark_seen(&self, uid: u32) -> anyhow::Result<()>;
+}
+
+/// A reply to send.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutgoingEmail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+    /// `Message-ID` of the email replied to, without angle brackets
+    pub in_reply_to: Option<String>,
+    /// `Message-ID`s of the conversation, oldest first, without angle brackets
+    pub references: Vec<String>,
+}
+
+/// Delivery of outgoing emails.
+#[async_trait]
+pub trait MailSender: Send + Sync {
+    async fn send(&self, email: OutgoingEmail) -> anyhow::Result<()>;
+}
+
+/// An attachment of an [`InboundEmail`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmailAttachment {
+    pub filename: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+/// A parsed incoming email.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InboundEmail {
+    pub uid: u32,
+    /// `Message-ID`, without angle brackets
+    pub message_id: String,
+    /// `Message-ID` of the conversation's root message
+    pub conversation_id: String,
+    /// `Message-ID`s of the conversation before this email, oldest first
+    pub references: Vec<String>,
+    /// Address replies go to: `Reply-To`, else `From`
+    pub reply_to: String,
+    pub from: String,
+    pub from_name: Option<String>,
+    pub subject: String,
+    /// Plain-text body, converted from HTML when there is no text part
+    pub text: String,
+    pub attachments: Vec<EmailAttachment>,
+    /// Sent by an auto-responder or mailing list (`Auto-Submitted`), never answered
+    pub auto_submitted: bool,
+}
+
+impl InboundEmail {
+    /// Parse a fetched email, `None` when it has no sender.
+    pub fn parse(raw: &RawEmail) -> Option<Self> {
+        let message = MessageParser::default().parse(&raw.data)?;
+        let from = message.from()?.first()?;
+        let from_address = from.address()?.to_string();
+        let reply_to = message
+            .reply_to()
+            .and_then(|reply_to| reply_to.first())
+            .and_then(|reply_to| reply_to.address())
+            .unwrap_or(&from_address)
+            .to_string();
+        let message_id = message
+            .message_id()
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("uid-{}", raw.uid));
+
+        let text_list = |value: &mail_parser::HeaderValue| -> Vec<String> {
+            value
+                .as_text_list()
+                .map(|ids| ids.iter().map(|id| id.to_string()).collect())
+                .unwrap_or_default()
+        };
+        let mut references = text_list(message.references());
+        let in_reply_to = text_list(message.in_reply_to());
+        for id in in_reply_to.iter().rev() {
+            if !references.contains(id) {
+                references.push(id.clone());
+            }
+        }
+        let conversation_id = references.first().unwrap_or(&message_id).clone();
+
+        let attachments = message
+            .attachments()
+            .map(|part| EmailAttachment {
+                filename: part.attachment_name().unwrap_or("attachment").to_string(),
+                content_type: part
+                    .content_type()
+                    .map(|ct| match ct.subtype() {
+                        Some(subtype) => format!("{}/{}", ct.ctype(), subtype),
+                        None => ct.ctype().to_string(),
+                    })
+                    .unwrap_or_else(|| "application/octet-stream".to_string()),
+                data: part.contents().to_vec(),
+            })
+            .collect();
+
+        Some(Self {
+            uid: raw.uid,
+            message_id,
+            conversation_id,
+            references,
+            reply_to,
+            from: from_address,
+            from_name: from.name().map(str::to_string),
+            subject: message.subject().unwrap_or_default().to_string(),
+            text: message.body_text(0).unwrap_or_default().into_owned(),
+            attachments,
+            auto_submitted: message
+                .header_raw("Auto-Submitted")
+                .is_some_and(|value| !value.trim().eq_ignore_ascii_case("no")),
+        })
+    }
+}
+
+type ThreadMapper = Arc<dyn Fn(&InboundEmail) -> ThreadId + Send + Sync>;
+
+/// Inbox connector between a [`Mailbox`], a [`DeepAgent`] and a [`MailSender`].
+///
+/// Each conversation gets its own thread, `email:{conversation_id}` by default.
+/// Attachments are only kept when the agent has a checkpointer.
+///
+/// ```ignore
+/// let mailbox = ImapMailbox::new(
+///     ImapConfig::new("imap.example.com", "support@example.com", "secret://mail/password")
+///         .resolve_secrets(secrets.as_ref())
+///         .await?,
+/// );
+/// let sender = SmtpSender::new(
+///     SmtpConfig::new("smtp.example.com", "support@example.com", "secret://mail/password")
+///         .resolve_secrets(secrets.as_ref())
+///         .await?,
+/// )?;
+/// let channel = EmailChannel::new(agent.clone(), Arc::new(mailbox), Arc::new(sender))
+///     .with_poll_interval(Duration::from_secs(60));
+///
+/// channel.run_until(tokio::signal::ctrl_c().map(|_| ())).await;
+/// ```
+#[derive(Clone)]
+pub struct EmailChannel {
+    agent: Arc<DeepAgent>,
+    mailbox: Arc<dyn Mailbox>,
+    sender: Arc<dyn MailSender>,
+    poll_interval: Duration,
+    thread_mapper: ThreadMapper,
+    error_reply: Option<String>,
+    output_format: Option<OutputFormat>,
+}
+
+impl EmailChannel {
+    pub fn new(
+        agent: Arc<DeepAgent>,
+        mailbox: Arc<dyn Mailbox>,
+        sender: Arc<dyn MailSender>,
+    ) -> Self {
+        Self {
+            agent,
+            mailbox,
+            sender,
+            poll_interval: Duration::from_secs(30),
+            thread_mapper: Arc::new(|email| format!("email:{}", email.conversation_id)),
+            error_reply: None,
+            output_format: None,
+        }
+    }
+
+    /// Time between polls of the mailbox (default: 30 seconds).
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Choose the thread of each email, e.g. the thread of a support ticket.
+    pub fn with_thread_mapper(
+        mut self,
+        mapper: impl Fn(&InboundEmail) -> ThreadId + Send + Sync + 'static,
+    ) -> Self {
+        self.thread_mapper = Arc::new(mapper);
+        self
+    }
+
+    /// Reply with `text` when a run fails, instead of leaving the sender without answer.
+    pub fn with_error_reply(mut self, text: impl Into<String>) -> Self {
+        self.error_reply = Some(text.into());
+        self
+    }
+
+    /// Rewrite replies for the channel, e.g. with [`OutputFormat::plain_text`].
+    pub fn with_output_format(mut self, format: OutputFormat) -> Self {
+        self.output_format = Some(format);
+        self
+    }
+
+    /// Poll the mailbox until `shutdown` completes, finishing the email in progress.
+    pub async fn run_until<F: Future<Output = ()>>(&self, shutdown: F) {
+        tokio::pin!(shutdown);
+        tracing::info!("Email channel started");
+        loop {
+            match self.poll_once().await {
+                Ok(0) => {}
+                Ok(handled) => tracing::debug!(handled, "Answered emails"),
+                Err(e) => tracing::warn!(error = %e, "Failed to poll mailbox"),
+            }
+            tokio::select! {
+                _ = &mut shutdown => break,
+                _ = tokio::time::sleep(self.poll_interval) => {}
+            }
+        }
+        tracing::info!("Email channel stopped");
+    }
+
+    /// Answer the unseen emails, returning how many were handled.
+    ///
+    /// An email is marked as seen once handled, even when its run failed, so it is
+    /// not run again. When the reply cannot be sent it stays unseen and is retried
+    /// on the next poll.
+    pub async fn poll_once(&self) -> anyhow::Result<usize> {
+        let mut handled = 0;
+        for raw in self.mailbox.fetch_unseen().await? {
+            match InboundEmail::parse(&raw) {
+                Some(email) => self.handle_inbound(&email).await?,
+                None => tracing::warn!(uid = raw.uid, "Skipping email without sender"),
+            }
+            self.mailbox.mark_seen(raw.uid).await?;
+            handled += 1;
+        }
+        Ok(handled)
+    }
+
+    /// Run `email` on its conversation's thread and send the reply.
+    pub async fn handle_inbound(&self, email: &InboundEmail) -> anyhow::Result<()> {
+        if email.auto_submitted {
+            tracing::debug!(message_id = %email.message_id, "Not answering automatic email");
+            return Ok(());
+        }
+        let thread_id = (self.thread_mapper)(email);
+        let artifacts = self.store_attachments(&thread_id, email).await?;
+
+        let mut options = RunOptions::new();
+        options.output_format = self.output_format.clone();
+        let run = self.agent.handle_message_for_thread_with_options(
+            &thread_id,
+            agent_input(email, &artifacts),
+            options,
+        );
+        let reply = match with_correlation_id(email.message_id.clone(), run).await {
+            Ok(reply) => reply.content.text().into_owned(),
+            Err(e) => {
+                tracing::error!(thread_id = %thread_id, error = %e, "Email run failed");
+                match &self.error_reply {
+                    Some(error_reply) => error_reply.clone(),
+                    None => return Ok(()),
+                }
+            }
+        };
+        if reply.trim().is_empty() {
+            return Ok(());
+        }
+
+        let mut references = email.references.clone();
+        references.push(email.message_id.clone());
+        self.sender
+            .send(OutgoingEmail {
+                to: email.reply_to.clone(),
+                subject: reply_subject(&email.subject),
+                body: reply,
+                in_reply_to: Some(email.message_id.clone()),
+                references,
+            })
+            .await
+            .with_context(|| format!("Failed to send reply to {}", email.message_id))
+    }
+
+    /// Save the attachments to the thread's files, returning their paths.
+    async fn store_attachments<'a>(
+        &self,
+        thread_id: &ThreadId,
+        email: &'a InboundEmail,
+    ) -> anyhow::Result<Vec<(String, &'a EmailAttachment)>> {
+        if email.attachments.is_empty() {
+            return Ok(Vec::new());
+        }
+        let Some(checkpointer) = self.agent.checkpointer() else {
+            tracing::warn!(
+                message_id = %email.message_id,
+                "Dropping email attachments: the agent has no checkpointer"
+            );
+            return Ok(Vec::new());
+        };
+
+        let mut state = checkpointer
+            .load_state(thread_id)
+            .await?
+            .unwrap_or_default();
+        let mut artifacts = Vec::new();
+        for attachment in &email.attachments {
+            let dir = format!("{}/{}", EMAIL_ATTACHMENT_DIR, file_safe(&email.message_id));
+            let (path, content) = match std::str::from_utf8(&attachment.data) {
+                Ok(text) => (
+                    format!("{}/{}", dir, file_safe(&attachment.filename)),
+                    text.to_string(),
+                ),
+                Err(_) => (
+                    format!("{}/{}.base64", dir, file_safe(&attachment.filename)),
+                    STANDARD.encode(&attachment.data),
+                ),
+            };
+            state.files.insert(path.clone(), content);
+            artifacts.push((path, attachment));
+        }
+        checkpointer.save_state(thread_id, &state).await?;
+        Ok(artifacts)
+    }
+}
+
+/// The agent's input: sender, subject and body, then the stored attachments.
+fn agent_input(email: &InboundEmail, artifacts: &[(String, &EmailAttachment)]) -> String {
+    let from = match &email.from_name {
+        Some(name) => format!("{} <{}>", name, email.from),
+        None => email.from.clone(),
+    };
+    let mut input = format!(
+        "From: {}\nSubject: {}\n\n{}",
+        from,
+        email.subject,
+        email.text.trim()
+    );
+    if !artifacts.is_empty() {
+        input.push_str("\n\nAttachments:");
+        for (path, attachment) in artifacts {
+            input.push_str(&format!(
+                "\n- {} ({}, {} bytes)",
+                path,
+                attachment.content_type,
+                attachment.data.len()
+            ));
+        }
+    }
+    input
+}
+
+fn reply_subject(subject: &str) -> String {
+    let is_reply = subject
+        .get(..3)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case("re:"));
+    if is_reply {
+        subject.to_string()
+    } else {
+        format!("Re: {}", subject)
+    }
+}
+
+/// `name` with characters that are unsafe in file paths replaced.
+fn file_safe(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' | '@' => c,
+            _ => '_',
+        })
+        .collect::<String>()
+        .trim_start_matches('.')
+        .to_string()
+}
+
+/// IMAP server and account of an [`ImapMailbox`].
+#[derive(Clone)]
+pub struct ImapConfig {
+    pub host: String,
+    /// 993 by default
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+    /// Folder polled for new emails (default: `INBOX`)
+    pub mailbox: String,
+    /// Connect with TLS (default); plain connections are for local test servers
+    pub tls: bool,
+}
+
+impl std::fmt::Debug for ImapConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ImapConfig")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .field("mailbox", &self.mailbox)
+            .field("tls", &self.tls)
+            .finish()
+    }
+}
+
+impl ImapConfig {
+    pub fn new(
+        host: impl Into<String>,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        Self {
+            host: host.into(),
+            port: 993,
+            username: username.into(),
+            password: password.into(),
+            mailbox: "INBOX".to_string(),
+            tls: true,
+        }
+    }
+
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    pub fn with_mailbox(mut self, mailbox: impl Into<String>) -> Self {
+        self.mailbox = mailbox.into();
+        self
+    }
+
+    /// Connect without TLS, e.g. to a local test server.
+    pub fn without_tls(mut self) -> Self {
+        self.tls = false;
+        self
+    }
+
+    /// Replace a `secret://` reference in the password with its value from `secrets`.
+    pub async fn resolve_secrets(mut self, secrets: &dyn SecretsProvider) -> anyhow::Result<Self> {
+        self.password = resolve_secret(secrets, &self.password).await?;
+        Ok(self)
+    }
+}
+
+trait ImapStream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> ImapStream for T {}
+
+/// An untagged IMAP response line with the literals it carried.
+struct ImapResponse {
+    line: String,
+    literals: Vec<Vec<u8>>,
+}
+
+/// Logged-in IMAP connection with the mailbox selected.
+struct ImapSession {
+    stream: BufReader<Box<dyn ImapStream>>,
+    tag: u32,
+}
+
+impl ImapSession {
+    async fn connect(config: &ImapConfig) -> anyhow::Result<Self> {
+        let tcp = tokio::net::TcpStream::connect((config.host.as_str(), config.port))
+            .await
+            .with_context(|| format!("Failed to connect to IMAP server {}", config.host))?;
+        let stream: Box<dyn ImapStream> = if config.tls {
+            Box::new(tls_connect(&config.host, tcp).await?)
+        } else {
+            Box::new(tcp)
+        };
+        let mut session = Self {
+            stream: BufReader::new(stream),
+            tag: 0,
+        };
+
+        let greeting = session.read_response().await?.line;
+        if !greeting.starts_with("* OK") && !greeting.starts_with("* PREAUTH") {
+            anyhow::bail!("IMAP server refused the connection: {}", greeting);
+        }
+        session
+            .command(&format!(
+                "LOGIN {} {}",
+                quote(&config.username),
+                quote(&config.password)
+            ))
+            .await?;
+        session
+            .command(&format!("SELECT {}", quote(&config.mailbox)))
+            .await?;
+        Ok(session)
+    }
+
+    /// Run a command, returning its untagged responses; fails unless it completes with `OK`.
+    async fn command(&mut self, command: &str) -> anyhow::Result<Vec<ImapResponse>> {
+        self.tag += 1;
+        let tag = format!("A{}", self.tag);
+        let stream = self.stream.get_mut();
+        stream
+            .write_all(format!("{} {}\r\n", tag, command).as_bytes())
+            .await?;
+        stream.flush().await?;
+
+        let verb = command.split(' ').take(2).collect::<Vec<_>>().join(" ");
+        let mut responses = Vec::new();
+        loop {
+            let response = self.read_response().await?;
+            if let Some(status) = response.line.strip_prefix(&format!("{} ", tag)) {
+                if status.starts_with("OK") {
+                    return Ok(responses);
+                }
+                // LOGIN failures must not echo the password
+                let verb = if verb.starts_with("LOGIN") {
+                    "LOGIN"
+                } else {
+                    &verb
+                };
+                anyhow::bail!("IMAP {} failed: {}", verb, status);
+            }
+            responses.push(response);
+        }
+    }
+
+    /// Read one response line, including the literals (`{size}\r\n<bytes>`) in it.
+    async fn read_response(&mut self) -> anyhow::Result<ImapResponse> {
+        let mut line = String::new();
+        let mut literals = Vec::new();
+        loop {
+            let mut part = Vec::new();
+            if self.stream.read_until(b'\n', &mut part).await? == 0 {
+                anyhow::bail!("IMAP server closed the connection");
+            }
+            let part = String::from_utf8_lossy(&part);
+            let part = part.trim_end_matches(['\r', '\n']);
+            line.push_str(part);
+            match literal_size(part) {
+                Some(size) => {
+                    let mut literal = vec![0; size];
+                    self.stream.read_exact(&mut literal).await?;
+                    literals.push(literal);
+                }
+                None => return Ok(ImapResponse { line, literals }),
+            }
+        }
+    }
+
+    async fn fetch_unseen(&mut self) -> anyhow::Result<Vec<RawEmail>> {
+        let mut uids: Vec<u32> = Vec::new();
+        for response in self.command("UID SEARCH UNSEEN").await? {
+            if let Some(found) = response.line.strip_prefix("* SEARCH") {
+                uids.extend(
+                    found
+                        .split_whitespace()
+                        .filter_map(|uid| uid.parse::<u32>().ok()),
+                );
+            }
+        }
+        uids.sort_unstable();
+        uids.truncate(IMAP_FETCH_LIMIT);
+        if uids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let set = uids
+            .iter()
+            .map(u32::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        let mut emails = Vec::new();
+        for mut response in self
+            .command(&format!("UID FETCH {} (UID BODY.PEEK[])", set))
+            .await?
+        {
+            let uid = response
+                .line
+                .split_once("UID ")
+                .and_then(|(_, rest)| rest.split([' ', ')']).next())
+                .and_then(|uid| uid.parse().ok());
+            if let (Some(uid), false) = (uid, response.literals.is_empty()) {
+                emails.push(RawEmail {
+                    uid,
+                    data: response.literals.swap_remove(0),
+                });
+            }
+        }
+        emails.sort_by_key(|email| email.uid);
+        Ok(emails)
+    }
+
+    async fn mark_seen(&mut self, uid: u32) -> anyhow::Result<()> {
+        self.command(&format!("UID STORE {} +FLAGS.SILENT (\\Seen)", uid))
+            .await?;
+        Ok(())
+    }
+}
+
+async fn tls_connect(
+    host: &str,
+    tcp: tokio::net::TcpStream,
+) -> anyhow::Result<tokio_rustls::client::TlsStream<tokio::net::TcpStream>> {
+    use tokio_rustls::rustls::{self, pki_types::ServerName, RootCertStore};
+
+    let mut roots = RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    let config = rustls::ClientConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()?
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+    let server_name = ServerName::try_from(host.to_string())?;
+    tokio_rustls::TlsConnector::from(Arc::new(config))
+        .connect(server_name, tcp)
+        .await
+        .with_context(|| format!("TLS handshake with IMAP server {} failed", host))
+}
+
+/// Size of the literal announced at the end of `line`, e.g. `{1024}`.
+fn literal_size(line: &str) -> Option<usize> {
+    let open = line.strip_suffix('}')?.rfind('{')?;
+    line[open + 1..line.len() - 1].parse().ok()
+}
+
+/// `value` as an IMAP quoted string.
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// [`Mailbox`] reading a folder of an IMAP server.
+///
+/// Supports implicit TLS (port 993); emails are fetched without setting `\Seen`
+/// until [`mark_seen`](Mailbox::mark_seen). The connection is kept between polls
+/// and reopened after errors.
+pub struct ImapMailbox {
+    config: ImapConfig,
+    session: tokio::sync::Mutex<Option<ImapSession>>,
+}
+
+impl ImapMailbox {
+    pub fn new(config: ImapConfig) -> Self {
+        Self {
+            config,
+            session: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    /// The open session, connecting first when there is none.
+    async fn session(&self) -> anyhow::Result<tokio::sync::MappedMutexGuard<'_, ImapSession>> {
+        let mut guard = self.session.lock().await;
+        if guard.is_none() {
+            *guard = Some(ImapSession::connect(&self.config).await?);
+        }
+        Ok(tokio::sync::MutexGuard::map(guard, |session| {
+            session.as_mut().expect("session is connected")
+        }))
+    }
+
+    /// Close the session after a failed command, so the next one reconnects.
+    async fn reset(&self) {
+        *self.session.lock().await = None;
+    }
+}
+
+#[async_trait]
+impl Mailbox for ImapMailbox {
+    async fn fetch_unseen(&self) -> anyhow::Result<Vec<RawEmail>> {
+        let result = self.session().await?.fetch_unseen().await;
+        if result.is_err() {
+            self.reset().await;
+        }
+        result
+    }
+
+    async fn mark_seen(&self, uid: u32) -> anyhow::Result<()> {
+        let result = self.session().await?.mark_seen(uid).await;
+        if result.is_err() {
+            self.reset().await;
+        }
+        result
+    }
+}
+
+/// How an [`SmtpSender`] secures its connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SmtpSecurity {
+    /// Upgrade with STARTTLS, port 587 by default
+    #[default]
+    StartTls,
+    /// Implicit TLS, port 465 by default
+    Tls,
+    /// No encryption, for local test servers
+    None,
+}
+
+/// SMTP server and sender address of an [`SmtpSender`].
+#[derive(Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    /// Default port of the security mode when unset
+    pub port: Option<u16>,
+    pub username: String,
+    pub password: String,
+    /// `From` of replies; the username when unset
+    pub from: Option<String>,
+    pub security: SmtpSecurity,
+}
+
+impl std::fmt::Debug for SmtpConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SmtpConfig")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .field("from", &self.from)
+            .field("security", &self.security)
+            .finish()
+    }
+}
+
+impl SmtpConfig {
+    pub fn new(
+        host: impl Into<String>,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        Self {
+            host: host.into(),
+            port: None,
+            username: username.into(),
+            password: password.into(),
+            from: None,
+            security: SmtpSecurity::default(),
+        }
+    }
+
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Sender of replies, e.g. `Support <support@example.com>`.
+    pub fn with_from(mut self, from: impl Into<String>) -> Self {
+        self.from = Some(from.into());
+        self
+    }
+
+    pub fn with_security(mut self, security: SmtpSecurity) -> Self {
+        self.security = security;
+        self
+    }
+
+    /// Replace a `secret://` reference in the password with its value from `secrets`.
+    pub async fn resolve_secrets(mut self, secrets: &dyn SecretsProvider) -> anyhow::Result<Self> {
+        self.password = resolve_secret(secrets, &self.password).await?;
+        Ok(self)
+    }
+}
+
+/// [`MailSender`] delivering through an SMTP server.
+pub struct SmtpSender {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: lettre::message::Mailbox,
+}
+
+impl SmtpSender {
+    pub fn new(config: SmtpConfig) -> anyhow::Result<Self> {
+        let builder = match config.security {
+            SmtpSecurity::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?
+            }
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)?,
+            SmtpSecurity::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host).port(25)
+            }
+        };
+        let builder = match config.port {
+            Some(port) => builder.port(port),
+            None => builder,
+        };
+        let from = config
+            .from
+            .as_deref()
+            .unwrap_or(&config.username)
+            .parse()
+            .context("Invalid SMTP sender address")?;
+        Ok(Self {
+            transport: builder
+                .credentials(Credentials::new(config.username, config.password))
+                .build(),
+            from,
+        })
+    }
+}
+
+#[async_trait]
+impl MailSender for SmtpSender {
+    async fn send(&self, email: OutgoingEmail) -> anyhow::Result<()> {
+        let mut message = lettre::Message::builder()
+            .from(self.from.clone())
+            .to(email.to.parse().context("Invalid recipient address")?)
+            .subject(email.subject);
+        if let Some(in_reply_to) = email.in_reply_to {
+            message = message.in_reply_to(format!("<{}>", in_reply_to));
+        }
+        if !email.references.is_empty() {
+            let references: Vec<String> = email
+                .references
+                .iter()
+                .map(|id| format!("<{}>", id))
+                .collect();
+            message = message.references(references.join(" "));
+        }
+        let message = message.header(ContentType::TEXT_PLAIN).body(email.body)?;
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::ConfigurableAgentBuilder;
+    use crate::test_support::EchoModel;
+
+    use agents_core::persistence::{Checkpointer, InMemoryCheckpointer};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryMailbox {
+        unseen: Mutex<Vec<RawEmail>>,
+    }
+
+    #[async_trait]
+    impl Mailbox for MemoryMailbox {
+        async fn fetch_unseen(&self) -> anyhow::Result<Vec<RawEmail>> {
+            Ok(self.unseen.lock().unwrap().clone())
+        }
+
+        async fn mark_seen(&self, uid: u32) -> anyhow::Result<()> {
+            self.unseen.lock().unwrap().retain(|email| email.uid != uid);
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct MemorySender(Mutex<Vec<OutgoingEmail>>);
+
+    #[async_trait]
+    impl MailSender for MemorySender {
+        async fn send(&self, email: OutgoingEmail) -> anyhow::Result<()> {
+            self.0.lock().unwrap().push(email);
+            Ok(())
+        }
+    }
+
+    const FIRST: &str = "From: Jane Doe <jane@example.com>\r\n\
+        To: support@example.com\r\n\
+        Subject: Invoice question\r\n\
+        Message-ID: <first@example.com>\r\n\
+        Content-Type: multipart/mixed; boundary=\"b\"\r\n\
+        \r\n\
+        --b\r\n\
+        Content-Type: text/plain\r\n\
+        \r\n\
+        Why was I charged twice?\r\n\
+        --b\r\n\
+        Content-Type: text/csv; name=\"charges.csv\"\r\n\
+        Content-Disposition: attachment; filename=\"charges.csv\"\r\n\
+        \r\n\
+        date,amount\r\n\
+        --b\r\n\
+        Content-Type: application/pdf; name=\"invoice.pdf\"\r\n\
+        Content-Disposition: attachment; filename=\"invoice.pdf\"\r\n\
+        Content-Transfer-Encoding: base64\r\n\
+        \r\n\
+        /////w==\r\n\
+        --b--\r\n";
+
+    const FOLLOW_UP: &str = "From: jane@example.com\r\n\
+        Subject: Re: Invoice question\r\n\
+        Message-ID: <third@example.com>\r\n\
+        In-Reply-To: <second@example.com>\r\n\
+        References: <first@example.com> <second@example.com>\r\n\
+        \r\n\
+        Any update?\r\n";
+
+    const AUTO_REPLY: &str = "From: bounce@example.com\r\n\
+        Subject: Out of office\r\n\
+        Auto-Submitted: auto-replied\r\n\
+        \r\n\
+        Away until Monday\r\n";
+
+    fn raw(uid: u32, data: &str) -> RawEmail {
+        RawEmail {
+            uid,
+            data: data.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn conversations_are_identified_by_their_root_message() {
+        let first = InboundEmail::parse(&raw(1, FIRST)).unwrap();
+        assert_eq!(first.conversation_id, "first@example.com");
+        assert_eq!(first.from_name.as_deref(), Some("Jane Doe"));
+        assert_eq!(first.text.trim(), "Why was I charged twice?");
+        assert_eq!(first.attachments.len(), 2);
+        assert_eq!(first.attachments[1].content_type, "application/pdf");
+        assert_eq!(first.attachments[1].data, vec![0xff, 0xff, 0xff, 0xff]);
+
+        let follow_up = InboundEmail::parse(&raw(3, FOLLOW_UP)).unwrap();
+        assert_eq!(follow_up.conversation_id, "first@example.com");
+        assert_eq!(
+            follow_up.references,
+            vec!["first@example.com", "second@example.com"]
+        );
+
+        assert!(
+            InboundEmail::parse(&raw(4, AUTO_REPLY))
+                .unwrap()
+                .auto_submitted
+        );
+        assert_eq!(reply_subject("RE: Invoice"), "RE: Invoice");
+        assert_eq!(file_safe("../etc/passwd"), "_etc_passwd");
+    }
+
+    #[tokio::test]
+    async fn emails_are_answered_on_their_conversation_thread() {
+        let checkpointer = Arc::new(InMemoryCheckpointer::new());
+        let agent = ConfigurableAgentBuilder::new("Echo")
+            .with_model(Arc::new(EchoModel("Echo: ")))
+            .with_checkpointer(checkpointer.clone())
+            .build()
+            .unwrap();
+        let mailbox = Arc::new(MemoryMailbox::default());
+        *mailbox.unseen.lock().unwrap() =
+            vec![raw(1, FIRST), raw(3, FOLLOW_UP), raw(4, AUTO_REPLY)];
+        let sender = Arc::new(MemorySender::default());
+        let channel = EmailChannel::new(Arc::new(agent), mailbox.clone(), sender.clone());
+
+        assert_eq!(channel.poll_once().await.unwrap(), 3);
+        assert!(mailbox.unseen.lock().unwrap().is_empty());
+
+        let sent = sender.0.lock().unwrap().clone();
+        assert_eq!(sent.len(), 2, "automatic emails are not answered");
+        assert_eq!(sent[0].to, "jane@example.com");
+        assert_eq!(sent[0].subject, "Re: Invoice question");
+        assert_eq!(sent[0].in_reply_to.as_deref(), Some("first@example.com"));
+        assert!(sent[0].body.contains("Why was I charged twice?"));
+        assert!(sent[0].body.contains(
+            "- email_attachments/first@example.com/invoice.pdf.base64 (application/pdf, 4 bytes)"
+        ));
+        assert_eq!(sent[1].subject, "Re: Invoice question");
+        assert_eq!(
+            sent[1].references,
+            vec![
+                "first@example.com",
+                "second@example.com",
+                "third@example.com"
+            ]
+        );
+
+        let state = checkpointer
+            .load_state(&"email:first@example.com".to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            state.files["email_attachments/first@example.com/charges.csv"].trim(),
+            "date,amount"
+        );
+        assert_eq!(
+            state.files["email_attachments/first@example.com/invoice.pdf.base64"],
+            "/////w=="
+        );
+    }
+
+    #[tokio::test]
+    async fn imap_mailbox_fetches_unseen_emails_and_marks_them_seen() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let commands = Arc::new(Mutex::new(Vec::new()));
+        let seen = commands.clone();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut socket = BufReader::new(socket);
+            socket.get_mut().write_all(b"* OK ready\r\n").await.unwrap();
+            loop {
+                let mut line = String::new();
+                if socket.read_line(&mut line).await.unwrap() == 0 {
+                    return;
+                }
+                let (tag, command) = line.trim_end().split_once(' ').unwrap();
+                seen.lock().unwrap().push(command.to_string());
+                let untagged = if command.starts_with("UID SEARCH") {
+                    "* SEARCH 7 3\r\n".to_string()
+                } else if command.starts_with("UID FETCH") {
+                    format!(
+                        "* 1 FETCH (UID 3 BODY[] {{{}}}\r\n{})\r\n* 2 FETCH (UID 7 BODY[] {{{}}}\r\n{})\r\n",
+                        AUTO_REPLY.len(),
+                        AUTO_REPLY,
+                        FOLLOW_UP.len(),
+                        FOLLOW_UP
+                    )
+                } else {
+                    String::new()
+                };
+                let reply = format!("{}{} OK done\r\n", untagged, tag);
+                socket.get_mut().write_all(reply.as_bytes()).await.unwrap();
+            }
+        });
+
+        let mailbox = ImapMailbox::new(
+            ImapConfig::new("127.0.0.1", "support@example.com", "pa\"ss")
+                .with_port(port)
+                .without_tls(),
+        );
+        let emails = mailbox.fetch_unseen().await.unwrap();
+        assert_eq!(emails.len(), 2);
+        assert_eq!(emails[0].uid, 3);
+        assert_eq!(emails[1].data, FOLLOW_UP.as_bytes());
+        mailbox.mark_seen(7).await.unwrap();
+
+        assert_eq!(
+            *commands.lock().unwrap(),
+            vec![
+                r#"LOGIN "support@example.com" "pa\"ss""#,
+                r#"SELECT "INBOX""#,
+                "UID SEARCH UNSEEN",
+                "UID FETCH 3,7 (UID BODY.PEEK[])",
+                r"UID STORE 7 +FLAGS.SILENT (\Seen)",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn smtp_replies_keep_the_conversation_headers() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (data_tx, data_rx) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut socket = BufReader::new(socket);
+            socket.get_mut().write_all(b"220 ready\r\n").await.unwrap();
+            let mut data = String::new();
+            let mut in_data = false;
+            loop {
+                let mut line = String::new();
+                if socket.read_line(&mut line).await.unwrap() == 0 {
+                    break;
+                }
+                let reply: &[u8] = if in_data {
+                    if line != ".\r\n" {
+                        data.push_str(&line);
+                        continue;
+                    }
+                    in_data = false;
+                    b"250 queued\r\n"
+                } else if line.starts_with("DATA") {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                } else if line.starts_with("EHLO") {
+                    b"250-localhost\r\n250 AUTH PLAIN\r\n"
+                } else if line.starts_with("AUTH") {
+                    b"235 authenticated\r\n"
+                } else if line.starts_with("QUIT") {
+                    socket.get_mut().write_all(b"221 bye\r\n").await.unwrap();
+                    break;
+                } else {
+                    b"250 ok\r\n"
+                };
+                socket.get_mut().write_all(reply).await.unwrap();
+            }
+            let _ = data_tx.send(data);
+        });
+
+        let sender = SmtpSender::new(
+            SmtpConfig::new("127.0.0.1", "support@example.com", "password")
+                .with_port(port)
+                .with_from("Support <support@example.com>")
+                .with_security(SmtpSecurity::None),
+        )
+        .unwrap();
+        sender
+            .send(OutgoingEmail {
+                to: "jane@example.com".into(),
+                subject: "Re: Invoice question".into(),
+                body: "We refunded the second charge.".into(),
+                in_reply_to: Some("second@example.com".into()),
+                references: vec!["first@example.com".into(), "second@example.com".into()],
+            })
+            .await
+            .unwrap();
+        drop(sender);
+
+        let data = data_rx.await.unwrap();
+        assert!(data.contains("From: Support <support@example.com>"));
+        assert!(data.contains("In-Reply-To: <second@example.com>"));
+        assert!(data.contains("References: <first@example.com> <second@example.com>"));
+        assert!(data.contains("We refunded the second charge."));
+    }
+}
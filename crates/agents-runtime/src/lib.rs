@@ -10,28 +10,109 @@ use agents_core::state::AgentStateSnapshot;
 use async_trait::async_trait;
 
 pub mod agent;
+pub mod batch;
+pub mod discord;
+#[cfg(feature = "email")]
+pub mod email;
+pub mod loadtest;
+pub mod locale;
+pub mod metering;
 pub mod middleware;
+pub mod output_format;
 pub mod planner;
+pub mod policy;
 pub mod prompts;
 pub mod providers;
+pub mod slack;
+#[cfg(feature = "telegram")]
+pub mod telegram;
+#[cfg(test)]
+mod test_support;
+#[cfg(feature = "twilio")]
+pub mod twilio;
+pub mod webhook;
+pub mod worker;
 
 // Re-export key functions for convenience - now from the agent module
 pub use agent::{
-    create_async_deep_agent, create_deep_agent, get_default_model, ConfigurableAgentBuilder,
-    DeepAgent, SubAgentConfig, SummarizationConfig,
+    create_async_deep_agent, create_deep_agent, get_default_model, AgentPool, AgentPoolKey,
+    AgentShutdownError, AnswerMatch, ApprovalConfig, BenchmarkReport, BenchmarkRunner,
+    BenchmarkSuite, BenchmarkSummary, BenchmarkTask, BuiltinSkill, CannedResponse, CitationConfig,
+    ConcurrencyConfig, ConcurrencyLimitError, ConcurrencyStats, ConfigIssue, ConfigValidationError,
+    ConfigurableAgentBuilder, ConsentStore, DeepAgent, DeterministicConfig, Experiment,
+    ExperimentVariant, GraphMemoryConfig, InMemoryConsentStore, KeywordClassifier,
+    KeywordToolSelector, MemoryClassifier, MemoryDecision, MemoryPolicy, OffloadConfig,
+    OutputSinkConfig, PriorityWeights, Profile, ProfileSettings, PromptSnapshot, QuotaAction,
+    QuotaConfig, QuotaExceededError, RecordedStep, RunDebugger, RunOptions, RunPriority,
+    RunRecording, RunReport, RunTrace, SelfTestCheck, SelfTestCheckKind, SelfTestOptions,
+    SelfTestReport, SelfTestStatus, ShutdownReport, Skill, SkillRequirement, StateMetrics,
+    StateSize, StateSizeLimits, StreamCoalescing, SubAgentConfig, SummarizationConfig, TaskResult,
+    TeedChunk, ThreadExport, ThreadLockConfig, ThreadLockError, ThreadTitleConfig,
+    ToolArgumentValidation, ToolConflictStrategy, ToolExamplesConfig, ToolMock, ToolMocks,
+    ToolOutputConfig, ToolOutputStrategy, ToolRetryConfig, ToolRetryPolicy, ToolSelectionConfig,
+    ToolSelector, TranslationConfig, Translator, UserErasure, UserMemoryConfig,
 };
 
+#[cfg(feature = "debug-ui")]
+pub use agent::DebugUi;
+
+// Re-export the batch executor
+pub use batch::{BatchExecutor, BatchJob, BatchOutput};
+
+// Re-export the load-testing utility
+pub use loadtest::{
+    ConversationScript, FailureKind, HandleTarget, HttpTarget, LoadTarget, LoadTest, LoadTestReport,
+};
+
+// Re-export the output formatting profiles
+pub use output_format::{EmojiPolicy, MarkdownDialect, OutputFormat};
+
+// Re-export the policy engine integrations
+#[cfg(feature = "cedar")]
+pub use policy::CedarPolicyProvider;
+pub use policy::OpaPolicyProvider;
+
+// Re-export usage metering
+pub use metering::{StripeMeterSink, UsageMeter, UsageMeterHandle};
+
+// Re-export the webhook and chat broadcasters
+pub use discord::DiscordBroadcaster;
+pub use slack::SlackBroadcaster;
+pub use webhook::{EventTemplates, WebhookBroadcaster};
+
+// Re-export the email channel
+#[cfg(feature = "email")]
+pub use email::{
+    EmailChannel, ImapConfig, ImapMailbox, InboundEmail, MailSender, Mailbox, SmtpConfig,
+    SmtpSecurity, SmtpSender,
+};
+
+// Re-export the Telegram channel
+#[cfg(feature = "telegram")]
+pub use telegram::{TelegramChannel, TelegramConfig, TelegramMessage};
+
+// Re-export the Twilio channel
+#[cfg(feature = "twilio")]
+pub use twilio::{InboundMessage, TwilioChannel, TwilioConfig};
+
+// Re-export the job queue worker
+pub use worker::{AgentWorker, WorkerConfig, WorkerStats};
+
 // Re-export provider configurations and models
 pub use providers::{
-    AnthropicConfig, AnthropicMessagesModel, GeminiChatModel, GeminiConfig, OpenAiChatModel,
-    OpenAiConfig,
+    AnthropicConfig, AnthropicMessagesModel, CostTier, CredentialRotation, GatewayChatModel,
+    GatewayConfig, GeminiChatModel, GeminiConfig, ModelRoute, OllamaChatModel, OllamaConfig,
+    OpenAiChatModel, OpenAiConfig, ReplicaHealth, SelfHostedChatModel, SelfHostedConfig,
 };
 
 // Re-export HITL types
 pub use middleware::HitlPolicy;
 
 // Re-export prompt format for TOON support
-pub use prompts::PromptFormat;
+pub use prompts::{PromptFormat, PromptSection, PromptSections};
+
+// Re-export locale prompt packs
+pub use locale::{LocaleConfig, PromptPack};
 
 /// Default runtime wrapper that delegates to an inner agent implementation.
 pub struct RuntimeAgent<T>
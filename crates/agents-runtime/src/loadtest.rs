@@ -0,0 +1,539 @@
+//! Load testing agents and agent servers
+//!
+//! A [`LoadTest`] starts synthetic conversations at a fixed arrival rate, each
+//! playing a [`ConversationScript`] turn by turn against a [`LoadTarget`]: a
+//! [`DeepAgent`] (one thread per conversation), any [`AgentHandle`], or an HTTP chat
+//! endpoint. The [`LoadTestReport`] gives throughput, latency percentiles per turn
+//! and error rates split by cause, so provider rate limits can be told apart from
+//! failures of the agent itself.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::agent::DeepAgent;
+use agents_core::agent::AgentHandle;
+use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+use agents_core::state::AgentStateSnapshot;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+
+/// What a load test sends conversation turns to.
+#[async_trait]
+pub trait LoadTarget: Send + Sync {
+    /// Send one user message of conversation `conversation_id`, returning the reply.
+    async fn send(&self, conversation_id: &str, message: &str) -> anyhow::Result<String>;
+}
+
+#[async_trait]
+impl LoadTarget for DeepAgent {
+    async fn send(&self, conversation_id: &str, message: &str) -> anyhow::Result<String> {
+        let reply = self
+            .handle_message_for_thread(&conversation_id.to_string(), message)
+            .await?;
+        Ok(reply.content.to_text())
+    }
+}
+
+/// Target sending each turn to an [`AgentHandle`] with empty state, e.g. a remote
+/// or custom agent without thread support.
+pub struct HandleTarget {
+    agent: Arc<dyn AgentHandle>,
+}
+
+impl HandleTarget {
+    pub fn new(agent: Arc<dyn AgentHandle>) -> Self {
+        Self { agent }
+    }
+}
+
+#[async_trait]
+impl LoadTarget for HandleTarget {
+    async fn send(&self, _conversation_id: &str, message: &str) -> anyhow::Result<String> {
+        let input = AgentMessage {
+            role: MessageRole::User,
+            content: MessageContent::Text(message.to_string()),
+            metadata: None,
+        };
+        let reply = self
+            .agent
+            .handle_message(input, Arc::new(AgentStateSnapshot::default()))
+            .await?;
+        Ok(reply.content.to_text())
+    }
+}
+
+/// Target posting each turn as JSON to an HTTP chat endpoint.
+///
+/// The request body is `{"message": ..., "session_id": ...}` and the reply is read
+/// from the `response` field, as served by the `deep-agent-server` example; the
+/// field names are configurable.
+pub struct HttpTarget {
+    client: reqwest::Client,
+    url: String,
+    headers: Vec<(String, String)>,
+    message_field: String,
+    conversation_field: String,
+    response_field: String,
+}
+
+impl HttpTarget {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.into(),
+            headers: Vec::new(),
+            message_field: "message".to_string(),
+            conversation_field: "session_id".to_string(),
+            response_field: "response".to_string(),
+        }
+    }
+
+    /// Send a header with every request, e.g. `Authorization`.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Request field carrying the user message (default: `message`).
+    pub fn with_message_field(mut self, field: impl Into<String>) -> Self {
+        self.message_field = field.into();
+        self
+    }
+
+    /// Request field carrying the conversation id (default: `session_id`).
+    pub fn with_conversation_field(mut self, field: impl Into<String>) -> Self {
+        self.conversation_field = field.into();
+        self
+    }
+
+    /// Response field carrying the reply (default: `response`).
+    pub fn with_response_field(mut self, field: impl Into<String>) -> Self {
+        self.response_field = field.into();
+        self
+    }
+}
+
+#[async_trait]
+impl LoadTarget for HttpTarget {
+    async fn send(&self, conversation_id: &str, message: &str) -> anyhow::Result<String> {
+        let body = serde_json::json!({
+            &self.message_field: message,
+            &self.conversation_field: conversation_id,
+        });
+        let mut request = self.client.post(&self.url).json(&body);
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            anyhow::bail!("HTTP {} - {}", status, error_text);
+        }
+        let reply: serde_json::Value = response.json().await?;
+        Ok(match &reply[&self.response_field] {
+            serde_json::Value::String(text) => text.clone(),
+            serde_json::Value::Null => String::new(),
+            other => other.to_string(),
+        })
+    }
+}
+
+/// User messages of a synthetic conversation, sent in order.
+///
+/// `{conversation}` in a message is replaced with the conversation's number, so
+/// conversations can differ, e.g. `"Book a service for car {conversation}"`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConversationScript {
+    pub name: String,
+    pub turns: Vec<String>,
+}
+
+impl ConversationScript {
+    pub fn new<I, S>(name: impl Into<String>, turns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            turns: turns.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// Cause of a failed turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    /// The provider or server answered 429
+    RateLimited,
+    /// Another error returned by a model provider's API
+    Provider,
+    /// The turn ran longer than the test's timeout
+    Timeout,
+    /// Any other error, e.g. a tool or the agent failing
+    Other,
+}
+
+impl FailureKind {
+    /// Classify an error by its message and source chain.
+    pub fn classify(error: &anyhow::Error) -> Self {
+        if let Some(status) = error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<reqwest::Error>()?.status())
+        {
+            return if status.as_u16() == 429 {
+                Self::RateLimited
+            } else {
+                Self::Provider
+            };
+        }
+        let message = error.to_string();
+        if message.contains("429") || message.to_lowercase().contains("rate limit") {
+            Self::RateLimited
+        } else if message.contains("API error") {
+            Self::Provider
+        } else {
+            Self::Other
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::RateLimited => "rate limited",
+            Self::Provider => "provider error",
+            Self::Timeout => "timeout",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// Drives synthetic conversations against a target.
+///
+/// # Example
+///
+/// ```ignore
+/// let report = LoadTest::new(Arc::new(HttpTarget::new("http://localhost:3000/api/v1/chat")))
+///     .with_script(ConversationScript::new(
+///         "booking",
+///         ["Hi, I need a service for car {conversation}", "Tomorrow at 9 works"],
+///     ))
+///     .with_arrival_rate(5.0)
+///     .with_conversations(200)
+///     .with_max_concurrency(50)
+///     .run()
+///     .await?;
+/// println!("{}", report.to_markdown());
+/// ```
+pub struct LoadTest {
+    target: Arc<dyn LoadTarget>,
+    scripts: Vec<ConversationScript>,
+    arrival_rate: f64,
+    conversations: usize,
+    max_concurrency: usize,
+    think_time: Duration,
+    timeout: Option<Duration>,
+}
+
+impl LoadTest {
+    pub fn new(target: Arc<dyn LoadTarget>) -> Self {
+        Self {
+            target,
+            scripts: Vec::new(),
+            arrival_rate: 1.0,
+            conversations: 10,
+            max_concurrency: 100,
+            think_time: Duration::ZERO,
+            timeout: None,
+        }
+    }
+
+    /// Add a script; conversations take the scripts in turn.
+    pub fn with_script(mut self, script: ConversationScript) -> Self {
+        self.scripts.push(script);
+        self
+    }
+
+    /// New conversations started per second (default: 1).
+    pub fn with_arrival_rate(mut self, per_second: f64) -> Self {
+        self.arrival_rate = per_second;
+        self
+    }
+
+    /// Conversations to run in total (default: 10).
+    pub fn with_conversations(mut self, conversations: usize) -> Self {
+        self.conversations = conversations;
+        self
+    }
+
+    /// Most conversations in flight; arrivals beyond it wait (default: 100).
+    pub fn with_max_concurrency(mut self, max: usize) -> Self {
+        self.max_concurrency = max.max(1);
+        self
+    }
+
+    /// Pause between a reply and the next turn of a conversation (default: none).
+    pub fn with_think_time(mut self, think_time: Duration) -> Self {
+        self.think_time = think_time;
+        self
+    }
+
+    /// Fail turns that take longer than `timeout` (default: no limit).
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Run every conversation and report. A conversation stops at its first
+    /// failed turn.
+    pub async fn run(&self) -> anyhow::Result<LoadTestReport> {
+        if self.scripts.is_empty() {
+            anyhow::bail!("A load test needs at least one conversation script");
+        }
+        if self.arrival_rate.is_nan() || self.arrival_rate <= 0.0 {
+            anyhow::bail!("The arrival rate must be positive");
+        }
+        let run_id = uuid::Uuid::new_v4().simple().to_string();
+        let slots = Arc::new(Semaphore::new(self.max_concurrency));
+        let mut interval = tokio::time::interval(Duration::from_secs_f64(1.0 / self.arrival_rate));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let started = Instant::now();
+
+        let mut conversations = tokio::task::JoinSet::new();
+        for number in 0..self.conversations {
+            interval.tick().await;
+            let slot = slots.clone().acquire_owned().await?;
+            let target = self.target.clone();
+            let script = self.scripts[number % self.scripts.len()].clone();
+            let conversation_id = format!("loadtest-{run_id}-{number}");
+            let (think_time, timeout) = (self.think_time, self.timeout);
+            conversations.spawn(async move {
+                let _slot = slot;
+                let mut turns = Vec::with_capacity(script.turns.len());
+                for (index, message) in script.turns.iter().enumerate() {
+                    if index > 0 && !think_time.is_zero() {
+                        tokio::time::sleep(think_time).await;
+                    }
+                    let message = message.replace("{conversation}", &number.to_string());
+                    let turn_started = Instant::now();
+                    let sent = target.send(&conversation_id, &message);
+                    let outcome = match timeout {
+                        Some(timeout) => tokio::time::timeout(timeout, sent)
+                            .await
+                            .map_err(|_| FailureKind::Timeout),
+                        None => Ok(sent.await),
+                    }
+                    .and_then(|reply| {
+                        reply.map_err(|e| {
+                            tracing::debug!(%conversation_id, "Load test turn failed: {}", e);
+                            FailureKind::classify(&e)
+                        })
+                    });
+                    let failed = outcome.is_err();
+                    turns.push((turn_started.elapsed(), outcome.err()));
+                    if failed {
+                        break;
+                    }
+                }
+                turns
+            });
+        }
+
+        let mut latencies = Vec::new();
+        let mut failures: BTreeMap<FailureKind, usize> = BTreeMap::new();
+        let mut completed = 0;
+        while let Some(turns) = conversations.join_next().await {
+            let turns = turns?;
+            let mut conversation_failed = false;
+            for (latency, failure) in turns {
+                match failure {
+                    None => latencies.push(latency.as_millis() as u64),
+                    Some(kind) => {
+                        *failures.entry(kind).or_default() += 1;
+                        conversation_failed = true;
+                    }
+                }
+            }
+            if !conversation_failed {
+                completed += 1;
+            }
+        }
+        Ok(LoadTestReport::new(
+            self.conversations,
+            completed,
+            latencies,
+            failures,
+            started.elapsed(),
+        ))
+    }
+}
+
+/// Results of a load test.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LoadTestReport {
+    pub conversations: usize,
+    /// Conversations whose every turn succeeded
+    pub completed_conversations: usize,
+    pub turns: usize,
+    pub failed_turns: usize,
+    pub duration_ms: u64,
+    /// Successful turns per second over the whole test
+    pub throughput: f64,
+    pub p50_latency_ms: u64,
+    pub p90_latency_ms: u64,
+    pub p99_latency_ms: u64,
+    pub max_latency_ms: u64,
+    /// Failed turns by cause
+    pub failures: BTreeMap<FailureKind, usize>,
+}
+
+impl LoadTestReport {
+    fn new(
+        conversations: usize,
+        completed_conversations: usize,
+        mut latencies: Vec<u64>,
+        failures: BTreeMap<FailureKind, usize>,
+        duration: Duration,
+    ) -> Self {
+        latencies.sort_unstable();
+        let percentile = |p: f64| {
+            if latencies.is_empty() {
+                0
+            } else {
+                latencies[((latencies.len() - 1) as f64 * p).round() as usize]
+            }
+        };
+        let failed_turns: usize = failures.values().sum();
+        Self {
+            conversations,
+            completed_conversations,
+            turns: latencies.len() + failed_turns,
+            failed_turns,
+            duration_ms: duration.as_millis() as u64,
+            throughput: latencies.len() as f64 / duration.as_secs_f64().max(f64::EPSILON),
+            p50_latency_ms: percentile(0.5),
+            p90_latency_ms: percentile(0.9),
+            p99_latency_ms: percentile(0.99),
+            max_latency_ms: latencies.last().copied().unwrap_or(0),
+            failures,
+        }
+    }
+
+    /// Share of turns that failed with `kind`, from 0.0 to 1.0.
+    pub fn error_rate(&self, kind: FailureKind) -> f64 {
+        if self.turns == 0 {
+            return 0.0;
+        }
+        self.failures.get(&kind).copied().unwrap_or(0) as f64 / self.turns as f64
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+
+    /// Render the report as a Markdown table.
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::new();
+        let _ = writeln!(md, "# Load Test\n");
+        let _ = writeln!(md, "| Metric | Value |\n|--------|-------|");
+        let _ = writeln!(
+            md,
+            "| Conversations | {} ({} completed) |",
+            self.conversations, self.completed_conversations
+        );
+        let _ = writeln!(
+            md,
+            "| Turns | {} ({} failed) |",
+            self.turns, self.failed_turns
+        );
+        let _ = writeln!(
+            md,
+            "| Duration | {:.1}s |",
+            self.duration_ms as f64 / 1_000.0
+        );
+        let _ = writeln!(md, "| Throughput | {:.2} turns/s |", self.throughput);
+        let _ = writeln!(
+            md,
+            "| Latency | p50 {}ms / p90 {}ms / p99 {}ms / max {}ms |",
+            self.p50_latency_ms, self.p90_latency_ms, self.p99_latency_ms, self.max_latency_ms
+        );
+        for (kind, count) in &self.failures {
+            let _ = writeln!(
+                md,
+                "| Errors: {} | {} ({:.1}%) |",
+                kind.label(),
+                count,
+                self.error_rate(*kind) * 100.0
+            );
+        }
+        md
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Replies to every turn, rate limiting every third one
+    #[derive(Default)]
+    struct FlakyTarget {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LoadTarget for FlakyTarget {
+        async fn send(&self, conversation_id: &str, message: &str) -> anyhow::Result<String> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            if call % 3 == 2 {
+                anyhow::bail!("OpenAI API error: 429 Too Many Requests - slow down");
+            }
+            assert!(conversation_id.starts_with("loadtest-"));
+            Ok(format!("ok: {message}"))
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_throughput_latency_and_error_rates() {
+        let target = Arc::new(FlakyTarget::default());
+        let report = LoadTest::new(target.clone())
+            .with_script(ConversationScript::new(
+                "booking",
+                ["Service for car {conversation}", "Tomorrow"],
+            ))
+            .with_arrival_rate(200.0)
+            .with_conversations(6)
+            .with_max_concurrency(1)
+            .run()
+            .await
+            .unwrap();
+
+        // With one conversation at a time the calls alternate ok, ok, 429, ...
+        assert_eq!(report.conversations, 6);
+        assert_eq!(report.turns, 9);
+        assert_eq!(report.failed_turns, 3);
+        assert_eq!(report.failures[&FailureKind::RateLimited], 3);
+        assert!((report.error_rate(FailureKind::RateLimited) - 1.0 / 3.0).abs() < 1e-9);
+        assert!(report.p50_latency_ms >= 5);
+        assert!(report.throughput > 0.0);
+        assert!(report
+            .to_markdown()
+            .contains("| Errors: rate limited | 3 (33.3%) |"));
+
+        assert_eq!(
+            FailureKind::classify(&anyhow::anyhow!("Tool get_weather failed")),
+            FailureKind::Other
+        );
+        assert_eq!(
+            FailureKind::classify(&anyhow::anyhow!(
+                "Anthropic API error: 529 Overloaded - try again"
+            )),
+            FailureKind::Provider
+        );
+    }
+}
@@ -0,0 +1,337 @@
+//! Locale-aware prompt packs
+//!
+//! A [`PromptPack`] holds the localized pieces of what the model sees: an instruction
+//! telling it which language to answer in, an optional replacement for the built-in
+//! Deep Agent system prompt, and translated tool descriptions.
+//!
+//! A [`LocaleConfig`] selects packs through a fallback chain. For `ar-AE` the chain is
+//! `ar-AE` → `ar` → configured fallbacks → `en`; each localized piece is taken from the
+//! first pack in the chain that provides it. The locale of a thread can be overridden
+//! at runtime through [`AgentStateSnapshot::locale`], which is persisted with the rest
+//! of the thread state.
+//!
+//! English (`en`) and Arabic (`ar`) packs are built in.
+
+use agents_core::state::AgentStateSnapshot;
+use agents_core::tools::ToolSchema;
+use std::collections::HashMap;
+
+/// Locale used when nothing else is configured.
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// Placeholder in [`PromptPack::system_prompt`] replaced by the agent instructions.
+pub const INSTRUCTIONS_PLACEHOLDER: &str = "{custom_instructions}";
+
+/// Localized prompt and tool text for one locale.
+#[derive(Debug, Clone, Default)]
+pub struct PromptPack {
+    /// BCP 47 tag, e.g. `ar` or `ar-AE`.
+    pub locale: String,
+    /// Appended to the system prompt to fix the response language.
+    pub response_instruction: Option<String>,
+    /// Replaces the built-in Deep Agent prompt. Must contain [`INSTRUCTIONS_PLACEHOLDER`].
+    pub system_prompt: Option<String>,
+    /// Tool name → localized description.
+    pub tool_descriptions: HashMap<String, String>,
+}
+
+impl PromptPack {
+    pub fn new(locale: impl Into<String>) -> Self {
+        Self {
+            locale: locale.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_response_instruction(mut self, instruction: impl Into<String>) -> Self {
+        self.response_instruction = Some(instruction.into());
+        self
+    }
+
+    pub fn with_system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.system_prompt = Some(prompt.into());
+        self
+    }
+
+    pub fn with_tool_description(
+        mut self,
+        tool_name: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        self.tool_descriptions
+            .insert(tool_name.into(), description.into());
+        self
+    }
+
+    /// The built-in English pack. The default prompt is already English, so it only
+    /// pins the response language.
+    pub fn english() -> Self {
+        Self::new("en").with_response_instruction("Always respond in English.")
+    }
+
+    /// The built-in Arabic pack (Modern Standard Arabic).
+    pub fn arabic() -> Self {
+        Self::new("ar")
+            .with_response_instruction(
+                "أجب دائمًا باللغة العربية الفصحى، حتى إذا كتب المستخدم بلغة أخرى، \
+                 ما لم يطلب المستخدم صراحةً لغة مختلفة. \
+                 اكتب أسماء الأدوات ووسائطها كما هي دون ترجمة.",
+            )
+            .with_system_prompt(ARABIC_SYSTEM_PROMPT)
+            .with_tool_description("write_todos", "تحديث قائمة مهام الوكيل لتتبع تقدم العمل")
+            .with_tool_description("read_todos", "قراءة قائمة المهام الحالية للتحقق من تقدم العمل")
+            .with_tool_description("ls", "عرض جميع الملفات في نظام الملفات")
+            .with_tool_description(
+                "read_file",
+                "قراءة محتوى ملف مع إمكانية تحديد سطر البداية وعدد الأسطر",
+            )
+            .with_tool_description(
+                "write_file",
+                "كتابة محتوى في ملف (ينشئ ملفًا جديدًا أو يستبدل الملف الموجود)",
+            )
+            .with_tool_description("edit_file", "تعديل ملف باستبدال old_string بـ new_string")
+            .with_tool_description(
+                "task",
+                "تفويض مهمة إلى وكيل فرعي متخصص. استخدمها عندما تحتاج إلى خبرة متخصصة أو لتقسيم المهام المعقدة.",
+            )
+    }
+}
+
+const ARABIC_SYSTEM_PROMPT: &str = r#"{custom_instructions}
+
+═══════════════════════════════════════════════════════════════
+🤖 نظام الوكيل العميق - استخدام الأدوات إلزامي
+═══════════════════════════════════════════════════════════════
+
+أنت وكيل عميق لديك أدوات ووكلاء فرعيون. عندما تتوفر الأدوات يجب عليك استخدامها.
+لا تكتفِ بوصف ما ستفعله، بل استدعِ الأدوات فعلًا.
+
+## 🔧 قواعد استخدام الأدوات
+
+1. **استخدم الأدوات دائمًا عند توفرها** - لا تتحدث عن استخدامها فقط
+2. **استدعِ الأدوات بصيغة JSON الصحيحة** - التزم بالمخطط المقدم حرفيًا
+3. **أجب دائمًا بعد تنفيذ الأدوات** - قدّم ردًا طبيعيًا للمستخدم بعد كل استدعاء
+4. **نفّذ بصمت** - لا تعلن أنك ستستدعي أداة، بل استدعها مباشرة
+5. **استفد من النتائج** - استخدم مخرجات الأداة في ردك
+6. **لا ترسل ردًا فارغًا أبدًا** - قدّم دائمًا نصًا مفيدًا للمستخدم
+
+## 📋 الأدوات المدمجة
+
+- **write_todos**: أنشئ خطة منظمة للطلبات متعددة الخطوات وحدّثها مع تقدم العمل
+- **ls / read_file / write_file / edit_file**: احفظ المعلومات في نظام الملفات الافتراضي واسترجعها
+- **task**: فوّض المهام المتخصصة إلى الوكلاء الفرعيين
+
+## ✅ تذكّر
+
+- الأدوات ليست اختيارية: إذا وُجدت أداة للمهمة فاستخدمها
+- أسماء الأدوات ووسائطها تبقى بالإنجليزية كما هي في المخطط
+- هدفك مساعدة المستخدم، وليس مجرد استدعاء الأدوات
+
+═══════════════════════════════════════════════════════════════
+نهاية تعليمات نظام الوكيل العميق
+═══════════════════════════════════════════════════════════════
+"#;
+
+/// Locale selection for an agent: default locale, fallback chain and prompt packs.
+///
+/// # Example
+///
+/// ```ignore
+/// let locale = LocaleConfig::new("ar-AE")
+///     .with_fallbacks(["en"])
+///     .with_pack(PromptPack::new("ar-AE").with_tool_description("lookup_vehicle", "..."));
+/// ```
+#[derive(Debug, Clone)]
+pub struct LocaleConfig {
+    /// Locale used when the thread state does not set one.
+    pub default_locale: String,
+    /// Locales tried after the requested locale and its parent tags.
+    pub fallbacks: Vec<String>,
+    packs: HashMap<String, PromptPack>,
+}
+
+impl Default for LocaleConfig {
+    fn default() -> Self {
+        Self::new(DEFAULT_LOCALE)
+    }
+}
+
+impl LocaleConfig {
+    /// Use `default_locale` with the built-in packs registered.
+    pub fn new(default_locale: impl Into<String>) -> Self {
+        Self {
+            default_locale: default_locale.into(),
+            fallbacks: Vec::new(),
+            packs: HashMap::new(),
+        }
+        .with_pack(PromptPack::english())
+        .with_pack(PromptPack::arabic())
+    }
+
+    /// Locales tried (in order) after the requested locale and its parent tags.
+    pub fn with_fallbacks<I, S>(mut self, fallbacks: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.fallbacks = fallbacks.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Register a pack, replacing any pack for the same locale.
+    pub fn with_pack(mut self, pack: PromptPack) -> Self {
+        self.packs.insert(normalize(&pack.locale), pack);
+        self
+    }
+
+    pub fn pack(&self, locale: &str) -> Option<&PromptPack> {
+        self.packs.get(&normalize(locale))
+    }
+
+    /// Locale for a thread: the state's locale if set, otherwise the default.
+    pub fn effective_locale<'a>(&'a self, state: &'a AgentStateSnapshot) -> &'a str {
+        state.locale.as_deref().unwrap_or(&self.default_locale)
+    }
+
+    /// Fallback chain for `locale`: the locale, its parent tags, the configured
+    /// fallbacks and finally [`DEFAULT_LOCALE`], without duplicates.
+    pub fn resolution_chain(&self, locale: &str) -> Vec<String> {
+        let mut chain: Vec<String> = Vec::new();
+        let mut push = |tag: String| {
+            if !tag.is_empty() && !chain.contains(&tag) {
+                chain.push(tag);
+            }
+        };
+
+        let mut tag = normalize(locale);
+        loop {
+            push(tag.clone());
+            match tag.rfind('-') {
+                Some(idx) => tag.truncate(idx),
+                None => break,
+            }
+        }
+        for fallback in &self.fallbacks {
+            push(normalize(fallback));
+        }
+        push(DEFAULT_LOCALE.to_string());
+        chain
+    }
+
+    /// Packs along the fallback chain of `locale`, most specific first.
+    fn packs_for(&self, locale: &str) -> impl Iterator<Item = &PromptPack> {
+        self.resolution_chain(locale)
+            .into_iter()
+            .filter_map(move |tag| self.packs.get(&tag))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    pub fn response_instruction(&self, locale: &str) -> Option<&str> {
+        self.packs_for(locale)
+            .find_map(|pack| pack.response_instruction.as_deref())
+    }
+
+    /// Localized Deep Agent prompt with the instructions filled in, if a pack in the
+    /// chain provides one.
+    pub fn system_prompt(&self, locale: &str, custom_instructions: &str) -> Option<String> {
+        self.packs_for(locale)
+            .find_map(|pack| pack.system_prompt.as_deref())
+            .map(|template| template.replace(INSTRUCTIONS_PLACEHOLDER, custom_instructions))
+    }
+
+    pub fn tool_description(&self, locale: &str, tool_name: &str) -> Option<&str> {
+        self.packs_for(locale)
+            .find_map(|pack| pack.tool_descriptions.get(tool_name).map(String::as_str))
+    }
+
+    /// Replace tool descriptions with their localized versions where available.
+    pub fn localize_tools(&self, locale: &str, schemas: Vec<ToolSchema>) -> Vec<ToolSchema> {
+        schemas
+            .into_iter()
+            .map(|mut schema| {
+                if let Some(description) = self.tool_description(locale, &schema.name) {
+                    schema.description = description.to_string();
+                }
+                schema
+            })
+            .collect()
+    }
+}
+
+/// Lowercase tag with `_` separators turned into `-` (`ar_AE` → `ar-ae`).
+fn normalize(locale: &str) -> String {
+    locale.trim().replace('_', "-").to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::tools::ToolParameterSchema;
+
+    #[test]
+    fn resolution_chain_walks_parent_tags_then_fallbacks() {
+        let config = LocaleConfig::new("ar-AE").with_fallbacks(["fr", "ar"]);
+        assert_eq!(
+            config.resolution_chain("ar_AE"),
+            vec!["ar-ae", "ar", "fr", "en"]
+        );
+        assert_eq!(
+            config.resolution_chain("en-US"),
+            vec!["en-us", "en", "fr", "ar"]
+        );
+    }
+
+    #[test]
+    fn arabic_locale_uses_arabic_pack() {
+        let config = LocaleConfig::new("ar-AE");
+        let prompt = config.system_prompt("ar-AE", "أنت مساعد").unwrap();
+        assert!(prompt.starts_with("أنت مساعد"));
+        assert!(config
+            .response_instruction("ar-AE")
+            .unwrap()
+            .contains("العربية"));
+        assert!(config.system_prompt("en", "x").is_none());
+    }
+
+    #[test]
+    fn pieces_fall_back_independently() {
+        let config = LocaleConfig::new("ar-AE")
+            .with_pack(PromptPack::new("ar-AE").with_tool_description("ls", "اعرض ملفات المركبة"));
+        assert_eq!(
+            config.tool_description("ar-AE", "ls"),
+            Some("اعرض ملفات المركبة")
+        );
+        // Not in the ar-AE pack: falls back to the ar pack.
+        assert!(config.tool_description("ar-AE", "task").is_some());
+        assert!(config.response_instruction("ar-AE").is_some());
+        // Unknown locale falls back to English.
+        assert_eq!(
+            config.response_instruction("de"),
+            Some("Always respond in English.")
+        );
+    }
+
+    #[test]
+    fn state_locale_overrides_default_and_tools_are_localized() {
+        let config = LocaleConfig::new("en");
+        let state = AgentStateSnapshot {
+            locale: Some("ar".into()),
+            ..Default::default()
+        };
+        let locale = config.effective_locale(&state);
+        assert_eq!(locale, "ar");
+
+        let schemas = vec![
+            ToolSchema::no_params("ls", "List all files in the filesystem"),
+            ToolSchema::new(
+                "custom",
+                "Custom tool",
+                ToolParameterSchema::object("p", HashMap::new(), Vec::new()),
+            ),
+        ];
+        let localized = config.localize_tools(locale, schemas);
+        assert_eq!(localized[0].description, "عرض جميع الملفات في نظام الملفات");
+        assert_eq!(localized[1].description, "Custom tool");
+    }
+}
@@ -0,0 +1,472 @@
+//! Metering token usage per tenant for usage-based billing
+//!
+//! A [`UsageMeter`] is an event broadcaster: registered on an agent with token
+//! tracking, it adds up the token usage of runs started with
+//! [`RunOptions::with_tenant`](crate::agent::RunOptions::with_tenant) per tenant. On
+//! every flush, scheduled with [`UsageMeter::spawn`], the totals become
+//! [`MeterEvent`]s delivered to a [`MeterSink`]: a callback of your own, or
+//! [`StripeMeterSink`] reporting them as Stripe billing meter events.
+//!
+//! Events keep their identifier until they are delivered. A failed delivery is
+//! retried on the next flush with the same events, so Stripe, which deduplicates
+//! meter events by identifier, bills each of them once.
+
+use agents_core::events::{AgentEvent, EventBroadcaster, TokenUsage};
+use agents_core::metering::{MeterEvent, MeterSink};
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::Utc;
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+type MeterValue = dyn Fn(&TokenUsage) -> u64 + Send + Sync;
+type CustomerLookup = dyn Fn(&str) -> Option<String> + Send + Sync;
+
+/// Event broadcaster turning per-tenant token usage into meter events.
+///
+/// # Example
+///
+/// ```ignore
+/// let meter = Arc::new(UsageMeter::new(
+///     "agent_tokens",
+///     Arc::new(StripeMeterSink::new(std::env::var("STRIPE_SECRET_KEY")?)?),
+/// ));
+/// let agent = ConfigurableAgentBuilder::new("You are a support agent")
+///     .with_model(model)
+///     .with_token_tracking(true)
+///     .with_event_broadcaster(meter.clone())
+///     .build()?;
+/// let reporter = meter.clone().spawn(Duration::from_secs(60));
+///
+/// agent
+///     .handle_message_with_options(text, RunOptions::new().with_tenant("cus_NffrFeUfNV2Hib"), state)
+///     .await?;
+///
+/// // On shutdown, report what is left
+/// reporter.stop().await;
+/// ```
+pub struct UsageMeter {
+    id: String,
+    event_name: String,
+    value: Arc<MeterValue>,
+    sink: Arc<dyn MeterSink>,
+    /// Usage per tenant not turned into events yet
+    pending: Mutex<BTreeMap<String, u64>>,
+    /// Events not delivered yet; one flush at a time
+    undelivered: tokio::sync::Mutex<Vec<MeterEvent>>,
+}
+
+impl std::fmt::Debug for UsageMeter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UsageMeter")
+            .field("id", &self.id)
+            .field("event_name", &self.event_name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl UsageMeter {
+    /// Meter the total tokens of each tenant as `event_name` events.
+    pub fn new(event_name: impl Into<String>, sink: Arc<dyn MeterSink>) -> Self {
+        Self {
+            id: "usage-meter".to_string(),
+            event_name: event_name.into(),
+            value: Arc::new(|usage| u64::from(usage.total_tokens)),
+            sink,
+            pending: Mutex::new(BTreeMap::new()),
+            undelivered: tokio::sync::Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Broadcaster id used in delivery logs.
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = id.into();
+        self
+    }
+
+    /// Meter something else than total tokens, e.g. output tokens only or the cost
+    /// in hundredths of a cent.
+    pub fn with_value(
+        mut self,
+        value: impl Fn(&TokenUsage) -> u64 + Send + Sync + 'static,
+    ) -> Self {
+        self.value = Arc::new(value);
+        self
+    }
+
+    /// Add a token usage record to its tenant's total. Records without a tenant are
+    /// not metered.
+    pub fn record(&self, usage: &TokenUsage) {
+        let Some(tenant) = &usage.tenant else {
+            return;
+        };
+        let value = (self.value)(usage);
+        if value == 0 {
+            return;
+        }
+        if let Ok(mut pending) = self.pending.lock() {
+            *pending.entry(tenant.clone()).or_default() += value;
+        }
+    }
+
+    /// Turn the totals recorded since the last flush into events and deliver them,
+    /// together with events whose delivery failed before. Returns the number of
+    /// events delivered.
+    pub async fn flush(&self) -> anyhow::Result<usize> {
+        let mut undelivered = self.undelivered.lock().await;
+        let totals = match self.pending.lock() {
+            Ok(mut pending) => std::mem::take(&mut *pending),
+            Err(_) => anyhow::bail!("Usage meter poisoned"),
+        };
+        let timestamp = Utc::now();
+        undelivered.extend(totals.into_iter().map(|(tenant, value)| MeterEvent {
+            identifier: uuid::Uuid::new_v4().to_string(),
+            event_name: self.event_name.clone(),
+            tenant,
+            value,
+            timestamp,
+        }));
+        if undelivered.is_empty() {
+            return Ok(0);
+        }
+
+        self.sink
+            .send(&undelivered)
+            .await
+            .with_context(|| format!("Failed to deliver {} meter events", undelivered.len()))?;
+        let delivered = undelivered.len();
+        undelivered.clear();
+        Ok(delivered)
+    }
+
+    /// Flush every `interval` in the background until [`UsageMeterHandle::stop`] is
+    /// called, which flushes a last time.
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> UsageMeterHandle {
+        let stop = Arc::new(Notify::new());
+        let task = tokio::spawn({
+            let stop = stop.clone();
+            async move {
+                loop {
+                    let stopped = tokio::select! {
+                        _ = stop.notified() => true,
+                        _ = tokio::time::sleep(interval) => false,
+                    };
+                    match self.flush().await {
+                        Ok(0) => {}
+                        Ok(delivered) => tracing::debug!(delivered, "Delivered meter events"),
+                        Err(e) => tracing::warn!("{:#}; retrying on the next flush", e),
+                    }
+                    if stopped {
+                        return;
+                    }
+                }
+            }
+        });
+        UsageMeterHandle { stop, task }
+    }
+}
+
+#[async_trait]
+impl EventBroadcaster for UsageMeter {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    async fn broadcast(&self, event: &AgentEvent) -> anyhow::Result<()> {
+        if let AgentEvent::TokenUsage(event) = event {
+            self.record(&event.usage);
+        }
+        Ok(())
+    }
+
+    fn should_broadcast(&self, event: &AgentEvent) -> bool {
+        matches!(event, AgentEvent::TokenUsage(_))
+    }
+}
+
+/// Handle to a [`UsageMeter`] flushing in the background.
+pub struct UsageMeterHandle {
+    stop: Arc<Notify>,
+    task: JoinHandle<()>,
+}
+
+impl UsageMeterHandle {
+    /// Flush a last time and stop.
+    pub async fn stop(self) {
+        self.stop.notify_one();
+        let _ = self.task.await;
+    }
+}
+
+#[derive(Deserialize)]
+struct StripeErrorResponse {
+    error: StripeError,
+}
+
+#[derive(Deserialize)]
+struct StripeError {
+    #[serde(default)]
+    code: Option<String>,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+/// Sink reporting meter events to Stripe's
+/// [billing meter events API](https://docs.stripe.com/api/billing/meter-event/create).
+///
+/// The event name must match the event name of a meter configured in Stripe. By
+/// default tenants are Stripe customer ids; map other tenant ids with
+/// [`with_customer_lookup`](Self::with_customer_lookup).
+///
+/// # Example
+///
+/// ```ignore
+/// let customers: HashMap<String, String> = load_customer_ids().await?;
+/// let stripe = StripeMeterSink::new(std::env::var("STRIPE_SECRET_KEY")?)?
+///     .with_customer_lookup(move |tenant| customers.get(tenant).cloned());
+/// ```
+pub struct StripeMeterSink {
+    client: Client,
+    api_key: String,
+    api_url: String,
+    customer_lookup: Option<Arc<CustomerLookup>>,
+}
+
+impl StripeMeterSink {
+    pub fn new(api_key: impl Into<String>) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: Client::builder()
+                .user_agent("rust-deep-agents-sdk/0.1")
+                .timeout(Duration::from_secs(30))
+                .build()?,
+            api_key: api_key.into(),
+            api_url: "https://api.stripe.com".to_string(),
+            customer_lookup: None,
+        })
+    }
+
+    /// API base URL, for a proxy or a mock server.
+    pub fn with_api_url(mut self, api_url: impl Into<String>) -> Self {
+        self.api_url = api_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Stripe customer id of each tenant. Events of tenants without one are logged
+    /// and dropped.
+    pub fn with_customer_lookup(
+        mut self,
+        lookup: impl Fn(&str) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.customer_lookup = Some(Arc::new(lookup));
+        self
+    }
+
+    fn customer_id(&self, tenant: &str) -> Option<String> {
+        match &self.customer_lookup {
+            Some(lookup) => lookup(tenant),
+            None => Some(tenant.to_string()),
+        }
+    }
+
+    async fn send_event(&self, event: &MeterEvent, customer_id: &str) -> anyhow::Result<()> {
+        let response = self
+            .client
+            .post(format!("{}/v1/billing/meter_events", self.api_url))
+            .bearer_auth(&self.api_key)
+            .form(&stripe_form(event, customer_id))
+            .send()
+            .await
+            .context("Failed to reach Stripe")?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let body = response.text().await.unwrap_or_default();
+        match serde_json::from_str::<StripeErrorResponse>(&body) {
+            // Delivered by an earlier attempt
+            Ok(StripeErrorResponse { error }) if is_duplicate(&error) => Ok(()),
+            Ok(StripeErrorResponse { error }) => anyhow::bail!(
+                "Stripe rejected meter event {} ({}): {}",
+                event.identifier,
+                status,
+                error.message.unwrap_or_default()
+            ),
+            Err(_) => anyhow::bail!(
+                "Stripe rejected meter event {} ({}): {}",
+                event.identifier,
+                status,
+                body
+            ),
+        }
+    }
+}
+
+#[async_trait]
+impl MeterSink for StripeMeterSink {
+    async fn send(&self, events: &[MeterEvent]) -> anyhow::Result<()> {
+        for event in events {
+            let Some(customer_id) = self.customer_id(&event.tenant) else {
+                tracing::warn!(
+                    tenant = %event.tenant,
+                    value = event.value,
+                    "No Stripe customer for tenant; dropping meter event"
+                );
+                continue;
+            };
+            self.send_event(event, &customer_id).await?;
+        }
+        Ok(())
+    }
+}
+
+fn is_duplicate(error: &StripeError) -> bool {
+    error.code.as_deref() == Some("resource_already_exists")
+}
+
+/// Form fields of a Stripe meter event.
+fn stripe_form(event: &MeterEvent, customer_id: &str) -> Vec<(&'static str, String)> {
+    vec![
+        ("event_name", event.event_name.clone()),
+        ("identifier", event.identifier.clone()),
+        ("timestamp", event.timestamp.timestamp().to_string()),
+        ("payload[stripe_customer_id]", customer_id.to_string()),
+        ("payload[value]", event.value.to_string()),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::metering::InMemoryMeterSink;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    fn usage(tenant: Option<&str>, tokens: u32) -> TokenUsage {
+        let usage = TokenUsage::new(tokens, 0, "openai", "gpt-4o-mini", 10, 0.0);
+        match tenant {
+            Some(tenant) => usage.with_tenant(tenant),
+            None => usage,
+        }
+    }
+
+    /// Fails while `down` is set, then delivers to `inner`.
+    #[derive(Default)]
+    struct FlakySink {
+        down: AtomicBool,
+        inner: InMemoryMeterSink,
+    }
+
+    #[async_trait]
+    impl MeterSink for FlakySink {
+        async fn send(&self, events: &[MeterEvent]) -> anyhow::Result<()> {
+            if self.down.load(Ordering::SeqCst) {
+                anyhow::bail!("billing API unavailable");
+            }
+            self.inner.send(events).await
+        }
+    }
+
+    #[tokio::test]
+    async fn usage_is_summed_per_tenant_and_redelivered_with_the_same_identifiers() {
+        let sink = Arc::new(FlakySink::default());
+        let meter = UsageMeter::new("agent_tokens", sink.clone());
+
+        meter.record(&usage(Some("acme"), 120));
+        meter.record(&usage(Some("acme"), 30));
+        meter.record(&usage(Some("globex"), 5));
+        meter.record(&usage(None, 1_000));
+
+        sink.down.store(true, Ordering::SeqCst);
+        assert!(meter.flush().await.is_err());
+        meter.record(&usage(Some("acme"), 50));
+
+        sink.down.store(false, Ordering::SeqCst);
+        assert_eq!(meter.flush().await.unwrap(), 3);
+        assert_eq!(meter.flush().await.unwrap(), 0);
+
+        let events = sink.inner.events();
+        let values: Vec<_> = events
+            .iter()
+            .map(|e| (e.tenant.as_str(), e.value))
+            .collect();
+        assert_eq!(values, vec![("acme", 150), ("globex", 5), ("acme", 50)]);
+        assert!(events.iter().all(|e| e.event_name == "agent_tokens"));
+    }
+
+    /// Answers every request, reporting 100 tokens.
+    struct CountedModel;
+
+    #[async_trait]
+    impl agents_core::llm::LanguageModel for CountedModel {
+        async fn generate(
+            &self,
+            _request: agents_core::llm::LlmRequest,
+        ) -> anyhow::Result<agents_core::llm::LlmResponse> {
+            let message = agents_core::messaging::AgentMessage {
+                role: agents_core::messaging::MessageRole::Agent,
+                content: agents_core::messaging::MessageContent::Text("done".to_string()),
+                metadata: None,
+            };
+            Ok(agents_core::llm::LlmResponse::new(message)
+                .with_usage(agents_core::llm::LlmUsage::new("test", "test", 80, 20)))
+        }
+    }
+
+    #[tokio::test]
+    async fn runs_are_metered_for_their_tenant() {
+        use crate::agent::{ConfigurableAgentBuilder, RunOptions};
+        use agents_core::state::AgentStateSnapshot;
+
+        let sink = Arc::new(InMemoryMeterSink::new());
+        let meter = Arc::new(UsageMeter::new("agent_tokens", sink.clone()));
+        let agent = ConfigurableAgentBuilder::new("assist")
+            .with_model(Arc::new(CountedModel))
+            .with_token_tracking(true)
+            .with_event_broadcaster(meter.clone())
+            .build()
+            .unwrap();
+
+        for options in [RunOptions::new().with_tenant("acme"), RunOptions::new()] {
+            agent
+                .handle_message_with_options("hi", options, Arc::new(AgentStateSnapshot::default()))
+                .await
+                .unwrap();
+        }
+        agent.flush_broadcasts().await;
+        meter.flush().await.unwrap();
+
+        let events = sink.events();
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].tenant.as_str(), events[0].value), ("acme", 100));
+    }
+
+    #[test]
+    fn stripe_events_are_sent_as_form_fields() {
+        let event = MeterEvent {
+            identifier: "evt-1".to_string(),
+            event_name: "agent_tokens".to_string(),
+            tenant: "acme".to_string(),
+            value: 150,
+            timestamp: chrono::DateTime::from_timestamp(1_760_000_000, 0).unwrap(),
+        };
+        let sink = StripeMeterSink::new("sk_test")
+            .unwrap()
+            .with_customer_lookup(|tenant| (tenant == "acme").then(|| "cus_123".to_string()));
+
+        assert_eq!(sink.customer_id("globex"), None);
+        assert_eq!(
+            stripe_form(&event, &sink.customer_id("acme").unwrap()),
+            vec![
+                ("event_name", "agent_tokens".to_string()),
+                ("identifier", "evt-1".to_string()),
+                ("timestamp", "1760000000".to_string()),
+                ("payload[stripe_customer_id]", "cus_123".to_string()),
+                ("payload[value]", "150".to_string()),
+            ]
+        );
+    }
+}
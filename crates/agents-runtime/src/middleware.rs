@@ -15,7 +15,13 @@ use agents_toolkit::create_filesystem_tools;
 use async_trait::async_trait;
 use serde::Deserialize;
 
+pub mod context_assembly;
+pub mod guardrails;
+pub mod prompt_compression;
+pub mod response_cache;
+pub mod time_context;
 pub mod token_tracking;
+pub mod user_profile;
 
 /// Request sent to the underlying language model. Middlewares can augment
 /// the system prompt or mutate the pending message list before the model call.
@@ -143,11 +149,21 @@ impl AgentMiddleware for SummarizationMiddleware {
 
 pub struct PlanningMiddleware {
     _state: Arc<RwLock<AgentStateSnapshot>>,
+    include_prompt: bool,
 }
 
 impl PlanningMiddleware {
     pub fn new(state: Arc<RwLock<AgentStateSnapshot>>) -> Self {
-        Self { _state: state }
+        Self {
+            _state: state,
+            include_prompt: true,
+        }
+    }
+
+    /// Provide the tools without adding their guidance to the system prompt.
+    pub fn without_prompt(mut self) -> Self {
+        self.include_prompt = false;
+        self
     }
 }
 
@@ -166,18 +182,30 @@ impl AgentMiddleware for PlanningMiddleware {
     }
 
     async fn modify_model_request(&self, ctx: &mut MiddlewareContext<'_>) -> anyhow::Result<()> {
-        ctx.request.append_prompt(WRITE_TODOS_SYSTEM_PROMPT);
+        if self.include_prompt {
+            ctx.request.append_prompt(WRITE_TODOS_SYSTEM_PROMPT);
+        }
         Ok(())
     }
 }
 
 pub struct FilesystemMiddleware {
     _state: Arc<RwLock<AgentStateSnapshot>>,
+    include_prompt: bool,
 }
 
 impl FilesystemMiddleware {
     pub fn new(state: Arc<RwLock<AgentStateSnapshot>>) -> Self {
-        Self { _state: state }
+        Self {
+            _state: state,
+            include_prompt: true,
+        }
+    }
+
+    /// Provide the tools without adding their guidance to the system prompt.
+    pub fn without_prompt(mut self) -> Self {
+        self.include_prompt = false;
+        self
     }
 }
 
@@ -192,7 +220,9 @@ impl AgentMiddleware for FilesystemMiddleware {
     }
 
     async fn modify_model_request(&self, ctx: &mut MiddlewareContext<'_>) -> anyhow::Result<()> {
-        ctx.request.append_prompt(FILESYSTEM_SYSTEM_PROMPT);
+        if self.include_prompt {
+            ctx.request.append_prompt(FILESYSTEM_SYSTEM_PROMPT);
+        }
         Ok(())
     }
 }
@@ -229,6 +259,8 @@ pub struct SubAgentMiddleware {
     task_tool: ToolBox,
     descriptors: Vec<SubAgentDescriptor>,
     _registry: Arc<SubAgentRegistry>,
+    prompt_format: crate::prompts::PromptFormat,
+    include_prompt: bool,
 }
 
 impl SubAgentMiddleware {
@@ -240,6 +272,8 @@ impl SubAgentMiddleware {
             task_tool,
             descriptors,
             _registry: registry,
+            prompt_format: crate::prompts::PromptFormat::Json,
+            include_prompt: true,
         }
     }
 
@@ -254,10 +288,43 @@ impl SubAgentMiddleware {
             task_tool,
             descriptors,
             _registry: registry,
+            prompt_format: crate::prompts::PromptFormat::Json,
+            include_prompt: true,
         }
     }
 
+    /// List the available sub-agents as a TOON table in `PromptFormat::Toon`.
+    pub fn with_prompt_format(mut self, format: crate::prompts::PromptFormat) -> Self {
+        self.prompt_format = format;
+        self
+    }
+
+    /// Provide the `task` tool without adding its guidance to the system prompt.
+    pub fn without_prompt(mut self) -> Self {
+        self.include_prompt = false;
+        self
+    }
+
+    /// Guidance for the `task` tool followed by the list of sub-agents.
+    pub fn system_prompt(&self) -> String {
+        format!("{}\n\n{}", TASK_SYSTEM_PROMPT, self.prompt_fragment())
+    }
+
     fn prompt_fragment(&self) -> String {
+        let toon =
+            self.prompt_format == crate::prompts::PromptFormat::Toon && cfg!(feature = "toon");
+        if toon && !self.descriptors.is_empty() {
+            let agents: Vec<_> = self
+                .descriptors
+                .iter()
+                .map(|agent| serde_json::json!({ "name": agent.name, "description": agent.description }))
+                .collect();
+            let listing = serde_json::json!({ "agents": agents });
+            if let Ok(table) = agents_core::toon::ToonEncoder::new().encode(&listing) {
+                return TASK_TOOL_DESCRIPTION.replace("{other_agents}", &table);
+            }
+        }
+
         let descriptions: Vec<String> = if self.descriptors.is_empty() {
             vec![String::from("- general-purpose: Default reasoning agent")]
         } else {
@@ -282,8 +349,9 @@ impl AgentMiddleware for SubAgentMiddleware {
     }
 
     async fn modify_model_request(&self, ctx: &mut MiddlewareContext<'_>) -> anyhow::Result<()> {
-        ctx.request.append_prompt(TASK_SYSTEM_PROMPT);
-        ctx.request.append_prompt(&self.prompt_fragment());
+        if self.include_prompt {
+            ctx.request.append_prompt(&self.system_prompt());
+        }
         Ok(())
     }
 }
@@ -415,6 +483,10 @@ pub struct DeepAgentPromptMiddleware {
     prompt_format: crate::prompts::PromptFormat,
     /// If set, this completely replaces the default Deep Agent system prompt
     override_system_prompt: Option<String>,
+    /// Localized prompt packs, resolved per thread from the state locale
+    locale: Option<crate::locale::LocaleConfig>,
+    /// If set, the whole system prompt is rendered from these sections
+    sections: Option<(crate::prompts::PromptSections, String)>,
 }
 
 impl DeepAgentPromptMiddleware {
@@ -423,6 +495,8 @@ impl DeepAgentPromptMiddleware {
             custom_instructions: custom_instructions.into(),
             prompt_format: crate::prompts::PromptFormat::Json,
             override_system_prompt: None,
+            locale: None,
+            sections: None,
         }
     }
 
@@ -438,6 +512,8 @@ impl DeepAgentPromptMiddleware {
             custom_instructions: custom_instructions.into(),
             prompt_format: format,
             override_system_prompt: None,
+            locale: None,
+            sections: None,
         }
     }
 
@@ -450,6 +526,63 @@ impl DeepAgentPromptMiddleware {
             custom_instructions: String::new(),
             prompt_format: crate::prompts::PromptFormat::Json,
             override_system_prompt: Some(system_prompt.into()),
+            locale: None,
+            sections: None,
+        }
+    }
+
+    /// Localize the prompt for the thread's locale.
+    ///
+    /// A pack's system prompt replaces the built-in Deep Agent prompt (but never a
+    /// custom override), and its response instruction is appended either way.
+    pub fn with_locale(mut self, locale: crate::locale::LocaleConfig) -> Self {
+        self.locale = Some(locale);
+        self
+    }
+
+    /// Render the whole system prompt from `sections`, with `sub_agents` as the
+    /// built-in sub-agent section.
+    ///
+    /// The base, planning, filesystem and sub-agent middlewares must then be used
+    /// without their prompts. An override or localized prompt takes the place of
+    /// the custom instructions and replaces the built-in tool protocol.
+    pub fn with_sections(
+        mut self,
+        sections: crate::prompts::PromptSections,
+        sub_agents: impl Into<String>,
+    ) -> Self {
+        self.sections = Some((sections, sub_agents.into()));
+        self
+    }
+
+    fn render_sections(
+        &self,
+        sections: &crate::prompts::PromptSections,
+        sub_agents: &str,
+        instructions: &str,
+        replaced: Option<String>,
+        request: &mut ModelRequest,
+    ) {
+        use crate::prompts::{deep_agent_tool_protocol, PromptSection};
+
+        for section in sections.sections() {
+            if let Some(text) = sections.replacement(section) {
+                request.append_prompt(text);
+                continue;
+            }
+            match section {
+                PromptSection::Base => request.append_prompt(BASE_AGENT_PROMPT),
+                PromptSection::CustomInstructions => {
+                    request.append_prompt(replaced.as_deref().unwrap_or(instructions))
+                }
+                PromptSection::ToolProtocol if replaced.is_none() => {
+                    request.append_prompt(deep_agent_tool_protocol(self.prompt_format))
+                }
+                PromptSection::ToolProtocol => {}
+                PromptSection::Planning => request.append_prompt(WRITE_TODOS_SYSTEM_PROMPT),
+                PromptSection::Filesystem => request.append_prompt(FILESYSTEM_SYSTEM_PROMPT),
+                PromptSection::SubAgents => request.append_prompt(sub_agents),
+            }
         }
     }
 }
@@ -461,15 +594,41 @@ impl AgentMiddleware for DeepAgentPromptMiddleware {
     }
 
     async fn modify_model_request(&self, ctx: &mut MiddlewareContext<'_>) -> anyhow::Result<()> {
-        let prompt = if let Some(ref override_prompt) = self.override_system_prompt {
-            // Use the custom system prompt directly, bypassing the Deep Agent prompt
-            override_prompt.clone()
+        // Run options may replace the agent's instructions
+        let run_instructions = crate::agent::run_options::current_instructions();
+        let instructions = run_instructions
+            .as_deref()
+            .unwrap_or(&self.custom_instructions);
+        let locale = self.locale.as_ref().map(|config| {
+            let state = ctx.state.read().map(|s| s.locale.clone()).unwrap_or(None);
+            let locale = state.unwrap_or_else(|| config.default_locale.clone());
+            (config, locale)
+        });
+
+        // An override or localized prompt replaces the whole Deep Agent prompt
+        let replaced = self.override_system_prompt.clone().or_else(|| {
+            locale
+                .as_ref()
+                .and_then(|(config, locale)| config.system_prompt(locale, instructions))
+        });
+
+        if let Some((sections, sub_agents)) = &self.sections {
+            self.render_sections(sections, sub_agents, instructions, replaced, ctx.request);
         } else {
-            // Use the formatted Deep Agent prompt based on prompt_format
-            use crate::prompts::get_deep_agent_system_prompt_formatted;
-            get_deep_agent_system_prompt_formatted(&self.custom_instructions, self.prompt_format)
-        };
-        ctx.request.append_prompt(&prompt);
+            let prompt = replaced.unwrap_or_else(|| {
+                // Use the formatted Deep Agent prompt based on prompt_format
+                use crate::prompts::get_deep_agent_system_prompt_formatted;
+                get_deep_agent_system_prompt_formatted(instructions, self.prompt_format)
+            });
+            ctx.request.append_prompt(&prompt);
+        }
+
+        if let Some(instruction) = locale
+            .as_ref()
+            .and_then(|(config, locale)| config.response_instruction(locale))
+        {
+            ctx.request.append_prompt(instruction);
+        }
         Ok(())
     }
 }
@@ -521,6 +680,8 @@ impl AgentMiddleware for AnthropicPromptCachingMiddleware {
                     cache_control: Some(CacheControl {
                         cache_type: "ephemeral".to_string(),
                     }),
+                    traceparent: None,
+                    correlation_id: None,
                 }),
             };
 
@@ -565,19 +726,12 @@ impl TaskRouterTool {
 
     fn emit_event(&self, event: agents_core::events::AgentEvent) {
         if let Some(dispatcher) = &self.event_dispatcher {
-            let dispatcher_clone = dispatcher.clone();
-            tokio::spawn(async move {
-                dispatcher_clone.dispatch(event).await;
-            });
+            dispatcher.emit(event);
         }
     }
 
     fn create_event_metadata(&self) -> agents_core::events::EventMetadata {
-        agents_core::events::EventMetadata::new(
-            "default".to_string(),
-            uuid::Uuid::new_v4().to_string(),
-            None,
-        )
+        agents_core::events::EventMetadata::for_current_run("default")
     }
 
     fn get_delegation_depth(&self) -> u32 {
@@ -679,7 +833,13 @@ impl Tool for TaskRouterTool {
             let user_message = AgentMessage {
                 role: MessageRole::User,
                 content: MessageContent::Text(args.instruction.clone()),
-                metadata: None,
+                // Remote sub-agents join the trace and correlation id through the
+                // metadata; in-process ones also pick them up from the enclosing scope
+                metadata: Some(MessageMetadata {
+                    traceparent: ctx.trace.as_ref().map(|trace| trace.traceparent()),
+                    correlation_id: agents_core::correlation::current_correlation_id(),
+                    ..Default::default()
+                }),
             };
 
             let response = agent
@@ -707,6 +867,7 @@ impl Tool for TaskRouterTool {
                         json_str
                     }
                 }
+                MessageContent::Image { .. } => response.content.to_text(),
             };
 
             // Emit: SubAgentCompleted event
@@ -730,14 +891,15 @@ impl Tool for TaskRouterTool {
             // Decrement delegation depth
             self.decrement_delegation_depth();
 
-            // Return sub-agent response as text content, not as a separate tool message
-            // This will be incorporated into the LLM's next response naturally
-            let result_text = match response.content {
-                MessageContent::Text(text) => text,
-                MessageContent::Json(json) => json.to_string(),
-            };
-
-            return Ok(ToolResult::text(&ctx, result_text));
+            // Return sub-agent response as content, not as a separate tool message.
+            // Structured responses stay JSON so the parent can re-encode them (TOON).
+            return Ok(match response.content {
+                MessageContent::Text(text) => ToolResult::text(&ctx, text),
+                MessageContent::Json(json) => ToolResult::json(&ctx, json),
+                MessageContent::Image { text, images } => {
+                    ToolResult::Message(ctx.image_response(text, images))
+                }
+            });
         }
 
         tracing::error!(
@@ -876,11 +1038,7 @@ mod tests {
     #[async_trait]
     impl AgentHandle for StubAgent {
         async fn describe(&self) -> AgentDescriptor {
-            AgentDescriptor {
-                name: "stub".into(),
-                version: "0.0.1".into(),
-                description: None,
-            }
+            AgentDescriptor::new("stub", "0.0.1")
         }
 
         async fn handle_message(
@@ -950,6 +1108,60 @@ mod tests {
         assert!(tool_names.contains(&"task".to_string()));
     }
 
+    /// Answers with the `traceparent` it was called with.
+    struct TraceEchoAgent;
+
+    #[async_trait]
+    impl AgentHandle for TraceEchoAgent {
+        async fn describe(&self) -> AgentDescriptor {
+            AgentDescriptor::new("trace-echo", "0.0.1")
+        }
+
+        async fn handle_message(
+            &self,
+            input: AgentMessage,
+            _state: Arc<AgentStateSnapshot>,
+        ) -> anyhow::Result<AgentMessage> {
+            let traceparent = input.metadata.and_then(|m| m.traceparent);
+            Ok(AgentMessage {
+                role: MessageRole::Agent,
+                content: MessageContent::Text(traceparent.unwrap_or_default()),
+                metadata: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn task_router_forwards_trace_context_to_subagent() {
+        let registry = Arc::new(SubAgentRegistry::new(vec![SubAgentRegistration {
+            descriptor: SubAgentDescriptor {
+                name: "trace-echo".into(),
+                description: "Echoes the trace".into(),
+            },
+            agent: Arc::new(TraceEchoAgent),
+        }]));
+        let task_tool = TaskRouterTool::new(registry, None);
+        let trace = agents_core::trace_context::TraceContext::new_root();
+        let ctx =
+            ToolContext::new(Arc::new(AgentStateSnapshot::default())).with_trace(trace.clone());
+
+        let response = task_tool
+            .execute(
+                json!({ "agent": "trace-echo", "instruction": "hello" }),
+                ctx,
+            )
+            .await
+            .unwrap();
+
+        match response {
+            ToolResult::Message(msg) => match msg.content {
+                MessageContent::Text(text) => assert_eq!(text, trace.traceparent()),
+                other => panic!("expected text, got {other:?}"),
+            },
+            _ => panic!("expected message"),
+        }
+    }
+
     #[tokio::test]
     async fn task_router_invokes_registered_subagent() {
         let registry = Arc::new(SubAgentRegistry::new(vec![SubAgentRegistration {
@@ -1004,6 +1216,115 @@ mod tests {
             .contains("danger-tool: Requires security review"));
     }
 
+    #[tokio::test]
+    async fn deep_agent_prompt_follows_thread_locale() {
+        use crate::locale::LocaleConfig;
+
+        let middleware = DeepAgentPromptMiddleware::new("Help with car service")
+            .with_locale(LocaleConfig::new("en"));
+
+        let state = Arc::new(RwLock::new(AgentStateSnapshot::default()));
+        let mut request = ModelRequest::new("", vec![]);
+        let mut ctx = MiddlewareContext::with_request(&mut request, state);
+        middleware.modify_model_request(&mut ctx).await.unwrap();
+        assert!(ctx
+            .request
+            .system_prompt
+            .contains("TOOL USAGE IS MANDATORY"));
+        assert!(ctx
+            .request
+            .system_prompt
+            .ends_with("Always respond in English."));
+
+        let state = Arc::new(RwLock::new(
+            AgentStateSnapshot::default().with_locale("ar-AE"),
+        ));
+        let mut request = ModelRequest::new("", vec![]);
+        let mut ctx = MiddlewareContext::with_request(&mut request, state);
+        middleware.modify_model_request(&mut ctx).await.unwrap();
+        assert!(ctx.request.system_prompt.contains("Help with car service"));
+        assert!(ctx.request.system_prompt.contains("استخدام الأدوات إلزامي"));
+        assert!(!ctx
+            .request
+            .system_prompt
+            .contains("TOOL USAGE IS MANDATORY"));
+    }
+
+    #[tokio::test]
+    async fn custom_prompt_is_kept_but_response_language_is_localized() {
+        use crate::locale::LocaleConfig;
+
+        let middleware = DeepAgentPromptMiddleware::with_override("Custom prompt")
+            .with_locale(LocaleConfig::new("ar"));
+        let state = Arc::new(RwLock::new(AgentStateSnapshot::default()));
+        let mut request = ModelRequest::new("", vec![]);
+        let mut ctx = MiddlewareContext::with_request(&mut request, state);
+        middleware.modify_model_request(&mut ctx).await.unwrap();
+        assert!(ctx.request.system_prompt.contains("Custom prompt"));
+        assert!(ctx.request.system_prompt.contains("باللغة العربية"));
+    }
+
+    #[tokio::test]
+    async fn prompt_sections_reorder_drop_and_replace_sections() {
+        use crate::prompts::{PromptSection, PromptSections};
+
+        let state = Arc::new(RwLock::new(AgentStateSnapshot::default()));
+        let subagents = SubAgentMiddleware::new(vec![]);
+        let render = |middlewares: Vec<Arc<dyn AgentMiddleware>>| {
+            let state = state.clone();
+            async move {
+                let mut request = ModelRequest::new("", vec![]);
+                let mut ctx = MiddlewareContext::with_request(&mut request, state);
+                for middleware in middlewares {
+                    middleware.modify_model_request(&mut ctx).await.unwrap();
+                }
+                request.system_prompt
+            }
+        };
+
+        // The default sections render the same prompt as the middleware stack
+        let stacked = render(vec![
+            Arc::new(BaseSystemPromptMiddleware),
+            Arc::new(DeepAgentPromptMiddleware::new("Help with billing")),
+            Arc::new(PlanningMiddleware::new(state.clone())),
+            Arc::new(FilesystemMiddleware::new(state.clone())),
+            Arc::new(SubAgentMiddleware::new(vec![])),
+        ])
+        .await;
+        let sectioned = render(vec![
+            Arc::new(
+                DeepAgentPromptMiddleware::new("Help with billing")
+                    .with_sections(PromptSections::new(), subagents.system_prompt()),
+            ),
+            Arc::new(PlanningMiddleware::new(state.clone()).without_prompt()),
+            Arc::new(FilesystemMiddleware::new(state.clone()).without_prompt()),
+            Arc::new(SubAgentMiddleware::new(vec![]).without_prompt()),
+        ])
+        .await;
+        assert_eq!(sectioned, stacked);
+
+        let sections = PromptSections::ordered([
+            PromptSection::ToolProtocol,
+            PromptSection::Planning,
+            PromptSection::CustomInstructions,
+        ])
+        .replace(
+            PromptSection::ToolProtocol,
+            "Call tools, don't describe them.",
+        );
+        let prompt = render(vec![Arc::new(
+            DeepAgentPromptMiddleware::new("Help with billing")
+                .with_sections(sections, subagents.system_prompt()),
+        )])
+        .await;
+        assert!(prompt.starts_with("\n\nCall tools, don't describe them.\n\n## `write_todos`"));
+        assert!(prompt.ends_with("Help with billing"));
+        assert!(!prompt.contains("TOOL USAGE IS MANDATORY"));
+        assert!(!prompt.contains(BASE_AGENT_PROMPT));
+        assert!(!prompt.contains(FILESYSTEM_SYSTEM_PROMPT));
+        assert!(!prompt.contains(TASK_SYSTEM_PROMPT));
+    }
+
     #[tokio::test]
     async fn anthropic_prompt_caching_moves_system_prompt_to_messages() {
         let middleware = AnthropicPromptCachingMiddleware::new("5m", "ignore");
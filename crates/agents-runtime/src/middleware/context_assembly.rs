@@ -0,0 +1,584 @@
+//! Context assembly per model family
+//!
+//! Providers accept very different amounts of context: 200k tokens for Claude,
+//! 128k for GPT-4o and GPT-4o-mini, often 8k for a local model. Instead of one
+//! global history limit, `ContextAssemblyMiddleware` looks up the model each
+//! request goes to in a catalog and fits the request to that entry's
+//! [`ContextStrategy`]: when the request would not leave the reserved completion
+//! tokens free, older messages are dropped section by section in the strategy's
+//! order, and either left out with a note or replaced by a model summary.
+//!
+//! The most recent messages and system messages are never dropped, and only the
+//! outgoing request changes: the stored history keeps every message.
+
+use std::sync::Arc;
+
+use crate::middleware::token_tracking::TokenTrackingMiddleware;
+use agents_core::llm::{
+    with_usage_purpose, ChunkStream, LanguageModel, LlmRequest, LlmResponse, UsagePurpose,
+};
+use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+use async_trait::async_trait;
+
+/// Prompt tokens assumed per character when estimating the size of a request
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Longest summary, in characters, that replaces dropped messages
+const SUMMARY_MAX_CHARS: usize = 2_000;
+
+const SUMMARY_SYSTEM_PROMPT: &str = "You condense the earlier part of a conversation \
+between a user, an AI agent and the agent's tools. Summarize it, preserving the user's \
+requests, decisions made, and concrete facts, numbers, identifiers, URLs and error \
+messages the agent may still need. Reply with the summary only.";
+
+/// Part of the history that gives way when a request does not fit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContextSection {
+    /// Results of earlier tool calls
+    ToolResults,
+    /// Earlier replies and tool calls of the agent
+    AgentTurns,
+    /// Earlier user messages
+    UserTurns,
+}
+
+impl ContextSection {
+    fn contains(self, message: &AgentMessage) -> bool {
+        matches!(
+            (self, &message.role),
+            (Self::ToolResults, MessageRole::Tool)
+                | (Self::AgentTurns, MessageRole::Agent)
+                | (Self::UserTurns, MessageRole::User)
+        )
+    }
+}
+
+/// What takes the place of dropped messages.
+#[derive(Clone, Default)]
+pub enum ContextReduction {
+    /// A note saying how many messages were left out
+    #[default]
+    Truncate,
+    /// A summary of the dropped messages written by the given model.
+    ///
+    /// Falls back to the note if the model call fails.
+    Summarize(Arc<dyn LanguageModel>),
+}
+
+impl std::fmt::Debug for ContextReduction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Truncate => write!(f, "Truncate"),
+            Self::Summarize(_) => write!(f, "Summarize(..)"),
+        }
+    }
+}
+
+/// How requests to one model family are fitted to its context window.
+#[derive(Debug, Clone)]
+pub struct ContextStrategy {
+    /// Prompt and completion tokens the model accepts
+    pub context_window: u32,
+    /// Completion tokens kept free (default: 4096)
+    pub reserved_output_tokens: u32,
+    /// Number of most recent messages never dropped (default: 4)
+    pub keep_recent: usize,
+    /// Sections dropped first to last, oldest message first within a section
+    /// (default: tool results, agent turns, user turns)
+    pub drop_order: Vec<ContextSection>,
+    pub reduction: ContextReduction,
+}
+
+impl ContextStrategy {
+    pub fn new(context_window: u32) -> Self {
+        Self {
+            context_window,
+            reserved_output_tokens: 4_096,
+            keep_recent: 4,
+            drop_order: vec![
+                ContextSection::ToolResults,
+                ContextSection::AgentTurns,
+                ContextSection::UserTurns,
+            ],
+            reduction: ContextReduction::Truncate,
+        }
+    }
+
+    pub fn with_reserved_output_tokens(mut self, tokens: u32) -> Self {
+        self.reserved_output_tokens = tokens;
+        self
+    }
+
+    pub fn with_keep_recent(mut self, messages: usize) -> Self {
+        self.keep_recent = messages;
+        self
+    }
+
+    /// Drop only these sections, in this order.
+    pub fn with_drop_order(mut self, sections: impl IntoIterator<Item = ContextSection>) -> Self {
+        self.drop_order = sections.into_iter().collect();
+        self
+    }
+
+    pub fn with_reduction(mut self, reduction: ContextReduction) -> Self {
+        self.reduction = reduction;
+        self
+    }
+
+    /// Prompt tokens a request may use
+    fn budget(&self) -> usize {
+        self.context_window
+            .saturating_sub(self.reserved_output_tokens) as usize
+    }
+}
+
+/// Catalog of context strategies by model name, for [`ContextAssemblyMiddleware`].
+///
+/// Entries match model names by prefix, ignoring case and any provider path
+/// (`openai/gpt-4o-mini` matches `gpt-4o`); the longest matching prefix wins.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use agents_runtime::middleware::context_assembly::*;
+///
+/// let agent = ConfigurableAgentBuilder::new("You are a research assistant")
+///     .with_model(model)
+///     .with_context_assembly(
+///         ContextAssemblyConfig::builtin()
+///             .with_model(
+///                 "claude",
+///                 ContextStrategy::new(200_000)
+///                     .with_reduction(ContextReduction::Summarize(small_model)),
+///             )
+///             .with_model("llama3.1", ContextStrategy::new(16_384).with_keep_recent(2))
+///             .with_default(ContextStrategy::new(32_000)),
+///     )
+///     .build()?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ContextAssemblyConfig {
+    /// Model name prefixes and their strategies
+    pub catalog: Vec<(String, ContextStrategy)>,
+    /// Strategy of models no entry matches; their requests are left alone when unset
+    pub default: Option<ContextStrategy>,
+}
+
+impl ContextAssemblyConfig {
+    /// An empty catalog.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Entries for the common hosted model families and for small local models.
+    pub fn builtin() -> Self {
+        let local = || {
+            ContextStrategy::new(8_192)
+                .with_reserved_output_tokens(1_024)
+                .with_keep_recent(2)
+        };
+        Self::new()
+            .with_model("claude", ContextStrategy::new(200_000))
+            .with_model("gpt-4o", ContextStrategy::new(128_000))
+            .with_model("gpt-4.1", ContextStrategy::new(1_000_000))
+            .with_model("o1", ContextStrategy::new(200_000))
+            .with_model("o3", ContextStrategy::new(200_000))
+            .with_model("o4", ContextStrategy::new(200_000))
+            .with_model("gemini", ContextStrategy::new(1_000_000))
+            .with_model("llama", local())
+            .with_model("mistral", local())
+            .with_model("qwen", local())
+            .with_model("gemma", local())
+            .with_model("phi", local())
+    }
+
+    /// Use `strategy` for models whose name starts with `prefix`, replacing any
+    /// entry for the same prefix.
+    pub fn with_model(mut self, prefix: impl Into<String>, strategy: ContextStrategy) -> Self {
+        let prefix = prefix.into().to_lowercase();
+        self.catalog.retain(|(existing, _)| *existing != prefix);
+        self.catalog.push((prefix, strategy));
+        self
+    }
+
+    pub fn with_default(mut self, strategy: ContextStrategy) -> Self {
+        self.default = Some(strategy);
+        self
+    }
+
+    /// Strategy of the model named `model`, or the default.
+    pub fn strategy_for(&self, model: Option<&str>) -> Option<&ContextStrategy> {
+        let name = model.map(|model| {
+            let name = model.rsplit('/').next().unwrap_or(model);
+            name.to_lowercase()
+        });
+        name.and_then(|name| {
+            self.catalog
+                .iter()
+                .filter(|(prefix, _)| name.starts_with(prefix.as_str()))
+                .max_by_key(|(prefix, _)| prefix.len())
+                .map(|(_, strategy)| strategy)
+        })
+        .or(self.default.as_ref())
+    }
+
+    /// Route the summarizers' requests through `tracker` so their cost is attributed.
+    pub(crate) fn tracked_by(mut self, tracker: &TokenTrackingMiddleware) -> Self {
+        let strategies = self
+            .catalog
+            .iter_mut()
+            .map(|(_, strategy)| strategy)
+            .chain(self.default.as_mut());
+        for strategy in strategies {
+            if let ContextReduction::Summarize(model) = &strategy.reduction {
+                strategy.reduction =
+                    ContextReduction::Summarize(Arc::new(tracker.share(model.clone())));
+            }
+        }
+        self
+    }
+}
+
+fn estimate_tokens(chars: usize) -> usize {
+    chars.div_ceil(CHARS_PER_TOKEN)
+}
+
+fn message_tokens(message: &AgentMessage) -> usize {
+    estimate_tokens(message.content.text().len())
+}
+
+/// Language model wrapper fitting requests to the context window of the model they
+/// go to.
+pub struct ContextAssemblyMiddleware {
+    inner_model: Arc<dyn LanguageModel>,
+    config: ContextAssemblyConfig,
+}
+
+impl ContextAssemblyMiddleware {
+    pub fn new(config: ContextAssemblyConfig, inner_model: Arc<dyn LanguageModel>) -> Self {
+        Self {
+            inner_model,
+            config,
+        }
+    }
+
+    async fn assemble(&self, mut request: LlmRequest) -> LlmRequest {
+        let model = self.inner_model.model_name();
+        let Some(strategy) = self.config.strategy_for(model.as_deref()) else {
+            return request;
+        };
+        let model = model.as_deref().unwrap_or("the model");
+
+        let budget = strategy.budget();
+        let tools_chars: usize = request
+            .tools
+            .iter()
+            .map(|tool| serde_json::to_string(tool).map_or(0, |json| json.len()))
+            .sum();
+        let sizes: Vec<usize> = request.messages.iter().map(message_tokens).collect();
+        let mut total = estimate_tokens(request.system_prompt.len() + tools_chars)
+            + sizes.iter().sum::<usize>();
+        if total <= budget {
+            return request;
+        }
+        // Room for what replaces the dropped messages
+        if matches!(strategy.reduction, ContextReduction::Summarize(_)) {
+            total += estimate_tokens(SUMMARY_MAX_CHARS);
+        }
+
+        let droppable = request.messages.len().saturating_sub(strategy.keep_recent);
+        let mut dropped = vec![false; request.messages.len()];
+        'sections: for section in &strategy.drop_order {
+            for index in 0..droppable {
+                if dropped[index] || !section.contains(&request.messages[index]) {
+                    continue;
+                }
+                dropped[index] = true;
+                total -= sizes[index];
+                if total <= budget {
+                    break 'sections;
+                }
+            }
+        }
+        if total > budget {
+            tracing::warn!(
+                model,
+                estimated_tokens = total,
+                budget,
+                "Request exceeds the context window even after dropping older messages"
+            );
+        }
+        let Some(first) = dropped.iter().position(|&d| d) else {
+            return request;
+        };
+
+        let mut kept = Vec::with_capacity(request.messages.len());
+        let mut removed = Vec::new();
+        for (message, dropped) in request.messages.into_iter().zip(dropped) {
+            if dropped {
+                removed.push(message);
+            } else {
+                kept.push(message);
+            }
+        }
+        tracing::info!(
+            model,
+            dropped = removed.len(),
+            "✂️ Older messages dropped to fit the context window"
+        );
+        let note = self.replacement(strategy, model, &removed).await;
+        kept.insert(
+            first,
+            AgentMessage {
+                role: MessageRole::System,
+                content: MessageContent::Text(note),
+                metadata: None,
+            },
+        );
+        request.messages = kept;
+        request
+    }
+
+    /// Text taking the place of the `removed` messages.
+    async fn replacement(
+        &self,
+        strategy: &ContextStrategy,
+        model: &str,
+        removed: &[AgentMessage],
+    ) -> String {
+        let omitted = format!(
+            "[{} earlier messages were left out to fit the context window of {}.]",
+            removed.len(),
+            model
+        );
+        let ContextReduction::Summarize(summarizer) = &strategy.reduction else {
+            return omitted;
+        };
+
+        let transcript = removed
+            .iter()
+            .map(|message| {
+                let speaker = match message.role {
+                    MessageRole::User => "User",
+                    MessageRole::Agent => "Agent",
+                    MessageRole::Tool => "Tool",
+                    MessageRole::System => "System",
+                };
+                format!("{}: {}", speaker, message.content.text())
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let request = LlmRequest::new(
+            SUMMARY_SYSTEM_PROMPT,
+            vec![AgentMessage {
+                role: MessageRole::User,
+                content: MessageContent::Text(transcript),
+                metadata: None,
+            }],
+        );
+        match with_usage_purpose(UsagePurpose::Summarization, summarizer.generate(request)).await {
+            Ok(response) => {
+                let summary: String = response
+                    .message
+                    .content
+                    .text()
+                    .chars()
+                    .take(SUMMARY_MAX_CHARS)
+                    .collect();
+                format!(
+                    "Summary of {} earlier messages:\n{}",
+                    removed.len(),
+                    summary
+                )
+            }
+            Err(e) => {
+                tracing::warn!("Failed to summarize dropped messages: {}", e);
+                omitted
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl LanguageModel for ContextAssemblyMiddleware {
+    async fn generate(&self, request: LlmRequest) -> anyhow::Result<LlmResponse> {
+        let request = self.assemble(request).await;
+        self.inner_model.generate(request).await
+    }
+
+    async fn generate_stream(&self, request: LlmRequest) -> anyhow::Result<ChunkStream> {
+        let request = self.assemble(request).await;
+        self.inner_model.generate_stream(request).await
+    }
+
+    fn model_name(&self) -> Option<String> {
+        self.inner_model.model_name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Records the requests it receives and answers with a fixed text.
+    struct NamedModel {
+        name: &'static str,
+        requests: Mutex<Vec<LlmRequest>>,
+    }
+
+    impl NamedModel {
+        fn new(name: &'static str) -> Arc<Self> {
+            Arc::new(Self {
+                name,
+                requests: Mutex::new(Vec::new()),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl LanguageModel for NamedModel {
+        async fn generate(&self, request: LlmRequest) -> anyhow::Result<LlmResponse> {
+            self.requests.lock().unwrap().push(request);
+            Ok(LlmResponse::new(AgentMessage {
+                role: MessageRole::Agent,
+                content: MessageContent::Text("the user asked for a report".into()),
+                metadata: None,
+            }))
+        }
+
+        fn model_name(&self) -> Option<String> {
+            Some(self.name.to_string())
+        }
+    }
+
+    fn message(role: MessageRole, chars: usize) -> AgentMessage {
+        AgentMessage {
+            role,
+            content: MessageContent::Text("x".repeat(chars)),
+            metadata: None,
+        }
+    }
+
+    fn history() -> Vec<AgentMessage> {
+        vec![
+            message(MessageRole::User, 400),
+            message(MessageRole::Agent, 400),
+            message(MessageRole::Tool, 4_000),
+            message(MessageRole::Agent, 400),
+            message(MessageRole::Tool, 4_000),
+            message(MessageRole::User, 400),
+        ]
+    }
+
+    #[test]
+    fn catalog_matches_longest_prefix_ignoring_provider_path() {
+        let config = ContextAssemblyConfig::builtin()
+            .with_model("gpt-4o-mini", ContextStrategy::new(64_000));
+
+        assert_eq!(
+            window_of(&config, "claude-sonnet-4-20250514"),
+            Some(200_000)
+        );
+        assert_eq!(window_of(&config, "openai/GPT-4o-mini"), Some(64_000));
+        assert_eq!(window_of(&config, "gpt-4o-2024-08-06"), Some(128_000));
+        assert_eq!(window_of(&config, "llama3.1:8b"), Some(8_192));
+        assert_eq!(window_of(&config, "unknown-model"), None);
+        assert!(config.strategy_for(None).is_none());
+
+        let config = config.with_default(ContextStrategy::new(32_000));
+        assert_eq!(window_of(&config, "unknown-model"), Some(32_000));
+    }
+
+    fn window_of(config: &ContextAssemblyConfig, model: &str) -> Option<u32> {
+        config
+            .strategy_for(Some(model))
+            .map(|strategy| strategy.context_window)
+    }
+
+    #[tokio::test]
+    async fn small_models_drop_older_sections_in_order() {
+        let config = ContextAssemblyConfig::new()
+            .with_model(
+                "llama",
+                ContextStrategy::new(2_000)
+                    .with_reserved_output_tokens(500)
+                    .with_keep_recent(2),
+            )
+            .with_model("claude", ContextStrategy::new(200_000));
+
+        // Plenty of room: the request is untouched
+        let large = NamedModel::new("claude-sonnet-4");
+        let middleware = ContextAssemblyMiddleware::new(config.clone(), large.clone());
+        middleware
+            .generate(LlmRequest::new("system", history()))
+            .await
+            .unwrap();
+        assert_eq!(large.requests.lock().unwrap()[0].messages, history());
+
+        // Dropping the older tool result is enough; the recent one is kept
+        let small = NamedModel::new("llama3.1:8b");
+        let middleware = ContextAssemblyMiddleware::new(config, small.clone());
+        middleware
+            .generate(LlmRequest::new("system", history()))
+            .await
+            .unwrap();
+        let messages = small.requests.lock().unwrap()[0].messages.clone();
+        let roles: Vec<_> = messages.iter().map(|m| m.role.clone()).collect();
+        assert_eq!(
+            roles,
+            [
+                MessageRole::User,
+                MessageRole::Agent,
+                MessageRole::System,
+                MessageRole::Agent,
+                MessageRole::Tool,
+                MessageRole::User,
+            ]
+        );
+        assert_eq!(
+            messages[2].content.as_text(),
+            Some("[1 earlier messages were left out to fit the context window of llama3.1:8b.]")
+        );
+    }
+
+    #[tokio::test]
+    async fn summarize_replaces_dropped_messages_with_a_summary() {
+        let summarizer = NamedModel::new("small");
+        let config = ContextAssemblyConfig::new().with_default(
+            ContextStrategy::new(900)
+                .with_reserved_output_tokens(0)
+                .with_keep_recent(1)
+                .with_drop_order([ContextSection::ToolResults, ContextSection::UserTurns])
+                .with_reduction(ContextReduction::Summarize(summarizer.clone())),
+        );
+        let model = NamedModel::new("custom");
+        let middleware = ContextAssemblyMiddleware::new(config, model.clone());
+        middleware
+            .generate(LlmRequest::new("system", history()))
+            .await
+            .unwrap();
+
+        let messages = model.requests.lock().unwrap()[0].messages.clone();
+        // Agent turns are not in the drop order
+        let roles: Vec<_> = messages.iter().map(|m| m.role.clone()).collect();
+        assert_eq!(
+            roles,
+            [
+                MessageRole::System,
+                MessageRole::Agent,
+                MessageRole::Agent,
+                MessageRole::User,
+            ]
+        );
+        assert_eq!(
+            messages[0].content.as_text(),
+            Some("Summary of 3 earlier messages:\nthe user asked for a report")
+        );
+        let summarized = &summarizer.requests.lock().unwrap()[0];
+        assert!(summarized.messages[0]
+            .content
+            .text()
+            .starts_with("User: xxxx"));
+    }
+}
@@ -0,0 +1,258 @@
+//! Guardrails around model calls
+//!
+//! `GuardrailMiddleware` wraps a language model and runs the configured
+//! [`Guardrail`]s on the latest user message before the call and on the text of the
+//! response after it. Masked input reaches the model in its masked form; blocked input
+//! never reaches the model and the guardrail's message becomes the response. A
+//! guardrail that fails to answer fails the call, so content is never let through
+//! unchecked.
+//!
+//! Streaming calls are answered in one chunk so the whole response can be checked
+//! before any of it reaches the user.
+
+use agents_core::events::{AgentEvent, EventDispatcher, EventMetadata, GuardrailIntervenedEvent};
+use agents_core::guardrail::{Guardrail, GuardrailSource, GuardrailVerdict};
+use agents_core::llm::{LanguageModel, LlmRequest, LlmResponse};
+use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Checked user messages kept so later calls of a run don't check them again.
+const MAX_CACHED_INPUTS: usize = 1024;
+
+/// Sent in place of an earlier user message a guardrail blocked.
+const BLOCKED_PLACEHOLDER: &str = "[message removed by guardrail]";
+
+/// Result of running every guardrail on a text.
+#[derive(Debug, Clone, PartialEq)]
+enum Checked {
+    /// The text to use, masked where a guardrail asked for it
+    Pass(String),
+    Blocked(String),
+}
+
+/// Language model wrapper applying guardrails to input and output.
+pub struct GuardrailMiddleware {
+    inner_model: Arc<dyn LanguageModel>,
+    guardrails: Vec<Arc<dyn Guardrail>>,
+    event_dispatcher: Option<Arc<EventDispatcher>>,
+    checked_inputs: Mutex<HashMap<String, Checked>>,
+}
+
+impl GuardrailMiddleware {
+    pub fn new(
+        guardrails: Vec<Arc<dyn Guardrail>>,
+        inner_model: Arc<dyn LanguageModel>,
+        event_dispatcher: Option<Arc<EventDispatcher>>,
+    ) -> Self {
+        Self {
+            inner_model,
+            guardrails,
+            event_dispatcher,
+            checked_inputs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn check(&self, text: &str, source: GuardrailSource) -> anyhow::Result<Checked> {
+        let mut current = text.to_string();
+        for guardrail in &self.guardrails {
+            match guardrail.check(&current, source).await? {
+                GuardrailVerdict::Allow => {}
+                GuardrailVerdict::Mask { text, reasons } => {
+                    self.intervened(guardrail.id(), source, false, reasons);
+                    current = text;
+                }
+                GuardrailVerdict::Block { message, reasons } => {
+                    self.intervened(guardrail.id(), source, true, reasons);
+                    return Ok(Checked::Blocked(message));
+                }
+            }
+        }
+        Ok(Checked::Pass(current))
+    }
+
+    fn intervened(
+        &self,
+        guardrail: &str,
+        source: GuardrailSource,
+        blocked: bool,
+        reasons: Vec<String>,
+    ) {
+        tracing::warn!(
+            guardrail = %guardrail,
+            source = ?source,
+            blocked,
+            reasons = ?reasons,
+            "🛡️ Guardrail intervened"
+        );
+        if let Some(dispatcher) = &self.event_dispatcher {
+            dispatcher.emit(AgentEvent::GuardrailIntervened(GuardrailIntervenedEvent {
+                metadata: EventMetadata::for_current_run("default"),
+                guardrail: guardrail.to_string(),
+                source,
+                blocked,
+                reasons,
+            }));
+        }
+    }
+
+    /// Check the latest user message and apply earlier verdicts to older ones.
+    /// Returns the blocked message when the latest one is blocked.
+    async fn guard_input(&self, request: &mut LlmRequest) -> anyhow::Result<Option<String>> {
+        let Some(latest) = request
+            .messages
+            .iter()
+            .rposition(|m| m.role == MessageRole::User)
+        else {
+            return Ok(None);
+        };
+
+        for (index, message) in request.messages.iter_mut().enumerate() {
+            if message.role != MessageRole::User {
+                continue;
+            }
+            let MessageContent::Text(text) = &message.content else {
+                continue;
+            };
+            let cached = self
+                .checked_inputs
+                .lock()
+                .ok()
+                .and_then(|cache| cache.get(text).cloned());
+            let checked = match cached {
+                Some(checked) => checked,
+                // Older messages were checked when they were the latest
+                None if index != latest => continue,
+                None => {
+                    let checked = self.check(text, GuardrailSource::Input).await?;
+                    if let Ok(mut cache) = self.checked_inputs.lock() {
+                        if cache.len() >= MAX_CACHED_INPUTS {
+                            cache.clear();
+                        }
+                        cache.insert(text.clone(), checked.clone());
+                    }
+                    checked
+                }
+            };
+            match checked {
+                Checked::Pass(masked) => message.content = MessageContent::Text(masked),
+                Checked::Blocked(blocked) if index == latest => return Ok(Some(blocked)),
+                // A blocked message was answered by the guardrail; keep it out of the call
+                Checked::Blocked(_) => {
+                    message.content = MessageContent::Text(BLOCKED_PLACEHOLDER.to_string())
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[async_trait]
+impl LanguageModel for GuardrailMiddleware {
+    fn model_name(&self) -> Option<String> {
+        self.inner_model.model_name()
+    }
+
+    async fn generate(&self, mut request: LlmRequest) -> anyhow::Result<LlmResponse> {
+        if let Some(blocked) = self.guard_input(&mut request).await? {
+            return Ok(LlmResponse::new(AgentMessage {
+                role: MessageRole::Agent,
+                content: MessageContent::Text(blocked),
+                metadata: None,
+            }));
+        }
+
+        let mut response = self.inner_model.generate(request).await?;
+        // Tool calls come back as JSON and are not user-facing text
+        if let MessageContent::Text(text) = &response.message.content {
+            if !text.is_empty() {
+                let text = match self.check(text, GuardrailSource::Output).await? {
+                    Checked::Pass(text) | Checked::Blocked(text) => text,
+                };
+                response.message.content = MessageContent::Text(text);
+            }
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::EchoModel;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Masks "4111" and blocks "weapons".
+    #[derive(Default)]
+    struct WordGuardrail(AtomicUsize);
+
+    #[async_trait]
+    impl Guardrail for WordGuardrail {
+        fn id(&self) -> &str {
+            "words"
+        }
+
+        async fn check(
+            &self,
+            text: &str,
+            _source: GuardrailSource,
+        ) -> anyhow::Result<GuardrailVerdict> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            if text.contains("weapons") {
+                return Ok(GuardrailVerdict::Block {
+                    message: "I can't help with that.".into(),
+                    reasons: vec!["topic:weapons".into()],
+                });
+            }
+            if text.contains("4111") {
+                return Ok(GuardrailVerdict::Mask {
+                    text: text.replace("4111", "{CARD}"),
+                    reasons: vec!["pii:CREDIT_DEBIT_CARD_NUMBER".into()],
+                });
+            }
+            Ok(GuardrailVerdict::Allow)
+        }
+    }
+
+    fn user(text: &str) -> AgentMessage {
+        AgentMessage {
+            role: MessageRole::User,
+            content: MessageContent::Text(text.into()),
+            metadata: None,
+        }
+    }
+
+    fn text(response: &LlmResponse) -> &str {
+        match &response.message.content {
+            MessageContent::Text(text) => text,
+            _ => panic!("expected text"),
+        }
+    }
+
+    #[tokio::test]
+    async fn input_is_masked_or_blocked_before_the_model() {
+        let guardrail = Arc::new(WordGuardrail::default());
+        let model =
+            GuardrailMiddleware::new(vec![guardrail.clone()], Arc::new(EchoModel("")), None);
+
+        let masked = model
+            .generate(LlmRequest::new("", vec![user("card 4111")]))
+            .await
+            .unwrap();
+        assert_eq!(text(&masked), "card {CARD}");
+        // input and output
+        assert_eq!(guardrail.0.load(Ordering::SeqCst), 2);
+
+        let blocked = model
+            .generate(LlmRequest::new(
+                "",
+                vec![user("card 4111"), user("weapons")],
+            ))
+            .await
+            .unwrap();
+        assert_eq!(text(&blocked), "I can't help with that.");
+        // the older message came from the cache
+        assert_eq!(guardrail.0.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,427 @@
+//! Prompt compression middleware
+//!
+//! Long conversations are dominated by old tool outputs and verbose turns the model
+//! rarely needs verbatim. In the spirit of LLMLingua, this middleware shrinks older
+//! text messages to a target ratio before every model call, either by keeping the
+//! most informative sentences ([`ExtractiveCompressor`]) or by having a small, cheap
+//! model rewrite them ([`ModelCompressor`]). The most recent messages are never
+//! touched, and only the outgoing request is compressed: the stored history keeps
+//! the original text.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+use crate::middleware::{AgentMiddleware, MiddlewareContext};
+use agents_core::llm::{with_usage_purpose, LanguageModel, LlmRequest, UsagePurpose};
+use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+use async_trait::async_trait;
+
+/// Compressed texts remembered across turns; cleared when full.
+const CACHE_CAPACITY: usize = 512;
+
+/// Placed where the extractive compressor dropped segments.
+const OMISSION_MARKER: &str = " ... ";
+
+/// Shrinks a piece of text to roughly a fraction of its length.
+#[async_trait]
+pub trait PromptCompressor: Send + Sync {
+    /// Compress `text` to about `target_ratio` (0.0–1.0) of its length.
+    async fn compress(&self, text: &str, target_ratio: f32) -> anyhow::Result<String>;
+}
+
+/// Keeps the sentences and lines that carry the most information.
+///
+/// Segments are scored by how rare their words are within the text, with a bonus
+/// for numbers, identifiers and paths, then the best ones are kept in their
+/// original order. Omitted runs are marked with `...`. No model calls are made.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExtractiveCompressor;
+
+#[async_trait]
+impl PromptCompressor for ExtractiveCompressor {
+    async fn compress(&self, text: &str, target_ratio: f32) -> anyhow::Result<String> {
+        Ok(extract(text, target_ratio))
+    }
+}
+
+/// Asks a (preferably small and cheap) model to rewrite the text more concisely.
+///
+/// Requests are reported as summarization usage by token tracking.
+pub struct ModelCompressor {
+    model: Arc<dyn LanguageModel>,
+}
+
+impl ModelCompressor {
+    pub fn new(model: Arc<dyn LanguageModel>) -> Self {
+        Self { model }
+    }
+}
+
+#[async_trait]
+impl PromptCompressor for ModelCompressor {
+    async fn compress(&self, text: &str, target_ratio: f32) -> anyhow::Result<String> {
+        let system_prompt = format!(
+            "You compress context for another AI model. Rewrite the text below in about \
+             {}% of its length. Drop filler, repetition and formatting; keep facts, numbers, \
+             names, identifiers, URLs and error messages verbatim. Reply with the compressed \
+             text only.",
+            (target_ratio * 100.0).round()
+        );
+        let request = LlmRequest::new(
+            system_prompt,
+            vec![AgentMessage {
+                role: MessageRole::User,
+                content: MessageContent::Text(text.to_string()),
+                metadata: None,
+            }],
+        );
+        let response =
+            with_usage_purpose(UsagePurpose::Summarization, self.model.generate(request)).await?;
+        Ok(response.message.content.to_text())
+    }
+}
+
+/// Configuration for [`PromptCompressionMiddleware`].
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use agents_runtime::middleware::prompt_compression::PromptCompressionConfig;
+///
+/// let agent = ConfigurableAgentBuilder::new("You are a researcher")
+///     .with_model(model)
+///     .with_prompt_compression(
+///         PromptCompressionConfig::default()
+///             .with_target_ratio(0.4)
+///             .with_keep_recent(6),
+///     )
+///     .build()?;
+/// ```
+#[derive(Clone)]
+pub struct PromptCompressionConfig {
+    /// Approximate length of a compressed message relative to the original (default: 0.5).
+    pub target_ratio: f32,
+    /// Number of most recent messages sent uncompressed (default: 4).
+    pub keep_recent: usize,
+    /// Messages shorter than this many characters are left alone (default: 1000).
+    pub min_chars: usize,
+    pub compressor: Arc<dyn PromptCompressor>,
+}
+
+impl Default for PromptCompressionConfig {
+    fn default() -> Self {
+        Self::new(Arc::new(ExtractiveCompressor))
+    }
+}
+
+impl std::fmt::Debug for PromptCompressionConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PromptCompressionConfig")
+            .field("target_ratio", &self.target_ratio)
+            .field("keep_recent", &self.keep_recent)
+            .field("min_chars", &self.min_chars)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PromptCompressionConfig {
+    pub fn new(compressor: Arc<dyn PromptCompressor>) -> Self {
+        Self {
+            target_ratio: 0.5,
+            keep_recent: 4,
+            min_chars: 1_000,
+            compressor,
+        }
+    }
+
+    /// Compress with `model` instead of the extractive heuristic.
+    pub fn with_model(model: Arc<dyn LanguageModel>) -> Self {
+        Self::new(Arc::new(ModelCompressor::new(model)))
+    }
+
+    /// Target length ratio, clamped to `0.05..=1.0`.
+    pub fn with_target_ratio(mut self, ratio: f32) -> Self {
+        self.target_ratio = ratio.clamp(0.05, 1.0);
+        self
+    }
+
+    pub fn with_keep_recent(mut self, count: usize) -> Self {
+        self.keep_recent = count;
+        self
+    }
+
+    pub fn with_min_chars(mut self, min_chars: usize) -> Self {
+        self.min_chars = min_chars;
+        self
+    }
+}
+
+/// Compresses older text messages of every model request.
+///
+/// Results are cached by content, so each message is compressed once even though
+/// the request is rebuilt from the full history on every turn. System messages
+/// and JSON content are never modified, and a failed compression sends the
+/// original text.
+pub struct PromptCompressionMiddleware {
+    config: PromptCompressionConfig,
+    cache: Mutex<HashMap<u64, String>>,
+}
+
+impl PromptCompressionMiddleware {
+    pub fn new(config: PromptCompressionConfig) -> Self {
+        Self {
+            config,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn compress_cached(&self, text: &str) -> String {
+        let mut hasher = DefaultHasher::new();
+        text.hash(&mut hasher);
+        let key = hasher.finish();
+        if let Some(cached) = self.cache.lock().unwrap().get(&key) {
+            return cached.clone();
+        }
+
+        let compressed = match self
+            .config
+            .compressor
+            .compress(text, self.config.target_ratio)
+            .await
+        {
+            Ok(compressed) if compressed.len() < text.len() => compressed,
+            Ok(_) => text.to_string(),
+            Err(e) => {
+                tracing::warn!("🗜️ Prompt compression failed, sending original text: {}", e);
+                return text.to_string();
+            }
+        };
+
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= CACHE_CAPACITY {
+            cache.clear();
+        }
+        cache.insert(key, compressed.clone());
+        compressed
+    }
+}
+
+#[async_trait]
+impl AgentMiddleware for PromptCompressionMiddleware {
+    fn id(&self) -> &'static str {
+        "prompt-compression"
+    }
+
+    async fn modify_model_request(&self, ctx: &mut MiddlewareContext<'_>) -> anyhow::Result<()> {
+        let cutoff = ctx
+            .request
+            .messages
+            .len()
+            .saturating_sub(self.config.keep_recent);
+        let (mut before, mut after) = (0, 0);
+
+        for message in &mut ctx.request.messages[..cutoff] {
+            if message.role == MessageRole::System {
+                continue;
+            }
+            let MessageContent::Text(text) = &message.content else {
+                continue;
+            };
+            if text.chars().count() < self.config.min_chars {
+                continue;
+            }
+            let compressed = self.compress_cached(text).await;
+            before += text.len();
+            after += compressed.len();
+            message.content = MessageContent::Text(compressed);
+        }
+
+        if before > after {
+            tracing::debug!(before, after, "🗜️ Compressed prompt history");
+        }
+        Ok(())
+    }
+}
+
+/// A sentence or line of the text being compressed.
+struct Segment<'a> {
+    text: &'a str,
+    /// Whether the segment starts a new line in the original text
+    line_start: bool,
+}
+
+fn extract(text: &str, target_ratio: f32) -> String {
+    let segments = split_segments(text);
+    if segments.len() < 2 {
+        return text.to_string();
+    }
+
+    let words: Vec<HashSet<String>> = segments.iter().map(|s| segment_words(s.text)).collect();
+    let mut document_frequency: HashMap<&str, usize> = HashMap::new();
+    for word in words.iter().flatten() {
+        *document_frequency.entry(word).or_default() += 1;
+    }
+    let total = segments.len() as f32;
+    let scores: Vec<f32> = segments
+        .iter()
+        .zip(&words)
+        .enumerate()
+        .map(|(index, (segment, words))| {
+            if words.is_empty() {
+                return 0.0;
+            }
+            let rarity: f32 = words
+                .iter()
+                .map(|w| (total / document_frequency[w.as_str()] as f32).ln())
+                .sum::<f32>()
+                / (words.len() as f32).sqrt();
+            let facts = segment
+                .text
+                .split_whitespace()
+                .filter(|token| is_factual(token))
+                .count() as f32;
+            // The opening segment usually says what the rest is about
+            let opening = if index == 0 { 1.0 } else { 0.0 };
+            rarity + facts + opening
+        })
+        .collect();
+
+    let budget = (text.len() as f32 * target_ratio).ceil() as usize;
+    let mut ranked: Vec<usize> = (0..segments.len()).collect();
+    ranked.sort_by(|a, b| scores[*b].total_cmp(&scores[*a]));
+    let mut keep = vec![false; segments.len()];
+    let mut used = 0;
+    for index in ranked {
+        // Leave room for the separator or omission marker in front of the segment
+        let len = segments[index].text.len() + OMISSION_MARKER.len();
+        if used > 0 && used + len > budget {
+            continue;
+        }
+        keep[index] = true;
+        used += len;
+    }
+
+    let mut compressed = String::new();
+    let mut previous_kept = true;
+    for (segment, kept) in segments.iter().zip(keep) {
+        if kept {
+            if !compressed.is_empty() {
+                compressed.push_str(match (previous_kept, segment.line_start) {
+                    (false, _) => OMISSION_MARKER,
+                    (true, true) => "\n",
+                    (true, false) => " ",
+                });
+            }
+            compressed.push_str(segment.text);
+        }
+        previous_kept = kept;
+    }
+    compressed
+}
+
+/// Split `text` into its non-empty lines, and long lines into sentences.
+fn split_segments(text: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    for line in text.lines() {
+        let mut line_start = true;
+        let mut rest = line.trim();
+        while !rest.is_empty() {
+            let end = rest
+                .match_indices(['.', '!', '?'])
+                .map(|(i, _)| i + 1)
+                .find(|&i| rest[i..].starts_with(char::is_whitespace))
+                .unwrap_or(rest.len());
+            segments.push(Segment {
+                text: &rest[..end],
+                line_start,
+            });
+            line_start = false;
+            rest = rest[end..].trim_start();
+        }
+    }
+    segments
+}
+
+fn segment_words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.len() > 2)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Numbers, identifiers, paths and URLs the model may need verbatim.
+fn is_factual(token: &str) -> bool {
+    token.chars().any(|c| c.is_ascii_digit())
+        || token.contains(['_', '/', '@', ':'])
+        || token.chars().skip(1).any(|c| c.is_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::state::AgentStateSnapshot;
+    use std::sync::RwLock;
+
+    use crate::middleware::ModelRequest;
+
+    fn text_message(role: MessageRole, text: &str) -> AgentMessage {
+        AgentMessage {
+            role,
+            content: MessageContent::Text(text.to_string()),
+            metadata: None,
+        }
+    }
+
+    fn report() -> String {
+        let filler = "The team talked about the weather and the general mood of the week.";
+        format!(
+            "Deployment report for the payments service.\n{filler} {filler}\n\
+             Order ord_8812 failed with HTTP 502 at /api/v2/charge.\n{filler}\n\
+             Rollback finished at 14:05 UTC.\n{filler}"
+        )
+    }
+
+    #[test]
+    fn extractive_keeps_facts_within_ratio() {
+        let text = report();
+        let compressed = extract(&text, 0.5);
+
+        assert!(compressed.len() <= text.len() / 2 + 1);
+        assert!(compressed.contains("ord_8812 failed with HTTP 502"));
+        assert!(compressed.contains("14:05 UTC"));
+        assert!(!compressed.contains("weather"));
+    }
+
+    #[tokio::test]
+    async fn compresses_only_older_long_text_messages() {
+        let middleware = PromptCompressionMiddleware::new(
+            PromptCompressionConfig::default()
+                .with_keep_recent(1)
+                .with_min_chars(100),
+        );
+        let mut request = ModelRequest::new(
+            "system",
+            vec![
+                text_message(MessageRole::System, &report()),
+                text_message(MessageRole::Tool, &report()),
+                text_message(MessageRole::User, "short question"),
+                text_message(MessageRole::Agent, &report()),
+            ],
+        );
+        let state = Arc::new(RwLock::new(AgentStateSnapshot::default()));
+        let mut ctx = MiddlewareContext::with_request(&mut request, state);
+        middleware.modify_model_request(&mut ctx).await.unwrap();
+
+        let texts: Vec<String> = request
+            .messages
+            .iter()
+            .map(|m| m.content.to_text())
+            .collect();
+        assert_eq!(texts[0], report());
+        assert!(texts[1].len() < report().len());
+        assert_eq!(texts[2], "short question");
+        assert_eq!(texts[3], report());
+    }
+}
@@ -0,0 +1,363 @@
+//! Response caching for repeated prompts
+//!
+//! `ResponseCacheMiddleware` wraps a language model and answers requests from a
+//! [`ResponseCache`] before calling the provider. A request ending in a user
+//! message is looked up by its exact key: the system prompt, earlier messages,
+//! tools, temperature and tenant, plus the latest message normalized (case,
+//! whitespace and trailing punctuation ignored). With an [`Embedder`] configured, a
+//! miss is retried semantically: the closest cached prompt of the same context
+//! answers when its similarity reaches the threshold. Only answers are matched
+//! semantically; tool calls are replayed for exactly the same request only.
+//!
+//! Cache and embedder failures are logged and the request goes to the model.
+//! Streaming requests are answered from the cache on a hit and streamed from the
+//! model uncached otherwise.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::agent::experiments::fnv1a;
+use crate::planner::is_tool_call;
+use agents_core::llm::{ChunkStream, LanguageModel, LlmRequest, LlmResponse, StreamChunk};
+use agents_core::messaging::{MessageContent, MessageRole};
+use agents_core::metering::current_tenant;
+use agents_core::response_cache::{CachedResponse, Embedder, ResponseCache};
+use async_trait::async_trait;
+
+/// Configuration for [`ResponseCacheMiddleware`].
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use agents_runtime::middleware::response_cache::ResponseCacheConfig;
+///
+/// let agent = ConfigurableAgentBuilder::new("You answer questions about our product")
+///     .with_model(model)
+///     .with_response_cache(
+///         ResponseCacheConfig::new(Arc::new(InMemoryResponseCache::new()))
+///             .with_semantic_matching(embedder, 0.93)
+///             .with_ttl(Duration::from_secs(6 * 3600)),
+///     )
+///     .build()?;
+/// ```
+#[derive(Clone)]
+pub struct ResponseCacheConfig {
+    pub cache: Arc<dyn ResponseCache>,
+    /// Embeds prompts for semantic matching; exact matching only when unset
+    pub embedder: Option<Arc<dyn Embedder>>,
+    /// Minimum cosine similarity of a semantic match (default: 0.95)
+    pub similarity_threshold: f32,
+    /// How long responses stay cached; forever when unset (default: 1 hour)
+    pub ttl: Option<Duration>,
+}
+
+impl std::fmt::Debug for ResponseCacheConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResponseCacheConfig")
+            .field("semantic", &self.embedder.is_some())
+            .field("similarity_threshold", &self.similarity_threshold)
+            .field("ttl", &self.ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ResponseCacheConfig {
+    /// Exact-match caching in `cache`.
+    pub fn new(cache: Arc<dyn ResponseCache>) -> Self {
+        Self {
+            cache,
+            embedder: None,
+            similarity_threshold: 0.95,
+            ttl: Some(Duration::from_secs(3600)),
+        }
+    }
+
+    /// Also answer prompts whose embedding is at least `threshold` similar to a
+    /// cached one.
+    pub fn with_semantic_matching(mut self, embedder: Arc<dyn Embedder>, threshold: f32) -> Self {
+        self.embedder = Some(embedder);
+        self.similarity_threshold = threshold;
+        self
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Keep responses until the cache evicts them.
+    pub fn without_ttl(mut self) -> Self {
+        self.ttl = None;
+        self
+    }
+}
+
+/// Keys of a cacheable request.
+struct RequestKeys {
+    key: String,
+    context_key: String,
+    prompt: String,
+}
+
+impl RequestKeys {
+    /// Keys of `request`, unless it does not end in a user message or contains
+    /// images, which are not part of the keys.
+    fn of(request: &LlmRequest) -> Option<Self> {
+        let (last, earlier) = request.messages.split_last()?;
+        if last.role != MessageRole::User
+            || request
+                .messages
+                .iter()
+                .any(|m| matches!(m.content, MessageContent::Image { .. }))
+        {
+            return None;
+        }
+
+        let mut context = vec![request.system_prompt.clone()];
+        for message in earlier {
+            context.push(format!("{:?}", message.role));
+            context.push(message.content.to_text());
+        }
+        let mut tools: Vec<String> = request.tools.iter().map(|t| t.name.clone()).collect();
+        tools.sort();
+        context.extend(tools);
+        context.push(format!("{:?}", request.temperature));
+        context.push(current_tenant().unwrap_or_default());
+        let context_key = hash(&context);
+
+        let prompt = normalize(&last.content.text());
+        let key = hash(&[context_key.clone(), prompt.clone()]);
+        Some(Self {
+            key,
+            context_key,
+            prompt,
+        })
+    }
+}
+
+fn hash(parts: &[String]) -> String {
+    let bytes: Vec<&[u8]> = parts
+        .iter()
+        .flat_map(|part| [part.as_bytes(), b"\0"])
+        .collect();
+    format!("{:016x}", fnv1a(&bytes))
+}
+
+/// Lowercase `prompt`, collapse its whitespace and drop trailing punctuation.
+fn normalize(prompt: &str) -> String {
+    let words: Vec<&str> = prompt.split_whitespace().collect();
+    words
+        .join(" ")
+        .to_lowercase()
+        .trim_end_matches(['?', '!', '.'])
+        .trim_end()
+        .to_string()
+}
+
+/// Language model wrapper answering repeated prompts from a cache.
+pub struct ResponseCacheMiddleware {
+    inner_model: Arc<dyn LanguageModel>,
+    config: ResponseCacheConfig,
+}
+
+impl ResponseCacheMiddleware {
+    pub fn new(config: ResponseCacheConfig, inner_model: Arc<dyn LanguageModel>) -> Self {
+        Self {
+            inner_model,
+            config,
+        }
+    }
+
+    /// The cached response for `keys`, with the embedding of its prompt when
+    /// semantic matching is on.
+    async fn lookup(&self, keys: &RequestKeys) -> (Option<LlmResponse>, Option<Vec<f32>>) {
+        match self.config.cache.get(&keys.key).await {
+            Ok(Some(response)) => {
+                tracing::debug!(key = %keys.key, "Response cache hit");
+                return (Some(response), None);
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Failed to read response cache: {}", e),
+        }
+
+        let Some(embedder) = &self.config.embedder else {
+            return (None, None);
+        };
+        let embedding = match embedder.embed(&keys.prompt).await {
+            Ok(embedding) => embedding,
+            Err(e) => {
+                tracing::warn!("Failed to embed prompt for the response cache: {}", e);
+                return (None, None);
+            }
+        };
+        match self
+            .config
+            .cache
+            .find_similar(
+                &keys.context_key,
+                &embedding,
+                self.config.similarity_threshold,
+            )
+            .await
+        {
+            Ok(Some((response, similarity))) => {
+                tracing::debug!(similarity, "Response cache semantic hit");
+                (Some(response), None)
+            }
+            Ok(None) => (None, Some(embedding)),
+            Err(e) => {
+                tracing::warn!("Failed to search response cache: {}", e);
+                (None, Some(embedding))
+            }
+        }
+    }
+
+    async fn store(&self, keys: RequestKeys, embedding: Option<Vec<f32>>, response: &LlmResponse) {
+        // A tool call answers this exact conversation, not a similar question
+        let embedding = embedding.filter(|_| !is_tool_call(&response.message));
+        let entry = CachedResponse {
+            key: keys.key,
+            context_key: keys.context_key,
+            prompt: keys.prompt,
+            embedding,
+            response: LlmResponse::new(response.message.clone()),
+        };
+        if let Err(e) = self.config.cache.put(entry, self.config.ttl).await {
+            tracing::warn!("Failed to write response cache: {}", e);
+        }
+    }
+}
+
+#[async_trait]
+impl LanguageModel for ResponseCacheMiddleware {
+    fn model_name(&self) -> Option<String> {
+        self.inner_model.model_name()
+    }
+
+    async fn generate(&self, request: LlmRequest) -> anyhow::Result<LlmResponse> {
+        let Some(keys) = RequestKeys::of(&request) else {
+            return self.inner_model.generate(request).await;
+        };
+        let (cached, embedding) = self.lookup(&keys).await;
+        if let Some(response) = cached {
+            return Ok(response);
+        }
+        let response = self.inner_model.generate(request).await?;
+        self.store(keys, embedding, &response).await;
+        Ok(response)
+    }
+
+    async fn generate_stream(&self, request: LlmRequest) -> anyhow::Result<ChunkStream> {
+        if let Some(keys) = RequestKeys::of(&request) {
+            if let (Some(response), _) = self.lookup(&keys).await {
+                return Ok(Box::pin(futures::stream::once(async move {
+                    Ok(StreamChunk::Done {
+                        message: response.message,
+                    })
+                })));
+            }
+        }
+        self.inner_model.generate_stream(request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{ConfigurableAgentBuilder, RunOptions};
+    use agents_core::messaging::AgentMessage;
+    use agents_core::response_cache::InMemoryResponseCache;
+    use agents_core::state::AgentStateSnapshot;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Counts calls and answers with the call number.
+    #[derive(Default)]
+    struct CountingModel(AtomicUsize);
+
+    #[async_trait]
+    impl LanguageModel for CountingModel {
+        async fn generate(&self, _request: LlmRequest) -> anyhow::Result<LlmResponse> {
+            let call = self.0.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(LlmResponse::new(AgentMessage {
+                role: MessageRole::Agent,
+                content: MessageContent::Text(format!("answer {call}")),
+                metadata: None,
+            }))
+        }
+    }
+
+    /// Embeds prompts mentioning "hours" and "refund" on separate axes.
+    struct TopicEmbedder;
+
+    #[async_trait]
+    impl Embedder for TopicEmbedder {
+        async fn embed(&self, text: &str) -> anyhow::Result<Vec<f32>> {
+            let axis = |word: &str| if text.contains(word) { 1.0 } else { 0.0 };
+            Ok(vec![axis("hours"), axis("refund"), 0.1])
+        }
+    }
+
+    /// Ask a new conversation of an agent answering with `model` through `cache`.
+    async fn ask(
+        model: &Arc<CountingModel>,
+        cache: &Arc<InMemoryResponseCache>,
+        text: &str,
+        tenant: Option<&str>,
+    ) -> String {
+        let agent = ConfigurableAgentBuilder::new("assist")
+            .with_model(model.clone())
+            .with_response_cache(
+                ResponseCacheConfig::new(cache.clone())
+                    .with_semantic_matching(Arc::new(TopicEmbedder), 0.9),
+            )
+            .build()
+            .unwrap();
+        let mut options = RunOptions::new();
+        if let Some(tenant) = tenant {
+            options = options.with_tenant(tenant);
+        }
+        agent
+            .handle_message_with_options(text, options, Arc::new(AgentStateSnapshot::default()))
+            .await
+            .unwrap()
+            .content
+            .to_text()
+    }
+
+    #[tokio::test]
+    async fn test_repeated_and_similar_prompts_are_answered_from_the_cache() {
+        let model = Arc::new(CountingModel::default());
+        let cache = Arc::new(InMemoryResponseCache::new());
+
+        assert_eq!(
+            ask(&model, &cache, "What are your hours?", None).await,
+            "answer 1"
+        );
+        assert_eq!(
+            ask(&model, &cache, "  what are your HOURS ", None).await,
+            "answer 1"
+        );
+        assert_eq!(
+            ask(&model, &cache, "When are you open? hours please", None).await,
+            "answer 1"
+        );
+        assert_eq!(
+            ask(&model, &cache, "How do I get a refund?", None).await,
+            "answer 2"
+        );
+        // Tenants don't share cached answers
+        assert_eq!(
+            ask(&model, &cache, "What are your hours?", Some("acme")).await,
+            "answer 3"
+        );
+        assert_eq!(model.0.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize("  What   are your\nHours?? "),
+            "what are your hours"
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Time context middleware
+//!
+//! Models have no clock and will guess "today" from their training data. This
+//! middleware stamps every model request with the current date and time in a
+//! configured timezone and can expose the `get_current_time` and `date_math`
+//! tools for anything more precise.
+
+use crate::middleware::{AgentMiddleware, MiddlewareContext};
+use agents_core::determinism;
+use agents_core::tools::ToolBox;
+use agents_toolkit::builtin::time::{create_time_tools, parse_timezone};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use std::future::Future;
+
+/// Run `future` with the time context and time tools reporting `now` instead of the
+/// current time, so rendered prompts are reproducible.
+pub async fn with_fixed_time<F: Future>(now: DateTime<Utc>, future: F) -> F::Output {
+    determinism::with_frozen_time(now, future).await
+}
+
+/// Configuration for the time context provider
+#[derive(Debug, Clone)]
+pub struct TimeContextConfig {
+    /// Timezone the current time is reported in
+    pub timezone: Tz,
+    /// Whether to expose the `get_current_time` and `date_math` tools
+    pub include_tools: bool,
+}
+
+impl Default for TimeContextConfig {
+    fn default() -> Self {
+        Self::new(Tz::UTC)
+    }
+}
+
+impl TimeContextConfig {
+    pub fn new(timezone: Tz) -> Self {
+        Self {
+            timezone,
+            include_tools: true,
+        }
+    }
+
+    /// Build a configuration from an IANA timezone name such as `Asia/Dubai`.
+    pub fn for_timezone(name: &str) -> anyhow::Result<Self> {
+        Ok(Self::new(parse_timezone(name)?))
+    }
+
+    pub fn with_tools(mut self, include_tools: bool) -> Self {
+        self.include_tools = include_tools;
+        self
+    }
+}
+
+/// Injects the current date and time into the system prompt on every turn
+pub struct TimeContextMiddleware {
+    config: TimeContextConfig,
+}
+
+impl TimeContextMiddleware {
+    pub fn new(config: TimeContextConfig) -> Self {
+        Self { config }
+    }
+
+    /// Prompt section describing `now` in the configured timezone.
+    pub fn render(&self, now: DateTime<Utc>) -> String {
+        let local = now.with_timezone(&self.config.timezone);
+        let mut section = format!(
+            "## Current Date and Time\n\nIt is {} ({}, UTC{}). Use this as the reference \
+             for relative dates such as \"today\", \"tomorrow\" or \"next week\".",
+            local.format("%A, %B %-d, %Y %H:%M"),
+            self.config.timezone.name(),
+            local.format("%:z"),
+        );
+        if self.config.include_tools {
+            section.push_str(
+                " Call `get_current_time` for other timezones and `date_math` instead of \
+                 calculating dates or durations yourself.",
+            );
+        }
+        section
+    }
+}
+
+#[async_trait]
+impl AgentMiddleware for TimeContextMiddleware {
+    fn id(&self) -> &'static str {
+        "time-context"
+    }
+
+    fn tools(&self) -> Vec<ToolBox> {
+        if self.config.include_tools {
+            create_time_tools(self.config.timezone)
+        } else {
+            Vec::new()
+        }
+    }
+
+    async fn modify_model_request(&self, ctx: &mut MiddlewareContext<'_>) -> anyhow::Result<()> {
+        ctx.request.append_prompt(&self.render(determinism::now()));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn renders_local_time_with_offset() {
+        let config = TimeContextConfig::for_timezone("Asia/Dubai").unwrap();
+        let middleware = TimeContextMiddleware::new(config);
+        let now = Utc.with_ymd_and_hms(2026, 10, 16, 21, 30, 0).unwrap();
+
+        let section = middleware.render(now);
+        assert!(section.contains("Saturday, October 17, 2026 01:30"));
+        assert!(section.contains("Asia/Dubai, UTC+04:00"));
+        assert!(section.contains("date_math"));
+        assert_eq!(middleware.tools().len(), 2);
+    }
+
+    #[test]
+    fn tools_can_be_disabled() {
+        let middleware = TimeContextMiddleware::new(TimeContextConfig::default().with_tools(false));
+        let section = middleware.render(Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap());
+        assert!(section.contains("(UTC, UTC+00:00)"));
+        assert!(!section.contains("date_math"));
+        assert!(middleware.tools().is_empty());
+        assert!(TimeContextConfig::for_timezone("Nowhere/Land").is_err());
+    }
+}
@@ -4,12 +4,15 @@
 //! costs, and other usage metrics across different providers.
 
 use crate::middleware::{AgentMiddleware, MiddlewareContext};
+use agents_core::agent::current_agent_name;
 use agents_core::events::{AgentEvent, EventMetadata, TokenUsage, TokenUsageEvent};
-use agents_core::llm::{LanguageModel, LlmRequest, LlmResponse};
-use agents_core::messaging::AgentMessage;
+use agents_core::experiment::{current_experiment, ExperimentAssignment};
+use agents_core::llm::{current_usage_purpose, LanguageModel, LlmRequest, LlmResponse};
+use agents_core::metering::current_tenant;
 use async_trait::async_trait;
 use futures::StreamExt;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::{Arc, RwLock};
 use std::time::Instant;
 
@@ -48,6 +51,12 @@ pub struct TokenCosts {
     pub provider: String,
     /// Model name for reference
     pub model: String,
+    /// Cost per prompt token read from the provider's cache (defaults to the input cost)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_cost_per_token: Option<f64>,
+    /// Cost per prompt token written to the provider's cache (defaults to the input cost)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_write_cost_per_token: Option<f64>,
 }
 
 impl TokenCosts {
@@ -62,9 +71,34 @@ impl TokenCosts {
             model: model.into(),
             input_cost_per_token: input_cost,
             output_cost_per_token: output_cost,
+            cache_read_cost_per_token: None,
+            cache_write_cost_per_token: None,
         }
     }
 
+    /// Price cached prompt tokens separately from regular input tokens
+    pub fn with_cache_costs(mut self, read_cost: f64, write_cost: f64) -> Self {
+        self.cache_read_cost_per_token = Some(read_cost);
+        self.cache_write_cost_per_token = Some(write_cost);
+        self
+    }
+
+    /// Cost of a request; cache tokens are part of `usage.input_tokens`
+    pub fn cost_of(&self, usage: &TokenUsage) -> f64 {
+        let cached = usage.cache_read_tokens + usage.cache_write_tokens;
+        let uncached = usage.input_tokens.saturating_sub(cached);
+        uncached as f64 * self.input_cost_per_token
+            + usage.cache_read_tokens as f64
+                * self
+                    .cache_read_cost_per_token
+                    .unwrap_or(self.input_cost_per_token)
+            + usage.cache_write_tokens as f64
+                * self
+                    .cache_write_cost_per_token
+                    .unwrap_or(self.input_cost_per_token)
+            + usage.output_tokens as f64 * self.output_cost_per_token
+    }
+
     /// Predefined costs for common models
     pub fn openai_gpt4o_mini() -> Self {
         Self::new("openai", "gpt-4o-mini", 0.00015 / 1000.0, 0.0006 / 1000.0)
@@ -103,6 +137,8 @@ pub struct TokenTrackingMiddleware {
     inner_model: Arc<dyn LanguageModel>,
     event_dispatcher: Option<Arc<agents_core::events::EventDispatcher>>,
     usage_stats: Arc<RwLock<Vec<TokenUsage>>>,
+    /// Tool executions per agent, then per tool
+    tool_calls: Arc<RwLock<BTreeMap<String, BTreeMap<String, u32>>>>,
 }
 
 impl TokenTrackingMiddleware {
@@ -116,6 +152,29 @@ impl TokenTrackingMiddleware {
             inner_model,
             event_dispatcher,
             usage_stats: Arc::new(RwLock::new(Vec::new())),
+            tool_calls: Arc::new(RwLock::new(BTreeMap::new())),
+        }
+    }
+
+    /// Track another model (a sub-agent's or a summarizer) into the same statistics.
+    pub fn share(&self, inner_model: Arc<dyn LanguageModel>) -> Self {
+        Self {
+            config: self.config.clone(),
+            inner_model,
+            event_dispatcher: self.event_dispatcher.clone(),
+            usage_stats: self.usage_stats.clone(),
+            tool_calls: self.tool_calls.clone(),
+        }
+    }
+
+    /// Count a tool execution against the agent that made it.
+    pub fn record_tool_call(&self, agent_name: &str, tool_name: &str) {
+        if let Ok(mut calls) = self.tool_calls.write() {
+            *calls
+                .entry(agent_name.to_string())
+                .or_default()
+                .entry(tool_name.to_string())
+                .or_default() += 1;
         }
     }
 
@@ -124,52 +183,61 @@ impl TokenTrackingMiddleware {
         self.usage_stats.read().unwrap().clone()
     }
 
-    /// Get total usage summary
+    /// Get total usage summary, broken down by component
     pub fn get_total_usage(&self) -> TokenUsageSummary {
-        let stats = self.get_usage_stats();
-        let mut total_input = 0;
-        let mut total_output = 0;
-        let mut total_cost = 0.0;
-        let mut total_duration = 0;
-
-        for usage in &stats {
-            total_input += usage.input_tokens;
-            total_output += usage.output_tokens;
-            total_cost += usage.estimated_cost;
-            total_duration += usage.duration_ms;
+        let mut summary = TokenUsageSummary::from_usages(&self.get_usage_stats());
+        let calls = self.tool_calls.read().unwrap().clone();
+        for (agent, tools) in calls {
+            summary.components.entry(agent).or_default().tool_calls = tools;
         }
+        summary
+    }
 
-        TokenUsageSummary {
-            total_input_tokens: total_input,
-            total_output_tokens: total_output,
-            total_tokens: total_input + total_output,
-            total_cost,
-            total_duration_ms: total_duration,
-            request_count: stats.len(),
+    /// Usage summary per agent; requests made outside an agent run are keyed by `""`
+    pub fn get_usage_by_agent(&self) -> BTreeMap<String, TokenUsageSummary> {
+        let mut by_agent: BTreeMap<String, Vec<TokenUsage>> = BTreeMap::new();
+        for usage in self.get_usage_stats() {
+            by_agent
+                .entry(usage.agent_name.clone().unwrap_or_default())
+                .or_default()
+                .push(usage);
         }
+        by_agent
+            .into_iter()
+            .map(|(agent, usages)| (agent, TokenUsageSummary::from_usages(&usages)))
+            .collect()
+    }
+
+    /// Usage summary per experiment variant, for comparing the cost of variants;
+    /// requests made outside an experiment are left out
+    pub fn get_usage_by_variant(&self) -> BTreeMap<ExperimentAssignment, TokenUsageSummary> {
+        let mut by_variant: BTreeMap<ExperimentAssignment, Vec<TokenUsage>> = BTreeMap::new();
+        for usage in self.get_usage_stats() {
+            if let Some(experiment) = usage.experiment.clone() {
+                by_variant.entry(experiment).or_default().push(usage);
+            }
+        }
+        by_variant
+            .into_iter()
+            .map(|(variant, usages)| (variant, TokenUsageSummary::from_usages(&usages)))
+            .collect()
     }
 
     /// Clear usage statistics
     pub fn clear_stats(&self) {
         self.usage_stats.write().unwrap().clear();
+        self.tool_calls.write().unwrap().clear();
     }
 
     fn emit_token_event(&self, usage: TokenUsage) {
         if self.config.emit_events {
             if let Some(dispatcher) = &self.event_dispatcher {
                 let event = AgentEvent::TokenUsage(TokenUsageEvent {
-                    metadata: EventMetadata::new(
-                        "default".to_string(),
-                        uuid::Uuid::new_v4().to_string(),
-                        None,
-                    ),
+                    metadata: EventMetadata::for_current_run("default"),
                     usage,
                 });
 
-                let dispatcher_clone = dispatcher.clone();
-                tokio::spawn(async move {
-                    dispatcher_clone.dispatch(event).await;
-                });
+                dispatcher.emit(event);
             }
         }
     }
@@ -179,8 +247,11 @@ impl TokenTrackingMiddleware {
             tracing::info!(
                 provider = %usage.provider,
                 model = %usage.model,
+                agent = usage.agent_name.as_deref().unwrap_or_default(),
                 input_tokens = usage.input_tokens,
                 output_tokens = usage.output_tokens,
+                cache_read_tokens = usage.cache_read_tokens,
+                cache_write_tokens = usage.cache_write_tokens,
                 total_tokens = usage.total_tokens,
                 estimated_cost = usage.estimated_cost,
                 duration_ms = usage.duration_ms,
@@ -189,39 +260,52 @@ impl TokenTrackingMiddleware {
         }
     }
 
-    fn extract_token_usage(&self, request: &LlmRequest, response: &LlmResponse) -> TokenUsage {
-        let start_time = Instant::now();
-
-        // Estimate tokens based on text length (rough approximation)
-        let input_tokens = self.estimate_tokens(&request.system_prompt)
-            + request
-                .messages
-                .iter()
-                .map(|msg| self.estimate_tokens(&self.message_to_text(msg)))
-                .sum::<u32>();
-
-        let output_tokens = self.estimate_tokens(&self.message_to_text(&response.message));
-        let duration_ms = start_time.elapsed().as_millis() as u64;
-
-        // Try to determine provider and model from the inner model
-        let (provider, model) = self.detect_provider_model();
-
-        // Calculate estimated cost
-        let estimated_cost = if let Some(costs) = &self.config.custom_costs {
-            (input_tokens as f64 * costs.input_cost_per_token)
-                + (output_tokens as f64 * costs.output_cost_per_token)
-        } else {
-            0.0 // Unknown cost
+    fn extract_token_usage(
+        &self,
+        estimated_input_tokens: u32,
+        response: &LlmResponse,
+        duration_ms: u64,
+    ) -> TokenUsage {
+        let mut usage = match &response.usage {
+            // Counts reported by the provider
+            Some(reported) => TokenUsage::new(
+                reported.input_tokens,
+                reported.output_tokens,
+                reported.provider.clone(),
+                reported.model.clone(),
+                duration_ms,
+                // Cost reported by a gateway, unless custom costs are configured
+                reported.cost.unwrap_or(0.0),
+            )
+            .with_cache_tokens(reported.cache_read_tokens, reported.cache_write_tokens),
+            // Estimate tokens based on text length (rough approximation)
+            None => {
+                let output_tokens = self.estimate_tokens(&response.message.content.text());
+                let (provider, model) = self.detect_provider_model();
+                TokenUsage::new(
+                    estimated_input_tokens,
+                    output_tokens,
+                    provider,
+                    model,
+                    duration_ms,
+                    0.0,
+                )
+            }
         };
 
-        TokenUsage::new(
-            input_tokens,
-            output_tokens,
-            provider,
-            model,
-            duration_ms,
-            estimated_cost,
-        )
+        if let Some(costs) = &self.config.custom_costs {
+            usage.estimated_cost = costs.cost_of(&usage);
+        }
+        if let Some(agent) = current_agent_name() {
+            usage = usage.with_agent_name(agent);
+        }
+        if let Some(experiment) = current_experiment() {
+            usage = usage.with_experiment(experiment);
+        }
+        if let Some(tenant) = current_tenant() {
+            usage = usage.with_tenant(tenant);
+        }
+        usage.with_purpose(current_usage_purpose())
     }
 
     fn estimate_tokens(&self, text: &str) -> u32 {
@@ -230,11 +314,15 @@ impl TokenTrackingMiddleware {
         (text.len() as f32 / 4.0).ceil() as u32
     }
 
-    fn message_to_text(&self, message: &AgentMessage) -> String {
-        match &message.content {
-            agents_core::messaging::MessageContent::Text(text) => text.clone(),
-            agents_core::messaging::MessageContent::Json(json) => json.to_string(),
-        }
+    /// Estimated prompt tokens of `request`, for providers that report no usage.
+    /// Taken before the request is handed to the inner model, so it is not cloned.
+    fn estimate_input_tokens(&self, request: &LlmRequest) -> u32 {
+        self.estimate_tokens(&request.system_prompt)
+            + request
+                .messages
+                .iter()
+                .map(|msg| self.estimate_tokens(&msg.content.text()))
+                .sum::<u32>()
     }
 
     fn detect_provider_model(&self) -> (String, String) {
@@ -247,14 +335,21 @@ impl TokenTrackingMiddleware {
 
 #[async_trait]
 impl LanguageModel for TokenTrackingMiddleware {
+    fn model_name(&self) -> Option<String> {
+        self.inner_model.model_name()
+    }
+
     async fn generate(&self, request: LlmRequest) -> anyhow::Result<LlmResponse> {
         if !self.config.enabled {
             return self.inner_model.generate(request).await;
         }
 
-        let response = self.inner_model.generate(request.clone()).await?;
+        let estimated_input_tokens = self.estimate_input_tokens(&request);
+        let start_time = Instant::now();
+        let response = self.inner_model.generate(request).await?;
+        let duration_ms = start_time.elapsed().as_millis() as u64;
 
-        let usage = self.extract_token_usage(&request, &response);
+        let usage = self.extract_token_usage(estimated_input_tokens, &response, duration_ms);
 
         // Store usage statistics
         {
@@ -286,6 +381,9 @@ impl LanguageModel for TokenTrackingMiddleware {
         let config = self.config.clone();
         let usage_stats = self.usage_stats.clone();
         let event_dispatcher = self.event_dispatcher.clone();
+        let agent_name = current_agent_name();
+        let experiment = current_experiment();
+        let tenant = current_tenant();
 
         Ok(Box::pin(futures::stream::unfold(
             (response, Instant::now()),
@@ -293,15 +391,15 @@ impl LanguageModel for TokenTrackingMiddleware {
                 let config = config.clone();
                 let usage_stats = usage_stats.clone();
                 let event_dispatcher = event_dispatcher.clone();
+                let agent_name = agent_name.clone();
+                let experiment = experiment.clone();
+                let tenant = tenant.clone();
                 async move {
                     match stream.next().await {
                         Some(Ok(chunk)) => {
                             match chunk {
-                                agents_core::llm::StreamChunk::Done { ref message } => {
+                                agents_core::llm::StreamChunk::Done { .. } => {
                                     // Stream completed - track usage
-                                    let _response = LlmResponse {
-                                        message: message.clone(),
-                                    };
                                     let duration_ms = start_time.elapsed().as_millis() as u64;
 
                                     // Calculate estimated cost (simplified)
@@ -315,7 +413,7 @@ impl LanguageModel for TokenTrackingMiddleware {
                                         0.0 // Unknown cost
                                     };
 
-                                    let usage = TokenUsage::new(
+                                    let mut usage = TokenUsage::new(
                                         input_tokens,
                                         output_tokens,
                                         "unknown",
@@ -323,6 +421,15 @@ impl LanguageModel for TokenTrackingMiddleware {
                                         duration_ms,
                                         estimated_cost,
                                     );
+                                    if let Some(agent) = agent_name {
+                                        usage = usage.with_agent_name(agent);
+                                    }
+                                    if let Some(experiment) = experiment {
+                                        usage = usage.with_experiment(experiment);
+                                    }
+                                    if let Some(tenant) = tenant {
+                                        usage = usage.with_tenant(tenant);
+                                    }
 
                                     // Store and emit usage
                                     {
@@ -333,18 +440,11 @@ impl LanguageModel for TokenTrackingMiddleware {
                                     if config.emit_events {
                                         if let Some(dispatcher) = &event_dispatcher {
                                             let event = AgentEvent::TokenUsage(TokenUsageEvent {
-                                                metadata: EventMetadata::new(
-                                                    "default".to_string(),
-                                                    uuid::Uuid::new_v4().to_string(),
-                                                    None,
-                                                ),
+                                                metadata: EventMetadata::for_current_run("default"),
                                                 usage,
                                             });
 
-                                            let dispatcher_clone = dispatcher.clone();
-                                            tokio::spawn(async move {
-                                                dispatcher_clone.dispatch(event).await;
-                                            });
+                                            dispatcher.emit(event);
                                         }
                                     }
 
@@ -396,9 +496,82 @@ pub struct TokenUsageSummary {
     pub total_cost: f64,
     pub total_duration_ms: u64,
     pub request_count: usize,
+    #[serde(default)]
+    pub total_cache_read_tokens: u32,
+    #[serde(default)]
+    pub total_cache_write_tokens: u32,
+    /// Breakdown by agent, sub-agent and `"summarization"` (see [`TokenUsage::component`])
+    #[serde(default)]
+    pub components: BTreeMap<String, ComponentUsage>,
+}
+
+/// Usage and tool executions of one component of an agent.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ComponentUsage {
+    pub request_count: usize,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub cache_read_tokens: u32,
+    pub cache_write_tokens: u32,
+    pub cost: f64,
+    /// Tool executions by tool name
+    #[serde(default)]
+    pub tool_calls: BTreeMap<String, u32>,
+}
+
+impl ComponentUsage {
+    pub fn total_tool_calls(&self) -> u32 {
+        self.tool_calls.values().sum()
+    }
 }
 
 impl TokenUsageSummary {
+    pub fn from_usages(usages: &[TokenUsage]) -> Self {
+        let mut summary = Self {
+            total_input_tokens: 0,
+            total_output_tokens: 0,
+            total_tokens: 0,
+            total_cost: 0.0,
+            total_duration_ms: 0,
+            request_count: usages.len(),
+            total_cache_read_tokens: 0,
+            total_cache_write_tokens: 0,
+            components: BTreeMap::new(),
+        };
+        for usage in usages {
+            let component = summary
+                .components
+                .entry(usage.component().to_string())
+                .or_default();
+            component.request_count += 1;
+            component.input_tokens += usage.input_tokens;
+            component.output_tokens += usage.output_tokens;
+            component.cache_read_tokens += usage.cache_read_tokens;
+            component.cache_write_tokens += usage.cache_write_tokens;
+            component.cost += usage.estimated_cost;
+
+            summary.total_input_tokens += usage.input_tokens;
+            summary.total_output_tokens += usage.output_tokens;
+            summary.total_cost += usage.estimated_cost;
+            summary.total_duration_ms += usage.duration_ms;
+            summary.total_cache_read_tokens += usage.cache_read_tokens;
+            summary.total_cache_write_tokens += usage.cache_write_tokens;
+        }
+        summary.total_tokens = summary.total_input_tokens + summary.total_output_tokens;
+        summary
+    }
+
+    /// Components ordered from most to least expensive
+    pub fn components_by_cost(&self) -> Vec<(&str, &ComponentUsage)> {
+        let mut components: Vec<_> = self
+            .components
+            .iter()
+            .map(|(name, usage)| (name.as_str(), usage))
+            .collect();
+        components.sort_by(|a, b| b.1.cost.total_cmp(&a.1.cost));
+        components
+    }
+
     pub fn average_tokens_per_request(&self) -> f64 {
         if self.request_count > 0 {
             self.total_tokens as f64 / self.request_count as f64
@@ -415,3 +588,103 @@ impl TokenUsageSummary {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::agent::with_current_agent;
+    use agents_core::llm::{with_usage_purpose, LlmUsage, UsagePurpose};
+    use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+
+    struct ReportingModel;
+
+    #[async_trait]
+    impl LanguageModel for ReportingModel {
+        async fn generate(&self, _request: LlmRequest) -> anyhow::Result<LlmResponse> {
+            Ok(LlmResponse::new(AgentMessage {
+                role: MessageRole::Agent,
+                content: MessageContent::Text("done".into()),
+                metadata: None,
+            })
+            .with_usage(
+                LlmUsage::new("anthropic", "claude-3", 1_000, 100).with_cache_tokens(800, 0),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn reported_usage_is_attributed_to_the_running_agent() {
+        let config = TokenTrackingConfig {
+            log_usage: false,
+            custom_costs: Some(
+                TokenCosts::new("anthropic", "claude-3", 1.0, 2.0).with_cache_costs(0.1, 1.25),
+            ),
+            ..Default::default()
+        };
+        let tracker = TokenTrackingMiddleware::new(config, Arc::new(ReportingModel), None);
+        let request = LlmRequest::new("system", Vec::new());
+
+        tracker.generate(request.clone()).await.unwrap();
+        with_current_agent("researcher", tracker.generate(request))
+            .await
+            .unwrap();
+
+        let stats = tracker.get_usage_stats();
+        assert_eq!(stats[1].provider, "anthropic");
+        assert_eq!(stats[1].cache_read_tokens, 800);
+        assert_eq!(stats[1].agent_name.as_deref(), Some("researcher"));
+        // 200 uncached + 800 cached input tokens, 100 output tokens
+        assert!((stats[1].estimated_cost - (200.0 + 80.0 + 200.0)).abs() < 1e-9);
+
+        let by_agent = tracker.get_usage_by_agent();
+        assert_eq!(by_agent["researcher"].total_input_tokens, 1_000);
+        assert_eq!(by_agent["researcher"].total_cache_read_tokens, 800);
+        assert_eq!(by_agent[""].request_count, 1);
+        assert_eq!(tracker.get_total_usage().request_count, 2);
+    }
+
+    #[tokio::test]
+    async fn summary_breaks_cost_down_by_component() {
+        let config = TokenTrackingConfig {
+            log_usage: false,
+            custom_costs: Some(TokenCosts::new("anthropic", "claude-3", 1.0, 2.0)),
+            ..Default::default()
+        };
+        let main = TokenTrackingMiddleware::new(config, Arc::new(ReportingModel), None);
+        let sub = main.share(Arc::new(ReportingModel));
+        let request = LlmRequest::new("system", Vec::new());
+
+        with_current_agent("main", main.generate(request.clone()))
+            .await
+            .unwrap();
+        with_current_agent("researcher", async {
+            sub.generate(request.clone()).await.unwrap();
+            sub.generate(request.clone()).await.unwrap();
+        })
+        .await;
+        with_current_agent(
+            "main",
+            with_usage_purpose(UsagePurpose::Summarization, main.generate(request)),
+        )
+        .await
+        .unwrap();
+        main.record_tool_call("main", "task");
+        sub.record_tool_call("researcher", "web_search");
+        sub.record_tool_call("researcher", "web_search");
+
+        let summary = main.get_total_usage();
+        assert_eq!(summary.request_count, 4);
+        assert_eq!(summary.components["main"].request_count, 1);
+        assert_eq!(summary.components["summarization"].request_count, 1);
+        assert_eq!(summary.components["researcher"].tool_calls["web_search"], 2);
+        assert_eq!(summary.components["main"].total_tool_calls(), 1);
+
+        let ranked: Vec<&str> = summary
+            .components_by_cost()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(ranked[0], "researcher");
+        assert_eq!(ranked.len(), 3);
+    }
+}
@@ -0,0 +1,400 @@
+//! User profile middleware
+//!
+//! Users mention who they are in passing: their name, the car they drive, the
+//! account they are calling about, that they prefer short answers. This middleware
+//! has a (preferably small and cheap) model read each new user message, keeps the
+//! stable facts it states in the thread state as a [`UserProfile`], and adds the
+//! profile to the system prompt so later turns, and later sessions on the same
+//! thread, can be personalized without asking again. With a
+//! [`MemoryPolicy`], facts it blocks are never stored.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use crate::agent::memory_policy::{MemoryDecision, MemoryPolicy};
+use crate::middleware::{AgentMiddleware, MiddlewareContext};
+use agents_core::llm::{with_usage_purpose, LanguageModel, LlmRequest, UsagePurpose};
+use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+use agents_core::user_profile::{ProfileFact, UserProfile};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::Deserialize;
+
+/// Characters of the user message kept as a fact's source.
+const SOURCE_CHARS: usize = 200;
+
+const EXTRACTION_PROMPT: &str = "You maintain a profile of the user from what they say \
+    about themselves. Read the user's latest message (and the assistant message before \
+    it, for context) and reply with JSON only: {\"facts\": [{\"key\": \"...\", \
+    \"value\": \"...\", \"confidence\": 0.0}]}. Keys are short snake_case names such as \
+    name, email, phone, language, vehicle, account_id, location or a preference like \
+    preferred_contact_method; reuse a key from the current profile when the fact updates \
+    it. Only include stable facts the user states about themselves, not requests, \
+    questions, moods or facts about other people. Confidence is 0.0 to 1.0: 0.9 or more \
+    when stated outright, lower when implied. Reply {\"facts\": []} when there are none.";
+
+/// Configuration for [`UserProfileMiddleware`].
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use agents_runtime::middleware::user_profile::UserProfileConfig;
+///
+/// let agent = ConfigurableAgentBuilder::new("You schedule car service appointments")
+///     .with_model(model)
+///     .with_checkpointer(checkpointer)
+///     .with_user_profile(UserProfileConfig::new(small_model).with_min_confidence(0.7))
+///     .build()?;
+/// ```
+#[derive(Clone)]
+pub struct UserProfileConfig {
+    /// Model extracting facts from user messages
+    pub model: Arc<dyn LanguageModel>,
+    /// Facts extracted with a lower confidence are ignored (default: 0.6)
+    pub min_confidence: f32,
+    /// Most facts kept; the least confident are dropped first (default: 30)
+    pub max_facts: usize,
+    /// Characters of each message given to the extraction model (default: 2000)
+    pub excerpt_chars: usize,
+}
+
+impl std::fmt::Debug for UserProfileConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UserProfileConfig")
+            .field("min_confidence", &self.min_confidence)
+            .field("max_facts", &self.max_facts)
+            .field("excerpt_chars", &self.excerpt_chars)
+            .finish_non_exhaustive()
+    }
+}
+
+impl UserProfileConfig {
+    pub fn new(model: Arc<dyn LanguageModel>) -> Self {
+        Self {
+            model,
+            min_confidence: 0.6,
+            max_facts: 30,
+            excerpt_chars: 2_000,
+        }
+    }
+
+    /// Minimum confidence, clamped to `0.0..=1.0`.
+    pub fn with_min_confidence(mut self, confidence: f32) -> Self {
+        self.min_confidence = confidence.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_max_facts(mut self, max_facts: usize) -> Self {
+        self.max_facts = max_facts;
+        self
+    }
+
+    pub fn with_excerpt_chars(mut self, chars: usize) -> Self {
+        self.excerpt_chars = chars;
+        self
+    }
+}
+
+/// Extracts user facts from each new user message into the state's profile and
+/// adds the profile to the system prompt.
+///
+/// Extraction runs once per user message, before the first model call of the turn.
+/// A failed extraction is logged and the turn continues with the profile as it was.
+pub struct UserProfileMiddleware {
+    config: Option<UserProfileConfig>,
+    policy: Option<Arc<MemoryPolicy>>,
+}
+
+impl UserProfileMiddleware {
+    pub fn new(config: UserProfileConfig) -> Self {
+        Self {
+            config: Some(config),
+            policy: None,
+        }
+    }
+
+    /// Add the profile already in the state to the prompt without extracting new
+    /// facts, e.g. facts loaded from user memory.
+    pub fn prompt_only() -> Self {
+        Self {
+            config: None,
+            policy: None,
+        }
+    }
+
+    /// Review extracted facts with `policy`, storing only those it allows, with
+    /// their category and expiry.
+    pub fn with_policy(mut self, policy: Arc<MemoryPolicy>) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Prompt section listing the profile, empty when nothing is known.
+    pub fn render(profile: &UserProfile) -> String {
+        if profile.facts.is_empty() {
+            return String::new();
+        }
+        let mut section = String::from(
+            "## About the User\n\nThe user told you this in earlier messages. Use it to \
+             personalize your answers instead of asking again; the user's latest message \
+             wins if it contradicts it.\n",
+        );
+        for (key, fact) in &profile.facts {
+            section.push_str(&format!("\n- {}: {}", key.replace('_', " "), fact.value));
+        }
+        section
+    }
+}
+
+impl UserProfileConfig {
+    async fn extract(
+        &self,
+        profile: &UserProfile,
+        previous: Option<&AgentMessage>,
+        message: &str,
+    ) -> anyhow::Result<Vec<(String, ProfileFact)>> {
+        let mut input = String::new();
+        if !profile.facts.is_empty() {
+            input.push_str("Current profile:\n");
+            for (key, fact) in &profile.facts {
+                input.push_str(&format!("- {key}: {}\n", fact.value));
+            }
+            input.push('\n');
+        }
+        if let Some(previous) = previous {
+            input.push_str(&format!(
+                "Assistant: {}\n",
+                self.excerpt(&previous.content.to_text(), self.excerpt_chars)
+            ));
+        }
+        input.push_str(&format!(
+            "User: {}",
+            self.excerpt(message, self.excerpt_chars)
+        ));
+
+        let request = LlmRequest::new(
+            EXTRACTION_PROMPT,
+            vec![AgentMessage {
+                role: MessageRole::User,
+                content: MessageContent::Text(input),
+                metadata: None,
+            }],
+        );
+        let response =
+            with_usage_purpose(UsagePurpose::Summarization, self.model.generate(request)).await?;
+        let facts = parse_facts(&response.message.content.to_text())
+            .ok_or_else(|| anyhow::anyhow!("Extraction model returned no JSON"))?;
+
+        let source = self.excerpt(message, SOURCE_CHARS);
+        let now = Utc::now();
+        Ok(facts
+            .into_iter()
+            .filter(|(_, _, confidence)| *confidence >= self.min_confidence)
+            .map(|(key, value, confidence)| {
+                (
+                    key,
+                    ProfileFact {
+                        value,
+                        confidence,
+                        source: source.clone(),
+                        updated_at: now,
+                        category: None,
+                        expires_at: None,
+                    },
+                )
+            })
+            .collect())
+    }
+
+    fn excerpt(&self, text: &str, chars: usize) -> String {
+        let text = text.trim();
+        let mut excerpt: String = text.chars().take(chars).collect();
+        if excerpt.len() < text.len() {
+            excerpt.push('…');
+        }
+        excerpt
+    }
+}
+
+#[async_trait]
+impl AgentMiddleware for UserProfileMiddleware {
+    fn id(&self) -> &'static str {
+        "user-profile"
+    }
+
+    async fn modify_model_request(&self, ctx: &mut MiddlewareContext<'_>) -> anyhow::Result<()> {
+        let messages = &ctx.request.messages;
+        let latest = messages
+            .iter()
+            .rposition(|message| matches!(message.role, MessageRole::User));
+        let (user_id, mut profile) = ctx
+            .state
+            .read()
+            .map(|state| (state.user_id.clone(), state.user_profile.clone()))
+            .unwrap_or_default();
+
+        if let (Some(config), Some(index)) = (&self.config, latest) {
+            let text = messages[index].content.to_text();
+            let hash = message_hash(&text);
+            if !text.trim().is_empty() && profile.last_message_hash != Some(hash) {
+                let previous = messages[..index]
+                    .iter()
+                    .rev()
+                    .find(|message| matches!(message.role, MessageRole::Agent));
+                match config.extract(&profile, previous, &text).await {
+                    Ok(facts) => {
+                        for (key, mut fact) in facts {
+                            if let Some(policy) = &self.policy {
+                                match policy.review(user_id.as_ref(), &key, &fact.value).await {
+                                    MemoryDecision::Allow {
+                                        category,
+                                        expires_at,
+                                    } => {
+                                        fact.category = category;
+                                        fact.expires_at = expires_at;
+                                    }
+                                    MemoryDecision::Block { .. } => continue,
+                                }
+                            }
+                            profile.set(key, fact);
+                        }
+                        profile.truncate(config.max_facts);
+                    }
+                    Err(e) => tracing::warn!("Failed to extract user profile facts: {}", e),
+                }
+                profile.last_message_hash = Some(hash);
+                if let Ok(mut state) = ctx.state.write() {
+                    state.user_profile = profile.clone();
+                }
+            }
+        }
+
+        ctx.request.append_prompt(&Self::render(&profile));
+        Ok(())
+    }
+}
+
+fn message_hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[derive(Deserialize)]
+struct ExtractedFact {
+    key: String,
+    value: serde_json::Value,
+    #[serde(default)]
+    confidence: f32,
+}
+
+#[derive(Deserialize)]
+struct Extraction {
+    #[serde(default)]
+    facts: Vec<ExtractedFact>,
+}
+
+/// `(key, value, confidence)` facts from the model's reply, possibly in a code
+/// fence, with snake_case keys. Facts missing a key or value are dropped.
+fn parse_facts(reply: &str) -> Option<Vec<(String, String, f32)>> {
+    let trimmed = reply.trim();
+    let json = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|body| body.strip_suffix("```"))
+        .unwrap_or(trimmed)
+        .trim();
+    let extraction: Extraction = serde_json::from_str(json).ok()?;
+    Some(
+        extraction
+            .facts
+            .into_iter()
+            .filter_map(|fact| {
+                let key = fact
+                    .key
+                    .split(|c: char| !c.is_alphanumeric())
+                    .filter(|word| !word.is_empty())
+                    .map(str::to_lowercase)
+                    .collect::<Vec<_>>()
+                    .join("_");
+                let value = match fact.value {
+                    serde_json::Value::String(value) => value.trim().to_string(),
+                    serde_json::Value::Null => String::new(),
+                    other => other.to_string(),
+                };
+                (!key.is_empty() && !value.is_empty())
+                    .then(|| (key, value, fact.confidence.clamp(0.0, 1.0)))
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::ModelRequest;
+    use agents_core::llm::LlmResponse;
+    use agents_core::state::AgentStateSnapshot;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::RwLock;
+
+    #[derive(Default)]
+    struct ExtractionModel {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LanguageModel for ExtractionModel {
+        async fn generate(&self, request: LlmRequest) -> anyhow::Result<LlmResponse> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let input = request.messages[0].content.to_text();
+            assert!(input.contains("Assistant: Which car is it?"));
+            assert!(input.ends_with("User: It's my 2019 Civic, I'm Ana by the way"));
+            let reply = r#"```json
+{"facts": [{"key": "Vehicle", "value": "2019 Honda Civic", "confidence": 0.95},
+           {"key": "name", "value": "Ana", "confidence": 0.9},
+           {"key": "mood", "value": "impatient", "confidence": 0.3}]}
+```"#;
+            Ok(LlmResponse::new(text(MessageRole::Agent, reply)))
+        }
+    }
+
+    fn text(role: MessageRole, text: &str) -> AgentMessage {
+        AgentMessage {
+            role,
+            content: MessageContent::Text(text.to_string()),
+            metadata: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn extracts_each_user_message_once_and_renders_the_profile() {
+        let model = Arc::new(ExtractionModel::default());
+        let middleware = UserProfileMiddleware::new(UserProfileConfig::new(model.clone()));
+        let state = Arc::new(RwLock::new(AgentStateSnapshot::default()));
+        let messages = vec![
+            text(MessageRole::User, "I need a service appointment"),
+            text(MessageRole::Agent, "Which car is it?"),
+            text(MessageRole::User, "It's my 2019 Civic, I'm Ana by the way"),
+        ];
+
+        for _ in 0..2 {
+            let mut request = ModelRequest::new("You schedule appointments", messages.clone());
+            let mut ctx = MiddlewareContext::with_request(&mut request, state.clone());
+            middleware.modify_model_request(&mut ctx).await.unwrap();
+            assert!(request.system_prompt.contains("- name: Ana"));
+            assert!(request
+                .system_prompt
+                .contains("- vehicle: 2019 Honda Civic"));
+        }
+        assert_eq!(model.calls.load(Ordering::SeqCst), 1);
+
+        let profile = state.read().unwrap().user_profile.clone();
+        assert_eq!(profile.facts.len(), 2);
+        assert_eq!(
+            profile.get("vehicle").unwrap().source,
+            "It's my 2019 Civic, I'm Ana by the way"
+        );
+    }
+}
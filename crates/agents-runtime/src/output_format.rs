@@ -0,0 +1,494 @@
+//! Output formatting profiles for messaging channels
+//!
+//! Models answer in Markdown, which reads well in a terminal or a web UI but shows
+//! as stray asterisks and hashes in an SMS, and differs from the formatting Slack
+//! and WhatsApp understand. An [`OutputFormat`] rewrites a finished reply for one
+//! channel: it converts the Markdown to the channel's dialect, optionally strips
+//! emoji and cuts the reply to a length limit.
+//!
+//! Formats apply after generation, to the reply returned to the caller. The
+//! conversation history keeps the model's own Markdown, so later turns are not
+//! affected. Set one per run with
+//! [`RunOptions::with_output_format`](crate::RunOptions::with_output_format), or on
+//! a connector with its `with_output_format`.
+
+use crate::webhook::split_message;
+use agents_core::messaging::{AgentMessage, MessageContent};
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+
+/// Formatting understood by a channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MarkdownDialect {
+    /// Markdown as written by the model
+    #[default]
+    Markdown,
+    /// No formatting: markup is removed, links become `text (url)`
+    PlainText,
+    /// Slack `mrkdwn`: `*bold*`, `_italic_`, `~strike~` and `<url|text>` links
+    SlackMrkdwn,
+    /// WhatsApp: `*bold*`, `_italic_`, `~strike~` and ```` ``` ```` code blocks
+    WhatsApp,
+}
+
+/// Whether emoji are kept in replies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmojiPolicy {
+    #[default]
+    Keep,
+    /// Remove emoji and pictographic symbols
+    Strip,
+}
+
+/// How replies are rewritten for a channel.
+///
+/// # Example
+///
+/// ```ignore
+/// // Short plain-text replies for SMS
+/// let options = RunOptions::new().with_output_format(OutputFormat::sms());
+/// let reply = agent.handle_message_for_thread_with_options(&thread_id, text, options).await?;
+///
+/// // Slack formatting without emoji
+/// let format = OutputFormat::slack().with_emoji(EmojiPolicy::Strip);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct OutputFormat {
+    pub dialect: MarkdownDialect,
+    pub emoji: EmojiPolicy,
+    /// Characters kept; longer replies are cut at a line or word break and end with `…`
+    pub max_chars: Option<usize>,
+}
+
+/// Three concatenated GSM-7 SMS segments
+const SMS_MAX_CHARS: usize = 459;
+
+/// Body limit of a WhatsApp message
+const WHATSAPP_MAX_CHARS: usize = 4096;
+
+impl OutputFormat {
+    /// Replies as generated.
+    pub fn markdown() -> Self {
+        Self::default()
+    }
+
+    pub fn plain_text() -> Self {
+        Self {
+            dialect: MarkdownDialect::PlainText,
+            ..Self::default()
+        }
+    }
+
+    /// Plain text without emoji, which switch SMS to an encoding with 70 instead of
+    /// 160 characters per segment, cut to three segments.
+    pub fn sms() -> Self {
+        Self {
+            dialect: MarkdownDialect::PlainText,
+            emoji: EmojiPolicy::Strip,
+            max_chars: Some(SMS_MAX_CHARS),
+        }
+    }
+
+    pub fn whatsapp() -> Self {
+        Self {
+            dialect: MarkdownDialect::WhatsApp,
+            emoji: EmojiPolicy::Keep,
+            max_chars: Some(WHATSAPP_MAX_CHARS),
+        }
+    }
+
+    /// Slack `mrkdwn`. Long replies are left to the connector, which splits them.
+    pub fn slack() -> Self {
+        Self {
+            dialect: MarkdownDialect::SlackMrkdwn,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_dialect(mut self, dialect: MarkdownDialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    pub fn with_emoji(mut self, emoji: EmojiPolicy) -> Self {
+        self.emoji = emoji;
+        self
+    }
+
+    pub fn with_max_chars(mut self, max_chars: usize) -> Self {
+        self.max_chars = Some(max_chars);
+        self
+    }
+
+    /// Rewrite `text` for the channel.
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = match self.dialect {
+            MarkdownDialect::Markdown => text.to_string(),
+            dialect => ChatRenderer::new(dialect).render(text),
+        };
+        if self.emoji == EmojiPolicy::Strip {
+            text = strip_emoji(&text);
+        }
+        match self.max_chars {
+            Some(max_chars) => truncate(&text, max_chars),
+            None => text,
+        }
+    }
+
+    /// Rewrite the text of a reply; other content is returned unchanged.
+    pub(crate) fn format_message(&self, mut message: AgentMessage) -> AgentMessage {
+        if let MessageContent::Text(text) = &message.content {
+            message.content = MessageContent::Text(self.apply(text));
+        }
+        message
+    }
+}
+
+/// Renders Markdown in a chat dialect.
+struct ChatRenderer {
+    dialect: MarkdownDialect,
+    /// Output, with a nested buffer for each open link and quote
+    buffers: Vec<String>,
+    /// Next number of each open list, `None` for bullet lists
+    lists: Vec<Option<u64>>,
+    link_urls: Vec<String>,
+    first_cell: bool,
+}
+
+impl ChatRenderer {
+    fn new(dialect: MarkdownDialect) -> Self {
+        Self {
+            dialect,
+            buffers: vec![String::new()],
+            lists: Vec::new(),
+            link_urls: Vec::new(),
+            first_cell: true,
+        }
+    }
+
+    fn render(mut self, markdown: &str) -> String {
+        let options =
+            Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TABLES | Options::ENABLE_TASKLISTS;
+        for event in Parser::new_ext(markdown, options) {
+            self.event(event);
+        }
+        let text = self.buffers.swap_remove(0);
+        text.trim().to_string()
+    }
+
+    fn event(&mut self, event: Event<'_>) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) | Event::Html(text) | Event::InlineHtml(text) => {
+                self.push_text(&text)
+            }
+            Event::Code(code) => {
+                let marker = self.marker("`");
+                self.push(marker);
+                self.push_text(&code);
+                self.push(marker);
+            }
+            Event::InlineMath(math) | Event::DisplayMath(math) => self.push_text(&math),
+            Event::SoftBreak | Event::HardBreak => self.push("\n"),
+            Event::Rule => {
+                self.newlines(1);
+                self.push("---");
+                self.newlines(2);
+            }
+            Event::TaskListMarker(checked) => self.push(if checked { "[x] " } else { "[ ] " }),
+            Event::FootnoteReference(name) => {
+                self.push("[");
+                self.push_text(&name);
+                self.push("]");
+            }
+        }
+    }
+
+    fn start(&mut self, tag: Tag<'_>) {
+        match tag {
+            Tag::Heading { .. } => {
+                self.newlines(2);
+                let marker = self.marker("*");
+                self.push(marker);
+            }
+            Tag::Strong => {
+                let marker = self.marker("*");
+                self.push(marker);
+            }
+            Tag::Emphasis => {
+                let marker = self.marker("_");
+                self.push(marker);
+            }
+            Tag::Strikethrough => {
+                let marker = self.marker("~");
+                self.push(marker);
+            }
+            Tag::CodeBlock(_) => {
+                self.newlines(1);
+                let marker = self.marker("```\n");
+                self.push(marker);
+            }
+            Tag::List(start) => {
+                self.newlines(1);
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.newlines(1);
+                let depth = self.lists.len().saturating_sub(1);
+                self.push(&"  ".repeat(depth));
+                let bullet = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => "• ".to_string(),
+                };
+                self.push(&bullet);
+            }
+            Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. } => {
+                self.link_urls.push(dest_url.to_string());
+                self.buffers.push(String::new());
+            }
+            Tag::BlockQuote(_) => self.buffers.push(String::new()),
+            Tag::TableRow | Tag::TableHead => self.first_cell = true,
+            Tag::TableCell => {
+                if !self.first_cell {
+                    self.push(" | ");
+                }
+                self.first_cell = false;
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Heading(_) => {
+                let marker = self.marker("*");
+                self.push(marker);
+                self.end_block();
+            }
+            TagEnd::Strong => {
+                let marker = self.marker("*");
+                self.push(marker);
+            }
+            TagEnd::Emphasis => {
+                let marker = self.marker("_");
+                self.push(marker);
+            }
+            TagEnd::Strikethrough => {
+                let marker = self.marker("~");
+                self.push(marker);
+            }
+            TagEnd::CodeBlock => {
+                if self.dialect != MarkdownDialect::PlainText {
+                    self.newlines(1);
+                    self.push("```");
+                }
+                self.end_block();
+            }
+            TagEnd::List(_) => {
+                self.lists.pop();
+                self.end_block();
+            }
+            TagEnd::Link | TagEnd::Image => {
+                let text = self.buffers.pop().unwrap_or_default();
+                let url = self.link_urls.pop().unwrap_or_default();
+                let link = match self.dialect {
+                    MarkdownDialect::SlackMrkdwn if text.is_empty() => format!("<{}>", url),
+                    MarkdownDialect::SlackMrkdwn => format!("<{}|{}>", url, text),
+                    _ if text.is_empty() || text == url => url,
+                    _ => format!("{} ({})", text, url),
+                };
+                self.push(&link);
+            }
+            TagEnd::BlockQuote(_) => {
+                let quote = self.buffers.pop().unwrap_or_default();
+                let quote = quote.trim();
+                let quote = if self.dialect == MarkdownDialect::PlainText {
+                    quote.to_string()
+                } else {
+                    quote
+                        .lines()
+                        .map(|line| format!("> {}", line))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                };
+                self.newlines(1);
+                self.push(&quote);
+                self.end_block();
+            }
+            TagEnd::TableHead | TagEnd::TableRow => self.newlines(1),
+            TagEnd::Paragraph | TagEnd::Table | TagEnd::HtmlBlock => self.end_block(),
+            _ => {}
+        }
+    }
+
+    /// `marker` in dialects with formatting, nothing in plain text.
+    fn marker<'m>(&self, marker: &'m str) -> &'m str {
+        match self.dialect {
+            MarkdownDialect::PlainText => "",
+            _ => marker,
+        }
+    }
+
+    fn buffer(&mut self) -> &mut String {
+        self.buffers
+            .last_mut()
+            .expect("renderer always has a buffer")
+    }
+
+    fn push(&mut self, text: &str) {
+        self.buffer().push_str(text);
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if self.dialect == MarkdownDialect::SlackMrkdwn {
+            // Slack reads these as the start of links and mentions
+            let escaped = text
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;");
+            self.push(&escaped);
+        } else {
+            self.push(text);
+        }
+    }
+
+    /// Separate the block just ended from the next, by a line inside lists and a
+    /// blank line elsewhere.
+    fn end_block(&mut self) {
+        self.newlines(if self.lists.is_empty() { 2 } else { 1 });
+    }
+
+    /// End the output with at least `count` newlines, unless it is empty.
+    fn newlines(&mut self, count: usize) {
+        let buffer = self.buffer();
+        if buffer.is_empty() {
+            return;
+        }
+        let trailing = buffer.chars().rev().take_while(|&c| c == '\n').count();
+        for _ in trailing..count {
+            buffer.push('\n');
+        }
+    }
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1F000..=0x1FAFF // pictographs, emoticons, transport, flags
+            | 0x2300..=0x23FF // technical symbols such as ⌛ and ⏳
+            | 0x2600..=0x27BF // miscellaneous symbols and dingbats
+            | 0x2B05..=0x2B55 // arrows, stars and circles
+            | 0xFE0F // emoji presentation selector
+            | 0x200D // zero-width joiner of emoji sequences
+            | 0x20E3 // keycap
+            | 0xE0020..=0xE007F // tags of subdivision flags
+    )
+}
+
+/// Remove emoji along with the space they leave behind.
+fn strip_emoji(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut removed = false;
+    for c in text.chars() {
+        if is_emoji(c) {
+            removed = true;
+            continue;
+        }
+        if c == ' ' && removed && (stripped.is_empty() || stripped.ends_with([' ', '\n'])) {
+            continue;
+        }
+        removed = false;
+        stripped.push(c);
+    }
+    stripped
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Cut `text` to `max_chars` characters, ending with `…` when shortened.
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let kept = split_message(text, max_chars.saturating_sub(1).max(1)).swap_remove(0);
+    format!("{}…", kept.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPLY: &str = "## Order status\n\n\
+        Your order **#1042** has *shipped* ~~yesterday~~ today.\n\n\
+        - Tracking: [UPS](https://ups.com/t/1Z9) 📦\n\
+        - Run `track 1Z9` for updates\n\n\
+        > Deliveries take 2 < 3 days\n\n\
+        ```sh\ntrack --all\n```";
+
+    #[test]
+    fn markdown_is_converted_to_chat_dialects() {
+        assert_eq!(
+            OutputFormat::slack().apply(REPLY),
+            "*Order status*\n\n\
+             Your order *#1042* has _shipped_ ~yesterday~ today.\n\n\
+             • Tracking: <https://ups.com/t/1Z9|UPS> 📦\n\
+             • Run `track 1Z9` for updates\n\n\
+             > Deliveries take 2 &lt; 3 days\n\n\
+             ```\ntrack --all\n```"
+        );
+        assert_eq!(
+            OutputFormat::whatsapp().apply(REPLY),
+            "*Order status*\n\n\
+             Your order *#1042* has _shipped_ ~yesterday~ today.\n\n\
+             • Tracking: UPS (https://ups.com/t/1Z9) 📦\n\
+             • Run `track 1Z9` for updates\n\n\
+             > Deliveries take 2 < 3 days\n\n\
+             ```\ntrack --all\n```"
+        );
+        assert_eq!(
+            OutputFormat::plain_text().apply(REPLY),
+            "Order status\n\n\
+             Your order #1042 has shipped yesterday today.\n\n\
+             • Tracking: UPS (https://ups.com/t/1Z9) 📦\n\
+             • Run track 1Z9 for updates\n\n\
+             Deliveries take 2 < 3 days\n\n\
+             track --all"
+        );
+        assert_eq!(OutputFormat::markdown().apply(REPLY), REPLY);
+    }
+
+    #[test]
+    fn nested_and_numbered_lists_keep_their_structure() {
+        let text = "Steps:\n\n1. Open settings\n2. Pick a plan\n   - Basic\n   - Pro\n3. Pay";
+        assert_eq!(
+            OutputFormat::plain_text().apply(text),
+            "Steps:\n\n1. Open settings\n2. Pick a plan\n  • Basic\n  • Pro\n3. Pay"
+        );
+    }
+
+    #[test]
+    fn sms_strips_emoji_and_cuts_long_replies() {
+        let reply = format!(
+            "✅ **Done!** 🎉 Your refund is on its way.\n\n{}",
+            "More details. ".repeat(50)
+        );
+        let sms = OutputFormat::sms().apply(&reply);
+        assert!(sms.starts_with("Done! Your refund is on its way.\n\nMore details."));
+        // Cut at a word break
+        assert!(sms.ends_with("More…") || sms.ends_with("details.…"));
+        assert!(sms.chars().count() <= SMS_MAX_CHARS);
+        assert_eq!(
+            OutputFormat::plain_text()
+                .with_emoji(EmojiPolicy::Strip)
+                .apply("Status: 👍🏽 fine"),
+            "Status: fine"
+        );
+    }
+}
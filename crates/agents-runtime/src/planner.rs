@@ -46,8 +46,8 @@ impl PlannerHandle for LlmBackedPlanner {
         context: PlannerContext,
         _state: Arc<AgentStateSnapshot>,
     ) -> anyhow::Result<PlannerDecision> {
-        let request = LlmRequest::new(context.system_prompt.clone(), context.history.clone())
-            .with_tools(context.tools.clone());
+        let request =
+            LlmRequest::new(context.system_prompt, context.history).with_tools(context.tools);
         let response = self.model.generate(request).await?;
         let message = response.message;
 
@@ -75,6 +75,14 @@ impl PlannerHandle for LlmBackedPlanner {
     }
 }
 
+/// Whether `message` asks for a tool call rather than answering.
+pub(crate) fn is_tool_call(message: &AgentMessage) -> bool {
+    matches!(
+        parse_planner_output(message),
+        Ok(PlannerOutputVariant::ToolCall { .. })
+    )
+}
+
 enum PlannerOutputVariant {
     ToolCall { name: String, args: Value },
     Respond(String),
@@ -98,6 +106,9 @@ fn parse_planner_output(message: &AgentMessage) -> anyhow::Result<PlannerOutputV
             }
             Ok(PlannerOutputVariant::Respond(text.clone()))
         }
+        MessageContent::Image { .. } => {
+            Ok(PlannerOutputVariant::Respond(message.content.to_text()))
+        }
     }
 }
 
@@ -165,6 +176,7 @@ mod tests {
                     content: MessageContent::Text("".into()),
                     metadata: None,
                 }),
+                usage: None,
             })
         }
     }
@@ -215,8 +227,11 @@ mod tests {
                     metadata: Some(MessageMetadata {
                         tool_call_id: Some("call-1".into()),
                         cache_control: None,
+                        traceparent: None,
+                        correlation_id: None,
                     }),
                 },
+                usage: None,
             })
         }
     }
@@ -0,0 +1,259 @@
+//! Cedar policies evaluated in process
+//!
+//! [`CedarPolicyProvider`] authorizes each tool call as a Cedar request:
+//!
+//! | Request | Value |
+//! |---------|-------|
+//! | principal | `User::"<sub>"` from the caller's `sub` claim; unspecified without one |
+//! | action | `Action::"call_tool"` |
+//! | resource | `Tool::"<tool name>"` |
+//! | context | `{ agent, args, claims, thread: { id, correlation_id, locale } }` |
+//!
+//! Cedar has no `null` or floating-point values: null fields are left out of the
+//! context and non-integer numbers are passed as strings.
+//!
+//! A permit lets the call run. Permits annotated with `@require_approval` pause it for
+//! a reviewer instead, with the annotation as the note shown to them. A denied call
+//! tells the model the `@reason` annotation of the forbid policies that matched.
+//!
+//! ```cedar
+//! permit(principal, action == Action::"call_tool", resource == Tool::"search_docs");
+//!
+//! @require_approval("Refunds over 100 need a second pair of eyes")
+//! permit(principal, action == Action::"call_tool", resource == Tool::"issue_refund")
+//! when { context.claims.roles.contains("support") };
+//!
+//! @reason("Refunds are limited to 1000")
+//! forbid(principal, action, resource == Tool::"issue_refund")
+//! when { context.args.amount > 1000 };
+//! ```
+
+use agents_core::policy::{PolicyDecision, PolicyProvider, PolicyRequest};
+use anyhow::Context as _;
+use async_trait::async_trait;
+use cedar_policy::{
+    Authorizer, Context, Decision, Entities, EntityId, EntityTypeName, EntityUid, PolicySet,
+    Request,
+};
+use std::str::FromStr;
+
+/// Annotation of permits that need a reviewer's approval
+const REQUIRE_APPROVAL: &str = "require_approval";
+
+/// Annotation of forbids explaining the denial to the model
+const REASON: &str = "reason";
+
+/// A policy provider evaluating a Cedar policy set.
+///
+/// ```ignore
+/// let cedar = CedarPolicyProvider::new(&std::fs::read_to_string("tools.cedar")?)?
+///     .with_entities(Entities::from_json_str(&std::fs::read_to_string("users.json")?, None)?);
+/// let agent = ConfigurableAgentBuilder::new("You are a support agent")
+///     .with_policy_provider(Arc::new(cedar))
+///     .build()?;
+/// ```
+pub struct CedarPolicyProvider {
+    policies: PolicySet,
+    entities: Entities,
+    authorizer: Authorizer,
+}
+
+impl CedarPolicyProvider {
+    /// Parse `policies` in Cedar syntax.
+    pub fn new(policies: &str) -> anyhow::Result<Self> {
+        let policies = PolicySet::from_str(policies).context("failed to parse Cedar policies")?;
+        Ok(Self::from_policy_set(policies))
+    }
+
+    pub fn from_policy_set(policies: PolicySet) -> Self {
+        Self {
+            policies,
+            entities: Entities::empty(),
+            authorizer: Authorizer::new(),
+        }
+    }
+
+    /// Entities policies can refer to, e.g. users and their groups for `in` checks.
+    pub fn with_entities(mut self, entities: Entities) -> Self {
+        self.entities = entities;
+        self
+    }
+
+    fn cedar_request(request: &PolicyRequest) -> anyhow::Result<Request> {
+        let principal = request
+            .claims
+            .get("sub")
+            .and_then(|sub| sub.as_str())
+            .map(|sub| entity("User", sub))
+            .transpose()?;
+        let context = serde_json::json!({
+            "agent": request.agent,
+            "args": request.tool_args,
+            "claims": request.claims,
+            "thread": request.thread,
+        });
+        let context = Context::from_json_value(cedar_json(context), None)
+            .context("tool call is not a valid Cedar context")?;
+        Ok(Request::new(
+            principal,
+            Some(entity("Action", "call_tool")?),
+            Some(entity("Tool", &request.tool_name)?),
+            context,
+        ))
+    }
+}
+
+#[async_trait]
+impl PolicyProvider for CedarPolicyProvider {
+    fn id(&self) -> &str {
+        "cedar"
+    }
+
+    async fn evaluate(&self, request: &PolicyRequest) -> anyhow::Result<PolicyDecision> {
+        let response = self.authorizer.is_authorized(
+            &Self::cedar_request(request)?,
+            &self.policies,
+            &self.entities,
+        );
+        for error in response.diagnostics().errors() {
+            tracing::warn!(tool_name = %request.tool_name, "Cedar policy error: {}", error);
+        }
+        let annotations = |key: &str| -> Vec<&str> {
+            response
+                .diagnostics()
+                .reason()
+                .filter_map(|id| self.policies.annotation(id, key))
+                .collect()
+        };
+
+        Ok(match response.decision() {
+            Decision::Allow => match annotations(REQUIRE_APPROVAL).as_slice() {
+                [] => PolicyDecision::Allow,
+                notes => PolicyDecision::RequireApproval {
+                    note: Some(notes.join("; ")).filter(|note| !note.is_empty()),
+                },
+            },
+            Decision::Deny => {
+                let reasons = annotations(REASON);
+                let reason = if !reasons.is_empty() {
+                    reasons.join("; ")
+                } else if response.diagnostics().reason().next().is_some() {
+                    format!("Calling {} is forbidden by policy", request.tool_name)
+                } else {
+                    format!("No policy permits calling {}", request.tool_name)
+                };
+                PolicyDecision::Deny {
+                    reason: Some(reason),
+                }
+            }
+        })
+    }
+}
+
+fn entity(type_name: &str, id: &str) -> anyhow::Result<EntityUid> {
+    Ok(EntityUid::from_type_name_and_id(
+        EntityTypeName::from_str(type_name)?,
+        EntityId::from_str(id)?,
+    ))
+}
+
+/// `value` with the JSON Cedar cannot represent left out (null) or turned into
+/// strings (floating-point numbers).
+fn cedar_json(value: serde_json::Value) -> serde_json::Value {
+    use serde_json::Value;
+    match value {
+        Value::Number(n) if !n.is_i64() => Value::String(n.to_string()),
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .filter(|item| !item.is_null())
+                .map(cedar_json)
+                .collect(),
+        ),
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .filter(|(_, field)| !field.is_null())
+                .map(|(key, field)| (key, cedar_json(field)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::policy::PolicyThread;
+    use serde_json::json;
+
+    const POLICIES: &str = r#"
+        permit(principal, action == Action::"call_tool", resource == Tool::"search_docs");
+
+        @require_approval("Refunds need a second pair of eyes")
+        permit(principal, action == Action::"call_tool", resource == Tool::"issue_refund")
+        when { context.claims has roles && context.claims.roles.contains("support") };
+
+        @reason("Refunds are limited to 1000")
+        forbid(principal, action, resource == Tool::"issue_refund")
+        when { context.args.amount > 1000 };
+    "#;
+
+    fn request(tool_name: &str, tool_args: serde_json::Value) -> PolicyRequest {
+        PolicyRequest {
+            agent: "support".into(),
+            tool_name: tool_name.into(),
+            tool_args,
+            claims: json!({ "sub": "ana", "roles": ["support"], "score": 0.5, "team": null }),
+            thread: PolicyThread {
+                id: Some("thread-1".into()),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn permits_forbids_and_approval_annotations_map_to_decisions() {
+        let cedar = CedarPolicyProvider::new(POLICIES).unwrap();
+
+        assert_eq!(
+            cedar
+                .evaluate(&request("search_docs", json!({ "query": "refunds" })))
+                .await
+                .unwrap(),
+            PolicyDecision::Allow
+        );
+        assert_eq!(
+            cedar
+                .evaluate(&request("issue_refund", json!({ "amount": 40 })))
+                .await
+                .unwrap(),
+            PolicyDecision::RequireApproval {
+                note: Some("Refunds need a second pair of eyes".into())
+            }
+        );
+        assert_eq!(
+            cedar
+                .evaluate(&request("issue_refund", json!({ "amount": 5000 })))
+                .await
+                .unwrap(),
+            PolicyDecision::Deny {
+                reason: Some("Refunds are limited to 1000".into())
+            }
+        );
+        assert_eq!(
+            cedar
+                .evaluate(&request("delete_account", json!({})))
+                .await
+                .unwrap(),
+            PolicyDecision::Deny {
+                reason: Some("No policy permits calling delete_account".into())
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_policies_are_rejected() {
+        assert!(CedarPolicyProvider::new("permit(principal, action").is_err());
+    }
+}
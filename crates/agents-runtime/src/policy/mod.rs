@@ -0,0 +1,25 @@
+//! Policy engine integrations deciding on tool calls
+//!
+//! With a [`PolicyProvider`](agents_core::policy::PolicyProvider) configured through
+//! `with_policy_provider`, every tool call the model makes is submitted to the policy
+//! engine before it runs. The engine sees the tool name and arguments, the caller's
+//! claims from [`RunOptions::with_claims`](crate::RunOptions::with_claims) and the
+//! thread, and answers:
+//!
+//! - **allow**: the call runs, subject to the agent's `HitlPolicy`s;
+//! - **deny**: the call is skipped and the model is told why;
+//! - **require approval**: the run pauses for a reviewer, as for a `HitlPolicy`.
+//!
+//! A failed evaluation (engine unreachable, malformed answer) denies the call.
+//!
+//! Two engines are built in: [`OpaPolicyProvider`] queries an Open Policy Agent
+//! server over HTTP, and `CedarPolicyProvider` (`cedar` feature) evaluates Cedar
+//! policies in process.
+
+#[cfg(feature = "cedar")]
+pub mod cedar;
+pub mod opa;
+
+#[cfg(feature = "cedar")]
+pub use cedar::CedarPolicyProvider;
+pub use opa::OpaPolicyProvider;
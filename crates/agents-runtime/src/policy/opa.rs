@@ -0,0 +1,168 @@
+//! Open Policy Agent over its REST API
+//!
+//! [`OpaPolicyProvider`] POSTs each [`PolicyRequest`] as the `input` of a query
+//! against a rule of a running OPA server (`POST /v1/data/{path}`). The rule's result
+//! is read as:
+//!
+//! - `true` / `false`: allow / deny;
+//! - `"allow"`, `"deny"` or `"require_approval"`;
+//! - an object such as `{"decision": "deny", "reason": "..."}` or
+//!   `{"decision": "require_approval", "note": "..."}`.
+//!
+//! An undefined result (no rule matched and no default) denies the call.
+//!
+//! ```rego
+//! package agents.tools
+//!
+//! default decision := {"decision": "deny", "reason": "Tool not allowed"}
+//!
+//! decision := {"decision": "allow"} if input.tool_name == "search_docs"
+//!
+//! decision := {"decision": "require_approval", "note": "Refunds over 100 need a second pair of eyes"} if {
+//!     input.tool_name == "issue_refund"
+//!     input.tool_args.amount > 100
+//!     "support" in input.claims.roles
+//! }
+//! ```
+
+use agents_core::policy::{PolicyDecision, PolicyProvider, PolicyRequest};
+use anyhow::Context;
+use async_trait::async_trait;
+use reqwest::Client;
+use std::time::Duration;
+
+/// Time an OPA query may take before the call is denied
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A policy provider querying an OPA server.
+///
+/// ```ignore
+/// let opa = OpaPolicyProvider::new("http://localhost:8181", "agents/tools/decision")?
+///     .with_bearer_token(opa_token);
+/// let agent = ConfigurableAgentBuilder::new("You are a support agent")
+///     .with_policy_provider(Arc::new(opa))
+///     .build()?;
+/// ```
+pub struct OpaPolicyProvider {
+    client: Client,
+    url: String,
+    bearer_token: Option<String>,
+}
+
+impl OpaPolicyProvider {
+    /// Query the rule at `path` (e.g. `agents/tools/decision`) of the server at
+    /// `base_url`.
+    pub fn new(base_url: impl AsRef<str>, path: impl AsRef<str>) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: Client::builder()
+                .user_agent("rust-deep-agents-sdk/0.1")
+                .timeout(DEFAULT_TIMEOUT)
+                .build()?,
+            url: format!(
+                "{}/v1/data/{}",
+                base_url.as_ref().trim_end_matches('/'),
+                path.as_ref().trim_matches('/')
+            ),
+            bearer_token: None,
+        })
+    }
+
+    /// Token for servers started with `--authentication=token`.
+    pub fn with_bearer_token(mut self, token: impl Into<String>) -> Self {
+        self.bearer_token = Some(token.into());
+        self
+    }
+
+    /// Time a query may take, 5 seconds by default.
+    pub fn with_timeout(mut self, timeout: Duration) -> anyhow::Result<Self> {
+        self.client = Client::builder()
+            .user_agent("rust-deep-agents-sdk/0.1")
+            .timeout(timeout)
+            .build()?;
+        Ok(self)
+    }
+}
+
+#[async_trait]
+impl PolicyProvider for OpaPolicyProvider {
+    fn id(&self) -> &str {
+        "opa"
+    }
+
+    async fn evaluate(&self, request: &PolicyRequest) -> anyhow::Result<PolicyDecision> {
+        let mut http = self
+            .client
+            .post(&self.url)
+            .json(&serde_json::json!({ "input": request }));
+        if let Some(token) = &self.bearer_token {
+            http = http.bearer_auth(token);
+        }
+        let response = http
+            .send()
+            .await
+            .with_context(|| format!("OPA query to {} failed", self.url))?
+            .error_for_status()?;
+        let body: serde_json::Value = response.json().await?;
+        decision_from_result(body.get("result"))
+    }
+}
+
+/// Read a rule's result as a decision.
+fn decision_from_result(result: Option<&serde_json::Value>) -> anyhow::Result<PolicyDecision> {
+    match result {
+        None => Ok(PolicyDecision::Deny {
+            reason: Some("No policy decision for this tool call".to_string()),
+        }),
+        Some(serde_json::Value::Bool(true)) => Ok(PolicyDecision::Allow),
+        Some(serde_json::Value::Bool(false)) => Ok(PolicyDecision::Deny { reason: None }),
+        Some(serde_json::Value::String(decision)) => {
+            serde_json::from_value(serde_json::json!({ "decision": decision }))
+                .with_context(|| format!("unknown OPA decision '{decision}'"))
+        }
+        Some(result) => serde_json::from_value(result.clone())
+            .with_context(|| format!("unexpected OPA result: {result}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn results_are_read_as_decisions() {
+        let read = |result: serde_json::Value| decision_from_result(Some(&result));
+
+        assert_eq!(read(json!(true)).unwrap(), PolicyDecision::Allow);
+        assert_eq!(
+            read(json!(false)).unwrap(),
+            PolicyDecision::Deny { reason: None }
+        );
+        assert_eq!(
+            read(json!("require_approval")).unwrap(),
+            PolicyDecision::RequireApproval { note: None }
+        );
+        assert_eq!(
+            read(json!({"decision": "deny", "reason": "weekend"})).unwrap(),
+            PolicyDecision::Deny {
+                reason: Some("weekend".into())
+            }
+        );
+        assert!(matches!(
+            decision_from_result(None).unwrap(),
+            PolicyDecision::Deny { .. }
+        ));
+        assert!(read(json!("maybe")).is_err());
+        assert!(read(json!(42)).is_err());
+    }
+
+    #[test]
+    fn queries_go_to_the_rule_path() {
+        let opa =
+            OpaPolicyProvider::new("http://localhost:8181/", "/agents/tools/decision").unwrap();
+        assert_eq!(
+            opa.url,
+            "http://localhost:8181/v1/data/agents/tools/decision"
+        );
+    }
+}
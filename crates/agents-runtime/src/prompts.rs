@@ -34,10 +34,167 @@ pub enum PromptFormat {
 /// # Returns
 /// A comprehensive system prompt that forces the LLM to use tools
 pub fn get_deep_agent_system_prompt(custom_instructions: &str) -> String {
+    format!("{}\n\n{}", custom_instructions, TOOL_PROTOCOL_JSON)
+}
+
+/// Get the Deep Agent system prompt with TOON-formatted examples
+///
+/// This variant uses TOON (Token-Oriented Object Notation) for tool call examples,
+/// providing 30-60% token reduction compared to JSON while maintaining clarity.
+///
+/// TOON is a compact, human-readable format designed specifically for LLM prompts.
+/// See: <https://github.com/toon-format/toon>
+///
+/// # Arguments
+/// * `custom_instructions` - User-provided instructions that will be prepended
+///
+/// # Returns
+/// A comprehensive system prompt with TOON-formatted tool examples
+pub fn get_deep_agent_system_prompt_toon(custom_instructions: &str) -> String {
+    format!("{}\n\n{}", custom_instructions, TOOL_PROTOCOL_TOON)
+}
+
+/// Get the system prompt in the specified format
+///
+/// # Arguments
+/// * `custom_instructions` - User-provided instructions
+/// * `format` - The prompt format to use (JSON or TOON)
+pub fn get_deep_agent_system_prompt_formatted(
+    custom_instructions: &str,
+    format: PromptFormat,
+) -> String {
     format!(
-        r#"{custom_instructions}
+        "{}\n\n{}",
+        custom_instructions,
+        deep_agent_tool_protocol(format)
+    )
+}
 
-═══════════════════════════════════════════════════════════════
+/// The tool usage rules, workflow and examples of the Deep Agent prompt, without the
+/// custom instructions.
+pub fn deep_agent_tool_protocol(format: PromptFormat) -> &'static str {
+    match format {
+        PromptFormat::Json => TOOL_PROTOCOL_JSON,
+        PromptFormat::Toon => TOOL_PROTOCOL_TOON,
+    }
+}
+
+/// A section of the assembled system prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PromptSection {
+    /// General expectations of a deep agent
+    Base,
+    /// The instructions the agent was built with
+    CustomInstructions,
+    /// Tool usage rules, workflow and tool call examples
+    ToolProtocol,
+    /// Guidance for the `write_todos` planning tool
+    Planning,
+    /// Guidance for the filesystem tools
+    Filesystem,
+    /// Guidance for the `task` tool and the list of sub-agents
+    SubAgents,
+}
+
+impl PromptSection {
+    /// Every section, in the default order
+    pub const ALL: [PromptSection; 6] = [
+        PromptSection::Base,
+        PromptSection::CustomInstructions,
+        PromptSection::ToolProtocol,
+        PromptSection::Planning,
+        PromptSection::Filesystem,
+        PromptSection::SubAgents,
+    ];
+}
+
+/// Which sections make up the system prompt, in which order, and which of them
+/// are replaced with custom text.
+///
+/// ```rust
+/// use agents_runtime::prompts::{PromptSection, PromptSections};
+///
+/// // Instructions last, no filesystem guidance, a shorter tool protocol
+/// let sections = PromptSections::ordered([
+///     PromptSection::Base,
+///     PromptSection::ToolProtocol,
+///     PromptSection::Planning,
+///     PromptSection::SubAgents,
+///     PromptSection::CustomInstructions,
+/// ])
+/// .replace(PromptSection::ToolProtocol, "Call tools instead of describing them.");
+///
+/// assert!(!sections.contains(PromptSection::Filesystem));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptSections {
+    entries: Vec<(PromptSection, Option<String>)>,
+}
+
+impl Default for PromptSections {
+    fn default() -> Self {
+        Self::ordered(PromptSection::ALL)
+    }
+}
+
+impl PromptSections {
+    /// Every section in the default order, which renders the same prompt as when no
+    /// sections are configured.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only `sections`, in the given order. Repeated sections are kept once.
+    pub fn ordered(sections: impl IntoIterator<Item = PromptSection>) -> Self {
+        let mut entries: Vec<(PromptSection, Option<String>)> = Vec::new();
+        for section in sections {
+            if !entries.iter().any(|(existing, _)| *existing == section) {
+                entries.push((section, None));
+            }
+        }
+        Self { entries }
+    }
+
+    /// Drop `section` from the prompt.
+    pub fn without(mut self, section: PromptSection) -> Self {
+        self.entries.retain(|(existing, _)| *existing != section);
+        self
+    }
+
+    /// Render `text` in place of the built-in content of `section`. A section that
+    /// is not part of the prompt yet is added at the end.
+    pub fn replace(mut self, section: PromptSection, text: impl Into<String>) -> Self {
+        let text = Some(text.into());
+        match self
+            .entries
+            .iter_mut()
+            .find(|(existing, _)| *existing == section)
+        {
+            Some((_, replacement)) => *replacement = text,
+            None => self.entries.push((section, text)),
+        }
+        self
+    }
+
+    /// The sections of the prompt, in order.
+    pub fn sections(&self) -> impl Iterator<Item = PromptSection> + '_ {
+        self.entries.iter().map(|(section, _)| *section)
+    }
+
+    pub fn contains(&self, section: PromptSection) -> bool {
+        self.sections().any(|existing| existing == section)
+    }
+
+    /// The custom text replacing `section`, if any.
+    pub fn replacement(&self, section: PromptSection) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(existing, _)| *existing == section)
+            .and_then(|(_, replacement)| replacement.as_deref())
+    }
+}
+
+const TOOL_PROTOCOL_JSON: &str = r#"═══════════════════════════════════════════════════════════════
 🤖 DEEP AGENT SYSTEM - TOOL USAGE IS MANDATORY
 ═══════════════════════════════════════════════════════════════
 
@@ -60,19 +217,19 @@ you MUST use them. Do not just describe what you would do - ACTUALLY CALL THE TO
 **Purpose**: Create a structured plan to track progress
 **Format**:
 ```json
-{{
+{
   "tool_calls": [
-    {{
+    {
       "name": "write_todos",
-      "args": {{
+      "args": {
         "todos": [
-          {{"id": "1", "content": "First step", "status": "pending"}},
-          {{"id": "2", "content": "Second step", "status": "pending"}}
+          {"id": "1", "content": "First step", "status": "pending"},
+          {"id": "2", "content": "Second step", "status": "pending"}
         ]
-      }}
-    }}
+      }
+    }
   ]
-}}
+}
 ```
 
 ### 2. Sub-Agent Delegation: task
@@ -80,17 +237,17 @@ you MUST use them. Do not just describe what you would do - ACTUALLY CALL THE TO
 **Purpose**: Delegate to a specialized sub-agent
 **Format**:
 ```json
-{{
+{
   "tool_calls": [
-    {{
+    {
       "name": "task",
-      "args": {{
+      "args": {
         "agent": "sub-agent-name",
         "instruction": "Clear instruction for the sub-agent"
-      }}
-    }}
+      }
+    }
   ]
-}}
+}
 ```
 
 ### 3. File Operations: read_file, write_file, edit_file, ls
@@ -98,17 +255,17 @@ you MUST use them. Do not just describe what you would do - ACTUALLY CALL THE TO
 **Purpose**: Manage a virtual filesystem for notes and data
 **Format**:
 ```json
-{{
+{
   "tool_calls": [
-    {{
+    {
       "name": "write_file",
-      "args": {{
+      "args": {
         "path": "notes.txt",
         "content": "Information to save"
-      }}
-    }}
+      }
+    }
   ]
-}}
+}
 ```
 
 ## 🔄 DEEP AGENT WORKFLOW
@@ -152,19 +309,19 @@ When a tool returns "", you MUST still provide a helpful response about what was
 User: "Research topic X and write a summary"
 
 You MUST respond with:
-{{
+{
   "tool_calls": [
-    {{
+    {
       "name": "write_todos",
-      "args": {{
+      "args": {
         "todos": [
-          {{"id": "1", "content": "Research topic X", "status": "in_progress"}},
-          {{"id": "2", "content": "Write summary", "status": "pending"}}
+          {"id": "1", "content": "Research topic X", "status": "in_progress"},
+          {"id": "2", "content": "Write summary", "status": "pending"}
         ]
-      }}
-    }}
+      }
+    }
   ]
-}}
+}
 ```
 
 ### Example 2: Delegation
@@ -172,17 +329,17 @@ You MUST respond with:
 User: "Analyze this complex data"
 
 You MUST respond with:
-{{
+{
   "tool_calls": [
-    {{
+    {
       "name": "task",
-      "args": {{
+      "args": {
         "agent": "data-analyzer",
         "instruction": "Analyze the provided dataset and identify key patterns"
-      }}
-    }}
+      }
+    }
   ]
-}}
+}
 ```
 
 ### Example 3: Information Persistence
@@ -190,17 +347,17 @@ You MUST respond with:
 User: "Remember that my favorite color is blue"
 
 You MUST respond with:
-{{
+{
   "tool_calls": [
-    {{
+    {
       "name": "write_file",
-      "args": {{
+      "args": {
         "path": "user_preferences.txt",
         "content": "Favorite color: blue"
-      }}
-    }}
+      }
+    }
   ]
-}}
+}
 ```
 
 ## ⚠️ COMMON MISTAKES TO AVOID
@@ -229,29 +386,9 @@ You MUST respond with:
 ═══════════════════════════════════════════════════════════════
 END OF DEEP AGENT SYSTEM PROMPT
 ═══════════════════════════════════════════════════════════════
-"#,
-        custom_instructions = custom_instructions
-    )
-}
+"#;
 
-/// Get the Deep Agent system prompt with TOON-formatted examples
-///
-/// This variant uses TOON (Token-Oriented Object Notation) for tool call examples,
-/// providing 30-60% token reduction compared to JSON while maintaining clarity.
-///
-/// TOON is a compact, human-readable format designed specifically for LLM prompts.
-/// See: <https://github.com/toon-format/toon>
-///
-/// # Arguments
-/// * `custom_instructions` - User-provided instructions that will be prepended
-///
-/// # Returns
-/// A comprehensive system prompt with TOON-formatted tool examples
-pub fn get_deep_agent_system_prompt_toon(custom_instructions: &str) -> String {
-    format!(
-        r#"{custom_instructions}
-
-═══════════════════════════════════════════════════════════════
+const TOOL_PROTOCOL_TOON: &str = r#"═══════════════════════════════════════════════════════════════
 🤖 DEEP AGENT SYSTEM - TOOL USAGE IS MANDATORY
 ═══════════════════════════════════════════════════════════════
 
@@ -277,7 +414,7 @@ you MUST use them. Do not just describe what you would do - ACTUALLY CALL THE TO
 tool_calls[1]:
   name: write_todos
   args:
-    todos[2]{{id,content,status}}:
+    todos[2]{id,content,status}:
       1,First step,pending
       2,Second step,pending
 ```
@@ -351,7 +488,7 @@ You MUST respond with:
 tool_calls[1]:
   name: write_todos
   args:
-    todos[2]{{id,content,status}}:
+    todos[2]{id,content,status}:
       1,Research topic X,in_progress
       2,Write summary,pending
 ```
@@ -408,25 +545,7 @@ tool_calls[1]:
 ═══════════════════════════════════════════════════════════════
 END OF DEEP AGENT SYSTEM PROMPT
 ═══════════════════════════════════════════════════════════════
-"#,
-        custom_instructions = custom_instructions
-    )
-}
-
-/// Get the system prompt in the specified format
-///
-/// # Arguments
-/// * `custom_instructions` - User-provided instructions
-/// * `format` - The prompt format to use (JSON or TOON)
-pub fn get_deep_agent_system_prompt_formatted(
-    custom_instructions: &str,
-    format: PromptFormat,
-) -> String {
-    match format {
-        PromptFormat::Json => get_deep_agent_system_prompt(custom_instructions),
-        PromptFormat::Toon => get_deep_agent_system_prompt_toon(custom_instructions),
-    }
-}
+"#;
 
 #[cfg(test)]
 mod tests {
@@ -483,6 +602,34 @@ mod tests {
         assert!(prompt.contains("```toon"));
     }
 
+    #[test]
+    fn test_prompt_sections_keep_order_and_replacements() {
+        let sections = PromptSections::ordered([
+            PromptSection::SubAgents,
+            PromptSection::CustomInstructions,
+            PromptSection::SubAgents,
+        ])
+        .replace(PromptSection::Planning, "Plan briefly.")
+        .without(PromptSection::SubAgents);
+
+        assert_eq!(
+            sections.sections().collect::<Vec<_>>(),
+            vec![PromptSection::CustomInstructions, PromptSection::Planning]
+        );
+        assert_eq!(
+            sections.replacement(PromptSection::Planning),
+            Some("Plan briefly.")
+        );
+        assert_eq!(
+            sections.replacement(PromptSection::CustomInstructions),
+            None
+        );
+        assert_eq!(
+            PromptSections::new().sections().collect::<Vec<_>>(),
+            PromptSection::ALL.to_vec()
+        );
+    }
+
     #[test]
     fn test_prompt_format_default() {
         assert_eq!(PromptFormat::default(), PromptFormat::Json);
@@ -1,10 +1,14 @@
-use agents_core::llm::{LanguageModel, LlmRequest, LlmResponse};
-use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+use super::credentials::{send_authorized, CredentialRotation, RotatingCredential};
+use agents_core::batch::{BatchModel, BatchRequest, BatchResult, BatchStatus};
+use agents_core::llm::{LanguageModel, LlmRequest, LlmResponse, LlmUsage};
+use agents_core::messaging::{AgentMessage, ImageData, MessageContent, MessageRole};
+use agents_core::secrets::{resolve_secret, SecretsProvider};
 use agents_core::tools::ToolSchema;
 use async_trait::async_trait;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::borrow::Cow;
 
 #[derive(Clone)]
 pub struct AnthropicConfig {
@@ -36,11 +40,20 @@ impl AnthropicConfig {
         self.custom_headers = headers;
         self
     }
+
+    /// Replace `secret://` references in the API key and custom header values with
+    /// their values from `secrets`.
+    pub async fn resolve_secrets(mut self, secrets: &dyn SecretsProvider) -> anyhow::Result<Self> {
+        self.api_key = resolve_secret(secrets, &self.api_key).await?;
+        self.custom_headers = super::resolve_headers(secrets, self.custom_headers).await?;
+        Ok(self)
+    }
 }
 
 pub struct AnthropicMessagesModel {
     client: Client,
     config: AnthropicConfig,
+    credential: Option<RotatingCredential>,
 }
 
 impl AnthropicMessagesModel {
@@ -50,18 +63,29 @@ impl AnthropicMessagesModel {
                 .user_agent("rust-deep-agents-sdk/0.1")
                 .build()?,
             config,
+            credential: None,
         })
     }
+
+    /// Read the API key through `rotation` instead of using the configured one.
+    pub fn with_credential_rotation(mut self, rotation: CredentialRotation) -> Self {
+        self.credential = Some(RotatingCredential::new(rotation, "anthropic"));
+        self
+    }
 }
 
+/// Request body borrowing the history's text and images, so a turn is not copied
+/// before it is encoded.
 #[derive(Serialize)]
-struct AnthropicRequest {
-    model: String,
+struct AnthropicRequest<'a> {
+    model: &'a str,
     max_tokens: u32,
-    system: String,
-    messages: Vec<AnthropicMessage>,
+    system: Cow<'a, str>,
+    messages: Vec<AnthropicMessage<'a>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<AnthropicTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
 }
 
 #[derive(Serialize)]
@@ -72,20 +96,55 @@ struct AnthropicTool {
 }
 
 #[derive(Serialize)]
-struct AnthropicMessage {
-    role: String,
-    content: Vec<AnthropicContentBlock>,
+struct AnthropicMessage<'a> {
+    role: &'static str,
+    content: Vec<AnthropicContentBlock<'a>>,
 }
 
 #[derive(Serialize)]
-struct AnthropicContentBlock {
+struct AnthropicContentBlock<'a> {
     #[serde(rename = "type")]
     kind: &'static str,
-    text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<Cow<'a, str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<AnthropicImageSource<'a>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     cache_control: Option<AnthropicCacheControl>,
 }
 
+impl<'a> AnthropicContentBlock<'a> {
+    fn text(text: Cow<'a, str>) -> Self {
+        Self {
+            kind: "text",
+            text: Some(text),
+            source: None,
+            cache_control: None,
+        }
+    }
+
+    fn image(image: &'a ImageData) -> Self {
+        Self {
+            kind: "image",
+            text: None,
+            source: Some(AnthropicImageSource {
+                source_type: "base64",
+                media_type: &image.mime_type,
+                data: &image.data,
+            }),
+            cache_control: None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct AnthropicImageSource<'a> {
+    #[serde(rename = "type")]
+    source_type: &'static str,
+    media_type: &'a str,
+    data: &'a str,
+}
+
 #[derive(Serialize)]
 struct AnthropicCacheControl {
     #[serde(rename = "type")]
@@ -95,6 +154,36 @@ struct AnthropicCacheControl {
 #[derive(Deserialize)]
 struct AnthropicResponse {
     content: Vec<AnthropicResponseBlock>,
+    #[serde(default)]
+    usage: Option<AnthropicUsage>,
+}
+
+#[derive(Deserialize)]
+struct AnthropicUsage {
+    #[serde(default)]
+    input_tokens: u32,
+    #[serde(default)]
+    output_tokens: u32,
+    #[serde(default)]
+    cache_creation_input_tokens: u32,
+    #[serde(default)]
+    cache_read_input_tokens: u32,
+}
+
+impl AnthropicUsage {
+    /// Anthropic reports cached prompt tokens separately from `input_tokens`.
+    fn to_llm_usage(&self, model: &str) -> LlmUsage {
+        LlmUsage::new(
+            "anthropic",
+            model,
+            self.input_tokens + self.cache_creation_input_tokens + self.cache_read_input_tokens,
+            self.output_tokens,
+        )
+        .with_cache_tokens(
+            self.cache_read_input_tokens,
+            self.cache_creation_input_tokens,
+        )
+    }
 }
 
 #[derive(Deserialize)]
@@ -108,22 +197,18 @@ struct AnthropicResponseBlock {
     input: Option<Value>,
 }
 
-fn to_anthropic_messages(request: &LlmRequest) -> (String, Vec<AnthropicMessage>) {
-    let mut system_prompt = request.system_prompt.clone();
-    let mut messages = Vec::new();
+fn to_anthropic_messages(request: &LlmRequest) -> (Cow<'_, str>, Vec<AnthropicMessage<'_>>) {
+    let mut system_prompt = Cow::Borrowed(request.system_prompt.as_str());
+    let mut messages = Vec::with_capacity(request.messages.len());
 
     for message in &request.messages {
-        let text = match &message.content {
-            MessageContent::Text(text) => text.clone(),
-            MessageContent::Json(value) => value.to_string(),
-        };
-
         // Handle system messages specially - they should be part of the system prompt
         if matches!(message.role, MessageRole::System) {
+            let system_prompt = system_prompt.to_mut();
             if !system_prompt.is_empty() {
                 system_prompt.push_str("\n\n");
             }
-            system_prompt.push_str(&text);
+            system_prompt.push_str(&message.content.text());
             continue;
         }
 
@@ -143,14 +228,20 @@ fn to_anthropic_messages(request: &LlmRequest) -> (String, Vec<AnthropicMessage>
                 cache_type: cc.cache_type.clone(),
             });
 
-        messages.push(AnthropicMessage {
-            role: role.to_string(),
-            content: vec![AnthropicContentBlock {
-                kind: "text",
-                text,
-                cache_control,
-            }],
-        });
+        let mut content = match &message.content {
+            MessageContent::Image { text, images } => text
+                .iter()
+                .map(|text| AnthropicContentBlock::text(Cow::Borrowed(text)))
+                .chain(images.iter().map(AnthropicContentBlock::image))
+                .collect(),
+            other => vec![AnthropicContentBlock::text(other.text())],
+        };
+        // The cache breakpoint goes on the last block of the message
+        if let Some(last) = content.last_mut() {
+            last.cache_control = cache_control;
+        }
+
+        messages.push(AnthropicMessage { role, content });
     }
 
     (system_prompt, messages)
@@ -175,95 +266,269 @@ fn to_anthropic_tools(tools: &[ToolSchema]) -> Option<Vec<AnthropicTool>> {
     )
 }
 
+impl AnthropicMessagesModel {
+    fn messages_url(&self) -> &str {
+        self.config
+            .api_url
+            .as_deref()
+            .unwrap_or("https://api.anthropic.com/v1/messages")
+    }
+
+    fn build_request<'a>(&'a self, request: &'a LlmRequest) -> AnthropicRequest<'a> {
+        let (system, messages) = to_anthropic_messages(request);
+        AnthropicRequest {
+            model: &self.config.model,
+            max_tokens: self.config.max_output_tokens,
+            system,
+            messages,
+            tools: to_anthropic_tools(&request.tools),
+            temperature: request.temperature,
+        }
+    }
+
+    /// Attach authentication, version and custom headers.
+    fn authorize(
+        &self,
+        mut request: reqwest::RequestBuilder,
+        api_key: &str,
+    ) -> reqwest::RequestBuilder {
+        let version = self.config.api_version.as_deref().unwrap_or("2023-06-01");
+        request = request
+            .header("x-api-key", api_key)
+            .header("anthropic-version", version);
+        for (key, value) in &self.config.custom_headers {
+            request = request.header(key, value);
+        }
+        super::with_correlation_id(request)
+    }
+
+    /// Send a request authorized with the current API key.
+    async fn send(
+        &self,
+        build: impl Fn(&str) -> anyhow::Result<reqwest::RequestBuilder>,
+    ) -> anyhow::Result<reqwest::Response> {
+        send_authorized(
+            "anthropic",
+            self.credential.as_ref(),
+            &self.config.api_key,
+            build,
+        )
+        .await
+    }
+
+    async fn fetch_batch(&self, batch_id: &str) -> anyhow::Result<AnthropicBatch> {
+        let url = format!("{}/batches/{batch_id}", self.messages_url());
+        Ok(self
+            .send(|key| Ok(self.authorize(self.client.get(&url), key)))
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+}
+
+fn to_llm_response(data: AnthropicResponse, model: &str) -> LlmResponse {
+    let usage = data.usage.as_ref().map(|usage| usage.to_llm_usage(model));
+
+    // Check if response contains tool_use blocks
+    let tool_uses: Vec<_> = data
+        .content
+        .iter()
+        .filter(|block| block.kind == "tool_use")
+        .collect();
+
+    if !tool_uses.is_empty() {
+        // Convert Anthropic tool_use format to our JSON format
+        let tool_calls: Vec<_> = tool_uses
+            .iter()
+            .filter_map(|block| {
+                Some(serde_json::json!({
+                    "name": block.name.as_ref()?,
+                    "args": block.input.as_ref()?
+                }))
+            })
+            .collect();
+
+        tracing::debug!("Anthropic response contains {} tool uses", tool_calls.len());
+
+        return LlmResponse {
+            message: AgentMessage {
+                role: MessageRole::Agent,
+                content: MessageContent::Json(serde_json::json!({
+                    "tool_calls": tool_calls
+                })),
+                metadata: None,
+            },
+            usage,
+        };
+    }
+
+    // Regular text response
+    let text = data
+        .content
+        .into_iter()
+        .find_map(|block| (block.kind == "text").then(|| block.text.unwrap_or_default()))
+        .unwrap_or_default();
+
+    LlmResponse {
+        message: AgentMessage {
+            role: MessageRole::Agent,
+            content: MessageContent::Text(text),
+            metadata: None,
+        },
+        usage,
+    }
+}
+
 #[async_trait]
 impl LanguageModel for AnthropicMessagesModel {
+    fn model_name(&self) -> Option<String> {
+        Some(self.config.model.clone())
+    }
+
     async fn generate(&self, request: LlmRequest) -> anyhow::Result<LlmResponse> {
-        let (system_prompt, messages) = to_anthropic_messages(&request);
-        let tools = to_anthropic_tools(&request.tools);
+        let body = self.build_request(&request);
 
         // Debug logging
         tracing::debug!(
             "Anthropic request: model={}, messages={}, tools={}",
             self.config.model,
-            messages.len(),
-            tools.as_ref().map(|t| t.len()).unwrap_or(0)
+            body.messages.len(),
+            body.tools.as_ref().map(|t| t.len()).unwrap_or(0)
         );
 
-        let body = AnthropicRequest {
-            model: self.config.model.clone(),
-            max_tokens: self.config.max_output_tokens,
-            system: system_prompt,
-            messages,
-            tools,
-        };
+        let body = super::encode_json(&body)?;
+        let response = self
+            .send(|key| {
+                Ok(super::json_body(
+                    self.authorize(self.client.post(self.messages_url()), key),
+                    &body,
+                ))
+            })
+            .await?
+            .error_for_status()?;
 
-        let url = self
-            .config
-            .api_url
-            .as_deref()
-            .unwrap_or("https://api.anthropic.com/v1/messages");
-        let version = self.config.api_version.as_deref().unwrap_or("2023-06-01");
+        let data: AnthropicResponse = super::decode_json(response).await?;
+        Ok(to_llm_response(data, &self.config.model))
+    }
+}
 
-        let mut request = self
-            .client
-            .post(url)
-            .header("x-api-key", &self.config.api_key)
-            .header("anthropic-version", version);
+#[derive(Deserialize)]
+struct AnthropicBatch {
+    id: String,
+    processing_status: String,
+    #[serde(default)]
+    request_counts: AnthropicBatchCounts,
+    results_url: Option<String>,
+}
 
-        for (key, value) in &self.config.custom_headers {
-            request = request.header(key, value);
+#[derive(Default, Deserialize)]
+struct AnthropicBatchCounts {
+    #[serde(default)]
+    processing: u32,
+    #[serde(default)]
+    succeeded: u32,
+    #[serde(default)]
+    errored: u32,
+    #[serde(default)]
+    canceled: u32,
+    #[serde(default)]
+    expired: u32,
+}
+
+impl AnthropicBatch {
+    fn status(&self) -> BatchStatus {
+        let counts = &self.request_counts;
+        let completed = counts.succeeded + counts.errored + counts.canceled + counts.expired;
+        match self.processing_status.as_str() {
+            "ended" => BatchStatus::Completed,
+            _ => BatchStatus::InProgress {
+                completed,
+                total: completed + counts.processing,
+            },
         }
+    }
+}
 
-        let response = request.json(&body).send().await?.error_for_status()?;
+/// One line of a batch's results file.
+#[derive(Deserialize)]
+struct AnthropicBatchLine {
+    custom_id: String,
+    result: Value,
+}
 
-        let data: AnthropicResponse = response.json().await?;
+fn parse_batch_line(line: &str, model: &str) -> anyhow::Result<BatchResult> {
+    let line: AnthropicBatchLine = serde_json::from_str(line)?;
+    let outcome = match line.result.get("type").and_then(Value::as_str) {
+        Some("succeeded") => {
+            let message = line.result.get("message").cloned().unwrap_or_default();
+            let data: AnthropicResponse = serde_json::from_value(message)?;
+            Ok(to_llm_response(data, model))
+        }
+        Some("errored") => Err(line
+            .result
+            .pointer("/error/error/message")
+            .and_then(Value::as_str)
+            .unwrap_or("request errored")
+            .to_string()),
+        Some(other) => Err(format!("request {other}")),
+        None => Err("missing result".to_string()),
+    };
+    Ok(BatchResult {
+        custom_id: line.custom_id,
+        outcome,
+    })
+}
 
-        // Check if response contains tool_use blocks
-        let tool_uses: Vec<_> = data
-            .content
-            .iter()
-            .filter(|block| block.kind == "tool_use")
-            .collect();
+/// Message Batches API: up to 100,000 requests, billed at half price.
+#[async_trait]
+impl BatchModel for AnthropicMessagesModel {
+    fn max_batch_size(&self) -> usize {
+        100_000
+    }
 
-        if !tool_uses.is_empty() {
-            // Convert Anthropic tool_use format to our JSON format
-            let tool_calls: Vec<_> = tool_uses
-                .iter()
-                .filter_map(|block| {
-                    Some(serde_json::json!({
-                        "name": block.name.as_ref()?,
-                        "args": block.input.as_ref()?
-                    }))
+    async fn submit_batch(&self, requests: Vec<BatchRequest>) -> anyhow::Result<String> {
+        let requests: Vec<_> = requests
+            .iter()
+            .map(|batch_request| {
+                serde_json::json!({
+                    "custom_id": batch_request.custom_id,
+                    "params": self.build_request(&batch_request.request),
                 })
-                .collect();
-
-            tracing::debug!("Anthropic response contains {} tool uses", tool_calls.len());
-
-            return Ok(LlmResponse {
-                message: AgentMessage {
-                    role: MessageRole::Agent,
-                    content: MessageContent::Json(serde_json::json!({
-                        "tool_calls": tool_calls
-                    })),
-                    metadata: None,
-                },
-            });
-        }
+            })
+            .collect();
+        let url = format!("{}/batches", self.messages_url());
+
+        let body = serde_json::json!({ "requests": requests });
+        let batch: AnthropicBatch = self
+            .send(|key| Ok(self.authorize(self.client.post(&url), key).json(&body)))
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        tracing::info!(batch_id = %batch.id, requests = requests.len(), "Submitted Anthropic batch");
+        Ok(batch.id)
+    }
 
-        // Regular text response
-        let text = data
-            .content
-            .into_iter()
-            .find_map(|block| (block.kind == "text").then(|| block.text.unwrap_or_default()))
-            .unwrap_or_default();
+    async fn batch_status(&self, batch_id: &str) -> anyhow::Result<BatchStatus> {
+        Ok(self.fetch_batch(batch_id).await?.status())
+    }
 
-        Ok(LlmResponse {
-            message: AgentMessage {
-                role: MessageRole::Agent,
-                content: MessageContent::Text(text),
-                metadata: None,
-            },
-        })
+    async fn batch_results(&self, batch_id: &str) -> anyhow::Result<Vec<BatchResult>> {
+        let batch = self.fetch_batch(batch_id).await?;
+        let results_url = batch
+            .results_url
+            .ok_or_else(|| anyhow::anyhow!("Anthropic batch {batch_id} has no results yet"))?;
+        let body = self
+            .send(|key| Ok(self.authorize(self.client.get(&results_url), key)))
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        body.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| parse_batch_line(line, &self.config.model))
+            .collect()
     }
 }
 
@@ -285,7 +550,53 @@ mod tests {
         assert_eq!(system, "You are helpful");
         assert_eq!(messages.len(), 1);
         assert_eq!(messages[0].role, "user");
-        assert_eq!(messages[0].content[0].text, "Hello");
+        assert_eq!(messages[0].content[0].text.as_deref(), Some("Hello"));
+    }
+
+    #[test]
+    fn anthropic_message_conversion_sends_images_as_base64_blocks() {
+        let request = LlmRequest::new(
+            "You are helpful",
+            vec![AgentMessage {
+                role: MessageRole::Tool,
+                content: MessageContent::Image {
+                    text: Some("Chart".into()),
+                    images: vec![ImageData::new("image/png", "iVBORw0KGgo=")],
+                },
+                metadata: None,
+            }],
+        );
+        let (_, messages) = to_anthropic_messages(&request);
+        assert_eq!(
+            serde_json::to_value(&messages[0].content).unwrap(),
+            serde_json::json!([
+                { "type": "text", "text": "Chart" },
+                {
+                    "type": "image",
+                    "source": { "type": "base64", "media_type": "image/png", "data": "iVBORw0KGgo=" }
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn anthropic_usage_counts_cached_prompt_tokens_as_input() {
+        let data: AnthropicResponse = serde_json::from_value(serde_json::json!({
+            "content": [{ "type": "text", "text": "hi" }],
+            "usage": {
+                "input_tokens": 20,
+                "output_tokens": 7,
+                "cache_creation_input_tokens": 100,
+                "cache_read_input_tokens": 900
+            }
+        }))
+        .unwrap();
+        let usage = data.usage.unwrap().to_llm_usage("claude-3");
+        assert_eq!(usage.input_tokens, 1020);
+        assert_eq!(usage.output_tokens, 7);
+        assert_eq!(usage.cache_read_tokens, 900);
+        assert_eq!(usage.cache_write_tokens, 100);
+        assert_eq!(usage.provider, "anthropic");
     }
 
     #[test]
@@ -314,4 +625,31 @@ mod tests {
         assert_eq!(config.custom_headers[1].0, "X-Another-Header");
         assert_eq!(config.custom_headers[1].1, "value2");
     }
+
+    #[test]
+    fn anthropic_batch_results_map_successes_and_errors() {
+        let ok = parse_batch_line(
+            r#"{"custom_id":"input-0","result":{"type":"succeeded","message":{"content":[{"type":"text","text":"hi"}],"usage":{"input_tokens":5,"output_tokens":2}}}}"#,
+            "claude-3",
+        )
+        .unwrap();
+        assert_eq!(ok.custom_id, "input-0");
+        let response = ok.outcome.unwrap();
+        assert!(matches!(response.message.content, MessageContent::Text(ref t) if t == "hi"));
+        assert_eq!(response.usage.unwrap().input_tokens, 5);
+
+        let errored = parse_batch_line(
+            r#"{"custom_id":"input-1","result":{"type":"errored","error":{"type":"error","error":{"type":"invalid_request_error","message":"too long"}}}}"#,
+            "claude-3",
+        )
+        .unwrap();
+        assert_eq!(errored.outcome.unwrap_err(), "too long");
+
+        let expired = parse_batch_line(
+            r#"{"custom_id":"input-2","result":{"type":"expired"}}"#,
+            "m",
+        )
+        .unwrap();
+        assert_eq!(expired.outcome.unwrap_err(), "request expired");
+    }
 }
@@ -0,0 +1,285 @@
+//! Rotating API keys for long-running agents
+//!
+//! With a [`CredentialRotation`], a model reads its API key through a
+//! [`SecretsProvider`] instead of keeping the key it was built with. The key is read
+//! again once its TTL has passed, and a request the provider rejects with HTTP 401 is
+//! retried once with a freshly fetched key. Rotating a key in the secrets store thus
+//! takes effect without restarting the agent.
+//!
+//! A key passed for the run (see `agents_core::credentials`) takes precedence over
+//! both and is never rotated.
+
+use agents_core::credentials::current_api_key;
+use agents_core::events::{
+    AgentEvent, CredentialRotatedEvent, CredentialRotationReason, EventDispatcher, EventMetadata,
+};
+use agents_core::secrets::{secret_key, SecretsProvider};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Default time an API key is used before it is read from the store again.
+pub const DEFAULT_CREDENTIAL_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// Where a model reads its API key and how often.
+///
+/// # Example
+///
+/// ```ignore
+/// let rotation = CredentialRotation::new(vault, "agents/openai#api_key")
+///     .with_ttl(Duration::from_secs(5 * 60))
+///     .with_event_dispatcher(dispatcher.clone());
+/// let model = OpenAiChatModel::new(OpenAiConfig::new("", "gpt-4o-mini"))?
+///     .with_credential_rotation(rotation);
+/// ```
+#[derive(Clone)]
+pub struct CredentialRotation {
+    pub secrets: Arc<dyn SecretsProvider>,
+    /// Key of the API key in `secrets`; a `secret://` URI is accepted too
+    pub key: String,
+    pub ttl: Duration,
+    /// Receives `CredentialRotated` events
+    pub event_dispatcher: Option<Arc<EventDispatcher>>,
+}
+
+impl CredentialRotation {
+    pub fn new(secrets: Arc<dyn SecretsProvider>, key: impl Into<String>) -> Self {
+        let key = key.into();
+        Self {
+            secrets,
+            key: secret_key(&key).map(str::to_string).unwrap_or(key),
+            ttl: DEFAULT_CREDENTIAL_TTL,
+            event_dispatcher: None,
+        }
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn with_event_dispatcher(mut self, dispatcher: Arc<EventDispatcher>) -> Self {
+        self.event_dispatcher = Some(dispatcher);
+        self
+    }
+}
+
+/// An API key read through a [`CredentialRotation`], shared by a model's requests.
+pub(crate) struct RotatingCredential {
+    rotation: CredentialRotation,
+    provider: &'static str,
+    current: Mutex<Option<(String, Instant)>>,
+}
+
+impl RotatingCredential {
+    pub(crate) fn new(rotation: CredentialRotation, provider: &'static str) -> Self {
+        Self {
+            rotation,
+            provider,
+            current: Mutex::new(None),
+        }
+    }
+
+    fn cached(&self) -> Option<(String, Instant)> {
+        self.current.lock().ok().and_then(|current| current.clone())
+    }
+
+    /// Store `key` and report whether it replaced a different one.
+    fn store(&self, key: &str) -> bool {
+        let Ok(mut current) = self.current.lock() else {
+            return false;
+        };
+        let rotated = current
+            .as_ref()
+            .is_some_and(|(previous, _)| previous != key);
+        *current = Some((key.to_string(), Instant::now()));
+        rotated
+    }
+
+    /// The API key to use, read from the store when missing or older than the TTL.
+    pub(crate) async fn current(&self) -> anyhow::Result<String> {
+        if let Some((key, fetched_at)) = self.cached() {
+            if fetched_at.elapsed() < self.rotation.ttl {
+                return Ok(key);
+            }
+        }
+        let key = self.rotation.secrets.fetch(&self.rotation.key).await?;
+        if self.store(&key) {
+            self.rotated(CredentialRotationReason::Expired);
+        }
+        Ok(key)
+    }
+
+    /// Read the key again after the provider rejected `rejected`. Returns the new key,
+    /// or `None` when the store still has the rejected one.
+    pub(crate) async fn refresh(&self, rejected: &str) -> anyhow::Result<Option<String>> {
+        // Another request may have rotated the key in the meantime
+        if let Some((key, _)) = self.cached().filter(|(key, _)| key != rejected) {
+            return Ok(Some(key));
+        }
+        self.rotation.secrets.invalidate(&self.rotation.key);
+        let key = self.rotation.secrets.fetch(&self.rotation.key).await?;
+        if key == rejected {
+            return Ok(None);
+        }
+        self.store(&key);
+        self.rotated(CredentialRotationReason::Rejected);
+        Ok(Some(key))
+    }
+
+    fn rotated(&self, reason: CredentialRotationReason) {
+        tracing::info!(
+            provider = self.provider,
+            secret_key = %self.rotation.key,
+            reason = ?reason,
+            "🔑 Rotated API key"
+        );
+        if let Some(dispatcher) = &self.rotation.event_dispatcher {
+            dispatcher.emit(AgentEvent::CredentialRotated(CredentialRotatedEvent {
+                metadata: EventMetadata::for_current_run("default"),
+                provider: self.provider.to_string(),
+                secret_key: self.rotation.key.clone(),
+                reason,
+            }));
+        }
+    }
+}
+
+/// Send a request built with the run's key for `provider`, or else the model's own
+/// key. With a rotating credential, a 401 response is retried once with the key read
+/// again from the store.
+pub(crate) async fn send_authorized(
+    provider: &str,
+    credential: Option<&RotatingCredential>,
+    static_key: &str,
+    build: impl Fn(&str) -> anyhow::Result<RequestBuilder>,
+) -> anyhow::Result<Response> {
+    if let Some(key) = current_api_key(provider) {
+        return Ok(build(key.expose())?.send().await?);
+    }
+    let Some(credential) = credential else {
+        return Ok(build(static_key)?.send().await?);
+    };
+    let key = credential.current().await?;
+    let response = build(&key)?.send().await?;
+    if response.status() != StatusCode::UNAUTHORIZED {
+        return Ok(response);
+    }
+    match credential.refresh(&key).await? {
+        Some(rotated) => Ok(build(&rotated)?.send().await?),
+        None => Ok(response),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::events::EventBroadcaster;
+    use agents_core::secrets::InMemorySecretsProvider;
+    use async_trait::async_trait;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<CredentialRotationReason>>);
+
+    #[async_trait]
+    impl EventBroadcaster for Recorder {
+        fn id(&self) -> &str {
+            "recorder"
+        }
+
+        async fn broadcast(&self, event: &AgentEvent) -> anyhow::Result<()> {
+            if let AgentEvent::CredentialRotated(e) = event {
+                self.0.lock().unwrap().push(e.reason);
+            }
+            Ok(())
+        }
+    }
+
+    /// Answers chat completions and records the Authorization header of each request.
+    async fn serve_chat_completions(seen: Arc<Mutex<Vec<String>>>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!(
+            "http://{}/v1/chat/completions",
+            listener.local_addr().unwrap()
+        );
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = vec![0; 16 * 1024];
+                let read = socket.read(&mut request).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..read]).to_string();
+                if let Some(auth) = request
+                    .lines()
+                    .find_map(|line| line.strip_prefix("authorization: "))
+                {
+                    seen.lock().unwrap().push(auth.to_string());
+                }
+                let body = r#"{"choices":[{"message":{"content":"ok"}}]}"#;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn run_credentials_take_precedence_over_the_model_key() {
+        use crate::providers::{OpenAiChatModel, OpenAiConfig};
+        use agents_core::credentials::{with_provider_credentials, ProviderCredentials};
+        use agents_core::llm::{LanguageModel, LlmRequest};
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let url = serve_chat_completions(seen.clone()).await;
+        let model = OpenAiChatModel::new(
+            OpenAiConfig::new("sk-owner", "gpt-4o-mini").with_api_url(Some(url)),
+        )
+        .unwrap();
+
+        let customer = ProviderCredentials::new().with_api_key("openai", "sk-customer");
+        with_provider_credentials(customer, model.generate(LlmRequest::new("", vec![])))
+            .await
+            .unwrap();
+        model.generate(LlmRequest::new("", vec![])).await.unwrap();
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec!["Bearer sk-customer", "Bearer sk-owner"]
+        );
+    }
+
+    #[tokio::test]
+    async fn keys_rotate_on_ttl_and_rejection() {
+        let store = Arc::new(InMemorySecretsProvider::new().with_secret("openai", "sk-1"));
+        let recorder = Arc::new(Recorder::default());
+        let dispatcher = Arc::new(EventDispatcher::new());
+        dispatcher.add_broadcaster(recorder.clone());
+        let rotation = CredentialRotation::new(store.clone(), "secret://openai")
+            .with_ttl(Duration::ZERO)
+            .with_event_dispatcher(dispatcher.clone());
+        let credential = RotatingCredential::new(rotation, "openai");
+
+        assert_eq!(credential.current().await.unwrap(), "sk-1");
+        store.set("openai", "sk-2");
+        assert_eq!(credential.current().await.unwrap(), "sk-2");
+
+        // The store still has the rejected key
+        assert_eq!(credential.refresh("sk-2").await.unwrap(), None);
+        store.set("openai", "sk-3");
+        assert_eq!(
+            credential.refresh("sk-2").await.unwrap().as_deref(),
+            Some("sk-3")
+        );
+
+        dispatcher.flush().await;
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            vec![
+                CredentialRotationReason::Expired,
+                CredentialRotationReason::Rejected
+            ]
+        );
+    }
+}
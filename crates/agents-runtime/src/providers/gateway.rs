@@ -0,0 +1,458 @@
+//! OpenAI-compatible gateways such as LiteLLM and OpenRouter
+//!
+//! A [`GatewayChatModel`] talks to a gateway's chat completions endpoint and picks
+//! the model name per request from its [`ModelRoute`]s: the cheapest route of at
+//! least the requested [`CostTier`] whose context window fits the request. Runs
+//! ask for a tier with [`with_cost_tier`]; the configured default applies otherwise.
+//!
+//! Usage records name the model the gateway actually served and carry the cost the
+//! gateway reports, either in the response body (OpenRouter) or in a response
+//! header (LiteLLM).
+
+use super::credentials::send_authorized;
+use super::openai::{self, ChatRequest, ChatResponse};
+use agents_core::llm::{ChunkStream, LanguageModel, LlmRequest, LlmResponse};
+use agents_core::secrets::{resolve_secret, SecretsProvider};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+
+tokio::task_local! {
+    static COST_TIER: CostTier;
+}
+
+/// Prompt tokens assumed per character when estimating the size of a request
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Completion tokens kept free in the context window by default
+pub const DEFAULT_RESERVED_OUTPUT_TOKENS: u32 = 4_096;
+
+/// Price class of a model, cheapest first.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum CostTier {
+    #[default]
+    Economy,
+    Standard,
+    Premium,
+}
+
+/// Run `future` with requests to gateways routed to models of at least `tier`.
+///
+/// ```ignore
+/// let tier = if customer.is_premium() { CostTier::Premium } else { CostTier::Economy };
+/// let reply = with_cost_tier(tier, agent.handle_message(text, state)).await?;
+/// ```
+pub async fn with_cost_tier<F: Future>(tier: CostTier, future: F) -> F::Output {
+    COST_TIER.scope(tier, future).await
+}
+
+/// A model the gateway can route to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelRoute {
+    /// Model name as the gateway knows it, e.g. `openai/gpt-4o-mini`
+    pub model: String,
+    pub tier: CostTier,
+    /// Prompt and completion tokens the model accepts
+    pub context_window: u32,
+}
+
+impl ModelRoute {
+    pub fn new(model: impl Into<String>, tier: CostTier, context_window: u32) -> Self {
+        Self {
+            model: model.into(),
+            tier,
+            context_window,
+        }
+    }
+}
+
+/// Connection and routing settings of a gateway.
+///
+/// # Example
+///
+/// ```ignore
+/// let config = GatewayConfig::openrouter(api_key, "openai/gpt-4o-mini")
+///     .with_app_attribution("https://support.example.com", "Support Agent")
+///     .with_route(ModelRoute::new("openai/gpt-4o-mini", CostTier::Economy, 128_000))
+///     .with_route(ModelRoute::new("google/gemini-2.0-flash-001", CostTier::Economy, 1_000_000))
+///     .with_route(ModelRoute::new("anthropic/claude-sonnet-4", CostTier::Premium, 200_000));
+/// let model = Arc::new(GatewayChatModel::new(config)?);
+/// ```
+#[derive(Clone)]
+pub struct GatewayConfig {
+    /// Provider name in usage records and the provider keys of `RunOptions`
+    pub name: String,
+    pub api_key: String,
+    /// Chat completions endpoint
+    pub api_url: String,
+    /// Model used when no route fits the request
+    pub default_model: String,
+    /// Routes in order of preference within a tier
+    pub routes: Vec<ModelRoute>,
+    /// Tier requested outside a [`with_cost_tier`] scope
+    pub default_tier: CostTier,
+    /// Completion tokens kept free when checking a route's context window
+    pub reserved_output_tokens: u32,
+    pub custom_headers: Vec<(String, String)>,
+    /// Fields added to every request body
+    pub extra_body: serde_json::Map<String, serde_json::Value>,
+    /// Response header carrying the request's cost in USD
+    pub cost_header: Option<String>,
+}
+
+impl GatewayConfig {
+    pub fn new(
+        api_url: impl Into<String>,
+        api_key: impl Into<String>,
+        default_model: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: "gateway".to_string(),
+            api_key: api_key.into(),
+            api_url: api_url.into(),
+            default_model: default_model.into(),
+            routes: Vec::new(),
+            default_tier: CostTier::default(),
+            reserved_output_tokens: DEFAULT_RESERVED_OUTPUT_TOKENS,
+            custom_headers: Vec::new(),
+            extra_body: serde_json::Map::new(),
+            cost_header: None,
+        }
+    }
+
+    /// OpenRouter, asking it to report the cost of each request.
+    pub fn openrouter(api_key: impl Into<String>, default_model: impl Into<String>) -> Self {
+        let mut config = Self::new(
+            "https://openrouter.ai/api/v1/chat/completions",
+            api_key,
+            default_model,
+        )
+        .with_name("openrouter");
+        config
+            .extra_body
+            .insert("usage".into(), serde_json::json!({ "include": true }));
+        config
+    }
+
+    /// A LiteLLM proxy at `base_url`, e.g. `http://litellm:4000`.
+    pub fn litellm(
+        base_url: impl AsRef<str>,
+        api_key: impl Into<String>,
+        default_model: impl Into<String>,
+    ) -> Self {
+        let url = format!(
+            "{}/chat/completions",
+            base_url.as_ref().trim_end_matches('/')
+        );
+        Self::new(url, api_key, default_model)
+            .with_name("litellm")
+            .with_cost_header("x-litellm-response-cost")
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn with_route(mut self, route: ModelRoute) -> Self {
+        self.routes.push(route);
+        self
+    }
+
+    pub fn with_default_tier(mut self, tier: CostTier) -> Self {
+        self.default_tier = tier;
+        self
+    }
+
+    pub fn with_reserved_output_tokens(mut self, tokens: u32) -> Self {
+        self.reserved_output_tokens = tokens;
+        self
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.custom_headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Identify the app in OpenRouter's rankings and analytics.
+    pub fn with_app_attribution(
+        self,
+        site_url: impl Into<String>,
+        app_name: impl Into<String>,
+    ) -> Self {
+        self.with_header("HTTP-Referer", site_url)
+            .with_header("X-Title", app_name)
+    }
+
+    pub fn with_extra_body(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
+        self.extra_body.insert(key.into(), value);
+        self
+    }
+
+    pub fn with_cost_header(mut self, header: impl Into<String>) -> Self {
+        self.cost_header = Some(header.into());
+        self
+    }
+
+    /// Replace `secret://` references in the API key and custom header values with
+    /// their values from `secrets`.
+    pub async fn resolve_secrets(mut self, secrets: &dyn SecretsProvider) -> anyhow::Result<Self> {
+        self.api_key = resolve_secret(secrets, &self.api_key).await?;
+        self.custom_headers = super::resolve_headers(secrets, self.custom_headers).await?;
+        Ok(self)
+    }
+
+    /// Model for a request of about `prompt_tokens` tokens in `tier`: the cheapest
+    /// route of at least that tier with room for the prompt and the reserved output.
+    pub fn route(&self, tier: CostTier, prompt_tokens: u32) -> &str {
+        let needed = prompt_tokens.saturating_add(self.reserved_output_tokens);
+        self.routes
+            .iter()
+            .enumerate()
+            .filter(|(_, route)| route.tier >= tier && route.context_window >= needed)
+            .min_by_key(|(index, route)| (route.tier, *index))
+            .map_or(&self.default_model, |(_, route)| &route.model)
+    }
+}
+
+/// Chat model served through an OpenAI-compatible gateway.
+pub struct GatewayChatModel {
+    client: Client,
+    config: GatewayConfig,
+}
+
+impl GatewayChatModel {
+    pub fn new(config: GatewayConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: Client::builder()
+                .user_agent("rust-deep-agents-sdk/0.1")
+                .build()?,
+            config,
+        })
+    }
+
+    /// Model routed to for `request`, in the current run's cost tier.
+    fn select_model(&self, request: &LlmRequest) -> &str {
+        let tier = COST_TIER
+            .try_with(|tier| *tier)
+            .unwrap_or(self.config.default_tier);
+        let model = self.config.route(tier, estimate_prompt_tokens(request));
+        tracing::debug!(gateway = %self.config.name, ?tier, model, "Routed gateway request");
+        model
+    }
+
+    async fn send(&self, body: &bytes::Bytes) -> anyhow::Result<reqwest::Response> {
+        let response = send_authorized(&self.config.name, None, &self.config.api_key, |key| {
+            let mut request = self.client.post(&self.config.api_url).bearer_auth(key);
+            for (name, value) in &self.config.custom_headers {
+                request = request.header(name, value);
+            }
+            Ok(super::json_body(super::with_correlation_id(request), body))
+        })
+        .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            tracing::error!(
+                gateway = %self.config.name,
+                "Gateway API error: status={}, body={}",
+                status,
+                error_text
+            );
+            anyhow::bail!(
+                "{} API error: {} - {}",
+                self.config.name,
+                status,
+                error_text
+            );
+        }
+        Ok(response)
+    }
+
+    fn encode(
+        &self,
+        request: &LlmRequest,
+        model: &str,
+        stream: bool,
+    ) -> anyhow::Result<bytes::Bytes> {
+        let messages = openai::to_openai_messages(request);
+        let body = ChatRequest {
+            model,
+            messages: &messages,
+            stream: stream.then_some(true),
+            tools: openai::to_openai_tools(&request.tools),
+            temperature: request.temperature,
+            seed: request.seed,
+            extra: (!self.config.extra_body.is_empty()).then_some(&self.config.extra_body),
+        };
+        super::encode_json(&body)
+    }
+
+    /// Cost reported in the configured response header.
+    fn header_cost(&self, response: &reqwest::Response) -> Option<f64> {
+        let header = self.config.cost_header.as_deref()?;
+        response
+            .headers()
+            .get(header)?
+            .to_str()
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    }
+}
+
+/// Rough prompt size of `request`, for checking context windows.
+fn estimate_prompt_tokens(request: &LlmRequest) -> u32 {
+    let chars = request.system_prompt.len()
+        + request
+            .messages
+            .iter()
+            .map(|message| message.content.text().len())
+            .sum::<usize>()
+        + request
+            .tools
+            .iter()
+            .map(|tool| serde_json::to_string(tool).map_or(0, |json| json.len()))
+            .sum::<usize>();
+    u32::try_from(chars.div_ceil(CHARS_PER_TOKEN)).unwrap_or(u32::MAX)
+}
+
+#[async_trait]
+impl LanguageModel for GatewayChatModel {
+    fn model_name(&self) -> Option<String> {
+        Some(self.config.default_model.clone())
+    }
+
+    async fn generate(&self, request: LlmRequest) -> anyhow::Result<LlmResponse> {
+        let model = self.select_model(&request);
+        let body = self.encode(&request, model, false)?;
+        let response = self.send(&body).await?;
+        let header_cost = self.header_cost(&response);
+
+        let mut data: ChatResponse = super::decode_json(response).await?;
+        let served = data.model.take();
+        let mut response = openai::to_llm_response(data, model)?;
+        if let Some(usage) = &mut response.usage {
+            usage.provider = self.config.name.clone();
+            if let Some(served) = served {
+                usage.model = served;
+            }
+            if usage.cost.is_none() {
+                usage.cost = header_cost;
+            }
+        }
+        Ok(response)
+    }
+
+    async fn generate_stream(&self, request: LlmRequest) -> anyhow::Result<ChunkStream> {
+        let model = self.select_model(&request);
+        let body = self.encode(&request, model, true)?;
+        let response = self.send(&body).await?;
+        Ok(openai::chunk_stream(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Answers chat completions like a LiteLLM proxy, recording each request.
+    async fn serve_gateway(seen: Arc<Mutex<Vec<String>>>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = vec![0; 64 * 1024];
+                let read = socket.read(&mut request).await.unwrap_or(0);
+                seen.lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&request[..read]).to_string());
+                let body = r#"{"model":"gpt-4o-mini-2024-07-18","choices":[{"message":{"content":"ok"}}],"usage":{"prompt_tokens":12,"completion_tokens":3}}"#;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\nx-litellm-response-cost: 0.00042\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        url
+    }
+
+    fn routed() -> GatewayConfig {
+        GatewayConfig::new("http://gateway/v1/chat/completions", "sk", "fallback")
+            .with_reserved_output_tokens(1_000)
+            .with_route(ModelRoute::new("mini", CostTier::Economy, 8_000))
+            .with_route(ModelRoute::new("long", CostTier::Economy, 1_000_000))
+            .with_route(ModelRoute::new("standard", CostTier::Standard, 128_000))
+            .with_route(ModelRoute::new("premium", CostTier::Premium, 200_000))
+    }
+
+    #[test]
+    fn routes_pick_the_cheapest_model_that_fits() {
+        let config = routed();
+        assert_eq!(config.route(CostTier::Economy, 2_000), "mini");
+        // Prompt plus reserved output exceeds the first route's window
+        assert_eq!(config.route(CostTier::Economy, 7_500), "long");
+        assert_eq!(config.route(CostTier::Standard, 2_000), "standard");
+        assert_eq!(config.route(CostTier::Premium, 2_000), "premium");
+        assert_eq!(config.route(CostTier::Premium, 500_000), "fallback");
+    }
+
+    #[test]
+    fn openrouter_requests_usage_and_attributes_the_app() {
+        let config = GatewayConfig::openrouter("sk-or", "openai/gpt-4o-mini")
+            .with_app_attribution("https://example.com", "Support Agent");
+        assert_eq!(config.name, "openrouter");
+        assert_eq!(
+            config.extra_body["usage"],
+            serde_json::json!({ "include": true })
+        );
+        assert!(config
+            .custom_headers
+            .contains(&("X-Title".to_string(), "Support Agent".to_string())));
+
+        let litellm = GatewayConfig::litellm("http://litellm:4000/", "sk", "gpt-4o-mini");
+        assert_eq!(litellm.api_url, "http://litellm:4000/chat/completions");
+    }
+
+    #[tokio::test]
+    async fn requests_are_routed_by_tier_and_report_gateway_usage() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let url = serve_gateway(seen.clone()).await;
+        let model = GatewayChatModel::new(
+            GatewayConfig::litellm(url, "sk-proxy", "fallback")
+                .with_route(ModelRoute::new("gpt-4o-mini", CostTier::Economy, 128_000))
+                .with_route(ModelRoute::new("claude-sonnet", CostTier::Premium, 200_000))
+                .with_header("x-litellm-tags", "support"),
+        )
+        .unwrap();
+
+        let response = model.generate(LlmRequest::new("", vec![])).await.unwrap();
+        with_cost_tier(
+            CostTier::Premium,
+            model.generate(LlmRequest::new("", vec![])),
+        )
+        .await
+        .unwrap();
+
+        let usage = response.usage.unwrap();
+        assert_eq!(usage.provider, "litellm");
+        assert_eq!(usage.model, "gpt-4o-mini-2024-07-18");
+        assert_eq!((usage.input_tokens, usage.output_tokens), (12, 3));
+        assert_eq!(usage.cost, Some(0.00042));
+
+        let seen = seen.lock().unwrap();
+        assert!(seen[0].contains(r#""model":"gpt-4o-mini""#));
+        assert!(seen[0].contains("x-litellm-tags: support"));
+        assert!(seen[0].contains("authorization: Bearer sk-proxy"));
+        assert!(seen[1].contains(r#""model":"claude-sonnet""#));
+    }
+}
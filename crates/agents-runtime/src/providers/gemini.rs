@@ -1,10 +1,13 @@
-use agents_core::llm::{LanguageModel, LlmRequest, LlmResponse};
-use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+use super::credentials::{send_authorized, CredentialRotation, RotatingCredential};
+use agents_core::llm::{LanguageModel, LlmRequest, LlmResponse, LlmUsage};
+use agents_core::messaging::{AgentMessage, ImageData, MessageContent, MessageRole};
+use agents_core::secrets::{resolve_secret, SecretsProvider};
 use agents_core::tools::ToolSchema;
 use async_trait::async_trait;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::borrow::Cow;
 
 #[derive(Clone)]
 pub struct GeminiConfig {
@@ -28,11 +31,20 @@ impl GeminiConfig {
         self.custom_headers = headers;
         self
     }
+
+    /// Replace `secret://` references in the API key and custom header values with
+    /// their values from `secrets`.
+    pub async fn resolve_secrets(mut self, secrets: &dyn SecretsProvider) -> anyhow::Result<Self> {
+        self.api_key = resolve_secret(secrets, &self.api_key).await?;
+        self.custom_headers = super::resolve_headers(secrets, self.custom_headers).await?;
+        Ok(self)
+    }
 }
 
 pub struct GeminiChatModel {
     client: Client,
     config: GeminiConfig,
+    credential: Option<RotatingCredential>,
 }
 
 impl GeminiChatModel {
@@ -42,17 +54,39 @@ impl GeminiChatModel {
                 .user_agent("rust-deep-agents-sdk/0.1")
                 .build()?,
             config,
+            credential: None,
         })
     }
+
+    /// Read the API key through `rotation` instead of using the configured one.
+    ///
+    /// Gemini answers requests with an invalid key with HTTP 400 rather than 401, so
+    /// only the TTL picks up rotated keys.
+    pub fn with_credential_rotation(mut self, rotation: CredentialRotation) -> Self {
+        self.credential = Some(RotatingCredential::new(rotation, "gemini"));
+        self
+    }
 }
 
+/// Request body borrowing the history's text and images, so a turn is not copied
+/// before it is encoded.
 #[derive(Serialize)]
-struct GeminiRequest {
-    contents: Vec<GeminiContent>,
+struct GeminiRequest<'a> {
+    contents: Vec<GeminiContent<'a>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    system_instruction: Option<GeminiContent>,
+    system_instruction: Option<GeminiContent<'a>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<GeminiToolDeclaration>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    generation_config: Option<GeminiGenerationConfig>,
+}
+
+#[derive(Serialize)]
+struct GeminiGenerationConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
 }
 
 #[derive(Clone, Serialize)]
@@ -68,19 +102,76 @@ struct GeminiFunctionDeclaration {
 }
 
 #[derive(Serialize)]
-struct GeminiContent {
-    role: String,
-    parts: Vec<GeminiPart>,
+struct GeminiContent<'a> {
+    role: &'static str,
+    parts: Vec<GeminiPart<'a>>,
+}
+
+#[derive(Serialize)]
+struct GeminiPart<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<Cow<'a, str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    inline_data: Option<GeminiInlineData<'a>>,
+}
+
+impl<'a> GeminiPart<'a> {
+    fn text(text: Cow<'a, str>) -> Self {
+        Self {
+            text: Some(text),
+            inline_data: None,
+        }
+    }
+
+    fn image(image: &'a ImageData) -> Self {
+        Self {
+            text: None,
+            inline_data: Some(GeminiInlineData {
+                mime_type: &image.mime_type,
+                data: &image.data,
+            }),
+        }
+    }
 }
 
 #[derive(Serialize)]
-struct GeminiPart {
-    text: String,
+struct GeminiInlineData<'a> {
+    mime_type: &'a str,
+    data: &'a str,
 }
 
 #[derive(Deserialize)]
 struct GeminiResponse {
     candidates: Vec<GeminiCandidate>,
+    #[serde(rename = "usageMetadata", default)]
+    usage_metadata: Option<GeminiUsageMetadata>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiUsageMetadata {
+    #[serde(default)]
+    prompt_token_count: u32,
+    #[serde(default)]
+    candidates_token_count: u32,
+    /// Billed as output on thinking models
+    #[serde(default)]
+    thoughts_token_count: u32,
+    /// Part of `prompt_token_count`
+    #[serde(default)]
+    cached_content_token_count: u32,
+}
+
+impl GeminiUsageMetadata {
+    fn to_llm_usage(&self, model: &str) -> LlmUsage {
+        LlmUsage::new(
+            "gemini",
+            model,
+            self.prompt_token_count,
+            self.candidates_token_count + self.thoughts_token_count,
+        )
+        .with_cache_tokens(self.cached_content_token_count, 0)
+    }
 }
 
 #[derive(Deserialize)]
@@ -106,8 +197,8 @@ struct GeminiFunctionCall {
     args: Value,
 }
 
-fn to_gemini_contents(request: &LlmRequest) -> (Vec<GeminiContent>, Option<GeminiContent>) {
-    let mut contents = Vec::new();
+fn to_gemini_contents(request: &LlmRequest) -> (Vec<GeminiContent<'_>>, Option<GeminiContent<'_>>) {
+    let mut contents = Vec::with_capacity(request.messages.len());
     for message in &request.messages {
         let role = match message.role {
             MessageRole::User => "user",
@@ -115,24 +206,23 @@ fn to_gemini_contents(request: &LlmRequest) -> (Vec<GeminiContent>, Option<Gemin
             MessageRole::Tool => "user",
             MessageRole::System => "user",
         };
-        let text = match &message.content {
-            MessageContent::Text(text) => text.clone(),
-            MessageContent::Json(value) => value.to_string(),
+        let parts = match &message.content {
+            MessageContent::Image { text, images } => text
+                .iter()
+                .map(|text| GeminiPart::text(Cow::Borrowed(text)))
+                .chain(images.iter().map(GeminiPart::image))
+                .collect(),
+            other => vec![GeminiPart::text(other.text())],
         };
-        contents.push(GeminiContent {
-            role: role.into(),
-            parts: vec![GeminiPart { text }],
-        });
+        contents.push(GeminiContent { role, parts });
     }
 
     let system_instruction = if request.system_prompt.trim().is_empty() {
         None
     } else {
         Some(GeminiContent {
-            role: "system".into(),
-            parts: vec![GeminiPart {
-                text: request.system_prompt.clone(),
-            }],
+            role: "system",
+            parts: vec![GeminiPart::text(Cow::Borrowed(&request.system_prompt))],
         })
     };
 
@@ -160,6 +250,10 @@ fn to_gemini_tools(tools: &[ToolSchema]) -> Option<Vec<GeminiToolDeclaration>> {
 
 #[async_trait]
 impl LanguageModel for GeminiChatModel {
+    fn model_name(&self) -> Option<String> {
+        Some(self.config.model.clone())
+    }
+
     async fn generate(&self, request: LlmRequest) -> anyhow::Result<LlmResponse> {
         let (contents, system_instruction) = to_gemini_contents(&request);
         let tools = to_gemini_tools(&request.tools);
@@ -182,6 +276,12 @@ impl LanguageModel for GeminiChatModel {
             contents,
             system_instruction,
             tools,
+            generation_config: (request.temperature.is_some() || request.seed.is_some()).then_some(
+                GeminiGenerationConfig {
+                    temperature: request.temperature,
+                    seed: request.seed,
+                },
+            ),
         };
 
         let base_url = self
@@ -189,20 +289,32 @@ impl LanguageModel for GeminiChatModel {
             .api_url
             .clone()
             .unwrap_or_else(|| "https://generativelanguage.googleapis.com/v1beta".into());
-        let url = format!(
-            "{}/models/{}:generateContent?key={}",
-            base_url, self.config.model, self.config.api_key
-        );
-
-        let mut request = self.client.post(&url);
-
-        for (key, value) in &self.config.custom_headers {
-            request = request.header(key, value);
-        }
-
-        let response = request.json(&body).send().await?.error_for_status()?;
+        let url = format!("{}/models/{}:generateContent", base_url, self.config.model);
+        let body = super::encode_json(&body)?;
+        let response = send_authorized(
+            "gemini",
+            self.credential.as_ref(),
+            &self.config.api_key,
+            |api_key| {
+                // A header rather than the `key` query parameter keeps the key out of
+                // URLs, which show up in errors and logs
+                let mut request = self.client.post(&url).header("x-goog-api-key", api_key);
+
+                for (key, value) in &self.config.custom_headers {
+                    request = request.header(key, value);
+                }
+
+                Ok(super::json_body(super::with_correlation_id(request), &body))
+            },
+        )
+        .await?
+        .error_for_status()?;
 
-        let data: GeminiResponse = response.json().await?;
+        let data: GeminiResponse = super::decode_json(response).await?;
+        let usage = data
+            .usage_metadata
+            .as_ref()
+            .map(|usage| usage.to_llm_usage(&self.config.model));
 
         // Check if response contains function calls
         let function_calls: Vec<_> = data
@@ -238,6 +350,7 @@ impl LanguageModel for GeminiChatModel {
                     })),
                     metadata: None,
                 },
+                usage,
             });
         }
 
@@ -256,6 +369,7 @@ impl LanguageModel for GeminiChatModel {
                 content: MessageContent::Text(text),
                 metadata: None,
             },
+            usage,
         })
     }
 }
@@ -264,6 +378,29 @@ impl LanguageModel for GeminiChatModel {
 mod tests {
     use super::*;
 
+    #[test]
+    fn gemini_usage_metadata_includes_thoughts_and_cache() {
+        let data: GeminiResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [],
+            "usageMetadata": {
+                "promptTokenCount": 1200,
+                "candidatesTokenCount": 40,
+                "thoughtsTokenCount": 10,
+                "cachedContentTokenCount": 1000,
+                "totalTokenCount": 1250
+            }
+        }))
+        .unwrap();
+        let usage = data
+            .usage_metadata
+            .unwrap()
+            .to_llm_usage("gemini-2.0-flash");
+        assert_eq!(usage.input_tokens, 1200);
+        assert_eq!(usage.output_tokens, 50);
+        assert_eq!(usage.cache_read_tokens, 1000);
+        assert_eq!(usage.model, "gemini-2.0-flash");
+    }
+
     #[test]
     fn gemini_conversion_handles_system_prompt() {
         let request = LlmRequest::new(
@@ -278,7 +415,10 @@ mod tests {
         assert_eq!(contents.len(), 1);
         assert_eq!(contents[0].role, "user");
         assert!(system.is_some());
-        assert_eq!(system.unwrap().parts[0].text, "You are concise");
+        assert_eq!(
+            system.unwrap().parts[0].text.as_deref(),
+            Some("You are concise")
+        );
     }
 
     #[test]
@@ -1,7 +1,69 @@
 pub mod anthropic;
+pub mod credentials;
+pub mod gateway;
 pub mod gemini;
+pub mod ollama;
 pub mod openai;
+pub mod self_hosted;
 
 pub use anthropic::{AnthropicConfig, AnthropicMessagesModel};
+pub use credentials::{CredentialRotation, DEFAULT_CREDENTIAL_TTL};
+pub use gateway::{with_cost_tier, CostTier, GatewayChatModel, GatewayConfig, ModelRoute};
 pub use gemini::{GeminiChatModel, GeminiConfig};
+pub use ollama::{OllamaChatModel, OllamaConfig};
 pub use openai::{OpenAiChatModel, OpenAiConfig};
+pub use self_hosted::{ReplicaHealth, SelfHostedChatModel, SelfHostedConfig};
+
+use agents_core::profiling::{self, ProfilePhase};
+use agents_core::secrets::{resolve_secret, SecretsProvider};
+use bytes::Bytes;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Header carrying the run's correlation id on provider requests.
+pub const CORRELATION_ID_HEADER: &str = "x-correlation-id";
+
+/// Tag a provider request with the current run's correlation id, if any.
+pub(crate) fn with_correlation_id(request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+    match agents_core::correlation::current_correlation_id() {
+        Some(id) => request.header(CORRELATION_ID_HEADER, id),
+        None => request,
+    }
+}
+
+/// Encode a request body, timed as serialization when the run is profiled.
+pub(crate) fn encode_json<T: Serialize>(body: &T) -> anyhow::Result<Bytes> {
+    let encoded = profiling::time(ProfilePhase::Serialization, || serde_json::to_vec(body))?;
+    Ok(Bytes::from(encoded))
+}
+
+/// Attach a body encoded with [`encode_json`] to a request. Retries share the
+/// encoded bytes instead of copying them.
+pub(crate) fn json_body(request: reqwest::RequestBuilder, body: &Bytes) -> reqwest::RequestBuilder {
+    request
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body.clone())
+}
+
+/// Read and decode a JSON response body, timed as serialization when the run is
+/// profiled.
+pub(crate) async fn decode_json<T: DeserializeOwned>(
+    response: reqwest::Response,
+) -> anyhow::Result<T> {
+    let bytes = response.bytes().await?;
+    Ok(profiling::time(ProfilePhase::Serialization, || {
+        serde_json::from_slice(&bytes)
+    })?)
+}
+
+/// Resolve `secret://` references in custom header values.
+pub(crate) async fn resolve_headers(
+    secrets: &dyn SecretsProvider,
+    headers: Vec<(String, String)>,
+) -> anyhow::Result<Vec<(String, String)>> {
+    let mut resolved = Vec::with_capacity(headers.len());
+    for (name, value) in headers {
+        let value = resolve_secret(secrets, &value).await?;
+        resolved.push((name, value));
+    }
+    Ok(resolved)
+}
@@ -0,0 +1,581 @@
+//! Local models served by Ollama
+//!
+//! [`OllamaChatModel`] talks to Ollama's native `/api/chat` endpoint, so no API
+//! key is needed. Streamed responses arrive as one JSON object per line rather
+//! than server-sent events. Tool calls come back whole, with their arguments as
+//! an object, from models whose template supports tools; other models reject
+//! requests with tools with a client error.
+
+use super::openai::{self, OpenAiTool};
+use agents_core::llm::{
+    ChunkStream, LanguageModel, LlmRequest, LlmResponse, LlmUsage, StreamChunk,
+};
+use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+use agents_core::secrets::SecretsProvider;
+use async_trait::async_trait;
+use futures::stream::StreamExt;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::sync::{Arc, Mutex};
+
+/// Address `ollama serve` listens on by default
+pub const DEFAULT_OLLAMA_URL: &str = "http://localhost:11434";
+
+/// Server and model settings of an Ollama model.
+///
+/// # Example
+///
+/// ```ignore
+/// let config = OllamaConfig::new("llama3.1:8b")
+///     .with_keep_alive("30m")
+///     .with_context_length(16_384);
+/// let model = Arc::new(OllamaChatModel::new(config)?);
+/// ```
+#[derive(Clone)]
+pub struct OllamaConfig {
+    /// Model tag, e.g. `llama3.1:8b` or `qwen2.5:14b`
+    pub model: String,
+    /// Server root, e.g. `http://localhost:11434`
+    pub base_url: String,
+    /// How long the server keeps the model loaded after a request, e.g. `30m`;
+    /// the server's default (5 minutes) when unset
+    pub keep_alive: Option<String>,
+    /// Model options sent with each request, e.g. `num_ctx` or `top_k`
+    pub options: serde_json::Map<String, Value>,
+    pub custom_headers: Vec<(String, String)>,
+}
+
+impl OllamaConfig {
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            base_url: DEFAULT_OLLAMA_URL.to_string(),
+            keep_alive: None,
+            options: serde_json::Map::new(),
+            custom_headers: Vec::new(),
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: impl AsRef<str>) -> Self {
+        self.base_url = base_url.as_ref().trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_keep_alive(mut self, keep_alive: impl Into<String>) -> Self {
+        self.keep_alive = Some(keep_alive.into());
+        self
+    }
+
+    /// Context window in tokens. Ollama defaults to a few thousand tokens
+    /// whatever the model supports, which long agent prompts quickly exceed.
+    pub fn with_context_length(self, tokens: usize) -> Self {
+        self.with_option("num_ctx", serde_json::json!(tokens))
+    }
+
+    pub fn with_option(mut self, key: impl Into<String>, value: Value) -> Self {
+        self.options.insert(key.into(), value);
+        self
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.custom_headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Replace `secret://` references in custom header values, e.g. for a proxy
+    /// in front of the server, with their values from `secrets`.
+    pub async fn resolve_secrets(mut self, secrets: &dyn SecretsProvider) -> anyhow::Result<Self> {
+        self.custom_headers = super::resolve_headers(secrets, self.custom_headers).await?;
+        Ok(self)
+    }
+}
+
+/// Chat model served by a local or remote Ollama server.
+pub struct OllamaChatModel {
+    client: Client,
+    config: OllamaConfig,
+}
+
+impl OllamaChatModel {
+    pub fn new(config: OllamaConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: Client::builder()
+                .user_agent("rust-deep-agents-sdk/0.1")
+                .build()?,
+            config,
+        })
+    }
+
+    async fn send(&self, body: &bytes::Bytes) -> anyhow::Result<reqwest::Response> {
+        let mut request = self
+            .client
+            .post(format!("{}/api/chat", self.config.base_url));
+        for (name, value) in &self.config.custom_headers {
+            request = request.header(name, value);
+        }
+        let request = super::json_body(super::with_correlation_id(request), body);
+        let response = request.send().await.map_err(|e| {
+            anyhow::Error::new(e).context(format!(
+                "Ollama server at {} unreachable; is `ollama serve` running?",
+                self.config.base_url
+            ))
+        })?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            // Ollama reports errors as {"error": "..."}
+            let error_text = serde_json::from_str::<OllamaError>(&error_text)
+                .map(|e| e.error)
+                .unwrap_or(error_text);
+            anyhow::bail!("Ollama API error: {} - {}", status, error_text);
+        }
+        Ok(response)
+    }
+
+    fn encode(&self, request: &LlmRequest, stream: bool) -> anyhow::Result<bytes::Bytes> {
+        let mut options = Cow::Borrowed(&self.config.options);
+        if let Some(temperature) = request.temperature {
+            options
+                .to_mut()
+                .insert("temperature".into(), serde_json::json!(temperature));
+        }
+        if let Some(seed) = request.seed {
+            options
+                .to_mut()
+                .insert("seed".into(), serde_json::json!(seed));
+        }
+        let body = OllamaRequest {
+            model: &self.config.model,
+            messages: to_ollama_messages(request),
+            stream,
+            tools: openai::to_openai_tools(&request.tools),
+            options: (!options.is_empty()).then_some(options),
+            keep_alive: self.config.keep_alive.as_deref(),
+        };
+        super::encode_json(&body)
+    }
+}
+
+#[derive(Serialize)]
+struct OllamaRequest<'a> {
+    model: &'a str,
+    messages: Vec<OllamaMessage<'a>>,
+    /// Ollama streams unless told otherwise
+    stream: bool,
+    /// Same shape as OpenAI function tools
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<OpenAiTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<Cow<'a, serde_json::Map<String, Value>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<&'a str>,
+}
+
+/// A message borrowing its text and images from the request's history.
+#[derive(Serialize)]
+struct OllamaMessage<'a> {
+    role: &'static str,
+    content: Cow<'a, str>,
+    /// Base64 image data without a `data:` prefix
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    images: Vec<&'a str>,
+}
+
+fn to_ollama_messages(request: &LlmRequest) -> Vec<OllamaMessage<'_>> {
+    let mut messages = Vec::with_capacity(request.messages.len() + 1);
+    if !request.system_prompt.trim().is_empty() {
+        messages.push(OllamaMessage {
+            role: "system",
+            content: Cow::Borrowed(&request.system_prompt),
+            images: Vec::new(),
+        });
+    }
+    for message in &request.messages {
+        let role = match message.role {
+            MessageRole::User => "user",
+            MessageRole::Agent => "assistant",
+            // As with OpenAI, the history has no tool call ids to pair results with
+            MessageRole::Tool => "user",
+            MessageRole::System => "system",
+        };
+        let (content, images) = match &message.content {
+            MessageContent::Image { text, images } => (
+                Cow::Borrowed(text.as_deref().unwrap_or_default()),
+                images.iter().map(|image| image.data.as_str()).collect(),
+            ),
+            other => (other.text(), Vec::new()),
+        };
+        messages.push(OllamaMessage {
+            role,
+            content,
+            images,
+        });
+    }
+    messages
+}
+
+#[derive(Deserialize)]
+struct OllamaError {
+    error: String,
+}
+
+/// A complete response, or one line of a streamed one.
+#[derive(Deserialize)]
+struct OllamaResponse {
+    #[serde(default)]
+    message: Option<OllamaResponseMessage>,
+    #[serde(default)]
+    done: bool,
+    /// `stop`, `length`, or `load`/`unload` for requests that only (un)load the model
+    #[serde(default)]
+    done_reason: Option<String>,
+    /// Input tokens; left out when the whole prompt was cached
+    #[serde(default)]
+    prompt_eval_count: u32,
+    #[serde(default)]
+    eval_count: u32,
+}
+
+#[derive(Deserialize)]
+struct OllamaResponseMessage {
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    tool_calls: Vec<OllamaToolCall>,
+}
+
+#[derive(Deserialize)]
+struct OllamaToolCall {
+    function: OllamaFunctionCall,
+}
+
+#[derive(Deserialize)]
+struct OllamaFunctionCall {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+impl OllamaFunctionCall {
+    fn to_json(&self) -> Value {
+        let args = match &self.arguments {
+            Value::Null => serde_json::json!({}),
+            args => args.clone(),
+        };
+        serde_json::json!({ "name": self.name, "args": args })
+    }
+}
+
+impl OllamaResponse {
+    fn usage(&self, model: &str) -> LlmUsage {
+        LlmUsage::new("ollama", model, self.prompt_eval_count, self.eval_count)
+    }
+
+    fn warn_if_truncated(&self, model: &str) {
+        if self.done_reason.as_deref() == Some("length") {
+            tracing::warn!(model, "Response was cut off at the output token limit");
+        }
+    }
+}
+
+fn agent_message(content: String, tool_calls: Vec<Value>) -> AgentMessage {
+    let content = if tool_calls.is_empty() {
+        MessageContent::Text(content)
+    } else {
+        MessageContent::Json(serde_json::json!({ "tool_calls": tool_calls }))
+    };
+    AgentMessage {
+        role: MessageRole::Agent,
+        content,
+        metadata: None,
+    }
+}
+
+fn to_llm_response(data: OllamaResponse, model: &str) -> LlmResponse {
+    data.warn_if_truncated(model);
+    let usage = data.usage(model);
+    let (content, tool_calls) = match data.message {
+        Some(message) => (
+            message.content,
+            message
+                .tool_calls
+                .iter()
+                .map(|call| call.function.to_json())
+                .collect(),
+        ),
+        None => (String::new(), Vec::new()),
+    };
+    LlmResponse {
+        message: agent_message(content, tool_calls),
+        usage: Some(usage),
+    }
+}
+
+/// Accumulates a streamed chat response from its JSON lines.
+#[derive(Default)]
+struct StreamState {
+    /// Received text not yet forming a complete line
+    buffer: String,
+    content: String,
+    tool_calls: Vec<Value>,
+    done: bool,
+}
+
+impl StreamState {
+    /// Process the next bytes of the response, returning the chunks they complete.
+    fn push(&mut self, bytes: &[u8], model: &str) -> Vec<anyhow::Result<StreamChunk>> {
+        if self.done {
+            return Vec::new();
+        }
+        self.buffer.push_str(&String::from_utf8_lossy(bytes));
+        let complete = match self.buffer.rfind('\n') {
+            Some(end) => {
+                let rest = self.buffer.split_off(end + 1);
+                std::mem::replace(&mut self.buffer, rest)
+            }
+            None => return Vec::new(),
+        };
+
+        let mut chunks = Vec::new();
+        let mut delta = String::new();
+        let mut finished = false;
+        for line in complete.lines().filter(|line| !line.trim().is_empty()) {
+            if let Ok(error) = serde_json::from_str::<OllamaError>(line) {
+                self.done = true;
+                chunks.push(Err(anyhow::anyhow!("Ollama stream error: {}", error.error)));
+                return chunks;
+            }
+            let data = match serde_json::from_str::<OllamaResponse>(line) {
+                Ok(data) => data,
+                Err(e) => {
+                    tracing::debug!("Failed to parse Ollama stream line: {}", e);
+                    continue;
+                }
+            };
+            if let Some(message) = &data.message {
+                delta.push_str(&message.content);
+                for call in &message.tool_calls {
+                    chunks.push(Ok(StreamChunk::ToolCallStarted {
+                        index: self.tool_calls.len(),
+                        name: call.function.name.clone(),
+                        partial_args: call.function.arguments.clone(),
+                    }));
+                    self.tool_calls.push(call.function.to_json());
+                }
+            }
+            if data.done {
+                data.warn_if_truncated(model);
+                finished = true;
+                break;
+            }
+        }
+
+        self.content.push_str(&delta);
+        if !delta.is_empty() {
+            chunks.insert(0, Ok(StreamChunk::TextDelta(delta)));
+        }
+        if finished {
+            chunks.push(Ok(self.complete()));
+        }
+        chunks
+    }
+
+    /// The `Done` chunk for a stream that ended without a final line, if anything
+    /// was received.
+    fn finish(&mut self) -> Option<StreamChunk> {
+        if self.done || (self.content.is_empty() && self.tool_calls.is_empty()) {
+            return None;
+        }
+        Some(self.complete())
+    }
+
+    fn complete(&mut self) -> StreamChunk {
+        self.done = true;
+        self.buffer.clear();
+        StreamChunk::Done {
+            message: agent_message(
+                std::mem::take(&mut self.content),
+                std::mem::take(&mut self.tool_calls),
+            ),
+        }
+    }
+}
+
+fn chunk_stream(response: reqwest::Response, model: String) -> ChunkStream {
+    let state = Arc::new(Mutex::new(StreamState::default()));
+    let finale_state = state.clone();
+
+    let chunk_stream = response.bytes_stream().flat_map(move |result| {
+        let mut state = state.lock().unwrap();
+        let chunks = match result {
+            Ok(bytes) => state.push(&bytes, &model),
+            Err(e) => match state.finish() {
+                Some(done) => vec![Ok(done)],
+                None if state.done => Vec::new(),
+                None => vec![Err(anyhow::anyhow!("Stream error: {}", e))],
+            },
+        };
+        futures::stream::iter(chunks)
+    });
+
+    let stream_with_finale = chunk_stream.chain(
+        futures::stream::once(async move { finale_state.lock().unwrap().finish() })
+            .filter_map(|done| async move { done.map(Ok) }),
+    );
+
+    Box::pin(stream_with_finale)
+}
+
+#[async_trait]
+impl LanguageModel for OllamaChatModel {
+    fn model_name(&self) -> Option<String> {
+        Some(self.config.model.clone())
+    }
+
+    async fn generate(&self, request: LlmRequest) -> anyhow::Result<LlmResponse> {
+        tracing::debug!(
+            "Ollama request: model={}, messages={}, tools={}",
+            self.config.model,
+            request.messages.len(),
+            request.tools.len()
+        );
+        let body = self.encode(&request, false)?;
+        let response = self.send(&body).await?;
+        let data: OllamaResponse = super::decode_json(response).await?;
+        Ok(to_llm_response(data, &self.config.model))
+    }
+
+    async fn generate_stream(&self, request: LlmRequest) -> anyhow::Result<ChunkStream> {
+        let body = self.encode(&request, true)?;
+        let response = self.send(&body).await?;
+        Ok(chunk_stream(response, self.config.model.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::messaging::ImageData;
+
+    #[test]
+    fn requests_carry_images_tools_and_options() {
+        let mut request = LlmRequest::new(
+            "You are concise",
+            vec![AgentMessage {
+                role: MessageRole::User,
+                content: MessageContent::Image {
+                    text: Some("What is this?".into()),
+                    images: vec![ImageData::new("image/png", "aGVsbG8=")],
+                },
+                metadata: None,
+            }],
+        );
+        request.temperature = Some(0.0);
+        request.tools = vec![agents_core::tools::ToolSchema::no_params(
+            "lookup",
+            "Look up a record",
+        )];
+        let model = OllamaChatModel::new(
+            OllamaConfig::new("llama3.1")
+                .with_base_url("http://gpu-box:11434/")
+                .with_keep_alive("30m")
+                .with_context_length(16_384),
+        )
+        .unwrap();
+        assert_eq!(model.config.base_url, "http://gpu-box:11434");
+
+        let body: Value = serde_json::from_slice(&model.encode(&request, true).unwrap()).unwrap();
+        assert_eq!(body["stream"], true);
+        assert_eq!(body["keep_alive"], "30m");
+        assert_eq!(body["options"]["num_ctx"], 16_384);
+        assert_eq!(body["options"]["temperature"], 0.0);
+        assert_eq!(body["messages"][0]["role"], "system");
+        assert_eq!(body["messages"][1]["content"], "What is this?");
+        assert_eq!(body["messages"][1]["images"][0], "aGVsbG8=");
+        assert_eq!(body["tools"][0]["function"]["name"], "lookup");
+    }
+
+    #[test]
+    fn tool_calls_and_usage_are_read_from_responses() {
+        let data: OllamaResponse = serde_json::from_value(serde_json::json!({
+            "model": "llama3.1",
+            "message": {
+                "role": "assistant",
+                "content": "",
+                "tool_calls": [{ "function": { "name": "lookup", "arguments": { "id": 7 } } }]
+            },
+            "done": true,
+            "done_reason": "stop",
+            "prompt_eval_count": 120,
+            "eval_count": 12
+        }))
+        .unwrap();
+        let response = to_llm_response(data, "llama3.1");
+        assert_eq!(
+            response.message.content,
+            MessageContent::Json(serde_json::json!({
+                "tool_calls": [{ "name": "lookup", "args": { "id": 7 } }]
+            }))
+        );
+        let usage = response.usage.unwrap();
+        assert_eq!(usage.provider, "ollama");
+        assert_eq!((usage.input_tokens, usage.output_tokens), (120, 12));
+    }
+
+    #[test]
+    fn streamed_lines_split_across_reads_are_joined() {
+        let mut state = StreamState::default();
+        let first = state.push(
+            b"{\"message\":{\"content\":\"Hel\"},\"done\":false}\n{\"mess",
+            "m",
+        );
+        assert!(matches!(&first[..], [Ok(StreamChunk::TextDelta(text))] if text == "Hel"));
+
+        let rest = state.push(
+            b"age\":{\"content\":\"lo\"},\"done\":false}\n{\"message\":{\"content\":\"\"},\"done\":true,\"done_reason\":\"stop\"}\n",
+            "m",
+        );
+        let [Ok(StreamChunk::TextDelta(delta)), Ok(StreamChunk::Done { message })] = &rest[..]
+        else {
+            panic!("unexpected chunks");
+        };
+        assert_eq!(delta, "lo");
+        assert_eq!(message.content, MessageContent::Text("Hello".into()));
+        assert!(state.finish().is_none());
+    }
+
+    #[test]
+    fn streamed_tool_calls_are_announced_and_completed() {
+        let mut state = StreamState::default();
+        let chunks = state.push(
+            b"{\"message\":{\"content\":\"\",\"tool_calls\":[{\"function\":{\"name\":\"lookup\",\"arguments\":{\"id\":7}}}]},\"done\":false}\n",
+            "m",
+        );
+        assert!(matches!(
+            &chunks[..],
+            [Ok(StreamChunk::ToolCallStarted { index: 0, name, .. })] if name == "lookup"
+        ));
+        let Some(StreamChunk::Done { message }) = state.finish() else {
+            panic!("expected a final message");
+        };
+        assert_eq!(
+            message.content,
+            MessageContent::Json(serde_json::json!({
+                "tool_calls": [{ "name": "lookup", "args": { "id": 7 } }]
+            }))
+        );
+    }
+
+    #[test]
+    fn stream_errors_are_reported() {
+        let mut state = StreamState::default();
+        let chunks = state.push(
+            b"{\"error\":\"model runner has unexpectedly stopped\"}\n",
+            "m",
+        );
+        assert!(matches!(&chunks[..], [Err(e)] if e.to_string().contains("unexpectedly stopped")));
+        assert!(state.finish().is_none());
+    }
+}
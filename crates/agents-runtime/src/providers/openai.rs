@@ -1,10 +1,17 @@
-use agents_core::llm::{ChunkStream, LanguageModel, LlmRequest, LlmResponse, StreamChunk};
-use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+use super::credentials::{send_authorized, CredentialRotation, RotatingCredential};
+use agents_core::batch::{BatchModel, BatchRequest, BatchResult, BatchStatus};
+use agents_core::json_stream::JsonStreamParser;
+use agents_core::llm::{
+    ChunkStream, LanguageModel, LlmRequest, LlmResponse, LlmUsage, StreamChunk,
+};
+use agents_core::messaging::{AgentMessage, ImageData, MessageContent, MessageRole};
+use agents_core::secrets::{resolve_secret, SecretsProvider};
 use agents_core::tools::ToolSchema;
 use async_trait::async_trait;
 use futures::stream::StreamExt;
 use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
+use std::borrow::Cow;
 use std::sync::{Arc, Mutex};
 
 #[derive(Clone)]
@@ -34,11 +41,20 @@ impl OpenAiConfig {
         self.custom_headers = headers;
         self
     }
+
+    /// Replace `secret://` references in the API key and custom header values with
+    /// their values from `secrets`.
+    pub async fn resolve_secrets(mut self, secrets: &dyn SecretsProvider) -> anyhow::Result<Self> {
+        self.api_key = resolve_secret(secrets, &self.api_key).await?;
+        self.custom_headers = super::resolve_headers(secrets, self.custom_headers).await?;
+        Ok(self)
+    }
 }
 
 pub struct OpenAiChatModel {
     client: Client,
     config: OpenAiConfig,
+    credential: Option<RotatingCredential>,
 }
 
 impl OpenAiChatModel {
@@ -48,28 +64,101 @@ impl OpenAiChatModel {
                 .user_agent("rust-deep-agents-sdk/0.1")
                 .build()?,
             config,
+            credential: None,
         })
     }
+
+    /// Read the API key through `rotation` instead of using the configured one.
+    pub fn with_credential_rotation(mut self, rotation: CredentialRotation) -> Self {
+        self.credential = Some(RotatingCredential::new(rotation, "openai"));
+        self
+    }
 }
 
 #[derive(Serialize)]
-struct ChatRequest<'a> {
-    model: &'a str,
-    messages: &'a [OpenAiMessage],
+pub(super) struct ChatRequest<'a> {
+    pub(super) model: &'a str,
+    pub(super) messages: &'a [OpenAiMessage<'a>],
     #[serde(skip_serializing_if = "Option::is_none")]
-    stream: Option<bool>,
+    pub(super) stream: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    tools: Option<Vec<OpenAiTool>>,
+    pub(super) tools: Option<Vec<OpenAiTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) seed: Option<u64>,
+    /// Additional body fields understood by OpenAI-compatible gateways
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub(super) extra: Option<&'a serde_json::Map<String, serde_json::Value>>,
 }
 
+/// A message borrowing its text and images from the request's history.
 #[derive(Serialize)]
-struct OpenAiMessage {
+pub(super) struct OpenAiMessage<'a> {
     role: &'static str,
-    content: String,
+    content: OpenAiContent<'a>,
+}
+
+/// Plain text, or content parts when a message carries images.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum OpenAiContent<'a> {
+    Text(Cow<'a, str>),
+    Parts(Vec<OpenAiContentPart<'a>>),
+}
+
+impl<'a> OpenAiContent<'a> {
+    fn from_message(content: &'a MessageContent) -> Self {
+        match content {
+            MessageContent::Image { text, images } => {
+                let text_part = text.iter().map(|text| OpenAiContentPart::Text { text });
+                let image_parts = images.iter().map(|image| OpenAiContentPart::ImageUrl {
+                    image_url: OpenAiImageUrl { url: image },
+                });
+                Self::Parts(text_part.chain(image_parts).collect())
+            }
+            other => Self::Text(other.text()),
+        }
+    }
+
+    /// Length of the text content, for logging.
+    fn text_len(&self) -> usize {
+        match self {
+            Self::Text(text) => text.len(),
+            Self::Parts(parts) => parts
+                .iter()
+                .map(|part| match part {
+                    OpenAiContentPart::Text { text } => text.len(),
+                    OpenAiContentPart::ImageUrl { .. } => 0,
+                })
+                .sum(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum OpenAiContentPart<'a> {
+    Text { text: &'a str },
+    ImageUrl { image_url: OpenAiImageUrl<'a> },
+}
+
+#[derive(Serialize)]
+struct OpenAiImageUrl<'a> {
+    /// Written as a `data:` URL straight into the request body
+    #[serde(serialize_with = "serialize_data_url")]
+    url: &'a ImageData,
+}
+
+fn serialize_data_url<S: Serializer>(image: &&ImageData, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&format_args!(
+        "data:{};base64,{}",
+        image.mime_type, image.data
+    ))
 }
 
 #[derive(Clone, Serialize)]
-struct OpenAiTool {
+pub(super) struct OpenAiTool {
     #[serde(rename = "type")]
     tool_type: String,
     function: OpenAiFunction,
@@ -83,13 +172,56 @@ struct OpenAiFunction {
 }
 
 #[derive(Deserialize)]
-struct ChatResponse {
+pub(super) struct ChatResponse {
+    /// Model that served the request; gateways report the model they routed to
+    #[serde(default)]
+    pub(super) model: Option<String>,
     choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<OpenAiUsage>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiUsage {
+    #[serde(default)]
+    prompt_tokens: u32,
+    #[serde(default)]
+    completion_tokens: u32,
+    #[serde(default)]
+    prompt_tokens_details: Option<OpenAiPromptTokensDetails>,
+    /// Cost in USD, reported by gateways such as OpenRouter
+    #[serde(default)]
+    cost: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiPromptTokensDetails {
+    #[serde(default)]
+    cached_tokens: u32,
+}
+
+impl OpenAiUsage {
+    fn to_llm_usage(&self, model: &str) -> LlmUsage {
+        let cached = self
+            .prompt_tokens_details
+            .as_ref()
+            .map_or(0, |details| details.cached_tokens);
+        let usage = LlmUsage::new("openai", model, self.prompt_tokens, self.completion_tokens)
+            .with_cache_tokens(cached, 0);
+        match self.cost {
+            Some(cost) => usage.with_cost(cost),
+            None => usage,
+        }
+    }
 }
 
 #[derive(Deserialize)]
 struct Choice {
     message: ChoiceMessage,
+    /// `stop`, `length` or `tool_calls` from OpenAI; self-hosted servers also send
+    /// `eos_token`, `stop_sequence` or `max_tokens`
+    #[serde(default)]
+    finish_reason: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -101,9 +233,11 @@ struct ChoiceMessage {
 
 #[derive(Deserialize)]
 struct OpenAiToolCall {
+    // Some OpenAI-compatible servers leave out the id and type
+    #[serde(default)]
     #[allow(dead_code)]
     id: String,
-    #[serde(rename = "type")]
+    #[serde(rename = "type", default)]
     #[allow(dead_code)]
     tool_type: String,
     function: OpenAiFunctionCall,
@@ -112,7 +246,26 @@ struct OpenAiToolCall {
 #[derive(Deserialize)]
 struct OpenAiFunctionCall {
     name: String,
-    arguments: String,
+    /// A JSON string; Text Generation Inference sends the object itself
+    #[serde(default)]
+    arguments: serde_json::Value,
+}
+
+impl OpenAiFunctionCall {
+    fn args(&self) -> serde_json::Value {
+        match &self.arguments {
+            serde_json::Value::String(text) => {
+                serde_json::from_str(text).unwrap_or_else(|_| serde_json::json!({}))
+            }
+            serde_json::Value::Null => serde_json::json!({}),
+            args => args.clone(),
+        }
+    }
+}
+
+/// Whether a finish reason means the response was cut off at the token limit.
+fn is_truncated(finish_reason: &str) -> bool {
+    matches!(finish_reason, "length" | "max_tokens")
 }
 
 // Streaming response structures
@@ -130,13 +283,197 @@ struct StreamChoice {
 #[derive(Deserialize)]
 struct StreamDelta {
     content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<StreamToolCallDelta>,
+}
+
+/// A fragment of a tool call. The name arrives with the first fragment of a call,
+/// the arguments are split across any number of them.
+#[derive(Deserialize)]
+struct StreamToolCallDelta {
+    index: usize,
+    function: Option<StreamFunctionDelta>,
+}
+
+#[derive(Deserialize)]
+struct StreamFunctionDelta {
+    name: Option<String>,
+    arguments: Option<String>,
+}
+
+/// A tool call being streamed. Arguments are parsed as they arrive instead of
+/// being buffered until the call is complete.
+#[derive(Default)]
+struct StreamedToolCall {
+    name: String,
+    arguments: JsonStreamParser,
+    /// Set when the arguments were not valid JSON
+    invalid: bool,
+    announced: bool,
+}
+
+/// Accumulates a streamed chat completion from its server-sent events.
+#[derive(Default)]
+struct StreamState {
+    /// Received text not yet forming a complete event
+    buffer: String,
+    content: String,
+    tool_calls: Vec<StreamedToolCall>,
+    done: bool,
 }
 
-fn to_openai_messages(request: &LlmRequest) -> Vec<OpenAiMessage> {
+impl StreamState {
+    /// Process the next bytes of the response, returning the chunks they complete.
+    fn push(&mut self, bytes: &[u8]) -> Vec<anyhow::Result<StreamChunk>> {
+        if self.done {
+            return Vec::new();
+        }
+        self.buffer.push_str(&String::from_utf8_lossy(bytes));
+
+        let mut delta = String::new();
+        let mut finished = false;
+        // Events are separated by a blank line; the last part may be incomplete
+        let complete = match self.buffer.rfind("\n\n") {
+            Some(end) => {
+                let rest = self.buffer.split_off(end + 2);
+                std::mem::replace(&mut self.buffer, rest)
+            }
+            None => String::new(),
+        };
+        'events: for event in complete.split("\n\n") {
+            for line in event.lines() {
+                let Some(data) = line.strip_prefix("data: ") else {
+                    continue;
+                };
+                let data = data.trim();
+                if data == "[DONE]" {
+                    finished = true;
+                    break 'events;
+                }
+                let chunk = match serde_json::from_str::<StreamResponse>(data) {
+                    Ok(chunk) => chunk,
+                    Err(e) => {
+                        tracing::debug!("Failed to parse SSE message: {}", e);
+                        continue;
+                    }
+                };
+                let Some(choice) = chunk.choices.into_iter().next() else {
+                    continue;
+                };
+                if let Some(content) = choice.delta.content {
+                    self.content.push_str(&content);
+                    delta.push_str(&content);
+                }
+                for call in choice.delta.tool_calls {
+                    self.push_tool_call(call);
+                }
+                // Some servers send an empty finish reason until the last event
+                if choice
+                    .finish_reason
+                    .is_some_and(|reason| !reason.is_empty())
+                {
+                    finished = true;
+                    break 'events;
+                }
+            }
+        }
+
+        let mut chunks = Vec::new();
+        if !delta.is_empty() {
+            chunks.push(Ok(StreamChunk::TextDelta(delta)));
+        }
+        for (index, call) in self.tool_calls.iter_mut().enumerate() {
+            if call.announced || call.name.is_empty() {
+                continue;
+            }
+            call.announced = true;
+            chunks.push(Ok(StreamChunk::ToolCallStarted {
+                index,
+                name: call.name.clone(),
+                partial_args: call
+                    .arguments
+                    .snapshot()
+                    .unwrap_or_else(|| serde_json::json!({})),
+            }));
+        }
+        if finished {
+            chunks.push(Ok(self.complete()));
+        }
+        chunks
+    }
+
+    fn push_tool_call(&mut self, delta: StreamToolCallDelta) {
+        if self.tool_calls.len() <= delta.index {
+            self.tool_calls
+                .resize_with(delta.index + 1, StreamedToolCall::default);
+        }
+        let call = &mut self.tool_calls[delta.index];
+        let Some(function) = delta.function else {
+            return;
+        };
+        if let Some(name) = function.name {
+            call.name.push_str(&name);
+        }
+        if let Some(arguments) = function.arguments {
+            if !call.invalid {
+                if let Err(e) = call.arguments.push(&arguments) {
+                    tracing::warn!(tool = %call.name, error = %e, "Invalid streamed tool arguments");
+                    call.invalid = true;
+                }
+            }
+        }
+    }
+
+    /// The `Done` chunk for a stream that ended without a finish marker, if
+    /// anything was received.
+    fn finish(&mut self) -> Option<StreamChunk> {
+        if self.done || (self.content.is_empty() && self.tool_calls.is_empty()) {
+            return None;
+        }
+        tracing::debug!(
+            "Stream ended naturally, sending final Done chunk with {} chars",
+            self.content.len()
+        );
+        Some(self.complete())
+    }
+
+    fn complete(&mut self) -> StreamChunk {
+        self.done = true;
+        self.buffer.clear();
+        let content = if self.tool_calls.is_empty() {
+            MessageContent::Text(std::mem::take(&mut self.content))
+        } else {
+            let tool_calls: Vec<_> = std::mem::take(&mut self.tool_calls)
+                .into_iter()
+                .map(|call| {
+                    let args = if call.invalid {
+                        None
+                    } else {
+                        call.arguments.finish().ok()
+                    };
+                    serde_json::json!({
+                        "name": call.name,
+                        "args": args.unwrap_or_else(|| serde_json::json!({}))
+                    })
+                })
+                .collect();
+            MessageContent::Json(serde_json::json!({ "tool_calls": tool_calls }))
+        };
+        StreamChunk::Done {
+            message: AgentMessage {
+                role: MessageRole::Agent,
+                content,
+                metadata: None,
+            },
+        }
+    }
+}
+
+pub(super) fn to_openai_messages(request: &LlmRequest) -> Vec<OpenAiMessage<'_>> {
     let mut messages = Vec::with_capacity(request.messages.len() + 1);
     messages.push(OpenAiMessage {
         role: "system",
-        content: request.system_prompt.clone(),
+        content: OpenAiContent::Text(Cow::Borrowed(&request.system_prompt)),
     });
 
     // Convert all messages to OpenAI format
@@ -150,18 +487,16 @@ fn to_openai_messages(request: &LlmRequest) -> Vec<OpenAiMessage> {
             MessageRole::System => "system",
         };
 
-        let content = match &msg.content {
-            MessageContent::Text(text) => text.clone(),
-            MessageContent::Json(value) => value.to_string(),
-        };
-
-        messages.push(OpenAiMessage { role, content });
+        messages.push(OpenAiMessage {
+            role,
+            content: OpenAiContent::from_message(&msg.content),
+        });
     }
     messages
 }
 
 /// Convert tool schemas to OpenAI function calling format
-fn to_openai_tools(tools: &[ToolSchema]) -> Option<Vec<OpenAiTool>> {
+pub(super) fn to_openai_tools(tools: &[ToolSchema]) -> Option<Vec<OpenAiTool>> {
     if tools.is_empty() {
         return None;
     }
@@ -182,8 +517,87 @@ fn to_openai_tools(tools: &[ToolSchema]) -> Option<Vec<OpenAiTool>> {
     )
 }
 
+pub(super) fn to_llm_response(data: ChatResponse, model: &str) -> anyhow::Result<LlmResponse> {
+    let usage = data.usage.as_ref().map(|usage| usage.to_llm_usage(model));
+    let choice = data
+        .choices
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("OpenAI response missing choices"))?;
+    if choice.finish_reason.as_deref().is_some_and(is_truncated) {
+        tracing::warn!(model, "Response was cut off at the output token limit");
+    }
+
+    // Handle tool calls if present
+    if !choice.message.tool_calls.is_empty() {
+        // Convert OpenAI tool_calls format to our JSON format
+        let tool_calls: Vec<_> = choice
+            .message
+            .tool_calls
+            .iter()
+            .map(|tc| {
+                serde_json::json!({
+                    "name": tc.function.name,
+                    "args": tc.function.args()
+                })
+            })
+            .collect();
+
+        // Enhanced logging for tool call detection
+        let tool_names: Vec<&str> = choice
+            .message
+            .tool_calls
+            .iter()
+            .map(|tc| tc.function.name.as_str())
+            .collect();
+
+        tracing::warn!(
+            "🔧 LLM CALLED {} TOOL(S): {:?}",
+            tool_calls.len(),
+            tool_names
+        );
+
+        // Log argument sizes for debugging
+        for (i, tc) in choice.message.tool_calls.iter().enumerate() {
+            tracing::debug!(
+                "Tool call {}: {} with {} bytes of arguments",
+                i + 1,
+                tc.function.name,
+                tc.function.arguments.to_string().len()
+            );
+        }
+
+        return Ok(LlmResponse {
+            message: AgentMessage {
+                role: MessageRole::Agent,
+                content: MessageContent::Json(serde_json::json!({
+                    "tool_calls": tool_calls
+                })),
+                metadata: None,
+            },
+            usage,
+        });
+    }
+
+    // Regular text response
+    let content = choice.message.content.unwrap_or_else(|| "".to_string());
+
+    Ok(LlmResponse {
+        message: AgentMessage {
+            role: MessageRole::Agent,
+            content: MessageContent::Text(content),
+            metadata: None,
+        },
+        usage,
+    })
+}
+
 #[async_trait]
 impl LanguageModel for OpenAiChatModel {
+    fn model_name(&self) -> Option<String> {
+        Some(self.config.model.clone())
+    }
+
     async fn generate(&self, request: LlmRequest) -> anyhow::Result<LlmResponse> {
         let messages = to_openai_messages(&request);
         let tools = to_openai_tools(&request.tools);
@@ -192,7 +606,10 @@ impl LanguageModel for OpenAiChatModel {
             model: &self.config.model,
             messages: &messages,
             stream: None,
-            tools: tools.clone(),
+            tools,
+            temperature: request.temperature,
+            seed: request.seed,
+            extra: None,
         };
         let url = self
             .config
@@ -205,27 +622,31 @@ impl LanguageModel for OpenAiChatModel {
             "OpenAI request: model={}, messages={}, tools={}",
             self.config.model,
             messages.len(),
-            tools.as_ref().map(|t| t.len()).unwrap_or(0)
+            request.tools.len()
         );
         for (i, msg) in messages.iter().enumerate() {
             tracing::debug!(
                 "Message {}: role={}, content_len={}",
                 i,
                 msg.role,
-                msg.content.len()
+                msg.content.text_len()
             );
-            if msg.content.len() < 500 {
-                tracing::debug!("Message {} content: {}", i, msg.content);
+            if let OpenAiContent::Text(text) = &msg.content {
+                if text.len() < 500 {
+                    tracing::debug!("Message {} content: {}", i, text);
+                }
             }
         }
 
-        let mut request = self.client.post(url).bearer_auth(&self.config.api_key);
-
-        for (key, value) in &self.config.custom_headers {
-            request = request.header(key, value);
-        }
-
-        let response = request.json(&body).send().await?;
+        let body = super::encode_json(&body)?;
+        let response = self
+            .send(|key| {
+                Ok(super::json_body(
+                    self.authorize(self.client.post(url), key),
+                    &body,
+                ))
+            })
+            .await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -238,74 +659,8 @@ impl LanguageModel for OpenAiChatModel {
             ));
         }
 
-        let data: ChatResponse = response.json().await?;
-        let choice = data
-            .choices
-            .into_iter()
-            .next()
-            .ok_or_else(|| anyhow::anyhow!("OpenAI response missing choices"))?;
-
-        // Handle tool calls if present
-        if !choice.message.tool_calls.is_empty() {
-            // Convert OpenAI tool_calls format to our JSON format
-            let tool_calls: Vec<_> = choice
-                .message
-                .tool_calls
-                .iter()
-                .map(|tc| {
-                    serde_json::json!({
-                        "name": tc.function.name,
-                        "args": serde_json::from_str::<serde_json::Value>(&tc.function.arguments)
-                            .unwrap_or_else(|_| serde_json::json!({}))
-                    })
-                })
-                .collect();
-
-            // Enhanced logging for tool call detection
-            let tool_names: Vec<&str> = choice
-                .message
-                .tool_calls
-                .iter()
-                .map(|tc| tc.function.name.as_str())
-                .collect();
-
-            tracing::warn!(
-                "🔧 LLM CALLED {} TOOL(S): {:?}",
-                tool_calls.len(),
-                tool_names
-            );
-
-            // Log argument sizes for debugging
-            for (i, tc) in choice.message.tool_calls.iter().enumerate() {
-                tracing::debug!(
-                    "Tool call {}: {} with {} bytes of arguments",
-                    i + 1,
-                    tc.function.name,
-                    tc.function.arguments.len()
-                );
-            }
-
-            return Ok(LlmResponse {
-                message: AgentMessage {
-                    role: MessageRole::Agent,
-                    content: MessageContent::Json(serde_json::json!({
-                        "tool_calls": tool_calls
-                    })),
-                    metadata: None,
-                },
-            });
-        }
-
-        // Regular text response
-        let content = choice.message.content.unwrap_or_else(|| "".to_string());
-
-        Ok(LlmResponse {
-            message: AgentMessage {
-                role: MessageRole::Agent,
-                content: MessageContent::Text(content),
-                metadata: None,
-            },
-        })
+        let data: ChatResponse = super::decode_json(response).await?;
+        to_llm_response(data, &self.config.model)
     }
 
     async fn generate_stream(&self, request: LlmRequest) -> anyhow::Result<ChunkStream> {
@@ -317,6 +672,9 @@ impl LanguageModel for OpenAiChatModel {
             messages: &messages,
             stream: Some(true),
             tools,
+            temperature: request.temperature,
+            seed: request.seed,
+            extra: None,
         };
         let url = self
             .config
@@ -331,13 +689,9 @@ impl LanguageModel for OpenAiChatModel {
             request.tools.len()
         );
 
-        let mut http_request = self.client.post(url).bearer_auth(&self.config.api_key);
-
-        for (key, value) in &self.config.custom_headers {
-            http_request = http_request.header(key, value);
-        }
-
-        let response = http_request.json(&body).send().await?;
+        let response = self
+            .send(|key| Ok(self.authorize(self.client.post(url), key).json(&body)))
+            .await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -350,173 +704,380 @@ impl LanguageModel for OpenAiChatModel {
             ));
         }
 
-        // Create stream from SSE response
-        let stream = response.bytes_stream();
-        let accumulated_content = Arc::new(Mutex::new(String::new()));
-        let buffer = Arc::new(Mutex::new(String::new()));
-
-        let is_done = Arc::new(Mutex::new(false));
-
-        // Clone Arcs for use in finale
-        let final_accumulated = accumulated_content.clone();
-        let final_is_done = is_done.clone();
+        Ok(chunk_stream(response))
+    }
+}
 
-        let chunk_stream = stream.map(move |result| {
-            let accumulated = accumulated_content.clone();
-            let buffer = buffer.clone();
-            let is_done = is_done.clone();
+/// Chunks of a streamed chat completion response.
+pub(super) fn chunk_stream(response: reqwest::Response) -> ChunkStream {
+    let state = Arc::new(Mutex::new(StreamState::default()));
+    let finale_state = state.clone();
+
+    let chunk_stream = response.bytes_stream().flat_map(move |result| {
+        let mut state = state.lock().unwrap();
+        let chunks = match result {
+            Ok(bytes) => state.push(&bytes),
+            // Stream ended - finish with what was received, if anything
+            Err(e) => match state.finish() {
+                Some(done) => vec![Ok(done)],
+                None if state.done => Vec::new(),
+                None => vec![Err(anyhow::anyhow!("Stream error: {}", e))],
+            },
+        };
+        futures::stream::iter(chunks)
+    });
 
-            // Check if we're already done
-            if *is_done.lock().unwrap() {
-                return Ok(StreamChunk::TextDelta(String::new()));
-            }
+    // Chain a final chunk to ensure Done is sent when stream completes
+    let stream_with_finale = chunk_stream.chain(
+        futures::stream::once(async move { finale_state.lock().unwrap().finish() })
+            .filter_map(|done| async move { done.map(Ok) }),
+    );
 
-            match result {
-                Ok(bytes) => {
-                    let text = String::from_utf8_lossy(&bytes);
+    Box::pin(stream_with_finale)
+}
 
-                    // Append to buffer
-                    buffer.lock().unwrap().push_str(&text);
+impl OpenAiChatModel {
+    /// Base API url, derived from a custom chat completions url when one is set.
+    fn api_base(&self) -> &str {
+        self.config
+            .api_url
+            .as_deref()
+            .and_then(|url| url.strip_suffix("/chat/completions"))
+            .unwrap_or("https://api.openai.com/v1")
+    }
 
-                    let mut buf = buffer.lock().unwrap();
+    fn authorize(
+        &self,
+        mut request: reqwest::RequestBuilder,
+        api_key: &str,
+    ) -> reqwest::RequestBuilder {
+        request = request.bearer_auth(api_key);
+        for (key, value) in &self.config.custom_headers {
+            request = request.header(key, value);
+        }
+        super::with_correlation_id(request)
+    }
 
-                    // Process complete SSE messages (separated by \n\n)
-                    let mut collected_deltas = String::new();
-                    let mut found_done = false;
-                    let mut found_finish = false;
+    /// Send a request authorized with the current API key.
+    async fn send(
+        &self,
+        build: impl Fn(&str) -> anyhow::Result<reqwest::RequestBuilder>,
+    ) -> anyhow::Result<reqwest::Response> {
+        send_authorized(
+            "openai",
+            self.credential.as_ref(),
+            &self.config.api_key,
+            build,
+        )
+        .await
+    }
 
-                    // Split on double newline to get complete SSE messages
-                    let parts: Vec<&str> = buf.split("\n\n").collect();
-                    let complete_messages = if parts.len() > 1 {
-                        &parts[..parts.len() - 1] // All but last (potentially incomplete)
-                    } else {
-                        &[] // No complete messages yet
-                    };
+    async fn fetch_batch(&self, batch_id: &str) -> anyhow::Result<OpenAiBatch> {
+        let url = format!("{}/batches/{batch_id}", self.api_base());
+        Ok(self
+            .send(|key| Ok(self.authorize(self.client.get(&url), key)))
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
 
-                    // Process each complete SSE message
-                    for msg in complete_messages {
-                        for line in msg.lines() {
-                            if let Some(data) = line.strip_prefix("data: ") {
-                                let json_str = data.trim();
-
-                                // Check for [DONE] marker
-                                if json_str == "[DONE]" {
-                                    found_done = true;
-                                    break;
-                                }
-
-                                // Parse JSON chunk
-                                match serde_json::from_str::<StreamResponse>(json_str) {
-                                    Ok(chunk) => {
-                                        if let Some(choice) = chunk.choices.first() {
-                                            // Collect delta content
-                                            if let Some(content) = &choice.delta.content {
-                                                if !content.is_empty() {
-                                                    accumulated.lock().unwrap().push_str(content);
-                                                    collected_deltas.push_str(content);
-                                                }
-                                            }
-
-                                            // Check if stream is finished
-                                            if choice.finish_reason.is_some() {
-                                                found_finish = true;
-                                            }
-                                        }
-                                    }
-                                    Err(e) => {
-                                        tracing::debug!("Failed to parse SSE message: {}", e);
-                                    }
-                                }
-                            }
-                        }
-                        if found_done || found_finish {
-                            break;
-                        }
-                    }
+    async fn file_content(&self, file_id: &str) -> anyhow::Result<String> {
+        let url = format!("{}/files/{file_id}/content", self.api_base());
+        Ok(self
+            .send(|key| Ok(self.authorize(self.client.get(&url), key)))
+            .await?
+            .error_for_status()?
+            .text()
+            .await?)
+    }
+}
 
-                    // Clear processed messages from buffer, keep only incomplete part
-                    if !complete_messages.is_empty() {
-                        *buf = parts.last().unwrap_or(&"").to_string();
-                    }
+#[derive(Deserialize)]
+struct OpenAiFile {
+    id: String,
+}
 
-                    // Handle completion
-                    if found_done || found_finish {
-                        let content = accumulated.lock().unwrap().clone();
-                        let final_message = AgentMessage {
-                            role: MessageRole::Agent,
-                            content: MessageContent::Text(content),
-                            metadata: None,
-                        };
-                        *is_done.lock().unwrap() = true;
-                        buf.clear();
-                        return Ok(StreamChunk::Done {
-                            message: final_message,
-                        });
-                    }
+#[derive(Deserialize)]
+struct OpenAiBatch {
+    id: String,
+    status: String,
+    #[serde(default)]
+    request_counts: Option<OpenAiBatchCounts>,
+    output_file_id: Option<String>,
+    error_file_id: Option<String>,
+    #[serde(default)]
+    errors: Option<serde_json::Value>,
+}
 
-                    // Return collected deltas (may be empty)
-                    if !collected_deltas.is_empty() {
-                        return Ok(StreamChunk::TextDelta(collected_deltas));
-                    }
+#[derive(Deserialize)]
+struct OpenAiBatchCounts {
+    #[serde(default)]
+    total: u32,
+    #[serde(default)]
+    completed: u32,
+    #[serde(default)]
+    failed: u32,
+}
 
-                    Ok(StreamChunk::TextDelta(String::new()))
-                }
-                Err(e) => {
-                    // Stream ended - check if we have accumulated content
-                    if !*is_done.lock().unwrap() {
-                        let content = accumulated.lock().unwrap().clone();
-                        if !content.is_empty() {
-                            let final_message = AgentMessage {
-                                role: MessageRole::Agent,
-                                content: MessageContent::Text(content),
-                                metadata: None,
-                            };
-                            *is_done.lock().unwrap() = true;
-                            return Ok(StreamChunk::Done {
-                                message: final_message,
-                            });
-                        }
-                    }
-                    Err(anyhow::anyhow!("Stream error: {}", e))
+impl OpenAiBatch {
+    fn status(&self) -> BatchStatus {
+        match self.status.as_str() {
+            "completed" => BatchStatus::Completed,
+            "failed" | "expired" | "cancelled" => BatchStatus::Failed {
+                reason: self
+                    .errors
+                    .as_ref()
+                    .and_then(|errors| errors.pointer("/data/0/message"))
+                    .and_then(|message| message.as_str())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("batch {}", self.status)),
+            },
+            _ => {
+                let counts = self.request_counts.as_ref();
+                BatchStatus::InProgress {
+                    completed: counts.map_or(0, |c| c.completed + c.failed),
+                    total: counts.map_or(0, |c| c.total),
                 }
             }
-        });
+        }
+    }
+}
 
-        // Chain a final chunk to ensure Done is sent when stream completes
-        let stream_with_finale = chunk_stream.chain(futures::stream::once(async move {
-            // Check if we already sent Done
-            if !*final_is_done.lock().unwrap() {
-                let content = final_accumulated.lock().unwrap().clone();
-                if !content.is_empty() {
-                    let final_message = AgentMessage {
-                        role: MessageRole::Agent,
-                        content: MessageContent::Text(content),
-                        metadata: None,
-                    };
-                    let content_text = match &final_message.content {
-                        MessageContent::Text(t) => t.as_str(),
-                        _ => "non-text",
-                    };
-                    tracing::debug!(
-                        "Stream ended naturally, sending final Done chunk with {} chars",
-                        content_text.len()
+/// One line of a batch's output or error file.
+#[derive(Deserialize)]
+struct OpenAiBatchLine {
+    custom_id: String,
+    #[serde(default)]
+    response: Option<OpenAiBatchResponse>,
+    #[serde(default)]
+    error: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiBatchResponse {
+    status_code: u16,
+    body: serde_json::Value,
+}
+
+fn parse_batch_line(line: &str, model: &str) -> anyhow::Result<BatchResult> {
+    let line: OpenAiBatchLine = serde_json::from_str(line)?;
+    let outcome = match (line.response, line.error) {
+        (Some(response), _) if response.status_code == 200 => {
+            let data: ChatResponse = serde_json::from_value(response.body)?;
+            to_llm_response(data, model).map_err(|e| e.to_string())
+        }
+        (Some(response), _) => Err(response
+            .body
+            .pointer("/error/message")
+            .and_then(|message| message.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("status {}", response.status_code))),
+        (None, error) => Err(error
+            .as_ref()
+            .and_then(|error| error.get("message"))
+            .and_then(|message| message.as_str())
+            .unwrap_or("request failed")
+            .to_string()),
+    };
+    Ok(BatchResult {
+        custom_id: line.custom_id,
+        outcome,
+    })
+}
+
+/// Batch API: requests are uploaded as a JSONL file and billed at half price.
+#[async_trait]
+impl BatchModel for OpenAiChatModel {
+    fn max_batch_size(&self) -> usize {
+        50_000
+    }
+
+    async fn submit_batch(&self, requests: Vec<BatchRequest>) -> anyhow::Result<String> {
+        let mut input = String::new();
+        for batch_request in &requests {
+            let messages = to_openai_messages(&batch_request.request);
+            let body = ChatRequest {
+                model: &self.config.model,
+                messages: &messages,
+                stream: None,
+                tools: to_openai_tools(&batch_request.request.tools),
+                temperature: batch_request.request.temperature,
+                seed: batch_request.request.seed,
+                extra: None,
+            };
+            input.push_str(&serde_json::to_string(&serde_json::json!({
+                "custom_id": batch_request.custom_id,
+                "method": "POST",
+                "url": "/v1/chat/completions",
+                "body": body,
+            }))?);
+            input.push('\n');
+        }
+
+        let files_url = format!("{}/files", self.api_base());
+        let file: OpenAiFile = self
+            .send(|key| {
+                // Multipart bodies can't be cloned, so each attempt builds its own
+                let form = reqwest::multipart::Form::new()
+                    .text("purpose", "batch")
+                    .part(
+                        "file",
+                        reqwest::multipart::Part::text(input.clone())
+                            .file_name("batch.jsonl")
+                            .mime_str("application/jsonl")?,
                     );
-                    return Ok(StreamChunk::Done {
-                        message: final_message,
-                    });
-                }
-            }
-            // Return empty delta if already done or no content
-            Ok(StreamChunk::TextDelta(String::new()))
-        }));
+                Ok(self
+                    .authorize(self.client.post(&files_url), key)
+                    .multipart(form))
+            })
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let batches_url = format!("{}/batches", self.api_base());
+        let body = serde_json::json!({
+            "input_file_id": file.id,
+            "endpoint": "/v1/chat/completions",
+            "completion_window": "24h",
+        });
+        let batch: OpenAiBatch = self
+            .send(|key| {
+                Ok(self
+                    .authorize(self.client.post(&batches_url), key)
+                    .json(&body))
+            })
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        tracing::info!(batch_id = %batch.id, requests = requests.len(), "Submitted OpenAI batch");
+        Ok(batch.id)
+    }
 
-        Ok(Box::pin(stream_with_finale))
+    async fn batch_status(&self, batch_id: &str) -> anyhow::Result<BatchStatus> {
+        Ok(self.fetch_batch(batch_id).await?.status())
+    }
+
+    async fn batch_results(&self, batch_id: &str) -> anyhow::Result<Vec<BatchResult>> {
+        let batch = self.fetch_batch(batch_id).await?;
+        let mut results = Vec::new();
+        // Successful requests land in the output file, failed ones in the error file
+        for file_id in [batch.output_file_id, batch.error_file_id]
+            .into_iter()
+            .flatten()
+        {
+            let content = self.file_content(&file_id).await?;
+            for line in content.lines().filter(|line| !line.trim().is_empty()) {
+                results.push(parse_batch_line(line, &self.config.model)?);
+            }
+        }
+        Ok(results)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use agents_core::messaging::ImageData;
+
+    #[test]
+    fn openai_messages_send_images_as_data_url_parts() {
+        let request = LlmRequest::new(
+            "You are helpful",
+            vec![AgentMessage {
+                role: MessageRole::Tool,
+                content: MessageContent::Image {
+                    text: None,
+                    images: vec![ImageData::new("image/png", "iVBORw0KGgo=")],
+                },
+                metadata: None,
+            }],
+        );
+        let messages = to_openai_messages(&request);
+        assert_eq!(
+            serde_json::to_value(&messages[1]).unwrap(),
+            serde_json::json!({
+                "role": "user",
+                "content": [{
+                    "type": "image_url",
+                    "image_url": { "url": "data:image/png;base64,iVBORw0KGgo=" }
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn tool_call_arguments_may_be_objects_without_ids() {
+        // Text Generation Inference sends the arguments as an object and no call id
+        let data: ChatResponse = serde_json::from_value(serde_json::json!({
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "function": { "name": "lookup", "arguments": { "id": 7 } }
+                    }]
+                },
+                "finish_reason": "eos_token"
+            }]
+        }))
+        .unwrap();
+        let response = to_llm_response(data, "tgi").unwrap();
+        assert_eq!(
+            response.message.content,
+            MessageContent::Json(serde_json::json!({
+                "tool_calls": [{ "name": "lookup", "args": { "id": 7 } }]
+            }))
+        );
+    }
+
+    #[test]
+    fn streamed_tool_calls_are_announced_before_their_arguments_complete() {
+        let event = |delta: serde_json::Value| {
+            format!(
+                "data: {}\n\n",
+                serde_json::json!({ "choices": [{ "delta": delta, "finish_reason": null }] })
+            )
+        };
+        let mut state = StreamState::default();
+
+        let first = event(serde_json::json!({ "tool_calls": [{
+            "index": 0,
+            "id": "call_1",
+            "type": "function",
+            "function": { "name": "write_file", "arguments": "{\"path\": \"notes.md\", \"con" }
+        }] }));
+        // An event split across network reads is processed once complete
+        let (head, tail) = first.split_at(40);
+        assert!(state.push(head.as_bytes()).is_empty());
+        let chunks = state.push(tail.as_bytes());
+        assert!(matches!(
+            chunks.as_slice(),
+            [Ok(StreamChunk::ToolCallStarted { index: 0, name, partial_args })]
+                if name == "write_file" && partial_args == &serde_json::json!({ "path": "notes.md" })
+        ));
+
+        let rest = event(serde_json::json!({ "tool_calls": [{
+            "index": 0,
+            "function": { "arguments": "tent\": \"draft\"}" }
+        }] }));
+        assert!(state.push(rest.as_bytes()).is_empty());
+
+        let chunks = state.push(b"data: [DONE]\n\n");
+        let [Ok(StreamChunk::Done { message })] = chunks.as_slice() else {
+            panic!("expected the final message");
+        };
+        assert_eq!(
+            message.content,
+            MessageContent::Json(serde_json::json!({ "tool_calls": [{
+                "name": "write_file",
+                "args": { "path": "notes.md", "content": "draft" }
+            }] }))
+        );
+        assert!(state.finish().is_none());
+    }
 
     #[test]
     fn openai_config_new_initializes_empty_custom_headers() {
@@ -541,4 +1102,54 @@ mod tests {
         assert_eq!(config.custom_headers[1].0, "X-Another-Header");
         assert_eq!(config.custom_headers[1].1, "value2");
     }
+
+    #[tokio::test]
+    async fn openai_config_resolves_secret_references() {
+        let secrets = agents_core::secrets::InMemorySecretsProvider::new()
+            .with_secret("openai/api-key", "sk-live")
+            .with_secret("openai/org", "org-1");
+        let config = OpenAiConfig::new("secret://openai/api-key", "gpt-4")
+            .with_custom_headers(vec![
+                (
+                    "OpenAI-Organization".to_string(),
+                    "secret://openai/org".to_string(),
+                ),
+                ("X-Team".to_string(), "search".to_string()),
+            ])
+            .resolve_secrets(&secrets)
+            .await
+            .unwrap();
+
+        assert_eq!(config.api_key, "sk-live");
+        assert_eq!(config.custom_headers[0].1, "org-1");
+        assert_eq!(config.custom_headers[1].1, "search");
+    }
+
+    #[test]
+    fn openai_batch_lines_map_to_results() {
+        let ok = parse_batch_line(
+            r#"{"custom_id":"input-0","response":{"status_code":200,"body":{"choices":[{"message":{"content":"hi"}}],"usage":{"prompt_tokens":5,"completion_tokens":2}}},"error":null}"#,
+            "gpt-4o-mini",
+        )
+        .unwrap();
+        let response = ok.outcome.unwrap();
+        assert!(matches!(response.message.content, MessageContent::Text(ref t) if t == "hi"));
+        assert_eq!(response.usage.unwrap().output_tokens, 2);
+
+        let rejected = parse_batch_line(
+            r#"{"custom_id":"input-1","response":{"status_code":400,"body":{"error":{"message":"bad request"}}},"error":null}"#,
+            "gpt-4o-mini",
+        )
+        .unwrap();
+        assert_eq!(rejected.custom_id, "input-1");
+        assert_eq!(rejected.outcome.unwrap_err(), "bad request");
+    }
+
+    #[test]
+    fn openai_batch_urls_follow_custom_api_url() {
+        let config = OpenAiConfig::new("key", "gpt-4o-mini")
+            .with_api_url(Some("http://localhost:8080/v1/chat/completions".into()));
+        let model = OpenAiChatModel::new(config).unwrap();
+        assert_eq!(model.api_base(), "http://localhost:8080/v1");
+    }
 }
@@ -0,0 +1,479 @@
+//! Self-hosted OpenAI-compatible servers such as vLLM and Text Generation Inference
+//!
+//! A [`SelfHostedChatModel`] spreads requests over the replicas of one model. Each
+//! request goes to the healthy replica with the fewest requests in flight. A
+//! replica that refuses connections or answers with a server error is taken out of
+//! rotation for a cooldown and the request is retried on the next one. Health
+//! checks against the servers' `/health` endpoint bring replicas back early, or
+//! take them out before a request fails.
+//!
+//! The servers differ from OpenAI in a few ways the provider absorbs: requests
+//! never set `tool_choice`, tool calls may come back without ids and with their
+//! arguments as an object, and finish reasons such as `eos_token` are accepted.
+
+use super::openai::{self, ChatRequest, ChatResponse};
+use agents_core::llm::{ChunkStream, LanguageModel, LlmRequest, LlmResponse};
+use agents_core::secrets::{resolve_secret, SecretsProvider};
+use async_trait::async_trait;
+use reqwest::Client;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+/// How long a failing replica is skipped by default
+pub const DEFAULT_UNHEALTHY_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Replicas and request settings of a self-hosted model.
+///
+/// # Example
+///
+/// ```ignore
+/// let config = SelfHostedConfig::vllm(
+///     ["http://vllm-0:8000", "http://vllm-1:8000"],
+///     "meta-llama/Llama-3.1-8B-Instruct",
+/// );
+/// let model = Arc::new(SelfHostedChatModel::new(config)?);
+/// model.spawn_health_checks(Duration::from_secs(10));
+/// ```
+#[derive(Clone)]
+pub struct SelfHostedConfig {
+    /// Provider name in usage records and the provider keys of `RunOptions`
+    pub name: String,
+    /// Model name sent with each request
+    pub model: String,
+    /// Server roots, e.g. `http://vllm-0:8000`
+    pub base_urls: Vec<String>,
+    /// Bearer token, for servers started with an API key
+    pub api_key: Option<String>,
+    /// How long a replica is skipped after a failed request or health check
+    pub unhealthy_cooldown: Duration,
+    pub custom_headers: Vec<(String, String)>,
+    /// Fields added to every request body, e.g. vLLM sampling parameters
+    pub extra_body: serde_json::Map<String, serde_json::Value>,
+}
+
+impl SelfHostedConfig {
+    pub fn new<I, S>(base_urls: I, model: impl Into<String>) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self {
+            name: "self-hosted".to_string(),
+            model: model.into(),
+            base_urls: base_urls
+                .into_iter()
+                .map(|url| url.as_ref().trim_end_matches('/').to_string())
+                .collect(),
+            api_key: None,
+            unhealthy_cooldown: DEFAULT_UNHEALTHY_COOLDOWN,
+            custom_headers: Vec::new(),
+            extra_body: serde_json::Map::new(),
+        }
+    }
+
+    /// vLLM replicas started with `vllm serve <model>`.
+    pub fn vllm<I, S>(base_urls: I, model: impl Into<String>) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self::new(base_urls, model).with_name("vllm")
+    }
+
+    /// Text Generation Inference replicas. TGI serves a single model and ignores
+    /// the model name, so it is only used in usage records.
+    pub fn tgi<I, S>(base_urls: I, model: impl Into<String>) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self::new(base_urls, model).with_name("tgi")
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn with_unhealthy_cooldown(mut self, cooldown: Duration) -> Self {
+        self.unhealthy_cooldown = cooldown;
+        self
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.custom_headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn with_extra_body(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
+        self.extra_body.insert(key.into(), value);
+        self
+    }
+
+    /// Replace `secret://` references in the API key and custom header values with
+    /// their values from `secrets`.
+    pub async fn resolve_secrets(mut self, secrets: &dyn SecretsProvider) -> anyhow::Result<Self> {
+        if let Some(api_key) = &self.api_key {
+            self.api_key = Some(resolve_secret(secrets, api_key).await?);
+        }
+        self.custom_headers = super::resolve_headers(secrets, self.custom_headers).await?;
+        Ok(self)
+    }
+}
+
+/// Health of one replica, see [`SelfHostedChatModel::replica_health`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplicaHealth {
+    pub base_url: String,
+    pub healthy: bool,
+    pub in_flight: usize,
+}
+
+struct Replica {
+    base_url: String,
+    in_flight: AtomicUsize,
+    /// Skipped until this instant after a failure
+    unhealthy_until: Mutex<Option<Instant>>,
+}
+
+impl Replica {
+    fn is_healthy(&self) -> bool {
+        self.unhealthy_until
+            .lock()
+            .unwrap()
+            .is_none_or(|until| Instant::now() >= until)
+    }
+
+    fn set_healthy(&self, healthy: bool, cooldown: Duration) {
+        *self.unhealthy_until.lock().unwrap() = (!healthy).then(|| Instant::now() + cooldown);
+    }
+}
+
+/// Counts a request against its replica while it is in flight.
+struct InFlight<'a>(&'a Replica);
+
+impl<'a> InFlight<'a> {
+    fn start(replica: &'a Replica) -> Self {
+        replica.in_flight.fetch_add(1, Ordering::SeqCst);
+        Self(replica)
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Chat model served by a pool of self-hosted replicas.
+pub struct SelfHostedChatModel {
+    client: Client,
+    config: SelfHostedConfig,
+    replicas: Vec<Replica>,
+    /// Rotates the starting point among equally loaded replicas
+    next: AtomicUsize,
+}
+
+impl SelfHostedChatModel {
+    pub fn new(config: SelfHostedConfig) -> anyhow::Result<Self> {
+        if config.base_urls.is_empty() {
+            anyhow::bail!("{} needs at least one base URL", config.name);
+        }
+        let replicas = config
+            .base_urls
+            .iter()
+            .map(|base_url| Replica {
+                base_url: base_url.clone(),
+                in_flight: AtomicUsize::new(0),
+                unhealthy_until: Mutex::new(None),
+            })
+            .collect();
+        Ok(Self {
+            client: Client::builder()
+                .user_agent("rust-deep-agents-sdk/0.1")
+                .build()?,
+            config,
+            replicas,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// Current health and load of every replica.
+    pub fn replica_health(&self) -> Vec<ReplicaHealth> {
+        self.replicas
+            .iter()
+            .map(|replica| ReplicaHealth {
+                base_url: replica.base_url.clone(),
+                healthy: replica.is_healthy(),
+                in_flight: replica.in_flight.load(Ordering::SeqCst),
+            })
+            .collect()
+    }
+
+    /// Probe every replica's `/health` endpoint and update its health.
+    pub async fn check_health(&self) {
+        let probes = self.replicas.iter().map(|replica| async move {
+            let mut request = self
+                .client
+                .get(format!("{}/health", replica.base_url))
+                .timeout(Duration::from_secs(5));
+            if let Some(api_key) = &self.config.api_key {
+                request = request.bearer_auth(api_key);
+            }
+            let healthy = request
+                .send()
+                .await
+                .is_ok_and(|response| response.status().is_success());
+            if healthy != replica.is_healthy() {
+                tracing::info!(
+                    provider = %self.config.name,
+                    replica = %replica.base_url,
+                    healthy,
+                    "Replica health changed"
+                );
+            }
+            replica.set_healthy(healthy, self.config.unhealthy_cooldown);
+        });
+        futures::future::join_all(probes).await;
+    }
+
+    /// Check health every `interval` in the background until the model is dropped.
+    pub fn spawn_health_checks(
+        self: &Arc<Self>,
+        interval: Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        let model: Weak<Self> = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let Some(model) = model.upgrade() else {
+                    break;
+                };
+                model.check_health().await;
+            }
+        })
+    }
+
+    /// Replicas in the order a request tries them: healthy ones by load, ties
+    /// broken round-robin, then the unhealthy ones as a last resort.
+    fn candidates(&self) -> Vec<&Replica> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let count = self.replicas.len();
+        let mut order: Vec<_> = (0..count)
+            .map(|offset| &self.replicas[(start + offset) % count])
+            .collect();
+        // Stable, so equally loaded replicas keep their rotated order
+        order.sort_by_key(|replica| {
+            (
+                !replica.is_healthy(),
+                replica.in_flight.load(Ordering::SeqCst),
+            )
+        });
+        order
+    }
+
+    async fn send(&self, body: &bytes::Bytes) -> anyhow::Result<reqwest::Response> {
+        let mut last_error = None;
+        for replica in self.candidates() {
+            let _in_flight = InFlight::start(replica);
+            let mut request = self
+                .client
+                .post(format!("{}/v1/chat/completions", replica.base_url));
+            if let Some(api_key) = &self.config.api_key {
+                request = request.bearer_auth(api_key);
+            }
+            for (name, value) in &self.config.custom_headers {
+                request = request.header(name, value);
+            }
+            let request = super::json_body(super::with_correlation_id(request), body);
+
+            let error = match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(response),
+                Ok(response) if !response.status().is_server_error() => {
+                    // The request itself is at fault; another replica would reject it too
+                    let status = response.status();
+                    let error_text = response.text().await.unwrap_or_default();
+                    anyhow::bail!(
+                        "{} API error: {} - {}",
+                        self.config.name,
+                        status,
+                        error_text
+                    );
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    let error_text = response.text().await.unwrap_or_default();
+                    anyhow::anyhow!(
+                        "{} API error: {} - {}",
+                        self.config.name,
+                        status,
+                        error_text
+                    )
+                }
+                Err(e) => anyhow::Error::new(e).context(format!(
+                    "{} replica {} unreachable",
+                    self.config.name, replica.base_url
+                )),
+            };
+            tracing::warn!(
+                provider = %self.config.name,
+                replica = %replica.base_url,
+                "Replica failed, trying the next one: {:#}",
+                error
+            );
+            replica.set_healthy(false, self.config.unhealthy_cooldown);
+            last_error = Some(error);
+        }
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("{} has no replicas", self.config.name)))
+    }
+
+    fn encode(&self, request: &LlmRequest, stream: bool) -> anyhow::Result<bytes::Bytes> {
+        let messages = openai::to_openai_messages(request);
+        let body = ChatRequest {
+            model: &self.config.model,
+            messages: &messages,
+            stream: stream.then_some(true),
+            tools: openai::to_openai_tools(&request.tools),
+            temperature: request.temperature,
+            seed: request.seed,
+            extra: (!self.config.extra_body.is_empty()).then_some(&self.config.extra_body),
+        };
+        super::encode_json(&body)
+    }
+}
+
+#[async_trait]
+impl LanguageModel for SelfHostedChatModel {
+    fn model_name(&self) -> Option<String> {
+        Some(self.config.model.clone())
+    }
+
+    async fn generate(&self, request: LlmRequest) -> anyhow::Result<LlmResponse> {
+        let body = self.encode(&request, false)?;
+        let response = self.send(&body).await?;
+        let data: ChatResponse = super::decode_json(response).await?;
+        let mut response = openai::to_llm_response(data, &self.config.model)?;
+        if let Some(usage) = &mut response.usage {
+            usage.provider = self.config.name.clone();
+        }
+        Ok(response)
+    }
+
+    async fn generate_stream(&self, request: LlmRequest) -> anyhow::Result<ChunkStream> {
+        let body = self.encode(&request, true)?;
+        let response = self.send(&body).await?;
+        Ok(openai::chunk_stream(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::messaging::MessageContent;
+
+    /// Answers every request with `status`, counting the requests.
+    async fn serve_replica(status: u16, hits: Arc<AtomicUsize>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = vec![0; 64 * 1024];
+                let _ = socket.read(&mut request).await;
+                hits.fetch_add(1, Ordering::SeqCst);
+                let body = if status == 200 {
+                    r#"{"choices":[{"message":{"content":"ok"},"finish_reason":"eos_token"}],"usage":{"prompt_tokens":5,"completion_tokens":1}}"#
+                } else {
+                    "overloaded"
+                };
+                let response = format!(
+                    "HTTP/1.1 {status} X\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn failing_replicas_are_skipped_until_they_recover() {
+        let failing_hits = Arc::new(AtomicUsize::new(0));
+        let healthy_hits = Arc::new(AtomicUsize::new(0));
+        let failing = serve_replica(503, failing_hits.clone()).await;
+        let healthy = serve_replica(200, healthy_hits.clone()).await;
+        let model = SelfHostedChatModel::new(SelfHostedConfig::tgi(
+            [failing.as_str(), healthy.as_str()],
+            "llama",
+        ))
+        .unwrap();
+
+        for _ in 0..4 {
+            let response = model.generate(LlmRequest::new("", vec![])).await.unwrap();
+            assert_eq!(response.message.content, MessageContent::Text("ok".into()));
+            assert_eq!(response.usage.unwrap().provider, "tgi");
+        }
+        // The failing replica is tried at most once, then skipped for the cooldown
+        assert!(failing_hits.load(Ordering::SeqCst) <= 1);
+        assert_eq!(healthy_hits.load(Ordering::SeqCst), 4);
+        let health = model.replica_health();
+        assert_eq!(health[1].in_flight, 0);
+        assert!(health[1].healthy);
+
+        // Health checks take the failing replica out and keep the healthy one in
+        model.check_health().await;
+        let health = model.replica_health();
+        assert!(!health[0].healthy);
+        assert!(health[1].healthy);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried_on_other_replicas() {
+        let first_hits = Arc::new(AtomicUsize::new(0));
+        let second_hits = Arc::new(AtomicUsize::new(0));
+        let first = serve_replica(400, first_hits.clone()).await;
+        let second = serve_replica(400, second_hits.clone()).await;
+        let model =
+            SelfHostedChatModel::new(SelfHostedConfig::vllm([first, second], "llama")).unwrap();
+
+        let error = model
+            .generate(LlmRequest::new("", vec![]))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().starts_with("vllm API error: 400"));
+        assert_eq!(
+            first_hits.load(Ordering::SeqCst) + second_hits.load(Ordering::SeqCst),
+            1
+        );
+        assert!(model.replica_health().iter().all(|replica| replica.healthy));
+    }
+
+    #[test]
+    fn equally_loaded_replicas_take_turns() {
+        let model = SelfHostedChatModel::new(SelfHostedConfig::new(["http://a/", "http://b"], "m"))
+            .unwrap();
+        let first = model.candidates()[0].base_url.clone();
+        let second = model.candidates()[0].base_url.clone();
+        assert_ne!(first, second);
+
+        let busy = model.candidates()[0];
+        let _in_flight = InFlight::start(busy);
+        let idle = model.candidates()[0];
+        assert_ne!(idle.base_url, busy.base_url);
+        assert_eq!(
+            model
+                .replicas
+                .iter()
+                .map(|r| r.base_url.as_str())
+                .collect::<Vec<_>>(),
+            ["http://a", "http://b"]
+        );
+    }
+}
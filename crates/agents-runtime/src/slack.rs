@@ -0,0 +1,363 @@
+//! Slack broadcaster posting agent progress to a channel
+//!
+//! Two ways to reach Slack:
+//!
+//! - an [incoming webhook](https://api.slack.com/messaging/webhooks), which needs
+//!   no bot but always posts top-level messages to the channel it was created for;
+//! - the [Web API](https://api.slack.com/methods/chat.postMessage) with a bot token,
+//!   which keeps each agent thread in one Slack thread: the first message of a
+//!   thread is posted to the channel and later ones reply to it.
+//!
+//! Messages are rendered with [`EventTemplates`], [`EventTemplates::text`] by
+//! default, and split when longer than Slack accepts.
+
+use crate::output_format::OutputFormat;
+use crate::webhook::{send_rate_limited, split_message, EventTemplates};
+use agents_core::events::{AgentEvent, EventBroadcaster};
+use anyhow::Context;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+/// Characters per Slack message; longer texts are split into several messages
+pub const SLACK_MESSAGE_LIMIT: usize = 4000;
+
+/// Agent threads whose Slack thread is remembered before the oldest are forgotten
+const MAX_TRACKED_THREADS: usize = 10_000;
+
+enum SlackTarget {
+    Webhook { url: String },
+    WebApi { token: String, channel: String },
+}
+
+#[derive(Deserialize)]
+struct PostMessageResponse {
+    ok: bool,
+    #[serde(default)]
+    ts: Option<String>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// Event broadcaster posting messages to Slack.
+///
+/// # Example
+///
+/// ```ignore
+/// let slack = SlackBroadcaster::web_api(std::env::var("SLACK_BOT_TOKEN")?, "#agent-ops")?
+///     .with_event_types(["agent_completed", "tool_failed"]);
+/// dispatcher.add_broadcaster(Arc::new(slack));
+/// ```
+pub struct SlackBroadcaster {
+    id: String,
+    client: Client,
+    target: SlackTarget,
+    api_url: String,
+    event_types: Option<HashSet<String>>,
+    templates: EventTemplates,
+    output_format: Option<OutputFormat>,
+    threaded: bool,
+    /// Slack thread (`ts` of its first message) of each agent thread
+    threads: Mutex<(HashMap<String, String>, Vec<String>)>,
+    /// Posts one event at a time, keeping messages and threads in order
+    send_lock: tokio::sync::Mutex<()>,
+}
+
+impl SlackBroadcaster {
+    /// Post through an incoming webhook URL.
+    pub fn webhook(url: impl Into<String>) -> anyhow::Result<Self> {
+        Self::new(SlackTarget::Webhook { url: url.into() })
+    }
+
+    /// Post with `chat.postMessage` as the bot owning `token` (`xoxb-...`), which
+    /// must be a member of `channel`, given by name or id.
+    pub fn web_api(token: impl Into<String>, channel: impl Into<String>) -> anyhow::Result<Self> {
+        Self::new(SlackTarget::WebApi {
+            token: token.into(),
+            channel: channel.into(),
+        })
+    }
+
+    fn new(target: SlackTarget) -> anyhow::Result<Self> {
+        Ok(Self {
+            id: "slack".to_string(),
+            client: Client::builder()
+                .user_agent("rust-deep-agents-sdk/0.1")
+                .build()?,
+            target,
+            api_url: "https://slack.com/api".to_string(),
+            event_types: None,
+            templates: EventTemplates::text(),
+            output_format: None,
+            threaded: true,
+            threads: Mutex::new((HashMap::new(), Vec::new())),
+            send_lock: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    /// Broadcaster id used in delivery logs.
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = id.into();
+        self
+    }
+
+    /// Web API base URL, for GovSlack or a proxy.
+    pub fn with_api_url(mut self, api_url: impl Into<String>) -> Self {
+        self.api_url = api_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Only post events of these types.
+    pub fn with_event_types<I, S>(mut self, event_types: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.event_types = Some(event_types.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Templates rendering the message text of each event type.
+    pub fn with_templates(mut self, templates: EventTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// Rewrite messages for Slack, e.g. with [`OutputFormat::slack`].
+    pub fn with_output_format(mut self, format: OutputFormat) -> Self {
+        self.output_format = Some(format);
+        self
+    }
+
+    /// Post every message to the channel instead of replying in agent threads.
+    pub fn without_threads(mut self) -> Self {
+        self.threaded = false;
+        self
+    }
+
+    fn thread_ts(&self, thread_id: &str) -> Option<String> {
+        self.threads.lock().unwrap().0.get(thread_id).cloned()
+    }
+
+    fn remember_thread(&self, thread_id: &str, ts: String) {
+        let mut guard = self.threads.lock().unwrap();
+        let (threads, order) = &mut *guard;
+        if threads.insert(thread_id.to_string(), ts).is_none() {
+            order.push(thread_id.to_string());
+        }
+        if order.len() > MAX_TRACKED_THREADS {
+            let forgotten: Vec<String> = order.drain(..order.len() / 2).collect();
+            for thread_id in forgotten {
+                threads.remove(&thread_id);
+            }
+        }
+    }
+
+    /// Post one message, returning its `ts` when posted through the Web API.
+    async fn post(
+        &self,
+        text: &str,
+        thread_ts: Option<&str>,
+        correlation_id: &str,
+    ) -> anyhow::Result<Option<String>> {
+        let target = format!("Slack {}", self.id);
+        match &self.target {
+            SlackTarget::Webhook { url } => {
+                let request = self
+                    .client
+                    .post(url)
+                    .header(crate::providers::CORRELATION_ID_HEADER, correlation_id)
+                    .json(&json!({ "text": text }));
+                send_rate_limited(request, &target).await?;
+                Ok(None)
+            }
+            SlackTarget::WebApi { token, channel } => {
+                let mut body = json!({ "channel": channel, "text": text });
+                if let Some(thread_ts) = thread_ts {
+                    body["thread_ts"] = json!(thread_ts);
+                }
+                let request = self
+                    .client
+                    .post(format!("{}/chat.postMessage", self.api_url))
+                    .bearer_auth(token)
+                    .header(crate::providers::CORRELATION_ID_HEADER, correlation_id)
+                    .json(&body);
+                let response: PostMessageResponse = send_rate_limited(request, &target)
+                    .await?
+                    .json()
+                    .await
+                    .with_context(|| {
+                        format!("Invalid chat.postMessage response from {}", target)
+                    })?;
+                // The Web API reports errors in the body of 200 responses
+                if !response.ok {
+                    anyhow::bail!(
+                        "{} chat.postMessage failed: {}",
+                        target,
+                        response
+                            .error
+                            .unwrap_or_else(|| "unknown error".to_string())
+                    );
+                }
+                Ok(response.ts)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl EventBroadcaster for SlackBroadcaster {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn should_broadcast(&self, event: &AgentEvent) -> bool {
+        let event_type = event.event_type_name();
+        self.event_types
+            .as_ref()
+            .is_none_or(|types| types.contains(event_type))
+            && self.templates.covers(event_type)
+    }
+
+    async fn broadcast(&self, event: &AgentEvent) -> anyhow::Result<()> {
+        let text = match self.templates.render(event)? {
+            Some(text) if !text.trim().is_empty() => text,
+            _ => return Ok(()),
+        };
+        let text = match &self.output_format {
+            Some(format) => format.apply(&text),
+            None => text,
+        };
+        let metadata = event.metadata();
+
+        let _send = self.send_lock.lock().await;
+        let mut thread_ts = if self.threaded {
+            self.thread_ts(&metadata.thread_id)
+        } else {
+            None
+        };
+        for chunk in split_message(&text, SLACK_MESSAGE_LIMIT) {
+            let ts = self
+                .post(&chunk, thread_ts.as_deref(), &metadata.correlation_id)
+                .await?;
+            if let (true, None, Some(ts)) = (self.threaded, &thread_ts, ts) {
+                self.remember_thread(&metadata.thread_id, ts.clone());
+                thread_ts = Some(ts);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::events::{EventMetadata, ToolFailedEvent};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn tool_failed(error_message: &str) -> AgentEvent {
+        AgentEvent::ToolFailed(ToolFailedEvent {
+            metadata: EventMetadata::new("thread-1".into(), "run-1".into(), None),
+            tool_name: "search".into(),
+            duration_ms: 12,
+            error_message: error_message.into(),
+            is_recoverable: true,
+            retry_count: 0,
+        })
+    }
+
+    /// Answers requests with `responses` in turn, recording their raw text.
+    async fn serve_slack(responses: Vec<String>, seen: Arc<Mutex<Vec<String>>>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/api", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            for response in responses {
+                let Ok((mut socket, _)) = listener.accept().await else {
+                    return;
+                };
+                let mut request = Vec::new();
+                let mut buf = vec![0; 16 * 1024];
+                // Read until the whole JSON body has arrived
+                while !String::from_utf8_lossy(&request).ends_with('}') {
+                    let read = socket.read(&mut buf).await.unwrap_or(0);
+                    if read == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..read]);
+                }
+                seen.lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&request).to_string());
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        url
+    }
+
+    fn posted(ts: &str) -> String {
+        let body = format!(r#"{{"ok":true,"channel":"C1","ts":"{ts}"}}"#);
+        format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+            body.len()
+        )
+    }
+
+    #[tokio::test]
+    async fn web_api_replies_in_the_thread_of_the_first_message() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let rate_limited =
+            "HTTP/1.1 429 Too Many Requests\r\nretry-after: 0\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+                .to_string();
+        let url = serve_slack(
+            vec![
+                rate_limited,
+                posted("100.1"),
+                posted("100.2"),
+                posted("100.3"),
+            ],
+            seen.clone(),
+        )
+        .await;
+        let slack = SlackBroadcaster::web_api("xoxb-test", "#agent-ops")
+            .unwrap()
+            .with_api_url(&url);
+
+        slack.broadcast(&tool_failed("timed out")).await.unwrap();
+        // Split in two messages, both replying in the thread
+        let long_error = "lorem ipsum ".repeat(400);
+        slack.broadcast(&tool_failed(&long_error)).await.unwrap();
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 4);
+        assert!(seen[0].starts_with("POST /api/chat.postMessage "));
+        assert!(seen[0].contains("authorization: Bearer xoxb-test"));
+        assert_eq!(seen[0], seen[1], "rate-limited request is retried");
+        assert!(seen[1].contains(r##""channel":"#agent-ops""##));
+        assert!(seen[1].contains(r#""text":"Tool search failed after 0 retries: timed out""#));
+        assert!(!seen[1].contains("thread_ts"));
+        for reply in &seen[2..] {
+            assert!(reply.contains(r#""thread_ts":"100.1""#));
+        }
+    }
+
+    #[tokio::test]
+    async fn web_api_errors_fail_the_delivery() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let body = r#"{"ok":false,"error":"not_in_channel"}"#;
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        let url = serve_slack(vec![response], seen).await;
+        let slack = SlackBroadcaster::web_api("xoxb-test", "C1")
+            .unwrap()
+            .with_api_url(&url);
+
+        let error = slack.broadcast(&tool_failed("boom")).await.unwrap_err();
+        assert!(error.to_string().contains("not_in_channel"));
+    }
+}
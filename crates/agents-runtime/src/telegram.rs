@@ -0,0 +1,1178 @@
+//! Telegram bot channel: chats with an agent through the Bot API
+//!
+//! With the `telegram` feature, a [`TelegramChannel`] connects an agent to a bot
+//! created with @BotFather. Updates arrive by long polling
+//! ([`TelegramChannel::run_until`]) or through a webhook ([`TelegramChannel::router`]),
+//! and each chat's messages run on their own thread with
+//! [`DeepAgent::handle_message_for_thread`].
+//!
+//! The bot first answers with a placeholder message, edits it while the run makes
+//! progress - the tool being called, or the text so far for streamed runs - and
+//! finally replaces it with the reply. When a run pauses for human approval, the
+//! bot posts the tool call with Approve and Reject buttons; pressing one resumes
+//! the thread.
+
+use crate::agent::{DeepAgent, RunOptions};
+use crate::output_format::OutputFormat;
+use crate::webhook::{send_rate_limited, split_message};
+use agents_core::approval_store::ApprovalDecision;
+use agents_core::correlation::with_correlation_id;
+use agents_core::events::{AgentEvent, EventBroadcaster};
+use agents_core::hitl::{AgentInterrupt, HitlAction, HitlInterrupt};
+use agents_core::messaging::AgentMessage;
+use agents_core::persistence::ThreadId;
+use agents_core::secrets::{resolve_secret, SecretsProvider};
+use anyhow::Context;
+use async_trait::async_trait;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// Characters per Telegram message; longer replies are split
+pub const TELEGRAM_MESSAGE_LIMIT: usize = 4096;
+
+/// Seconds a `getUpdates` call waits for new updates
+const LONG_POLL_TIMEOUT_SECS: u64 = 30;
+
+/// Wait before polling again after `getUpdates` failed
+const POLL_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Characters of tool arguments shown in an approval request
+const APPROVAL_ARGS_PREVIEW: usize = 1000;
+
+const PLACEHOLDER: &str = "…";
+
+/// Bot of a [`TelegramChannel`].
+#[derive(Clone)]
+pub struct TelegramConfig {
+    /// Token from @BotFather, `123456:ABC-...`
+    pub bot_token: String,
+    /// `secret_token` passed to `setWebhook`; webhook requests without it in the
+    /// `X-Telegram-Bot-Api-Secret-Token` header are rejected. Required to serve
+    /// the webhook.
+    pub webhook_secret: Option<String>,
+    pub api_url: String,
+}
+
+impl std::fmt::Debug for TelegramConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TelegramConfig")
+            .field("bot_token", &"<redacted>")
+            .field(
+                "webhook_secret",
+                &self.webhook_secret.as_ref().map(|_| "<redacted>"),
+            )
+            .field("api_url", &self.api_url)
+            .finish()
+    }
+}
+
+impl TelegramConfig {
+    pub fn new(bot_token: impl Into<String>) -> Self {
+        Self {
+            bot_token: bot_token.into(),
+            webhook_secret: None,
+            api_url: "https://api.telegram.org".to_string(),
+        }
+    }
+
+    pub fn with_webhook_secret(mut self, secret: impl Into<String>) -> Self {
+        self.webhook_secret = Some(secret.into());
+        self
+    }
+
+    /// Bot API base URL, for a local Bot API server or a proxy.
+    pub fn with_api_url(mut self, api_url: impl Into<String>) -> Self {
+        self.api_url = api_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Replace `secret://` references in the bot token and webhook secret with
+    /// their values from `secrets`.
+    pub async fn resolve_secrets(mut self, secrets: &dyn SecretsProvider) -> anyhow::Result<Self> {
+        self.bot_token = resolve_secret(secrets, &self.bot_token).await?;
+        if let Some(secret) = &self.webhook_secret {
+            self.webhook_secret = Some(resolve_secret(secrets, secret).await?);
+        }
+        Ok(self)
+    }
+}
+
+/// A text message sent to the bot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TelegramMessage {
+    pub chat_id: i64,
+    pub message_id: i64,
+    /// Sender, absent for messages posted on behalf of a channel
+    pub user_id: Option<i64>,
+    pub username: Option<String>,
+    /// Text of the message, or caption of a photo or document
+    pub text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Update {
+    update_id: i64,
+    #[serde(default)]
+    message: Option<WireMessage>,
+    #[serde(default)]
+    callback_query: Option<CallbackQuery>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WireMessage {
+    message_id: i64,
+    chat: Chat,
+    #[serde(default)]
+    from: Option<User>,
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    caption: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Chat {
+    id: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct User {
+    id: i64,
+    #[serde(default)]
+    username: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CallbackQuery {
+    id: String,
+    from: User,
+    #[serde(default)]
+    message: Option<WireMessage>,
+    #[serde(default)]
+    data: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ApiResponse<T> {
+    ok: bool,
+    #[serde(default = "Option::default")]
+    result: Option<T>,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+impl WireMessage {
+    fn into_message(self) -> Option<TelegramMessage> {
+        Some(TelegramMessage {
+            chat_id: self.chat.id,
+            message_id: self.message_id,
+            user_id: self.from.as_ref().map(|user| user.id),
+            username: self.from.and_then(|user| user.username),
+            text: self.text.or(self.caption)?,
+        })
+    }
+}
+
+/// What a run has shown of its reply so far.
+enum Progress {
+    Tool(String),
+    Token { sequence: u64, text: String },
+}
+
+/// Reply text shown while the run is in progress.
+#[derive(Default)]
+struct Draft {
+    tokens: BTreeMap<u64, String>,
+    tool: Option<String>,
+}
+
+impl Draft {
+    fn apply(&mut self, progress: Progress) {
+        match progress {
+            Progress::Tool(tool_name) => self.tool = Some(tool_name),
+            // Broadcasts may arrive out of order
+            Progress::Token { sequence, text } => {
+                self.tokens.insert(sequence, text);
+            }
+        }
+    }
+
+    fn text(&self) -> String {
+        if !self.tokens.is_empty() {
+            let text: String = self.tokens.values().map(String::as_str).collect();
+            return text.chars().take(TELEGRAM_MESSAGE_LIMIT).collect();
+        }
+        match &self.tool {
+            Some(tool_name) => format!("⏳ Running {}…", tool_name),
+            None => PLACEHOLDER.to_string(),
+        }
+    }
+}
+
+/// Runs answering a message, by correlation id, with the channel receiving their
+/// progress.
+type LiveReplies = Arc<Mutex<HashMap<String, mpsc::UnboundedSender<Progress>>>>;
+
+/// Forwards the events of runs answering Telegram messages to their reply.
+struct ProgressBroadcaster(LiveReplies);
+
+#[async_trait]
+impl EventBroadcaster for ProgressBroadcaster {
+    fn id(&self) -> &str {
+        "telegram-progress"
+    }
+
+    fn should_broadcast(&self, event: &AgentEvent) -> bool {
+        matches!(
+            event,
+            AgentEvent::ToolStarted(_) | AgentEvent::StreamingToken(_)
+        ) && self
+            .0
+            .lock()
+            .unwrap()
+            .contains_key(&event.metadata().correlation_id)
+    }
+
+    async fn broadcast(&self, event: &AgentEvent) -> anyhow::Result<()> {
+        let progress = match event {
+            AgentEvent::ToolStarted(e) => Progress::Tool(e.tool_name.clone()),
+            AgentEvent::StreamingToken(e) => Progress::Token {
+                sequence: e.sequence,
+                text: e.token.clone(),
+            },
+            _ => return Ok(()),
+        };
+        if let Some(sender) = self.0.lock().unwrap().get(&event.metadata().correlation_id) {
+            // The run may have finished in the meantime
+            let _ = sender.send(progress);
+        }
+        Ok(())
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+}
+
+type ThreadMapper = Arc<dyn Fn(&TelegramMessage) -> ThreadId + Send + Sync>;
+
+/// Telegram bot connector between the Bot API and a [`DeepAgent`].
+///
+/// Each chat gets its own thread, `telegram:{chat_id}` by default, so give the
+/// agent a checkpointer to keep conversations across messages. Progress edits
+/// follow the agent's events and need an event dispatcher, e.g. one added with
+/// [`ConfigurableAgentBuilder::with_event_dispatcher`](crate::agent::ConfigurableAgentBuilder::with_event_dispatcher);
+/// without one the placeholder is only replaced by the reply.
+///
+/// Approvals are decided in the agent's approval store when it has one, so they
+/// are recorded like any other decision; otherwise the thread is resumed directly.
+///
+/// ```ignore
+/// let config = TelegramConfig::new("secret://telegram/bot-token")
+///     .resolve_secrets(secrets.as_ref())
+///     .await?;
+/// let channel = TelegramChannel::new(agent.clone(), config)?
+///     .with_allowed_chats([-1001234567890])
+///     .with_approvers([42]);
+///
+/// // Long polling, without a public URL
+/// channel.run_until(tokio::signal::ctrl_c().map(|_| ())).await;
+///
+/// // Or a webhook registered with setWebhook, which needs its secret token
+/// let config = TelegramConfig::new(bot_token).with_webhook_secret(webhook_secret);
+/// let channel = TelegramChannel::new(agent.clone(), config)?;
+/// let app = app.nest("/telegram", channel.router()?);
+/// ```
+#[derive(Clone)]
+pub struct TelegramChannel {
+    agent: Arc<DeepAgent>,
+    config: Arc<TelegramConfig>,
+    client: Client,
+    thread_mapper: ThreadMapper,
+    error_reply: Option<String>,
+    edit_interval: Duration,
+    allowed_chats: Option<Arc<HashSet<i64>>>,
+    approvers: Option<Arc<HashSet<i64>>>,
+    output_format: Option<OutputFormat>,
+    live: LiveReplies,
+    /// Thread of each approval request awaiting a button press, by call id
+    approvals: Arc<Mutex<HashMap<String, ThreadId>>>,
+}
+
+impl TelegramChannel {
+    pub fn new(agent: Arc<DeepAgent>, config: TelegramConfig) -> anyhow::Result<Self> {
+        let live = LiveReplies::default();
+        agent.add_broadcaster(Arc::new(ProgressBroadcaster(live.clone())));
+        Ok(Self {
+            agent,
+            config: Arc::new(config),
+            client: Client::builder()
+                .user_agent("rust-deep-agents-sdk/0.1")
+                .build()?,
+            thread_mapper: Arc::new(|message| format!("telegram:{}", message.chat_id)),
+            error_reply: None,
+            edit_interval: Duration::from_secs(1),
+            allowed_chats: None,
+            approvers: None,
+            output_format: None,
+            live,
+            approvals: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// Choose the thread of each message, e.g. one per user in group chats.
+    pub fn with_thread_mapper(
+        mut self,
+        mapper: impl Fn(&TelegramMessage) -> ThreadId + Send + Sync + 'static,
+    ) -> Self {
+        self.thread_mapper = Arc::new(mapper);
+        self
+    }
+
+    /// Reply with `text` when a run fails, instead of leaving the sender without answer.
+    pub fn with_error_reply(mut self, text: impl Into<String>) -> Self {
+        self.error_reply = Some(text.into());
+        self
+    }
+
+    /// Minimum time between two edits of a reply in progress, 1 second by default
+    /// to stay within Telegram's rate limits.
+    pub fn with_edit_interval(mut self, interval: Duration) -> Self {
+        self.edit_interval = interval;
+        self
+    }
+
+    /// Only answer messages from these chats; others are ignored.
+    pub fn with_allowed_chats(mut self, chat_ids: impl IntoIterator<Item = i64>) -> Self {
+        self.allowed_chats = Some(Arc::new(chat_ids.into_iter().collect()));
+        self
+    }
+
+    /// Only let these users press approval buttons. Anyone in the chat can by default.
+    pub fn with_approvers(mut self, user_ids: impl IntoIterator<Item = i64>) -> Self {
+        self.approvers = Some(Arc::new(user_ids.into_iter().collect()));
+        self
+    }
+
+    /// Rewrite replies for the channel, e.g. with [`OutputFormat::plain_text`].
+    pub fn with_output_format(mut self, format: OutputFormat) -> Self {
+        self.output_format = Some(format);
+        self
+    }
+
+    /// The bot's webhook, to serve on its own or nest in an existing app.
+    ///
+    /// Fails without a webhook secret: anyone could post updates otherwise, with
+    /// forged chats and approvers.
+    pub fn router(&self) -> anyhow::Result<Router> {
+        if self
+            .config
+            .webhook_secret
+            .as_deref()
+            .is_none_or(str::is_empty)
+        {
+            anyhow::bail!(
+                "The Telegram webhook needs a secret token, see TelegramConfig::with_webhook_secret"
+            );
+        }
+        Ok(Router::new()
+            .route("/", post(webhook))
+            .with_state(self.clone()))
+    }
+
+    /// Serve the webhook on `addr` until the task is dropped.
+    pub async fn serve(self, addr: impl tokio::net::ToSocketAddrs) -> anyhow::Result<()> {
+        let router = self.router()?;
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        tracing::info!(address = %listener.local_addr()?, "Telegram webhook listening");
+        axum::serve(listener, router).await?;
+        Ok(())
+    }
+
+    /// Long-poll the Bot API for updates until `shutdown` completes, then wait for
+    /// the runs in progress.
+    ///
+    /// Telegram only delivers updates by polling while the bot has no webhook.
+    pub async fn run_until<F: Future<Output = ()>>(&self, shutdown: F) {
+        tokio::pin!(shutdown);
+        tracing::info!("Telegram channel started");
+        let mut offset = 0;
+        let mut runs = tokio::task::JoinSet::new();
+        loop {
+            let poll = self.get_updates(offset);
+            let updates = tokio::select! {
+                _ = &mut shutdown => break,
+                updates = poll => updates,
+            };
+            match updates {
+                Ok(updates) => {
+                    for update in updates {
+                        offset = offset.max(update.update_id + 1);
+                        let channel = self.clone();
+                        runs.spawn(async move { channel.handle_update(update).await });
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to poll Telegram updates");
+                    tokio::select! {
+                        _ = &mut shutdown => break,
+                        _ = tokio::time::sleep(POLL_RETRY_DELAY) => {}
+                    }
+                }
+            }
+            // Reap finished runs
+            while runs.try_join_next().is_some() {}
+        }
+        while runs.join_next().await.is_some() {}
+        tracing::info!("Telegram channel stopped");
+    }
+
+    async fn get_updates(&self, offset: i64) -> anyhow::Result<Vec<Update>> {
+        self.call(
+            "getUpdates",
+            &json!({
+                "offset": offset,
+                "timeout": LONG_POLL_TIMEOUT_SECS,
+                "allowed_updates": ["message", "callback_query"],
+            }),
+        )
+        .await
+    }
+
+    async fn handle_update(&self, update: Update) {
+        let update_id = update.update_id;
+        let result = if let Some(query) = update.callback_query {
+            self.handle_callback(query).await
+        } else if let Some(message) = update.message.and_then(WireMessage::into_message) {
+            self.handle_message(message).await
+        } else {
+            Ok(())
+        };
+        if let Err(e) = result {
+            tracing::error!(update_id, error = %e, "Failed to handle Telegram update");
+        }
+    }
+
+    /// Run `message` on its chat's thread, editing the reply while the run is in
+    /// progress, and ask for approval when the run pauses.
+    ///
+    /// Called for every update carrying a message; call it directly to feed
+    /// messages received some other way.
+    pub async fn handle_message(&self, message: TelegramMessage) -> anyhow::Result<()> {
+        if let Some(allowed) = &self.allowed_chats {
+            if !allowed.contains(&message.chat_id) {
+                tracing::debug!(
+                    chat_id = message.chat_id,
+                    "Ignoring message from chat not allowed"
+                );
+                return Ok(());
+            }
+        }
+        let chat_id = message.chat_id;
+        let thread_id = (self.thread_mapper)(&message);
+        let placeholder = self
+            .send_message(chat_id, PLACEHOLDER, Some(message.message_id), None)
+            .await?;
+
+        let (result, shown) = self
+            .run_with_progress(&thread_id, &message, placeholder)
+            .await;
+        let reply = match result {
+            Ok(reply) => reply.content.text().into_owned(),
+            Err(e) => match &self.error_reply {
+                Some(error_reply) => {
+                    tracing::error!(thread_id = %thread_id, error = %e, "Telegram message run failed");
+                    error_reply.clone()
+                }
+                None => {
+                    self.delete_message(chat_id, placeholder).await;
+                    return Err(e);
+                }
+            },
+        };
+        self.finish_reply(chat_id, placeholder, &shown, &reply)
+            .await?;
+        self.request_pending_approval(&thread_id, chat_id).await
+    }
+
+    /// Run the message, applying its progress to the placeholder. Returns the run's
+    /// result and the text the placeholder shows.
+    async fn run_with_progress(
+        &self,
+        thread_id: &ThreadId,
+        message: &TelegramMessage,
+        placeholder: i64,
+    ) -> (anyhow::Result<AgentMessage>, String) {
+        let correlation_id = format!("telegram:{}:{}", message.chat_id, message.message_id);
+        let (sender, mut progress) = mpsc::unbounded_channel();
+        self.live
+            .lock()
+            .unwrap()
+            .insert(correlation_id.clone(), sender);
+
+        let mut options = RunOptions::new();
+        options.output_format = self.output_format.clone();
+        let run = with_correlation_id(
+            correlation_id.clone(),
+            self.agent
+                .handle_message_for_thread_with_options(thread_id, &message.text, options),
+        );
+        tokio::pin!(run);
+        let mut draft = Draft::default();
+        let mut shown = PLACEHOLDER.to_string();
+        let mut last_edit = Instant::now();
+        let result = loop {
+            tokio::select! {
+                result = &mut run => break result,
+                Some(update) = progress.recv() => {
+                    draft.apply(update);
+                    let text = draft.text();
+                    if text != shown && last_edit.elapsed() >= self.edit_interval {
+                        match self.edit_message(message.chat_id, placeholder, &text, None).await {
+                            Ok(()) => shown = text,
+                            Err(e) => tracing::debug!(error = %e, "Failed to edit Telegram reply"),
+                        }
+                        last_edit = Instant::now();
+                    }
+                }
+            }
+        };
+        self.live.lock().unwrap().remove(&correlation_id);
+        (result, shown)
+    }
+
+    /// Replace the placeholder with the first part of `reply` and send the rest.
+    async fn finish_reply(
+        &self,
+        chat_id: i64,
+        placeholder: i64,
+        shown: &str,
+        reply: &str,
+    ) -> anyhow::Result<()> {
+        if reply.trim().is_empty() {
+            self.delete_message(chat_id, placeholder).await;
+            return Ok(());
+        }
+        for (i, chunk) in split_message(reply, TELEGRAM_MESSAGE_LIMIT)
+            .into_iter()
+            .enumerate()
+        {
+            if i > 0 {
+                self.send_message(chat_id, &chunk, None, None).await?;
+            } else if chunk != shown {
+                // Telegram rejects edits that leave the text unchanged
+                self.edit_message(chat_id, placeholder, &chunk, None)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Post the thread's pending interrupt with Approve and Reject buttons.
+    async fn request_pending_approval(
+        &self,
+        thread_id: &ThreadId,
+        chat_id: i64,
+    ) -> anyhow::Result<()> {
+        let Some(AgentInterrupt::HumanInLoop(interrupt)) = self.agent.current_interrupt() else {
+            return Ok(());
+        };
+        let newly_pending = self
+            .approvals
+            .lock()
+            .unwrap()
+            .insert(interrupt.call_id.clone(), thread_id.clone())
+            .is_none();
+        if !newly_pending {
+            return Ok(());
+        }
+        let keyboard = json!({
+            "inline_keyboard": [[
+                { "text": "Approve", "callback_data": format!("approve:{}", interrupt.call_id) },
+                { "text": "Reject", "callback_data": format!("reject:{}", interrupt.call_id) },
+            ]]
+        });
+        self.send_message(chat_id, &approval_request(&interrupt), None, Some(keyboard))
+            .await?;
+        Ok(())
+    }
+
+    /// Resume the thread with the decision of an approval button.
+    async fn handle_callback(&self, query: CallbackQuery) -> anyhow::Result<()> {
+        let decision = query.data.as_deref().and_then(|data| data.split_once(':'));
+        let (action, call_id) = match decision {
+            Some(("approve", call_id)) => (HitlAction::Accept, call_id),
+            Some(("reject", call_id)) => (HitlAction::Reject { reason: None }, call_id),
+            _ => return self.answer_callback(&query.id, None).await,
+        };
+        if let Some(approvers) = &self.approvers {
+            if !approvers.contains(&query.from.id) {
+                return self
+                    .answer_callback(&query.id, Some("You are not allowed to approve this"))
+                    .await;
+            }
+        }
+        let thread_id = self.approvals.lock().unwrap().remove(call_id);
+        let Some(thread_id) = thread_id else {
+            return self
+                .answer_callback(&query.id, Some("This approval is no longer pending"))
+                .await;
+        };
+        let accepted = matches!(action, HitlAction::Accept);
+        self.answer_callback(
+            &query.id,
+            Some(if accepted { "Approved" } else { "Rejected" }),
+        )
+        .await?;
+
+        let decided_by = match &query.from.username {
+            Some(username) => format!("@{}", username),
+            None => query.from.id.to_string(),
+        };
+        let Some(request) = query.message else {
+            return Ok(());
+        };
+        let chat_id = request.chat.id;
+        // Record the decision on the request, removing its buttons
+        let verdict = if accepted { "Approved" } else { "Rejected" };
+        let text = format!(
+            "{}\n\n{} by {}",
+            request.text.as_deref().unwrap_or_default(),
+            verdict,
+            decided_by
+        );
+        if let Err(e) = self
+            .edit_message(chat_id, request.message_id, &text, None)
+            .await
+        {
+            tracing::debug!(error = %e, "Failed to mark Telegram approval request as decided");
+        }
+
+        let reply = match self.resume(&thread_id, call_id, action, &decided_by).await {
+            Ok(Some(message)) => match &self.output_format {
+                Some(format) => format.apply(&message.content.text()),
+                None => message.content.text().into_owned(),
+            },
+            Ok(None) => return Ok(()),
+            Err(e) => match &self.error_reply {
+                Some(error_reply) => {
+                    tracing::error!(thread_id = %thread_id, error = %e, "Telegram approval resume failed");
+                    error_reply.clone()
+                }
+                None => return Err(e),
+            },
+        };
+        for chunk in split_message(&reply, TELEGRAM_MESSAGE_LIMIT) {
+            if !chunk.trim().is_empty() {
+                self.send_message(chat_id, &chunk, None, None).await?;
+            }
+        }
+        self.request_pending_approval(&thread_id, chat_id).await
+    }
+
+    async fn resume(
+        &self,
+        thread_id: &ThreadId,
+        call_id: &str,
+        action: HitlAction,
+        decided_by: &str,
+    ) -> anyhow::Result<Option<AgentMessage>> {
+        if let Some(approvals) = self.agent.approval_config() {
+            approvals
+                .store
+                .decide(
+                    thread_id,
+                    call_id,
+                    ApprovalDecision::new(action, decided_by),
+                )
+                .await?;
+            return self.agent.resume_thread(thread_id).await;
+        }
+        if !self.agent.load_state(thread_id).await? {
+            return Ok(None);
+        }
+        match self.agent.current_interrupt() {
+            Some(AgentInterrupt::HumanInLoop(interrupt)) if interrupt.call_id == call_id => {}
+            _ => return Ok(None),
+        }
+        let message = self
+            .agent
+            .resume_with_decision(ApprovalDecision::new(action, decided_by))
+            .await?;
+        self.agent.save_state(thread_id).await?;
+        Ok(Some(message))
+    }
+
+    /// Send `text` to a chat, returning the message id.
+    async fn send_message(
+        &self,
+        chat_id: i64,
+        text: &str,
+        reply_to: Option<i64>,
+        reply_markup: Option<Value>,
+    ) -> anyhow::Result<i64> {
+        let mut body = json!({ "chat_id": chat_id, "text": text });
+        if let Some(reply_to) = reply_to {
+            body["reply_parameters"] =
+                json!({ "message_id": reply_to, "allow_sending_without_reply": true });
+        }
+        if let Some(reply_markup) = reply_markup {
+            body["reply_markup"] = reply_markup;
+        }
+        let sent: WireMessage = self.call("sendMessage", &body).await?;
+        Ok(sent.message_id)
+    }
+
+    async fn edit_message(
+        &self,
+        chat_id: i64,
+        message_id: i64,
+        text: &str,
+        reply_markup: Option<Value>,
+    ) -> anyhow::Result<()> {
+        let mut body = json!({ "chat_id": chat_id, "message_id": message_id, "text": text });
+        if let Some(reply_markup) = reply_markup {
+            body["reply_markup"] = reply_markup;
+        }
+        self.call::<Value>("editMessageText", &body).await?;
+        Ok(())
+    }
+
+    async fn delete_message(&self, chat_id: i64, message_id: i64) {
+        let body = json!({ "chat_id": chat_id, "message_id": message_id });
+        if let Err(e) = self.call::<Value>("deleteMessage", &body).await {
+            tracing::debug!(error = %e, "Failed to delete Telegram message");
+        }
+    }
+
+    async fn answer_callback(&self, query_id: &str, text: Option<&str>) -> anyhow::Result<()> {
+        let mut body = json!({ "callback_query_id": query_id });
+        if let Some(text) = text {
+            body["text"] = json!(text);
+        }
+        self.call::<Value>("answerCallbackQuery", &body).await?;
+        Ok(())
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, body: &Value) -> anyhow::Result<T> {
+        let request = self
+            .client
+            .post(format!(
+                "{}/bot{}/{}",
+                self.config.api_url, self.config.bot_token, method
+            ))
+            .json(body);
+        let response: ApiResponse<T> = send_rate_limited(request, "Telegram Bot API")
+            .await?
+            .json()
+            .await
+            .with_context(|| format!("Invalid {} response from the Telegram Bot API", method))?;
+        match response.result {
+            Some(result) if response.ok => Ok(result),
+            _ => anyhow::bail!(
+                "Telegram {} failed: {}",
+                method,
+                response
+                    .description
+                    .unwrap_or_else(|| "unknown error".to_string())
+            ),
+        }
+    }
+}
+
+/// Text asking to approve a paused tool call.
+fn approval_request(interrupt: &HitlInterrupt) -> String {
+    let mut text = format!("Approval needed to run {}", interrupt.tool_name);
+    if let Some(note) = &interrupt.policy_note {
+        text.push('\n');
+        text.push_str(note);
+    }
+    let args = serde_json::to_string_pretty(&interrupt.tool_args).unwrap_or_default();
+    text.push_str("\n\n");
+    text.push_str(&agents_core::security::truncate_string(
+        &args,
+        APPROVAL_ARGS_PREVIEW,
+    ));
+    text
+}
+
+/// Compare secrets in constant time.
+fn secret_matches(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+async fn webhook(
+    State(channel): State<TelegramChannel>,
+    headers: HeaderMap,
+    Json(update): Json<Update>,
+) -> Response {
+    let secret = channel.config.webhook_secret.as_deref().unwrap_or_default();
+    let given = headers
+        .get("x-telegram-bot-api-secret-token")
+        .and_then(|token| token.to_str().ok())
+        .unwrap_or_default();
+    if secret.is_empty() || !secret_matches(secret, given) {
+        tracing::warn!("Rejected Telegram webhook with invalid secret token");
+        return (StatusCode::UNAUTHORIZED, "Invalid secret token").into_response();
+    }
+    // Runs can outlast the webhook timeout, after which Telegram resends the update
+    tokio::spawn(async move { channel.handle_update(update).await });
+    StatusCode::OK.into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::ConfigurableAgentBuilder;
+    use crate::middleware::HitlPolicy;
+    use agents_core::events::EventDispatcher;
+    use agents_core::llm::{LanguageModel, LlmRequest, LlmResponse};
+    use agents_core::messaging::{MessageContent, MessageRole};
+    use agents_core::persistence::{Checkpointer, InMemoryCheckpointer};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Writes `notes.txt` when asked to save, echoes the user otherwise.
+    struct NotesModel;
+
+    #[async_trait]
+    impl LanguageModel for NotesModel {
+        async fn generate(&self, request: LlmRequest) -> anyhow::Result<LlmResponse> {
+            let last = request
+                .messages
+                .iter()
+                .rev()
+                .find(|m| matches!(m.role, MessageRole::User))
+                .map(|m| m.content.to_text());
+            let content = match last.as_deref() {
+                Some("save notes") => MessageContent::Json(json!({
+                    "tool_calls": [{
+                        "name": "write_file",
+                        "args": { "file_path": "notes.txt", "content": "remember" }
+                    }]
+                })),
+                other => MessageContent::Text(format!("You said: {}", other.unwrap_or_default())),
+            };
+            Ok(LlmResponse::new(AgentMessage {
+                role: MessageRole::Agent,
+                content,
+                metadata: None,
+            }))
+        }
+    }
+
+    /// Answers Bot API calls, recording their method and body. `getUpdates`
+    /// returns `updates` once, then nothing.
+    async fn serve_bot_api(updates: Value, seen: Arc<Mutex<Vec<(String, Value)>>>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut updates = Some(updates);
+            let mut next_message_id = 100;
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buf = vec![0; 16 * 1024];
+                let (head, body) = loop {
+                    let read = socket.read(&mut buf).await.unwrap_or(0);
+                    if read == 0 {
+                        return;
+                    }
+                    request.extend_from_slice(&buf[..read]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    let Some(end) = text.find("\r\n\r\n") else {
+                        continue;
+                    };
+                    let length: usize = text[..end]
+                        .lines()
+                        .find_map(|line| line.strip_prefix("content-length: "))
+                        .and_then(|length| length.trim().parse().ok())
+                        .unwrap_or(0);
+                    if request.len() >= end + 4 + length {
+                        break (text[..end].to_string(), text[end + 4..].to_string());
+                    }
+                };
+                let path = head.split(' ').nth(1).unwrap_or_default();
+                let method = path.rsplit('/').next().unwrap_or_default().to_string();
+                assert!(path.starts_with("/bottest-token/"));
+
+                let result = match method.as_str() {
+                    "getUpdates" => match updates.take() {
+                        Some(updates) => updates,
+                        None => {
+                            tokio::time::sleep(Duration::from_millis(50)).await;
+                            json!([])
+                        }
+                    },
+                    "sendMessage" => {
+                        next_message_id += 1;
+                        json!({ "message_id": next_message_id, "chat": { "id": 1 } })
+                    }
+                    _ => json!(true),
+                };
+                seen.lock()
+                    .unwrap()
+                    .push((method, serde_json::from_str(&body).unwrap_or(Value::Null)));
+                let body = json!({ "ok": true, "result": result }).to_string();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        url
+    }
+
+    /// Waits until the Bot API was called with `method`, returning the bodies of
+    /// all calls so far.
+    async fn wait_for(
+        seen: &Arc<Mutex<Vec<(String, Value)>>>,
+        method: &str,
+    ) -> Vec<(String, Value)> {
+        for _ in 0..200 {
+            let calls = seen.lock().unwrap().clone();
+            if calls.iter().any(|(called, _)| called == method) {
+                return calls;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("Bot API was not called with {}", method);
+    }
+
+    fn agent(checkpointer: Arc<InMemoryCheckpointer>) -> Arc<DeepAgent> {
+        Arc::new(
+            ConfigurableAgentBuilder::new("Notes")
+                .with_model(Arc::new(NotesModel))
+                .with_checkpointer(checkpointer)
+                .with_event_dispatcher(Arc::new(EventDispatcher::new()))
+                .with_tool_interrupt(
+                    "write_file",
+                    HitlPolicy {
+                        allow_auto: false,
+                        note: Some("Writes to the notebook".to_string()),
+                    },
+                )
+                .build()
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn drafts_show_streamed_text_in_order_or_the_running_tool() {
+        let mut draft = Draft::default();
+        assert_eq!(draft.text(), PLACEHOLDER);
+        draft.apply(Progress::Tool("search".into()));
+        assert_eq!(draft.text(), "⏳ Running search…");
+        draft.apply(Progress::Token {
+            sequence: 1,
+            text: " world".into(),
+        });
+        draft.apply(Progress::Token {
+            sequence: 0,
+            text: "Hello".into(),
+        });
+        assert_eq!(draft.text(), "Hello world");
+    }
+
+    #[tokio::test]
+    async fn polled_messages_are_answered_by_editing_the_placeholder() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let updates = json!([
+            {
+                "update_id": 7,
+                "message": {
+                    "message_id": 5,
+                    "chat": { "id": 1, "type": "private" },
+                    "from": { "id": 42, "is_bot": false, "first_name": "Ana", "username": "ana" },
+                    "text": "hello"
+                }
+            },
+            {
+                "update_id": 8,
+                "message": { "message_id": 6, "chat": { "id": 2, "type": "private" }, "text": "hi" }
+            }
+        ]);
+        let api_url = serve_bot_api(updates, seen.clone()).await;
+        let checkpointer = Arc::new(InMemoryCheckpointer::new());
+        let channel = TelegramChannel::new(
+            agent(checkpointer.clone()),
+            TelegramConfig::new("test-token").with_api_url(&api_url),
+        )
+        .unwrap()
+        .with_allowed_chats([1]);
+
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let polling = tokio::spawn(async move {
+            channel
+                .run_until(async {
+                    let _ = stopped.await;
+                })
+                .await
+        });
+        let calls = wait_for(&seen, "editMessageText").await;
+        let _ = stop.send(());
+        polling.await.unwrap();
+
+        let calls: Vec<_> = calls
+            .into_iter()
+            .filter(|(method, _)| method != "getUpdates")
+            .collect();
+        assert_eq!(calls.len(), 2, "chat 2 is not answered: {:?}", calls);
+        assert_eq!(calls[0].0, "sendMessage");
+        assert_eq!(calls[0].1["text"], PLACEHOLDER);
+        assert_eq!(calls[0].1["reply_parameters"]["message_id"], 5);
+        assert_eq!(
+            calls[1].1,
+            json!({ "chat_id": 1, "message_id": 101, "text": "You said: hello" })
+        );
+        let polled = seen.lock().unwrap().clone();
+        assert!(polled
+            .iter()
+            .any(|(method, body)| method == "getUpdates" && body["offset"] == 9));
+        assert!(checkpointer
+            .load_state(&"telegram:1".to_string())
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn webhook_requires_a_secret_token() {
+        let checkpointer = Arc::new(InMemoryCheckpointer::new());
+        for config in [
+            TelegramConfig::new("test-token"),
+            TelegramConfig::new("test-token").with_webhook_secret(""),
+        ] {
+            let channel = TelegramChannel::new(agent(checkpointer.clone()), config).unwrap();
+            assert!(channel.router().is_err());
+            assert!(channel.serve("127.0.0.1:0").await.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn approval_buttons_resume_the_paused_thread() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let api_url = serve_bot_api(json!([]), seen.clone()).await;
+        let checkpointer = Arc::new(InMemoryCheckpointer::new());
+        let channel = TelegramChannel::new(
+            agent(checkpointer.clone()),
+            TelegramConfig::new("test-token")
+                .with_api_url(&api_url)
+                .with_webhook_secret("s3cret"),
+        )
+        .unwrap()
+        .with_approvers([42]);
+
+        channel
+            .handle_message(TelegramMessage {
+                chat_id: 1,
+                message_id: 5,
+                user_id: Some(42),
+                username: Some("ana".into()),
+                text: "save notes".into(),
+            })
+            .await
+            .unwrap();
+        let calls = seen.lock().unwrap().clone();
+        let (_, request) = calls
+            .iter()
+            .find(|(method, body)| method == "sendMessage" && body.get("reply_markup").is_some())
+            .expect("approval request sent");
+        assert!(request["text"]
+            .as_str()
+            .unwrap()
+            .starts_with("Approval needed to run write_file\nWrites to the notebook"));
+        let approve = request["reply_markup"]["inline_keyboard"][0][0]["callback_data"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert!(approve.starts_with("approve:call_"));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let webhook_url = format!("http://{}/telegram", listener.local_addr().unwrap());
+        let app = Router::new().nest("/telegram", channel.router().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let press = |user_id: i64| {
+            json!({
+                "update_id": 9,
+                "callback_query": {
+                    "id": format!("cb-{}", user_id),
+                    "from": { "id": user_id, "is_bot": false, "first_name": "Ana", "username": "ana" },
+                    "message": { "message_id": 102, "chat": { "id": 1 }, "text": "Approval needed" },
+                    "data": approve,
+                }
+            })
+        };
+        let client = Client::new();
+        let forged = client
+            .post(&webhook_url)
+            .header("x-telegram-bot-api-secret-token", "guess")
+            .json(&press(42))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(forged.status(), StatusCode::UNAUTHORIZED);
+        let unsigned = client
+            .post(&webhook_url)
+            .json(&press(42))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(unsigned.status(), StatusCode::UNAUTHORIZED);
+
+        // Only approvers can decide
+        client
+            .post(&webhook_url)
+            .header("x-telegram-bot-api-secret-token", "s3cret")
+            .json(&press(7))
+            .send()
+            .await
+            .unwrap();
+        let calls = wait_for(&seen, "answerCallbackQuery").await;
+        let (_, refused) = calls.last().unwrap();
+        assert_eq!(refused["callback_query_id"], "cb-7");
+        assert_eq!(refused["text"], "You are not allowed to approve this");
+
+        let response = client
+            .post(&webhook_url)
+            .header("x-telegram-bot-api-secret-token", "s3cret")
+            .json(&press(42))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        for _ in 0..200 {
+            let decided = seen
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|(_, body)| body["text"] == "Approval needed\n\nApproved by @ana");
+            let state = checkpointer
+                .load_state(&"telegram:1".to_string())
+                .await
+                .unwrap()
+                .unwrap();
+            if decided && state.files.contains_key("notes.txt") {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("approval was not applied: {:?}", seen.lock().unwrap());
+    }
+}
@@ -0,0 +1,129 @@
+//! Planners and models shared by the crate's tests.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use agents_core::agent::{PlannerAction, PlannerContext, PlannerDecision, PlannerHandle};
+use agents_core::llm::{LanguageModel, LlmRequest, LlmResponse};
+use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+use agents_core::state::AgentStateSnapshot;
+use async_trait::async_trait;
+
+fn agent_text(text: impl Into<String>) -> AgentMessage {
+    AgentMessage {
+        role: MessageRole::Agent,
+        content: MessageContent::Text(text.into()),
+        metadata: None,
+    }
+}
+
+/// Answers every prompt with the same text.
+pub(crate) struct ReplyPlanner(pub &'static str);
+
+#[async_trait]
+impl PlannerHandle for ReplyPlanner {
+    async fn plan(
+        &self,
+        _context: PlannerContext,
+        _state: Arc<AgentStateSnapshot>,
+    ) -> anyhow::Result<PlannerDecision> {
+        Ok(PlannerDecision {
+            next_action: PlannerAction::Respond {
+                message: agent_text(self.0),
+            },
+        })
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Sleeps on every step, then answers "done" or keeps calling an unknown tool.
+pub(crate) struct SlowPlanner {
+    pub delay: Duration,
+    pub respond: bool,
+}
+
+impl SlowPlanner {
+    pub(crate) fn answering(delay: Duration) -> Self {
+        Self {
+            delay,
+            respond: true,
+        }
+    }
+}
+
+#[async_trait]
+impl PlannerHandle for SlowPlanner {
+    async fn plan(
+        &self,
+        _context: PlannerContext,
+        _state: Arc<AgentStateSnapshot>,
+    ) -> anyhow::Result<PlannerDecision> {
+        tokio::time::sleep(self.delay).await;
+        let next_action = if self.respond {
+            PlannerAction::Respond {
+                message: agent_text("done"),
+            }
+        } else {
+            PlannerAction::CallTool {
+                tool_name: "keep_going".into(),
+                payload: serde_json::json!({}),
+            }
+        };
+        Ok(PlannerDecision { next_action })
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Writes `{message}.txt` for the user's message, then answers "done"; sleeps
+/// before every step so concurrent runs interleave.
+pub(crate) struct FileWritingPlanner;
+
+#[async_trait]
+impl PlannerHandle for FileWritingPlanner {
+    async fn plan(
+        &self,
+        context: PlannerContext,
+        _state: Arc<AgentStateSnapshot>,
+    ) -> anyhow::Result<PlannerDecision> {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let last = context.history.last().expect("history has the input");
+        let next_action = match (&last.role, last.content.as_text()) {
+            (MessageRole::User, Some(name)) => PlannerAction::CallTool {
+                tool_name: "write_file".into(),
+                payload: serde_json::json!({
+                    "file_path": format!("{name}.txt"),
+                    "content": name,
+                }),
+            },
+            _ => PlannerAction::Respond {
+                message: agent_text("done"),
+            },
+        };
+        Ok(PlannerDecision { next_action })
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Answers with its prefix followed by the text of the last message.
+pub(crate) struct EchoModel(pub &'static str);
+
+#[async_trait]
+impl LanguageModel for EchoModel {
+    async fn generate(&self, request: LlmRequest) -> anyhow::Result<LlmResponse> {
+        let last = request
+            .messages
+            .last()
+            .map(|message| message.content.to_text())
+            .unwrap_or_default();
+        Ok(LlmResponse::new(agent_text(format!("{}{}", self.0, last))))
+    }
+}
@@ -0,0 +1,508 @@
+//! Twilio messaging channel: SMS and WhatsApp conversations with an agent
+//!
+//! With the `twilio` feature, a [`TwilioChannel`] connects an agent to a Twilio
+//! phone number or WhatsApp sender. Twilio POSTs each incoming message to the
+//! channel's webhook, which runs it with [`DeepAgent::handle_message_for_thread`]
+//! on the thread mapped to the conversation and sends the reply back through the
+//! Messages API.
+//!
+//! The webhook answers right away with an empty TwiML response and the run happens
+//! in the background, since agent runs can outlast Twilio's 15 second webhook
+//! timeout. Requests without a valid `X-Twilio-Signature` are rejected.
+
+use crate::agent::{DeepAgent, RunOptions};
+use crate::output_format::OutputFormat;
+use crate::webhook::{send_rate_limited, split_message};
+use agents_core::correlation::with_correlation_id;
+use agents_core::persistence::ThreadId;
+use agents_core::secrets::{resolve_secret, SecretsProvider};
+use anyhow::Context;
+use axum::extract::{OriginalUri, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Form, Router};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use serde::Deserialize;
+use sha1::Sha1;
+use std::sync::Arc;
+
+/// Characters per message accepted by the Messages API; longer replies are split
+pub const TWILIO_MESSAGE_LIMIT: usize = 1600;
+
+const EMPTY_TWIML: &str = r#"<?xml version="1.0" encoding="UTF-8"?><Response></Response>"#;
+
+/// Account and addresses of a [`TwilioChannel`].
+#[derive(Clone)]
+pub struct TwilioConfig {
+    pub account_sid: String,
+    /// Signs webhooks and authenticates Messages API calls
+    pub auth_token: String,
+    /// Sender of messages that do not reply to an inbound one, e.g. `+15550100`
+    /// or `whatsapp:+15550100`
+    pub from: Option<String>,
+    /// Webhook URL exactly as configured in Twilio, used to check signatures;
+    /// `https://` plus the request's `Host` and path when unset
+    pub webhook_url: Option<String>,
+    pub api_url: String,
+}
+
+impl std::fmt::Debug for TwilioConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TwilioConfig")
+            .field("account_sid", &self.account_sid)
+            .field("auth_token", &"<redacted>")
+            .field("from", &self.from)
+            .field("webhook_url", &self.webhook_url)
+            .field("api_url", &self.api_url)
+            .finish()
+    }
+}
+
+impl TwilioConfig {
+    pub fn new(account_sid: impl Into<String>, auth_token: impl Into<String>) -> Self {
+        Self {
+            account_sid: account_sid.into(),
+            auth_token: auth_token.into(),
+            from: None,
+            webhook_url: None,
+            api_url: "https://api.twilio.com".to_string(),
+        }
+    }
+
+    pub fn with_from(mut self, from: impl Into<String>) -> Self {
+        self.from = Some(from.into());
+        self
+    }
+
+    /// Set the webhook URL when a proxy or load balancer changes the host or path.
+    pub fn with_webhook_url(mut self, webhook_url: impl Into<String>) -> Self {
+        self.webhook_url = Some(webhook_url.into());
+        self
+    }
+
+    pub fn with_api_url(mut self, api_url: impl Into<String>) -> Self {
+        self.api_url = api_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Replace a `secret://` reference in the auth token with its value from `secrets`.
+    pub async fn resolve_secrets(mut self, secrets: &dyn SecretsProvider) -> anyhow::Result<Self> {
+        self.auth_token = resolve_secret(secrets, &self.auth_token).await?;
+        Ok(self)
+    }
+}
+
+/// A message received through a Twilio webhook.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InboundMessage {
+    pub message_sid: String,
+    /// Sender, `+15550123` for SMS or `whatsapp:+15550123`
+    pub from: String,
+    /// Number or WhatsApp sender the message was sent to
+    pub to: String,
+    pub body: String,
+    /// WhatsApp profile name of the sender
+    pub profile_name: Option<String>,
+    /// URLs of attached images, audio and documents
+    pub media_urls: Vec<String>,
+}
+
+impl InboundMessage {
+    /// Read a message from webhook form parameters, `None` when required ones are missing.
+    pub fn from_params(params: &[(String, String)]) -> Option<Self> {
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.clone())
+        };
+        let num_media: usize = param("NumMedia").and_then(|n| n.parse().ok()).unwrap_or(0);
+        Some(Self {
+            message_sid: param("MessageSid")?,
+            from: param("From")?,
+            to: param("To")?,
+            body: param("Body").unwrap_or_default(),
+            profile_name: param("ProfileName").filter(|name| !name.is_empty()),
+            media_urls: (0..num_media)
+                .filter_map(|i| param(&format!("MediaUrl{}", i)))
+                .collect(),
+        })
+    }
+
+    /// The agent's input: the body followed by a line per attachment.
+    fn agent_input(&self) -> String {
+        let mut input = self.body.clone();
+        for url in &self.media_urls {
+            if !input.is_empty() {
+                input.push('\n');
+            }
+            input.push_str(&format!("[Attachment: {}]", url));
+        }
+        input
+    }
+}
+
+type ThreadMapper = Arc<dyn Fn(&InboundMessage) -> ThreadId + Send + Sync>;
+
+#[derive(Deserialize)]
+struct CreatedMessage {
+    sid: String,
+}
+
+/// SMS and WhatsApp connector between Twilio and a [`DeepAgent`].
+///
+/// Each sender gets its own thread, `twilio:{From}` by default, so give the
+/// agent a checkpointer to keep conversations across messages.
+///
+/// ```ignore
+/// let config = TwilioConfig::new(std::env::var("TWILIO_ACCOUNT_SID")?, "secret://twilio/auth-token")
+///     .with_from("whatsapp:+15550100")
+///     .resolve_secrets(secrets.as_ref())
+///     .await?;
+/// let channel = TwilioChannel::new(agent.clone(), config)?
+///     .with_error_reply("Sorry, something went wrong. Please try again.");
+///
+/// // Configure https://example.com/twilio as the number's messaging webhook
+/// let app = app.nest("/twilio", channel.router());
+///
+/// // Outbound messages outside a conversation
+/// channel.send("whatsapp:+15550123", "Your order has shipped").await?;
+/// ```
+#[derive(Clone)]
+pub struct TwilioChannel {
+    agent: Arc<DeepAgent>,
+    config: Arc<TwilioConfig>,
+    client: Client,
+    thread_mapper: ThreadMapper,
+    error_reply: Option<String>,
+    output_format: Option<OutputFormat>,
+}
+
+impl TwilioChannel {
+    pub fn new(agent: Arc<DeepAgent>, config: TwilioConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            agent,
+            config: Arc::new(config),
+            client: Client::builder()
+                .user_agent("rust-deep-agents-sdk/0.1")
+                .build()?,
+            thread_mapper: Arc::new(|message| format!("twilio:{}", message.from)),
+            error_reply: None,
+            output_format: None,
+        })
+    }
+
+    /// Choose the thread of each message, e.g. a customer's existing thread.
+    pub fn with_thread_mapper(
+        mut self,
+        mapper: impl Fn(&InboundMessage) -> ThreadId + Send + Sync + 'static,
+    ) -> Self {
+        self.thread_mapper = Arc::new(mapper);
+        self
+    }
+
+    /// Reply with `text` when a run fails, instead of leaving the sender without answer.
+    pub fn with_error_reply(mut self, text: impl Into<String>) -> Self {
+        self.error_reply = Some(text.into());
+        self
+    }
+
+    /// Rewrite replies for the channel, e.g. with [`OutputFormat::sms`].
+    pub fn with_output_format(mut self, format: OutputFormat) -> Self {
+        self.output_format = Some(format);
+        self
+    }
+
+    /// The messaging webhook, to serve on its own or nest in an existing app.
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route("/", post(webhook))
+            .with_state(self.clone())
+    }
+
+    /// Serve the webhook on `addr` until the task is dropped.
+    pub async fn serve(self, addr: impl tokio::net::ToSocketAddrs) -> anyhow::Result<()> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        tracing::info!(address = %listener.local_addr()?, "Twilio webhook listening");
+        axum::serve(listener, self.router()).await?;
+        Ok(())
+    }
+
+    /// Run `message` on its thread and send the reply to the sender.
+    ///
+    /// Called by the webhook for every valid message; call it directly to feed
+    /// messages received some other way.
+    pub async fn handle_inbound(&self, message: InboundMessage) -> anyhow::Result<()> {
+        let thread_id = (self.thread_mapper)(&message);
+        let mut options = RunOptions::new();
+        options.output_format = self.output_format.clone();
+        let run = self.agent.handle_message_for_thread_with_options(
+            &thread_id,
+            message.agent_input(),
+            options,
+        );
+        let reply = match with_correlation_id(message.message_sid.clone(), run).await {
+            Ok(reply) => reply.content.text().into_owned(),
+            Err(e) => match &self.error_reply {
+                Some(error_reply) => {
+                    tracing::error!(thread_id = %thread_id, error = %e, "Twilio message run failed");
+                    error_reply.clone()
+                }
+                None => return Err(e),
+            },
+        };
+        if !reply.trim().is_empty() {
+            self.send_from(&message.to, &message.from, &reply).await?;
+        }
+        Ok(())
+    }
+
+    /// Send `body` to `to` from the configured sender, returning the message sids.
+    pub async fn send(&self, to: &str, body: &str) -> anyhow::Result<Vec<String>> {
+        let from = self
+            .config
+            .from
+            .as_deref()
+            .context("TwilioConfig::from is required to send messages")?;
+        self.send_from(from, to, body).await
+    }
+
+    async fn send_from(&self, from: &str, to: &str, body: &str) -> anyhow::Result<Vec<String>> {
+        let url = format!(
+            "{}/2010-04-01/Accounts/{}/Messages.json",
+            self.config.api_url, self.config.account_sid
+        );
+        let mut sids = Vec::new();
+        for chunk in split_message(body, TWILIO_MESSAGE_LIMIT) {
+            let request = self
+                .client
+                .post(&url)
+                .basic_auth(&self.config.account_sid, Some(&self.config.auth_token))
+                .form(&[("From", from), ("To", to), ("Body", chunk.as_str())]);
+            let created: CreatedMessage = send_rate_limited(request, "Twilio Messages API")
+                .await?
+                .json()
+                .await
+                .context("Invalid Twilio Messages API response")?;
+            sids.push(created.sid);
+        }
+        Ok(sids)
+    }
+}
+
+/// The `X-Twilio-Signature` of a webhook request to `url` with form `params`.
+pub fn twilio_signature(auth_token: &str, url: &str, params: &[(String, String)]) -> String {
+    STANDARD.encode(
+        signature_mac(auth_token, url, params)
+            .finalize()
+            .into_bytes(),
+    )
+}
+
+fn signature_mac(auth_token: &str, url: &str, params: &[(String, String)]) -> Hmac<Sha1> {
+    let mut sorted: Vec<_> = params.iter().collect();
+    sorted.sort();
+    let mut mac =
+        Hmac::<Sha1>::new_from_slice(auth_token.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(url.as_bytes());
+    for (key, value) in sorted {
+        mac.update(key.as_bytes());
+        mac.update(value.as_bytes());
+    }
+    mac
+}
+
+fn signature_is_valid(
+    auth_token: &str,
+    url: &str,
+    params: &[(String, String)],
+    signature: &str,
+) -> bool {
+    let Ok(signature) = STANDARD.decode(signature) else {
+        return false;
+    };
+    // Constant-time comparison
+    signature_mac(auth_token, url, params)
+        .verify_slice(&signature)
+        .is_ok()
+}
+
+async fn webhook(
+    State(channel): State<TwilioChannel>,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+    Form(params): Form<Vec<(String, String)>>,
+) -> Response {
+    let url = match &channel.config.webhook_url {
+        Some(url) => url.clone(),
+        None => {
+            let host = headers
+                .get(header::HOST)
+                .and_then(|host| host.to_str().ok())
+                .unwrap_or_default();
+            format!("https://{}{}", host, uri)
+        }
+    };
+    let signed = headers
+        .get("x-twilio-signature")
+        .and_then(|signature| signature.to_str().ok())
+        .is_some_and(|signature| {
+            signature_is_valid(&channel.config.auth_token, &url, &params, signature)
+        });
+    if !signed {
+        tracing::warn!(url = %url, "Rejected Twilio webhook with invalid signature");
+        return (StatusCode::FORBIDDEN, "Invalid Twilio signature").into_response();
+    }
+    let Some(message) = InboundMessage::from_params(&params) else {
+        return (StatusCode::BAD_REQUEST, "Not a Twilio message").into_response();
+    };
+
+    tokio::spawn(async move {
+        let message_sid = message.message_sid.clone();
+        if let Err(e) = channel.handle_inbound(message).await {
+            tracing::error!(message_sid = %message_sid, error = %e, "Failed to answer Twilio message");
+        }
+    });
+    ([(header::CONTENT_TYPE, "text/xml")], EMPTY_TWIML).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::ConfigurableAgentBuilder;
+    use crate::test_support::EchoModel;
+
+    use agents_core::persistence::{Checkpointer, InMemoryCheckpointer};
+
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    /// Accepts Messages API calls, recording their raw text.
+    async fn serve_messages_api(seen: Arc<Mutex<Vec<String>>>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = vec![0; 16 * 1024];
+                let read = socket.read(&mut request).await.unwrap_or(0);
+                seen.lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&request[..read]).to_string());
+                let body = r#"{"sid":"SM100"}"#;
+                let response = format!(
+                    "HTTP/1.1 201 Created\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn signatures_cover_url_and_sorted_params() {
+        let url = "https://example.com/twilio";
+        let sent = params(&[("To", "+15550100"), ("Body", "hi"), ("From", "+15550123")]);
+        let signature = twilio_signature("token", url, &sent);
+
+        let mut reordered = sent.clone();
+        reordered.reverse();
+        assert!(signature_is_valid("token", url, &reordered, &signature));
+        assert!(!signature_is_valid("other", url, &sent, &signature));
+        assert!(!signature_is_valid(
+            "token",
+            "https://example.com/other",
+            &sent,
+            &signature
+        ));
+        let tampered = params(&[("To", "+15550100"), ("Body", "hi!"), ("From", "+15550123")]);
+        assert!(!signature_is_valid("token", url, &tampered, &signature));
+        assert!(!signature_is_valid("token", url, &sent, "not base64"));
+    }
+
+    #[tokio::test]
+    async fn inbound_messages_are_answered_on_the_senders_thread() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let api_url = serve_messages_api(seen.clone()).await;
+        let checkpointer = Arc::new(InMemoryCheckpointer::new());
+        let agent = ConfigurableAgentBuilder::new("Echo")
+            .with_model(Arc::new(EchoModel("You said: ")))
+            .with_checkpointer(checkpointer.clone())
+            .build()
+            .unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let webhook_url = format!("http://{}/twilio", listener.local_addr().unwrap());
+        let config = TwilioConfig::new("AC123", "token")
+            .with_webhook_url(&webhook_url)
+            .with_api_url(&api_url);
+        let channel = TwilioChannel::new(Arc::new(agent), config).unwrap();
+        let app = Router::new().nest("/twilio", channel.router());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let form = params(&[
+            ("MessageSid", "SM1"),
+            ("From", "whatsapp:+15550123"),
+            ("To", "whatsapp:+15550100"),
+            ("Body", "hello"),
+            ("NumMedia", "0"),
+        ]);
+        let client = Client::new();
+        let forged = client
+            .post(&webhook_url)
+            .header(
+                "x-twilio-signature",
+                twilio_signature("wrong", &webhook_url, &form),
+            )
+            .form(&form)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(forged.status(), StatusCode::FORBIDDEN);
+
+        let response = client
+            .post(&webhook_url)
+            .header(
+                "x-twilio-signature",
+                twilio_signature("token", &webhook_url, &form),
+            )
+            .form(&form)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.text().await.unwrap(), EMPTY_TWIML);
+
+        // The reply is sent in the background
+        for _ in 0..100 {
+            if !seen.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let seen = seen.lock().unwrap().clone();
+        assert_eq!(seen.len(), 1);
+        assert!(seen[0].starts_with("POST /2010-04-01/Accounts/AC123/Messages.json "));
+        assert!(seen[0].contains("authorization: Basic "));
+        assert!(seen[0].ends_with(
+            "From=whatsapp%3A%2B15550100&To=whatsapp%3A%2B15550123&Body=You+said%3A+hello"
+        ));
+        assert!(checkpointer
+            .load_state(&"twilio:whatsapp:+15550123".to_string())
+            .await
+            .unwrap()
+            .is_some());
+    }
+}
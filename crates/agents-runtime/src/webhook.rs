@@ -0,0 +1,523 @@
+//! Webhook broadcaster with per-event-type templates
+//!
+//! A [`WebhookBroadcaster`] POSTs agent events to an HTTP endpoint. Without
+//! templates, every event is sent in the versioned
+//! [wire format](agents_core::event_schema). With [`EventTemplates`], the body of
+//! each event type is rendered from a [minijinja](https://docs.rs/minijinja)
+//! template, so the shape a consumer needs (Slack messages, Teams cards, a
+//! trimmed-down JSON document) lives in configuration instead of broadcaster code.
+//!
+//! Templates see the event's wire JSON: its fields at the top level, e.g.
+//! `{{ tool_name }}` or `{{ metadata.thread_id }}`, plus `event_type` and
+//! `schema_version`. Output is not escaped; use the `tojson` filter to embed values
+//! in JSON bodies.
+//!
+//! Rate-limited deliveries (`429 Too Many Requests`) are retried after the
+//! `Retry-After` the endpoint asks for. The chat broadcasters in
+//! [`slack`](crate::slack) and [`discord`](crate::discord) share this delivery.
+
+use agents_core::events::{AgentEvent, EventBroadcaster};
+use anyhow::Context;
+use async_trait::async_trait;
+use minijinja::Environment;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// Name of the template used for event types without their own
+const DEFAULT_TEMPLATE: &str = "*";
+
+/// Retries of a rate-limited delivery before it fails
+const RATE_LIMIT_RETRIES: u32 = 3;
+
+/// Longest wait honoured for a rate limit, and the wait when none is given
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+const DEFAULT_RATE_LIMIT_WAIT: Duration = Duration::from_secs(1);
+
+/// Event types covered by the Slack and Teams presets, with the text they show
+const PRESET_TEXTS: &[(&str, &str)] = &[
+    (
+        "agent_completed",
+        r#"agent_name ~ " replied in " ~ duration_ms ~ " ms: " ~ response_preview"#,
+    ),
+    (
+        "tool_failed",
+        r#""Tool " ~ tool_name ~ " failed after " ~ retry_count ~ " retries: " ~ error_message"#,
+    ),
+    (
+        "guardrail_intervened",
+        r#""Guardrail " ~ guardrail ~ (" blocked " if blocked else " masked ") ~ source ~ " text: " ~ reasons|join(", ")"#,
+    ),
+];
+
+/// Templates rendering webhook bodies, keyed by event type.
+///
+/// # Example
+///
+/// ```ignore
+/// let templates = EventTemplates::new()
+///     .with_template(
+///         "tool_failed",
+///         r#"{"text": {{ ("Tool " ~ tool_name ~ " failed: " ~ error_message)|tojson }}}"#,
+///     )?
+///     .with_template(
+///         "agent_completed",
+///         r#"{"thread": {{ metadata.thread_id|tojson }}, "answer": {{ response|tojson }}}"#,
+///     )?;
+/// ```
+#[derive(Clone)]
+pub struct EventTemplates {
+    env: Environment<'static>,
+}
+
+impl Default for EventTemplates {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for EventTemplates {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut names: Vec<_> = self.env.templates().map(|(name, _)| name).collect();
+        names.sort();
+        f.debug_struct("EventTemplates")
+            .field("templates", &names)
+            .finish()
+    }
+}
+
+impl EventTemplates {
+    pub fn new() -> Self {
+        Self {
+            env: Environment::new(),
+        }
+    }
+
+    /// Slack incoming webhook messages for completed runs, failed tools and
+    /// guardrail interventions.
+    pub fn slack() -> Self {
+        Self::preset(|text| format!(r#"{{"text": {{{{ ({text})|tojson }}}}}}"#))
+    }
+
+    /// Microsoft Teams Adaptive Card messages, for Workflows webhooks, for completed
+    /// runs, failed tools and guardrail interventions.
+    pub fn teams() -> Self {
+        Self::preset(|text| {
+            format!(
+                r#"{{"type": "message", "attachments": [{{"contentType": "application/vnd.microsoft.card.adaptive", "content": {{"$schema": "http://adaptivecards.io/schemas/adaptive-card.json", "type": "AdaptiveCard", "version": "1.4", "body": [{{"type": "TextBlock", "wrap": true, "text": {{{{ ({text})|tojson }}}}}}]}}}}]}}"#
+            )
+        })
+    }
+
+    /// Plain-text messages for completed runs, failed tools and guardrail
+    /// interventions, as posted by the Slack and Discord broadcasters.
+    pub fn text() -> Self {
+        Self::preset(|text| format!("{{{{ {text} }}}}"))
+    }
+
+    fn preset(render: impl Fn(&str) -> String) -> Self {
+        let mut templates = Self::new();
+        for (event_type, text) in PRESET_TEXTS {
+            templates = templates
+                .with_template(*event_type, render(text))
+                .expect("preset templates are valid");
+        }
+        templates
+    }
+
+    /// Render events of `event_type` with `source`, replacing any earlier template.
+    ///
+    /// Fails when the template does not parse.
+    pub fn with_template(
+        mut self,
+        event_type: impl Into<String>,
+        source: impl Into<String>,
+    ) -> anyhow::Result<Self> {
+        let event_type = event_type.into();
+        self.env
+            .add_template_owned(event_type.clone(), source.into())
+            .with_context(|| format!("Invalid template for {} events", event_type))?;
+        Ok(self)
+    }
+
+    /// Render event types without their own template with `source`.
+    pub fn with_default_template(self, source: impl Into<String>) -> anyhow::Result<Self> {
+        self.with_template(DEFAULT_TEMPLATE, source)
+    }
+
+    /// Whether events of `event_type` have a template, their own or the default.
+    pub fn covers(&self, event_type: &str) -> bool {
+        self.env.get_template(event_type).is_ok() || self.env.get_template(DEFAULT_TEMPLATE).is_ok()
+    }
+
+    /// The body for `event`, `None` when no template covers its type.
+    pub fn render(&self, event: &AgentEvent) -> anyhow::Result<Option<String>> {
+        let template = match self.env.get_template(event.event_type_name()) {
+            Ok(template) => template,
+            Err(_) => match self.env.get_template(DEFAULT_TEMPLATE) {
+                Ok(template) => template,
+                Err(_) => return Ok(None),
+            },
+        };
+        let context: serde_json::Value = serde_json::from_str(&event.to_wire_json()?)?;
+        let body = template
+            .render(context)
+            .with_context(|| format!("Failed to render {} event", event.event_type_name()))?;
+        Ok(Some(body))
+    }
+}
+
+/// Event broadcaster POSTing events to an HTTP endpoint.
+///
+/// With templates, only event types they cover are sent. Delivery fails on
+/// non-2xx responses, so an outbox relay retries the event.
+///
+/// # Example
+///
+/// ```ignore
+/// let slack = WebhookBroadcaster::new(std::env::var("SLACK_WEBHOOK_URL")?)?
+///     .with_templates(EventTemplates::slack());
+/// let audit = WebhookBroadcaster::new("https://audit.example.com/agent-events")?
+///     .with_header("authorization", format!("Bearer {}", token))
+///     .with_event_types(["agent_completed", "tool_failed"]);
+/// dispatcher.add_broadcaster(Arc::new(slack));
+/// dispatcher.add_broadcaster(Arc::new(audit));
+/// ```
+pub struct WebhookBroadcaster {
+    id: String,
+    client: Client,
+    url: String,
+    headers: Vec<(String, String)>,
+    content_type: String,
+    event_types: Option<HashSet<String>>,
+    templates: Option<EventTemplates>,
+}
+
+impl WebhookBroadcaster {
+    pub fn new(url: impl Into<String>) -> anyhow::Result<Self> {
+        Ok(Self {
+            id: "webhook".to_string(),
+            client: Client::builder()
+                .user_agent("rust-deep-agents-sdk/0.1")
+                .build()?,
+            url: url.into(),
+            headers: Vec::new(),
+            content_type: "application/json".to_string(),
+            event_types: None,
+            templates: None,
+        })
+    }
+
+    /// Broadcaster id used in delivery logs.
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = id.into();
+        self
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Content type of rendered bodies, `application/json` by default.
+    pub fn with_content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = content_type.into();
+        self
+    }
+
+    /// Only send events of these types, e.g. `["agent_completed", "tool_failed"]`.
+    pub fn with_event_types<I, S>(mut self, event_types: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.event_types = Some(event_types.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn with_templates(mut self, templates: EventTemplates) -> Self {
+        self.templates = Some(templates);
+        self
+    }
+}
+
+#[async_trait]
+impl EventBroadcaster for WebhookBroadcaster {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn should_broadcast(&self, event: &AgentEvent) -> bool {
+        let event_type = event.event_type_name();
+        self.event_types
+            .as_ref()
+            .is_none_or(|types| types.contains(event_type))
+            && self
+                .templates
+                .as_ref()
+                .is_none_or(|templates| templates.covers(event_type))
+    }
+
+    async fn broadcast(&self, event: &AgentEvent) -> anyhow::Result<()> {
+        let body = match &self.templates {
+            Some(templates) => match templates.render(event)? {
+                Some(body) => body,
+                None => return Ok(()),
+            },
+            None => event.to_wire_json()?,
+        };
+
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, &self.content_type)
+            .header(
+                crate::providers::CORRELATION_ID_HEADER,
+                &event.metadata().correlation_id,
+            );
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        send_rate_limited(request.body(body), &format!("Webhook {}", self.id)).await?;
+        Ok(())
+    }
+}
+
+/// Send `request`, retrying while `target` answers `429 Too Many Requests`.
+///
+/// Also waits out an exhausted rate-limit bucket (`x-ratelimit-remaining: 0`)
+/// before returning, so the next delivery is not rejected. Fails on non-2xx
+/// responses.
+pub(crate) async fn send_rate_limited(
+    request: RequestBuilder,
+    target: &str,
+) -> anyhow::Result<Response> {
+    let mut retries = 0;
+    loop {
+        let response = request
+            .try_clone()
+            .context("Streaming request bodies cannot be retried")?
+            .send()
+            .await
+            .with_context(|| format!("Failed to deliver event to {}", target))?;
+        let status = response.status();
+
+        if status == StatusCode::TOO_MANY_REQUESTS && retries < RATE_LIMIT_RETRIES {
+            let wait = header_seconds(&response, "retry-after").unwrap_or(DEFAULT_RATE_LIMIT_WAIT);
+            tracing::warn!(
+                target,
+                wait_ms = wait.as_millis() as u64,
+                "Rate limited, retrying"
+            );
+            tokio::time::sleep(wait.min(MAX_RATE_LIMIT_WAIT)).await;
+            retries += 1;
+            continue;
+        }
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            anyhow::bail!("{} returned {} - {}", target, status, error_text);
+        }
+
+        let exhausted = response
+            .headers()
+            .get("x-ratelimit-remaining")
+            .is_some_and(|remaining| remaining == "0");
+        if exhausted {
+            if let Some(wait) = header_seconds(&response, "x-ratelimit-reset-after") {
+                tokio::time::sleep(wait.min(MAX_RATE_LIMIT_WAIT)).await;
+            }
+        }
+        return Ok(response);
+    }
+}
+
+/// A header holding a number of seconds, fractional for Discord.
+fn header_seconds(response: &Response, name: &str) -> Option<Duration> {
+    let seconds: f64 = response
+        .headers()
+        .get(name)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// Split `text` into chunks of at most `max_chars` characters for chat APIs
+/// with a message length limit, breaking at a newline or else a space when
+/// one is close enough.
+pub(crate) fn split_message(text: &str, max_chars: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while let Some((limit, _)) = rest.char_indices().nth(max_chars) {
+        let head = &rest[..limit];
+        let break_at = head
+            .rfind('\n')
+            .or_else(|| head.rfind(' '))
+            // Breaking early in the chunk would leave mostly empty messages
+            .filter(|&at| at >= limit / 2);
+        match break_at {
+            Some(at) => {
+                chunks.push(rest[..at].to_string());
+                rest = &rest[at + 1..];
+            }
+            None => {
+                chunks.push(head.to_string());
+                rest = &rest[limit..];
+            }
+        }
+    }
+    if !rest.is_empty() || chunks.is_empty() {
+        chunks.push(rest.to_string());
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::events::{EventMetadata, ToolFailedEvent, ToolStartedEvent};
+    use std::sync::{Arc, Mutex};
+
+    fn tool_failed() -> AgentEvent {
+        AgentEvent::ToolFailed(ToolFailedEvent {
+            metadata: EventMetadata::new("thread-1".into(), "run-1".into(), None),
+            tool_name: "search".into(),
+            duration_ms: 12,
+            error_message: "timed \"out\"".into(),
+            is_recoverable: true,
+            retry_count: 2,
+        })
+    }
+
+    fn tool_started() -> AgentEvent {
+        AgentEvent::ToolStarted(ToolStartedEvent {
+            metadata: EventMetadata::new("thread-1".into(), "run-1".into(), None),
+            tool_name: "search".into(),
+            input_summary: "rust".into(),
+        })
+    }
+
+    /// Accepts every request, recording its raw text.
+    async fn serve_webhook(seen: Arc<Mutex<Vec<String>>>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = vec![0; 64 * 1024];
+                let read = socket.read(&mut request).await.unwrap_or(0);
+                seen.lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&request[..read]).to_string());
+                let _ = socket
+                    .write_all(
+                        b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok",
+                    )
+                    .await;
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn templates_render_per_event_type() {
+        let templates = EventTemplates::new()
+            .with_template(
+                "tool_failed",
+                r#"{"tool": {{ tool_name|tojson }}, "thread": {{ metadata.thread_id|tojson }}, "error": {{ error_message|tojson }}}"#,
+            )
+            .unwrap();
+
+        let body = templates.render(&tool_failed()).unwrap().unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&body).unwrap(),
+            serde_json::json!({ "tool": "search", "thread": "thread-1", "error": "timed \"out\"" })
+        );
+        assert!(templates.render(&tool_started()).unwrap().is_none());
+
+        let templates = templates
+            .with_default_template("{{ event_type }} v{{ schema_version }}")
+            .unwrap();
+        assert_eq!(
+            templates.render(&tool_started()).unwrap().as_deref(),
+            Some("tool_started v1")
+        );
+
+        assert!(EventTemplates::new()
+            .with_template("tool_failed", "{{ unclosed")
+            .is_err());
+    }
+
+    #[test]
+    fn presets_produce_valid_messages() {
+        let slack = EventTemplates::slack()
+            .render(&tool_failed())
+            .unwrap()
+            .unwrap();
+        let slack: serde_json::Value = serde_json::from_str(&slack).unwrap();
+        assert_eq!(
+            slack["text"],
+            "Tool search failed after 2 retries: timed \"out\""
+        );
+
+        let teams = EventTemplates::teams()
+            .render(&tool_failed())
+            .unwrap()
+            .unwrap();
+        let teams: serde_json::Value = serde_json::from_str(&teams).unwrap();
+        assert_eq!(
+            teams["attachments"][0]["content"]["body"][0]["text"],
+            slack["text"]
+        );
+        assert!(!EventTemplates::slack().covers("tool_started"));
+    }
+
+    #[test]
+    fn long_messages_split_at_line_and_word_breaks() {
+        assert_eq!(split_message("short", 10), vec!["short"]);
+        assert_eq!(split_message("", 10), vec![""]);
+        assert_eq!(
+            split_message("first line\nsecond line", 15),
+            vec!["first line", "second line"]
+        );
+        assert_eq!(
+            split_message("one two three four", 10),
+            vec!["one two", "three four"]
+        );
+        // No break close enough: split mid-word, on character boundaries
+        assert_eq!(split_message("ééééé x", 4), vec!["éééé", "é x"]);
+        assert!(split_message(&"word ".repeat(1000), 2000)
+            .iter()
+            .all(|chunk| chunk.chars().count() <= 2000));
+    }
+
+    #[tokio::test]
+    async fn webhooks_post_rendered_or_wire_bodies() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let url = serve_webhook(seen.clone()).await;
+
+        let slack = WebhookBroadcaster::new(&url)
+            .unwrap()
+            .with_templates(EventTemplates::slack());
+        assert!(!slack.should_broadcast(&tool_started()));
+        slack.broadcast(&tool_failed()).await.unwrap();
+
+        let plain = WebhookBroadcaster::new(&url)
+            .unwrap()
+            .with_header("authorization", "Bearer token")
+            .with_event_types(["tool_started"]);
+        assert!(!plain.should_broadcast(&tool_failed()));
+        plain.broadcast(&tool_started()).await.unwrap();
+
+        let seen = seen.lock().unwrap();
+        assert!(seen[0].starts_with("POST /hook "));
+        assert!(seen[0].contains("x-correlation-id: run-1"));
+        assert!(
+            seen[0].ends_with(r#"{"text": "Tool search failed after 2 retries: timed \"out\""}"#)
+        );
+        assert!(seen[1].contains("authorization: Bearer token"));
+        assert!(seen[1].contains(r#"{"schema_version":1,"event_type":"tool_started","#));
+    }
+}
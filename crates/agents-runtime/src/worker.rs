@@ -0,0 +1,550 @@
+//! Worker mode: process agent jobs from a shared queue.
+//!
+//! Producers push [`AgentJob`]s to a [`JobQueue`] (Redis Streams, ...) and any number
+//! of worker processes run [`AgentWorker::run_until`]. Each job is run on its thread
+//! with [`DeepAgent::handle_message_for_thread`] under the job id as correlation id;
+//! the run's events are published to the queue as they happen and the result once
+//! the run is done.
+//!
+//! A worker runs one job per agent instance: add instances with
+//! [`AgentWorker::with_agent`] to process several jobs at once.
+
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use agents_core::correlation::with_correlation_id;
+use agents_core::events::{AgentEvent, EventBroadcaster};
+use agents_core::job_queue::{AgentJobResult, ClaimedJob, JobQueue};
+use async_trait::async_trait;
+use futures::stream::{FuturesUnordered, StreamExt};
+
+use crate::agent::{AgentShutdownError, DeepAgent, ThreadLockError};
+
+/// Settings for an [`AgentWorker`].
+#[derive(Debug, Clone)]
+pub struct WorkerConfig {
+    /// Name of this worker in the queue's consumer group (default: random)
+    pub consumer: String,
+    /// Jobs processed at the same time, at most one per agent instance (default: 1)
+    pub concurrency: usize,
+    /// How long one claim waits for new jobs (default: 5 seconds)
+    pub poll_wait: Duration,
+    /// Jobs handed out more often than this fail without being run (default: 3)
+    pub max_deliveries: u32,
+}
+
+impl Default for WorkerConfig {
+    fn default() -> Self {
+        Self {
+            consumer: format!("worker-{}", &uuid::Uuid::new_v4().simple().to_string()[..8]),
+            concurrency: 1,
+            poll_wait: Duration::from_secs(5),
+            max_deliveries: 3,
+        }
+    }
+}
+
+impl WorkerConfig {
+    /// Use a stable name (e.g. the pod name) so a restarted worker is recognizable.
+    pub fn with_consumer(mut self, consumer: impl Into<String>) -> Self {
+        self.consumer = consumer.into();
+        self
+    }
+
+    /// Jobs processed at the same time; the worker needs one agent instance per
+    /// job, see [`AgentWorker::with_agent`].
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn with_poll_wait(mut self, wait: Duration) -> Self {
+        self.poll_wait = wait;
+        self
+    }
+
+    pub fn with_max_deliveries(mut self, max_deliveries: u32) -> Self {
+        self.max_deliveries = max_deliveries.max(1);
+        self
+    }
+}
+
+/// Jobs handled by [`AgentWorker::run_until`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WorkerStats {
+    pub completed: u64,
+    pub failed: u64,
+    /// Jobs left on the queue for a later attempt (busy thread, shutdown)
+    pub deferred: u64,
+}
+
+enum JobOutcome {
+    Completed,
+    Failed,
+    Deferred,
+}
+
+/// Consumes [`AgentJob`](agents_core::job_queue::AgentJob)s from a queue and runs
+/// them on a [`DeepAgent`].
+///
+/// Events are only published when the agent has an event dispatcher (any broadcaster
+/// configured on the builder).
+///
+/// A job runs on an agent instance of its own: the worker processes at most as many
+/// jobs at once as it has instances, whatever the configured concurrency.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// let queue = Arc::new(RedisJobQueue::new("redis://127.0.0.1:6379").await?);
+/// let worker = AgentWorker::new(Arc::new(build_agent()?), queue)
+///     .with_agent(Arc::new(build_agent()?))
+///     .with_config(WorkerConfig::default().with_consumer(pod_name).with_concurrency(2));
+///
+/// let stats = worker.run_until(async { tokio::signal::ctrl_c().await.ok(); }).await;
+/// ```
+pub struct AgentWorker {
+    /// One agent instance per job slot
+    agents: usize,
+    /// Instances not running a job
+    idle: Mutex<Vec<Arc<DeepAgent>>>,
+    queue: Arc<dyn JobQueue>,
+    config: WorkerConfig,
+    /// Jobs being processed; their events are published to the queue
+    active: Arc<Mutex<HashSet<String>>>,
+}
+
+impl AgentWorker {
+    pub fn new(agent: Arc<DeepAgent>, queue: Arc<dyn JobQueue>) -> Self {
+        let worker = Self {
+            agents: 0,
+            idle: Mutex::new(Vec::new()),
+            queue,
+            config: WorkerConfig::default(),
+            active: Arc::new(Mutex::new(HashSet::new())),
+        };
+        worker.with_agent(agent)
+    }
+
+    /// Add an agent instance, so the worker can process one more job at a time.
+    pub fn with_agent(mut self, agent: Arc<DeepAgent>) -> Self {
+        agent.add_broadcaster(Arc::new(JobEventPublisher {
+            queue: self.queue.clone(),
+            active: self.active.clone(),
+        }));
+        self.idle_agents().push(agent);
+        self.agents += 1;
+        self
+    }
+
+    pub fn with_config(mut self, config: WorkerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Process jobs until `shutdown` completes, then finish the jobs in progress.
+    pub async fn run_until<F: Future<Output = ()>>(&self, shutdown: F) -> WorkerStats {
+        tokio::pin!(shutdown);
+        let mut running = FuturesUnordered::new();
+        let mut stats = WorkerStats::default();
+        tracing::info!(consumer = %self.config.consumer, "Agent worker started");
+
+        loop {
+            let capacity = self
+                .config
+                .concurrency
+                .min(self.agents)
+                .saturating_sub(running.len());
+            tokio::select! {
+                _ = &mut shutdown => break,
+                Some(outcome) = running.next(), if !running.is_empty() => {
+                    stats.record(outcome);
+                }
+                claimed = self.queue.claim(&self.config.consumer, capacity, self.config.poll_wait),
+                    if capacity > 0 =>
+                {
+                    match claimed {
+                        Ok(jobs) => running.extend(jobs.into_iter().map(|job| self.process(job))),
+                        Err(e) => {
+                            tracing::warn!(error = %e, "Failed to claim jobs");
+                            tokio::time::sleep(self.config.poll_wait).await;
+                        }
+                    }
+                }
+            }
+        }
+
+        while let Some(outcome) = running.next().await {
+            stats.record(outcome);
+        }
+        tracing::info!(consumer = %self.config.consumer, ?stats, "Agent worker stopped");
+        stats
+    }
+
+    async fn process(&self, claimed: ClaimedJob) -> JobOutcome {
+        // The queue may hand out more jobs than asked for
+        let Some(agent) = self.check_out() else {
+            tracing::info!(job_id = %claimed.job.job_id, "No idle agent, leaving job for a later attempt");
+            if let Err(e) = self.queue.release(&claimed).await {
+                tracing::warn!(job_id = %claimed.job.job_id, error = %e, "Failed to release job");
+            }
+            return JobOutcome::Deferred;
+        };
+        self.process_on(&agent, claimed).await
+    }
+
+    /// Take an idle agent instance; it returns to the pool when dropped, even if
+    /// the job panics or is cancelled.
+    fn check_out(&self) -> Option<CheckedOutAgent<'_>> {
+        let agent = self.idle_agents().pop()?;
+        Some(CheckedOutAgent {
+            agent: Some(agent),
+            idle: &self.idle,
+        })
+    }
+
+    async fn process_on(&self, agent: &DeepAgent, claimed: ClaimedJob) -> JobOutcome {
+        let job = &claimed.job;
+        let outcome = if claimed.deliveries > self.config.max_deliveries {
+            Err(format!(
+                "giving up after {} deliveries",
+                self.config.max_deliveries
+            ))
+        } else {
+            self.active_jobs().insert(job.job_id.clone());
+            let run = agent.handle_message_for_thread(&job.thread_id, &job.message);
+            let result = with_correlation_id(job.job_id.clone(), run).await;
+            // Publish every event of the run before its result
+            agent.flush_broadcasts().await;
+            self.active_jobs().remove(&job.job_id);
+
+            match result {
+                Err(e) if e.is::<AgentShutdownError>() || is_busy(&e) => {
+                    tracing::info!(job_id = %job.job_id, error = %e, "Leaving job for a later attempt");
+                    if let Err(e) = self.queue.release(&claimed).await {
+                        tracing::warn!(job_id = %job.job_id, error = %e, "Failed to release job");
+                    }
+                    return JobOutcome::Deferred;
+                }
+                result => result.map_err(|e| format!("{e:#}")),
+            }
+        };
+
+        let failed = outcome.is_err();
+        if let Err(reason) = &outcome {
+            tracing::warn!(job_id = %job.job_id, %reason, "Job failed");
+        }
+        let result = AgentJobResult {
+            job_id: job.job_id.clone(),
+            thread_id: job.thread_id.clone(),
+            outcome,
+            worker: self.config.consumer.clone(),
+        };
+        if let Err(e) = self.queue.complete(&claimed, &result).await {
+            // The job is redelivered once its claim times out
+            tracing::error!(job_id = %job.job_id, error = %e, "Failed to complete job");
+            return JobOutcome::Deferred;
+        }
+        if failed {
+            JobOutcome::Failed
+        } else {
+            JobOutcome::Completed
+        }
+    }
+
+    fn idle_agents(&self) -> std::sync::MutexGuard<'_, Vec<Arc<DeepAgent>>> {
+        self.idle.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn active_jobs(&self) -> std::sync::MutexGuard<'_, HashSet<String>> {
+        self.active.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl WorkerStats {
+    fn record(&mut self, outcome: JobOutcome) {
+        match outcome {
+            JobOutcome::Completed => self.completed += 1,
+            JobOutcome::Failed => self.failed += 1,
+            JobOutcome::Deferred => self.deferred += 1,
+        }
+    }
+}
+
+/// An agent instance running a job of an [`AgentWorker`].
+struct CheckedOutAgent<'a> {
+    agent: Option<Arc<DeepAgent>>,
+    idle: &'a Mutex<Vec<Arc<DeepAgent>>>,
+}
+
+impl std::ops::Deref for CheckedOutAgent<'_> {
+    type Target = DeepAgent;
+
+    fn deref(&self) -> &DeepAgent {
+        self.agent.as_ref().expect("agent until dropped")
+    }
+}
+
+impl Drop for CheckedOutAgent<'_> {
+    fn drop(&mut self) {
+        if let Some(agent) = self.agent.take() {
+            self.idle
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(agent);
+        }
+    }
+}
+
+fn is_busy(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<ThreadLockError>(),
+        Some(ThreadLockError::Busy { .. })
+    )
+}
+
+/// Publishes the events of active jobs, keyed by the run's correlation id (the job id).
+struct JobEventPublisher {
+    queue: Arc<dyn JobQueue>,
+    active: Arc<Mutex<HashSet<String>>>,
+}
+
+#[async_trait]
+impl EventBroadcaster for JobEventPublisher {
+    fn id(&self) -> &str {
+        "job-queue"
+    }
+
+    fn should_broadcast(&self, event: &AgentEvent) -> bool {
+        self.active
+            .lock()
+            .map(|active| active.contains(&event.metadata().correlation_id))
+            .unwrap_or(false)
+    }
+
+    async fn broadcast(&self, event: &AgentEvent) -> anyhow::Result<()> {
+        self.queue
+            .publish_event(&event.metadata().correlation_id, event)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::runtime::create_deep_agent_from_config;
+    use crate::agent::DeepAgentConfig;
+    use crate::test_support::FileWritingPlanner;
+    use agents_core::agent::{PlannerAction, PlannerContext, PlannerDecision, PlannerHandle};
+    use agents_core::events::EventDispatcher;
+    use agents_core::job_queue::{AgentJob, InMemoryJobQueue};
+    use agents_core::messaging::{AgentMessage, MessageContent, MessageRole};
+    use agents_core::persistence::{Checkpointer, InMemoryCheckpointer};
+    use agents_core::state::AgentStateSnapshot;
+
+    /// Replies with the user's message in upper case.
+    struct ShoutPlanner;
+
+    #[async_trait]
+    impl PlannerHandle for ShoutPlanner {
+        async fn plan(
+            &self,
+            context: PlannerContext,
+            _state: Arc<AgentStateSnapshot>,
+        ) -> anyhow::Result<PlannerDecision> {
+            let text = match &context.history.last().unwrap().content {
+                MessageContent::Text(text) => text.to_uppercase(),
+                other => other.to_text(),
+            };
+            Ok(PlannerDecision {
+                next_action: PlannerAction::Respond {
+                    message: AgentMessage {
+                        role: MessageRole::Agent,
+                        content: MessageContent::Text(text),
+                        metadata: None,
+                    },
+                },
+            })
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    /// Records the user messages of every prompt, then answers.
+    #[derive(Default)]
+    struct RecordingPlanner(Mutex<Vec<Vec<String>>>);
+
+    #[async_trait]
+    impl PlannerHandle for RecordingPlanner {
+        async fn plan(
+            &self,
+            context: PlannerContext,
+            _state: Arc<AgentStateSnapshot>,
+        ) -> anyhow::Result<PlannerDecision> {
+            let users = context
+                .history
+                .iter()
+                .filter(|message| message.role == MessageRole::User)
+                .map(|message| message.content.to_text())
+                .collect();
+            self.0.lock().unwrap().push(users);
+            Ok(PlannerDecision {
+                next_action: PlannerAction::Respond {
+                    message: AgentMessage {
+                        role: MessageRole::Agent,
+                        content: MessageContent::Text("noted".into()),
+                        metadata: None,
+                    },
+                },
+            })
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    #[tokio::test]
+    async fn jobs_of_other_threads_stay_out_of_the_prompt() {
+        let planner = Arc::new(RecordingPlanner::default());
+        let agent = Arc::new(create_deep_agent_from_config(DeepAgentConfig::new(
+            "notes",
+            planner.clone(),
+        )));
+        let queue = Arc::new(InMemoryJobQueue::default());
+        for (thread, message) in [("thread-a", "secret of a"), ("thread-b", "hello from b")] {
+            queue
+                .enqueue(&AgentJob::new(thread, message))
+                .await
+                .unwrap();
+        }
+
+        let worker = AgentWorker::new(agent, queue.clone())
+            .with_config(WorkerConfig::default().with_poll_wait(Duration::from_millis(10)));
+        let stats = worker
+            .run_until(async {
+                while !queue.is_empty() {
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                }
+            })
+            .await;
+
+        assert_eq!(stats.completed, 2);
+        let prompts = planner.0.lock().unwrap().clone();
+        assert_eq!(
+            prompts,
+            vec![
+                vec!["secret of a".to_string()],
+                vec!["hello from b".to_string()]
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn jobs_without_an_idle_agent_are_deferred() {
+        let agent = Arc::new(create_deep_agent_from_config(DeepAgentConfig::new(
+            "shout",
+            Arc::new(ShoutPlanner),
+        )));
+        let queue = Arc::new(InMemoryJobQueue::default());
+        for message in ["hello", "bye"] {
+            queue
+                .enqueue(&AgentJob::new("thread-1", message))
+                .await
+                .unwrap();
+        }
+        let worker = AgentWorker::new(agent, queue.clone());
+        // The queue hands out more jobs than the worker has agents for
+        let mut jobs = queue.claim("worker", 2, Duration::ZERO).await.unwrap();
+        let second = jobs.pop().unwrap();
+        let first = jobs.pop().unwrap();
+
+        let busy = worker.check_out().unwrap();
+        assert!(matches!(worker.process(second).await, JobOutcome::Deferred));
+        drop(busy);
+        assert!(matches!(worker.process(first).await, JobOutcome::Completed));
+        assert_eq!(worker.idle_agents().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn concurrent_jobs_run_on_their_own_agent_and_thread() {
+        let checkpointer = Arc::new(InMemoryCheckpointer::new());
+        let agent = || {
+            Arc::new(create_deep_agent_from_config(
+                DeepAgentConfig::new("files", Arc::new(FileWritingPlanner))
+                    .with_checkpointer(checkpointer.clone()),
+            ))
+        };
+        let queue = Arc::new(InMemoryJobQueue::default());
+        for thread in ["thread-a", "thread-b"] {
+            queue.enqueue(&AgentJob::new(thread, thread)).await.unwrap();
+        }
+
+        let worker = AgentWorker::new(agent(), queue.clone())
+            .with_agent(agent())
+            .with_config(
+                WorkerConfig::default()
+                    .with_concurrency(2)
+                    .with_poll_wait(Duration::from_millis(10)),
+            );
+        let stats = worker
+            .run_until(async {
+                while !queue.is_empty() {
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                }
+            })
+            .await;
+
+        assert_eq!(stats.completed, 2);
+        for thread in ["thread-a", "thread-b"] {
+            let state = checkpointer
+                .load_state(&thread.to_string())
+                .await
+                .unwrap()
+                .unwrap();
+            let files: Vec<&String> = state.files.keys().collect();
+            assert_eq!(files, vec![&format!("{thread}.txt")]);
+        }
+    }
+
+    #[tokio::test]
+    async fn worker_publishes_results_and_events_per_job() {
+        let agent = Arc::new(create_deep_agent_from_config(
+            DeepAgentConfig::new("shout", Arc::new(ShoutPlanner))
+                .with_event_dispatcher(Arc::new(EventDispatcher::new())),
+        ));
+        let queue = Arc::new(InMemoryJobQueue::default());
+        for (id, message) in [("job-1", "hello"), ("job-2", "bye")] {
+            queue
+                .enqueue(&AgentJob::new("thread-1", message).with_job_id(id))
+                .await
+                .unwrap();
+        }
+
+        let worker = AgentWorker::new(agent, queue.clone())
+            .with_config(WorkerConfig::default().with_poll_wait(Duration::from_millis(10)));
+        let stats = worker
+            .run_until(async {
+                while !queue.is_empty() {
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                }
+            })
+            .await;
+
+        assert_eq!(stats.completed, 2);
+        let result = queue.result("job-2").await.unwrap().unwrap();
+        let response = result.outcome.unwrap();
+        assert!(matches!(response.content, MessageContent::Text(ref t) if t == "BYE"));
+        let events = queue.events("job-1");
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, AgentEvent::AgentCompleted(_))));
+        assert!(events
+            .iter()
+            .all(|event| event.metadata().correlation_id == "job-1"));
+    }
+}
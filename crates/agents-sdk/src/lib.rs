@@ -50,12 +50,32 @@
 //!
 //! - `toolkit` (default): Includes agents-toolkit with built-in tools
 //! - `aws`: Includes AWS integrations
-//! - `redis`: Redis-backed state persistence
+//! - `redis`: Redis-backed state persistence and user memory
 //! - `postgres`: PostgreSQL-backed state persistence
-//! - `dynamodb`: DynamoDB-backed state persistence (AWS)
-//! - `persistence`: Grouped feature for Redis + PostgreSQL
-//! - `aws-full`: Grouped feature for AWS + DynamoDB
+//! - `sqlite`: SQLite-backed tool result cache
+//! - `neo4j`: Neo4j-backed knowledge graph memory
+//! - `dynamodb`: DynamoDB-backed state persistence and HITL approval store (AWS)
+//! - `s3`: S3 output sink for long final responses (AWS)
+//! - `sqs`: SQS job queue for agent workers (AWS)
+//! - `sns`: SNS event broadcaster (AWS)
+//! - `bedrock`: Bedrock Guardrails for any model (AWS)
+//! - `cedar`: Cedar policies deciding on tool calls
+//! - `lambda`: Lambda adapter for API Gateway and Function URLs (AWS)
+//! - `persistence`: Grouped feature for Redis + PostgreSQL + SQLite + Neo4j
+//! - `aws-full`: Grouped feature for AWS + DynamoDB + S3 + SQS + SNS + Bedrock
+//! - `secrets`: Env file and secret directory providers for `secret://` references
+//! - `vault`: HashiCorp Vault secrets provider
 //! - `mcp`: Model Context Protocol client for external tools
+//! - `debug-ui`: Local web dashboard of threads, state, approvals and live events
+//! - `schema`: JSON Schema export of the event wire format
+//! - `email`: IMAP/SMTP channel answering a mailbox with an agent
+//! - `telegram`: Telegram bot channel with streamed replies and approval buttons
+//! - `twilio`: SMS and WhatsApp channel connecting Twilio numbers to an agent
+//! - `xlsx`: Excel workbooks for the data analysis tools
+//! - `charts`: `render_chart` tool drawing PNG and SVG charts into a blob store
+//! - `sql-postgres`: `sql_query` and `sql_schema` tools for Postgres databases
+//! - `sql-mysql`: `sql_query` and `sql_schema` tools for MySQL databases
+//! - `browser`: headless Chrome `browser` tool with a domain allowlist
 //! - `full`: Includes all features
 //!
 //! ## Installation Options
@@ -149,35 +169,229 @@
 
 // Re-export core functionality (always available)
 pub use agents_core::agent::{AgentHandle, AgentStream};
+pub use agents_core::approval_store::{
+    ApprovalDecision, ApprovalError, ApprovalStore, InMemoryApprovalStore, PendingApproval,
+};
+pub use agents_core::batch::{BatchModel, BatchRequest, BatchResult, BatchStatus};
+pub use agents_core::blob::{Blob, BlobStore, FileBlobStore, InMemoryBlobStore};
+pub use agents_core::credentials::{ApiKey, ProviderCredentials};
+pub use agents_core::dead_letter::{DeadLetter, DeadLetterStore, InMemoryDeadLetterStore};
+pub use agents_core::event_schema::{EventDecodeError, EventEnvelope, EVENT_SCHEMA_VERSION};
+pub use agents_core::experiment::ExperimentAssignment;
+pub use agents_core::graph::{GraphEntity, GraphFact, GraphStore, InMemoryGraphStore};
+pub use agents_core::guardrail::{Guardrail, GuardrailSource, GuardrailVerdict};
+pub use agents_core::job_queue::{
+    AgentJob, AgentJobResult, ClaimedJob, InMemoryJobQueue, JobQueue,
+};
+pub use agents_core::json_stream::JsonStreamParser;
 pub use agents_core::llm::{ChunkStream, StreamChunk};
+pub use agents_core::memory::{InMemoryMemoryStore, MemoryEntry, MemoryStore, UserId};
+pub use agents_core::metering::{InMemoryMeterSink, MeterEvent, MeterSink};
+pub use agents_core::outbox::{
+    EventOutbox, InMemoryEventOutbox, OutboxRecord, OutboxRelay, OutboxRelayHandle, RelayStats,
+};
+pub use agents_core::policy::{PolicyDecision, PolicyProvider, PolicyRequest, PolicyThread};
+pub use agents_core::profiling::{ProfilePhase, RunProfile};
+pub use agents_core::quota::{
+    InMemoryQuotaStore, QuotaLimit, QuotaPeriod, QuotaRemaining, QuotaStore, QuotaUsage,
+};
+pub use agents_core::response_cache::{
+    cosine_similarity, CachedResponse, Embedder, InMemoryResponseCache, ResponseCache,
+};
+pub use agents_core::search::{
+    ConversationIndex, InMemoryConversationIndex, IndexedMessage, SearchHit,
+};
+pub use agents_core::secrets::{
+    resolve_secret, CachedSecretsProvider, InMemorySecretsProvider, SecretsProvider,
+};
+pub use agents_core::sink::{
+    FileOutputSink, InMemoryOutputSink, OutputSink, OutputWriter, WriterOutputSink,
+};
+pub use agents_core::thread_lock::{InMemoryThreadLock, LeaseRequest, ThreadLease, ThreadLock};
+pub use agents_core::thread_template::ThreadTemplate;
+pub use agents_core::tool_cache::{InMemoryToolResultCache, ToolResultCache};
 pub use agents_core::tools::{
-    Tool, ToolBox, ToolContext, ToolParameterSchema, ToolRegistry, ToolResult, ToolSchema,
+    ArgumentError, Tool, ToolArgs, ToolBox, ToolContext, ToolDeprecation, ToolExample,
+    ToolExtensions, ToolParameterSchema, ToolParameterType, ToolProvider, ToolRegistry, ToolResult,
+    ToolSchema,
+};
+pub use agents_core::trace_context::TraceContext;
+pub use agents_core::user_profile::{ProfileFact, UserProfile};
+pub use agents_core::{
+    agent, approval_store, batch, blob, correlation, credentials, dead_letter, event_schema,
+    events, experiment, graph, guardrail, hitl, job_queue, json_stream, llm, memory, messaging,
+    outbox, persistence, policy, profiling, response_cache, search, secrets, security, sink, state,
+    thread_lock, thread_template, tool_cache, tools, trace_context, user_profile,
 };
-pub use agents_core::{agent, events, hitl, llm, messaging, persistence, security, state, tools};
 pub use agents_runtime::{
     create_async_deep_agent,
     create_deep_agent,
     get_default_model,
     // Provider configurations and models
+    AgentPool,
+    AgentPoolKey,
+    AgentShutdownError,
+    AgentWorker,
+    AnswerMatch,
     AnthropicConfig,
     AnthropicMessagesModel,
+    ApprovalConfig,
+    BatchExecutor,
+    BatchJob,
+    BatchOutput,
+    BenchmarkReport,
+    BenchmarkRunner,
+    BenchmarkSuite,
+    BenchmarkSummary,
+    BenchmarkTask,
+    BuiltinSkill,
+    CannedResponse,
+    CitationConfig,
+    ConcurrencyConfig,
+    ConcurrencyLimitError,
+    ConcurrencyStats,
+    ConfigIssue,
+    ConfigValidationError,
     ConfigurableAgentBuilder,
+    ConsentStore,
+    ConversationScript,
+    CostTier,
+    CredentialRotation,
     DeepAgent,
+    DeterministicConfig,
+    DiscordBroadcaster,
+    EmojiPolicy,
+    EventTemplates,
+    Experiment,
+    ExperimentVariant,
+    FailureKind,
+    GatewayChatModel,
+    GatewayConfig,
     GeminiChatModel,
     GeminiConfig,
+    GraphMemoryConfig,
+    HandleTarget,
     HitlPolicy,
+    HttpTarget,
+    InMemoryConsentStore,
+    KeywordClassifier,
+    KeywordToolSelector,
+    LoadTarget,
+    LoadTest,
+    LoadTestReport,
+    LocaleConfig,
+    MarkdownDialect,
+    MemoryClassifier,
+    MemoryDecision,
+    MemoryPolicy,
+    ModelRoute,
+    OffloadConfig,
+    OllamaChatModel,
+    OllamaConfig,
+    OpaPolicyProvider,
     OpenAiChatModel,
     OpenAiConfig,
+    OutputFormat,
+    OutputSinkConfig,
+    PriorityWeights,
+    Profile,
+    ProfileSettings,
+    PromptPack,
+    PromptSection,
+    PromptSections,
+    PromptSnapshot,
+    QuotaAction,
+    QuotaConfig,
+    QuotaExceededError,
+    RecordedStep,
+    ReplicaHealth,
+    RunDebugger,
+    RunOptions,
+    RunPriority,
+    RunRecording,
+    RunReport,
+    RunTrace,
+    SelfHostedChatModel,
+    SelfHostedConfig,
+    SelfTestCheck,
+    SelfTestCheckKind,
+    SelfTestOptions,
+    SelfTestReport,
+    SelfTestStatus,
+    ShutdownReport,
+    Skill,
+    SkillRequirement,
+    SlackBroadcaster,
+    StateMetrics,
+    StateSize,
+    StateSizeLimits,
+    StreamCoalescing,
+    StripeMeterSink,
     SubAgentConfig,
     SummarizationConfig,
+    TaskResult,
+    TeedChunk,
+    ThreadExport,
+    ThreadLockConfig,
+    ThreadLockError,
+    ThreadTitleConfig,
+    ToolArgumentValidation,
+    ToolConflictStrategy,
+    ToolExamplesConfig,
+    ToolMock,
+    ToolMocks,
+    ToolOutputConfig,
+    ToolOutputStrategy,
+    ToolRetryConfig,
+    ToolRetryPolicy,
+    ToolSelectionConfig,
+    ToolSelector,
+    TranslationConfig,
+    Translator,
+    UsageMeter,
+    UsageMeterHandle,
+    UserErasure,
+    UserMemoryConfig,
+    WebhookBroadcaster,
+    WorkerConfig,
+    WorkerStats,
 };
 
 // Re-export token tracking functionality
 pub use agents_core::events::TokenUsage;
 pub use agents_runtime::middleware::token_tracking::{
-    TokenCosts, TokenTrackingConfig, TokenTrackingMiddleware, TokenUsageSummary,
+    ComponentUsage, TokenCosts, TokenTrackingConfig, TokenTrackingMiddleware, TokenUsageSummary,
 };
 
+// Re-export guardrails
+pub use agents_runtime::middleware::guardrails::GuardrailMiddleware;
+
+// Re-export response caching
+pub use agents_runtime::middleware::response_cache::{
+    ResponseCacheConfig, ResponseCacheMiddleware,
+};
+
+// Re-export context assembly
+pub use agents_runtime::middleware::context_assembly::{
+    ContextAssemblyConfig, ContextAssemblyMiddleware, ContextReduction, ContextSection,
+    ContextStrategy,
+};
+
+// Re-export prompt compression
+pub use agents_runtime::middleware::prompt_compression::{
+    ExtractiveCompressor, ModelCompressor, PromptCompressionConfig, PromptCompressionMiddleware,
+    PromptCompressor,
+};
+
+// Re-export the time context provider
+pub use agents_runtime::middleware::time_context::{TimeContextConfig, TimeContextMiddleware};
+
+// Re-export the user profile middleware
+pub use agents_runtime::middleware::user_profile::{UserProfileConfig, UserProfileMiddleware};
+
+// Re-export cost tier routing for gateway models
+pub use agents_runtime::providers::gateway::with_cost_tier;
+
 // Re-export toolkit functionality (when toolkit feature is enabled)
 #[cfg(feature = "toolkit")]
 #[cfg_attr(docsrs, doc(cfg(feature = "toolkit")))]
@@ -185,7 +399,7 @@ pub use agents_toolkit::*;
 
 // Re-export procedural macros from toolkit
 #[cfg(feature = "toolkit")]
-pub use agents_macros::tool;
+pub use agents_macros::{tool, tools, ToolArgs, ToolEnum};
 
 // Re-export AWS functionality (when aws feature is enabled)
 #[cfg(feature = "aws")]
@@ -195,24 +409,78 @@ pub use agents_aws::*;
 // Re-export persistence functionality (when persistence features are enabled)
 #[cfg(feature = "redis")]
 #[cfg_attr(docsrs, doc(cfg(feature = "redis")))]
-pub use agents_persistence::RedisCheckpointer;
+pub use agents_persistence::{
+    RedisCheckpointer, RedisJobQueue, RedisJobQueueBuilder, RedisMemoryStore,
+    RedisMemoryStoreBuilder, RedisQuotaStore, RedisQuotaStoreBuilder, RedisToolResultCache,
+    RedisToolResultCacheBuilder,
+};
 
 #[cfg(feature = "postgres")]
 #[cfg_attr(docsrs, doc(cfg(feature = "postgres")))]
-pub use agents_persistence::PostgresCheckpointer;
+pub use agents_persistence::{
+    PostgresCheckpointer, PostgresConversationIndex, PostgresConversationIndexBuilder,
+};
+
+#[cfg(feature = "sqlite")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sqlite")))]
+pub use agents_persistence::{SqliteToolResultCache, SqliteToolResultCacheBuilder};
+
+#[cfg(feature = "neo4j")]
+#[cfg_attr(docsrs, doc(cfg(feature = "neo4j")))]
+pub use agents_persistence::Neo4jGraphStore;
+
+// Re-export secrets providers
+#[cfg(feature = "secrets")]
+#[cfg_attr(docsrs, doc(cfg(feature = "secrets")))]
+pub use agents_secrets::{DirectorySecretsProvider, EnvFileSecretsProvider};
+
+#[cfg(feature = "vault")]
+#[cfg_attr(docsrs, doc(cfg(feature = "vault")))]
+pub use agents_secrets::{VaultSecretsProvider, VaultSecretsProviderBuilder};
+
+// Re-export the Cedar policy provider (when cedar feature is enabled)
+#[cfg(feature = "cedar")]
+#[cfg_attr(docsrs, doc(cfg(feature = "cedar")))]
+pub use agents_runtime::CedarPolicyProvider;
+
+// Re-export the debug dashboard (when debug-ui feature is enabled)
+#[cfg(feature = "debug-ui")]
+#[cfg_attr(docsrs, doc(cfg(feature = "debug-ui")))]
+pub use agents_runtime::DebugUi;
+
+// Re-export the email channel (when email feature is enabled)
+#[cfg(feature = "email")]
+#[cfg_attr(docsrs, doc(cfg(feature = "email")))]
+pub use agents_runtime::{
+    EmailChannel, ImapConfig, ImapMailbox, InboundEmail, MailSender, Mailbox, SmtpConfig,
+    SmtpSecurity, SmtpSender,
+};
+
+// Re-export the Telegram channel (when telegram feature is enabled)
+#[cfg(feature = "telegram")]
+#[cfg_attr(docsrs, doc(cfg(feature = "telegram")))]
+pub use agents_runtime::{TelegramChannel, TelegramConfig, TelegramMessage};
+
+// Re-export the Twilio channel (when twilio feature is enabled)
+#[cfg(feature = "twilio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "twilio")))]
+pub use agents_runtime::{InboundMessage, TwilioChannel, TwilioConfig};
 
 // Re-export MCP functionality (when mcp feature is enabled)
 #[cfg(feature = "mcp")]
 #[cfg_attr(docsrs, doc(cfg(feature = "mcp")))]
 pub use agents_mcp::{
-    create_mcp_tools, McpClient, McpClientConfig, McpContent, McpError, McpTool, McpToolAdapter,
-    McpToolResult, StdioTransport,
+    create_mcp_tools, McpAgentServer, McpClient, McpClientConfig, McpContent, McpError,
+    McpServerConfig, McpServerTransport, McpServers, McpServersConfig, McpTool, McpToolAdapter,
+    McpToolCache, McpToolResult, StdioTransport,
 };
 
 // Re-export HTTP transport (when mcp-http feature is enabled)
 #[cfg(feature = "mcp-http")]
 #[cfg_attr(docsrs, doc(cfg(feature = "mcp-http")))]
-pub use agents_mcp::{HttpTransport, HttpTransportBuilder};
+pub use agents_mcp::{
+    AuthorizationHandler, HttpTransport, HttpTransportBuilder, McpOAuth, OAuthCredentials,
+};
 
 /// MCP (Model Context Protocol) integration module
 ///
@@ -253,7 +521,9 @@ pub mod prelude {
 
     // Core types
     pub use agents_core::agent::{AgentHandle, PlannerHandle};
-    pub use agents_core::messaging::{AgentMessage, MessageContent, MessageRole, ToolInvocation};
+    pub use agents_core::messaging::{
+        AgentMessage, ImageData, MessageContent, MessageRole, ToolInvocation,
+    };
     pub use agents_core::persistence::{Checkpointer, ThreadId};
     pub use agents_core::state::AgentStateSnapshot;
 
@@ -0,0 +1,69 @@
+//! Secrets from a directory with one file per secret.
+//!
+//! This is how Docker (`/run/secrets`) and Kubernetes secret volumes expose
+//! secrets: `secret://openai-api-key` reads `<dir>/openai-api-key`. Files are read
+//! on every fetch, so values updated in the mount are picked up without a restart.
+
+use agents_core::secrets::SecretsProvider;
+use anyhow::Context;
+use async_trait::async_trait;
+use std::path::{Component, Path, PathBuf};
+
+/// Secrets provider reading files of a directory.
+#[derive(Debug, Clone)]
+pub struct DirectorySecretsProvider {
+    dir: PathBuf,
+}
+
+impl DirectorySecretsProvider {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Path of a secret's file. Keys may contain subdirectories but can't leave `dir`.
+    fn path(&self, key: &str) -> anyhow::Result<PathBuf> {
+        let relative = Path::new(key);
+        if key.is_empty()
+            || !relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        {
+            anyhow::bail!("Invalid secret key: {key}");
+        }
+        Ok(self.dir.join(relative))
+    }
+}
+
+#[async_trait]
+impl SecretsProvider for DirectorySecretsProvider {
+    async fn fetch(&self, key: &str) -> anyhow::Result<String> {
+        let path = self.path(key)?;
+        let value = tokio::fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("Failed to read secret file {}", path.display()))?;
+        // Files written with `echo` end with a newline that isn't part of the secret
+        Ok(value.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn secrets_are_read_from_files_inside_the_directory() {
+        let dir = std::env::temp_dir().join(format!("agents-secrets-{}", std::process::id()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        tokio::fs::write(dir.join("openai-api-key"), "sk-file\n")
+            .await
+            .unwrap();
+        let secrets = DirectorySecretsProvider::new(&dir);
+
+        assert_eq!(secrets.fetch("openai-api-key").await.unwrap(), "sk-file");
+        assert!(secrets.fetch("../etc/passwd").await.is_err());
+        assert!(secrets.fetch("/etc/passwd").await.is_err());
+        assert!(secrets.fetch("missing").await.is_err());
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
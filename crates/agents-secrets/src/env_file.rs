@@ -0,0 +1,134 @@
+//! Secrets from a dotenv file.
+//!
+//! The key of a secret is the variable name: `secret://OPENAI_API_KEY` reads
+//! `OPENAI_API_KEY=...` from the file. The file is read on every fetch, so edits
+//! (e.g. by a rotation job) are picked up without a restart.
+
+use agents_core::secrets::SecretsProvider;
+use anyhow::Context;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Secrets provider reading `KEY=value` lines of an env file.
+///
+/// Supports `#` comments, an optional `export` prefix, single-quoted (literal) and
+/// double-quoted (with `\n`, `\"` and `\\` escapes) values.
+#[derive(Debug, Clone)]
+pub struct EnvFileSecretsProvider {
+    path: PathBuf,
+    env_fallback: bool,
+}
+
+impl EnvFileSecretsProvider {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            env_fallback: false,
+        }
+    }
+
+    /// Fall back to the process environment for keys missing from the file, and
+    /// treat a missing file as empty (default: false).
+    pub fn with_env_fallback(mut self, enabled: bool) -> Self {
+        self.env_fallback = enabled;
+        self
+    }
+
+    async fn read(&self) -> anyhow::Result<HashMap<String, String>> {
+        match tokio::fs::read_to_string(&self.path).await {
+            Ok(contents) => Ok(parse_env(&contents)),
+            Err(e) if self.env_fallback && e.kind() == std::io::ErrorKind::NotFound => {
+                Ok(HashMap::new())
+            }
+            Err(e) => {
+                Err(e).with_context(|| format!("Failed to read env file {}", self.path.display()))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl SecretsProvider for EnvFileSecretsProvider {
+    async fn fetch(&self, key: &str) -> anyhow::Result<String> {
+        if let Some(value) = self.read().await?.remove(key) {
+            return Ok(value);
+        }
+        if self.env_fallback {
+            if let Ok(value) = std::env::var(key) {
+                return Ok(value);
+            }
+        }
+        anyhow::bail!("Secret {key} not found in {}", self.path.display())
+    }
+}
+
+/// Variables of an env file. Later definitions win.
+fn parse_env(contents: &str) -> HashMap<String, String> {
+    let mut vars = HashMap::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            tracing::debug!("Skipping env file line without '='");
+            continue;
+        };
+        vars.insert(key.trim().to_string(), parse_value(value.trim()));
+    }
+    vars
+}
+
+fn parse_value(value: &str) -> String {
+    if let Some(quoted) = value.strip_prefix('\'') {
+        return quoted.split('\'').next().unwrap_or_default().to_string();
+    }
+    if let Some(quoted) = value.strip_prefix('"') {
+        let mut parsed = String::new();
+        let mut chars = quoted.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => break,
+                '\\' => match chars.next() {
+                    Some('n') => parsed.push('\n'),
+                    Some(escaped) => parsed.push(escaped),
+                    None => break,
+                },
+                c => parsed.push(c),
+            }
+        }
+        return parsed;
+    }
+    // Unquoted values end at an inline comment
+    match value.find(" #") {
+        Some(comment) => value[..comment].trim_end().to_string(),
+        None => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_file_values_are_unquoted_and_unescaped() {
+        let vars = parse_env(
+            r#"
+# Provider credentials
+OPENAI_API_KEY=sk-plain # rotated monthly
+export ANTHROPIC_API_KEY="sk-ant \"quoted\""
+GEMINI_API_KEY='lit\n eral'
+PEM="line1\nline2"
+not a variable
+"#,
+        );
+
+        assert_eq!(vars["OPENAI_API_KEY"], "sk-plain");
+        assert_eq!(vars["ANTHROPIC_API_KEY"], "sk-ant \"quoted\"");
+        assert_eq!(vars["GEMINI_API_KEY"], "lit\\n eral");
+        assert_eq!(vars["PEM"], "line1\nline2");
+        assert_eq!(vars.len(), 4);
+    }
+}
@@ -0,0 +1,45 @@
+//! Secrets providers for agent configuration.
+//!
+//! Implementations of [`SecretsProvider`] that don't depend on a cloud, so provider
+//! configs can reference `secret://` URIs wherever the agent runs. The trait, the
+//! `secret://` resolution and the TTL cache live in `agents_core::secrets`.
+//!
+//! ## Available Providers
+//!
+//! - [`EnvFileSecretsProvider`]: variables of a dotenv file
+//! - [`DirectorySecretsProvider`]: one file per secret, as mounted by Docker and
+//!   Kubernetes
+//! - `VaultSecretsProvider`: HashiCorp Vault KV v2 (feature `vault`)
+//!
+//! ## Feature Flags
+//!
+//! - `vault`: Enable the HashiCorp Vault provider
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use agents_core::secrets::resolve_secret;
+//! use agents_secrets::EnvFileSecretsProvider;
+//!
+//! #[tokio::main]
+//! async fn main() -> anyhow::Result<()> {
+//!     let secrets = EnvFileSecretsProvider::new(".env");
+//!     let api_key = resolve_secret(&secrets, "secret://OPENAI_API_KEY").await?;
+//!     Ok(())
+//! }
+//! ```
+
+pub mod directory;
+pub mod env_file;
+
+#[cfg(feature = "vault")]
+pub mod vault;
+
+pub use directory::DirectorySecretsProvider;
+pub use env_file::EnvFileSecretsProvider;
+
+#[cfg(feature = "vault")]
+pub use vault::{VaultSecretsProvider, VaultSecretsProviderBuilder};
+
+// Re-export core types for convenience
+pub use agents_core::secrets::{CachedSecretsProvider, SecretsProvider};
@@ -0,0 +1,205 @@
+//! Secrets from HashiCorp Vault's KV version 2 engine.
+//!
+//! Keys have the form `<path>#<field>`: `secret://agents/openai#api_key` reads the
+//! `api_key` field of the secret at `agents/openai` in the configured mount. The
+//! field may be omitted when the secret has a single field.
+//!
+//! Each fetch is a request to Vault; wrap the provider in a `CachedSecretsProvider`
+//! to keep values for a while.
+
+use agents_core::secrets::SecretsProvider;
+use anyhow::Context;
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use serde_json::Value;
+
+/// Secrets provider reading the KV v2 secrets engine of HashiCorp Vault.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use agents_secrets::VaultSecretsProvider;
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let vault = VaultSecretsProvider::builder()
+///     .address("https://vault.internal:8200")
+///     .token(std::env::var("VAULT_TOKEN")?)
+///     .mount("kv")
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct VaultSecretsProvider {
+    client: Client,
+    address: String,
+    token: String,
+    namespace: Option<String>,
+    mount: String,
+}
+
+impl VaultSecretsProvider {
+    /// Create a builder for configuring the Vault provider.
+    pub fn builder() -> VaultSecretsProviderBuilder {
+        VaultSecretsProviderBuilder::default()
+    }
+}
+
+#[async_trait]
+impl SecretsProvider for VaultSecretsProvider {
+    async fn fetch(&self, key: &str) -> anyhow::Result<String> {
+        let (path, field) = match key.split_once('#') {
+            Some((path, field)) => (path, Some(field)),
+            None => (key, None),
+        };
+        let url = format!(
+            "{}/v1/{}/data/{}",
+            self.address,
+            self.mount,
+            path.trim_start_matches('/')
+        );
+
+        let mut request = self.client.get(&url).header("X-Vault-Token", &self.token);
+        if let Some(namespace) = &self.namespace {
+            request = request.header("X-Vault-Namespace", namespace);
+        }
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach Vault at {}", self.address))?;
+
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND {
+            anyhow::bail!("Secret {path} not found in Vault mount {}", self.mount);
+        }
+        if !status.is_success() {
+            anyhow::bail!("Vault returned {status} reading secret {path}");
+        }
+        let body: Value = response.json().await?;
+        select_field(&body, path, field)
+    }
+}
+
+/// Value of `field` in a KV v2 read response.
+fn select_field(body: &Value, path: &str, field: Option<&str>) -> anyhow::Result<String> {
+    let data = body
+        .pointer("/data/data")
+        .and_then(Value::as_object)
+        .with_context(|| format!("Vault response for {path} has no data"))?;
+    let value = match field {
+        Some(field) => data
+            .get(field)
+            .with_context(|| format!("Secret {path} has no field {field}"))?,
+        None if data.len() == 1 => data.values().next().unwrap_or(&Value::Null),
+        None => anyhow::bail!(
+            "Secret {path} has {} fields; select one with {path}#<field>",
+            data.len()
+        ),
+    };
+    Ok(match value {
+        Value::String(value) => value.clone(),
+        other => other.to_string(),
+    })
+}
+
+/// Builder for configuring the Vault provider.
+///
+/// Address, token and namespace default to the `VAULT_ADDR`, `VAULT_TOKEN` and
+/// `VAULT_NAMESPACE` environment variables, like the Vault CLI.
+#[derive(Default)]
+pub struct VaultSecretsProviderBuilder {
+    address: Option<String>,
+    token: Option<String>,
+    namespace: Option<String>,
+    mount: Option<String>,
+    client: Option<Client>,
+}
+
+impl VaultSecretsProviderBuilder {
+    /// Set the Vault address, e.g. `https://vault.internal:8200`.
+    pub fn address(mut self, address: impl Into<String>) -> Self {
+        self.address = Some(address.into());
+        self
+    }
+
+    /// Set the Vault token.
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Set the Vault Enterprise namespace.
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Set the mount path of the KV v2 engine (default: "secret").
+    pub fn mount(mut self, mount: impl Into<String>) -> Self {
+        self.mount = Some(mount.into());
+        self
+    }
+
+    /// Use a custom HTTP client, e.g. one trusting a private CA.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Build the Vault provider.
+    pub fn build(self) -> anyhow::Result<VaultSecretsProvider> {
+        let address = self
+            .address
+            .or_else(|| std::env::var("VAULT_ADDR").ok())
+            .context("Vault address is required (set VAULT_ADDR or call address())")?;
+        let token = self
+            .token
+            .or_else(|| std::env::var("VAULT_TOKEN").ok())
+            .context("Vault token is required (set VAULT_TOKEN or call token())")?;
+        let client = match self.client {
+            Some(client) => client,
+            None => Client::builder()
+                .user_agent("rust-deep-agents-sdk/0.1")
+                .build()?,
+        };
+
+        Ok(VaultSecretsProvider {
+            client,
+            address: address.trim_end_matches('/').to_string(),
+            token,
+            namespace: self
+                .namespace
+                .or_else(|| std::env::var("VAULT_NAMESPACE").ok()),
+            mount: self
+                .mount
+                .unwrap_or_else(|| "secret".to_string())
+                .trim_matches('/')
+                .to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn fields_are_selected_from_kv_v2_responses() {
+        let body = json!({
+            "data": {
+                "data": { "api_key": "sk-vault", "org": "org-1" },
+                "metadata": { "version": 3 }
+            }
+        });
+        assert_eq!(
+            select_field(&body, "agents/openai", Some("api_key")).unwrap(),
+            "sk-vault"
+        );
+        assert!(select_field(&body, "agents/openai", Some("missing")).is_err());
+        // Ambiguous without a field
+        assert!(select_field(&body, "agents/openai", None).is_err());
+
+        let single = json!({ "data": { "data": { "token": "t-1" } } });
+        assert_eq!(select_field(&single, "agents/slack", None).unwrap(), "t-1");
+    }
+}
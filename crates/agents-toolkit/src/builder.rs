@@ -3,7 +3,9 @@
 //! This module provides ergonomic helpers for converting regular Rust functions
 //! into Tool implementations that can be registered with agents.
 
-use agents_core::tools::{Tool, ToolBox, ToolContext, ToolParameterSchema, ToolResult, ToolSchema};
+use agents_core::tools::{
+    Tool, ToolBox, ToolContext, ToolDeprecation, ToolParameterSchema, ToolResult, ToolSchema,
+};
 use async_trait::async_trait;
 use serde_json::Value;
 use std::future::Future;
@@ -41,6 +43,9 @@ pub struct ToolBuilder {
     name: String,
     description: String,
     parameters: Option<ToolParameterSchema>,
+    version: Option<String>,
+    deprecation: Option<ToolDeprecation>,
+    self_test_args: Option<Value>,
 }
 
 impl ToolBuilder {
@@ -50,6 +55,9 @@ impl ToolBuilder {
             name: name.into(),
             description: description.into(),
             parameters: None,
+            version: None,
+            deprecation: None,
+            self_test_args: None,
         }
     }
 
@@ -59,19 +67,40 @@ impl ToolBuilder {
         self
     }
 
+    /// Set the schema version for this tool
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// Mark this tool as deprecated
+    pub fn deprecated(mut self, deprecation: ToolDeprecation) -> Self {
+        self.deprecation = Some(deprecation);
+        self
+    }
+
+    /// Mark this tool safe to call from agent self-tests, with `args`
+    pub fn with_self_test_args(mut self, args: Value) -> Self {
+        self.self_test_args = Some(args);
+        self
+    }
+
     /// Build the tool with an async handler function
     pub fn build_async<F, Fut>(self, handler: F) -> ToolBox
     where
         F: Fn(Value, ToolContext) -> Fut + Send + Sync + 'static,
         Fut: Future<Output = anyhow::Result<ToolResult>> + Send + 'static,
     {
-        let schema = ToolSchema::new(
+        let mut schema = ToolSchema::new(
             self.name,
             self.description,
             self.parameters.unwrap_or_else(|| {
                 ToolParameterSchema::object("No parameters", Default::default(), Vec::new())
             }),
         );
+        schema.version = self.version;
+        schema.deprecation = self.deprecation;
+        schema.self_test_args = self.self_test_args;
 
         let handler: AsyncToolFn = Arc::new(move |args, ctx| Box::pin(handler(args, ctx)));
 
@@ -0,0 +1,651 @@
+//! Built-in headless browser tool
+//!
+//! `browser` drives a headless Chrome or Chromium over the DevTools protocol (via
+//! `chromiumoxide`) for pages that only render with JavaScript. The model can
+//! `navigate` to a page, `extract_text` from it and take a `screenshot`, which is
+//! stored in a [`BlobStore`] like a chart.
+//!
+//! The browser only talks to allowed domains: every request the page makes,
+//! including redirects, scripts and images, is checked against the allowlist and
+//! blocked when its host is not on it. Navigation to any other domain needs a
+//! reviewer's approval; register a [`BrowserApprovalPolicy`] so those calls pause
+//! the run instead of failing:
+//!
+//! ```ignore
+//! let browser = BrowserTool::new()
+//!     .allow_domain("docs.rs")
+//!     .allow_domain("*.rust-lang.org");
+//! let agent = ConfigurableAgentBuilder::new("You research Rust crates")
+//!     .with_model(model)
+//!     .with_policy_provider(Arc::new(BrowserApprovalPolicy::for_tool(&browser)))
+//!     .with_checkpointer(checkpointer)
+//!     .with_tool(Arc::new(browser))
+//!     .build()?;
+//! ```
+//!
+//! Each navigation outside the allowlist is reviewed; once approved, the page may
+//! also load resources from that domain for the rest of the thread. The browser is
+//! launched on first use and shared by every thread of the agent; actions run one
+//! at a time and each is bounded by a timeout.
+
+use agents_core::blob::BlobStore;
+use agents_core::hitl::HitlDecisionKind;
+use agents_core::policy::{PolicyDecision, PolicyProvider, PolicyRequest};
+use agents_core::state::AgentStateSnapshot;
+use agents_core::tools::{Tool, ToolBox, ToolContext, ToolParameterSchema, ToolResult, ToolSchema};
+use async_trait::async_trait;
+use chromiumoxide::browser::{Browser, BrowserConfig};
+use chromiumoxide::cdp::browser_protocol::fetch::{
+    ContinueRequestParams, EventRequestPaused, FailRequestParams,
+};
+use chromiumoxide::cdp::browser_protocol::network::ErrorReason;
+use chromiumoxide::cdp::browser_protocol::page::CaptureScreenshotFormat;
+use chromiumoxide::handler::viewport::Viewport;
+use chromiumoxide::page::{Page, ScreenshotParams};
+use futures::StreamExt;
+use reqwest::Url;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Name the tool registers under
+const TOOL_NAME: &str = "browser";
+/// How long one action may take by default
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Most characters of page text returned by default
+const DEFAULT_MAX_TEXT_CHARS: usize = 20_000;
+/// Default viewport in pixels
+const DEFAULT_VIEWPORT: (u32, u32) = (1280, 800);
+/// Largest screenshot stored, in bytes
+const MAX_SCREENSHOT_BYTES: usize = 10 * 1024 * 1024;
+
+/// Domains the browser may load pages and resources from.
+///
+/// `example.com` allows that host only; `*.example.com` allows its subdomains.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DomainAllowlist {
+    hosts: HashSet<String>,
+    suffixes: HashSet<String>,
+}
+
+impl DomainAllowlist {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn allow(&mut self, domain: &str) {
+        let domain = domain.trim().trim_end_matches('.').to_ascii_lowercase();
+        match domain.strip_prefix("*.") {
+            Some(parent) => self.suffixes.insert(format!(".{parent}")),
+            None => self.hosts.insert(domain),
+        };
+    }
+
+    pub fn allows(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        self.hosts.contains(&host) || self.suffixes.iter().any(|suffix| host.ends_with(suffix))
+    }
+}
+
+/// Host of an `http` or `https` URL; other schemes are refused.
+fn web_host(url: &str) -> anyhow::Result<String> {
+    let parsed = Url::parse(url).map_err(|e| anyhow::anyhow!("Invalid URL '{url}': {e}"))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        anyhow::bail!("Only http and https URLs can be opened, not '{url}'");
+    }
+    parsed
+        .host_str()
+        .map(|host| host.to_ascii_lowercase())
+        .ok_or_else(|| anyhow::anyhow!("URL '{url}' has no host"))
+}
+
+/// Host a `navigate` call of the browser tool goes to, if `args` are one.
+fn navigation_host(args: &serde_json::Value) -> Option<String> {
+    if args.get("action").and_then(|action| action.as_str()) != Some("navigate") {
+        return None;
+    }
+    web_host(args.get("url")?.as_str()?).ok()
+}
+
+/// Domains a reviewer approved navigating to on this thread.
+fn approved_hosts(state: &AgentStateSnapshot, tool_name: &str) -> HashSet<String> {
+    state
+        .hitl_decisions
+        .iter()
+        .filter(|record| {
+            matches!(
+                record.action,
+                HitlDecisionKind::Accept | HitlDecisionKind::Edit
+            )
+        })
+        .filter(|record| {
+            record
+                .edited_tool_name
+                .as_deref()
+                .unwrap_or(&record.tool_name)
+                == tool_name
+        })
+        .filter_map(|record| {
+            navigation_host(record.edited_args.as_ref().unwrap_or(&record.original_args))
+        })
+        .collect()
+}
+
+/// Hosts the page may currently reach: the allowlist plus the domains approved on
+/// the thread the current action runs for.
+#[derive(Clone, Default)]
+struct Access {
+    allowlist: DomainAllowlist,
+    approved: Arc<RwLock<HashSet<String>>>,
+}
+
+impl Access {
+    fn allows_url(&self, url: &str) -> bool {
+        if url.starts_with("data:") || url.starts_with("blob:") || url == "about:blank" {
+            return true;
+        }
+        match web_host(url) {
+            Ok(host) => {
+                self.allowlist.allows(&host)
+                    || self
+                        .approved
+                        .read()
+                        .map(|approved| approved.contains(&host))
+                        .unwrap_or(false)
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+/// A running browser with one page, and the tasks driving it.
+struct Session {
+    _browser: Browser,
+    page: Arc<Page>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// Headless browser tool - opens pages that need JavaScript, extracts their text
+/// and takes screenshots
+pub struct BrowserTool {
+    access: Access,
+    executable: Option<PathBuf>,
+    viewport: (u32, u32),
+    timeout: Duration,
+    max_text_chars: usize,
+    store: Option<Arc<dyn BlobStore>>,
+    session: tokio::sync::Mutex<Option<Session>>,
+}
+
+impl Default for BrowserTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BrowserTool {
+    /// A browser allowed to reach no domain; add some with [`allow_domain`](Self::allow_domain).
+    pub fn new() -> Self {
+        Self {
+            access: Access::default(),
+            executable: None,
+            viewport: DEFAULT_VIEWPORT,
+            timeout: DEFAULT_TIMEOUT,
+            max_text_chars: DEFAULT_MAX_TEXT_CHARS,
+            store: None,
+            session: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    /// Allow pages and resources from `domain` (`example.com`) or its subdomains
+    /// (`*.example.com`).
+    pub fn allow_domain(mut self, domain: &str) -> Self {
+        self.access.allowlist.allow(domain);
+        self
+    }
+
+    /// Domains the browser may reach without approval.
+    pub fn allowlist(&self) -> &DomainAllowlist {
+        &self.access.allowlist
+    }
+
+    /// Run this Chrome or Chromium binary instead of the one found on the system.
+    pub fn with_executable(mut self, path: impl Into<PathBuf>) -> Self {
+        self.executable = Some(path.into());
+        self
+    }
+
+    pub fn with_viewport(mut self, width: u32, height: u32) -> Self {
+        self.viewport = (width, height);
+        self
+    }
+
+    /// How long launching the browser or one action may take (default: 30 seconds).
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Cut page text after this many characters (default: 20,000).
+    pub fn with_max_text_chars(mut self, max_text_chars: usize) -> Self {
+        self.max_text_chars = max_text_chars;
+        self
+    }
+
+    /// Store screenshots here; without a store the `screenshot` action is refused.
+    pub fn with_blob_store(mut self, store: Arc<dyn BlobStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    async fn launch(&self) -> anyhow::Result<Session> {
+        let (width, height) = self.viewport;
+        let mut config = BrowserConfig::builder()
+            .window_size(width, height)
+            .viewport(Viewport {
+                width,
+                height,
+                ..Default::default()
+            })
+            .request_timeout(self.timeout)
+            .launch_timeout(self.timeout)
+            .enable_request_intercept()
+            .incognito()
+            .arg("--disable-extensions")
+            .arg("--disable-sync")
+            .arg("--mute-audio");
+        if let Some(path) = &self.executable {
+            config = config.chrome_executable(path);
+        }
+        let config = config
+            .build()
+            .map_err(|e| anyhow::anyhow!("Invalid browser configuration: {e}"))?;
+
+        let (browser, mut handler) = Browser::launch(config)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to launch the browser: {e}"))?;
+        let mut tasks = vec![tokio::spawn(async move {
+            while let Some(event) = handler.next().await {
+                if event.is_err() {
+                    break;
+                }
+            }
+        })];
+
+        let page = Arc::new(browser.new_page("about:blank").await?);
+        let mut paused = page.event_listener::<EventRequestPaused>().await?;
+        let intercept_page = page.clone();
+        let access = self.access.clone();
+        tasks.push(tokio::spawn(async move {
+            while let Some(event) = paused.next().await {
+                let result = if access.allows_url(&event.request.url) {
+                    intercept_page
+                        .execute(ContinueRequestParams::new(event.request_id.clone()))
+                        .await
+                        .map(|_| ())
+                } else {
+                    tracing::debug!(url = %event.request.url, "Browser request blocked");
+                    intercept_page
+                        .execute(FailRequestParams::new(
+                            event.request_id.clone(),
+                            ErrorReason::BlockedByClient,
+                        ))
+                        .await
+                        .map(|_| ())
+                };
+                if let Err(e) = result {
+                    tracing::warn!("Failed to answer intercepted browser request: {e}");
+                }
+            }
+        }));
+
+        Ok(Session {
+            _browser: browser,
+            page,
+            tasks,
+        })
+    }
+
+    async fn run(&self, args: BrowserArgs, ctx: &ToolContext) -> anyhow::Result<String> {
+        let approved = approved_hosts(&ctx.state, TOOL_NAME);
+        if let BrowserAction::Navigate = args.action {
+            let url = args
+                .url
+                .as_deref()
+                .ok_or_else(|| anyhow::anyhow!("Give the url to navigate to"))?;
+            let host = web_host(url)?;
+            if !self.access.allowlist.allows(&host) && !approved.contains(&host) {
+                anyhow::bail!(
+                    "'{host}' is not an allowed domain and navigating to it was not approved"
+                );
+            }
+        }
+        if let BrowserAction::Screenshot = args.action {
+            if self.store.is_none() {
+                anyhow::bail!("Screenshots are not enabled for this browser");
+            }
+        }
+
+        let mut session = self.session.lock().await;
+        *self
+            .access
+            .approved
+            .write()
+            .map_err(|_| anyhow::anyhow!("Failed to acquire write lock on approved domains"))? =
+            approved;
+        if session.is_none() {
+            let launched = tokio::time::timeout(self.timeout, self.launch())
+                .await
+                .map_err(|_| anyhow::anyhow!("The browser did not start in time"))??;
+            *session = Some(launched);
+        }
+        let page = session.as_ref().map(|session| session.page.clone());
+        let Some(page) = page else {
+            anyhow::bail!("The browser is not running");
+        };
+
+        let result = tokio::time::timeout(self.timeout, self.act(&page, args, ctx))
+            .await
+            .unwrap_or_else(|_| {
+                Err(anyhow::anyhow!(
+                    "The browser action took longer than {} seconds",
+                    self.timeout.as_secs()
+                ))
+            });
+        if result.is_err() {
+            // Start from a fresh browser next time rather than a half-loaded page
+            *session = None;
+        }
+        result
+    }
+
+    async fn act(
+        &self,
+        page: &Page,
+        args: BrowserArgs,
+        ctx: &ToolContext,
+    ) -> anyhow::Result<String> {
+        match args.action {
+            BrowserAction::Navigate => {
+                let url = args.url.unwrap_or_default();
+                page.goto(url.as_str())
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Failed to open {url}: {e}"))?;
+                let current = page.url().await?.unwrap_or(url);
+                if !self.access.allows_url(&current) {
+                    anyhow::bail!("The page redirected to {current}, which is not allowed");
+                }
+                let title = page.get_title().await?.unwrap_or_default();
+                let text = self.page_text(page, None).await?;
+                Ok(format!("Opened {current}\nTitle: {title}\n\n{text}"))
+            }
+            BrowserAction::ExtractText => self.page_text(page, args.selector.as_deref()).await,
+            BrowserAction::Screenshot => {
+                let bytes = page
+                    .screenshot(
+                        ScreenshotParams::builder()
+                            .format(CaptureScreenshotFormat::Png)
+                            .full_page(args.full_page)
+                            .build(),
+                    )
+                    .await?;
+                if bytes.len() > MAX_SCREENSHOT_BYTES {
+                    anyhow::bail!("The screenshot is too large; take one of the viewport only");
+                }
+                let Some(store) = &self.store else {
+                    anyhow::bail!("Screenshots are not enabled for this browser");
+                };
+                let key = format!("screenshots/{}.png", ctx.new_uuid());
+                let location = store.put(&key, bytes, "image/png").await?;
+                let url = page.url().await?.unwrap_or_default();
+                Ok(json!({
+                    "location": location,
+                    "content_type": "image/png",
+                    "page": url,
+                    "markdown": format!("![Screenshot of {url}]({location})"),
+                })
+                .to_string())
+            }
+        }
+    }
+
+    /// Visible text of the page, or of the elements matching `selector`.
+    async fn page_text(&self, page: &Page, selector: Option<&str>) -> anyhow::Result<String> {
+        let script = match selector {
+            Some(selector) => format!(
+                "Array.from(document.querySelectorAll({})).map(e => e.innerText).join('\\n\\n')",
+                serde_json::to_string(selector)?
+            ),
+            None => "document.body ? document.body.innerText : ''".to_string(),
+        };
+        let text: String = page.evaluate(script).await?.into_value()?;
+        if text.trim().is_empty() {
+            return Ok(match selector {
+                Some(selector) => format!("No text found for '{selector}'"),
+                None => "The page has no text".to_string(),
+            });
+        }
+        let total = text.chars().count();
+        if total <= self.max_text_chars {
+            return Ok(text);
+        }
+        let clipped: String = text.chars().take(self.max_text_chars).collect();
+        Ok(format!(
+            "{clipped}\n\n[Showing the first {} of {total} characters; use a selector to read a part of the page]",
+            self.max_text_chars
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum BrowserAction {
+    Navigate,
+    ExtractText,
+    Screenshot,
+}
+
+#[derive(Deserialize)]
+struct BrowserArgs {
+    action: BrowserAction,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    selector: Option<String>,
+    #[serde(default)]
+    full_page: bool,
+}
+
+#[async_trait]
+impl Tool for BrowserTool {
+    fn schema(&self) -> ToolSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "action".to_string(),
+            ToolParameterSchema::string_enum(
+                "navigate opens a URL and returns its text, extract_text reads the open page, screenshot captures it",
+                ["navigate", "extract_text", "screenshot"],
+            ),
+        );
+        properties.insert(
+            "url".to_string(),
+            ToolParameterSchema::string("URL to open (navigate only)"),
+        );
+        properties.insert(
+            "selector".to_string(),
+            ToolParameterSchema::string(
+                "CSS selector of the elements to read, e.g. 'main' or '.price' (extract_text only; default: the whole page)",
+            ),
+        );
+        properties.insert(
+            "full_page".to_string(),
+            ToolParameterSchema::boolean(
+                "Capture the whole page instead of the visible part (screenshot only)",
+            )
+            .with_default(false),
+        );
+
+        ToolSchema::new(
+            TOOL_NAME,
+            "Open web pages that need JavaScript in a headless browser, read their text or take a screenshot. Only some domains are allowed; others need approval.",
+            ToolParameterSchema::object(
+                "Browser action",
+                properties,
+                vec!["action".to_string()],
+            ),
+        )
+    }
+
+    async fn execute(
+        &self,
+        args: serde_json::Value,
+        ctx: ToolContext,
+    ) -> anyhow::Result<ToolResult> {
+        let args: BrowserArgs = serde_json::from_value(args)?;
+        match self.run(args, &ctx).await {
+            Ok(text) => Ok(ToolResult::text(&ctx, text)),
+            Err(e) => Ok(ToolResult::text(&ctx, format!("Error: {e}"))),
+        }
+    }
+}
+
+/// Policy pausing the run for approval when the model navigates the browser to a
+/// domain outside its allowlist. Every other call is allowed.
+#[derive(Debug, Clone)]
+pub struct BrowserApprovalPolicy {
+    allowlist: DomainAllowlist,
+}
+
+impl BrowserApprovalPolicy {
+    pub fn new(allowlist: DomainAllowlist) -> Self {
+        Self { allowlist }
+    }
+
+    /// Review navigation outside `tool`'s allowlist.
+    pub fn for_tool(tool: &BrowserTool) -> Self {
+        Self::new(tool.allowlist().clone())
+    }
+}
+
+#[async_trait]
+impl PolicyProvider for BrowserApprovalPolicy {
+    fn id(&self) -> &str {
+        "browser-domains"
+    }
+
+    async fn evaluate(&self, request: &PolicyRequest) -> anyhow::Result<PolicyDecision> {
+        if request.tool_name != TOOL_NAME {
+            return Ok(PolicyDecision::Allow);
+        }
+        Ok(match navigation_host(&request.tool_args) {
+            Some(host) if !self.allowlist.allows(&host) => PolicyDecision::RequireApproval {
+                note: Some(format!("Browser navigation to a new domain: {host}")),
+            },
+            _ => PolicyDecision::Allow,
+        })
+    }
+}
+
+/// Create the browser tool, allowed to reach `domains`
+pub fn create_browser_tools<'a>(domains: impl IntoIterator<Item = &'a str>) -> Vec<ToolBox> {
+    let tool = domains
+        .into_iter()
+        .fold(BrowserTool::new(), |tool, domain| tool.allow_domain(domain));
+    vec![Arc::new(tool)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::hitl::{HitlAction, HitlDecisionRecord, HitlInterrupt};
+    use agents_core::policy::PolicyThread;
+
+    fn allowlist() -> DomainAllowlist {
+        let mut allowlist = DomainAllowlist::new();
+        allowlist.allow("docs.rs");
+        allowlist.allow("*.rust-lang.org");
+        allowlist
+    }
+
+    #[test]
+    fn allowlist_matches_hosts_and_subdomains() {
+        let access = Access {
+            allowlist: allowlist(),
+            ..Default::default()
+        };
+        assert!(access.allows_url("https://docs.rs/serde"));
+        assert!(access.allows_url("https://blog.rust-lang.org/2024/"));
+        assert!(access.allows_url("data:image/png;base64,AAAA"));
+        assert!(!access.allows_url("https://rust-lang.org/"));
+        assert!(!access.allows_url("https://docs.rs.evil.com/"));
+        assert!(!access.allows_url("file:///etc/passwd"));
+
+        access.approved.write().unwrap().insert("crates.io".into());
+        assert!(access.allows_url("https://crates.io/crates/tokio"));
+    }
+
+    #[tokio::test]
+    async fn navigation_to_new_domains_needs_approval() {
+        let policy = BrowserApprovalPolicy::new(allowlist());
+        let request = |args: serde_json::Value| PolicyRequest {
+            agent: "agent".into(),
+            tool_name: TOOL_NAME.into(),
+            tool_args: args,
+            claims: json!({}),
+            thread: PolicyThread::default(),
+        };
+        let navigate = json!({"action": "navigate", "url": "https://crates.io/"});
+        assert!(matches!(
+            policy.evaluate(&request(navigate.clone())).await.unwrap(),
+            PolicyDecision::RequireApproval { .. }
+        ));
+        assert_eq!(
+            policy
+                .evaluate(&request(
+                    json!({"action": "navigate", "url": "https://docs.rs/"})
+                ))
+                .await
+                .unwrap(),
+            PolicyDecision::Allow
+        );
+        assert_eq!(
+            policy
+                .evaluate(&request(json!({"action": "extract_text"})))
+                .await
+                .unwrap(),
+            PolicyDecision::Allow
+        );
+
+        let mut state = AgentStateSnapshot::default();
+        let interrupt = HitlInterrupt::new(TOOL_NAME, navigate, "call_1", None);
+        state.hitl_decisions.push(HitlDecisionRecord::new(
+            &interrupt,
+            &HitlAction::Reject { reason: None },
+            None,
+            None,
+        ));
+        assert!(approved_hosts(&state, TOOL_NAME).is_empty());
+        state.hitl_decisions.push(HitlDecisionRecord::new(
+            &interrupt,
+            &HitlAction::Accept,
+            None,
+            None,
+        ));
+        assert_eq!(
+            approved_hosts(&state, TOOL_NAME),
+            HashSet::from(["crates.io".to_string()])
+        );
+    }
+}
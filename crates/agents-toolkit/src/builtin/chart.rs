@@ -0,0 +1,737 @@
+//! Built-in chart rendering tool
+//!
+//! `render_chart` draws line, bar and scatter charts with `plotters`, either from
+//! series given inline or from columns of a table loaded with the data tools. The
+//! PNG or SVG is stored in a [`BlobStore`] and the tool returns its location and a
+//! Markdown image the model can put in its reply, so web UIs can show the chart.
+//!
+//! Rasterizing text needs a font. Pass one with [`RenderChartTool::with_font`]
+//! (e.g. `include_bytes!` of a TTF file); otherwise a few common system fonts
+//! (DejaVu Sans, Liberation Sans, Arial) are tried.
+
+use super::data::Table;
+use agents_core::blob::BlobStore;
+use agents_core::tools::{Tool, ToolBox, ToolContext, ToolParameterSchema, ToolResult, ToolSchema};
+use async_trait::async_trait;
+use plotters::prelude::*;
+use plotters::style::{register_font, FontStyle};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::sync::{Arc, OnceLock};
+
+/// Default image size in pixels
+const DEFAULT_SIZE: (u32, u32) = (800, 500);
+/// Smallest and largest width or height accepted, in pixels
+const SIZE_RANGE: (u32, u32) = (200, 2400);
+/// Most series in one chart
+const MAX_SERIES: usize = 20;
+/// Most data points over all series
+const MAX_POINTS: usize = 10_000;
+/// Font family chart text is drawn in; SVG viewers also fall back to it
+const FONT_FAMILY: &str = "sans-serif";
+/// Fonts tried, in order, when the tool has no font of its own
+const SYSTEM_FONTS: &[&str] = &[
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+    "/usr/share/fonts/liberation-sans/LiberationSans-Regular.ttf",
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+    "/Library/Fonts/Arial.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChartKind {
+    #[default]
+    Line,
+    Bar,
+    Scatter,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChartFormat {
+    #[default]
+    Png,
+    Svg,
+}
+
+impl ChartFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ChartFormat::Png => "png",
+            ChartFormat::Svg => "svg",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            ChartFormat::Png => "image/png",
+            ChartFormat::Svg => "image/svg+xml",
+        }
+    }
+}
+
+/// One line, set of bars or set of points.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChartSeries {
+    pub name: Option<String>,
+    pub points: Vec<(f64, f64)>,
+}
+
+/// What to draw. Without explicit x values, points sit at x = 0, 1, 2, ... and
+/// `categories` label them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChartSpec {
+    pub kind: ChartKind,
+    pub title: Option<String>,
+    pub x_label: Option<String>,
+    pub y_label: Option<String>,
+    pub categories: Option<Vec<String>>,
+    pub series: Vec<ChartSeries>,
+    /// Whether x values are positions 0, 1, 2, ... rather than data
+    pub indexed: bool,
+}
+
+impl ChartSpec {
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.series.is_empty() {
+            anyhow::bail!("Give at least one series");
+        }
+        if self.series.len() > MAX_SERIES {
+            anyhow::bail!("At most {MAX_SERIES} series fit in one chart");
+        }
+        let points: usize = self.series.iter().map(|series| series.points.len()).sum();
+        if points > MAX_POINTS {
+            anyhow::bail!("{points} points is more than the {MAX_POINTS} a chart may have");
+        }
+        if let Some(series) = self.series.iter().find(|series| series.points.is_empty()) {
+            anyhow::bail!(
+                "Series '{}' has no values",
+                series.name.as_deref().unwrap_or("unnamed")
+            );
+        }
+        if self.kind == ChartKind::Bar && !self.indexed {
+            anyhow::bail!("Bar charts take categories, not x values");
+        }
+        Ok(())
+    }
+
+    fn x_range(&self) -> (f64, f64) {
+        if self.indexed {
+            let count = self
+                .series
+                .iter()
+                .map(|series| series.points.len())
+                .chain(self.categories.as_ref().map(Vec::len))
+                .max()
+                .unwrap_or(1);
+            return (-0.5, count as f64 - 0.5);
+        }
+        padded(
+            self.series
+                .iter()
+                .flat_map(|s| s.points.iter().map(|p| p.0)),
+        )
+    }
+
+    fn y_range(&self) -> (f64, f64) {
+        let values = self
+            .series
+            .iter()
+            .flat_map(|s| s.points.iter().map(|p| p.1));
+        if self.kind != ChartKind::Bar {
+            return padded(values);
+        }
+        // Bars grow from zero, so the axis must include it
+        let (low, high) = values.fold((0.0f64, 0.0f64), |(low, high), y| (low.min(y), high.max(y)));
+        let pad = if high > low { (high - low) * 0.05 } else { 1.0 };
+        (
+            if low < 0.0 { low - pad } else { 0.0 },
+            if high > 0.0 || low == 0.0 {
+                high + pad
+            } else {
+                0.0
+            },
+        )
+    }
+
+    fn x_tick(&self, x: f64) -> String {
+        if !self.indexed {
+            return format_number(x);
+        }
+        let index = x.round();
+        if (x - index).abs() > 1e-6 || index < 0.0 {
+            return String::new();
+        }
+        match &self.categories {
+            Some(categories) => categories.get(index as usize).cloned().unwrap_or_default(),
+            None => format_number(index),
+        }
+    }
+}
+
+/// Smallest and largest value, widened by 5% so points don't sit on the frame.
+fn padded(values: impl Iterator<Item = f64>) -> (f64, f64) {
+    let (low, high) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), v| {
+        (low.min(v), high.max(v))
+    });
+    if !low.is_finite() || !high.is_finite() {
+        return (0.0, 1.0);
+    }
+    let pad = if high > low {
+        (high - low) * 0.05
+    } else {
+        low.abs().max(1.0) * 0.1
+    };
+    (low - pad, high + pad)
+}
+
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        return format!("{value:.0}");
+    }
+    let text = format!("{value:.3}");
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" { "0" } else { text }.to_string()
+}
+
+fn plot_error(e: impl std::fmt::Display) -> anyhow::Error {
+    anyhow::anyhow!("Failed to draw chart: {e}")
+}
+
+fn draw<DB: DrawingBackend>(
+    root: &DrawingArea<DB, plotters::coord::Shift>,
+    spec: &ChartSpec,
+) -> anyhow::Result<()> {
+    root.fill(&WHITE).map_err(plot_error)?;
+
+    let (x_low, x_high) = spec.x_range();
+    let (y_low, y_high) = spec.y_range();
+    let mut builder = ChartBuilder::on(root);
+    builder
+        .margin(20)
+        .x_label_area_size(if spec.x_label.is_some() { 50 } else { 35 })
+        .y_label_area_size(if spec.y_label.is_some() { 70 } else { 55 });
+    if let Some(title) = &spec.title {
+        builder.caption(title, (FONT_FAMILY, 24));
+    }
+    let mut chart = builder
+        .build_cartesian_2d(x_low..x_high, y_low..y_high)
+        .map_err(plot_error)?;
+
+    let x_tick = |x: &f64| spec.x_tick(*x);
+    let y_tick = |y: &f64| format_number(*y);
+    let mut mesh = chart.configure_mesh();
+    mesh.label_style((FONT_FAMILY, 14))
+        .axis_desc_style((FONT_FAMILY, 16))
+        .x_label_formatter(&x_tick)
+        .y_label_formatter(&y_tick);
+    if spec.indexed {
+        mesh.disable_x_mesh()
+            .x_labels(spec.x_range().1.ceil() as usize + 1);
+    }
+    if let Some(label) = &spec.x_label {
+        mesh.x_desc(label);
+    }
+    if let Some(label) = &spec.y_label {
+        mesh.y_desc(label);
+    }
+    mesh.draw().map_err(plot_error)?;
+
+    let bar_width = 0.8 / spec.series.len() as f64;
+    for (i, series) in spec.series.iter().enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        let annotation = match spec.kind {
+            ChartKind::Line => chart.draw_series(LineSeries::new(
+                series.points.iter().copied(),
+                color.stroke_width(2),
+            )),
+            ChartKind::Scatter => chart.draw_series(
+                series
+                    .points
+                    .iter()
+                    .map(|&point| Circle::new(point, 4, color.filled())),
+            ),
+            ChartKind::Bar => chart.draw_series(series.points.iter().map(|&(x, y)| {
+                let left = x - 0.4 + bar_width * i as f64;
+                Rectangle::new([(left, 0.0), (left + bar_width, y)], color.filled())
+            })),
+        }
+        .map_err(plot_error)?;
+        if let Some(name) = &series.name {
+            annotation.label(name).legend(move |(x, y)| {
+                Rectangle::new([(x, y - 5), (x + 12, y + 5)], color.filled())
+            });
+        }
+    }
+
+    if spec.series.iter().any(|series| series.name.is_some()) {
+        chart
+            .configure_series_labels()
+            .label_font((FONT_FAMILY, 14))
+            .background_style(WHITE.mix(0.85))
+            .border_style(BLACK)
+            .draw()
+            .map_err(plot_error)?;
+    }
+    root.present().map_err(plot_error)?;
+    Ok(())
+}
+
+/// Render `spec` as a PNG or SVG image of `size` pixels. Text is drawn in the
+/// `sans-serif` family, which must have been registered with [`register_font`].
+pub fn render_chart(
+    spec: &ChartSpec,
+    format: ChartFormat,
+    size: (u32, u32),
+) -> anyhow::Result<Vec<u8>> {
+    spec.validate()?;
+    match format {
+        ChartFormat::Svg => {
+            let mut svg = String::new();
+            {
+                let root = SVGBackend::with_string(&mut svg, size).into_drawing_area();
+                draw(&root, spec)?;
+            }
+            Ok(svg.into_bytes())
+        }
+        ChartFormat::Png => {
+            let (width, height) = size;
+            let mut pixels = vec![0u8; width as usize * height as usize * 3];
+            {
+                let root = BitMapBackend::with_buffer(&mut pixels, size).into_drawing_area();
+                draw(&root, spec)?;
+            }
+            let image = image::RgbImage::from_raw(width, height, pixels)
+                .ok_or_else(|| anyhow::anyhow!("Chart buffer does not match its size"))?;
+            let mut png = Vec::new();
+            image.write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png)?;
+            Ok(png)
+        }
+    }
+}
+
+/// Registers the first system font found, once per process.
+fn register_system_font() -> anyhow::Result<()> {
+    static REGISTERED: OnceLock<bool> = OnceLock::new();
+    let registered = *REGISTERED.get_or_init(|| {
+        SYSTEM_FONTS.iter().any(|path| {
+            std::fs::read(path).is_ok_and(|bytes| {
+                let bytes: &'static [u8] = Box::leak(bytes.into_boxed_slice());
+                register_font(FONT_FAMILY, FontStyle::Normal, bytes).is_ok()
+            })
+        })
+    });
+    if registered {
+        Ok(())
+    } else {
+        anyhow::bail!(
+            "No font found for chart text; give the tool one with RenderChartTool::with_font"
+        )
+    }
+}
+
+/// Chart rendering tool - draws a chart and stores the image in a blob store
+pub struct RenderChartTool {
+    store: Arc<dyn BlobStore>,
+    font: Option<&'static [u8]>,
+}
+
+impl RenderChartTool {
+    pub fn new(store: Arc<dyn BlobStore>) -> Self {
+        Self { store, font: None }
+    }
+
+    /// Render text with this TrueType or OpenType font instead of a system font.
+    pub fn with_font(mut self, font: &'static [u8]) -> Self {
+        self.font = Some(font);
+        self
+    }
+
+    fn register_font(&self) -> anyhow::Result<()> {
+        match self.font {
+            Some(bytes) => register_font(FONT_FAMILY, FontStyle::Normal, bytes)
+                .map_err(|_| anyhow::anyhow!("The chart font is not a valid TTF or OTF font")),
+            None => register_system_font(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SeriesArgs {
+    #[serde(default)]
+    name: Option<String>,
+    values: Vec<f64>,
+    #[serde(default)]
+    x: Option<Vec<f64>>,
+}
+
+#[derive(Deserialize)]
+struct RenderChartArgs {
+    #[serde(default)]
+    chart_type: ChartKind,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    x_label: Option<String>,
+    #[serde(default)]
+    y_label: Option<String>,
+    #[serde(default)]
+    categories: Option<Vec<String>>,
+    #[serde(default)]
+    series: Vec<SeriesArgs>,
+    #[serde(default)]
+    table: Option<String>,
+    #[serde(default)]
+    x_column: Option<String>,
+    #[serde(default)]
+    y_columns: Vec<String>,
+    #[serde(default)]
+    format: ChartFormat,
+    #[serde(default)]
+    width: Option<u32>,
+    #[serde(default)]
+    height: Option<u32>,
+}
+
+impl RenderChartArgs {
+    fn spec(self, ctx: &ToolContext) -> anyhow::Result<ChartSpec> {
+        let mut spec = ChartSpec {
+            kind: self.chart_type,
+            title: self.title,
+            x_label: self.x_label,
+            y_label: self.y_label,
+            categories: self.categories,
+            ..Default::default()
+        };
+        if let Some(name) = &self.table {
+            if !self.series.is_empty() {
+                anyhow::bail!("Give either series or a table, not both");
+            }
+            let table = Table::load(&ctx.state, name)?;
+            table_series(&table, self.x_column.as_deref(), &self.y_columns, &mut spec)?;
+            return Ok(spec);
+        }
+
+        let with_x = self
+            .series
+            .iter()
+            .filter(|series| series.x.is_some())
+            .count();
+        if with_x != 0 && with_x != self.series.len() {
+            anyhow::bail!("Give x values for every series or for none");
+        }
+        spec.indexed = with_x == 0;
+        for series in self.series {
+            let points = match series.x {
+                Some(x) if x.len() != series.values.len() => anyhow::bail!(
+                    "Series '{}' has {} x values for {} values",
+                    series.name.as_deref().unwrap_or("unnamed"),
+                    x.len(),
+                    series.values.len()
+                ),
+                Some(x) => x.into_iter().zip(series.values).collect(),
+                None => series
+                    .values
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, y)| (i as f64, y))
+                    .collect(),
+            };
+            spec.series.push(ChartSeries {
+                name: series.name,
+                points,
+            });
+        }
+        Ok(spec)
+    }
+}
+
+/// Fill `spec` with a series per `y_columns` entry. A numeric x column gives x
+/// values, any other x column labels the rows.
+fn table_series(
+    table: &Table,
+    x_column: Option<&str>,
+    y_columns: &[String],
+    spec: &mut ChartSpec,
+) -> anyhow::Result<()> {
+    if y_columns.is_empty() {
+        anyhow::bail!("Name the table columns to plot in y_columns");
+    }
+    let x_index = x_column
+        .map(|column| table.column_index(column))
+        .transpose()?;
+    let x_numbers: Option<Vec<f64>> =
+        x_index.and_then(|index| table.rows.iter().map(|row| row[index].as_f64()).collect());
+    spec.indexed = x_numbers.is_none() || spec.kind == ChartKind::Bar;
+    if spec.indexed && spec.categories.is_none() {
+        spec.categories = x_index.map(|index| {
+            table
+                .rows
+                .iter()
+                .map(|row| match &row[index] {
+                    Value::String(text) => text.clone(),
+                    Value::Null => String::new(),
+                    other => other.to_string(),
+                })
+                .collect()
+        });
+    }
+    if spec.x_label.is_none() {
+        spec.x_label = x_index.map(|index| table.columns[index].clone());
+    }
+
+    for column in y_columns {
+        let index = table.column_index(column)?;
+        let mut points = Vec::with_capacity(table.rows.len());
+        for (row_number, row) in table.rows.iter().enumerate() {
+            let y = match &row[index] {
+                Value::Null => continue,
+                value => value.as_f64().ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Column '{column}' has the non-numeric value {value} in row {}",
+                        row_number + 1
+                    )
+                })?,
+            };
+            let x = match &x_numbers {
+                Some(x) if !spec.indexed => x[row_number],
+                _ => row_number as f64,
+            };
+            points.push((x, y));
+        }
+        spec.series.push(ChartSeries {
+            name: Some(table.columns[index].clone()),
+            points,
+        });
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl Tool for RenderChartTool {
+    fn schema(&self) -> ToolSchema {
+        let mut series = HashMap::new();
+        series.insert(
+            "name".to_string(),
+            ToolParameterSchema::string("Legend label"),
+        );
+        series.insert(
+            "values".to_string(),
+            ToolParameterSchema::array("Y values", ToolParameterSchema::number("Value")),
+        );
+        series.insert(
+            "x".to_string(),
+            ToolParameterSchema::array(
+                "X values, one per value (line and scatter charts; default: positions 0, 1, 2, ...)",
+                ToolParameterSchema::number("Value"),
+            ),
+        );
+
+        let mut properties = HashMap::new();
+        properties.insert(
+            "chart_type".to_string(),
+            ToolParameterSchema::string_enum(
+                "Kind of chart (default: line)",
+                ["line", "bar", "scatter"],
+            ),
+        );
+        properties.insert(
+            "title".to_string(),
+            ToolParameterSchema::string("Chart title"),
+        );
+        properties.insert(
+            "x_label".to_string(),
+            ToolParameterSchema::string("X axis label"),
+        );
+        properties.insert(
+            "y_label".to_string(),
+            ToolParameterSchema::string("Y axis label"),
+        );
+        properties.insert(
+            "categories".to_string(),
+            ToolParameterSchema::array(
+                "Labels for positions 0, 1, 2, ... on the x axis, e.g. months or regions",
+                ToolParameterSchema::string("Label"),
+            ),
+        );
+        properties.insert(
+            "series".to_string(),
+            ToolParameterSchema::array(
+                "Data to plot",
+                ToolParameterSchema::object("Series", series, vec!["values".to_string()]),
+            ),
+        );
+        properties.insert(
+            "table".to_string(),
+            ToolParameterSchema::string(
+                "Plot columns of a table loaded with load_table or saved by query_table instead of series",
+            ),
+        );
+        properties.insert(
+            "x_column".to_string(),
+            ToolParameterSchema::string("Table column for the x axis"),
+        );
+        properties.insert(
+            "y_columns".to_string(),
+            ToolParameterSchema::array(
+                "Numeric table columns to plot, one series each",
+                ToolParameterSchema::string("Column"),
+            ),
+        );
+        properties.insert(
+            "format".to_string(),
+            ToolParameterSchema::string_enum("Image format (default: png)", ["png", "svg"]),
+        );
+        properties.insert(
+            "width".to_string(),
+            ToolParameterSchema::integer(format!("Width in pixels (default: {})", DEFAULT_SIZE.0)),
+        );
+        properties.insert(
+            "height".to_string(),
+            ToolParameterSchema::integer(format!("Height in pixels (default: {})", DEFAULT_SIZE.1)),
+        );
+
+        ToolSchema::new(
+            "render_chart",
+            "Draw a line, bar or scatter chart and store it as an image. Returns its URL \
+             and a Markdown image to include in your reply",
+            ToolParameterSchema::object("Render chart parameters", properties, vec![]),
+        )
+    }
+
+    async fn execute(&self, args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+        let args: RenderChartArgs = serde_json::from_value(args)?;
+        let format = args.format;
+        let size = (
+            args.width
+                .unwrap_or(DEFAULT_SIZE.0)
+                .clamp(SIZE_RANGE.0, SIZE_RANGE.1),
+            args.height
+                .unwrap_or(DEFAULT_SIZE.1)
+                .clamp(SIZE_RANGE.0, SIZE_RANGE.1),
+        );
+        let rendered = self.register_font().and_then(|()| {
+            let spec = args.spec(&ctx)?;
+            Ok((render_chart(&spec, format, size)?, spec))
+        });
+        let (bytes, spec) = match rendered {
+            Ok(rendered) => rendered,
+            Err(e) => return Ok(ToolResult::text(&ctx, format!("Error: {e}"))),
+        };
+
+        let key = format!("charts/{}.{}", ctx.new_uuid(), format.extension());
+        let location = self.store.put(&key, bytes, format.content_type()).await?;
+        let alt = spec.title.as_deref().unwrap_or("Chart");
+        Ok(ToolResult::json(
+            &ctx,
+            json!({
+                "location": location,
+                "content_type": format.content_type(),
+                "width": size.0,
+                "height": size.1,
+                "markdown": format!("![{alt}]({location})"),
+            }),
+        ))
+    }
+}
+
+/// Create the `render_chart` tool, storing images in `store`
+pub fn create_chart_tools(store: Arc<dyn BlobStore>) -> Vec<ToolBox> {
+    vec![Arc::new(RenderChartTool::new(store))]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::blob::InMemoryBlobStore;
+    use agents_core::messaging::MessageContent;
+    use agents_core::state::AgentStateSnapshot;
+
+    async fn run(tool: &dyn Tool, state: AgentStateSnapshot, args: Value) -> Value {
+        let ctx = ToolContext::new(Arc::new(state));
+        match tool.execute(args, ctx).await.unwrap() {
+            ToolResult::Message(message) => match message.content {
+                MessageContent::Json(value) => value,
+                MessageContent::Text(text) => Value::String(text),
+                other => panic!("unexpected content: {other:?}"),
+            },
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn bar_axis_includes_zero_and_labels_categories() {
+        let spec = ChartSpec {
+            kind: ChartKind::Bar,
+            categories: Some(vec!["North".into(), "South".into()]),
+            series: vec![ChartSeries {
+                name: None,
+                points: vec![(0.0, 40.0), (1.0, 60.0)],
+            }],
+            indexed: true,
+            ..Default::default()
+        };
+        assert_eq!(spec.x_range(), (-0.5, 1.5));
+        assert_eq!(spec.y_range(), (0.0, 63.0));
+        assert_eq!(spec.x_tick(1.0), "South");
+        assert_eq!(spec.x_tick(0.5), "");
+        assert_eq!(format_number(2.50), "2.5");
+        assert_eq!(format_number(-0.0001), "0");
+    }
+
+    #[tokio::test]
+    async fn renders_table_columns_into_the_blob_store() {
+        if register_system_font().is_err() {
+            eprintln!("skipping: no system font to render chart text");
+            return;
+        }
+        let store = Arc::new(InMemoryBlobStore::new());
+        let tool = RenderChartTool::new(store.clone());
+        let table =
+            Table::from_csv("month,revenue,cost\nJan,10,4\nFeb,14,6\nMar,9,5\n", b',').unwrap();
+        let mut state = AgentStateSnapshot::default();
+        state.scratchpad.insert(
+            "table:sales".to_string(),
+            serde_json::to_value(table).unwrap(),
+        );
+
+        let response = run(
+            &tool,
+            state.clone(),
+            json!({
+                "chart_type": "bar",
+                "title": "Q1",
+                "table": "sales",
+                "x_column": "month",
+                "y_columns": ["revenue", "cost"],
+            }),
+        )
+        .await;
+        let location = response["location"].as_str().unwrap();
+        assert_eq!(response["markdown"], format!("![Q1]({location})"));
+        let key = location.trim_start_matches("memory://");
+        let blob = store.get(key).await.unwrap().unwrap();
+        assert_eq!(blob.content_type, "image/png");
+        assert!(blob.bytes.starts_with(b"\x89PNG"));
+
+        let response = run(
+            &tool,
+            state,
+            json!({
+                "format": "svg",
+                "series": [{"name": "a", "values": [1, 2]}, {"values": [3], "x": [0]}],
+            }),
+        )
+        .await;
+        assert_eq!(
+            response,
+            "Error: Give x values for every series or for none"
+        );
+    }
+}
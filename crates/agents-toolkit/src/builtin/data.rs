@@ -0,0 +1,1086 @@
+//! Built-in tabular data tools
+//!
+//! Most "analyze this spreadsheet" requests are a filter, a group-by and a few
+//! sums. These tools cover them without a code interpreter: `load_table` parses a
+//! CSV file from the agent's filesystem (or an Excel workbook with the `xlsx`
+//! feature) into a [`Table`] kept in the state's scratchpad, `query_table`
+//! filters, groups, aggregates and sorts it, and `describe_table` summarizes its
+//! columns. Results are rendered as Markdown tables the model can quote.
+//!
+//! Numbers are summed and averaged as exact decimals, like the `calculate` tool.
+
+use agents_core::command::StateDiff;
+use agents_core::state::AgentStateSnapshot;
+use agents_core::tools::{Tool, ToolBox, ToolContext, ToolParameterSchema, ToolResult, ToolSchema};
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Scratchpad key prefix under which tables are stored
+pub const TABLE_KEY_PREFIX: &str = "table:";
+/// Most rows a loaded table may have; tables are checkpointed with the state
+pub const MAX_TABLE_ROWS: usize = 50_000;
+/// Rows shown when a result has no explicit limit
+const DEFAULT_DISPLAY_ROWS: usize = 50;
+/// Rows previewed after loading a table
+const PREVIEW_ROWS: usize = 5;
+
+/// Rows and named columns. Cells are JSON numbers, strings, booleans or null.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Table {
+    /// Parse CSV text whose first record is the header.
+    pub fn from_csv(text: &str, delimiter: u8) -> anyhow::Result<Self> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_reader(text.as_bytes());
+        let header: Vec<String> = reader.headers()?.iter().map(str::to_string).collect();
+
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            let record = record?;
+            if record.iter().all(str::is_empty) {
+                continue;
+            }
+            rows.push(record.iter().map(parse_cell).collect());
+            if rows.len() > MAX_TABLE_ROWS {
+                anyhow::bail!("The file has more than {MAX_TABLE_ROWS} rows");
+            }
+        }
+        Ok(Self::from_parts(header, rows))
+    }
+
+    /// Parse a sheet of an Excel workbook whose first row is the header; the
+    /// first sheet unless `sheet` names one.
+    #[cfg(feature = "xlsx")]
+    pub fn from_xlsx(bytes: &[u8], sheet: Option<&str>) -> anyhow::Result<Self> {
+        use calamine::{Data, DataType, Reader};
+
+        let mut workbook = calamine::open_workbook_auto_from_rs(std::io::Cursor::new(bytes))?;
+        let names = workbook.sheet_names();
+        let name = match sheet {
+            Some(sheet) => names
+                .iter()
+                .find(|name| name.eq_ignore_ascii_case(sheet))
+                .ok_or_else(|| {
+                    anyhow::anyhow!("No sheet '{sheet}'; sheets: {}", names.join(", "))
+                })?,
+            None => names
+                .first()
+                .ok_or_else(|| anyhow::anyhow!("The workbook has no sheets"))?,
+        }
+        .clone();
+        let range = workbook.worksheet_range(&name)?;
+
+        let cell = |data: &Data| match data {
+            Data::Int(value) => Value::from(*value),
+            // Excel stores every number as a float
+            Data::Float(value) if value.fract() == 0.0 && value.abs() < 1e15 => {
+                Value::from(*value as i64)
+            }
+            Data::Float(value) => serde_json::Number::from_f64(*value)
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+            Data::Bool(value) => Value::Bool(*value),
+            Data::String(text) => parse_cell(text),
+            Data::DateTime(_) => match data.as_datetime() {
+                Some(datetime) if datetime.time() == chrono::NaiveTime::MIN => {
+                    Value::String(datetime.format("%Y-%m-%d").to_string())
+                }
+                Some(datetime) => Value::String(datetime.format("%Y-%m-%d %H:%M:%S").to_string()),
+                None => Value::Null,
+            },
+            Data::DateTimeIso(text) | Data::DurationIso(text) => Value::String(text.clone()),
+            Data::Error(_) | Data::Empty => Value::Null,
+        };
+
+        let mut rows = range.rows();
+        let header = rows
+            .next()
+            .map(|row| {
+                row.iter()
+                    .map(|data| data.to_string().trim().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut table_rows = Vec::new();
+        for row in rows {
+            if row.iter().all(|data| data.is_empty()) {
+                continue;
+            }
+            table_rows.push(row.iter().map(cell).collect());
+            if table_rows.len() > MAX_TABLE_ROWS {
+                anyhow::bail!("The sheet has more than {MAX_TABLE_ROWS} rows");
+            }
+        }
+        Ok(Self::from_parts(header, table_rows))
+    }
+
+    /// Name empty and repeated headers and pad rows to the header's width.
+    fn from_parts(header: Vec<String>, mut rows: Vec<Vec<Value>>) -> Self {
+        let width = rows
+            .iter()
+            .map(Vec::len)
+            .max()
+            .unwrap_or(0)
+            .max(header.len());
+        let mut seen = HashSet::new();
+        let columns = (0..width)
+            .map(|i| {
+                let base = header
+                    .get(i)
+                    .filter(|name| !name.is_empty())
+                    .cloned()
+                    .unwrap_or_else(|| format!("column_{}", i + 1));
+                let mut name = base.clone();
+                let mut n = 2;
+                while !seen.insert(name.clone()) {
+                    name = format!("{base}_{n}");
+                    n += 1;
+                }
+                name
+            })
+            .collect();
+        for row in &mut rows {
+            row.resize(width, Value::Null);
+        }
+        Self { columns, rows }
+    }
+
+    /// The table saved as `name` in the state's scratchpad.
+    pub fn load(state: &AgentStateSnapshot, name: &str) -> anyhow::Result<Self> {
+        let value = state.scratchpad.get(&table_key(name)).ok_or_else(|| {
+            let tables = table_names(state);
+            if tables.is_empty() {
+                anyhow::anyhow!("No table '{name}'; load one with load_table first")
+            } else {
+                anyhow::anyhow!("No table '{name}'; tables: {}", tables.join(", "))
+            }
+        })?;
+        Ok(serde_json::from_value(value.clone())?)
+    }
+
+    /// Index of the column named `name`, ignoring case.
+    pub fn column_index(&self, name: &str) -> anyhow::Result<usize> {
+        self.columns
+            .iter()
+            .position(|column| column == name)
+            .or_else(|| {
+                self.columns
+                    .iter()
+                    .position(|column| column.eq_ignore_ascii_case(name))
+            })
+            .ok_or_else(|| {
+                anyhow::anyhow!("No column '{name}'; columns: {}", self.columns.join(", "))
+            })
+    }
+
+    /// Apply `query` and return the resulting table.
+    pub fn query(&self, query: &TableQuery) -> anyhow::Result<Table> {
+        let filters = query
+            .filters
+            .iter()
+            .map(|filter| Ok((self.column_index(&filter.column)?, filter)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let rows: Vec<&Vec<Value>> = self
+            .rows
+            .iter()
+            .filter(|row| filters.iter().all(|(i, filter)| filter.matches(&row[*i])))
+            .collect();
+
+        let mut result = if query.group_by.is_empty() && query.aggregates.is_empty() {
+            let indices = if query.columns.is_empty() {
+                (0..self.columns.len()).collect()
+            } else {
+                query
+                    .columns
+                    .iter()
+                    .map(|column| self.column_index(column))
+                    .collect::<anyhow::Result<Vec<_>>>()?
+            };
+            Table {
+                columns: indices.iter().map(|&i| self.columns[i].clone()).collect(),
+                rows: rows
+                    .iter()
+                    .map(|row| indices.iter().map(|&i| row[i].clone()).collect())
+                    .collect(),
+            }
+        } else {
+            self.aggregate(&rows, query)?
+        };
+
+        if !query.sort_by.is_empty() {
+            let keys = query
+                .sort_by
+                .iter()
+                .map(|sort| Ok((result.column_index(&sort.column)?, sort.descending)))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            result.rows.sort_by(|a, b| {
+                keys.iter()
+                    .map(|&(i, descending)| {
+                        let ordering = compare(&a[i], &b[i]);
+                        if descending {
+                            ordering.reverse()
+                        } else {
+                            ordering
+                        }
+                    })
+                    .find(|ordering| ordering.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            });
+        }
+        if let Some(limit) = query.limit {
+            result.rows.truncate(limit);
+        }
+        Ok(result)
+    }
+
+    fn aggregate(&self, rows: &[&Vec<Value>], query: &TableQuery) -> anyhow::Result<Table> {
+        let keys = query
+            .group_by
+            .iter()
+            .map(|column| self.column_index(column))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let aggregates = query
+            .aggregates
+            .iter()
+            .map(|aggregate| {
+                let column = match &aggregate.column {
+                    Some(column) => Some(self.column_index(column)?),
+                    None if aggregate.function == AggregateFunction::Count => None,
+                    None => anyhow::bail!("{} needs a column", aggregate.function.name()),
+                };
+                Ok((column, aggregate))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        // Groups in order of first appearance; one group of all rows without keys
+        let mut groups: Vec<(Vec<Value>, Vec<&Vec<Value>>)> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        if keys.is_empty() {
+            groups.push((Vec::new(), rows.to_vec()));
+        } else {
+            for row in rows {
+                let key: Vec<Value> = keys.iter().map(|&i| row[i].clone()).collect();
+                let id = serde_json::to_string(&key)?;
+                let position = *index.entry(id).or_insert_with(|| {
+                    groups.push((key, Vec::new()));
+                    groups.len() - 1
+                });
+                groups[position].1.push(row);
+            }
+        }
+
+        let mut columns: Vec<String> = keys.iter().map(|&i| self.columns[i].clone()).collect();
+        columns.extend(aggregates.iter().map(|(column, aggregate)| {
+            aggregate.alias.clone().unwrap_or_else(|| match column {
+                Some(i) => format!("{}({})", aggregate.function.name(), self.columns[*i]),
+                None => aggregate.function.name().to_string(),
+            })
+        }));
+
+        let mut result_rows = Vec::with_capacity(groups.len());
+        for (key, members) in groups {
+            let mut row = key;
+            for (column, aggregate) in &aggregates {
+                row.push(aggregate.function.apply(
+                    &members,
+                    *column,
+                    column.map(|i| self.columns[i].as_str()),
+                )?);
+            }
+            result_rows.push(row);
+        }
+        Ok(Table {
+            columns,
+            rows: result_rows,
+        })
+    }
+
+    /// The first `max_rows` rows as a Markdown table, with a note on the rest.
+    pub fn to_markdown(&self, max_rows: usize) -> String {
+        let escape = |text: String| text.replace('|', "\\|").replace('\n', " ");
+        let mut out = format!(
+            "| {} |\n|{}|\n",
+            self.columns
+                .iter()
+                .map(|column| escape(column.clone()))
+                .collect::<Vec<_>>()
+                .join(" | "),
+            vec![" --- "; self.columns.len()].join("|")
+        );
+        for row in self.rows.iter().take(max_rows) {
+            let cells: Vec<String> = row.iter().map(|cell| escape(display(cell))).collect();
+            out.push_str(&format!("| {} |\n", cells.join(" | ")));
+        }
+        if self.rows.len() > max_rows {
+            out.push_str(&format!(
+                "\n{} more rows not shown ({} in total)\n",
+                self.rows.len() - max_rows,
+                self.rows.len()
+            ));
+        }
+        out
+    }
+
+    /// One row per column: its kind, how many cells are filled, distinct values,
+    /// and the minimum, maximum and mean of numeric columns.
+    pub fn describe(&self) -> Table {
+        let columns = ["column", "kind", "filled", "distinct", "min", "max", "mean"]
+            .map(String::from)
+            .to_vec();
+        let rows = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let values: Vec<&Value> = self
+                    .rows
+                    .iter()
+                    .map(|row| &row[i])
+                    .filter(|v| !v.is_null())
+                    .collect();
+                let distinct: HashSet<String> = values.iter().map(|v| display(v)).collect();
+                let numbers: Vec<Decimal> = values.iter().filter_map(|v| number(v)).collect();
+                let kind = if values.is_empty() {
+                    "empty"
+                } else if numbers.len() == values.len() {
+                    "number"
+                } else if numbers.is_empty() {
+                    "text"
+                } else {
+                    "mixed"
+                };
+                let (min, max) = if kind == "number" {
+                    (
+                        numbers.iter().min().copied().map(decimal_value),
+                        numbers.iter().max().copied().map(decimal_value),
+                    )
+                } else {
+                    (
+                        values
+                            .iter()
+                            .min_by(|a, b| compare(a, b))
+                            .map(|v| (*v).clone()),
+                        values
+                            .iter()
+                            .max_by(|a, b| compare(a, b))
+                            .map(|v| (*v).clone()),
+                    )
+                };
+                let mean = (kind == "number")
+                    .then(|| mean(&numbers))
+                    .flatten()
+                    .map(decimal_value);
+                vec![
+                    Value::String(name.clone()),
+                    Value::String(kind.to_string()),
+                    Value::from(values.len()),
+                    Value::from(distinct.len()),
+                    min.unwrap_or(Value::Null),
+                    max.unwrap_or(Value::Null),
+                    mean.unwrap_or(Value::Null),
+                ]
+            })
+            .collect();
+        Table { columns, rows }
+    }
+}
+
+/// Filters, grouping, aggregates and ordering applied by [`Table::query`].
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TableQuery {
+    /// Rows must match every filter
+    #[serde(default)]
+    pub filters: Vec<Filter>,
+    #[serde(default)]
+    pub group_by: Vec<String>,
+    #[serde(default)]
+    pub aggregates: Vec<Aggregate>,
+    /// Columns to keep when not aggregating; all when empty
+    #[serde(default)]
+    pub columns: Vec<String>,
+    #[serde(default)]
+    pub sort_by: Vec<Sort>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Filter {
+    pub column: String,
+    pub op: FilterOp,
+    #[serde(default)]
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    /// Case-insensitive substring match
+    Contains,
+    IsEmpty,
+    NotEmpty,
+}
+
+impl Filter {
+    fn matches(&self, cell: &Value) -> bool {
+        match self.op {
+            FilterOp::IsEmpty => is_empty(cell),
+            FilterOp::NotEmpty => !is_empty(cell),
+            FilterOp::Contains => display(cell)
+                .to_lowercase()
+                .contains(&display(&self.value).to_lowercase()),
+            FilterOp::Eq if is_empty(cell) => is_empty(&self.value),
+            FilterOp::Ne if is_empty(cell) => !is_empty(&self.value),
+            _ if is_empty(cell) => false,
+            op => {
+                let ordering = compare(cell, &self.value);
+                match op {
+                    FilterOp::Eq => ordering.is_eq(),
+                    FilterOp::Ne => ordering.is_ne(),
+                    FilterOp::Gt => ordering.is_gt(),
+                    FilterOp::Gte => ordering.is_ge(),
+                    FilterOp::Lt => ordering.is_lt(),
+                    _ => ordering.is_le(),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Aggregate {
+    pub function: AggregateFunction,
+    /// Column to aggregate; `count` without one counts rows
+    #[serde(default)]
+    pub column: Option<String>,
+    /// Name of the result column, e.g. `total_revenue`
+    #[serde(default)]
+    pub alias: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AggregateFunction {
+    Count,
+    CountDistinct,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl AggregateFunction {
+    fn name(self) -> &'static str {
+        match self {
+            AggregateFunction::Count => "count",
+            AggregateFunction::CountDistinct => "count_distinct",
+            AggregateFunction::Sum => "sum",
+            AggregateFunction::Avg => "avg",
+            AggregateFunction::Min => "min",
+            AggregateFunction::Max => "max",
+        }
+    }
+
+    fn apply(
+        self,
+        rows: &[&Vec<Value>],
+        column: Option<usize>,
+        column_name: Option<&str>,
+    ) -> anyhow::Result<Value> {
+        let Some(i) = column else {
+            return Ok(Value::from(rows.len()));
+        };
+        let values: Vec<&Value> = rows
+            .iter()
+            .map(|row| &row[i])
+            .filter(|v| !is_empty(v))
+            .collect();
+        let numbers = || {
+            values
+                .iter()
+                .map(|value| {
+                    number(value).ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "Cannot {} column '{}': '{}' is not a number",
+                            self.name(),
+                            column_name.unwrap_or_default(),
+                            display(value)
+                        )
+                    })
+                })
+                .collect::<anyhow::Result<Vec<_>>>()
+        };
+        Ok(match self {
+            AggregateFunction::Count => Value::from(values.len()),
+            AggregateFunction::CountDistinct => Value::from(
+                values
+                    .iter()
+                    .map(|v| display(v))
+                    .collect::<HashSet<_>>()
+                    .len(),
+            ),
+            AggregateFunction::Sum => {
+                let mut total = Decimal::ZERO;
+                for value in numbers()? {
+                    total = total
+                        .checked_add(value)
+                        .ok_or_else(|| anyhow::anyhow!("Sum is out of range"))?;
+                }
+                decimal_value(total)
+            }
+            AggregateFunction::Avg => mean(&numbers()?).map(decimal_value).unwrap_or(Value::Null),
+            AggregateFunction::Min => values
+                .iter()
+                .min_by(|a, b| compare(a, b))
+                .map(|v| (*v).clone())
+                .unwrap_or(Value::Null),
+            AggregateFunction::Max => values
+                .iter()
+                .max_by(|a, b| compare(a, b))
+                .map(|v| (*v).clone())
+                .unwrap_or(Value::Null),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Sort {
+    pub column: String,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+fn table_key(name: &str) -> String {
+    format!("{TABLE_KEY_PREFIX}{name}")
+}
+
+/// Names of the tables in the state's scratchpad.
+pub fn table_names(state: &AgentStateSnapshot) -> Vec<String> {
+    state
+        .scratchpad
+        .keys()
+        .filter_map(|key| key.strip_prefix(TABLE_KEY_PREFIX))
+        .map(str::to_string)
+        .collect()
+}
+
+/// A CSV cell as a number when it reads as one, null when empty, text otherwise.
+/// Numbers with leading zeros, such as ZIP codes, stay text.
+fn parse_cell(text: &str) -> Value {
+    let text = text.trim();
+    if text.is_empty() {
+        return Value::Null;
+    }
+    let digits = text.trim_start_matches('-');
+    let leading_zero = digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.");
+    if !leading_zero && Decimal::from_str(text).is_ok() {
+        if let Ok(number) = serde_json::from_str::<serde_json::Number>(text) {
+            return Value::Number(number);
+        }
+    }
+    Value::String(text.to_string())
+}
+
+fn number(value: &Value) -> Option<Decimal> {
+    match value {
+        Value::Number(number) => {
+            let text = number.to_string();
+            Decimal::from_str(&text)
+                .or_else(|_| Decimal::from_scientific(&text))
+                .ok()
+        }
+        _ => None,
+    }
+}
+
+fn decimal_value(value: Decimal) -> Value {
+    serde_json::from_str(&value.normalize().to_string()).unwrap_or(Value::Null)
+}
+
+fn mean(numbers: &[Decimal]) -> Option<Decimal> {
+    if numbers.is_empty() {
+        return None;
+    }
+    let total = numbers
+        .iter()
+        .try_fold(Decimal::ZERO, |total, n| total.checked_add(*n))?;
+    total
+        .checked_div(Decimal::from(numbers.len()))
+        .map(|mean| mean.round_dp(10))
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(text) => text.is_empty(),
+        _ => false,
+    }
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Numbers compare numerically (a number given as text included), everything
+/// else as case-insensitive text; empty cells sort last.
+fn compare(a: &Value, b: &Value) -> Ordering {
+    match (is_empty(a), is_empty(b)) {
+        (true, true) => return Ordering::Equal,
+        (true, false) => return Ordering::Greater,
+        (false, true) => return Ordering::Less,
+        _ => {}
+    }
+    let numeric = |value: &Value| match value {
+        Value::String(text) => Decimal::from_str(text.trim()).ok(),
+        other => number(other),
+    };
+    match (numeric(a), numeric(b)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        _ => display(a).to_lowercase().cmp(&display(b).to_lowercase()),
+    }
+}
+
+pub(crate) fn save_table(
+    ctx: &ToolContext,
+    name: &str,
+    table: &Table,
+) -> anyhow::Result<StateDiff> {
+    let value = serde_json::to_value(table)?;
+    if let Some(state_handle) = &ctx.state_handle {
+        let mut state = state_handle.write().expect("state lock poisoned");
+        state.scratchpad.insert(table_key(name), value.clone());
+    }
+    Ok(StateDiff {
+        scratchpad: Some(BTreeMap::from([(table_key(name), value)])),
+        ..StateDiff::default()
+    })
+}
+
+/// Load table tool - parses a CSV (or Excel) file into a table
+pub struct LoadTableTool;
+
+#[derive(Deserialize)]
+struct LoadTableArgs {
+    file_path: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    delimiter: Option<String>,
+    #[cfg_attr(not(feature = "xlsx"), allow(dead_code))]
+    #[serde(default)]
+    sheet: Option<String>,
+}
+
+impl LoadTableTool {
+    fn parse(args: &LoadTableArgs, content: &str) -> anyhow::Result<Table> {
+        let path = args.file_path.to_lowercase();
+        if path.ends_with(".xlsx") || path.ends_with(".xls") || path.ends_with(".ods") {
+            #[cfg(feature = "xlsx")]
+            {
+                use base64::Engine;
+                // Binary files are kept base64-encoded in the agent's filesystem
+                let bytes = base64::engine::general_purpose::STANDARD
+                    .decode(content.trim())
+                    .map_err(|_| anyhow::anyhow!("Spreadsheet files must be base64-encoded"))?;
+                return Table::from_xlsx(&bytes, args.sheet.as_deref());
+            }
+            #[cfg(not(feature = "xlsx"))]
+            anyhow::bail!("Loading spreadsheets needs the `xlsx` feature; export the sheet as CSV");
+        }
+        let delimiter = match args.delimiter.as_deref() {
+            Some("\\t" | "tab") => b'\t',
+            Some(text) if text.len() == 1 => text.as_bytes()[0],
+            Some(text) => anyhow::bail!("Delimiter must be one character, got '{text}'"),
+            None if path.ends_with(".tsv") => b'\t',
+            None => b',',
+        };
+        Table::from_csv(content, delimiter)
+    }
+}
+
+#[async_trait]
+impl Tool for LoadTableTool {
+    fn schema(&self) -> ToolSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "file_path".to_string(),
+            ToolParameterSchema::string(
+                "CSV or TSV file to load; Excel workbooks (.xlsx) when supported",
+            ),
+        );
+        properties.insert(
+            "name".to_string(),
+            ToolParameterSchema::string("Name to query the table by (default: the file name)"),
+        );
+        properties.insert(
+            "delimiter".to_string(),
+            ToolParameterSchema::string("Field delimiter, e.g. ';' or 'tab' (default: ',')"),
+        );
+        properties.insert(
+            "sheet".to_string(),
+            ToolParameterSchema::string("Sheet of an Excel workbook (default: the first)"),
+        );
+
+        ToolSchema::new(
+            "load_table",
+            "Load a CSV file (or Excel sheet) whose first row is the header into a table \
+             for query_table and describe_table",
+            ToolParameterSchema::object(
+                "Load table parameters",
+                properties,
+                vec!["file_path".to_string()],
+            ),
+        )
+    }
+
+    async fn execute(&self, args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+        let args: LoadTableArgs = serde_json::from_value(args)?;
+        let Some(content) = ctx.state.files.get(&args.file_path) else {
+            return Ok(ToolResult::text(
+                &ctx,
+                format!("Error: File '{}' not found", args.file_path),
+            ));
+        };
+        let table = match Self::parse(&args, content) {
+            Ok(table) => table,
+            Err(e) => return Ok(ToolResult::text(&ctx, format!("Error: {e}"))),
+        };
+
+        let name = args.name.clone().unwrap_or_else(|| {
+            let file = args.file_path.rsplit('/').next().unwrap_or(&args.file_path);
+            file.split('.').next().unwrap_or(file).to_string()
+        });
+        let diff = save_table(&ctx, &name, &table)?;
+        let message = ctx.text_response(format!(
+            "Loaded table '{name}' with {} rows and columns: {}\n\n{}",
+            table.rows.len(),
+            table.columns.join(", "),
+            table.to_markdown(PREVIEW_ROWS)
+        ));
+        Ok(ToolResult::with_state(message, diff))
+    }
+}
+
+/// Query table tool - filters, groups, aggregates and sorts a table
+pub struct QueryTableTool;
+
+#[derive(Deserialize)]
+struct QueryTableArgs {
+    table: String,
+    #[serde(flatten)]
+    query: TableQuery,
+    #[serde(default)]
+    save_as: Option<String>,
+}
+
+#[async_trait]
+impl Tool for QueryTableTool {
+    fn schema(&self) -> ToolSchema {
+        let string = ToolParameterSchema::string;
+        let mut filter = HashMap::new();
+        filter.insert("column".to_string(), string("Column to test"));
+        filter.insert(
+            "op".to_string(),
+            ToolParameterSchema::string_enum(
+                "Comparison; contains ignores case",
+                [
+                    "eq",
+                    "ne",
+                    "gt",
+                    "gte",
+                    "lt",
+                    "lte",
+                    "contains",
+                    "is_empty",
+                    "not_empty",
+                ],
+            ),
+        );
+        filter.insert(
+            "value".to_string(),
+            string("Value to compare with; numbers compare numerically, text ignoring case"),
+        );
+        let mut aggregate = HashMap::new();
+        aggregate.insert(
+            "function".to_string(),
+            ToolParameterSchema::string_enum(
+                "Aggregate function",
+                ["count", "count_distinct", "sum", "avg", "min", "max"],
+            ),
+        );
+        aggregate.insert(
+            "column".to_string(),
+            string("Column to aggregate; count without one counts rows"),
+        );
+        aggregate.insert("alias".to_string(), string("Name of the result column"));
+        let mut sort = HashMap::new();
+        sort.insert("column".to_string(), string("Column to sort by"));
+        sort.insert(
+            "descending".to_string(),
+            ToolParameterSchema::boolean("Sort largest first (default: false)"),
+        );
+
+        let mut properties = HashMap::new();
+        properties.insert("table".to_string(), string("Table to query"));
+        properties.insert(
+            "filters".to_string(),
+            ToolParameterSchema::array(
+                "Conditions every row must meet",
+                ToolParameterSchema::object(
+                    "Filter",
+                    filter,
+                    vec!["column".to_string(), "op".to_string()],
+                ),
+            ),
+        );
+        properties.insert(
+            "group_by".to_string(),
+            ToolParameterSchema::array("Columns to group rows by", string("Column")),
+        );
+        properties.insert(
+            "aggregates".to_string(),
+            ToolParameterSchema::array(
+                "Values to compute per group, or over all rows without group_by",
+                ToolParameterSchema::object("Aggregate", aggregate, vec!["function".to_string()]),
+            ),
+        );
+        properties.insert(
+            "columns".to_string(),
+            ToolParameterSchema::array(
+                "Columns to return when not aggregating (default: all)",
+                string("Column"),
+            ),
+        );
+        properties.insert(
+            "sort_by".to_string(),
+            ToolParameterSchema::array(
+                "Result columns to sort by, in order",
+                ToolParameterSchema::object("Sort", sort, vec!["column".to_string()]),
+            ),
+        );
+        properties.insert(
+            "limit".to_string(),
+            ToolParameterSchema::integer("Most rows to return"),
+        );
+        properties.insert(
+            "save_as".to_string(),
+            string("Save the result as a new table with this name"),
+        );
+
+        ToolSchema::new(
+            "query_table",
+            "Filter, group, aggregate (count, sum, avg, min, max) and sort a loaded table; \
+             returns the result as a Markdown table",
+            ToolParameterSchema::object(
+                "Query table parameters",
+                properties,
+                vec!["table".to_string()],
+            ),
+        )
+    }
+
+    async fn execute(&self, args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+        let args: QueryTableArgs = serde_json::from_value(args)?;
+        let result =
+            match Table::load(&ctx.state, &args.table).and_then(|table| table.query(&args.query)) {
+                Ok(result) => result,
+                Err(e) => return Ok(ToolResult::text(&ctx, format!("Error: {e}"))),
+            };
+
+        let shown = args.query.limit.unwrap_or(DEFAULT_DISPLAY_ROWS);
+        let mut text = format!(
+            "{} rows\n\n{}",
+            result.rows.len(),
+            result.to_markdown(shown)
+        );
+        match args.save_as {
+            Some(name) => {
+                let diff = save_table(&ctx, &name, &result)?;
+                text.push_str(&format!("\nSaved as table '{name}'"));
+                Ok(ToolResult::with_state(ctx.text_response(text), diff))
+            }
+            None => Ok(ToolResult::text(&ctx, text)),
+        }
+    }
+}
+
+/// Describe table tool - summarizes the columns of a table
+pub struct DescribeTableTool;
+
+#[derive(Deserialize)]
+struct DescribeTableArgs {
+    table: String,
+}
+
+#[async_trait]
+impl Tool for DescribeTableTool {
+    fn schema(&self) -> ToolSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "table".to_string(),
+            ToolParameterSchema::string("Table to describe"),
+        );
+
+        ToolSchema::new(
+            "describe_table",
+            "Summarize each column of a loaded table: kind, filled and distinct values, \
+             and the minimum, maximum and mean of numeric columns",
+            ToolParameterSchema::object(
+                "Describe table parameters",
+                properties,
+                vec!["table".to_string()],
+            ),
+        )
+    }
+
+    async fn execute(&self, args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+        let args: DescribeTableArgs = serde_json::from_value(args)?;
+        match Table::load(&ctx.state, &args.table) {
+            Ok(table) => Ok(ToolResult::text(
+                &ctx,
+                format!(
+                    "{} rows\n\n{}",
+                    table.rows.len(),
+                    table.describe().to_markdown(usize::MAX)
+                ),
+            )),
+            Err(e) => Ok(ToolResult::text(&ctx, format!("Error: {e}"))),
+        }
+    }
+}
+
+/// Create the `load_table`, `query_table` and `describe_table` tools
+pub fn create_data_tools() -> Vec<ToolBox> {
+    vec![
+        Arc::new(LoadTableTool),
+        Arc::new(QueryTableTool),
+        Arc::new(DescribeTableTool),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const SALES: &str = "region,product,units,price,zip\n\
+        North,Widget,3,19.99,02134\n\
+        South,Widget,1,19.99,30301\n\
+        North,Gadget,2,5.10,02134\n\
+        ,Gadget,4,5.10,\n";
+
+    #[test]
+    fn groups_and_aggregates_exactly() {
+        let table = Table::from_csv(SALES, b',').unwrap();
+        assert_eq!(table.rows[0][4], json!("02134"));
+        assert_eq!(table.rows[3][0], Value::Null);
+
+        let query: TableQuery = serde_json::from_value(json!({
+            "filters": [{"column": "region", "op": "not_empty"}],
+            "group_by": ["Region"],
+            "aggregates": [
+                {"function": "count"},
+                {"function": "sum", "column": "price", "alias": "revenue"},
+                {"function": "avg", "column": "units"},
+            ],
+            "sort_by": [{"column": "revenue", "descending": true}],
+        }))
+        .unwrap();
+        let result = table.query(&query).unwrap();
+        assert_eq!(
+            result.columns,
+            ["region", "count", "revenue", "avg(units)"].map(String::from)
+        );
+        assert_eq!(
+            result.rows[0],
+            vec![json!("North"), json!(2), json!(25.09), json!(2.5)]
+        );
+        assert_eq!(
+            result.rows[1],
+            vec![json!("South"), json!(1), json!(19.99), json!(1)]
+        );
+        assert!(result
+            .to_markdown(10)
+            .starts_with("| region | count | revenue | avg(units) |\n| --- | --- | --- | --- |\n| North | 2 | 25.09 | 2.5 |"));
+
+        let query: TableQuery = serde_json::from_value(json!({
+            "filters": [{"column": "units", "op": "gte", "value": "2"}],
+            "columns": ["product", "units"],
+            "sort_by": [{"column": "units"}],
+            "limit": 2,
+        }))
+        .unwrap();
+        let result = table.query(&query).unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![json!("Gadget"), json!(2)],
+                vec![json!("Widget"), json!(3)]
+            ]
+        );
+
+        let query: TableQuery = serde_json::from_value(json!({
+            "aggregates": [{"function": "sum", "column": "product"}],
+        }))
+        .unwrap();
+        let error = table.query(&query).unwrap_err().to_string();
+        assert!(error.contains("'Widget' is not a number"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn tables_are_loaded_into_state_and_queried() {
+        let mut state = AgentStateSnapshot::default();
+        state.insert_file("/data/sales.csv", SALES);
+        let ctx = ToolContext::new(Arc::new(state.clone()));
+        let result = LoadTableTool
+            .execute(json!({"file_path": "/data/sales.csv"}), ctx)
+            .await
+            .unwrap();
+        let ToolResult::WithStateUpdate { state_diff, .. } = result else {
+            panic!("expected a state update");
+        };
+        state.scratchpad.extend(state_diff.scratchpad.unwrap());
+        assert_eq!(table_names(&state), ["sales"]);
+
+        let ctx = ToolContext::new(Arc::new(state));
+        let result = DescribeTableTool
+            .execute(json!({"table": "sales"}), ctx)
+            .await
+            .unwrap();
+        let ToolResult::Message(message) = result else {
+            panic!("expected a message");
+        };
+        let text = message.content.to_text();
+        assert!(
+            text.contains("| price | number | 4 | 2 | 5.1 | 19.99 | 12.545 |"),
+            "{text}"
+        );
+        assert!(
+            text.contains("| region | text | 3 | 2 | North | South |  |"),
+            "{text}"
+        );
+    }
+}
@@ -20,7 +20,7 @@ impl Tool for LsTool {
     }
 
     async fn execute(&self, _args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
-        let files: Vec<String> = ctx.state.files.keys().cloned().collect();
+        let files = ctx.state.file_paths();
         Ok(ToolResult::json(&ctx, serde_json::json!(files)))
     }
 }
@@ -73,7 +73,7 @@ impl Tool for ReadFileTool {
     async fn execute(&self, args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
         let args: ReadFileArgs = serde_json::from_value(args)?;
 
-        let Some(contents) = ctx.state.files.get(&args.path) else {
+        let Some(contents) = ctx.read_file(&args.path).await? else {
             return Ok(ToolResult::text(
                 &ctx,
                 format!("Error: File '{}' not found", args.path),
@@ -160,7 +160,7 @@ impl Tool for WriteFileTool {
             let mut state = state_handle
                 .write()
                 .expect("filesystem write lock poisoned");
-            state.files.insert(args.path.clone(), args.content.clone());
+            state.insert_file(args.path.clone(), args.content.clone());
         }
 
         // Create state diff for persistence
@@ -230,7 +230,7 @@ impl Tool for EditFileTool {
     async fn execute(&self, args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
         let args: EditFileArgs = serde_json::from_value(args)?;
 
-        let Some(existing) = ctx.state.files.get(&args.path).cloned() else {
+        let Some(existing) = ctx.read_file(&args.path).await? else {
             return Ok(ToolResult::text(
                 &ctx,
                 format!("Error: File '{}' not found", args.path),
@@ -274,7 +274,7 @@ impl Tool for EditFileTool {
             let mut state = state_handle
                 .write()
                 .expect("filesystem write lock poisoned");
-            state.files.insert(args.path.clone(), updated.clone());
+            state.insert_file(args.path.clone(), updated.clone());
         }
 
         // Create state diff
@@ -0,0 +1,386 @@
+//! Built-in exact arithmetic tool
+//!
+//! Language models are unreliable at arithmetic, and binary floating point turns
+//! `0.1 + 0.2` into `0.30000000000000004`. The `calculate` tool evaluates
+//! expressions with 28-digit decimal numbers instead, so prices, totals and
+//! percentages come out exactly as a person would write them.
+
+use agents_core::tools::{Tool, ToolBox, ToolContext, ToolParameterSchema, ToolResult, ToolSchema};
+use async_trait::async_trait;
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Longest expression accepted, in characters
+const MAX_EXPRESSION_LEN: usize = 2_000;
+/// Deepest nesting of parentheses, functions and unary signs
+const MAX_DEPTH: usize = 64;
+/// Largest exponent magnitude for `^`
+const MAX_EXPONENT: i64 = 1_000;
+
+/// Evaluate an arithmetic expression with exact decimal numbers.
+///
+/// Supports `+ - * / %`, `^` with integer exponents, parentheses and the
+/// functions `abs`, `round(x, places)`, `floor`, `ceil`, `min` and `max`.
+pub fn evaluate(expression: &str) -> anyhow::Result<Decimal> {
+    if expression.chars().count() > MAX_EXPRESSION_LEN {
+        anyhow::bail!("Expression is longer than {MAX_EXPRESSION_LEN} characters");
+    }
+    let mut parser = Parser {
+        chars: expression.chars().filter(|c| !c.is_whitespace()).collect(),
+        position: 0,
+        depth: 0,
+    };
+    let value = parser.expression()?;
+    if let Some(c) = parser.peek() {
+        anyhow::bail!("Unexpected '{c}' at position {}", parser.position + 1);
+    }
+    Ok(value)
+}
+
+/// Recursive descent parser evaluating as it goes
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> anyhow::Result<()> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            match self.peek() {
+                Some(c) => anyhow::bail!("Expected '{expected}' but found '{c}'"),
+                None => anyhow::bail!("Expected '{expected}' at the end of the expression"),
+            }
+        }
+    }
+
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> anyhow::Result<T>) -> anyhow::Result<T> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            anyhow::bail!("Expression is nested more than {MAX_DEPTH} levels deep");
+        }
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn expression(&mut self) -> anyhow::Result<Decimal> {
+        let mut value = self.term()?;
+        loop {
+            if self.eat('+') {
+                value = value.checked_add(self.term()?).ok_or_else(overflow)?;
+            } else if self.eat('-') {
+                value = value.checked_sub(self.term()?).ok_or_else(overflow)?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn term(&mut self) -> anyhow::Result<Decimal> {
+        let mut value = self.unary()?;
+        loop {
+            if self.eat('*') {
+                value = value.checked_mul(self.unary()?).ok_or_else(overflow)?;
+            } else if self.eat('/') {
+                let divisor = self.unary()?;
+                if divisor.is_zero() {
+                    anyhow::bail!("Division by zero");
+                }
+                value = value.checked_div(divisor).ok_or_else(overflow)?;
+            } else if self.eat('%') {
+                let divisor = self.unary()?;
+                if divisor.is_zero() {
+                    anyhow::bail!("Division by zero");
+                }
+                value = value.checked_rem(divisor).ok_or_else(overflow)?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Signs bind looser than `^`, so `-2^2` is -4.
+    fn unary(&mut self) -> anyhow::Result<Decimal> {
+        if self.eat('-') {
+            self.nested(|p| p.unary()).map(|value| -value)
+        } else if self.eat('+') {
+            self.nested(|p| p.unary())
+        } else {
+            self.power()
+        }
+    }
+
+    fn power(&mut self) -> anyhow::Result<Decimal> {
+        let base = self.primary()?;
+        if !self.eat('^') {
+            return Ok(base);
+        }
+        let exponent = self.nested(|p| p.unary())?;
+        power(base, exponent)
+    }
+
+    fn primary(&mut self) -> anyhow::Result<Decimal> {
+        match self.peek() {
+            Some('(') => {
+                self.position += 1;
+                let value = self.nested(|p| p.expression())?;
+                self.expect(')')?;
+                Ok(value)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => self.number(),
+            Some(c) if c.is_ascii_alphabetic() => self.function(),
+            Some(c) => anyhow::bail!("Unexpected '{c}' at position {}", self.position + 1),
+            None => anyhow::bail!("Expression ends unexpectedly"),
+        }
+    }
+
+    fn number(&mut self) -> anyhow::Result<Decimal> {
+        let start = self.position;
+        while matches!(self.peek(), Some(c) if c.is_ascii_digit() || c == '.') {
+            self.position += 1;
+        }
+        // Scientific notation such as 1.5e3 or 2e-4
+        if matches!(self.peek(), Some('e' | 'E')) {
+            let mark = self.position;
+            self.position += 1;
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.position += 1;
+            }
+            if matches!(self.peek(), Some(c) if c.is_ascii_digit()) {
+                while matches!(self.peek(), Some(c) if c.is_ascii_digit()) {
+                    self.position += 1;
+                }
+            } else {
+                self.position = mark;
+            }
+        }
+        let text: String = self.chars[start..self.position].iter().collect();
+        let parsed = if text.contains(['e', 'E']) {
+            Decimal::from_scientific(&text)
+        } else {
+            text.parse::<Decimal>()
+        };
+        parsed.map_err(|_| anyhow::anyhow!("Invalid number '{text}'"))
+    }
+
+    fn function(&mut self) -> anyhow::Result<Decimal> {
+        let start = self.position;
+        while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || c == '_') {
+            self.position += 1;
+        }
+        let name: String = self.chars[start..self.position].iter().collect();
+        self.expect('(')?;
+        let mut args = Vec::new();
+        if !self.eat(')') {
+            loop {
+                args.push(self.nested(|p| p.expression())?);
+                if self.eat(')') {
+                    break;
+                }
+                self.expect(',')?;
+            }
+        }
+
+        let arity = |expected: &[usize]| -> anyhow::Result<()> {
+            if expected.contains(&args.len()) {
+                Ok(())
+            } else {
+                anyhow::bail!("{name}() takes {expected:?} arguments, got {}", args.len())
+            }
+        };
+        match name.to_ascii_lowercase().as_str() {
+            "abs" => {
+                arity(&[1])?;
+                Ok(args[0].abs())
+            }
+            "floor" => {
+                arity(&[1])?;
+                Ok(args[0].floor())
+            }
+            "ceil" => {
+                arity(&[1])?;
+                Ok(args[0].ceil())
+            }
+            "round" => {
+                arity(&[1, 2])?;
+                let places = args.get(1).copied().unwrap_or_default();
+                Ok(round(args[0], places)?)
+            }
+            "min" | "max" if args.is_empty() => anyhow::bail!("{name}() needs an argument"),
+            "min" => Ok(args.into_iter().min().unwrap_or_default()),
+            "max" => Ok(args.into_iter().max().unwrap_or_default()),
+            _ => anyhow::bail!(
+                "Unknown function '{name}'; available: abs, round, floor, ceil, min, max"
+            ),
+        }
+    }
+}
+
+fn overflow() -> anyhow::Error {
+    anyhow::anyhow!("Result is out of range")
+}
+
+/// Round half away from zero, the way people round by hand.
+fn round(value: Decimal, places: Decimal) -> anyhow::Result<Decimal> {
+    if !places.fract().is_zero() || places.is_sign_negative() || places > Decimal::from(28) {
+        anyhow::bail!("Decimal places must be a whole number from 0 to 28");
+    }
+    let places = u32::try_from(places)?;
+    Ok(value.round_dp_with_strategy(places, RoundingStrategy::MidpointAwayFromZero))
+}
+
+fn power(base: Decimal, exponent: Decimal) -> anyhow::Result<Decimal> {
+    if !exponent.fract().is_zero() {
+        anyhow::bail!("Exponents must be whole numbers");
+    }
+    let exponent = i64::try_from(exponent)
+        .ok()
+        .filter(|e| e.abs() <= MAX_EXPONENT)
+        .ok_or_else(|| {
+            anyhow::anyhow!("Exponents must be between -{MAX_EXPONENT} and {MAX_EXPONENT}")
+        })?;
+
+    let mut result = Decimal::ONE;
+    let mut square = base;
+    let mut remaining = exponent.unsigned_abs();
+    while remaining > 0 {
+        if remaining & 1 == 1 {
+            result = result.checked_mul(square).ok_or_else(overflow)?;
+        }
+        remaining >>= 1;
+        if remaining > 0 {
+            square = square.checked_mul(square).ok_or_else(overflow)?;
+        }
+    }
+    if exponent < 0 {
+        if result.is_zero() {
+            anyhow::bail!("Division by zero");
+        }
+        result = Decimal::ONE.checked_div(result).ok_or_else(overflow)?;
+    }
+    Ok(result)
+}
+
+/// Calculator tool - evaluates arithmetic expressions exactly
+pub struct CalculateTool;
+
+#[derive(Deserialize)]
+struct CalculateArgs {
+    expression: String,
+    #[serde(default)]
+    decimal_places: Option<u32>,
+}
+
+#[async_trait]
+impl Tool for CalculateTool {
+    fn schema(&self) -> ToolSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "expression".to_string(),
+            ToolParameterSchema::string(
+                "Expression such as '(19.99 * 3) * 1.05' or 'round(1250 / 7, 2)'. Supports \
+                 + - * / % ^, parentheses, abs, round, floor, ceil, min and max",
+            ),
+        );
+        properties.insert(
+            "decimal_places".to_string(),
+            ToolParameterSchema::integer("Round the result to this many decimal places"),
+        );
+
+        ToolSchema::new(
+            "calculate",
+            "Evaluate an arithmetic expression exactly with decimal numbers. Use it for \
+             every calculation instead of computing in your head",
+            ToolParameterSchema::object(
+                "Calculate parameters",
+                properties,
+                vec!["expression".to_string()],
+            ),
+        )
+    }
+
+    async fn execute(&self, args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+        let args: CalculateArgs = serde_json::from_value(args)?;
+
+        let outcome = evaluate(&args.expression).and_then(|value| match args.decimal_places {
+            Some(places) => round(value, Decimal::from(places)),
+            None => Ok(value),
+        });
+        match outcome {
+            Ok(value) => Ok(ToolResult::json(
+                &ctx,
+                json!({
+                    "expression": args.expression,
+                    "result": value.normalize().to_string(),
+                }),
+            )),
+            Err(e) => Ok(ToolResult::text(&ctx, format!("Error: {e}"))),
+        }
+    }
+}
+
+/// Create the `calculate` tool
+pub fn create_math_tools() -> Vec<ToolBox> {
+    vec![Arc::new(CalculateTool)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(expression: &str) -> String {
+        evaluate(expression).unwrap().normalize().to_string()
+    }
+
+    #[test]
+    fn evaluates_exactly() {
+        assert_eq!(eval("0.1 + 0.2"), "0.3");
+        assert_eq!(eval("2 + 3 * 4 - 10 / 4"), "11.5");
+        assert_eq!(eval("(19.99 * 3) * 1.05"), "62.9685");
+        assert_eq!(eval("-2^2 + 2^-1"), "-3.5");
+        assert_eq!(eval("2^3^2"), "512");
+        assert_eq!(eval("17 % 5 + abs(-3)"), "5");
+        assert_eq!(eval("round(2.345, 2) + round(2.5)"), "5.35");
+        assert_eq!(
+            eval("min(4, 2.5, 9) * max(1, 3) + floor(1.9) + ceil(1.1)"),
+            "10.5"
+        );
+        assert_eq!(eval("1.5e3 / 3"), "500");
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        for (expression, error) in [
+            ("1 / (2 - 2)", "Division by zero"),
+            ("2 +", "ends unexpectedly"),
+            ("2 ^ 0.5", "whole numbers"),
+            ("sqrt(4)", "Unknown function"),
+            ("(1 + 2", "Expected ')'"),
+            ("1 + 2)", "Unexpected ')'"),
+            ("10 ^ 100", "out of range"),
+        ] {
+            let message = evaluate(expression).unwrap_err().to_string();
+            assert!(message.contains(error), "{expression}: {message}");
+        }
+        assert!(evaluate(&"(".repeat(100)).is_err());
+    }
+}
@@ -1,7 +1,31 @@
 //! Built-in tools for common agent operations
 
+#[cfg(feature = "browser")]
+pub mod browser;
+#[cfg(feature = "charts")]
+pub mod chart;
+pub mod data;
 pub mod filesystem;
+pub mod math;
+#[cfg(any(feature = "sql-postgres", feature = "sql-mysql"))]
+pub mod sql;
+pub mod text;
+pub mod time;
 pub mod todos;
+pub mod units;
 
+#[cfg(feature = "browser")]
+pub use browser::{create_browser_tools, BrowserApprovalPolicy, BrowserTool, DomainAllowlist};
+#[cfg(feature = "charts")]
+pub use chart::{create_chart_tools, RenderChartTool};
+pub use data::{
+    create_data_tools, DescribeTableTool, LoadTableTool, QueryTableTool, Table, TableQuery,
+};
 pub use filesystem::{create_filesystem_tools, EditFileTool, LsTool, ReadFileTool, WriteFileTool};
+pub use math::{create_math_tools, CalculateTool};
+#[cfg(any(feature = "sql-postgres", feature = "sql-mysql"))]
+pub use sql::{create_sql_tools, SqlQueryTool, SqlSchemaTool, StatementKind};
+pub use text::{create_text_tools, RegexExtractTool, RegexReplaceTool, TextStatsTool};
+pub use time::{create_time_tools, parse_timezone, DateMathTool, GetCurrentTimeTool};
 pub use todos::{create_todos_tool, create_todos_tools, ReadTodosTool, WriteTodosTool};
+pub use units::{create_unit_tools, ConvertUnitsTool};
@@ -0,0 +1,810 @@
+//! Built-in SQL query tools
+//!
+//! `sql_query` runs a query against a Postgres (`sql-postgres` feature) or MySQL
+//! (`sql-mysql` feature) database and returns the rows as a Markdown table, and
+//! `sql_schema` lists the tables and columns the model can query. The database is
+//! a `sqlx` pool registered on the agent with `with_tool_state`, so credentials
+//! never pass through the model:
+//!
+//! ```ignore
+//! let pool = PgPool::connect(&std::env::var("ANALYTICS_DATABASE_URL")?).await?;
+//! let agent = ConfigurableAgentBuilder::new("You answer questions about sales")
+//!     .with_model(model)
+//!     .with_tool_state(pool)
+//!     .with_tools(create_sql_tools())
+//!     .build()?;
+//! ```
+//!
+//! Only `SELECT` statements run by default, each in a read-only transaction, so a
+//! write the statement check misses still fails in the database. Queries without a
+//! `LIMIT` get one, at most [`DEFAULT_MAX_ROWS`] rows are returned and long cells
+//! are cut, keeping results small enough for the context window. Connecting as a
+//! database role that can only read is still recommended.
+
+use super::data::{save_table, Table};
+use agents_core::tools::{Tool, ToolBox, ToolContext, ToolParameterSchema, ToolResult, ToolSchema};
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use serde::Deserialize;
+use serde_json::Value;
+use sqlx::{Column, Database, Either, Executor, Pool, Row, TypeInfo};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Most rows `sql_query` returns by default
+pub const DEFAULT_MAX_ROWS: usize = 100;
+/// Longest cell shown by default, in characters
+const DEFAULT_MAX_CELL_CHARS: usize = 200;
+/// How long a query may run by default
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Most columns `sql_schema` reads from the information schema
+const MAX_SCHEMA_COLUMNS: usize = 5_000;
+
+/// The kinds of statement `sql_query` can be allowed to run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StatementKind {
+    /// `SELECT`, `WITH`, `VALUES`, `TABLE`, `SHOW`, `DESCRIBE` and `EXPLAIN`
+    Select,
+    /// `INSERT` and `REPLACE`
+    Insert,
+    Update,
+    Delete,
+    /// `CREATE`, `ALTER`, `DROP`, `TRUNCATE`, `RENAME` and `COMMENT`
+    Ddl,
+    /// Everything else (`GRANT`, `SET`, `CALL`, `COPY`, `SELECT ... INTO`, ...)
+    Other,
+}
+
+impl fmt::Display for StatementKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            StatementKind::Select => "SELECT",
+            StatementKind::Insert => "INSERT",
+            StatementKind::Update => "UPDATE",
+            StatementKind::Delete => "DELETE",
+            StatementKind::Ddl => "DDL",
+            StatementKind::Other => "other",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Open,
+    Close,
+    Semicolon,
+    Other,
+}
+
+/// Splits SQL into words and punctuation, skipping comments, string literals,
+/// quoted identifiers and dollar-quoted strings. Backslashes are not treated as
+/// escapes: where a dialect does, a string only looks shorter than it is, which
+/// can make a harmless query look suspicious but never hides a statement.
+/// Returns each token with the byte offset where it ends.
+fn tokenize(sql: &str) -> anyhow::Result<Vec<(Token, usize)>> {
+    let bytes = sql.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let next = bytes.get(i + 1).copied();
+        let token = match c {
+            _ if c.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'-' if next == Some(b'-') => {
+                i = sql[i..].find('\n').map_or(bytes.len(), |end| i + end);
+                continue;
+            }
+            b'/' if next == Some(b'*') => {
+                let end = sql[i + 2..]
+                    .find("*/")
+                    .ok_or_else(|| anyhow::anyhow!("Unterminated comment"))?;
+                i += end + 4;
+                continue;
+            }
+            b'\'' | b'"' | b'`' => {
+                let mut end = i + 1;
+                loop {
+                    match bytes.get(end) {
+                        None => anyhow::bail!("Unterminated quote"),
+                        Some(&q) if q == c && bytes.get(end + 1) == Some(&c) => end += 2,
+                        Some(&q) if q == c => break,
+                        Some(_) => end += 1,
+                    }
+                }
+                i = end + 1;
+                Token::Other
+            }
+            b'$' if next.is_some_and(|n| n == b'$' || n.is_ascii_alphabetic() || n == b'_') => {
+                let tag_len = sql[i + 1..]
+                    .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_'))
+                    .unwrap_or(bytes.len() - i - 1);
+                if bytes.get(i + 1 + tag_len) != Some(&b'$') {
+                    // `$name` without a closing `$` is not a dollar quote
+                    i += 1;
+                    tokens.push((Token::Other, i));
+                    continue;
+                }
+                let tag = &sql[i..i + tag_len + 2];
+                let body = i + tag.len();
+                let end = sql[body..]
+                    .find(tag)
+                    .ok_or_else(|| anyhow::anyhow!("Unterminated dollar-quoted string"))?;
+                i = body + end + tag.len();
+                Token::Other
+            }
+            b'(' => {
+                i += 1;
+                Token::Open
+            }
+            b')' => {
+                i += 1;
+                Token::Close
+            }
+            b';' => {
+                i += 1;
+                Token::Semicolon
+            }
+            _ if c.is_ascii_alphabetic() || c == b'_' => {
+                let start = i;
+                while i < bytes.len()
+                    && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_' || bytes[i] == b'$')
+                {
+                    i += 1;
+                }
+                Token::Word(sql[start..i].to_ascii_uppercase())
+            }
+            _ => {
+                i += sql[i..].chars().next().map_or(1, char::len_utf8);
+                Token::Other
+            }
+        };
+        tokens.push((token, i));
+    }
+    Ok(tokens)
+}
+
+/// A single SQL statement, checked and classified.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SqlStatement {
+    pub kind: StatementKind,
+    sql: String,
+    /// Whether rows come from a query a `LIMIT` can be appended to
+    limitable: bool,
+    has_limit: bool,
+}
+
+impl SqlStatement {
+    /// Check that `sql` is one statement and classify it. A `SELECT` that
+    /// mentions `INSERT`, `UPDATE`, `DELETE`, `INTO` or DDL anywhere (e.g. in a
+    /// data-modifying CTE) is classified as that write.
+    pub fn parse(sql: &str) -> anyhow::Result<Self> {
+        let tokens = tokenize(sql)?;
+        let end = tokens
+            .iter()
+            .rposition(|(token, _)| *token != Token::Semicolon)
+            .map_or(0, |last| last + 1);
+        let tokens = &tokens[..end];
+        if tokens.iter().any(|(token, _)| *token == Token::Semicolon) {
+            anyhow::bail!("Run one statement at a time");
+        }
+        let words = || {
+            tokens.iter().filter_map(|(token, _)| match token {
+                Token::Word(word) => Some(word.as_str()),
+                _ => None,
+            })
+        };
+        let first = words()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("The query is empty"))?;
+
+        let mut kind = match first {
+            "SELECT" | "WITH" | "VALUES" | "TABLE" | "SHOW" | "DESCRIBE" | "DESC" | "EXPLAIN" => {
+                StatementKind::Select
+            }
+            "INSERT" | "REPLACE" => StatementKind::Insert,
+            "UPDATE" => StatementKind::Update,
+            "DELETE" => StatementKind::Delete,
+            "CREATE" | "ALTER" | "DROP" | "TRUNCATE" | "RENAME" | "COMMENT" => StatementKind::Ddl,
+            _ => StatementKind::Other,
+        };
+        if kind == StatementKind::Select {
+            // REPLACE and TRUNCATE are also string and number functions, so
+            // they only count as the first word
+            let write = words().find_map(|word| match word {
+                "INSERT" => Some(StatementKind::Insert),
+                "UPDATE" => Some(StatementKind::Update),
+                "DELETE" => Some(StatementKind::Delete),
+                "CREATE" | "ALTER" | "DROP" => Some(StatementKind::Ddl),
+                "INTO" | "MERGE" | "COPY" | "GRANT" | "REVOKE" => Some(StatementKind::Other),
+                _ => None,
+            });
+            kind = write.unwrap_or(kind);
+        }
+
+        let mut depth = 0i32;
+        let mut has_limit = false;
+        for (token, _) in tokens {
+            match token {
+                Token::Open => depth += 1,
+                Token::Close => depth -= 1,
+                Token::Word(word) if depth == 0 && (word == "LIMIT" || word == "FETCH") => {
+                    has_limit = true
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            kind,
+            sql: sql[..tokens.last().map_or(0, |(_, end)| *end)].to_string(),
+            limitable: kind == StatementKind::Select
+                && matches!(first, "SELECT" | "WITH" | "VALUES" | "TABLE"),
+            has_limit,
+        })
+    }
+
+    /// The statement, with `LIMIT limit` appended to a query that has no limit.
+    pub fn with_limit(&self, limit: usize) -> String {
+        if self.limitable && !self.has_limit {
+            format!("{}\nLIMIT {limit}", self.sql)
+        } else {
+            self.sql.clone()
+        }
+    }
+}
+
+/// Rows read by a statement.
+#[derive(Debug, Default)]
+struct QueryOutput {
+    table: Table,
+    truncated: bool,
+    rows_affected: u64,
+}
+
+fn float(value: f64) -> Value {
+    serde_json::Number::from_f64(value)
+        .map_or_else(|| Value::String(value.to_string()), Value::Number)
+}
+
+fn decimal(value: rust_decimal::Decimal) -> Value {
+    serde_json::from_str(&value.normalize().to_string()).unwrap_or(Value::Null)
+}
+
+/// Returns the cell as the first of the types that matches the column.
+macro_rules! decode_cell {
+    ($row:expr, $index:expr, $($ty:ty => $convert:expr),+ $(,)?) => {{
+        $(
+            if let Ok(value) = $row.try_get::<Option<$ty>, _>($index) {
+                return value.map($convert).unwrap_or(Value::Null);
+            }
+        )+
+        Value::String(format!("<{}>", $row.column($index).type_info().name()))
+    }};
+}
+
+#[cfg(feature = "sql-postgres")]
+fn postgres_cell(row: &sqlx::postgres::PgRow, index: usize) -> Value {
+    use sqlx::types::chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+    decode_cell!(row, index,
+        bool => Value::Bool,
+        i16 => Value::from,
+        i32 => Value::from,
+        i64 => Value::from,
+        f32 => |v: f32| float(f64::from(v)),
+        f64 => float,
+        rust_decimal::Decimal => decimal,
+        Value => |v: Value| v,
+        String => Value::String,
+        DateTime<Utc> => |v: DateTime<Utc>| Value::String(v.to_rfc3339()),
+        NaiveDateTime => |v: NaiveDateTime| Value::String(v.to_string()),
+        NaiveDate => |v: NaiveDate| Value::String(v.to_string()),
+        NaiveTime => |v: NaiveTime| Value::String(v.to_string()),
+        sqlx::types::Uuid => |v: sqlx::types::Uuid| Value::String(v.to_string()),
+        Vec<u8> => |v: Vec<u8>| Value::String(format!("<{} bytes>", v.len())),
+    )
+}
+
+#[cfg(feature = "sql-mysql")]
+fn mysql_cell(row: &sqlx::mysql::MySqlRow, index: usize) -> Value {
+    use sqlx::types::chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+    // MySQL booleans are TINYINT(1), which also decode as integers
+    if row.column(index).type_info().name() == "BOOLEAN" {
+        if let Ok(value) = row.try_get::<Option<bool>, _>(index) {
+            return value.map(Value::Bool).unwrap_or(Value::Null);
+        }
+    }
+    decode_cell!(row, index,
+        i64 => Value::from,
+        u64 => Value::from,
+        f32 => |v: f32| float(f64::from(v)),
+        f64 => float,
+        rust_decimal::Decimal => decimal,
+        Value => |v: Value| v,
+        String => Value::String,
+        DateTime<Utc> => |v: DateTime<Utc>| Value::String(v.to_rfc3339()),
+        NaiveDateTime => |v: NaiveDateTime| Value::String(v.to_string()),
+        NaiveDate => |v: NaiveDate| Value::String(v.to_string()),
+        NaiveTime => |v: NaiveTime| Value::String(v.to_string()),
+        Vec<u8> => |v: Vec<u8>| Value::String(format!("<{} bytes>", v.len())),
+    )
+}
+
+/// Runs `sql` in a transaction, read-only when asked to, and reads at most
+/// `max_rows` rows.
+async fn fetch<DB>(
+    pool: &Pool<DB>,
+    sql: &str,
+    read_only: bool,
+    max_rows: usize,
+    cell: fn(&DB::Row, usize) -> Value,
+    rows_affected: fn(&DB::QueryResult) -> u64,
+) -> anyhow::Result<QueryOutput>
+where
+    DB: Database,
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+{
+    let mut tx = if read_only {
+        pool.begin_with("START TRANSACTION READ ONLY").await?
+    } else {
+        pool.begin().await?
+    };
+    let mut output = QueryOutput::default();
+    {
+        let mut stream = sqlx::raw_sql(sql).fetch_many(&mut *tx);
+        while let Some(item) = stream.try_next().await? {
+            match item {
+                Either::Left(result) => output.rows_affected += rows_affected(&result),
+                Either::Right(row) => {
+                    if output.table.columns.is_empty() {
+                        output.table.columns = row
+                            .columns()
+                            .iter()
+                            .map(|column| column.name().to_string())
+                            .collect();
+                    }
+                    if output.table.rows.len() == max_rows {
+                        output.truncated = true;
+                        break;
+                    }
+                    output
+                        .table
+                        .rows
+                        .push((0..row.len()).map(|index| cell(&row, index)).collect());
+                }
+            }
+        }
+    }
+    if read_only {
+        tx.rollback().await?;
+    } else {
+        tx.commit().await?;
+    }
+    Ok(output)
+}
+
+/// The database registered on the agent.
+enum Backend {
+    #[cfg(feature = "sql-postgres")]
+    Postgres(sqlx::PgPool),
+    #[cfg(feature = "sql-mysql")]
+    MySql(sqlx::MySqlPool),
+}
+
+impl Backend {
+    fn from_context(ctx: &ToolContext) -> anyhow::Result<Self> {
+        #[cfg(feature = "sql-postgres")]
+        if let Some(pool) = ctx.extension::<sqlx::PgPool>() {
+            return Ok(Backend::Postgres(pool.clone()));
+        }
+        #[cfg(feature = "sql-mysql")]
+        if let Some(pool) = ctx.extension::<sqlx::MySqlPool>() {
+            return Ok(Backend::MySql(pool.clone()));
+        }
+        let _ = ctx;
+        anyhow::bail!("No database is connected; register a sqlx pool with with_tool_state")
+    }
+
+    async fn fetch(
+        &self,
+        sql: &str,
+        read_only: bool,
+        max_rows: usize,
+    ) -> anyhow::Result<QueryOutput> {
+        match self {
+            #[cfg(feature = "sql-postgres")]
+            Backend::Postgres(pool) => {
+                fetch(pool, sql, read_only, max_rows, postgres_cell, |result| {
+                    result.rows_affected()
+                })
+                .await
+            }
+            #[cfg(feature = "sql-mysql")]
+            Backend::MySql(pool) => {
+                fetch(pool, sql, read_only, max_rows, mysql_cell, |result| {
+                    result.rows_affected()
+                })
+                .await
+            }
+        }
+    }
+
+    /// Query listing schema, table, column, type and nullability of every column
+    fn schema_sql(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "sql-postgres")]
+            Backend::Postgres(_) => {
+                "SELECT table_schema, table_name, column_name, data_type, is_nullable \
+                 FROM information_schema.columns \
+                 WHERE table_schema NOT IN ('pg_catalog', 'information_schema') \
+                 ORDER BY table_schema, table_name, ordinal_position"
+            }
+            #[cfg(feature = "sql-mysql")]
+            Backend::MySql(_) => {
+                "SELECT table_schema, table_name, column_name, column_type, is_nullable \
+                 FROM information_schema.columns \
+                 WHERE table_schema = DATABASE() \
+                 ORDER BY table_schema, table_name, ordinal_position"
+            }
+        }
+    }
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Cuts text and JSON cells longer than `max_chars`.
+fn clip(value: Value, max_chars: usize) -> Value {
+    let long = match &value {
+        Value::String(text) => text.chars().count() > max_chars,
+        Value::Array(_) | Value::Object(_) => value.to_string().chars().count() > max_chars,
+        _ => false,
+    };
+    if !long {
+        return value;
+    }
+    let text: String = text(&value).chars().take(max_chars).collect();
+    Value::String(format!("{text}…"))
+}
+
+/// Describe the tables of the connected database, one line per table with its
+/// columns, types and which are `NOT NULL`. `tables` limits the output to those
+/// tables (by name, ignoring case).
+async fn describe_schema(backend: &Backend, tables: &[String]) -> anyhow::Result<String> {
+    let output = backend
+        .fetch(backend.schema_sql(), true, MAX_SCHEMA_COLUMNS)
+        .await?;
+    let mut described: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for row in &output.table.rows {
+        let [schema, table, column, data_type, nullable] = [0, 1, 2, 3, 4].map(|i| text(&row[i]));
+        if !tables.is_empty() && !tables.iter().any(|name| name.eq_ignore_ascii_case(&table)) {
+            continue;
+        }
+        let not_null = if nullable == "NO" { " not null" } else { "" };
+        described
+            .entry(format!("{schema}.{table}"))
+            .or_default()
+            .push(format!("{column} {data_type}{not_null}"));
+    }
+    if described.is_empty() {
+        return Ok(if tables.is_empty() {
+            "No tables found".to_string()
+        } else {
+            format!("No table named {}", tables.join(", "))
+        });
+    }
+    let mut text: String = described
+        .into_iter()
+        .map(|(table, columns)| format!("{table}({})\n", columns.join(", ")))
+        .collect();
+    if output.truncated {
+        text.push_str(&format!(
+            "\nOnly the first {MAX_SCHEMA_COLUMNS} columns are listed; name the tables to see others\n"
+        ));
+    }
+    Ok(text)
+}
+
+/// SQL query tool - runs a statement against the registered database
+pub struct SqlQueryTool {
+    allowed: HashSet<StatementKind>,
+    max_rows: usize,
+    max_cell_chars: usize,
+    timeout: Duration,
+}
+
+impl Default for SqlQueryTool {
+    fn default() -> Self {
+        Self {
+            allowed: HashSet::from([StatementKind::Select]),
+            max_rows: DEFAULT_MAX_ROWS,
+            max_cell_chars: DEFAULT_MAX_CELL_CHARS,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+impl SqlQueryTool {
+    /// A tool running `SELECT` statements only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also allow statements of `kind`, e.g. [`StatementKind::Insert`].
+    pub fn allow(mut self, kind: StatementKind) -> Self {
+        self.allowed.insert(kind);
+        self
+    }
+
+    /// Return at most `max_rows` rows; queries without a `LIMIT` get this one.
+    pub fn with_max_rows(mut self, max_rows: usize) -> Self {
+        self.max_rows = max_rows.max(1);
+        self
+    }
+
+    /// Cut cells longer than `max_chars` characters.
+    pub fn with_max_cell_chars(mut self, max_chars: usize) -> Self {
+        self.max_cell_chars = max_chars;
+        self
+    }
+
+    /// Give up on queries running longer than `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    async fn run(&self, args: &SqlQueryArgs, ctx: &ToolContext) -> anyhow::Result<QueryOutput> {
+        let statement = SqlStatement::parse(&args.query)?;
+        if !self.allowed.contains(&statement.kind) {
+            let mut allowed: Vec<String> = self.allowed.iter().map(ToString::to_string).collect();
+            allowed.sort();
+            anyhow::bail!(
+                "{} statements are not allowed; only {}",
+                statement.kind,
+                allowed.join(", ")
+            );
+        }
+        let backend = Backend::from_context(ctx)?;
+        // One row past the limit tells whether the result was cut
+        let sql = statement.with_limit(self.max_rows + 1);
+        let read_only = statement.kind == StatementKind::Select;
+        let mut output =
+            tokio::time::timeout(self.timeout, backend.fetch(&sql, read_only, self.max_rows))
+                .await
+                .map_err(|_| {
+                    anyhow::anyhow!(
+                        "The query took longer than {}s; narrow it down",
+                        self.timeout.as_secs_f64()
+                    )
+                })??;
+        for row in &mut output.table.rows {
+            for cell in row.iter_mut() {
+                *cell = clip(std::mem::take(cell), self.max_cell_chars);
+            }
+        }
+        Ok(output)
+    }
+}
+
+#[derive(Deserialize)]
+struct SqlQueryArgs {
+    query: String,
+    #[serde(default)]
+    save_as: Option<String>,
+}
+
+#[async_trait]
+impl Tool for SqlQueryTool {
+    fn schema(&self) -> ToolSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "query".to_string(),
+            ToolParameterSchema::string("One SQL statement"),
+        );
+        properties.insert(
+            "save_as".to_string(),
+            ToolParameterSchema::string(
+                "Also save the rows as a table with this name, for query_table or render_chart",
+            ),
+        );
+
+        let description = if self.allowed.len() == 1 {
+            format!(
+                "Run a read-only SQL query against the connected database and get the rows \
+                 as a table (at most {} rows). Use sql_schema to see the tables first",
+                self.max_rows
+            )
+        } else {
+            format!(
+                "Run one SQL statement against the connected database and get the rows as a \
+                 table (at most {} rows) or the number of rows changed. Use sql_schema to see \
+                 the tables first",
+                self.max_rows
+            )
+        };
+        ToolSchema::new(
+            "sql_query",
+            description,
+            ToolParameterSchema::object(
+                "SQL query parameters",
+                properties,
+                vec!["query".to_string()],
+            ),
+        )
+    }
+
+    async fn execute(&self, args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+        let args: SqlQueryArgs = serde_json::from_value(args)?;
+        let output = match self.run(&args, &ctx).await {
+            Ok(output) => output,
+            Err(e) => return Ok(ToolResult::text(&ctx, format!("Error: {e}"))),
+        };
+
+        if output.table.columns.is_empty() {
+            return Ok(ToolResult::text(
+                &ctx,
+                match SqlStatement::parse(&args.query).map(|s| s.kind) {
+                    Ok(StatementKind::Select) => "0 rows".to_string(),
+                    _ => format!("{} rows affected", output.rows_affected),
+                },
+            ));
+        }
+        let count = output.table.rows.len();
+        let mut text = if output.truncated {
+            format!(
+                "First {count} rows; there are more. Aggregate or filter in SQL instead of \
+                 reading them all\n\n"
+            )
+        } else {
+            format!("{count} rows\n\n")
+        };
+        text.push_str(&output.table.to_markdown(count));
+        match args.save_as {
+            Some(name) => {
+                let diff = save_table(&ctx, &name, &output.table)?;
+                text.push_str(&format!("\nSaved as table '{name}'"));
+                Ok(ToolResult::with_state(ctx.text_response(text), diff))
+            }
+            None => Ok(ToolResult::text(&ctx, text)),
+        }
+    }
+}
+
+/// SQL schema tool - lists the tables and columns of the registered database
+pub struct SqlSchemaTool;
+
+#[derive(Deserialize)]
+struct SqlSchemaArgs {
+    #[serde(default)]
+    tables: Vec<String>,
+}
+
+#[async_trait]
+impl Tool for SqlSchemaTool {
+    fn schema(&self) -> ToolSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "tables".to_string(),
+            ToolParameterSchema::array(
+                "Only describe these tables (default: all)",
+                ToolParameterSchema::string("Table name"),
+            ),
+        );
+
+        ToolSchema::new(
+            "sql_schema",
+            "List the tables of the connected database with their columns and types",
+            ToolParameterSchema::object("SQL schema parameters", properties, vec![]),
+        )
+    }
+
+    async fn execute(&self, args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+        let args: SqlSchemaArgs = serde_json::from_value(args)?;
+        let described = match Backend::from_context(&ctx) {
+            Ok(backend) => describe_schema(&backend, &args.tables).await,
+            Err(e) => Err(e),
+        };
+        match described {
+            Ok(text) => Ok(ToolResult::text(&ctx, text)),
+            Err(e) => Ok(ToolResult::text(&ctx, format!("Error: {e}"))),
+        }
+    }
+}
+
+/// Create the `sql_query` (`SELECT` only) and `sql_schema` tools
+pub fn create_sql_tools() -> Vec<ToolBox> {
+    vec![Arc::new(SqlQueryTool::new()), Arc::new(SqlSchemaTool)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::messaging::MessageContent;
+    use agents_core::state::AgentStateSnapshot;
+    use serde_json::json;
+
+    fn kind(sql: &str) -> StatementKind {
+        SqlStatement::parse(sql).unwrap().kind
+    }
+
+    #[test]
+    fn classifies_statements_and_injects_limits() {
+        assert_eq!(kind("select * from orders;"), StatementKind::Select);
+        assert_eq!(
+            kind("WITH t AS (SELECT 1) SELECT * FROM t"),
+            StatementKind::Select
+        );
+        assert_eq!(
+            kind("select 'delete' as \"update\" -- drop"),
+            StatementKind::Select
+        );
+        assert_eq!(
+            kind("SELECT replace(name, 'a', 'b') FROM t"),
+            StatementKind::Select
+        );
+        assert_eq!(
+            kind("WITH gone AS (DELETE FROM t RETURNING *) SELECT * FROM gone"),
+            StatementKind::Delete
+        );
+        assert_eq!(kind("SELECT * INTO backup FROM t"), StatementKind::Other);
+        assert_eq!(kind("insert into t values (1)"), StatementKind::Insert);
+        assert_eq!(kind("DROP TABLE t"), StatementKind::Ddl);
+        assert_eq!(kind("GRANT ALL ON t TO bob"), StatementKind::Other);
+
+        assert!(SqlStatement::parse("SELECT 1; DELETE FROM t").is_err());
+        // A dollar-quoted string cannot hide a second statement
+        assert!(SqlStatement::parse("SELECT $$ ' $$; DELETE FROM t; --'").is_err());
+        assert!(SqlStatement::parse("SELECT 'a;b' FROM t; ").is_ok());
+        assert!(SqlStatement::parse("  -- nothing").is_err());
+
+        let statement = SqlStatement::parse("SELECT * FROM t; -- all").unwrap();
+        assert_eq!(statement.with_limit(101), "SELECT * FROM t\nLIMIT 101");
+        let statement = SqlStatement::parse("SELECT * FROM (SELECT 1 LIMIT 2) s").unwrap();
+        assert!(statement.with_limit(5).ends_with("LIMIT 5"));
+        let statement = SqlStatement::parse("select * from t limit 3").unwrap();
+        assert_eq!(statement.with_limit(5), "select * from t limit 3");
+        let statement = SqlStatement::parse("SHOW TABLES").unwrap();
+        assert_eq!(statement.with_limit(5), "SHOW TABLES");
+    }
+
+    #[tokio::test]
+    async fn rejects_writes_and_missing_connections() {
+        let ctx = ToolContext::new(Arc::new(AgentStateSnapshot::default()));
+        let reply = |result: ToolResult| match result {
+            ToolResult::Message(message) => match message.content {
+                MessageContent::Text(text) => text,
+                other => panic!("unexpected content: {other:?}"),
+            },
+            other => panic!("unexpected result: {other:?}"),
+        };
+
+        let result = SqlQueryTool::new()
+            .execute(json!({"query": "UPDATE t SET a = 1"}), ctx.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            reply(result),
+            "Error: UPDATE statements are not allowed; only SELECT"
+        );
+
+        let result = SqlQueryTool::new()
+            .allow(StatementKind::Update)
+            .execute(json!({"query": "UPDATE t SET a = 1"}), ctx.clone())
+            .await
+            .unwrap();
+        assert!(reply(result).starts_with("Error: No database is connected"));
+
+        assert_eq!(clip(json!("abcdef"), 3), json!("abc…"),);
+    }
+}
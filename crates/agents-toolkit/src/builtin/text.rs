@@ -0,0 +1,350 @@
+//! Built-in text processing tools
+//!
+//! Counting words or pulling every order number out of a long email are easy to
+//! get subtly wrong by reading. `regex_extract`, `regex_replace` and `text_stats`
+//! do them deterministically. Patterns use the `regex` crate syntax, which runs
+//! in linear time, so a pathological pattern cannot stall the agent.
+
+use agents_core::tools::{Tool, ToolBox, ToolContext, ToolParameterSchema, ToolResult, ToolSchema};
+use async_trait::async_trait;
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Most matches `regex_extract` returns by default
+const DEFAULT_MAX_MATCHES: usize = 100;
+/// Compiled size limit for patterns, in bytes
+const PATTERN_SIZE_LIMIT: usize = 1 << 20;
+
+fn compile(pattern: &str, case_insensitive: bool) -> anyhow::Result<Regex> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(case_insensitive)
+        .size_limit(PATTERN_SIZE_LIMIT)
+        .build()
+        .map_err(|e| anyhow::anyhow!("Invalid pattern: {e}"))
+}
+
+fn pattern_properties() -> HashMap<String, ToolParameterSchema> {
+    let mut properties = HashMap::new();
+    properties.insert(
+        "text".to_string(),
+        ToolParameterSchema::string("Text to search"),
+    );
+    properties.insert(
+        "pattern".to_string(),
+        ToolParameterSchema::string(
+            "Regular expression (Rust regex syntax; no look-around or backreferences)",
+        ),
+    );
+    properties.insert(
+        "case_insensitive".to_string(),
+        ToolParameterSchema::boolean("Ignore case when matching (default: false)"),
+    );
+    properties
+}
+
+/// Regex extraction tool - lists the matches of a pattern and their capture groups
+pub struct RegexExtractTool;
+
+#[derive(Deserialize)]
+struct RegexExtractArgs {
+    text: String,
+    pattern: String,
+    #[serde(default)]
+    case_insensitive: bool,
+    #[serde(default)]
+    max_matches: Option<usize>,
+}
+
+#[async_trait]
+impl Tool for RegexExtractTool {
+    fn schema(&self) -> ToolSchema {
+        let mut properties = pattern_properties();
+        properties.insert(
+            "max_matches".to_string(),
+            ToolParameterSchema::integer(format!(
+                "Most matches to return (default: {DEFAULT_MAX_MATCHES})"
+            )),
+        );
+
+        ToolSchema::new(
+            "regex_extract",
+            "Find every match of a regular expression in a text, with numbered and named \
+             capture groups",
+            ToolParameterSchema::object(
+                "Regex extract parameters",
+                properties,
+                vec!["text".to_string(), "pattern".to_string()],
+            ),
+        )
+    }
+
+    async fn execute(&self, args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+        let args: RegexExtractArgs = serde_json::from_value(args)?;
+        let regex = match compile(&args.pattern, args.case_insensitive) {
+            Ok(regex) => regex,
+            Err(e) => return Ok(ToolResult::text(&ctx, format!("Error: {e}"))),
+        };
+
+        let max_matches = args.max_matches.unwrap_or(DEFAULT_MAX_MATCHES);
+        let names: Vec<_> = regex.capture_names().collect();
+        let mut matches = Vec::new();
+        let mut total = 0;
+        for captures in regex.captures_iter(&args.text) {
+            total += 1;
+            if matches.len() >= max_matches {
+                continue;
+            }
+            let whole = captures.get(0).expect("group 0 always matches");
+            let mut entry = json!({ "match": whole.as_str(), "start": whole.start() });
+            if captures.len() > 1 {
+                let groups: Vec<Value> = (1..captures.len())
+                    .map(|i| captures.get(i).map(|m| m.as_str()).into())
+                    .collect();
+                entry["groups"] = Value::Array(groups);
+                let named: Map<String, Value> = names
+                    .iter()
+                    .flatten()
+                    .map(|name| {
+                        (
+                            name.to_string(),
+                            captures.name(name).map(|m| m.as_str()).into(),
+                        )
+                    })
+                    .collect();
+                if !named.is_empty() {
+                    entry["named"] = Value::Object(named);
+                }
+            }
+            matches.push(entry);
+        }
+
+        Ok(ToolResult::json(
+            &ctx,
+            json!({
+                "count": total,
+                "truncated": total > matches.len(),
+                "matches": matches,
+            }),
+        ))
+    }
+}
+
+/// Regex replacement tool - replaces matches of a pattern
+pub struct RegexReplaceTool;
+
+#[derive(Deserialize)]
+struct RegexReplaceArgs {
+    text: String,
+    pattern: String,
+    replacement: String,
+    #[serde(default)]
+    case_insensitive: bool,
+    #[serde(default)]
+    limit: usize,
+}
+
+#[async_trait]
+impl Tool for RegexReplaceTool {
+    fn schema(&self) -> ToolSchema {
+        let mut properties = pattern_properties();
+        properties.insert(
+            "replacement".to_string(),
+            ToolParameterSchema::string(
+                "Replacement text; $1 or ${name} insert capture groups, $$ a literal $",
+            ),
+        );
+        properties.insert(
+            "limit".to_string(),
+            ToolParameterSchema::integer("Replace at most this many matches (default: all)"),
+        );
+
+        ToolSchema::new(
+            "regex_replace",
+            "Replace the matches of a regular expression in a text",
+            ToolParameterSchema::object(
+                "Regex replace parameters",
+                properties,
+                vec![
+                    "text".to_string(),
+                    "pattern".to_string(),
+                    "replacement".to_string(),
+                ],
+            ),
+        )
+    }
+
+    async fn execute(&self, args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+        let args: RegexReplaceArgs = serde_json::from_value(args)?;
+        let regex = match compile(&args.pattern, args.case_insensitive) {
+            Ok(regex) => regex,
+            Err(e) => return Ok(ToolResult::text(&ctx, format!("Error: {e}"))),
+        };
+
+        let found = regex.find_iter(&args.text).count();
+        let replacements = if args.limit == 0 {
+            found
+        } else {
+            found.min(args.limit)
+        };
+        let result = regex.replacen(&args.text, args.limit, args.replacement.as_str());
+        Ok(ToolResult::json(
+            &ctx,
+            json!({
+                "result": result,
+                "replacements": replacements,
+            }),
+        ))
+    }
+}
+
+/// Counts describing a text.
+pub fn text_stats(text: &str) -> Value {
+    let words: Vec<&str> = text
+        .split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .collect();
+    let letters: usize = words
+        .iter()
+        .map(|word| word.chars().filter(|c| c.is_alphanumeric()).count())
+        .sum();
+    let sentences = text
+        .split(['.', '!', '?'])
+        .filter(|sentence| sentence.chars().any(char::is_alphanumeric))
+        .count();
+    let paragraphs = text
+        .split("\n\n")
+        .filter(|paragraph| !paragraph.trim().is_empty())
+        .count();
+    let average_word_length = if words.is_empty() {
+        0.0
+    } else {
+        (letters as f64 / words.len() as f64 * 100.0).round() / 100.0
+    };
+
+    json!({
+        "characters": text.chars().count(),
+        "characters_excluding_spaces": text.chars().filter(|c| !c.is_whitespace()).count(),
+        "bytes": text.len(),
+        "words": words.len(),
+        "sentences": sentences,
+        "lines": text.lines().count(),
+        "paragraphs": paragraphs,
+        "average_word_length": average_word_length,
+    })
+}
+
+/// Text statistics tool - counts characters, words, sentences, lines and paragraphs
+pub struct TextStatsTool;
+
+#[derive(Deserialize)]
+struct TextStatsArgs {
+    text: String,
+}
+
+#[async_trait]
+impl Tool for TextStatsTool {
+    fn schema(&self) -> ToolSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "text".to_string(),
+            ToolParameterSchema::string("Text to measure"),
+        );
+
+        ToolSchema::new(
+            "text_stats",
+            "Count the characters, words, sentences, lines and paragraphs of a text",
+            ToolParameterSchema::object(
+                "Text stats parameters",
+                properties,
+                vec!["text".to_string()],
+            ),
+        )
+    }
+
+    async fn execute(&self, args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+        let args: TextStatsArgs = serde_json::from_value(args)?;
+        Ok(ToolResult::json(&ctx, text_stats(&args.text)))
+    }
+}
+
+/// Create the `regex_extract`, `regex_replace` and `text_stats` tools
+pub fn create_text_tools() -> Vec<ToolBox> {
+    vec![
+        Arc::new(RegexExtractTool),
+        Arc::new(RegexReplaceTool),
+        Arc::new(TextStatsTool),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::messaging::MessageContent;
+    use agents_core::state::AgentStateSnapshot;
+
+    async fn run(tool: &dyn Tool, args: Value) -> Value {
+        let ctx = ToolContext::new(Arc::new(AgentStateSnapshot::default()));
+        match tool.execute(args, ctx).await.unwrap() {
+            ToolResult::Message(message) => match message.content {
+                MessageContent::Json(value) => value,
+                MessageContent::Text(text) => Value::String(text),
+                other => panic!("unexpected content: {other:?}"),
+            },
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn regex_tools_extract_and_replace() {
+        let text = "Orders ORD-118 and ord-204 shipped; ORD-7 is late.";
+        let response = run(
+            &RegexExtractTool,
+            json!({
+                "text": text,
+                "pattern": r"ord-(?P<number>\d+)",
+                "case_insensitive": true,
+                "max_matches": 2,
+            }),
+        )
+        .await;
+        assert_eq!(response["count"], 3);
+        assert_eq!(response["truncated"], true);
+        assert_eq!(response["matches"][1]["match"], "ord-204");
+        assert_eq!(response["matches"][1]["groups"], json!(["204"]));
+        assert_eq!(response["matches"][0]["named"]["number"], "118");
+
+        let response = run(
+            &RegexReplaceTool,
+            json!({"text": text, "pattern": r"ORD-(\d+)", "replacement": "#$1"}),
+        )
+        .await;
+        assert_eq!(
+            response["result"],
+            "Orders #118 and ord-204 shipped; #7 is late."
+        );
+        assert_eq!(response["replacements"], 2);
+
+        let response = run(
+            &RegexReplaceTool,
+            json!({"text": text, "pattern": "(", "replacement": ""}),
+        )
+        .await;
+        assert!(response
+            .as_str()
+            .unwrap()
+            .starts_with("Error: Invalid pattern"));
+    }
+
+    #[test]
+    fn text_stats_counts() {
+        let stats = text_stats("Hello, world! How are you?\n\nFine — thanks.");
+        assert_eq!(stats["words"], 7);
+        assert_eq!(stats["sentences"], 3);
+        assert_eq!(stats["lines"], 3);
+        assert_eq!(stats["paragraphs"], 2);
+        assert_eq!(stats["average_word_length"], 4.14);
+    }
+}
@@ -0,0 +1,370 @@
+//! Built-in date and time tools
+//!
+//! Language models have no clock of their own. These tools give agents the
+//! current time and timezone-correct date arithmetic (DST transitions and
+//! month lengths included) backed by the IANA timezone database.
+
+use agents_core::determinism;
+use agents_core::tools::{Tool, ToolBox, ToolContext, ToolParameterSchema, ToolResult, ToolSchema};
+use async_trait::async_trait;
+use chrono::{
+    DateTime, Datelike, Days, Duration, LocalResult, Months, NaiveDate, NaiveDateTime, TimeZone,
+};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Parse an IANA timezone name such as `Asia/Dubai` or `UTC`.
+pub fn parse_timezone(name: &str) -> anyhow::Result<Tz> {
+    name.trim().parse::<Tz>().map_err(|_| {
+        anyhow::anyhow!("Unknown timezone '{name}'; use an IANA name such as 'Europe/London'")
+    })
+}
+
+/// Describe an instant in a timezone as the JSON object returned by the time tools.
+pub fn describe_datetime(dt: &DateTime<Tz>) -> Value {
+    json!({
+        "datetime": dt.to_rfc3339(),
+        "date": dt.format("%Y-%m-%d").to_string(),
+        "time": dt.format("%H:%M:%S").to_string(),
+        "weekday": dt.format("%A").to_string(),
+        "timezone": dt.timezone().name(),
+        "utc_offset": dt.format("%:z").to_string(),
+        "unix": dt.timestamp(),
+    })
+}
+
+/// Parse `now`, an RFC 3339 timestamp, `YYYY-MM-DD HH:MM[:SS]` or `YYYY-MM-DD`.
+/// Values without an offset are read as local time in `tz`.
+fn parse_datetime(input: &str, tz: Tz) -> anyhow::Result<DateTime<Tz>> {
+    let input = input.trim();
+    if input.is_empty() || input.eq_ignore_ascii_case("now") {
+        return Ok(determinism::now().with_timezone(&tz));
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc3339(input) {
+        return Ok(dt.with_timezone(&tz));
+    }
+
+    let naive = [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+    .or_else(|| {
+        NaiveDate::parse_from_str(input, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+    })
+    .ok_or_else(|| {
+        anyhow::anyhow!(
+            "Could not parse '{input}'; use RFC 3339, 'YYYY-MM-DD HH:MM' or 'YYYY-MM-DD'"
+        )
+    })?;
+
+    match tz.from_local_datetime(&naive) {
+        LocalResult::Single(dt) => Ok(dt),
+        // Clocks went back: the wall time happened twice, take the first occurrence
+        LocalResult::Ambiguous(earliest, _) => Ok(earliest),
+        LocalResult::None => anyhow::bail!(
+            "'{input}' does not exist in {} (skipped by a daylight saving transition)",
+            tz.name()
+        ),
+    }
+}
+
+fn timezone_or(name: Option<&str>, default: Tz) -> anyhow::Result<Tz> {
+    match name {
+        Some(name) if !name.trim().is_empty() => parse_timezone(name),
+        _ => Ok(default),
+    }
+}
+
+/// Current time tool - reports the current date and time in a timezone
+pub struct GetCurrentTimeTool {
+    timezone: Tz,
+}
+
+impl GetCurrentTimeTool {
+    pub fn new(timezone: Tz) -> Self {
+        Self { timezone }
+    }
+}
+
+#[derive(Deserialize)]
+struct GetCurrentTimeArgs {
+    #[serde(default)]
+    timezone: Option<String>,
+}
+
+#[async_trait]
+impl Tool for GetCurrentTimeTool {
+    fn schema(&self) -> ToolSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "timezone".to_string(),
+            ToolParameterSchema::string(format!(
+                "IANA timezone name, e.g. 'America/New_York' (default: {})",
+                self.timezone.name()
+            )),
+        );
+
+        ToolSchema::new(
+            "get_current_time",
+            "Get the current date, time and weekday in a timezone",
+            ToolParameterSchema::object("Get current time parameters", properties, vec![]),
+        )
+    }
+
+    async fn execute(&self, args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+        let args: GetCurrentTimeArgs = serde_json::from_value(args)?;
+        let tz = match timezone_or(args.timezone.as_deref(), self.timezone) {
+            Ok(tz) => tz,
+            Err(e) => return Ok(ToolResult::text(&ctx, format!("Error: {e}"))),
+        };
+
+        let now = determinism::now().with_timezone(&tz);
+        Ok(ToolResult::json(&ctx, describe_datetime(&now)))
+    }
+}
+
+/// Date math tool - shifts a date by calendar units or measures the time between two dates
+pub struct DateMathTool {
+    timezone: Tz,
+}
+
+impl DateMathTool {
+    pub fn new(timezone: Tz) -> Self {
+        Self { timezone }
+    }
+}
+
+#[derive(Deserialize)]
+struct DateMathArgs {
+    #[serde(default)]
+    start: Option<String>,
+    #[serde(default)]
+    until: Option<String>,
+    #[serde(default)]
+    timezone: Option<String>,
+    #[serde(default)]
+    years: i64,
+    #[serde(default)]
+    months: i64,
+    #[serde(default)]
+    weeks: i64,
+    #[serde(default)]
+    days: i64,
+    #[serde(default)]
+    hours: i64,
+    #[serde(default)]
+    minutes: i64,
+}
+
+impl DateMathArgs {
+    fn shift(&self, start: DateTime<Tz>) -> anyhow::Result<DateTime<Tz>> {
+        let overflow = || anyhow::anyhow!("Date arithmetic is out of range");
+
+        // Calendar units move the local wall clock, so "+1 day" across a DST
+        // change keeps the same time of day.
+        let months = self
+            .years
+            .checked_mul(12)
+            .and_then(|m| m.checked_add(self.months))
+            .ok_or_else(overflow)?;
+        let span = Months::new(u32::try_from(months.unsigned_abs()).map_err(|_| overflow())?);
+        let shifted = if months < 0 {
+            start.checked_sub_months(span)
+        } else {
+            start.checked_add_months(span)
+        }
+        .ok_or_else(overflow)?;
+
+        let days = self
+            .weeks
+            .checked_mul(7)
+            .and_then(|d| d.checked_add(self.days))
+            .ok_or_else(overflow)?;
+        let shifted = if days < 0 {
+            shifted.checked_sub_days(Days::new(days.unsigned_abs()))
+        } else {
+            shifted.checked_add_days(Days::new(days.unsigned_abs()))
+        }
+        .ok_or_else(overflow)?;
+
+        // Clock units are elapsed time
+        let elapsed = Duration::try_hours(self.hours)
+            .zip(Duration::try_minutes(self.minutes))
+            .and_then(|(h, m)| h.checked_add(&m))
+            .ok_or_else(overflow)?;
+        shifted.checked_add_signed(elapsed).ok_or_else(overflow)
+    }
+}
+
+fn describe_difference(start: &DateTime<Tz>, end: &DateTime<Tz>) -> Value {
+    let total_seconds = end.signed_duration_since(*start).num_seconds();
+    let abs = total_seconds.unsigned_abs();
+    let (days, rem) = (abs / 86_400, abs % 86_400);
+    let (hours, rem) = (rem / 3_600, rem % 3_600);
+    let minutes = rem / 60;
+
+    json!({
+        "total_seconds": total_seconds,
+        "calendar_days": end.date_naive().num_days_from_ce() - start.date_naive().num_days_from_ce(),
+        "days": days,
+        "hours": hours,
+        "minutes": minutes,
+        "direction": if total_seconds < 0 { "past" } else { "future" },
+    })
+}
+
+#[async_trait]
+impl Tool for DateMathTool {
+    fn schema(&self) -> ToolSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "start".to_string(),
+            ToolParameterSchema::string(
+                "Start date: 'now', RFC 3339, 'YYYY-MM-DD HH:MM' or 'YYYY-MM-DD' (default: now)",
+            ),
+        );
+        properties.insert(
+            "until".to_string(),
+            ToolParameterSchema::string("Optional end date; returns the time between start and it"),
+        );
+        properties.insert(
+            "timezone".to_string(),
+            ToolParameterSchema::string(format!(
+                "IANA timezone used for dates without an offset and for the result (default: {})",
+                self.timezone.name()
+            )),
+        );
+        for (unit, description) in [
+            ("years", "Years to add (negative to subtract)"),
+            ("months", "Months to add (negative to subtract)"),
+            ("weeks", "Weeks to add (negative to subtract)"),
+            ("days", "Days to add (negative to subtract)"),
+            ("hours", "Hours to add (negative to subtract)"),
+            ("minutes", "Minutes to add (negative to subtract)"),
+        ] {
+            properties.insert(unit.to_string(), ToolParameterSchema::integer(description));
+        }
+
+        ToolSchema::new(
+            "date_math",
+            "Add or subtract years, months, weeks, days, hours or minutes from a date, \
+             or compute the time between two dates, with correct timezone handling",
+            ToolParameterSchema::object("Date math parameters", properties, vec![]),
+        )
+    }
+
+    async fn execute(&self, args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+        let args: DateMathArgs = serde_json::from_value(args)?;
+
+        let outcome = (|| -> anyhow::Result<Value> {
+            let tz = timezone_or(args.timezone.as_deref(), self.timezone)?;
+            let start = parse_datetime(args.start.as_deref().unwrap_or("now"), tz)?;
+            let result = args.shift(start)?;
+
+            let mut response = json!({
+                "start": describe_datetime(&start),
+                "result": describe_datetime(&result),
+            });
+            if let Some(until) = args.until.as_deref() {
+                let until = parse_datetime(until, tz)?;
+                response["until"] = describe_datetime(&until);
+                response["difference"] = describe_difference(&start, &until);
+            }
+            Ok(response)
+        })();
+
+        match outcome {
+            Ok(response) => Ok(ToolResult::json(&ctx, response)),
+            Err(e) => Ok(ToolResult::text(&ctx, format!("Error: {e}"))),
+        }
+    }
+}
+
+/// Create the `get_current_time` and `date_math` tools, defaulting to `timezone`
+pub fn create_time_tools(timezone: Tz) -> Vec<ToolBox> {
+    vec![
+        Arc::new(GetCurrentTimeTool::new(timezone)),
+        Arc::new(DateMathTool::new(timezone)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agents_core::messaging::MessageContent;
+    use agents_core::state::AgentStateSnapshot;
+
+    async fn run(tool: &dyn Tool, args: Value) -> Value {
+        let ctx = ToolContext::new(Arc::new(AgentStateSnapshot::default()));
+        match tool.execute(args, ctx).await.unwrap() {
+            ToolResult::Message(message) => match message.content {
+                MessageContent::Json(value) => value,
+                MessageContent::Text(text) => Value::String(text),
+                other => panic!("unexpected content: {other:?}"),
+            },
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn current_time_uses_requested_timezone() {
+        let tool = GetCurrentTimeTool::new(chrono_tz::UTC);
+        let response = run(&tool, json!({"timezone": "Asia/Dubai"})).await;
+        assert_eq!(response["timezone"], "Asia/Dubai");
+        assert_eq!(response["utc_offset"], "+04:00");
+
+        let response = run(&tool, json!({"timezone": "Mars/Olympus"})).await;
+        assert!(response.as_str().unwrap().contains("Unknown timezone"));
+    }
+
+    #[tokio::test]
+    async fn date_math_keeps_wall_clock_across_dst() {
+        let tool = DateMathTool::new(chrono_tz::Europe::London);
+        let response = run(
+            &tool,
+            json!({"start": "2026-03-28 09:00", "days": 1, "hours": 2}),
+        )
+        .await;
+        assert_eq!(response["start"]["utc_offset"], "+00:00");
+        assert_eq!(response["result"]["datetime"], "2026-03-29T11:00:00+01:00");
+        assert_eq!(response["result"]["weekday"], "Sunday");
+
+        let response = run(&tool, json!({"start": "2026-01-31", "months": 1})).await;
+        assert_eq!(response["result"]["date"], "2026-02-28");
+    }
+
+    #[tokio::test]
+    async fn date_math_measures_difference() {
+        let tool = DateMathTool::new(chrono_tz::UTC);
+        let response = run(
+            &tool,
+            json!({
+                "start": "2026-10-16T08:00:00Z",
+                "until": "2026-10-19 14:30",
+                "timezone": "Asia/Dubai",
+            }),
+        )
+        .await;
+        assert_eq!(response["start"]["time"], "12:00:00");
+        assert_eq!(response["difference"]["days"], 3);
+        assert_eq!(response["difference"]["hours"], 2);
+        assert_eq!(response["difference"]["minutes"], 30);
+        assert_eq!(response["difference"]["calendar_days"], 3);
+
+        let response = run(
+            &tool,
+            json!({"start": "2026-03-29 01:30", "timezone": "Europe/London"}),
+        )
+        .await;
+        assert!(response.as_str().unwrap().contains("does not exist"));
+    }
+}
@@ -0,0 +1,369 @@
+//! Built-in unit conversion tool
+//!
+//! Conversions use the defining decimal factors of each unit (an inch is exactly
+//! 0.0254 m, a pound exactly 0.45359237 kg), so `convert_units` answers "how many
+//! miles is 10 km" without the rounding drift of a model's memory.
+
+use agents_core::tools::{Tool, ToolBox, ToolContext, ToolParameterSchema, ToolResult, ToolSchema};
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Kinds of quantity; units only convert within their kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Quantity {
+    Length,
+    Mass,
+    Volume,
+    Area,
+    Time,
+    Speed,
+    Data,
+    Temperature,
+}
+
+impl Quantity {
+    fn name(self) -> &'static str {
+        match self {
+            Quantity::Length => "length",
+            Quantity::Mass => "mass",
+            Quantity::Volume => "volume",
+            Quantity::Area => "area",
+            Quantity::Time => "time",
+            Quantity::Speed => "speed",
+            Quantity::Data => "data",
+            Quantity::Temperature => "temperature",
+        }
+    }
+}
+
+/// Units with their aliases and their size in the quantity's base unit (metre,
+/// kilogram, litre, square metre, second, metre per second, byte). Temperatures
+/// are converted through kelvin separately.
+const UNITS: &[(Quantity, &[&str], &str)] = &[
+    (
+        Quantity::Length,
+        &["mm", "millimeter", "millimetre"],
+        "0.001",
+    ),
+    (
+        Quantity::Length,
+        &["cm", "centimeter", "centimetre"],
+        "0.01",
+    ),
+    (Quantity::Length, &["m", "meter", "metre"], "1"),
+    (Quantity::Length, &["km", "kilometer", "kilometre"], "1000"),
+    (Quantity::Length, &["in", "inch"], "0.0254"),
+    (Quantity::Length, &["ft", "foot", "feet"], "0.3048"),
+    (Quantity::Length, &["yd", "yard"], "0.9144"),
+    (Quantity::Length, &["mi", "mile"], "1609.344"),
+    (Quantity::Length, &["nmi", "nautical mile"], "1852"),
+    (Quantity::Mass, &["mg", "milligram"], "0.000001"),
+    (Quantity::Mass, &["g", "gram"], "0.001"),
+    (Quantity::Mass, &["kg", "kilogram"], "1"),
+    (Quantity::Mass, &["t", "tonne", "metric ton"], "1000"),
+    (Quantity::Mass, &["oz", "ounce"], "0.028349523125"),
+    (Quantity::Mass, &["lb", "lbs", "pound"], "0.45359237"),
+    (Quantity::Mass, &["st", "stone"], "6.35029318"),
+    (
+        Quantity::Volume,
+        &["ml", "milliliter", "millilitre"],
+        "0.001",
+    ),
+    (Quantity::Volume, &["l", "liter", "litre"], "1"),
+    (
+        Quantity::Volume,
+        &["m3", "cubic meter", "cubic metre"],
+        "1000",
+    ),
+    (Quantity::Volume, &["tsp", "teaspoon"], "0.00492892159375"),
+    (
+        Quantity::Volume,
+        &["tbsp", "tablespoon"],
+        "0.01478676478125",
+    ),
+    (
+        Quantity::Volume,
+        &["fl oz", "fluid ounce"],
+        "0.0295735295625",
+    ),
+    (Quantity::Volume, &["cup"], "0.2365882365"),
+    (Quantity::Volume, &["pt", "pint"], "0.473176473"),
+    (Quantity::Volume, &["qt", "quart"], "0.946352946"),
+    (Quantity::Volume, &["gal", "gallon"], "3.785411784"),
+    (Quantity::Area, &["m2", "square meter", "square metre"], "1"),
+    (
+        Quantity::Area,
+        &["km2", "square kilometer", "square kilometre"],
+        "1000000",
+    ),
+    (
+        Quantity::Area,
+        &["ft2", "square foot", "square feet"],
+        "0.09290304",
+    ),
+    (Quantity::Area, &["acre"], "4046.8564224"),
+    (Quantity::Area, &["ha", "hectare"], "10000"),
+    (Quantity::Time, &["ms", "millisecond"], "0.001"),
+    (Quantity::Time, &["s", "sec", "second"], "1"),
+    (Quantity::Time, &["min", "minute"], "60"),
+    (Quantity::Time, &["h", "hr", "hour"], "3600"),
+    (Quantity::Time, &["d", "day"], "86400"),
+    (Quantity::Time, &["wk", "week"], "604800"),
+    (
+        Quantity::Speed,
+        &["m/s", "meter per second", "metre per second"],
+        "1",
+    ),
+    (
+        Quantity::Speed,
+        &["km/h", "kph", "kilometer per hour", "kilometre per hour"],
+        "0.277777777777777777777777778",
+    ),
+    (Quantity::Speed, &["mph", "mile per hour"], "0.44704"),
+    (
+        Quantity::Speed,
+        &["kn", "knot"],
+        "0.514444444444444444444444444",
+    ),
+    (Quantity::Data, &["b", "byte"], "1"),
+    (Quantity::Data, &["kb", "kilobyte"], "1000"),
+    (Quantity::Data, &["mb", "megabyte"], "1000000"),
+    (Quantity::Data, &["gb", "gigabyte"], "1000000000"),
+    (Quantity::Data, &["tb", "terabyte"], "1000000000000"),
+    (Quantity::Data, &["kib", "kibibyte"], "1024"),
+    (Quantity::Data, &["mib", "mebibyte"], "1048576"),
+    (Quantity::Data, &["gib", "gibibyte"], "1073741824"),
+    (Quantity::Data, &["tib", "tebibyte"], "1099511627776"),
+];
+
+const TEMPERATURES: &[(&[&str], Temperature)] = &[
+    (&["c", "°c", "celsius"], Temperature::Celsius),
+    (&["f", "°f", "fahrenheit"], Temperature::Fahrenheit),
+    (&["k", "kelvin"], Temperature::Kelvin),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Temperature {
+    Celsius,
+    Fahrenheit,
+    Kelvin,
+}
+
+impl Temperature {
+    fn to_kelvin(self, value: Decimal) -> Option<Decimal> {
+        match self {
+            Temperature::Celsius => value.checked_add(Decimal::new(27315, 2)),
+            Temperature::Fahrenheit => value
+                .checked_sub(Decimal::from(32))?
+                .checked_mul(Decimal::from(5))?
+                .checked_div(Decimal::from(9))?
+                .checked_add(Decimal::new(27315, 2)),
+            Temperature::Kelvin => Some(value),
+        }
+    }
+
+    fn kelvin_to(self, kelvin: Decimal) -> Option<Decimal> {
+        match self {
+            Temperature::Celsius => kelvin.checked_sub(Decimal::new(27315, 2)),
+            Temperature::Fahrenheit => kelvin
+                .checked_sub(Decimal::new(27315, 2))?
+                .checked_mul(Decimal::from(9))?
+                .checked_div(Decimal::from(5))?
+                .checked_add(Decimal::from(32)),
+            Temperature::Kelvin => Some(kelvin),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Unit {
+    Scaled(Quantity, &'static str),
+    Temperature(Temperature),
+}
+
+impl Unit {
+    fn quantity(self) -> Quantity {
+        match self {
+            Unit::Scaled(quantity, _) => quantity,
+            Unit::Temperature(_) => Quantity::Temperature,
+        }
+    }
+}
+
+/// Look a unit up by symbol or name, ignoring case and plurals.
+fn find_unit(name: &str) -> Option<Unit> {
+    let name = name.trim().to_lowercase();
+    let mut candidates = vec![name.clone()];
+    for suffix in ["es", "s"] {
+        if let Some(stem) = name.strip_suffix(suffix) {
+            candidates.push(stem.to_string());
+        }
+    }
+    candidates.push(name.replacen("s per ", " per ", 1));
+
+    candidates.iter().find_map(|candidate| {
+        let candidate = candidate.as_str();
+        TEMPERATURES
+            .iter()
+            .find(|(aliases, _)| aliases.contains(&candidate))
+            .map(|(_, temperature)| Unit::Temperature(*temperature))
+            .or_else(|| {
+                UNITS
+                    .iter()
+                    .find(|(_, aliases, _)| aliases.contains(&candidate))
+                    .map(|(quantity, _, factor)| Unit::Scaled(*quantity, factor))
+            })
+    })
+}
+
+/// Convert `value` between two units of the same kind.
+pub fn convert(value: Decimal, from: &str, to: &str) -> anyhow::Result<Decimal> {
+    let unknown = |name: &str| anyhow::anyhow!("Unknown unit '{name}'");
+    let source = find_unit(from).ok_or_else(|| unknown(from))?;
+    let target = find_unit(to).ok_or_else(|| unknown(to))?;
+    if source.quantity() != target.quantity() {
+        anyhow::bail!(
+            "Cannot convert {} ({from}) to {} ({to})",
+            source.quantity().name(),
+            target.quantity().name()
+        );
+    }
+
+    let converted = match (source, target) {
+        (Unit::Temperature(source), Unit::Temperature(target)) => source
+            .to_kelvin(value)
+            .and_then(|kelvin| target.kelvin_to(kelvin)),
+        (Unit::Scaled(_, source), Unit::Scaled(_, target)) => {
+            let source = Decimal::from_str(source)?;
+            let target = Decimal::from_str(target)?;
+            value
+                .checked_mul(source)
+                .and_then(|base| base.checked_div(target))
+        }
+        _ => None,
+    };
+    converted.ok_or_else(|| anyhow::anyhow!("Result is out of range"))
+}
+
+/// Unit conversion tool - converts lengths, weights, volumes, temperatures and more
+pub struct ConvertUnitsTool;
+
+#[derive(Deserialize)]
+struct ConvertUnitsArgs {
+    value: Value,
+    from: String,
+    to: String,
+    #[serde(default)]
+    decimal_places: Option<u32>,
+}
+
+#[async_trait]
+impl Tool for ConvertUnitsTool {
+    fn schema(&self) -> ToolSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "value".to_string(),
+            ToolParameterSchema::string("Amount to convert, e.g. '12.5'"),
+        );
+        properties.insert(
+            "from".to_string(),
+            ToolParameterSchema::string("Unit of the value, e.g. 'km', 'lb', '°F', 'cup', 'GiB'"),
+        );
+        properties.insert(
+            "to".to_string(),
+            ToolParameterSchema::string("Unit to convert to, of the same kind"),
+        );
+        properties.insert(
+            "decimal_places".to_string(),
+            ToolParameterSchema::integer("Round the result to this many decimal places"),
+        );
+
+        ToolSchema::new(
+            "convert_units",
+            "Convert an amount between units of length, mass, volume, area, time, speed, \
+             data size or temperature, using the defining conversion factors",
+            ToolParameterSchema::object(
+                "Convert units parameters",
+                properties,
+                vec!["value".to_string(), "from".to_string(), "to".to_string()],
+            ),
+        )
+    }
+
+    async fn execute(&self, args: Value, ctx: ToolContext) -> anyhow::Result<ToolResult> {
+        let args: ConvertUnitsArgs = serde_json::from_value(args)?;
+
+        let outcome = (|| -> anyhow::Result<Value> {
+            // Models send numbers as either JSON numbers or strings
+            let text = match &args.value {
+                Value::String(text) => text.trim().to_string(),
+                Value::Number(number) => number.to_string(),
+                other => anyhow::bail!("'value' must be a number, got {other}"),
+            };
+            let value = Decimal::from_str(&text)
+                .or_else(|_| Decimal::from_scientific(&text))
+                .map_err(|_| anyhow::anyhow!("Invalid number '{text}'"))?;
+            let mut result = convert(value, &args.from, &args.to)?;
+            if let Some(places) = args.decimal_places {
+                result = result.round_dp(places.min(28));
+            }
+            Ok(json!({
+                "value": value.normalize().to_string(),
+                "from": args.from,
+                "to": args.to,
+                "result": result.normalize().to_string(),
+            }))
+        })();
+
+        match outcome {
+            Ok(response) => Ok(ToolResult::json(&ctx, response)),
+            Err(e) => Ok(ToolResult::text(&ctx, format!("Error: {e}"))),
+        }
+    }
+}
+
+/// Create the `convert_units` tool
+pub fn create_unit_tools() -> Vec<ToolBox> {
+    vec![Arc::new(ConvertUnitsTool)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert_text(value: &str, from: &str, to: &str) -> String {
+        convert(Decimal::from_str(value).unwrap(), from, to)
+            .unwrap()
+            .round_dp(6)
+            .normalize()
+            .to_string()
+    }
+
+    #[test]
+    fn converts_with_exact_factors() {
+        assert_eq!(convert_text("10", "km", "miles"), "6.213712");
+        assert_eq!(convert_text("12", "inches", "cm"), "30.48");
+        assert_eq!(convert_text("1", "lb", "g"), "453.59237");
+        assert_eq!(convert_text("2", "cups", "ml"), "473.176473");
+        assert_eq!(convert_text("1", "GiB", "MB"), "1073.741824");
+        assert_eq!(convert_text("100", "km/h", "m/s"), "27.777778");
+        assert_eq!(convert_text("212", "°F", "celsius"), "100");
+        assert_eq!(convert_text("-40", "C", "F"), "-40");
+        assert_eq!(convert_text("0", "K", "C"), "-273.15");
+    }
+
+    #[test]
+    fn rejects_unknown_and_mismatched_units() {
+        let error = convert(Decimal::ONE, "km", "kg").unwrap_err().to_string();
+        assert!(error.contains("Cannot convert length"), "{error}");
+        let error = convert(Decimal::ONE, "furlong", "m")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Unknown unit 'furlong'"), "{error}");
+    }
+}
@@ -1,7 +1,7 @@
 //! Toolkit of built-in tools and utilities for AI agents
 //!
 //! This crate provides:
-//! - Built-in tools (filesystem, todos, etc.)
+//! - Built-in tools (filesystem, todos, time, math, units, text, tabular data, charts, SQL, browser, etc.)
 //! - `#[tool]` macro for automatic tool generation
 //! - Tool builder utilities for advanced custom tools
 //! - Tool registration and management helpers
@@ -27,17 +27,27 @@ pub mod builtin;
 
 // Re-export core types from agents-core for convenience
 pub use agents_core::tools::{
-    Tool, ToolBox, ToolContext, ToolParameterSchema, ToolRegistry, ToolResult, ToolSchema,
+    Tool, ToolArgs, ToolBox, ToolContext, ToolDeprecation, ToolExtensions, ToolParameterSchema,
+    ToolParameterType, ToolRegistry, ToolResult, ToolSchema,
 };
 
 // Re-export builder utilities (advanced use cases)
 pub use builder::{tool, tool_sync, ToolBuilder};
 
 // Re-export the #[tool] macro - this is the recommended way to define tools
-pub use agents_macros::tool;
+pub use agents_macros::{tool, tools, ToolArgs, ToolEnum};
 
 // Re-export built-in tools
+#[cfg(feature = "browser")]
+pub use builtin::{create_browser_tools, BrowserApprovalPolicy, BrowserTool, DomainAllowlist};
+#[cfg(feature = "charts")]
+pub use builtin::{create_chart_tools, RenderChartTool};
 pub use builtin::{
-    create_filesystem_tools, create_todos_tool, create_todos_tools, EditFileTool, LsTool,
-    ReadFileTool, ReadTodosTool, WriteFileTool, WriteTodosTool,
+    create_data_tools, create_filesystem_tools, create_math_tools, create_text_tools,
+    create_time_tools, create_todos_tool, create_todos_tools, create_unit_tools, CalculateTool,
+    ConvertUnitsTool, DateMathTool, DescribeTableTool, EditFileTool, GetCurrentTimeTool,
+    LoadTableTool, LsTool, QueryTableTool, ReadFileTool, ReadTodosTool, RegexExtractTool,
+    RegexReplaceTool, TextStatsTool, WriteFileTool, WriteTodosTool,
 };
+#[cfg(any(feature = "sql-postgres", feature = "sql-mysql"))]
+pub use builtin::{create_sql_tools, SqlQueryTool, SqlSchemaTool, StatementKind};
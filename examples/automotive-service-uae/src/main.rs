@@ -634,6 +634,9 @@ async fn main() -> anyhow::Result<()> {
                 std::io::Write::flush(&mut std::io::stdout()).unwrap();
                 full_response.push_str(&delta);
             }
+            StreamChunk::ToolCallStarted { name, .. } => {
+                println!("\n🔧 Calling {}...", name);
+            }
             StreamChunk::Done { message } => {
                 // Stream complete
                 println!("\n");
@@ -461,6 +461,11 @@ async fn chat_sse(
                                     .data(serde_json::json!({"text": delta}).to_string()));
                             }
                         }
+                        Ok(StreamChunk::ToolCallStarted { name, partial_args, .. }) => {
+                            yield Ok(Event::default()
+                                .event("tool_call")
+                                .data(serde_json::json!({"name": name, "args": partial_args}).to_string()));
+                        }
                         Ok(StreamChunk::Done { message }) => {
                             tracing::info!("Received Done chunk, sending done event to client");
                             if let MessageContent::Text(text) = message.content {
@@ -16,7 +16,8 @@
 //! - GET /api/v1/sessions/{id} - Get session info
 //! - GET /api/v1/sessions - List all sessions
 //! - GET /api/v1/health - Health check
-//! - GET /api/v1/agents - List available agents and tools
+//! - GET /api/v1/agents - List available agents and their capabilities
+//! - GET /api/v1/agents/{name} - Get one agent's descriptor for capability negotiation
 
 use axum::{
     extract::{Path, State},
@@ -35,6 +36,7 @@ use tower_http::{cors::CorsLayer, trace::TraceLayer};
 use uuid::Uuid;
 
 use agents_sdk::{
+    agent::{AgentDescriptor, AgentHandle},
     persistence::{Checkpointer, InMemoryCheckpointer, ThreadId},
     state::TodoStatus,
     tool,
@@ -100,14 +102,6 @@ struct HealthResponse {
     active_sessions: usize,
 }
 
-#[derive(Debug, Serialize)]
-struct AgentInfo {
-    name: String,
-    description: String,
-    tools: Vec<String>,
-    subagents: Vec<String>,
-}
-
 #[derive(Debug, Clone, Serialize)]
 struct AgentStatus {
     session_id: String,
@@ -472,23 +466,30 @@ async fn health_handler(State(state): State<AppState>) -> Json<HealthResponse> {
     })
 }
 
-async fn agents_info_handler(State(_state): State<AppState>) -> Json<Vec<AgentInfo>> {
-    let agents = vec![AgentInfo {
-        name: "research".to_string(),
-        description: "Deep research agent with specialized subagents for comprehensive analysis"
-            .to_string(),
-        tools: vec![
-            "write_file".to_string(),
-            "read_file".to_string(),
-            "edit_file".to_string(),
-            "ls".to_string(),
-            "write_todos".to_string(),
-            "task".to_string(),
-        ],
-        subagents: vec!["research-agent".to_string(), "critique-agent".to_string()],
-    }];
-
-    Json(agents)
+async fn agents_info_handler(State(state): State<AppState>) -> Json<Vec<AgentDescriptor>> {
+    let mut descriptors = Vec::new();
+    for instance in state.agents.values() {
+        descriptors.push(instance.agent.describe().await);
+    }
+    descriptors.sort_by(|a, b| a.name.cmp(&b.name));
+    Json(descriptors)
+}
+
+async fn get_agent_descriptor_handler(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<AgentDescriptor>, (StatusCode, Json<ErrorResponse>)> {
+    match state.agents.get(&name) {
+        Some(instance) => Ok(Json(instance.agent.describe().await)),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Agent not found".to_string(),
+                code: "AGENT_NOT_FOUND".to_string(),
+                timestamp: Utc::now(),
+            }),
+        )),
+    }
 }
 
 async fn get_agent_status_handler(
@@ -578,6 +579,10 @@ Responsibilities:
 
     let agent = ConfigurableAgentBuilder::new(main_instructions)
         .with_model(model)
+        .with_name("research")
+        .with_description(
+            "Deep research agent with specialized subagents for comprehensive analysis",
+        )
         .with_builtin_tools(["ls", "read_file", "write_file", "edit_file", "write_todos"])
         .with_tools(vec![internet_search.clone()])
         .with_subagent_config(vec![research_subagent, critique_subagent])
@@ -632,6 +637,7 @@ async fn main() -> anyhow::Result<()> {
         .route("/api/v1/sessions", get(list_sessions_handler))
         .route("/api/v1/health", get(health_handler))
         .route("/api/v1/agents", get(agents_info_handler))
+        .route("/api/v1/agents/:name", get(get_agent_descriptor_handler))
         .route("/api/v1/status/:session_id", get(get_agent_status_handler))
         .route("/api/v1/status", get(list_agent_status_handler))
         .layer(CorsLayer::permissive())
@@ -649,6 +655,7 @@ async fn main() -> anyhow::Result<()> {
     println!("   GET  /api/v1/sessions       - List all sessions");
     println!("   GET  /api/v1/health         - Health check");
     println!("   GET  /api/v1/agents         - List available agents");
+    println!("   GET  /api/v1/agents/:name   - Agent descriptor and capabilities");
     println!("\n🔧 Example usage:");
     println!("   curl -X POST http://{}/api/v1/chat \\", bind_addr);
     println!("     -H 'Content-Type: application/json' \\");